@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_message_reactions_message;
+DROP INDEX IF EXISTS idx_message_reactions_session;
+DROP TABLE IF EXISTS message_reactions;
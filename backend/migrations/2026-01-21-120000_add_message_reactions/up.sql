@@ -0,0 +1,16 @@
+-- Emoji reactions left by session members on individual messages
+CREATE TABLE message_reactions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    message_id UUID NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    session_id UUID NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    emoji VARCHAR(32) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+
+    -- A user can only leave each emoji once per message
+    UNIQUE(message_id, user_id, emoji)
+);
+
+-- Reactions are always loaded per session
+CREATE INDEX idx_message_reactions_session ON message_reactions(session_id);
+CREATE INDEX idx_message_reactions_message ON message_reactions(message_id);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_turn_feedback_user;
+DROP INDEX IF EXISTS idx_turn_feedback_session;
+DROP TABLE IF EXISTS turn_feedback;
@@ -0,0 +1,19 @@
+-- Thumbs-up/down feedback on completed turns, exportable as a prompt dataset
+CREATE TABLE turn_feedback (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    session_id UUID NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    -- Kept when the rated message is pruned by retention so the dataset survives
+    message_id UUID REFERENCES messages(id) ON DELETE SET NULL,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    rating VARCHAR(10) NOT NULL CHECK (rating IN ('up', 'down')),
+    comment TEXT,
+    prompt TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+
+    -- One rating per user per turn
+    UNIQUE(message_id, user_id)
+);
+
+CREATE INDEX idx_turn_feedback_session ON turn_feedback(session_id);
+CREATE INDEX idx_turn_feedback_user ON turn_feedback(user_id);
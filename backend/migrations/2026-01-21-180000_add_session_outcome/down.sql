@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_sessions_outcome_created_at;
+ALTER TABLE sessions DROP COLUMN outcome_set_at;
+ALTER TABLE sessions DROP COLUMN outcome;
@@ -0,0 +1,6 @@
+-- Explicit outcome label for finished sessions, used for success-rate analytics
+ALTER TABLE sessions ADD COLUMN outcome VARCHAR(16)
+    CHECK (outcome IN ('succeeded', 'partially', 'failed', 'abandoned'));
+ALTER TABLE sessions ADD COLUMN outcome_set_at TIMESTAMP;
+
+CREATE INDEX idx_sessions_outcome_created_at ON sessions(created_at) WHERE outcome IS NOT NULL;
@@ -0,0 +1,10 @@
+-- Diagnostic bundles generated when a session errors or the Claude process crashes
+CREATE TABLE session_incidents (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    session_id UUID NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    reason TEXT NOT NULL,
+    bundle JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_session_incidents_session_id ON session_incidents(session_id, created_at DESC);
@@ -0,0 +1,33 @@
+-- Cumulative usage last reported by each session's Claude process, per model.
+-- Result messages carry running totals, so this is what daily deltas are taken against.
+CREATE TABLE session_model_usage (
+    session_id UUID NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    model VARCHAR(128) NOT NULL,
+    cost_usd DOUBLE PRECISION NOT NULL DEFAULT 0,
+    input_tokens BIGINT NOT NULL DEFAULT 0,
+    output_tokens BIGINT NOT NULL DEFAULT 0,
+    cache_creation_tokens BIGINT NOT NULL DEFAULT 0,
+    cache_read_tokens BIGINT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (session_id, model)
+);
+
+-- Usage per session, model, and UTC day. Rows outlive their session so
+-- budgets and historical totals stay accurate after cleanup.
+CREATE TABLE usage_daily (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    session_id UUID REFERENCES sessions(id) ON DELETE SET NULL,
+    session_name VARCHAR(255) NOT NULL,
+    day DATE NOT NULL,
+    model VARCHAR(128) NOT NULL,
+    cost_usd DOUBLE PRECISION NOT NULL DEFAULT 0,
+    input_tokens BIGINT NOT NULL DEFAULT 0,
+    output_tokens BIGINT NOT NULL DEFAULT 0,
+    cache_creation_tokens BIGINT NOT NULL DEFAULT 0,
+    cache_read_tokens BIGINT NOT NULL DEFAULT 0,
+    turns INTEGER NOT NULL DEFAULT 0,
+    UNIQUE (session_id, day, model)
+);
+
+CREATE INDEX idx_usage_daily_user_day ON usage_daily(user_id, day);
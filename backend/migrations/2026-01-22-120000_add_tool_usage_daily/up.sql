@@ -0,0 +1,9 @@
+-- Tool calls per user, tool, and UTC day for the usage dashboard
+CREATE TABLE tool_usage_daily (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    tool_name VARCHAR(128) NOT NULL,
+    uses INTEGER NOT NULL DEFAULT 0,
+    UNIQUE (user_id, day, tool_name)
+);
@@ -0,0 +1,14 @@
+-- Admin-configured budget caps. A single row; NULL means no cap.
+CREATE TABLE budget_limits (
+    id INTEGER PRIMARY KEY DEFAULT 1 CHECK (id = 1),
+    session_cost_soft_usd DOUBLE PRECISION,
+    session_cost_hard_usd DOUBLE PRECISION,
+    daily_cost_soft_usd DOUBLE PRECISION,
+    daily_cost_hard_usd DOUBLE PRECISION,
+    session_tokens_soft BIGINT,
+    session_tokens_hard BIGINT,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_by UUID REFERENCES users(id) ON DELETE SET NULL
+);
+
+INSERT INTO budget_limits (id) VALUES (1);
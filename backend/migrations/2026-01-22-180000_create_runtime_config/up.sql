@@ -0,0 +1,10 @@
+-- Admin overrides for settings that apply without a restart. A single row;
+-- keys absent from `overrides` fall back to the server's environment.
+CREATE TABLE runtime_config (
+    id INTEGER PRIMARY KEY DEFAULT 1 CHECK (id = 1),
+    overrides JSONB NOT NULL DEFAULT '{}',
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_by UUID REFERENCES users(id) ON DELETE SET NULL
+);
+
+INSERT INTO runtime_config (id) VALUES (1);
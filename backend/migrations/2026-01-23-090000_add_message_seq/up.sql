@@ -0,0 +1,5 @@
+-- Monotonic position of each message, so reconnecting web clients can ask for
+-- exactly the messages they missed instead of relying on their own clock.
+ALTER TABLE messages ADD COLUMN seq BIGSERIAL NOT NULL;
+
+CREATE INDEX idx_messages_session_seq ON messages(session_id, seq);
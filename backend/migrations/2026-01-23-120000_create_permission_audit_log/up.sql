@@ -0,0 +1,19 @@
+-- Every permission request a session made, and how it was answered. Rows are
+-- written when the request arrives and completed when someone (or a remembered
+-- rule) decides it.
+CREATE TABLE permission_audit_log (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    session_id UUID NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    request_id VARCHAR(255) NOT NULL,
+    tool_name VARCHAR(255) NOT NULL,
+    input JSONB NOT NULL,
+    requested_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    allowed BOOLEAN,
+    channel VARCHAR(20),
+    decided_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    detail TEXT,
+    decided_at TIMESTAMP
+);
+
+CREATE INDEX idx_permission_audit_log_session ON permission_audit_log(session_id, requested_at DESC);
+CREATE INDEX idx_permission_audit_log_request ON permission_audit_log(session_id, request_id);
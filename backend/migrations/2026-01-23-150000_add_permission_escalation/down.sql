@@ -0,0 +1,5 @@
+ALTER TABLE permission_audit_log
+    DROP COLUMN escalated_to,
+    DROP COLUMN escalated_at;
+
+DROP TABLE escalation_policies;
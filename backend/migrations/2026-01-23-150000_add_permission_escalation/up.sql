@@ -0,0 +1,15 @@
+-- Per-session escalation for permission requests nobody answers: notify a
+-- second member after one delay, apply a default answer after another.
+CREATE TABLE escalation_policies (
+    session_id UUID PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+    notify_after_minutes INTEGER,
+    notify_user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    default_after_minutes INTEGER,
+    default_action VARCHAR(10) NOT NULL DEFAULT 'deny',
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- Escalation steps taken for each request
+ALTER TABLE permission_audit_log
+    ADD COLUMN escalated_at TIMESTAMP,
+    ADD COLUMN escalated_to TEXT;
@@ -0,0 +1,5 @@
+ALTER TABLE users DROP COLUMN billing_customer_id;
+
+DROP TABLE metering_exports;
+
+DROP TABLE speech_usage_daily;
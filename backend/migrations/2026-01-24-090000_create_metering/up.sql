@@ -0,0 +1,19 @@
+-- Audio streamed to speech recognition, per user per day, for usage billing
+CREATE TABLE speech_usage_daily (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    audio_ms BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (user_id, day)
+);
+
+-- Days whose usage records were sent to the billing sink. A row is claimed
+-- before sending so only one backend instance exports each day.
+CREATE TABLE metering_exports (
+    day DATE PRIMARY KEY,
+    sink VARCHAR(32) NOT NULL,
+    records INTEGER NOT NULL DEFAULT 0,
+    exported_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- Customer the billing provider knows this user as (e.g. a Stripe customer ID)
+ALTER TABLE users ADD COLUMN billing_customer_id VARCHAR(255);
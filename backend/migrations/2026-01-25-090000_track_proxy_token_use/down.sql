@@ -0,0 +1,7 @@
+DROP INDEX IF EXISTS idx_sessions_proxy_token_id;
+
+ALTER TABLE sessions
+    DROP COLUMN proxy_token_id;
+
+ALTER TABLE proxy_auth_tokens
+    DROP COLUMN last_used_host;
@@ -0,0 +1,9 @@
+-- Where each proxy token was last used from, and which token opened each
+-- session, so users can see every machine that still has access.
+ALTER TABLE proxy_auth_tokens
+    ADD COLUMN last_used_host VARCHAR(255);
+
+ALTER TABLE sessions
+    ADD COLUMN proxy_token_id UUID REFERENCES proxy_auth_tokens(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_sessions_proxy_token_id ON sessions(proxy_token_id);
@@ -0,0 +1,2 @@
+ALTER TABLE proxy_auth_tokens
+    DROP COLUMN scopes;
@@ -0,0 +1,5 @@
+-- Restrictions on what a proxy token may do (directories, read-only,
+-- concurrent sessions, tool categories), as serialized ProxyTokenScopes.
+-- Existing tokens stay unrestricted.
+ALTER TABLE proxy_auth_tokens
+    ADD COLUMN scopes JSONB NOT NULL DEFAULT '{}';
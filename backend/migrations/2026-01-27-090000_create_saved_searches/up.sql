@@ -0,0 +1,17 @@
+-- Transcript searches a user saved, optionally alerting on new matches.
+-- checked_until marks how far the alert task has looked, so each message is
+-- evaluated once.
+CREATE TABLE saved_searches (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    query TEXT NOT NULL,
+    regex BOOLEAN NOT NULL DEFAULT FALSE,
+    alert BOOLEAN NOT NULL DEFAULT FALSE,
+    checked_until TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_match_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_saved_searches_user_id ON saved_searches(user_id);
+CREATE INDEX idx_saved_searches_alert ON saved_searches(alert) WHERE alert;
@@ -0,0 +1,10 @@
+-- Embeddings of prompts and assistant turns for semantic search. A message
+-- with no text to embed gets an empty vector so it isn't picked up again.
+CREATE TABLE message_embeddings (
+    message_id UUID PRIMARY KEY REFERENCES messages(id) ON DELETE CASCADE,
+    model VARCHAR(255) NOT NULL,
+    embedding REAL[] NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_message_embeddings_model ON message_embeddings(model);
@@ -0,0 +1,16 @@
+-- Sign-in identities: one row per provider account that signs in as a user.
+-- Lets one user sign in with Google, GitHub, or an OpenID Connect issuer.
+CREATE TABLE user_identities (
+    provider VARCHAR(32) NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (provider, subject)
+);
+
+CREATE INDEX idx_user_identities_user_id ON user_identities(user_id);
+
+-- Existing users all signed in with Google
+INSERT INTO user_identities (provider, subject, user_id)
+SELECT 'google', google_id, id FROM users
+WHERE google_id <> 'dev_mode_test_user';
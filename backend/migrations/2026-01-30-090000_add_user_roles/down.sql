@@ -0,0 +1,5 @@
+ALTER TABLE users ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT FALSE;
+UPDATE users SET is_admin = TRUE WHERE role = 'admin';
+CREATE INDEX idx_users_is_admin ON users(is_admin) WHERE is_admin = TRUE;
+
+ALTER TABLE users DROP COLUMN role;
@@ -0,0 +1,9 @@
+-- Account roles replace the admin flag: admins see every session and manage
+-- users, members see their own and shared sessions, viewers are read-only.
+ALTER TABLE users ADD COLUMN role VARCHAR(16) NOT NULL DEFAULT 'member'
+    CHECK (role IN ('admin', 'member', 'viewer'));
+
+UPDATE users SET role = 'admin' WHERE is_admin;
+
+DROP INDEX IF EXISTS idx_users_is_admin;
+ALTER TABLE users DROP COLUMN is_admin;
@@ -0,0 +1,5 @@
+-- When the proxy read each output, corrected for the proxy's clock skew.
+-- created_at stays the time the backend received it; the two differ for
+-- output buffered while the proxy was disconnected. NULL for messages that
+-- didn't come from a proxy, or from one too old to stamp them.
+ALTER TABLE messages ADD COLUMN produced_at TIMESTAMP;
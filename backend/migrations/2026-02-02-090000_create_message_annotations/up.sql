@@ -0,0 +1,21 @@
+-- Annotations external tools (CI, scanners) attach to a range of a
+-- session's messages, shown as badges in the web UI
+CREATE TABLE message_annotations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    session_id UUID NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    -- Whose token or browser session posted it
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    source VARCHAR(64) NOT NULL,
+    level VARCHAR(16) NOT NULL DEFAULT 'info',
+    label VARCHAR(200) NOT NULL,
+    url TEXT,
+    data JSONB,
+    start_seq BIGINT NOT NULL,
+    end_seq BIGINT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+
+    CHECK (end_seq >= start_seq)
+);
+
+-- Annotations are always loaded per session
+CREATE INDEX idx_message_annotations_session ON message_annotations(session_id);
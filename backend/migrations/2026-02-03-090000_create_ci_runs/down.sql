@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS ci_runs;
+ALTER TABLE sessions DROP COLUMN IF EXISTS git_repo;
@@ -0,0 +1,18 @@
+-- GitHub repository (owner/repo) of the session's origin remote, reported
+-- by the proxy, for matching CI results to sessions
+ALTER TABLE sessions ADD COLUMN git_repo VARCHAR(255);
+
+-- Latest result of each CI workflow or check on each branch, from GitHub
+-- webhooks
+CREATE TABLE ci_runs (
+    repository VARCHAR(255) NOT NULL,
+    branch VARCHAR(255) NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    head_sha VARCHAR(64) NOT NULL,
+    -- pending, success, or failure
+    state VARCHAR(16) NOT NULL,
+    url TEXT,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (repository, branch, name)
+);
@@ -0,0 +1,7 @@
+-- Latest SessionSnapshot each proxy uploaded, so a session's pending state
+-- can be inspected or recovered if its proxy host is lost
+CREATE TABLE session_snapshots (
+    session_id UUID PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+    snapshot JSONB NOT NULL,
+    uploaded_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
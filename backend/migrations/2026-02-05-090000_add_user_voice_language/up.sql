@@ -0,0 +1,2 @@
+-- Language each user's voice input is recognized in, or 'auto'
+ALTER TABLE users ADD COLUMN voice_language VARCHAR(16) NOT NULL DEFAULT 'en-US';
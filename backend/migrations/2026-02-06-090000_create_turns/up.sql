@@ -0,0 +1,18 @@
+-- One row per finished turn, worked out from the session's messages as they
+-- are stored so analytics and the UI needn't reparse transcripts
+CREATE TABLE turns (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    session_id UUID NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    turn_index INTEGER NOT NULL,
+    start_seq BIGINT NOT NULL,
+    end_seq BIGINT NOT NULL,
+    end_message_id UUID,
+    duration_ms BIGINT NOT NULL DEFAULT 0,
+    input_tokens BIGINT NOT NULL DEFAULT 0,
+    output_tokens BIGINT NOT NULL DEFAULT 0,
+    cost_usd DOUBLE PRECISION NOT NULL DEFAULT 0,
+    tools TEXT[] NOT NULL DEFAULT '{}',
+    error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (session_id, turn_index)
+);
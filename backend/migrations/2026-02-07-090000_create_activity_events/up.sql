@@ -0,0 +1,11 @@
+-- Notable session events, as sent to webhooks, kept for the activity feed
+CREATE TABLE activity_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    kind VARCHAR(32) NOT NULL,
+    session_id UUID NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    data JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_activity_events_created_at ON activity_events (created_at DESC);
+CREATE INDEX idx_activity_events_session_id ON activity_events (session_id, created_at DESC);
@@ -0,0 +1,9 @@
+-- Per-session redaction for viewers: every tool's results, or those of the
+-- listed tools, are replaced with a placeholder for members with the viewer
+-- role.
+CREATE TABLE redaction_policies (
+    session_id UUID PRIMARY KEY REFERENCES sessions(id) ON DELETE CASCADE,
+    whole_session BOOLEAN NOT NULL DEFAULT FALSE,
+    tools TEXT[] NOT NULL DEFAULT '{}',
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
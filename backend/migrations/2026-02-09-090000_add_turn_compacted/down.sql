@@ -0,0 +1 @@
+ALTER TABLE turns DROP COLUMN IF EXISTS compacted;
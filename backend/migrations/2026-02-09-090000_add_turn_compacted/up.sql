@@ -0,0 +1,3 @@
+-- Turns during which Claude Code compacted the context, so a replayed
+-- transcript can show where earlier messages became a summary
+ALTER TABLE turns ADD COLUMN compacted BOOLEAN NOT NULL DEFAULT FALSE;
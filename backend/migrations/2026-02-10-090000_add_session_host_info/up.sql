@@ -0,0 +1,3 @@
+-- Machine, OS, checkout state and Claude version the proxy reported when it
+-- last registered, so the dashboard can show where a session runs
+ALTER TABLE sessions ADD COLUMN host_info JSONB;
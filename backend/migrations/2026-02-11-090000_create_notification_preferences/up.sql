@@ -0,0 +1,8 @@
+-- Which channels each notification event reaches a user on, and where to
+-- reach them (see shared::NotificationPreferences). Users without a row get
+-- the defaults.
+CREATE TABLE notification_preferences (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    preferences JSONB NOT NULL DEFAULT '{}',
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,2 @@
+ALTER TABLE session_members DROP COLUMN IF EXISTS expires_at;
+DROP TABLE IF EXISTS guest_invites;
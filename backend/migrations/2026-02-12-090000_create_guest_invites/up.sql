@@ -0,0 +1,22 @@
+-- Links that let someone without an account into one session for a while.
+-- Opening one creates a guest user (a viewer account) and a session
+-- membership that ends at the invite's expiry.
+CREATE TABLE guest_invites (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    session_id UUID NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    -- sha256 of the link's token; the token itself is never stored
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    name VARCHAR(64) NOT NULL,
+    -- viewer or approver
+    role VARCHAR(20) NOT NULL,
+    invited_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    guest_user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    expires_at TIMESTAMP NOT NULL,
+    revoked_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_guest_invites_session ON guest_invites(session_id);
+
+-- Guests' memberships end; everyone else's don't
+ALTER TABLE session_members ADD COLUMN expires_at TIMESTAMP;
@@ -0,0 +1,10 @@
+-- The admin kill switch, stored so that every backend instance enforces it
+-- and a restart doesn't release it. One row while it's engaged, none
+-- otherwise.
+CREATE TABLE kill_switch (
+    id INTEGER PRIMARY KEY DEFAULT 1 CHECK (id = 1),
+    reason TEXT NOT NULL,
+    -- Email of the admin who engaged it
+    engaged_by TEXT NOT NULL,
+    engaged_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,6 @@
+-- Days whose session digests were sent. A row is claimed before sending so
+-- only one backend instance sends each day's digests.
+CREATE TABLE notification_digests (
+    day DATE PRIMARY KEY,
+    sent_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,437 @@
+//! Sign-in providers for the web UI
+//!
+//! Users sign in through one of the providers configured in the environment:
+//!
+//! - Google: `GOOGLE_CLIENT_ID`, `GOOGLE_CLIENT_SECRET`
+//! - GitHub: `GITHUB_CLIENT_ID`, `GITHUB_CLIENT_SECRET`
+//! - Any OpenID Connect issuer (Okta, Auth0, Keycloak, Entra ID, ...):
+//!   `OIDC_ISSUER`, `OIDC_CLIENT_ID`, `OIDC_CLIENT_SECRET`, and optionally
+//!   `OIDC_NAME` for the button label. Endpoints come from the issuer's
+//!   discovery document.
+//!
+//! Each provider's callback is `<BASE_URL>/api/auth/<id>/callback` unless
+//! `<PREFIX>_REDIRECT_URI` overrides it. Outside dev mode at least one
+//! provider must be configured.
+//!
+//! A provider yields an [`Identity`]: a stable subject plus the user's email.
+//! Accounts are matched by (provider, subject), then by verified email, so
+//! one user can sign in through several providers.
+
+use anyhow::{bail, Context};
+use oauth2::basic::BasicClient;
+use oauth2::{AuthUrl, ClientId, ClientSecret, RedirectUrl, TokenUrl};
+use serde::Deserialize;
+use serde_json::Value;
+use shared::AuthProviderInfo;
+
+const GOOGLE_AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const GOOGLE_USERINFO_URL: &str = "https://www.googleapis.com/oauth2/v3/userinfo";
+
+const GITHUB_AUTH_URL: &str = "https://github.com/login/oauth/authorize";
+const GITHUB_TOKEN_URL: &str = "https://github.com/login/oauth/access_token";
+const GITHUB_API_URL: &str = "https://api.github.com";
+
+/// GitHub's API rejects requests without a User-Agent
+const USER_AGENT: &str = concat!("claude-code-portal/", env!("CARGO_PKG_VERSION"));
+
+/// How the provider reports who signed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProviderKind {
+    /// An OpenID Connect userinfo endpoint (Google, or a discovered issuer)
+    Oidc,
+    /// GitHub's REST API, which isn't OpenID Connect
+    GitHub,
+}
+
+/// Who a provider says signed in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identity {
+    /// Stable id at the provider; emails can change
+    pub subject: String,
+    pub email: String,
+    /// Whether the provider vouched for the email. Only a verified email
+    /// links the sign-in to an existing user with that address.
+    pub email_verified: bool,
+    pub name: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthProvider {
+    /// URL segment: `google`, `github`, or `oidc`
+    pub id: &'static str,
+    /// Shown on the sign-in button
+    pub name: String,
+    kind: ProviderKind,
+    pub client: BasicClient,
+    /// Userinfo endpoint (OpenID Connect) or API base (GitHub)
+    userinfo_url: String,
+    pub scopes: &'static [&'static str],
+}
+
+/// The configured providers, in the order their buttons are shown
+#[derive(Debug, Clone, Default)]
+pub struct AuthProviders(Vec<AuthProvider>);
+
+#[derive(Deserialize)]
+struct DiscoveryDocument {
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: String,
+}
+
+/// Client id, secret, and redirect URI for one provider, if its variables are set
+fn credentials(
+    var: &impl Fn(&str) -> Option<String>,
+    prefix: &str,
+    id: &str,
+    public_url: &str,
+) -> anyhow::Result<Option<(ClientId, ClientSecret, RedirectUrl)>> {
+    let Some(client_id) = var(&format!("{}_CLIENT_ID", prefix)) else {
+        return Ok(None);
+    };
+    let secret_var = format!("{}_CLIENT_SECRET", prefix);
+    let Some(secret) = var(&secret_var) else {
+        bail!("{} must be set", secret_var);
+    };
+    let redirect = var(&format!("{}_REDIRECT_URI", prefix)).unwrap_or_else(|| {
+        format!(
+            "{}/api/auth/{}/callback",
+            public_url.trim_end_matches('/'),
+            id
+        )
+    });
+    Ok(Some((
+        ClientId::new(client_id),
+        ClientSecret::new(secret),
+        RedirectUrl::new(redirect).with_context(|| format!("{}_REDIRECT_URI", prefix))?,
+    )))
+}
+
+impl AuthProviders {
+    /// Read providers from the environment, fetching the OpenID Connect
+    /// discovery document if an issuer is set
+    pub async fn from_env(public_url: &str) -> anyhow::Result<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let discovery = match var("OIDC_ISSUER") {
+            Some(issuer) => Some(discover(&issuer).await?),
+            None => None,
+        };
+        Self::parse(var, public_url, discovery)
+    }
+
+    fn parse(
+        var: impl Fn(&str) -> Option<String>,
+        public_url: &str,
+        discovery: Option<DiscoveryDocument>,
+    ) -> anyhow::Result<Self> {
+        let mut providers = Vec::new();
+
+        if let Some((id, secret, redirect)) = credentials(&var, "GOOGLE", "google", public_url)? {
+            providers.push(AuthProvider {
+                id: "google",
+                name: "Google".to_string(),
+                kind: ProviderKind::Oidc,
+                client: BasicClient::new(
+                    id,
+                    Some(secret),
+                    AuthUrl::new(GOOGLE_AUTH_URL.to_string())?,
+                    Some(TokenUrl::new(GOOGLE_TOKEN_URL.to_string())?),
+                )
+                .set_redirect_uri(redirect),
+                userinfo_url: GOOGLE_USERINFO_URL.to_string(),
+                scopes: &["openid", "email", "profile"],
+            });
+        }
+
+        if let Some((id, secret, redirect)) = credentials(&var, "GITHUB", "github", public_url)? {
+            providers.push(AuthProvider {
+                id: "github",
+                name: "GitHub".to_string(),
+                kind: ProviderKind::GitHub,
+                client: BasicClient::new(
+                    id,
+                    Some(secret),
+                    AuthUrl::new(GITHUB_AUTH_URL.to_string())?,
+                    Some(TokenUrl::new(GITHUB_TOKEN_URL.to_string())?),
+                )
+                .set_redirect_uri(redirect),
+                userinfo_url: GITHUB_API_URL.to_string(),
+                scopes: &["read:user", "user:email"],
+            });
+        }
+
+        if let Some(discovery) = discovery {
+            let Some((id, secret, redirect)) = credentials(&var, "OIDC", "oidc", public_url)?
+            else {
+                bail!("OIDC_CLIENT_ID must be set with OIDC_ISSUER");
+            };
+            providers.push(AuthProvider {
+                id: "oidc",
+                name: var("OIDC_NAME").unwrap_or_else(|| "Single sign-on".to_string()),
+                kind: ProviderKind::Oidc,
+                client: BasicClient::new(
+                    id,
+                    Some(secret),
+                    AuthUrl::new(discovery.authorization_endpoint)
+                        .context("authorization_endpoint")?,
+                    Some(TokenUrl::new(discovery.token_endpoint).context("token_endpoint")?),
+                )
+                .set_redirect_uri(redirect),
+                userinfo_url: discovery.userinfo_endpoint,
+                scopes: &["openid", "email", "profile"],
+            });
+        }
+
+        Ok(Self(providers))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&AuthProvider> {
+        self.0.iter().find(|p| p.id == id)
+    }
+
+    /// The only provider, when there's exactly one
+    pub fn single(&self) -> Option<&AuthProvider> {
+        match self.0.as_slice() {
+            [provider] => Some(provider),
+            _ => None,
+        }
+    }
+
+    pub fn info(&self) -> Vec<AuthProviderInfo> {
+        self.0
+            .iter()
+            .map(|p| AuthProviderInfo {
+                id: p.id.to_string(),
+                name: p.name.clone(),
+            })
+            .collect()
+    }
+}
+
+async fn discover(issuer: &str) -> anyhow::Result<DiscoveryDocument> {
+    let url = format!(
+        "{}/.well-known/openid-configuration",
+        issuer.trim_end_matches('/')
+    );
+    reqwest::get(&url)
+        .await
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("fetching {}", url))?
+        .json()
+        .await
+        .with_context(|| format!("reading {}", url))
+}
+
+impl AuthProvider {
+    /// Look up who `access_token` belongs to
+    pub async fn identity(
+        &self,
+        http: &reqwest::Client,
+        access_token: &str,
+    ) -> anyhow::Result<Identity> {
+        match self.kind {
+            ProviderKind::Oidc => {
+                let claims: Value = http
+                    .get(&self.userinfo_url)
+                    .bearer_auth(access_token)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                identity_from_claims(&claims)
+            }
+            ProviderKind::GitHub => {
+                let get = |path: &str| {
+                    http.get(format!("{}{}", self.userinfo_url, path))
+                        .bearer_auth(access_token)
+                        .header(reqwest::header::USER_AGENT, USER_AGENT)
+                        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+                        .send()
+                };
+                let user: Value = get("/user").await?.error_for_status()?.json().await?;
+                let emails: Value = get("/user/emails")
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                identity_from_github(&user, &emails)
+            }
+        }
+    }
+}
+
+/// Identity from OpenID Connect userinfo claims. An email the issuer marks
+/// unverified is refused. One without the claim can still sign in, since
+/// some issuers (Entra ID) omit it and the operator chose the issuer, but
+/// counts as unverified.
+fn identity_from_claims(claims: &Value) -> anyhow::Result<Identity> {
+    let subject = claims
+        .get("sub")
+        .and_then(Value::as_str)
+        .context("userinfo has no sub")?;
+    let email = claims
+        .get("email")
+        .and_then(Value::as_str)
+        .filter(|e| e.contains('@'))
+        .context("userinfo has no email; request the email scope")?;
+    let email_verified = match claims.get("email_verified") {
+        Some(Value::Bool(verified)) => Some(*verified),
+        Some(Value::String(verified)) => Some(verified == "true"),
+        _ => None,
+    };
+    if email_verified == Some(false) {
+        bail!("{} is not verified with the provider", email);
+    }
+    let text = |key: &str| claims.get(key).and_then(Value::as_str).map(String::from);
+    Ok(Identity {
+        subject: subject.to_string(),
+        email: email.to_string(),
+        email_verified: email_verified == Some(true),
+        name: text("name"),
+        avatar_url: text("picture"),
+    })
+}
+
+/// Identity from GitHub's `/user` and `/user/emails`, using the primary
+/// verified email (or any verified one)
+fn identity_from_github(user: &Value, emails: &Value) -> anyhow::Result<Identity> {
+    let subject = user
+        .get("id")
+        .and_then(Value::as_u64)
+        .context("GitHub user has no id")?;
+    let verified: Vec<&Value> = emails
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|e| e.get("verified").and_then(Value::as_bool) == Some(true))
+        .collect();
+    let email = verified
+        .iter()
+        .find(|e| e.get("primary").and_then(Value::as_bool) == Some(true))
+        .or(verified.first())
+        .and_then(|e| e.get("email").and_then(Value::as_str))
+        .context("GitHub account has no verified email")?;
+    let text = |key: &str| user.get(key).and_then(Value::as_str).map(String::from);
+    Ok(Identity {
+        subject: subject.to_string(),
+        email: email.to_string(),
+        email_verified: true,
+        name: text("name").or_else(|| text("login")),
+        avatar_url: text("avatar_url"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn parse(
+        vars: &[(&str, &str)],
+        discovery: Option<DiscoveryDocument>,
+    ) -> anyhow::Result<AuthProviders> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        AuthProviders::parse(
+            |name| vars.get(name).cloned(),
+            "https://portal.example.com/",
+            discovery,
+        )
+    }
+
+    #[test]
+    fn test_providers_from_env() {
+        assert!(parse(&[], None).unwrap().is_empty());
+
+        let providers = parse(
+            &[
+                ("GOOGLE_CLIENT_ID", "g-id"),
+                ("GOOGLE_CLIENT_SECRET", "g-secret"),
+                (
+                    "GOOGLE_REDIRECT_URI",
+                    "https://portal.example.com/api/auth/google/callback",
+                ),
+                ("GITHUB_CLIENT_ID", "gh-id"),
+                ("GITHUB_CLIENT_SECRET", "gh-secret"),
+                ("OIDC_CLIENT_ID", "okta-id"),
+                ("OIDC_CLIENT_SECRET", "okta-secret"),
+                ("OIDC_NAME", "Okta"),
+            ],
+            Some(DiscoveryDocument {
+                authorization_endpoint: "https://acme.okta.com/oauth2/v1/authorize".into(),
+                token_endpoint: "https://acme.okta.com/oauth2/v1/token".into(),
+                userinfo_endpoint: "https://acme.okta.com/oauth2/v1/userinfo".into(),
+            }),
+        )
+        .unwrap();
+        let ids: Vec<_> = providers.info().into_iter().map(|p| p.id).collect();
+        assert_eq!(ids, ["google", "github", "oidc"]);
+        assert!(providers.single().is_none());
+
+        let github = providers.get("github").unwrap();
+        assert_eq!(
+            github.client.redirect_url().unwrap().as_str(),
+            "https://portal.example.com/api/auth/github/callback"
+        );
+        assert_eq!(providers.get("oidc").unwrap().name, "Okta");
+
+        // A client id without its secret is a mistake worth failing on
+        assert!(parse(&[("GITHUB_CLIENT_ID", "gh-id")], None).is_err());
+    }
+
+    #[test]
+    fn test_identity_from_claims() {
+        let identity = identity_from_claims(&json!({
+            "sub": "1234", "email": "ada@example.com", "email_verified": true,
+            "name": "Ada", "picture": "https://example.com/a.png"
+        }))
+        .unwrap();
+        assert_eq!(identity.subject, "1234");
+        assert_eq!(identity.name.as_deref(), Some("Ada"));
+        assert!(identity.email_verified);
+
+        // Some issuers send the claim as a string, some leave it out
+        assert!(
+            identity_from_claims(&json!({"sub": "1", "email": "a@b.c", "email_verified": "true"}))
+                .unwrap()
+                .email_verified
+        );
+        assert!(
+            !identity_from_claims(&json!({"sub": "1", "email": "a@b.c"}))
+                .unwrap()
+                .email_verified
+        );
+        assert!(identity_from_claims(
+            &json!({"sub": "1", "email": "a@b.c", "email_verified": false})
+        )
+        .is_err());
+        assert!(identity_from_claims(&json!({"sub": "1"})).is_err());
+    }
+
+    #[test]
+    fn test_identity_from_github() {
+        let user = json!({"id": 42, "login": "ada", "name": null, "avatar_url": "https://x/a"});
+        let emails = json!([
+            {"email": "old@example.com", "primary": false, "verified": true},
+            {"email": "ada@example.com", "primary": true, "verified": true},
+            {"email": "spam@example.com", "primary": false, "verified": false}
+        ]);
+        let identity = identity_from_github(&user, &emails).unwrap();
+        assert_eq!(identity.subject, "42");
+        assert_eq!(identity.email, "ada@example.com");
+        assert_eq!(identity.name.as_deref(), Some("ada"));
+
+        let unverified = json!([{"email": "ada@example.com", "primary": true, "verified": false}]);
+        assert!(identity_from_github(&user, &unverified).is_err());
+    }
+}
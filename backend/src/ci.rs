@@ -0,0 +1,256 @@
+//! CI status from GitHub
+//!
+//! When `GITHUB_WEBHOOK_SECRET` is set, `POST /api/webhooks/github` accepts
+//! `workflow_run` and `check_run` events from a repository or organization
+//! webhook. The latest result of each workflow or check is kept per branch,
+//! and sessions whose proxy reported that repository and branch show the
+//! combined result for the newest commit.
+
+use crate::models::CiRun;
+use crate::schema::ci_runs;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+use shared::{CiState, CiStatus};
+use std::collections::HashMap;
+
+pub const SIGNATURE_HEADER: &str = "X-Hub-Signature-256";
+pub const EVENT_HEADER: &str = "X-GitHub-Event";
+
+/// Check runs from GitHub Actions jobs; their workflow runs are reported
+/// instead, so each workflow shows once
+const ACTIONS_APP_SLUG: &str = "github-actions";
+
+/// Secret GitHub signs webhook deliveries with
+#[derive(Debug, Clone)]
+pub struct GitHubWebhook {
+    secret: String,
+}
+
+impl GitHubWebhook {
+    /// The configured webhook, `None` when CI status is off
+    pub fn from_env() -> Option<Self> {
+        std::env::var("GITHUB_WEBHOOK_SECRET")
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .map(|secret| Self { secret })
+    }
+
+    /// Whether `signature` (the `X-Hub-Signature-256` header) is GitHub's
+    /// signature of `body`
+    pub fn verify(&self, signature: Option<&str>, body: &[u8]) -> bool {
+        let Some(tag) = signature
+            .and_then(|s| s.strip_prefix("sha256="))
+            .and_then(|hex_tag| hex::decode(hex_tag).ok())
+        else {
+            return false;
+        };
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes())
+            .expect("HMAC accepts any key length");
+        mac.update(body);
+        mac.verify_slice(&tag).is_ok()
+    }
+}
+
+/// A run's state as reported by one webhook delivery
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunUpdate {
+    pub repository: String,
+    pub branch: String,
+    pub name: String,
+    pub head_sha: String,
+    pub state: CiState,
+    pub url: Option<String>,
+}
+
+/// The run a `workflow_run` or `check_run` event reports, if it's one that
+/// is tracked
+pub fn parse_event(event: &str, payload: &Value) -> Option<RunUpdate> {
+    let str_at = |value: &Value, pointer: &str| {
+        value
+            .pointer(pointer)
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+    };
+    let repository = str_at(payload, "/repository/full_name")?;
+
+    let (run, branch) = match event {
+        "workflow_run" => {
+            let run = payload.get("workflow_run")?;
+            (run, str_at(run, "/head_branch")?)
+        }
+        "check_run" => {
+            let run = payload.get("check_run")?;
+            if str_at(run, "/app/slug").as_deref() == Some(ACTIONS_APP_SLUG) {
+                return None;
+            }
+            (run, str_at(run, "/check_suite/head_branch")?)
+        }
+        _ => return None,
+    };
+
+    let status = str_at(run, "/status")?;
+    let conclusion = str_at(run, "/conclusion");
+    Some(RunUpdate {
+        repository,
+        branch,
+        name: str_at(run, "/name")?,
+        head_sha: str_at(run, "/head_sha")?,
+        state: CiState::from_github(&status, conclusion.as_deref()),
+        url: str_at(run, "/html_url"),
+    })
+}
+
+/// Store a run's latest state, replacing the previous one for its branch
+pub fn record(conn: &mut PgConnection, update: RunUpdate) -> QueryResult<()> {
+    let run = CiRun {
+        repository: update.repository,
+        branch: update.branch,
+        name: update.name,
+        head_sha: update.head_sha,
+        state: update.state.as_str().to_string(),
+        url: update.url,
+        updated_at: chrono::Utc::now().naive_utc(),
+    };
+    diesel::insert_into(ci_runs::table)
+        .values(&run)
+        .on_conflict((ci_runs::repository, ci_runs::branch, ci_runs::name))
+        .do_update()
+        .set(&run)
+        .execute(conn)?;
+    Ok(())
+}
+
+/// CI status of each `(repository, branch)` that has any runs
+pub fn statuses(
+    conn: &mut PgConnection,
+    branches: &[(String, String)],
+) -> QueryResult<HashMap<(String, String), CiStatus>> {
+    if branches.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let repositories: Vec<&String> = branches.iter().map(|(repo, _)| repo).collect();
+    let runs = ci_runs::table
+        .filter(ci_runs::repository.eq_any(repositories))
+        .select(CiRun::as_select())
+        .load::<CiRun>(conn)?;
+
+    let mut by_branch: HashMap<(String, String), Vec<CiRun>> = HashMap::new();
+    for run in runs {
+        let key = (run.repository.clone(), run.branch.clone());
+        if branches.contains(&key) {
+            by_branch.entry(key).or_default().push(run);
+        }
+    }
+    Ok(by_branch
+        .into_iter()
+        .filter_map(|(key, runs)| Some((key, status_of(runs)?)))
+        .collect())
+}
+
+/// Combined status of the newest commit's runs on a branch
+fn status_of(mut runs: Vec<CiRun>) -> Option<CiStatus> {
+    let newest = runs.iter().max_by_key(|r| r.updated_at)?;
+    let head_sha = newest.head_sha.clone();
+    let updated_at: NaiveDateTime = newest.updated_at;
+    runs.retain(|r| r.head_sha == head_sha);
+    runs.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let runs: Vec<shared::CiRun> = runs
+        .into_iter()
+        .map(|r| shared::CiRun {
+            state: CiState::parse(&r.state).unwrap_or(CiState::Pending),
+            name: r.name,
+            url: r.url,
+        })
+        .collect();
+    Some(CiStatus {
+        state: CiState::combine(runs.iter().map(|r| r.state))?,
+        head_sha,
+        runs,
+        updated_at: updated_at.and_utc().to_rfc3339(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn run(name: &str, sha: &str, state: &str, minute: u32) -> CiRun {
+        CiRun {
+            repository: "o/r".to_string(),
+            branch: "feature".to_string(),
+            name: name.to_string(),
+            head_sha: sha.to_string(),
+            state: state.to_string(),
+            url: Some(format!("https://github.com/o/r/runs/{}", name)),
+            updated_at: chrono::NaiveDate::from_ymd_opt(2026, 2, 1)
+                .unwrap()
+                .and_hms_opt(12, minute, 0)
+                .unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_verify() {
+        let webhook = GitHubWebhook {
+            secret: "It's a Secret to Everybody".to_string(),
+        };
+        // Example from GitHub's webhook documentation
+        let signature = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+        assert!(webhook.verify(Some(signature), b"Hello, World!"));
+        assert!(!webhook.verify(Some(signature), b"Hello, World?"));
+        assert!(!webhook.verify(None, b"Hello, World!"));
+    }
+
+    #[test]
+    fn test_parse_workflow_run() {
+        let payload = json!({
+            "action": "completed",
+            "repository": { "full_name": "o/r" },
+            "workflow_run": {
+                "name": "CI",
+                "head_branch": "feature",
+                "head_sha": "abc123",
+                "status": "completed",
+                "conclusion": "failure",
+                "html_url": "https://github.com/o/r/actions/runs/1"
+            }
+        });
+        let update = parse_event("workflow_run", &payload).unwrap();
+        assert_eq!(update.branch, "feature");
+        assert_eq!(update.state, CiState::Failure);
+
+        // Actions jobs arrive as workflow runs, not check runs
+        let payload = json!({
+            "repository": { "full_name": "o/r" },
+            "check_run": {
+                "name": "test",
+                "head_sha": "abc123",
+                "status": "queued",
+                "app": { "slug": "github-actions" },
+                "check_suite": { "head_branch": "feature" }
+            }
+        });
+        assert_eq!(parse_event("check_run", &payload), None);
+        assert_eq!(parse_event("push", &payload), None);
+    }
+
+    #[test]
+    fn test_status_uses_newest_commit() {
+        let status = status_of(vec![
+            run("lint", "old", "failure", 0),
+            run("test", "new", "success", 5),
+            run("build", "new", "pending", 6),
+        ])
+        .unwrap();
+        assert_eq!(status.head_sha, "new");
+        assert_eq!(status.state, CiState::Pending);
+        assert_eq!(status.runs.len(), 2);
+        assert_eq!(status.runs[0].name, "build");
+    }
+}
@@ -0,0 +1,373 @@
+//! Per-connection actors
+//!
+//! Every proxy and web client socket is served by its own actor, which owns
+//! all of that connection's state and handles one decoded message at a time
+//! from its mailbox. Around it, [`serve`] runs:
+//!
+//! - a reader that decodes frames into the mailbox. The mailbox is bounded,
+//!   so a peer sending faster than its actor keeps up waits on its own
+//!   socket rather than piling up memory;
+//! - a writer task that drains the connection's relay queue (see
+//!   [`crate::relay`]) onto the socket, so a peer that stops reading never
+//!   holds up whoever is sending to it;
+//! - a timer for actors with periodic work between messages, see
+//!   [`ConnectionActor::TICK_INTERVAL`];
+//! - supervision: the connection ends when the peer closes or goes silent,
+//!   the actor asks to stop, the actor panics, or the server closes it
+//!   through its [`ConnectionActor::close_token`]. However it ends, the
+//!   actor's [`ConnectionActor::stopped`] cleanup runs and the writer stops,
+//!   and a panic takes down only that connection.
+//!
+//! Actors see decoded messages and answer through a relay queue, so relay
+//! logic can be exercised in tests with a stream of frames and no socket.
+
+use crate::relay::RelayReceiver;
+use async_trait::async_trait;
+use axum::extract::ws::Message;
+use futures_util::{FutureExt, Sink, SinkExt, Stream, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use std::panic::AssertUnwindSafe;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+/// Decoded messages waiting for an actor before its reader stops reading
+/// the socket
+pub const MAILBOX_CAPACITY: usize = 64;
+
+/// Whether the connection carries on after a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    /// Close the connection, e.g. after refusing the peer
+    Stop,
+}
+
+/// How a connection ended, for logs and tests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ended {
+    /// The peer closed the socket, errored, or went silent
+    PeerGone,
+    /// The actor returned [`Flow::Stop`]
+    Stopped,
+    /// The actor panicked while handling a message
+    Panicked,
+    /// The server closed the connection through its close token
+    Closed,
+}
+
+/// One connection's state and message handling
+#[async_trait]
+pub trait ConnectionActor: Send {
+    /// What the peer sends, decoded from JSON text frames. Frames that
+    /// don't decode are ignored.
+    type Message: DeserializeOwned + Send;
+
+    /// Names the peer kind in logs
+    const PEER: &'static str;
+
+    /// How often [`ConnectionActor::tick`] runs, if at all
+    const TICK_INTERVAL: Option<Duration> = None;
+
+    /// Whether a message shows the peer sends heartbeats. Once one has
+    /// arrived, silence for longer than the heartbeat timeout ends the
+    /// connection, so half-open sockets are dropped.
+    fn is_heartbeat(_msg: &Self::Message) -> bool {
+        false
+    }
+
+    async fn handle(&mut self, msg: Self::Message) -> Flow;
+
+    /// Periodic work between messages, like checking access that can lapse
+    async fn tick(&mut self) -> Flow {
+        Flow::Continue
+    }
+
+    /// Cancelled to close the connection from elsewhere in the server
+    fn close_token(&self) -> CancellationToken {
+        CancellationToken::new()
+    }
+
+    /// Cleanup once the connection has ended, however it ended
+    fn stopped(&mut self, _ended: Ended) {}
+}
+
+/// Serve one connection: `frames` feed `actor`, and whatever is queued on
+/// `outbox` is written to `sink` after `prepare` has had a look at it
+pub async fn serve<A, W, R, T, P>(
+    mut actor: A,
+    sink: W,
+    frames: R,
+    outbox: RelayReceiver<T>,
+    prepare: P,
+) -> Ended
+where
+    A: ConnectionActor,
+    W: Sink<Message> + Unpin + Send + 'static,
+    R: Stream<Item = Result<Message, axum::Error>> + Unpin,
+    T: Serialize + Send + 'static,
+    P: FnMut(&mut T) + Send + 'static,
+{
+    let writer = tokio::spawn(write_frames(sink, outbox, prepare));
+    let (mailbox, inbox) = mpsc::channel(MAILBOX_CAPACITY);
+
+    let ended = {
+        let reader = read_frames::<A, R>(frames, mailbox);
+        let handler = handle_mail(&mut actor, inbox);
+        tokio::pin!(handler);
+        tokio::select! {
+            ended = &mut handler => ended,
+            // Finish what the peer sent before it went away
+            _ = reader => handler.await,
+        }
+    };
+
+    actor.stopped(ended);
+    writer.abort();
+    ended
+}
+
+/// Decode frames into the mailbox until the peer goes away or the actor
+/// stops taking mail
+async fn read_frames<A, R>(mut frames: R, mailbox: mpsc::Sender<A::Message>)
+where
+    A: ConnectionActor,
+    R: Stream<Item = Result<Message, axum::Error>> + Unpin,
+{
+    let mut heartbeat_seen = false;
+    while let Some(frame) = next_frame(&mut frames, heartbeat_seen).await {
+        match frame {
+            Ok(Message::Text(text)) => {
+                let Ok(msg) = serde_json::from_str::<A::Message>(&text) else {
+                    continue;
+                };
+                heartbeat_seen |= A::is_heartbeat(&msg);
+                if mailbox.send(msg).await.is_err() {
+                    return;
+                }
+            }
+            Ok(Message::Close(_)) => {
+                info!("{} WebSocket closed", A::PEER);
+                return;
+            }
+            Err(e) => {
+                error!("{} WebSocket error: {}", A::PEER, e);
+                return;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Next frame from a socket. Once the peer has sent a heartbeat, going
+/// silent for longer than the heartbeat timeout ends the stream.
+async fn next_frame<R>(frames: &mut R, heartbeat_seen: bool) -> Option<Result<Message, axum::Error>>
+where
+    R: Stream<Item = Result<Message, axum::Error>> + Unpin,
+{
+    if !heartbeat_seen {
+        return frames.next().await;
+    }
+    let timeout = Duration::from_millis(shared::HEARTBEAT_TIMEOUT_MS);
+    match tokio::time::timeout(timeout, frames.next()).await {
+        Ok(frame) => frame,
+        Err(_) => {
+            warn!("No heartbeat for {:?}, closing connection", timeout);
+            None
+        }
+    }
+}
+
+/// Hand the actor its mail and ticks, one at a time
+async fn handle_mail<A: ConnectionActor>(
+    actor: &mut A,
+    mut inbox: mpsc::Receiver<A::Message>,
+) -> Ended {
+    let close = actor.close_token();
+    let mut ticks = A::TICK_INTERVAL.map(|period| {
+        let mut ticks = tokio::time::interval_at(Instant::now() + period, period);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ticks
+    });
+    loop {
+        let flow = tokio::select! {
+            msg = inbox.recv() => match msg {
+                Some(msg) => AssertUnwindSafe(actor.handle(msg)).catch_unwind().await,
+                None => return Ended::PeerGone,
+            },
+            _ = next_tick(&mut ticks) => AssertUnwindSafe(actor.tick()).catch_unwind().await,
+            _ = close.cancelled() => {
+                info!("{} connection closed by the server", A::PEER);
+                return Ended::Closed;
+            }
+        };
+        match flow {
+            Ok(Flow::Continue) => {}
+            Ok(Flow::Stop) => return Ended::Stopped,
+            Err(_) => {
+                error!("{} connection panicked, closing it", A::PEER);
+                return Ended::Panicked;
+            }
+        }
+    }
+}
+
+/// Wait for the actor's next tick; never, if it has none
+async fn next_tick(ticks: &mut Option<Interval>) {
+    match ticks {
+        Some(ticks) => {
+            ticks.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+async fn write_frames<W, T, P>(mut sink: W, mut outbox: RelayReceiver<T>, mut prepare: P)
+where
+    W: Sink<Message> + Unpin,
+    T: Serialize,
+    P: FnMut(&mut T),
+{
+    while let Some(mut msg) = outbox.recv().await {
+        prepare(&mut msg);
+        if let Ok(json) = serde_json::to_string(&msg) {
+            if sink.send(Message::Text(json)).await.is_err() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::relay;
+    use futures_util::stream;
+    use std::sync::atomic::AtomicU64;
+    use std::sync::{Arc, Mutex};
+
+    /// Records what it handles; stops on "stop" and panics on "panic".
+    /// Ticks are recorded as "tick", and the third one stops.
+    #[derive(Default)]
+    struct Recorder {
+        handled: Arc<Mutex<Vec<String>>>,
+        ended: Arc<Mutex<Option<Ended>>>,
+        ticks: usize,
+        close: CancellationToken,
+    }
+
+    #[async_trait]
+    impl ConnectionActor for Recorder {
+        type Message = String;
+        const PEER: &'static str = "Test";
+        const TICK_INTERVAL: Option<Duration> = Some(Duration::from_millis(20));
+
+        async fn handle(&mut self, msg: String) -> Flow {
+            match msg.as_str() {
+                "stop" => Flow::Stop,
+                "panic" => panic!("handler failed"),
+                _ => {
+                    self.handled.lock().unwrap().push(msg);
+                    Flow::Continue
+                }
+            }
+        }
+
+        async fn tick(&mut self) -> Flow {
+            self.ticks += 1;
+            self.handled.lock().unwrap().push("tick".to_string());
+            if self.ticks == 3 {
+                Flow::Stop
+            } else {
+                Flow::Continue
+            }
+        }
+
+        fn close_token(&self) -> CancellationToken {
+            self.close.clone()
+        }
+
+        fn stopped(&mut self, ended: Ended) {
+            *self.ended.lock().unwrap() = Some(ended);
+        }
+    }
+
+    fn text(msg: &str) -> Result<Message, axum::Error> {
+        Ok(Message::Text(serde_json::to_string(msg).unwrap()))
+    }
+
+    async fn run<R>(frames: R) -> (Ended, Vec<String>, Option<Ended>)
+    where
+        R: Stream<Item = Result<Message, axum::Error>> + Unpin,
+    {
+        run_actor(Recorder::default(), frames).await
+    }
+
+    async fn run_actor<R>(actor: Recorder, frames: R) -> (Ended, Vec<String>, Option<Ended>)
+    where
+        R: Stream<Item = Result<Message, axum::Error>> + Unpin,
+    {
+        let handled = actor.handled.clone();
+        let stopped = actor.ended.clone();
+        let (_tx, outbox) = relay::channel::<String>(8, Arc::new(AtomicU64::new(0)), "test");
+        let ended = serve(actor, futures_util::sink::drain(), frames, outbox, |_| {}).await;
+        let handled = handled.lock().unwrap().clone();
+        let stopped = *stopped.lock().unwrap();
+        (ended, handled, stopped)
+    }
+
+    #[tokio::test]
+    async fn test_handles_messages_in_order_until_peer_leaves() {
+        let frames = stream::iter(vec![
+            text("a"),
+            Ok(Message::Text("not json".to_string())),
+            text("b"),
+            Ok(Message::Close(None)),
+            text("after close"),
+        ]);
+        let (ended, handled, stopped) = run(frames).await;
+        assert_eq!(ended, Ended::PeerGone);
+        assert_eq!(handled, vec!["a", "b"]);
+        assert_eq!(stopped, Some(Ended::PeerGone));
+    }
+
+    #[tokio::test]
+    async fn test_stop_ends_open_connection() {
+        let frames =
+            stream::iter(vec![text("a"), text("stop"), text("b")]).chain(stream::pending());
+        let (ended, handled, stopped) = run(frames).await;
+        assert_eq!(ended, Ended::Stopped);
+        assert_eq!(handled, vec!["a"]);
+        assert_eq!(stopped, Some(Ended::Stopped));
+    }
+
+    #[tokio::test]
+    async fn test_panic_still_cleans_up() {
+        let frames = stream::iter(vec![text("panic"), text("a")]).chain(stream::pending());
+        let (ended, handled, stopped) = run(frames).await;
+        assert_eq!(ended, Ended::Panicked);
+        assert!(handled.is_empty());
+        assert_eq!(stopped, Some(Ended::Panicked));
+    }
+
+    #[tokio::test]
+    async fn test_ticks_run_between_messages() {
+        let frames = stream::iter(vec![text("a")]).chain(stream::pending());
+        let (ended, handled, stopped) = run(frames).await;
+        assert_eq!(ended, Ended::Stopped);
+        assert_eq!(handled, vec!["a", "tick", "tick", "tick"]);
+        assert_eq!(stopped, Some(Ended::Stopped));
+    }
+
+    #[tokio::test]
+    async fn test_close_token_ends_connection() {
+        let actor = Recorder::default();
+        actor.close.cancel();
+        let frames = stream::iter(vec![text("a")]).chain(stream::pending());
+        let (ended, _, stopped) = run_actor(actor, frames).await;
+        assert_eq!(ended, Ended::Closed);
+        assert_eq!(stopped, Some(Ended::Closed));
+    }
+}
@@ -3,7 +3,7 @@ use bigdecimal::ToPrimitive;
 use diesel::pg::PgConnection;
 use diesel::prelude::*;
 use diesel::r2d2::{self, ConnectionManager, Pool};
-use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations};
 use std::env;
 use uuid::Uuid;
 
@@ -25,21 +25,6 @@ pub fn create_pool() -> Result<DbPool> {
     Ok(pool)
 }
 
-/// Run pending database migrations
-/// Returns the list of migrations that were applied
-pub fn run_migrations(pool: &DbPool) -> Result<Vec<String>> {
-    let mut conn = pool.get()?;
-
-    let applied: Vec<String> = conn
-        .run_pending_migrations(MIGRATIONS)
-        .map_err(|e| anyhow::anyhow!("Failed to run migrations: {}", e))?
-        .iter()
-        .map(|m| m.to_string())
-        .collect();
-
-    Ok(applied)
-}
-
 /// Aggregated usage data for a user (includes both active and deleted sessions)
 #[derive(Debug, Default, Clone)]
 pub struct UserUsage {
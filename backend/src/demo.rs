@@ -0,0 +1,200 @@
+//! Demo mode: a simulated session for trying the frontend without Claude
+//!
+//! With `--demo` (which implies `--dev-mode`) the backend creates a session
+//! for the test user and plays the scripted transcript in `demo/session.jsonl`
+//! into it, a step at a time, through the same storage and broadcast path
+//! real proxy output takes. The session stays connected afterwards: prompts
+//! sent from the web UI are echoed back with a canned reply, so sending,
+//! rendering, and cost tracking can all be exercised without a Claude login
+//! or a proxy. The session is recreated from scratch on every start.
+
+use crate::db::DbPool;
+use crate::handlers::websocket::{delete_acked_inputs, handle_claude_output, SessionManager};
+use crate::models::{NewSessionMember, NewSessionWithId};
+use crate::schema::{session_members, sessions, users};
+use diesel::prelude::*;
+use serde_json::{json, Value};
+use shared::ProxyMessage;
+use std::time::Duration;
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// The same id every start, so bookmarked links keep working
+pub const DEMO_SESSION_ID: Uuid = Uuid::from_u128(0xdec0de00_0000_4000_8000_000000000001);
+
+const SCRIPT: &str = include_str!("../demo/session.jsonl");
+
+/// Pause between scripted messages, roughly a streaming turn's pace
+const STEP_DELAY: Duration = Duration::from_millis(700);
+
+/// The scripted transcript, one Claude output message per line
+fn script() -> Vec<Value> {
+    SCRIPT
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// What the simulated Claude says back to a prompt
+fn reply(prompt: &str) -> Vec<Value> {
+    vec![
+        json!({
+            "type": "user",
+            "message": {"role": "user", "content": [{"type": "text", "text": prompt}]},
+            "session_id": "demo",
+        }),
+        json!({
+            "type": "assistant",
+            "message": {
+                "id": format!("msg_demo_{}", Uuid::new_v4().simple()),
+                "type": "message",
+                "role": "assistant",
+                "content": [{
+                    "type": "text",
+                    "text": format!(
+                        "This is a demo session, so nothing ran. You asked:\n\n> {}",
+                        prompt.replace('\n', "\n> ")
+                    ),
+                }],
+                "usage": {"input_tokens": 200, "output_tokens": 30},
+            },
+            "session_id": "demo",
+        }),
+        json!({
+            "type": "result",
+            "subtype": "success",
+            "is_error": false,
+            "duration_ms": 900,
+            "num_turns": 1,
+            "result": "Demo reply",
+            "session_id": "demo",
+            "total_cost_usd": 0.0012,
+            "usage": {"input_tokens": 200, "output_tokens": 30},
+        }),
+    ]
+}
+
+/// Recreate the demo session for `user_id`, dropping what earlier runs stored
+fn reset_session(conn: &mut PgConnection, user_id: Uuid) -> QueryResult<()> {
+    conn.transaction(|conn| {
+        diesel::delete(sessions::table.find(DEMO_SESSION_ID)).execute(conn)?;
+        diesel::insert_into(sessions::table)
+            .values(&NewSessionWithId {
+                id: DEMO_SESSION_ID,
+                user_id,
+                session_name: "demo-host-todo-app".to_string(),
+                session_key: DEMO_SESSION_ID.to_string(),
+                working_directory: "/home/demo/todo-app".to_string(),
+                status: "active".to_string(),
+                git_branch: Some("fix-duplicate-todos".to_string()),
+                git_repo: None,
+                host_info: None,
+                client_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            })
+            .execute(conn)?;
+        diesel::insert_into(session_members::table)
+            .values(&NewSessionMember {
+                session_id: DEMO_SESSION_ID,
+                user_id,
+                role: "owner".to_string(),
+            })
+            .execute(conn)?;
+        Ok(())
+    })
+}
+
+/// Run the simulated session until the server stops
+pub async fn run(db_pool: DbPool, session_manager: SessionManager) {
+    let user_id = match db_pool
+        .get()
+        .map_err(|e| e.to_string())
+        .and_then(|mut conn| {
+            let user_id = users::table
+                .filter(users::email.eq("testing@testing.local"))
+                .select(users::id)
+                .first::<Uuid>(&mut conn)
+                .map_err(|e| e.to_string())?;
+            reset_session(&mut conn, user_id).map_err(|e| e.to_string())?;
+            Ok(user_id)
+        }) {
+        Ok(user_id) => user_id,
+        Err(e) => {
+            error!("Failed to set up demo session: {}", e);
+            return;
+        }
+    };
+    info!(
+        "Demo session {} ready for user {}",
+        DEMO_SESSION_ID, user_id
+    );
+
+    let key = DEMO_SESSION_ID.to_string();
+    let (tx, mut rx) = session_manager.proxy_channel();
+    session_manager.register_session(key.clone(), tx.clone());
+
+    let output = |content: Value| {
+        handle_claude_output(
+            &session_manager,
+            &Some(key.clone()),
+            Some(DEMO_SESSION_ID),
+            &db_pool,
+            &tx,
+            content,
+            None,
+            None,
+            None,
+        );
+    };
+
+    for content in script() {
+        tokio::time::sleep(STEP_DELAY).await;
+        output(content);
+    }
+
+    while let Some(msg) = rx.recv().await {
+        let content = match msg {
+            ProxyMessage::SequencedInput { seq, content, .. } => {
+                delete_acked_inputs(&db_pool, DEMO_SESSION_ID, seq);
+                content
+            }
+            ProxyMessage::ClaudeInput { content, .. } => content,
+            _ => continue,
+        };
+        let prompt = match content {
+            Value::String(text) => text,
+            other => other.to_string(),
+        };
+        for content in reply(&prompt) {
+            tokio::time::sleep(STEP_DELAY).await;
+            output(content);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_parses() {
+        let script = script();
+        assert_eq!(script.len(), SCRIPT.lines().count());
+        assert!(script.iter().all(|m| m.get("type").is_some()));
+        assert_eq!(script.last().unwrap()["type"], "result");
+    }
+
+    #[test]
+    fn test_reply_quotes_prompt() {
+        let messages = reply("fix it\nplease");
+        assert_eq!(messages.len(), 3);
+        assert_eq!(
+            messages[0]["message"]["content"][0]["text"],
+            "fix it\nplease"
+        );
+        let text = messages[1]["message"]["content"][0]["text"]
+            .as_str()
+            .unwrap();
+        assert!(text.ends_with("> fix it\n> please"));
+    }
+}
@@ -0,0 +1,255 @@
+//! Email notifications
+//!
+//! When `EMAIL_API_KEY` and `EMAIL_FROM` are set, notifications routed to
+//! email are sent through Resend's API to the address each recipient signed
+//! in with, one message per recipient. Guests have no address of their own
+//! and are skipped. By default only daily digests go out this way.
+
+use crate::db::DbPool;
+use crate::notifier::{self, Notification, NotificationKind, Notifier, Recipient};
+use crate::schema::users;
+use async_trait::async_trait;
+use diesel::prelude::*;
+use serde_json::{json, Value};
+use shared::NotificationChannel;
+use std::time::Duration;
+use tracing::warn;
+use uuid::Uuid;
+
+const SEND_URL: &str = "https://api.resend.com/emails";
+
+/// Requests that take longer than this count as failed
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Result summaries are cut to this many characters
+const MAX_SUMMARY_CHARS: usize = 2000;
+
+/// Domain of the placeholder addresses guests are given
+const GUEST_EMAIL_DOMAIN: &str = "@guests.invalid";
+
+/// Where mail comes from, read from `EMAIL_*` variables
+#[derive(Clone)]
+pub struct Email {
+    api_key: String,
+    from: String,
+    public_url: String,
+    client: reqwest::Client,
+}
+
+impl std::fmt::Debug for Email {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Email")
+            .field("from", &self.from)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Email {
+    /// The configured sender, `None` when email is off
+    pub fn from_env(public_url: &str) -> Result<Option<Self>, String> {
+        Self::parse(|name| std::env::var(name).ok(), public_url)
+    }
+
+    fn parse(
+        var: impl Fn(&str) -> Option<String>,
+        public_url: &str,
+    ) -> Result<Option<Self>, String> {
+        let var = |name: &str| {
+            var(name)
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+
+        let Some(api_key) = var("EMAIL_API_KEY") else {
+            return Ok(None);
+        };
+        let from = var("EMAIL_FROM").ok_or("EMAIL_FROM must be set")?;
+
+        Ok(Some(Self {
+            api_key,
+            from,
+            public_url: public_url.to_string(),
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+        }))
+    }
+
+    async fn send(&self, to: &str, subject: &str, text: &str) -> anyhow::Result<()> {
+        self.client
+            .post(SEND_URL)
+            .bearer_auth(&self.api_key)
+            .json(&json!({
+                "from": self.from,
+                "to": [to],
+                "subject": subject,
+                "text": text,
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Notifier for Email {
+    fn channel(&self) -> NotificationChannel {
+        NotificationChannel::Email
+    }
+
+    /// Mail each recipient who has a real address
+    async fn deliver(
+        &self,
+        db_pool: &DbPool,
+        notification: &Notification,
+        recipients: &[Recipient],
+    ) -> anyhow::Result<()> {
+        let user_ids: Vec<Uuid> = recipients.iter().map(|r| r.user_id).collect();
+        let addresses: Vec<String> = users::table
+            .filter(users::id.eq_any(&user_ids))
+            .select(users::email)
+            .load(&mut db_pool.get()?)?;
+
+        let link = notifier::session_link(&self.public_url, notification.session_id);
+        let subject = subject(notification);
+        let text = text(notification, &link);
+        let mut failed = 0;
+        for address in addresses
+            .iter()
+            .filter(|address| !address.ends_with(GUEST_EMAIL_DOMAIN))
+        {
+            if let Err(e) = self.send(address, &subject, &text).await {
+                warn!("Failed to email {}: {}", address, e);
+                failed += 1;
+            }
+        }
+        if failed > 0 {
+            anyhow::bail!("{} of {} emails failed", failed, addresses.len());
+        }
+        Ok(())
+    }
+}
+
+/// The subject line for a notification
+fn subject(notification: &Notification) -> String {
+    let label = &notification.label;
+    match &notification.kind {
+        NotificationKind::TurnFinished { result } => {
+            let failed = result.get("is_error").and_then(|e| e.as_bool()) == Some(true);
+            format!("{} {}", label, if failed { "failed" } else { "finished" })
+        }
+        NotificationKind::PermissionRequested { tool_name } => {
+            format!("{} is asking for permission to use {}", label, tool_name)
+        }
+        NotificationKind::PermissionWaiting { tool_name, .. } => {
+            format!("{} is waiting for permission to use {}", label, tool_name)
+        }
+        NotificationKind::PermissionEscalated { tool_name, .. } => format!(
+            "Escalated to you: {} is waiting for permission to use {}",
+            label, tool_name
+        ),
+        NotificationKind::DailyDigest { day, .. } => format!("{} on {}", label, day),
+    }
+}
+
+/// The message for a notification, in plain text
+fn text(notification: &Notification, link: &str) -> String {
+    let label = &notification.label;
+    let body = match &notification.kind {
+        NotificationKind::TurnFinished { result } => turn_finished_text(label, result),
+        NotificationKind::PermissionRequested { tool_name } => {
+            format!("{} is asking for permission to use {}.", label, tool_name)
+        }
+        NotificationKind::PermissionWaiting { tool_name, minutes } => format!(
+            "{} has been waiting {} minute{} for permission to use {}.",
+            label,
+            minutes,
+            if *minutes == 1 { "" } else { "s" },
+            tool_name
+        ),
+        NotificationKind::PermissionEscalated { tool_name, minutes } => format!(
+            "{} has been waiting {} minutes for permission to use {}, and you're its escalation contact.",
+            label, minutes, tool_name
+        ),
+        NotificationKind::DailyDigest {
+            day,
+            turns,
+            failed_turns,
+            cost_usd,
+        } => format!(
+            "{} on {} (UTC): {}.",
+            label,
+            day,
+            notifier::digest_summary(*turns, *failed_turns, *cost_usd)
+        ),
+    };
+    format!("{}\n\nOpen the session: {}\n", body, link)
+}
+
+fn turn_finished_text(label: &str, result: &Value) -> String {
+    let failed = result.get("is_error").and_then(|e| e.as_bool()) == Some(true);
+    let mut text = format!("{} {}", label, if failed { "failed" } else { "finished" });
+    if let Some(cost) = result.get("total_cost_usd").and_then(|c| c.as_f64()) {
+        text.push_str(&format!(" (${:.2} so far)", cost));
+    }
+    text.push('.');
+    if let Some(summary) = result
+        .get("result")
+        .and_then(|r| r.as_str())
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+    {
+        text.push_str("\n\n");
+        text.push_str(&notifier::truncate(summary, MAX_SUMMARY_CHARS));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn parse(vars: &[(&str, &str)]) -> Result<Option<Email>, String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Email::parse(|name| vars.get(name).cloned(), "https://portal.example.com")
+    }
+
+    #[test]
+    fn test_config() {
+        assert!(parse(&[]).unwrap().is_none());
+        assert!(parse(&[("EMAIL_API_KEY", "re_1")]).is_err());
+
+        let email = parse(&[
+            ("EMAIL_API_KEY", "re_1"),
+            ("EMAIL_FROM", "Portal <portal@example.com>"),
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(email.from, "Portal <portal@example.com>");
+    }
+
+    #[test]
+    fn test_digest() {
+        let digest = Notification {
+            session_id: Uuid::nil(),
+            label: "api (laptop)".to_string(),
+            kind: NotificationKind::DailyDigest {
+                day: chrono::NaiveDate::from_ymd_opt(2026, 2, 14).unwrap(),
+                turns: 3,
+                failed_turns: 1,
+                cost_usd: 1.5,
+            },
+        };
+        assert_eq!(subject(&digest), "api (laptop) on 2026-02-14");
+        assert_eq!(
+            text(&digest, "https://p/x"),
+            "api (laptop) on 2026-02-14 (UTC): 3 turns (1 failed), $1.50.\n\nOpen the session: https://p/x\n"
+        );
+    }
+}
@@ -0,0 +1,335 @@
+//! Message embeddings for semantic search
+//!
+//! When an embedding provider is configured, a background task embeds the
+//! text transcript search indexes for each stored prompt and assistant turn
+//! and keeps the vectors in `message_embeddings`. Semantic search
+//! (`GET /api/search?mode=semantic`) embeds the query the same way and ranks
+//! messages by cosine similarity.
+//!
+//! Providers speak the OpenAI `/embeddings` API: `openai`, or `local` for a
+//! server such as Ollama or llama.cpp running next to the backend. Vectors are
+//! tagged with the model that produced them, so changing `EMBEDDINGS_MODEL`
+//! re-embeds messages as the task reaches them; vectors from other models are
+//! never compared.
+
+use crate::db::DbPool;
+use crate::schema::{message_embeddings, messages};
+use anyhow::{bail, Context};
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// How often the task looks for messages without embeddings
+pub const INDEX_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Messages sent to the provider per request
+const BATCH_SIZE: i64 = 64;
+
+/// Requests per run, so a large backlog doesn't hold a connection for long
+const MAX_BATCHES_PER_RUN: usize = 10;
+
+/// Longer text is cut before embedding; most models stop well short of this
+/// anyway
+const MAX_INPUT_CHARS: usize = 4000;
+
+const OPENAI_URL: &str = "https://api.openai.com/v1/embeddings";
+const OPENAI_MODEL: &str = "text-embedding-3-small";
+const LOCAL_URL: &str = "http://localhost:11434/v1/embeddings";
+const LOCAL_MODEL: &str = "nomic-embed-text";
+
+/// Where embeddings come from, read from `EMBEDDINGS_*` variables
+#[derive(Debug, Clone)]
+pub struct EmbeddingProvider {
+    kind: &'static str,
+    url: String,
+    api_key: Option<String>,
+    /// Stored with each vector; only vectors from this model are searched
+    pub model: String,
+    client: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl EmbeddingProvider {
+    /// The configured provider, `None` when semantic search is off
+    pub fn from_env() -> Result<Option<Self>, String> {
+        Self::parse(|name| std::env::var(name).ok())
+    }
+
+    fn parse(var: impl Fn(&str) -> Option<String>) -> Result<Option<Self>, String> {
+        let var = |name: &str| var(name).filter(|v| !v.trim().is_empty());
+
+        let kind = match var("EMBEDDINGS_PROVIDER") {
+            Some(kind) => kind.trim().to_lowercase(),
+            None => return Ok(None),
+        };
+        let (kind, default_url, default_model) = match kind.as_str() {
+            "openai" => ("openai", OPENAI_URL, OPENAI_MODEL),
+            "local" => ("local", LOCAL_URL, LOCAL_MODEL),
+            other => return Err(format!("Unknown EMBEDDINGS_PROVIDER {:?}", other)),
+        };
+        let api_key = var("EMBEDDINGS_API_KEY");
+        if kind == "openai" && api_key.is_none() {
+            return Err("EMBEDDINGS_API_KEY must be set".to_string());
+        }
+        Ok(Some(Self {
+            kind,
+            url: var("EMBEDDINGS_URL").unwrap_or_else(|| default_url.to_string()),
+            api_key,
+            model: var("EMBEDDINGS_MODEL").unwrap_or_else(|| default_model.to_string()),
+            client: reqwest::Client::new(),
+        }))
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.kind
+    }
+
+    /// One vector per input, in order
+    pub async fn embed(&self, inputs: &[String]) -> anyhow::Result<Vec<Vec<f32>>> {
+        let mut request = self.client.post(&self.url).json(&serde_json::json!({
+            "model": self.model,
+            "input": inputs,
+        }));
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+        let response = request.send().await.context("sending embedding request")?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            bail!("Embedding provider returned {}: {}", status, body);
+        }
+        let mut data = response
+            .json::<EmbeddingResponse>()
+            .await
+            .context("reading embedding response")?
+            .data;
+        if data.len() != inputs.len() {
+            bail!(
+                "Embedding provider returned {} vectors for {} inputs",
+                data.len(),
+                inputs.len()
+            );
+        }
+        data.sort_by_key(|d| d.index);
+        Ok(data.into_iter().map(|d| d.embedding).collect())
+    }
+}
+
+/// Cosine similarity, or `None` when either vector is empty, zero, or a
+/// different length
+pub fn cosine(a: &[f32], b: &[f32]) -> Option<f32> {
+    if a.is_empty() || a.len() != b.len() {
+        return None;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return None;
+    }
+    Some(dot / (norm_a.sqrt() * norm_b.sqrt()))
+}
+
+/// What gets embedded for a stored message, cut to `MAX_INPUT_CHARS`
+fn embedding_input(content: &str) -> String {
+    let Ok(value) = serde_json::from_str::<Value>(content) else {
+        return String::new();
+    };
+    truncate_input(&crate::search::searchable_text(&value))
+}
+
+/// Trimmed and cut to `MAX_INPUT_CHARS`, so typed text embeds like stored
+/// messages do
+pub fn truncate_input(text: &str) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(MAX_INPUT_CHARS) {
+        Some((end, _)) => text[..end].to_string(),
+        None => text.to_string(),
+    }
+}
+
+/// Embed prompts and assistant turns that have no vector from the current
+/// model yet, newest first
+pub async fn run_indexing(db_pool: &DbPool, provider: &EmbeddingProvider) {
+    let mut embedded = 0;
+    for _ in 0..MAX_BATCHES_PER_RUN {
+        let batch: Vec<(Uuid, String)> = {
+            let Ok(mut conn) = db_pool.get() else {
+                error!("Failed to get DB connection for message embeddings");
+                return;
+            };
+            match messages::table
+                .left_join(
+                    message_embeddings::table.on(message_embeddings::message_id
+                        .eq(messages::id)
+                        .and(message_embeddings::model.eq(&provider.model))),
+                )
+                .filter(message_embeddings::message_id.is_null())
+                .filter(messages::role.eq_any(["user", "assistant"]))
+                .order(messages::created_at.desc())
+                .select((messages::id, messages::content))
+                .limit(BATCH_SIZE)
+                .load(&mut conn)
+            {
+                Ok(batch) => batch,
+                Err(e) => {
+                    error!("Failed to load messages to embed: {}", e);
+                    return;
+                }
+            }
+        };
+        if batch.is_empty() {
+            break;
+        }
+        let full = batch.len() as i64 == BATCH_SIZE;
+
+        let inputs: Vec<(Uuid, String)> = batch
+            .into_iter()
+            .map(|(id, content)| (id, embedding_input(&content)))
+            .collect();
+        let texts: Vec<String> = inputs
+            .iter()
+            .filter(|(_, text)| !text.is_empty())
+            .map(|(_, text)| text.clone())
+            .collect();
+        let mut vectors = if texts.is_empty() {
+            Vec::new()
+        } else {
+            match provider.embed(&texts).await {
+                Ok(vectors) => vectors,
+                Err(e) => {
+                    warn!("Embedding {} messages failed: {:#}", texts.len(), e);
+                    return;
+                }
+            }
+        }
+        .into_iter();
+
+        // Messages without text get an empty vector so they aren't retried
+        let rows: Vec<_> = inputs
+            .iter()
+            .map(|(id, text)| {
+                let embedding = if text.is_empty() {
+                    Vec::new()
+                } else {
+                    vectors.next().unwrap_or_default()
+                };
+                (
+                    message_embeddings::message_id.eq(*id),
+                    message_embeddings::model.eq(&provider.model),
+                    message_embeddings::embedding.eq(embedding),
+                )
+            })
+            .collect();
+
+        let Ok(mut conn) = db_pool.get() else {
+            error!("Failed to get DB connection for message embeddings");
+            return;
+        };
+        let result = diesel::insert_into(message_embeddings::table)
+            .values(&rows)
+            .on_conflict(message_embeddings::message_id)
+            .do_update()
+            .set((
+                message_embeddings::model.eq(excluded(message_embeddings::model)),
+                message_embeddings::embedding.eq(excluded(message_embeddings::embedding)),
+                message_embeddings::created_at.eq(diesel::dsl::now),
+            ))
+            .execute(&mut conn);
+        if let Err(e) = result {
+            // Usually a message removed by retention meanwhile; the rest are
+            // picked up next run
+            warn!("Failed to store message embeddings: {}", e);
+            return;
+        }
+        embedded += rows.len();
+
+        if !full {
+            break;
+        }
+    }
+    if embedded > 0 {
+        info!("Embedded {} messages with {}", embedded, provider.model);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn parse(vars: &[(&str, &str)]) -> Result<Option<EmbeddingProvider>, String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        EmbeddingProvider::parse(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_provider_config() {
+        assert!(parse(&[]).unwrap().is_none());
+
+        let local = parse(&[("EMBEDDINGS_PROVIDER", "Local")]).unwrap().unwrap();
+        assert_eq!(local.url, LOCAL_URL);
+        assert_eq!(local.model, LOCAL_MODEL);
+        assert!(local.api_key.is_none());
+
+        let openai = parse(&[
+            ("EMBEDDINGS_PROVIDER", "openai"),
+            ("EMBEDDINGS_API_KEY", "sk-test"),
+            ("EMBEDDINGS_MODEL", "text-embedding-3-large"),
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(openai.url, OPENAI_URL);
+        assert_eq!(openai.model, "text-embedding-3-large");
+
+        assert!(parse(&[("EMBEDDINGS_PROVIDER", "openai")]).is_err());
+        assert!(parse(&[("EMBEDDINGS_PROVIDER", "word2vec")]).is_err());
+    }
+
+    #[test]
+    fn test_cosine() {
+        assert_eq!(cosine(&[1.0, 0.0], &[2.0, 0.0]), Some(1.0));
+        assert_eq!(cosine(&[1.0, 0.0], &[0.0, 3.0]), Some(0.0));
+        assert_eq!(cosine(&[], &[]), None);
+        assert_eq!(cosine(&[0.0, 0.0], &[1.0, 0.0]), None);
+        assert_eq!(cosine(&[1.0], &[1.0, 0.0]), None);
+    }
+
+    #[test]
+    fn test_embedding_input() {
+        let prompt = r#"{"type": "user", "content": "Why is the cache cold?"}"#;
+        assert_eq!(embedding_input(prompt), "Why is the cache cold?");
+
+        // Tool results carry no searchable text
+        let result = r#"{"type": "user", "message": {"content": [
+            {"type": "tool_result", "content": "ok"}
+        ]}}"#;
+        assert_eq!(embedding_input(result), "");
+
+        let long = serde_json::json!({"type": "user", "content": "é".repeat(5000)});
+        assert_eq!(
+            embedding_input(&long.to_string()).chars().count(),
+            MAX_INPUT_CHARS
+        );
+    }
+}
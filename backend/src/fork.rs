@@ -0,0 +1,158 @@
+//! Session forking
+//!
+//! Rebuilds a session's conversation up to a message as the first prompt of a
+//! new session. Claude's own transcript lives on the proxy's machine, so a
+//! fork replays the stored messages as text rather than resuming it: prompts
+//! and replies verbatim, tool calls by name only, tool output left out.
+
+use crate::schema::messages;
+use diesel::prelude::*;
+use serde_json::Value;
+use uuid::Uuid;
+
+/// Longest replayed transcript, in characters. Older turns are dropped first.
+const MAX_TRANSCRIPT_CHARS: usize = 60_000;
+
+/// One turn of the replayed transcript, or `None` for messages that carry no
+/// conversation (tool results, system and status messages)
+fn transcript_turn(content: &Value) -> Option<String> {
+    let speaker = match content.get("type").and_then(Value::as_str)? {
+        "user" => "User",
+        "assistant" => "Assistant",
+        _ => return None,
+    };
+    let mut parts: Vec<String> = Vec::new();
+    if let Some(text) = content.get("content").and_then(Value::as_str) {
+        parts.push(text.to_string());
+    }
+    match content.pointer("/message/content") {
+        Some(Value::String(text)) => parts.push(text.clone()),
+        Some(Value::Array(blocks)) => {
+            for block in blocks {
+                match block.get("type").and_then(Value::as_str) {
+                    Some("text") => {
+                        if let Some(text) = block.get("text").and_then(Value::as_str) {
+                            parts.push(text.to_string());
+                        }
+                    }
+                    Some("tool_use") => {
+                        if let Some(name) = block.get("name").and_then(Value::as_str) {
+                            parts.push(format!("[used the {} tool]", name));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+    let text = parts
+        .iter()
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    (!text.is_empty()).then(|| format!("{}: {}", speaker, text))
+}
+
+/// The fork's first prompt: the most recent `turns` that fit, framed so
+/// Claude takes them as context instead of instructions to carry out again
+pub fn build_prompt(source_session_name: &str, turns: &[String]) -> String {
+    let mut kept = 0;
+    let mut total = 0;
+    for turn in turns.iter().rev() {
+        let len = turn.chars().count() + 2;
+        if kept > 0 && total + len > MAX_TRANSCRIPT_CHARS {
+            break;
+        }
+        total += len;
+        kept += 1;
+    }
+    let omitted = turns.len() - kept;
+
+    let mut prompt = format!(
+        "This session is a fork of \"{}\". Below is our conversation up to the \
+         point I forked it. Treat it as context only and don't redo any of it: \
+         reply with a one-line summary of where we left off, then wait for me \
+         to say how to continue.\n\n<transcript>\n",
+        source_session_name
+    );
+    if omitted > 0 {
+        prompt.push_str(&format!("({} earlier turns omitted)\n\n", omitted));
+    }
+    prompt.push_str(&turns[omitted..].join("\n\n"));
+    prompt.push_str("\n</transcript>");
+    prompt
+}
+
+/// The transcript turns of `session_id` up to and including message `at_seq`
+pub fn load_turns(
+    conn: &mut PgConnection,
+    session_id: Uuid,
+    at_seq: i64,
+) -> QueryResult<Vec<String>> {
+    let contents: Vec<String> = messages::table
+        .filter(messages::session_id.eq(session_id))
+        .filter(messages::seq.le(at_seq))
+        .order(messages::seq.asc())
+        .select(messages::content)
+        .load(conn)?;
+    Ok(contents
+        .iter()
+        .filter_map(|content| serde_json::from_str::<Value>(content).ok())
+        .filter_map(|content| transcript_turn(&content))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_transcript_turn() {
+        let prompt = json!({"type": "user", "content": "Fix the login bug"});
+        assert_eq!(
+            transcript_turn(&prompt).as_deref(),
+            Some("User: Fix the login bug")
+        );
+
+        let reply = json!({
+            "type": "assistant",
+            "message": {"content": [
+                {"type": "text", "text": "Looking at auth.rs."},
+                {"type": "tool_use", "name": "Read", "input": {"file_path": "auth.rs"}},
+            ]}
+        });
+        assert_eq!(
+            transcript_turn(&reply).as_deref(),
+            Some("Assistant: Looking at auth.rs.\n\n[used the Read tool]")
+        );
+
+        let tool_result = json!({
+            "type": "user",
+            "message": {"content": [{"type": "tool_result", "content": "fn login() {}"}]}
+        });
+        assert_eq!(transcript_turn(&tool_result), None);
+        assert_eq!(
+            transcript_turn(&json!({"type": "result", "result": "done"})),
+            None
+        );
+    }
+
+    #[test]
+    fn test_build_prompt_drops_oldest_turns() {
+        let turns = vec![
+            format!("User: {}", "a".repeat(MAX_TRANSCRIPT_CHARS)),
+            "Assistant: ok".to_string(),
+            "User: now the tests".to_string(),
+        ];
+        let prompt = build_prompt("auth-fix", &turns);
+        assert!(prompt.contains("fork of \"auth-fix\""));
+        assert!(prompt.contains("(1 earlier turns omitted)"));
+        assert!(prompt.ends_with("Assistant: ok\n\nUser: now the tests\n</transcript>"));
+
+        let prompt = build_prompt("auth-fix", &turns[1..]);
+        assert!(!prompt.contains("omitted"));
+    }
+}
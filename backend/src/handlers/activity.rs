@@ -0,0 +1,203 @@
+//! Workspace activity feed.
+//!
+//! Every event published through [`SessionManager::publish_event`] is stored
+//! here as well as sent to webhooks. The feed lists them newest first across
+//! the sessions the caller can see (every session, for admins), filtered by
+//! kind or session, a page at a time.
+//!
+//! [`SessionManager::publish_event`]: super::websocket::SessionManager::publish_event
+
+use crate::db::DbPool;
+use crate::models::{ActivityEventRow, NewActivityEvent};
+use crate::schema::{activity_events, session_members, sessions, users};
+use crate::AppState;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use diesel::prelude::*;
+use serde::Deserialize;
+use shared::{ActivityEvent, EventKind, UserRole};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::error;
+use uuid::Uuid;
+
+const SESSION_COOKIE_NAME: &str = "cc_session";
+
+/// Events returned when the request doesn't say
+const DEFAULT_LIMIT: i64 = 100;
+
+/// Most events a single request can ask for
+const MAX_LIMIT: i64 = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct ActivityParams {
+    /// Comma-separated event kinds, e.g. `error,budget_exceeded`; all when
+    /// missing
+    pub kinds: Option<String>,
+    pub session_id: Option<Uuid>,
+    /// Only events older than this RFC 3339 time, for the next page
+    pub before: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Extract user_id from signed session cookie
+fn extract_user_id(app_state: &AppState, cookies: &Cookies) -> Result<Uuid, StatusCode> {
+    // In dev mode, allow unauthenticated access with test user
+    if app_state.dev_mode {
+        let mut conn = app_state
+            .db_pool
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        return users::table
+            .filter(users::email.eq("testing@testing.local"))
+            .select(users::id)
+            .first::<Uuid>(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let cookie = cookies
+        .signed(&app_state.cookie_key)
+        .get(SESSION_COOKIE_NAME)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    cookie.value().parse().map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+/// Store an event for the feed
+pub fn record(db_pool: &DbPool, kind: EventKind, session_id: Uuid, data: &serde_json::Value) {
+    let Ok(mut conn) = db_pool.get() else {
+        error!(
+            "Failed to get DB connection to record {} event",
+            kind.name()
+        );
+        return;
+    };
+    let event = NewActivityEvent {
+        kind: kind.name().to_string(),
+        session_id,
+        data: data.clone(),
+    };
+    if let Err(e) = diesel::insert_into(activity_events::table)
+        .values(&event)
+        .execute(&mut conn)
+    {
+        error!("Failed to record {} event: {}", kind.name(), e);
+    }
+}
+
+/// Parse the `kinds` filter; unknown names are a 400 rather than an empty feed
+fn parse_kinds(kinds: &str) -> Result<Vec<EventKind>, String> {
+    kinds
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| EventKind::parse(name).ok_or_else(|| format!("Unknown event kind {:?}", name)))
+        .collect()
+}
+
+/// GET /api/activity?kinds=error,budget_exceeded&session_id=...&before=...&limit=100
+pub async fn list_activity(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Query(params): Query<ActivityParams>,
+) -> Result<Json<Vec<ActivityEvent>>, (StatusCode, String)> {
+    let user_id = extract_user_id(&app_state, &cookies).map_err(|code| (code, String::new()))?;
+    let kinds = params
+        .kinds
+        .as_deref()
+        .map(parse_kinds)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?
+        .filter(|kinds| !kinds.is_empty());
+    let before = params
+        .before
+        .as_deref()
+        .map(|before| {
+            chrono::DateTime::parse_from_rfc3339(before)
+                .map(|t| t.naive_utc())
+                .map_err(|_| (StatusCode::BAD_REQUEST, format!("Bad time {:?}", before)))
+        })
+        .transpose()?;
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let internal = |e: diesel::result::Error| {
+        error!("Failed to list activity: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, String::new())
+    };
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, String::new()))?;
+    let account = super::helpers::account_role(&mut conn, user_id).map_err(internal)?;
+
+    let mut query = activity_events::table
+        .inner_join(sessions::table)
+        .left_join(users::table.on(users::id.eq(sessions::user_id)))
+        .select((
+            ActivityEventRow::as_select(),
+            sessions::session_name,
+            users::email.nullable(),
+        ))
+        .order(activity_events::created_at.desc())
+        .limit(limit)
+        .into_boxed();
+    // Admins see every session; everyone else sees the ones they're a member of
+    if account != UserRole::Admin {
+        query = query.filter(
+            activity_events::session_id.eq_any(
+                session_members::table
+                    .filter(session_members::user_id.eq(user_id))
+                    .select(session_members::session_id),
+            ),
+        );
+    }
+    if let Some(kinds) = &kinds {
+        let names: Vec<&str> = kinds.iter().map(EventKind::name).collect();
+        query = query.filter(activity_events::kind.eq_any(names));
+    }
+    if let Some(session_id) = params.session_id {
+        query = query.filter(activity_events::session_id.eq(session_id));
+    }
+    if let Some(before) = before {
+        query = query.filter(activity_events::created_at.lt(before));
+    }
+
+    let rows: Vec<(ActivityEventRow, String, Option<String>)> =
+        query.load(&mut conn).map_err(internal)?;
+    Ok(Json(rows.into_iter().filter_map(to_event).collect()))
+}
+
+/// The API form of a stored event; rows of a kind this build doesn't know
+/// are left out
+fn to_event(
+    (row, session_name, owner_email): (ActivityEventRow, String, Option<String>),
+) -> Option<ActivityEvent> {
+    Some(ActivityEvent {
+        id: row.id,
+        kind: EventKind::parse(&row.kind)?,
+        session_id: row.session_id,
+        session_name,
+        owner_email,
+        data: row.data,
+        created_at: row.created_at.and_utc().to_rfc3339(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_kinds() {
+        assert_eq!(
+            parse_kinds("error, budget_exceeded,"),
+            Ok(vec![EventKind::Error, EventKind::BudgetExceeded])
+        );
+        assert_eq!(parse_kinds(""), Ok(vec![]));
+        assert!(parse_kinds("error,nope").is_err());
+    }
+}
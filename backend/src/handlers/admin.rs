@@ -1,6 +1,6 @@
 //! Admin dashboard API handlers
 //!
-//! These endpoints are restricted to users with is_admin=true.
+//! These endpoints are restricted to users with the admin role.
 
 use axum::{
     extract::{Path, State},
@@ -8,8 +8,10 @@ use axum::{
     Json,
 };
 use bigdecimal::ToPrimitive;
+use chrono::Datelike;
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use shared::{SessionOutcome, UserRole};
 use std::sync::Arc;
 use tower_cookies::Cookies;
 use tracing::{error, info, warn};
@@ -17,7 +19,9 @@ use uuid::Uuid;
 
 use crate::{
     db::get_user_usage,
+    handlers::websocket::CompressionTotals,
     models::{NewRawMessageLog, RawMessageLog, User},
+    relay::RelayDropTotals,
     schema, AppState,
 };
 
@@ -62,7 +66,7 @@ pub async fn require_admin(
     }
 
     // Check if user is admin
-    if !user.is_admin {
+    if !user.is_admin() {
         warn!("Non-admin user {} attempted admin access", user.email);
         return Err(StatusCode::FORBIDDEN);
     }
@@ -78,7 +82,7 @@ pub async fn require_admin(
 pub struct AdminStats {
     /// Total number of registered users
     pub total_users: i64,
-    /// Number of users with is_admin=true
+    /// Number of users with the admin role
     pub admin_users: i64,
     /// Number of disabled users
     pub disabled_users: i64,
@@ -100,6 +104,70 @@ pub struct AdminStats {
     pub total_cache_creation_tokens: i64,
     /// Total cache read tokens across all sessions
     pub total_cache_read_tokens: i64,
+    /// Session outcomes per week, oldest first
+    pub outcome_trend: Vec<OutcomeWeek>,
+    /// Large outputs received compressed from proxies since startup
+    pub proxy_compression: CompressionTotals,
+    /// Large outputs sent compressed to web clients since startup
+    pub web_compression: CompressionTotals,
+    /// Messages dropped since startup because a peer's relay queue was full
+    pub relay_drops: RelayDropTotals,
+}
+
+/// Number of weeks of session outcomes included in the stats
+const OUTCOME_TREND_WEEKS: i64 = 12;
+
+/// Session outcome counts for sessions created in a single week
+#[derive(Debug, Serialize, PartialEq)]
+pub struct OutcomeWeek {
+    /// Monday the week starts on (YYYY-MM-DD)
+    pub week_start: String,
+    pub succeeded: i64,
+    pub partially: i64,
+    pub failed: i64,
+    pub abandoned: i64,
+    /// Sessions nobody labeled yet
+    pub unlabeled: i64,
+}
+
+/// Bucket (created_at, outcome) rows into weeks ending with the week containing `today`
+fn outcome_trend(
+    rows: &[(chrono::NaiveDateTime, Option<String>)],
+    today: chrono::NaiveDate,
+) -> Vec<OutcomeWeek> {
+    let week_of = |date: chrono::NaiveDate| {
+        date - chrono::Duration::days(date.weekday().num_days_from_monday() as i64)
+    };
+    let current_week = week_of(today);
+
+    let mut weeks: Vec<OutcomeWeek> = (0..OUTCOME_TREND_WEEKS)
+        .rev()
+        .map(|i| OutcomeWeek {
+            week_start: (current_week - chrono::Duration::weeks(i)).to_string(),
+            succeeded: 0,
+            partially: 0,
+            failed: 0,
+            abandoned: 0,
+            unlabeled: 0,
+        })
+        .collect();
+
+    for (created_at, outcome) in rows {
+        let weeks_ago = (current_week - week_of(created_at.date())).num_weeks();
+        if !(0..OUTCOME_TREND_WEEKS).contains(&weeks_ago) {
+            continue;
+        }
+        let week = &mut weeks[(OUTCOME_TREND_WEEKS - 1 - weeks_ago) as usize];
+        match outcome.as_deref().and_then(SessionOutcome::parse) {
+            Some(SessionOutcome::Succeeded) => week.succeeded += 1,
+            Some(SessionOutcome::Partially) => week.partially += 1,
+            Some(SessionOutcome::Failed) => week.failed += 1,
+            Some(SessionOutcome::Abandoned) => week.abandoned += 1,
+            None => week.unlabeled += 1,
+        }
+    }
+
+    weeks
 }
 
 pub async fn get_stats(
@@ -124,7 +192,7 @@ pub async fn get_stats(
         })?;
 
     let admin_users: i64 = schema::users::table
-        .filter(schema::users::is_admin.eq(true))
+        .filter(schema::users::role.eq(UserRole::Admin.as_str()))
         .count()
         .get_result(&mut conn)
         .map_err(|e| {
@@ -253,6 +321,21 @@ pub async fn get_stats(
     let total_cache_creation_tokens = active_cache_creation + deleted_cache_creation;
     let total_cache_read_tokens = active_cache_read + deleted_cache_read;
 
+    // Session outcomes over the trend window
+    let today = chrono::Utc::now().date_naive();
+    let trend_start = (today - chrono::Duration::weeks(OUTCOME_TREND_WEEKS))
+        .and_hms_opt(0, 0, 0)
+        .unwrap_or_default();
+    let outcome_rows: Vec<(chrono::NaiveDateTime, Option<String>)> = schema::sessions::table
+        .filter(schema::sessions::created_at.ge(trend_start))
+        .select((schema::sessions::created_at, schema::sessions::outcome))
+        .load(&mut conn)
+        .map_err(|e| {
+            error!("Failed to load session outcomes: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let outcome_trend = outcome_trend(&outcome_rows, today);
+
     // Get connected client counts from session manager
     let connected_proxy_clients = app_state.session_manager.sessions.len();
     let connected_web_clients: usize = app_state
@@ -261,6 +344,7 @@ pub async fn get_stats(
         .iter()
         .map(|r| r.value().len())
         .sum();
+    let compression = &app_state.session_manager.compression;
 
     Ok(Json(AdminStats {
         total_users,
@@ -275,6 +359,10 @@ pub async fn get_stats(
         total_output_tokens,
         total_cache_creation_tokens,
         total_cache_read_tokens,
+        outcome_trend,
+        proxy_compression: compression.from_proxies.totals(),
+        web_compression: compression.to_web_clients.totals(),
+        relay_drops: app_state.session_manager.relay_drops.totals(),
     }))
 }
 
@@ -288,9 +376,11 @@ pub struct AdminUserInfo {
     pub email: String,
     pub name: Option<String>,
     pub avatar_url: Option<String>,
+    pub role: UserRole,
     pub is_admin: bool,
     pub disabled: bool,
     pub voice_enabled: bool,
+    pub billing_customer_id: Option<String>,
     pub created_at: String,
     pub session_count: i64,
     pub total_spend_usd: f64,
@@ -339,14 +429,17 @@ pub async fn list_users(
         // Get aggregated usage via helper
         let usage = get_user_usage(&mut conn, user.id).unwrap_or_default();
 
+        let role = user.role();
         user_infos.push(AdminUserInfo {
             id: user.id,
             email: user.email,
             name: user.name,
             avatar_url: user.avatar_url,
-            is_admin: user.is_admin,
+            role,
+            is_admin: role == UserRole::Admin,
             disabled: user.disabled,
             voice_enabled: user.voice_enabled,
+            billing_customer_id: user.billing_customer_id,
             created_at: user.created_at.to_string(),
             session_count,
             total_spend_usd: usage.cost_usd,
@@ -362,10 +455,12 @@ pub async fn list_users(
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateUserRequest {
-    pub is_admin: Option<bool>,
+    pub role: Option<UserRole>,
     pub disabled: Option<bool>,
     pub voice_enabled: Option<bool>,
     pub ban_reason: Option<Option<String>>, // Option<Option<...>> to distinguish "not sent" from "sent as null"
+    /// Who the billing provider knows the user as (see metering)
+    pub billing_customer_id: Option<Option<String>>,
 }
 
 pub async fn update_user(
@@ -377,7 +472,7 @@ pub async fn update_user(
     let admin = require_admin(&app_state, &cookies).await?;
 
     // Prevent admin from demoting themselves
-    if user_id == admin.id && update.is_admin == Some(false) {
+    if user_id == admin.id && update.role.is_some_and(|role| role != UserRole::Admin) {
         warn!(
             "Admin {} attempted to remove their own admin status",
             admin.email
@@ -406,18 +501,43 @@ pub async fn update_user(
         .map_err(|_| StatusCode::NOT_FOUND)?;
 
     // Build update query
-    if let Some(is_admin_val) = update.is_admin {
+    if let Some(role) = update.role {
         diesel::update(schema::users::table.find(user_id))
-            .set(schema::users::is_admin.eq(is_admin_val))
+            .set(schema::users::role.eq(role.as_str()))
             .execute(&mut conn)
             .map_err(|e| {
-                error!("Failed to update user admin status: {}", e);
+                error!("Failed to update user role: {}", e);
                 StatusCode::INTERNAL_SERVER_ERROR
             })?;
         info!(
-            "Admin {} set is_admin={} for user {}",
-            admin.email, is_admin_val, target_user.email
+            "Admin {} set role={} for user {}",
+            admin.email,
+            role.as_str(),
+            target_user.email
         );
+
+        // Viewers can't run sessions, so their proxy tokens stop working
+        if role == UserRole::Viewer {
+            use crate::schema::proxy_auth_tokens;
+
+            let revoked_count = diesel::update(
+                proxy_auth_tokens::table
+                    .filter(proxy_auth_tokens::user_id.eq(user_id))
+                    .filter(proxy_auth_tokens::revoked.eq(false)),
+            )
+            .set(proxy_auth_tokens::revoked.eq(true))
+            .execute(&mut conn)
+            .map_err(|e| {
+                error!("Failed to revoke viewer tokens: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+            if revoked_count > 0 {
+                info!(
+                    "Revoked {} proxy tokens for viewer {}",
+                    revoked_count, target_user.email
+                );
+            }
+        }
     }
 
     if let Some(disabled_val) = update.disabled {
@@ -503,6 +623,23 @@ pub async fn update_user(
         );
     }
 
+    if let Some(customer_id) = update.billing_customer_id {
+        let customer_id = customer_id
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty());
+        diesel::update(schema::users::table.find(user_id))
+            .set(schema::users::billing_customer_id.eq(customer_id.as_ref()))
+            .execute(&mut conn)
+            .map_err(|e| {
+                error!("Failed to update billing customer ID: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        info!(
+            "Admin {} set billing_customer_id={:?} for user {}",
+            admin.email, customer_id, target_user.email
+        );
+    }
+
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -523,6 +660,7 @@ pub struct AdminSessionInfo {
     pub created_at: String,
     pub last_activity: String,
     pub is_connected: bool,
+    pub outcome: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -573,6 +711,7 @@ pub async fn list_sessions(
                 created_at: session.created_at.to_string(),
                 last_activity: session.last_activity.to_string(),
                 is_connected,
+                outcome: session.outcome,
             }
         })
         .collect();
@@ -821,3 +960,51 @@ pub async fn delete_raw_message(
 
     Ok(StatusCode::NO_CONTENT)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(y: i32, m: u32, d: u32) -> chrono::NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_outcome_trend_buckets_by_week() {
+        // 2026-01-21 is a Wednesday; its week starts Monday 2026-01-19
+        let today = NaiveDate::from_ymd_opt(2026, 1, 21).unwrap();
+        let rows = vec![
+            (at(2026, 1, 19), Some("succeeded".to_string())),
+            (at(2026, 1, 20), Some("failed".to_string())),
+            (at(2026, 1, 18), None),
+            (at(2026, 1, 12), Some("partially".to_string())),
+        ];
+
+        let trend = outcome_trend(&rows, today);
+        assert_eq!(trend.len(), OUTCOME_TREND_WEEKS as usize);
+
+        let current = trend.last().unwrap();
+        assert_eq!(current.week_start, "2026-01-19");
+        assert_eq!((current.succeeded, current.failed), (1, 1));
+
+        let previous = &trend[trend.len() - 2];
+        assert_eq!(previous.week_start, "2026-01-12");
+        assert_eq!((previous.unlabeled, previous.partially), (1, 1));
+    }
+
+    #[test]
+    fn test_outcome_trend_ignores_rows_outside_window() {
+        let today = NaiveDate::from_ymd_opt(2026, 1, 21).unwrap();
+        let rows = vec![
+            (at(2025, 6, 1), Some("succeeded".to_string())),
+            (at(2026, 2, 1), Some("succeeded".to_string())),
+        ];
+
+        let trend = outcome_trend(&rows, today);
+        assert!(trend.iter().all(|w| w.succeeded == 0));
+    }
+}
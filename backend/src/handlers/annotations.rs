@@ -0,0 +1,189 @@
+//! Annotations external tools attach to a session's messages
+//!
+//! Callers authenticate with a browser session or, for CI and scanners, a
+//! proxy token. Any member of a session may annotate it; annotating doesn't
+//! drive the session, so read-only tokens may too.
+
+use crate::models::{MessageAnnotation, NewMessageAnnotation};
+use crate::schema::{message_annotations, messages, sessions};
+use crate::AppState;
+use axum::{
+    extract::{ConnectInfo, Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use diesel::prelude::*;
+use shared::{AnnotationInfo, AnnotationLevel, CreateAnnotationRequest, ProxyMessage};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::error;
+use uuid::Uuid;
+
+/// Annotations kept per session; posting more is refused
+const MAX_ANNOTATIONS_PER_SESSION: i64 = 1_000;
+
+/// Check the caller can see the session and its token covers its directory
+fn verify_access(
+    conn: &mut diesel::pg::PgConnection,
+    session_id: Uuid,
+    user_id: Uuid,
+    scopes: &shared::ProxyTokenScopes,
+) -> Result<(), StatusCode> {
+    super::helpers::session_role(conn, session_id, user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let working_directory = sessions::table
+        .find(session_id)
+        .select(sessions::working_directory)
+        .first::<String>(conn)
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    if !scopes.allows_directory(&working_directory) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(())
+}
+
+/// An annotation with the stored messages it currently covers
+fn annotation_info(
+    conn: &mut diesel::pg::PgConnection,
+    annotation: MessageAnnotation,
+) -> Result<AnnotationInfo, diesel::result::Error> {
+    let message_ids = messages::table
+        .filter(messages::session_id.eq(annotation.session_id))
+        .filter(messages::seq.between(annotation.start_seq, annotation.end_seq))
+        .order(messages::seq.asc())
+        .select(messages::id)
+        .load::<Uuid>(conn)?;
+
+    Ok(AnnotationInfo {
+        id: annotation.id,
+        source: annotation.source,
+        level: AnnotationLevel::parse(&annotation.level).unwrap_or_default(),
+        label: annotation.label,
+        url: annotation.url,
+        data: annotation.data,
+        start_seq: annotation.start_seq,
+        end_seq: annotation.end_seq,
+        message_ids,
+        created_at: annotation.created_at.and_utc().to_rfc3339(),
+    })
+}
+
+/// List a session's annotations, oldest first
+pub async fn list_annotations(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(session_id): Path<Uuid>,
+) -> Result<Json<Vec<AnnotationInfo>>, StatusCode> {
+    let (user_id, scopes) = super::messages::extract_caller(&app_state, &cookies, &headers, peer)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    verify_access(&mut conn, session_id, user_id, &scopes)?;
+
+    let annotations = message_annotations::table
+        .filter(message_annotations::session_id.eq(session_id))
+        .order(message_annotations::created_at.asc())
+        .select(MessageAnnotation::as_select())
+        .load::<MessageAnnotation>(&mut conn)
+        .and_then(|rows| {
+            rows.into_iter()
+                .map(|annotation| annotation_info(&mut conn, annotation))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| {
+            error!("Failed to load annotations: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(annotations))
+}
+
+/// Annotate a range of a session's messages, then show it to everyone
+/// viewing the session
+pub async fn create_annotation(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(session_id): Path<Uuid>,
+    Json(req): Json<CreateAnnotationRequest>,
+) -> Result<Json<AnnotationInfo>, (StatusCode, String)> {
+    let (user_id, scopes) = super::messages::extract_caller(&app_state, &cookies, &headers, peer)
+        .map_err(|e| (e, "Not signed in".to_string()))?;
+    req.validate()
+        .map_err(|reason| (StatusCode::BAD_REQUEST, reason.to_string()))?;
+
+    let mut conn = app_state.db_pool.get().map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database unavailable".to_string(),
+        )
+    })?;
+    verify_access(&mut conn, session_id, user_id, &scopes)
+        .map_err(|e| (e, "Session not found".to_string()))?;
+
+    let existing: i64 = message_annotations::table
+        .filter(message_annotations::session_id.eq(session_id))
+        .count()
+        .get_result(&mut conn)
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Database error".to_string(),
+            )
+        })?;
+    if existing >= MAX_ANNOTATIONS_PER_SESSION {
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            "This session has too many annotations".to_string(),
+        ));
+    }
+
+    let end_seq = req.end_seq();
+    let annotation: MessageAnnotation = diesel::insert_into(message_annotations::table)
+        .values(&NewMessageAnnotation {
+            session_id,
+            user_id,
+            source: req.source.trim().to_string(),
+            level: req.level.as_str().to_string(),
+            label: req.label.trim().to_string(),
+            url: req.url,
+            data: req.data,
+            start_seq: req.start_seq,
+            end_seq,
+        })
+        .returning(MessageAnnotation::as_returning())
+        .get_result(&mut conn)
+        .map_err(|e| {
+            error!("Failed to create annotation: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to save annotation".to_string(),
+            )
+        })?;
+
+    let annotation = annotation_info(&mut conn, annotation).map_err(|e| {
+        error!("Failed to load annotated messages: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database error".to_string(),
+        )
+    })?;
+
+    app_state.session_manager.broadcast_to_web_clients(
+        &session_id.to_string(),
+        ProxyMessage::MessageAnnotation {
+            session_id,
+            annotation: annotation.clone(),
+        },
+    );
+
+    Ok(Json(annotation))
+}
@@ -0,0 +1,244 @@
+//! Files Claude wrote during a session, and their export as a zip.
+//!
+//! The list comes from Write tool uses in the stored transcript. The files
+//! themselves only exist on the proxy's machine, so a download asks the
+//! connected proxy to build the archive and streams its chunks straight
+//! through to the browser. Viewers under a redaction policy can list the
+//! files but not download them.
+
+use crate::handlers::websocket::ArtifactReceiver;
+use crate::schema::{messages, users};
+use crate::AppState;
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use shared::ArtifactInfo;
+use std::sync::Arc;
+use std::time::Duration;
+use tower_cookies::Cookies;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+const SESSION_COOKIE_NAME: &str = "cc_session";
+
+/// How long to wait for the proxy to start sending the archive
+const FIRST_CHUNK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Extract user_id from signed session cookie
+fn extract_user_id(app_state: &AppState, cookies: &Cookies) -> Result<Uuid, StatusCode> {
+    // In dev mode, allow unauthenticated access with test user
+    if app_state.dev_mode {
+        let mut conn = app_state
+            .db_pool
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        return users::table
+            .filter(users::email.eq("testing@testing.local"))
+            .select(users::id)
+            .first::<Uuid>(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let cookie = cookies
+        .signed(&app_state.cookie_key)
+        .get(SESSION_COOKIE_NAME)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    cookie.value().parse().map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+/// Verify that a user can see a session (members, or any admin)
+fn verify_membership(
+    conn: &mut diesel::pg::PgConnection,
+    session_id: Uuid,
+    user_id: Uuid,
+) -> Result<String, StatusCode> {
+    super::helpers::session_role(conn, session_id, user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// One entry per path, reflecting its latest write, in first-write order
+fn artifacts_from_messages(rows: Vec<(String, NaiveDateTime)>) -> Vec<ArtifactInfo> {
+    let mut artifacts: Vec<ArtifactInfo> = Vec::new();
+    for (content, created_at) in rows {
+        let Ok(content) = serde_json::from_str::<serde_json::Value>(&content) else {
+            continue;
+        };
+        for (path, size_bytes) in shared::artifacts::write_tool_uses(&content) {
+            let written_at = created_at.and_utc().to_rfc3339();
+            match artifacts.iter_mut().find(|a| a.path == path) {
+                Some(existing) => {
+                    existing.size_bytes = size_bytes;
+                    existing.written_at = written_at;
+                }
+                None => artifacts.push(ArtifactInfo {
+                    path,
+                    size_bytes,
+                    written_at,
+                }),
+            }
+        }
+    }
+    artifacts
+}
+
+fn load_artifacts(
+    conn: &mut diesel::pg::PgConnection,
+    session_id: Uuid,
+) -> Result<Vec<ArtifactInfo>, StatusCode> {
+    let rows: Vec<(String, NaiveDateTime)> = messages::table
+        .filter(messages::session_id.eq(session_id))
+        .filter(messages::role.eq("assistant"))
+        .order(messages::created_at.asc())
+        .select((messages::content, messages::created_at))
+        .load(conn)
+        .map_err(|e| {
+            error!("Failed to load messages for artifacts: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok(artifacts_from_messages(rows))
+}
+
+/// List files written by Write tool uses in a session
+pub async fn list_artifacts(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(session_id): Path<Uuid>,
+) -> Result<Json<Vec<ArtifactInfo>>, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    verify_membership(&mut conn, session_id, current_user_id)?;
+    load_artifacts(&mut conn, session_id).map(Json)
+}
+
+/// Turn the archive chunks into a response body; an error after the first
+/// chunk aborts the download
+fn archive_body(first: Vec<u8>, rx: ArtifactReceiver) -> Body {
+    let rest = futures_util::stream::unfold(rx, |mut rx| async move {
+        let chunk = rx.recv().await?;
+        Some((chunk.map_err(std::io::Error::other), rx))
+    });
+    Body::from_stream(futures_util::StreamExt::chain(
+        futures_util::stream::once(async move { Ok(first) }),
+        rest,
+    ))
+}
+
+/// Download the session's artifacts as a zip built by its proxy
+pub async fn download_artifacts(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(session_id): Path<Uuid>,
+) -> Result<Response, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let role = verify_membership(&mut conn, session_id, current_user_id)?;
+    // The archive holds the files as written, which can't be redacted
+    if super::redaction::redactor_for(&mut conn, session_id, &role).is_some() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let paths: Vec<String> = load_artifacts(&mut conn, session_id)?
+        .into_iter()
+        .map(|a| a.path)
+        .collect();
+    drop(conn);
+
+    if paths.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let session_manager = &app_state.session_manager;
+    let (request_id, mut rx) = session_manager
+        .request_artifacts(session_id, paths)
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    info!(
+        "Requested artifact archive {} for session {}",
+        request_id, session_id
+    );
+
+    // Wait for the first chunk so a failure can still get a proper status
+    let first = match tokio::time::timeout(FIRST_CHUNK_TIMEOUT, rx.recv()).await {
+        Ok(Some(Ok(chunk))) => chunk,
+        Ok(Some(Err(e))) => {
+            warn!("Proxy failed to build artifact archive: {}", e);
+            return Err(StatusCode::BAD_GATEWAY);
+        }
+        Ok(None) => return Err(StatusCode::BAD_GATEWAY),
+        Err(_) => {
+            session_manager.cancel_artifacts(request_id);
+            warn!("Timed out waiting for artifact archive {}", request_id);
+            return Err(StatusCode::GATEWAY_TIMEOUT);
+        }
+    };
+
+    let short_id = &session_id.to_string()[..8];
+    let disposition = format!(
+        "attachment; filename=\"session-{}-artifacts.zip\"",
+        short_id
+    );
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        archive_body(first, rx),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn write_message(path: &str, content: &str) -> String {
+        json!({
+            "type": "assistant",
+            "message": {"content": [
+                {"type": "tool_use", "id": "t", "name": "Write",
+                 "input": {"file_path": path, "content": content}}
+            ]}
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_artifacts_from_messages_keeps_latest_write() {
+        let at = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+        let rows = vec![
+            (write_message("/w/a.rs", "v1"), at("2026-01-01 10:00:00")),
+            (write_message("/w/b.rs", "b"), at("2026-01-01 10:01:00")),
+            ("not json".to_string(), at("2026-01-01 10:02:00")),
+            (
+                write_message("/w/a.rs", "version 2"),
+                at("2026-01-01 10:03:00"),
+            ),
+        ];
+
+        let artifacts = artifacts_from_messages(rows);
+        assert_eq!(artifacts.len(), 2);
+        assert_eq!(artifacts[0].path, "/w/a.rs");
+        assert_eq!(artifacts[0].size_bytes, 9);
+        assert_eq!(artifacts[0].written_at, "2026-01-01T10:03:00+00:00");
+        assert_eq!(artifacts[1].path, "/w/b.rs");
+    }
+}
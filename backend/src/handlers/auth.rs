@@ -1,40 +1,96 @@
+//! Web sign-in
+//!
+//! `GET /api/auth/{provider}` starts an authorization-code flow with PKCE
+//! against one of the configured [`AuthProviders`](crate::auth_providers).
+//! The CSRF state, PKCE verifier, and any device code being approved travel
+//! in a short-lived signed cookie, and `GET /api/auth/{provider}/callback`
+//! checks them before exchanging the code. A successful sign-in sets the
+//! `cc_session` cookie every other handler reads.
+
 use axum::{
-    extract::{Query, State},
+    extract::{Path, Query, State},
     http::StatusCode,
-    response::{IntoResponse, Redirect},
+    response::{IntoResponse, Redirect, Response},
     Json,
 };
 use diesel::prelude::*;
-use oauth2::{AuthorizationCode, CsrfToken, Scope, TokenResponse};
+use diesel::sql_types::Text;
+use oauth2::{
+    AuthorizationCode, CsrfToken, PkceCodeChallenge, PkceCodeVerifier, Scope, TokenResponse,
+};
 use serde::{Deserialize, Serialize};
+use shared::{AuthProviderInfo, UserRole};
 use std::sync::Arc;
 use tower_cookies::{cookie::SameSite, Cookie, Cookies};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
 use crate::{
-    models::{NewUser, User},
+    auth_providers::{AuthProvider, Identity},
+    models::{NewUser, NewUserIdentity, User},
+    schema::{user_identities, users},
     AppState,
 };
 
 const SESSION_COOKIE_NAME: &str = "cc_session";
 
-/// Regular web login - redirects to Google OAuth
-pub async fn login(State(app_state): State<Arc<AppState>>) -> impl IntoResponse {
-    let client = match &app_state.oauth_basic_client {
-        Some(c) => c,
-        None => return Redirect::temporary("/api/auth/dev-login").into_response(),
-    };
+/// Carries [`OAuthState`] from login to callback
+const OAUTH_STATE_COOKIE_NAME: &str = "cc_oauth_state";
 
-    let auth_request = client
-        .authorize_url(CsrfToken::new_random)
-        .add_scope(Scope::new("openid".to_string()))
-        .add_scope(Scope::new("email".to_string()))
-        .add_scope(Scope::new("profile".to_string()));
+/// How long a sign-in can sit at the provider before the callback refuses it
+const OAUTH_STATE_MAX_AGE_MINUTES: i64 = 10;
 
-    let (auth_url, _csrf_token) = auth_request.url();
+diesel::define_sql_function!(fn lower(x: Text) -> Text);
 
-    Redirect::temporary(auth_url.as_str()).into_response()
+/// What the callback needs to finish a sign-in it started
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct OAuthState {
+    provider: String,
+    csrf: String,
+    pkce_verifier: String,
+    /// Set when signing in to approve a CLI device code
+    device_user_code: Option<String>,
+}
+
+pub(crate) fn session_cookie(user_id: Uuid, secure: bool) -> Cookie<'static> {
+    let mut cookie = Cookie::new(SESSION_COOKIE_NAME, user_id.to_string());
+    cookie.set_path("/");
+    cookie.set_http_only(true);
+    cookie.set_secure(secure); // Don't require HTTPS in dev mode
+    cookie.set_same_site(SameSite::Lax);
+    cookie
+}
+
+fn oauth_state_cookie(value: String, secure: bool) -> Cookie<'static> {
+    let mut cookie = Cookie::new(OAUTH_STATE_COOKIE_NAME, value);
+    cookie.set_path("/api/auth");
+    cookie.set_http_only(true);
+    cookie.set_secure(secure);
+    // Lax still sends it on the provider's top-level redirect back to us
+    cookie.set_same_site(SameSite::Lax);
+    cookie
+}
+
+/// GET /api/auth/providers - sign-in options for the splash page; empty in
+/// dev mode, where `/api/auth/dev-login` signs in directly
+pub async fn providers(State(app_state): State<Arc<AppState>>) -> Json<Vec<AuthProviderInfo>> {
+    Json(app_state.auth_providers.info())
+}
+
+/// GET /api/auth/:provider - regular web login
+pub async fn login(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(provider): Path<String>,
+) -> Result<Response, StatusCode> {
+    if app_state.auth_providers.is_empty() {
+        return Ok(Redirect::temporary("/api/auth/dev-login").into_response());
+    }
+    let provider = app_state
+        .auth_providers
+        .get(&provider)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(start_login(&app_state, &cookies, provider, None))
 }
 
 /// Device flow login - separate endpoint that stores device_user_code in state
@@ -42,6 +98,9 @@ pub async fn login(State(app_state): State<Arc<AppState>>) -> impl IntoResponse
 #[derive(Debug, Deserialize)]
 pub struct DeviceLoginQuery {
     pub device_user_code: String,
+    /// Which provider to sign in with; without one, a deployment with several
+    /// providers sends the user to the splash page to pick
+    pub provider: Option<String>,
 }
 
 pub async fn device_login(
@@ -50,16 +109,14 @@ pub async fn device_login(
     Query(query): Query<DeviceLoginQuery>,
 ) -> Result<impl IntoResponse, StatusCode> {
     // In dev mode, auto-login and redirect to device approval page
-    if app_state.oauth_basic_client.is_none() {
-        use crate::schema::users::dsl::*;
-
+    if app_state.auth_providers.is_empty() {
         let mut conn = app_state
             .db_pool
             .get()
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-        let user = users
-            .filter(email.eq("testing@testing.local"))
+        let user = users::table
+            .filter(users::email.eq("testing@testing.local"))
             .first::<User>(&mut conn)
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
@@ -70,12 +127,9 @@ pub async fn device_login(
 
         info!("Dev mode: auto-logged in for device flow");
 
-        let mut cookie = Cookie::new(SESSION_COOKIE_NAME, user.id.to_string());
-        cookie.set_path("/");
-        cookie.set_http_only(true);
-        cookie.set_secure(false);
-        cookie.set_same_site(SameSite::Lax);
-        cookies.signed(&app_state.cookie_key).add(cookie);
+        cookies
+            .signed(&app_state.cookie_key)
+            .add(session_cookie(user.id, false));
 
         return Ok(Redirect::temporary(&format!(
             "/api/auth/device?user_code={}",
@@ -84,121 +138,149 @@ pub async fn device_login(
         .into_response());
     }
 
-    let client = app_state.oauth_basic_client.as_ref().unwrap();
-
-    // Use a prefixed state to identify this as a device flow callback
-    let state_value = format!("device:{}", query.device_user_code);
+    let provider = match &query.provider {
+        Some(id) => app_state.auth_providers.get(id),
+        None => app_state.auth_providers.single(),
+    };
+    let Some(provider) = provider else {
+        return Ok(
+            Redirect::temporary(&format!("/?device_user_code={}", query.device_user_code))
+                .into_response(),
+        );
+    };
 
-    let auth_request = client
-        .authorize_url(|| CsrfToken::new(state_value))
-        .add_scope(Scope::new("openid".to_string()))
-        .add_scope(Scope::new("email".to_string()))
-        .add_scope(Scope::new("profile".to_string()));
+    Ok(start_login(
+        &app_state,
+        &cookies,
+        provider,
+        Some(query.device_user_code),
+    ))
+}
 
-    let (auth_url, _csrf_token) = auth_request.url();
+/// Remember the flow in a signed cookie and send the browser to the provider
+fn start_login(
+    app_state: &AppState,
+    cookies: &Cookies,
+    provider: &AuthProvider,
+    device_user_code: Option<String>,
+) -> Response {
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+    let (auth_url, csrf) = provider
+        .client
+        .authorize_url(CsrfToken::new_random)
+        .add_scopes(provider.scopes.iter().map(|s| Scope::new(s.to_string())))
+        .set_pkce_challenge(pkce_challenge)
+        .url();
+
+    let state = OAuthState {
+        provider: provider.id.to_string(),
+        csrf: csrf.secret().clone(),
+        pkce_verifier: pkce_verifier.secret().clone(),
+        device_user_code,
+    };
+    let mut cookie = oauth_state_cookie(
+        serde_json::to_string(&state).unwrap_or_default(),
+        !app_state.dev_mode,
+    );
+    cookie.set_max_age(tower_cookies::cookie::time::Duration::minutes(
+        OAUTH_STATE_MAX_AGE_MINUTES,
+    ));
+    cookies.signed(&app_state.cookie_key).add(cookie);
 
-    Ok(Redirect::temporary(auth_url.as_str()).into_response())
+    Redirect::temporary(auth_url.as_str()).into_response()
 }
 
 #[derive(Debug, Deserialize)]
 pub struct AuthCallbackQuery {
-    code: String,
+    code: Option<String>,
     state: Option<String>,
+    /// Set instead of `code` when the user declines or the provider refuses
+    error: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
-struct GoogleUserInfo {
-    sub: String,
-    email: String,
-    name: Option<String>,
-    picture: Option<String>,
-}
-
+/// GET /api/auth/:provider/callback
 pub async fn callback(
     State(app_state): State<Arc<AppState>>,
     cookies: Cookies,
+    Path(provider_id): Path<String>,
     Query(query): Query<AuthCallbackQuery>,
-) -> Result<impl IntoResponse, StatusCode> {
-    let client = app_state
-        .oauth_basic_client
-        .as_ref()
-        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+) -> Result<Response, StatusCode> {
+    let provider = app_state
+        .auth_providers
+        .get(&provider_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    // The state cookie is single-use
+    let signed = cookies.signed(&app_state.cookie_key);
+    let saved = signed
+        .get(OAUTH_STATE_COOKIE_NAME)
+        .and_then(|c| serde_json::from_str::<OAuthState>(c.value()).ok());
+    signed.remove(oauth_state_cookie(String::new(), !app_state.dev_mode));
+
+    if let Some(e) = query.error {
+        info!("Sign-in with {} was not completed: {}", provider.id, e);
+        return Ok(Redirect::temporary("/").into_response());
+    }
+    // Expired or from another browser; starting over is all the user can do
+    let Some(saved) = saved else {
+        info!("Sign-in callback without a pending sign-in; restarting");
+        return Ok(Redirect::temporary("/").into_response());
+    };
+    if saved.provider != provider.id || query.state.as_deref() != Some(saved.csrf.as_str()) {
+        warn!("Sign-in callback for {} with mismatched state", provider.id);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let code = query.code.ok_or(StatusCode::BAD_REQUEST)?;
 
     // Exchange code for token
-    let token: oauth2::StandardTokenResponse<
-        oauth2::EmptyExtraTokenFields,
-        oauth2::basic::BasicTokenType,
-    > = client
-        .exchange_code(AuthorizationCode::new(query.code))
+    let token = provider
+        .client
+        .exchange_code(AuthorizationCode::new(code))
+        .set_pkce_verifier(PkceCodeVerifier::new(saved.pkce_verifier))
         .request_async(oauth2::reqwest::async_http_client)
         .await
         .map_err(|e| {
-            error!("Failed to exchange code: {}", e);
+            error!("Failed to exchange code with {}: {}", provider.id, e);
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
-    // Fetch user info from Google
-    let client = reqwest::Client::new();
-    let user_info: GoogleUserInfo = client
-        .get("https://www.googleapis.com/oauth2/v3/userinfo")
-        .bearer_auth(token.access_token().secret())
-        .send()
-        .await
-        .map_err(|e| {
-            error!("Failed to fetch user info: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .json()
+    let identity = provider
+        .identity(&reqwest::Client::new(), token.access_token().secret())
         .await
         .map_err(|e| {
-            error!("Failed to parse user info: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+            warn!("Failed to identify {} user: {:#}", provider.id, e);
+            StatusCode::FORBIDDEN
         })?;
 
-    info!("User authenticated: {}", user_info.email);
+    info!(
+        "User authenticated with {}: {}",
+        provider.id, identity.email
+    );
 
     // Check email access control
-    if let Err(redirect) = check_email_allowed(&app_state, &user_info.email) {
-        return Ok(redirect);
+    if let Err(redirect) = check_email_allowed(&app_state, &identity.email) {
+        return Ok(redirect.into_response());
     }
 
-    // Save or update user in database
     let mut conn = app_state.db_pool.get().map_err(|e| {
         error!("Failed to get db connection: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    use crate::schema::users::dsl::*;
-
-    let user = users
-        .filter(google_id.eq(&user_info.sub))
-        .first::<User>(&mut conn)
-        .optional()
+    let user = conn
+        .transaction(|conn| resolve_user(conn, provider.id, &identity))
         .map_err(|e| {
-            error!("Database error: {}", e);
+            error!("Failed to resolve user for {}: {}", identity.email, e);
             StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or_else(|| {
+            warn!(
+                "Refusing {} sign-in as existing user {}: the provider didn't verify the email",
+                provider.id, identity.email
+            );
+            StatusCode::FORBIDDEN
         })?;
 
-    let user = match user {
-        Some(user) => user,
-        None => {
-            let new_user = NewUser {
-                google_id: user_info.sub,
-                email: user_info.email,
-                name: user_info.name,
-                avatar_url: user_info.picture,
-            };
-
-            diesel::insert_into(users)
-                .values(&new_user)
-                .get_result::<User>(&mut conn)
-                .map_err(|e| {
-                    error!("Failed to create user: {}", e);
-                    StatusCode::INTERNAL_SERVER_ERROR
-                })?
-        }
-    };
-
     // Check if user is banned
     if user.disabled {
         let reason = user.ban_reason.as_deref().unwrap_or("No reason provided");
@@ -216,44 +298,91 @@ pub async fn callback(
             })
             .collect();
         info!("Banned user {} attempted login", user.email);
+        return Ok(
+            Redirect::temporary(&format!("/banned?reason={}", encoded_reason)).into_response(),
+        );
+    }
+
+    cookies
+        .signed(&app_state.cookie_key)
+        .add(session_cookie(user.id, !app_state.dev_mode));
+
+    if let Some(device_user_code) = saved.device_user_code {
+        // Redirect back to device verify page to show approval UI
+        info!(
+            "OAuth complete for device flow, redirecting to approval page for user: {}",
+            user.email
+        );
         return Ok(Redirect::temporary(&format!(
-            "/banned?reason={}",
-            encoded_reason
-        )));
+            "/api/auth/device?user_code={}",
+            device_user_code
+        ))
+        .into_response());
+    }
+
+    Ok(Redirect::temporary("/dashboard").into_response())
+}
+
+/// The user an identity signs in as: the one it's linked to, else the user
+/// with the same email (linking this identity), else a new user. `None` if
+/// the email belongs to a user but isn't verified, so can't be linked.
+fn resolve_user(
+    conn: &mut PgConnection,
+    provider: &str,
+    identity: &Identity,
+) -> QueryResult<Option<User>> {
+    let linked = user_identities::table
+        .inner_join(users::table)
+        .filter(user_identities::provider.eq(provider))
+        .filter(user_identities::subject.eq(&identity.subject))
+        .select(User::as_select())
+        .first(conn)
+        .optional()?;
+    if let Some(user) = linked {
+        return Ok(Some(user));
     }
 
-    // Check if this is part of a device flow (state starts with "device:")
-    if let Some(ref state) = query.state {
-        if let Some(device_user_code) = state.strip_prefix("device:") {
-            // Set session cookie first so user is logged in
-            let mut cookie = Cookie::new(SESSION_COOKIE_NAME, user.id.to_string());
-            cookie.set_path("/");
-            cookie.set_http_only(true);
-            cookie.set_secure(!app_state.dev_mode);
-            cookie.set_same_site(SameSite::Lax);
-            cookies.signed(&app_state.cookie_key).add(cookie);
-
-            // Redirect back to device verify page to show approval UI
+    let existing = users::table
+        .filter(lower(users::email).eq(identity.email.to_lowercase()))
+        .order(users::created_at.asc())
+        .select(User::as_select())
+        .first(conn)
+        .optional()?;
+    let user = match existing {
+        Some(_) if !identity.email_verified => return Ok(None),
+        Some(user) => {
             info!(
-                "OAuth complete for device flow, redirecting to approval page for user: {}",
-                user.email
+                "Linking {} sign-in to existing user {}",
+                provider, user.email
             );
-            return Ok(Redirect::temporary(&format!(
-                "/api/auth/device?user_code={}",
-                device_user_code
-            )));
+            user
         }
-    }
-
-    // Set session cookie with user ID
-    let mut cookie = Cookie::new(SESSION_COOKIE_NAME, user.id.to_string());
-    cookie.set_path("/");
-    cookie.set_http_only(true);
-    cookie.set_secure(!app_state.dev_mode); // Don't require HTTPS in dev mode
-    cookie.set_same_site(SameSite::Lax);
-    cookies.signed(&app_state.cookie_key).add(cookie);
+        None => {
+            // google_id is unique and required; other providers namespace it
+            let google_id = if provider == "google" {
+                identity.subject.clone()
+            } else {
+                format!("{}:{}", provider, identity.subject)
+            };
+            diesel::insert_into(users::table)
+                .values(&NewUser {
+                    google_id,
+                    email: identity.email.clone(),
+                    name: identity.name.clone(),
+                    avatar_url: identity.avatar_url.clone(),
+                })
+                .get_result::<User>(conn)?
+        }
+    };
 
-    Ok(Redirect::temporary("/dashboard"))
+    diesel::insert_into(user_identities::table)
+        .values(&NewUserIdentity {
+            provider,
+            subject: &identity.subject,
+            user_id: user.id,
+        })
+        .execute(conn)?;
+    Ok(Some(user))
 }
 
 #[derive(Debug, Serialize)]
@@ -262,8 +391,10 @@ pub struct UserResponse {
     pub email: String,
     pub name: Option<String>,
     pub avatar_url: Option<String>,
+    pub role: UserRole,
     pub is_admin: bool,
     pub voice_enabled: bool,
+    pub voice_language: String,
 }
 
 pub async fn me(
@@ -293,13 +424,16 @@ pub async fn me(
         .first::<User>(&mut conn)
         .map_err(|_| StatusCode::NOT_FOUND)?;
 
+    let user_role = user.role();
     Ok(Json(UserResponse {
         id: user.id,
         email: user.email,
         name: user.name,
         avatar_url: user.avatar_url,
-        is_admin: user.is_admin,
+        role: user_role,
+        is_admin: user_role == UserRole::Admin,
         voice_enabled: user.voice_enabled,
+        voice_language: user.voice_language,
     }))
 }
 
@@ -370,19 +504,20 @@ pub async fn dev_login(
     Ok(Redirect::temporary("/dashboard"))
 }
 
-/// Check if an email is allowed based on ALLOWED_EMAIL_DOMAIN and ALLOWED_EMAILS
+/// Check if an email is allowed by the runtime config's domain and email allowlists
 ///
 /// Returns Ok(()) if allowed, or Err(Redirect) to the access denied page
 fn check_email_allowed(app_state: &AppState, email: &str) -> Result<(), Redirect> {
     let email_lower = email.to_lowercase();
+    let config = app_state.runtime_config.current();
 
     // If no restrictions are set, allow all
-    if app_state.allowed_email_domain.is_none() && app_state.allowed_emails.is_none() {
+    if config.allowed_email_domain.is_none() && config.allowed_emails.is_none() {
         return Ok(());
     }
 
     // Check domain allowlist
-    if let Some(ref domain) = app_state.allowed_email_domain {
+    if let Some(ref domain) = config.allowed_email_domain {
         let domain_lower = domain.to_lowercase();
         if email_lower.ends_with(&format!("@{}", domain_lower)) {
             return Ok(());
@@ -390,7 +525,7 @@ fn check_email_allowed(app_state: &AppState, email: &str) -> Result<(), Redirect
     }
 
     // Check specific email allowlist
-    if let Some(ref emails) = app_state.allowed_emails {
+    if let Some(ref emails) = config.allowed_emails {
         if emails.contains(&email_lower) {
             return Ok(());
         }
@@ -0,0 +1,290 @@
+//! Budget limits: admin configuration and per-session evaluation.
+//!
+//! Usage is taken from `usage_daily`, which survives proxy restarts, rather than
+//! the running totals on `sessions`. The websocket handler checks a session's
+//! status before forwarding input and after each result message.
+
+use crate::handlers::admin::require_admin;
+use crate::models::{BudgetLimitsRow, BudgetLimitsUpdate};
+use crate::schema::{budget_limits, sessions, usage_daily, users};
+use crate::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use diesel::prelude::*;
+use shared::{BudgetCap, BudgetLimits, BudgetStatus, BudgetUsage};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::{error, info};
+use uuid::Uuid;
+
+const SESSION_COOKIE_NAME: &str = "cc_session";
+
+/// Extract user_id from signed session cookie
+fn extract_user_id(app_state: &AppState, cookies: &Cookies) -> Result<Uuid, StatusCode> {
+    // In dev mode, allow unauthenticated access with test user
+    if app_state.dev_mode {
+        let mut conn = app_state
+            .db_pool
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        return users::table
+            .filter(users::email.eq("testing@testing.local"))
+            .select(users::id)
+            .first::<Uuid>(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let cookie = cookies
+        .signed(&app_state.cookie_key)
+        .get(SESSION_COOKIE_NAME)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    cookie.value().parse().map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+fn to_limits(row: BudgetLimitsRow) -> BudgetLimits {
+    BudgetLimits {
+        session_cost_usd: BudgetCap {
+            soft: row.session_cost_soft_usd,
+            hard: row.session_cost_hard_usd,
+        },
+        daily_cost_usd: BudgetCap {
+            soft: row.daily_cost_soft_usd,
+            hard: row.daily_cost_hard_usd,
+        },
+        session_tokens: BudgetCap {
+            soft: row.session_tokens_soft.map(|t| t as f64),
+            hard: row.session_tokens_hard.map(|t| t as f64),
+        },
+    }
+}
+
+/// Caps must be non-negative, and a soft cap can't be above its hard cap
+fn validate_limits(limits: &BudgetLimits) -> bool {
+    [
+        limits.session_cost_usd,
+        limits.daily_cost_usd,
+        limits.session_tokens,
+    ]
+    .iter()
+    .all(|cap| {
+        let valid = |v: Option<f64>| v.is_none_or(|v| v.is_finite() && v >= 0.0);
+        let ordered = match (cap.soft, cap.hard) {
+            (Some(soft), Some(hard)) => soft <= hard,
+            _ => true,
+        };
+        valid(cap.soft) && valid(cap.hard) && ordered
+    })
+}
+
+pub fn load_limits(conn: &mut diesel::pg::PgConnection) -> QueryResult<BudgetLimits> {
+    Ok(budget_limits::table
+        .select(BudgetLimitsRow::as_select())
+        .first(conn)
+        .optional()?
+        .map(to_limits)
+        .unwrap_or_default())
+}
+
+/// Session and daily usage for the session's owner
+pub fn session_usage(
+    conn: &mut diesel::pg::PgConnection,
+    session_id: Uuid,
+) -> QueryResult<BudgetUsage> {
+    let user_id: Uuid = sessions::table
+        .find(session_id)
+        .select(sessions::user_id)
+        .first(conn)?;
+
+    let rows: Vec<(f64, i64, i64, i64, i64)> = usage_daily::table
+        .filter(usage_daily::session_id.eq(session_id))
+        .select((
+            usage_daily::cost_usd,
+            usage_daily::input_tokens,
+            usage_daily::output_tokens,
+            usage_daily::cache_creation_tokens,
+            usage_daily::cache_read_tokens,
+        ))
+        .load(conn)?;
+
+    let today = chrono::Utc::now().date_naive();
+    let daily_cost_usd = usage_daily::table
+        .filter(usage_daily::user_id.eq(user_id))
+        .filter(usage_daily::day.eq(today))
+        .select(usage_daily::cost_usd)
+        .load::<f64>(conn)?
+        .into_iter()
+        .sum();
+
+    Ok(BudgetUsage {
+        session_cost_usd: rows.iter().map(|r| r.0).sum(),
+        daily_cost_usd,
+        session_tokens: rows.iter().map(|r| r.1 + r.2 + r.3 + r.4).sum(),
+    })
+}
+
+/// Current budget status of a session, or None if it couldn't be determined
+pub fn check_session(db_pool: &crate::db::DbPool, session_id: Uuid) -> Option<BudgetStatus> {
+    let mut conn = db_pool
+        .get()
+        .map_err(|e| error!("Failed to get DB connection for budget check: {}", e))
+        .ok()?;
+
+    let limits = load_limits(&mut conn)
+        .map_err(|e| error!("Failed to load budget limits: {}", e))
+        .ok()?;
+    if limits == BudgetLimits::default() {
+        return Some(BudgetStatus::default());
+    }
+
+    session_usage(&mut conn, session_id)
+        .map(|usage| limits.evaluate(&usage))
+        .map_err(|e| error!("Failed to load usage for budget check: {}", e))
+        .ok()
+}
+
+/// Get the configured budget caps (admin only)
+pub async fn get_limits(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<BudgetLimits>, StatusCode> {
+    require_admin(&app_state, &cookies).await?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    load_limits(&mut conn).map(Json).map_err(|e| {
+        error!("Failed to load budget limits: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Replace the configured budget caps (admin only)
+pub async fn update_limits(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(limits): Json<BudgetLimits>,
+) -> Result<Json<BudgetLimits>, StatusCode> {
+    let admin = require_admin(&app_state, &cookies).await?;
+
+    if !validate_limits(&limits) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let update = BudgetLimitsUpdate {
+        session_cost_soft_usd: limits.session_cost_usd.soft,
+        session_cost_hard_usd: limits.session_cost_usd.hard,
+        daily_cost_soft_usd: limits.daily_cost_usd.soft,
+        daily_cost_hard_usd: limits.daily_cost_usd.hard,
+        session_tokens_soft: limits.session_tokens.soft.map(|t| t.round() as i64),
+        session_tokens_hard: limits.session_tokens.hard.map(|t| t.round() as i64),
+        updated_at: chrono::Utc::now().naive_utc(),
+        updated_by: Some(admin.id),
+    };
+
+    let row = diesel::insert_into(budget_limits::table)
+        .values(budget_limits::id.eq(1))
+        .on_conflict(budget_limits::id)
+        .do_update()
+        .set(&update)
+        .returning(BudgetLimitsRow::as_returning())
+        .get_result(&mut conn)
+        .map_err(|e| {
+            error!("Failed to update budget limits: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    info!("Admin {} updated budget limits: {:?}", admin.email, limits);
+    Ok(Json(to_limits(row)))
+}
+
+/// Budget status of a session the current user belongs to
+pub async fn get_session_budget(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(session_id): Path<Uuid>,
+) -> Result<Json<BudgetStatus>, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    super::helpers::session_role(&mut conn, session_id, current_user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    drop(conn);
+
+    check_session(&app_state.db_pool, session_id)
+        .map(Json)
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_limits() {
+        assert!(validate_limits(&BudgetLimits::default()));
+
+        let mut limits = BudgetLimits {
+            session_cost_usd: BudgetCap {
+                soft: Some(1.0),
+                hard: Some(5.0),
+            },
+            session_tokens: BudgetCap {
+                soft: None,
+                hard: Some(1_000_000.0),
+            },
+            ..Default::default()
+        };
+        assert!(validate_limits(&limits));
+
+        limits.daily_cost_usd = BudgetCap {
+            soft: Some(10.0),
+            hard: Some(5.0),
+        };
+        assert!(!validate_limits(&limits));
+
+        limits.daily_cost_usd = BudgetCap {
+            soft: Some(-1.0),
+            hard: None,
+        };
+        assert!(!validate_limits(&limits));
+
+        limits.daily_cost_usd = BudgetCap {
+            soft: None,
+            hard: Some(f64::NAN),
+        };
+        assert!(!validate_limits(&limits));
+    }
+
+    #[test]
+    fn test_to_limits() {
+        let limits = to_limits(BudgetLimitsRow {
+            session_cost_soft_usd: Some(1.0),
+            session_cost_hard_usd: None,
+            daily_cost_soft_usd: None,
+            daily_cost_hard_usd: Some(20.0),
+            session_tokens_soft: None,
+            session_tokens_hard: Some(500_000),
+        });
+        assert_eq!(limits.session_cost_usd.soft, Some(1.0));
+        assert_eq!(limits.daily_cost_usd.hard, Some(20.0));
+        assert_eq!(limits.session_tokens.hard, Some(500_000.0));
+        assert_eq!(limits.session_tokens.soft, None);
+    }
+}
@@ -0,0 +1,50 @@
+//! GitHub webhook deliveries carrying CI results
+
+use crate::ci;
+use crate::AppState;
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+};
+use std::sync::Arc;
+use tracing::{debug, error};
+
+/// Record a `workflow_run` or `check_run` event; other events (including
+/// GitHub's `ping`) are acknowledged and ignored
+pub async fn github_webhook(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    let Some(webhook) = &app_state.github_webhook else {
+        return StatusCode::NOT_FOUND;
+    };
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    if !webhook.verify(header(ci::SIGNATURE_HEADER), &body) {
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    let Ok(payload) = serde_json::from_slice::<serde_json::Value>(&body) else {
+        return StatusCode::BAD_REQUEST;
+    };
+    let Some(update) = ci::parse_event(header(ci::EVENT_HEADER).unwrap_or_default(), &payload)
+    else {
+        return StatusCode::NO_CONTENT;
+    };
+    debug!(
+        "CI {} on {}@{}: {:?}",
+        update.name, update.repository, update.branch, update.state
+    );
+
+    let Ok(mut conn) = app_state.db_pool.get() else {
+        return StatusCode::SERVICE_UNAVAILABLE;
+    };
+    match ci::record(&mut conn, update) {
+        Ok(()) => StatusCode::NO_CONTENT,
+        Err(e) => {
+            error!("Failed to record CI run: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
@@ -1,15 +1,88 @@
-//! App configuration endpoint
+//! App configuration endpoints
 //!
-//! Returns public application configuration to the frontend.
+//! Returns public application configuration to the frontend, and lets admins
+//! override runtime settings without restarting the server.
 
-use crate::AppState;
-use axum::{extract::State, Json};
-use shared::AppConfig;
+use crate::handlers::admin::require_admin;
+use crate::{runtime_config, AppState};
+use axum::{extract::State, http::StatusCode, Json};
+use shared::{AppConfig, RuntimeConfigInfo, RuntimeConfigOverrides};
 use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::{error, info, warn};
 
 /// GET /api/config - Returns application configuration
 pub async fn get_config(State(app_state): State<Arc<AppState>>) -> Json<AppConfig> {
+    let config = app_state.runtime_config.current();
     Json(AppConfig {
-        app_title: app_state.app_title.clone(),
+        app_title: config.app_title.clone(),
+        issue_report_url: config.issue_report_url.clone(),
+        semantic_search: app_state.embeddings.is_some(),
+        push_public_key: app_state
+            .session_manager
+            .push
+            .as_ref()
+            .map(|push| push.public_key().to_string()),
+        voice_auto_language: app_state
+            .speech
+            .as_ref()
+            .is_some_and(|speech| speech.detects_language()),
+        render_limits: config.render_limits,
     })
 }
+
+fn config_info(app_state: &AppState, overrides: RuntimeConfigOverrides) -> RuntimeConfigInfo {
+    RuntimeConfigInfo {
+        defaults: app_state.runtime_config.defaults().clone(),
+        overrides,
+        effective: (*app_state.runtime_config.current()).clone(),
+    }
+}
+
+/// GET /api/admin/config - Runtime settings with their defaults and overrides
+pub async fn get_runtime_config(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<RuntimeConfigInfo>, StatusCode> {
+    require_admin(&app_state, &cookies).await?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let overrides = runtime_config::load_overrides(&mut conn).map_err(|e| {
+        error!("Failed to load runtime config overrides: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(config_info(&app_state, overrides)))
+}
+
+/// PUT /api/admin/config - Replace the runtime overrides and apply them immediately
+pub async fn update_runtime_config(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(overrides): Json<RuntimeConfigOverrides>,
+) -> Result<Json<RuntimeConfigInfo>, StatusCode> {
+    let admin = require_admin(&app_state, &cookies).await?;
+
+    if let Err(reason) = overrides.validate() {
+        warn!("Rejected runtime config from {}: {}", admin.email, reason);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    runtime_config::save_overrides(&mut conn, &overrides, admin.id).map_err(|e| {
+        error!("Failed to save runtime config overrides: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    app_state.runtime_config.apply(&overrides);
+    info!("Admin {} updated runtime config overrides", admin.email);
+
+    Ok(Json(config_info(&app_state, overrides)))
+}
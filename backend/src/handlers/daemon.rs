@@ -0,0 +1,156 @@
+//! Proxy daemons: many sessions over one socket
+//!
+//! A daemon's socket at `/ws/daemon` carries [`DaemonFrame`]s. The first
+//! frame for a session opens a connection for it that
+//! `websocket::serve_session` serves exactly as it would a lone proxy's
+//! socket. Frames are only routed here; closing one session's connection,
+//! from either end, leaves the others alone.
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo, State,
+    },
+    http::HeaderMap,
+    response::Response,
+};
+use futures_util::{SinkExt, StreamExt};
+use shared::DaemonFrame;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{info, warn, Instrument};
+use uuid::Uuid;
+
+use crate::AppState;
+
+pub async fn handle_daemon_websocket(
+    ws: WebSocketUpgrade,
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    let host = super::proxy_tokens::client_host(&headers, peer);
+    let span = tracing::info_span!("daemon_connection");
+    ws.on_upgrade(|socket| handle_daemon_socket(socket, app_state, host).instrument(span))
+}
+
+/// Where a session's frames from the daemon go
+struct Route {
+    /// Tells this connection apart from earlier ones for the same session
+    generation: u64,
+    inbound: mpsc::UnboundedSender<Message>,
+}
+
+async fn handle_daemon_socket(socket: WebSocket, app_state: Arc<AppState>, host: String) {
+    let (mut sender, mut receiver) = socket.split();
+    // Frames from every session, to the daemon
+    let (out_tx, mut out_rx) = mpsc::unbounded_channel::<DaemonFrame>();
+    // Session connections that have ended, by session and generation
+    let (ended_tx, mut ended_rx) = mpsc::unbounded_channel::<(Uuid, u64)>();
+
+    let send_task = tokio::spawn(async move {
+        while let Some(frame) = out_rx.recv().await {
+            if let Ok(json) = serde_json::to_string(&frame) {
+                if sender.send(Message::Text(json)).await.is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    let mut routes: HashMap<Uuid, Route> = HashMap::new();
+    let mut next_generation = 0;
+    info!("Proxy daemon connected from {}", host);
+    loop {
+        tokio::select! {
+            msg = receiver.next() => {
+                let text = match msg {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                let Ok(frame) = serde_json::from_str::<DaemonFrame>(&text) else {
+                    warn!("Ignoring malformed daemon frame");
+                    continue;
+                };
+                match frame {
+                    DaemonFrame::Message { session_id, message } => {
+                        let route = routes.entry(session_id).or_insert_with(|| {
+                            next_generation += 1;
+                            open_session(
+                                &app_state,
+                                &host,
+                                session_id,
+                                next_generation,
+                                out_tx.clone(),
+                                ended_tx.clone(),
+                            )
+                        });
+                        let _ = route.inbound.send(Message::Text(message.to_string()));
+                    }
+                    // Dropping the route ends the session's stream
+                    DaemonFrame::Closed { session_id } => {
+                        routes.remove(&session_id);
+                    }
+                }
+            }
+            Some((session_id, generation)) = ended_rx.recv() => {
+                // The backend ended it, e.g. for revoked access; tell the daemon
+                if routes.get(&session_id).is_some_and(|r| r.generation == generation) {
+                    routes.remove(&session_id);
+                    let _ = out_tx.send(DaemonFrame::Closed { session_id });
+                }
+            }
+        }
+    }
+
+    // Each session cleans up as if its own socket had closed
+    info!(
+        "Proxy daemon disconnected, ending {} session connection(s)",
+        routes.len()
+    );
+    drop(routes);
+    send_task.abort();
+}
+
+/// Start serving a session that shares the daemon's socket
+fn open_session(
+    app_state: &Arc<AppState>,
+    host: &str,
+    session_id: Uuid,
+    generation: u64,
+    out: mpsc::UnboundedSender<DaemonFrame>,
+    ended: mpsc::UnboundedSender<(Uuid, u64)>,
+) -> Route {
+    let (inbound, inbound_rx) = mpsc::unbounded_channel::<Message>();
+    let receiver = futures_util::stream::unfold(inbound_rx, |mut rx| async move {
+        rx.recv().await.map(|msg| (Ok(msg), rx))
+    });
+    let sender = futures_util::sink::unfold(out, move |out, msg: Message| async move {
+        if let Message::Text(text) = msg {
+            if let Some(frame) = DaemonFrame::from_text(session_id, &text) {
+                out.send(frame)
+                    .map_err(|_| axum::Error::new("daemon connection closed"))?;
+            }
+        }
+        Ok::<_, axum::Error>(out)
+    });
+
+    let app_state = app_state.clone();
+    let host = host.to_string();
+    let span = tracing::info_span!("proxy_connection", session_id = tracing::field::Empty);
+    tokio::spawn(
+        async move {
+            super::websocket::serve_session(Box::pin(sender), Box::pin(receiver), app_state, host)
+                .await;
+            let _ = ended.send((session_id, generation));
+        }
+        .instrument(span),
+    );
+    Route {
+        generation,
+        inbound,
+    }
+}
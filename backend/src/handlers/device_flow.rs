@@ -357,6 +357,20 @@ pub async fn device_approve(
         .as_ref()
         .ok_or_else(DeviceFlowApiError::service_unavailable)?;
 
+    let role = app_state
+        .db_pool
+        .get()
+        .ok()
+        .and_then(|mut conn| super::helpers::account_role(&mut conn, user_id).ok())
+        .ok_or_else(|| DeviceFlowApiError::internal_error("Failed to look up account"))?;
+    if role == shared::UserRole::Viewer {
+        return Err(DeviceFlowApiError {
+            status: StatusCode::FORBIDDEN,
+            error: "forbidden".to_string(),
+            message: "Viewer accounts can't authorize a proxy".to_string(),
+        });
+    }
+
     // Complete the device flow
     complete_device_flow(&app_state, store, &req.user_code, user_id)
         .await
@@ -785,6 +799,11 @@ pub async fn complete_device_flow(
         error!("Failed to find user: {}", e);
     })?;
 
+    if user.role() == shared::UserRole::Viewer {
+        error!("Viewer {} can't authorize a proxy", user.email);
+        return Err(());
+    }
+
     // Generate token ID and create JWT
     let token_id = Uuid::new_v4();
     let expires_in_days: u32 = 30; // Device flow tokens valid for 30 days
@@ -807,6 +826,8 @@ pub async fn complete_device_flow(
         ),
         token_hash,
         expires_at: expires_at.naive_utc(),
+        // Device logins are the user's own machine, so unrestricted
+        scopes: serde_json::json!({}),
     };
 
     diesel::insert_into(proxy_auth_tokens::table)
@@ -0,0 +1,448 @@
+//! Escalation of permission requests nobody answers.
+//!
+//! Session owners configure a policy per session. A background task checks
+//! waiting requests: after the first delay the chosen member is alerted in
+//! their open web clients and on their notification channels, after the second the default answer is sent to the
+//! proxy. Both steps are written to the permission audit log and published
+//! as `permission_escalated` events.
+
+use crate::models::EscalationPolicyRow;
+use crate::notifier::{Audience, Notification, NotificationKind};
+use crate::schema::{escalation_policies, permission_audit_log, session_members, sessions, users};
+use crate::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use shared::{
+    DecisionChannel, EscalationPolicy, EscalationStep, EventKind, ProxyMessage, RuleDecision,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+const SESSION_COOKIE_NAME: &str = "cc_session";
+
+/// How often waiting requests are checked
+pub const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Requests older than this are assumed abandoned and never escalated
+const MAX_REQUEST_AGE_HOURS: i64 = 24;
+
+/// Extract user_id from signed session cookie
+fn extract_user_id(app_state: &AppState, cookies: &Cookies) -> Result<Uuid, StatusCode> {
+    // In dev mode, allow unauthenticated access with test user
+    if app_state.dev_mode {
+        let mut conn = app_state
+            .db_pool
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        return users::table
+            .filter(users::email.eq("testing@testing.local"))
+            .select(users::id)
+            .first::<Uuid>(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let cookie = cookies
+        .signed(&app_state.cookie_key)
+        .get(SESSION_COOKIE_NAME)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    cookie.value().parse().map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+/// The caller's role in a session, or 404 if they can't see it
+fn member_role(
+    conn: &mut PgConnection,
+    session_id: Uuid,
+    user_id: Uuid,
+) -> Result<String, StatusCode> {
+    super::helpers::session_role(conn, session_id, user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+fn to_policy(row: &EscalationPolicyRow, notify_email: Option<String>) -> EscalationPolicy {
+    EscalationPolicy {
+        notify_after_minutes: row.notify_after_minutes.map(|m| m.max(0) as u32),
+        notify_email,
+        default_after_minutes: row.default_after_minutes.map(|m| m.max(0) as u32),
+        default_action: RuleDecision::parse(&row.default_action).unwrap_or(RuleDecision::Deny),
+    }
+}
+
+fn load_policy(conn: &mut PgConnection, session_id: Uuid) -> QueryResult<EscalationPolicy> {
+    let row: Option<(EscalationPolicyRow, Option<String>)> = escalation_policies::table
+        .left_join(users::table.on(users::id.nullable().eq(escalation_policies::notify_user_id)))
+        .filter(escalation_policies::session_id.eq(session_id))
+        .select((EscalationPolicyRow::as_select(), users::email.nullable()))
+        .first(conn)
+        .optional()?;
+    Ok(row
+        .map(|(row, email)| to_policy(&row, email))
+        .unwrap_or_default())
+}
+
+/// Get a session's escalation policy (any member)
+pub async fn get_escalation_policy(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(session_id): Path<Uuid>,
+) -> Result<Json<EscalationPolicy>, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    member_role(&mut conn, session_id, current_user_id)?;
+
+    load_policy(&mut conn, session_id).map(Json).map_err(|e| {
+        error!("Failed to load escalation policy: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Replace a session's escalation policy (owner only). A policy with no
+/// steps removes it. The contact must be a member who can answer requests.
+pub async fn update_escalation_policy(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(session_id): Path<Uuid>,
+    Json(policy): Json<EscalationPolicy>,
+) -> Result<Json<EscalationPolicy>, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if member_role(&mut conn, session_id, current_user_id)? != "owner" {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    if let Err(reason) = policy.validate() {
+        warn!("Rejected escalation policy for {}: {}", session_id, reason);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    if policy.notify_after_minutes.is_none() && policy.default_after_minutes.is_none() {
+        diesel::delete(escalation_policies::table.find(session_id))
+            .execute(&mut conn)
+            .map_err(|e| {
+                error!("Failed to clear escalation policy: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        return Ok(Json(EscalationPolicy::default()));
+    }
+
+    let notify_user_id = match policy.notify_email.as_deref().map(str::trim) {
+        Some(email) if !email.is_empty() => {
+            let contact = session_members::table
+                .inner_join(users::table.on(users::id.eq(session_members::user_id)))
+                .filter(session_members::session_id.eq(session_id))
+                .filter(users::email.eq(email.to_lowercase()))
+                .filter(session_members::role.ne("viewer"))
+                .select(users::id)
+                .first::<Uuid>(&mut conn)
+                .optional()
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            if contact.is_none() {
+                warn!(
+                    "Rejected escalation policy for {}: {} can't answer its requests",
+                    session_id, email
+                );
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            contact
+        }
+        _ => None,
+    };
+
+    let row = EscalationPolicyRow {
+        session_id,
+        notify_after_minutes: policy.notify_after_minutes.map(|m| m as i32),
+        notify_user_id,
+        default_after_minutes: policy.default_after_minutes.map(|m| m as i32),
+        default_action: policy.default_action.as_str().to_string(),
+    };
+    diesel::insert_into(escalation_policies::table)
+        .values(&row)
+        .on_conflict(escalation_policies::session_id)
+        .do_update()
+        .set((&row, escalation_policies::updated_at.eq(diesel::dsl::now)))
+        .execute(&mut conn)
+        .map_err(|e| {
+            error!("Failed to save escalation policy: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    info!(
+        "Escalation policy for session {} updated by {}",
+        session_id, current_user_id
+    );
+    load_policy(&mut conn, session_id).map(Json).map_err(|e| {
+        error!("Failed to load escalation policy: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// A waiting request in a session with an escalation policy
+struct WaitingRequest {
+    session_id: Uuid,
+    session_name: String,
+    request_id: String,
+    tool_name: String,
+    input: serde_json::Value,
+    requested_at: NaiveDateTime,
+    escalated: bool,
+    policy: EscalationPolicyRow,
+}
+
+/// Escalate waiting permission requests whose delays have passed
+pub fn run_escalations(app_state: &AppState) {
+    let Ok(mut conn) = app_state.db_pool.get() else {
+        error!("Failed to get DB connection for permission escalation");
+        return;
+    };
+
+    let now = chrono::Utc::now().naive_utc();
+    let oldest = now - chrono::Duration::hours(MAX_REQUEST_AGE_HOURS);
+    type Row = (
+        Uuid,
+        String,
+        String,
+        String,
+        serde_json::Value,
+        NaiveDateTime,
+        Option<NaiveDateTime>,
+        EscalationPolicyRow,
+    );
+    let rows: Vec<Row> = match permission_audit_log::table
+        .inner_join(
+            escalation_policies::table
+                .on(escalation_policies::session_id.eq(permission_audit_log::session_id)),
+        )
+        .inner_join(sessions::table.on(sessions::id.eq(permission_audit_log::session_id)))
+        .filter(permission_audit_log::decided_at.is_null())
+        .filter(permission_audit_log::requested_at.gt(oldest))
+        .select((
+            permission_audit_log::session_id,
+            sessions::session_name,
+            permission_audit_log::request_id,
+            permission_audit_log::tool_name,
+            permission_audit_log::input,
+            permission_audit_log::requested_at,
+            permission_audit_log::escalated_at,
+            EscalationPolicyRow::as_select(),
+        ))
+        .load(&mut conn)
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!("Failed to load waiting permission requests: {}", e);
+            return;
+        }
+    };
+    if rows.is_empty() {
+        return;
+    }
+
+    let contact_ids: Vec<Uuid> = rows.iter().filter_map(|r| r.7.notify_user_id).collect();
+    let emails: HashMap<Uuid, String> = users::table
+        .filter(users::id.eq_any(&contact_ids))
+        .select((users::id, users::email))
+        .load::<(Uuid, String)>(&mut conn)
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+
+    for (
+        session_id,
+        session_name,
+        request_id,
+        tool_name,
+        input,
+        requested_at,
+        escalated_at,
+        policy,
+    ) in rows
+    {
+        let request = WaitingRequest {
+            session_id,
+            session_name,
+            request_id,
+            tool_name,
+            input,
+            requested_at,
+            escalated: escalated_at.is_some(),
+            policy,
+        };
+        escalate(app_state, &request, &emails, now);
+    }
+}
+
+fn escalate(
+    app_state: &AppState,
+    request: &WaitingRequest,
+    emails: &HashMap<Uuid, String>,
+    now: NaiveDateTime,
+) {
+    // Only act while the proxy is there to take the answer; a request from a
+    // proxy that has since gone away may no longer exist
+    let key = request.session_id.to_string();
+    if !app_state.session_manager.sessions.contains_key(&key) {
+        return;
+    }
+
+    let contact = request
+        .policy
+        .notify_user_id
+        .and_then(|id| Some((id, emails.get(&id)?.clone())));
+    let policy = to_policy(&request.policy, contact.as_ref().map(|(_, e)| e.clone()));
+    let waited_minutes = (now - request.requested_at).num_minutes();
+
+    match policy.due_step(waited_minutes, request.escalated) {
+        Some(EscalationStep::Notify) => {
+            let Some((user_id, email)) = contact else {
+                return;
+            };
+            info!(
+                "Permission request {} in session {} waited {} minutes, notifying {}",
+                request.request_id, request.session_id, waited_minutes, email
+            );
+            app_state.session_manager.broadcast_to_user(
+                &user_id,
+                ProxyMessage::PermissionEscalated {
+                    session_id: request.session_id,
+                    session_name: request.session_name.clone(),
+                    request_id: request.request_id.clone(),
+                    tool_name: request.tool_name.clone(),
+                    waiting_minutes: waited_minutes,
+                },
+            );
+            if let Some(notifiers) = &app_state.session_manager.notifiers {
+                notifiers.notify(
+                    Notification {
+                        session_id: request.session_id,
+                        label: request.session_name.clone(),
+                        kind: NotificationKind::PermissionEscalated {
+                            tool_name: request.tool_name.clone(),
+                            minutes: waited_minutes,
+                        },
+                    },
+                    Audience::User(user_id),
+                );
+            }
+            super::permission_history::record_escalation(
+                &app_state.db_pool,
+                request.session_id,
+                &request.request_id,
+                &email,
+            );
+            app_state.session_manager.publish_event(
+                EventKind::PermissionEscalated,
+                request.session_id,
+                serde_json::json!({
+                    "request_id": request.request_id,
+                    "tool_name": request.tool_name,
+                    "waiting_minutes": waited_minutes,
+                    "notified": email,
+                }),
+            );
+        }
+        Some(EscalationStep::ApplyDefault(decision)) => {
+            apply_default(app_state, request, decision, waited_minutes);
+        }
+        None => {}
+    }
+}
+
+fn apply_default(
+    app_state: &AppState,
+    request: &WaitingRequest,
+    decision: RuleDecision,
+    waited_minutes: i64,
+) {
+    let allow = decision == RuleDecision::Allow;
+    let detail = format!("No answer after {} minutes", waited_minutes);
+    info!(
+        "Permission request {} in session {}: {}, applying default ({})",
+        request.request_id,
+        request.session_id,
+        detail,
+        decision.as_str()
+    );
+
+    let key = request.session_id.to_string();
+    let sent = app_state.session_manager.send_to_session(
+        &key,
+        ProxyMessage::PermissionResponse {
+            request_id: request.request_id.clone(),
+            allow,
+            input: allow.then(|| request.input.clone()),
+            permissions: Vec::new(),
+            reason: (!allow).then(|| detail.clone()),
+            remember: None,
+        },
+    );
+    if !sent {
+        warn!(
+            "Failed to deliver escalation default for session {}",
+            request.session_id
+        );
+        return;
+    }
+
+    super::permission_history::record_decision(
+        &app_state.db_pool,
+        request.session_id,
+        &request.request_id,
+        super::permission_history::Decision {
+            allowed: allow,
+            channel: DecisionChannel::Timeout,
+            decided_by: None,
+            detail: Some(detail),
+        },
+    );
+    app_state.session_manager.publish_event(
+        EventKind::PermissionEscalated,
+        request.session_id,
+        serde_json::json!({
+            "request_id": request.request_id,
+            "tool_name": request.tool_name,
+            "waiting_minutes": waited_minutes,
+            "default": decision.as_str(),
+        }),
+    );
+
+    if let Ok(mut conn) = app_state.db_pool.get() {
+        use crate::schema::pending_permission_requests;
+        if let Err(e) = diesel::delete(
+            pending_permission_requests::table
+                .filter(pending_permission_requests::session_id.eq(request.session_id))
+                .filter(pending_permission_requests::request_id.eq(&request.request_id)),
+        )
+        .execute(&mut conn)
+        {
+            error!("Failed to clear pending permission request: {}", e);
+        }
+    }
+
+    app_state.session_manager.broadcast_to_web_clients(
+        &key,
+        ProxyMessage::PermissionResolved {
+            request_id: request.request_id.clone(),
+            allow,
+        },
+    );
+}
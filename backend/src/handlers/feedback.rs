@@ -0,0 +1,388 @@
+//! Turn-level feedback: thumbs-up/down with an optional comment on result messages.
+//!
+//! Feedback is exported as JSON lines so teams can build a dataset of which
+//! prompts worked and refine their templates.
+
+use crate::models::{NewTurnFeedback, TurnFeedback};
+use crate::schema::{messages, session_members, sessions, turn_feedback, users};
+use crate::AppState;
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use diesel::prelude::*;
+use serde::Serialize;
+use shared::{FeedbackRating, TurnFeedbackInfo, TurnFeedbackRequest, UserRole};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::error;
+use uuid::Uuid;
+
+const SESSION_COOKIE_NAME: &str = "cc_session";
+
+/// Maximum length of a feedback comment
+const MAX_COMMENT_LENGTH: usize = 2000;
+
+/// Extract user_id from signed session cookie
+fn extract_user_id(app_state: &AppState, cookies: &Cookies) -> Result<Uuid, StatusCode> {
+    // In dev mode, allow unauthenticated access with test user
+    if app_state.dev_mode {
+        let mut conn = app_state
+            .db_pool
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        return users::table
+            .filter(users::email.eq("testing@testing.local"))
+            .select(users::id)
+            .first::<Uuid>(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let cookie = cookies
+        .signed(&app_state.cookie_key)
+        .get(SESSION_COOKIE_NAME)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    cookie.value().parse().map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+/// Verify that a user can see a session (members, or any admin)
+fn verify_membership(
+    conn: &mut diesel::pg::PgConnection,
+    session_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), StatusCode> {
+    super::helpers::session_role(conn, session_id, user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(|_| ())
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+fn parse_rating(rating: &str) -> FeedbackRating {
+    match rating {
+        "down" => FeedbackRating::Down,
+        _ => FeedbackRating::Up,
+    }
+}
+
+fn to_info(feedback: TurnFeedback) -> TurnFeedbackInfo {
+    TurnFeedbackInfo {
+        message_id: feedback.message_id,
+        session_id: feedback.session_id,
+        rating: parse_rating(&feedback.rating),
+        comment: feedback.comment,
+        prompt: feedback.prompt,
+        created_at: feedback.created_at.and_utc().to_rfc3339(),
+    }
+}
+
+/// Extract the typed prompt from a stored user message.
+///
+/// Returns None for tool result messages, which are stored with the same role.
+fn extract_user_prompt(content: &serde_json::Value) -> Option<String> {
+    if content.get("type").and_then(|t| t.as_str()) != Some("user") {
+        return None;
+    }
+
+    let inner = content.get("message").and_then(|m| m.get("content"))?;
+    let text = match inner {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(blocks) => blocks
+            .iter()
+            .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("text"))
+            .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => return None,
+    };
+
+    let trimmed = text.trim();
+    (!trimmed.is_empty()).then(|| trimmed.to_string())
+}
+
+/// Find the prompt that started the turn ending with `result_id`, created at
+/// `result_created_at`
+fn find_turn_prompt(
+    conn: &mut diesel::pg::PgConnection,
+    session_id: Uuid,
+    result_id: Uuid,
+    result_created_at: chrono::NaiveDateTime,
+) -> Option<String> {
+    // The recorded turn says where it started
+    if let Ok(Some(turn)) = super::turns::turn_ending_at(conn, result_id) {
+        let first = messages::table
+            .filter(messages::session_id.eq(session_id))
+            .filter(messages::seq.eq(turn.start_seq))
+            .select(messages::content)
+            .first::<String>(conn)
+            .ok()
+            .and_then(|c| serde_json::from_str::<serde_json::Value>(&c).ok())
+            .and_then(|v| extract_user_prompt(&v));
+        if first.is_some() {
+            return first;
+        }
+    }
+
+    // Turns from before they were recorded: the latest prompt before the result
+    let candidates: Vec<String> = messages::table
+        .filter(messages::session_id.eq(session_id))
+        .filter(messages::role.eq("user"))
+        .filter(messages::created_at.le(result_created_at))
+        .order(messages::created_at.desc())
+        .select(messages::content)
+        .limit(50)
+        .load(conn)
+        .ok()?;
+
+    candidates
+        .iter()
+        .filter_map(|c| serde_json::from_str::<serde_json::Value>(c).ok())
+        .find_map(|v| extract_user_prompt(&v))
+}
+
+/// List the current user's feedback in a session
+pub async fn list_feedback(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(session_id): Path<Uuid>,
+) -> Result<Json<Vec<TurnFeedbackInfo>>, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    verify_membership(&mut conn, session_id, current_user_id)?;
+
+    let feedback: Vec<TurnFeedback> = turn_feedback::table
+        .filter(turn_feedback::session_id.eq(session_id))
+        .filter(turn_feedback::user_id.eq(current_user_id))
+        .order(turn_feedback::created_at.asc())
+        .load(&mut conn)
+        .map_err(|e| {
+            error!("Failed to list feedback: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(feedback.into_iter().map(to_info).collect()))
+}
+
+/// Rate a result message (creates or replaces the current user's feedback)
+pub async fn submit_feedback(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path((session_id, message_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<TurnFeedbackRequest>,
+) -> Result<Json<TurnFeedbackInfo>, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    let comment = req
+        .comment
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty());
+    if comment
+        .as_ref()
+        .is_some_and(|c| c.len() > MAX_COMMENT_LENGTH)
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    verify_membership(&mut conn, session_id, current_user_id)?;
+
+    // Only result messages (the end of a turn) can be rated
+    let (role, created_at): (String, chrono::NaiveDateTime) = messages::table
+        .filter(messages::id.eq(message_id))
+        .filter(messages::session_id.eq(session_id))
+        .select((messages::role, messages::created_at))
+        .first(&mut conn)
+        .optional()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if role != "result" {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let prompt = find_turn_prompt(&mut conn, session_id, message_id, created_at);
+
+    let new_feedback = NewTurnFeedback {
+        session_id,
+        message_id: Some(message_id),
+        user_id: current_user_id,
+        rating: req.rating.as_str().to_string(),
+        comment: comment.clone(),
+        prompt: prompt.clone(),
+    };
+
+    let feedback: TurnFeedback = diesel::insert_into(turn_feedback::table)
+        .values(&new_feedback)
+        .on_conflict((turn_feedback::message_id, turn_feedback::user_id))
+        .do_update()
+        .set((
+            turn_feedback::rating.eq(req.rating.as_str()),
+            turn_feedback::comment.eq(comment),
+            turn_feedback::prompt.eq(prompt),
+            turn_feedback::updated_at.eq(diesel::dsl::now),
+        ))
+        .get_result(&mut conn)
+        .map_err(|e| {
+            error!("Failed to store feedback: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(to_info(feedback)))
+}
+
+/// Remove the current user's feedback from a result message
+pub async fn delete_feedback(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path((session_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    diesel::delete(
+        turn_feedback::table
+            .filter(turn_feedback::session_id.eq(session_id))
+            .filter(turn_feedback::message_id.eq(message_id))
+            .filter(turn_feedback::user_id.eq(current_user_id)),
+    )
+    .execute(&mut conn)
+    .map_err(|e| {
+        error!("Failed to delete feedback: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// A single line of the feedback export
+#[derive(Debug, Serialize)]
+struct FeedbackExportRow {
+    session_id: Uuid,
+    session_name: String,
+    working_directory: String,
+    message_id: Option<Uuid>,
+    user_email: String,
+    rating: FeedbackRating,
+    comment: Option<String>,
+    prompt: Option<String>,
+    created_at: String,
+}
+
+/// Export all feedback on sessions the current user can access, as JSON lines
+pub async fn export_feedback(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Response, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let is_admin = super::helpers::account_role(&mut conn, current_user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        == UserRole::Admin;
+
+    let mut query = turn_feedback::table
+        .inner_join(sessions::table.on(sessions::id.eq(turn_feedback::session_id)))
+        .inner_join(users::table.on(users::id.eq(turn_feedback::user_id)))
+        .into_boxed();
+    // Admins export feedback from every session
+    if !is_admin {
+        let accessible_sessions = session_members::table
+            .filter(session_members::user_id.eq(current_user_id))
+            .select(session_members::session_id);
+        query = query.filter(turn_feedback::session_id.eq_any(accessible_sessions));
+    }
+
+    let rows: Vec<(TurnFeedback, String, String, String)> = query
+        .order(turn_feedback::created_at.asc())
+        .select((
+            TurnFeedback::as_select(),
+            sessions::session_name,
+            sessions::working_directory,
+            users::email,
+        ))
+        .load(&mut conn)
+        .map_err(|e| {
+            error!("Failed to export feedback: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let mut body = String::new();
+    for (feedback, session_name, working_directory, user_email) in rows {
+        let row = FeedbackExportRow {
+            session_id: feedback.session_id,
+            session_name,
+            working_directory,
+            message_id: feedback.message_id,
+            user_email,
+            rating: parse_rating(&feedback.rating),
+            comment: feedback.comment,
+            prompt: feedback.prompt,
+            created_at: feedback.created_at.and_utc().to_rfc3339(),
+        };
+        if let Ok(line) = serde_json::to_string(&row) {
+            body.push_str(&line);
+            body.push('\n');
+        }
+    }
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"turn-feedback.jsonl\"",
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_extract_prompt_from_string_content() {
+        let msg = json!({"type": "user", "message": {"role": "user", "content": "fix the bug"}});
+        assert_eq!(extract_user_prompt(&msg), Some("fix the bug".to_string()));
+    }
+
+    #[test]
+    fn test_extract_prompt_from_text_blocks() {
+        let msg = json!({
+            "type": "user",
+            "message": {"content": [{"type": "text", "text": "first"}, {"type": "text", "text": "second"}]}
+        });
+        assert_eq!(extract_user_prompt(&msg), Some("first\nsecond".to_string()));
+    }
+
+    #[test]
+    fn test_extract_prompt_skips_tool_results() {
+        let msg = json!({
+            "type": "user",
+            "message": {"content": [{"type": "tool_result", "tool_use_id": "t1", "content": "ok"}]}
+        });
+        assert_eq!(extract_user_prompt(&msg), None);
+    }
+}
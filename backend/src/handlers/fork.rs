@@ -0,0 +1,88 @@
+//! Session fork handler
+//!
+//! `claude-portal fork` fetches a fork's starting point from here, so this
+//! accepts the proxy token as well as the browser session cookie.
+
+use crate::schema::{messages, sessions};
+use crate::AppState;
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use diesel::prelude::*;
+use serde::Deserialize;
+use shared::SessionFork;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::error;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct ForkParams {
+    /// The last message to replay into the fork
+    pub at: Uuid,
+}
+
+/// GET /api/sessions/:id/fork?at=<message id>
+pub async fn fork_session(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(session_id): Path<Uuid>,
+    Query(params): Query<ForkParams>,
+) -> Result<Json<SessionFork>, StatusCode> {
+    let (current_user_id, scopes) =
+        super::messages::extract_caller(&app_state, &cookies, &headers, peer)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let role = super::helpers::session_role(&mut conn, session_id, current_user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    // A fork's briefing carries tool output the viewer isn't shown
+    if super::redaction::redactor_for(&mut conn, session_id, &role).is_some() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let session = sessions::table
+        .find(session_id)
+        .select(crate::models::Session::as_select())
+        .first::<crate::models::Session>(&mut conn)
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    if !scopes.allows_directory(&session.working_directory) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let at_seq: i64 = messages::table
+        .find(params.at)
+        .filter(messages::session_id.eq(session_id))
+        .select(messages::seq)
+        .first(&mut conn)
+        .optional()
+        .map_err(|e| {
+            error!("Failed to look up fork point: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let turns = crate::fork::load_turns(&mut conn, session_id, at_seq).map_err(|e| {
+        error!("Failed to load messages to fork: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if turns.is_empty() {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    Ok(Json(SessionFork {
+        source_session_id: session_id,
+        prompt: crate::fork::build_prompt(&session.session_name, &turns),
+        source_session_name: session.session_name,
+        working_directory: session.working_directory,
+        at_message_id: params.at,
+    }))
+}
@@ -0,0 +1,404 @@
+//! Time-boxed guest access
+//!
+//! A session owner invites someone without an account by creating a guest
+//! link. Opening it the first time creates a guest user, a viewer account
+//! named after the guest, with a membership in that one session that ends
+//! when the invite does; later visits sign the same guest in again. The
+//! guest's decisions land in the permission audit log under their name,
+//! and invites and first visits are published as activity events.
+//!
+//! A background task ends the access of guests whose invite has expired or
+//! was revoked: their membership is removed, their account disabled, and
+//! their open connections closed.
+
+use super::websocket::{SessionManager, GUEST_ACCESS_ENDED};
+use crate::models::{GuestInvite, NewGuestInvite, NewUser, User};
+use crate::schema::{guest_invites, session_members, users};
+use crate::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    Json,
+};
+use diesel::prelude::*;
+use rand::{distributions::Alphanumeric, Rng};
+use shared::{
+    guests::describe_minutes, CreateGuestInviteRequest, CreatedGuestInvite, EventKind, GuestAccess,
+    GuestInviteInfo, UserRole, APPROVER_ROLE,
+};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+const SESSION_COOKIE_NAME: &str = "cc_session";
+
+/// How often expired and revoked guests are cleaned up
+pub const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Where an unusable link sends the guest
+const LINK_ENDED_PATH: &str = "/access-denied?reason=guest";
+
+/// Extract user_id from signed session cookie
+fn extract_user_id(app_state: &AppState, cookies: &Cookies) -> Result<Uuid, StatusCode> {
+    // In dev mode, allow unauthenticated access with test user
+    if app_state.dev_mode {
+        let mut conn = app_state
+            .db_pool
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        return users::table
+            .filter(users::email.eq("testing@testing.local"))
+            .select(users::id)
+            .first::<Uuid>(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let cookie = cookies
+        .signed(&app_state.cookie_key)
+        .get(SESSION_COOKIE_NAME)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    cookie.value().parse().map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+/// 403 unless the caller owns the session
+fn require_owner(
+    conn: &mut PgConnection,
+    session_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), StatusCode> {
+    let role = super::helpers::session_role(conn, session_id, user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if role != "owner" {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(())
+}
+
+fn generate_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(|c| c as char)
+        .collect()
+}
+
+fn access(role: &str) -> GuestAccess {
+    if role == APPROVER_ROLE {
+        GuestAccess::Approver
+    } else {
+        GuestAccess::Viewer
+    }
+}
+
+fn to_info(invite: GuestInvite, invited_by: String) -> GuestInviteInfo {
+    GuestInviteInfo {
+        id: invite.id,
+        access: access(&invite.role),
+        name: invite.name,
+        invited_by,
+        expires_at: invite.expires_at.and_utc().to_rfc3339(),
+        joined: invite.guest_user_id.is_some(),
+    }
+}
+
+/// The guest's display name, marked so nobody mistakes them for a member
+fn guest_display_name(name: &str) -> String {
+    format!("{} (guest)", name)
+}
+
+/// GET /api/sessions/:id/guests - Invites still in effect (owner only)
+pub async fn list_guest_invites(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(session_id): Path<Uuid>,
+) -> Result<Json<Vec<GuestInviteInfo>>, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    require_owner(&mut conn, session_id, current_user_id)?;
+
+    let invites: Vec<(GuestInvite, String)> = guest_invites::table
+        .inner_join(users::table.on(users::id.eq(guest_invites::invited_by)))
+        .filter(guest_invites::session_id.eq(session_id))
+        .filter(guest_invites::revoked_at.is_null())
+        .filter(guest_invites::expires_at.gt(diesel::dsl::now))
+        .order(guest_invites::created_at.desc())
+        .select((GuestInvite::as_select(), users::email))
+        .load(&mut conn)
+        .map_err(|e| {
+            error!("Failed to load guest invites: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(
+        invites
+            .into_iter()
+            .map(|(invite, email)| to_info(invite, email))
+            .collect(),
+    ))
+}
+
+/// POST /api/sessions/:id/guests - Create a guest link (owner only). The
+/// link is in the response and nowhere else.
+pub async fn create_guest_invite(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(session_id): Path<Uuid>,
+    Json(req): Json<CreateGuestInviteRequest>,
+) -> Result<Json<CreatedGuestInvite>, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    if let Err(reason) = req.validate() {
+        warn!("Rejected guest invite for {}: {}", session_id, reason);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    require_owner(&mut conn, session_id, current_user_id)?;
+
+    let invited_by: String = users::table
+        .find(current_user_id)
+        .select(users::email)
+        .first(&mut conn)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let token = generate_token();
+    let name = req.name.trim();
+    let expires_at =
+        chrono::Utc::now().naive_utc() + chrono::Duration::minutes(i64::from(req.minutes));
+    let invite = diesel::insert_into(guest_invites::table)
+        .values(&NewGuestInvite {
+            session_id,
+            token_hash: &crate::jwt::hash_token(&token),
+            name,
+            role: req.access.role(),
+            invited_by: current_user_id,
+            expires_at,
+        })
+        .returning(GuestInvite::as_returning())
+        .get_result(&mut conn)
+        .map_err(|e| {
+            error!("Failed to create guest invite: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    info!(
+        "{} invited guest {:?} to session {} for {} minutes",
+        invited_by, name, session_id, req.minutes
+    );
+    app_state.session_manager.publish_event(
+        EventKind::GuestInvited,
+        session_id,
+        serde_json::json!({
+            "invite_id": invite.id,
+            "guest": name,
+            "access": req.access.role(),
+            "invited_by": invited_by,
+            "duration": describe_minutes(req.minutes),
+            "expires_at": expires_at.and_utc().to_rfc3339(),
+        }),
+    );
+
+    Ok(Json(CreatedGuestInvite {
+        url: format!(
+            "{}/api/guest/{}",
+            app_state.public_url.trim_end_matches('/'),
+            token
+        ),
+        invite: to_info(invite, invited_by),
+    }))
+}
+
+/// DELETE /api/sessions/:id/guests/:invite_id - End a guest's access now
+/// (owner only)
+pub async fn revoke_guest_invite(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path((session_id, invite_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    require_owner(&mut conn, session_id, current_user_id)?;
+
+    let revoked = diesel::update(
+        guest_invites::table
+            .filter(guest_invites::id.eq(invite_id))
+            .filter(guest_invites::session_id.eq(session_id))
+            .filter(guest_invites::revoked_at.is_null()),
+    )
+    .set(guest_invites::revoked_at.eq(diesel::dsl::now.nullable()))
+    .execute(&mut conn)
+    .map_err(|e| {
+        error!("Failed to revoke guest invite: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if revoked == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    // Don't wait for the cleanup task to take away a revoked guest's access
+    end_ended_guests(&mut conn, &app_state.session_manager).map_err(|e| {
+        error!("Failed to end revoked guest's access: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/guest/:token - Sign in as the link's guest and open the session
+pub async fn redeem_guest_link(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(token): Path<String>,
+) -> Result<Response, StatusCode> {
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let token_hash = crate::jwt::hash_token(&token);
+    let joined = conn
+        .transaction::<_, diesel::result::Error, _>(|conn| {
+            // Locked so two first visits can't both create a guest
+            let Some(invite) = guest_invites::table
+                .filter(guest_invites::token_hash.eq(&token_hash))
+                .filter(guest_invites::revoked_at.is_null())
+                .filter(guest_invites::expires_at.gt(diesel::dsl::now))
+                .select(GuestInvite::as_select())
+                .for_update()
+                .first(conn)
+                .optional()?
+            else {
+                return Ok(None);
+            };
+            if let Some(guest_user_id) = invite.guest_user_id {
+                return Ok(Some((invite, guest_user_id, false)));
+            }
+
+            let guest: User = diesel::insert_into(users::table)
+                .values(&NewUser {
+                    google_id: format!("guest:{}", invite.id),
+                    email: format!("guest-{}@guests.invalid", invite.id.simple()),
+                    name: Some(guest_display_name(&invite.name)),
+                    avatar_url: None,
+                })
+                .get_result(conn)?;
+            // A viewer account can't create tokens or run sessions of its own
+            diesel::update(users::table.find(guest.id))
+                .set(users::role.eq(UserRole::Viewer.as_str()))
+                .execute(conn)?;
+            diesel::insert_into(session_members::table)
+                .values((
+                    session_members::session_id.eq(invite.session_id),
+                    session_members::user_id.eq(guest.id),
+                    session_members::role.eq(&invite.role),
+                    session_members::expires_at.eq(Some(invite.expires_at)),
+                ))
+                .execute(conn)?;
+            diesel::update(guest_invites::table.find(invite.id))
+                .set(guest_invites::guest_user_id.eq(Some(guest.id)))
+                .execute(conn)?;
+            Ok(Some((invite, guest.id, true)))
+        })
+        .map_err(|e| {
+            error!("Failed to redeem guest link: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let Some((invite, guest_user_id, first_visit)) = joined else {
+        info!("Rejected an expired, revoked, or unknown guest link");
+        return Ok(Redirect::temporary(LINK_ENDED_PATH).into_response());
+    };
+
+    if first_visit {
+        info!(
+            "Guest {:?} joined session {} as {}",
+            invite.name, invite.session_id, guest_user_id
+        );
+        app_state.session_manager.publish_event(
+            EventKind::GuestJoined,
+            invite.session_id,
+            serde_json::json!({ "invite_id": invite.id, "guest": invite.name }),
+        );
+    }
+
+    // The cookie lasts as long as the invite; the membership's expiry is
+    // what actually ends access
+    let remaining = invite.expires_at - chrono::Utc::now().naive_utc();
+    let mut cookie = super::auth::session_cookie(guest_user_id, !app_state.dev_mode);
+    cookie.set_max_age(tower_cookies::cookie::time::Duration::seconds(
+        remaining.num_seconds().max(0),
+    ));
+    cookies.signed(&app_state.cookie_key).add(cookie);
+
+    Ok(Redirect::temporary(&format!("/dashboard?session={}", invite.session_id)).into_response())
+}
+
+/// Remove the memberships of guests whose invite expired or was revoked,
+/// disable their accounts, and close their open connections. Returns how
+/// many guests lost access.
+fn end_ended_guests(conn: &mut PgConnection, sessions: &SessionManager) -> QueryResult<usize> {
+    let ended: Vec<Uuid> = guest_invites::table
+        .inner_join(users::table.on(users::id.nullable().eq(guest_invites::guest_user_id)))
+        .filter(
+            guest_invites::revoked_at
+                .is_not_null()
+                .or(guest_invites::expires_at.le(diesel::dsl::now)),
+        )
+        .filter(users::disabled.eq(false))
+        .select(users::id)
+        .load(conn)?;
+    if ended.is_empty() {
+        return Ok(0);
+    }
+
+    let disabled = conn.transaction(|conn| {
+        diesel::delete(session_members::table.filter(session_members::user_id.eq_any(&ended)))
+            .execute(conn)?;
+        diesel::update(
+            users::table
+                .filter(users::id.eq_any(&ended))
+                .filter(users::disabled.eq(false)),
+        )
+        .set((
+            users::disabled.eq(true),
+            users::ban_reason.eq(Some("Guest access ended")),
+        ))
+        .execute(conn)
+    })?;
+    for user_id in ended {
+        sessions.close_user_connections(user_id, GUEST_ACCESS_ENDED);
+    }
+    Ok(disabled)
+}
+
+/// End the access of guests whose time is up or whose invite was revoked
+pub fn end_expired_guests(app_state: &AppState) {
+    let Ok(mut conn) = app_state.db_pool.get() else {
+        error!("Failed to get DB connection for guest expiry");
+        return;
+    };
+    match end_ended_guests(&mut conn, &app_state.session_manager) {
+        Ok(0) => {}
+        Ok(ended) => info!("Ended access for {} guest(s)", ended),
+        Err(e) => error!("Failed to end expired guest access: {}", e),
+    }
+}
@@ -1,11 +1,96 @@
 use crate::models::{NewDeletedSessionCosts, Session};
-use crate::schema::{deleted_session_costs, messages, raw_message_log, session_members, sessions};
+use crate::schema::{
+    deleted_session_costs, messages, raw_message_log, session_members, sessions, users,
+};
 use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, PooledConnection};
 use diesel::PgConnection;
+use shared::{UserRole, APPROVER_ROLE};
 use tracing::error;
 use uuid::Uuid;
 
+/// Session role an admin has on a session they aren't a member of
+pub const ADMIN_SESSION_ROLE: &str = "admin";
+
+/// A user's account role; missing or unknown roles count as member
+pub fn account_role(conn: &mut PgConnection, user_id: Uuid) -> QueryResult<UserRole> {
+    Ok(users::table
+        .find(user_id)
+        .select(users::role)
+        .first::<String>(conn)
+        .optional()?
+        .and_then(|role| UserRole::parse(&role))
+        .unwrap_or_default())
+}
+
+/// Combine an account role with a session membership role. Viewer accounts
+/// are read-only in every session, except that guests invited to approve
+/// (whose accounts are viewers) keep that; admins can open any session,
+/// with their own membership role where they have one.
+pub fn effective_session_role(account: UserRole, membership: Option<String>) -> Option<String> {
+    match (account, membership) {
+        (UserRole::Viewer, Some(role)) if role != APPROVER_ROLE => Some("viewer".to_string()),
+        (_, Some(role)) => Some(role),
+        (UserRole::Admin, None) => Some(ADMIN_SESSION_ROLE.to_string()),
+        (_, None) => None,
+    }
+}
+
+/// The user's effective role in a session, or `None` if they can't see it.
+/// Handlers check this instead of `session_members` directly.
+pub fn session_role(
+    conn: &mut PgConnection,
+    session_id: Uuid,
+    user_id: Uuid,
+) -> QueryResult<Option<String>> {
+    let account = account_role(conn, user_id)?;
+    let membership: Option<String> = session_members::table
+        .filter(session_members::session_id.eq(session_id))
+        .filter(session_members::user_id.eq(user_id))
+        .filter(
+            session_members::expires_at
+                .is_null()
+                .or(session_members::expires_at.gt(diesel::dsl::now)),
+        )
+        .select(session_members::role)
+        .first(conn)
+        .optional()?;
+    if membership.is_none() && account == UserRole::Admin {
+        let exists: i64 = sessions::table.find(session_id).count().get_result(conn)?;
+        if exists == 0 {
+            return Ok(None);
+        }
+    }
+    Ok(effective_session_role(account, membership))
+}
+
+/// Whether a session role may send input, answer permission requests, and
+/// change the session
+pub fn can_edit(role: &str) -> bool {
+    role != "viewer" && role != APPROVER_ROLE
+}
+
+/// Whether a session role may answer permission requests, which approvers
+/// can without being able to edit
+pub fn can_answer_permissions(role: &str) -> bool {
+    role != "viewer"
+}
+
+/// When a user's access to a session ends, for guests
+pub fn membership_expiry(
+    conn: &mut PgConnection,
+    session_id: Uuid,
+    user_id: Uuid,
+) -> QueryResult<Option<chrono::NaiveDateTime>> {
+    Ok(session_members::table
+        .filter(session_members::session_id.eq(session_id))
+        .filter(session_members::user_id.eq(user_id))
+        .select(session_members::expires_at)
+        .first::<Option<chrono::NaiveDateTime>>(conn)
+        .optional()?
+        .flatten())
+}
+
 /// Error type for helper operations
 pub struct DeleteSessionError(String);
 
@@ -170,3 +255,48 @@ pub fn delete_user_sessions(
         deleted_raw,
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effective_session_role() {
+        let owner = || Some("owner".to_string());
+        assert_eq!(
+            effective_session_role(UserRole::Member, owner()).as_deref(),
+            Some("owner")
+        );
+        assert_eq!(effective_session_role(UserRole::Member, None), None);
+
+        // Viewer accounts never get more than view access
+        assert_eq!(
+            effective_session_role(UserRole::Viewer, owner()).as_deref(),
+            Some("viewer")
+        );
+        assert_eq!(effective_session_role(UserRole::Viewer, None), None);
+
+        // Admins keep their membership role and can open everything else
+        assert_eq!(
+            effective_session_role(UserRole::Admin, owner()).as_deref(),
+            Some("owner")
+        );
+        assert_eq!(
+            effective_session_role(UserRole::Admin, None).as_deref(),
+            Some(ADMIN_SESSION_ROLE)
+        );
+        assert!(can_edit(ADMIN_SESSION_ROLE));
+        assert!(!can_edit("viewer"));
+    }
+
+    #[test]
+    fn test_approver_guests() {
+        // Guests have viewer accounts but keep an approver membership
+        let approver = effective_session_role(UserRole::Viewer, Some(APPROVER_ROLE.to_string()));
+        assert_eq!(approver.as_deref(), Some(APPROVER_ROLE));
+        assert!(!can_edit(APPROVER_ROLE));
+        assert!(can_answer_permissions(APPROVER_ROLE));
+        assert!(!can_answer_permissions("viewer"));
+        assert!(can_answer_permissions("editor"));
+    }
+}
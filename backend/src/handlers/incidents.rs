@@ -0,0 +1,353 @@
+//! Diagnostic bundles for sessions that end in an error or whose Claude process crashes.
+//!
+//! The proxy reports the incident with its own log tail and config summary; the
+//! backend adds a sanitized tail of the stored transcript and its estimate of
+//! the proxy's clock skew, and keeps the whole bundle as JSON so it can be
+//! downloaded from the session view.
+
+use crate::models::{Message, NewSessionIncident, Session, SessionIncident};
+use crate::schema::{messages, session_incidents, sessions, users};
+use crate::AppState;
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use diesel::prelude::*;
+use serde::Serialize;
+use shared::{redact_secrets, ClockSkewReport, IncidentConfigSummary, IncidentInfo};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::error;
+use uuid::Uuid;
+
+const SESSION_COOKIE_NAME: &str = "cc_session";
+
+/// Number of stored messages included in a bundle's transcript tail
+const TRANSCRIPT_TAIL_MESSAGES: i64 = 20;
+
+/// Longest message content kept in a bundle, in bytes
+const MAX_TRANSCRIPT_ENTRY_LEN: usize = 4000;
+
+/// Most proxy log lines accepted from a single report
+const MAX_LOG_TAIL_LINES: usize = 500;
+
+/// Extract user_id from signed session cookie
+fn extract_user_id(app_state: &AppState, cookies: &Cookies) -> Result<Uuid, StatusCode> {
+    // In dev mode, allow unauthenticated access with test user
+    if app_state.dev_mode {
+        let mut conn = app_state
+            .db_pool
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        return users::table
+            .filter(users::email.eq("testing@testing.local"))
+            .select(users::id)
+            .first::<Uuid>(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let cookie = cookies
+        .signed(&app_state.cookie_key)
+        .get(SESSION_COOKIE_NAME)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    cookie.value().parse().map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+/// Verify that a user can see a session's incidents (members, or any
+/// admin). Bundles carry a transcript tail that can't be redacted, so
+/// members whose view of the session is redacted are refused.
+fn verify_membership(
+    conn: &mut diesel::pg::PgConnection,
+    session_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), StatusCode> {
+    let role = super::helpers::session_role(conn, session_id, user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if super::redaction::redactor_for(conn, session_id, &role).is_some() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(())
+}
+
+/// What the proxy sent with `ProxyMessage::IncidentReport`
+pub struct IncidentReport {
+    pub reason: String,
+    pub proxy_version: String,
+    pub platform: String,
+    pub config_summary: IncidentConfigSummary,
+    pub log_tail: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct BundleSession {
+    id: Uuid,
+    name: String,
+    status: String,
+    git_branch: Option<String>,
+    created_at: String,
+    last_activity: String,
+}
+
+#[derive(Debug, Serialize)]
+struct BundleVersions {
+    backend: &'static str,
+    proxy: String,
+    /// Version the proxy reported when it registered
+    registered_client: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct TranscriptEntry {
+    role: String,
+    /// When the proxy produced it, corrected for clock skew, if known;
+    /// otherwise when the backend received it
+    created_at: String,
+    content: String,
+    truncated: bool,
+}
+
+/// The downloadable bundle, stored as JSONB
+#[derive(Debug, Serialize)]
+struct IncidentBundle {
+    reason: String,
+    created_at: String,
+    platform: String,
+    versions: BundleVersions,
+    session: BundleSession,
+    config: IncidentConfigSummary,
+    /// How far the proxy's clock was off, which shifts `proxy_log_tail`
+    /// timestamps; `None` if it never pinged
+    proxy_clock: Option<ClockSkewReport>,
+    transcript_tail: Vec<TranscriptEntry>,
+    proxy_log_tail: Vec<String>,
+}
+
+/// Cut `text` to at most `max_len` bytes without splitting a character
+fn truncate_on_char_boundary(text: &str, max_len: usize) -> &str {
+    if text.len() <= max_len {
+        return text;
+    }
+    let mut end = max_len;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+fn transcript_entry(message: Message) -> TranscriptEntry {
+    let content = redact_secrets(&message.content);
+    let kept = truncate_on_char_boundary(&content, MAX_TRANSCRIPT_ENTRY_LEN);
+    TranscriptEntry {
+        role: message.role,
+        created_at: message
+            .produced_at
+            .unwrap_or(message.created_at)
+            .and_utc()
+            .to_rfc3339(),
+        truncated: kept.len() < content.len(),
+        content: kept.to_string(),
+    }
+}
+
+fn to_info(incident: SessionIncident) -> IncidentInfo {
+    IncidentInfo {
+        id: incident.id,
+        session_id: incident.session_id,
+        reason: incident.reason,
+        created_at: incident.created_at.and_utc().to_rfc3339(),
+    }
+}
+
+/// Assemble and store a diagnostic bundle for a session
+pub fn record_incident(
+    db_pool: &crate::db::DbPool,
+    session_id: Uuid,
+    report: IncidentReport,
+    proxy_clock: Option<ClockSkewReport>,
+) -> Option<IncidentInfo> {
+    let mut conn = db_pool
+        .get()
+        .map_err(|e| error!("Failed to get DB connection for incident: {}", e))
+        .ok()?;
+
+    let session: Session = sessions::table
+        .find(session_id)
+        .first(&mut conn)
+        .map_err(|e| error!("Failed to load session {} for incident: {}", session_id, e))
+        .ok()?;
+
+    let mut recent: Vec<Message> = messages::table
+        .filter(messages::session_id.eq(session_id))
+        .order(messages::created_at.desc())
+        .limit(TRANSCRIPT_TAIL_MESSAGES)
+        .load(&mut conn)
+        .map_err(|e| error!("Failed to load transcript for incident: {}", e))
+        .unwrap_or_default();
+    recent.reverse();
+
+    // The proxy redacts too, but don't rely on an older client having done so
+    let reason = redact_secrets(&report.reason);
+    let skip = report.log_tail.len().saturating_sub(MAX_LOG_TAIL_LINES);
+    let proxy_log_tail = report
+        .log_tail
+        .iter()
+        .skip(skip)
+        .map(|line| redact_secrets(line))
+        .collect();
+    let config = IncidentConfigSummary {
+        claude_args: report
+            .config_summary
+            .claude_args
+            .iter()
+            .map(|arg| redact_secrets(arg))
+            .collect(),
+        ..report.config_summary
+    };
+
+    let bundle = IncidentBundle {
+        reason: reason.clone(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        platform: report.platform,
+        versions: BundleVersions {
+            backend: env!("CARGO_PKG_VERSION"),
+            proxy: report.proxy_version,
+            registered_client: session.client_version.clone(),
+        },
+        session: BundleSession {
+            id: session.id,
+            name: session.session_name,
+            status: session.status,
+            git_branch: session.git_branch,
+            created_at: session.created_at.and_utc().to_rfc3339(),
+            last_activity: session.last_activity.and_utc().to_rfc3339(),
+        },
+        config,
+        proxy_clock,
+        transcript_tail: recent.into_iter().map(transcript_entry).collect(),
+        proxy_log_tail,
+    };
+
+    let bundle = serde_json::to_value(&bundle)
+        .map_err(|e| error!("Failed to serialize incident bundle: {}", e))
+        .ok()?;
+
+    diesel::insert_into(session_incidents::table)
+        .values(NewSessionIncident {
+            session_id,
+            reason,
+            bundle,
+        })
+        .get_result::<SessionIncident>(&mut conn)
+        .map(to_info)
+        .map_err(|e| error!("Failed to store incident bundle: {}", e))
+        .ok()
+}
+
+/// List incidents recorded for a session, newest first
+pub async fn list_incidents(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(session_id): Path<Uuid>,
+) -> Result<Json<Vec<IncidentInfo>>, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    verify_membership(&mut conn, session_id, current_user_id)?;
+
+    let incidents: Vec<SessionIncident> = session_incidents::table
+        .filter(session_incidents::session_id.eq(session_id))
+        .order(session_incidents::created_at.desc())
+        .load(&mut conn)
+        .map_err(|e| {
+            error!("Failed to list incidents: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(incidents.into_iter().map(to_info).collect()))
+}
+
+/// Download an incident bundle as a JSON file
+pub async fn download_incident_bundle(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path((session_id, incident_id)): Path<(Uuid, Uuid)>,
+) -> Result<Response, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    verify_membership(&mut conn, session_id, current_user_id)?;
+
+    let incident: SessionIncident = session_incidents::table
+        .filter(session_incidents::id.eq(incident_id))
+        .filter(session_incidents::session_id.eq(session_id))
+        .first(&mut conn)
+        .optional()
+        .map_err(|e| {
+            error!("Failed to load incident: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let body = serde_json::to_string_pretty(&incident.bundle)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let disposition = format!(
+        "attachment; filename=\"incident-{}.json\"",
+        incident.created_at.format("%Y%m%d-%H%M%S")
+    );
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_on_char_boundary() {
+        assert_eq!(truncate_on_char_boundary("hello", 10), "hello");
+        assert_eq!(truncate_on_char_boundary("hello", 3), "hel");
+        // "é" is two bytes; cutting inside it backs off to the previous boundary
+        assert_eq!(truncate_on_char_boundary("aé", 2), "a");
+    }
+
+    #[test]
+    fn test_transcript_entry_is_redacted_and_truncated() {
+        let message = Message {
+            id: Uuid::new_v4(),
+            session_id: Uuid::new_v4(),
+            role: "user".to_string(),
+            content: format!(
+                "export ANTHROPIC_API_KEY=sk-ant-abcdefghijklmnop {}",
+                "x".repeat(MAX_TRANSCRIPT_ENTRY_LEN)
+            ),
+            created_at: chrono::NaiveDateTime::default(),
+            user_id: Uuid::new_v4(),
+            seq: 1,
+            produced_at: None,
+        };
+        let entry = transcript_entry(message);
+        assert!(!entry.content.contains("sk-ant-"));
+        assert!(entry.truncated);
+        assert_eq!(entry.content.len(), MAX_TRANSCRIPT_ENTRY_LEN);
+    }
+}
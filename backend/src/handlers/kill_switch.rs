@@ -0,0 +1,137 @@
+//! Kill switch endpoints
+//!
+//! An admin engages the switch during an incident to pause every running
+//! session: it is pushed to every connected proxy, which holds input back
+//! from Claude and denies new permission requests, and to every web client,
+//! which shows a banner. The switch is stored in the database, so it holds
+//! across restarts, and other backend instances pick it up when they next
+//! reload it (see [`reload`]).
+
+use crate::handlers::admin::require_admin;
+use crate::schema::kill_switch;
+use crate::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use chrono::SubsecRound;
+use diesel::prelude::*;
+use shared::{EngageKillSwitchRequest, KillSwitch};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::{error, warn};
+
+/// The kill switch stored in the database, if engaged
+fn load(conn: &mut PgConnection) -> QueryResult<Option<KillSwitch>> {
+    Ok(kill_switch::table
+        .select((
+            kill_switch::reason,
+            kill_switch::engaged_by,
+            kill_switch::engaged_at,
+        ))
+        .first::<(String, String, chrono::NaiveDateTime)>(conn)
+        .optional()?
+        .map(|(reason, engaged_by, engaged_at)| KillSwitch {
+            reason,
+            engaged_by,
+            engaged_at: engaged_at.and_utc(),
+        }))
+}
+
+/// Re-read the kill switch from the database and push it out if it
+/// changed, e.g. on another backend instance. Returns whether it changed.
+pub fn reload(app_state: &AppState) -> anyhow::Result<bool> {
+    let mut conn = app_state.db_pool.get()?;
+    let stored = load(&mut conn)?;
+    if stored == app_state.session_manager.kill_switch() {
+        return Ok(false);
+    }
+    app_state.session_manager.set_kill_switch(stored);
+    Ok(true)
+}
+
+/// GET /api/kill-switch - The kill switch, if engaged
+pub async fn get_kill_switch(State(app_state): State<Arc<AppState>>) -> Json<Option<KillSwitch>> {
+    Json(app_state.session_manager.kill_switch())
+}
+
+/// PUT /api/admin/kill-switch - Engage the kill switch, or change its
+/// reason (admin only)
+pub async fn engage(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(request): Json<EngageKillSwitchRequest>,
+) -> Result<Json<KillSwitch>, StatusCode> {
+    let admin = require_admin(&app_state, &cookies).await?;
+
+    if let Err(reason) = request.validate() {
+        warn!("Rejected kill switch from {}: {}", admin.email, reason);
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    // Keep the original time when only the reason changes. The database
+    // keeps microseconds, so the time does too, or the next reload would
+    // see a change.
+    let engaged_at = app_state
+        .session_manager
+        .kill_switch()
+        .map(|current| current.engaged_at)
+        .unwrap_or_else(|| chrono::Utc::now().trunc_subsecs(6));
+    let kill_switch = KillSwitch {
+        reason: request.reason.trim().to_string(),
+        engaged_by: admin.email.clone(),
+        engaged_at,
+    };
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    diesel::insert_into(kill_switch::table)
+        .values((
+            kill_switch::id.eq(1),
+            kill_switch::reason.eq(&kill_switch.reason),
+            kill_switch::engaged_by.eq(&kill_switch.engaged_by),
+            kill_switch::engaged_at.eq(kill_switch.engaged_at.naive_utc()),
+        ))
+        .on_conflict(kill_switch::id)
+        .do_update()
+        .set((
+            kill_switch::reason.eq(&kill_switch.reason),
+            kill_switch::engaged_by.eq(&kill_switch.engaged_by),
+            kill_switch::engaged_at.eq(kill_switch.engaged_at.naive_utc()),
+        ))
+        .execute(&mut conn)
+        .map_err(|e| {
+            error!("Failed to save kill switch: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    warn!(
+        "Admin {} engaged the kill switch: {:?}",
+        admin.email, kill_switch.reason
+    );
+    app_state
+        .session_manager
+        .set_kill_switch(Some(kill_switch.clone()));
+    Ok(Json(kill_switch))
+}
+
+/// DELETE /api/admin/kill-switch - Release the kill switch (admin only)
+pub async fn release(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<StatusCode, StatusCode> {
+    let admin = require_admin(&app_state, &cookies).await?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    diesel::delete(kill_switch::table)
+        .execute(&mut conn)
+        .map_err(|e| {
+            error!("Failed to clear kill switch: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    warn!("Admin {} released the kill switch", admin.email);
+    app_state.session_manager.set_kill_switch(None);
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,59 @@
+//! Maintenance banner endpoints
+//!
+//! Admins post a banner (and optionally a downtime window) that is pushed to
+//! every connected proxy and web client. Pages without a socket poll the
+//! public endpoint. The banner lives in memory, so a restart clears it.
+
+use crate::handlers::admin::require_admin;
+use crate::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use shared::MaintenanceNotice;
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::{info, warn};
+
+/// GET /api/maintenance - The banner currently up, if any
+pub async fn get_notice(State(app_state): State<Arc<AppState>>) -> Json<Option<MaintenanceNotice>> {
+    Json(app_state.session_manager.maintenance_notice())
+}
+
+/// PUT /api/admin/maintenance - Post or replace the banner (admin only)
+pub async fn set_notice(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(notice): Json<MaintenanceNotice>,
+) -> Result<Json<MaintenanceNotice>, StatusCode> {
+    let admin = require_admin(&app_state, &cookies).await?;
+
+    if let Err(reason) = notice.validate() {
+        warn!(
+            "Rejected maintenance banner from {}: {}",
+            admin.email, reason
+        );
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let notice = MaintenanceNotice {
+        message: notice.message.trim().to_string(),
+        ..notice
+    };
+
+    info!(
+        "Admin {} posted maintenance banner: {:?}",
+        admin.email, notice.message
+    );
+    app_state
+        .session_manager
+        .set_maintenance_notice(Some(notice.clone()));
+    Ok(Json(notice))
+}
+
+/// DELETE /api/admin/maintenance - Take the banner down (admin only)
+pub async fn clear_notice(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<StatusCode, StatusCode> {
+    let admin = require_admin(&app_state, &cookies).await?;
+    info!("Admin {} cleared the maintenance banner", admin.email);
+    app_state.session_manager.set_maintenance_notice(None);
+    Ok(StatusCode::NO_CONTENT)
+}
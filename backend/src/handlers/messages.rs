@@ -2,15 +2,17 @@ use crate::models::{Message, NewMessage};
 use crate::schema::messages;
 use crate::AppState;
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{ConnectInfo, Path, Query, State},
+    http::{HeaderMap, StatusCode},
     Json,
 };
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use shared::{OperatorAction, ProxyTokenScopes, SendMode};
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tower_cookies::Cookies;
-use tracing::error;
+use tracing::{error, info};
 use uuid::Uuid;
 
 const SESSION_COOKIE_NAME: &str = "cc_session";
@@ -35,6 +37,31 @@ pub struct MessagesListResponse {
     pub total: i64,
 }
 
+/// Query parameters for listing messages
+#[derive(Debug, Deserialize)]
+pub struct ListMessagesParams {
+    /// Only return messages with a greater `seq`, for polling new output
+    pub after_seq: Option<i64>,
+}
+
+/// Request body for sending input to a session's Claude
+#[derive(Debug, Deserialize)]
+pub struct SendInputRequest {
+    pub content: String,
+    #[serde(default)]
+    pub send_mode: Option<SendMode>,
+}
+
+/// Response for sending input
+#[derive(Debug, Serialize)]
+pub struct SendInputResponse {
+    /// Sequence number the input was queued under
+    pub input_seq: i64,
+    /// Latest message `seq` when the input was sent; poll
+    /// `messages?after_seq=` with it to read the reply
+    pub after_seq: i64,
+}
+
 /// Extract user_id from signed session cookie
 fn extract_user_id(app_state: &AppState, cookies: &Cookies) -> Result<Uuid, StatusCode> {
     // In dev mode, allow unauthenticated access with test user
@@ -61,20 +88,41 @@ fn extract_user_id(app_state: &AppState, cookies: &Cookies) -> Result<Uuid, Stat
     cookie.value().parse().map_err(|_| StatusCode::UNAUTHORIZED)
 }
 
-/// Verify that a user has access to a session (is a member with any role)
+/// Authenticate a browser session cookie or, for scripts, an
+/// `Authorization: Bearer <proxy token>` header. Cookie callers get
+/// unrestricted scopes.
+pub(crate) fn extract_caller(
+    app_state: &AppState,
+    cookies: &Cookies,
+    headers: &HeaderMap,
+    peer: SocketAddr,
+) -> Result<(Uuid, ProxyTokenScopes), StatusCode> {
+    match extract_user_id(app_state, cookies) {
+        Ok(user_id) => Ok((user_id, ProxyTokenScopes::default())),
+        Err(StatusCode::UNAUTHORIZED) => {
+            super::proxy_tokens::user_from_bearer(app_state, headers, peer)
+                .ok_or(StatusCode::UNAUTHORIZED)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Verify that a user can see a session, returning it with their effective role
 fn verify_session_access(
     conn: &mut diesel::pg::PgConnection,
     session_id: Uuid,
     user_id: Uuid,
-) -> Result<crate::models::Session, StatusCode> {
-    use crate::schema::{session_members, sessions};
-    sessions::table
-        .inner_join(session_members::table.on(session_members::session_id.eq(sessions::id)))
-        .filter(sessions::id.eq(session_id))
-        .filter(session_members::user_id.eq(user_id))
+) -> Result<(crate::models::Session, String), StatusCode> {
+    use crate::schema::sessions;
+    let role = super::helpers::session_role(conn, session_id, user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let session = sessions::table
+        .find(session_id)
         .select(crate::models::Session::as_select())
         .first::<crate::models::Session>(conn)
-        .map_err(|_| StatusCode::NOT_FOUND)
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok((session, role))
 }
 
 /// Create a new message for a session
@@ -92,14 +140,18 @@ pub async fn create_message(
         .get()
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // Verify the user has access to the session
-    let session = verify_session_access(&mut conn, session_id, current_user_id)?;
+    // Verify the user has access to the session and isn't read-only
+    let (session, role) = verify_session_access(&mut conn, session_id, current_user_id)?;
+    if !super::helpers::can_edit(&role) {
+        return Err(StatusCode::FORBIDDEN);
+    }
 
     let new_message = NewMessage {
         session_id,
         role: req.role,
         content: req.content,
         user_id: session.user_id,
+        produced_at: None,
     };
 
     let message: Message = diesel::insert_into(messages::table)
@@ -116,14 +168,17 @@ pub async fn create_message(
     Ok(Json(MessageResponse { message }))
 }
 
-/// List messages for a session
+/// List messages for a session, optionally only those after `after_seq`
 pub async fn list_messages(
     State(app_state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
     cookies: Cookies,
+    headers: HeaderMap,
     Path(session_id): Path<Uuid>,
+    Query(params): Query<ListMessagesParams>,
 ) -> Result<Json<MessagesListResponse>, StatusCode> {
     // Require authentication
-    let current_user_id = extract_user_id(&app_state, &cookies)?;
+    let (current_user_id, scopes) = extract_caller(&app_state, &cookies, &headers, peer)?;
 
     let mut conn = app_state
         .db_pool
@@ -131,16 +186,29 @@ pub async fn list_messages(
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     // Verify the user has access to the session
-    let _session = verify_session_access(&mut conn, session_id, current_user_id)?;
+    let (session, role) = verify_session_access(&mut conn, session_id, current_user_id)?;
+    if !scopes.allows_directory(&session.working_directory) {
+        return Err(StatusCode::FORBIDDEN);
+    }
 
-    let message_list: Vec<Message> = messages::table
+    let query = messages::table
         .filter(messages::session_id.eq(session_id))
-        .order(messages::created_at.asc())
-        .load(&mut conn)
-        .map_err(|e| {
-            error!("Failed to list messages: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        .into_boxed();
+    let query = match params.after_seq {
+        Some(after_seq) => query
+            .filter(messages::seq.gt(after_seq))
+            .order(messages::seq.asc()),
+        None => query.order(messages::created_at.asc()),
+    };
+    let mut message_list: Vec<Message> = query.load(&mut conn).map_err(|e| {
+        error!("Failed to list messages: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if let Some(mut redactor) = super::redaction::redactor_for(&mut conn, session_id, &role) {
+        for message in &mut message_list {
+            super::redaction::redact_stored(&mut redactor, message);
+        }
+    }
 
     let total = message_list.len() as i64;
 
@@ -149,3 +217,127 @@ pub async fn list_messages(
         total,
     }))
 }
+
+/// Send input to a session's Claude, as a web client's input box would.
+///
+/// For scripts and CI jobs that authenticate with a proxy token rather than
+/// speaking the WebSocket protocol. Claude's reply arrives as messages after
+/// the returned `after_seq`.
+pub async fn send_input(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(session_id): Path<Uuid>,
+    Json(req): Json<SendInputRequest>,
+) -> Result<Json<SendInputResponse>, (StatusCode, String)> {
+    let (current_user_id, scopes) = extract_caller(&app_state, &cookies, &headers, peer)
+        .map_err(|e| (e, "Not signed in".to_string()))?;
+    if scopes.read_only {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "This proxy token is read-only".to_string(),
+        ));
+    }
+    if req.content.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Input is empty".to_string()));
+    }
+
+    let mut conn = app_state.db_pool.get().map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database unavailable".to_string(),
+        )
+    })?;
+    let (session, role) = verify_session_access(&mut conn, session_id, current_user_id)
+        .map_err(|e| (e, "Session not found".to_string()))?;
+    if !scopes.allows_directory(&session.working_directory) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "This proxy token can't access sessions in that directory".to_string(),
+        ));
+    }
+    if !super::helpers::can_edit(&role) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "You have view-only access to this session".to_string(),
+        ));
+    }
+
+    let session_manager = &app_state.session_manager;
+    if let Some(kill_switch) = session_manager.kill_switch() {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!(
+                "Input not sent. All sessions are paused: {}",
+                kill_switch.reason
+            ),
+        ));
+    }
+    if !session_manager.is_connected(&session_id.to_string()) {
+        return Err((
+            StatusCode::CONFLICT,
+            "Session isn't connected to a proxy".to_string(),
+        ));
+    }
+    if let Err(driver) = session_manager.claim_driver(session_id, current_user_id) {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("{} is driving this session", driver),
+        ));
+    }
+    // Hand the connection back before the budget check and relay take their own
+    drop(conn);
+    if let Err(status) =
+        super::websocket::check_input_budget(&app_state.db_pool, session_manager, session_id)
+    {
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            format!("Input not sent. {}.", status.summary()),
+        ));
+    }
+
+    let mut conn = app_state.db_pool.get().map_err(|_| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Database unavailable".to_string(),
+        )
+    })?;
+    let after_seq = messages::table
+        .filter(messages::session_id.eq(session_id))
+        .select(diesel::dsl::max(messages::seq))
+        .first::<Option<i64>>(&mut conn)
+        .map_err(|e| {
+            error!("Failed to read latest message seq: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to read session".to_string(),
+            )
+        })?
+        .unwrap_or(0);
+    drop(conn);
+
+    info!("REST client sending input to session: {}", session_id);
+    super::websocket::record_operator_action(
+        &app_state,
+        session_id,
+        current_user_id,
+        OperatorAction::Input {
+            content: req.content.clone(),
+            send_mode: req.send_mode.unwrap_or_default(),
+        },
+    );
+    let input_seq = super::websocket::relay_input(
+        &app_state.db_pool,
+        session_manager,
+        session_id,
+        serde_json::Value::String(req.content),
+        req.send_mode,
+        None,
+    );
+
+    Ok(Json(SendInputResponse {
+        input_seq,
+        after_seq,
+    }))
+}
@@ -1,12 +1,38 @@
+pub mod activity;
 pub mod admin;
+pub mod annotations;
+pub mod artifacts;
 pub mod auth;
+pub mod budget;
+pub mod ci;
 pub mod config;
+pub mod daemon;
 pub mod device_flow;
 pub mod downloads;
+pub mod escalation;
+pub mod feedback;
+pub mod fork;
+pub mod guests;
 pub mod helpers;
+pub mod incidents;
+pub mod kill_switch;
+pub mod maintenance;
 pub mod messages;
+pub mod metering;
+pub mod notifications;
+pub mod onboarding;
+pub mod permission_history;
 pub mod proxy_tokens;
+pub mod push;
+pub mod reactions;
+pub mod redaction;
 pub mod retention;
+pub mod saved_searches;
+pub mod search;
 pub mod sessions;
+pub mod snapshots;
+pub mod stream;
+pub mod turns;
+pub mod usage;
 pub mod voice;
 pub mod websocket;
@@ -0,0 +1,128 @@
+//! Notification preferences
+//!
+//! Each user chooses which channels each notification event reaches them
+//! on, and gives the Telegram chat the bot should message. Users who never
+//! saved preferences get every event's defaults.
+
+use crate::schema::{notification_preferences, users};
+use crate::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use diesel::prelude::*;
+use shared::{NotificationPreferences, NotificationSettings};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+const SESSION_COOKIE_NAME: &str = "cc_session";
+
+/// Extract user_id from signed session cookie
+fn extract_user_id(app_state: &AppState, cookies: &Cookies) -> Result<Uuid, StatusCode> {
+    // In dev mode, allow unauthenticated access with test user
+    if app_state.dev_mode {
+        let mut conn = app_state
+            .db_pool
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        return users::table
+            .filter(users::email.eq("testing@testing.local"))
+            .select(users::id)
+            .first::<Uuid>(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let cookie = cookies
+        .signed(&app_state.cookie_key)
+        .get(SESSION_COOKIE_NAME)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    cookie.value().parse().map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+fn settings(app_state: &AppState, preferences: NotificationPreferences) -> NotificationSettings {
+    NotificationSettings {
+        preferences,
+        available: app_state
+            .session_manager
+            .notifiers
+            .as_ref()
+            .map(|n| n.available())
+            .unwrap_or_default(),
+    }
+}
+
+/// GET /api/notifications - The user's notification preferences and the
+/// channels this server delivers on
+pub async fn get_notifications(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<NotificationSettings>, StatusCode> {
+    let user_id = extract_user_id(&app_state, &cookies)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let saved = notification_preferences::table
+        .find(user_id)
+        .select(notification_preferences::preferences)
+        .first::<serde_json::Value>(&mut conn)
+        .optional()
+        .map_err(|e| {
+            error!("Failed to load notification preferences: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let preferences = saved
+        .and_then(|saved| serde_json::from_value(saved).ok())
+        .unwrap_or_default();
+
+    Ok(Json(settings(&app_state, preferences)))
+}
+
+/// PUT /api/notifications - Replace the user's notification preferences
+pub async fn update_notifications(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(mut preferences): Json<NotificationPreferences>,
+) -> Result<Json<NotificationSettings>, StatusCode> {
+    let user_id = extract_user_id(&app_state, &cookies)?;
+
+    preferences.telegram_chat_id = preferences
+        .telegram_chat_id
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty());
+    if let Err(reason) = preferences.validate() {
+        warn!(
+            "Rejected notification preferences for {}: {}",
+            user_id, reason
+        );
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let json = serde_json::to_value(&preferences).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    diesel::insert_into(notification_preferences::table)
+        .values((
+            notification_preferences::user_id.eq(user_id),
+            notification_preferences::preferences.eq(&json),
+        ))
+        .on_conflict(notification_preferences::user_id)
+        .do_update()
+        .set((
+            notification_preferences::preferences.eq(&json),
+            notification_preferences::updated_at.eq(diesel::dsl::now),
+        ))
+        .execute(&mut conn)
+        .map_err(|e| {
+            error!("Failed to save notification preferences: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(settings(&app_state, preferences)))
+}
@@ -0,0 +1,94 @@
+//! First-use checklist
+//!
+//! Reports which setup steps the signed-in user has completed, worked out
+//! from their tokens, sessions, and messages, so the dashboard can show new
+//! users what's left.
+
+use crate::schema::{messages, proxy_auth_tokens, sessions, users};
+use crate::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use diesel::prelude::*;
+use shared::OnboardingStatus;
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::error;
+use uuid::Uuid;
+
+const SESSION_COOKIE_NAME: &str = "cc_session";
+
+/// Extract user_id from signed session cookie
+fn extract_user_id(app_state: &AppState, cookies: &Cookies) -> Result<Uuid, StatusCode> {
+    // In dev mode, allow unauthenticated access with test user
+    if app_state.dev_mode {
+        let mut conn = app_state
+            .db_pool
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        return users::table
+            .filter(users::email.eq("testing@testing.local"))
+            .select(users::id)
+            .first::<Uuid>(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let cookie = cookies
+        .signed(&app_state.cookie_key)
+        .get(SESSION_COOKIE_NAME)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    cookie.value().parse().map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+fn load_status(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    voice_available: bool,
+) -> QueryResult<OnboardingStatus> {
+    let token_created = diesel::select(diesel::dsl::exists(
+        proxy_auth_tokens::table.filter(proxy_auth_tokens::user_id.eq(user_id)),
+    ))
+    .get_result(conn)?;
+    let proxy_connected = diesel::select(diesel::dsl::exists(
+        sessions::table.filter(sessions::user_id.eq(user_id)),
+    ))
+    .get_result(conn)?;
+    let session_streamed = diesel::select(diesel::dsl::exists(
+        messages::table
+            .filter(messages::user_id.eq(user_id))
+            .filter(messages::role.eq("assistant")),
+    ))
+    .get_result(conn)?;
+    let voice_enabled = users::table
+        .find(user_id)
+        .select(users::voice_enabled)
+        .first(conn)?;
+
+    Ok(OnboardingStatus {
+        token_created,
+        proxy_connected,
+        session_streamed,
+        voice_available,
+        voice_enabled,
+    })
+}
+
+/// GET /api/onboarding - The caller's progress through first-use setup
+pub async fn get_onboarding(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<OnboardingStatus>, StatusCode> {
+    let user_id = extract_user_id(&app_state, &cookies)?;
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let voice_available = app_state.speech.is_some();
+    load_status(&mut conn, user_id, voice_available)
+        .map(Json)
+        .map_err(|e| {
+            error!("Failed to load onboarding status: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
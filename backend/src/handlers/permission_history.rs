@@ -0,0 +1,228 @@
+//! Audit log of permission requests and how each one was answered.
+//!
+//! A row is written when the proxy forwards a request and completed when the
+//! decision comes back, so requests left waiting during an unattended stretch
+//! show up too. Requests the proxy answers from a remembered rule never reach
+//! the web UI and are recorded already decided. Escalation steps (see
+//! [`super::escalation`]) are recorded on the same row.
+
+use crate::models::{NewPermissionAuditEntry, PermissionAuditEntry};
+use crate::schema::{permission_audit_log, users};
+use crate::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use diesel::prelude::*;
+use shared::{DecisionChannel, PermissionDecisionInfo};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::error;
+use uuid::Uuid;
+
+const SESSION_COOKIE_NAME: &str = "cc_session";
+
+/// Most entries returned for one session, newest first
+const MAX_HISTORY_ENTRIES: i64 = 500;
+
+/// Extract user_id from signed session cookie
+fn extract_user_id(app_state: &AppState, cookies: &Cookies) -> Result<Uuid, StatusCode> {
+    // In dev mode, allow unauthenticated access with test user
+    if app_state.dev_mode {
+        let mut conn = app_state
+            .db_pool
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        return users::table
+            .filter(users::email.eq("testing@testing.local"))
+            .select(users::id)
+            .first::<Uuid>(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let cookie = cookies
+        .signed(&app_state.cookie_key)
+        .get(SESSION_COOKIE_NAME)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    cookie.value().parse().map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+/// How a request was answered
+pub struct Decision {
+    pub allowed: bool,
+    pub channel: DecisionChannel,
+    pub decided_by: Option<Uuid>,
+    pub detail: Option<String>,
+}
+
+/// Record a permission request forwarded to the web UI
+pub fn record_request(
+    db_pool: &crate::db::DbPool,
+    session_id: Uuid,
+    request_id: &str,
+    tool_name: &str,
+    input: &serde_json::Value,
+) {
+    let Ok(mut conn) = db_pool.get() else {
+        error!("Failed to get DB connection to record permission request");
+        return;
+    };
+    let entry = NewPermissionAuditEntry {
+        session_id,
+        request_id: request_id.to_string(),
+        tool_name: tool_name.to_string(),
+        input: input.clone(),
+    };
+    if let Err(e) = diesel::insert_into(permission_audit_log::table)
+        .values(&entry)
+        .execute(&mut conn)
+    {
+        error!("Failed to record permission request: {}", e);
+    }
+}
+
+/// Complete the entry for an answered request. Requests answered before this
+/// log existed have no entry and are skipped.
+pub fn record_decision(
+    db_pool: &crate::db::DbPool,
+    session_id: Uuid,
+    request_id: &str,
+    decision: Decision,
+) {
+    let Ok(mut conn) = db_pool.get() else {
+        error!("Failed to get DB connection to record permission decision");
+        return;
+    };
+    let result = diesel::update(
+        permission_audit_log::table
+            .filter(permission_audit_log::session_id.eq(session_id))
+            .filter(permission_audit_log::request_id.eq(request_id))
+            .filter(permission_audit_log::decided_at.is_null()),
+    )
+    .set((
+        permission_audit_log::allowed.eq(Some(decision.allowed)),
+        permission_audit_log::channel.eq(Some(decision.channel.as_str())),
+        permission_audit_log::decided_by.eq(decision.decided_by),
+        permission_audit_log::detail.eq(decision.detail),
+        permission_audit_log::decided_at.eq(diesel::dsl::now.nullable()),
+    ))
+    .execute(&mut conn);
+    if let Err(e) = result {
+        error!("Failed to record permission decision: {}", e);
+    }
+}
+
+/// Note that the session's escalation contact was told about a request
+pub fn record_escalation(
+    db_pool: &crate::db::DbPool,
+    session_id: Uuid,
+    request_id: &str,
+    escalated_to: &str,
+) {
+    let Ok(mut conn) = db_pool.get() else {
+        error!("Failed to get DB connection to record permission escalation");
+        return;
+    };
+    let result = diesel::update(
+        permission_audit_log::table
+            .filter(permission_audit_log::session_id.eq(session_id))
+            .filter(permission_audit_log::request_id.eq(request_id))
+            .filter(permission_audit_log::decided_at.is_null()),
+    )
+    .set((
+        permission_audit_log::escalated_at.eq(diesel::dsl::now.nullable()),
+        permission_audit_log::escalated_to.eq(Some(escalated_to)),
+    ))
+    .execute(&mut conn);
+    if let Err(e) = result {
+        error!("Failed to record permission escalation: {}", e);
+    }
+}
+
+/// Record a request the proxy answered from a remembered rule
+pub fn record_rule_decision(
+    db_pool: &crate::db::DbPool,
+    session_id: Uuid,
+    request_id: &str,
+    tool_name: &str,
+    input: &serde_json::Value,
+    decision: Decision,
+) {
+    record_request(db_pool, session_id, request_id, tool_name, input);
+    record_decision(db_pool, session_id, request_id, decision);
+}
+
+fn to_info(entry: PermissionAuditEntry, names: &HashMap<Uuid, String>) -> PermissionDecisionInfo {
+    PermissionDecisionInfo {
+        request_id: entry.request_id,
+        tool_name: entry.tool_name,
+        input: entry.input,
+        requested_at: entry.requested_at.and_utc().to_rfc3339(),
+        allowed: entry.allowed,
+        channel: entry.channel.as_deref().and_then(DecisionChannel::parse),
+        decided_by: entry.decided_by.and_then(|id| names.get(&id).cloned()),
+        detail: entry.detail,
+        decided_at: entry.decided_at.map(|at| at.and_utc().to_rfc3339()),
+        escalated_at: entry.escalated_at.map(|at| at.and_utc().to_rfc3339()),
+        escalated_to: entry.escalated_to,
+    }
+}
+
+/// List a session's permission requests and decisions, newest first
+pub async fn list_permission_history(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(session_id): Path<Uuid>,
+) -> Result<Json<Vec<PermissionDecisionInfo>>, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let role = super::helpers::session_role(&mut conn, session_id, current_user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let redactor = super::redaction::redactor_for(&mut conn, session_id, &role);
+
+    let mut entries: Vec<PermissionAuditEntry> = permission_audit_log::table
+        .filter(permission_audit_log::session_id.eq(session_id))
+        .order(permission_audit_log::requested_at.desc())
+        .limit(MAX_HISTORY_ENTRIES)
+        .select(PermissionAuditEntry::as_select())
+        .load(&mut conn)
+        .map_err(|e| {
+            error!("Failed to list permission history: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if let Some(redactor) = &redactor {
+        for entry in &mut entries {
+            redactor.redact_input(&entry.tool_name, &mut entry.input);
+        }
+    }
+
+    let decider_ids: Vec<Uuid> = entries.iter().filter_map(|e| e.decided_by).collect();
+    let names: HashMap<Uuid, String> = users::table
+        .filter(users::id.eq_any(&decider_ids))
+        .select((users::id, users::name, users::email))
+        .load::<(Uuid, Option<String>, String)>(&mut conn)
+        .map_err(|e| {
+            error!("Failed to load permission deciders: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .into_iter()
+        .map(|(id, name, email)| (id, name.unwrap_or(email)))
+        .collect();
+
+    Ok(Json(
+        entries
+            .into_iter()
+            .map(|entry| to_info(entry, &names))
+            .collect(),
+    ))
+}
@@ -5,21 +5,23 @@
 
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
     Json,
 };
 use diesel::prelude::*;
 use shared::{
-    CreateProxyTokenRequest, CreateProxyTokenResponse, ProxyInitConfig, ProxyTokenInfo,
-    ProxyTokenListResponse,
+    CreateProxyTokenRequest, CreateProxyTokenResponse, ErrorCode, ProxyInitConfig,
+    ProxyTokenDetail, ProxyTokenInfo, ProxyTokenListResponse, ProxyTokenScopes, ProxyTokenSession,
+    UserRole,
 };
+use std::net::SocketAddr;
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
 use crate::{
     jwt::{create_proxy_token, hash_token},
-    models::{NewProxyAuthToken, ProxyAuthToken, User},
+    models::{NewProxyAuthToken, ProxyAuthToken, Session, User},
     schema::proxy_auth_tokens,
     AppState,
 };
@@ -42,6 +44,13 @@ pub async fn create_token(
         StatusCode::NOT_FOUND
     })?;
 
+    // Viewers watch shared sessions but can't run their own
+    if user.role() == UserRole::Viewer {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let scopes = normalize_scopes(req.scopes).ok_or(StatusCode::BAD_REQUEST)?;
+
     // Generate token ID
     let token_id = Uuid::new_v4();
 
@@ -71,6 +80,7 @@ pub async fn create_token(
         name: req.name.clone(),
         token_hash,
         expires_at: expires_at.naive_utc(),
+        scopes: serde_json::to_value(&scopes).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
     };
 
     let saved_token: ProxyAuthToken = diesel::insert_into(proxy_auth_tokens::table)
@@ -121,23 +131,186 @@ pub async fn list_tokens(
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
-    let token_infos: Vec<ProxyTokenInfo> = tokens
-        .into_iter()
-        .map(|t| ProxyTokenInfo {
-            id: t.id,
-            name: t.name,
-            created_at: t.created_at.and_utc().to_rfc3339(),
-            last_used_at: t.last_used_at.map(|dt| dt.and_utc().to_rfc3339()),
-            expires_at: t.expires_at.and_utc().to_rfc3339(),
-            revoked: t.revoked,
-        })
-        .collect();
+    let token_infos: Vec<ProxyTokenInfo> = tokens.into_iter().map(token_info).collect();
 
     Ok(Json(ProxyTokenListResponse {
         tokens: token_infos,
     }))
 }
 
+fn token_info(t: ProxyAuthToken) -> ProxyTokenInfo {
+    ProxyTokenInfo {
+        id: t.id,
+        name: t.name,
+        created_at: t.created_at.and_utc().to_rfc3339(),
+        last_used_at: t.last_used_at.map(|dt| dt.and_utc().to_rfc3339()),
+        expires_at: t.expires_at.and_utc().to_rfc3339(),
+        revoked: t.revoked,
+        last_used_host: t.last_used_host,
+        scopes: parse_scopes(t.scopes),
+    }
+}
+
+/// Trim and de-duplicate requested scopes; `None` if they make no sense
+fn normalize_scopes(mut scopes: ProxyTokenScopes) -> Option<ProxyTokenScopes> {
+    let mut directories: Vec<String> = Vec::new();
+    for dir in &scopes.directories {
+        let dir = dir.trim();
+        if dir.is_empty() {
+            continue;
+        }
+        if dir.split('/').any(|part| part == "..") {
+            return None;
+        }
+        if !directories.iter().any(|d| d == dir) {
+            directories.push(dir.to_string());
+        }
+    }
+    scopes.directories = directories;
+    if scopes.max_sessions == Some(0) {
+        return None;
+    }
+    if let Some(categories) = &mut scopes.tool_categories {
+        categories.sort_by_key(|c| c.as_str());
+        categories.dedup();
+    }
+    Some(scopes)
+}
+
+/// Stored scopes. These are only ever written by [`create_token`], but if
+/// one can't be read the token gets nothing rather than everything.
+fn parse_scopes(value: serde_json::Value) -> ProxyTokenScopes {
+    serde_json::from_value(value).unwrap_or_else(|e| {
+        error!("Unreadable proxy token scopes, denying everything: {}", e);
+        ProxyTokenScopes {
+            read_only: true,
+            tool_categories: Some(Vec::new()),
+            ..Default::default()
+        }
+    })
+}
+
+/// The id and scopes of a token we issued, or `None` for a token that
+/// isn't ours
+pub fn token_scopes(
+    conn: &mut diesel::pg::PgConnection,
+    token: &str,
+) -> diesel::QueryResult<Option<(Uuid, ProxyTokenScopes)>> {
+    Ok(proxy_auth_tokens::table
+        .filter(proxy_auth_tokens::token_hash.eq(hash_token(token)))
+        .select((proxy_auth_tokens::id, proxy_auth_tokens::scopes))
+        .first::<(Uuid, serde_json::Value)>(conn)
+        .optional()?
+        .map(|(id, scopes)| (id, parse_scopes(scopes))))
+}
+
+/// Whether a proxy using a token with `scopes` may register `session_id`
+/// in `working_directory`
+pub fn check_register_scopes(
+    conn: &mut diesel::pg::PgConnection,
+    token_id: Uuid,
+    scopes: &ProxyTokenScopes,
+    session_id: Uuid,
+    working_directory: &str,
+) -> Result<(), (ErrorCode, String)> {
+    if scopes.read_only {
+        return Err((
+            ErrorCode::AccessDenied,
+            "This proxy token is read-only: it can watch sessions but not run them".to_string(),
+        ));
+    }
+    if !scopes.allows_directory(working_directory) {
+        return Err((
+            ErrorCode::AccessDenied,
+            format!(
+                "This proxy token can't run sessions in {} (allowed: {})",
+                working_directory,
+                scopes.directories.join(", ")
+            ),
+        ));
+    }
+    if let Some(max) = scopes.max_sessions {
+        use crate::schema::sessions;
+        let running: i64 = sessions::table
+            .filter(sessions::proxy_token_id.eq(token_id))
+            .filter(sessions::status.eq("active"))
+            .filter(sessions::id.ne(session_id))
+            .count()
+            .get_result(conn)
+            .map_err(|e| {
+                // Without a count the limit can't be enforced, so refuse
+                error!("Failed to count sessions for token: {}", e);
+                (ErrorCode::Internal, "database unavailable".to_string())
+            })?;
+        if running >= max as i64 {
+            warn!(
+                "Token {} already has {} of {} sessions running",
+                token_id, running, max
+            );
+            return Err((
+                ErrorCode::QuotaExceeded,
+                format!(
+                    "This proxy token already has {} of {} sessions running",
+                    running, max
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Sessions shown when inspecting a token
+const MAX_TOKEN_SESSIONS: i64 = 50;
+
+/// GET /api/proxy-tokens/:id - A token and the sessions opened with it
+pub async fn get_token(
+    State(app_state): State<Arc<AppState>>,
+    user_id: Uuid,
+    Path(token_id): Path<Uuid>,
+) -> Result<Json<ProxyTokenDetail>, StatusCode> {
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let token: ProxyAuthToken = proxy_auth_tokens::table
+        .filter(proxy_auth_tokens::id.eq(token_id))
+        .filter(proxy_auth_tokens::user_id.eq(user_id))
+        .first(&mut conn)
+        .optional()
+        .map_err(|e| {
+            error!("Failed to load token: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    use crate::schema::sessions;
+    let sessions: Vec<Session> = sessions::table
+        .filter(sessions::proxy_token_id.eq(token_id))
+        .order(sessions::last_activity.desc())
+        .limit(MAX_TOKEN_SESSIONS)
+        .select(Session::as_select())
+        .load(&mut conn)
+        .map_err(|e| {
+            error!("Failed to load sessions for token: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(ProxyTokenDetail {
+        token: token_info(token),
+        sessions: sessions
+            .into_iter()
+            .map(|s| ProxyTokenSession {
+                id: s.id,
+                session_name: s.session_name,
+                working_directory: s.working_directory,
+                status: s.status,
+                last_activity: s.last_activity.and_utc().to_rfc3339(),
+            })
+            .collect(),
+    }))
+}
+
 /// DELETE /api/proxy-tokens/:id - Revoke a token
 pub async fn revoke_token(
     State(app_state): State<Arc<AppState>>,
@@ -166,7 +339,29 @@ pub async fn revoke_token(
         return Err(StatusCode::NOT_FOUND);
     }
 
-    info!("Revoked proxy token {}", token_id);
+    // Cut off proxies still connected with the token
+    use crate::schema::sessions;
+    let session_ids: Vec<Uuid> = sessions::table
+        .filter(sessions::proxy_token_id.eq(token_id))
+        .select(sessions::id)
+        .load(&mut conn)
+        .unwrap_or_else(|e| {
+            error!("Failed to find sessions for revoked token: {}", e);
+            Vec::new()
+        });
+    let disconnected = session_ids
+        .iter()
+        .filter(|id| {
+            app_state
+                .session_manager
+                .disconnect_proxy(&id.to_string(), "This proxy token was revoked")
+        })
+        .count();
+
+    info!(
+        "Revoked proxy token {}, disconnecting {} proxies",
+        token_id, disconnected
+    );
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -219,6 +414,11 @@ pub fn verify_and_get_user(
         return Err(StatusCode::FORBIDDEN);
     }
 
+    if user.role() == UserRole::Viewer {
+        error!("Token belongs to viewer account: {}", user.email);
+        return Err(StatusCode::FORBIDDEN);
+    }
+
     // Update last_used_at
     let _ = diesel::update(proxy_auth_tokens::table.find(db_token.id))
         .set(proxy_auth_tokens::last_used_at.eq(diesel::dsl::now))
@@ -227,6 +427,88 @@ pub fn verify_and_get_user(
     Ok((claims.sub, claims.email))
 }
 
+/// Whether `token` is one we issued that has since been revoked
+pub fn is_revoked(conn: &mut diesel::pg::PgConnection, token: &str) -> bool {
+    proxy_auth_tokens::table
+        .filter(proxy_auth_tokens::token_hash.eq(hash_token(token)))
+        .select(proxy_auth_tokens::revoked)
+        .first::<bool>(conn)
+        .unwrap_or(false)
+}
+
+/// Where a request came from: the first `X-Forwarded-For` hop when behind a
+/// reverse proxy, otherwise the peer address
+pub fn client_host(headers: &HeaderMap, peer: SocketAddr) -> String {
+    headers
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(str::trim)
+        .filter(|host| !host.is_empty())
+        .map(|host| host.chars().take(255).collect())
+        .unwrap_or_else(|| peer.ip().to_string())
+}
+
+/// Note that a token was just used from `host`, and link the session it
+/// registered (if any) to it
+pub fn record_token_use(
+    conn: &mut diesel::pg::PgConnection,
+    token: &str,
+    host: &str,
+    session_id: Option<Uuid>,
+) {
+    let token_id: Option<Uuid> = match diesel::update(
+        proxy_auth_tokens::table.filter(proxy_auth_tokens::token_hash.eq(hash_token(token))),
+    )
+    .set((
+        proxy_auth_tokens::last_used_at.eq(diesel::dsl::now),
+        proxy_auth_tokens::last_used_host.eq(host),
+    ))
+    .returning(proxy_auth_tokens::id)
+    .get_result(conn)
+    .optional()
+    {
+        Ok(token_id) => token_id,
+        Err(e) => {
+            error!("Failed to record token use: {}", e);
+            return;
+        }
+    };
+
+    if let (Some(token_id), Some(session_id)) = (token_id, session_id) {
+        use crate::schema::sessions;
+        if let Err(e) = diesel::update(sessions::table.find(session_id))
+            .set(sessions::proxy_token_id.eq(token_id))
+            .execute(conn)
+        {
+            error!("Failed to link session {} to its token: {}", session_id, e);
+        }
+    }
+}
+
+/// Authenticate an `Authorization: Bearer <proxy token>` header, returning
+/// the token's user and scopes
+pub fn user_from_bearer(
+    app_state: &AppState,
+    headers: &HeaderMap,
+    peer: SocketAddr,
+) -> Option<(Uuid, ProxyTokenScopes)> {
+    let token = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?
+        .trim();
+    let mut conn = app_state.db_pool.get().ok()?;
+    let (user_id, _email) = verify_and_get_user(app_state, &mut conn, token).ok()?;
+    let host = client_host(headers, peer);
+    record_token_use(&mut conn, token, &host, None);
+    let (_, scopes) = token_scopes(&mut conn, token)
+        .map_err(|e| error!("Failed to load proxy token scopes: {}", e))
+        .ok()??;
+    Some((user_id, scopes))
+}
+
 // ============================================================================
 // Wrapper handlers that extract user_id from session
 // ============================================================================
@@ -252,6 +534,16 @@ pub async fn list_tokens_handler(
     list_tokens(State(app_state), user_id).await
 }
 
+/// Wrapper for get_token that extracts user from session
+pub async fn get_token_handler(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(token_id): Path<Uuid>,
+) -> Result<Json<ProxyTokenDetail>, StatusCode> {
+    let user_id = get_user_id_from_session(&app_state, &cookies).await?;
+    get_token(State(app_state), user_id, Path(token_id)).await
+}
+
 /// Wrapper for revoke_token that extracts user from session
 pub async fn revoke_token_handler(
     State(app_state): State<Arc<AppState>>,
@@ -297,3 +589,53 @@ async fn get_user_id_from_session(
 
     Ok(user_id)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_scopes() {
+        let scopes = normalize_scopes(ProxyTokenScopes {
+            directories: vec![" /work/a ".into(), "".into(), "/work/a".into()],
+            tool_categories: Some(vec![
+                shared::ToolCategory::Edit,
+                shared::ToolCategory::Read,
+                shared::ToolCategory::Edit,
+            ]),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(scopes.directories, vec!["/work/a"]);
+        assert_eq!(scopes.tool_categories.unwrap().len(), 2);
+
+        assert!(normalize_scopes(ProxyTokenScopes {
+            directories: vec!["/work/../etc".into()],
+            ..Default::default()
+        })
+        .is_none());
+        assert!(normalize_scopes(ProxyTokenScopes {
+            max_sessions: Some(0),
+            ..Default::default()
+        })
+        .is_none());
+    }
+
+    #[test]
+    fn test_unreadable_scopes_deny() {
+        let scopes = parse_scopes(serde_json::json!({"read_only": "yes"}));
+        assert!(scopes.read_only);
+        assert!(!scopes.allows_tool("Read"));
+        assert!(parse_scopes(serde_json::json!({})).is_unrestricted());
+    }
+
+    #[test]
+    fn test_client_host_prefers_forwarded_for() {
+        let peer: SocketAddr = "10.0.0.2:51234".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        assert_eq!(client_host(&headers, peer), "10.0.0.2");
+
+        headers.insert("x-forwarded-for", "203.0.113.7, 10.0.0.1".parse().unwrap());
+        assert_eq!(client_host(&headers, peer), "203.0.113.7");
+    }
+}
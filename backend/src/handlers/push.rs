@@ -0,0 +1,126 @@
+//! Browser push subscriptions
+//!
+//! The settings page subscribes a browser with the VAPID key from
+//! `/api/config` and registers the subscription here. Registering an
+//! endpoint that's already known moves it to the current user, since the
+//! browser may have been signed in as someone else before.
+
+use crate::models::NewPushSubscription;
+use crate::schema::{push_subscriptions, users};
+use crate::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use diesel::prelude::*;
+use shared::{PushSubscriptionInfo, UnsubscribePushRequest};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::{error, info};
+use uuid::Uuid;
+
+const SESSION_COOKIE_NAME: &str = "cc_session";
+
+/// Push services use HTTPS URLs well under this length
+const MAX_ENDPOINT_LEN: usize = 2048;
+
+/// Extract user_id from signed session cookie
+fn extract_user_id(app_state: &AppState, cookies: &Cookies) -> Result<Uuid, StatusCode> {
+    // In dev mode, allow unauthenticated access with test user
+    if app_state.dev_mode {
+        let mut conn = app_state
+            .db_pool
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        return users::table
+            .filter(users::email.eq("testing@testing.local"))
+            .select(users::id)
+            .first::<Uuid>(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let cookie = cookies
+        .signed(&app_state.cookie_key)
+        .get(SESSION_COOKIE_NAME)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    cookie.value().parse().map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+fn valid_subscription(info: &PushSubscriptionInfo) -> bool {
+    info.endpoint.starts_with("https://")
+        && info.endpoint.len() <= MAX_ENDPOINT_LEN
+        && !info.keys.p256dh.is_empty()
+        && !info.keys.auth.is_empty()
+}
+
+/// POST /api/push/subscriptions - Register this browser for notifications
+pub async fn subscribe(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(info): Json<PushSubscriptionInfo>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&app_state, &cookies)?;
+    if app_state.session_manager.push.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    if !valid_subscription(&info) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    diesel::insert_into(push_subscriptions::table)
+        .values(&NewPushSubscription {
+            user_id,
+            endpoint: info.endpoint.clone(),
+            p256dh: info.keys.p256dh.clone(),
+            auth: info.keys.auth.clone(),
+        })
+        .on_conflict(push_subscriptions::endpoint)
+        .do_update()
+        .set((
+            push_subscriptions::user_id.eq(user_id),
+            push_subscriptions::p256dh.eq(&info.keys.p256dh),
+            push_subscriptions::auth.eq(&info.keys.auth),
+        ))
+        .execute(&mut conn)
+        .map_err(|e| {
+            error!("Failed to store push subscription: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    info!(
+        "User {} subscribed a browser to push notifications",
+        user_id
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /api/push/subscriptions - Stop notifying this browser
+pub async fn unsubscribe(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(request): Json<UnsubscribePushRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = extract_user_id(&app_state, &cookies)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    diesel::delete(
+        push_subscriptions::table
+            .filter(push_subscriptions::endpoint.eq(&request.endpoint))
+            .filter(push_subscriptions::user_id.eq(user_id)),
+    )
+    .execute(&mut conn)
+    .map_err(|e| {
+        error!("Failed to delete push subscription: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,205 @@
+use crate::models::{MessageReaction, NewMessageReaction};
+use crate::schema::{message_reactions, messages, users};
+use crate::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use diesel::prelude::*;
+use shared::{
+    MessageReactionsInfo, ProxyMessage, ReactionSummary, ToggleReactionRequest, REACTION_EMOJIS,
+};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::error;
+use uuid::Uuid;
+
+const SESSION_COOKIE_NAME: &str = "cc_session";
+
+/// Extract user_id from signed session cookie
+fn extract_user_id(app_state: &AppState, cookies: &Cookies) -> Result<Uuid, StatusCode> {
+    // In dev mode, allow unauthenticated access with test user
+    if app_state.dev_mode {
+        let mut conn = app_state
+            .db_pool
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        return users::table
+            .filter(users::email.eq("testing@testing.local"))
+            .select(users::id)
+            .first::<Uuid>(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let cookie = cookies
+        .signed(&app_state.cookie_key)
+        .get(SESSION_COOKIE_NAME)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    cookie.value().parse().map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+/// Verify that a user can see a session (any role may react)
+fn verify_membership(
+    conn: &mut diesel::pg::PgConnection,
+    session_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), StatusCode> {
+    super::helpers::session_role(conn, session_id, user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(|_| ())
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Load reactions for a session (or a single message within it), grouped by message and emoji
+fn load_reactions(
+    conn: &mut diesel::pg::PgConnection,
+    session_id: Uuid,
+    message_id: Option<Uuid>,
+) -> Result<Vec<MessageReactionsInfo>, diesel::result::Error> {
+    let mut query = message_reactions::table
+        .inner_join(users::table.on(users::id.eq(message_reactions::user_id)))
+        .filter(message_reactions::session_id.eq(session_id))
+        .into_boxed();
+    if let Some(message_id) = message_id {
+        query = query.filter(message_reactions::message_id.eq(message_id));
+    }
+
+    let rows: Vec<(MessageReaction, String, Option<String>)> = query
+        .order(message_reactions::created_at.asc())
+        .select((MessageReaction::as_select(), users::email, users::name))
+        .load(conn)?;
+
+    // message_id -> emoji -> users, keeping the first-seen emoji order per message
+    let mut grouped: BTreeMap<Uuid, Vec<ReactionSummary>> = BTreeMap::new();
+    for (reaction, email, name) in rows {
+        let display = name.unwrap_or(email);
+        let summaries = grouped.entry(reaction.message_id).or_default();
+        match summaries.iter_mut().find(|s| s.emoji == reaction.emoji) {
+            Some(summary) => summary.users.push(display),
+            None => summaries.push(ReactionSummary {
+                emoji: reaction.emoji,
+                users: vec![display],
+            }),
+        }
+    }
+
+    Ok(grouped
+        .into_iter()
+        .map(|(message_id, reactions)| MessageReactionsInfo {
+            message_id,
+            reactions,
+        })
+        .collect())
+}
+
+/// List all reactions in a session
+pub async fn list_reactions(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(session_id): Path<Uuid>,
+) -> Result<Json<Vec<MessageReactionsInfo>>, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    verify_membership(&mut conn, session_id, current_user_id)?;
+
+    let reactions = load_reactions(&mut conn, session_id, None).map_err(|e| {
+        error!("Failed to load reactions: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(reactions))
+}
+
+/// Toggle the current user's reaction on a message.
+///
+/// Adds the reaction if the user hasn't left it yet, removes it otherwise, then
+/// broadcasts the updated reactions to everyone viewing the session.
+pub async fn toggle_reaction(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path((session_id, message_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<ToggleReactionRequest>,
+) -> Result<Json<MessageReactionsInfo>, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    if !REACTION_EMOJIS.contains(&req.emoji.as_str()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    verify_membership(&mut conn, session_id, current_user_id)?;
+
+    // The message must belong to this session
+    messages::table
+        .filter(messages::id.eq(message_id))
+        .filter(messages::session_id.eq(session_id))
+        .select(messages::id)
+        .first::<Uuid>(&mut conn)
+        .optional()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let removed = diesel::delete(
+        message_reactions::table
+            .filter(message_reactions::message_id.eq(message_id))
+            .filter(message_reactions::user_id.eq(current_user_id))
+            .filter(message_reactions::emoji.eq(&req.emoji)),
+    )
+    .execute(&mut conn)
+    .map_err(|e| {
+        error!("Failed to remove reaction: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if removed == 0 {
+        diesel::insert_into(message_reactions::table)
+            .values(&NewMessageReaction {
+                message_id,
+                session_id,
+                user_id: current_user_id,
+                emoji: req.emoji,
+            })
+            .on_conflict_do_nothing()
+            .execute(&mut conn)
+            .map_err(|e| {
+                error!("Failed to add reaction: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+    }
+
+    let reactions = load_reactions(&mut conn, session_id, Some(message_id))
+        .map_err(|e| {
+            error!("Failed to load reactions: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .pop()
+        .map(|info| info.reactions)
+        .unwrap_or_default();
+
+    app_state.session_manager.broadcast_to_web_clients(
+        &session_id.to_string(),
+        ProxyMessage::MessageReactions {
+            session_id,
+            message_id,
+            reactions: reactions.clone(),
+        },
+    );
+
+    Ok(Json(MessageReactionsInfo {
+        message_id,
+        reactions,
+    }))
+}
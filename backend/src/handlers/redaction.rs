@@ -0,0 +1,173 @@
+//! Redaction of sensitive tool output for viewers.
+//!
+//! Session owners mark the whole session, or particular tools, as sensitive.
+//! Everything sent to a member with the viewer role then passes through a
+//! [`Redactor`]: live output and history on the WebSocket, the SSE stream,
+//! the message list, and the permission history. Owners and editors are
+//! never redacted.
+
+use crate::models::{Message, RedactionPolicyRow};
+use crate::schema::{redaction_policies, users};
+use crate::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use diesel::prelude::*;
+use shared::{RedactionPolicy, Redactor};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::{error, info};
+use uuid::Uuid;
+
+const SESSION_COOKIE_NAME: &str = "cc_session";
+
+/// Extract user_id from signed session cookie
+fn extract_user_id(app_state: &AppState, cookies: &Cookies) -> Result<Uuid, StatusCode> {
+    // In dev mode, allow unauthenticated access with test user
+    if app_state.dev_mode {
+        let mut conn = app_state
+            .db_pool
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        return users::table
+            .filter(users::email.eq("testing@testing.local"))
+            .select(users::id)
+            .first::<Uuid>(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let cookie = cookies
+        .signed(&app_state.cookie_key)
+        .get(SESSION_COOKIE_NAME)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    cookie.value().parse().map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+/// The caller's role in a session, or 404 if they can't see it
+fn member_role(
+    conn: &mut PgConnection,
+    session_id: Uuid,
+    user_id: Uuid,
+) -> Result<String, StatusCode> {
+    super::helpers::session_role(conn, session_id, user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+fn load_policy(conn: &mut PgConnection, session_id: Uuid) -> QueryResult<RedactionPolicy> {
+    let row = redaction_policies::table
+        .find(session_id)
+        .select(RedactionPolicyRow::as_select())
+        .first(conn)
+        .optional()?;
+    Ok(row
+        .map(|row| RedactionPolicy {
+            whole_session: row.whole_session,
+            tools: row.tools,
+        })
+        .unwrap_or_default())
+}
+
+/// A redactor for someone with `role` in the session, or `None` if they see
+/// everything. A policy that can't be read redacts everything rather than
+/// nothing.
+pub fn redactor_for(conn: &mut PgConnection, session_id: Uuid, role: &str) -> Option<Redactor> {
+    if super::helpers::can_edit(role) {
+        return None;
+    }
+    let policy = load_policy(conn, session_id).unwrap_or_else(|e| {
+        error!("Failed to load redaction policy for {}: {}", session_id, e);
+        RedactionPolicy {
+            whole_session: true,
+            tools: Vec::new(),
+        }
+    });
+    (!policy.is_empty()).then(|| Redactor::new(policy))
+}
+
+/// Redact a stored message's content in place
+pub fn redact_stored(redactor: &mut Redactor, message: &mut Message) {
+    let mut content = super::websocket::stored_content(message);
+    if redactor.apply(&mut content) {
+        message.content = content.to_string();
+    }
+}
+
+/// Get a session's redaction policy (any member)
+pub async fn get_redaction_policy(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(session_id): Path<Uuid>,
+) -> Result<Json<RedactionPolicy>, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    member_role(&mut conn, session_id, current_user_id)?;
+
+    load_policy(&mut conn, session_id).map(Json).map_err(|e| {
+        error!("Failed to load redaction policy: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Replace a session's redaction policy (owner only). A policy that redacts
+/// nothing removes it. Viewers already watching pick it up when they next
+/// connect.
+pub async fn update_redaction_policy(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(session_id): Path<Uuid>,
+    Json(policy): Json<RedactionPolicy>,
+) -> Result<Json<RedactionPolicy>, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if member_role(&mut conn, session_id, current_user_id)? != "owner" {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let policy = policy.normalized();
+    if policy.is_empty() {
+        diesel::delete(redaction_policies::table.find(session_id))
+            .execute(&mut conn)
+            .map_err(|e| {
+                error!("Failed to clear redaction policy: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        return Ok(Json(RedactionPolicy::default()));
+    }
+
+    let row = RedactionPolicyRow {
+        session_id,
+        whole_session: policy.whole_session,
+        tools: policy.tools.clone(),
+    };
+    diesel::insert_into(redaction_policies::table)
+        .values(&row)
+        .on_conflict(redaction_policies::session_id)
+        .do_update()
+        .set((&row, redaction_policies::updated_at.eq(diesel::dsl::now)))
+        .execute(&mut conn)
+        .map_err(|e| {
+            error!("Failed to save redaction policy: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    info!(
+        "Redaction policy for session {} updated by {}",
+        session_id, current_user_id
+    );
+    Ok(Json(policy))
+}
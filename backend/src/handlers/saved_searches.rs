@@ -0,0 +1,333 @@
+//! Saved searches and search alerts.
+//!
+//! Users save queries from the search page. A saved search with `alert` set
+//! is re-run by a background task over the messages stored since its last
+//! check; when something new matches, the owner's open clients get a
+//! `SearchAlert`. Queries are validated with the same parser as
+//! `GET /api/search`, so a 400 carries a message for the search box.
+
+use crate::models::{NewSavedSearch, SavedSearchRow};
+use crate::schema::{saved_searches, users};
+use crate::search::{self, SearchQuery};
+use crate::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use shared::{ProxyMessage, SaveSearchRequest, SavedSearch, UpdateSavedSearchRequest};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+const SESSION_COOKIE_NAME: &str = "cc_session";
+
+/// How often alerting searches are evaluated
+pub const ALERT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Messages newer than this are left for the next run, so one whose insert
+/// is still committing isn't skipped
+const ALERT_LAG_SECS: i64 = 5;
+
+/// Hits included in one alert
+const ALERT_HITS: usize = 5;
+
+/// Saved searches a user can keep
+const MAX_SAVED_SEARCHES: i64 = 50;
+
+const MAX_NAME_LEN: usize = 255;
+
+type ApiError = (StatusCode, String);
+
+/// Extract user_id from signed session cookie
+fn extract_user_id(app_state: &AppState, cookies: &Cookies) -> Result<Uuid, StatusCode> {
+    // In dev mode, allow unauthenticated access with test user
+    if app_state.dev_mode {
+        let mut conn = app_state
+            .db_pool
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        return users::table
+            .filter(users::email.eq("testing@testing.local"))
+            .select(users::id)
+            .first::<Uuid>(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let cookie = cookies
+        .signed(&app_state.cookie_key)
+        .get(SESSION_COOKIE_NAME)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    cookie.value().parse().map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+fn to_saved_search(row: SavedSearchRow) -> SavedSearch {
+    SavedSearch {
+        id: row.id,
+        name: row.name,
+        query: row.query,
+        regex: row.regex,
+        alert: row.alert,
+        last_match_at: row.last_match_at.map(|t| t.and_utc().to_rfc3339()),
+        created_at: row.created_at.and_utc().to_rfc3339(),
+    }
+}
+
+/// Trimmed name, or a 400 explaining what's wrong with it
+fn validate_name(name: &str) -> Result<String, ApiError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Give the search a name".to_string(),
+        ));
+    }
+    if name.chars().count() > MAX_NAME_LEN {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Names are limited to {} characters", MAX_NAME_LEN),
+        ));
+    }
+    Ok(name.to_string())
+}
+
+fn internal(context: &str, e: impl std::fmt::Display) -> ApiError {
+    error!("{}: {}", context, e);
+    (StatusCode::INTERNAL_SERVER_ERROR, String::new())
+}
+
+/// GET /api/saved-searches
+pub async fn list_saved_searches(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+) -> Result<Json<Vec<SavedSearch>>, ApiError> {
+    let user_id = extract_user_id(&app_state, &cookies).map_err(|code| (code, String::new()))?;
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, String::new()))?;
+
+    let rows: Vec<SavedSearchRow> = saved_searches::table
+        .filter(saved_searches::user_id.eq(user_id))
+        .order(saved_searches::created_at.asc())
+        .select(SavedSearchRow::as_select())
+        .load(&mut conn)
+        .map_err(|e| internal("Failed to list saved searches", e))?;
+
+    Ok(Json(rows.into_iter().map(to_saved_search).collect()))
+}
+
+/// POST /api/saved-searches
+pub async fn create_saved_search(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<SaveSearchRequest>,
+) -> Result<Json<SavedSearch>, ApiError> {
+    let user_id = extract_user_id(&app_state, &cookies).map_err(|code| (code, String::new()))?;
+    let name = validate_name(&req.name)?;
+    SearchQuery::parse(&req.query, req.regex)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, String::new()))?;
+
+    let existing: i64 = saved_searches::table
+        .filter(saved_searches::user_id.eq(user_id))
+        .count()
+        .get_result(&mut conn)
+        .map_err(|e| internal("Failed to count saved searches", e))?;
+    if existing >= MAX_SAVED_SEARCHES {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "You can keep up to {} saved searches; delete one first",
+                MAX_SAVED_SEARCHES
+            ),
+        ));
+    }
+
+    // checked_until defaults to now, so alerts only cover what comes next
+    let row: SavedSearchRow = diesel::insert_into(saved_searches::table)
+        .values(NewSavedSearch {
+            user_id,
+            name,
+            query: req.query.trim().to_string(),
+            regex: req.regex,
+            alert: req.alert,
+        })
+        .returning(SavedSearchRow::as_returning())
+        .get_result(&mut conn)
+        .map_err(|e| internal("Failed to save search", e))?;
+
+    info!(
+        "User {} saved search {} (alert: {})",
+        user_id, row.id, row.alert
+    );
+    Ok(Json(to_saved_search(row)))
+}
+
+/// PATCH /api/saved-searches/:id - rename, or turn alerting on or off
+pub async fn update_saved_search(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(id): Path<Uuid>,
+    Json(req): Json<UpdateSavedSearchRequest>,
+) -> Result<Json<SavedSearch>, ApiError> {
+    let user_id = extract_user_id(&app_state, &cookies).map_err(|code| (code, String::new()))?;
+    let name = req.name.as_deref().map(validate_name).transpose()?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, String::new()))?;
+
+    let owned = saved_searches::table
+        .filter(saved_searches::id.eq(id))
+        .filter(saved_searches::user_id.eq(user_id));
+    let current: SavedSearchRow = owned
+        .select(SavedSearchRow::as_select())
+        .first(&mut conn)
+        .optional()
+        .map_err(|e| internal("Failed to load saved search", e))?
+        .ok_or((StatusCode::NOT_FOUND, String::new()))?;
+
+    let alert = req.alert.unwrap_or(current.alert);
+    // Turning an alert on starts from now rather than replaying everything
+    // stored while it was off
+    let checked_until = if alert && !current.alert {
+        chrono::Utc::now().naive_utc()
+    } else {
+        current.checked_until
+    };
+
+    let row: SavedSearchRow = diesel::update(owned)
+        .set((
+            saved_searches::name.eq(name.unwrap_or(current.name)),
+            saved_searches::alert.eq(alert),
+            saved_searches::checked_until.eq(checked_until),
+        ))
+        .returning(SavedSearchRow::as_returning())
+        .get_result(&mut conn)
+        .map_err(|e| internal("Failed to update saved search", e))?;
+
+    Ok(Json(to_saved_search(row)))
+}
+
+/// DELETE /api/saved-searches/:id
+pub async fn delete_saved_search(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = extract_user_id(&app_state, &cookies).map_err(|code| (code, String::new()))?;
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, String::new()))?;
+
+    let deleted = diesel::delete(
+        saved_searches::table
+            .filter(saved_searches::id.eq(id))
+            .filter(saved_searches::user_id.eq(user_id)),
+    )
+    .execute(&mut conn)
+    .map_err(|e| internal("Failed to delete saved search", e))?;
+
+    if deleted == 0 {
+        return Err((StatusCode::NOT_FOUND, String::new()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Evaluate every alerting search over the messages stored since its last
+/// check and notify owners of new matches
+pub fn run_alerts(app_state: &AppState) {
+    let Ok(mut conn) = app_state.db_pool.get() else {
+        error!("Failed to get DB connection for search alerts");
+        return;
+    };
+
+    let rows: Vec<SavedSearchRow> = match saved_searches::table
+        .filter(saved_searches::alert.eq(true))
+        .select(SavedSearchRow::as_select())
+        .load(&mut conn)
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!("Failed to load alerting saved searches: {}", e);
+            return;
+        }
+    };
+
+    let until = chrono::Utc::now().naive_utc() - chrono::Duration::seconds(ALERT_LAG_SECS);
+    for row in rows {
+        if row.checked_until >= until {
+            continue;
+        }
+        evaluate(app_state, &mut conn, &row, until);
+    }
+}
+
+fn evaluate(
+    app_state: &AppState,
+    conn: &mut PgConnection,
+    row: &SavedSearchRow,
+    until: NaiveDateTime,
+) {
+    // Stored queries were valid when saved; relative dates are re-read now
+    let query = match SearchQuery::parse(&row.query, row.regex) {
+        Ok(query) => query.within(row.checked_until, until),
+        Err(e) => {
+            warn!("Saved search {} no longer parses: {}", row.id, e);
+            return;
+        }
+    };
+    let response = match search::run(conn, row.user_id, &query, ALERT_HITS) {
+        Ok(response) => response,
+        Err(e) => {
+            error!("Failed to evaluate saved search {}: {}", row.id, e);
+            return;
+        }
+    };
+
+    let matched = !response.hits.is_empty();
+    if matched {
+        info!(
+            "Saved search {} matched {}{} new messages",
+            row.id,
+            response.hits.len(),
+            if response.truncated { "+" } else { "" }
+        );
+        app_state.session_manager.broadcast_to_user(
+            &row.user_id,
+            ProxyMessage::SearchAlert {
+                saved_search_id: row.id,
+                name: row.name.clone(),
+                hits: response.hits,
+                more: response.truncated,
+            },
+        );
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+    let result = diesel::update(saved_searches::table.find(row.id))
+        .set((
+            saved_searches::checked_until.eq(until),
+            saved_searches::last_match_at.eq(if matched {
+                Some(now)
+            } else {
+                row.last_match_at
+            }),
+        ))
+        .execute(conn);
+    if let Err(e) = result {
+        error!("Failed to record check of saved search {}: {}", row.id, e);
+    }
+}
@@ -0,0 +1,224 @@
+//! Transcript search across the caller's sessions.
+//!
+//! The query language is parsed and matched in [`crate::search`]; this only
+//! handles auth, the request parameters, and embedding the query in semantic
+//! mode. Parse errors come back as 400 with a message meant for the search
+//! box.
+//!
+//! Related-session suggestions live here too, since they use the same
+//! embeddings. They're best effort: without a provider, or when it fails,
+//! the list is just empty.
+
+use crate::schema::users;
+use crate::search::{self, SearchQuery};
+use crate::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use diesel::prelude::*;
+use serde::Deserialize;
+use shared::{RelatedSession, SearchResponse};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+const SESSION_COOKIE_NAME: &str = "cc_session";
+
+/// Results returned when the request doesn't say
+const DEFAULT_LIMIT: usize = 50;
+
+/// Most results a single request can ask for
+const MAX_LIMIT: usize = 200;
+
+/// Related sessions suggested at once
+const RELATED_LIMIT: usize = 3;
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Every word (or the regex) must appear in the message
+    #[default]
+    Text,
+    /// Rank by similarity of meaning to the free text
+    Semantic,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchParams {
+    pub q: String,
+    /// Treat the free text as a regular expression
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub mode: SearchMode,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RelatedParams {
+    /// A draft first prompt to compare instead of the session's own
+    pub q: Option<String>,
+}
+
+/// Extract user_id from signed session cookie
+fn extract_user_id(app_state: &AppState, cookies: &Cookies) -> Result<Uuid, StatusCode> {
+    // In dev mode, allow unauthenticated access with test user
+    if app_state.dev_mode {
+        let mut conn = app_state
+            .db_pool
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        return users::table
+            .filter(users::email.eq("testing@testing.local"))
+            .select(users::id)
+            .first::<Uuid>(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let cookie = cookies
+        .signed(&app_state.cookie_key)
+        .get(SESSION_COOKIE_NAME)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    cookie.value().parse().map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+/// GET /api/search?q=...&regex=true&mode=semantic&limit=50
+pub async fn search_messages(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<SearchResponse>, (StatusCode, String)> {
+    let user_id = extract_user_id(&app_state, &cookies).map_err(|code| (code, String::new()))?;
+    let semantic = params.mode == SearchMode::Semantic;
+    let query = SearchQuery::parse(&params.q, params.regex && !semantic)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    // Embed the query before taking a connection; the provider may be slow
+    let target = if semantic {
+        let Some(provider) = &app_state.embeddings else {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Semantic search isn't enabled on this server".to_string(),
+            ));
+        };
+        if query.text().is_empty() {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Semantic search needs some text to compare, not just filters".to_string(),
+            ));
+        }
+        let vector = match provider.embed(&[query.text().to_string()]).await {
+            Ok(mut vectors) => vectors.pop(),
+            Err(e) => {
+                warn!(
+                    "Failed to embed search query with {}: {:#}",
+                    provider.name(),
+                    e
+                );
+                None
+            }
+        }
+        .ok_or_else(|| {
+            (
+                StatusCode::BAD_GATEWAY,
+                "The embedding provider didn't respond; try again or use text search".to_string(),
+            )
+        })?;
+        Some((provider.model.as_str(), vector))
+    } else {
+        None
+    };
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, String::new()))?;
+
+    let response = match &target {
+        Some((model, vector)) => {
+            search::run_semantic(&mut conn, user_id, &query, model, vector, limit)
+        }
+        None => search::run(&mut conn, user_id, &query, limit),
+    }
+    .map_err(|e| {
+        error!("Search failed: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, String::new())
+    })?;
+
+    Ok(Json(response))
+}
+
+/// GET /api/sessions/:id/related?q=...
+pub async fn related_sessions(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(session_id): Path<Uuid>,
+    Query(params): Query<RelatedParams>,
+) -> Result<Json<Vec<RelatedSession>>, (StatusCode, String)> {
+    let user_id = extract_user_id(&app_state, &cookies).map_err(|code| (code, String::new()))?;
+    let internal = |e: diesel::result::Error| {
+        error!("Related sessions failed: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, String::new())
+    };
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, String::new()))?;
+    super::helpers::session_role(&mut conn, session_id, user_id)
+        .map_err(internal)?
+        .ok_or((StatusCode::NOT_FOUND, String::new()))?;
+
+    let Some(provider) = &app_state.embeddings else {
+        return Ok(Json(Vec::new()));
+    };
+    let draft = params
+        .q
+        .as_deref()
+        .map(crate::embeddings::truncate_input)
+        .filter(|q| !q.is_empty());
+    let target = match draft {
+        Some(draft) => {
+            // Don't hold a connection while the provider works
+            drop(conn);
+            let vector = match provider.embed(&[draft]).await {
+                Ok(mut vectors) => vectors.pop(),
+                Err(e) => {
+                    warn!(
+                        "Failed to embed draft prompt with {}: {:#}",
+                        provider.name(),
+                        e
+                    );
+                    None
+                }
+            };
+            conn = app_state
+                .db_pool
+                .get()
+                .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, String::new()))?;
+            vector
+        }
+        None => search::first_prompt_embedding(&mut conn, session_id, &provider.model)
+            .map_err(internal)?,
+    };
+    let Some(target) = target else {
+        return Ok(Json(Vec::new()));
+    };
+
+    let related = search::related_sessions(
+        &mut conn,
+        user_id,
+        session_id,
+        &provider.model,
+        &target,
+        RELATED_LIMIT,
+    )
+    .map_err(internal)?;
+    Ok(Json(related))
+}
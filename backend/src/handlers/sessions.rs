@@ -6,6 +6,7 @@ use axum::{
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use shared::{CiStatus, SessionHealth, SessionQuota, SetSessionOutcomeRequest, UserRole};
 use std::sync::Arc;
 use tower_cookies::Cookies;
 use uuid::Uuid;
@@ -23,11 +24,43 @@ pub struct SessionWithRole {
     #[serde(flatten)]
     pub session: Session,
     pub my_role: String,
+    /// CI on the session's branch, if GitHub reported any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ci: Option<CiStatus>,
+    /// Health of a running session
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health: Option<SessionHealth>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct SessionListResponse {
     pub sessions: Vec<SessionWithRole>,
+    /// The user's running sessions against the per-user limit
+    pub quota: SessionQuota,
+}
+
+/// Running sessions owned by `user_id` against the configured limit,
+/// not counting `except` (a session that is reconnecting)
+pub fn session_quota(
+    conn: &mut PgConnection,
+    app_state: &AppState,
+    user_id: Uuid,
+    except: Option<Uuid>,
+) -> QueryResult<SessionQuota> {
+    use crate::schema::sessions;
+
+    let mut query = sessions::table
+        .filter(sessions::user_id.eq(user_id))
+        .filter(sessions::status.eq("active"))
+        .into_boxed();
+    if let Some(except) = except {
+        query = query.filter(sessions::id.ne(except));
+    }
+    let running: i64 = query.count().get_result(conn)?;
+    Ok(SessionQuota::new(
+        running as u32,
+        app_state.runtime_config.current().max_sessions_per_user,
+    ))
 }
 
 pub async fn list_sessions(
@@ -44,25 +77,79 @@ pub async fn list_sessions(
 
     use crate::schema::{session_members, sessions};
 
-    // Get all sessions the user is a member of, including their role
-    let results: Vec<(Session, String)> = sessions::table
-        .inner_join(session_members::table.on(session_members::session_id.eq(sessions::id)))
-        .filter(session_members::user_id.eq(current_user_id))
-        .select((Session::as_select(), session_members::role))
-        .order(sessions::last_activity.desc())
-        .load(&mut conn)
+    let account = super::helpers::account_role(&mut conn, current_user_id)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
+    // Admins see every session; everyone else sees the ones they're a member of
+    let results: Vec<(Session, Option<String>)> = if account == UserRole::Admin {
+        sessions::table
+            .left_join(
+                session_members::table.on(session_members::session_id
+                    .eq(sessions::id)
+                    .and(session_members::user_id.eq(current_user_id))),
+            )
+            .select((Session::as_select(), session_members::role.nullable()))
+            .order(sessions::last_activity.desc())
+            .load(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    } else {
+        sessions::table
+            .inner_join(session_members::table.on(session_members::session_id.eq(sessions::id)))
+            .filter(session_members::user_id.eq(current_user_id))
+            .filter(
+                session_members::expires_at
+                    .is_null()
+                    .or(session_members::expires_at.gt(diesel::dsl::now)),
+            )
+            .select((Session::as_select(), session_members::role.nullable()))
+            .order(sessions::last_activity.desc())
+            .load(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    };
+
+    let branches: Vec<(String, String)> = results
+        .iter()
+        .filter_map(|(session, _)| Some((session.git_repo.clone()?, session.git_branch.clone()?)))
+        .collect();
+    // CI status is decoration; the list still loads without it
+    let ci = crate::ci::statuses(&mut conn, &branches).unwrap_or_else(|e| {
+        tracing::error!("Failed to load CI status: {}", e);
+        Default::default()
+    });
+    let running: Vec<Uuid> = results
+        .iter()
+        .filter(|(session, _)| session.status == "active")
+        .map(|(session, _)| session.id)
+        .collect();
+    // Like CI status, health is decoration
+    let mut health = crate::health::assess(&mut conn, &running).unwrap_or_else(|e| {
+        tracing::error!("Failed to assess session health: {}", e);
+        Default::default()
+    });
+
     let sessions_with_role = results
         .into_iter()
-        .map(|(session, role)| SessionWithRole {
-            session,
-            my_role: role,
+        .filter_map(|(session, role)| {
+            let ci = session
+                .git_repo
+                .clone()
+                .zip(session.git_branch.clone())
+                .and_then(|key| ci.get(&key).cloned());
+            Some(SessionWithRole {
+                my_role: super::helpers::effective_session_role(account, role)?,
+                ci,
+                health: health.remove(&session.id),
+                session,
+            })
         })
         .collect();
 
+    let quota = session_quota(&mut conn, &app_state, current_user_id, None)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
     Ok(Json(SessionListResponse {
         sessions: sessions_with_role,
+        quota,
     }))
 }
 
@@ -111,13 +198,14 @@ pub async fn get_session(
         .get()
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    use crate::schema::{messages, session_members, sessions};
+    use crate::schema::{messages, sessions};
 
-    // Only return session if user is a member (owner, editor, or viewer)
+    // Only return session if user is a member (owner, editor, or viewer) or an admin
+    super::helpers::session_role(&mut conn, session_id, current_user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
     let session = sessions::table
-        .inner_join(session_members::table.on(session_members::session_id.eq(sessions::id)))
-        .filter(sessions::id.eq(session_id))
-        .filter(session_members::user_id.eq(current_user_id))
+        .find(session_id)
         .select(Session::as_select())
         .first::<Session>(&mut conn)
         .optional()
@@ -149,14 +237,16 @@ pub async fn delete_session(
         .get()
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    use crate::schema::{session_members, sessions};
+    use crate::schema::sessions;
 
-    // Only owners can delete sessions - verify user is an owner
+    // Only owners can delete sessions (viewer accounts never count as owners)
+    let role = super::helpers::session_role(&mut conn, session_id, current_user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if role.as_deref() != Some("owner") {
+        return Err(StatusCode::NOT_FOUND);
+    }
     let session = sessions::table
-        .inner_join(session_members::table.on(session_members::session_id.eq(sessions::id)))
-        .filter(sessions::id.eq(session_id))
-        .filter(session_members::user_id.eq(current_user_id))
-        .filter(session_members::role.eq("owner"))
+        .find(session_id)
         .select(Session::as_select())
         .first::<Session>(&mut conn)
         .optional()
@@ -170,6 +260,46 @@ pub async fn delete_session(
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Set or clear a session's outcome label (owner or editor)
+pub async fn set_session_outcome(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Path(session_id): Path<Uuid>,
+    Json(req): Json<SetSessionOutcomeRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    use crate::schema::sessions;
+
+    // Viewers can't label sessions
+    let role = super::helpers::session_role(&mut conn, session_id, current_user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if !super::helpers::can_edit(&role) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let outcome = req.outcome.map(|o| o.as_str());
+    let outcome_set_at = outcome.map(|_| chrono::Utc::now().naive_utc());
+    diesel::update(sessions::table.find(session_id))
+        .set((
+            sessions::outcome.eq(outcome),
+            sessions::outcome_set_at.eq(outcome_set_at),
+        ))
+        .execute(&mut conn)
+        .map_err(|e| {
+            tracing::error!("Failed to set session outcome: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
 // ============================================================================
 // Session Member Management
 // ============================================================================
@@ -181,6 +311,8 @@ pub struct SessionMemberInfo {
     pub name: Option<String>,
     pub role: String,
     pub created_at: NaiveDateTime,
+    /// When a guest's access ends
+    pub expires_at: Option<NaiveDateTime>,
 }
 
 #[derive(Debug, Serialize)]
@@ -211,12 +343,8 @@ pub async fn list_session_members(
 
     use crate::schema::{session_members, users};
 
-    // Verify the current user is a member of this session
-    let _membership = session_members::table
-        .filter(session_members::session_id.eq(session_id))
-        .filter(session_members::user_id.eq(current_user_id))
-        .first::<SessionMember>(&mut conn)
-        .optional()
+    // Verify the current user can see this session
+    super::helpers::session_role(&mut conn, session_id, current_user_id)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
         .ok_or(StatusCode::NOT_FOUND)?;
 
@@ -239,6 +367,7 @@ pub async fn list_session_members(
             name: user.name,
             role: member.role,
             created_at: member.created_at,
+            expires_at: member.expires_at,
         })
         .collect();
 
@@ -275,14 +404,11 @@ pub async fn add_session_member(
     use crate::schema::{session_members, users};
 
     // Verify the current user is the owner
-    let _owner_membership = session_members::table
-        .filter(session_members::session_id.eq(session_id))
-        .filter(session_members::user_id.eq(current_user_id))
-        .filter(session_members::role.eq("owner"))
-        .first::<SessionMember>(&mut conn)
-        .optional()
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::FORBIDDEN)?;
+    let role = super::helpers::session_role(&mut conn, session_id, current_user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if role.as_deref() != Some("owner") {
+        return Err(StatusCode::FORBIDDEN);
+    }
 
     // Find the user by email
     let target_user_id: Uuid = users::table
@@ -400,14 +526,11 @@ pub async fn update_session_member_role(
     use crate::schema::session_members;
 
     // Verify the current user is the owner
-    let _owner_membership = session_members::table
-        .filter(session_members::session_id.eq(session_id))
-        .filter(session_members::user_id.eq(current_user_id))
-        .filter(session_members::role.eq("owner"))
-        .first::<SessionMember>(&mut conn)
-        .optional()
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::FORBIDDEN)?;
+    let role = super::helpers::session_role(&mut conn, session_id, current_user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if role.as_deref() != Some("owner") {
+        return Err(StatusCode::FORBIDDEN);
+    }
 
     // Cannot change own role
     if current_user_id == target_user_id {
@@ -0,0 +1,173 @@
+//! Session snapshots mirrored from the proxy
+//!
+//! The proxy uploads its latest `SessionSnapshot` every so often, replacing
+//! the last one. If the proxy host is lost, the session's unacknowledged
+//! outputs and pending permission request can still be seen from the web
+//! UI, or downloaded and read with `claude-portal snapshot inspect`.
+
+use crate::models::SessionSnapshotRow;
+use crate::schema::{session_snapshots, sessions};
+use crate::AppState;
+use axum::{
+    extract::{ConnectInfo, Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use diesel::prelude::*;
+use shared::SnapshotSummary;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::error;
+use uuid::Uuid;
+
+/// Check the caller can see the session and its token covers its directory,
+/// returning the caller's role
+fn verify_access(
+    conn: &mut diesel::pg::PgConnection,
+    session_id: Uuid,
+    user_id: Uuid,
+    scopes: &shared::ProxyTokenScopes,
+) -> Result<String, StatusCode> {
+    let role = super::helpers::session_role(conn, session_id, user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let working_directory = sessions::table
+        .find(session_id)
+        .select(sessions::working_directory)
+        .first::<String>(conn)
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    if !scopes.allows_directory(&working_directory) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(role)
+}
+
+fn load(
+    conn: &mut diesel::pg::PgConnection,
+    session_id: Uuid,
+) -> Result<SessionSnapshotRow, StatusCode> {
+    session_snapshots::table
+        .find(session_id)
+        .select(SessionSnapshotRow::as_select())
+        .first(conn)
+        .optional()
+        .map_err(|e| {
+            error!("Failed to load snapshot: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+fn summary(row: &SessionSnapshotRow) -> SnapshotSummary {
+    SnapshotSummary::from_json(&row.snapshot, row.uploaded_at.and_utc().to_rfc3339())
+}
+
+/// Replace the session's stored snapshot. Only callers that can drive the
+/// session may, so read-only tokens can't.
+pub async fn upload_snapshot(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(session_id): Path<Uuid>,
+    Json(snapshot): Json<serde_json::Value>,
+) -> Result<Json<SnapshotSummary>, StatusCode> {
+    let (user_id, scopes) = super::messages::extract_caller(&app_state, &cookies, &headers, peer)?;
+    if scopes.read_only {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    // A snapshot of some other session would restore the wrong one
+    if snapshot["id"].as_str() != Some(session_id.to_string().as_str()) {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let role = verify_access(&mut conn, session_id, user_id, &scopes)?;
+    if !super::helpers::can_edit(&role) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+    let row = SessionSnapshotRow {
+        session_id,
+        snapshot,
+        uploaded_at: now,
+    };
+    diesel::insert_into(session_snapshots::table)
+        .values(&row)
+        .on_conflict(session_snapshots::session_id)
+        .do_update()
+        .set((
+            session_snapshots::snapshot.eq(&row.snapshot),
+            session_snapshots::uploaded_at.eq(now),
+        ))
+        .execute(&mut conn)
+        .map_err(|e| {
+            error!("Failed to store snapshot: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(summary(&row)))
+}
+
+/// Summary of the session's stored snapshot
+pub async fn get_snapshot(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(session_id): Path<Uuid>,
+) -> Result<Json<SnapshotSummary>, StatusCode> {
+    let (user_id, scopes) = super::messages::extract_caller(&app_state, &cookies, &headers, peer)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    verify_access(&mut conn, session_id, user_id, &scopes)?;
+
+    Ok(Json(summary(&load(&mut conn, session_id)?)))
+}
+
+/// Download the session's stored snapshot as a JSON file
+pub async fn download_snapshot(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(session_id): Path<Uuid>,
+) -> Result<Response, StatusCode> {
+    let (user_id, scopes) = super::messages::extract_caller(&app_state, &cookies, &headers, peer)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let role = verify_access(&mut conn, session_id, user_id, &scopes)?;
+    // The snapshot holds raw output, which can't be redacted piecemeal
+    if super::redaction::redactor_for(&mut conn, session_id, &role).is_some() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let row = load(&mut conn, session_id)?;
+    let body = serde_json::to_string_pretty(&row.snapshot)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let disposition = format!(
+        "attachment; filename=\"snapshot-{}.json\"",
+        row.uploaded_at.format("%Y%m%d-%H%M%S")
+    );
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response())
+}
@@ -0,0 +1,267 @@
+//! Server-Sent Events stream of a session's output
+//!
+//! For consumers that can't hold a WebSocket: curl scripts, serverless
+//! functions, simple dashboards. Like a web client's socket it first replays
+//! stored messages after a sequence number, then follows new output. Each
+//! `output` event's id is the message's `seq`, so a reconnecting client's
+//! `Last-Event-ID` resumes where it left off. A guest's stream ends once
+//! their access expires or is taken away, as their socket would be closed.
+
+use crate::AppState;
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use shared::ProxyMessage;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+use tower_cookies::Cookies;
+use tracing::info;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub struct StreamParams {
+    /// Replay stored messages after this `seq`; overrides `Last-Event-ID`
+    pub after_seq: Option<i64>,
+}
+
+/// Data of an `output` event
+#[derive(Debug, PartialEq, Serialize)]
+struct StreamOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seq: Option<i64>,
+    content: serde_json::Value,
+}
+
+impl StreamOutput {
+    fn into_event(self) -> Event {
+        let event = Event::default().event("output");
+        let event = match self.seq {
+            Some(seq) => event.id(seq.to_string()),
+            None => event,
+        };
+        event
+            .json_data(&self)
+            .unwrap_or_else(|_| Event::default().comment("unserializable output"))
+    }
+}
+
+/// The output a live relay message carries, unless it's one already sent
+/// (at or before `last_seq`). Advances `last_seq` past it.
+fn live_output(msg: ProxyMessage, last_seq: &mut Option<i64>) -> Option<StreamOutput> {
+    let ProxyMessage::ClaudeOutput {
+        content,
+        message_id,
+        message_seq,
+        ..
+    } = msg
+    else {
+        return None;
+    };
+    let seq = message_seq.map(|seq| seq as i64);
+    if let Some(seq) = seq {
+        if last_seq.is_some_and(|last| seq <= last) {
+            return None;
+        }
+        *last_seq = Some(seq);
+    }
+    Some(StreamOutput {
+        message_id,
+        seq,
+        content,
+    })
+}
+
+/// What the live half of a stream carries between events
+struct Follow {
+    app_state: Arc<AppState>,
+    session_id: Uuid,
+    user_id: Uuid,
+    rx: super::websocket::ClientReceiver,
+    last_seq: Option<i64>,
+    redactor: Option<shared::Redactor>,
+    /// When to check a guest's access is still there; `None` for members
+    recheck: Option<Interval>,
+    /// Cancelled when the server takes the user's access away
+    close: CancellationToken,
+}
+
+impl Follow {
+    /// The next output event, or `None` once the session's output ends or
+    /// the caller's access does
+    async fn next_event(&mut self) -> Option<Event> {
+        loop {
+            tokio::select! {
+                msg = self.rx.recv() => {
+                    if let Some(mut output) = live_output(msg?, &mut self.last_seq) {
+                        if let Some(redactor) = &mut self.redactor {
+                            redactor.apply(&mut output.content);
+                        }
+                        return Some(output.into_event());
+                    }
+                }
+                _ = next_recheck(&mut self.recheck) => {
+                    if !super::websocket::guest_access_continues(
+                        &self.app_state,
+                        Some(self.session_id),
+                        self.user_id,
+                    ) {
+                        info!("Guest access for user {} has ended", self.user_id);
+                        return None;
+                    }
+                }
+                _ = self.close.cancelled() => return None,
+            }
+        }
+    }
+}
+
+/// Wait for the next guest access check; never, for members
+async fn next_recheck(recheck: &mut Option<Interval>) {
+    match recheck {
+        Some(recheck) => {
+            recheck.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// GET /api/sessions/:id/stream?after_seq=
+pub async fn stream_session(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(session_id): Path<Uuid>,
+    Query(params): Query<StreamParams>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    let (current_user_id, scopes) =
+        super::messages::extract_caller(&app_state, &cookies, &headers, peer)?;
+    let after_seq = params.after_seq.or_else(|| {
+        headers
+            .get("last-event-id")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+    });
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let role = super::helpers::session_role(&mut conn, session_id, current_user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let working_directory: String = {
+        use crate::schema::sessions;
+        use diesel::prelude::*;
+        sessions::table
+            .find(session_id)
+            .select(sessions::working_directory)
+            .first(&mut conn)
+            .map_err(|_| StatusCode::NOT_FOUND)?
+    };
+    if !scopes.allows_directory(&working_directory) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let guest = !matches!(
+        super::helpers::membership_expiry(&mut conn, session_id, current_user_id),
+        Ok(None)
+    );
+
+    // Follow before reading history so nothing lands in between; output in
+    // both is skipped by seq
+    let session_manager = &app_state.session_manager;
+    let (tx, rx) = session_manager.web_client_channel();
+    session_manager.add_web_client(session_id.to_string(), tx);
+    let close = session_manager.user_close_token(current_user_id);
+
+    let mut history = super::websocket::load_history(&mut conn, session_id, after_seq, None);
+    let mut redactor = super::redaction::redactor_for(&mut conn, session_id, &role);
+    if let Some(redactor) = &mut redactor {
+        for msg in &mut history {
+            super::redaction::redact_stored(redactor, msg);
+        }
+    }
+    drop(conn);
+    info!(
+        "Streaming session {} to user {} over SSE ({} stored messages after {:?})",
+        session_id,
+        current_user_id,
+        history.len(),
+        after_seq
+    );
+
+    let last_seq = history.iter().map(|msg| msg.seq).max().or(after_seq);
+    let replay = stream::iter(history).map(|msg| {
+        Ok(StreamOutput {
+            message_id: Some(msg.id),
+            seq: Some(msg.seq),
+            content: super::websocket::stored_content(&msg),
+        }
+        .into_event())
+    });
+    let recheck = guest.then(|| {
+        let period = super::websocket::GUEST_RECHECK_INTERVAL;
+        let mut recheck = tokio::time::interval_at(Instant::now() + period, period);
+        recheck.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        recheck
+    });
+    let follow = Follow {
+        app_state: app_state.clone(),
+        session_id,
+        user_id: current_user_id,
+        rx,
+        last_seq,
+        redactor,
+        recheck,
+        close,
+    };
+    let live = stream::unfold(follow, |mut follow| async move {
+        let event = follow.next_event().await?;
+        Some((Ok(event), follow))
+    });
+
+    Ok(Sse::new(replay.chain(live)).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn output(seq: Option<u64>) -> ProxyMessage {
+        ProxyMessage::ClaudeOutput {
+            content: json!({"type": "assistant"}),
+            message_id: None,
+            message_seq: seq,
+            traceparent: None,
+            content_encoding: None,
+        }
+    }
+
+    #[test]
+    fn test_live_output_skips_replayed_messages() {
+        let mut last_seq = Some(10);
+        assert_eq!(live_output(output(Some(9)), &mut last_seq), None);
+        assert_eq!(live_output(output(Some(10)), &mut last_seq), None);
+        assert_eq!(
+            live_output(output(Some(11)), &mut last_seq).and_then(|o| o.seq),
+            Some(11)
+        );
+        assert_eq!(last_seq, Some(11));
+
+        // Unstored output has no seq to compare and always goes out
+        assert!(live_output(output(None), &mut last_seq).is_some());
+        assert_eq!(last_seq, Some(11));
+
+        assert_eq!(live_output(ProxyMessage::Heartbeat, &mut last_seq), None);
+    }
+}
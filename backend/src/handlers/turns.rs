@@ -0,0 +1,210 @@
+//! Per-turn records
+//!
+//! Each stored message is fed to the session's `TurnAnalyzer`; when a result
+//! finishes a turn, its record is written to the `turns` table. Analytics and
+//! the turn summaries in the UI read those rows instead of reparsing the
+//! transcript.
+
+use crate::models::{NewTurn, Turn};
+use crate::schema::{messages, sessions, turns};
+use crate::AppState;
+use axum::{
+    extract::{ConnectInfo, Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use diesel::prelude::*;
+use shared::{TurnAnalyzer, TurnInfo, TurnRecord};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::error;
+use uuid::Uuid;
+
+use super::websocket::SessionManager;
+
+fn parse(content: &str) -> serde_json::Value {
+    serde_json::from_str(content).unwrap_or(serde_json::Value::Null)
+}
+
+/// Rebuild a session's analyzer from what's stored: the last recorded turn,
+/// then every message after it up to (not including) `before_seq`
+fn seed(
+    conn: &mut diesel::pg::PgConnection,
+    session_id: Uuid,
+    before_seq: i64,
+) -> QueryResult<TurnAnalyzer> {
+    let last = turns::table
+        .filter(turns::session_id.eq(session_id))
+        .order(turns::turn_index.desc())
+        .select(Turn::as_select())
+        .first(conn)
+        .optional()?;
+
+    let (mut analyzer, after_seq) = match last {
+        Some(turn) => {
+            let result = messages::table
+                .filter(messages::session_id.eq(session_id))
+                .filter(messages::seq.eq(turn.end_seq))
+                .select(messages::content)
+                .first::<String>(conn)
+                .optional()?
+                .map(|c| parse(&c));
+            (
+                TurnAnalyzer::after(turn.turn_index, result.as_ref()),
+                turn.end_seq,
+            )
+        }
+        None => (TurnAnalyzer::new(), 0),
+    };
+
+    let rest: Vec<(Uuid, i64, String, chrono::NaiveDateTime)> = messages::table
+        .filter(messages::session_id.eq(session_id))
+        .filter(messages::seq.gt(after_seq))
+        .filter(messages::seq.lt(before_seq))
+        .order(messages::seq.asc())
+        .select((
+            messages::id,
+            messages::seq,
+            messages::content,
+            messages::created_at,
+        ))
+        .load(conn)?;
+    for (id, seq, content, created_at) in rest {
+        // A turn that finished while nobody was recording is caught up here
+        let at_ms = created_at.and_utc().timestamp_millis();
+        if let Some(record) = analyzer.push(seq, at_ms, &parse(&content)) {
+            insert(conn, session_id, Some(id), record)?;
+        }
+    }
+    Ok(analyzer)
+}
+
+fn insert(
+    conn: &mut diesel::pg::PgConnection,
+    session_id: Uuid,
+    end_message_id: Option<Uuid>,
+    record: TurnRecord,
+) -> QueryResult<usize> {
+    diesel::insert_into(turns::table)
+        .values(NewTurn {
+            session_id,
+            turn_index: record.turn_index,
+            start_seq: record.start_seq,
+            end_seq: record.end_seq,
+            end_message_id,
+            duration_ms: record.duration_ms,
+            input_tokens: record.input_tokens,
+            output_tokens: record.output_tokens,
+            cost_usd: record.cost_usd,
+            tools: record.tools,
+            error: record.error,
+            compacted: record.compacted,
+        })
+        .on_conflict((turns::session_id, turns::turn_index))
+        .do_nothing()
+        .execute(conn)
+}
+
+/// Follow a message just stored as `message_id` at `seq`, recording the turn
+/// it finishes, if any
+pub fn observe(
+    session_manager: &SessionManager,
+    conn: &mut diesel::pg::PgConnection,
+    session_id: Uuid,
+    (message_id, seq): (Uuid, i64),
+    content: &serde_json::Value,
+) {
+    if !session_manager.turn_analyzers.contains_key(&session_id) {
+        match seed(conn, session_id, seq) {
+            Ok(analyzer) => {
+                session_manager.turn_analyzers.insert(session_id, analyzer);
+            }
+            Err(e) => {
+                error!("Failed to load turns for session {}: {}", session_id, e);
+                return;
+            }
+        }
+    }
+
+    let at_ms = chrono::Utc::now().timestamp_millis();
+    let finished = session_manager
+        .turn_analyzers
+        .get_mut(&session_id)
+        .and_then(|mut analyzer| analyzer.push(seq, at_ms, content));
+    if let Some(record) = finished {
+        if let Err(e) = insert(conn, session_id, Some(message_id), record) {
+            error!("Failed to record turn: {}", e);
+        }
+    }
+}
+
+/// Look up the turn that ended with result message `message_id`
+pub fn turn_ending_at(
+    conn: &mut diesel::pg::PgConnection,
+    message_id: Uuid,
+) -> QueryResult<Option<Turn>> {
+    turns::table
+        .filter(turns::end_message_id.eq(message_id))
+        .select(Turn::as_select())
+        .first(conn)
+        .optional()
+}
+
+fn to_info(turn: Turn) -> TurnInfo {
+    TurnInfo {
+        record: TurnRecord {
+            turn_index: turn.turn_index,
+            start_seq: turn.start_seq,
+            end_seq: turn.end_seq,
+            duration_ms: turn.duration_ms,
+            input_tokens: turn.input_tokens,
+            output_tokens: turn.output_tokens,
+            cost_usd: turn.cost_usd,
+            tools: turn.tools,
+            error: turn.error,
+            compacted: turn.compacted,
+        },
+        end_message_id: turn.end_message_id,
+        ended_at: turn.created_at.and_utc().to_rfc3339(),
+    }
+}
+
+/// The session's finished turns, oldest first
+pub async fn list_turns(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Path(session_id): Path<Uuid>,
+) -> Result<Json<Vec<TurnInfo>>, StatusCode> {
+    let (user_id, scopes) = super::messages::extract_caller(&app_state, &cookies, &headers, peer)?;
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    super::helpers::session_role(&mut conn, session_id, user_id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let working_directory = sessions::table
+        .find(session_id)
+        .select(sessions::working_directory)
+        .first::<String>(&mut conn)
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    if !scopes.allows_directory(&working_directory) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let rows = turns::table
+        .filter(turns::session_id.eq(session_id))
+        .order(turns::turn_index.asc())
+        .select(Turn::as_select())
+        .load(&mut conn)
+        .map_err(|e| {
+            error!("Failed to load turns: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(rows.into_iter().map(to_info).collect()))
+}
@@ -0,0 +1,589 @@
+//! Cost and token usage aggregation.
+//!
+//! Result messages report running totals for the Claude process (overall and
+//! per model under `modelUsage`). Each result is diffed against the last totals
+//! seen for the session, and the difference is added to a per-day row, which
+//! `GET /api/usage` then rolls up by session, model, and day. Tool calls in
+//! assistant messages are counted per day alongside.
+
+use crate::models::{NewToolUsageDaily, NewUsageDaily, Session, SessionModelUsage, UsageDaily};
+use crate::schema::{session_model_usage, tool_usage_daily, usage_daily, users};
+use crate::AppState;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use serde::Deserialize;
+use shared::{
+    DailyUsage, ModelUsage, SessionUsage, ToolUsage, UsageRange, UsageReport, UsageTotals,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tower_cookies::Cookies;
+use tracing::error;
+use uuid::Uuid;
+
+const SESSION_COOKIE_NAME: &str = "cc_session";
+
+/// Model name used when a result doesn't break usage down by model
+const UNKNOWN_MODEL: &str = "unknown";
+
+/// Extract user_id from signed session cookie
+fn extract_user_id(app_state: &AppState, cookies: &Cookies) -> Result<Uuid, StatusCode> {
+    // In dev mode, allow unauthenticated access with test user
+    if app_state.dev_mode {
+        let mut conn = app_state
+            .db_pool
+            .get()
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        return users::table
+            .filter(users::email.eq("testing@testing.local"))
+            .select(users::id)
+            .first::<Uuid>(&mut conn)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let cookie = cookies
+        .signed(&app_state.cookie_key)
+        .get(SESSION_COOKIE_NAME)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    cookie.value().parse().map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
+fn json_i64(value: Option<&serde_json::Value>, key: &str) -> i64 {
+    value
+        .and_then(|v| v.get(key))
+        .and_then(|v| v.as_i64())
+        .unwrap_or(0)
+}
+
+/// Running totals per model from a result message.
+///
+/// Falls back to the top-level `total_cost_usd` and `usage` fields, attributed
+/// to [`UNKNOWN_MODEL`], when `modelUsage` is missing.
+fn parse_model_usage(content: &serde_json::Value) -> Vec<(String, UsageTotals)> {
+    if let Some(models) = content.get("modelUsage").and_then(|m| m.as_object()) {
+        if !models.is_empty() {
+            return models
+                .iter()
+                .map(|(model, usage)| {
+                    let usage = Some(usage);
+                    let totals = UsageTotals {
+                        cost_usd: usage
+                            .and_then(|u| u.get("costUSD"))
+                            .and_then(|c| c.as_f64())
+                            .unwrap_or(0.0),
+                        input_tokens: json_i64(usage, "inputTokens"),
+                        output_tokens: json_i64(usage, "outputTokens"),
+                        cache_creation_tokens: json_i64(usage, "cacheCreationInputTokens"),
+                        cache_read_tokens: json_i64(usage, "cacheReadInputTokens"),
+                        turns: 0,
+                    };
+                    (model.clone(), totals)
+                })
+                .collect();
+        }
+    }
+
+    let usage = content.get("usage");
+    let totals = UsageTotals {
+        cost_usd: content
+            .get("total_cost_usd")
+            .and_then(|c| c.as_f64())
+            .unwrap_or(0.0),
+        input_tokens: json_i64(usage, "input_tokens"),
+        output_tokens: json_i64(usage, "output_tokens"),
+        cache_creation_tokens: json_i64(usage, "cache_creation_input_tokens"),
+        cache_read_tokens: json_i64(usage, "cache_read_input_tokens"),
+        turns: 0,
+    };
+    vec![(UNKNOWN_MODEL.to_string(), totals)]
+}
+
+/// Usage added since `previous` was reported.
+///
+/// Totals that went backwards mean the Claude process restarted and began
+/// counting from zero, so the new totals are all new usage.
+fn usage_delta(previous: Option<&UsageTotals>, current: &UsageTotals) -> UsageTotals {
+    let Some(prev) = previous else {
+        return current.clone();
+    };
+    let restarted = current.cost_usd < prev.cost_usd
+        || current.input_tokens < prev.input_tokens
+        || current.output_tokens < prev.output_tokens
+        || current.cache_creation_tokens < prev.cache_creation_tokens
+        || current.cache_read_tokens < prev.cache_read_tokens;
+    if restarted {
+        return current.clone();
+    }
+    UsageTotals {
+        cost_usd: current.cost_usd - prev.cost_usd,
+        input_tokens: current.input_tokens - prev.input_tokens,
+        output_tokens: current.output_tokens - prev.output_tokens,
+        cache_creation_tokens: current.cache_creation_tokens - prev.cache_creation_tokens,
+        cache_read_tokens: current.cache_read_tokens - prev.cache_read_tokens,
+        turns: 0,
+    }
+}
+
+fn snapshot_totals(snapshot: &SessionModelUsage) -> UsageTotals {
+    UsageTotals {
+        cost_usd: snapshot.cost_usd,
+        input_tokens: snapshot.input_tokens,
+        output_tokens: snapshot.output_tokens,
+        cache_creation_tokens: snapshot.cache_creation_tokens,
+        cache_read_tokens: snapshot.cache_read_tokens,
+        turns: 0,
+    }
+}
+
+/// Add the usage reported by a result message to the daily aggregates
+pub fn record_result_usage(
+    conn: &mut diesel::pg::PgConnection,
+    session: &Session,
+    content: &serde_json::Value,
+) -> QueryResult<()> {
+    let current = parse_model_usage(content);
+
+    let previous: HashMap<String, UsageTotals> = session_model_usage::table
+        .filter(session_model_usage::session_id.eq(session.id))
+        .select(SessionModelUsage::as_select())
+        .load(conn)?
+        .into_iter()
+        .map(|s| (s.model.clone(), snapshot_totals(&s)))
+        .collect();
+
+    let mut deltas: Vec<(String, UsageTotals)> = current
+        .iter()
+        .map(|(model, totals)| (model.clone(), usage_delta(previous.get(model), totals)))
+        .collect();
+
+    // Count the turn once, against the model that did most of the work
+    if let Some((_, primary)) = deltas
+        .iter_mut()
+        .max_by(|(_, a), (_, b)| a.cost_usd.total_cmp(&b.cost_usd))
+    {
+        primary.turns = 1;
+    }
+
+    let day = chrono::Utc::now().date_naive();
+    conn.transaction(|conn| {
+        for (model, totals) in &current {
+            diesel::insert_into(session_model_usage::table)
+                .values(SessionModelUsage {
+                    session_id: session.id,
+                    model: model.clone(),
+                    cost_usd: totals.cost_usd,
+                    input_tokens: totals.input_tokens,
+                    output_tokens: totals.output_tokens,
+                    cache_creation_tokens: totals.cache_creation_tokens,
+                    cache_read_tokens: totals.cache_read_tokens,
+                })
+                .on_conflict((session_model_usage::session_id, session_model_usage::model))
+                .do_update()
+                .set((
+                    session_model_usage::cost_usd.eq(excluded(session_model_usage::cost_usd)),
+                    session_model_usage::input_tokens
+                        .eq(excluded(session_model_usage::input_tokens)),
+                    session_model_usage::output_tokens
+                        .eq(excluded(session_model_usage::output_tokens)),
+                    session_model_usage::cache_creation_tokens
+                        .eq(excluded(session_model_usage::cache_creation_tokens)),
+                    session_model_usage::cache_read_tokens
+                        .eq(excluded(session_model_usage::cache_read_tokens)),
+                    session_model_usage::updated_at.eq(diesel::dsl::now),
+                ))
+                .execute(conn)?;
+        }
+
+        for (model, delta) in deltas {
+            if delta == UsageTotals::default() {
+                continue;
+            }
+            diesel::insert_into(usage_daily::table)
+                .values(NewUsageDaily {
+                    user_id: session.user_id,
+                    session_id: Some(session.id),
+                    session_name: session.session_name.clone(),
+                    day,
+                    model,
+                    cost_usd: delta.cost_usd,
+                    input_tokens: delta.input_tokens,
+                    output_tokens: delta.output_tokens,
+                    cache_creation_tokens: delta.cache_creation_tokens,
+                    cache_read_tokens: delta.cache_read_tokens,
+                    turns: delta.turns as i32,
+                })
+                .on_conflict((
+                    usage_daily::session_id,
+                    usage_daily::day,
+                    usage_daily::model,
+                ))
+                .do_update()
+                .set((
+                    usage_daily::session_name.eq(&session.session_name),
+                    usage_daily::cost_usd.eq(usage_daily::cost_usd + delta.cost_usd),
+                    usage_daily::input_tokens.eq(usage_daily::input_tokens + delta.input_tokens),
+                    usage_daily::output_tokens.eq(usage_daily::output_tokens + delta.output_tokens),
+                    usage_daily::cache_creation_tokens
+                        .eq(usage_daily::cache_creation_tokens + delta.cache_creation_tokens),
+                    usage_daily::cache_read_tokens
+                        .eq(usage_daily::cache_read_tokens + delta.cache_read_tokens),
+                    usage_daily::turns.eq(usage_daily::turns + delta.turns as i32),
+                ))
+                .execute(conn)?;
+        }
+        Ok(())
+    })
+}
+
+/// Names of the tools called in an assistant message
+fn tool_names(content: &serde_json::Value) -> Vec<&str> {
+    content
+        .get("message")
+        .and_then(|m| m.get("content"))
+        .and_then(|c| c.as_array())
+        .map(|blocks| {
+            blocks
+                .iter()
+                .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("tool_use"))
+                .filter_map(|b| b.get("name").and_then(|n| n.as_str()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Count the tool calls in an assistant message
+pub fn record_tool_usage(
+    conn: &mut diesel::pg::PgConnection,
+    session: &Session,
+    content: &serde_json::Value,
+) -> QueryResult<()> {
+    let day = chrono::Utc::now().date_naive();
+    for tool_name in tool_names(content) {
+        diesel::insert_into(tool_usage_daily::table)
+            .values(NewToolUsageDaily {
+                user_id: session.user_id,
+                day,
+                tool_name: tool_name.to_string(),
+                uses: 1,
+            })
+            .on_conflict((
+                tool_usage_daily::user_id,
+                tool_usage_daily::day,
+                tool_usage_daily::tool_name,
+            ))
+            .do_update()
+            .set(tool_usage_daily::uses.eq(tool_usage_daily::uses + 1))
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+fn row_totals(row: &UsageDaily) -> UsageTotals {
+    UsageTotals {
+        cost_usd: row.cost_usd,
+        input_tokens: row.input_tokens,
+        output_tokens: row.output_tokens,
+        cache_creation_tokens: row.cache_creation_tokens,
+        cache_read_tokens: row.cache_read_tokens,
+        turns: row.turns as i64,
+    }
+}
+
+/// Roll daily rows up into a report covering `since` (or the first day with
+/// usage) through `today`
+fn build_report(
+    range: UsageRange,
+    since: Option<NaiveDate>,
+    today: NaiveDate,
+    rows: &[UsageDaily],
+    tools: Vec<(String, i64)>,
+) -> UsageReport {
+    let mut totals = UsageTotals::default();
+    let mut by_day: BTreeMap<NaiveDate, UsageTotals> = BTreeMap::new();
+    let mut by_model: HashMap<&str, UsageTotals> = HashMap::new();
+    // Deleted sessions are grouped by name since their ID is gone
+    let mut by_session: HashMap<(Option<Uuid>, &str), UsageTotals> = HashMap::new();
+
+    for row in rows {
+        let row_total = row_totals(row);
+        totals.add(&row_total);
+        by_day.entry(row.day).or_default().add(&row_total);
+        by_model
+            .entry(row.model.as_str())
+            .or_default()
+            .add(&row_total);
+        let session_key = match row.session_id {
+            Some(id) => (Some(id), ""),
+            None => (None, row.session_name.as_str()),
+        };
+        by_session.entry(session_key).or_default().add(&row_total);
+    }
+
+    // Fill in quiet days so charts get an evenly spaced axis
+    let first_day = since.or_else(|| by_day.keys().next().copied());
+    if let Some(first_day) = first_day {
+        let mut day = first_day;
+        while day <= today {
+            by_day.entry(day).or_default();
+            day += chrono::Duration::days(1);
+        }
+    }
+
+    // Show a live session under its most recent name
+    let session_names: HashMap<Uuid, &str> = rows
+        .iter()
+        .filter_map(|r| r.session_id.map(|id| (id, r.session_name.as_str())))
+        .collect();
+
+    let mut by_model: Vec<ModelUsage> = by_model
+        .into_iter()
+        .map(|(model, totals)| ModelUsage {
+            model: model.to_string(),
+            totals,
+        })
+        .collect();
+    by_model.sort_by(|a, b| b.totals.cost_usd.total_cmp(&a.totals.cost_usd));
+
+    let mut by_session: Vec<SessionUsage> = by_session
+        .into_iter()
+        .map(|((session_id, name), totals)| SessionUsage {
+            session_id,
+            session_name: session_id
+                .and_then(|id| session_names.get(&id).copied())
+                .unwrap_or(name)
+                .to_string(),
+            totals,
+        })
+        .collect();
+    by_session.sort_by(|a, b| b.totals.cost_usd.total_cmp(&a.totals.cost_usd));
+
+    let mut by_tool: Vec<ToolUsage> = tools
+        .into_iter()
+        .map(|(tool, uses)| ToolUsage { tool, uses })
+        .collect();
+    by_tool.sort_by(|a, b| b.uses.cmp(&a.uses).then_with(|| a.tool.cmp(&b.tool)));
+
+    UsageReport {
+        range,
+        since: since.map(|d| d.to_string()),
+        totals,
+        by_day: by_day
+            .into_iter()
+            .map(|(day, totals)| DailyUsage {
+                day: day.to_string(),
+                totals,
+            })
+            .collect(),
+        by_model,
+        by_session,
+        by_tool,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UsageQuery {
+    /// `7d`, `30d` (default), `90d`, or `all`
+    pub range: Option<String>,
+}
+
+/// Usage totals for the current user's sessions over a time range
+pub async fn get_usage(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<UsageReport>, StatusCode> {
+    let current_user_id = extract_user_id(&app_state, &cookies)?;
+
+    let range = match query.range.as_deref() {
+        Some(r) => UsageRange::parse(r).ok_or(StatusCode::BAD_REQUEST)?,
+        None => UsageRange::default(),
+    };
+    let today = chrono::Utc::now().date_naive();
+    let since = range
+        .days()
+        .map(|days| today - chrono::Duration::days(days - 1));
+
+    let mut conn = app_state
+        .db_pool
+        .get()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut rows_query = usage_daily::table
+        .filter(usage_daily::user_id.eq(current_user_id))
+        .select(UsageDaily::as_select())
+        .into_boxed();
+    if let Some(since) = since {
+        rows_query = rows_query.filter(usage_daily::day.ge(since));
+    }
+    let rows: Vec<UsageDaily> = rows_query.load(&mut conn).map_err(|e| {
+        error!("Failed to load usage: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut tools_query = tool_usage_daily::table
+        .filter(tool_usage_daily::user_id.eq(current_user_id))
+        .group_by(tool_usage_daily::tool_name)
+        .select((
+            tool_usage_daily::tool_name,
+            diesel::dsl::sum(tool_usage_daily::uses),
+        ))
+        .into_boxed();
+    if let Some(since) = since {
+        tools_query = tools_query.filter(tool_usage_daily::day.ge(since));
+    }
+    let tools: Vec<(String, Option<i64>)> = tools_query.load(&mut conn).map_err(|e| {
+        error!("Failed to load tool usage: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let tools = tools
+        .into_iter()
+        .map(|(tool, uses)| (tool, uses.unwrap_or(0)))
+        .collect();
+
+    Ok(Json(build_report(range, since, today, &rows, tools)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_model_usage() {
+        let content = json!({
+            "type": "result",
+            "total_cost_usd": 0.5,
+            "modelUsage": {
+                "claude-sonnet-4": {
+                    "inputTokens": 100,
+                    "outputTokens": 50,
+                    "cacheReadInputTokens": 10,
+                    "cacheCreationInputTokens": 5,
+                    "costUSD": 0.4
+                }
+            }
+        });
+        let usage = parse_model_usage(&content);
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage[0].0, "claude-sonnet-4");
+        assert_eq!(usage[0].1.input_tokens, 100);
+        assert_eq!(usage[0].1.cache_read_tokens, 10);
+        assert_eq!(usage[0].1.cost_usd, 0.4);
+
+        // Older results without a per-model breakdown
+        let content = json!({
+            "type": "result",
+            "total_cost_usd": 0.25,
+            "usage": { "input_tokens": 7, "output_tokens": 3 }
+        });
+        let usage = parse_model_usage(&content);
+        assert_eq!(usage[0].0, UNKNOWN_MODEL);
+        assert_eq!(usage[0].1.cost_usd, 0.25);
+        assert_eq!(usage[0].1.output_tokens, 3);
+    }
+
+    #[test]
+    fn test_usage_delta() {
+        let prev = UsageTotals {
+            cost_usd: 1.0,
+            input_tokens: 100,
+            output_tokens: 10,
+            ..Default::default()
+        };
+        let current = UsageTotals {
+            cost_usd: 1.5,
+            input_tokens: 150,
+            output_tokens: 30,
+            ..Default::default()
+        };
+        let delta = usage_delta(Some(&prev), &current);
+        assert_eq!(delta.cost_usd, 0.5);
+        assert_eq!(delta.input_tokens, 50);
+        assert_eq!(delta.output_tokens, 20);
+
+        // A restarted process starts counting from zero again
+        let restarted = UsageTotals {
+            cost_usd: 0.2,
+            input_tokens: 20,
+            output_tokens: 5,
+            ..Default::default()
+        };
+        assert_eq!(usage_delta(Some(&prev), &restarted), restarted);
+        assert_eq!(usage_delta(None, &current), current);
+    }
+
+    fn row(session_id: Option<Uuid>, name: &str, day: &str, model: &str, cost: f64) -> UsageDaily {
+        UsageDaily {
+            session_id,
+            session_name: name.to_string(),
+            day: day.parse().unwrap(),
+            model: model.to_string(),
+            cost_usd: cost,
+            input_tokens: 10,
+            output_tokens: 1,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 0,
+            turns: 1,
+        }
+    }
+
+    #[test]
+    fn test_build_report() {
+        let live = Uuid::new_v4();
+        let rows = vec![
+            row(Some(live), "api", "2026-01-02", "opus", 2.0),
+            row(Some(live), "api", "2026-01-01", "sonnet", 0.5),
+            row(None, "old", "2026-01-01", "sonnet", 1.0),
+        ];
+        let tools = vec![("Read".to_string(), 3), ("Bash".to_string(), 9)];
+        let report = build_report(
+            UsageRange::Week,
+            "2025-12-27".parse().ok(),
+            "2026-01-02".parse().unwrap(),
+            &rows,
+            tools,
+        );
+
+        assert_eq!(report.since.as_deref(), Some("2025-12-27"));
+        assert_eq!(report.totals.cost_usd, 3.5);
+        assert_eq!(report.totals.turns, 3);
+        // Every day in the range is present, including ones without usage
+        assert_eq!(report.by_day.len(), 7);
+        assert_eq!(report.by_day[0].day, "2025-12-27");
+        assert_eq!(report.by_day[0].totals, UsageTotals::default());
+        assert_eq!(report.by_day[5].day, "2026-01-01");
+        assert_eq!(report.by_day[5].totals.cost_usd, 1.5);
+        assert_eq!(report.by_model[0].model, "opus");
+        assert_eq!(report.by_model[1].totals.cost_usd, 1.5);
+        assert_eq!(report.by_session.len(), 2);
+        assert_eq!(report.by_session[0].session_id, Some(live));
+        assert_eq!(report.by_session[0].totals.cost_usd, 2.5);
+        assert_eq!(report.by_session[1].session_name, "old");
+        assert_eq!(report.by_tool[0].tool, "Bash");
+        assert_eq!(report.by_tool[1].uses, 3);
+    }
+
+    #[test]
+    fn test_tool_names() {
+        let content = serde_json::json!({
+            "type": "assistant",
+            "message": {
+                "content": [
+                    { "type": "text", "text": "Let me look." },
+                    { "type": "tool_use", "id": "1", "name": "Read", "input": {} },
+                    { "type": "tool_use", "id": "2", "name": "Grep", "input": {} }
+                ]
+            }
+        });
+        assert_eq!(tool_names(&content), vec!["Read", "Grep"]);
+        assert!(tool_names(&serde_json::json!({ "type": "result" })).is_empty());
+    }
+}
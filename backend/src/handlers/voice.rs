@@ -1,10 +1,12 @@
 //! Voice WebSocket Handler
 //!
 //! Handles audio streaming for voice-to-text functionality.
-//! Audio is received as binary PCM16 frames and forwarded to
-//! Google Speech-to-Text for transcription.
+//! Audio is received as binary PCM16 frames and forwarded to the
+//! configured speech-to-text provider for transcription.
 
-use crate::speech::{SpeechConfig, SpeechService};
+use crate::db::DbPool;
+use crate::metering;
+use crate::speech::{SpeechConfig, SpeechProvider};
 use crate::AppState;
 use axum::{
     extract::{
@@ -13,14 +15,15 @@ use axum::{
     },
     http::StatusCode,
     response::{IntoResponse, Response},
+    Json,
 };
 use diesel::prelude::*;
 use futures_util::{SinkExt, StreamExt};
-use shared::ProxyMessage;
+use shared::{ProxyMessage, SetVoiceLanguageRequest};
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use tower_cookies::Cookies;
-use tracing::{error, info, warn};
+use tracing::{error, info, warn, Instrument};
 use uuid::Uuid;
 
 const SESSION_COOKIE_NAME: &str = "cc_session";
@@ -60,22 +63,47 @@ fn check_voice_enabled(app_state: &AppState, user_id: Uuid) -> bool {
         .unwrap_or(false)
 }
 
-/// Verify that a user has access to a session (is a member with any role)
+/// Verify that a user may speak into a session; viewers can't send input
 fn verify_session_access(app_state: &AppState, session_id: Uuid, user_id: Uuid) -> bool {
     let mut conn = match app_state.db_pool.get() {
         Ok(c) => c,
         Err(_) => return false,
     };
 
-    use crate::schema::{session_members, sessions};
-    sessions::table
-        .inner_join(session_members::table.on(session_members::session_id.eq(sessions::id)))
-        .filter(sessions::id.eq(session_id))
-        .filter(session_members::user_id.eq(user_id))
-        .count()
-        .get_result::<i64>(&mut conn)
-        .unwrap_or(0)
-        > 0
+    super::helpers::session_role(&mut conn, session_id, user_id)
+        .ok()
+        .flatten()
+        .is_some_and(|role| super::helpers::can_edit(&role))
+}
+
+/// PUT /api/auth/me/voice-language - Choose the language voice input is
+/// recognized in
+pub async fn set_voice_language(
+    State(app_state): State<Arc<AppState>>,
+    cookies: Cookies,
+    Json(req): Json<SetVoiceLanguageRequest>,
+) -> StatusCode {
+    let Some(user_id) = extract_user_id_from_cookies(&app_state, &cookies) else {
+        return StatusCode::UNAUTHORIZED;
+    };
+    if !shared::voice::is_voice_language(&req.language_code) {
+        return StatusCode::BAD_REQUEST;
+    }
+    let Ok(mut conn) = app_state.db_pool.get() else {
+        return StatusCode::INTERNAL_SERVER_ERROR;
+    };
+
+    use crate::schema::users;
+    match diesel::update(users::table.find(user_id))
+        .set(users::voice_language.eq(&req.language_code))
+        .execute(&mut conn)
+    {
+        Ok(_) => StatusCode::NO_CONTENT,
+        Err(e) => {
+            error!("Failed to save voice language: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
 }
 
 /// WebSocket endpoint for voice audio streaming
@@ -118,15 +146,22 @@ pub async fn handle_voice_websocket(
         return StatusCode::FORBIDDEN.into_response();
     }
 
-    // Check if speech credentials are configured
-    let speech_credentials = app_state.speech_credentials_path.clone();
+    let speech = app_state.speech.clone();
 
     info!(
         "Voice WebSocket upgrade for user {} on session {}",
         user_id, session_id
     );
+    let span = tracing::info_span!("voice_connection", %user_id, %session_id);
     ws.on_upgrade(move |socket| {
-        handle_voice_socket(socket, user_id, session_id, speech_credentials)
+        handle_voice_socket(
+            socket,
+            app_state.db_pool.clone(),
+            user_id,
+            session_id,
+            speech,
+        )
+        .instrument(span)
     })
 }
 
@@ -137,9 +172,10 @@ struct VoiceRecognitionSession {
 
 async fn handle_voice_socket(
     socket: WebSocket,
+    db_pool: DbPool,
     user_id: Uuid,
     session_id: Uuid,
-    speech_credentials: Option<String>,
+    speech: Option<Arc<dyn SpeechProvider>>,
 ) {
     let (mut ws_sender, mut ws_receiver) = socket.split();
 
@@ -164,6 +200,8 @@ async fn handle_voice_socket(
 
     // Current recognition session (if any)
     let mut recognition_session: Option<VoiceRecognitionSession> = None;
+    // Audio forwarded to recognition, for usage metering
+    let mut audio_bytes: u64 = 0;
 
     // Handle incoming messages
     while let Some(msg) = ws_receiver.next().await {
@@ -172,6 +210,7 @@ async fn handle_voice_socket(
                 // Binary audio data (PCM16, 16kHz mono)
                 if let Some(ref session) = recognition_session {
                     // Forward to speech recognition
+                    audio_bytes += data.len() as u64;
                     if session.audio_tx.send(data.to_vec()).is_err() {
                         warn!("Speech recognition session closed unexpectedly");
                         recognition_session = None;
@@ -204,33 +243,37 @@ async fn handle_voice_socket(
                                 session_id, language_code
                             );
 
-                            // Check if speech credentials are configured
-                            let credentials = match &speech_credentials {
-                                Some(path) => path.clone(),
-                                None => {
-                                    let error_msg = ProxyMessage::VoiceError {
-                                        session_id,
-                                        message: "Speech-to-text not configured on server"
-                                            .to_string(),
-                                    };
-                                    let _ = client_tx.send(error_msg);
-                                    continue;
-                                }
+                            let Some(speech) = &speech else {
+                                let error_msg = ProxyMessage::VoiceError {
+                                    session_id,
+                                    message: "Speech-to-text not configured on server".to_string(),
+                                };
+                                let _ = client_tx.send(error_msg);
+                                continue;
                             };
 
-                            // Create speech service with config
+                            if language_code == shared::voice::AUTO_LANGUAGE
+                                && !speech.detects_language()
+                            {
+                                let error_msg = ProxyMessage::VoiceError {
+                                    session_id,
+                                    message: "This server can't detect the spoken language; pick one instead".to_string(),
+                                };
+                                let _ = client_tx.send(error_msg);
+                                continue;
+                            }
+
+                            // Start streaming recognition
                             let config = SpeechConfig {
-                                credentials_path: Some(credentials),
-                                language_code: language_code.clone(),
+                                language_code,
                                 ..Default::default()
                             };
-                            let speech_service = SpeechService::new(config);
-
-                            // Start streaming recognition
-                            match speech_service.start_streaming(Some(language_code)).await {
-                                Ok((audio_tx, mut result_rx)) => {
-                                    recognition_session =
-                                        Some(VoiceRecognitionSession { audio_tx });
+                            match speech.start_streaming(config).await {
+                                Ok(stream) => {
+                                    recognition_session = Some(VoiceRecognitionSession {
+                                        audio_tx: stream.audio,
+                                    });
+                                    let mut result_rx = stream.transcripts;
 
                                     // Spawn task to forward transcription results to client
                                     let client_tx_clone = client_tx.clone();
@@ -261,7 +304,11 @@ async fn handle_voice_socket(
                                         );
                                     });
 
-                                    info!("Speech recognition session started for {}", session_id);
+                                    info!(
+                                        "{} speech recognition started for {}",
+                                        speech.name(),
+                                        session_id
+                                    );
                                 }
                                 Err(e) => {
                                     error!(
@@ -314,6 +361,7 @@ async fn handle_voice_socket(
     // Cleanup
     drop(recognition_session);
     send_task.abort();
+    metering::record_speech_audio(&db_pool, user_id, audio_bytes);
 
     info!(
         "Voice WebSocket disconnected for user {} on session {}",
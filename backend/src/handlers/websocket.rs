@@ -1,25 +1,44 @@
 use crate::{
+    connection::{self, ConnectionActor, Ended, Flow},
     models::{NewPendingInput, NewSessionMember, NewSessionWithId},
+    notifier::{Audience, Notification, NotificationKind, Notifiers},
+    push::WebPush,
+    rate_limit,
+    relay::{self, RelayDrops},
+    telemetry,
+    webhooks::{EventKind, Webhooks},
     AppState,
 };
+use async_trait::async_trait;
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        State,
+        ConnectInfo, State,
     },
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
 };
+use dashmap::mapref::entry::Entry;
 use dashmap::{DashMap, DashSet};
 use diesel::prelude::*;
-use futures_util::{SinkExt, StreamExt};
-use shared::ProxyMessage;
+use futures_util::StreamExt;
+use shared::compression::{self, ContentEncoding};
+use shared::protocol::{self, Compatibility};
+use shared::{
+    BudgetLevel, BudgetStatus, ClockSkew, ClockSkewReport, DecisionChannel, ErrorCode, KillSwitch,
+    MaintenanceNotice, OperatorAction, OperatorEvent, PresenceViewer, ProxyMessage,
+    ProxyTokenScopes, Redactor, SendMode, SessionLifecycle, TurnAnalyzer, UserRole,
+    OPERATOR_MESSAGE_TYPE,
+};
 use std::collections::VecDeque;
-use std::sync::Arc;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 use tower_cookies::Cookies;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, info, warn, Instrument};
 use uuid::Uuid;
 
 const SESSION_COOKIE_NAME: &str = "cc_session";
@@ -30,6 +49,13 @@ const MAX_PENDING_MESSAGES_PER_SESSION: usize = 100;
 /// Maximum age of pending messages before they're dropped (5 minutes)
 const MAX_PENDING_MESSAGE_AGE: Duration = Duration::from_secs(300);
 
+/// How often a connected guest's access is checked again, so an invite that
+/// expires ends their connection
+pub(crate) const GUEST_RECHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// What a guest is told when their connection is closed
+pub const GUEST_ACCESS_ENDED: &str = "Your guest access to this session has ended";
+
 /// A message queued for a disconnected proxy
 #[derive(Clone)]
 struct PendingMessage {
@@ -38,7 +64,63 @@ struct PendingMessage {
 }
 
 pub type SessionId = String;
-pub type ClientSender = mpsc::UnboundedSender<ProxyMessage>;
+pub type ClientSender = relay::RelaySender<ProxyMessage>;
+pub(crate) type ClientReceiver = relay::RelayReceiver<ProxyMessage>;
+/// Decoded chunks of an artifact archive, or the proxy's error
+type ArtifactChunk = Result<Vec<u8>, String>;
+pub type ArtifactReceiver = mpsc::UnboundedReceiver<ArtifactChunk>;
+
+/// A single web client connection viewing a session
+#[derive(Clone)]
+struct PresenceEntry {
+    connection_id: Uuid,
+    viewer: PresenceViewer,
+}
+
+/// Running totals for compressed output on one leg of the relay
+#[derive(Default)]
+pub struct CompressionCounter {
+    messages: AtomicU64,
+    raw_bytes: AtomicU64,
+    compressed_bytes: AtomicU64,
+}
+
+impl CompressionCounter {
+    pub fn record(&self, raw_bytes: usize, compressed_bytes: usize) {
+        self.messages.fetch_add(1, Ordering::Relaxed);
+        self.raw_bytes
+            .fetch_add(raw_bytes as u64, Ordering::Relaxed);
+        self.compressed_bytes
+            .fetch_add(compressed_bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn totals(&self) -> CompressionTotals {
+        CompressionTotals {
+            messages: self.messages.load(Ordering::Relaxed),
+            raw_bytes: self.raw_bytes.load(Ordering::Relaxed),
+            compressed_bytes: self.compressed_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Compressed output seen since the backend started
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct CompressionTotals {
+    pub messages: u64,
+    /// Size of the content before compression
+    pub raw_bytes: u64,
+    /// Size actually sent over the socket
+    pub compressed_bytes: u64,
+}
+
+/// Compression metrics for both legs of the relay
+#[derive(Default)]
+pub struct CompressionStats {
+    /// Output received compressed from proxies
+    pub from_proxies: CompressionCounter,
+    /// Output sent compressed to web clients
+    pub to_web_clients: CompressionCounter,
+}
 
 #[derive(Clone)]
 pub struct SessionManager {
@@ -54,6 +136,40 @@ pub struct SessionManager {
     pending_messages: Arc<DashMap<SessionId, VecDeque<PendingMessage>>>,
     // Set of session IDs that need message truncation (batched for efficiency)
     pub pending_truncations: Arc<DashSet<Uuid>>,
+    // Map of session_key -> web client connections currently viewing the session
+    presence: Arc<DashMap<SessionId, Vec<PresenceEntry>>>,
+    // Map of session_key -> user currently holding input control
+    drivers: Arc<DashMap<SessionId, Uuid>>,
+    // Map of session_id -> last budget level sent to clients
+    budget_levels: Arc<DashMap<Uuid, BudgetLevel>>,
+    // Map of session_id -> lifecycle state its connected proxy last reported
+    lifecycles: Arc<DashMap<Uuid, SessionLifecycle>>,
+    // Map of session_id -> turn analyzer following its stored messages
+    pub turn_analyzers: Arc<DashMap<Uuid, TurnAnalyzer>>,
+    // Map of artifact request_id -> (session_id, sender feeding the HTTP download)
+    artifact_requests: Arc<DashMap<Uuid, (Uuid, mpsc::UnboundedSender<ArtifactChunk>)>>,
+    // Bytes saved by compressing large outputs
+    pub compression: Arc<CompressionStats>,
+    // Map of session_id -> estimate of its proxy's clock skew
+    clock_skew: Arc<DashMap<Uuid, ClockSkew>>,
+    // Banner an admin has posted for every client, if any
+    maintenance: Arc<RwLock<Option<MaintenanceNotice>>>,
+    // Kill switch an admin has engaged to pause every session, if any
+    kill_switch: Arc<RwLock<Option<KillSwitch>>>,
+    // Messages shed by full relay queues
+    pub relay_drops: Arc<RelayDrops>,
+    // Proxies to drop when they next send anything (their token was revoked)
+    disconnect_requests: Arc<DashSet<SessionId>>,
+    // Map of user_id -> token cancelled to close that user's web client connections
+    user_closers: Arc<DashMap<Uuid, CancellationToken>>,
+    // Where lifecycle events are posted, if anywhere
+    webhooks: Option<Arc<Webhooks>>,
+    // Where lifecycle events are stored for the activity feed
+    activity: Option<crate::db::DbPool>,
+    // Channels finished turns and permission requests are sent on
+    pub notifiers: Option<Notifiers>,
+    // Browser push notifications, if VAPID keys are configured
+    pub push: Option<WebPush>,
 }
 
 impl Default for SessionManager {
@@ -65,6 +181,23 @@ impl Default for SessionManager {
             last_ack_seq: Arc::new(DashMap::new()),
             pending_messages: Arc::new(DashMap::new()),
             pending_truncations: Arc::new(DashSet::new()),
+            presence: Arc::new(DashMap::new()),
+            drivers: Arc::new(DashMap::new()),
+            budget_levels: Arc::new(DashMap::new()),
+            lifecycles: Arc::new(DashMap::new()),
+            turn_analyzers: Arc::new(DashMap::new()),
+            artifact_requests: Arc::new(DashMap::new()),
+            compression: Arc::new(CompressionStats::default()),
+            clock_skew: Arc::new(DashMap::new()),
+            maintenance: Arc::new(RwLock::new(None)),
+            kill_switch: Arc::new(RwLock::new(None)),
+            relay_drops: Arc::new(RelayDrops::default()),
+            disconnect_requests: Arc::new(DashSet::new()),
+            user_closers: Arc::new(DashMap::new()),
+            webhooks: None,
+            activity: None,
+            notifiers: None,
+            push: None,
         }
     }
 }
@@ -74,6 +207,59 @@ impl SessionManager {
         Self::default()
     }
 
+    /// Post session lifecycle events to these webhooks
+    pub fn with_webhooks(mut self, webhooks: Option<Webhooks>) -> Self {
+        self.webhooks = webhooks.map(Arc::new);
+        self
+    }
+
+    /// Store lifecycle events for the activity feed
+    pub fn with_activity(mut self, db_pool: crate::db::DbPool) -> Self {
+        self.activity = Some(db_pool);
+        self
+    }
+
+    /// Send finished turns and permission requests on these channels
+    pub fn with_notifiers(mut self, notifiers: Option<Notifiers>) -> Self {
+        self.notifiers = notifiers;
+        self
+    }
+
+    /// Accept browser push subscriptions for this VAPID key
+    pub fn with_push(mut self, push: Option<WebPush>) -> Self {
+        self.push = push;
+        self
+    }
+
+    /// Store a lifecycle event for the activity feed and send it to the
+    /// configured webhooks, if any
+    pub fn publish_event(&self, kind: EventKind, session_id: Uuid, data: serde_json::Value) {
+        if let Some(db_pool) = &self.activity {
+            super::activity::record(db_pool, kind, session_id, &data);
+        }
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.send(kind, session_id, data);
+        }
+    }
+
+    /// Queue for messages bound for a proxy socket
+    pub fn proxy_channel(&self) -> (ClientSender, ClientReceiver) {
+        relay::channel(
+            relay::PROXY_QUEUE_CAPACITY,
+            self.relay_drops.to_proxies.clone(),
+            "proxy",
+        )
+    }
+
+    /// Queue for messages bound for a web client socket
+    pub fn web_client_channel(&self) -> (ClientSender, ClientReceiver) {
+        relay::channel(
+            relay::WEB_CLIENT_QUEUE_CAPACITY,
+            self.relay_drops.to_web_clients.clone(),
+            "web client",
+        )
+    }
+
     pub fn register_session(&self, session_key: SessionId, sender: ClientSender) {
         info!("Registering session: {}", session_key);
 
@@ -86,9 +272,29 @@ impl SessionManager {
             );
         }
 
+        self.disconnect_requests.remove(&session_key);
         self.sessions.insert(session_key, sender);
     }
 
+    /// Tell a connected proxy it has lost access and close its connection.
+    /// The socket closes on the proxy's next message, at the latest its next
+    /// heartbeat. Returns whether the proxy was connected.
+    pub fn disconnect_proxy(&self, session_key: &SessionId, reason: &str) -> bool {
+        let Some(sender) = self.sessions.get(session_key) else {
+            return false;
+        };
+        let _ = sender.send(ProxyMessage::Error {
+            code: ErrorCode::AuthFailed,
+            message: reason.to_string(),
+        });
+        self.disconnect_requests.insert(session_key.clone());
+        true
+    }
+
+    fn take_disconnect_request(&self, session_key: &SessionId) -> bool {
+        self.disconnect_requests.remove(session_key).is_some()
+    }
+
     /// Replay pending messages to a newly connected proxy
     /// Returns the number of messages replayed
     fn replay_pending_messages(&self, session_key: &SessionId, sender: &ClientSender) -> usize {
@@ -156,6 +362,14 @@ impl SessionManager {
         self.queue_pending_message(session_key, msg)
     }
 
+    /// Send a message to a session's proxy only if it's connected right now.
+    /// Returns false if it isn't.
+    pub fn send_to_connected(&self, session_key: &SessionId, msg: ProxyMessage) -> bool {
+        self.sessions
+            .get(session_key)
+            .is_some_and(|sender| sender.send(msg).is_ok())
+    }
+
     /// Queue a message for a disconnected proxy
     fn queue_pending_message(&self, session_key: &SessionId, msg: ProxyMessage) -> bool {
         let mut queue = self
@@ -188,6 +402,11 @@ impl SessionManager {
         true
     }
 
+    /// Whether a proxy is currently connected for a session
+    pub fn is_connected(&self, session_key: &SessionId) -> bool {
+        self.sessions.contains_key(session_key)
+    }
+
     /// Get the number of pending messages for a session (for monitoring/debugging)
     #[allow(dead_code)]
     pub fn pending_message_count(&self, session_key: &SessionId) -> usize {
@@ -208,6 +427,27 @@ impl SessionManager {
         }
     }
 
+    /// Token that closes a web client connection of this user when
+    /// [`Self::close_user_connections`] is called for them
+    pub fn user_close_token(&self, user_id: Uuid) -> CancellationToken {
+        self.user_closers.entry(user_id).or_default().child_token()
+    }
+
+    /// Tell a user why, then close all their web client connections, e.g.
+    /// once their access has been taken away
+    pub fn close_user_connections(&self, user_id: Uuid, reason: &str) {
+        self.broadcast_to_user(
+            &user_id,
+            ProxyMessage::Error {
+                code: ErrorCode::AccessDenied,
+                message: reason.to_string(),
+            },
+        );
+        if let Some((_, closer)) = self.user_closers.remove(&user_id) {
+            closer.cancel();
+        }
+    }
+
     pub fn get_all_user_ids(&self) -> Vec<Uuid> {
         self.user_clients.iter().map(|r| *r.key()).collect()
     }
@@ -235,6 +475,255 @@ impl SessionManager {
         }
     }
 
+    /// The maintenance banner currently up. A notice whose window has passed
+    /// is dropped here rather than by a timer.
+    pub fn maintenance_notice(&self) -> Option<MaintenanceNotice> {
+        let mut notice = self.maintenance.write().unwrap_or_else(|e| e.into_inner());
+        if notice
+            .as_ref()
+            .is_some_and(|n| n.is_over(chrono::Utc::now()))
+        {
+            *notice = None;
+        }
+        notice.clone()
+    }
+
+    /// Replace (or clear) the maintenance banner and push it to every proxy
+    /// and web client
+    pub fn set_maintenance_notice(&self, notice: Option<MaintenanceNotice>) {
+        *self.maintenance.write().unwrap_or_else(|e| e.into_inner()) = notice.clone();
+        self.broadcast_to_all(ProxyMessage::MaintenanceBanner { notice });
+    }
+
+    /// The kill switch, if engaged
+    pub fn kill_switch(&self) -> Option<KillSwitch> {
+        self.kill_switch
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Engage (or release) the kill switch and push it to every proxy and
+    /// web client; proxies hold input and deny permission requests while
+    /// it is engaged
+    pub fn set_kill_switch(&self, state: Option<KillSwitch>) {
+        *self.kill_switch.write().unwrap_or_else(|e| e.into_inner()) = state.clone();
+        self.broadcast_to_all(ProxyMessage::KillSwitch { state });
+    }
+
+    /// Record a web client connection viewing a session and broadcast the new presence
+    pub fn join_presence(&self, session_id: Uuid, connection_id: Uuid, viewer: PresenceViewer) {
+        let key = session_id.to_string();
+        self.presence
+            .entry(key.clone())
+            .or_default()
+            .push(PresenceEntry {
+                connection_id,
+                viewer,
+            });
+        self.broadcast_presence(session_id, &key);
+    }
+
+    /// Remove a web client connection from a session and broadcast the new presence
+    pub fn leave_presence(&self, session_id: Uuid, connection_id: Uuid) {
+        let key = session_id.to_string();
+        let now_empty = match self.presence.get_mut(&key) {
+            Some(mut entries) => {
+                entries.retain(|e| e.connection_id != connection_id);
+                entries.is_empty()
+            }
+            None => return,
+        };
+        if now_empty {
+            self.presence
+                .remove_if(&key, |_, entries| entries.is_empty());
+        }
+
+        // Control is released once the driver has no connections left
+        let driver = self.drivers.get(&key).map(|d| *d);
+        if let Some(driver) = driver {
+            if !self.is_present(&key, driver) {
+                self.drivers.remove(&key);
+            }
+        }
+        self.broadcast_presence(session_id, &key);
+    }
+
+    fn is_present(&self, session_key: &SessionId, user_id: Uuid) -> bool {
+        self.presence
+            .get(session_key)
+            .is_some_and(|entries| entries.iter().any(|e| e.viewer.user_id == user_id))
+    }
+
+    /// The user currently holding input control of a session, if any
+    pub fn driver(&self, session_key: &SessionId) -> Option<Uuid> {
+        self.drivers.get(session_key).map(|d| *d)
+    }
+
+    /// Try to give a user input control of a session.
+    ///
+    /// Succeeds if the user already drives the session, nobody drives it, or the
+    /// driver has disconnected. Otherwise returns the current driver's display name.
+    pub fn claim_driver(&self, session_id: Uuid, user_id: Uuid) -> Result<(), String> {
+        let key = session_id.to_string();
+        // Check and claim under the entry's lock, so two racing claims can't both win
+        let holder = match self.drivers.entry(key.clone()) {
+            Entry::Occupied(entry) if *entry.get() == user_id => return Ok(()),
+            Entry::Occupied(entry) if self.is_present(&key, *entry.get()) => *entry.get(),
+            Entry::Occupied(mut entry) => {
+                entry.insert(user_id);
+                user_id
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(user_id);
+                user_id
+            }
+        };
+        if holder == user_id {
+            self.broadcast_presence(session_id, &key);
+            return Ok(());
+        }
+        Err(self
+            .presence_snapshot(&key)
+            .into_iter()
+            .find(|v| v.user_id == holder)
+            .map(|v| v.name)
+            .unwrap_or_else(|| "Another user".to_string()))
+    }
+
+    /// Record a session's budget status, notifying its web clients and proxy when the
+    /// level changes
+    pub fn set_budget_status(&self, session_id: Uuid, status: BudgetStatus) {
+        let previous = self
+            .budget_levels
+            .insert(session_id, status.level)
+            .unwrap_or_default();
+        if previous == status.level {
+            return;
+        }
+        if status.level > previous {
+            self.publish_event(
+                EventKind::BudgetExceeded,
+                session_id,
+                serde_json::json!({ "level": status.level, "summary": status.summary() }),
+            );
+        }
+        let key = session_id.to_string();
+        let msg = ProxyMessage::BudgetStatus { session_id, status };
+        self.broadcast_to_web_clients(&key, msg.clone());
+        self.send_to_session(&key, msg);
+    }
+
+    /// Ask a session's proxy to zip `paths`. Unlike input, the request isn't queued
+    /// for a disconnected proxy: returns None so the download can fail right away.
+    pub fn request_artifacts(
+        &self,
+        session_id: Uuid,
+        paths: Vec<String>,
+    ) -> Option<(Uuid, ArtifactReceiver)> {
+        let sender = self.sessions.get(&session_id.to_string())?;
+        let request_id = Uuid::new_v4();
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.artifact_requests.insert(request_id, (session_id, tx));
+        if sender
+            .send(ProxyMessage::ArtifactsRequest { request_id, paths })
+            .is_err()
+        {
+            self.artifact_requests.remove(&request_id);
+            return None;
+        }
+        Some((request_id, rx))
+    }
+
+    /// Stop forwarding an artifact archive, e.g. when the download timed out
+    pub fn cancel_artifacts(&self, request_id: Uuid) {
+        self.artifact_requests.remove(&request_id);
+    }
+
+    /// Forward a chunk from a session's proxy to the waiting download
+    fn forward_artifacts_chunk(
+        &self,
+        session_id: Uuid,
+        request_id: Uuid,
+        data: &str,
+        done: bool,
+        error: Option<String>,
+    ) {
+        use base64::Engine;
+
+        let chunk = match error {
+            Some(e) => Err(e),
+            None => base64::engine::general_purpose::STANDARD
+                .decode(data)
+                .map_err(|e| format!("Invalid archive chunk: {}", e)),
+        };
+        let finished = done || chunk.is_err();
+
+        let delivered = match self.artifact_requests.get(&request_id) {
+            // Only the proxy that was asked may answer
+            Some(entry) if entry.0 == session_id => entry.1.send(chunk).is_ok(),
+            Some(_) => {
+                warn!(
+                    "Artifact chunk for request {} from wrong session",
+                    request_id
+                );
+                return;
+            }
+            None => return,
+        };
+        if finished || !delivered {
+            self.artifact_requests.remove(&request_id);
+        }
+    }
+
+    /// Hand control of a session to a user (or release it), broadcasting if the driver changed.
+    ///
+    /// Returns false if the target user isn't connected to the session.
+    pub fn set_driver(&self, session_id: Uuid, user_id: Option<Uuid>) -> bool {
+        let key = session_id.to_string();
+        let previous = match user_id {
+            Some(user_id) => {
+                if !self.is_present(&key, user_id) {
+                    return false;
+                }
+                self.drivers.insert(key.clone(), user_id)
+            }
+            None => self.drivers.remove(&key).map(|(_, d)| d),
+        };
+        if previous != user_id {
+            self.broadcast_presence(session_id, &key);
+        }
+        true
+    }
+
+    /// Current viewers of a session, one entry per user in join order
+    pub fn presence_snapshot(&self, session_key: &SessionId) -> Vec<PresenceViewer> {
+        let driver = self.drivers.get(session_key).map(|d| *d);
+        let mut viewers: Vec<PresenceViewer> = Vec::new();
+        if let Some(entries) = self.presence.get(session_key) {
+            for entry in entries.iter() {
+                if viewers.iter().any(|v| v.user_id == entry.viewer.user_id) {
+                    continue;
+                }
+                let mut viewer = entry.viewer.clone();
+                viewer.driving = driver == Some(viewer.user_id);
+                viewers.push(viewer);
+            }
+        }
+        viewers
+    }
+
+    fn broadcast_presence(&self, session_id: Uuid, session_key: &SessionId) {
+        let viewers = self.presence_snapshot(session_key);
+        self.broadcast_to_web_clients(
+            session_key,
+            ProxyMessage::Presence {
+                session_id,
+                viewers,
+            },
+        );
+    }
+
     /// Queue a session for message truncation (batched for efficiency)
     /// The actual truncation runs periodically in a background task
     pub fn queue_truncation(&self, session_id: Uuid) {
@@ -249,6 +738,65 @@ impl SessionManager {
         }
         ids
     }
+
+    /// Record the lifecycle state a session's proxy reported, passing it on
+    /// to the session's web clients
+    pub fn set_lifecycle(&self, session_id: Uuid, state: SessionLifecycle) {
+        if self.lifecycles.insert(session_id, state) == Some(state) {
+            return;
+        }
+        self.broadcast_to_web_clients(
+            &session_id.to_string(),
+            ProxyMessage::StateChanged { state },
+        );
+    }
+
+    /// The session's lifecycle state, if its proxy is connected and has said
+    pub fn lifecycle(&self, session_id: Uuid) -> Option<SessionLifecycle> {
+        self.lifecycles.get(&session_id).map(|state| *state)
+    }
+
+    /// Forget a session's lifecycle state once its proxy is gone
+    pub fn clear_lifecycle(&self, session_id: Uuid) {
+        self.lifecycles.remove(&session_id);
+    }
+
+    /// Update the session's clock skew estimate from a proxy ping stamped
+    /// `sent_at` on the proxy's clock
+    pub fn record_proxy_ping(&self, session_id: Uuid, sent_at: u64) {
+        let received_at = chrono::Utc::now().timestamp_millis().max(0) as u64;
+        let mut skew = self.clock_skew.entry(session_id).or_default();
+        let was_large = skew.report().large;
+        skew.record(sent_at, received_at);
+        let report = skew.report();
+        if report.large && !was_large {
+            warn!(
+                "Proxy clock for session {} is off by {}ms",
+                session_id,
+                report.offset_ms.unwrap_or_default()
+            );
+        }
+    }
+
+    /// The session's proxy clock skew, for diagnostics
+    pub fn clock_skew_report(&self, session_id: Uuid) -> Option<ClockSkewReport> {
+        self.clock_skew.get(&session_id).map(|skew| skew.report())
+    }
+
+    /// Backend time of an output the session's proxy stamped at
+    /// `produced_at`, `None` until the proxy has pinged
+    pub fn normalize_proxy_time(
+        &self,
+        session_id: Uuid,
+        produced_at: u64,
+    ) -> Option<chrono::NaiveDateTime> {
+        let received_at = chrono::Utc::now().timestamp_millis().max(0) as u64;
+        let normalized = self
+            .clock_skew
+            .get(&session_id)?
+            .normalize(produced_at, received_at)?;
+        chrono::DateTime::from_timestamp_millis(normalized as i64).map(|t| t.naive_utc())
+    }
 }
 
 /// Replay pending inputs from the database to a reconnected proxy
@@ -303,6 +851,7 @@ fn replay_pending_inputs_from_db(
             session_id,
             seq: input.seq_num,
             content,
+            traceparent: None,
         };
 
         if sender.send(msg).is_ok() {
@@ -323,26 +872,114 @@ fn replay_pending_inputs_from_db(
     replayed
 }
 
+/// Expand output a proxy sent compressed, recording the bytes it saved.
+/// Returns `None` (after logging) if the payload cannot be decoded.
+fn expand_proxy_output(
+    session_manager: &SessionManager,
+    content: serde_json::Value,
+    encoding: Option<ContentEncoding>,
+) -> Option<serde_json::Value> {
+    let compressed_len = content.as_str().map_or(0, str::len);
+    match compression::expand_content(content, encoding) {
+        Ok((content, raw_len)) => {
+            if let Some(raw_len) = raw_len {
+                session_manager
+                    .compression
+                    .from_proxies
+                    .record(raw_len, compressed_len);
+            }
+            Some(content)
+        }
+        Err(e) => {
+            error!("Dropping undecodable compressed output: {}", e);
+            None
+        }
+    }
+}
+
+/// Compress a large `ClaudeOutput` bound for a web client that accepts it
+fn compress_client_output(msg: &mut ProxyMessage, stats: &CompressionStats) {
+    if let ProxyMessage::ClaudeOutput {
+        content,
+        content_encoding: content_encoding @ None,
+        ..
+    } = msg
+    {
+        if let Some(compressed) = compression::compress_content(content) {
+            stats
+                .to_web_clients
+                .record(compressed.raw_len, compressed.encoded_len);
+            *content = compressed.content;
+            *content_encoding = Some(ContentEncoding::Deflate);
+        }
+    }
+}
+
+/// Redact output and permission requests on their way to a viewer, before
+/// they're compressed
+fn redact_client_output(msg: &mut ProxyMessage, redactor: &std::sync::Mutex<Option<Redactor>>) {
+    match msg {
+        ProxyMessage::ClaudeOutput {
+            content,
+            content_encoding: None,
+            ..
+        } => {
+            if let Some(redactor) = redactor.lock().unwrap().as_mut() {
+                redactor.apply(content);
+            }
+        }
+        ProxyMessage::PermissionRequest {
+            tool_name, input, ..
+        } => {
+            if let Some(redactor) = redactor.lock().unwrap().as_ref() {
+                redactor.redact_input(tool_name, input);
+            }
+        }
+        _ => {}
+    }
+}
+
 /// Handle Claude output (both legacy ClaudeOutput and new SequencedOutput)
-fn handle_claude_output(
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn handle_claude_output(
     session_manager: &SessionManager,
     session_key: &Option<SessionId>,
     db_session_id: Option<Uuid>,
     db_pool: &crate::db::DbPool,
     tx: &ClientSender,
-    content: serde_json::Value,
+    mut content: serde_json::Value,
     seq: Option<u64>,
+    traceparent: Option<String>,
+    produced_at: Option<u64>,
 ) {
-    // Broadcast output to all web clients (always, even for replays)
-    if let Some(ref key) = session_key {
-        session_manager.broadcast_to_web_clients(
-            key,
-            ProxyMessage::ClaudeOutput {
-                content: content.clone(),
-            },
-        );
+    let span = tracing::info_span!("relay_output", session_id = ?db_session_id, seq);
+    telemetry::set_remote_parent(&span, traceparent.as_deref());
+    let _enter = span.enter();
+    let traceparent = telemetry::traceparent(&span, traceparent);
+
+    // Cursor movement, titles, clipboard writes and the like mean nothing in
+    // the web UI and can mislead it; only colors are stored and relayed
+    if shared::sanitize::sanitize_value(&mut content) {
+        debug!("Stripped terminal control sequences from output");
     }
 
+    // Broadcast output to all web clients (always, even for replays). The broadcast
+    // happens after storage so web clients learn the stored message ID and sequence.
+    let broadcast = |stored: Option<(Uuid, i64)>| {
+        if let Some(ref key) = session_key {
+            session_manager.broadcast_to_web_clients(
+                key,
+                ProxyMessage::ClaudeOutput {
+                    content: content.clone(),
+                    message_id: stored.map(|(id, _)| id),
+                    message_seq: stored.map(|(_, seq)| seq as u64),
+                    traceparent: traceparent.clone(),
+                    content_encoding: None,
+                },
+            );
+        }
+    };
+
     // Check for deduplication if this is a sequenced message
     if let (Some(session_id), Some(seq_num)) = (db_session_id, seq) {
         let last_ack = session_manager
@@ -357,6 +994,7 @@ fn handle_claude_output(
                 "Skipping duplicate message seq={} (last_ack={})",
                 seq_num, last_ack
             );
+            broadcast(None);
             // Still send ACK to confirm we have it
             let _ = tx.send(ProxyMessage::OutputAck {
                 session_id,
@@ -367,108 +1005,195 @@ fn handle_claude_output(
     }
 
     // Store message and update last_activity in DB
-    if let (Some(session_id), Ok(mut conn)) = (db_session_id, db_pool.get()) {
-        use crate::schema::{messages, sessions};
+    use crate::schema::{messages, sessions};
+    let Some(session_id) = db_session_id else {
+        broadcast(None);
+        return;
+    };
+    let mut conn = match db_pool.get() {
+        Ok(conn) => conn,
+        Err(e) => {
+            error!("Failed to get DB connection for output storage: {}", e);
+            broadcast(None);
+            return;
+        }
+    };
 
-        // Get user_id from session
-        if let Ok(session) = sessions::table
-            .find(session_id)
-            .first::<crate::models::Session>(&mut conn)
-        {
-            // Determine role from content type
-            let role = content
-                .get("type")
-                .and_then(|t| t.as_str())
-                .unwrap_or("assistant");
+    let mut stored_message = None;
 
-            let new_message = crate::models::NewMessage {
-                session_id,
-                role: role.to_string(),
-                content: content.to_string(),
-                user_id: session.user_id,
-            };
+    // Get user_id from session
+    if let Ok(session) = sessions::table
+        .find(session_id)
+        .first::<crate::models::Session>(&mut conn)
+    {
+        // Determine role from content type
+        let role = content
+            .get("type")
+            .and_then(|t| t.as_str())
+            .unwrap_or("assistant");
 
-            if let Err(e) = diesel::insert_into(messages::table)
-                .values(&new_message)
-                .execute(&mut conn)
-            {
-                error!("Failed to store message: {}", e);
-            }
+        let new_message = crate::models::NewMessage {
+            session_id,
+            role: role.to_string(),
+            content: content.to_string(),
+            user_id: session.user_id,
+            produced_at: produced_at
+                .and_then(|t| session_manager.normalize_proxy_time(session_id, t)),
+        };
 
-            // Extract and store cost and token usage from result messages
-            if role == "result" {
-                let cost = content.get("total_cost_usd").and_then(|c| c.as_f64());
-                // Token counts are nested under "usage" in the result message
-                let usage = content.get("usage");
-                let input_tokens = usage
-                    .and_then(|u| u.get("input_tokens"))
-                    .and_then(|t| t.as_i64());
-                let output_tokens = usage
-                    .and_then(|u| u.get("output_tokens"))
-                    .and_then(|t| t.as_i64());
-                let cache_creation = usage
-                    .and_then(|u| u.get("cache_creation_input_tokens"))
-                    .and_then(|t| t.as_i64());
-                let cache_read = usage
-                    .and_then(|u| u.get("cache_read_input_tokens"))
-                    .and_then(|t| t.as_i64());
-
-                // Update cost if present
-                if let Some(cost_val) = cost {
-                    if let Err(e) = diesel::update(sessions::table.find(session_id))
-                        .set(sessions::total_cost_usd.eq(cost_val))
-                        .execute(&mut conn)
-                    {
-                        error!("Failed to update session cost: {}", e);
-                    }
+        match diesel::insert_into(messages::table)
+            .values(&new_message)
+            .returning((messages::id, messages::seq))
+            .get_result::<(Uuid, i64)>(&mut conn)
+        {
+            Ok(stored) => stored_message = Some(stored),
+            Err(e) => error!("Failed to store message: {}", e),
+        }
+
+        // Extract and store cost and token usage from result messages
+        if role == "result" {
+            let cost = content.get("total_cost_usd").and_then(|c| c.as_f64());
+            // Token counts are nested under "usage" in the result message
+            let usage = content.get("usage");
+            let input_tokens = usage
+                .and_then(|u| u.get("input_tokens"))
+                .and_then(|t| t.as_i64());
+            let output_tokens = usage
+                .and_then(|u| u.get("output_tokens"))
+                .and_then(|t| t.as_i64());
+            let cache_creation = usage
+                .and_then(|u| u.get("cache_creation_input_tokens"))
+                .and_then(|t| t.as_i64());
+            let cache_read = usage
+                .and_then(|u| u.get("cache_read_input_tokens"))
+                .and_then(|t| t.as_i64());
+
+            // Update cost if present
+            if let Some(cost_val) = cost {
+                if let Err(e) = diesel::update(sessions::table.find(session_id))
+                    .set(sessions::total_cost_usd.eq(cost_val))
+                    .execute(&mut conn)
+                {
+                    error!("Failed to update session cost: {}", e);
                 }
+            }
 
-                // Update token counts if present
-                if input_tokens.is_some()
-                    || output_tokens.is_some()
-                    || cache_creation.is_some()
-                    || cache_read.is_some()
+            // Update token counts if present
+            if input_tokens.is_some()
+                || output_tokens.is_some()
+                || cache_creation.is_some()
+                || cache_read.is_some()
+            {
+                if let Err(e) = diesel::update(sessions::table.find(session_id))
+                    .set((
+                        sessions::input_tokens.eq(input_tokens.unwrap_or(0)),
+                        sessions::output_tokens.eq(output_tokens.unwrap_or(0)),
+                        sessions::cache_creation_tokens.eq(cache_creation.unwrap_or(0)),
+                        sessions::cache_read_tokens.eq(cache_read.unwrap_or(0)),
+                    ))
+                    .execute(&mut conn)
                 {
-                    if let Err(e) = diesel::update(sessions::table.find(session_id))
-                        .set((
-                            sessions::input_tokens.eq(input_tokens.unwrap_or(0)),
-                            sessions::output_tokens.eq(output_tokens.unwrap_or(0)),
-                            sessions::cache_creation_tokens.eq(cache_creation.unwrap_or(0)),
-                            sessions::cache_read_tokens.eq(cache_read.unwrap_or(0)),
-                        ))
-                        .execute(&mut conn)
-                    {
-                        error!("Failed to update session tokens: {}", e);
-                    }
+                    error!("Failed to update session tokens: {}", e);
                 }
             }
 
-            // Queue session for truncation (batched for efficiency)
-            session_manager.queue_truncation(session_id);
+            if let Err(e) = super::usage::record_result_usage(&mut conn, &session, &content) {
+                error!("Failed to record usage aggregates: {}", e);
+            }
+
+            if let Some(status) = super::budget::check_session(db_pool, session_id) {
+                session_manager.set_budget_status(session_id, status);
+            }
+
+            if let Some(notifiers) = &session_manager.notifiers {
+                notifiers.notify(
+                    Notification::new(
+                        &session,
+                        NotificationKind::TurnFinished {
+                            result: content.clone(),
+                        },
+                    ),
+                    Audience::Members,
+                );
+            }
+
+            if content.get("is_error").and_then(|e| e.as_bool()) == Some(true) {
+                let message = content
+                    .get("result")
+                    .and_then(|r| r.as_str())
+                    .or_else(|| content.get("subtype").and_then(|s| s.as_str()))
+                    .unwrap_or("Claude reported an error");
+                session_manager.publish_event(
+                    EventKind::Error,
+                    session_id,
+                    serde_json::json!({ "source": "claude", "message": message }),
+                );
+            }
         }
 
-        // Update last_activity
-        let _ = diesel::update(sessions::table.find(session_id))
-            .set(sessions::last_activity.eq(diesel::dsl::now))
-            .execute(&mut conn);
+        if role == "assistant" {
+            if let Err(e) = super::usage::record_tool_usage(&mut conn, &session, &content) {
+                error!("Failed to record tool usage: {}", e);
+            }
+        }
 
-        // Update last_ack tracker and send acknowledgment for sequenced messages
-        if let Some(seq_num) = seq {
-            session_manager
-                .last_ack_seq
-                .entry(session_id)
-                .and_modify(|v| {
-                    if seq_num > *v {
-                        *v = seq_num;
-                    }
-                })
-                .or_insert(seq_num);
+        if let Some(stored) = stored_message {
+            super::turns::observe(session_manager, &mut conn, session_id, stored, &content);
+        }
 
-            // Send acknowledgment back to proxy
-            let _ = tx.send(ProxyMessage::OutputAck {
-                session_id,
-                ack_seq: seq_num,
-            });
+        // Queue session for truncation (batched for efficiency)
+        session_manager.queue_truncation(session_id);
+    }
+
+    broadcast(stored_message);
+
+    // Update last_activity
+    let _ = diesel::update(sessions::table.find(session_id))
+        .set(sessions::last_activity.eq(diesel::dsl::now))
+        .execute(&mut conn);
+
+    // Update last_ack tracker and send acknowledgment for sequenced messages
+    if let Some(seq_num) = seq {
+        session_manager
+            .last_ack_seq
+            .entry(session_id)
+            .and_modify(|v| {
+                if seq_num > *v {
+                    *v = seq_num;
+                }
+            })
+            .or_insert(seq_num);
+
+        // Send acknowledgment back to proxy
+        let _ = tx.send(ProxyMessage::OutputAck {
+            session_id,
+            ack_seq: seq_num,
+        });
+    }
+}
+
+/// Drop pending inputs the proxy has acknowledged, up to and including `ack_seq`
+pub(crate) fn delete_acked_inputs(db_pool: &crate::db::DbPool, session_id: Uuid, ack_seq: i64) {
+    let Ok(mut conn) = db_pool.get() else {
+        return;
+    };
+    use crate::schema::pending_inputs;
+    let deleted = diesel::delete(
+        pending_inputs::table
+            .filter(pending_inputs::session_id.eq(session_id))
+            .filter(pending_inputs::seq_num.le(ack_seq)),
+    )
+    .execute(&mut conn);
+
+    match deleted {
+        Ok(count) => {
+            info!(
+                "Deleted {} pending inputs for session {} (ack_seq={})",
+                count, session_id, ack_seq
+            );
+        }
+        Err(e) => {
+            error!("Failed to delete pending inputs: {}", e);
         }
     }
 }
@@ -476,456 +1201,1077 @@ fn handle_claude_output(
 pub async fn handle_session_websocket(
     ws: WebSocketUpgrade,
     State(app_state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
 ) -> Response {
-    ws.on_upgrade(|socket| handle_session_socket(socket, app_state))
+    let host = super::proxy_tokens::client_host(&headers, peer);
+    // Child of the upgrade request's span, so relay logs carry its request id
+    let span = tracing::info_span!("proxy_connection", session_id = tracing::field::Empty);
+    ws.on_upgrade(|socket| handle_session_socket(socket, app_state, host).instrument(span))
 }
 
-async fn handle_session_socket(socket: WebSocket, app_state: Arc<AppState>) {
-    let session_manager = app_state.session_manager.clone();
-    let db_pool = app_state.db_pool.clone();
-    let (mut sender, mut receiver) = socket.split();
-    let (tx, mut rx) = mpsc::unbounded_channel::<ProxyMessage>();
-
-    let mut session_key: Option<SessionId> = None;
-    let mut db_session_id: Option<Uuid> = None;
-
-    // Spawn task to send messages to the WebSocket
-    let send_task = tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
-            if let Ok(json) = serde_json::to_string(&msg) {
-                if sender.send(Message::Text(json)).await.is_err() {
-                    break;
-                }
-            }
+/// Answer a client's protocol version with `Hello` (only if it sent one;
+/// older clients don't know the message) and decide whether to serve it.
+/// Returns the rejection message for clients the server no longer supports.
+fn negotiate_protocol(tx: &ClientSender, client: &str, version: Option<u32>) -> Result<(), String> {
+    let compatibility = protocol::check_peer(version);
+    let (negotiated, error) = match compatibility {
+        Compatibility::Current => (protocol::PROTOCOL_VERSION, None),
+        Compatibility::Degraded { negotiated } => {
+            info!(
+                "{} speaks protocol {:?}, server speaks {}; using {}",
+                client,
+                version,
+                protocol::PROTOCOL_VERSION,
+                negotiated
+            );
+            (negotiated, None)
         }
-    });
+        Compatibility::TooOld => {
+            warn!(
+                "Rejecting {} with unsupported protocol {:?}",
+                client, version
+            );
+            (
+                protocol::PROTOCOL_VERSION,
+                Some(protocol::too_old_message(version)),
+            )
+        }
+    };
+    if version.is_some() {
+        let _ = tx.send(ProxyMessage::Hello {
+            protocol_version: protocol::PROTOCOL_VERSION,
+            min_protocol_version: protocol::MIN_PROTOCOL_VERSION,
+            server_version: env!("CARGO_PKG_VERSION").to_string(),
+            negotiated_version: negotiated,
+            error: error.clone(),
+        });
+    }
+    error.map_or(Ok(()), Err)
+}
 
-    // Handle incoming messages
-    while let Some(msg) = receiver.next().await {
-        match msg {
-            Ok(Message::Text(text)) => {
-                if let Ok(proxy_msg) = serde_json::from_str::<ProxyMessage>(&text) {
-                    match proxy_msg {
-                        ProxyMessage::Register {
-                            session_id: claude_session_id,
-                            session_name,
-                            auth_token,
-                            working_directory,
-                            resuming,
-                            git_branch,
-                            replay_after: _, // Not used for proxy connections
-                            client_version,
-                        } => {
-                            // Use session_id as the key for in-memory tracking
-                            let key = claude_session_id.to_string();
-                            session_key = Some(key.clone());
-
-                            // Register in memory
-                            session_manager.register_session(key.clone(), tx.clone());
-
-                            // Track registration result for RegisterAck
-                            let mut registration_success = false;
-                            let mut registration_error: Option<String> = None;
-
-                            // Persist to database
-                            if let Ok(mut conn) = db_pool.get() {
-                                use crate::schema::sessions;
-
-                                // Look up by the Claude session ID (which is now our primary key)
-                                let existing: Option<crate::models::Session> = sessions::table
-                                    .find(claude_session_id)
-                                    .first(&mut conn)
-                                    .optional()
-                                    .unwrap_or(None);
-
-                                if let Some(existing_session) = existing {
-                                    // Update existing session to active
-                                    match diesel::update(sessions::table.find(existing_session.id))
-                                        .set((
-                                            sessions::status.eq("active"),
-                                            sessions::last_activity.eq(diesel::dsl::now),
-                                            sessions::working_directory.eq(&working_directory),
-                                            sessions::git_branch.eq(&git_branch),
-                                            sessions::client_version.eq(&client_version),
-                                        ))
-                                        .execute(&mut conn)
-                                    {
-                                        Ok(_) => {
-                                            db_session_id = Some(existing_session.id);
-                                            registration_success = true;
-                                            info!(
-                                                "Session reactivated in DB: {} ({}) branch: {:?}",
-                                                session_name, claude_session_id, git_branch
-                                            );
-                                        }
-                                        Err(e) => {
-                                            error!("Failed to reactivate session: {}", e);
-                                            registration_error =
-                                                Some("Failed to reactivate session".to_string());
-                                        }
-                                    }
-                                } else if resuming {
-                                    // Trying to resume but session doesn't exist in DB
-                                    // This can happen if the session was deleted or is on a different backend
-                                    warn!("Resuming session {} but not found in DB, creating new entry", claude_session_id);
-
-                                    let user_id =
-                                        get_user_id_from_token(&app_state, auth_token.as_deref());
-                                    if let Some(user_id) = user_id {
-                                        let new_session = NewSessionWithId {
-                                            id: claude_session_id,
-                                            user_id,
-                                            session_name: session_name.clone(),
-                                            session_key: key.clone(),
-                                            working_directory: working_directory.clone(),
-                                            status: "active".to_string(),
-                                            git_branch: git_branch.clone(),
-                                            client_version: client_version.clone(),
-                                        };
-
-                                        match diesel::insert_into(sessions::table)
-                                            .values(&new_session)
-                                            .get_result::<crate::models::Session>(&mut conn)
-                                        {
-                                            Ok(session) => {
-                                                // Create session_members entry for the owner
-                                                use crate::schema::session_members;
-                                                let new_member = NewSessionMember {
-                                                    session_id: session.id,
-                                                    user_id,
-                                                    role: "owner".to_string(),
-                                                };
-                                                if let Err(e) =
-                                                    diesel::insert_into(session_members::table)
-                                                        .values(&new_member)
-                                                        .execute(&mut conn)
-                                                {
-                                                    error!(
-                                                        "Failed to create session_member: {}",
-                                                        e
-                                                    );
-                                                }
-
-                                                db_session_id = Some(session.id);
-                                                registration_success = true;
-                                                info!(
-                                                    "Session created in DB: {} ({}) branch: {:?}",
-                                                    session_name, claude_session_id, git_branch
-                                                );
-                                            }
-                                            Err(e) => {
-                                                error!("Failed to persist session: {}", e);
-                                                registration_error =
-                                                    Some("Failed to persist session".to_string());
-                                            }
-                                        }
-                                    } else {
-                                        warn!("No valid user_id for session, not persisting to DB");
-                                        registration_error = Some(
-                                            "Authentication failed - please re-authenticate"
-                                                .to_string(),
-                                        );
-                                    }
-                                } else {
-                                    // Create new session with the provided session_id as primary key
-                                    let user_id =
-                                        get_user_id_from_token(&app_state, auth_token.as_deref());
-
-                                    if let Some(user_id) = user_id {
-                                        let new_session = NewSessionWithId {
-                                            id: claude_session_id,
-                                            user_id,
-                                            session_name: session_name.clone(),
-                                            session_key: key.clone(),
-                                            working_directory: working_directory.clone(),
-                                            status: "active".to_string(),
-                                            git_branch: git_branch.clone(),
-                                            client_version: client_version.clone(),
-                                        };
-
-                                        match diesel::insert_into(sessions::table)
-                                            .values(&new_session)
-                                            .get_result::<crate::models::Session>(&mut conn)
-                                        {
-                                            Ok(session) => {
-                                                // Create session_members entry for the owner
-                                                use crate::schema::session_members;
-                                                let new_member = NewSessionMember {
-                                                    session_id: session.id,
-                                                    user_id,
-                                                    role: "owner".to_string(),
-                                                };
-                                                if let Err(e) =
-                                                    diesel::insert_into(session_members::table)
-                                                        .values(&new_member)
-                                                        .execute(&mut conn)
-                                                {
-                                                    error!(
-                                                        "Failed to create session_member: {}",
-                                                        e
-                                                    );
-                                                }
-
-                                                db_session_id = Some(session.id);
-                                                registration_success = true;
-                                                info!(
-                                                    "Session persisted to DB: {} ({}) branch: {:?}",
-                                                    session_name, claude_session_id, git_branch
-                                                );
-                                            }
-                                            Err(e) => {
-                                                error!("Failed to persist session: {}", e);
-                                                registration_error =
-                                                    Some("Failed to persist session".to_string());
-                                            }
-                                        }
-                                    } else {
-                                        warn!("No valid user_id for session, not persisting to DB");
-                                        registration_error = Some(
-                                            "Authentication failed - please re-authenticate"
-                                                .to_string(),
-                                        );
+/// Count a socket message against its sender's budget. Over the limit the
+/// sender is told to slow down and the message is dropped; a proxy resends
+/// sequenced output it never got an ack for when it reconnects.
+fn within_message_limit(
+    app_state: &AppState,
+    tx: &ClientSender,
+    key: &str,
+    msg: &ProxyMessage,
+) -> bool {
+    if matches!(msg, ProxyMessage::Heartbeat) {
+        return true;
+    }
+    let per_minute = app_state
+        .runtime_config
+        .current()
+        .rate_limit_messages_per_minute;
+    match app_state.rate_limits.messages.check(key, per_minute) {
+        Ok(()) => true,
+        Err(wait) => {
+            debug!("Dropping socket message over the rate limit for {}", key);
+            let _ = tx.send(ProxyMessage::Error {
+                code: ErrorCode::RateLimited,
+                message: format!(
+                    "Too many messages; try again in {}s",
+                    rate_limit::retry_after_secs(wait)
+                ),
+            });
+            false
+        }
+    }
+}
+
+/// `host` is where the proxy connected from, recorded against its token
+async fn handle_session_socket(socket: WebSocket, app_state: Arc<AppState>, host: String) {
+    let (sender, receiver) = socket.split();
+    serve_session(sender, receiver, app_state, host).await;
+}
+
+/// Serve one session's proxy connection, whether it has a socket of its own
+/// or shares a daemon's (see `handlers::daemon`)
+pub async fn serve_session<W, R>(sender: W, receiver: R, app_state: Arc<AppState>, host: String)
+where
+    W: futures_util::Sink<Message> + Unpin + Send + 'static,
+    R: futures_util::Stream<Item = Result<Message, axum::Error>> + Unpin,
+{
+    let (tx, rx) = app_state.session_manager.proxy_channel();
+    let proxy = ProxyConnection {
+        app_state,
+        host,
+        tx,
+        session_key: None,
+        db_session_id: None,
+        rate_key: None,
+        token_scopes: None,
+    };
+    connection::serve(proxy, sender, receiver, rx, |_| {}).await;
+}
+
+/// State of one proxy connection
+struct ProxyConnection {
+    app_state: Arc<AppState>,
+    /// Where the proxy connected from, recorded against its token
+    host: String,
+    tx: ClientSender,
+    session_key: Option<SessionId>,
+    db_session_id: Option<Uuid>,
+    /// Message budget key, known once the proxy registers
+    rate_key: Option<String>,
+    /// Restrictions of the token the proxy registered with
+    token_scopes: Option<ProxyTokenScopes>,
+}
+
+#[async_trait]
+impl ConnectionActor for ProxyConnection {
+    type Message = ProxyMessage;
+    const PEER: &'static str = "Proxy";
+
+    fn is_heartbeat(msg: &ProxyMessage) -> bool {
+        matches!(msg, ProxyMessage::Ping { .. })
+    }
+
+    async fn handle(&mut self, proxy_msg: ProxyMessage) -> Flow {
+        let app_state = &self.app_state;
+        let session_manager = &app_state.session_manager;
+        let db_pool = &app_state.db_pool;
+        let tx = &self.tx;
+        let host = &self.host;
+
+        if let Some(key) = &self.session_key {
+            if session_manager.take_disconnect_request(key) {
+                info!(
+                    "Closing proxy connection for session {}: access revoked",
+                    key
+                );
+                return Flow::Stop;
+            }
+        }
+        if let Some(key) = &self.rate_key {
+            if !within_message_limit(app_state, tx, key, &proxy_msg) {
+                return Flow::Continue;
+            }
+        }
+
+        match proxy_msg {
+            ProxyMessage::Register {
+                session_id: claude_session_id,
+                session_name,
+                auth_token,
+                working_directory,
+                resuming,
+                git_branch,
+                git_repo,
+                host_info,
+                replay_after: _, // Not used for proxy connections
+                last_seq: _,
+                client_version,
+                accept_encodings,
+                protocol_version,
+            } => {
+                if let Err(error) = negotiate_protocol(&tx, "Proxy", protocol_version) {
+                    let _ = tx.send(ProxyMessage::RegisterAck {
+                        success: false,
+                        session_id: claude_session_id,
+                        error: Some(error),
+                        error_code: Some(ErrorCode::IncompatibleProtocol),
+                        content_encoding: None,
+                    });
+                    return Flow::Continue;
+                }
+                if let Err(error) =
+                    check_session_quota(&app_state, claude_session_id, auth_token.as_deref())
+                {
+                    let _ = tx.send(ProxyMessage::RegisterAck {
+                        success: false,
+                        session_id: claude_session_id,
+                        error: Some(error),
+                        error_code: Some(ErrorCode::QuotaExceeded),
+                        content_encoding: None,
+                    });
+                    return Flow::Continue;
+                }
+
+                if auth_token.as_deref().is_some_and(|token| {
+                    db_pool
+                        .get()
+                        .is_ok_and(|mut conn| super::proxy_tokens::is_revoked(&mut conn, token))
+                }) {
+                    warn!(
+                        "Rejecting session {}: proxy token was revoked",
+                        claude_session_id
+                    );
+                    let _ = tx.send(ProxyMessage::RegisterAck {
+                        success: false,
+                        session_id: claude_session_id,
+                        error: Some(
+                            "This proxy token was revoked - please re-authenticate".to_string(),
+                        ),
+                        error_code: Some(ErrorCode::AuthFailed),
+                        content_encoding: None,
+                    });
+                    return Flow::Continue;
+                }
+
+                match check_token_scopes(
+                    &app_state,
+                    auth_token.as_deref(),
+                    claude_session_id,
+                    &working_directory,
+                ) {
+                    Ok(scopes) => self.token_scopes = scopes,
+                    Err((code, error)) => {
+                        warn!("Rejecting session {}: {}", claude_session_id, error);
+                        let _ = tx.send(ProxyMessage::RegisterAck {
+                            success: false,
+                            session_id: claude_session_id,
+                            error: Some(error),
+                            error_code: Some(code),
+                            content_encoding: None,
+                        });
+                        return Flow::Continue;
+                    }
+                }
+
+                // Use session_id as the key for in-memory tracking
+                let key = claude_session_id.to_string();
+                self.session_key = Some(key.clone());
+                self.rate_key = Some(
+                    auth_token
+                        .as_deref()
+                        .map(rate_limit::token_key)
+                        .unwrap_or_else(|| format!("session:{}", claude_session_id)),
+                );
+
+                // Register in memory
+                session_manager.register_session(key.clone(), tx.clone());
+
+                // Track registration result for RegisterAck
+                let mut registration_success = false;
+                let mut registration_error: Option<(ErrorCode, String)> = None;
+
+                let host_info_json = host_info
+                    .as_ref()
+                    .and_then(|info| serde_json::to_value(info).ok());
+
+                // Persist to database
+                if let Ok(mut conn) = db_pool.get() {
+                    use crate::schema::sessions;
+
+                    // Look up by the Claude session ID (which is now our primary key)
+                    let existing: Option<crate::models::Session> = sessions::table
+                        .find(claude_session_id)
+                        .first(&mut conn)
+                        .optional()
+                        .unwrap_or(None);
+
+                    if let Some(existing_session) = existing {
+                        // Update existing session to active
+                        match diesel::update(sessions::table.find(existing_session.id))
+                            .set((
+                                sessions::status.eq("active"),
+                                sessions::last_activity.eq(diesel::dsl::now),
+                                sessions::working_directory.eq(&working_directory),
+                                sessions::git_branch.eq(&git_branch),
+                                sessions::git_repo.eq(&git_repo),
+                                sessions::host_info.eq(&host_info_json),
+                                sessions::client_version.eq(&client_version),
+                            ))
+                            .execute(&mut conn)
+                        {
+                            Ok(_) => {
+                                self.db_session_id = Some(existing_session.id);
+                                registration_success = true;
+                                info!(
+                                    "Session reactivated in DB: {} ({}) branch: {:?}",
+                                    session_name, claude_session_id, git_branch
+                                );
+                            }
+                            Err(e) => {
+                                error!("Failed to reactivate session: {}", e);
+                                registration_error = Some((
+                                    ErrorCode::Internal,
+                                    "Failed to reactivate session".to_string(),
+                                ));
+                            }
+                        }
+                    } else if resuming {
+                        // Trying to resume but session doesn't exist in DB
+                        // This can happen if the session was deleted or is on a different backend
+                        warn!(
+                            "Resuming session {} but not found in DB, creating new entry",
+                            claude_session_id
+                        );
+
+                        let user_id = get_user_id_from_token(&app_state, auth_token.as_deref());
+                        if let Some(user_id) = user_id {
+                            let new_session = NewSessionWithId {
+                                id: claude_session_id,
+                                user_id,
+                                session_name: session_name.clone(),
+                                session_key: key.clone(),
+                                working_directory: working_directory.clone(),
+                                status: "active".to_string(),
+                                git_branch: git_branch.clone(),
+                                git_repo: git_repo.clone(),
+                                host_info: host_info_json.clone(),
+                                client_version: client_version.clone(),
+                            };
+
+                            match diesel::insert_into(sessions::table)
+                                .values(&new_session)
+                                .get_result::<crate::models::Session>(&mut conn)
+                            {
+                                Ok(session) => {
+                                    // Create session_members entry for the owner
+                                    use crate::schema::session_members;
+                                    let new_member = NewSessionMember {
+                                        session_id: session.id,
+                                        user_id,
+                                        role: "owner".to_string(),
+                                    };
+                                    if let Err(e) = diesel::insert_into(session_members::table)
+                                        .values(&new_member)
+                                        .execute(&mut conn)
+                                    {
+                                        error!("Failed to create session_member: {}", e);
                                     }
+
+                                    self.db_session_id = Some(session.id);
+                                    registration_success = true;
+                                    info!(
+                                        "Session created in DB: {} ({}) branch: {:?}",
+                                        session_name, claude_session_id, git_branch
+                                    );
+                                }
+                                Err(e) => {
+                                    error!("Failed to persist session: {}", e);
+                                    registration_error = Some((
+                                        ErrorCode::Internal,
+                                        "Failed to persist session".to_string(),
+                                    ));
                                 }
-                            } else {
-                                error!("Failed to get database connection");
-                                registration_error = Some("Database connection failed".to_string());
                             }
-
-                            // Send RegisterAck to proxy
-                            let ack = ProxyMessage::RegisterAck {
-                                success: registration_success,
-                                session_id: claude_session_id,
-                                error: registration_error,
+                        } else {
+                            warn!("No valid user_id for session, not persisting to DB");
+                            registration_error = Some((
+                                ErrorCode::AuthFailed,
+                                "Authentication failed - please re-authenticate".to_string(),
+                            ));
+                        }
+                    } else {
+                        // Create new session with the provided session_id as primary key
+                        let user_id = get_user_id_from_token(&app_state, auth_token.as_deref());
+
+                        if let Some(user_id) = user_id {
+                            let new_session = NewSessionWithId {
+                                id: claude_session_id,
+                                user_id,
+                                session_name: session_name.clone(),
+                                session_key: key.clone(),
+                                working_directory: working_directory.clone(),
+                                status: "active".to_string(),
+                                git_branch: git_branch.clone(),
+                                git_repo: git_repo.clone(),
+                                host_info: host_info_json.clone(),
+                                client_version: client_version.clone(),
                             };
-                            let _ = tx.send(ack);
 
-                            info!(
-                                "Session registered: {} ({}) - success: {}, client_version: {:?}",
-                                session_name,
-                                claude_session_id,
-                                registration_success,
-                                client_version
-                            );
+                            match diesel::insert_into(sessions::table)
+                                .values(&new_session)
+                                .get_result::<crate::models::Session>(&mut conn)
+                            {
+                                Ok(session) => {
+                                    // Create session_members entry for the owner
+                                    use crate::schema::session_members;
+                                    let new_member = NewSessionMember {
+                                        session_id: session.id,
+                                        user_id,
+                                        role: "owner".to_string(),
+                                    };
+                                    if let Err(e) = diesel::insert_into(session_members::table)
+                                        .values(&new_member)
+                                        .execute(&mut conn)
+                                    {
+                                        error!("Failed to create session_member: {}", e);
+                                    }
 
-                            // Replay any pending inputs from the database to the reconnected proxy
-                            if registration_success {
-                                if let Some(session_id) = db_session_id {
-                                    replay_pending_inputs_from_db(&db_pool, session_id, &tx);
+                                    self.db_session_id = Some(session.id);
+                                    registration_success = true;
+                                    info!(
+                                        "Session persisted to DB: {} ({}) branch: {:?}",
+                                        session_name, claude_session_id, git_branch
+                                    );
+                                }
+                                Err(e) => {
+                                    error!("Failed to persist session: {}", e);
+                                    registration_error = Some((
+                                        ErrorCode::Internal,
+                                        "Failed to persist session".to_string(),
+                                    ));
                                 }
                             }
+                        } else {
+                            warn!("No valid user_id for session, not persisting to DB");
+                            registration_error = Some((
+                                ErrorCode::AuthFailed,
+                                "Authentication failed - please re-authenticate".to_string(),
+                            ));
                         }
-                        ProxyMessage::ClaudeOutput { content } => {
-                            // Legacy: Handle unsequenced output (for backwards compatibility)
-                            handle_claude_output(
-                                &session_manager,
-                                &session_key,
-                                db_session_id,
-                                &db_pool,
-                                &tx,
-                                content,
-                                None, // No sequence number
-                            );
-                        }
-                        ProxyMessage::SequencedOutput { seq, content } => {
-                            // New: Handle sequenced output with acknowledgment
-                            handle_claude_output(
-                                &session_manager,
-                                &session_key,
-                                db_session_id,
-                                &db_pool,
-                                &tx,
-                                content,
-                                Some(seq),
+                    }
+                } else {
+                    error!("Failed to get database connection");
+                    registration_error = Some((
+                        ErrorCode::Internal,
+                        "Database connection failed".to_string(),
+                    ));
+                }
+
+                if registration_success {
+                    if let (Some(token), Ok(mut conn)) = (auth_token.as_deref(), db_pool.get()) {
+                        super::proxy_tokens::record_token_use(
+                            &mut conn,
+                            token,
+                            &host,
+                            Some(claude_session_id),
+                        );
+                    }
+                }
+
+                // Send RegisterAck to proxy
+                let ack = ProxyMessage::RegisterAck {
+                    success: registration_success,
+                    session_id: claude_session_id,
+                    error_code: registration_error.as_ref().map(|(code, _)| *code),
+                    error: registration_error.map(|(_, message)| message),
+                    content_encoding: compression::negotiate_encoding(&accept_encodings),
+                };
+                let _ = tx.send(ack);
+                if let Some(notice) = session_manager.maintenance_notice() {
+                    let _ = tx.send(ProxyMessage::MaintenanceBanner {
+                        notice: Some(notice),
+                    });
+                }
+                // Sent even when released: a proxy that was away
+                // may still be holding input
+                let _ = tx.send(ProxyMessage::KillSwitch {
+                    state: session_manager.kill_switch(),
+                });
+
+                if let Some(session_id) = self.db_session_id {
+                    tracing::Span::current()
+                        .record("session_id", tracing::field::display(session_id));
+                }
+                info!(
+                    "Session registered: {} ({}) - success: {}, client_version: {:?}",
+                    session_name, claude_session_id, registration_success, client_version
+                );
+
+                // Replay any pending inputs from the database to the reconnected proxy
+                if registration_success {
+                    if let Some(session_id) = self.db_session_id {
+                        replay_pending_inputs_from_db(&db_pool, session_id, &tx);
+                        session_manager.publish_event(
+                            EventKind::SessionStarted,
+                            session_id,
+                            serde_json::json!({
+                                "session_name": session_name,
+                                "working_directory": working_directory,
+                                "git_branch": git_branch,
+                                "resuming": resuming,
+                            }),
+                        );
+                    }
+                }
+            }
+            ProxyMessage::ClaudeOutput {
+                content,
+                traceparent,
+                content_encoding,
+                ..
+            } => {
+                let Some(content) =
+                    expand_proxy_output(&session_manager, content, content_encoding)
+                else {
+                    return Flow::Continue;
+                };
+                // Legacy: Handle unsequenced output (for backwards compatibility)
+                handle_claude_output(
+                    &session_manager,
+                    &self.session_key,
+                    self.db_session_id,
+                    &db_pool,
+                    &tx,
+                    content,
+                    None, // No sequence number
+                    traceparent,
+                    None,
+                );
+            }
+            ProxyMessage::SequencedOutput {
+                seq,
+                content,
+                traceparent,
+                content_encoding,
+                produced_at,
+            } => {
+                let Some(content) =
+                    expand_proxy_output(&session_manager, content, content_encoding)
+                else {
+                    return Flow::Continue;
+                };
+                // New: Handle sequenced output with acknowledgment
+                handle_claude_output(
+                    &session_manager,
+                    &self.session_key,
+                    self.db_session_id,
+                    &db_pool,
+                    &tx,
+                    content,
+                    Some(seq),
+                    traceparent,
+                    produced_at,
+                );
+            }
+            ProxyMessage::Heartbeat => {
+                // Respond to heartbeat
+                let _ = tx.send(ProxyMessage::Heartbeat);
+            }
+            ProxyMessage::Ping { sent_at } => {
+                if let Some(session_id) = self.db_session_id {
+                    session_manager.record_proxy_ping(session_id, sent_at);
+                }
+                let _ = tx.send(ProxyMessage::Pong { sent_at });
+            }
+            ProxyMessage::StateChanged { state } => {
+                if let Some(session_id) = self.db_session_id {
+                    session_manager.set_lifecycle(session_id, state);
+                }
+            }
+            ProxyMessage::PermissionRequest {
+                request_id,
+                tool_name,
+                input,
+                permission_suggestions,
+            } => {
+                if let Some(scopes) = &self.token_scopes {
+                    if !scopes.allows_tool(&tool_name) {
+                        deny_out_of_scope(
+                            &db_pool,
+                            &tx,
+                            self.db_session_id,
+                            request_id,
+                            &tool_name,
+                            &input,
+                        );
+                        return Flow::Continue;
+                    }
+                }
+
+                // Store permission request in database for replay on reconnect
+                if let (Some(session_id), Ok(mut conn)) = (self.db_session_id, db_pool.get()) {
+                    use crate::schema::pending_permission_requests;
+
+                    let new_request = crate::models::NewPendingPermissionRequest {
+                        session_id,
+                        request_id: request_id.clone(),
+                        tool_name: tool_name.clone(),
+                        input: input.clone(),
+                        permission_suggestions: if permission_suggestions.is_empty() {
+                            None
+                        } else {
+                            Some(serde_json::to_value(&permission_suggestions).unwrap_or_default())
+                        },
+                    };
+
+                    // Use upsert to replace any existing pending request for this session
+                    if let Err(e) = diesel::insert_into(pending_permission_requests::table)
+                        .values(&new_request)
+                        .on_conflict(pending_permission_requests::session_id)
+                        .do_update()
+                        .set((
+                            pending_permission_requests::request_id.eq(&request_id),
+                            pending_permission_requests::tool_name.eq(&tool_name),
+                            pending_permission_requests::input.eq(&input),
+                            pending_permission_requests::permission_suggestions.eq(
+                                if permission_suggestions.is_empty() {
+                                    None
+                                } else {
+                                    Some(
+                                        serde_json::to_value(&permission_suggestions)
+                                            .unwrap_or_default(),
+                                    )
+                                },
+                            ),
+                            pending_permission_requests::created_at.eq(diesel::dsl::now),
+                        ))
+                        .execute(&mut conn)
+                    {
+                        error!("Failed to store pending permission request: {}", e);
+                    }
+                }
+                if let Some(session_id) = self.db_session_id {
+                    super::permission_history::record_request(
+                        &db_pool,
+                        session_id,
+                        &request_id,
+                        &tool_name,
+                        &input,
+                    );
+                    session_manager.publish_event(
+                        EventKind::PermissionRequested,
+                        session_id,
+                        serde_json::json!({
+                            "request_id": request_id,
+                            "tool_name": tool_name,
+                            "input": input,
+                        }),
+                    );
+                    if let Some(notifiers) = &session_manager.notifiers {
+                        let session = db_pool.get().ok().and_then(|mut conn| {
+                            crate::schema::sessions::table
+                                .find(session_id)
+                                .first::<crate::models::Session>(&mut conn)
+                                .ok()
+                        });
+                        if let Some(session) = session {
+                            notifiers.notify(
+                                Notification::new(
+                                    &session,
+                                    NotificationKind::PermissionRequested {
+                                        tool_name: tool_name.clone(),
+                                    },
+                                ),
+                                Audience::Members,
                             );
                         }
-                        ProxyMessage::Heartbeat => {
-                            // Respond to heartbeat
-                            let _ = tx.send(ProxyMessage::Heartbeat);
-                        }
+                    }
+                }
+
+                // Forward permission request to all web clients
+                if let Some(ref key) = self.session_key {
+                    info!("Permission request from proxy for tool: {} (request_id: {}, suggestions: {})", tool_name, request_id, permission_suggestions.len());
+                    session_manager.broadcast_to_web_clients(
+                        key,
                         ProxyMessage::PermissionRequest {
                             request_id,
                             tool_name,
                             input,
                             permission_suggestions,
-                        } => {
-                            // Store permission request in database for replay on reconnect
-                            if let (Some(session_id), Ok(mut conn)) = (db_session_id, db_pool.get())
+                        },
+                    );
+                }
+            }
+            ProxyMessage::PermissionRuleApplied {
+                request_id,
+                tool_name,
+                input,
+                allow,
+                rule,
+            } => {
+                if let Some(session_id) = self.db_session_id {
+                    info!(
+                        "Permission request {} for {} answered by rule {} (allow: {})",
+                        request_id, tool_name, rule, allow
+                    );
+                    super::permission_history::record_rule_decision(
+                        &db_pool,
+                        session_id,
+                        &request_id,
+                        &tool_name,
+                        &input,
+                        super::permission_history::Decision {
+                            allowed: allow,
+                            channel: DecisionChannel::Rule,
+                            decided_by: None,
+                            detail: Some(rule),
+                        },
+                    );
+                }
+            }
+            ProxyMessage::PermissionAnsweredInTerminal { request_id, allow } => {
+                if let Some(session_id) = self.db_session_id {
+                    info!(
+                        "Permission request {} answered in the proxy's terminal (allow: {})",
+                        request_id, allow
+                    );
+                    super::permission_history::record_decision(
+                        &db_pool,
+                        session_id,
+                        &request_id,
+                        super::permission_history::Decision {
+                            allowed: allow,
+                            channel: DecisionChannel::Terminal,
+                            decided_by: None,
+                            detail: None,
+                        },
+                    );
+                    if let Ok(mut conn) = db_pool.get() {
+                        use crate::schema::pending_permission_requests;
+                        if let Err(e) = diesel::delete(
+                            pending_permission_requests::table
+                                .filter(pending_permission_requests::session_id.eq(session_id))
+                                .filter(pending_permission_requests::request_id.eq(&request_id)),
+                        )
+                        .execute(&mut conn)
+                        {
+                            error!("Failed to clear pending permission request: {}", e);
+                        }
+                    }
+                    if let Some(ref key) = self.session_key {
+                        session_manager.broadcast_to_web_clients(
+                            key,
+                            ProxyMessage::PermissionResolved { request_id, allow },
+                        );
+                    }
+                }
+            }
+            ProxyMessage::PermissionDeniedByKillSwitch {
+                request_id,
+                tool_name,
+                input,
+                forwarded,
+            } => {
+                if let Some(session_id) = self.db_session_id {
+                    info!(
+                        "Permission request {} for {} denied by the kill switch",
+                        request_id, tool_name
+                    );
+                    let decision = super::permission_history::Decision {
+                        allowed: false,
+                        channel: DecisionChannel::KillSwitch,
+                        decided_by: None,
+                        detail: session_manager.kill_switch().map(|k| k.reason),
+                    };
+                    if !forwarded {
+                        super::permission_history::record_rule_decision(
+                            &db_pool,
+                            session_id,
+                            &request_id,
+                            &tool_name,
+                            &input,
+                            decision,
+                        );
+                    } else {
+                        // Already on screen; record it and dismiss it
+                        // like a web answer
+                        super::permission_history::record_decision(
+                            &db_pool,
+                            session_id,
+                            &request_id,
+                            decision,
+                        );
+                        if let Ok(mut conn) = db_pool.get() {
+                            use crate::schema::pending_permission_requests;
+                            if let Err(e) = diesel::delete(
+                                pending_permission_requests::table
+                                    .filter(pending_permission_requests::session_id.eq(session_id))
+                                    .filter(
+                                        pending_permission_requests::request_id.eq(&request_id),
+                                    ),
+                            )
+                            .execute(&mut conn)
                             {
-                                use crate::schema::pending_permission_requests;
-
-                                let new_request = crate::models::NewPendingPermissionRequest {
-                                    session_id,
-                                    request_id: request_id.clone(),
-                                    tool_name: tool_name.clone(),
-                                    input: input.clone(),
-                                    permission_suggestions: if permission_suggestions.is_empty() {
-                                        None
-                                    } else {
-                                        Some(
-                                            serde_json::to_value(&permission_suggestions)
-                                                .unwrap_or_default(),
-                                        )
-                                    },
-                                };
-
-                                // Use upsert to replace any existing pending request for this session
-                                if let Err(e) =
-                                    diesel::insert_into(pending_permission_requests::table)
-                                        .values(&new_request)
-                                        .on_conflict(pending_permission_requests::session_id)
-                                        .do_update()
-                                        .set((
-                                            pending_permission_requests::request_id.eq(&request_id),
-                                            pending_permission_requests::tool_name.eq(&tool_name),
-                                            pending_permission_requests::input.eq(&input),
-                                            pending_permission_requests::permission_suggestions.eq(
-                                                if permission_suggestions.is_empty() {
-                                                    None
-                                                } else {
-                                                    Some(
-                                                        serde_json::to_value(
-                                                            &permission_suggestions,
-                                                        )
-                                                        .unwrap_or_default(),
-                                                    )
-                                                },
-                                            ),
-                                            pending_permission_requests::created_at
-                                                .eq(diesel::dsl::now),
-                                        ))
-                                        .execute(&mut conn)
-                                {
-                                    error!("Failed to store pending permission request: {}", e);
-                                }
+                                error!("Failed to clear pending permission request: {}", e);
                             }
+                        }
+                        if let Some(ref key) = self.session_key {
+                            session_manager.broadcast_to_web_clients(
+                                key,
+                                ProxyMessage::PermissionResolved {
+                                    request_id,
+                                    allow: false,
+                                },
+                            );
+                        }
+                    }
+                }
+            }
+            ProxyMessage::SessionUpdate {
+                session_id: update_session_id,
+                git_branch,
+            } => {
+                // Update session metadata in DB
+                if let (Some(current_session_id), Ok(mut conn)) =
+                    (self.db_session_id, db_pool.get())
+                {
+                    // Verify the session_id matches to prevent spoofing
+                    if current_session_id == update_session_id {
+                        use crate::schema::sessions;
+                        if let Err(e) = diesel::update(sessions::table.find(current_session_id))
+                            .set(sessions::git_branch.eq(&git_branch))
+                            .execute(&mut conn)
+                        {
+                            error!("Failed to update git_branch: {}", e);
+                        } else {
+                            info!(
+                                "Updated git_branch for session {}: {:?}",
+                                current_session_id, git_branch
+                            );
 
-                            // Forward permission request to all web clients
-                            if let Some(ref key) = session_key {
-                                info!("Permission request from proxy for tool: {} (request_id: {}, suggestions: {})", tool_name, request_id, permission_suggestions.len());
+                            // Broadcast to web clients so they update immediately
+                            if let Some(ref key) = self.session_key {
                                 session_manager.broadcast_to_web_clients(
                                     key,
-                                    ProxyMessage::PermissionRequest {
-                                        request_id,
-                                        tool_name,
-                                        input,
-                                        permission_suggestions,
+                                    ProxyMessage::SessionUpdate {
+                                        session_id: current_session_id,
+                                        git_branch: git_branch.clone(),
                                     },
                                 );
                             }
                         }
-                        ProxyMessage::SessionUpdate {
-                            session_id: update_session_id,
-                            git_branch,
-                        } => {
-                            // Update session metadata in DB
-                            if let (Some(current_session_id), Ok(mut conn)) =
-                                (db_session_id, db_pool.get())
-                            {
-                                // Verify the session_id matches to prevent spoofing
-                                if current_session_id == update_session_id {
-                                    use crate::schema::sessions;
-                                    if let Err(e) =
-                                        diesel::update(sessions::table.find(current_session_id))
-                                            .set(sessions::git_branch.eq(&git_branch))
-                                            .execute(&mut conn)
-                                    {
-                                        error!("Failed to update git_branch: {}", e);
-                                    } else {
-                                        info!(
-                                            "Updated git_branch for session {}: {:?}",
-                                            current_session_id, git_branch
-                                        );
-
-                                        // Broadcast to web clients so they update immediately
-                                        if let Some(ref key) = session_key {
-                                            session_manager.broadcast_to_web_clients(
-                                                key,
-                                                ProxyMessage::SessionUpdate {
-                                                    session_id: current_session_id,
-                                                    git_branch: git_branch.clone(),
-                                                },
-                                            );
-                                        }
-                                    }
-                                } else {
-                                    warn!(
-                                        "SessionUpdate session_id mismatch: {} != {}",
-                                        update_session_id, current_session_id
-                                    );
-                                }
-                            }
-                        }
-                        ProxyMessage::InputAck {
-                            session_id: ack_session_id,
-                            ack_seq,
-                        } => {
-                            // Proxy acknowledged receipt of inputs, delete them from pending
-                            if let Some(current_session_id) = db_session_id {
-                                if ack_session_id == current_session_id {
-                                    if let Ok(mut conn) = db_pool.get() {
-                                        use crate::schema::pending_inputs;
-                                        let deleted = diesel::delete(
-                                            pending_inputs::table
-                                                .filter(
-                                                    pending_inputs::session_id
-                                                        .eq(current_session_id),
-                                                )
-                                                .filter(pending_inputs::seq_num.le(ack_seq)),
-                                        )
-                                        .execute(&mut conn);
-
-                                        match deleted {
-                                            Ok(count) => {
-                                                info!(
-                                                    "Deleted {} pending inputs for session {} (ack_seq={})",
-                                                    count, current_session_id, ack_seq
-                                                );
-                                            }
-                                            Err(e) => {
-                                                error!("Failed to delete pending inputs: {}", e);
-                                            }
-                                        }
-                                    }
-                                } else {
-                                    warn!(
-                                        "InputAck session_id mismatch: {} != {}",
-                                        ack_session_id, current_session_id
-                                    );
-                                }
-                            }
-                        }
-                        _ => {}
+                    } else {
+                        warn!(
+                            "SessionUpdate session_id mismatch: {} != {}",
+                            update_session_id, current_session_id
+                        );
                     }
                 }
             }
-            Ok(Message::Close(_)) => {
-                info!("WebSocket closed");
-                break;
+            ProxyMessage::IncidentReport {
+                reason,
+                proxy_version,
+                platform,
+                config_summary,
+                log_tail,
+            } => {
+                if let Some(session_id) = self.db_session_id {
+                    warn!("Incident reported for session {}: {}", session_id, reason);
+                    session_manager.publish_event(
+                        EventKind::Error,
+                        session_id,
+                        serde_json::json!({ "source": "proxy", "message": reason }),
+                    );
+                    let report = super::incidents::IncidentReport {
+                        reason,
+                        proxy_version,
+                        platform,
+                        config_summary,
+                        log_tail,
+                    };
+                    let incident = super::incidents::record_incident(
+                        &db_pool,
+                        session_id,
+                        report,
+                        session_manager.clock_skew_report(session_id),
+                    );
+                    if let (Some(incident), Some(ref key)) = (incident, &self.session_key) {
+                        session_manager.broadcast_to_web_clients(
+                            key,
+                            ProxyMessage::IncidentCreated { incident },
+                        );
+                    }
+                }
             }
-            Err(e) => {
-                error!("WebSocket error: {}", e);
-                break;
+            ProxyMessage::ArtifactsChunk {
+                request_id,
+                data,
+                done,
+                error,
+            } => {
+                if let Some(session_id) = self.db_session_id {
+                    session_manager
+                        .forward_artifacts_chunk(session_id, request_id, &data, done, error);
+                }
+            }
+            ProxyMessage::InputAck {
+                session_id: ack_session_id,
+                ack_seq,
+            } => {
+                // Proxy acknowledged receipt of inputs, delete them from pending
+                if let Some(current_session_id) = self.db_session_id {
+                    if ack_session_id == current_session_id {
+                        delete_acked_inputs(&db_pool, current_session_id, ack_seq);
+                    } else {
+                        warn!(
+                            "InputAck session_id mismatch: {} != {}",
+                            ack_session_id, current_session_id
+                        );
+                    }
+                }
             }
             _ => {}
         }
+        Flow::Continue
+    }
+
+    /// Mark the session as disconnected in the DB
+    fn stopped(&mut self, _ended: Ended) {
+        let session_manager = &self.app_state.session_manager;
+        let db_pool = &self.app_state.db_pool;
+
+        if let Some(session_id) = self.db_session_id {
+            if let Ok(mut conn) = db_pool.get() {
+                use crate::schema::sessions;
+                let _ = diesel::update(sessions::table.find(session_id))
+                    .set(sessions::status.eq("disconnected"))
+                    .execute(&mut conn);
+            }
+            session_manager.publish_event(
+                EventKind::SessionEnded,
+                session_id,
+                serde_json::json!({}),
+            );
+            session_manager.clear_lifecycle(session_id);
+            // Reseeded from the database when the proxy reconnects
+            session_manager.turn_analyzers.remove(&session_id);
+        }
+
+        if let Some(key) = &self.session_key {
+            session_manager.unregister_session(key);
+        }
     }
+}
 
-    // Cleanup - mark session as disconnected in DB
-    if let Some(session_id) = db_session_id {
-        if let Ok(mut conn) = db_pool.get() {
-            use crate::schema::sessions;
-            let _ = diesel::update(sessions::table.find(session_id))
-                .set(sessions::status.eq("disconnected"))
-                .execute(&mut conn);
+/// Turn away a proxy whose owner already has as many sessions running as
+/// allowed. A session reconnecting doesn't count against itself.
+fn check_session_quota(
+    app_state: &AppState,
+    session_id: Uuid,
+    auth_token: Option<&str>,
+) -> Result<(), String> {
+    if app_state.runtime_config.current().max_sessions_per_user == 0 {
+        return Ok(());
+    }
+    let Ok(mut conn) = app_state.db_pool.get() else {
+        // Registration reports the database problem itself
+        return Ok(());
+    };
+    use crate::schema::sessions;
+    let owner = sessions::table
+        .find(session_id)
+        .select(sessions::user_id)
+        .first::<Uuid>(&mut conn)
+        .optional()
+        .unwrap_or(None)
+        .or_else(|| get_user_id_from_token(app_state, auth_token));
+    let Some(owner) = owner else {
+        return Ok(());
+    };
+    match super::sessions::session_quota(&mut conn, app_state, owner, Some(session_id)) {
+        Ok(quota) if quota.is_full() => {
+            warn!(
+                "Rejecting session {} for user {}: {} of {:?} sessions running",
+                session_id, owner, quota.running, quota.limit
+            );
+            Err(quota.exceeded_message())
+        }
+        Ok(_) => Ok(()),
+        Err(e) => {
+            error!("Failed to check session quota: {}", e);
+            Ok(())
         }
     }
+}
+
+/// Whether the token a proxy registered with allows this session. Returns
+/// the token's scopes to enforce for the rest of the connection, or `None`
+/// when the proxy has no token of ours.
+fn check_token_scopes(
+    app_state: &AppState,
+    auth_token: Option<&str>,
+    session_id: Uuid,
+    working_directory: &str,
+) -> Result<Option<ProxyTokenScopes>, (ErrorCode, String)> {
+    let Some(token) = auth_token else {
+        return Ok(None);
+    };
+    let Ok(mut conn) = app_state.db_pool.get() else {
+        // Scopes can't be checked, so the registration can't be allowed
+        return Err((ErrorCode::Internal, "database unavailable".to_string()));
+    };
+    let (token_id, scopes) = match super::proxy_tokens::token_scopes(&mut conn, token) {
+        Ok(Some(found)) => found,
+        Ok(None) => return Ok(None),
+        Err(e) => {
+            error!("Failed to load proxy token scopes: {}", e);
+            return Err((ErrorCode::Internal, "database unavailable".to_string()));
+        }
+    };
+    super::proxy_tokens::check_register_scopes(
+        &mut conn,
+        token_id,
+        &scopes,
+        session_id,
+        working_directory,
+    )?;
+    Ok(Some(scopes))
+}
 
-    if let Some(key) = session_key {
-        session_manager.unregister_session(&key);
+/// Answer a permission request for a tool the proxy's token isn't scoped
+/// for. Web clients never see it; the denial is still recorded in the
+/// session's permission history.
+fn deny_out_of_scope(
+    db_pool: &crate::db::DbPool,
+    tx: &ClientSender,
+    session_id: Option<Uuid>,
+    request_id: String,
+    tool_name: &str,
+    input: &serde_json::Value,
+) {
+    let reason = format!(
+        "{} tools aren't allowed by this session's proxy token",
+        shared::ToolCategory::of(tool_name).as_str()
+    );
+    info!(
+        "Denying {} (request_id: {}): {}",
+        tool_name, request_id, reason
+    );
+    if let Some(session_id) = session_id {
+        super::permission_history::record_rule_decision(
+            db_pool,
+            session_id,
+            &request_id,
+            tool_name,
+            input,
+            super::permission_history::Decision {
+                allowed: false,
+                channel: DecisionChannel::Scope,
+                decided_by: None,
+                detail: Some(reason.clone()),
+            },
+        );
     }
+    let _ = tx.send(ProxyMessage::PermissionResponse {
+        request_id,
+        allow: false,
+        input: None,
+        permissions: Vec::new(),
+        reason: Some(reason),
+        remember: None,
+    });
+}
 
-    send_task.abort();
+/// Messages that act on a session rather than watch it
+fn is_session_action(msg: &ProxyMessage) -> bool {
+    matches!(
+        msg,
+        ProxyMessage::ClaudeInput { .. }
+            | ProxyMessage::Interrupt
+            | ProxyMessage::PermissionResponse { .. }
+            | ProxyMessage::RequestControl
+            | ProxyMessage::HandOffControl { .. }
+            | ProxyMessage::TakeControl
+    )
+}
+
+/// Whether a guest can still see the session they're connected to
+pub(crate) fn guest_access_continues(
+    app_state: &AppState,
+    session_id: Option<Uuid>,
+    user_id: Uuid,
+) -> bool {
+    let Some(session_id) = session_id else {
+        return true;
+    };
+    let Ok(mut conn) = app_state.db_pool.get() else {
+        // Don't cut a guest off over a busy pool; the next check will tell
+        return true;
+    };
+    matches!(
+        super::helpers::session_role(&mut conn, session_id, user_id),
+        Ok(Some(_))
+    )
 }
 
 /// Get user_id from auth token using JWT verification
@@ -979,31 +2325,262 @@ fn extract_user_id_from_cookies(app_state: &AppState, cookies: &Cookies) -> Opti
     cookie.value().parse().ok()
 }
 
-/// Verify that a user has access to a session (is a member with any role)
+/// A session's stored messages for a client to catch up on: those after
+/// `last_seq` if given, else those after `replay_after`, else all of them
+pub(crate) fn load_history(
+    conn: &mut diesel::pg::PgConnection,
+    session_id: Uuid,
+    last_seq: Option<i64>,
+    replay_after: Option<chrono::NaiveDateTime>,
+) -> Vec<crate::models::Message> {
+    use crate::schema::messages;
+
+    let query = messages::table
+        .filter(messages::session_id.eq(session_id))
+        .into_boxed();
+    let query = if let Some(after_seq) = last_seq {
+        query
+            .filter(messages::seq.gt(after_seq))
+            .order(messages::seq.asc())
+    } else if let Some(after) = replay_after {
+        query
+            .filter(messages::created_at.gt(after))
+            .order(messages::created_at.asc())
+    } else {
+        query.order(messages::created_at.asc())
+    };
+    query.load(conn).unwrap_or_default()
+}
+
+/// A stored message's content as Claude output. It is stored as a JSON
+/// string; anything that isn't valid JSON is wrapped as text.
+pub(crate) fn stored_content(msg: &crate::models::Message) -> serde_json::Value {
+    serde_json::from_str(&msg.content).unwrap_or_else(|_| {
+        serde_json::json!({
+            "type": msg.role,
+            "content": msg.content
+        })
+    })
+}
+
+/// Refresh a session's budget status before relaying input to it, returning
+/// the status if its hard limit blocks the input
+pub(crate) fn check_input_budget(
+    db_pool: &crate::db::DbPool,
+    session_manager: &SessionManager,
+    session_id: Uuid,
+) -> Result<(), BudgetStatus> {
+    let Some(status) = super::budget::check_session(db_pool, session_id) else {
+        return Ok(());
+    };
+    session_manager.set_budget_status(session_id, status.clone());
+    if status.is_blocked() {
+        warn!(
+            "Refusing input to session {}: budget hard limit reached",
+            session_id
+        );
+        return Err(status);
+    }
+    Ok(())
+}
+
+/// Store input as the session's next pending input and send it to the proxy,
+/// queueing it if the proxy is disconnected. Returns the input's sequence
+/// number, or 0 if sequencing failed and it went out unsequenced.
+pub(crate) fn relay_input(
+    db_pool: &crate::db::DbPool,
+    session_manager: &SessionManager,
+    session_id: Uuid,
+    content: serde_json::Value,
+    send_mode: Option<SendMode>,
+    traceparent: Option<String>,
+) -> i64 {
+    let key = session_id.to_string();
+    let seq = match db_pool.get() {
+        Ok(mut conn) => {
+            use crate::schema::{pending_inputs, sessions};
+
+            // Increment and get the next sequence number atomically
+            let next_seq: i64 = diesel::update(sessions::table.find(session_id))
+                .set(sessions::input_seq.eq(sessions::input_seq + 1))
+                .returning(sessions::input_seq)
+                .get_result(&mut conn)
+                .unwrap_or(1);
+
+            // Store the pending input
+            let new_input = NewPendingInput {
+                session_id,
+                seq_num: next_seq,
+                content: serde_json::to_string(&content).unwrap_or_default(),
+            };
+            if let Err(e) = diesel::insert_into(pending_inputs::table)
+                .values(&new_input)
+                .execute(&mut conn)
+            {
+                error!("Failed to store pending input: {}", e);
+            }
+            next_seq
+        }
+        Err(e) => {
+            error!("Failed to get db connection for pending input: {}", e);
+            0 // Fall back to unsequenced
+        }
+    };
+
+    // Send as SequencedInput to proxy
+    if seq > 0 {
+        if !session_manager.send_to_session(
+            &key,
+            ProxyMessage::SequencedInput {
+                session_id,
+                seq,
+                content,
+                traceparent,
+            },
+        ) {
+            warn!(
+                "Failed to send to session '{}', session not found in SessionManager (input queued)",
+                key
+            );
+        }
+    } else {
+        // Fallback to old behavior if sequencing failed
+        if !session_manager.send_to_session(
+            &key,
+            ProxyMessage::ClaudeInput {
+                content,
+                send_mode,
+                traceparent,
+            },
+        ) {
+            warn!(
+                "Failed to send to session '{}', session not found in SessionManager",
+                key
+            );
+        }
+    }
+    seq
+}
+
+/// Verify that a user has access to a session, returning it with their effective role
 fn verify_session_access(
     app_state: &AppState,
     session_id: Uuid,
     user_id: Uuid,
-) -> Result<crate::models::Session, ()> {
+) -> Result<(crate::models::Session, String), ()> {
     let mut conn = app_state.db_pool.get().map_err(|_| ())?;
-    use crate::schema::{session_members, sessions};
-    sessions::table
-        .inner_join(session_members::table.on(session_members::session_id.eq(sessions::id)))
-        .filter(sessions::id.eq(session_id))
-        .filter(session_members::user_id.eq(user_id))
+    use crate::schema::sessions;
+    let role = super::helpers::session_role(&mut conn, session_id, user_id)
+        .ok()
+        .flatten()
+        .ok_or(())?;
+    let session = sessions::table
+        .find(session_id)
         .select(crate::models::Session::as_select())
         .first::<crate::models::Session>(&mut conn)
-        .map_err(|_| ())
+        .map_err(|_| ())?;
+    Ok((session, role))
+}
+
+/// Look up how a user should appear in a session's presence list
+/// Store something a person did in a session alongside Claude's output, and
+/// show it to everyone watching
+pub(crate) fn record_operator_action(
+    app_state: &AppState,
+    session_id: Uuid,
+    user_id: Uuid,
+    action: OperatorAction,
+) {
+    use crate::schema::{messages, sessions};
+
+    let user_name = load_presence_viewer(app_state, user_id)
+        .map(|viewer| viewer.name)
+        .unwrap_or_else(|| "Someone".to_string());
+    let content = match serde_json::to_value(OperatorEvent::new(user_id, user_name, action)) {
+        Ok(content) => content,
+        Err(e) => {
+            error!("Failed to encode operator action: {}", e);
+            return;
+        }
+    };
+    let Ok(mut conn) = app_state.db_pool.get() else {
+        error!("Failed to get DB connection for operator action");
+        return;
+    };
+    let stored = sessions::table
+        .find(session_id)
+        .select(sessions::user_id)
+        .first::<Uuid>(&mut conn)
+        .and_then(|owner| {
+            diesel::insert_into(messages::table)
+                .values(&crate::models::NewMessage {
+                    session_id,
+                    role: OPERATOR_MESSAGE_TYPE.to_string(),
+                    content: content.to_string(),
+                    user_id: owner,
+                    produced_at: None,
+                })
+                .returning((messages::id, messages::seq))
+                .get_result::<(Uuid, i64)>(&mut conn)
+        });
+    match stored {
+        Ok((message_id, seq)) => app_state.session_manager.broadcast_to_web_clients(
+            &session_id.to_string(),
+            ProxyMessage::ClaudeOutput {
+                content,
+                message_id: Some(message_id),
+                message_seq: Some(seq as u64),
+                traceparent: None,
+                content_encoding: None,
+            },
+        ),
+        Err(e) => error!("Failed to store operator action: {}", e),
+    }
+}
+
+fn load_presence_viewer(app_state: &AppState, user_id: Uuid) -> Option<PresenceViewer> {
+    use crate::schema::users;
+    let mut conn = app_state.db_pool.get().ok()?;
+    let (email, name, avatar_url): (String, Option<String>, Option<String>) = users::table
+        .find(user_id)
+        .select((users::email, users::name, users::avatar_url))
+        .first(&mut conn)
+        .ok()?;
+    Some(PresenceViewer {
+        user_id,
+        name: name.unwrap_or(email),
+        avatar_url,
+        driving: false,
+    })
+}
+
+/// Whether a user may override a session's driver
+fn is_admin_user(app_state: &AppState, user_id: Uuid) -> bool {
+    use crate::schema::users;
+    let Ok(mut conn) = app_state.db_pool.get() else {
+        return false;
+    };
+    users::table
+        .find(user_id)
+        .select(users::role)
+        .first::<String>(&mut conn)
+        .is_ok_and(|role| role == UserRole::Admin.as_str())
 }
 
 pub async fn handle_web_client_websocket(
     ws: WebSocketUpgrade,
     State(app_state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
     cookies: Cookies,
+    headers: HeaderMap,
 ) -> Response {
-    // Authenticate the user before upgrading the WebSocket
-    let user_id = match extract_user_id_from_cookies(&app_state, &cookies) {
-        Some(id) => id,
+    // Authenticate the user before upgrading the WebSocket. Browsers send the
+    // session cookie; `claude-portal tail` sends its proxy token instead.
+    let (user_id, scopes) = match extract_user_id_from_cookies(&app_state, &cookies)
+        .map(|id| (id, ProxyTokenScopes::default()))
+        .or_else(|| super::proxy_tokens::user_from_bearer(&app_state, &headers, peer))
+    {
+        Some(auth) => auth,
         None => {
             warn!("Unauthenticated WebSocket connection attempt to /ws/client");
             return StatusCode::UNAUTHORIZED.into_response();
@@ -1011,313 +2588,642 @@ pub async fn handle_web_client_websocket(
     };
 
     info!("Authenticated WebSocket upgrade for user: {}", user_id);
-    ws.on_upgrade(move |socket| handle_web_client_socket(socket, app_state, user_id))
+    let span = tracing::info_span!(
+        "web_client_connection",
+        %user_id,
+        session_id = tracing::field::Empty
+    );
+    ws.on_upgrade(move |socket| {
+        handle_web_client_socket(socket, app_state, user_id, scopes).instrument(span)
+    })
 }
 
-async fn handle_web_client_socket(socket: WebSocket, app_state: Arc<AppState>, user_id: Uuid) {
+/// `scopes` are those of the proxy token the client authenticated with, if
+/// it used one rather than a browser session
+async fn handle_web_client_socket(
+    socket: WebSocket,
+    app_state: Arc<AppState>,
+    user_id: Uuid,
+    scopes: ProxyTokenScopes,
+) {
     let session_manager = app_state.session_manager.clone();
-    let db_pool = app_state.db_pool.clone();
-    let (mut sender, mut receiver) = socket.split();
-    let (tx, mut rx) = mpsc::unbounded_channel::<ProxyMessage>();
-
-    let mut session_key: Option<SessionId> = None;
-    let mut verified_session_id: Option<Uuid> = None;
+    let (sender, receiver) = socket.split();
+    let (tx, rx) = session_manager.web_client_channel();
 
     // Register this client for user-level broadcasts (like spend updates)
     session_manager.add_user_client(user_id, tx.clone());
+    if let Some(notice) = session_manager.maintenance_notice() {
+        let _ = tx.send(ProxyMessage::MaintenanceBanner {
+            notice: Some(notice),
+        });
+    }
+    if let Some(kill_switch) = session_manager.kill_switch() {
+        let _ = tx.send(ProxyMessage::KillSwitch {
+            state: Some(kill_switch),
+        });
+    }
 
-    // Spawn task to send messages to the WebSocket
-    let send_task = tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
-            if let Ok(json) = serde_json::to_string(&msg) {
-                if sender.send(Message::Text(json)).await.is_err() {
-                    break;
-                }
+    let client = WebClientConnection {
+        app_state,
+        tx,
+        user_id,
+        scopes,
+        connection_id: Uuid::new_v4(),
+        rate_key: rate_limit::user_key(user_id),
+        compress_output: Arc::new(AtomicBool::new(false)),
+        redactor: Arc::new(std::sync::Mutex::new(None)),
+        session_key: None,
+        verified_session_id: None,
+        session_read_only: false,
+        session_approve_only: false,
+        guest: false,
+        close: session_manager.user_close_token(user_id),
+        display_name: None,
+    };
+    let prepare = {
+        let compress_output = client.compress_output.clone();
+        let redactor = client.redactor.clone();
+        let stats = session_manager.compression.clone();
+        move |msg: &mut ProxyMessage| {
+            redact_client_output(msg, &redactor);
+            if compress_output.load(Ordering::Relaxed) {
+                compress_client_output(msg, &stats);
             }
         }
-    });
+    };
+    connection::serve(client, sender, receiver, rx, prepare).await;
+}
 
-    // Handle incoming messages from web client
-    while let Some(msg) = receiver.next().await {
-        match msg {
-            Ok(Message::Text(text)) => {
-                if let Ok(proxy_msg) = serde_json::from_str::<ProxyMessage>(&text) {
-                    match proxy_msg {
-                        ProxyMessage::Register {
-                            session_id,
-                            session_name,
-                            auth_token: _,
-                            working_directory: _,
-                            resuming: _,
-                            git_branch: _,
-                            replay_after,
-                            client_version: _, // Not used for web clients
-                        } => {
-                            // Verify the user has access to this session before allowing connection
-                            match verify_session_access(&app_state, session_id, user_id) {
-                                Ok(_session) => {
-                                    // User has access to this session, allow connection
-                                    let key = session_id.to_string();
-                                    session_key = Some(key.clone());
-                                    verified_session_id = Some(session_id);
-
-                                    // Register this web client to receive new messages
-                                    session_manager.add_web_client(key, tx.clone());
-                                    info!(
-                                        "Web client connected to session: {} ({}) for user {}",
-                                        session_name, session_id, user_id
-                                    );
+/// State of one web client connection
+struct WebClientConnection {
+    app_state: Arc<AppState>,
+    tx: ClientSender,
+    user_id: Uuid,
+    /// Those of the proxy token the client authenticated with, if any
+    scopes: ProxyTokenScopes,
+    connection_id: Uuid,
+    rate_key: String,
+    /// Set once the client's Register offers compression
+    compress_output: Arc<AtomicBool>,
+    /// Set at Register for a viewer of a session with a redaction policy
+    redactor: Arc<std::sync::Mutex<Option<Redactor>>>,
+    session_key: Option<SessionId>,
+    verified_session_id: Option<Uuid>,
+    /// Set when the user's role in the registered session is viewer
+    session_read_only: bool,
+    /// Set for approvers, who may answer permission requests but not send input
+    session_approve_only: bool,
+    /// Set at Register for guests, whose access is checked again every
+    /// `GUEST_RECHECK_INTERVAL`
+    guest: bool,
+    /// Cancelled when the server takes the user's access away
+    close: CancellationToken,
+    display_name: Option<String>,
+}
 
-                                    // Send existing messages from DB as history
-                                    // If replay_after is set, only send messages after that timestamp
-                                    if let Ok(mut conn) = db_pool.get() {
-                                        use crate::schema::messages;
-
-                                        // Parse replay_after timestamp if provided
-                                        let replay_after_time =
-                                            replay_after.as_ref().and_then(|ts| {
-                                                chrono::NaiveDateTime::parse_from_str(
-                                                    ts,
-                                                    "%Y-%m-%dT%H:%M:%S%.f",
-                                                )
-                                                .or_else(|_| {
-                                                    chrono::NaiveDateTime::parse_from_str(
-                                                        ts,
-                                                        "%Y-%m-%dT%H:%M:%S",
-                                                    )
-                                                })
-                                                .ok()
-                                            });
-
-                                        let history: Vec<crate::models::Message> =
-                                            if let Some(after) = replay_after_time {
-                                                messages::table
-                                                    .filter(messages::session_id.eq(session_id))
-                                                    .filter(messages::created_at.gt(after))
-                                                    .order(messages::created_at.asc())
-                                                    .load(&mut conn)
-                                                    .unwrap_or_default()
-                                            } else {
-                                                messages::table
-                                                    .filter(messages::session_id.eq(session_id))
-                                                    .order(messages::created_at.asc())
-                                                    .load(&mut conn)
-                                                    .unwrap_or_default()
-                                            };
-
-                                        info!(
-                                            "Sending {} historical messages to web client (replay_after: {:?})",
-                                            history.len(), replay_after
-                                        );
-
-                                        for msg in history {
-                                            // Convert stored message to ClaudeOutput format
-                                            // The content is stored as JSON string, parse it back
-                                            let content =
-                                                match serde_json::from_str::<serde_json::Value>(
-                                                    &msg.content,
-                                                ) {
-                                                    Ok(json) => json,
-                                                    Err(_) => {
-                                                        // If not valid JSON, wrap as text
-                                                        serde_json::json!({
-                                                            "type": msg.role,
-                                                            "content": msg.content
-                                                        })
-                                                    }
-                                                };
-
-                                            let _ = tx.send(ProxyMessage::ClaudeOutput { content });
-                                        }
-
-                                        // Replay pending permission request if one exists
-                                        use crate::schema::pending_permission_requests;
-                                        if let Ok(pending) = pending_permission_requests::table
-                                            .filter(
-                                                pending_permission_requests::session_id
-                                                    .eq(session_id),
-                                            )
-                                            .first::<crate::models::PendingPermissionRequest>(
-                                                &mut conn,
-                                            )
-                                        {
-                                            info!(
-                                                "Replaying pending permission request for session {}: {} ({})",
-                                                session_id, pending.tool_name, pending.request_id
-                                            );
-
-                                            // Convert stored permission_suggestions back to Vec
-                                            let suggestions: Vec<shared::PermissionSuggestion> =
-                                                pending
-                                                    .permission_suggestions
-                                                    .and_then(|v| serde_json::from_value(v).ok())
-                                                    .unwrap_or_default();
-
-                                            let _ = tx.send(ProxyMessage::PermissionRequest {
-                                                request_id: pending.request_id,
-                                                tool_name: pending.tool_name,
-                                                input: pending.input,
-                                                permission_suggestions: suggestions,
-                                            });
-                                        }
-                                    }
-                                }
-                                Err(_) => {
-                                    // User doesn't own this session - reject
-                                    warn!(
-                                        "User {} attempted to access session {} they don't own",
-                                        user_id, session_id
-                                    );
-                                    let _ = tx.send(ProxyMessage::Error {
-                                        message: "Access denied: you don't own this session"
-                                            .to_string(),
-                                    });
-                                    break;
-                                }
-                            }
+#[async_trait]
+impl ConnectionActor for WebClientConnection {
+    type Message = ProxyMessage;
+    const PEER: &'static str = "Web client";
+    const TICK_INTERVAL: Option<Duration> = Some(GUEST_RECHECK_INTERVAL);
+
+    fn is_heartbeat(msg: &ProxyMessage) -> bool {
+        matches!(msg, ProxyMessage::Ping { .. })
+    }
+
+    async fn handle(&mut self, proxy_msg: ProxyMessage) -> Flow {
+        let app_state = &self.app_state;
+        let session_manager = &app_state.session_manager;
+        let db_pool = &app_state.db_pool;
+        let tx = &self.tx;
+        let user_id = self.user_id;
+        let scopes = &self.scopes;
+        let connection_id = self.connection_id;
+        let rate_key = &self.rate_key;
+        let compress_output = &self.compress_output;
+        let redactor = &self.redactor;
+
+        if !within_message_limit(&app_state, &tx, &rate_key, &proxy_msg) {
+            return Flow::Continue;
+        }
+        if scopes.read_only && is_session_action(&proxy_msg) {
+            let _ = tx.send(ProxyMessage::Error {
+                code: ErrorCode::AccessDenied,
+                message: "This proxy token is read-only".to_string(),
+            });
+            return Flow::Continue;
+        }
+        if self.session_read_only && is_session_action(&proxy_msg) {
+            let _ = tx.send(ProxyMessage::Error {
+                code: ErrorCode::AccessDenied,
+                message: "You have view-only access to this session".to_string(),
+            });
+            return Flow::Continue;
+        }
+        if self.session_approve_only
+            && matches!(
+                proxy_msg,
+                ProxyMessage::ClaudeInput { .. } | ProxyMessage::Interrupt
+            )
+        {
+            let _ = tx.send(ProxyMessage::Error {
+                code: ErrorCode::AccessDenied,
+                message: "You can only answer permission requests in this session".to_string(),
+            });
+            return Flow::Continue;
+        }
+        match proxy_msg {
+            ProxyMessage::Register {
+                session_id,
+                session_name,
+                auth_token: _,
+                working_directory: _,
+                resuming: _,
+                git_branch: _,
+                git_repo: _,
+                host_info: _,
+                replay_after,
+                last_seq,
+                client_version: _, // Not used for web clients
+                accept_encodings,
+                protocol_version,
+            } => {
+                if let Err(error) = negotiate_protocol(&tx, "Web client", protocol_version) {
+                    let _ = tx.send(ProxyMessage::Error {
+                        code: ErrorCode::IncompatibleProtocol,
+                        message: error,
+                    });
+                    return Flow::Stop;
+                }
+                compress_output.store(
+                    compression::negotiate_encoding(&accept_encodings).is_some(),
+                    Ordering::Relaxed,
+                );
+                // Verify the user has access to this session before allowing connection
+                match verify_session_access(&app_state, session_id, user_id) {
+                    Ok((session, _)) if !scopes.allows_directory(&session.working_directory) => {
+                        warn!(
+                            "Token for user {} isn't scoped for session {} in {}",
+                            user_id, session_id, session.working_directory
+                        );
+                        let _ = tx.send(ProxyMessage::Error {
+                            code: ErrorCode::AccessDenied,
+                            message: "Access denied: this proxy token can't access sessions in that directory".to_string(),
+                        });
+                        return Flow::Stop;
+                    }
+                    Ok((_session, role)) => {
+                        // User has access to this session, allow connection
+                        self.session_read_only = !super::helpers::can_answer_permissions(&role);
+                        self.session_approve_only =
+                            !self.session_read_only && !super::helpers::can_edit(&role);
+                        // Without the redaction policy nothing could be sent safely
+                        let Ok(mut conn) = db_pool.get() else {
+                            error!(
+                                "Refusing web client for session {}: database unavailable",
+                                session_id
+                            );
+                            let _ = tx.send(ProxyMessage::Error {
+                                code: ErrorCode::Internal,
+                                message: "Database unavailable, try again shortly".to_string(),
+                            });
+                            return Flow::Stop;
+                        };
+                        *redactor.lock().unwrap() =
+                            super::redaction::redactor_for(&mut conn, session_id, &role);
+                        // Treat a membership we can't read as a guest's, so it's checked again
+                        self.guest = !matches!(
+                            super::helpers::membership_expiry(&mut conn, session_id, user_id),
+                            Ok(None)
+                        );
+                        drop(conn);
+                        let key = session_id.to_string();
+                        self.session_key = Some(key.clone());
+                        self.verified_session_id = Some(session_id);
+                        tracing::Span::current()
+                            .record("session_id", tracing::field::display(session_id));
+
+                        // Register this web client to receive new messages
+                        session_manager.add_web_client(key, tx.clone());
+                        info!(
+                            "Web client connected to session: {} ({}) for user {}",
+                            session_name, session_id, user_id
+                        );
+
+                        // Let everyone watching know this user joined
+                        if let Some(viewer) = load_presence_viewer(&app_state, user_id) {
+                            self.display_name = Some(viewer.name.clone());
+                            session_manager.join_presence(session_id, connection_id, viewer);
                         }
-                        ProxyMessage::ClaudeInput { content, send_mode } => {
-                            // Only allow if session ownership was verified
-                            if let Some(ref key) = session_key {
-                                if let Some(session_id) = verified_session_id {
-                                    info!("Web client sending ClaudeInput to session: {}", key);
-
-                                    // Store as pending input with sequence number
-                                    let seq = match db_pool.get() {
-                                        Ok(mut conn) => {
-                                            use crate::schema::{pending_inputs, sessions};
-
-                                            // Increment and get the next sequence number atomically
-                                            let next_seq: i64 =
-                                                diesel::update(sessions::table.find(session_id))
-                                                    .set(
-                                                        sessions::input_seq
-                                                            .eq(sessions::input_seq + 1),
-                                                    )
-                                                    .returning(sessions::input_seq)
-                                                    .get_result(&mut conn)
-                                                    .unwrap_or(1);
-
-                                            // Store the pending input
-                                            let new_input = NewPendingInput {
-                                                session_id,
-                                                seq_num: next_seq,
-                                                content: serde_json::to_string(&content)
-                                                    .unwrap_or_default(),
-                                            };
-                                            if let Err(e) =
-                                                diesel::insert_into(pending_inputs::table)
-                                                    .values(&new_input)
-                                                    .execute(&mut conn)
-                                            {
-                                                error!("Failed to store pending input: {}", e);
-                                            }
-                                            next_seq
-                                        }
-                                        Err(e) => {
-                                            error!(
-                                                "Failed to get db connection for pending input: {}",
-                                                e
-                                            );
-                                            0 // Fall back to unsequenced
-                                        }
-                                    };
 
-                                    // Send as SequencedInput to proxy
-                                    if seq > 0 {
-                                        if !session_manager.send_to_session(
-                                            key,
-                                            ProxyMessage::SequencedInput {
-                                                session_id,
-                                                seq,
-                                                content,
-                                            },
-                                        ) {
-                                            warn!("Failed to send to session '{}', session not found in SessionManager (input queued)", key);
-                                        }
-                                    } else {
-                                        // Fallback to old behavior if sequencing failed
-                                        if !session_manager.send_to_session(
-                                            key,
-                                            ProxyMessage::ClaudeInput { content, send_mode },
-                                        ) {
-                                            warn!("Failed to send to session '{}', session not found in SessionManager", key);
-                                        }
-                                    }
-                                } else {
-                                    warn!(
-                                        "Attempted ClaudeInput without verified session ownership"
-                                    );
-                                }
-                            } else {
-                                warn!("Web client tried to send ClaudeInput but no session_key set (not registered?)");
+                        // Send existing messages from DB as history, skipping
+                        // those the client already has (by last_seq, or by the
+                        // older replay_after timestamp)
+                        if let Ok(mut conn) = db_pool.get() {
+                            // Parse replay_after timestamp if provided
+                            let replay_after_time = replay_after.as_ref().and_then(|ts| {
+                                chrono::NaiveDateTime::parse_from_str(ts, "%Y-%m-%dT%H:%M:%S%.f")
+                                    .or_else(|_| {
+                                        chrono::NaiveDateTime::parse_from_str(
+                                            ts,
+                                            "%Y-%m-%dT%H:%M:%S",
+                                        )
+                                    })
+                                    .ok()
+                            });
+
+                            let history = load_history(
+                                &mut conn,
+                                session_id,
+                                last_seq.map(|seq| seq as i64),
+                                replay_after_time,
+                            );
+
+                            info!(
+                                "Sending {} historical messages to web client (last_seq: {:?}, replay_after: {:?})",
+                                history.len(), last_seq, replay_after
+                            );
+
+                            for msg in history {
+                                // Wait for room rather than shed history
+                                let _ = tx
+                                    .send_wait(ProxyMessage::ClaudeOutput {
+                                        content: stored_content(&msg),
+                                        message_id: Some(msg.id),
+                                        message_seq: Some(msg.seq as u64),
+                                        traceparent: None,
+                                        content_encoding: None,
+                                    })
+                                    .await;
+                            }
+
+                            // Replay pending permission request if one exists
+                            use crate::schema::pending_permission_requests;
+                            if let Ok(pending) = pending_permission_requests::table
+                                .filter(pending_permission_requests::session_id.eq(session_id))
+                                .first::<crate::models::PendingPermissionRequest>(&mut conn)
+                            {
+                                info!(
+                                    "Replaying pending permission request for session {}: {} ({})",
+                                    session_id, pending.tool_name, pending.request_id
+                                );
+
+                                // Convert stored permission_suggestions back to Vec
+                                let suggestions: Vec<shared::PermissionSuggestion> = pending
+                                    .permission_suggestions
+                                    .and_then(|v| serde_json::from_value(v).ok())
+                                    .unwrap_or_default();
+
+                                let _ = tx.send(ProxyMessage::PermissionRequest {
+                                    request_id: pending.request_id,
+                                    tool_name: pending.tool_name,
+                                    input: pending.input,
+                                    permission_suggestions: suggestions,
+                                });
                             }
                         }
-                        ProxyMessage::PermissionResponse {
-                            request_id,
-                            allow,
-                            input,
-                            permissions,
-                            reason,
-                        } => {
-                            // Only allow if session ownership was verified
-                            if let Some(ref key) = session_key {
-                                if let Some(session_id) = verified_session_id {
-                                    info!("Web client sending PermissionResponse: {} -> {} (permissions: {}, reason: {:?})",
-                                          request_id, if allow { "allow" } else { "deny" }, permissions.len(), reason);
-
-                                    // Clear pending permission request from database
-                                    if let Ok(mut conn) = db_pool.get() {
-                                        use crate::schema::pending_permission_requests;
-                                        if let Err(e) = diesel::delete(
-                                            pending_permission_requests::table.filter(
-                                                pending_permission_requests::session_id
-                                                    .eq(session_id),
-                                            ),
-                                        )
-                                        .execute(&mut conn)
-                                        {
-                                            error!(
-                                                "Failed to clear pending permission request: {}",
-                                                e
-                                            );
-                                        }
-                                    }
 
-                                    if !session_manager.send_to_session(
-                                        key,
-                                        ProxyMessage::PermissionResponse {
-                                            request_id,
-                                            allow,
-                                            input,
-                                            permissions,
-                                            reason,
-                                        },
-                                    ) {
-                                        warn!("Failed to send PermissionResponse to session '{}', session not connected", key);
-                                    }
-                                } else {
-                                    warn!("Attempted PermissionResponse without verified session ownership");
+                        if let Some(state) = session_manager.lifecycle(session_id) {
+                            let _ = tx.send(ProxyMessage::StateChanged { state });
+                        }
+                    }
+                    Err(_) => {
+                        // User doesn't own this session - reject
+                        warn!(
+                            "User {} attempted to access session {} they don't own",
+                            user_id, session_id
+                        );
+                        let _ = tx.send(ProxyMessage::Error {
+                            code: ErrorCode::AccessDenied,
+                            message: "Access denied: you don't own this session".to_string(),
+                        });
+                        return Flow::Stop;
+                    }
+                }
+            }
+            ProxyMessage::ClaudeInput {
+                content,
+                send_mode,
+                traceparent,
+            } => {
+                // Only allow if session ownership was verified
+                if let Some(ref key) = self.session_key {
+                    if let Some(session_id) = self.verified_session_id {
+                        let span = tracing::info_span!("relay_input", %session_id);
+                        telemetry::set_remote_parent(&span, traceparent.as_deref());
+                        let _enter = span.enter();
+                        let traceparent = telemetry::traceparent(&span, traceparent);
+
+                        // Only the driver may send input
+                        if let Err(driver) = session_manager.claim_driver(session_id, user_id) {
+                            let _ = tx.send(ProxyMessage::Error {
+                                code: ErrorCode::NotDriver,
+                                message: format!(
+                                    "{} is driving this session. Request control to send input.",
+                                    driver
+                                ),
+                            });
+                            return Flow::Continue;
+                        }
+                        if let Some(kill_switch) = session_manager.kill_switch() {
+                            let _ = tx.send(ProxyMessage::Error {
+                                code: ErrorCode::KillSwitch,
+                                message: format!(
+                                    "Input not sent. All sessions are paused: {}",
+                                    kill_switch.reason
+                                ),
+                            });
+                            return Flow::Continue;
+                        }
+                        if let Err(status) =
+                            check_input_budget(&db_pool, &session_manager, session_id)
+                        {
+                            let _ = tx.send(ProxyMessage::Error {
+                                code: ErrorCode::BudgetExceeded,
+                                message: format!("Input not sent. {}.", status.summary()),
+                            });
+                            let _ = tx.send(ProxyMessage::BudgetStatus { session_id, status });
+                            return Flow::Continue;
+                        }
+                        info!("Web client sending ClaudeInput to session: {}", key);
+                        record_operator_action(
+                            &app_state,
+                            session_id,
+                            user_id,
+                            OperatorAction::Input {
+                                content: shared::paste::input_text(&content),
+                                send_mode: send_mode.unwrap_or_default(),
+                            },
+                        );
+                        relay_input(
+                            &db_pool,
+                            &session_manager,
+                            session_id,
+                            content,
+                            send_mode,
+                            traceparent,
+                        );
+                    } else {
+                        warn!("Attempted ClaudeInput without verified session ownership");
+                    }
+                } else {
+                    warn!("Web client tried to send ClaudeInput but no session_key set (not registered?)");
+                }
+            }
+            ProxyMessage::Interrupt => {
+                if let Some(session_id) = self.verified_session_id {
+                    if let Err(driver) = session_manager.claim_driver(session_id, user_id) {
+                        let _ = tx.send(ProxyMessage::Error {
+                            code: ErrorCode::NotDriver,
+                            message: format!(
+                                "{} is driving this session. Request control to interrupt.",
+                                driver
+                            ),
+                        });
+                        return Flow::Continue;
+                    }
+                    info!("Web client interrupting session: {}", session_id);
+                    if session_manager
+                        .send_to_connected(&session_id.to_string(), ProxyMessage::Interrupt)
+                    {
+                        record_operator_action(
+                            &app_state,
+                            session_id,
+                            user_id,
+                            OperatorAction::Interrupt,
+                        );
+                    } else {
+                        warn!(
+                            "Dropping interrupt for session {}: proxy not connected",
+                            session_id
+                        );
+                    }
+                }
+            }
+            ProxyMessage::PermissionResponse {
+                request_id,
+                allow,
+                input,
+                permissions,
+                reason,
+                remember,
+            } => {
+                // Only allow if session ownership was verified
+                if let Some(ref key) = self.session_key {
+                    if let Some(session_id) = self.verified_session_id {
+                        // Approvals are input too, so only the driver may answer
+                        if let Err(driver) = session_manager.claim_driver(session_id, user_id) {
+                            let _ = tx.send(ProxyMessage::Error {
+                                code: ErrorCode::NotDriver,
+                                message: format!(
+                                    "{} is driving this session. Request control to answer permission requests.",
+                                    driver
+                                ),
+                            });
+                            return Flow::Continue;
+                        }
+                        info!("Web client sending PermissionResponse: {} -> {} (permissions: {}, reason: {:?})",
+                              request_id, if allow { "allow" } else { "deny" }, permissions.len(), reason);
+
+                        super::permission_history::record_decision(
+                            &db_pool,
+                            session_id,
+                            &request_id,
+                            super::permission_history::Decision {
+                                allowed: allow,
+                                channel: DecisionChannel::Web,
+                                decided_by: Some(user_id),
+                                detail: if allow { None } else { reason.clone() },
+                            },
+                        );
+
+                        // Clear pending permission request from database
+                        let mut tool_name = None;
+                        if let Ok(mut conn) = db_pool.get() {
+                            use crate::schema::pending_permission_requests;
+                            match diesel::delete(
+                                pending_permission_requests::table
+                                    .filter(pending_permission_requests::session_id.eq(session_id)),
+                            )
+                            .returning(pending_permission_requests::tool_name)
+                            .get_results::<String>(&mut conn)
+                            {
+                                Ok(cleared) => tool_name = cleared.into_iter().next(),
+                                Err(e) => {
+                                    error!("Failed to clear pending permission request: {}", e)
                                 }
-                            } else {
-                                warn!("Web client tried to send PermissionResponse but no session_key set");
                             }
                         }
-                        _ => {}
+                        record_operator_action(
+                            &app_state,
+                            session_id,
+                            user_id,
+                            OperatorAction::PermissionDecision {
+                                request_id: request_id.clone(),
+                                tool_name,
+                                allowed: allow,
+                                reason: reason.clone(),
+                            },
+                        );
+
+                        if !session_manager.send_to_session(
+                            key,
+                            ProxyMessage::PermissionResponse {
+                                request_id,
+                                allow,
+                                input,
+                                permissions,
+                                reason,
+                                remember,
+                            },
+                        ) {
+                            warn!("Failed to send PermissionResponse to session '{}', session not connected", key);
+                        }
+                    } else {
+                        warn!("Attempted PermissionResponse without verified session ownership");
+                    }
+                } else {
+                    warn!("Web client tried to send PermissionResponse but no session_key set");
+                }
+            }
+            ProxyMessage::RequestControl => {
+                if let Some(session_id) = self.verified_session_id {
+                    if session_manager.claim_driver(session_id, user_id).is_err() {
+                        // Someone else is driving - ask them to hand off
+                        session_manager.broadcast_to_web_clients(
+                            &session_id.to_string(),
+                            ProxyMessage::ControlRequested {
+                                session_id,
+                                user_id,
+                                name: self
+                                    .display_name
+                                    .clone()
+                                    .unwrap_or_else(|| "Another user".to_string()),
+                            },
+                        );
                     }
                 }
             }
-            Ok(Message::Close(_)) => {
-                info!("Web client WebSocket closed");
-                break;
+            ProxyMessage::HandOffControl { to_user_id } => {
+                if let Some(session_id) = self.verified_session_id {
+                    let key = session_id.to_string();
+                    if session_manager.driver(&key) != Some(user_id) {
+                        warn!(
+                            "User {} tried to hand off session {} without driving it",
+                            user_id, session_id
+                        );
+                    } else if !session_manager.set_driver(session_id, to_user_id) {
+                        let _ = tx.send(ProxyMessage::Error {
+                            code: ErrorCode::Other,
+                            message: "That user is no longer connected to this session".to_string(),
+                        });
+                    }
+                }
             }
-            Err(e) => {
-                error!("Web client WebSocket error: {}", e);
-                break;
+            ProxyMessage::TakeControl => {
+                if let Some(session_id) = self.verified_session_id {
+                    if is_admin_user(&app_state, user_id) {
+                        info!("Admin {} took control of session {}", user_id, session_id);
+                        session_manager.set_driver(session_id, Some(user_id));
+                    } else {
+                        warn!(
+                            "Non-admin user {} attempted to take control of session {}",
+                            user_id, session_id
+                        );
+                    }
+                }
+            }
+            ProxyMessage::Ping { sent_at } => {
+                let _ = tx.send(ProxyMessage::Pong { sent_at });
             }
             _ => {}
         }
+        Flow::Continue
     }
 
-    send_task.abort();
+    async fn tick(&mut self) -> Flow {
+        if !self.guest
+            || guest_access_continues(&self.app_state, self.verified_session_id, self.user_id)
+        {
+            return Flow::Continue;
+        }
+        info!("Guest access for user {} has ended", self.user_id);
+        let _ = self.tx.send(ProxyMessage::Error {
+            code: ErrorCode::AccessDenied,
+            message: GUEST_ACCESS_ENDED.to_string(),
+        });
+        Flow::Stop
+    }
+
+    fn close_token(&self) -> CancellationToken {
+        self.close.clone()
+    }
+
+    fn stopped(&mut self, _ended: Ended) {
+        if let Some(session_id) = self.verified_session_id {
+            self.app_state
+                .session_manager
+                .leave_presence(session_id, self.connection_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viewer(user_id: Uuid) -> PresenceViewer {
+        PresenceViewer {
+            user_id,
+            name: user_id.to_string(),
+            avatar_url: None,
+            driving: false,
+        }
+    }
+
+    #[test]
+    fn test_concurrent_claims_have_one_winner() {
+        let manager = SessionManager::new();
+        let session_id = Uuid::new_v4();
+        let users: Vec<Uuid> = (0..8).map(|_| Uuid::new_v4()).collect();
+        for user in &users {
+            manager.join_presence(session_id, Uuid::new_v4(), viewer(*user));
+        }
+
+        let barrier = Arc::new(std::sync::Barrier::new(users.len()));
+        let handles: Vec<_> = users
+            .iter()
+            .map(|&user| {
+                let manager = manager.clone();
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    barrier.wait();
+                    manager.claim_driver(session_id, user).is_ok()
+                })
+            })
+            .collect();
+        let winners = handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .filter(|won| *won)
+            .count();
+
+        assert_eq!(winners, 1);
+        let driver = manager.driver(&session_id.to_string()).unwrap();
+        assert!(manager.claim_driver(session_id, driver).is_ok());
+    }
+
+    #[test]
+    fn test_close_user_connections() {
+        let manager = SessionManager::new();
+        let guest = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        let guest_token = manager.user_close_token(guest);
+        let other_token = manager.user_close_token(other);
+
+        manager.close_user_connections(guest, GUEST_ACCESS_ENDED);
+        assert!(guest_token.is_cancelled());
+        assert!(!other_token.is_cancelled());
+        assert!(!manager.user_close_token(guest).is_cancelled());
+    }
 }
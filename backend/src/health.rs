@@ -0,0 +1,123 @@
+//! Health scores for running sessions
+//!
+//! Signals come from what the backend already stores: recent turns for the
+//! error rate, the newest messages for stalls and context size, and daily
+//! usage for budget proximity. `shared::health` turns them into a score.
+
+use crate::handlers::budget;
+use crate::schema::{messages, pending_permission_requests, turns};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde_json::Value;
+use shared::health::RECENT_TURNS;
+use shared::{BudgetLimits, HealthSignals, SessionHealth};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Message roles that make up a turn; other stored messages (operator
+/// actions, portal notices) say nothing about whether Claude is working
+const TURN_ROLES: [&str; 3] = ["user", "assistant", "result"];
+
+/// Context size an assistant message's usage reports
+fn context_tokens(content: &str) -> Option<u64> {
+    let message: Value = serde_json::from_str(content).ok()?;
+    let usage = message.pointer("/message/usage")?;
+    let tokens = [
+        "input_tokens",
+        "cache_creation_input_tokens",
+        "cache_read_input_tokens",
+    ]
+    .iter()
+    .filter_map(|field| usage.get(field).and_then(Value::as_u64))
+    .sum();
+    Some(tokens)
+}
+
+fn signals(
+    conn: &mut PgConnection,
+    session_id: Uuid,
+    limits: &BudgetLimits,
+    now: NaiveDateTime,
+) -> QueryResult<HealthSignals> {
+    let errors: Vec<Option<String>> = turns::table
+        .filter(turns::session_id.eq(session_id))
+        .order(turns::turn_index.desc())
+        .limit(RECENT_TURNS as i64)
+        .select(turns::error)
+        .load(conn)?;
+
+    let last: Option<(String, NaiveDateTime)> = messages::table
+        .filter(messages::session_id.eq(session_id))
+        .filter(messages::role.eq_any(TURN_ROLES))
+        .order(messages::seq.desc())
+        .select((messages::role, messages::created_at))
+        .first(conn)
+        .optional()?;
+    // A turn waiting on a permission answer is waiting on a person, not stalled
+    let awaiting_permission: bool = diesel::select(diesel::dsl::exists(
+        pending_permission_requests::table
+            .filter(pending_permission_requests::session_id.eq(session_id)),
+    ))
+    .get_result(conn)?;
+    let quiet_secs = match last {
+        Some((role, at)) if role != "result" && !awaiting_permission => {
+            Some((now - at).num_seconds().max(0) as u64)
+        }
+        _ => None,
+    };
+
+    let context_tokens = messages::table
+        .filter(messages::session_id.eq(session_id))
+        .filter(messages::role.eq("assistant"))
+        .order(messages::seq.desc())
+        .select(messages::content)
+        .first::<String>(conn)
+        .optional()?
+        .and_then(|content| context_tokens(&content));
+
+    let (budget_level, budget_proximity) = if *limits == BudgetLimits::default() {
+        Default::default()
+    } else {
+        let usage = budget::session_usage(conn, session_id)?;
+        (limits.evaluate(&usage).level, limits.proximity(&usage))
+    };
+
+    Ok(HealthSignals {
+        recent_turns: errors.len() as u32,
+        recent_errors: errors.iter().filter(|e| e.is_some()).count() as u32,
+        quiet_secs,
+        context_tokens,
+        budget_proximity,
+        budget_level,
+    })
+}
+
+/// Health of each of `session_ids`
+pub fn assess(
+    conn: &mut PgConnection,
+    session_ids: &[Uuid],
+) -> QueryResult<HashMap<Uuid, SessionHealth>> {
+    if session_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let limits = budget::load_limits(conn)?;
+    let now = chrono::Utc::now().naive_utc();
+    session_ids
+        .iter()
+        .map(|&id| Ok((id, signals(conn, id, &limits, now)?.evaluate())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_tokens() {
+        let content = r#"{"type":"assistant","message":{"usage":{
+            "input_tokens":12,"cache_creation_input_tokens":3000,
+            "cache_read_input_tokens":150000,"output_tokens":400}}}"#;
+        assert_eq!(context_tokens(content), Some(153_012));
+        assert_eq!(context_tokens(r#"{"type":"assistant","message":{}}"#), None);
+    }
+}
@@ -0,0 +1,194 @@
+//! Log output configuration.
+//!
+//! Configured from the environment:
+//!
+//! - `RUST_LOG`: levels, per module if needed
+//!   (e.g. `info,backend::handlers::websocket=debug`). Default
+//!   `info,tower_http=info`.
+//! - `LOG_FORMAT`: `pretty` (default) or `json`, one object per line with the
+//!   enclosing spans, including the HTTP `request_id`.
+//! - `LOG_DIR`: also write logs to files in this directory.
+//! - `LOG_ROTATION`: how often log files roll over: `minutely`, `hourly`,
+//!   `daily` (default) or `never`.
+
+use std::path::PathBuf;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{EnvFilter, Layer, Registry};
+
+const DEFAULT_FILTER: &str = "info,tower_http=info";
+const LOG_FILE_PREFIX: &str = "backend";
+
+pub type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogConfig {
+    pub filter: String,
+    pub format: LogFormat,
+    pub dir: Option<PathBuf>,
+    pub rotation: Rotation,
+}
+
+impl LogConfig {
+    /// Read the config from the environment, along with any values that
+    /// were ignored. Logging isn't up yet, so the caller reports those.
+    pub fn from_env() -> (Self, Vec<String>) {
+        Self::parse(|name| std::env::var(name).ok())
+    }
+
+    fn parse(var: impl Fn(&str) -> Option<String>) -> (Self, Vec<String>) {
+        let mut problems = Vec::new();
+        let var = |name: &str| var(name).filter(|v| !v.trim().is_empty());
+
+        let filter = match var("RUST_LOG") {
+            Some(filter) if EnvFilter::try_new(&filter).is_ok() => filter,
+            Some(filter) => {
+                problems.push(format!("Invalid RUST_LOG {:?}, using default", filter));
+                DEFAULT_FILTER.to_string()
+            }
+            None => DEFAULT_FILTER.to_string(),
+        };
+
+        let format = match var("LOG_FORMAT")
+            .map(|v| v.trim().to_lowercase())
+            .as_deref()
+        {
+            None | Some("pretty") => LogFormat::Pretty,
+            Some("json") => LogFormat::Json,
+            Some(other) => {
+                problems.push(format!("Unknown LOG_FORMAT {:?}, using pretty", other));
+                LogFormat::Pretty
+            }
+        };
+
+        let rotation = match var("LOG_ROTATION")
+            .map(|v| v.trim().to_lowercase())
+            .as_deref()
+        {
+            Some("minutely") => Rotation::MINUTELY,
+            Some("hourly") => Rotation::HOURLY,
+            None | Some("daily") => Rotation::DAILY,
+            Some("never") => Rotation::NEVER,
+            Some(other) => {
+                problems.push(format!("Unknown LOG_ROTATION {:?}, using daily", other));
+                Rotation::DAILY
+            }
+        };
+
+        let config = Self {
+            filter,
+            format,
+            dir: var("LOG_DIR").map(PathBuf::from),
+            rotation,
+        };
+        (config, problems)
+    }
+
+    pub fn env_filter(&self) -> EnvFilter {
+        EnvFilter::new(&self.filter)
+    }
+}
+
+fn fmt_layer<W>(format: LogFormat, writer: W, ansi: bool) -> BoxedLayer
+where
+    W: for<'w> tracing_subscriber::fmt::MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi);
+    match format {
+        LogFormat::Pretty => layer.boxed(),
+        LogFormat::Json => layer
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+    }
+}
+
+/// Output layers for `config`. Keep the guard alive until exit so buffered
+/// file output is flushed.
+pub fn layers(config: &LogConfig) -> (Vec<BoxedLayer>, Option<WorkerGuard>, Vec<String>) {
+    let mut layers = vec![fmt_layer(config.format, std::io::stdout, true)];
+    let mut problems = Vec::new();
+
+    let Some(dir) = &config.dir else {
+        return (layers, None, problems);
+    };
+    match RollingFileAppender::builder()
+        .rotation(config.rotation.clone())
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix("log")
+        .build(dir)
+    {
+        Ok(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            layers.push(fmt_layer(config.format, writer, false));
+            return (layers, Some(guard), problems);
+        }
+        Err(e) => problems.push(format!(
+            "Failed to open log directory {}: {}",
+            dir.display(),
+            e
+        )),
+    }
+    (layers, None, problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn parse(vars: &[(&str, &str)]) -> (LogConfig, Vec<String>) {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        LogConfig::parse(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_defaults() {
+        let (config, problems) = parse(&[("LOG_DIR", "  ")]);
+        assert!(problems.is_empty());
+        assert_eq!(config.filter, DEFAULT_FILTER);
+        assert_eq!(config.format, LogFormat::Pretty);
+        assert_eq!(config.dir, None);
+        assert_eq!(config.rotation, Rotation::DAILY);
+    }
+
+    #[test]
+    fn test_parse_values() {
+        let (config, problems) = parse(&[
+            ("RUST_LOG", "warn,backend::handlers::websocket=debug"),
+            ("LOG_FORMAT", "JSON"),
+            ("LOG_DIR", "/var/log/cc-proxy"),
+            ("LOG_ROTATION", "hourly"),
+        ]);
+        assert!(problems.is_empty());
+        assert_eq!(config.filter, "warn,backend::handlers::websocket=debug");
+        assert_eq!(config.format, LogFormat::Json);
+        assert_eq!(config.dir, Some(PathBuf::from("/var/log/cc-proxy")));
+        assert_eq!(config.rotation, Rotation::HOURLY);
+    }
+
+    #[test]
+    fn test_invalid_values_fall_back() {
+        let (config, problems) = parse(&[
+            ("RUST_LOG", "backend=loud"),
+            ("LOG_FORMAT", "xml"),
+            ("LOG_ROTATION", "weekly"),
+        ]);
+        assert_eq!(problems.len(), 3);
+        assert_eq!(config.filter, DEFAULT_FILTER);
+        assert_eq!(config.format, LogFormat::Pretty);
+        assert_eq!(config.rotation, Rotation::DAILY);
+    }
+}
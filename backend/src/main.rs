@@ -1,10 +1,31 @@
+mod auth_providers;
+mod ci;
+mod connection;
 mod db;
+mod demo;
+mod email;
 mod embedded_assets;
+mod embeddings;
+mod fork;
 mod handlers;
+mod health;
 mod jwt;
+mod logging;
+mod metering;
+mod migrate;
 mod models;
+mod notifier;
+mod push;
+mod rate_limit;
+mod relay;
+mod runtime_config;
 mod schema;
+mod search;
+mod slack;
 mod speech;
+mod telegram;
+mod telemetry;
+mod webhooks;
 
 use crate::db::DbPool;
 use crate::handlers::device_flow::DeviceFlowStore;
@@ -12,12 +33,12 @@ use axum::{
     routing::{get, post},
     Router,
 };
-use clap::Parser;
-use oauth2::{basic::BasicClient, AuthUrl, ClientId, ClientSecret, RedirectUrl, TokenUrl};
+use clap::{Parser, Subcommand};
 use std::{env, sync::Arc};
 use tower_cookies::{CookieManagerLayer, Key};
 use tower_http::cors::{Any, CorsLayer};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::TraceLayer;
 
 use handlers::websocket::SessionManager;
 
@@ -28,6 +49,22 @@ struct Args {
     /// Enable development mode (bypasses OAuth, creates test user)
     #[arg(long)]
     dev_mode: bool,
+
+    /// Serve a simulated session with a scripted transcript (implies --dev-mode)
+    #[arg(long)]
+    demo: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Check and apply database migrations, then exit
+    Migrate(migrate::MigrateArgs),
+    /// List, download, or remove models for local speech recognition, then exit
+    #[cfg(feature = "local-speech")]
+    SpeechModels(speech::ModelArgs),
 }
 
 #[derive(Clone)]
@@ -35,50 +72,66 @@ pub struct AppState {
     pub dev_mode: bool,
     pub db_pool: DbPool,
     pub session_manager: SessionManager,
-    pub oauth_basic_client: Option<BasicClient>,
+    /// Sign-in providers; empty in dev mode
+    pub auth_providers: auth_providers::AuthProviders,
     pub device_flow_store: Option<DeviceFlowStore>,
     pub public_url: String,
     pub cookie_key: Key,
     pub jwt_secret: String,
-    pub speech_credentials_path: Option<String>,
-    pub app_title: String,
-    /// Allowed email domain (e.g., "company.com")
-    pub allowed_email_domain: Option<String>,
-    /// Allowed email addresses (comma-separated in env var)
-    pub allowed_emails: Option<Vec<String>>,
-    /// Maximum messages to keep per session (default: 100)
-    pub message_retention_count: i64,
-    /// Days to retain messages before deletion (default: 30, 0 = disabled)
-    pub message_retention_days: u32,
+    /// Speech-to-text for voice input, if a provider is configured
+    pub speech: Option<Arc<dyn speech::SpeechProvider>>,
+    /// Title, sign-in allowlist, retention, and other settings admins can
+    /// change without a restart
+    pub runtime_config: runtime_config::RuntimeConfigStore,
+    /// Request and message budgets per proxy token and web user
+    pub rate_limits: Arc<rate_limit::RateLimits>,
+    /// Embedding provider for semantic search, if one is configured
+    pub embeddings: Option<embeddings::EmbeddingProvider>,
+    /// GitHub webhook for CI status, if a secret is configured
+    pub github_webhook: Option<ci::GitHubWebhook>,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Parse CLI arguments
-    let args = Args::parse();
+    let mut args = Args::parse();
+    if args.demo {
+        args.dev_mode = true;
+    }
 
-    // Initialize tracing with info level by default
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "info,tower_http=info".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    // Load environment variables first so .env can configure logging
+    dotenvy::dotenv().ok();
+
+    let (log_config, log_problems) = logging::LogConfig::from_env();
+    let telemetry = telemetry::init(&log_config);
+    for problem in log_problems {
+        tracing::warn!("{}", problem);
+    }
 
     if args.dev_mode {
         tracing::warn!("🚧 DEV MODE ENABLED - OAuth is bypassed, test user will be used");
     }
 
-    // Load environment variables
-    dotenvy::dotenv().ok();
+    #[cfg(feature = "local-speech")]
+    if let Some(Command::SpeechModels(model_args)) = args.command {
+        return speech::run_models_command(model_args).await;
+    }
 
     // Create database pool
     let pool = db::create_pool()?;
 
-    // Run pending migrations automatically
+    if let Some(Command::Migrate(migrate_args)) = args.command {
+        return migrate::run_command(&pool, migrate_args);
+    }
+
+    // Run pending migrations automatically. Backups are opt-in here since the
+    // server may run where pg_dump isn't installed; `migrate` takes one by default.
     tracing::info!("Running database migrations...");
-    match db::run_migrations(&pool) {
+    let backup_dir = env::var("MIGRATION_BACKUP_DIR")
+        .ok()
+        .filter(|d| !d.is_empty())
+        .map(std::path::PathBuf::from);
+    match migrate::run_migrations(&pool, backup_dir.as_deref()) {
         Ok(applied) => {
             if applied.is_empty() {
                 tracing::info!("Database is up to date (no pending migrations)");
@@ -98,27 +151,6 @@ async fn main() -> anyhow::Result<()> {
     // Create device flow store
     let device_flow_store = handlers::device_flow::DeviceFlowStore::default();
 
-    // Create OAuth client (skip in dev mode)
-    let oauth_basic_client = if !args.dev_mode {
-        let client_id =
-            ClientId::new(env::var("GOOGLE_CLIENT_ID").expect("GOOGLE_CLIENT_ID must be set"));
-        let client_secret = ClientSecret::new(
-            env::var("GOOGLE_CLIENT_SECRET").expect("GOOGLE_CLIENT_SECRET must be set"),
-        );
-        let auth_url = AuthUrl::new("https://accounts.google.com/o/oauth2/v2/auth".to_string())?;
-        let token_url = TokenUrl::new("https://oauth2.googleapis.com/token".to_string())?;
-        let redirect_uri = RedirectUrl::new(
-            env::var("GOOGLE_REDIRECT_URI").expect("GOOGLE_REDIRECT_URI must be set"),
-        )?;
-
-        Some(
-            BasicClient::new(client_id, Some(client_secret), auth_url, Some(token_url))
-                .set_redirect_uri(redirect_uri),
-        )
-    } else {
-        None
-    };
-
     // Create test user in dev mode
     if args.dev_mode {
         use diesel::prelude::*;
@@ -147,9 +179,6 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
-    // Create session manager for WebSocket connections
-    let session_manager = SessionManager::new();
-
     // Cleanup stale sessions on startup (mark all "active" sessions as "disconnected"
     // since they can't be active if we just started)
     {
@@ -175,6 +204,78 @@ async fn main() -> anyhow::Result<()> {
         format!("http://localhost:{}", port)
     });
 
+    // Create session manager for WebSocket connections
+    let webhooks = match webhooks::Webhooks::from_env() {
+        Ok(Some(webhooks)) => {
+            tracing::info!(
+                "Sending session events to {} webhook(s)",
+                webhooks.url_count()
+            );
+            Some(webhooks)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            tracing::warn!("Webhooks disabled: {}", e);
+            None
+        }
+    };
+    let slack = match slack::Slack::from_env(&public_url) {
+        Ok(slack) => slack,
+        Err(e) => {
+            tracing::warn!("Slack notifications disabled: {}", e);
+            None
+        }
+    };
+    let push = match push::WebPush::from_env(&public_url) {
+        Ok(push) => push,
+        Err(e) => {
+            tracing::warn!("Push notifications disabled: {}", e);
+            None
+        }
+    };
+    let email = match email::Email::from_env(&public_url) {
+        Ok(email) => email,
+        Err(e) => {
+            tracing::warn!("Email notifications disabled: {}", e);
+            None
+        }
+    };
+    let notifiers = match notifier::Notifiers::from_env(pool.clone()) {
+        Ok(notifiers) => Some(
+            notifiers
+                .with(slack)
+                .with(push.clone())
+                .with(telegram::Telegram::from_env(&public_url))
+                .with(email),
+        )
+        .filter(|n| !n.is_empty()),
+        Err(e) => {
+            tracing::warn!("Notifications disabled: {}", e);
+            None
+        }
+    };
+    let session_manager = SessionManager::new()
+        .with_activity(pool.clone())
+        .with_webhooks(webhooks)
+        .with_notifiers(notifiers.clone())
+        .with_push(push);
+
+    // Sign-in providers (skipped in dev mode, which signs in as the test user)
+    let auth_providers = if args.dev_mode {
+        auth_providers::AuthProviders::default()
+    } else {
+        let providers = auth_providers::AuthProviders::from_env(&public_url).await?;
+        if providers.is_empty() {
+            anyhow::bail!(
+                "No sign-in provider configured; set GOOGLE_CLIENT_ID, GITHUB_CLIENT_ID, or OIDC_ISSUER"
+            );
+        }
+        for provider in providers.info() {
+            tracing::info!("Sign-in with {} enabled", provider.name);
+        }
+        providers
+    };
+
     // Create cookie signing key from SESSION_SECRET or generate random for dev
     let session_secret = env::var("SESSION_SECRET").ok();
     let cookie_key = match &session_secret {
@@ -199,13 +300,21 @@ async fn main() -> anyhow::Result<()> {
         }
     };
 
-    // Google Cloud Speech credentials path
-    let speech_credentials_path = env::var("GOOGLE_APPLICATION_CREDENTIALS").ok();
-    if speech_credentials_path.is_some() {
-        tracing::info!("Google Cloud Speech credentials configured for voice input");
-    } else {
-        tracing::info!("Voice input disabled - GOOGLE_APPLICATION_CREDENTIALS not set");
-    }
+    // Speech-to-text provider for voice input
+    let speech = match speech::from_env() {
+        Ok(Some(provider)) => {
+            tracing::info!("Voice input using {} speech-to-text", provider.name());
+            Some(provider)
+        }
+        Ok(None) => {
+            tracing::info!("Voice input disabled - no speech-to-text provider configured");
+            None
+        }
+        Err(e) => {
+            tracing::warn!("Voice input disabled: {}", e);
+            None
+        }
+    };
 
     // JWT secret for proxy tokens (uses SESSION_SECRET or generates for dev)
     let jwt_secret = session_secret.unwrap_or_else(|| {
@@ -224,6 +333,10 @@ async fn main() -> anyhow::Result<()> {
         env::var("APP_TITLE").unwrap_or_else(|_| "Claude Code Sessions".to_string())
     };
 
+    // Destination for in-app problem reports (GitHub new-issue URL or compatible endpoint)
+    let issue_report_url = env::var("ISSUE_REPORT_URL")
+        .unwrap_or_else(|_| shared::DEFAULT_ISSUE_REPORT_URL.to_string());
+
     // Email access control (optional)
     let allowed_email_domain = env::var("ALLOWED_EMAIL_DOMAIN").ok();
     let allowed_emails = env::var("ALLOWED_EMAILS").ok().map(|s| {
@@ -257,12 +370,84 @@ async fn main() -> anyhow::Result<()> {
         message_retention_days
     );
 
+    // Concurrent sessions per user (0 = unlimited)
+    let max_sessions_per_user: u32 = env::var("MAX_SESSIONS_PER_USER")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(0);
+    let rate_limit_requests_per_minute: u32 = env::var("RATE_LIMIT_REQUESTS_PER_MINUTE")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(0);
+    let rate_limit_messages_per_minute: u32 = env::var("RATE_LIMIT_MESSAGES_PER_MINUTE")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(0);
+
+    // How much of long tool output the transcript shows
+    let render_defaults = shared::RenderLimits::default();
+    let render_limit = |name: &str, default: usize| {
+        env::var(name)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(default)
+    };
+    let render_limits = shared::RenderLimits {
+        result_preview_chars: render_limit(
+            "RENDER_RESULT_PREVIEW_CHARS",
+            render_defaults.result_preview_chars,
+        ),
+        result_collapse_lines: render_limit(
+            "RENDER_RESULT_COLLAPSE_LINES",
+            render_defaults.result_collapse_lines,
+        ),
+        inline_file_max_bytes: render_limit(
+            "RENDER_INLINE_FILE_MAX_BYTES",
+            render_defaults.inline_file_max_bytes,
+        ),
+        file_preview_lines: render_limit(
+            "RENDER_FILE_PREVIEW_LINES",
+            render_defaults.file_preview_lines,
+        ),
+    };
+
+    // Environment values are the defaults; admin overrides stored in the
+    // database are layered on top and can change while the server runs
+    let runtime_config = runtime_config::RuntimeConfigStore::new(shared::RuntimeConfig {
+        app_title,
+        issue_report_url,
+        allowed_email_domain,
+        allowed_emails,
+        message_retention_count,
+        message_retention_days,
+        max_sessions_per_user,
+        rate_limit_requests_per_minute,
+        rate_limit_messages_per_minute,
+        render_limits,
+    });
+    match runtime_config.reload(&pool) {
+        Ok(true) => tracing::info!(
+            "Applied runtime config overrides from database: {:?}",
+            runtime_config.current()
+        ),
+        Ok(false) => {}
+        Err(e) => tracing::error!("Failed to load runtime config overrides: {}", e),
+    }
+
+    let embeddings = match embeddings::EmbeddingProvider::from_env() {
+        Ok(provider) => provider,
+        Err(e) => {
+            tracing::warn!("Semantic search disabled: {}", e);
+            None
+        }
+    };
+
     // Create app state
     let app_state = Arc::new(AppState {
         dev_mode: args.dev_mode,
         db_pool: pool.clone(),
         session_manager: session_manager.clone(),
-        oauth_basic_client,
+        auth_providers,
         device_flow_store: if args.dev_mode {
             None
         } else {
@@ -271,14 +456,23 @@ async fn main() -> anyhow::Result<()> {
         public_url: public_url.clone(),
         cookie_key,
         jwt_secret,
-        speech_credentials_path,
-        app_title,
-        allowed_email_domain,
-        allowed_emails,
-        message_retention_count,
-        message_retention_days,
+        speech,
+        runtime_config,
+        rate_limits: Arc::default(),
+        embeddings,
+        github_webhook: ci::GitHubWebhook::from_env(),
     });
 
+    // A kill switch engaged before the restart still holds
+    match handlers::kill_switch::reload(&app_state) {
+        Ok(true) => tracing::warn!(
+            "Kill switch is engaged: {:?}",
+            app_state.session_manager.kill_switch()
+        ),
+        Ok(false) => {}
+        Err(e) => tracing::error!("Failed to load kill switch: {}", e),
+    }
+
     // Setup CORS
     let cors = CorsLayer::new()
         .allow_origin(Any)
@@ -291,6 +485,11 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/health", get(|| async { "OK" }))
         // App configuration (public, no auth required)
         .route("/api/config", get(handlers::config::get_config))
+        .route("/api/maintenance", get(handlers::maintenance::get_notice))
+        .route(
+            "/api/kill-switch",
+            get(handlers::kill_switch::get_kill_switch),
+        )
         // Session API routes
         .route("/api/sessions", get(handlers::sessions::list_sessions))
         .route("/api/sessions/:id", get(handlers::sessions::get_session))
@@ -298,6 +497,14 @@ async fn main() -> anyhow::Result<()> {
             "/api/sessions/:id",
             axum::routing::delete(handlers::sessions::delete_session),
         )
+        .route(
+            "/api/sessions/:id/outcome",
+            axum::routing::put(handlers::sessions::set_session_outcome),
+        )
+        .route(
+            "/api/sessions/:id/related",
+            get(handlers::search::related_sessions),
+        )
         // Session member management routes
         .route(
             "/api/sessions/:id/members",
@@ -309,10 +516,143 @@ async fn main() -> anyhow::Result<()> {
             axum::routing::delete(handlers::sessions::remove_session_member)
                 .patch(handlers::sessions::update_session_member_role),
         )
+        // Time-boxed guest links (owner only), and where they lead
+        .route(
+            "/api/sessions/:id/guests",
+            get(handlers::guests::list_guest_invites).post(handlers::guests::create_guest_invite),
+        )
+        .route(
+            "/api/sessions/:id/guests/:invite_id",
+            axum::routing::delete(handlers::guests::revoke_guest_invite),
+        )
+        .route(
+            "/api/guest/:token",
+            get(handlers::guests::redeem_guest_link),
+        )
         .route(
             "/api/sessions/:id/messages",
             get(handlers::messages::list_messages).post(handlers::messages::create_message),
         )
+        .route(
+            "/api/sessions/:id/input",
+            post(handlers::messages::send_input),
+        )
+        .route("/api/sessions/:id/fork", get(handlers::fork::fork_session))
+        .route(
+            "/api/sessions/:id/stream",
+            get(handlers::stream::stream_session),
+        )
+        // Message reactions
+        .route(
+            "/api/sessions/:id/reactions",
+            get(handlers::reactions::list_reactions),
+        )
+        .route(
+            "/api/sessions/:id/messages/:message_id/reactions",
+            post(handlers::reactions::toggle_reaction),
+        )
+        // CI results from GitHub
+        .route("/api/webhooks/github", post(handlers::ci::github_webhook))
+        // Annotations posted by external tools (CI, scanners)
+        .route(
+            "/api/sessions/:id/annotations",
+            get(handlers::annotations::list_annotations)
+                .post(handlers::annotations::create_annotation),
+        )
+        // Turn feedback (thumbs up/down on result messages)
+        .route(
+            "/api/sessions/:id/feedback",
+            get(handlers::feedback::list_feedback),
+        )
+        .route(
+            "/api/sessions/:id/messages/:message_id/feedback",
+            axum::routing::put(handlers::feedback::submit_feedback)
+                .delete(handlers::feedback::delete_feedback),
+        )
+        .route(
+            "/api/feedback/export",
+            get(handlers::feedback::export_feedback),
+        )
+        // Cost and token usage aggregates
+        .route("/api/usage", get(handlers::usage::get_usage))
+        .route("/api/onboarding", get(handlers::onboarding::get_onboarding))
+        // Notification channels per event
+        .route(
+            "/api/notifications",
+            get(handlers::notifications::get_notifications)
+                .put(handlers::notifications::update_notifications),
+        )
+        // Browser push notification subscriptions
+        .route(
+            "/api/push/subscriptions",
+            post(handlers::push::subscribe).delete(handlers::push::unsubscribe),
+        )
+        // Transcript search across the user's sessions
+        .route("/api/search", get(handlers::search::search_messages))
+        .route("/api/activity", get(handlers::activity::list_activity))
+        .route(
+            "/api/saved-searches",
+            get(handlers::saved_searches::list_saved_searches)
+                .post(handlers::saved_searches::create_saved_search),
+        )
+        .route(
+            "/api/saved-searches/:id",
+            axum::routing::patch(handlers::saved_searches::update_saved_search)
+                .delete(handlers::saved_searches::delete_saved_search),
+        )
+        // Budget status (soft/hard caps configured by admins)
+        .route(
+            "/api/sessions/:id/budget",
+            get(handlers::budget::get_session_budget),
+        )
+        // Incident bundles (diagnostics for errored or crashed sessions)
+        .route(
+            "/api/sessions/:id/incidents",
+            get(handlers::incidents::list_incidents),
+        )
+        .route(
+            "/api/sessions/:id/incidents/:incident_id/bundle",
+            get(handlers::incidents::download_incident_bundle),
+        )
+        // Latest snapshot mirrored from the proxy, for disaster recovery
+        .route(
+            "/api/sessions/:id/snapshot",
+            get(handlers::snapshots::get_snapshot)
+                .put(handlers::snapshots::upload_snapshot)
+                .layer(axum::extract::DefaultBodyLimit::max(
+                    shared::MAX_SNAPSHOT_BYTES,
+                )),
+        )
+        .route(
+            "/api/sessions/:id/snapshot/download",
+            get(handlers::snapshots::download_snapshot),
+        )
+        // Turns recorded as the session's messages were stored
+        .route("/api/sessions/:id/turns", get(handlers::turns::list_turns))
+        // Permission requests and how each was answered
+        .route(
+            "/api/sessions/:id/permissions",
+            get(handlers::permission_history::list_permission_history),
+        )
+        .route(
+            "/api/sessions/:id/escalation",
+            get(handlers::escalation::get_escalation_policy)
+                .put(handlers::escalation::update_escalation_policy),
+        )
+        .route(
+            "/api/sessions/:id/redaction",
+            get(handlers::redaction::get_redaction_policy)
+                .put(handlers::redaction::update_redaction_policy),
+        )
+        // Files written during a session, zipped on demand by the proxy
+        .route(
+            "/api/sessions/:id/artifacts",
+            get(handlers::artifacts::list_artifacts),
+        )
+        .route(
+            "/api/sessions/:id/artifacts/download",
+            get(handlers::artifacts::download_artifacts),
+        )
         // Proxy token management endpoints
         .route(
             "/api/proxy-tokens",
@@ -321,12 +661,16 @@ async fn main() -> anyhow::Result<()> {
         )
         .route(
             "/api/proxy-tokens/:id",
-            axum::routing::delete(handlers::proxy_tokens::revoke_token_handler),
+            get(handlers::proxy_tokens::get_token_handler)
+                .delete(handlers::proxy_tokens::revoke_token_handler),
         )
         // Auth routes (under /api/auth)
-        .route("/api/auth/google", get(handlers::auth::login))
-        .route("/api/auth/google/callback", get(handlers::auth::callback))
+        .route("/api/auth/providers", get(handlers::auth::providers))
         .route("/api/auth/me", get(handlers::auth::me))
+        .route(
+            "/api/auth/me/voice-language",
+            axum::routing::put(handlers::voice::set_voice_language),
+        )
         .route("/api/auth/logout", get(handlers::auth::logout))
         .route("/api/auth/dev-login", get(handlers::auth::dev_login))
         // Device-specific login endpoint (separate from regular web login)
@@ -352,11 +696,18 @@ async fn main() -> anyhow::Result<()> {
             "/api/auth/device/deny",
             post(handlers::device_flow::device_deny),
         )
+        // Provider sign-in; the static /api/auth routes above take precedence
+        .route("/api/auth/:provider", get(handlers::auth::login))
+        .route(
+            "/api/auth/:provider/callback",
+            get(handlers::auth::callback),
+        )
         // WebSocket routes
         .route(
             "/ws/session",
             get(handlers::websocket::handle_session_websocket),
         )
+        .route("/ws/daemon", get(handlers::daemon::handle_daemon_websocket))
         .route(
             "/ws/client",
             get(handlers::websocket::handle_web_client_websocket),
@@ -381,6 +732,28 @@ async fn main() -> anyhow::Result<()> {
             "/api/admin/users/:id",
             axum::routing::patch(handlers::admin::update_user),
         )
+        .route(
+            "/api/admin/config",
+            get(handlers::config::get_runtime_config).put(handlers::config::update_runtime_config),
+        )
+        .route(
+            "/api/admin/budget",
+            get(handlers::budget::get_limits).put(handlers::budget::update_limits),
+        )
+        .route(
+            "/api/admin/maintenance",
+            axum::routing::put(handlers::maintenance::set_notice)
+                .delete(handlers::maintenance::clear_notice),
+        )
+        .route(
+            "/api/admin/kill-switch",
+            axum::routing::put(handlers::kill_switch::engage)
+                .delete(handlers::kill_switch::release),
+        )
+        .route(
+            "/api/admin/metering/export",
+            get(handlers::metering::export_usage),
+        )
         .route("/api/admin/sessions", get(handlers::admin::list_sessions))
         .route(
             "/api/admin/sessions/:id",
@@ -403,8 +776,20 @@ async fn main() -> anyhow::Result<()> {
 
     tracing::info!("Serving embedded frontend assets");
 
-    // Add CORS and cookie management
-    let app = app.layer(CookieManagerLayer::new()).layer(cors);
+    // Add rate limiting, CORS, cookie management, and request ids. The rate
+    // limiter sits inside the cookie layer so it can read the session
+    // cookie. The id is assigned outermost so the request span, and any
+    // WebSocket it upgrades to, can record it.
+    let app = app
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            rate_limit::limit_requests,
+        ))
+        .layer(CookieManagerLayer::new())
+        .layer(cors)
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(TraceLayer::new_for_http().make_span_with(telemetry::http_span))
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid));
 
     // Spawn background task to broadcast user spend updates
     {
@@ -419,6 +804,155 @@ async fn main() -> anyhow::Result<()> {
         tracing::info!("Started user spend broadcast task (every 5 seconds)");
     }
 
+    // Pick up runtime config and kill switch changes made by other backend
+    // instances
+    {
+        let app_state = app_state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(runtime_config::RELOAD_INTERVAL);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if let Err(e) = app_state.runtime_config.reload(&app_state.db_pool) {
+                    tracing::error!("Failed to reload runtime config: {}", e);
+                }
+                if let Err(e) = handlers::kill_switch::reload(&app_state) {
+                    tracing::error!("Failed to reload kill switch: {}", e);
+                }
+            }
+        });
+    }
+
+    // Log every runtime config change, whichever instance made it
+    {
+        let mut config_rx = app_state.runtime_config.subscribe();
+        tokio::spawn(async move {
+            while config_rx.changed().await.is_ok() {
+                let config = config_rx.borrow_and_update().clone();
+                tracing::info!("Runtime config updated: {:?}", config);
+            }
+        });
+    }
+
+    // Forget rate limit buckets of callers that have gone quiet
+    {
+        let app_state = app_state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(rate_limit::PRUNE_INTERVAL);
+            loop {
+                interval.tick().await;
+                app_state.rate_limits.prune();
+            }
+        });
+    }
+
+    // Notify contacts about, then default, permission requests nobody answers
+    {
+        let app_state = app_state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(handlers::escalation::CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                handlers::escalation::run_escalations(&app_state);
+            }
+        });
+    }
+
+    // End guests' access when their invite runs out or is revoked
+    {
+        let app_state = app_state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(handlers::guests::CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                handlers::guests::end_expired_guests(&app_state);
+            }
+        });
+    }
+
+    // Tell users when new messages match their alerting saved searches
+    {
+        let app_state = app_state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(handlers::saved_searches::ALERT_INTERVAL);
+            loop {
+                interval.tick().await;
+                handlers::saved_searches::run_alerts(&app_state);
+            }
+        });
+    }
+
+    // Remind people of permission requests that have waited too long, and
+    // send each finished day's digests
+    if let Some(notifiers) = notifiers {
+        tracing::info!(
+            "Started notifications on {:?} (permission wait {} min)",
+            notifiers.available(),
+            notifiers.permission_wait_minutes
+        );
+        {
+            let notifiers = notifiers.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(notifier::CHECK_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    notifiers.remind_waiting_permissions().await;
+                }
+            });
+        }
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(notifier::DIGEST_CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                notifiers.send_daily_digests().await;
+            }
+        });
+    }
+
+    // Play the scripted demo session
+    if args.demo {
+        tracing::warn!("🎬 DEMO MODE - serving a simulated session");
+        tokio::spawn(demo::run(
+            app_state.db_pool.clone(),
+            app_state.session_manager.clone(),
+        ));
+    }
+
+    // Embed new prompts and assistant turns for semantic search
+    if let Some(provider) = app_state.embeddings.clone() {
+        tracing::info!(
+            "Started message embedding task ({}, {})",
+            provider.name(),
+            provider.model
+        );
+        let db_pool = app_state.db_pool.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(embeddings::INDEX_INTERVAL);
+            loop {
+                interval.tick().await;
+                embeddings::run_indexing(&db_pool, &provider).await;
+            }
+        });
+    }
+
+    // Send each finished day's usage records to the billing sink, if one is set
+    match metering::MeteringSink::from_env() {
+        Ok(Some(sink)) => {
+            let db_pool = app_state.db_pool.clone();
+            tokio::spawn(async move {
+                let client = reqwest::Client::new();
+                let mut interval = tokio::time::interval(metering::CHECK_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    metering::run_exports(&db_pool, &sink, &client).await;
+                }
+            });
+            tracing::info!("Started usage metering export task");
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Usage metering disabled: {}", e),
+    }
+
     // Spawn background task for message retention cleanup (runs every 60 seconds)
     {
         let app_state = app_state.clone();
@@ -440,9 +974,15 @@ async fn main() -> anyhow::Result<()> {
 
     // Create graceful shutdown handler
     let shutdown_state = app_state.clone();
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal(shutdown_state))
-        .await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal(shutdown_state))
+    .await?;
+
+    // Flush buffered spans
+    telemetry.shutdown();
 
     Ok(())
 }
@@ -549,9 +1089,10 @@ async fn run_retention_cleanup(app_state: &Arc<AppState>) {
         return;
     };
 
+    let runtime_config = app_state.runtime_config.current();
     let config = RetentionConfig::new(
-        app_state.message_retention_count,
-        app_state.message_retention_days,
+        runtime_config.message_retention_count,
+        runtime_config.message_retention_days,
     );
 
     let (age_deleted, count_deleted) = run_retention_cleanup(&mut conn, session_ids, config);
@@ -0,0 +1,605 @@
+//! Usage metering for hosted deployments
+//!
+//! Operators who charge for a shared backend can have it emit one usage
+//! record per user per UTC day: sessions started, tokens, speech seconds,
+//! and stored bytes. After a day ends, a background task sends that day's
+//! records to the configured sink (a webhook, CSV files, or Stripe meter
+//! events). Each day is claimed in `metering_exports` before sending, so
+//! only one backend instance exports it. A failed export is retried on the
+//! next check.
+
+use crate::db::DbPool;
+use crate::schema::{metering_exports, sessions, speech_usage_daily, usage_daily, users};
+use anyhow::{bail, Context};
+use chrono::{Days, NaiveDate, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Uuid as SqlUuid};
+use diesel::upsert::excluded;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// How often the task looks for finished days to export
+pub const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// How far back the first export (or one after a long outage) reaches
+const MAX_CATCH_UP_DAYS: u64 = 7;
+
+/// Voice audio is PCM16 at 16 kHz mono
+const AUDIO_BYTES_PER_MS: u64 = 32;
+
+const STRIPE_METER_EVENTS_URL: &str = "https://api.stripe.com/v1/billing/meter_events";
+
+/// One user's usage for one UTC day
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UsageRecord {
+    pub day: NaiveDate,
+    pub user_id: Uuid,
+    pub email: String,
+    /// Who the billing provider knows this user as
+    pub billing_customer_id: Option<String>,
+    pub sessions_started: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_creation_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub cost_usd: f64,
+    pub speech_seconds: f64,
+    /// Message content stored across the user's sessions when the record was
+    /// built. A level, not a daily increase.
+    pub storage_bytes: i64,
+}
+
+impl UsageRecord {
+    fn new(day: NaiveDate, user_id: Uuid) -> Self {
+        Self {
+            day,
+            user_id,
+            email: String::new(),
+            billing_customer_id: None,
+            sessions_started: 0,
+            input_tokens: 0,
+            output_tokens: 0,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 0,
+            cost_usd: 0.0,
+            speech_seconds: 0.0,
+            storage_bytes: 0,
+        }
+    }
+}
+
+/// A quantity that can be reported as a Stripe meter event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    SessionsStarted,
+    InputTokens,
+    OutputTokens,
+    CacheCreationTokens,
+    CacheReadTokens,
+    SpeechSeconds,
+    StorageBytes,
+}
+
+impl Metric {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "sessions_started" => Metric::SessionsStarted,
+            "input_tokens" => Metric::InputTokens,
+            "output_tokens" => Metric::OutputTokens,
+            "cache_creation_tokens" => Metric::CacheCreationTokens,
+            "cache_read_tokens" => Metric::CacheReadTokens,
+            "speech_seconds" => Metric::SpeechSeconds,
+            "storage_bytes" => Metric::StorageBytes,
+            _ => return None,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Metric::SessionsStarted => "sessions_started",
+            Metric::InputTokens => "input_tokens",
+            Metric::OutputTokens => "output_tokens",
+            Metric::CacheCreationTokens => "cache_creation_tokens",
+            Metric::CacheReadTokens => "cache_read_tokens",
+            Metric::SpeechSeconds => "speech_seconds",
+            Metric::StorageBytes => "storage_bytes",
+        }
+    }
+
+    /// Whole-number value for a meter event; partial seconds round up
+    fn value(&self, record: &UsageRecord) -> i64 {
+        match self {
+            Metric::SessionsStarted => record.sessions_started,
+            Metric::InputTokens => record.input_tokens,
+            Metric::OutputTokens => record.output_tokens,
+            Metric::CacheCreationTokens => record.cache_creation_tokens,
+            Metric::CacheReadTokens => record.cache_read_tokens,
+            Metric::SpeechSeconds => record.speech_seconds.ceil() as i64,
+            Metric::StorageBytes => record.storage_bytes,
+        }
+    }
+}
+
+/// Where finished days are sent
+#[derive(Debug, Clone, PartialEq)]
+pub enum MeteringSink {
+    /// POST each day's records as JSON, with an optional bearer token
+    Webhook { url: String, token: Option<String> },
+    /// Write a `usage-YYYY-MM-DD.csv` file per day
+    Csv { dir: PathBuf },
+    /// Send a Stripe meter event per user and configured metric
+    Stripe {
+        api_key: String,
+        meters: Vec<(Metric, String)>,
+    },
+}
+
+impl MeteringSink {
+    /// Read the sink from the environment. `Ok(None)` means metering is off.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        Self::parse(|name| std::env::var(name).ok())
+    }
+
+    fn parse(var: impl Fn(&str) -> Option<String>) -> Result<Option<Self>, String> {
+        let var = |name: &str| var(name).filter(|v| !v.trim().is_empty());
+        let required = |name: &str| var(name).ok_or_else(|| format!("{} must be set", name));
+
+        let kind = match var("METERING_SINK") {
+            Some(kind) => kind.trim().to_lowercase(),
+            None => return Ok(None),
+        };
+        let sink = match kind.as_str() {
+            "webhook" => MeteringSink::Webhook {
+                url: required("METERING_WEBHOOK_URL")?,
+                token: var("METERING_WEBHOOK_TOKEN"),
+            },
+            "csv" => MeteringSink::Csv {
+                dir: PathBuf::from(required("METERING_CSV_DIR")?),
+            },
+            "stripe" => {
+                let meters = required("STRIPE_METERS")?
+                    .split(',')
+                    .filter(|pair| !pair.trim().is_empty())
+                    .map(|pair| {
+                        let (metric, event_name) = pair
+                            .split_once('=')
+                            .ok_or_else(|| format!("Expected metric=event_name, got {:?}", pair))?;
+                        let metric = Metric::parse(metric.trim())
+                            .ok_or_else(|| format!("Unknown metric {:?}", metric.trim()))?;
+                        Ok((metric, event_name.trim().to_string()))
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                if meters.is_empty() {
+                    return Err("STRIPE_METERS lists no meters".to_string());
+                }
+                MeteringSink::Stripe {
+                    api_key: required("STRIPE_API_KEY")?,
+                    meters,
+                }
+            }
+            other => return Err(format!("Unknown METERING_SINK {:?}", other)),
+        };
+        Ok(Some(sink))
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            MeteringSink::Webhook { .. } => "webhook",
+            MeteringSink::Csv { .. } => "csv",
+            MeteringSink::Stripe { .. } => "stripe",
+        }
+    }
+}
+
+/// Add audio streamed to speech recognition to the user's usage for today
+pub fn record_speech_audio(db_pool: &DbPool, user_id: Uuid, audio_bytes: u64) {
+    let audio_ms = (audio_bytes / AUDIO_BYTES_PER_MS) as i64;
+    if audio_ms == 0 {
+        return;
+    }
+    let mut conn = match db_pool.get() {
+        Ok(conn) => conn,
+        Err(e) => {
+            error!("Failed to get DB connection to record speech usage: {}", e);
+            return;
+        }
+    };
+    let result = diesel::insert_into(speech_usage_daily::table)
+        .values((
+            speech_usage_daily::user_id.eq(user_id),
+            speech_usage_daily::day.eq(Utc::now().date_naive()),
+            speech_usage_daily::audio_ms.eq(audio_ms),
+        ))
+        .on_conflict((speech_usage_daily::user_id, speech_usage_daily::day))
+        .do_update()
+        .set(
+            speech_usage_daily::audio_ms
+                .eq(speech_usage_daily::audio_ms + excluded(speech_usage_daily::audio_ms)),
+        )
+        .execute(&mut conn);
+    if let Err(e) = result {
+        error!("Failed to record speech usage for {}: {}", user_id, e);
+    }
+}
+
+#[derive(QueryableByName)]
+struct StorageRow {
+    #[diesel(sql_type = SqlUuid)]
+    user_id: Uuid,
+    #[diesel(sql_type = BigInt)]
+    bytes: i64,
+}
+
+fn entry(
+    records: &mut BTreeMap<Uuid, UsageRecord>,
+    day: NaiveDate,
+    user_id: Uuid,
+) -> &mut UsageRecord {
+    records
+        .entry(user_id)
+        .or_insert_with(|| UsageRecord::new(day, user_id))
+}
+
+/// Build every user's record for `day`. Users with no activity that day
+/// and nothing stored are left out.
+pub fn collect_records(conn: &mut PgConnection, day: NaiveDate) -> QueryResult<Vec<UsageRecord>> {
+    let mut records: BTreeMap<Uuid, UsageRecord> = BTreeMap::new();
+
+    let usage: Vec<(Uuid, f64, i64, i64, i64, i64)> = usage_daily::table
+        .filter(usage_daily::day.eq(day))
+        .select((
+            usage_daily::user_id,
+            usage_daily::cost_usd,
+            usage_daily::input_tokens,
+            usage_daily::output_tokens,
+            usage_daily::cache_creation_tokens,
+            usage_daily::cache_read_tokens,
+        ))
+        .load(conn)?;
+    for (user_id, cost, input, output, cache_creation, cache_read) in usage {
+        let r = entry(&mut records, day, user_id);
+        r.cost_usd += cost;
+        r.input_tokens += input;
+        r.output_tokens += output;
+        r.cache_creation_tokens += cache_creation;
+        r.cache_read_tokens += cache_read;
+    }
+
+    let start = day.and_hms_opt(0, 0, 0).expect("midnight is valid");
+    let end = start + chrono::Duration::days(1);
+    let started: Vec<Uuid> = sessions::table
+        .filter(sessions::created_at.ge(start))
+        .filter(sessions::created_at.lt(end))
+        .select(sessions::user_id)
+        .load(conn)?;
+    for user_id in started {
+        entry(&mut records, day, user_id).sessions_started += 1;
+    }
+
+    let speech: Vec<(Uuid, i64)> = speech_usage_daily::table
+        .filter(speech_usage_daily::day.eq(day))
+        .select((speech_usage_daily::user_id, speech_usage_daily::audio_ms))
+        .load(conn)?;
+    for (user_id, audio_ms) in speech {
+        entry(&mut records, day, user_id).speech_seconds = audio_ms as f64 / 1000.0;
+    }
+
+    let storage: Vec<StorageRow> = diesel::sql_query(
+        "SELECT s.user_id, COALESCE(SUM(octet_length(m.content)), 0)::BIGINT AS bytes \
+         FROM messages m JOIN sessions s ON s.id = m.session_id \
+         GROUP BY s.user_id",
+    )
+    .load(conn)?;
+    for row in storage.into_iter().filter(|row| row.bytes > 0) {
+        entry(&mut records, day, row.user_id).storage_bytes = row.bytes;
+    }
+
+    let ids: Vec<Uuid> = records.keys().copied().collect();
+    let accounts: Vec<(Uuid, String, Option<String>)> = users::table
+        .filter(users::id.eq_any(&ids))
+        .select((users::id, users::email, users::billing_customer_id))
+        .load(conn)?;
+    let mut found = Vec::with_capacity(accounts.len());
+    for (user_id, email, customer_id) in accounts {
+        if let Some(mut r) = records.remove(&user_id) {
+            r.email = email;
+            r.billing_customer_id = customer_id;
+            found.push(r);
+        }
+    }
+    found.sort_by(|a, b| a.email.cmp(&b.email));
+    Ok(found)
+}
+
+/// Quote a CSV field if it needs it
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Records as CSV with a header row
+pub fn to_csv(records: &[UsageRecord]) -> String {
+    let mut out = String::from(
+        "day,user_id,email,billing_customer_id,sessions_started,input_tokens,output_tokens,\
+         cache_creation_tokens,cache_read_tokens,cost_usd,speech_seconds,storage_bytes\n",
+    );
+    for r in records {
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{:.6},{:.1},{}\n",
+            r.day,
+            r.user_id,
+            csv_field(&r.email),
+            csv_field(r.billing_customer_id.as_deref().unwrap_or("")),
+            r.sessions_started,
+            r.input_tokens,
+            r.output_tokens,
+            r.cache_creation_tokens,
+            r.cache_read_tokens,
+            r.cost_usd,
+            r.speech_seconds,
+            r.storage_bytes,
+        ));
+    }
+    out
+}
+
+#[derive(Serialize)]
+struct WebhookBody<'a> {
+    day: NaiveDate,
+    records: &'a [UsageRecord],
+}
+
+async fn send(
+    sink: &MeteringSink,
+    client: &reqwest::Client,
+    day: NaiveDate,
+    records: &[UsageRecord],
+) -> anyhow::Result<()> {
+    match sink {
+        MeteringSink::Webhook { url, token } => {
+            let mut request = client.post(url).json(&WebhookBody { day, records });
+            if let Some(token) = token {
+                request = request.bearer_auth(token);
+            }
+            request.send().await?.error_for_status()?;
+        }
+        MeteringSink::Csv { dir } => {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .with_context(|| format!("creating {}", dir.display()))?;
+            let path = dir.join(format!("usage-{}.csv", day));
+            tokio::fs::write(&path, to_csv(records))
+                .await
+                .with_context(|| format!("writing {}", path.display()))?;
+        }
+        MeteringSink::Stripe { api_key, meters } => {
+            // Meter events are timestamped at the last second of the day
+            let timestamp = (day.and_hms_opt(23, 59, 59).expect("valid time"))
+                .and_utc()
+                .timestamp()
+                .to_string();
+            let mut unmapped = 0;
+            for record in records {
+                let Some(customer) = &record.billing_customer_id else {
+                    unmapped += 1;
+                    continue;
+                };
+                for (metric, event_name) in meters {
+                    let value = metric.value(record);
+                    if value == 0 {
+                        continue;
+                    }
+                    // Stripe drops repeats of an identifier, so a retried day isn't double-billed
+                    let identifier = format!("{}-{}-{}", record.user_id, day, metric.name());
+                    let response = client
+                        .post(STRIPE_METER_EVENTS_URL)
+                        .bearer_auth(api_key)
+                        .form(&[
+                            ("event_name", event_name.as_str()),
+                            ("payload[stripe_customer_id]", customer.as_str()),
+                            ("payload[value]", &value.to_string()),
+                            ("timestamp", &timestamp),
+                            ("identifier", &identifier),
+                        ])
+                        .send()
+                        .await?;
+                    if !response.status().is_success() {
+                        let status = response.status();
+                        let body = response.text().await.unwrap_or_default();
+                        bail!(
+                            "Stripe rejected meter event {}: {} {}",
+                            identifier,
+                            status,
+                            body
+                        );
+                    }
+                }
+            }
+            if unmapped > 0 {
+                warn!(
+                    "Skipped {} users without a billing customer ID in the {} export",
+                    unmapped, day
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Days that have ended but haven't been exported yet, oldest first
+fn days_to_export(last_exported: Option<NaiveDate>, today: NaiveDate) -> Vec<NaiveDate> {
+    let Some(yesterday) = today.checked_sub_days(Days::new(1)) else {
+        return Vec::new();
+    };
+    let earliest = yesterday
+        .checked_sub_days(Days::new(MAX_CATCH_UP_DAYS - 1))
+        .unwrap_or(yesterday);
+    let first = match last_exported.and_then(|d| d.succ_opt()) {
+        Some(next) => next.max(earliest),
+        // Never exported: start from the most recent full day
+        None => yesterday,
+    };
+    first.iter_days().take_while(|d| *d <= yesterday).collect()
+}
+
+/// Export each finished day that no instance has exported yet
+pub async fn run_exports(db_pool: &DbPool, sink: &MeteringSink, client: &reqwest::Client) {
+    let mut conn = match db_pool.get() {
+        Ok(conn) => conn,
+        Err(e) => {
+            error!("Failed to get DB connection for usage export: {}", e);
+            return;
+        }
+    };
+
+    let last_exported: Option<NaiveDate> = match metering_exports::table
+        .select(diesel::dsl::max(metering_exports::day))
+        .first(&mut conn)
+    {
+        Ok(day) => day,
+        Err(e) => {
+            error!("Failed to load usage export history: {}", e);
+            return;
+        }
+    };
+
+    for day in days_to_export(last_exported, Utc::now().date_naive()) {
+        let claimed = diesel::insert_into(metering_exports::table)
+            .values((
+                metering_exports::day.eq(day),
+                metering_exports::sink.eq(sink.name()),
+            ))
+            .on_conflict_do_nothing()
+            .execute(&mut conn);
+        match claimed {
+            Ok(0) => continue,
+            Ok(_) => {}
+            Err(e) => {
+                error!("Failed to claim usage export for {}: {}", day, e);
+                return;
+            }
+        }
+
+        let result = match collect_records(&mut conn, day) {
+            Ok(records) => send(sink, client, day, &records)
+                .await
+                .map(|_| records.len()),
+            Err(e) => Err(e.into()),
+        };
+        match result {
+            Ok(count) => {
+                let _ = diesel::update(metering_exports::table.find(day))
+                    .set(metering_exports::records.eq(count as i32))
+                    .execute(&mut conn);
+                info!(
+                    "Exported {} usage records for {} to {}",
+                    count,
+                    day,
+                    sink.name()
+                );
+            }
+            Err(e) => {
+                error!("Usage export for {} failed, will retry: {:#}", day, e);
+                let _ = diesel::delete(metering_exports::table.find(day)).execute(&mut conn);
+                // Later days wait so they go out in order
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn parse(vars: &[(&str, &str)]) -> Result<Option<MeteringSink>, String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        MeteringSink::parse(|name| vars.get(name).cloned())
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_sink_config() {
+        assert_eq!(parse(&[]), Ok(None));
+        assert_eq!(
+            parse(&[("METERING_SINK", "CSV"), ("METERING_CSV_DIR", "/var/usage")]),
+            Ok(Some(MeteringSink::Csv {
+                dir: PathBuf::from("/var/usage")
+            }))
+        );
+        assert_eq!(
+            parse(&[
+                ("METERING_SINK", "stripe"),
+                ("STRIPE_API_KEY", "sk_test"),
+                ("STRIPE_METERS", "input_tokens=in, speech_seconds = voice"),
+            ]),
+            Ok(Some(MeteringSink::Stripe {
+                api_key: "sk_test".to_string(),
+                meters: vec![
+                    (Metric::InputTokens, "in".to_string()),
+                    (Metric::SpeechSeconds, "voice".to_string()),
+                ],
+            }))
+        );
+    }
+
+    #[test]
+    fn test_sink_config_errors() {
+        assert!(parse(&[("METERING_SINK", "webhook")]).is_err());
+        assert!(parse(&[("METERING_SINK", "ledger")]).is_err());
+        assert!(parse(&[
+            ("METERING_SINK", "stripe"),
+            ("STRIPE_API_KEY", "sk_test"),
+            ("STRIPE_METERS", "minutes=x"),
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_days_to_export() {
+        let today = date("2026-01-24");
+        assert_eq!(days_to_export(None, today), vec![date("2026-01-23")]);
+        assert_eq!(days_to_export(Some(date("2026-01-23")), today), vec![]);
+        assert_eq!(
+            days_to_export(Some(date("2026-01-21")), today),
+            vec![date("2026-01-22"), date("2026-01-23")]
+        );
+        // A long outage only catches up the last week
+        let days = days_to_export(Some(date("2025-12-01")), today);
+        assert_eq!(days.len(), MAX_CATCH_UP_DAYS as usize);
+        assert_eq!(days.first(), Some(&date("2026-01-17")));
+    }
+
+    #[test]
+    fn test_csv_quotes_fields() {
+        let mut record = UsageRecord::new(date("2026-01-23"), Uuid::nil());
+        record.email = "a,b@example.com".to_string();
+        record.speech_seconds = 12.34;
+        let csv = to_csv(&[record]);
+        let row = csv.lines().nth(1).unwrap();
+        assert!(
+            row.starts_with("2026-01-23,00000000-0000-0000-0000-000000000000,\"a,b@example.com\",")
+        );
+        assert!(row.ends_with(",0.000000,12.3,0"));
+    }
+
+    #[test]
+    fn test_speech_seconds_round_up_for_meters() {
+        let mut record = UsageRecord::new(date("2026-01-23"), Uuid::nil());
+        record.speech_seconds = 0.2;
+        assert_eq!(Metric::SpeechSeconds.value(&record), 1);
+    }
+}
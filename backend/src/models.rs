@@ -1,6 +1,7 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use shared::UserRole;
 use uuid::Uuid;
 
 #[derive(Debug, Queryable, Selectable, Serialize, Deserialize, Clone)]
@@ -14,10 +15,24 @@ pub struct User {
     pub avatar_url: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
-    pub is_admin: bool,
+    /// `admin`, `member`, or `viewer`; see [`User::role`]
+    pub role: String,
     pub disabled: bool,
     pub voice_enabled: bool,
     pub ban_reason: Option<String>,
+    pub billing_customer_id: Option<String>,
+    /// Voice input language, or "auto"
+    pub voice_language: String,
+}
+
+impl User {
+    pub fn role(&self) -> UserRole {
+        UserRole::parse(&self.role).unwrap_or_default()
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.role() == UserRole::Admin
+    }
 }
 
 #[derive(Debug, Insertable)]
@@ -29,6 +44,15 @@ pub struct NewUser {
     pub avatar_url: Option<String>,
 }
 
+/// A provider account that signs in as a user
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::user_identities)]
+pub struct NewUserIdentity<'a> {
+    pub provider: &'a str,
+    pub subject: &'a str,
+    pub user_id: Uuid,
+}
+
 #[derive(Debug, Queryable, Selectable, Serialize, Deserialize, Clone)]
 #[diesel(table_name = crate::schema::sessions)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
@@ -50,6 +74,11 @@ pub struct Session {
     pub cache_read_tokens: i64,
     pub client_version: Option<String>,
     pub input_seq: i64,
+    pub outcome: Option<String>,
+    pub outcome_set_at: Option<NaiveDateTime>,
+    pub proxy_token_id: Option<Uuid>,
+    pub git_repo: Option<String>,
+    pub host_info: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Insertable)]
@@ -74,6 +103,8 @@ pub struct NewSessionWithId {
     pub working_directory: String,
     pub status: String,
     pub git_branch: Option<String>,
+    pub git_repo: Option<String>,
+    pub host_info: Option<serde_json::Value>,
     pub client_version: Option<String>,
 }
 
@@ -87,6 +118,11 @@ pub struct Message {
     pub content: String,
     pub created_at: NaiveDateTime,
     pub user_id: Uuid,
+    /// Position in insertion order, used by web clients to resume
+    pub seq: i64,
+    /// When the proxy read the output, corrected for its clock skew;
+    /// `created_at` is when the backend received it
+    pub produced_at: Option<NaiveDateTime>,
 }
 
 #[derive(Debug, Insertable)]
@@ -96,6 +132,278 @@ pub struct NewMessage {
     pub role: String,
     pub content: String,
     pub user_id: Uuid,
+    pub produced_at: Option<NaiveDateTime>,
+}
+
+// ============================================================================
+// Message Reaction Models
+// ============================================================================
+
+#[derive(Debug, Queryable, Selectable, Serialize, Deserialize, Clone)]
+#[diesel(table_name = crate::schema::message_reactions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct MessageReaction {
+    pub id: Uuid,
+    pub message_id: Uuid,
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+    pub emoji: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::message_reactions)]
+pub struct NewMessageReaction {
+    pub message_id: Uuid,
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+    pub emoji: String,
+}
+
+// ============================================================================
+// Message Annotation Models
+// ============================================================================
+
+#[derive(Debug, Queryable, Selectable, Serialize, Deserialize, Clone)]
+#[diesel(table_name = crate::schema::message_annotations)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct MessageAnnotation {
+    pub id: Uuid,
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+    pub source: String,
+    pub level: String,
+    pub label: String,
+    pub url: Option<String>,
+    pub data: Option<serde_json::Value>,
+    pub start_seq: i64,
+    pub end_seq: i64,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::message_annotations)]
+pub struct NewMessageAnnotation {
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+    pub source: String,
+    pub level: String,
+    pub label: String,
+    pub url: Option<String>,
+    pub data: Option<serde_json::Value>,
+    pub start_seq: i64,
+    pub end_seq: i64,
+}
+
+// ============================================================================
+// CI Run Models
+// ============================================================================
+
+/// Latest result of a CI workflow or check on a branch
+#[derive(Debug, Queryable, Selectable, Insertable, AsChangeset, Clone)]
+#[diesel(table_name = crate::schema::ci_runs)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct CiRun {
+    pub repository: String,
+    pub branch: String,
+    pub name: String,
+    pub head_sha: String,
+    pub state: String,
+    pub url: Option<String>,
+    pub updated_at: NaiveDateTime,
+}
+
+// ============================================================================
+// Turn Feedback Models
+// ============================================================================
+
+#[derive(Debug, Queryable, Selectable, Serialize, Deserialize, Clone)]
+#[diesel(table_name = crate::schema::turn_feedback)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct TurnFeedback {
+    pub id: Uuid,
+    pub session_id: Uuid,
+    pub message_id: Option<Uuid>,
+    pub user_id: Uuid,
+    pub rating: String,
+    pub comment: Option<String>,
+    pub prompt: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::turn_feedback)]
+pub struct NewTurnFeedback {
+    pub session_id: Uuid,
+    pub message_id: Option<Uuid>,
+    pub user_id: Uuid,
+    pub rating: String,
+    pub comment: Option<String>,
+    pub prompt: Option<String>,
+}
+
+// ============================================================================
+// Turn Models
+// ============================================================================
+
+#[derive(Debug, Queryable, Selectable, Clone)]
+#[diesel(table_name = crate::schema::turns)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Turn {
+    pub id: Uuid,
+    pub session_id: Uuid,
+    pub turn_index: i32,
+    pub start_seq: i64,
+    pub end_seq: i64,
+    pub end_message_id: Option<Uuid>,
+    pub duration_ms: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost_usd: f64,
+    pub tools: Vec<String>,
+    pub error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub compacted: bool,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::turns)]
+pub struct NewTurn {
+    pub session_id: Uuid,
+    pub turn_index: i32,
+    pub start_seq: i64,
+    pub end_seq: i64,
+    pub end_message_id: Option<Uuid>,
+    pub duration_ms: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost_usd: f64,
+    pub tools: Vec<String>,
+    pub error: Option<String>,
+    pub compacted: bool,
+}
+
+// ============================================================================
+// Session Incident Models
+// ============================================================================
+
+#[derive(Debug, Queryable, Selectable, Serialize, Deserialize, Clone)]
+#[diesel(table_name = crate::schema::session_incidents)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SessionIncident {
+    pub id: Uuid,
+    pub session_id: Uuid,
+    pub reason: String,
+    pub bundle: serde_json::Value,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::session_incidents)]
+pub struct NewSessionIncident {
+    pub session_id: Uuid,
+    pub reason: String,
+    pub bundle: serde_json::Value,
+}
+
+// ============================================================================
+// Session Snapshot Models
+// ============================================================================
+
+#[derive(Debug, Queryable, Selectable, Insertable, Clone)]
+#[diesel(table_name = crate::schema::session_snapshots)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SessionSnapshotRow {
+    pub session_id: Uuid,
+    pub snapshot: serde_json::Value,
+    pub uploaded_at: NaiveDateTime,
+}
+
+// ============================================================================
+// Usage Aggregate Models
+// ============================================================================
+
+#[derive(Debug, Queryable, Selectable, Insertable, Clone)]
+#[diesel(table_name = crate::schema::session_model_usage)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SessionModelUsage {
+    pub session_id: Uuid,
+    pub model: String,
+    pub cost_usd: f64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_creation_tokens: i64,
+    pub cache_read_tokens: i64,
+}
+
+#[derive(Debug, Queryable, Selectable, Clone)]
+#[diesel(table_name = crate::schema::usage_daily)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UsageDaily {
+    pub session_id: Option<Uuid>,
+    pub session_name: String,
+    pub day: NaiveDate,
+    pub model: String,
+    pub cost_usd: f64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_creation_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub turns: i32,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::usage_daily)]
+pub struct NewUsageDaily {
+    pub user_id: Uuid,
+    pub session_id: Option<Uuid>,
+    pub session_name: String,
+    pub day: NaiveDate,
+    pub model: String,
+    pub cost_usd: f64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_creation_tokens: i64,
+    pub cache_read_tokens: i64,
+    pub turns: i32,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::tool_usage_daily)]
+pub struct NewToolUsageDaily {
+    pub user_id: Uuid,
+    pub day: NaiveDate,
+    pub tool_name: String,
+    pub uses: i32,
+}
+
+// ============================================================================
+// Budget Limit Models
+// ============================================================================
+
+#[derive(Debug, Queryable, Selectable, Clone)]
+#[diesel(table_name = crate::schema::budget_limits)]
+pub struct BudgetLimitsRow {
+    pub session_cost_soft_usd: Option<f64>,
+    pub session_cost_hard_usd: Option<f64>,
+    pub daily_cost_soft_usd: Option<f64>,
+    pub daily_cost_hard_usd: Option<f64>,
+    pub session_tokens_soft: Option<i64>,
+    pub session_tokens_hard: Option<i64>,
+}
+
+#[derive(Debug, AsChangeset)]
+#[diesel(table_name = crate::schema::budget_limits, treat_none_as_null = true)]
+pub struct BudgetLimitsUpdate {
+    pub session_cost_soft_usd: Option<f64>,
+    pub session_cost_hard_usd: Option<f64>,
+    pub daily_cost_soft_usd: Option<f64>,
+    pub daily_cost_hard_usd: Option<f64>,
+    pub session_tokens_soft: Option<i64>,
+    pub session_tokens_hard: Option<i64>,
+    pub updated_at: NaiveDateTime,
+    pub updated_by: Option<Uuid>,
 }
 
 // ============================================================================
@@ -114,6 +422,9 @@ pub struct ProxyAuthToken {
     pub last_used_at: Option<NaiveDateTime>,
     pub expires_at: NaiveDateTime,
     pub revoked: bool,
+    pub last_used_host: Option<String>,
+    /// Serialized `shared::ProxyTokenScopes`
+    pub scopes: serde_json::Value,
 }
 
 #[derive(Debug, Insertable)]
@@ -123,6 +434,7 @@ pub struct NewProxyAuthToken {
     pub name: String,
     pub token_hash: String,
     pub expires_at: NaiveDateTime,
+    pub scopes: serde_json::Value,
 }
 
 // ============================================================================
@@ -152,6 +464,53 @@ pub struct NewPendingPermissionRequest {
     pub permission_suggestions: Option<serde_json::Value>,
 }
 
+#[derive(Debug, Queryable, Selectable, Clone)]
+#[diesel(table_name = crate::schema::permission_audit_log)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PermissionAuditEntry {
+    pub request_id: String,
+    pub tool_name: String,
+    pub input: serde_json::Value,
+    pub requested_at: NaiveDateTime,
+    pub allowed: Option<bool>,
+    pub channel: Option<String>,
+    pub decided_by: Option<Uuid>,
+    pub detail: Option<String>,
+    pub decided_at: Option<NaiveDateTime>,
+    pub escalated_at: Option<NaiveDateTime>,
+    pub escalated_to: Option<String>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::permission_audit_log)]
+pub struct NewPermissionAuditEntry {
+    pub session_id: Uuid,
+    pub request_id: String,
+    pub tool_name: String,
+    pub input: serde_json::Value,
+}
+
+#[derive(Debug, Queryable, Selectable, Insertable, AsChangeset, Clone)]
+#[diesel(table_name = crate::schema::escalation_policies)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(treat_none_as_null = true)]
+pub struct EscalationPolicyRow {
+    pub session_id: Uuid,
+    pub notify_after_minutes: Option<i32>,
+    pub notify_user_id: Option<Uuid>,
+    pub default_after_minutes: Option<i32>,
+    pub default_action: String,
+}
+
+#[derive(Debug, Queryable, Selectable, Insertable, AsChangeset, Clone)]
+#[diesel(table_name = crate::schema::redaction_policies)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RedactionPolicyRow {
+    pub session_id: Uuid,
+    pub whole_session: bool,
+    pub tools: Vec<String>,
+}
+
 // ============================================================================
 // Deleted Session Costs Models
 // ============================================================================
@@ -181,6 +540,8 @@ pub struct SessionMember {
     pub user_id: Uuid,
     pub role: String,
     pub created_at: NaiveDateTime,
+    /// When a guest's access ends; members' never does
+    pub expires_at: Option<NaiveDateTime>,
 }
 
 #[derive(Debug, Insertable)]
@@ -191,6 +552,31 @@ pub struct NewSessionMember {
     pub role: String,
 }
 
+#[derive(Debug, Queryable, Selectable, Clone)]
+#[diesel(table_name = crate::schema::guest_invites)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct GuestInvite {
+    pub id: Uuid,
+    pub session_id: Uuid,
+    pub name: String,
+    pub role: String,
+    pub invited_by: Uuid,
+    pub guest_user_id: Option<Uuid>,
+    pub expires_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::guest_invites)]
+pub struct NewGuestInvite<'a> {
+    pub session_id: Uuid,
+    pub token_hash: &'a str,
+    pub name: &'a str,
+    pub role: &'a str,
+    pub invited_by: Uuid,
+    pub expires_at: NaiveDateTime,
+}
+
 // ============================================================================
 // Raw Message Log Models
 // ============================================================================
@@ -242,3 +628,75 @@ pub struct NewPendingInput {
     pub seq_num: i64,
     pub content: String,
 }
+
+// ============================================================================
+// Saved Search Models
+// ============================================================================
+
+#[derive(Debug, Queryable, Selectable, Clone)]
+#[diesel(table_name = crate::schema::saved_searches)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SavedSearchRow {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub query: String,
+    pub regex: bool,
+    pub alert: bool,
+    pub checked_until: NaiveDateTime,
+    pub last_match_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::saved_searches)]
+pub struct NewSavedSearch {
+    pub user_id: Uuid,
+    pub name: String,
+    pub query: String,
+    pub regex: bool,
+    pub alert: bool,
+}
+
+/// Where and how to deliver to one subscribed browser
+#[derive(Debug, Queryable, Selectable, Clone)]
+#[diesel(table_name = crate::schema::push_subscriptions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PushSubscriptionRow {
+    pub id: Uuid,
+    pub endpoint: String,
+    pub p256dh: String,
+    pub auth: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::push_subscriptions)]
+pub struct NewPushSubscription {
+    pub user_id: Uuid,
+    pub endpoint: String,
+    pub p256dh: String,
+    pub auth: String,
+}
+
+// ============================================================================
+// Activity Feed Models
+// ============================================================================
+
+#[derive(Debug, Queryable, Selectable, Clone)]
+#[diesel(table_name = crate::schema::activity_events)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ActivityEventRow {
+    pub id: Uuid,
+    pub kind: String,
+    pub session_id: Uuid,
+    pub data: serde_json::Value,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::activity_events)]
+pub struct NewActivityEvent {
+    pub kind: String,
+    pub session_id: Uuid,
+    pub data: serde_json::Value,
+}
@@ -0,0 +1,537 @@
+//! Notification channels
+//!
+//! Slack, Web Push, Telegram and email each implement `Notifier`. Event
+//! sites hand a `Notification` to `Notifiers`, which looks up who it's for,
+//! works out from each person's preferences which channels they want it on,
+//! and gives every channel its share of the recipients. A new channel
+//! implements the trait and is registered in `main`; event sites don't
+//! change.
+//!
+//! Permission requests that wait longer than `PERMISSION_WAIT_MINUTES`
+//! (formerly `SLACK_PERMISSION_WAIT_MINUTES`) are sent again as
+//! `PermissionWaiting`, once each. After each UTC day, every session that
+//! finished turns that day sends its members a `DailyDigest`; the day is
+//! claimed in `notification_digests` first, so only one backend instance
+//! sends it.
+
+use crate::db::DbPool;
+use crate::schema::{
+    notification_digests, notification_preferences, pending_permission_requests, session_members,
+    sessions, turns,
+};
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use dashmap::DashSet;
+use diesel::prelude::*;
+use serde_json::Value;
+use shared::{NotificationChannel, NotificationEvent, NotificationPreferences};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// How often waiting permission requests are checked
+pub const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// How often the task looks for a finished day to send digests for
+pub const DIGEST_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// Minutes a permission request waits before the reminder, unless configured
+const DEFAULT_PERMISSION_WAIT_MINUTES: i64 = 5;
+
+/// Requests older than this are assumed abandoned and never sent
+const MAX_REQUEST_AGE_HOURS: i64 = 24;
+
+/// What happened, with the details channels describe it by
+#[derive(Debug, Clone)]
+pub enum NotificationKind {
+    PermissionRequested {
+        tool_name: String,
+    },
+    PermissionWaiting {
+        tool_name: String,
+        minutes: i64,
+    },
+    PermissionEscalated {
+        tool_name: String,
+        minutes: i64,
+    },
+    /// From the result message that ended the turn
+    TurnFinished {
+        result: Value,
+    },
+    /// The session's turns over one UTC day
+    DailyDigest {
+        day: NaiveDate,
+        turns: i64,
+        failed_turns: i64,
+        cost_usd: f64,
+    },
+}
+
+impl NotificationKind {
+    pub fn event(&self) -> NotificationEvent {
+        match self {
+            Self::PermissionRequested { .. } => NotificationEvent::PermissionRequested,
+            Self::PermissionWaiting { .. } => NotificationEvent::PermissionWaiting,
+            Self::PermissionEscalated { .. } => NotificationEvent::PermissionEscalated,
+            Self::TurnFinished { .. } => NotificationEvent::TurnFinished,
+            Self::DailyDigest { .. } => NotificationEvent::DailyDigest,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub session_id: Uuid,
+    /// How the session is named in the notification, e.g. "api (laptop)"
+    pub label: String,
+    pub kind: NotificationKind,
+}
+
+impl Notification {
+    pub fn new(session: &crate::models::Session, kind: NotificationKind) -> Self {
+        Self {
+            session_id: session.id,
+            label: session_label(&session.session_name, &session.working_directory),
+            kind,
+        }
+    }
+}
+
+/// A user a notification goes to
+#[derive(Debug, Clone)]
+pub struct Recipient {
+    pub user_id: Uuid,
+    pub preferences: NotificationPreferences,
+}
+
+/// A channel notifications can be delivered on
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    fn channel(&self) -> NotificationChannel;
+
+    /// Deliver `notification` to `recipients`, the users who want it on
+    /// this channel; never empty
+    async fn deliver(
+        &self,
+        db_pool: &DbPool,
+        notification: &Notification,
+        recipients: &[Recipient],
+    ) -> anyhow::Result<()>;
+}
+
+/// Who a notification is for
+#[derive(Debug, Clone, Copy)]
+pub enum Audience {
+    /// Everyone in the notification's session
+    Members,
+    User(Uuid),
+}
+
+/// The configured channels, and the reminder state for waiting requests
+#[derive(Clone)]
+pub struct Notifiers {
+    db_pool: DbPool,
+    channels: Vec<Arc<dyn Notifier>>,
+    /// Minutes a permission request waits before the reminder
+    pub permission_wait_minutes: i64,
+    /// Waiting requests already reminded about, so each is sent once
+    reminded: Arc<DashSet<String>>,
+}
+
+impl Notifiers {
+    pub fn from_env(db_pool: DbPool) -> Result<Self, String> {
+        Ok(Self {
+            db_pool,
+            channels: Vec::new(),
+            permission_wait_minutes: permission_wait_minutes(|name| std::env::var(name).ok())?,
+            reminded: Arc::default(),
+        })
+    }
+
+    /// Deliver on `notifier`'s channel too, if it's configured
+    pub fn with(mut self, notifier: Option<impl Notifier + 'static>) -> Self {
+        if let Some(notifier) = notifier {
+            self.channels.push(Arc::new(notifier));
+        }
+        self
+    }
+
+    /// Channels notifications can be delivered on
+    pub fn available(&self) -> Vec<NotificationChannel> {
+        self.channels.iter().map(|n| n.channel()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.channels.is_empty()
+    }
+
+    /// Send `notification` to `audience` in the background
+    pub fn notify(&self, notification: Notification, audience: Audience) {
+        if self.is_empty() {
+            return;
+        }
+        let notifiers = self.clone();
+        tokio::spawn(async move {
+            notifiers.dispatch(&notification, audience).await;
+        });
+    }
+
+    /// Send `notification` on each channel to the recipients who want it
+    /// there. Returns whether every channel delivered.
+    async fn dispatch(&self, notification: &Notification, audience: Audience) -> bool {
+        let recipients = match self.db_pool.get() {
+            Ok(mut conn) => recipients(&mut conn, notification.session_id, audience),
+            Err(e) => {
+                error!("Failed to get connection for notifications: {}", e);
+                return false;
+            }
+        };
+        let recipients = match recipients {
+            Ok(recipients) => recipients,
+            Err(e) => {
+                error!("Failed to load notification recipients: {}", e);
+                return false;
+            }
+        };
+
+        let event = notification.kind.event();
+        let mut delivered = true;
+        for notifier in &self.channels {
+            let channel = notifier.channel();
+            let wanted: Vec<Recipient> = recipients
+                .iter()
+                .filter(|r| r.preferences.routes_to(event, channel))
+                .cloned()
+                .collect();
+            if wanted.is_empty() {
+                continue;
+            }
+            if let Err(e) = notifier.deliver(&self.db_pool, notification, &wanted).await {
+                warn!("Failed to send {} notification: {}", channel.label(), e);
+                delivered = false;
+            }
+        }
+        delivered
+    }
+
+    /// Remind the sessions' members of permission requests that have waited
+    /// too long and weren't reminded about yet
+    pub async fn remind_waiting_permissions(&self) {
+        let waiting = match self.db_pool.get() {
+            Ok(mut conn) => load_waiting(&mut conn, self.permission_wait_minutes),
+            Err(e) => {
+                error!("Failed to get connection for permission reminders: {}", e);
+                return;
+            }
+        };
+        let waiting = match waiting {
+            Ok(waiting) => waiting,
+            Err(e) => {
+                error!("Failed to load waiting permission requests: {}", e);
+                return;
+            }
+        };
+
+        // Forget requests that have been answered
+        self.reminded
+            .retain(|request_id| waiting.iter().any(|w| &w.request_id == request_id));
+
+        let now = Utc::now().naive_utc();
+        for request in waiting {
+            if !self.reminded.insert(request.request_id.clone()) {
+                continue;
+            }
+            let notification = Notification {
+                session_id: request.session_id,
+                label: session_label(&request.session_name, &request.working_directory),
+                kind: NotificationKind::PermissionWaiting {
+                    tool_name: request.tool_name,
+                    minutes: (now - request.created_at).num_minutes(),
+                },
+            };
+            if !self.dispatch(&notification, Audience::Members).await {
+                // Try again on the next check
+                self.reminded.remove(&request.request_id);
+            }
+        }
+    }
+
+    /// Send yesterday's (UTC) digest of each session that finished turns
+    /// then to its members, unless another instance already has
+    pub async fn send_daily_digests(&self) {
+        let Some(day) = Utc::now().date_naive().pred_opt() else {
+            return;
+        };
+        let digests = match self.db_pool.get() {
+            Ok(mut conn) => claim_digests(&mut conn, day),
+            Err(e) => {
+                error!("Failed to get connection for daily digests: {}", e);
+                return;
+            }
+        };
+        let digests = match digests {
+            Ok(Some(digests)) => digests,
+            Ok(None) => return,
+            Err(e) => {
+                error!("Failed to load daily digests for {}: {}", day, e);
+                return;
+            }
+        };
+
+        info!("Sending daily digests for {} sessions", digests.len());
+        for notification in digests {
+            self.dispatch(&notification, Audience::Members).await;
+        }
+    }
+}
+
+fn permission_wait_minutes(var: impl Fn(&str) -> Option<String>) -> Result<i64, String> {
+    let configured = ["PERMISSION_WAIT_MINUTES", "SLACK_PERMISSION_WAIT_MINUTES"]
+        .into_iter()
+        .find_map(|name| {
+            let value = var(name)?.trim().to_string();
+            (!value.is_empty()).then_some((name, value))
+        });
+    match configured {
+        Some((name, minutes)) => minutes
+            .parse::<i64>()
+            .ok()
+            .filter(|m| *m > 0)
+            .ok_or_else(|| format!("Invalid {} {:?}", name, minutes)),
+        None => Ok(DEFAULT_PERMISSION_WAIT_MINUTES),
+    }
+}
+
+/// The audience's users, with their saved preferences or the defaults
+fn recipients(
+    conn: &mut PgConnection,
+    session_id: Uuid,
+    audience: Audience,
+) -> QueryResult<Vec<Recipient>> {
+    let user_ids: Vec<Uuid> = match audience {
+        Audience::Members => session_members::table
+            .filter(session_members::session_id.eq(session_id))
+            .select(session_members::user_id)
+            .load(conn)?,
+        Audience::User(user_id) => vec![user_id],
+    };
+    let saved: HashMap<Uuid, Value> = notification_preferences::table
+        .filter(notification_preferences::user_id.eq_any(&user_ids))
+        .select((
+            notification_preferences::user_id,
+            notification_preferences::preferences,
+        ))
+        .load::<(Uuid, Value)>(conn)?
+        .into_iter()
+        .collect();
+    Ok(user_ids
+        .into_iter()
+        .map(|user_id| Recipient {
+            user_id,
+            preferences: saved
+                .get(&user_id)
+                .and_then(|saved| serde_json::from_value(saved.clone()).ok())
+                .unwrap_or_default(),
+        })
+        .collect())
+}
+
+/// Claim `day`'s digests for this instance and build them; `None` if
+/// another instance already claimed the day. A digest that fails to send
+/// isn't retried.
+fn claim_digests(
+    conn: &mut PgConnection,
+    day: NaiveDate,
+) -> QueryResult<Option<Vec<Notification>>> {
+    let claimed = diesel::insert_into(notification_digests::table)
+        .values(notification_digests::day.eq(day))
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+    if claimed == 0 {
+        return Ok(None);
+    }
+
+    let start = day.and_time(NaiveTime::MIN);
+    let rows = turns::table
+        .inner_join(sessions::table)
+        .filter(turns::created_at.ge(start))
+        .filter(turns::created_at.lt(start + chrono::Duration::days(1)))
+        .select((
+            turns::session_id,
+            turns::error.is_not_null(),
+            turns::cost_usd,
+            sessions::session_name,
+            sessions::working_directory,
+        ))
+        .load::<(Uuid, bool, f64, String, String)>(conn)?;
+
+    let mut digests: BTreeMap<Uuid, Notification> = BTreeMap::new();
+    for (session_id, failed, cost, session_name, working_directory) in rows {
+        let digest = digests.entry(session_id).or_insert_with(|| Notification {
+            session_id,
+            label: session_label(&session_name, &working_directory),
+            kind: NotificationKind::DailyDigest {
+                day,
+                turns: 0,
+                failed_turns: 0,
+                cost_usd: 0.0,
+            },
+        });
+        if let NotificationKind::DailyDigest {
+            turns,
+            failed_turns,
+            cost_usd,
+            ..
+        } = &mut digest.kind
+        {
+            *turns += 1;
+            *failed_turns += failed as i64;
+            *cost_usd += cost;
+        }
+    }
+    Ok(Some(digests.into_values().collect()))
+}
+
+/// "3 turns (1 failed), $0.42" for a digest
+pub fn digest_summary(turns: i64, failed_turns: i64, cost_usd: f64) -> String {
+    let mut summary = format!("{} turn{}", turns, if turns == 1 { "" } else { "s" });
+    if failed_turns > 0 {
+        summary.push_str(&format!(" ({} failed)", failed_turns));
+    }
+    summary.push_str(&format!(", ${:.2}", cost_usd));
+    summary
+}
+
+struct WaitingRequest {
+    session_id: Uuid,
+    request_id: String,
+    tool_name: String,
+    created_at: NaiveDateTime,
+    session_name: String,
+    working_directory: String,
+}
+
+fn load_waiting(conn: &mut PgConnection, wait_minutes: i64) -> QueryResult<Vec<WaitingRequest>> {
+    let now = Utc::now().naive_utc();
+    let rows = pending_permission_requests::table
+        .inner_join(sessions::table)
+        .filter(
+            pending_permission_requests::created_at
+                .le(now - chrono::Duration::minutes(wait_minutes)),
+        )
+        .filter(
+            pending_permission_requests::created_at
+                .gt(now - chrono::Duration::hours(MAX_REQUEST_AGE_HOURS)),
+        )
+        .select((
+            pending_permission_requests::session_id,
+            pending_permission_requests::request_id,
+            pending_permission_requests::tool_name,
+            pending_permission_requests::created_at,
+            sessions::session_name,
+            sessions::working_directory,
+        ))
+        .load::<(Uuid, String, String, NaiveDateTime, String, String)>(conn)?;
+    Ok(rows
+        .into_iter()
+        .map(
+            |(session_id, request_id, tool_name, created_at, session_name, working_directory)| {
+                WaitingRequest {
+                    session_id,
+                    request_id,
+                    tool_name,
+                    created_at,
+                    session_name,
+                    working_directory,
+                }
+            },
+        )
+        .collect())
+}
+
+/// "project (host)" from the working directory's last component and the
+/// session name
+pub fn session_label(session_name: &str, working_directory: &str) -> String {
+    let folder = working_directory
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .filter(|f| !f.is_empty());
+    match folder {
+        Some(folder) if folder != session_name => format!("{} ({})", folder, session_name),
+        _ => session_name.to_string(),
+    }
+}
+
+/// `text` cut to `max_chars`, with an ellipsis if anything was cut
+pub fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Link to a session in the web UI
+pub fn session_link(public_url: &str, session_id: Uuid) -> String {
+    format!(
+        "{}/dashboard?session={}",
+        public_url.trim_end_matches('/'),
+        session_id
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_permission_wait_minutes() {
+        let vars = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                pairs
+                    .iter()
+                    .find(|(k, _)| *k == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        assert_eq!(
+            permission_wait_minutes(vars(&[])),
+            Ok(DEFAULT_PERMISSION_WAIT_MINUTES)
+        );
+        assert_eq!(
+            permission_wait_minutes(vars(&[("SLACK_PERMISSION_WAIT_MINUTES", "15")])),
+            Ok(15)
+        );
+        assert_eq!(
+            permission_wait_minutes(vars(&[
+                ("PERMISSION_WAIT_MINUTES", "10"),
+                ("SLACK_PERMISSION_WAIT_MINUTES", "15"),
+            ])),
+            Ok(10)
+        );
+        assert!(permission_wait_minutes(vars(&[("PERMISSION_WAIT_MINUTES", "0")])).is_err());
+    }
+
+    #[test]
+    fn test_session_label() {
+        assert_eq!(session_label("laptop", "/home/me/api/"), "api (laptop)");
+        assert_eq!(session_label("api", "/home/me/api"), "api");
+        assert_eq!(session_label("laptop", ""), "laptop");
+    }
+
+    #[test]
+    fn test_digest_summary() {
+        assert_eq!(digest_summary(1, 0, 0.4212), "1 turn, $0.42");
+        assert_eq!(digest_summary(3, 1, 2.0), "3 turns (1 failed), $2.00");
+    }
+
+    #[test]
+    fn test_session_link() {
+        assert_eq!(
+            session_link("https://portal.example.com/", Uuid::nil()),
+            "https://portal.example.com/dashboard?session=00000000-0000-0000-0000-000000000000"
+        );
+    }
+}
@@ -0,0 +1,534 @@
+//! Web Push notifications
+//!
+//! When `VAPID_PUBLIC_KEY` and `VAPID_PRIVATE_KEY` are set, users can turn on
+//! browser notifications from the settings page. Notifications routed to
+//! the browser then go to every subscribed device of their recipients; turns
+//! shorter than `PUSH_MIN_TURN_SECONDS` are skipped. Payloads are encrypted
+//! to each subscription (RFC 8291) and requests are signed with the VAPID
+//! key (RFC 8292); subscriptions the push service reports as gone are
+//! deleted.
+
+use crate::db::DbPool;
+use crate::models::PushSubscriptionRow;
+use crate::notifier::{self, Notification, NotificationKind, Notifier, Recipient};
+use crate::schema::push_subscriptions;
+use async_trait::async_trait;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use diesel::prelude::*;
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{aead, agreement, hkdf, signature};
+use serde_json::{json, Value};
+use shared::NotificationChannel;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+/// Turns shorter than this aren't worth a notification, unless configured
+const DEFAULT_MIN_TURN_SECONDS: u64 = 60;
+
+/// How long push services keep undelivered notifications
+const TTL_SECONDS: u32 = 24 * 60 * 60;
+
+/// Lifetime of the VAPID token on each request; at most 24 hours
+const VAPID_TOKEN_HOURS: i64 = 12;
+
+/// Push services that take longer than this count as failed
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Record size declared in the encrypted payload header; payloads are sent
+/// as a single record, so they must fit in it
+const RECORD_SIZE: u32 = 4096;
+
+/// Notification bodies are cut to this many characters
+const MAX_BODY_CHARS: usize = 200;
+
+/// VAPID key pair and notification settings, read from `VAPID_*` and `PUSH_*`
+/// variables
+#[derive(Clone)]
+pub struct WebPush {
+    key_pair: Arc<signature::EcdsaKeyPair>,
+    /// Uncompressed P-256 public key, base64url encoded
+    public_key: String,
+    /// Contact for push services, a `mailto:` or `https:` URL
+    subject: String,
+    /// Minimum turn duration that triggers a notification
+    pub min_turn_seconds: u64,
+    public_url: String,
+    client: reqwest::Client,
+}
+
+impl std::fmt::Debug for WebPush {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebPush")
+            .field("public_key", &self.public_key)
+            .field("subject", &self.subject)
+            .field("min_turn_seconds", &self.min_turn_seconds)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WebPush {
+    /// The configured keys, `None` when push notifications are off
+    pub fn from_env(public_url: &str) -> Result<Option<Self>, String> {
+        Self::parse(|name| std::env::var(name).ok(), public_url)
+    }
+
+    fn parse(
+        var: impl Fn(&str) -> Option<String>,
+        public_url: &str,
+    ) -> Result<Option<Self>, String> {
+        let var = |name: &str| {
+            var(name)
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+
+        let Some(public_key) = var("VAPID_PUBLIC_KEY") else {
+            return Ok(None);
+        };
+        let private_key = var("VAPID_PRIVATE_KEY").ok_or("VAPID_PRIVATE_KEY must be set")?;
+        let public_bytes = decode_base64url(&public_key)
+            .filter(|k| k.len() == 65 && k[0] == 0x04)
+            .ok_or("VAPID_PUBLIC_KEY must be an uncompressed P-256 key")?;
+        let private_bytes = decode_base64url(&private_key)
+            .filter(|k| k.len() == 32)
+            .ok_or("VAPID_PRIVATE_KEY must be a 32-byte P-256 key")?;
+        let key_pair = signature::EcdsaKeyPair::from_private_key_and_public_key(
+            &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            &private_bytes,
+            &public_bytes,
+            &SystemRandom::new(),
+        )
+        .map_err(|e| format!("VAPID keys don't form a key pair: {}", e))?;
+
+        let public_url = public_url.trim_end_matches('/').to_string();
+        let subject = var("VAPID_SUBJECT").unwrap_or_else(|| public_url.clone());
+        if !subject.starts_with("mailto:") && !subject.starts_with("https://") {
+            return Err(format!(
+                "VAPID_SUBJECT must be a mailto: or https: URL, got {:?}",
+                subject
+            ));
+        }
+        let min_turn_seconds = match var("PUSH_MIN_TURN_SECONDS") {
+            Some(seconds) => seconds
+                .parse::<u64>()
+                .map_err(|_| format!("Invalid PUSH_MIN_TURN_SECONDS {:?}", seconds))?,
+            None => DEFAULT_MIN_TURN_SECONDS,
+        };
+
+        Ok(Some(Self {
+            key_pair: Arc::new(key_pair),
+            public_key: URL_SAFE_NO_PAD.encode(&public_bytes),
+            subject,
+            min_turn_seconds,
+            public_url,
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+        }))
+    }
+
+    /// Key browsers pass to `PushManager.subscribe`
+    pub fn public_key(&self) -> &str {
+        &self.public_key
+    }
+
+    async fn send(
+        &self,
+        subscription: &PushSubscriptionRow,
+        payload: &[u8],
+    ) -> anyhow::Result<Delivery> {
+        let ua_public = decode_base64url(&subscription.p256dh)
+            .ok_or_else(|| anyhow::anyhow!("invalid p256dh key"))?;
+        let auth = decode_base64url(&subscription.auth)
+            .ok_or_else(|| anyhow::anyhow!("invalid auth secret"))?;
+        let body = encrypt(payload, &ua_public, &auth)
+            .map_err(|_| anyhow::anyhow!("failed to encrypt payload"))?;
+        let audience = reqwest::Url::parse(&subscription.endpoint)?
+            .origin()
+            .ascii_serialization();
+        let token = self.vapid_token(&audience)?;
+
+        let response = self
+            .client
+            .post(&subscription.endpoint)
+            .header(
+                "Authorization",
+                format!("vapid t={}, k={}", token, self.public_key),
+            )
+            .header("TTL", TTL_SECONDS.to_string())
+            .header("Content-Encoding", "aes128gcm")
+            .header("Content-Type", "application/octet-stream")
+            .header("Urgency", "high")
+            .body(body)
+            .send()
+            .await?;
+        match response.status().as_u16() {
+            404 | 410 => Ok(Delivery::Gone),
+            _ => {
+                response.error_for_status()?;
+                Ok(Delivery::Sent)
+            }
+        }
+    }
+
+    /// ES256 JWT identifying this server to the push service at `audience`
+    fn vapid_token(&self, audience: &str) -> anyhow::Result<String> {
+        let header = URL_SAFE_NO_PAD.encode(r#"{"typ":"JWT","alg":"ES256"}"#);
+        let claims = URL_SAFE_NO_PAD.encode(
+            json!({
+                "aud": audience,
+                "exp": (Utc::now() + chrono::Duration::hours(VAPID_TOKEN_HOURS)).timestamp(),
+                "sub": self.subject,
+            })
+            .to_string(),
+        );
+        let signing_input = format!("{}.{}", header, claims);
+        let signature = self
+            .key_pair
+            .sign(&SystemRandom::new(), signing_input.as_bytes())
+            .map_err(|_| anyhow::anyhow!("failed to sign VAPID token"))?;
+        Ok(format!(
+            "{}.{}",
+            signing_input,
+            URL_SAFE_NO_PAD.encode(signature.as_ref())
+        ))
+    }
+}
+
+#[async_trait]
+impl Notifier for WebPush {
+    fn channel(&self) -> NotificationChannel {
+        NotificationChannel::Push
+    }
+
+    /// Push to every device the recipients subscribed
+    async fn deliver(
+        &self,
+        db_pool: &DbPool,
+        notification: &Notification,
+        recipients: &[Recipient],
+    ) -> anyhow::Result<()> {
+        let Some(body) = notification_body(&notification.kind, self.min_turn_seconds) else {
+            return Ok(());
+        };
+        let tag = match notification.kind {
+            NotificationKind::TurnFinished { .. } => "turn",
+            NotificationKind::DailyDigest { .. } => "digest",
+            _ => "permission",
+        };
+        let payload = json!({
+            "title": notification.label,
+            "body": body,
+            "url": notifier::session_link(&self.public_url, notification.session_id),
+            "tag": format!("{}-{}", tag, notification.session_id),
+        })
+        .to_string();
+
+        let user_ids: Vec<Uuid> = recipients.iter().map(|r| r.user_id).collect();
+        let subscriptions = push_subscriptions::table
+            .filter(push_subscriptions::user_id.eq_any(&user_ids))
+            .select(PushSubscriptionRow::as_select())
+            .load(&mut db_pool.get()?)?;
+
+        for subscription in subscriptions {
+            match self.send(&subscription, payload.as_bytes()).await {
+                Ok(Delivery::Sent) => {}
+                Ok(Delivery::Gone) => {
+                    if let Ok(mut conn) = db_pool.get() {
+                        if let Err(e) =
+                            diesel::delete(push_subscriptions::table.find(subscription.id))
+                                .execute(&mut conn)
+                        {
+                            error!("Failed to delete expired push subscription: {}", e);
+                        }
+                    }
+                }
+                Err(e) => warn!("Failed to send push notification: {}", e),
+            }
+        }
+        Ok(())
+    }
+}
+
+enum Delivery {
+    Sent,
+    /// The subscription expired or was revoked
+    Gone,
+}
+
+/// Browsers report keys unpadded, but accept padded ones too
+fn decode_base64url(text: &str) -> Option<Vec<u8>> {
+    URL_SAFE_NO_PAD.decode(text.trim_end_matches('=')).ok()
+}
+
+/// Notification text, `None` for turns too short to notify about
+fn notification_body(kind: &NotificationKind, min_turn_seconds: u64) -> Option<String> {
+    match kind {
+        NotificationKind::TurnFinished { result } => turn_finished_body(result, min_turn_seconds),
+        NotificationKind::PermissionRequested { tool_name } => {
+            Some(format!("Waiting for permission to use {}", tool_name))
+        }
+        NotificationKind::PermissionWaiting { tool_name, minutes } => Some(format!(
+            "Still waiting for permission to use {} after {} min",
+            tool_name, minutes
+        )),
+        NotificationKind::PermissionEscalated { tool_name, minutes } => Some(format!(
+            "Escalated to you: waiting {} min for permission to use {}",
+            minutes, tool_name
+        )),
+        NotificationKind::DailyDigest {
+            day,
+            turns,
+            failed_turns,
+            cost_usd,
+        } => Some(format!(
+            "{}: {}",
+            day,
+            notifier::digest_summary(*turns, *failed_turns, *cost_usd)
+        )),
+    }
+}
+
+/// Notification text for a result message, `None` for turns too short to
+/// notify about
+fn turn_finished_body(result: &Value, min_turn_seconds: u64) -> Option<String> {
+    let duration_ms = result.get("duration_ms").and_then(|d| d.as_u64())?;
+    if duration_ms < min_turn_seconds * 1000 {
+        return None;
+    }
+    let failed = result.get("is_error").and_then(|e| e.as_bool()) == Some(true);
+    let minutes = duration_ms / 60_000;
+    let mut body = format!(
+        "{} after {}",
+        if failed { "Failed" } else { "Finished" },
+        if minutes > 0 {
+            format!("{} min", minutes)
+        } else {
+            format!("{} s", duration_ms / 1000)
+        }
+    );
+    if let Some(summary) = result
+        .get("result")
+        .and_then(|r| r.as_str())
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+    {
+        body.push_str(": ");
+        body.push_str(&notifier::truncate(summary, MAX_BODY_CHARS));
+    }
+    Some(body)
+}
+
+/// HKDF output length, for ring's `expand`
+struct Len(usize);
+
+impl hkdf::KeyType for Len {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+fn hkdf_expand(
+    prk: &hkdf::Prk,
+    info: &[u8],
+    out: &mut [u8],
+) -> Result<(), ring::error::Unspecified> {
+    prk.expand(&[info], Len(out.len()))?.fill(out)
+}
+
+/// Encrypt `payload` to a subscription's keys with `aes128gcm` content
+/// encoding, using a fresh sender key pair and salt
+fn encrypt(
+    payload: &[u8],
+    ua_public: &[u8],
+    auth: &[u8],
+) -> Result<Vec<u8>, ring::error::Unspecified> {
+    let rng = SystemRandom::new();
+    let private_key = agreement::EphemeralPrivateKey::generate(&agreement::ECDH_P256, &rng)?;
+    let as_public = private_key.compute_public_key()?;
+    let mut salt = [0u8; 16];
+    rng.fill(&mut salt)?;
+    let ecdh_secret = agreement::agree_ephemeral(
+        private_key,
+        &agreement::UnparsedPublicKey::new(&agreement::ECDH_P256, ua_public),
+        |secret| secret.to_vec(),
+    )?;
+    seal(
+        payload,
+        &ecdh_secret,
+        ua_public,
+        as_public.as_ref(),
+        auth,
+        &salt,
+    )
+}
+
+/// The RFC 8291 key derivation and single-record RFC 8188 encoding, given
+/// the agreed secret
+fn seal(
+    payload: &[u8],
+    ecdh_secret: &[u8],
+    ua_public: &[u8],
+    as_public: &[u8],
+    auth: &[u8],
+    salt: &[u8; 16],
+) -> Result<Vec<u8>, ring::error::Unspecified> {
+    // Plaintext, padding delimiter, and tag must fit in one record
+    if payload.len() + 1 + aead::AES_128_GCM.tag_len() > RECORD_SIZE as usize {
+        return Err(ring::error::Unspecified);
+    }
+
+    let mut key_info = b"WebPush: info\0".to_vec();
+    key_info.extend_from_slice(ua_public);
+    key_info.extend_from_slice(as_public);
+    let mut ikm = [0u8; 32];
+    hkdf_expand(
+        &hkdf::Salt::new(hkdf::HKDF_SHA256, auth).extract(ecdh_secret),
+        &key_info,
+        &mut ikm,
+    )?;
+
+    let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, salt).extract(&ikm);
+    let mut cek = [0u8; 16];
+    hkdf_expand(&prk, b"Content-Encoding: aes128gcm\0", &mut cek)?;
+    let mut nonce = [0u8; 12];
+    hkdf_expand(&prk, b"Content-Encoding: nonce\0", &mut nonce)?;
+
+    let key = aead::LessSafeKey::new(aead::UnboundKey::new(&aead::AES_128_GCM, &cek)?);
+    let mut record = payload.to_vec();
+    // Last-record delimiter, with no further padding
+    record.push(0x02);
+    key.seal_in_place_append_tag(
+        aead::Nonce::assume_unique_for_key(nonce),
+        aead::Aad::empty(),
+        &mut record,
+    )?;
+
+    let mut body = Vec::with_capacity(16 + 4 + 1 + as_public.len() + record.len());
+    body.extend_from_slice(salt);
+    body.extend_from_slice(&RECORD_SIZE.to_be_bytes());
+    body.push(as_public.len() as u8);
+    body.extend_from_slice(as_public);
+    body.extend_from_slice(&record);
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    // Key pair from RFC 8291 Appendix A (the application server's)
+    const PUBLIC_KEY: &str =
+        "BP4z9KsN6nGRTbVYI_c7VJSPQTBtkgcy27mlmlMoZIIgDll6e3vCYLocInmYWAmS6TlzAC8wEqKK6PBru3jl7A8";
+    const PRIVATE_KEY: &str = "yfWPiYE-n46HLnH0KqZOF1fJJU3MYrct3AELtAQ-oRw";
+
+    fn parse(vars: &[(&str, &str)]) -> Result<Option<WebPush>, String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        WebPush::parse(
+            |name| vars.get(name).cloned(),
+            "https://portal.example.com/",
+        )
+    }
+
+    #[test]
+    fn test_config() {
+        assert!(parse(&[]).unwrap().is_none());
+        assert!(parse(&[("VAPID_PUBLIC_KEY", PUBLIC_KEY)]).is_err());
+        assert!(parse(&[
+            ("VAPID_PUBLIC_KEY", PUBLIC_KEY),
+            ("VAPID_PRIVATE_KEY", "not-a-key")
+        ])
+        .is_err());
+
+        let push = parse(&[
+            ("VAPID_PUBLIC_KEY", PUBLIC_KEY),
+            ("VAPID_PRIVATE_KEY", PRIVATE_KEY),
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(push.public_key(), PUBLIC_KEY);
+        assert_eq!(push.subject, "https://portal.example.com");
+        assert_eq!(push.min_turn_seconds, DEFAULT_MIN_TURN_SECONDS);
+
+        assert!(parse(&[
+            ("VAPID_PUBLIC_KEY", PUBLIC_KEY),
+            ("VAPID_PRIVATE_KEY", PRIVATE_KEY),
+            ("VAPID_SUBJECT", "ops@example.com"),
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_vapid_token_verifies() {
+        let push = parse(&[
+            ("VAPID_PUBLIC_KEY", PUBLIC_KEY),
+            ("VAPID_PRIVATE_KEY", PRIVATE_KEY),
+            ("VAPID_SUBJECT", "mailto:ops@example.com"),
+        ])
+        .unwrap()
+        .unwrap();
+        let token = push.vapid_token("https://push.example.net").unwrap();
+        let (signing_input, sig) = token.rsplit_once('.').unwrap();
+        signature::UnparsedPublicKey::new(
+            &signature::ECDSA_P256_SHA256_FIXED,
+            decode_base64url(PUBLIC_KEY).unwrap(),
+        )
+        .verify(signing_input.as_bytes(), &decode_base64url(sig).unwrap())
+        .unwrap();
+
+        let claims: Value = serde_json::from_slice(
+            &decode_base64url(signing_input.split('.').nth(1).unwrap()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(claims["aud"], "https://push.example.net");
+        assert_eq!(claims["sub"], "mailto:ops@example.com");
+    }
+
+    #[test]
+    fn test_seal_matches_rfc8291() {
+        let b64 = |s: &str| decode_base64url(s).unwrap();
+        let salt: [u8; 16] = b64("DGv6ra1nlYgDCS1FRnbzlw").try_into().unwrap();
+        let body = seal(
+            b"When I grow up, I want to be a watermelon",
+            &b64("kyrL1jIIOHEzg3sM2ZWRHDRB62YACZhhSlknJ672kSs"),
+            &b64("BCVxsr7N_eNgVRqvHtD0zTZsEc6-VV-JvLexhqUzORcxaOzi6-AYWXvTBHm4bjyPjs7Vd8pZGH6SRpkNtoIAiw4"),
+            &b64(PUBLIC_KEY),
+            &b64("BTBZMqHH6r4Tts7J_aSIgg"),
+            &salt,
+        )
+        .unwrap();
+        assert_eq!(
+            URL_SAFE_NO_PAD.encode(body),
+            "DGv6ra1nlYgDCS1FRnbzlwAAEABBBP4z9KsN6nGRTbVYI_c7VJSPQTBtkgcy27mlmlMoZIIgDll6e3vCYLocInmYWAmS6TlzAC8wEqKK6PBru3jl7A_yl95bQpu6cVPTpK4Mqgkf1CXztLVBSt2Ks3oZwbuwXPXLWyouBWLVWGNWQexSgSxsj_Qulcy4a-fN"
+        );
+
+        let too_big = vec![0u8; RECORD_SIZE as usize];
+        assert!(seal(&too_big, &[0; 32], &[4; 65], &[4; 65], &[0; 16], &salt).is_err());
+    }
+
+    #[test]
+    fn test_turn_finished_body() {
+        let short = json!({"duration_ms": 5_000, "result": "Done"});
+        assert_eq!(turn_finished_body(&short, 60), None);
+        assert_eq!(
+            turn_finished_body(&short, 0).as_deref(),
+            Some("Finished after 5 s: Done")
+        );
+
+        let long = json!({"duration_ms": 754_000, "is_error": true});
+        assert_eq!(
+            turn_finished_body(&long, 60).as_deref(),
+            Some("Failed after 12 min")
+        );
+        assert_eq!(turn_finished_body(&json!({}), 0), None);
+    }
+}
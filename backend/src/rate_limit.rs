@@ -0,0 +1,219 @@
+//! Per-token rate limiting
+//!
+//! API requests and WebSocket messages are counted per caller: a signed-in
+//! web user, a proxy token (by hash), or for requests with neither, the
+//! client's address. A bearer token only counts as the caller once it has
+//! been verified, so made-up tokens can't each get a fresh allowance. Each
+//! caller gets a token bucket holding a minute's allowance that refills
+//! continuously, so short bursts are fine but a sustained flood is cut to the
+//! configured rate. Limits come from the runtime config; 0 turns a limit off.
+
+use crate::jwt;
+use crate::AppState;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use dashmap::DashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tower_cookies::Cookies;
+use tracing::warn;
+
+const SESSION_COOKIE_NAME: &str = "cc_session";
+
+/// How often idle buckets are forgotten
+pub const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A bucket untouched this long has refilled completely, so dropping it
+/// changes nothing
+const IDLE_BUCKET_TTL: Duration = Duration::from_secs(60);
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token buckets keyed by caller
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: DashMap<String, Bucket>,
+}
+
+impl RateLimiter {
+    /// Take one token from `key`'s bucket. When it's empty, returns how long
+    /// until the next token is available.
+    pub fn check(&self, key: &str, per_minute: u32) -> Result<(), Duration> {
+        self.check_at(key, per_minute, Instant::now())
+    }
+
+    fn check_at(&self, key: &str, per_minute: u32, now: Instant) -> Result<(), Duration> {
+        if per_minute == 0 {
+            return Ok(());
+        }
+        let capacity = per_minute as f64;
+        let per_second = capacity / 60.0;
+
+        let mut bucket = self.buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_second).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+        }
+    }
+
+    /// Forget callers that have been idle long enough to be back at full
+    pub fn prune(&self) {
+        let now = Instant::now();
+        self.buckets
+            .retain(|_, bucket| now.saturating_duration_since(bucket.updated) < IDLE_BUCKET_TTL);
+    }
+}
+
+/// Separate budgets for HTTP requests and socket messages
+#[derive(Default)]
+pub struct RateLimits {
+    pub requests: RateLimiter,
+    pub messages: RateLimiter,
+}
+
+impl RateLimits {
+    pub fn prune(&self) {
+        self.requests.prune();
+        self.messages.prune();
+    }
+}
+
+/// Bucket key for a proxy token; the token itself is never kept
+pub fn token_key(token: &str) -> String {
+    format!("token:{}", jwt::hash_token(token))
+}
+
+/// Bucket key for a signed-in web user
+pub fn user_key(user_id: uuid::Uuid) -> String {
+    format!("user:{}", user_id)
+}
+
+/// Whole seconds to tell the caller to wait, never 0
+pub fn retry_after_secs(wait: Duration) -> u64 {
+    wait.as_secs_f64().ceil().max(1.0) as u64
+}
+
+/// Bucket key for a caller without credentials
+fn host_key(host: &str) -> String {
+    format!("host:{}", host)
+}
+
+/// Who a request counts against, checked the way handlers authenticate it:
+/// the signed session cookie first, then a bearer token that verifies,
+/// otherwise the client's address
+fn request_key(
+    app_state: &AppState,
+    headers: &HeaderMap,
+    cookies: &Cookies,
+    peer: SocketAddr,
+) -> String {
+    let user_id = cookies
+        .signed(&app_state.cookie_key)
+        .get(SESSION_COOKIE_NAME)
+        .and_then(|cookie| cookie.value().parse().ok());
+    if let Some(user_id) = user_id {
+        return user_key(user_id);
+    }
+    if crate::handlers::proxy_tokens::user_from_bearer(app_state, headers, peer).is_some() {
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if let Some(token) = token {
+            return token_key(token.trim());
+        }
+    }
+    host_key(&crate::handlers::proxy_tokens::client_host(headers, peer))
+}
+
+/// Middleware answering 429 once a caller exceeds its API request limit
+pub async fn limit_requests(
+    State(app_state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    cookies: Cookies,
+    request: Request,
+    next: Next,
+) -> Response {
+    let per_minute = app_state
+        .runtime_config
+        .current()
+        .rate_limit_requests_per_minute;
+    if per_minute == 0 || !request.uri().path().starts_with("/api/") {
+        return next.run(request).await;
+    }
+
+    let key = request_key(&app_state, request.headers(), &cookies, peer);
+    match app_state.rate_limits.requests.check(&key, per_minute) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => {
+            warn!(
+                "Rate limited {} {} for {}",
+                request.method(),
+                request.uri().path(),
+                key
+            );
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after_secs(wait).to_string())],
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_then_limited() {
+        let limiter = RateLimiter::default();
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.check_at("a", 3, now).is_ok());
+        }
+        let wait = limiter.check_at("a", 3, now).unwrap_err();
+        assert_eq!(retry_after_secs(wait), 20);
+        // Other callers have their own bucket
+        assert!(limiter.check_at("b", 3, now).is_ok());
+    }
+
+    #[test]
+    fn test_refills_over_time() {
+        let limiter = RateLimiter::default();
+        let now = Instant::now();
+        for _ in 0..60 {
+            limiter.check_at("a", 60, now).unwrap();
+        }
+        assert!(limiter.check_at("a", 60, now).is_err());
+        let later = now + Duration::from_secs(1);
+        assert!(limiter.check_at("a", 60, later).is_ok());
+        assert!(limiter.check_at("a", 60, later).is_err());
+    }
+
+    #[test]
+    fn test_zero_is_unlimited() {
+        let limiter = RateLimiter::default();
+        for _ in 0..1000 {
+            assert!(limiter.check("a", 0).is_ok());
+        }
+        assert!(limiter.buckets.is_empty());
+    }
+}
@@ -0,0 +1,257 @@
+//! Bounded relay queues
+//!
+//! Each proxy and web client socket is fed through its own queue, drained by
+//! that socket's send task. The queues are bounded so a peer that stops
+//! reading (a backgrounded tab, a stalled network) holds at most a fixed
+//! number of messages. Live traffic never waits on a slow peer: once a queue
+//! is full, the oldest message is dropped to make room and counted. Bulk
+//! replays that the connection itself produces (history on connect) use
+//! [`RelaySender::send_wait`] instead, which waits for room.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tracing::{debug, warn};
+
+/// Messages dropped on overflow since the backend started, per peer kind
+#[derive(Default)]
+pub struct RelayDrops {
+    pub to_proxies: Arc<AtomicU64>,
+    pub to_web_clients: Arc<AtomicU64>,
+}
+
+impl RelayDrops {
+    pub fn totals(&self) -> RelayDropTotals {
+        RelayDropTotals {
+            to_proxies: self.to_proxies.load(Ordering::Relaxed),
+            to_web_clients: self.to_web_clients.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Snapshot of [`RelayDrops`] for admin stats
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+pub struct RelayDropTotals {
+    pub to_proxies: u64,
+    pub to_web_clients: u64,
+}
+
+/// Messages buffered for one web client before the oldest are dropped
+pub const WEB_CLIENT_QUEUE_CAPACITY: usize = 1024;
+
+/// Messages buffered for one proxy before the oldest are dropped. Inputs
+/// are also kept in `pending_inputs` until acknowledged, so a dropped input
+/// is replayed when the proxy reconnects.
+pub const PROXY_QUEUE_CAPACITY: usize = 1024;
+
+/// The receiving side is gone; hands back the message that wasn't sent
+#[derive(Debug, PartialEq, Eq)]
+pub struct Closed<T>(pub T);
+
+struct Shared<T> {
+    queue: Mutex<State<T>>,
+    capacity: usize,
+    /// Wakes the receiver when a message arrives or the last sender drops
+    message_ready: Notify,
+    /// Wakes senders waiting in `send_wait` when the receiver takes a message
+    space_ready: Notify,
+    senders: AtomicUsize,
+    receiver_closed: AtomicBool,
+    /// Shared across queues of the same kind, for admin stats
+    dropped: Arc<AtomicU64>,
+    label: &'static str,
+}
+
+struct State<T> {
+    messages: VecDeque<T>,
+    /// Set while the queue is shedding messages, so it only warns once per burst
+    overflowing: bool,
+}
+
+/// Create a queue holding up to `capacity` messages. `dropped` counts
+/// messages shed on overflow; `label` names the peer kind in logs.
+pub fn channel<T>(
+    capacity: usize,
+    dropped: Arc<AtomicU64>,
+    label: &'static str,
+) -> (RelaySender<T>, RelayReceiver<T>) {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(State {
+            messages: VecDeque::new(),
+            overflowing: false,
+        }),
+        capacity: capacity.max(1),
+        message_ready: Notify::new(),
+        space_ready: Notify::new(),
+        senders: AtomicUsize::new(1),
+        receiver_closed: AtomicBool::new(false),
+        dropped,
+        label,
+    });
+    (
+        RelaySender {
+            shared: shared.clone(),
+        },
+        RelayReceiver { shared },
+    )
+}
+
+pub struct RelaySender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> RelaySender<T> {
+    /// Queue a message without waiting, dropping the oldest one if the queue
+    /// is full. Fails only once the receiver is gone.
+    pub fn send(&self, msg: T) -> Result<(), Closed<T>> {
+        if self.shared.receiver_closed.load(Ordering::Acquire) {
+            return Err(Closed(msg));
+        }
+        {
+            let mut state = self.shared.queue.lock().unwrap();
+            if state.messages.len() >= self.shared.capacity {
+                state.messages.pop_front();
+                self.shared.dropped.fetch_add(1, Ordering::Relaxed);
+                if !state.overflowing {
+                    state.overflowing = true;
+                    warn!(
+                        "Relay queue for {} is full ({} messages), dropping oldest",
+                        self.shared.label, self.shared.capacity
+                    );
+                }
+            }
+            state.messages.push_back(msg);
+        }
+        self.shared.message_ready.notify_one();
+        Ok(())
+    }
+
+    /// Queue a message, waiting for room instead of dropping anything
+    pub async fn send_wait(&self, msg: T) -> Result<(), Closed<T>> {
+        loop {
+            let space = self.shared.space_ready.notified();
+            if self.shared.receiver_closed.load(Ordering::Acquire) {
+                return Err(Closed(msg));
+            }
+            {
+                let mut state = self.shared.queue.lock().unwrap();
+                if state.messages.len() < self.shared.capacity {
+                    state.messages.push_back(msg);
+                    drop(state);
+                    self.shared.message_ready.notify_one();
+                    return Ok(());
+                }
+            }
+            space.await;
+        }
+    }
+
+    /// Whether the receiver has been dropped
+    pub fn is_closed(&self) -> bool {
+        self.shared.receiver_closed.load(Ordering::Acquire)
+    }
+}
+
+impl<T> Clone for RelaySender<T> {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::Relaxed);
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for RelaySender<T> {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.shared.message_ready.notify_one();
+        }
+    }
+}
+
+pub struct RelayReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> RelayReceiver<T> {
+    /// Next message in order; `None` once every sender is gone and the
+    /// queue is drained
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            {
+                let mut state = self.shared.queue.lock().unwrap();
+                if let Some(msg) = state.messages.pop_front() {
+                    if state.overflowing && state.messages.is_empty() {
+                        state.overflowing = false;
+                        debug!("Relay queue for {} caught up", self.shared.label);
+                    }
+                    drop(state);
+                    self.shared.space_ready.notify_waiters();
+                    return Some(msg);
+                }
+            }
+            if self.shared.senders.load(Ordering::Acquire) == 0 {
+                return None;
+            }
+            self.shared.message_ready.notified().await;
+        }
+    }
+}
+
+impl<T> Drop for RelayReceiver<T> {
+    fn drop(&mut self) {
+        self.shared.receiver_closed.store(true, Ordering::Release);
+        self.shared.queue.lock().unwrap().messages.clear();
+        self.shared.space_ready.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_overflow_drops_oldest() {
+        let dropped = Arc::new(AtomicU64::new(0));
+        let (tx, mut rx) = channel(3, dropped.clone(), "test");
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(dropped.load(Ordering::Relaxed), 2);
+        drop(tx);
+        let mut received = Vec::new();
+        while let Some(i) = rx.recv().await {
+            received.push(i);
+        }
+        assert_eq!(received, vec![2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_send_fails_after_receiver_drops() {
+        let (tx, rx) = channel(3, Arc::new(AtomicU64::new(0)), "test");
+        assert!(!tx.is_closed());
+        drop(rx);
+        assert!(tx.is_closed());
+        assert_eq!(tx.send(7), Err(Closed(7)));
+        assert_eq!(tx.send_wait(8).await, Err(Closed(8)));
+    }
+
+    #[tokio::test]
+    async fn test_send_wait_waits_for_room() {
+        let dropped = Arc::new(AtomicU64::new(0));
+        let (tx, mut rx) = channel(2, dropped.clone(), "test");
+        let producer = tokio::spawn(async move {
+            for i in 0..10 {
+                tx.send_wait(i).await.unwrap();
+            }
+        });
+        let mut received = Vec::new();
+        while let Some(i) = rx.recv().await {
+            received.push(i);
+        }
+        producer.await.unwrap();
+        assert_eq!(received, (0..10).collect::<Vec<_>>());
+        assert_eq!(dropped.load(Ordering::Relaxed), 0);
+    }
+}
@@ -0,0 +1,146 @@
+//! Hot-reloadable runtime configuration.
+//!
+//! The effective config lives in a `watch` channel. Readers take a cheap
+//! snapshot with [`RuntimeConfigStore::current`]; admin updates and the periodic
+//! reload (which picks up changes made by other backend instances) replace it
+//! in place, so nothing needs a restart and no WebSocket is dropped.
+
+use crate::db::DbPool;
+use crate::schema::runtime_config;
+use diesel::prelude::*;
+use shared::{RuntimeConfig, RuntimeConfigOverrides};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use uuid::Uuid;
+
+/// How often overrides are re-read from the database
+pub const RELOAD_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone)]
+pub struct RuntimeConfigStore {
+    defaults: Arc<RuntimeConfig>,
+    tx: Arc<watch::Sender<Arc<RuntimeConfig>>>,
+}
+
+impl RuntimeConfigStore {
+    /// Start from the environment defaults, before any overrides are loaded
+    pub fn new(defaults: RuntimeConfig) -> Self {
+        let (tx, _) = watch::channel(Arc::new(defaults.clone()));
+        Self {
+            defaults: Arc::new(defaults),
+            tx: Arc::new(tx),
+        }
+    }
+
+    pub fn defaults(&self) -> &RuntimeConfig {
+        &self.defaults
+    }
+
+    /// Snapshot of the effective config
+    pub fn current(&self) -> Arc<RuntimeConfig> {
+        self.tx.borrow().clone()
+    }
+
+    /// Receiver that wakes whenever the effective config changes
+    pub fn subscribe(&self) -> watch::Receiver<Arc<RuntimeConfig>> {
+        self.tx.subscribe()
+    }
+
+    /// Apply overrides on top of the defaults. Returns true if the effective
+    /// config changed, in which case subscribers are notified.
+    pub fn apply(&self, overrides: &RuntimeConfigOverrides) -> bool {
+        let next = self.defaults.with_overrides(overrides);
+        self.tx.send_if_modified(|current| {
+            if **current == next {
+                return false;
+            }
+            *current = Arc::new(next);
+            true
+        })
+    }
+
+    /// Re-read overrides from the database and apply them
+    pub fn reload(&self, db_pool: &DbPool) -> anyhow::Result<bool> {
+        let mut conn = db_pool.get()?;
+        let overrides = load_overrides(&mut conn)?;
+        Ok(self.apply(&overrides))
+    }
+}
+
+pub fn load_overrides(conn: &mut PgConnection) -> anyhow::Result<RuntimeConfigOverrides> {
+    let stored: Option<serde_json::Value> = runtime_config::table
+        .select(runtime_config::overrides)
+        .first(conn)
+        .optional()?;
+    match stored {
+        Some(value) => Ok(serde_json::from_value(value)?),
+        None => Ok(RuntimeConfigOverrides::default()),
+    }
+}
+
+pub fn save_overrides(
+    conn: &mut PgConnection,
+    overrides: &RuntimeConfigOverrides,
+    updated_by: Uuid,
+) -> anyhow::Result<()> {
+    let value = serde_json::to_value(overrides)?;
+    diesel::insert_into(runtime_config::table)
+        .values((
+            runtime_config::id.eq(1),
+            runtime_config::overrides.eq(&value),
+            runtime_config::updated_by.eq(updated_by),
+        ))
+        .on_conflict(runtime_config::id)
+        .do_update()
+        .set((
+            runtime_config::overrides.eq(&value),
+            runtime_config::updated_at.eq(diesel::dsl::now),
+            runtime_config::updated_by.eq(updated_by),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> RuntimeConfig {
+        RuntimeConfig {
+            app_title: "Claude Code Sessions".to_string(),
+            issue_report_url: shared::DEFAULT_ISSUE_REPORT_URL.to_string(),
+            allowed_email_domain: None,
+            allowed_emails: None,
+            message_retention_count: 100,
+            message_retention_days: 30,
+            max_sessions_per_user: 0,
+            rate_limit_requests_per_minute: 0,
+            rate_limit_messages_per_minute: 0,
+            render_limits: shared::RenderLimits::default(),
+        }
+    }
+
+    #[test]
+    fn test_apply_notifies_only_on_change() {
+        let store = RuntimeConfigStore::new(defaults());
+        let mut rx = store.subscribe();
+
+        assert!(!store.apply(&RuntimeConfigOverrides::default()));
+        assert!(!rx.has_changed().unwrap());
+
+        let overrides = RuntimeConfigOverrides {
+            message_retention_count: Some(20),
+            ..Default::default()
+        };
+        assert!(store.apply(&overrides));
+        assert!(rx.has_changed().unwrap());
+        assert_eq!(rx.borrow_and_update().message_retention_count, 20);
+        assert_eq!(store.current().message_retention_count, 20);
+        assert_eq!(store.defaults().message_retention_count, 100);
+
+        // Same overrides again is a no-op
+        assert!(!store.apply(&overrides));
+        assert!(!rx.has_changed().unwrap());
+    }
+}
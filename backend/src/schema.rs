@@ -1,5 +1,47 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    activity_events (id) {
+        id -> Uuid,
+        #[max_length = 32]
+        kind -> Varchar,
+        session_id -> Uuid,
+        data -> Jsonb,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    ci_runs (repository, branch, name) {
+        #[max_length = 255]
+        repository -> Varchar,
+        #[max_length = 255]
+        branch -> Varchar,
+        #[max_length = 255]
+        name -> Varchar,
+        #[max_length = 64]
+        head_sha -> Varchar,
+        #[max_length = 16]
+        state -> Varchar,
+        url -> Nullable<Text>,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    budget_limits (id) {
+        id -> Int4,
+        session_cost_soft_usd -> Nullable<Float8>,
+        session_cost_hard_usd -> Nullable<Float8>,
+        daily_cost_soft_usd -> Nullable<Float8>,
+        daily_cost_hard_usd -> Nullable<Float8>,
+        session_tokens_soft -> Nullable<Int8>,
+        session_tokens_hard -> Nullable<Int8>,
+        updated_at -> Timestamp,
+        updated_by -> Nullable<Uuid>,
+    }
+}
+
 diesel::table! {
     deleted_session_costs (id) {
         id -> Uuid,
@@ -15,6 +57,86 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    escalation_policies (session_id) {
+        session_id -> Uuid,
+        notify_after_minutes -> Nullable<Int4>,
+        notify_user_id -> Nullable<Uuid>,
+        default_after_minutes -> Nullable<Int4>,
+        #[max_length = 10]
+        default_action -> Varchar,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    guest_invites (id) {
+        id -> Uuid,
+        session_id -> Uuid,
+        #[max_length = 64]
+        token_hash -> Varchar,
+        #[max_length = 64]
+        name -> Varchar,
+        #[max_length = 20]
+        role -> Varchar,
+        invited_by -> Uuid,
+        guest_user_id -> Nullable<Uuid>,
+        expires_at -> Timestamp,
+        revoked_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    kill_switch (id) {
+        id -> Int4,
+        reason -> Text,
+        engaged_by -> Text,
+        engaged_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    message_embeddings (message_id) {
+        message_id -> Uuid,
+        #[max_length = 255]
+        model -> Varchar,
+        embedding -> Array<Float4>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    message_annotations (id) {
+        id -> Uuid,
+        session_id -> Uuid,
+        user_id -> Uuid,
+        #[max_length = 64]
+        source -> Varchar,
+        #[max_length = 16]
+        level -> Varchar,
+        #[max_length = 200]
+        label -> Varchar,
+        url -> Nullable<Text>,
+        data -> Nullable<Jsonb>,
+        start_seq -> Int8,
+        end_seq -> Int8,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    message_reactions (id) {
+        id -> Uuid,
+        message_id -> Uuid,
+        session_id -> Uuid,
+        user_id -> Uuid,
+        #[max_length = 32]
+        emoji -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     messages (id) {
         id -> Uuid,
@@ -24,6 +146,33 @@ diesel::table! {
         content -> Text,
         created_at -> Timestamp,
         user_id -> Uuid,
+        seq -> Int8,
+        produced_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    metering_exports (day) {
+        day -> Date,
+        #[max_length = 32]
+        sink -> Varchar,
+        records -> Int4,
+        exported_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    notification_digests (day) {
+        day -> Date,
+        sent_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    notification_preferences (user_id) {
+        user_id -> Uuid,
+        preferences -> Jsonb,
+        updated_at -> Timestamp,
     }
 }
 
@@ -51,6 +200,27 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    permission_audit_log (id) {
+        id -> Uuid,
+        session_id -> Uuid,
+        #[max_length = 255]
+        request_id -> Varchar,
+        #[max_length = 255]
+        tool_name -> Varchar,
+        input -> Jsonb,
+        requested_at -> Timestamp,
+        allowed -> Nullable<Bool>,
+        #[max_length = 20]
+        channel -> Nullable<Varchar>,
+        decided_by -> Nullable<Uuid>,
+        detail -> Nullable<Text>,
+        decided_at -> Nullable<Timestamp>,
+        escalated_at -> Nullable<Timestamp>,
+        escalated_to -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     proxy_auth_tokens (id) {
         id -> Uuid,
@@ -63,6 +233,20 @@ diesel::table! {
         last_used_at -> Nullable<Timestamp>,
         expires_at -> Timestamp,
         revoked -> Bool,
+        #[max_length = 255]
+        last_used_host -> Nullable<Varchar>,
+        scopes -> Jsonb,
+    }
+}
+
+diesel::table! {
+    push_subscriptions (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        endpoint -> Text,
+        p256dh -> Text,
+        auth -> Text,
+        created_at -> Timestamp,
     }
 }
 
@@ -82,6 +266,49 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    redaction_policies (session_id) {
+        session_id -> Uuid,
+        whole_session -> Bool,
+        tools -> Array<Text>,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    runtime_config (id) {
+        id -> Int4,
+        overrides -> Jsonb,
+        updated_at -> Timestamp,
+        updated_by -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    saved_searches (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        #[max_length = 255]
+        name -> Varchar,
+        query -> Text,
+        regex -> Bool,
+        alert -> Bool,
+        checked_until -> Timestamp,
+        last_match_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    session_incidents (id) {
+        id -> Uuid,
+        session_id -> Uuid,
+        reason -> Text,
+        bundle -> Jsonb,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     session_members (id) {
         id -> Uuid,
@@ -90,6 +317,29 @@ diesel::table! {
         #[max_length = 20]
         role -> Varchar,
         created_at -> Timestamp,
+        expires_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    session_model_usage (session_id, model) {
+        session_id -> Uuid,
+        #[max_length = 128]
+        model -> Varchar,
+        cost_usd -> Float8,
+        input_tokens -> Int8,
+        output_tokens -> Int8,
+        cache_creation_tokens -> Int8,
+        cache_read_tokens -> Int8,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    session_snapshots (session_id) {
+        session_id -> Uuid,
+        snapshot -> Jsonb,
+        uploaded_at -> Timestamp,
     }
 }
 
@@ -117,6 +367,96 @@ diesel::table! {
         #[max_length = 32]
         client_version -> Nullable<Varchar>,
         input_seq -> Int8,
+        #[max_length = 16]
+        outcome -> Nullable<Varchar>,
+        outcome_set_at -> Nullable<Timestamp>,
+        proxy_token_id -> Nullable<Uuid>,
+        #[max_length = 255]
+        git_repo -> Nullable<Varchar>,
+        host_info -> Nullable<Jsonb>,
+    }
+}
+
+diesel::table! {
+    speech_usage_daily (user_id, day) {
+        user_id -> Uuid,
+        day -> Date,
+        audio_ms -> Int8,
+    }
+}
+
+diesel::table! {
+    tool_usage_daily (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        day -> Date,
+        #[max_length = 128]
+        tool_name -> Varchar,
+        uses -> Int4,
+    }
+}
+
+diesel::table! {
+    turn_feedback (id) {
+        id -> Uuid,
+        session_id -> Uuid,
+        message_id -> Nullable<Uuid>,
+        user_id -> Uuid,
+        #[max_length = 10]
+        rating -> Varchar,
+        comment -> Nullable<Text>,
+        prompt -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    turns (id) {
+        id -> Uuid,
+        session_id -> Uuid,
+        turn_index -> Int4,
+        start_seq -> Int8,
+        end_seq -> Int8,
+        end_message_id -> Nullable<Uuid>,
+        duration_ms -> Int8,
+        input_tokens -> Int8,
+        output_tokens -> Int8,
+        cost_usd -> Float8,
+        tools -> Array<Text>,
+        error -> Nullable<Text>,
+        created_at -> Timestamp,
+        compacted -> Bool,
+    }
+}
+
+diesel::table! {
+    usage_daily (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        session_id -> Nullable<Uuid>,
+        #[max_length = 255]
+        session_name -> Varchar,
+        day -> Date,
+        #[max_length = 128]
+        model -> Varchar,
+        cost_usd -> Float8,
+        input_tokens -> Int8,
+        output_tokens -> Int8,
+        cache_creation_tokens -> Int8,
+        cache_read_tokens -> Int8,
+        turns -> Int4,
+    }
+}
+
+diesel::table! {
+    user_identities (provider, subject) {
+        #[max_length = 32]
+        provider -> Varchar,
+        #[max_length = 255]
+        subject -> Varchar,
+        user_id -> Uuid,
+        created_at -> Timestamp,
     }
 }
 
@@ -132,33 +472,95 @@ diesel::table! {
         avatar_url -> Nullable<Text>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
-        is_admin -> Bool,
+        #[max_length = 16]
+        role -> Varchar,
         disabled -> Bool,
         voice_enabled -> Bool,
         ban_reason -> Nullable<Text>,
+        #[max_length = 255]
+        billing_customer_id -> Nullable<Varchar>,
+        #[max_length = 16]
+        voice_language -> Varchar,
     }
 }
 
+diesel::joinable!(activity_events -> sessions (session_id));
+diesel::joinable!(budget_limits -> users (updated_by));
 diesel::joinable!(deleted_session_costs -> users (user_id));
+diesel::joinable!(escalation_policies -> sessions (session_id));
+diesel::joinable!(escalation_policies -> users (notify_user_id));
+diesel::joinable!(guest_invites -> sessions (session_id));
+diesel::joinable!(message_embeddings -> messages (message_id));
+diesel::joinable!(message_annotations -> sessions (session_id));
+diesel::joinable!(message_annotations -> users (user_id));
+diesel::joinable!(message_reactions -> messages (message_id));
+diesel::joinable!(message_reactions -> sessions (session_id));
+diesel::joinable!(message_reactions -> users (user_id));
 diesel::joinable!(messages -> sessions (session_id));
 diesel::joinable!(messages -> users (user_id));
+diesel::joinable!(notification_preferences -> users (user_id));
 diesel::joinable!(pending_inputs -> sessions (session_id));
 diesel::joinable!(pending_permission_requests -> sessions (session_id));
+diesel::joinable!(permission_audit_log -> sessions (session_id));
+diesel::joinable!(permission_audit_log -> users (decided_by));
 diesel::joinable!(proxy_auth_tokens -> users (user_id));
+diesel::joinable!(push_subscriptions -> users (user_id));
 diesel::joinable!(raw_message_log -> sessions (session_id));
 diesel::joinable!(raw_message_log -> users (user_id));
+diesel::joinable!(redaction_policies -> sessions (session_id));
+diesel::joinable!(runtime_config -> users (updated_by));
+diesel::joinable!(saved_searches -> users (user_id));
+diesel::joinable!(session_incidents -> sessions (session_id));
 diesel::joinable!(session_members -> sessions (session_id));
 diesel::joinable!(session_members -> users (user_id));
+diesel::joinable!(session_model_usage -> sessions (session_id));
+diesel::joinable!(session_snapshots -> sessions (session_id));
+diesel::joinable!(sessions -> proxy_auth_tokens (proxy_token_id));
 diesel::joinable!(sessions -> users (user_id));
+diesel::joinable!(speech_usage_daily -> users (user_id));
+diesel::joinable!(tool_usage_daily -> users (user_id));
+diesel::joinable!(turn_feedback -> messages (message_id));
+diesel::joinable!(turn_feedback -> sessions (session_id));
+diesel::joinable!(turn_feedback -> users (user_id));
+diesel::joinable!(turns -> sessions (session_id));
+diesel::joinable!(usage_daily -> sessions (session_id));
+diesel::joinable!(usage_daily -> users (user_id));
+diesel::joinable!(user_identities -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    activity_events,
+    budget_limits,
+    ci_runs,
     deleted_session_costs,
+    escalation_policies,
+    guest_invites,
+    kill_switch,
+    message_embeddings,
+    message_annotations,
+    message_reactions,
     messages,
+    metering_exports,
+    notification_digests,
+    notification_preferences,
     pending_inputs,
     pending_permission_requests,
+    permission_audit_log,
     proxy_auth_tokens,
+    push_subscriptions,
     raw_message_log,
+    redaction_policies,
+    runtime_config,
+    saved_searches,
+    session_incidents,
     session_members,
+    session_model_usage,
+    session_snapshots,
     sessions,
+    speech_usage_daily,
+    tool_usage_daily,
+    turn_feedback,
+    turns,
+    usage_daily,
+    user_identities,
     users,
 );
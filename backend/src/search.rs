@@ -0,0 +1,1056 @@
+//! Transcript search
+//!
+//! Parses the query language used by `GET /api/search` and runs it over the
+//! stored messages of every session the caller belongs to. A query is free
+//! text plus filters:
+//!
+//! - `session:<name or id prefix>` limits to matching sessions
+//! - `tool:<name>` keeps assistant turns that called that tool
+//! - `model:<name>` keeps messages produced by a model containing `<name>`
+//! - `before:<date>` / `after:<date>` bound the message time, as
+//!   `YYYY-MM-DD`, `YYYY-MM-DDTHH:MM:SS` (UTC), or relative `7d` / `12h`
+//! - `is:error` keeps failed results, errors, and failed tool calls
+//!
+//! Repeating a filter matches any of its values; different filters and text
+//! terms must all match. Values with spaces go in double quotes. Unknown
+//! `key:value` words are searched as text. In regex mode the free text is
+//! one case-insensitive regular expression instead of separate terms.
+//!
+//! Text is matched against what the in-session search indexes: prompts,
+//! assistant text, tool names and inputs, results, and errors. Tool output is
+//! not searched. Messages are redacted for the searcher's role before they're
+//! matched, so viewers neither find nor see redacted tool inputs.
+//!
+//! Semantic mode ([`run_semantic`]) keeps the filters but ranks prompts and
+//! assistant turns by how close their embedding is to the free text's,
+//! instead of requiring the words to appear.
+//!
+//! [`related_sessions`] compares first prompts the same way, to suggest past
+//! sessions that started out on a similar task.
+
+use crate::embeddings;
+use crate::handlers::redaction::redactor_for;
+use crate::schema::{message_embeddings, messages, session_members, sessions};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+use regex::{Regex, RegexBuilder};
+use serde_json::Value;
+use shared::{Redactor, RelatedSession, SearchHit, SearchResponse, SessionOutcome};
+use uuid::Uuid;
+
+/// Stop after looking at this many candidate messages
+const MAX_SCANNED: i64 = 5000;
+
+/// Messages fetched from the database at a time
+const BATCH_SIZE: i64 = 500;
+
+/// Compiled size cap for user-supplied regexes
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// Best-scoring messages whose content is loaded to apply the JSON filters,
+/// as a multiple of the requested limit
+const SEMANTIC_CANDIDATES_PER_HIT: usize = 4;
+
+/// Past sessions scoring below this aren't suggested as related
+const RELATED_MIN_SCORE: f32 = 0.5;
+
+/// Snippet context around the first match, in characters
+const SNIPPET_BEFORE: usize = 60;
+const SNIPPET_AFTER: usize = 100;
+
+/// Message id, session id and name, role, content, received and produced
+/// times, and the searcher's role in the session, as loaded for a hit
+type HitRow = (
+    Uuid,
+    Uuid,
+    String,
+    String,
+    String,
+    NaiveDateTime,
+    Option<NaiveDateTime>,
+    String,
+);
+
+/// Why a query couldn't be parsed; shown to the user as-is
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum QueryError {
+    #[error("Enter something to search for")]
+    Empty,
+
+    #[error("Invalid date in {0}: expected YYYY-MM-DD, YYYY-MM-DDTHH:MM:SS, or e.g. 7d")]
+    InvalidDate(String),
+
+    #[error("Invalid regex: {0}")]
+    InvalidRegex(String),
+
+    #[error("Unterminated quote")]
+    UnterminatedQuote,
+}
+
+/// A parsed search query
+#[derive(Debug, Default)]
+pub struct SearchQuery {
+    /// The free text, filters removed
+    text: String,
+    /// Each must match the message text
+    terms: Vec<Regex>,
+    /// Plain terms safe to pre-filter with ILIKE on the stored JSON
+    prefilter: Vec<String>,
+    sessions: Vec<String>,
+    tools: Vec<String>,
+    models: Vec<String>,
+    before: Option<NaiveDateTime>,
+    after: Option<NaiveDateTime>,
+    errors_only: bool,
+}
+
+/// One word of the query: `key:value` or bare text
+#[derive(Debug, PartialEq)]
+struct Token {
+    key: Option<String>,
+    value: String,
+    /// As typed, quotes included
+    raw: String,
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            return Ok(tokens);
+        }
+
+        let mut raw = String::new();
+        let mut value = String::new();
+        let mut key = None;
+        let mut quoted = false;
+        while let Some(c) = chars.next_if(|c| quoted || !c.is_whitespace()) {
+            raw.push(c);
+            match c {
+                '"' => quoted = !quoted,
+                ':' if !quoted && key.is_none() && !value.is_empty() => {
+                    key = Some(std::mem::take(&mut value).to_lowercase());
+                }
+                _ => value.push(c),
+            }
+        }
+        if quoted {
+            return Err(QueryError::UnterminatedQuote);
+        }
+        tokens.push(Token { key, value, raw });
+    }
+}
+
+/// Start of a `before:`/`after:` bound, relative to `now` for `7d` / `12h`
+fn parse_time(value: &str, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return date.and_hms_opt(0, 0, 0);
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S") {
+        return Some(time);
+    }
+    let split = value.len().checked_sub(1)?;
+    let amount: i64 = value.get(..split)?.parse().ok()?;
+    let ago = match &value[split..] {
+        "d" => Duration::try_days(amount)?,
+        "h" => Duration::try_hours(amount)?,
+        _ => return None,
+    };
+    now.checked_sub_signed(ago)
+}
+
+fn build_regex(pattern: &str) -> Result<Regex, QueryError> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|e| QueryError::InvalidRegex(e.to_string()))
+}
+
+/// Whether ILIKE on the stored JSON finds `term` exactly when the decoded
+/// text contains it: no characters JSON escapes, and ASCII so the database
+/// and `regex` agree on case folding
+fn prefilterable(term: &str) -> bool {
+    term.chars()
+        .all(|c| c.is_ascii() && !c.is_ascii_control() && c != '"' && c != '\\')
+}
+
+impl SearchQuery {
+    pub fn parse(query: &str, regex_mode: bool) -> Result<Self, QueryError> {
+        Self::parse_at(query, regex_mode, chrono::Utc::now().naive_utc())
+    }
+
+    fn parse_at(query: &str, regex_mode: bool, now: NaiveDateTime) -> Result<Self, QueryError> {
+        let mut parsed = SearchQuery::default();
+        let mut text = Vec::new();
+        let mut has_filter = false;
+
+        for token in tokenize(query)? {
+            let value = token.value.trim().to_string();
+            match token.key.as_deref() {
+                Some("session") if !value.is_empty() => parsed.sessions.push(value.to_lowercase()),
+                Some("tool") if !value.is_empty() => parsed.tools.push(value.to_lowercase()),
+                Some("model") if !value.is_empty() => parsed.models.push(value.to_lowercase()),
+                Some(key @ ("before" | "after")) => {
+                    let time = parse_time(&value, now)
+                        .ok_or_else(|| QueryError::InvalidDate(token.raw.clone()))?;
+                    if key == "before" {
+                        parsed.before = Some(time);
+                    } else {
+                        parsed.after = Some(time);
+                    }
+                }
+                Some("is") if value.eq_ignore_ascii_case("error") => parsed.errors_only = true,
+                _ => {
+                    text.push(token);
+                    continue;
+                }
+            }
+            has_filter = true;
+        }
+
+        if regex_mode {
+            let pattern = text
+                .iter()
+                .map(|t| t.raw.as_str())
+                .collect::<Vec<_>>()
+                .join(" ");
+            if !pattern.is_empty() {
+                parsed.terms.push(build_regex(&pattern)?);
+            }
+            parsed.text = pattern;
+        } else {
+            let mut words = Vec::new();
+            for token in text {
+                // Filters look like key:value; anything else keeps its colon
+                let term = match token.key {
+                    Some(key) => format!("{}:{}", key, token.value),
+                    None => token.value,
+                };
+                if term.is_empty() {
+                    continue;
+                }
+                parsed.terms.push(build_regex(&regex::escape(&term))?);
+                if prefilterable(&term) {
+                    parsed.prefilter.push(term.clone());
+                }
+                words.push(term);
+            }
+            parsed.text = words.join(" ");
+        }
+
+        if parsed.terms.is_empty() && !has_filter {
+            return Err(QueryError::Empty);
+        }
+        Ok(parsed)
+    }
+
+    /// Narrow to messages created in `[since, until)`, keeping any tighter
+    /// `after:`/`before:` bound from the query itself
+    pub fn within(mut self, since: NaiveDateTime, until: NaiveDateTime) -> Self {
+        self.after = Some(self.after.map_or(since, |after| after.max(since)));
+        self.before = Some(self.before.map_or(until, |before| before.min(until)));
+        self
+    }
+
+    /// The free text with the filters taken out, what semantic mode embeds
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether a stored message passes the `tool:`, `model:`, and `is:error`
+    /// filters
+    fn passes_filters(&self, content: &Value) -> bool {
+        if self.errors_only && !is_error(content) {
+            return false;
+        }
+        if !self.tools.is_empty() {
+            let used = tool_names(content);
+            if !self.tools.iter().any(|t| used.contains(t)) {
+                return false;
+            }
+        }
+        if !self.models.is_empty() {
+            let used = model_names(content);
+            if !self
+                .models
+                .iter()
+                .any(|m| used.iter().any(|name| name.contains(m.as_str())))
+            {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Whether a stored message matches, and if so the snippet to show
+    fn matches(&self, content: &Value) -> Option<String> {
+        if !self.passes_filters(content) {
+            return None;
+        }
+
+        let text = searchable_text(content);
+        let mut first = None;
+        for term in &self.terms {
+            let found = term.find(&text)?;
+            first.get_or_insert(found.start());
+        }
+        Some(snippet(&text, first.unwrap_or(0)))
+    }
+}
+
+fn push_strings(value: &Value, out: &mut String) {
+    match value {
+        Value::String(s) => {
+            out.push_str(s);
+            out.push('\n');
+        }
+        Value::Array(items) => items.iter().for_each(|v| push_strings(v, out)),
+        Value::Object(fields) => fields.values().for_each(|v| push_strings(v, out)),
+        _ => {}
+    }
+}
+
+fn content_blocks(content: &Value) -> impl Iterator<Item = &Value> {
+    content
+        .pointer("/message/content")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+}
+
+fn block_type(block: &Value) -> Option<&str> {
+    block.get("type").and_then(Value::as_str)
+}
+
+/// Redactors for the searcher, one per session, loaded as hits turn up
+#[derive(Default)]
+struct Redactors(std::collections::HashMap<Uuid, Option<Redactor>>);
+
+impl Redactors {
+    /// The redactor for a session the searcher has `role` in, if they get one
+    fn get(&mut self, conn: &mut PgConnection, session_id: Uuid, role: &str) -> Option<&Redactor> {
+        self.0
+            .entry(session_id)
+            .or_insert_with(|| redactor_for(conn, session_id, role))
+            .as_ref()
+    }
+}
+
+/// Redact a message's content as its session's transcript would be for the
+/// searcher. Hits aren't in transcript order, so each message is redacted on
+/// its own; tool inputs are still redacted by their tool's name.
+fn visible(content: &mut Value, redactor: Option<&Redactor>) {
+    if let Some(redactor) = redactor {
+        redactor.clone().apply(content);
+    }
+}
+
+/// The text a message contributes to search, matching the in-session search
+pub(crate) fn searchable_text(content: &Value) -> String {
+    let mut out = String::new();
+    match content.get("type").and_then(Value::as_str) {
+        Some("assistant") | Some("user") => {
+            if let Some(text) = content.get("content").and_then(Value::as_str) {
+                out.push_str(text);
+                out.push('\n');
+            }
+            if let Some(text) = content.pointer("/message/content").and_then(Value::as_str) {
+                out.push_str(text);
+                out.push('\n');
+            }
+            for block in content_blocks(content) {
+                match block_type(block) {
+                    Some("text") => push_strings(&block["text"], &mut out),
+                    Some("tool_use") => {
+                        push_strings(&block["name"], &mut out);
+                        push_strings(&block["input"], &mut out);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Some("result") => push_strings(&content["result"], &mut out),
+        Some("error") => {
+            push_strings(&content["message"], &mut out);
+            push_strings(&content["error"], &mut out);
+        }
+        _ => {}
+    }
+    out
+}
+
+/// Lowercased names of the tools an assistant turn called
+fn tool_names(content: &Value) -> Vec<String> {
+    content_blocks(content)
+        .filter(|block| block_type(block) == Some("tool_use"))
+        .filter_map(|block| block.get("name").and_then(Value::as_str))
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Lowercased models named by an assistant turn, the session init, or a
+/// result's per-model usage
+fn model_names(content: &Value) -> Vec<String> {
+    let mut names: Vec<String> = [content.pointer("/message/model"), content.get("model")]
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(str::to_lowercase)
+        .collect();
+    if let Some(usage) = content.get("modelUsage").and_then(Value::as_object) {
+        names.extend(usage.keys().map(|k| k.to_lowercase()));
+    }
+    names
+}
+
+fn is_error(content: &Value) -> bool {
+    match content.get("type").and_then(Value::as_str) {
+        Some("error") => true,
+        Some("result") => content
+            .get("is_error")
+            .and_then(Value::as_bool)
+            .unwrap_or(false),
+        Some("user") => content_blocks(content).any(|block| {
+            block_type(block) == Some("tool_result")
+                && block
+                    .get("is_error")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false)
+        }),
+        _ => false,
+    }
+}
+
+/// Text around byte offset `at`, on one line
+fn snippet(text: &str, at: usize) -> String {
+    let start = text[..at]
+        .char_indices()
+        .rev()
+        .nth(SNIPPET_BEFORE - 1)
+        .map_or(0, |(i, _)| i);
+    let end = text[at..]
+        .char_indices()
+        .nth(SNIPPET_AFTER)
+        .map_or(text.len(), |(i, _)| at + i);
+    let mut out = text[start..end]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if start > 0 {
+        out.insert(0, '…');
+    }
+    if end < text.len() {
+        out.push('…');
+    }
+    out
+}
+
+/// Escape `%`, `_`, and `\` for a LIKE pattern
+fn like_escape(term: &str) -> String {
+    let mut out = String::with_capacity(term.len());
+    for c in term.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Ids of `user_id`'s sessions named by `session:` filters: a full id, an id
+/// prefix, or part of the name
+fn resolve_sessions(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    filters: &[String],
+) -> QueryResult<Vec<Uuid>> {
+    let accessible: Vec<(Uuid, String)> = sessions::table
+        .inner_join(session_members::table.on(session_members::session_id.eq(sessions::id)))
+        .filter(session_members::user_id.eq(user_id))
+        .select((sessions::id, sessions::session_name))
+        .load(conn)?;
+    Ok(accessible
+        .into_iter()
+        .filter(|(id, name)| {
+            let id = id.to_string();
+            let name = name.to_lowercase();
+            filters
+                .iter()
+                .any(|f| id.starts_with(f.as_str()) || name.contains(f.as_str()))
+        })
+        .map(|(id, _)| id)
+        .collect())
+}
+
+/// Run `query` over `user_id`'s sessions, newest messages first
+pub fn run(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    query: &SearchQuery,
+    limit: usize,
+) -> QueryResult<SearchResponse> {
+    let session_ids = if query.sessions.is_empty() {
+        None
+    } else {
+        let ids = resolve_sessions(conn, user_id, &query.sessions)?;
+        if ids.is_empty() {
+            return Ok(SearchResponse {
+                hits: Vec::new(),
+                truncated: false,
+            });
+        }
+        Some(ids)
+    };
+
+    let candidates = || {
+        let mut q = messages::table
+            .inner_join(sessions::table.on(sessions::id.eq(messages::session_id)))
+            .inner_join(session_members::table.on(session_members::session_id.eq(sessions::id)))
+            .filter(session_members::user_id.eq(user_id))
+            .select((
+                messages::id,
+                messages::session_id,
+                sessions::session_name,
+                messages::role,
+                messages::content,
+                messages::created_at,
+                messages::produced_at,
+                session_members::role,
+            ))
+            .into_boxed();
+        if let Some(ids) = &session_ids {
+            q = q.filter(messages::session_id.eq_any(ids.clone()));
+        }
+        if let Some(before) = query.before {
+            q = q.filter(messages::created_at.lt(before));
+        }
+        if let Some(after) = query.after {
+            q = q.filter(messages::created_at.ge(after));
+        }
+        if !query.tools.is_empty() {
+            q = q.filter(messages::role.eq("assistant"));
+        }
+        if query.errors_only {
+            q = q.filter(messages::role.eq_any(["result", "error", "user"]));
+        }
+        for term in &query.prefilter {
+            q = q.filter(messages::content.ilike(format!("%{}%", like_escape(term))));
+        }
+        q.order((messages::created_at.desc(), messages::id.desc()))
+    };
+
+    let mut redactors = Redactors::default();
+    let mut hits = Vec::new();
+    let mut scanned = 0;
+    loop {
+        let batch: Vec<HitRow> = candidates().offset(scanned).limit(BATCH_SIZE).load(conn)?;
+        let exhausted = (batch.len() as i64) < BATCH_SIZE;
+        scanned += batch.len() as i64;
+
+        for (
+            message_id,
+            session_id,
+            session_name,
+            role,
+            content,
+            created_at,
+            produced_at,
+            member_role,
+        ) in batch
+        {
+            let Ok(mut value) = serde_json::from_str::<Value>(&content) else {
+                continue;
+            };
+            visible(&mut value, redactors.get(conn, session_id, &member_role));
+            let Some(snippet) = query.matches(&value) else {
+                continue;
+            };
+            if hits.len() == limit {
+                return Ok(SearchResponse {
+                    hits,
+                    truncated: true,
+                });
+            }
+            hits.push(SearchHit {
+                message_id,
+                session_id,
+                session_name,
+                role,
+                created_at: produced_at.unwrap_or(created_at).and_utc().to_rfc3339(),
+                snippet,
+                score: None,
+            });
+        }
+
+        if exhausted {
+            return Ok(SearchResponse {
+                hits,
+                truncated: false,
+            });
+        }
+        if scanned >= MAX_SCANNED {
+            return Ok(SearchResponse {
+                hits,
+                truncated: true,
+            });
+        }
+    }
+}
+
+/// Rank `user_id`'s prompts and assistant turns by similarity to `target`,
+/// an embedding of the query text from `model`. Filters apply as in [`run`];
+/// the newest `MAX_SCANNED` candidates are compared.
+pub fn run_semantic(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    query: &SearchQuery,
+    model: &str,
+    target: &[f32],
+    limit: usize,
+) -> QueryResult<SearchResponse> {
+    let empty = SearchResponse {
+        hits: Vec::new(),
+        truncated: false,
+    };
+    let mut q = messages::table
+        .inner_join(session_members::table.on(session_members::session_id.eq(messages::session_id)))
+        .inner_join(message_embeddings::table.on(message_embeddings::message_id.eq(messages::id)))
+        .filter(session_members::user_id.eq(user_id))
+        .filter(message_embeddings::model.eq(model))
+        .filter(messages::role.eq_any(["user", "assistant"]))
+        .select((messages::id, message_embeddings::embedding))
+        .into_boxed();
+    if !query.sessions.is_empty() {
+        let ids = resolve_sessions(conn, user_id, &query.sessions)?;
+        if ids.is_empty() {
+            return Ok(empty);
+        }
+        q = q.filter(messages::session_id.eq_any(ids));
+    }
+    if let Some(before) = query.before {
+        q = q.filter(messages::created_at.lt(before));
+    }
+    if let Some(after) = query.after {
+        q = q.filter(messages::created_at.ge(after));
+    }
+    if !query.tools.is_empty() {
+        q = q.filter(messages::role.eq("assistant"));
+    }
+    if query.errors_only {
+        q = q.filter(messages::role.eq("user"));
+    }
+    let candidates: Vec<(Uuid, Vec<f32>)> = q
+        .order((messages::created_at.desc(), messages::id.desc()))
+        .limit(MAX_SCANNED)
+        .load(conn)?;
+    let truncated = candidates.len() as i64 == MAX_SCANNED;
+
+    let mut scored: Vec<(Uuid, f32)> = candidates
+        .into_iter()
+        .filter_map(|(id, embedding)| Some((id, embeddings::cosine(target, &embedding)?)))
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.truncate(limit.saturating_mul(SEMANTIC_CANDIDATES_PER_HIT));
+    if scored.is_empty() {
+        return Ok(empty);
+    }
+
+    let ids: Vec<Uuid> = scored.iter().map(|(id, _)| *id).collect();
+    let rows: Vec<HitRow> = messages::table
+        .inner_join(sessions::table.on(sessions::id.eq(messages::session_id)))
+        .inner_join(session_members::table.on(session_members::session_id.eq(sessions::id)))
+        .filter(session_members::user_id.eq(user_id))
+        .filter(messages::id.eq_any(ids))
+        .select((
+            messages::id,
+            messages::session_id,
+            sessions::session_name,
+            messages::role,
+            messages::content,
+            messages::created_at,
+            messages::produced_at,
+            session_members::role,
+        ))
+        .load(conn)?;
+    let mut rows: std::collections::HashMap<Uuid, _> =
+        rows.into_iter().map(|row| (row.0, row)).collect();
+
+    let mut redactors = Redactors::default();
+    let mut hits = Vec::new();
+    for (id, score) in scored {
+        let Some((
+            message_id,
+            session_id,
+            session_name,
+            role,
+            content,
+            created_at,
+            produced_at,
+            member_role,
+        )) = rows.remove(&id)
+        else {
+            continue;
+        };
+        let Ok(mut value) = serde_json::from_str::<Value>(&content) else {
+            continue;
+        };
+        visible(&mut value, redactors.get(conn, session_id, &member_role));
+        if !query.passes_filters(&value) {
+            continue;
+        }
+        if hits.len() == limit {
+            return Ok(SearchResponse {
+                hits,
+                truncated: true,
+            });
+        }
+        hits.push(SearchHit {
+            message_id,
+            session_id,
+            session_name,
+            role,
+            created_at: produced_at.unwrap_or(created_at).and_utc().to_rfc3339(),
+            snippet: snippet(&searchable_text(&value), 0),
+            score: Some(score),
+        });
+    }
+    Ok(SearchResponse { hits, truncated })
+}
+
+/// Embedding of a session's first prompt that has text, once it's indexed
+pub fn first_prompt_embedding(
+    conn: &mut PgConnection,
+    session_id: Uuid,
+    model: &str,
+) -> QueryResult<Option<Vec<f32>>> {
+    messages::table
+        .inner_join(message_embeddings::table.on(message_embeddings::message_id.eq(messages::id)))
+        .filter(messages::session_id.eq(session_id))
+        .filter(messages::role.eq("user"))
+        .filter(message_embeddings::model.eq(model))
+        .filter(message_embeddings::embedding.ne(Vec::<f32>::new()))
+        .order(messages::created_at.asc())
+        .select(message_embeddings::embedding)
+        .first(conn)
+        .optional()
+}
+
+/// Best `limit` candidates (session, first prompt, embedding) by similarity
+/// to `target`, dropping those below `RELATED_MIN_SCORE`
+fn rank_related(
+    candidates: Vec<(Uuid, Uuid, Vec<f32>)>,
+    target: &[f32],
+    limit: usize,
+) -> Vec<(Uuid, Uuid, f32)> {
+    let mut scored: Vec<(Uuid, Uuid, f32)> = candidates
+        .into_iter()
+        .filter_map(|(session_id, message_id, embedding)| {
+            let score = embeddings::cosine(target, &embedding)?;
+            (score >= RELATED_MIN_SCORE).then_some((session_id, message_id, score))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.2.total_cmp(&a.2));
+    scored.truncate(limit);
+    scored
+}
+
+/// `user_id`'s sessions other than `exclude` whose first prompt is most like
+/// `target`, an embedding from `model`
+pub fn related_sessions(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    exclude: Uuid,
+    model: &str,
+    target: &[f32],
+    limit: usize,
+) -> QueryResult<Vec<RelatedSession>> {
+    let candidates: Vec<(Uuid, Uuid, Vec<f32>)> = messages::table
+        .inner_join(session_members::table.on(session_members::session_id.eq(messages::session_id)))
+        .inner_join(message_embeddings::table.on(message_embeddings::message_id.eq(messages::id)))
+        .filter(session_members::user_id.eq(user_id))
+        .filter(messages::session_id.ne(exclude))
+        .filter(messages::role.eq("user"))
+        .filter(message_embeddings::model.eq(model))
+        .filter(message_embeddings::embedding.ne(Vec::<f32>::new()))
+        .distinct_on(messages::session_id)
+        .order((messages::session_id, messages::created_at.asc()))
+        .select((
+            messages::session_id,
+            messages::id,
+            message_embeddings::embedding,
+        ))
+        .load(conn)?;
+    let ranked = rank_related(candidates, target, limit);
+    if ranked.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let message_ids: Vec<Uuid> = ranked.iter().map(|(_, id, _)| *id).collect();
+    type RelatedRow = (
+        Uuid,
+        String,
+        String,
+        NaiveDateTime,
+        Option<String>,
+        String,
+        String,
+    );
+    let rows: Vec<RelatedRow> = messages::table
+        .inner_join(sessions::table.on(sessions::id.eq(messages::session_id)))
+        .inner_join(session_members::table.on(session_members::session_id.eq(sessions::id)))
+        .filter(session_members::user_id.eq(user_id))
+        .filter(messages::id.eq_any(message_ids))
+        .select((
+            messages::id,
+            sessions::session_name,
+            sessions::working_directory,
+            sessions::last_activity,
+            sessions::outcome,
+            messages::content,
+            session_members::role,
+        ))
+        .load(conn)?;
+    let mut rows: std::collections::HashMap<Uuid, _> =
+        rows.into_iter().map(|row| (row.0, row)).collect();
+
+    let mut redactors = Redactors::default();
+    Ok(ranked
+        .into_iter()
+        .filter_map(|(session_id, message_id, score)| {
+            let (_, session_name, working_directory, last_activity, outcome, content, member_role) =
+                rows.remove(&message_id)?;
+            let text = serde_json::from_str::<Value>(&content)
+                .map(|mut value| {
+                    visible(&mut value, redactors.get(conn, session_id, &member_role));
+                    searchable_text(&value)
+                })
+                .unwrap_or_default();
+            Some(RelatedSession {
+                session_id,
+                session_name,
+                working_directory,
+                first_prompt: snippet(text.trim(), 0),
+                last_activity: last_activity.and_utc().to_rfc3339(),
+                outcome: outcome.as_deref().and_then(SessionOutcome::parse),
+                score,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 3, 10)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_tokenize_quotes_and_keys() {
+        let tokens = tokenize(r#"  session:"api server" fix  "two words" "#).unwrap();
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(tokens[0].key.as_deref(), Some("session"));
+        assert_eq!(tokens[0].value, "api server");
+        assert_eq!(tokens[1].value, "fix");
+        assert_eq!(tokens[2].value, "two words");
+        assert_eq!(tokens[2].raw, r#""two words""#);
+        assert_eq!(
+            tokenize(r#"tool:"Bash"#).unwrap_err(),
+            QueryError::UnterminatedQuote
+        );
+    }
+
+    #[test]
+    fn test_parse_filters() {
+        let q = SearchQuery::parse_at(
+            "migration session:API tool:Bash model:opus after:2026-03-01 before:7d is:error",
+            false,
+            now(),
+        )
+        .unwrap();
+        assert_eq!(q.terms.len(), 1);
+        assert_eq!(q.prefilter, vec!["migration"]);
+        assert_eq!(q.sessions, vec!["api"]);
+        assert_eq!(q.tools, vec!["bash"]);
+        assert_eq!(q.models, vec!["opus"]);
+        assert_eq!(
+            q.after,
+            NaiveDate::from_ymd_opt(2026, 3, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+        );
+        assert_eq!(q.before, Some(now() - Duration::days(7)));
+        assert!(q.errors_only);
+
+        // Filters alone are a valid query; nothing at all isn't
+        assert!(SearchQuery::parse_at("is:error", false, now()).is_ok());
+        assert_eq!(
+            SearchQuery::parse_at("   ", false, now()).unwrap_err(),
+            QueryError::Empty
+        );
+        assert!(matches!(
+            SearchQuery::parse_at("before:yesterday", false, now()),
+            Err(QueryError::InvalidDate(_))
+        ));
+    }
+
+    #[test]
+    fn test_within_keeps_tighter_bounds() {
+        let since = now() - Duration::hours(1);
+        let q = SearchQuery::parse_at("deploy", false, now())
+            .unwrap()
+            .within(since, now());
+        assert_eq!((q.after, q.before), (Some(since), Some(now())));
+
+        // An earlier `before:` in the query itself wins
+        let q = SearchQuery::parse_at("deploy before:2026-03-10", false, now())
+            .unwrap()
+            .within(since, now());
+        assert_eq!(
+            q.before,
+            NaiveDate::from_ymd_opt(2026, 3, 10)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+        );
+        assert_eq!(q.after, Some(since));
+    }
+
+    #[test]
+    fn test_rank_related() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let candidates = vec![
+            (a, Uuid::new_v4(), vec![0.6, 0.8]),
+            (b, Uuid::new_v4(), vec![1.0, 0.0]),
+            // Unrelated, and an empty vector from a prompt with no text
+            (c, Uuid::new_v4(), vec![0.0, 1.0]),
+            (c, Uuid::new_v4(), vec![]),
+        ];
+        let ranked = rank_related(candidates.clone(), &[1.0, 0.0], 5);
+        let sessions: Vec<Uuid> = ranked.iter().map(|(id, _, _)| *id).collect();
+        assert_eq!(sessions, vec![b, a]);
+        assert!((ranked[1].2 - 0.6).abs() < 1e-6);
+
+        assert_eq!(rank_related(candidates, &[1.0, 0.0], 1).len(), 1);
+    }
+
+    #[test]
+    fn test_text_without_filters() {
+        let q = SearchQuery::parse_at(r#"flaky "login test" tool:Bash after:7d"#, false, now())
+            .unwrap();
+        assert_eq!(q.text(), "flaky login test");
+        let q = SearchQuery::parse_at("is:error", false, now()).unwrap();
+        assert_eq!(q.text(), "");
+    }
+
+    #[test]
+    fn test_unknown_keys_are_text() {
+        let q = SearchQuery::parse_at("http://localhost:8080", false, now()).unwrap();
+        assert!(q.sessions.is_empty());
+        let hit = json!({"type": "result", "result": "Serving on http://localhost:8080"});
+        assert!(q.matches(&hit).is_some());
+    }
+
+    #[test]
+    fn test_regex_mode() {
+        let q = SearchQuery::parse_at(r"fn \w+_test tool:edit", true, now()).unwrap();
+        assert_eq!(q.terms.len(), 1);
+        assert!(q.prefilter.is_empty());
+        let edit = json!({"type": "assistant", "message": {"content": [
+            {"type": "tool_use", "name": "Edit", "input": {"new_string": "fn parse_test() {}"}}
+        ]}});
+        assert!(q.matches(&edit).is_some());
+
+        assert!(matches!(
+            SearchQuery::parse_at("fn (", true, now()),
+            Err(QueryError::InvalidRegex(_))
+        ));
+        // The same text is literal outside regex mode
+        let literal = SearchQuery::parse_at("fn (", false, now()).unwrap();
+        assert!(literal
+            .matches(&json!({"type": "result", "result": "call fn (x)"}))
+            .is_some());
+    }
+
+    #[test]
+    fn test_matching_filters() {
+        let q = SearchQuery::parse_at("is:error", false, now()).unwrap();
+        assert!(q
+            .matches(&json!({"type": "result", "is_error": true, "result": "boom"}))
+            .is_some());
+        assert!(q
+            .matches(&json!({"type": "result", "is_error": false, "result": "ok"}))
+            .is_none());
+        assert!(q
+            .matches(&json!({"type": "user", "message": {"content": [
+                {"type": "tool_result", "is_error": true, "content": "denied"}
+            ]}}))
+            .is_some());
+
+        let q = SearchQuery::parse_at("model:sonnet tests", false, now()).unwrap();
+        let turn = json!({"type": "assistant", "message": {
+            "model": "claude-sonnet-4", "content": [{"type": "text", "text": "Running tests"}]
+        }});
+        assert!(q.matches(&turn).is_some());
+        let other = json!({"type": "assistant", "message": {
+            "model": "claude-opus-4", "content": [{"type": "text", "text": "Running tests"}]
+        }});
+        assert!(q.matches(&other).is_none());
+
+        // Tool output isn't searched
+        let q = SearchQuery::parse_at("secret", false, now()).unwrap();
+        assert!(q
+            .matches(&json!({"type": "user", "message": {"content": [
+                {"type": "tool_result", "content": "secret"}
+            ]}}))
+            .is_none());
+    }
+
+    #[test]
+    fn test_snippet_and_prefilter() {
+        let text = format!("{}needle{}", "a ".repeat(100), "b ".repeat(100));
+        let s = snippet(&text, text.find("needle").unwrap());
+        assert!(s.starts_with('…') && s.ends_with('…'));
+        assert!(s.contains("needle"));
+        assert_eq!(snippet("short\n\ntext", 0), "short text");
+
+        assert!(prefilterable("cargo test"));
+        assert!(!prefilterable(r#"say "hi""#));
+        assert!(!prefilterable("café"));
+        assert_eq!(like_escape("50%_a\\b"), "50\\%\\_a\\\\b");
+    }
+
+    #[test]
+    fn test_viewer_snippet_is_redacted() {
+        let write = json!({"type": "assistant", "message": {"content": [
+            {"type": "tool_use", "id": "t1", "name": "Write",
+             "input": {"file_path": "notes.txt", "content": "password hunter2"}}
+        ]}});
+        let policy = shared::RedactionPolicy {
+            whole_session: false,
+            tools: vec!["Write".to_string()],
+        };
+        let redactor = Redactor::new(policy);
+
+        let mut seen = write.clone();
+        visible(&mut seen, Some(&redactor));
+        let secret = SearchQuery::parse_at("hunter2", false, now()).unwrap();
+        assert!(secret.matches(&seen).is_none());
+        let path = SearchQuery::parse_at("notes.txt", false, now()).unwrap();
+        let snippet = path.matches(&seen).unwrap();
+        assert!(snippet.contains(shared::redaction::REDACTED));
+        assert!(!snippet.contains("hunter2"));
+
+        // Editors get no redactor and see the input
+        let mut unredacted = write;
+        visible(&mut unredacted, None);
+        assert!(secret.matches(&unredacted).unwrap().contains("hunter2"));
+    }
+}
@@ -0,0 +1,262 @@
+//! Slack notifications
+//!
+//! When `SLACK_BOT_TOKEN` and `SLACK_CHANNEL` are set, notifications routed
+//! to Slack are posted to that channel: by default finished turns, with
+//! their result summary and the session's cost, and permission requests
+//! that have waited a while. Each post links back to the session in the web
+//! UI. The bot needs the `chat:write` scope and must be invited to the
+//! channel.
+
+use crate::db::DbPool;
+use crate::notifier::{self, Notification, NotificationKind, Notifier, Recipient};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use shared::NotificationChannel;
+
+/// Result summaries are cut to this many characters
+const MAX_SUMMARY_CHARS: usize = 500;
+
+#[derive(Deserialize)]
+struct SlackResponse {
+    ok: bool,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+const POST_MESSAGE_URL: &str = "https://slack.com/api/chat.postMessage";
+
+/// Where to post, read from `SLACK_*` variables
+#[derive(Debug, Clone)]
+pub struct Slack {
+    token: String,
+    channel: String,
+    public_url: String,
+    client: reqwest::Client,
+}
+
+impl Slack {
+    /// The configured integration, `None` when Slack is off
+    pub fn from_env(public_url: &str) -> Result<Option<Self>, String> {
+        Self::parse(|name| std::env::var(name).ok(), public_url)
+    }
+
+    fn parse(
+        var: impl Fn(&str) -> Option<String>,
+        public_url: &str,
+    ) -> Result<Option<Self>, String> {
+        let var = |name: &str| {
+            var(name)
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+
+        let Some(token) = var("SLACK_BOT_TOKEN") else {
+            return Ok(None);
+        };
+        let channel = var("SLACK_CHANNEL").ok_or("SLACK_CHANNEL must be set")?;
+
+        Ok(Some(Self {
+            token,
+            channel,
+            public_url: public_url.to_string(),
+            client: reqwest::Client::new(),
+        }))
+    }
+
+    async fn send(&self, text: &str) -> anyhow::Result<()> {
+        let response: SlackResponse = self
+            .client
+            .post(POST_MESSAGE_URL)
+            .bearer_auth(&self.token)
+            .json(&json!({
+                "channel": self.channel,
+                "text": text,
+                "unfurl_links": false,
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if !response.ok {
+            anyhow::bail!(response
+                .error
+                .unwrap_or_else(|| "unknown error".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Notifier for Slack {
+    fn channel(&self) -> NotificationChannel {
+        NotificationChannel::Slack
+    }
+
+    /// One post to the shared channel, however many recipients want it
+    async fn deliver(
+        &self,
+        _db_pool: &DbPool,
+        notification: &Notification,
+        _recipients: &[Recipient],
+    ) -> anyhow::Result<()> {
+        let link = notifier::session_link(&self.public_url, notification.session_id);
+        self.send(&text(notification, &link)).await
+    }
+}
+
+/// Slack's mrkdwn treats these as control characters
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn turn_finished_text(label: &str, result: &Value, link: &str) -> String {
+    let failed = result.get("is_error").and_then(|e| e.as_bool()) == Some(true);
+    let mut text = format!(
+        "{} *{}* {}",
+        if failed { ":x:" } else { ":white_check_mark:" },
+        escape(label),
+        if failed { "failed" } else { "finished" }
+    );
+    if let Some(cost) = result.get("total_cost_usd").and_then(|c| c.as_f64()) {
+        text.push_str(&format!(" (${:.2} so far)", cost));
+    }
+    if let Some(summary) = result
+        .get("result")
+        .and_then(|r| r.as_str())
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+    {
+        text.push_str(&format!(
+            "\n>{}",
+            escape(&notifier::truncate(summary, MAX_SUMMARY_CHARS)).replace('\n', "\n>")
+        ));
+    }
+    text.push_str(&format!("\n<{}|Open session>", link));
+    text
+}
+
+/// The post for a notification
+fn text(notification: &Notification, link: &str) -> String {
+    let label = &notification.label;
+    match &notification.kind {
+        NotificationKind::TurnFinished { result } => turn_finished_text(label, result, link),
+        NotificationKind::PermissionRequested { tool_name } => format!(
+            ":raising_hand: *{}* is asking for permission to use `{}`\n<{}|Open session>",
+            escape(label),
+            escape(tool_name),
+            link
+        ),
+        NotificationKind::PermissionWaiting { tool_name, minutes } => {
+            permission_waiting_text(label, tool_name, *minutes, link)
+        }
+        NotificationKind::PermissionEscalated { tool_name, minutes } => format!(
+            ":rotating_light: *{}* has been waiting {} minutes for permission to use `{}` and was escalated\n<{}|Open session>",
+            escape(label),
+            minutes,
+            escape(tool_name),
+            link
+        ),
+        NotificationKind::DailyDigest {
+            day,
+            turns,
+            failed_turns,
+            cost_usd,
+        } => format!(
+            ":calendar: *{}* on {}: {}\n<{}|Open session>",
+            escape(label),
+            day,
+            notifier::digest_summary(*turns, *failed_turns, *cost_usd),
+            link
+        ),
+    }
+}
+
+fn permission_waiting_text(label: &str, tool_name: &str, minutes: i64, link: &str) -> String {
+    format!(
+        ":hourglass: *{}* has been waiting {} minute{} for permission to use `{}`\n<{}|Open session>",
+        escape(label),
+        minutes,
+        if minutes == 1 { "" } else { "s" },
+        escape(tool_name),
+        link
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn parse(vars: &[(&str, &str)]) -> Result<Option<Slack>, String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Slack::parse(
+            |name| vars.get(name).cloned(),
+            "https://portal.example.com/",
+        )
+    }
+
+    #[test]
+    fn test_config() {
+        assert!(parse(&[]).unwrap().is_none());
+        assert!(parse(&[("SLACK_BOT_TOKEN", "xoxb-1")]).is_err());
+
+        let slack = parse(&[("SLACK_BOT_TOKEN", "xoxb-1"), ("SLACK_CHANNEL", "#agents")])
+            .unwrap()
+            .unwrap();
+        assert_eq!(slack.channel, "#agents");
+    }
+
+    #[test]
+    fn test_turn_finished_text() {
+        let result = json!({
+            "type": "result",
+            "is_error": false,
+            "result": "Fixed <the> bug\nTests pass",
+            "total_cost_usd": 0.4212,
+        });
+        assert_eq!(
+            turn_finished_text("api (laptop)", &result, "https://p/x"),
+            ":white_check_mark: *api (laptop)* finished ($0.42 so far)\n>Fixed &lt;the&gt; bug\n>Tests pass\n<https://p/x|Open session>"
+        );
+
+        let failed = json!({"type": "result", "is_error": true});
+        assert_eq!(
+            turn_finished_text("api", &failed, "https://p/x"),
+            ":x: *api* failed\n<https://p/x|Open session>"
+        );
+
+        let long = json!({"result": "é".repeat(MAX_SUMMARY_CHARS + 10)});
+        let text = turn_finished_text("api", &long, "https://p/x");
+        assert!(text.contains(&format!("{}…", "é".repeat(MAX_SUMMARY_CHARS))));
+    }
+
+    #[test]
+    fn test_text() {
+        let notification = Notification {
+            session_id: uuid::Uuid::nil(),
+            label: "api".to_string(),
+            kind: NotificationKind::PermissionRequested {
+                tool_name: "Bash".to_string(),
+            },
+        };
+        assert_eq!(
+            text(&notification, "https://p/x"),
+            ":raising_hand: *api* is asking for permission to use `Bash`\n<https://p/x|Open session>"
+        );
+    }
+
+    #[test]
+    fn test_permission_waiting_text() {
+        assert_eq!(
+            permission_waiting_text("api", "Bash", 1, "https://p/x"),
+            ":hourglass: *api* has been waiting 1 minute for permission to use `Bash`\n<https://p/x|Open session>"
+        );
+    }
+}
@@ -1,7 +1,10 @@
-//! Google Speech-to-Text Service
+//! Google Cloud Speech-to-Text provider
 //!
-//! Provides streaming speech recognition using Google Cloud Speech-to-Text API.
+//! Streams audio to Google's streaming recognize API with the service account
+//! in `GOOGLE_APPLICATION_CREDENTIALS`.
 
+use super::{AudioEncoding, SpeechConfig, SpeechProvider, SpeechStream, TranscriptionResult};
+use async_trait::async_trait;
 use google_cognitive_apis::api::grpc::google::cloud::speechtotext::v1::{
     streaming_recognize_request::StreamingRequest, RecognitionConfig, StreamingRecognitionConfig,
     StreamingRecognizeRequest,
@@ -10,13 +13,6 @@ use google_cognitive_apis::speechtotext::recognizer::Recognizer;
 use tokio::sync::mpsc;
 use tracing::{error, info, warn};
 
-/// Audio encoding types supported by the speech service
-#[derive(Debug, Clone, Copy)]
-pub enum AudioEncoding {
-    /// Linear PCM 16-bit signed little-endian
-    Linear16,
-}
-
 impl From<AudioEncoding> for i32 {
     fn from(encoding: AudioEncoding) -> i32 {
         match encoding {
@@ -25,102 +21,37 @@ impl From<AudioEncoding> for i32 {
     }
 }
 
-/// Configuration for the speech recognition service
-#[derive(Debug, Clone)]
-pub struct SpeechConfig {
-    /// Path to Google Cloud service account credentials JSON file
-    pub credentials_path: Option<String>,
-    /// Sample rate in Hz (default: 16000)
-    pub sample_rate_hertz: i32,
-    /// Language code (default: "en-US")
-    pub language_code: String,
-    /// Audio encoding (default: Linear16)
-    pub encoding: AudioEncoding,
-    /// Enable interim results during recognition
-    pub interim_results: bool,
-    /// Enable single utterance mode - auto-end recognition when speaker stops
-    pub single_utterance: bool,
+/// Speech-to-text using Google Cloud
+pub struct GoogleSpeech {
+    /// Path to the service account credentials JSON file
+    credentials_path: String,
 }
 
-impl Default for SpeechConfig {
-    fn default() -> Self {
-        Self {
-            credentials_path: None,
-            sample_rate_hertz: 16000,
-            language_code: "en-US".to_string(),
-            encoding: AudioEncoding::Linear16,
-            interim_results: true,
-            single_utterance: true, // Auto-end when speaker stops, sends final result immediately
-        }
+impl GoogleSpeech {
+    pub fn new(credentials_path: String) -> Self {
+        Self { credentials_path }
     }
 }
 
-/// Result from speech recognition
-#[derive(Debug, Clone)]
-pub struct TranscriptionResult {
-    /// The transcribed text
-    pub transcript: String,
-    /// Whether this is a final result (vs interim)
-    pub is_final: bool,
-    /// Confidence score (0.0 to 1.0)
-    pub confidence: f32,
-}
-
-/// Speech-to-text service using Google Cloud
-pub struct SpeechService {
-    config: SpeechConfig,
-}
-
-impl SpeechService {
-    /// Create a new speech service with the given configuration
-    pub fn new(config: SpeechConfig) -> Self {
-        Self { config }
+#[async_trait]
+impl SpeechProvider for GoogleSpeech {
+    fn name(&self) -> &'static str {
+        "google"
     }
 
-    /// Create a new speech service with default configuration
-    #[allow(dead_code)]
-    pub fn with_defaults() -> Self {
-        Self::new(SpeechConfig::default())
-    }
-
-    /// Start a streaming recognition session
-    ///
-    /// Returns a tuple of:
-    /// - A sender to push audio data (PCM16 bytes)
-    /// - A receiver to get transcription results
-    ///
-    /// The session ends when the audio sender is dropped.
-    pub async fn start_streaming(
-        &self,
-        language_code: Option<String>,
-    ) -> Result<
-        (
-            mpsc::UnboundedSender<Vec<u8>>,
-            mpsc::UnboundedReceiver<TranscriptionResult>,
-        ),
-        String,
-    > {
-        let credentials_path = self
-            .config
-            .credentials_path
-            .clone()
-            .ok_or_else(|| "Google Cloud credentials not configured".to_string())?;
-
-        let language = language_code.unwrap_or_else(|| self.config.language_code.clone());
-
-        // Create recognition config
+    async fn start_streaming(&self, config: SpeechConfig) -> Result<SpeechStream, String> {
         let recognition_config = RecognitionConfig {
-            encoding: self.config.encoding.into(),
-            sample_rate_hertz: self.config.sample_rate_hertz,
-            language_code: language,
+            encoding: config.encoding.into(),
+            sample_rate_hertz: config.sample_rate_hertz,
+            language_code: config.language_code,
             enable_automatic_punctuation: true,
             ..Default::default()
         };
 
         let streaming_config = StreamingRecognitionConfig {
             config: Some(recognition_config),
-            interim_results: self.config.interim_results,
-            single_utterance: self.config.single_utterance,
+            interim_results: config.interim_results,
+            single_utterance: config.single_utterance,
         };
 
         // Create channels for audio input and transcription output
@@ -128,7 +59,7 @@ impl SpeechService {
         let (result_tx, result_rx) = mpsc::unbounded_channel::<TranscriptionResult>();
 
         // Spawn the recognition task
-        let credentials = credentials_path.clone();
+        let credentials = self.credentials_path.clone();
         tokio::spawn(async move {
             match run_recognition(credentials, streaming_config, audio_rx, result_tx).await {
                 Ok(()) => info!("Speech recognition session completed"),
@@ -136,7 +67,10 @@ impl SpeechService {
             }
         });
 
-        Ok((audio_tx, result_rx))
+        Ok(SpeechStream {
+            audio: audio_tx,
+            transcripts: result_rx,
+        })
     }
 }
 
@@ -235,19 +169,3 @@ async fn run_recognition(
 
     Ok(())
 }
-
-/// Check if Google Cloud credentials are available
-#[allow(dead_code)]
-pub fn credentials_available(path: Option<&str>) -> bool {
-    match path {
-        Some(p) => std::path::Path::new(p).exists(),
-        None => {
-            // Check for application default credentials
-            if let Ok(adc_path) = std::env::var("GOOGLE_APPLICATION_CREDENTIALS") {
-                std::path::Path::new(&adc_path).exists()
-            } else {
-                false
-            }
-        }
-    }
-}
@@ -0,0 +1,332 @@
+//! Offline speech recognition with whisper.cpp
+//!
+//! Built with `--features local-speech`. Segments of speech are transcribed
+//! on the backend's own CPU (or GPU, with whisper-rs's GPU features), so
+//! voice input needs no cloud credentials. Models are ggml files from the
+//! whisper.cpp repository on Hugging Face, kept in `SPEECH_MODEL_DIR`: a
+//! missing model is downloaded the first time someone speaks, or ahead of
+//! time with `speech-models --download`.
+//!
+//! `SPEECH_QUALITY` trades latency for accuracy by picking the default model
+//! and decoding strategy; `SPEECH_MODEL` overrides the model.
+
+use super::{segment, SpeechConfig, SpeechProvider, SpeechStream, TranscriptionResult};
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, OnceCell};
+use tracing::{error, info};
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+/// Models whisper.cpp publishes, smallest first
+pub const MODELS: &[&str] = &[
+    "tiny",
+    "tiny.en",
+    "base",
+    "base.en",
+    "small",
+    "small.en",
+    "medium",
+    "medium.en",
+    "large-v3-turbo",
+    "large-v3",
+];
+
+const MODEL_URL: &str = "https://huggingface.co/ggerganov/whisper.cpp/resolve/main";
+
+pub const DEFAULT_MODEL_DIR: &str = "speech-models";
+
+/// Whisper models only take 16kHz audio
+const SAMPLE_RATE: i32 = 16000;
+
+/// Latency against accuracy, from `SPEECH_QUALITY`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Quality {
+    /// Smallest model, greedy decoding
+    Fast,
+    #[default]
+    Balanced,
+    /// Larger model and beam search; slow without a GPU
+    Accurate,
+}
+
+impl Quality {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "fast" => Some(Self::Fast),
+            "balanced" => Some(Self::Balanced),
+            "accurate" => Some(Self::Accurate),
+            _ => None,
+        }
+    }
+
+    /// Model used when `SPEECH_MODEL` isn't set
+    pub fn default_model(self) -> &'static str {
+        match self {
+            Self::Fast => "tiny",
+            Self::Balanced => "base",
+            Self::Accurate => "small",
+        }
+    }
+
+    fn strategy(self) -> SamplingStrategy {
+        match self {
+            Self::Fast | Self::Balanced => SamplingStrategy::Greedy { best_of: 1 },
+            Self::Accurate => SamplingStrategy::BeamSearch {
+                beam_size: 5,
+                patience: -1.0,
+            },
+        }
+    }
+}
+
+/// Downloaded models, one `ggml-<name>.bin` file each
+#[derive(Debug, Clone)]
+pub struct ModelStore {
+    dir: PathBuf,
+}
+
+impl ModelStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Where a model lives, whether or not it's downloaded
+    pub fn path(&self, model: &str) -> anyhow::Result<PathBuf> {
+        if !MODELS.contains(&model) {
+            bail!(
+                "Unknown speech model {:?}; expected one of {}",
+                model,
+                MODELS.join(", ")
+            );
+        }
+        Ok(self.dir.join(format!("ggml-{}.bin", model)))
+    }
+
+    /// Downloaded models with their sizes in bytes
+    pub fn downloaded(&self) -> Vec<(&'static str, u64)> {
+        MODELS
+            .iter()
+            .filter_map(|model| {
+                let size = std::fs::metadata(self.path(model).ok()?).ok()?.len();
+                Some((*model, size))
+            })
+            .collect()
+    }
+
+    /// Download a model unless it's already here, returning its path
+    pub async fn download(&self, model: &str) -> anyhow::Result<PathBuf> {
+        let path = self.path(model)?;
+        if path.exists() {
+            return Ok(path);
+        }
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .with_context(|| format!("creating {}", self.dir.display()))?;
+
+        let url = format!("{}/ggml-{}.bin", MODEL_URL, model);
+        info!("Downloading speech model {} from {}", model, url);
+        let mut response = reqwest::get(&url)
+            .await
+            .and_then(|r| r.error_for_status())
+            .with_context(|| format!("downloading {}", url))?;
+
+        // Written under another name first so a cut-off download isn't loaded
+        let partial = path.with_extension("bin.part");
+        let mut file = tokio::fs::File::create(&partial)
+            .await
+            .with_context(|| format!("creating {}", partial.display()))?;
+        while let Some(chunk) = response.chunk().await.context("downloading model")? {
+            file.write_all(&chunk).await.context("writing model")?;
+        }
+        file.flush().await?;
+        tokio::fs::rename(&partial, &path).await?;
+        info!("Downloaded speech model to {}", path.display());
+        Ok(path)
+    }
+
+    pub fn remove(&self, model: &str) -> anyhow::Result<()> {
+        let path = self.path(model)?;
+        std::fs::remove_file(&path).with_context(|| format!("removing {}", path.display()))
+    }
+}
+
+/// Speech-to-text on the backend with whisper.cpp
+pub struct LocalSpeech {
+    store: ModelStore,
+    model: String,
+    quality: Quality,
+    /// Loaded on first use, then shared by every stream
+    context: OnceCell<Arc<WhisperContext>>,
+}
+
+impl LocalSpeech {
+    pub fn new(store: ModelStore, model: String, quality: Quality) -> Result<Self, String> {
+        store.path(&model).map_err(|e| e.to_string())?;
+        Ok(Self {
+            store,
+            model,
+            quality,
+            context: OnceCell::new(),
+        })
+    }
+
+    async fn context(&self) -> anyhow::Result<Arc<WhisperContext>> {
+        self.context
+            .get_or_try_init(|| async {
+                let path = self.store.download(&self.model).await?;
+                let context = tokio::task::spawn_blocking(move || {
+                    let path = path.to_string_lossy();
+                    WhisperContext::new_with_params(&path, WhisperContextParameters::default())
+                })
+                .await?
+                .context("loading speech model")?;
+                info!("Loaded speech model {}", self.model);
+                Ok::<_, anyhow::Error>(Arc::new(context))
+            })
+            .await
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl SpeechProvider for LocalSpeech {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn detects_language(&self) -> bool {
+        true
+    }
+
+    async fn start_streaming(&self, config: SpeechConfig) -> Result<SpeechStream, String> {
+        if config.sample_rate_hertz != SAMPLE_RATE {
+            return Err(format!("Local speech needs {}Hz audio", SAMPLE_RATE));
+        }
+        let context = self.context().await.map_err(|e| format!("{:#}", e))?;
+
+        let (audio_tx, audio_rx) = mpsc::unbounded_channel::<Vec<u8>>();
+        let (result_tx, result_rx) = mpsc::unbounded_channel::<TranscriptionResult>();
+
+        let quality = self.quality;
+        tokio::spawn(async move {
+            // Whisper takes ISO-639-1 codes: "en", not "en-US"; "auto"
+            // passes through and has whisper.cpp detect it
+            let language = config
+                .language_code
+                .split('-')
+                .next()
+                .filter(|l| !l.is_empty())
+                .unwrap_or("auto")
+                .to_lowercase();
+            let transcribe_segment = |pcm: Vec<u8>| {
+                let context = context.clone();
+                let language = language.clone();
+                async move {
+                    tokio::task::spawn_blocking(move || {
+                        transcribe(&context, &pcm, &language, quality)
+                    })
+                    .await
+                    .map_err(|e| e.to_string())?
+                }
+            };
+            match segment::run(&config, audio_rx, result_tx, transcribe_segment).await {
+                Ok(()) => info!("Speech recognition session completed"),
+                Err(e) => error!("Speech recognition error: {}", e),
+            }
+        });
+
+        Ok(SpeechStream {
+            audio: audio_tx,
+            transcripts: result_rx,
+        })
+    }
+}
+
+/// Text of one segment of PCM16 audio
+fn transcribe(
+    context: &WhisperContext,
+    pcm: &[u8],
+    language: &str,
+    quality: Quality,
+) -> Result<String, String> {
+    let audio: Vec<f32> = segment::samples(pcm)
+        .map(|s| f32::from(s) / 32768.0)
+        .collect();
+
+    let mut params = FullParams::new(quality.strategy());
+    params.set_language(Some(language));
+    params.set_no_context(true);
+    params.set_suppress_blank(true);
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+    params.set_print_special(false);
+    params.set_print_timestamps(false);
+
+    let mut state = context.create_state().map_err(|e| e.to_string())?;
+    state.full(params, &audio).map_err(|e| e.to_string())?;
+    let segments = state.full_n_segments().map_err(|e| e.to_string())?;
+    let mut text = String::new();
+    for i in 0..segments {
+        text.push_str(&state.full_get_segment_text(i).map_err(|e| e.to_string())?);
+    }
+    Ok(text.trim().to_string())
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ModelArgs {
+    /// Download a model (e.g. base.en) into SPEECH_MODEL_DIR
+    #[arg(long, value_name = "MODEL")]
+    pub download: Option<String>,
+
+    /// Delete a downloaded model
+    #[arg(long, value_name = "MODEL")]
+    pub remove: Option<String>,
+}
+
+/// `speech-models`: list the models, or download or remove one
+pub async fn run_models_command(args: ModelArgs) -> anyhow::Result<()> {
+    let store = ModelStore::new(
+        std::env::var("SPEECH_MODEL_DIR").unwrap_or_else(|_| DEFAULT_MODEL_DIR.to_string()),
+    );
+    if let Some(model) = args.download {
+        let path = store.download(&model).await?;
+        println!("Downloaded {} to {}", model, path.display());
+    } else if let Some(model) = args.remove {
+        store.remove(&model)?;
+        println!("Removed {}", model);
+    } else {
+        let downloaded = store.downloaded();
+        for model in MODELS {
+            match downloaded.iter().find(|(name, _)| name == model) {
+                Some((_, size)) => println!("{:<16} {} MB", model, size / 1_000_000),
+                None => println!("{:<16} not downloaded", model),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_path() {
+        let store = ModelStore::new("/models");
+        assert_eq!(
+            store.path("base.en").unwrap(),
+            PathBuf::from("/models/ggml-base.en.bin")
+        );
+        assert!(store.path("../etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_quality() {
+        assert_eq!(Quality::parse(" Fast"), Some(Quality::Fast));
+        assert_eq!(Quality::parse("best"), None);
+        assert_eq!(Quality::default().default_model(), "base");
+    }
+}
@@ -0,0 +1,201 @@
+//! Speech-to-text for voice input
+//!
+//! Engines implement [`SpeechProvider`]: the voice WebSocket handler starts a
+//! [`SpeechStream`] per utterance, pushes PCM16 audio chunks into it, and
+//! forwards the interim and final transcripts that come back. Which engine
+//! runs is chosen with `SPEECH_PROVIDER`; adding one means implementing the
+//! trait here, without touching the handlers.
+
+mod google;
+#[cfg(feature = "local-speech")]
+mod local;
+mod segment;
+mod whisper;
+
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+#[cfg(feature = "local-speech")]
+pub use local::{run_models_command, ModelArgs};
+
+/// Audio encoding types supported by the speech service
+#[derive(Debug, Clone, Copy)]
+pub enum AudioEncoding {
+    /// Linear PCM 16-bit signed little-endian
+    Linear16,
+}
+
+/// Configuration for one recognition stream
+#[derive(Debug, Clone)]
+pub struct SpeechConfig {
+    /// Sample rate in Hz (default: 16000)
+    pub sample_rate_hertz: i32,
+    /// Language code (default: "en-US"), or "auto" for providers that
+    /// [detect the language](SpeechProvider::detects_language)
+    pub language_code: String,
+    /// Audio encoding (default: Linear16)
+    pub encoding: AudioEncoding,
+    /// Enable interim results during recognition
+    pub interim_results: bool,
+    /// Enable single utterance mode - auto-end recognition when speaker stops
+    pub single_utterance: bool,
+}
+
+impl Default for SpeechConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate_hertz: 16000,
+            language_code: shared::voice::DEFAULT_LANGUAGE.to_string(),
+            encoding: AudioEncoding::Linear16,
+            interim_results: true,
+            single_utterance: true, // Auto-end when speaker stops, sends final result immediately
+        }
+    }
+}
+
+/// Result from speech recognition
+#[derive(Debug, Clone)]
+pub struct TranscriptionResult {
+    /// The transcribed text
+    pub transcript: String,
+    /// Whether this is a final result (vs interim)
+    pub is_final: bool,
+    /// Confidence score (0.0 to 1.0)
+    pub confidence: f32,
+}
+
+/// A running recognition: audio chunks in, transcripts out
+///
+/// Dropping `audio` ends the audio stream; `transcripts` closes once the
+/// engine has sent its last result, including when it detects the end of
+/// an utterance on its own.
+pub struct SpeechStream {
+    pub audio: mpsc::UnboundedSender<Vec<u8>>,
+    pub transcripts: mpsc::UnboundedReceiver<TranscriptionResult>,
+}
+
+/// A speech-to-text engine
+#[async_trait]
+pub trait SpeechProvider: Send + Sync {
+    /// Name for logs, e.g. "google"
+    fn name(&self) -> &'static str;
+
+    /// Whether it accepts "auto" as the language and identifies it itself
+    fn detects_language(&self) -> bool {
+        false
+    }
+
+    /// Start recognizing a stream of audio
+    async fn start_streaming(&self, config: SpeechConfig) -> Result<SpeechStream, String>;
+}
+
+/// The configured provider, `None` when voice input is off
+///
+/// `SPEECH_PROVIDER` picks the engine: `google`, `whisper` for the OpenAI
+/// audio API (`SPEECH_API_KEY`, plus `SPEECH_URL` and `SPEECH_MODEL` for
+/// compatible servers), or `local` for whisper.cpp on this machine
+/// (`SPEECH_QUALITY`, `SPEECH_MODEL`, `SPEECH_MODEL_DIR`). When unset,
+/// Google is used if `GOOGLE_APPLICATION_CREDENTIALS` is.
+pub fn from_env() -> Result<Option<Arc<dyn SpeechProvider>>, String> {
+    parse(|name| std::env::var(name).ok())
+}
+
+fn parse(var: impl Fn(&str) -> Option<String>) -> Result<Option<Arc<dyn SpeechProvider>>, String> {
+    let var = |name: &str| var(name).filter(|v| !v.trim().is_empty());
+    let credentials = var("GOOGLE_APPLICATION_CREDENTIALS");
+
+    let kind = match var("SPEECH_PROVIDER") {
+        Some(kind) => kind.trim().to_lowercase(),
+        None if credentials.is_some() => "google".to_string(),
+        None => return Ok(None),
+    };
+    match kind.as_str() {
+        "google" => {
+            let credentials = credentials
+                .ok_or_else(|| "GOOGLE_APPLICATION_CREDENTIALS must be set".to_string())?;
+            Ok(Some(Arc::new(google::GoogleSpeech::new(credentials))))
+        }
+        "whisper" => {
+            let api_key = var("SPEECH_API_KEY");
+            let url = var("SPEECH_URL");
+            if url.is_none() && api_key.is_none() {
+                return Err("SPEECH_API_KEY must be set".to_string());
+            }
+            Ok(Some(Arc::new(whisper::WhisperSpeech::new(
+                url.unwrap_or_else(|| whisper::OPENAI_URL.to_string()),
+                api_key,
+                var("SPEECH_MODEL").unwrap_or_else(|| whisper::OPENAI_MODEL.to_string()),
+            ))))
+        }
+        #[cfg(feature = "local-speech")]
+        "local" => {
+            let quality = match var("SPEECH_QUALITY") {
+                Some(quality) => local::Quality::parse(&quality)
+                    .ok_or_else(|| format!("Unknown SPEECH_QUALITY {:?}", quality))?,
+                None => local::Quality::default(),
+            };
+            let store = local::ModelStore::new(
+                var("SPEECH_MODEL_DIR").unwrap_or_else(|| local::DEFAULT_MODEL_DIR.to_string()),
+            );
+            let model = var("SPEECH_MODEL").unwrap_or_else(|| quality.default_model().to_string());
+            Ok(Some(Arc::new(local::LocalSpeech::new(
+                store, model, quality,
+            )?)))
+        }
+        #[cfg(not(feature = "local-speech"))]
+        "local" => Err(
+            "SPEECH_PROVIDER=local needs a backend built with --features local-speech".to_string(),
+        ),
+        "none" => Ok(None),
+        other => Err(format!("Unknown SPEECH_PROVIDER {:?}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn provider(vars: &[(&str, &str)]) -> Result<Option<&'static str>, String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        parse(|name| vars.get(name).cloned()).map(|p| p.map(|p| p.name()))
+    }
+
+    #[test]
+    fn test_provider_config() {
+        assert_eq!(provider(&[]), Ok(None));
+        assert_eq!(
+            provider(&[("GOOGLE_APPLICATION_CREDENTIALS", "/sa.json")]),
+            Ok(Some("google"))
+        );
+        assert_eq!(
+            provider(&[
+                ("SPEECH_PROVIDER", "none"),
+                ("GOOGLE_APPLICATION_CREDENTIALS", "/sa.json"),
+            ]),
+            Ok(None)
+        );
+        assert_eq!(
+            provider(&[
+                ("SPEECH_PROVIDER", "Whisper"),
+                ("SPEECH_API_KEY", "sk-test")
+            ]),
+            Ok(Some("whisper"))
+        );
+        // A local server needs no key
+        assert_eq!(
+            provider(&[
+                ("SPEECH_PROVIDER", "whisper"),
+                ("SPEECH_URL", "http://localhost:8080/inference"),
+            ]),
+            Ok(Some("whisper"))
+        );
+        assert!(provider(&[("SPEECH_PROVIDER", "whisper")]).is_err());
+        assert!(provider(&[("SPEECH_PROVIDER", "Google")]).is_err());
+        assert!(provider(&[("SPEECH_PROVIDER", "whisper")]).is_err());
+    }
+}
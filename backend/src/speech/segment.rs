@@ -0,0 +1,218 @@
+//! Segmenting audio for engines that transcribe whole recordings
+//!
+//! Whisper, over the API or run locally, takes a finished clip rather than a
+//! stream. Audio is cut into segments at pauses (or every
+//! `MAX_SEGMENT_SECS`) and each segment is transcribed as it completes.
+//! Each segment's text is sent as an interim result of everything heard so
+//! far; the final result comes at the first pause in single-utterance mode,
+//! else when the audio ends.
+
+use super::{SpeechConfig, TranscriptionResult};
+use std::future::Future;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+/// Longest segment transcribed at once
+const MAX_SEGMENT_SECS: u32 = 15;
+
+/// Quiet this long after speech counts as a pause
+const PAUSE_MS: u32 = 800;
+
+/// RMS level of a chunk above which it counts as speech
+const SPEECH_RMS: f64 = 500.0;
+
+/// Segment audio until it ends (or pauses, in single-utterance mode),
+/// transcribing each segment of PCM16 with `transcribe`
+pub async fn run<F, Fut>(
+    config: &SpeechConfig,
+    mut audio_rx: mpsc::UnboundedReceiver<Vec<u8>>,
+    result_tx: mpsc::UnboundedSender<TranscriptionResult>,
+    transcribe: F,
+) -> Result<(), String>
+where
+    F: Fn(Vec<u8>) -> Fut,
+    Fut: Future<Output = Result<String, String>>,
+{
+    let mut segmenter = Segmenter::new(config.sample_rate_hertz as u32);
+    let mut transcript = String::new();
+
+    loop {
+        let ended = match audio_rx.recv().await {
+            Some(chunk) => match segmenter.push(&chunk) {
+                Some(cut) => cut == Cut::Pause && config.single_utterance,
+                None => continue,
+            },
+            None => true,
+        };
+
+        if let Some(pcm) = segmenter.take() {
+            let text = transcribe(pcm).await?;
+            if !text.is_empty() {
+                if !transcript.is_empty() {
+                    transcript.push(' ');
+                }
+                transcript.push_str(&text);
+            }
+        }
+
+        if ended || config.interim_results {
+            if transcript.is_empty() {
+                if ended {
+                    break;
+                }
+                continue;
+            }
+            let result = TranscriptionResult {
+                transcript: transcript.clone(),
+                is_final: ended,
+                // Whisper doesn't report one
+                confidence: 0.0,
+            };
+            if result_tx.send(result).is_err() {
+                warn!("Result receiver closed, stopping recognition");
+                break;
+            }
+        }
+        if ended {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Why a segment ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cut {
+    /// The speaker paused
+    Pause,
+    /// The segment reached `MAX_SEGMENT_SECS`
+    Full,
+}
+
+/// Splits a PCM16 stream into segments at pauses in speech
+struct Segmenter {
+    sample_rate: u32,
+    audio: Vec<u8>,
+    heard_speech: bool,
+    /// Quiet samples since the last speech
+    quiet_samples: u32,
+}
+
+impl Segmenter {
+    fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            audio: Vec::new(),
+            heard_speech: false,
+            quiet_samples: 0,
+        }
+    }
+
+    /// Add a chunk, returning why the segment ended if this chunk ended it
+    fn push(&mut self, chunk: &[u8]) -> Option<Cut> {
+        self.audio.extend_from_slice(chunk);
+        let samples = (chunk.len() / 2) as u32;
+        if rms(chunk) > SPEECH_RMS {
+            self.heard_speech = true;
+            self.quiet_samples = 0;
+        } else if self.heard_speech {
+            self.quiet_samples += samples;
+        }
+
+        if self.heard_speech && self.quiet_samples >= self.sample_rate * PAUSE_MS / 1000 {
+            Some(Cut::Pause)
+        } else if self.audio.len() as u32 >= self.sample_rate * 2 * MAX_SEGMENT_SECS {
+            Some(Cut::Full)
+        } else {
+            None
+        }
+    }
+
+    /// The segment so far, or `None` if it was all silence
+    fn take(&mut self) -> Option<Vec<u8>> {
+        let audio = std::mem::take(&mut self.audio);
+        let heard_speech = std::mem::take(&mut self.heard_speech);
+        self.quiet_samples = 0;
+        heard_speech.then_some(audio)
+    }
+}
+
+/// PCM16 little-endian samples
+pub fn samples(pcm: &[u8]) -> impl Iterator<Item = i16> + '_ {
+    pcm.chunks_exact(2)
+        .map(|s| i16::from_le_bytes([s[0], s[1]]))
+}
+
+/// Root mean square of PCM16 little-endian samples
+fn rms(pcm: &[u8]) -> f64 {
+    let count = pcm.len() / 2;
+    if count == 0 {
+        return 0.0;
+    }
+    let sum: f64 = samples(pcm).map(|s| f64::from(s).powi(2)).sum();
+    (sum / count as f64).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 100ms of PCM16 at 16kHz with every sample at `level`
+    fn chunk(level: i16) -> Vec<u8> {
+        std::iter::repeat_n(level.to_le_bytes(), 1600)
+            .flatten()
+            .collect()
+    }
+
+    #[test]
+    fn test_segments_at_pause() {
+        let mut segmenter = Segmenter::new(16000);
+        // Leading silence doesn't start a segment
+        for _ in 0..20 {
+            assert_eq!(segmenter.push(&chunk(0)), None);
+        }
+        assert_eq!(segmenter.push(&chunk(3000)), None);
+        for _ in 0..7 {
+            assert_eq!(segmenter.push(&chunk(0)), None);
+        }
+        assert_eq!(segmenter.push(&chunk(0)), Some(Cut::Pause));
+        assert!(segmenter.take().is_some());
+
+        // All silence is never sent
+        for _ in 0..10 {
+            segmenter.push(&chunk(0));
+        }
+        assert_eq!(segmenter.take(), None);
+    }
+
+    #[test]
+    fn test_segments_when_full() {
+        let mut segmenter = Segmenter::new(16000);
+        let cuts: Vec<_> = (0..MAX_SEGMENT_SECS * 10)
+            .filter_map(|_| segmenter.push(&chunk(3000)))
+            .collect();
+        assert_eq!(cuts, vec![Cut::Full]);
+    }
+
+    #[tokio::test]
+    async fn test_run_single_utterance() {
+        let (audio_tx, audio_rx) = mpsc::unbounded_channel();
+        let (result_tx, mut result_rx) = mpsc::unbounded_channel();
+        audio_tx.send(chunk(3000)).unwrap();
+        for _ in 0..8 {
+            audio_tx.send(chunk(0)).unwrap();
+        }
+
+        let config = SpeechConfig::default();
+        run(&config, audio_rx, result_tx, |_| async {
+            Ok("hello".to_string())
+        })
+        .await
+        .unwrap();
+
+        let result = result_rx.recv().await.unwrap();
+        assert_eq!(result.transcript, "hello");
+        assert!(result.is_final);
+        assert!(result_rx.recv().await.is_none());
+    }
+}
@@ -0,0 +1,144 @@
+//! OpenAI Whisper speech provider
+//!
+//! Each segment of speech is posted to the audio transcriptions API as a WAV
+//! file. Works with any server that speaks the same API, such as a
+//! whisper.cpp server.
+
+use super::{segment, SpeechConfig, SpeechProvider, SpeechStream, TranscriptionResult};
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tracing::{error, info};
+
+pub const OPENAI_URL: &str = "https://api.openai.com/v1/audio/transcriptions";
+pub const OPENAI_MODEL: &str = "whisper-1";
+
+/// Speech-to-text using the OpenAI audio transcriptions API
+#[derive(Clone)]
+pub struct WhisperSpeech {
+    url: String,
+    api_key: Option<String>,
+    model: String,
+    client: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct TranscriptionResponse {
+    text: String,
+}
+
+impl WhisperSpeech {
+    pub fn new(url: String, api_key: Option<String>, model: String) -> Self {
+        Self {
+            url,
+            api_key,
+            model,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Text of one segment of PCM16 audio
+    async fn transcribe(&self, pcm: Vec<u8>, config: &SpeechConfig) -> Result<String, String> {
+        let file = reqwest::multipart::Part::bytes(wav(&pcm, config.sample_rate_hertz as u32))
+            .file_name("audio.wav")
+            .mime_str("audio/wav")
+            .map_err(|e| e.to_string())?;
+        let mut form = reqwest::multipart::Form::new()
+            .text("model", self.model.clone())
+            .part("file", file);
+        // Whisper takes ISO-639-1 codes: "en", not "en-US"; without one it
+        // detects the language
+        if let Some(language) = config
+            .language_code
+            .split('-')
+            .next()
+            .filter(|l| !l.is_empty() && *l != shared::voice::AUTO_LANGUAGE)
+        {
+            form = form.text("language", language.to_lowercase());
+        }
+
+        let mut request = self.client.post(&self.url).multipart(form);
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Transcription request failed: {}", e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Transcription API returned {}: {}", status, body));
+        }
+        response
+            .json::<TranscriptionResponse>()
+            .await
+            .map(|r| r.text.trim().to_string())
+            .map_err(|e| format!("Failed to read transcription: {}", e))
+    }
+}
+
+#[async_trait]
+impl SpeechProvider for WhisperSpeech {
+    fn name(&self) -> &'static str {
+        "whisper"
+    }
+
+    fn detects_language(&self) -> bool {
+        true
+    }
+
+    async fn start_streaming(&self, config: SpeechConfig) -> Result<SpeechStream, String> {
+        let (audio_tx, audio_rx) = mpsc::unbounded_channel::<Vec<u8>>();
+        let (result_tx, result_rx) = mpsc::unbounded_channel::<TranscriptionResult>();
+
+        let whisper = self.clone();
+        tokio::spawn(async move {
+            let transcribe = |pcm| whisper.transcribe(pcm, &config);
+            match segment::run(&config, audio_rx, result_tx, transcribe).await {
+                Ok(()) => info!("Speech recognition session completed"),
+                Err(e) => error!("Speech recognition error: {}", e),
+            }
+        });
+
+        Ok(SpeechStream {
+            audio: audio_tx,
+            transcripts: result_rx,
+        })
+    }
+}
+
+/// Mono PCM16 audio as a WAV file
+fn wav(pcm: &[u8], sample_rate: u32) -> Vec<u8> {
+    let data_len = pcm.len() as u32;
+    let mut out = Vec::with_capacity(44 + pcm.len());
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data_len).to_le_bytes());
+    out.extend_from_slice(b"WAVEfmt ");
+    out.extend_from_slice(&16u32.to_le_bytes()); // fmt chunk size
+    out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    out.extend_from_slice(&1u16.to_le_bytes()); // mono
+    out.extend_from_slice(&sample_rate.to_le_bytes());
+    out.extend_from_slice(&(sample_rate * 2).to_le_bytes()); // byte rate
+    out.extend_from_slice(&2u16.to_le_bytes()); // block align
+    out.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.to_le_bytes());
+    out.extend_from_slice(pcm);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wav_header() {
+        let out = wav(&[1, 2, 3, 4], 16000);
+        assert_eq!(out.len(), 48);
+        assert_eq!(&out[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(out[4..8].try_into().unwrap()), 40);
+        assert_eq!(u32::from_le_bytes(out[24..28].try_into().unwrap()), 16000);
+        assert_eq!(u32::from_le_bytes(out[40..44].try_into().unwrap()), 4);
+    }
+}
@@ -0,0 +1,218 @@
+//! Telegram notifications
+//!
+//! When `TELEGRAM_BOT_TOKEN` is set, notifications routed to Telegram are
+//! sent as direct messages from that bot. Each user starts a chat with the
+//! bot and enters the chat's ID on the settings page; users without one
+//! are skipped.
+
+use crate::db::DbPool;
+use crate::notifier::{self, Notification, NotificationKind, Notifier, Recipient};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use shared::NotificationChannel;
+use std::time::Duration;
+use tracing::warn;
+
+const API_URL: &str = "https://api.telegram.org";
+
+/// Requests that take longer than this count as failed
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Result summaries are cut to this many characters
+const MAX_SUMMARY_CHARS: usize = 500;
+
+#[derive(Deserialize)]
+struct TelegramResponse {
+    ok: bool,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+/// The bot to send as, read from `TELEGRAM_BOT_TOKEN`
+#[derive(Clone)]
+pub struct Telegram {
+    token: String,
+    public_url: String,
+    client: reqwest::Client,
+}
+
+impl std::fmt::Debug for Telegram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Telegram").finish_non_exhaustive()
+    }
+}
+
+impl Telegram {
+    /// The configured bot, `None` when Telegram is off
+    pub fn from_env(public_url: &str) -> Option<Self> {
+        Self::parse(|name| std::env::var(name).ok(), public_url)
+    }
+
+    fn parse(var: impl Fn(&str) -> Option<String>, public_url: &str) -> Option<Self> {
+        let token = var("TELEGRAM_BOT_TOKEN")
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())?;
+        Some(Self {
+            token,
+            public_url: public_url.to_string(),
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+        })
+    }
+
+    async fn send(&self, chat_id: &str, text: &str) -> anyhow::Result<()> {
+        let response: TelegramResponse = self
+            .client
+            .post(format!("{}/bot{}/sendMessage", API_URL, self.token))
+            .json(&json!({
+                "chat_id": chat_id,
+                "text": text,
+                "disable_web_page_preview": true,
+            }))
+            .send()
+            .await?
+            .json()
+            .await?;
+        if !response.ok {
+            anyhow::bail!(response
+                .description
+                .unwrap_or_else(|| "unknown error".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Notifier for Telegram {
+    fn channel(&self) -> NotificationChannel {
+        NotificationChannel::Telegram
+    }
+
+    /// Message each recipient who has given a chat ID
+    async fn deliver(
+        &self,
+        _db_pool: &DbPool,
+        notification: &Notification,
+        recipients: &[Recipient],
+    ) -> anyhow::Result<()> {
+        let link = notifier::session_link(&self.public_url, notification.session_id);
+        let text = text(notification, &link);
+        let mut failed = 0;
+        for chat_id in recipients
+            .iter()
+            .filter_map(|r| r.preferences.telegram_chat_id.as_deref())
+        {
+            if let Err(e) = self.send(chat_id, &text).await {
+                warn!("Failed to message Telegram chat {}: {}", chat_id, e);
+                failed += 1;
+            }
+        }
+        if failed > 0 {
+            anyhow::bail!("{} of {} messages failed", failed, recipients.len());
+        }
+        Ok(())
+    }
+}
+
+/// The message for a notification, in plain text
+fn text(notification: &Notification, link: &str) -> String {
+    let label = &notification.label;
+    let body = match &notification.kind {
+        NotificationKind::TurnFinished { result } => turn_finished_text(label, result),
+        NotificationKind::PermissionRequested { tool_name } => {
+            format!("✋ {} is asking for permission to use {}", label, tool_name)
+        }
+        NotificationKind::PermissionWaiting { tool_name, minutes } => format!(
+            "⏳ {} has been waiting {} minute{} for permission to use {}",
+            label,
+            minutes,
+            if *minutes == 1 { "" } else { "s" },
+            tool_name
+        ),
+        NotificationKind::PermissionEscalated { tool_name, minutes } => format!(
+            "🚨 {} has been waiting {} minutes for permission to use {}, and you're its escalation contact",
+            label, minutes, tool_name
+        ),
+        NotificationKind::DailyDigest {
+            day,
+            turns,
+            failed_turns,
+            cost_usd,
+        } => format!(
+            "📅 {} on {}: {}",
+            label,
+            day,
+            notifier::digest_summary(*turns, *failed_turns, *cost_usd)
+        ),
+    };
+    format!("{}\n{}", body, link)
+}
+
+fn turn_finished_text(label: &str, result: &Value) -> String {
+    let failed = result.get("is_error").and_then(|e| e.as_bool()) == Some(true);
+    let mut text = format!(
+        "{} {} {}",
+        if failed { "❌" } else { "✅" },
+        label,
+        if failed { "failed" } else { "finished" }
+    );
+    if let Some(cost) = result.get("total_cost_usd").and_then(|c| c.as_f64()) {
+        text.push_str(&format!(" (${:.2} so far)", cost));
+    }
+    if let Some(summary) = result
+        .get("result")
+        .and_then(|r| r.as_str())
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+    {
+        text.push_str("\n\n");
+        text.push_str(&notifier::truncate(summary, MAX_SUMMARY_CHARS));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_config() {
+        let parse = |token: Option<&str>| {
+            Telegram::parse(|_| token.map(str::to_string), "https://portal.example.com")
+        };
+        assert!(parse(None).is_none());
+        assert!(parse(Some("  ")).is_none());
+        assert_eq!(parse(Some("123:abc")).unwrap().token, "123:abc");
+    }
+
+    #[test]
+    fn test_text() {
+        let finished = Notification {
+            session_id: Uuid::nil(),
+            label: "api (laptop)".to_string(),
+            kind: NotificationKind::TurnFinished {
+                result: json!({"result": "Tests pass", "total_cost_usd": 0.4212}),
+            },
+        };
+        assert_eq!(
+            text(&finished, "https://p/x"),
+            "✅ api (laptop) finished ($0.42 so far)\n\nTests pass\nhttps://p/x"
+        );
+
+        let waiting = Notification {
+            kind: NotificationKind::PermissionWaiting {
+                tool_name: "Bash".to_string(),
+                minutes: 1,
+            },
+            ..finished
+        };
+        assert_eq!(
+            text(&waiting, "https://p/x"),
+            "⏳ api (laptop) has been waiting 1 minute for permission to use Bash\nhttps://p/x"
+        );
+    }
+}
@@ -0,0 +1,193 @@
+//! Subscriber setup and distributed tracing.
+//!
+//! Log output is configured by [`crate::logging`]. When `OTEL_EXPORTER_OTLP_ENDPOINT` (or the
+//! traces-specific `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`) is set they are also
+//! exported over OTLP/HTTP. Relayed messages carry a W3C `traceparent`, so a
+//! proxy output or browser input joins the same trace as the backend span
+//! that handled it.
+
+use crate::logging::{self, LogConfig};
+use axum::http::Request;
+use opentelemetry::trace::{
+    SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState, TracerProvider as _,
+};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use shared::TraceContext;
+use std::env;
+use tracing::Span;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+const SERVICE_NAME: &str = "cc-proxy-backend";
+
+/// Whether an OTLP endpoint is configured
+fn otlp_configured() -> bool {
+    [
+        "OTEL_EXPORTER_OTLP_ENDPOINT",
+        "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+    ]
+    .iter()
+    .any(|var| env::var(var).is_ok_and(|v| !v.is_empty()))
+}
+
+fn build_provider() -> anyhow::Result<SdkTracerProvider> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .build()?;
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+        .build())
+}
+
+/// Keeps log output and trace export running; shut down on exit
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+    _log_guard: Option<WorkerGuard>,
+}
+
+impl Telemetry {
+    /// Flush buffered spans to the exporter
+    pub fn shutdown(self) {
+        if let Some(provider) = self.provider {
+            if let Err(e) = provider.shutdown() {
+                tracing::warn!("Failed to flush traces: {}", e);
+            }
+        }
+    }
+}
+
+/// Install the global subscriber
+pub fn init(config: &LogConfig) -> Telemetry {
+    let mut problems = Vec::new();
+    let provider = if otlp_configured() {
+        match build_provider() {
+            Ok(provider) => Some(provider),
+            Err(e) => {
+                problems.push(format!("Failed to set up OTLP trace export: {}", e));
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let otel_layer = provider
+        .as_ref()
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME)));
+    let (layers, log_guard, layer_problems) = logging::layers(config);
+    problems.extend(layer_problems);
+
+    tracing_subscriber::registry()
+        .with(layers)
+        .with(config.env_filter())
+        .with(otel_layer)
+        .init();
+
+    for problem in problems {
+        tracing::warn!("{}", problem);
+    }
+    if provider.is_some() {
+        tracing::info!("Exporting traces over OTLP");
+    }
+    Telemetry {
+        provider,
+        _log_guard: log_guard,
+    }
+}
+
+/// Parent `span` to the remote span described by `traceparent`, if valid
+pub fn set_remote_parent(span: &Span, traceparent: Option<&str>) {
+    let Some(ctx) = traceparent.and_then(TraceContext::from_traceparent) else {
+        return;
+    };
+    let flags = if ctx.sampled {
+        TraceFlags::SAMPLED
+    } else {
+        TraceFlags::default()
+    };
+    let remote = SpanContext::new(
+        TraceId::from_bytes(ctx.trace_id),
+        SpanId::from_bytes(ctx.span_id),
+        flags,
+        true,
+        TraceState::default(),
+    );
+    let _ = span.set_parent(opentelemetry::Context::new().with_remote_span_context(remote));
+}
+
+/// `traceparent` to hand downstream from `span`. Without an exporter the
+/// span has no ids, so the incoming value is passed through unchanged.
+pub fn traceparent(span: &Span, incoming: Option<String>) -> Option<String> {
+    let context = span.context();
+    let span_context = context.span().span_context().clone();
+    if !span_context.is_valid() {
+        return incoming;
+    }
+    Some(
+        TraceContext {
+            trace_id: span_context.trace_id().to_bytes(),
+            span_id: span_context.span_id().to_bytes(),
+            sampled: span_context.is_sampled(),
+        }
+        .to_traceparent(),
+    )
+}
+
+/// Span for an HTTP request, tagged with its `x-request-id` and continuing
+/// the caller's trace if it sent a `traceparent` header
+pub fn http_span<B>(request: &Request<B>) -> Span {
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    let span = tracing::info_span!(
+        "http_request",
+        method = %request.method(),
+        path = %request.uri().path(),
+        request_id,
+    );
+    let parent = request
+        .headers()
+        .get("traceparent")
+        .and_then(|v| v.to_str().ok());
+    set_remote_parent(&span, parent);
+    span
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::Registry;
+
+    #[test]
+    fn test_traceparent_passes_through_without_exporter() {
+        let incoming = TraceContext::new_root().to_traceparent();
+        let span = tracing::info_span!("relay");
+        set_remote_parent(&span, Some(&incoming));
+        assert_eq!(traceparent(&span, Some(incoming.clone())), Some(incoming));
+    }
+
+    #[test]
+    fn test_child_span_joins_remote_trace() {
+        let provider = SdkTracerProvider::builder().build();
+        let subscriber = Registry::default()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let remote = TraceContext::new_root();
+            let span = tracing::info_span!("relay");
+            set_remote_parent(&span, Some(&remote.to_traceparent()));
+
+            let child = traceparent(&span, None)
+                .and_then(|t| TraceContext::from_traceparent(&t))
+                .unwrap();
+            assert_eq!(child.trace_id, remote.trace_id);
+            assert_ne!(child.span_id, remote.span_id);
+            assert!(child.sampled);
+        });
+    }
+}
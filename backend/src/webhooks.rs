@@ -0,0 +1,281 @@
+//! Outbound webhooks on session lifecycle events
+//!
+//! When `WEBHOOK_URLS` is set, the backend POSTs a JSON event to each URL
+//! when a session starts or ends, asks for permission, has a permission
+//! request escalated, hits an error, or goes over budget. With
+//! `WEBHOOK_SECRET` set, each request carries an HMAC-SHA256 signature of
+//! its timestamp and body so receivers can check it came from this backend.
+//! Deliveries that fail with a network error, a 429, or a 5xx are retried
+//! with exponential backoff; other responses are final.
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::Sha256;
+use std::time::Duration;
+use tracing::warn;
+use uuid::Uuid;
+
+pub use shared::EventKind;
+
+/// Attempts per delivery, including the first
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Wait before the first retry; doubles after each failure
+const INITIAL_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Receivers that take longer than this count as failed
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+
+/// The JSON body of a delivery
+#[derive(Debug, Serialize)]
+struct EventBody<'a> {
+    /// Same across retries, so receivers can drop duplicates
+    id: Uuid,
+    event: &'static str,
+    session_id: Uuid,
+    timestamp: String,
+    data: &'a Value,
+}
+
+/// Where events are sent, read from `WEBHOOK_*` variables
+#[derive(Debug, Clone)]
+pub struct Webhooks {
+    urls: Vec<String>,
+    secret: Option<String>,
+    /// Events to send; all of them when the list is empty
+    events: Vec<EventKind>,
+    client: reqwest::Client,
+}
+
+impl Webhooks {
+    /// The configured webhooks, `None` when none are set
+    pub fn from_env() -> Result<Option<Self>, String> {
+        Self::parse(|name| std::env::var(name).ok())
+    }
+
+    fn parse(var: impl Fn(&str) -> Option<String>) -> Result<Option<Self>, String> {
+        let var = |name: &str| var(name).filter(|v| !v.trim().is_empty());
+        let list = |name: &str| -> Vec<String> {
+            var(name)
+                .unwrap_or_default()
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        };
+
+        let urls = list("WEBHOOK_URLS");
+        if urls.is_empty() {
+            return Ok(None);
+        }
+        if let Some(url) = urls
+            .iter()
+            .find(|url| !url.starts_with("https://") && !url.starts_with("http://"))
+        {
+            return Err(format!("WEBHOOK_URLS has a non-HTTP URL {:?}", url));
+        }
+        let events = list("WEBHOOK_EVENTS")
+            .iter()
+            .map(|name| {
+                EventKind::parse(&name.to_lowercase())
+                    .ok_or_else(|| format!("Unknown webhook event {:?}", name))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+
+        Ok(Some(Self {
+            urls,
+            secret: var("WEBHOOK_SECRET"),
+            events,
+            client,
+        }))
+    }
+
+    pub fn url_count(&self) -> usize {
+        self.urls.len()
+    }
+
+    fn wants(&self, kind: EventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&kind)
+    }
+
+    /// Deliver an event to every URL in the background
+    pub fn send(&self, kind: EventKind, session_id: Uuid, data: Value) {
+        if !self.wants(kind) {
+            return;
+        }
+        let body = EventBody {
+            id: Uuid::new_v4(),
+            event: kind.name(),
+            session_id,
+            timestamp: Utc::now().to_rfc3339(),
+            data: &data,
+        };
+        let body = match serde_json::to_vec(&body) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Failed to encode {} webhook: {}", kind.name(), e);
+                return;
+            }
+        };
+        for url in &self.urls {
+            let webhooks = self.clone();
+            let url = url.clone();
+            let body = body.clone();
+            tokio::spawn(async move { webhooks.deliver(&url, kind, body).await });
+        }
+    }
+
+    async fn deliver(&self, url: &str, kind: EventKind, body: Vec<u8>) {
+        let mut backoff = INITIAL_BACKOFF;
+        for attempt in 1..=MAX_ATTEMPTS {
+            let timestamp = Utc::now().timestamp().to_string();
+            let mut request = self
+                .client
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, kind.name())
+                .header(TIMESTAMP_HEADER, &timestamp)
+                .body(body.clone());
+            if let Some(secret) = &self.secret {
+                request = request.header(
+                    SIGNATURE_HEADER,
+                    signature(secret, &signed_payload(&timestamp, &body)),
+                );
+            }
+
+            let retry = match request.send().await {
+                Ok(response) if response.status().is_success() => return,
+                Ok(response) => {
+                    let status = response.status();
+                    warn!(
+                        "Webhook {} to {} failed with {} (attempt {}/{})",
+                        kind.name(),
+                        url,
+                        status,
+                        attempt,
+                        MAX_ATTEMPTS
+                    );
+                    should_retry(status)
+                }
+                Err(e) => {
+                    warn!(
+                        "Webhook {} to {} failed: {} (attempt {}/{})",
+                        kind.name(),
+                        url,
+                        e,
+                        attempt,
+                        MAX_ATTEMPTS
+                    );
+                    true
+                }
+            };
+            if !retry || attempt == MAX_ATTEMPTS {
+                break;
+            }
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+        warn!("Gave up delivering webhook {} to {}", kind.name(), url);
+    }
+}
+
+/// Rate limits and server errors may pass; anything else won't change on retry
+fn should_retry(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// What the signature covers: the timestamp header, a dot, and the body
+fn signed_payload(timestamp: &str, body: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(timestamp.len() + 1 + body.len());
+    payload.extend_from_slice(timestamp.as_bytes());
+    payload.push(b'.');
+    payload.extend_from_slice(body);
+    payload
+}
+
+/// `sha256=` and the hex HMAC-SHA256 of `payload`
+fn signature(secret: &str, payload: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(payload);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn parse(vars: &[(&str, &str)]) -> Result<Option<Webhooks>, String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Webhooks::parse(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_config() {
+        assert!(parse(&[]).unwrap().is_none());
+        assert!(parse(&[("WEBHOOK_URLS", " , ")]).unwrap().is_none());
+
+        let webhooks = parse(&[
+            (
+                "WEBHOOK_URLS",
+                "https://events.pagerduty.com/x, http://bot.internal/hook",
+            ),
+            ("WEBHOOK_SECRET", "s3cret"),
+            ("WEBHOOK_EVENTS", "error,Budget_Exceeded"),
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(webhooks.url_count(), 2);
+        assert_eq!(webhooks.secret.as_deref(), Some("s3cret"));
+        assert!(webhooks.wants(EventKind::Error));
+        assert!(webhooks.wants(EventKind::BudgetExceeded));
+        assert!(!webhooks.wants(EventKind::SessionStarted));
+
+        let all = parse(&[("WEBHOOK_URLS", "https://example.com")])
+            .unwrap()
+            .unwrap();
+        assert!(EventKind::ALL.iter().all(|kind| all.wants(*kind)));
+
+        assert!(parse(&[("WEBHOOK_URLS", "example.com/hook")]).is_err());
+        assert!(parse(&[
+            ("WEBHOOK_URLS", "https://example.com"),
+            ("WEBHOOK_EVENTS", "session_paused"),
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_signature() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            signature("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            signed_payload("1700000000", br#"{"event":"error"}"#),
+            br#"1700000000.{"event":"error"}"#
+        );
+    }
+
+    #[test]
+    fn test_should_retry() {
+        assert!(should_retry(reqwest::StatusCode::BAD_GATEWAY));
+        assert!(should_retry(reqwest::StatusCode::TOO_MANY_REQUESTS));
+        assert!(!should_retry(reqwest::StatusCode::NOT_FOUND));
+        assert!(!should_retry(reqwest::StatusCode::UNAUTHORIZED));
+    }
+}
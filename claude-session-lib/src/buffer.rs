@@ -45,6 +45,19 @@ impl OutputBuffer {
         }
     }
 
+    /// Change the max size, dropping the oldest entries if over it
+    pub fn set_max_size(&mut self, max_size: usize) {
+        self.max_size = max_size;
+        while self.outputs.len() > self.max_size {
+            self.outputs.pop_front();
+        }
+    }
+
+    /// Most outputs kept before the oldest are dropped
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
     /// Get the session ID this buffer belongs to
     pub fn session_id(&self) -> Uuid {
         self.session_id
@@ -85,6 +98,27 @@ impl OutputBuffer {
         self.outputs.iter()
     }
 
+    /// Pending outputs from `seq` on, in order. Outputs before
+    /// [`Self::dropped_before`] are gone, so a client that has seen less
+    /// than that needs a full resync.
+    pub fn since(&self, seq: u64) -> impl Iterator<Item = &BufferedOutput> {
+        let start = self.outputs.partition_point(|o| o.seq < seq);
+        self.outputs.range(start..)
+    }
+
+    /// Pending outputs with sequence numbers from `from_seq` through
+    /// `to_seq`, in order
+    pub fn range(&self, from_seq: u64, to_seq: u64) -> impl Iterator<Item = &BufferedOutput> {
+        self.since(from_seq).take_while(move |o| o.seq <= to_seq)
+    }
+
+    /// The oldest sequence number still held; everything before it was
+    /// acked or dropped past the max size. Equal to the next sequence
+    /// number when the buffer is empty.
+    pub fn dropped_before(&self) -> u64 {
+        self.outputs.front().map_or(self.next_seq, |o| o.seq)
+    }
+
     /// Get count of pending outputs
     pub fn pending_count(&self) -> usize {
         self.outputs.len()
@@ -159,6 +193,32 @@ mod tests {
 
         let seqs: Vec<u64> = buffer.pending().map(|o| o.seq).collect();
         assert_eq!(seqs, vec![1, 2, 3]); // First one was dropped
+
+        buffer.set_max_size(1);
+        let seqs: Vec<u64> = buffer.pending().map(|o| o.seq).collect();
+        assert_eq!(seqs, vec![3]);
+    }
+
+    #[test]
+    fn test_since_and_range() {
+        let mut buffer = OutputBuffer::with_max_size(Uuid::new_v4(), 5);
+        assert_eq!(buffer.dropped_before(), 0);
+
+        for i in 0..8 {
+            buffer.push(serde_json::json!(i));
+        }
+        assert_eq!(buffer.dropped_before(), 3);
+
+        let seqs: Vec<u64> = buffer.since(5).map(|o| o.seq).collect();
+        assert_eq!(seqs, vec![5, 6, 7]);
+        let seqs: Vec<u64> = buffer.since(0).map(|o| o.seq).collect();
+        assert_eq!(seqs, vec![3, 4, 5, 6, 7]);
+        let seqs: Vec<u64> = buffer.range(4, 5).map(|o| o.seq).collect();
+        assert_eq!(seqs, vec![4, 5]);
+        assert_eq!(buffer.range(8, 10).count(), 0);
+
+        buffer.ack(7);
+        assert_eq!(buffer.dropped_before(), 8);
     }
 
     #[test]
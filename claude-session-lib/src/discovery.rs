@@ -0,0 +1,252 @@
+//! Finding the claude binary
+//!
+//! Without `SessionConfig::claude_path`, the binary is searched for on
+//! `PATH` and then in the places its installers put it: npm's global prefix,
+//! Homebrew, Volta, bun, and the native installer. A version can be pinned
+//! with `SessionConfig::claude_version` or a `.claude-version` file in the
+//! project (or any directory above it); only a binary reporting that
+//! version is used then.
+
+use crate::error::SessionError;
+use crate::snapshot::SessionConfig;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use tracing::debug;
+
+/// File naming the Claude version a project wants, e.g. `2.1.17` or `2.1`
+pub const VERSION_FILE: &str = ".claude-version";
+
+#[cfg(windows)]
+const BINARY_NAMES: &[&str] = &["claude.exe", "claude.cmd"];
+#[cfg(not(windows))]
+const BINARY_NAMES: &[&str] = &["claude"];
+
+/// Install locations under the home directory, after `PATH`
+const HOME_LOCATIONS: &[&str] = &[
+    ".claude/local",
+    ".local/bin",
+    ".npm-global/bin",
+    ".volta/bin",
+    ".bun/bin",
+];
+
+/// System-wide install locations, after the home directory ones
+const SYSTEM_LOCATIONS: &[&str] = &[
+    "/opt/homebrew/bin",
+    "/usr/local/bin",
+    "/home/linuxbrew/.linuxbrew/bin",
+];
+
+/// Where the native installer keeps each version, under the home directory
+const VERSIONS_DIR: &str = ".local/share/claude/versions";
+
+/// Candidate binaries, in the order they're tried
+pub fn search_paths(
+    home: Option<&Path>,
+    path_var: Option<&OsStr>,
+    npm_prefix: Option<&Path>,
+    version: Option<&str>,
+) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    if let Some(path_var) = path_var {
+        dirs.extend(std::env::split_paths(path_var));
+    }
+    if let Some(prefix) = npm_prefix {
+        dirs.push(if cfg!(windows) {
+            prefix.to_path_buf()
+        } else {
+            prefix.join("bin")
+        });
+    }
+    if let Some(home) = home {
+        dirs.extend(HOME_LOCATIONS.iter().map(|dir| home.join(dir)));
+    }
+    dirs.extend(SYSTEM_LOCATIONS.iter().map(PathBuf::from));
+
+    let mut paths = Vec::new();
+    // A pinned version the native installer has is the surest match
+    if let (Some(home), Some(version)) = (home, version) {
+        paths.push(home.join(VERSIONS_DIR).join(version));
+    }
+    for dir in dirs {
+        for name in BINARY_NAMES {
+            let path = dir.join(name);
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    paths
+}
+
+/// The version pinned in the config, or else by the nearest
+/// `.claude-version` file at or above the working directory
+pub fn pinned_version(config: &SessionConfig) -> Option<String> {
+    if let Some(version) = &config.claude_version {
+        return Some(version.trim().to_string());
+    }
+    config.working_directory.ancestors().find_map(|dir| {
+        let text = std::fs::read_to_string(dir.join(VERSION_FILE)).ok()?;
+        let version = text.trim();
+        (!version.is_empty()).then(|| version.to_string())
+    })
+}
+
+/// The version in `claude --version` output, e.g. "2.1.17 (Claude Code)"
+pub fn parse_version(output: &str) -> Option<&str> {
+    output
+        .split_whitespace()
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))
+}
+
+/// Whether `version` satisfies `pin`: the same, or `pin` is a prefix of
+/// whole components ("2.1" matches "2.1.17" but not "2.10.0")
+pub fn version_matches(pin: &str, version: &str) -> bool {
+    let pin = pin.trim_start_matches('v');
+    version == pin
+        || version
+            .strip_prefix(pin)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// Version a binary reports, if it runs
+pub async fn binary_version(path: &Path) -> Option<String> {
+    let output = Command::new(path).arg("--version").output().await.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_version(&String::from_utf8_lossy(&output.stdout)).map(str::to_string)
+}
+
+/// Version of the claude binary a session with `config` would run
+pub async fn claude_version(config: &SessionConfig) -> Option<String> {
+    binary_version(&find_claude(config).await.ok()?).await
+}
+
+/// Whether `path` is a binary that satisfies the pin, if any
+async fn accept(path: &Path, version: Option<&str>) -> bool {
+    if !path.is_file() {
+        return false;
+    }
+    let Some(pin) = version else {
+        return true;
+    };
+    match binary_version(path).await {
+        Some(found) => {
+            debug!("{} is Claude {}", path.display(), found);
+            version_matches(pin, &found)
+        }
+        None => false,
+    }
+}
+
+/// The claude binary a session should run
+pub async fn find_claude(config: &SessionConfig) -> Result<PathBuf, SessionError> {
+    let version = pinned_version(config);
+
+    if let Some(path) = &config.claude_path {
+        // A bare name is looked up on PATH like any other command
+        let searched = if path.components().count() > 1 {
+            vec![path.clone()]
+        } else {
+            std::env::var_os("PATH")
+                .map(|path_var| {
+                    std::env::split_paths(&path_var)
+                        .map(|dir| dir.join(path))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        for candidate in &searched {
+            if accept(candidate, version.as_deref()).await {
+                return Ok(candidate.clone());
+            }
+        }
+        return Err(SessionError::ClaudeNotFound { version, searched });
+    }
+
+    let home = std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from);
+    let npm_prefix = std::env::var_os("NPM_CONFIG_PREFIX").map(PathBuf::from);
+    let searched = search_paths(
+        home.as_deref(),
+        std::env::var_os("PATH").as_deref(),
+        npm_prefix.as_deref(),
+        version.as_deref(),
+    );
+    for path in &searched {
+        if accept(path, version.as_deref()).await {
+            return Ok(path.clone());
+        }
+    }
+    Err(SessionError::ClaudeNotFound { version, searched })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("2.1.17 (Claude Code)\n"), Some("2.1.17"));
+        assert_eq!(parse_version("claude 1.0.3"), Some("1.0.3"));
+        assert_eq!(parse_version("unknown"), None);
+    }
+
+    #[test]
+    fn test_version_matches() {
+        assert!(version_matches("2.1.17", "2.1.17"));
+        assert!(version_matches("2.1", "2.1.17"));
+        assert!(version_matches("v2", "2.1.17"));
+        assert!(!version_matches("2.1", "2.10.0"));
+        assert!(!version_matches("2.1.17", "2.1.1"));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_search_paths() {
+        let path_var = std::env::join_paths(["/usr/bin", "/usr/local/bin"]).unwrap();
+        let paths = search_paths(
+            Some(Path::new("/home/me")),
+            Some(&path_var),
+            Some(Path::new("/opt/npm")),
+            Some("2.1.17"),
+        );
+        assert_eq!(
+            paths[0],
+            PathBuf::from("/home/me/.local/share/claude/versions/2.1.17")
+        );
+        assert_eq!(paths[1], PathBuf::from("/usr/bin/claude"));
+        assert_eq!(paths[2], PathBuf::from("/usr/local/bin/claude"));
+        assert_eq!(paths[3], PathBuf::from("/opt/npm/bin/claude"));
+        assert!(paths.contains(&PathBuf::from("/home/me/.volta/bin/claude")));
+        assert!(paths.contains(&PathBuf::from("/opt/homebrew/bin/claude")));
+        // /usr/local/bin was already on PATH
+        let count = paths
+            .iter()
+            .filter(|p| *p == Path::new("/usr/local/bin/claude"))
+            .count();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_pinned_version() {
+        let dir = std::env::temp_dir().join(format!("claude-pin-{}", uuid::Uuid::new_v4()));
+        let project = dir.join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(dir.join(VERSION_FILE), "2.1\n").unwrap();
+
+        let mut config = SessionConfig {
+            working_directory: project,
+            ..Default::default()
+        };
+        assert_eq!(pinned_version(&config).as_deref(), Some("2.1"));
+
+        config.claude_version = Some("2.0.5".to_string());
+        assert_eq!(pinned_version(&config).as_deref(), Some("2.0.5"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,268 @@
+//! Encrypting snapshots at rest
+//!
+//! Snapshots hold full tool outputs, which can include source code and
+//! secrets. With [`SnapshotKeys`], they are sealed with AES-256-GCM, so a
+//! stored snapshot can be neither read nor altered without the key.
+//!
+//! Keys are named, and the name of the key that sealed a snapshot is stored
+//! with it. The first key seals; any key decrypts a snapshot it sealed. To
+//! rotate, put the new key first and keep the old one after it until every
+//! snapshot has been saved again.
+//!
+//! With keys set, plain snapshots are refused, so one can't be swapped in
+//! for a sealed one. While turning encryption on, [`SnapshotKeys::allow_plaintext`]
+//! lets existing plain snapshots load until they've been saved again.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::Engine;
+
+use crate::error::SessionError;
+
+/// Environment variable holding snapshot keys, as parsed by
+/// [`SnapshotKeys::parse`]
+pub const KEYS_ENV_VAR: &str = "CLAUDE_SNAPSHOT_KEYS";
+
+/// Environment variable that, set to `1`, lets plain snapshots load
+/// alongside the keys in [`KEYS_ENV_VAR`]
+pub const ALLOW_PLAINTEXT_ENV_VAR: &str = "CLAUDE_SNAPSHOT_ALLOW_PLAINTEXT";
+
+/// Starts every encrypted snapshot. Plain snapshots are JSON objects, so
+/// they always start with `{`.
+const MAGIC: &[u8] = b"CCSE1";
+
+const NONCE_LEN: usize = 12;
+
+/// Bytes in a key
+pub const KEY_LEN: usize = 32;
+
+#[derive(Clone)]
+struct SnapshotKey {
+    id: String,
+    cipher: Aes256Gcm,
+}
+
+/// Named keys for sealing and opening snapshots; the first one seals
+#[derive(Clone)]
+pub struct SnapshotKeys {
+    keys: Vec<SnapshotKey>,
+    allow_plaintext: bool,
+}
+
+impl std::fmt::Debug for SnapshotKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ids: Vec<_> = self.keys.iter().map(|k| k.id.as_str()).collect();
+        f.debug_struct("SnapshotKeys")
+            .field("ids", &ids)
+            .field("allow_plaintext", &self.allow_plaintext)
+            .finish()
+    }
+}
+
+fn key_error(why: impl std::fmt::Display) -> SessionError {
+    SessionError::EncryptionError(why.to_string())
+}
+
+impl SnapshotKeys {
+    /// Seal with this key
+    pub fn new(id: &str, key: &[u8; KEY_LEN]) -> Result<Self, SessionError> {
+        Ok(Self {
+            keys: vec![SnapshotKey::new(id, key)?],
+            allow_plaintext: false,
+        })
+    }
+
+    /// Also open snapshots sealed with an older key
+    pub fn with_previous(mut self, id: &str, key: &[u8; KEY_LEN]) -> Result<Self, SessionError> {
+        if self.keys.iter().any(|k| k.id == id) {
+            return Err(key_error(format!("Snapshot key {} is listed twice", id)));
+        }
+        self.keys.push(SnapshotKey::new(id, key)?);
+        Ok(self)
+    }
+
+    /// Keys from `id:base64key` pairs separated by commas, sealing with the
+    /// first, e.g. `2024-06:<new key>,2024-01:<old key>`
+    pub fn parse(spec: &str) -> Result<Self, SessionError> {
+        let mut keys: Option<Self> = None;
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (id, encoded) = entry
+                .split_once(':')
+                .ok_or_else(|| key_error("Snapshot keys must be id:base64key pairs"))?;
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(encoded.trim())
+                .map_err(|e| key_error(format!("Snapshot key {} is not base64: {}", id, e)))?;
+            let key: [u8; KEY_LEN] = bytes
+                .try_into()
+                .map_err(|_| key_error(format!("Snapshot key {} must be {} bytes", id, KEY_LEN)))?;
+            keys = Some(match keys {
+                None => Self::new(id.trim(), &key)?,
+                Some(keys) => keys.with_previous(id.trim(), &key)?,
+            });
+        }
+        keys.ok_or_else(|| key_error("No snapshot keys given"))
+    }
+
+    /// Also load plain snapshots, e.g. ones saved before encryption was
+    /// turned on. Off by default.
+    pub fn allow_plaintext(mut self) -> Self {
+        self.allow_plaintext = true;
+        self
+    }
+
+    /// Whether plain snapshots load alongside sealed ones
+    pub fn allows_plaintext(&self) -> bool {
+        self.allow_plaintext
+    }
+
+    /// Keys from [`KEYS_ENV_VAR`], if it's set, allowing plain snapshots if
+    /// [`ALLOW_PLAINTEXT_ENV_VAR`] is `1`
+    pub fn from_env() -> Result<Option<Self>, SessionError> {
+        let keys = match std::env::var(KEYS_ENV_VAR) {
+            Ok(spec) if !spec.trim().is_empty() => Self::parse(&spec)?,
+            _ => return Ok(None),
+        };
+        Ok(Some(match std::env::var(ALLOW_PLAINTEXT_ENV_VAR) {
+            Ok(flag) if flag.trim() == "1" => keys.allow_plaintext(),
+            _ => keys,
+        }))
+    }
+
+    /// Name of the key that seals
+    pub fn current_id(&self) -> &str {
+        &self.keys[0].id
+    }
+
+    /// Seal with the current key
+    pub(crate) fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, SessionError> {
+        let key = &self.keys[0];
+        let header = header(&key.id);
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let sealed = key
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad: &header,
+                },
+            )
+            .map_err(|_| key_error("Failed to encrypt snapshot"))?;
+        let mut bytes = header;
+        bytes.extend_from_slice(&nonce);
+        bytes.extend_from_slice(&sealed);
+        Ok(bytes)
+    }
+
+    /// Open a sealed snapshot with the key it names
+    pub(crate) fn decrypt(&self, bytes: &[u8]) -> Result<Vec<u8>, SessionError> {
+        let id = sealed_with(bytes).ok_or_else(|| key_error("Snapshot is not encrypted"))?;
+        let key = self
+            .keys
+            .iter()
+            .find(|k| k.id == id)
+            .ok_or_else(|| key_error(format!("No snapshot key named {}", id)))?;
+        let header_len = MAGIC.len() + 1 + id.len();
+        let rest = &bytes[header_len..];
+        if rest.len() < NONCE_LEN {
+            return Err(key_error("Encrypted snapshot is truncated"));
+        }
+        let (nonce, sealed) = rest.split_at(NONCE_LEN);
+        key.cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: sealed,
+                    aad: &bytes[..header_len],
+                },
+            )
+            .map_err(|_| {
+                key_error(format!(
+                    "Snapshot failed to decrypt with key {}; it is corrupt or was altered",
+                    id
+                ))
+            })
+    }
+}
+
+impl SnapshotKey {
+    fn new(id: &str, key: &[u8; KEY_LEN]) -> Result<Self, SessionError> {
+        if id.is_empty() || id.len() > u8::MAX as usize || id.contains([',', ':']) {
+            return Err(key_error(format!(
+                "Invalid snapshot key name {:?}: 1-255 bytes, without commas or colons",
+                id
+            )));
+        }
+        Ok(Self {
+            id: id.to_string(),
+            cipher: Aes256Gcm::new(key.into()),
+        })
+    }
+}
+
+/// Magic, key name length, and key name; authenticated along with the
+/// snapshot so the name can't be swapped
+fn header(id: &str) -> Vec<u8> {
+    let mut header = MAGIC.to_vec();
+    header.push(id.len() as u8);
+    header.extend_from_slice(id.as_bytes());
+    header
+}
+
+/// Name of the key a snapshot was sealed with, or `None` if it's plain
+pub fn sealed_with(bytes: &[u8]) -> Option<&str> {
+    let rest = bytes.strip_prefix(MAGIC)?;
+    let (&len, rest) = rest.split_first()?;
+    std::str::from_utf8(rest.get(..len as usize)?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(key: &[u8; KEY_LEN]) -> String {
+        base64::engine::general_purpose::STANDARD.encode(key)
+    }
+
+    #[test]
+    fn test_round_trip_and_tamper() {
+        let keys = SnapshotKeys::new("k1", &[7; KEY_LEN]).unwrap();
+        let mut sealed = keys.encrypt(b"{\"secret\":1}").unwrap();
+        assert_eq!(sealed_with(&sealed), Some("k1"));
+        assert_eq!(keys.decrypt(&sealed).unwrap(), b"{\"secret\":1}");
+        assert_eq!(sealed_with(b"{\"secret\":1}"), None);
+
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        assert!(keys.decrypt(&sealed).is_err());
+    }
+
+    #[test]
+    fn test_rotation() {
+        let old = SnapshotKeys::new("old", &[1; KEY_LEN]).unwrap();
+        let sealed = old.encrypt(b"{}").unwrap();
+
+        let spec = format!(
+            "new:{},old:{}",
+            encoded(&[2; KEY_LEN]),
+            encoded(&[1; KEY_LEN])
+        );
+        let rotated = SnapshotKeys::parse(&spec).unwrap();
+        assert_eq!(rotated.current_id(), "new");
+        assert_eq!(rotated.decrypt(&sealed).unwrap(), b"{}");
+        assert_eq!(sealed_with(&rotated.encrypt(b"{}").unwrap()), Some("new"));
+
+        let dropped = SnapshotKeys::parse(&format!("new:{}", encoded(&[2; KEY_LEN]))).unwrap();
+        assert!(dropped.decrypt(&sealed).is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_keys() {
+        assert!(SnapshotKeys::parse("").is_err());
+        assert!(SnapshotKeys::parse("nokey").is_err());
+        assert!(SnapshotKeys::parse("k:not base64!").is_err());
+        assert!(SnapshotKeys::parse(&format!("k:{}", &encoded(&[0; KEY_LEN])[4..])).is_err());
+        let twice = format!("k:{},k:{}", encoded(&[0; KEY_LEN]), encoded(&[1; KEY_LEN]));
+        assert!(SnapshotKeys::parse(&twice).is_err());
+    }
+}
@@ -18,11 +18,40 @@ pub enum SessionError {
     #[error("Session already exited with code {0}")]
     AlreadyExited(i32),
 
+    #[error("Invalid session state transition: {from} -> {to}")]
+    InvalidTransition {
+        from: crate::state::SessionState,
+        to: crate::state::SessionState,
+    },
+
+    #[error("Snapshot storage error: {0}")]
+    StorageError(String),
+
+    #[error("Snapshot encryption error: {0}")]
+    EncryptionError(String),
+
     #[error("Serialization error: {0}")]
     SerializationError(#[from] serde_json::Error),
 
     #[error("Claude client error: {0}")]
     ClaudeError(#[from] claude_codes::Error),
+
+    #[error("{}", not_found_message(.version.as_deref(), .searched))]
+    ClaudeNotFound {
+        /// Pinned version, if any
+        version: Option<String>,
+        /// Every path tried, in order
+        searched: Vec<std::path::PathBuf>,
+    },
+}
+
+fn not_found_message(version: Option<&str>, searched: &[std::path::PathBuf]) -> String {
+    let what = match version {
+        Some(version) => format!("Claude CLI version {}", version),
+        None => "Claude CLI".to_string(),
+    };
+    let paths: Vec<_> = searched.iter().map(|p| p.display().to_string()).collect();
+    format!("{} not found; searched:\n  {}", what, paths.join("\n  "))
 }
 
 #[cfg(test)]
@@ -48,6 +77,21 @@ mod tests {
             format!("{}", err),
             "Claude process communication error: connection lost"
         );
+
+        let err = SessionError::StorageError("reading a.json: denied".to_string());
+        assert_eq!(
+            format!("{}", err),
+            "Snapshot storage error: reading a.json: denied"
+        );
+
+        let err = SessionError::ClaudeNotFound {
+            version: Some("2.1".to_string()),
+            searched: vec!["/usr/bin/claude".into(), "/opt/homebrew/bin/claude".into()],
+        };
+        assert_eq!(
+            format!("{}", err),
+            "Claude CLI version 2.1 not found; searched:\n  /usr/bin/claude\n  /opt/homebrew/bin/claude"
+        );
     }
 
     #[test]
@@ -0,0 +1,215 @@
+//! Typed session events
+//!
+//! Claude's stream-json output, plus what its process does, as a
+//! [`SessionEvent`] per thing that happened: a run of assistant text, a
+//! tool call, a tool result, a permission request, the end of a turn, the
+//! process exiting, or a line on stderr. Subscribe with
+//! `Session::subscribe_events`; [`SessionEvent::from_output`] turns a single
+//! output into events for code that already has one in hand.
+
+use claude_codes::io::{ContentBlock, ControlRequestPayload, ToolResultContent};
+use claude_codes::ClaudeOutput;
+
+/// Events buffered per subscriber before the oldest are dropped
+pub(crate) const CHANNEL_CAPACITY: usize = 256;
+
+/// Something a session did
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionEvent {
+    /// A text block in one of Claude's messages
+    AssistantText { text: String },
+    /// Claude called a tool
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    /// A tool call finished; `content` is its text
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+        is_error: bool,
+    },
+    /// Claude is asking to use a tool
+    PermissionRequest {
+        request_id: String,
+        tool_name: String,
+        input: serde_json::Value,
+    },
+    /// A turn ended
+    Result {
+        is_error: bool,
+        /// Claude's final text for the turn
+        text: Option<String>,
+        duration_ms: u64,
+        num_turns: i32,
+        total_cost_usd: f64,
+    },
+    /// The Claude process exited
+    ProcessExited { code: i32 },
+    /// A line the Claude process wrote to stderr
+    Stderr { line: String },
+}
+
+impl SessionEvent {
+    /// The events in one output, in order. Outputs with nothing to report,
+    /// like system messages and thinking, have none.
+    pub fn from_output(output: &ClaudeOutput) -> Vec<SessionEvent> {
+        match output {
+            ClaudeOutput::Assistant(asst) => {
+                asst.message.content.iter().filter_map(from_block).collect()
+            }
+            ClaudeOutput::User(user) => user
+                .message
+                .content
+                .iter()
+                .filter(|block| matches!(block, ContentBlock::ToolResult(_)))
+                .filter_map(from_block)
+                .collect(),
+            ClaudeOutput::Result(res) => vec![SessionEvent::Result {
+                is_error: res.is_error,
+                text: res.result.clone(),
+                duration_ms: res.duration_ms,
+                num_turns: res.num_turns,
+                total_cost_usd: res.total_cost_usd,
+            }],
+            ClaudeOutput::ControlRequest(req) => match &req.request {
+                ControlRequestPayload::CanUseTool(tool_req) => {
+                    vec![SessionEvent::PermissionRequest {
+                        request_id: req.request_id.clone(),
+                        tool_name: tool_req.tool_name.clone(),
+                        input: tool_req.input.clone(),
+                    }]
+                }
+                _ => vec![],
+            },
+            _ => vec![],
+        }
+    }
+}
+
+fn from_block(block: &ContentBlock) -> Option<SessionEvent> {
+    match block {
+        ContentBlock::Text(t) => Some(SessionEvent::AssistantText {
+            text: t.text.clone(),
+        }),
+        ContentBlock::ToolUse(tu) => Some(SessionEvent::ToolUse {
+            id: tu.id.clone(),
+            name: tu.name.clone(),
+            input: tu.input.clone(),
+        }),
+        ContentBlock::ToolResult(tr) => Some(SessionEvent::ToolResult {
+            tool_use_id: tr.tool_use_id.clone(),
+            content: tr.content.as_ref().map(result_text).unwrap_or_default(),
+            is_error: tr.is_error.unwrap_or(false),
+        }),
+        _ => None,
+    }
+}
+
+/// The text of a tool result; structured results are joined from their
+/// text parts
+fn result_text(content: &ToolResultContent) -> String {
+    match content {
+        ToolResultContent::Text(text) => text.clone(),
+        ToolResultContent::Structured(parts) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(json: serde_json::Value) -> ClaudeOutput {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_assistant_blocks() {
+        let events = SessionEvent::from_output(&output(serde_json::json!({
+            "type": "assistant",
+            "session_id": "s",
+            "message": {
+                "id": "m", "role": "assistant", "model": "claude",
+                "content": [
+                    {"type": "thinking", "thinking": "hmm", "signature": "x"},
+                    {"type": "text", "text": "Listing files"},
+                    {"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "ls"}}
+                ]
+            }
+        })));
+        assert_eq!(
+            events,
+            vec![
+                SessionEvent::AssistantText {
+                    text: "Listing files".to_string()
+                },
+                SessionEvent::ToolUse {
+                    id: "t1".to_string(),
+                    name: "Bash".to_string(),
+                    input: serde_json::json!({"command": "ls"}),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tool_results() {
+        let events = SessionEvent::from_output(&output(serde_json::json!({
+            "type": "user",
+            "session_id": "8a0c5f7e-1d7b-4a39-9b0e-2f6a7d4c1e55",
+            "message": {
+                "role": "user",
+                "content": [
+                    {"type": "tool_result", "tool_use_id": "t1", "content": "a.rs"},
+                    {"type": "tool_result", "tool_use_id": "t2", "is_error": true,
+                     "content": [{"type": "text", "text": "no such file"}]}
+                ]
+            }
+        })));
+        assert_eq!(
+            events,
+            vec![
+                SessionEvent::ToolResult {
+                    tool_use_id: "t1".to_string(),
+                    content: "a.rs".to_string(),
+                    is_error: false,
+                },
+                SessionEvent::ToolResult {
+                    tool_use_id: "t2".to_string(),
+                    content: "no such file".to_string(),
+                    is_error: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_result() {
+        let events = SessionEvent::from_output(&output(serde_json::json!({
+            "type": "result",
+            "subtype": "success",
+            "is_error": false,
+            "duration_ms": 1200,
+            "duration_api_ms": 1000,
+            "num_turns": 2,
+            "result": "DONE",
+            "session_id": "s",
+            "total_cost_usd": 0.01
+        })));
+        assert_eq!(
+            events,
+            vec![SessionEvent::Result {
+                is_error: false,
+                text: Some("DONE".to_string()),
+                duration_ms: 1200,
+                num_turns: 2,
+                total_cost_usd: 0.01,
+            }]
+        );
+    }
+}
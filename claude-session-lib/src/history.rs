@@ -0,0 +1,172 @@
+//! Conversations in Claude's local session store
+//!
+//! Claude Code keeps each conversation as a JSONL transcript under
+//! `~/.claude/projects/<directory>/<session-id>.jsonl` (or under
+//! `CLAUDE_CONFIG_DIR`), where `<directory>` is the working directory with
+//! every character other than a letter or digit replaced by `-`. Only
+//! conversations started in a directory can be resumed there, so these
+//! helpers look in that directory's folder alone.
+
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use uuid::Uuid;
+
+/// Lines read from each transcript when looking for its title
+const TITLE_SCAN_LINES: usize = 200;
+
+/// A conversation that `claude --resume` can pick up
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredConversation {
+    pub session_id: Uuid,
+    /// When the transcript was last written
+    pub modified: SystemTime,
+    /// Claude's summary of the conversation, else its first prompt
+    pub title: Option<String>,
+}
+
+/// Claude's config directory: `CLAUDE_CONFIG_DIR`, else `~/.claude`
+pub fn claude_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("CLAUDE_CONFIG_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".claude"))
+}
+
+/// Folder under `projects` holding the conversations started in `cwd`
+pub fn project_folder(cwd: &Path) -> String {
+    cwd.to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// Transcript of conversation `session_id` started in `cwd`
+pub fn transcript_path(claude_dir: &Path, cwd: &Path, session_id: Uuid) -> PathBuf {
+    claude_dir
+        .join("projects")
+        .join(project_folder(cwd))
+        .join(format!("{}.jsonl", session_id))
+}
+
+/// Whether conversation `session_id` can be resumed in `cwd`
+pub fn conversation_exists(cwd: &Path, session_id: Uuid) -> bool {
+    claude_dir().is_some_and(|dir| transcript_path(&dir, cwd, session_id).is_file())
+}
+
+/// Conversations started in `cwd`, most recently written first, at most
+/// `limit` of them
+pub fn recent_conversations(cwd: &Path, limit: usize) -> Vec<StoredConversation> {
+    let Some(dir) = claude_dir() else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(dir.join("projects").join(project_folder(cwd))) else {
+        return Vec::new();
+    };
+
+    let mut found: Vec<(Uuid, SystemTime, PathBuf)> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != "jsonl" {
+                return None;
+            }
+            let session_id = path.file_stem()?.to_str()?.parse().ok()?;
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((session_id, modified, path))
+        })
+        .collect();
+    found.sort_by_key(|(_, modified, _)| std::cmp::Reverse(*modified));
+    found.truncate(limit);
+
+    found
+        .into_iter()
+        .map(|(session_id, modified, path)| StoredConversation {
+            session_id,
+            modified,
+            title: std::fs::File::open(&path).ok().and_then(|file| {
+                title(std::io::BufReader::new(file).lines().map_while(Result::ok))
+            }),
+        })
+        .collect()
+}
+
+/// Text of a transcript line that's a prompt someone typed
+fn prompt_text(line: &serde_json::Value) -> Option<String> {
+    if line.get("type")?.as_str()? != "user"
+        || line.get("isMeta").and_then(|v| v.as_bool()) == Some(true)
+        || line.get("isCompactSummary").and_then(|v| v.as_bool()) == Some(true)
+    {
+        return None;
+    }
+    let text = match line.pointer("/message/content")? {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Array(blocks) => blocks
+            .iter()
+            .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("text"))
+            .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join(" "),
+        _ => return None,
+    };
+    // Slash commands and their output are recorded as tagged user lines
+    let text = text.trim();
+    (!text.is_empty() && !text.starts_with('<')).then(|| text.to_string())
+}
+
+/// A transcript's title: Claude's summary if it wrote one, else the first
+/// prompt, on one line
+fn title(lines: impl Iterator<Item = String>) -> Option<String> {
+    let mut first_prompt = None;
+    for line in lines.take(TITLE_SCAN_LINES) {
+        let Ok(line) = serde_json::from_str::<serde_json::Value>(&line) else {
+            continue;
+        };
+        if line.get("type").and_then(|t| t.as_str()) == Some("summary") {
+            if let Some(summary) = line.get("summary").and_then(|s| s.as_str()) {
+                return Some(summary.to_string());
+            }
+        }
+        if first_prompt.is_none() {
+            first_prompt = prompt_text(&line);
+        }
+    }
+    first_prompt.map(|text| text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_folder() {
+        assert_eq!(project_folder(Path::new("/root/crate")), "-root-crate");
+        assert_eq!(
+            project_folder(Path::new("/home/me/my.app_v2")),
+            "-home-me-my-app-v2"
+        );
+    }
+
+    #[test]
+    fn test_title() {
+        let transcript = [
+            r#"{"type":"queue-operation","operation":"enqueue"}"#,
+            r#"{"type":"user","isMeta":true,"message":{"content":"Caveat: ..."}}"#,
+            r#"{"type":"user","message":{"content":"<command-name>/clear</command-name>"}}"#,
+            r#"{"type":"user","message":{"role":"user","content":"Fix the\nflaky test"}}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"text","text":"On it"}]}}"#,
+        ]
+        .map(str::to_string);
+        assert_eq!(
+            title(transcript.clone().into_iter()).as_deref(),
+            Some("Fix the flaky test")
+        );
+
+        let summary = r#"{"type":"summary","summary":"Flaky test fix","leafUuid":"x"}"#;
+        let summarized = std::iter::once(summary.to_string()).chain(transcript);
+        assert_eq!(title(summarized).as_deref(), Some("Flaky test fix"));
+        assert_eq!(title(std::iter::empty()), None);
+    }
+}
@@ -7,14 +7,23 @@
 //! # Overview
 //!
 //! The library provides:
-//! - `Session` - A managed Claude Code session with event-based API
+//! - `Session` - A managed Claude Code session, driven with `next_update`
+//! - `SessionEvent` - Typed events (text, tool calls and results, permission
+//!   requests, turn results, exits, stderr) broadcast to `subscribe_events`
 //! - `SessionSnapshot` - Serializable session state for persistence
+//! - `SnapshotStore` - Where snapshots are kept: a local directory, or S3/GCS
+//!   with the `cloud-snapshots` feature
+//! - `SnapshotKeys` - Optional AES-256-GCM encryption of snapshots at rest
+//! - `SnapshotScheduler` - Saves snapshots periodically and on significant events
+//! - `SessionState` - The session's lifecycle, with changes broadcast as `StateChanged`
 //! - `OutputBuffer` - Buffer for replay on session restore
+//! - `discovery` - Finding the claude binary, optionally at a pinned version
+//! - `history` - Conversations in Claude's local session store, for resuming
 //!
 //! # Example
 //!
 //! ```ignore
-//! use claude_session_lib::{Session, SessionConfig, SessionEvent, PermissionResponse};
+//! use claude_session_lib::{Session, SessionConfig, SessionUpdate, PermissionResponse};
 //! use uuid::Uuid;
 //!
 //! #[tokio::main]
@@ -25,6 +34,9 @@
 //!         session_name: "my-session".to_string(),
 //!         resume: false,
 //!         claude_path: None,
+//!         claude_version: None,
+//!         extra_args: vec![],
+//!         buffer_size: None,
 //!     };
 //!
 //!     let mut session = Session::new(config).await?;
@@ -32,21 +44,21 @@
 //!     // Send initial input
 //!     session.send_input(serde_json::json!("Hello!")).await?;
 //!
-//!     // Process events
-//!     while let Some(event) = session.next_event().await {
-//!         match event {
-//!             SessionEvent::Output(output) => {
+//!     // Process updates
+//!     while let Some(update) = session.next_update().await {
+//!         match update {
+//!             SessionUpdate::Output(output) => {
 //!                 println!("Claude: {:?}", output);
 //!             }
-//!             SessionEvent::PermissionRequest { request_id, tool_name, .. } => {
+//!             SessionUpdate::PermissionRequest { request_id, tool_name, .. } => {
 //!                 // Auto-approve for this example
 //!                 session.respond_permission(&request_id, PermissionResponse::allow()).await?;
 //!             }
-//!             SessionEvent::Exited { code } => {
+//!             SessionUpdate::Exited { code } => {
 //!                 println!("Session exited with code {}", code);
 //!                 break;
 //!             }
-//!             SessionEvent::Error(e) => {
+//!             SessionUpdate::Error(e) => {
 //!                 eprintln!("Error: {}", e);
 //!                 break;
 //!             }
@@ -58,15 +70,29 @@
 //! ```
 
 pub mod buffer;
+pub mod discovery;
+pub mod encryption;
 pub mod error;
+pub mod events;
+pub mod history;
+pub mod scheduler;
 pub mod session;
 pub mod snapshot;
+pub mod state;
+pub mod store;
 
 // Re-export main types at crate root
 pub use buffer::{BufferedOutput, OutputBuffer};
+pub use encryption::SnapshotKeys;
 pub use error::SessionError;
-pub use session::{PermissionResponse, Session, SessionEvent};
+pub use events::SessionEvent;
+pub use scheduler::{SnapshotScheduler, SnapshotTrigger};
+pub use session::{PermissionResponse, Session, SessionUpdate};
 pub use snapshot::{PendingPermission, SessionConfig, SessionSnapshot};
+pub use state::{SessionState, StateChanged};
+#[cfg(feature = "cloud-snapshots")]
+pub use store::ObjectSnapshotStore;
+pub use store::{DirectorySnapshotStore, SnapshotStore};
 
 // Re-export claude_codes types that appear in our public API
 pub use claude_codes::io::PermissionSuggestion;
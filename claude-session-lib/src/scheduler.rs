@@ -0,0 +1,244 @@
+//! Automatic snapshotting
+//!
+//! A [`SnapshotScheduler`] saves a session's snapshot to a [`SnapshotStore`]
+//! every so often, and straight away when something happens that would be
+//! expensive to lose: a permission request, the process exiting, or the
+//! output buffer filling up. Together with the store's atomic saves, a
+//! crash loses at most one interval of session state.
+//!
+//! The scheduler doesn't own the session; it sits in the caller's event
+//! loop, fed from the session's typed event stream:
+//!
+//! ```ignore
+//! let mut scheduler = SnapshotScheduler::new(store, SnapshotScheduler::DEFAULT_INTERVAL);
+//! let mut events = session.subscribe_events();
+//! loop {
+//!     tokio::select! {
+//!         trigger = scheduler.tick() => scheduler.save(&session, trigger).await?,
+//!         Ok(event) = events.recv() => {
+//!             scheduler.observe(&event, &session).await?;
+//!         }
+//!         Some(update) = session.next_update() => {
+//!             // handle the update
+//!         }
+//!     }
+//! }
+//! ```
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+use tracing::debug;
+
+use crate::error::SessionError;
+use crate::events::SessionEvent;
+use crate::session::Session;
+use crate::store::SnapshotStore;
+
+/// Why a snapshot was saved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotTrigger {
+    /// The interval passed without another save
+    Interval,
+    /// Claude is waiting on a permission decision
+    PermissionPending,
+    /// The Claude process exited
+    ProcessExited,
+    /// The output buffer filled past the high-water mark
+    BufferHighWater,
+}
+
+/// Decides when a session's snapshot is saved, and saves it
+pub struct SnapshotScheduler {
+    store: Arc<dyn SnapshotStore>,
+    interval: Interval,
+    /// Fraction of the output buffer that counts as nearly full
+    high_water: f64,
+    /// Whether the buffer was past the high-water mark at the last event,
+    /// so a save happens when it crosses the mark rather than on every output
+    above_high_water: bool,
+}
+
+impl SnapshotScheduler {
+    /// Default time between periodic saves
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(10);
+
+    /// Default fraction of the output buffer that triggers a save
+    pub const DEFAULT_HIGH_WATER: f64 = 0.8;
+
+    /// Save to `store` every `period`, and on significant events
+    pub fn new(store: Arc<dyn SnapshotStore>, period: Duration) -> Self {
+        // The first periodic save is a period from now, not immediately
+        let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Self {
+            store,
+            interval,
+            high_water: Self::DEFAULT_HIGH_WATER,
+            above_high_water: false,
+        }
+    }
+
+    /// Save when the output buffer is this full, as a fraction of its size
+    pub fn with_high_water(mut self, fraction: f64) -> Self {
+        self.high_water = fraction;
+        self
+    }
+
+    /// Wait until a periodic save is due
+    pub async fn tick(&mut self) -> SnapshotTrigger {
+        self.interval.tick().await;
+        SnapshotTrigger::Interval
+    }
+
+    /// Whether an event the session just produced calls for a save
+    pub fn trigger(&mut self, event: &SessionEvent, session: &Session) -> Option<SnapshotTrigger> {
+        match event {
+            SessionEvent::PermissionRequest { .. } => Some(SnapshotTrigger::PermissionPending),
+            SessionEvent::ProcessExited { .. } => Some(SnapshotTrigger::ProcessExited),
+            _ => {
+                let size = session.output_buffer_size().max(1);
+                let fill = session.pending_output_count() as f64 / size as f64;
+                let above = fill >= self.high_water;
+                let crossed = above && !self.above_high_water;
+                self.above_high_water = above;
+                crossed.then_some(SnapshotTrigger::BufferHighWater)
+            }
+        }
+    }
+
+    /// Save the session's snapshot now. The next periodic save is a full
+    /// interval later.
+    pub async fn save(
+        &mut self,
+        session: &Session,
+        trigger: SnapshotTrigger,
+    ) -> Result<(), SessionError> {
+        self.store.save(&session.snapshot()).await?;
+        self.interval.reset();
+        debug!("Saved snapshot of session {} ({:?})", session.id(), trigger);
+        Ok(())
+    }
+
+    /// Save if the event calls for it, returning why
+    pub async fn observe(
+        &mut self,
+        event: &SessionEvent,
+        session: &Session,
+    ) -> Result<Option<SnapshotTrigger>, SessionError> {
+        let trigger = self.trigger(event, session);
+        if let Some(trigger) = trigger {
+            self.save(session, trigger).await?;
+        }
+        Ok(trigger)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::BufferedOutput;
+    use crate::snapshot::{SessionConfig, SessionSnapshot};
+    use crate::store::DirectorySnapshotStore;
+    use uuid::Uuid;
+
+    /// An exited session holding `outputs` pending outputs out of `size`
+    async fn session(outputs: u64, size: usize) -> Session {
+        let config = SessionConfig {
+            session_id: Uuid::new_v4(),
+            buffer_size: Some(size),
+            ..SessionConfig::default()
+        };
+        let pending = (0..outputs)
+            .map(|seq| BufferedOutput {
+                seq,
+                content: serde_json::json!(seq),
+                timestamp: chrono::Utc::now(),
+            })
+            .collect();
+        let snapshot = SessionSnapshot::new(config.session_id, config, pending, None, false);
+        Session::restore(snapshot).await.unwrap()
+    }
+
+    fn scheduler(store: Arc<dyn SnapshotStore>) -> SnapshotScheduler {
+        SnapshotScheduler::new(store, SnapshotScheduler::DEFAULT_INTERVAL)
+    }
+
+    fn text_event() -> SessionEvent {
+        SessionEvent::AssistantText {
+            text: "Working on it".to_string(),
+        }
+    }
+
+    fn result_event() -> SessionEvent {
+        SessionEvent::Result {
+            is_error: false,
+            text: Some("Done".to_string()),
+            duration_ms: 1000,
+            num_turns: 1,
+            total_cost_usd: 0.01,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_significant_events_trigger() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut scheduler = scheduler(Arc::new(DirectorySnapshotStore::new(dir.path())));
+        let session = session(1, 10).await;
+
+        let permission = SessionEvent::PermissionRequest {
+            request_id: "perm-1".to_string(),
+            tool_name: "Bash".to_string(),
+            input: serde_json::json!({}),
+        };
+        assert_eq!(
+            scheduler.trigger(&permission, &session),
+            Some(SnapshotTrigger::PermissionPending)
+        );
+        assert_eq!(
+            scheduler.trigger(&SessionEvent::ProcessExited { code: 0 }, &session),
+            Some(SnapshotTrigger::ProcessExited)
+        );
+        assert_eq!(scheduler.trigger(&text_event(), &session), None);
+        assert_eq!(scheduler.trigger(&result_event(), &session), None);
+    }
+
+    #[tokio::test]
+    async fn test_high_water_triggers_once_per_crossing() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut scheduler =
+            scheduler(Arc::new(DirectorySnapshotStore::new(dir.path()))).with_high_water(0.5);
+
+        let full = session(5, 10).await;
+        assert_eq!(
+            scheduler.trigger(&text_event(), &full),
+            Some(SnapshotTrigger::BufferHighWater)
+        );
+        assert_eq!(scheduler.trigger(&result_event(), &full), None);
+
+        let drained = session(1, 10).await;
+        assert_eq!(scheduler.trigger(&text_event(), &drained), None);
+        assert_eq!(
+            scheduler.trigger(&result_event(), &full),
+            Some(SnapshotTrigger::BufferHighWater)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_observe_saves_to_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(DirectorySnapshotStore::new(dir.path()));
+        let mut scheduler = scheduler(store.clone());
+        let session = session(2, 10).await;
+
+        let trigger = scheduler.observe(&result_event(), &session).await.unwrap();
+        assert_eq!(trigger, None);
+        assert!(store.load(session.id()).await.unwrap().is_none());
+
+        let exited = SessionEvent::ProcessExited { code: 0 };
+        let trigger = scheduler.observe(&exited, &session).await.unwrap();
+        assert_eq!(trigger, Some(SnapshotTrigger::ProcessExited));
+        let saved = store.load(session.id()).await.unwrap().unwrap();
+        assert_eq!(saved.pending_outputs.len(), 2);
+    }
+}
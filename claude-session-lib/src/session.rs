@@ -3,17 +3,22 @@
 use chrono::Utc;
 use claude_codes::io::{ControlResponse, PermissionResult};
 use claude_codes::{AsyncClient, ClaudeInput, ClaudeOutput};
-use std::path::Path;
+use tokio::io::AsyncBufReadExt;
 use tokio::process::Command;
+use tokio::sync::broadcast;
 use uuid::Uuid;
 
 use crate::buffer::OutputBuffer;
 use crate::error::SessionError;
+use crate::events::{self, SessionEvent};
 use crate::snapshot::{PendingPermission, SessionConfig, SessionSnapshot};
+use crate::state::{SessionState, StateChanged, StateMachine};
 
-/// Events emitted by a session
+/// What `Session::next_update` returns: everything a caller driving the
+/// session must act on. Typed events for observers are on
+/// `Session::subscribe_events`.
 #[derive(Debug)]
-pub enum SessionEvent {
+pub enum SessionUpdate {
     /// Claude produced output (excluding permission requests, which have their own event)
     Output(ClaudeOutput),
 
@@ -115,42 +120,35 @@ impl PermissionResponse {
     }
 }
 
-/// Internal session state
-enum SessionState {
-    Running,
-    WaitingForPermission {
-        #[allow(dead_code)]
-        request_id: String,
-    },
-    Exited {
-        code: i32,
-    },
-}
-
 /// A managed Claude Code session
 pub struct Session {
     id: Uuid,
     config: SessionConfig,
     client: Option<AsyncClient>,
     buffer: OutputBuffer,
-    state: SessionState,
+    state: StateMachine,
     pending_permission: Option<PendingPermission>,
+    events: broadcast::Sender<SessionEvent>,
 }
 
 impl Session {
     /// Create a new session (spawns Claude process)
     pub async fn new(config: SessionConfig) -> Result<Self, SessionError> {
-        let buffer = OutputBuffer::new(config.session_id);
-        let client = Self::spawn_claude(&config).await?;
-
-        Ok(Self {
+        let buffer = match config.buffer_size {
+            Some(max_size) => OutputBuffer::with_max_size(config.session_id, max_size),
+            None => OutputBuffer::new(config.session_id),
+        };
+        let mut session = Self {
             id: config.session_id,
             config,
-            client: Some(client),
+            client: None,
             buffer,
-            state: SessionState::Running,
+            state: StateMachine::new(),
             pending_permission: None,
-        })
+            events: broadcast::channel(events::CHANNEL_CAPACITY).0,
+        };
+        session.spawn().await?;
+        Ok(session)
     }
 
     /// Restore a session from a snapshot
@@ -158,46 +156,40 @@ impl Session {
     /// This restores the buffer and pending permission state,
     /// then spawns a new Claude process with --resume.
     pub async fn restore(snapshot: SessionSnapshot) -> Result<Self, SessionError> {
-        let buffer = OutputBuffer::from_snapshot(snapshot.id, snapshot.pending_outputs);
+        let mut buffer = OutputBuffer::from_snapshot(snapshot.id, snapshot.pending_outputs);
+        if let Some(max_size) = snapshot.config.buffer_size {
+            buffer.set_max_size(max_size);
+        }
 
         // Always resume when restoring
         let mut config = snapshot.config;
         config.resume = true;
 
-        let client = if snapshot.was_running {
-            Some(Self::spawn_claude(&config).await?)
-        } else {
-            None
-        };
-
-        let state = if client.is_some() {
-            SessionState::Running
-        } else {
-            SessionState::Exited { code: 0 }
-        };
-
-        Ok(Self {
+        let mut session = Self {
             id: snapshot.id,
             config,
-            client,
+            client: None,
             buffer,
-            state,
+            state: StateMachine::new(),
             pending_permission: snapshot.pending_permission,
-        })
+            events: broadcast::channel(events::CHANNEL_CAPACITY).0,
+        };
+        if snapshot.was_running {
+            session.spawn().await?;
+        } else {
+            session.state.transition(SessionState::Exited { code: 0 })?;
+        }
+        Ok(session)
     }
 
     /// Serialize current state for persistence
     pub fn snapshot(&self) -> SessionSnapshot {
-        let was_running = matches!(
-            self.state,
-            SessionState::Running | SessionState::WaitingForPermission { .. }
-        );
         SessionSnapshot::new(
             self.id,
             self.config.clone(),
             self.buffer.to_snapshot(),
             self.pending_permission.clone(),
-            was_running,
+            self.state.state().is_running(),
         )
     }
 
@@ -211,11 +203,40 @@ impl Session {
         &self.config
     }
 
-    /// Poll for the next event
+    /// Where the session is in its lifecycle
+    pub fn state(&self) -> &SessionState {
+        self.state.state()
+    }
+
+    /// Receive every state change from now on
+    pub fn subscribe_state(&self) -> broadcast::Receiver<StateChanged> {
+        self.state.subscribe()
+    }
+
+    /// Receive every typed event from now on. Events are sent as
+    /// `next_update` reads Claude's output, and as Claude writes to stderr.
+    pub fn subscribe_events(&self) -> broadcast::Receiver<SessionEvent> {
+        self.events.subscribe()
+    }
+
+    /// Send to subscribers; nobody listening is fine
+    fn emit(&self, event: SessionEvent) {
+        let _ = self.events.send(event);
+    }
+
+    /// The process is gone
+    fn exited(&mut self, code: i32) {
+        self.state.observe(SessionState::Exited { code });
+        self.client = None;
+        self.emit(SessionEvent::ProcessExited { code });
+    }
+
+    /// Poll for the next update
     ///
-    /// Returns `None` if the session has exited and no more events are available.
+    /// Returns `None` if the session has exited or is paused and no more
+    /// events are available.
     /// Use this in a loop with other async operations via `tokio::select!`.
-    pub async fn next_event(&mut self) -> Option<SessionEvent> {
+    pub async fn next_update(&mut self) -> Option<SessionUpdate> {
         // Loop to skip internal messages (ControlResponse)
         loop {
             // Poll Claude for output
@@ -226,6 +247,9 @@ impl Session {
                     // Buffer the output
                     let output_value = serde_json::to_value(&output).unwrap_or_default();
                     self.buffer.push(output_value);
+                    for event in SessionEvent::from_output(&output) {
+                        self.emit(event);
+                    }
 
                     // Check for "No conversation found" error (session not found locally)
                     if let ClaudeOutput::Result(ref res) = output {
@@ -235,10 +259,11 @@ impl Session {
                                 .iter()
                                 .any(|e| e.contains("No conversation found"))
                         {
-                            self.state = SessionState::Exited { code: 1 };
-                            self.client = None;
-                            return Some(SessionEvent::SessionNotFound);
+                            self.exited(1);
+                            return Some(SessionUpdate::SessionNotFound);
                         }
+                        // The turn is over
+                        self.state.observe(SessionState::Ready);
                     }
 
                     // Check for permission requests - emit as PermissionRequest, not Output
@@ -253,12 +278,16 @@ impl Session {
                                 input: tool_req.input.clone(),
                                 requested_at: Utc::now(),
                             });
-                            self.state = SessionState::WaitingForPermission {
+                            if *self.state.state() == SessionState::Ready {
+                                // A turn we didn't start, e.g. one resumed after a restore
+                                self.state.observe(SessionState::InTurn);
+                            }
+                            self.state.observe(SessionState::AwaitingPermission {
                                 request_id: request_id.clone(),
-                            };
+                            });
 
                             // Emit PermissionRequest (not Output) for permission requests
-                            return Some(SessionEvent::PermissionRequest {
+                            return Some(SessionUpdate::PermissionRequest {
                                 request_id,
                                 tool_name: tool_req.tool_name.clone(),
                                 input: tool_req.input.clone(),
@@ -273,17 +302,16 @@ impl Session {
                         continue;
                     }
 
-                    return Some(SessionEvent::Output(output));
+                    return Some(SessionUpdate::Output(output));
                 }
                 Err(e) => {
                     // Check if process exited
                     let err_str = e.to_string();
                     if err_str.contains("exit") || err_str.contains("terminated") {
-                        self.state = SessionState::Exited { code: 1 };
-                        self.client = None;
-                        return Some(SessionEvent::Exited { code: 1 });
+                        self.exited(1);
+                        return Some(SessionUpdate::Exited { code: 1 });
                     }
-                    return Some(SessionEvent::Error(SessionError::ClaudeError(e)));
+                    return Some(SessionUpdate::Error(SessionError::ClaudeError(e)));
                 }
             }
         }
@@ -294,9 +322,7 @@ impl Session {
     /// The content can be a JSON string value for plain text,
     /// or a more complex JSON structure if needed.
     pub async fn send_input(&mut self, content: serde_json::Value) -> Result<(), SessionError> {
-        if let SessionState::Exited { code } = self.state {
-            return Err(SessionError::AlreadyExited(code));
-        }
+        self.check_accepts_input()?;
 
         if let Some(ref mut client) = self.client {
             // Extract string content or serialize to string
@@ -311,6 +337,33 @@ impl Session {
                 .map_err(SessionError::ClaudeError)?;
         }
 
+        // Input sent mid-turn (or while a permission is pending) is queued
+        // by Claude and doesn't change the state
+        if *self.state.state() == SessionState::Ready {
+            self.state.transition(SessionState::InTurn)?;
+        }
+        Ok(())
+    }
+
+    /// Ask Claude to stop the turn it's working on
+    ///
+    /// Sent as a stream-json `interrupt` control request. Claude finishes the
+    /// turn with a result and reads the next input as usual.
+    pub async fn interrupt(&mut self) -> Result<(), SessionError> {
+        self.check_accepts_input()?;
+
+        if let Some(ref mut client) = self.client {
+            let request = ClaudeInput::Raw(serde_json::json!({
+                "type": "control_request",
+                "request_id": format!("interrupt-{}", Uuid::new_v4()),
+                "request": { "subtype": "interrupt" },
+            }));
+            client
+                .send(&request)
+                .await
+                .map_err(SessionError::ClaudeError)?;
+        }
+
         Ok(())
     }
 
@@ -364,7 +417,9 @@ impl Session {
         }
 
         self.pending_permission = None;
-        self.state = SessionState::Running;
+        if matches!(self.state.state(), SessionState::AwaitingPermission { .. }) {
+            self.state.transition(SessionState::InTurn)?;
+        }
 
         Ok(())
     }
@@ -374,16 +429,36 @@ impl Session {
         if let Some(client) = self.client.take() {
             drop(client); // This should terminate the process
         }
-        self.state = SessionState::Exited { code: 0 };
+        if !matches!(self.state.state(), SessionState::Exited { .. }) {
+            self.state.transition(SessionState::Exited { code: 0 })?;
+            self.emit(SessionEvent::ProcessExited { code: 0 });
+        }
         Ok(())
     }
 
+    /// Stop the claude process, keeping the conversation to pick up again
+    /// with `unpause`. A turn in progress is cut short.
+    pub async fn pause(&mut self) -> Result<(), SessionError> {
+        self.state.transition(SessionState::Paused)?;
+        self.client = None;
+        Ok(())
+    }
+
+    /// Start the claude process again after `pause`, resuming the conversation
+    pub async fn unpause(&mut self) -> Result<(), SessionError> {
+        if *self.state.state() != SessionState::Paused {
+            return Err(SessionError::InvalidTransition {
+                from: self.state.state().clone(),
+                to: SessionState::Spawning,
+            });
+        }
+        self.config.resume = true;
+        self.spawn().await
+    }
+
     /// Check if session is still running
     pub fn is_running(&self) -> bool {
-        matches!(
-            self.state,
-            SessionState::Running | SessionState::WaitingForPermission { .. }
-        )
+        self.state.state().is_running()
     }
 
     /// Check if session has a pending permission request
@@ -406,9 +481,47 @@ impl Session {
         self.buffer.pending_count()
     }
 
+    /// Most pending outputs kept before the oldest are dropped
+    pub fn output_buffer_size(&self) -> usize {
+        self.buffer.max_size()
+    }
+
+    /// Fail if the session can no longer take input
+    fn check_accepts_input(&self) -> Result<(), SessionError> {
+        match self.state.state() {
+            SessionState::Exited { code } => Err(SessionError::AlreadyExited(*code)),
+            SessionState::Ready
+            | SessionState::InTurn
+            | SessionState::AwaitingPermission { .. } => Ok(()),
+            other => Err(SessionError::InvalidTransition {
+                from: other.clone(),
+                to: SessionState::InTurn,
+            }),
+        }
+    }
+
+    /// Spawn the claude process, moving through `Spawning` to `Ready`
+    async fn spawn(&mut self) -> Result<(), SessionError> {
+        self.state.transition(SessionState::Spawning)?;
+        match Self::spawn_claude(&self.config).await {
+            Ok(mut client) => {
+                if let Some(stderr) = client.take_stderr() {
+                    tokio::spawn(forward_stderr(stderr, self.events.clone()));
+                }
+                self.client = Some(client);
+                self.state.transition(SessionState::Ready)
+            }
+            Err(e) => {
+                self.state.observe(SessionState::Exited { code: 1 });
+                Err(e)
+            }
+        }
+    }
+
     /// Spawn the Claude process
     async fn spawn_claude(config: &SessionConfig) -> Result<AsyncClient, SessionError> {
-        let claude_path = config.claude_path.as_deref().unwrap_or(Path::new("claude"));
+        let claude_path = crate::discovery::find_claude(config).await?;
+        let claude_path = claude_path.as_path();
 
         let mut cmd = Command::new(claude_path);
         cmd.arg("--print")
@@ -472,3 +585,16 @@ impl Session {
         })
     }
 }
+
+/// Send each line Claude writes to stderr as an event until it closes it.
+/// Reading also keeps the pipe from filling and blocking Claude.
+async fn forward_stderr(
+    stderr: tokio::io::BufReader<tokio::process::ChildStderr>,
+    events: broadcast::Sender<SessionEvent>,
+) {
+    let mut lines = stderr.lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        tracing::debug!("claude stderr: {}", line);
+        let _ = events.send(SessionEvent::Stderr { line });
+    }
+}
@@ -6,6 +6,8 @@ use std::path::PathBuf;
 use uuid::Uuid;
 
 use crate::buffer::BufferedOutput;
+use crate::encryption::{self, SnapshotKeys};
+use crate::error::SessionError;
 
 /// Configuration for creating a session
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -18,11 +20,18 @@ pub struct SessionConfig {
     pub session_name: String,
     /// Whether to resume an existing Claude session (vs create new)
     pub resume: bool,
-    /// Optional path to claude binary (defaults to "claude" in PATH)
+    /// Path to the claude binary; searched for if unset (see `discovery`)
     pub claude_path: Option<PathBuf>,
+    /// Claude version to run, e.g. "2.1.17" or "2.1"; a `.claude-version`
+    /// file in the project pins it if unset
+    #[serde(default)]
+    pub claude_version: Option<String>,
     /// Extra arguments to pass to the claude CLI
     #[serde(default)]
     pub extra_args: Vec<String>,
+    /// Outputs kept for replay; `OutputBuffer::DEFAULT_MAX_SIZE` if unset
+    #[serde(default)]
+    pub buffer_size: Option<usize>,
 }
 
 /// A pending permission request that hasn't been responded to
@@ -84,8 +93,41 @@ impl SessionSnapshot {
 
     /// Deserialize snapshot from JSON bytes
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        if encryption::sealed_with(bytes).is_some() {
+            return Err(serde::de::Error::custom(
+                "snapshot is encrypted; snapshot keys are needed to read it",
+            ));
+        }
         serde_json::from_slice(bytes)
     }
+
+    /// Serialize, sealed with the current key if there are keys
+    pub fn to_bytes_with(&self, keys: Option<&SnapshotKeys>) -> Result<Vec<u8>, SessionError> {
+        let bytes = self.to_bytes()?;
+        match keys {
+            Some(keys) => keys.encrypt(&bytes),
+            None => Ok(bytes),
+        }
+    }
+
+    /// Deserialize a sealed snapshot with the key that sealed it. Plain
+    /// snapshots load only without keys, or with keys that allow them.
+    pub fn from_bytes_with(
+        bytes: &[u8],
+        keys: Option<&SnapshotKeys>,
+    ) -> Result<Self, SessionError> {
+        match (encryption::sealed_with(bytes), keys) {
+            (None, Some(keys)) if !keys.allows_plaintext() => Err(SessionError::EncryptionError(
+                "Snapshot is not encrypted, and snapshot keys are set".to_string(),
+            )),
+            (None, _) => Ok(Self::from_bytes(bytes)?),
+            (Some(_), Some(keys)) => Ok(serde_json::from_slice(&keys.decrypt(bytes)?)?),
+            (Some(id), None) => Err(SessionError::EncryptionError(format!(
+                "Snapshot is encrypted with key {}, and no snapshot keys are set",
+                id
+            ))),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -99,7 +141,9 @@ mod tests {
             session_name: "test-session".to_string(),
             resume: false,
             claude_path: None,
+            claude_version: None,
             extra_args: vec![],
+            buffer_size: None,
         }
     }
 
@@ -173,6 +217,40 @@ mod tests {
         assert_eq!(restored.pending_permission.unwrap().tool_name, "Write");
     }
 
+    #[test]
+    fn test_encrypted_snapshot_roundtrip() {
+        let config = sample_config();
+        let snapshot = SessionSnapshot::new(config.session_id, config, vec![], None, true);
+        let keys = SnapshotKeys::new("k1", &[3; encryption::KEY_LEN]).unwrap();
+
+        let bytes = snapshot.to_bytes_with(Some(&keys)).unwrap();
+        assert!(!String::from_utf8_lossy(&bytes).contains("test-session"));
+        assert!(SessionSnapshot::from_bytes(&bytes).is_err());
+        assert!(SessionSnapshot::from_bytes_with(&bytes, None).is_err());
+
+        let restored = SessionSnapshot::from_bytes_with(&bytes, Some(&keys)).unwrap();
+        assert_eq!(restored.id, snapshot.id);
+        assert_eq!(restored.config.session_name, "test-session");
+
+        // Plain snapshots only load with keys that allow them
+        let plain = snapshot.to_bytes().unwrap();
+        assert!(SessionSnapshot::from_bytes_with(&plain, None).is_ok());
+        assert!(
+            SessionSnapshot::from_bytes_with(&plain, Some(&keys.clone().allow_plaintext())).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_plaintext_rejected_with_keys() {
+        let config = sample_config();
+        let snapshot = SessionSnapshot::new(config.session_id, config, vec![], None, false);
+        let keys = SnapshotKeys::new("k1", &[3; encryption::KEY_LEN]).unwrap();
+
+        let plain = snapshot.to_bytes().unwrap();
+        let err = SessionSnapshot::from_bytes_with(&plain, Some(&keys)).unwrap_err();
+        assert!(matches!(err, SessionError::EncryptionError(_)));
+    }
+
     #[test]
     fn test_snapshot_without_pending_permission() {
         let config = sample_config();
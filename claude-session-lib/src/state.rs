@@ -0,0 +1,214 @@
+//! Session lifecycle
+//!
+//! A session moves through `SessionState`s as its process is spawned,
+//! takes turns, waits on permission, and exits. Only the transitions in
+//! `SessionState::can_transition_to` are allowed; each one is broadcast as a
+//! `StateChanged` to whoever subscribed with `Session::subscribe_state`.
+
+use std::fmt;
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+use crate::error::SessionError;
+
+/// State changes buffered per subscriber before the oldest are dropped
+const CHANNEL_CAPACITY: usize = 32;
+
+/// Where a session is in its lifecycle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionState {
+    /// Configured but no process started yet
+    Created,
+    /// The claude process is starting
+    Spawning,
+    /// Idle, waiting for input
+    Ready,
+    /// Claude is working on a turn
+    InTurn,
+    /// Claude is blocked on a permission request
+    AwaitingPermission { request_id: String },
+    /// The process was stopped but the conversation can be resumed
+    Paused,
+    /// The process is gone for good
+    Exited { code: i32 },
+}
+
+impl SessionState {
+    /// Short lowercase name, e.g. "awaiting_permission"
+    pub fn name(&self) -> &'static str {
+        match self {
+            SessionState::Created => "created",
+            SessionState::Spawning => "spawning",
+            SessionState::Ready => "ready",
+            SessionState::InTurn => "in_turn",
+            SessionState::AwaitingPermission { .. } => "awaiting_permission",
+            SessionState::Paused => "paused",
+            SessionState::Exited { .. } => "exited",
+        }
+    }
+
+    /// Whether the claude process is up
+    pub fn is_running(&self) -> bool {
+        matches!(
+            self,
+            SessionState::Ready | SessionState::InTurn | SessionState::AwaitingPermission { .. }
+        )
+    }
+
+    /// Whether the session may move from this state to `next`
+    pub fn can_transition_to(&self, next: &SessionState) -> bool {
+        use SessionState::*;
+        match (self, next) {
+            // Nothing comes back from exiting
+            (Exited { .. }, _) => false,
+            (_, Exited { .. }) => true,
+            (Created, Spawning) => true,
+            (Spawning, Ready) => true,
+            (Ready, InTurn) => true,
+            (InTurn, Ready | AwaitingPermission { .. }) => true,
+            // Parallel tool uses can ask one after another
+            (AwaitingPermission { .. }, InTurn | AwaitingPermission { .. }) => true,
+            (Ready | InTurn | AwaitingPermission { .. }, Paused) => true,
+            (Paused, Spawning) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for SessionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionState::Exited { code } => write!(f, "exited ({})", code),
+            other => f.write_str(other.name()),
+        }
+    }
+}
+
+/// A transition that happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateChanged {
+    pub from: SessionState,
+    pub to: SessionState,
+}
+
+/// The current state and the channel its changes are broadcast on
+pub(crate) struct StateMachine {
+    state: SessionState,
+    tx: broadcast::Sender<StateChanged>,
+}
+
+impl StateMachine {
+    pub(crate) fn new() -> Self {
+        let (tx, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self {
+            state: SessionState::Created,
+            tx,
+        }
+    }
+
+    pub(crate) fn state(&self) -> &SessionState {
+        &self.state
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<StateChanged> {
+        self.tx.subscribe()
+    }
+
+    /// Move to `to`, or fail if that isn't allowed from here. Moving to the
+    /// state the session is already in does nothing.
+    pub(crate) fn transition(&mut self, to: SessionState) -> Result<(), SessionError> {
+        if self.state == to {
+            return Ok(());
+        }
+        if !self.state.can_transition_to(&to) {
+            return Err(SessionError::InvalidTransition {
+                from: self.state.clone(),
+                to,
+            });
+        }
+        debug!("Session state {} -> {}", self.state, to);
+        let from = std::mem::replace(&mut self.state, to.clone());
+        // No subscribers is fine
+        let _ = self.tx.send(StateChanged { from, to });
+        Ok(())
+    }
+
+    /// Move to `to` because of something Claude did; a transition that isn't
+    /// allowed is logged and ignored rather than failing the session
+    pub(crate) fn observe(&mut self, to: SessionState) {
+        if let Err(e) = self.transition(to) {
+            warn!("{}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn awaiting(id: &str) -> SessionState {
+        SessionState::AwaitingPermission {
+            request_id: id.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_lifecycle_transitions() {
+        let mut machine = StateMachine::new();
+        let mut rx = machine.subscribe();
+
+        for state in [
+            SessionState::Spawning,
+            SessionState::Ready,
+            SessionState::InTurn,
+            awaiting("a"),
+            awaiting("b"),
+            SessionState::InTurn,
+            SessionState::Ready,
+            SessionState::Paused,
+            SessionState::Spawning,
+            SessionState::Ready,
+            SessionState::Exited { code: 0 },
+        ] {
+            machine.transition(state).unwrap();
+        }
+
+        let first = rx.try_recv().unwrap();
+        assert_eq!(first.from, SessionState::Created);
+        assert_eq!(first.to, SessionState::Spawning);
+        assert_eq!(rx.len(), 10);
+    }
+
+    #[test]
+    fn test_invalid_transitions() {
+        let mut machine = StateMachine::new();
+        let err = machine.transition(SessionState::InTurn).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid session state transition: created -> in_turn"
+        );
+
+        machine.transition(SessionState::Spawning).unwrap();
+        machine.transition(SessionState::Ready).unwrap();
+        assert!(machine.transition(awaiting("a")).is_err());
+        assert!(machine.transition(SessionState::Spawning).is_err());
+
+        machine
+            .transition(SessionState::Exited { code: 1 })
+            .unwrap();
+        assert!(machine.transition(SessionState::Spawning).is_err());
+        assert!(machine
+            .transition(SessionState::Exited { code: 0 })
+            .is_err());
+    }
+
+    #[test]
+    fn test_same_state_is_not_broadcast() {
+        let mut machine = StateMachine::new();
+        let mut rx = machine.subscribe();
+        machine.transition(SessionState::Spawning).unwrap();
+        machine.transition(SessionState::Spawning).unwrap();
+        assert!(rx.try_recv().is_ok());
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,362 @@
+//! Where session snapshots are kept
+//!
+//! A [`SnapshotStore`] saves, loads, lists and deletes [`SessionSnapshot`]s
+//! by session ID, so a service can restore its sessions after a restart.
+//! [`DirectorySnapshotStore`] keeps them as files in a local directory.
+//! With the `cloud-snapshots` feature, [`ObjectSnapshotStore`] keeps them in
+//! S3 or GCS instead, for containers whose filesystem doesn't survive a
+//! restart.
+
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::encryption::SnapshotKeys;
+use crate::error::SessionError;
+use crate::snapshot::SessionSnapshot;
+
+/// Snapshots are stored as `<session id>.json`
+const SNAPSHOT_EXTENSION: &str = "json";
+
+/// Storage for session snapshots, one per session
+#[async_trait]
+pub trait SnapshotStore: Send + Sync {
+    /// Save a snapshot, replacing any earlier one for the same session
+    async fn save(&self, snapshot: &SessionSnapshot) -> Result<(), SessionError>;
+
+    /// The session's snapshot, if it has one
+    async fn load(&self, id: Uuid) -> Result<Option<SessionSnapshot>, SessionError>;
+
+    /// IDs of every session with a snapshot, in no particular order
+    async fn list(&self) -> Result<Vec<Uuid>, SessionError>;
+
+    /// Remove the session's snapshot; removing one that isn't there is fine
+    async fn delete(&self, id: Uuid) -> Result<(), SessionError>;
+}
+
+fn file_name(id: Uuid) -> String {
+    format!("{}.{}", id, SNAPSHOT_EXTENSION)
+}
+
+/// The session ID a stored file name belongs to, if it's a snapshot
+fn parse_file_name(name: &str) -> Option<Uuid> {
+    let stem = name.strip_suffix(SNAPSHOT_EXTENSION)?.strip_suffix('.')?;
+    Uuid::parse_str(stem).ok()
+}
+
+fn storage_error(
+    action: &str,
+    what: impl std::fmt::Display,
+    e: impl std::fmt::Display,
+) -> SessionError {
+    SessionError::StorageError(format!("{} {}: {}", action, what, e))
+}
+
+async fn write_synced(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    use tokio::io::AsyncWriteExt;
+    let mut file = tokio::fs::File::create(path).await?;
+    file.write_all(bytes).await?;
+    file.sync_all().await
+}
+
+/// Snapshots as files in a local directory
+#[derive(Debug, Clone)]
+pub struct DirectorySnapshotStore {
+    dir: PathBuf,
+    keys: Option<SnapshotKeys>,
+}
+
+impl DirectorySnapshotStore {
+    /// Store snapshots in `dir`, which is created on the first save
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            keys: None,
+        }
+    }
+
+    /// Encrypt snapshots with these keys (see [`crate::encryption`])
+    pub fn with_keys(mut self, keys: SnapshotKeys) -> Self {
+        self.keys = Some(keys);
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, id: Uuid) -> PathBuf {
+        self.dir.join(file_name(id))
+    }
+}
+
+#[async_trait]
+impl SnapshotStore for DirectorySnapshotStore {
+    async fn save(&self, snapshot: &SessionSnapshot) -> Result<(), SessionError> {
+        let bytes = snapshot.to_bytes_with(self.keys.as_ref())?;
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| storage_error("creating", self.dir.display(), e))?;
+        // Write beside the snapshot, flush it to disk, and rename over it,
+        // so a crash mid-write leaves the previous snapshot rather than half
+        // of this one
+        let path = self.path(snapshot.id);
+        let partial = path.with_extension("partial");
+        write_synced(&partial, &bytes)
+            .await
+            .map_err(|e| storage_error("writing", partial.display(), e))?;
+        tokio::fs::rename(&partial, &path)
+            .await
+            .map_err(|e| storage_error("replacing", path.display(), e))
+    }
+
+    async fn load(&self, id: Uuid) -> Result<Option<SessionSnapshot>, SessionError> {
+        let path = self.path(id);
+        match tokio::fs::read(&path).await {
+            Ok(bytes) => Ok(Some(SessionSnapshot::from_bytes_with(
+                &bytes,
+                self.keys.as_ref(),
+            )?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(storage_error("reading", path.display(), e)),
+        }
+    }
+
+    async fn list(&self) -> Result<Vec<Uuid>, SessionError> {
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(storage_error("listing", self.dir.display(), e)),
+        };
+        let mut ids = Vec::new();
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| storage_error("listing", self.dir.display(), e))?
+        {
+            if let Some(id) = entry.file_name().to_str().and_then(parse_file_name) {
+                ids.push(id);
+            }
+        }
+        Ok(ids)
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<(), SessionError> {
+        let path = self.path(id);
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(storage_error("deleting", path.display(), e)),
+        }
+    }
+}
+
+#[cfg(feature = "cloud-snapshots")]
+pub use cloud::ObjectSnapshotStore;
+
+#[cfg(feature = "cloud-snapshots")]
+mod cloud {
+    use super::*;
+    use object_store::aws::AmazonS3Builder;
+    use object_store::gcp::GoogleCloudStorageBuilder;
+    use object_store::path::Path as ObjectPath;
+    use object_store::{ObjectStore, PutPayload};
+    use std::sync::Arc;
+
+    /// Snapshots as objects in a bucket, under a prefix
+    #[derive(Debug, Clone)]
+    pub struct ObjectSnapshotStore {
+        store: Arc<dyn ObjectStore>,
+        prefix: ObjectPath,
+        keys: Option<SnapshotKeys>,
+    }
+
+    impl ObjectSnapshotStore {
+        /// Store snapshots in `store` under `prefix`
+        pub fn new(store: Arc<dyn ObjectStore>, prefix: &str) -> Self {
+            Self {
+                store,
+                prefix: ObjectPath::from(prefix),
+                keys: None,
+            }
+        }
+
+        /// Encrypt snapshots with these keys (see [`crate::encryption`])
+        pub fn with_keys(mut self, keys: SnapshotKeys) -> Self {
+            self.keys = Some(keys);
+            self
+        }
+
+        /// A store for a URL such as `s3://bucket/snapshots` or
+        /// `gs://bucket/snapshots`. Credentials and region come from the
+        /// provider's usual environment variables (`AWS_*`, `GOOGLE_*`).
+        pub fn from_url(url: &str) -> Result<Self, SessionError> {
+            let invalid = |why: &str| {
+                SessionError::StorageError(format!("Invalid snapshot store URL {}: {}", url, why))
+            };
+            let (scheme, rest) = url
+                .split_once("://")
+                .ok_or_else(|| invalid("expected s3:// or gs://"))?;
+            let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+            if bucket.is_empty() {
+                return Err(invalid("no bucket"));
+            }
+            let store: Arc<dyn ObjectStore> = match scheme {
+                "s3" => Arc::new(
+                    AmazonS3Builder::from_env()
+                        .with_bucket_name(bucket)
+                        .build()
+                        .map_err(|e| invalid(&e.to_string()))?,
+                ),
+                "gs" => Arc::new(
+                    GoogleCloudStorageBuilder::from_env()
+                        .with_bucket_name(bucket)
+                        .build()
+                        .map_err(|e| invalid(&e.to_string()))?,
+                ),
+                _ => return Err(invalid("expected s3:// or gs://")),
+            };
+            Ok(Self::new(store, prefix))
+        }
+
+        fn path(&self, id: Uuid) -> ObjectPath {
+            self.prefix.child(file_name(id))
+        }
+    }
+
+    #[async_trait]
+    impl SnapshotStore for ObjectSnapshotStore {
+        async fn save(&self, snapshot: &SessionSnapshot) -> Result<(), SessionError> {
+            // Object puts are atomic: readers see the old snapshot or the new one
+            let path = self.path(snapshot.id);
+            self.store
+                .put(
+                    &path,
+                    PutPayload::from(snapshot.to_bytes_with(self.keys.as_ref())?),
+                )
+                .await
+                .map_err(|e| storage_error("writing", &path, e))?;
+            Ok(())
+        }
+
+        async fn load(&self, id: Uuid) -> Result<Option<SessionSnapshot>, SessionError> {
+            let path = self.path(id);
+            let result = match self.store.get(&path).await {
+                Ok(result) => result,
+                Err(object_store::Error::NotFound { .. }) => return Ok(None),
+                Err(e) => return Err(storage_error("reading", &path, e)),
+            };
+            let bytes = result
+                .bytes()
+                .await
+                .map_err(|e| storage_error("reading", &path, e))?;
+            Ok(Some(SessionSnapshot::from_bytes_with(
+                &bytes,
+                self.keys.as_ref(),
+            )?))
+        }
+
+        async fn list(&self) -> Result<Vec<Uuid>, SessionError> {
+            let listing = self
+                .store
+                .list_with_delimiter(Some(&self.prefix))
+                .await
+                .map_err(|e| storage_error("listing", &self.prefix, e))?;
+            Ok(listing
+                .objects
+                .iter()
+                .filter_map(|object| object.location.filename().and_then(parse_file_name))
+                .collect())
+        }
+
+        async fn delete(&self, id: Uuid) -> Result<(), SessionError> {
+            let path = self.path(id);
+            match self.store.delete(&path).await {
+                Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+                Err(e) => Err(storage_error("deleting", &path, e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::SessionConfig;
+
+    fn snapshot() -> SessionSnapshot {
+        let config = SessionConfig {
+            session_id: Uuid::new_v4(),
+            session_name: "stored".to_string(),
+            ..SessionConfig::default()
+        };
+        SessionSnapshot::new(config.session_id, config, vec![], None, true)
+    }
+
+    /// Save, load, list and delete through any store
+    async fn exercise(store: &dyn SnapshotStore) {
+        assert!(store.list().await.unwrap().is_empty());
+
+        let first = snapshot();
+        let second = snapshot();
+        store.save(&first).await.unwrap();
+        store.save(&second).await.unwrap();
+        let loaded = store.load(first.id).await.unwrap().unwrap();
+        assert_eq!(loaded.config.session_name, "stored");
+
+        let mut listed = store.list().await.unwrap();
+        listed.sort();
+        let mut expected = vec![first.id, second.id];
+        expected.sort();
+        assert_eq!(listed, expected);
+
+        store.delete(first.id).await.unwrap();
+        store.delete(first.id).await.unwrap();
+        assert!(store.load(first.id).await.unwrap().is_none());
+        assert_eq!(store.list().await.unwrap(), vec![second.id]);
+    }
+
+    #[test]
+    fn test_parse_file_name() {
+        let id = Uuid::new_v4();
+        assert_eq!(parse_file_name(&file_name(id)), Some(id));
+        assert_eq!(parse_file_name(&format!("{}.partial", id)), None);
+        assert_eq!(parse_file_name("notes.json"), None);
+    }
+
+    #[tokio::test]
+    async fn test_directory_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DirectorySnapshotStore::new(dir.path().join("snapshots"));
+        exercise(&store).await;
+    }
+
+    #[tokio::test]
+    async fn test_directory_store_encrypts() {
+        let dir = tempfile::tempdir().unwrap();
+        let keys = SnapshotKeys::new("k1", &[5; crate::encryption::KEY_LEN]).unwrap();
+        let store = DirectorySnapshotStore::new(dir.path()).with_keys(keys);
+        exercise(&store).await;
+
+        let saved = snapshot();
+        store.save(&saved).await.unwrap();
+        let bytes = std::fs::read(dir.path().join(file_name(saved.id))).unwrap();
+        assert_eq!(crate::encryption::sealed_with(&bytes), Some("k1"));
+
+        // A plain snapshot put in its place is refused
+        std::fs::write(
+            dir.path().join(file_name(saved.id)),
+            saved.to_bytes().unwrap(),
+        )
+        .unwrap();
+        assert!(store.load(saved.id).await.is_err());
+    }
+
+    #[cfg(feature = "cloud-snapshots")]
+    #[tokio::test]
+    async fn test_object_store() {
+        let memory = std::sync::Arc::new(object_store::memory::InMemory::new());
+        let store = ObjectSnapshotStore::new(memory, "snapshots");
+        exercise(&store).await;
+    }
+}
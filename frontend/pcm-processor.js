@@ -7,10 +7,17 @@
  * Audio spec:
  * - Input: Float32 samples in range [-1, 1] at AudioContext sample rate
  * - Output: Int16 samples in range [-32768, 32767] at 16kHz mono
+ *
+ * Chunks of silence aren't sent: only speech, about a second after it (so
+ * the speech engine still hears the pause), and the chunk just before it.
+ *
+ * processorOptions:
+ * - silenceTimeoutSecs: silence after speech before auto-stop, 0 for never
  */
 class PCMProcessor extends AudioWorkletProcessor {
-    constructor() {
+    constructor(options) {
         super();
+        const processorOptions = (options && options.processorOptions) || {};
 
         // Buffer to accumulate samples before sending
         // 4096 samples at 16kHz = ~256ms per chunk
@@ -34,11 +41,24 @@ class PCMProcessor extends AudioWorkletProcessor {
         this.smoothedVolume = 0;
         this.smoothingFactor = 0.3; // Lower = smoother (0.3 means 30% new, 70% old)
 
+        // Clipping detection - peak of the raw input since the last volume report
+        this.peak = 0;
+        this.clipLevel = 0.99; // Samples this close to full scale are clipped
+
         // Silence detection - auto-stop after sustained silence
         this.silenceThreshold = 0.01; // RMS level below which is considered silence
         this.silenceDuration = 0; // How long we've been in silence (in samples)
-        this.silenceTimeout = 32000; // ~2 seconds at 16kHz before auto-stop
+        const timeoutSecs = processorOptions.silenceTimeoutSecs ?? 2;
+        this.silenceTimeout = timeoutSecs * 16000; // Samples at 16kHz before auto-stop, 0 = never
         this.hasSpokenOnce = false; // Only trigger timeout after speech has started
+        this.silenceSignalled = false; // Auto-stop already requested
+
+        // Silence gating - don't stream silence to the speech engine
+        this.chunkHasSpeech = false; // Whether the chunk being filled has speech in it
+        this.hangover = 16000; // ~1 second of silence still sent after speech
+        this.preroll = null; // Last held-back chunk, sent ahead of the next speech
+        this.heldChunks = 0; // Chunks held back in a row
+        this.keepaliveChunks = 16; // Send one silent chunk every ~4s so streams don't time out
 
         // Listen for control messages from main thread
         this.port.onmessage = (event) => {
@@ -62,11 +82,42 @@ class PCMProcessor extends AudioWorkletProcessor {
         const samplesToSend = this.bufferIndex;
         if (samplesToSend === 0) return;
 
+        const hasSpeech = this.chunkHasSpeech;
+        this.chunkHasSpeech = false;
+        const inHangover = this.hasSpokenOnce && this.silenceDuration < this.hangover;
+        if (!hasSpeech && !inHangover) {
+            this.heldChunks++;
+            if (this.heldChunks % this.keepaliveChunks !== 0) {
+                this.preroll = this.buffer.slice(0, samplesToSend);
+                this.buffer = new Float32Array(this.bufferSize);
+                this.bufferIndex = 0;
+                return;
+            }
+        } else {
+            this.heldChunks = 0;
+            if (hasSpeech && this.preroll) {
+                this.sendSamples(this.preroll, this.preroll.length);
+            }
+        }
+        this.preroll = null;
+
+        this.sendSamples(this.buffer, samplesToSend);
+
+        // Reset buffer
+        this.buffer = new Float32Array(this.bufferSize);
+        this.bufferIndex = 0;
+    }
+
+    /**
+     * Convert Float32 samples to Int16 and send them to the main thread
+     */
+    sendSamples(samples, samplesToSend) {
+
         // Convert Float32 [-1, 1] to Int16 [-32768, 32767]
         const pcm16 = new Int16Array(samplesToSend);
         for (let i = 0; i < samplesToSend; i++) {
             // Clamp and scale
-            const sample = Math.max(-1, Math.min(1, this.buffer[i]));
+            const sample = Math.max(-1, Math.min(1, samples[i]));
             pcm16[i] = Math.floor(sample * 32767);
         }
 
@@ -75,10 +126,6 @@ class PCMProcessor extends AudioWorkletProcessor {
             { audioData: pcm16.buffer, samples: samplesToSend },
             [pcm16.buffer]
         );
-
-        // Reset buffer
-        this.buffer = new Float32Array(this.bufferSize);
-        this.bufferIndex = 0;
     }
 
     /**
@@ -100,6 +147,12 @@ class PCMProcessor extends AudioWorkletProcessor {
         // Simple resampling: skip samples to downsample from input rate to 16kHz
         // For better quality, a proper resampling filter could be used
         for (let i = 0; i < inputChannel.length; i++) {
+            // Peak is taken before resampling so no clipped sample is skipped
+            const magnitude = Math.abs(inputChannel[i]);
+            if (magnitude > this.peak) {
+                this.peak = magnitude;
+            }
+
             this.resampleAccumulator += 1;
 
             // Take a sample when we've accumulated enough input samples
@@ -120,22 +173,29 @@ class PCMProcessor extends AudioWorkletProcessor {
                     const rawLevel = Math.min(1.0, rms * 3);
                     // Apply exponential smoothing for less frantic display
                     this.smoothedVolume = this.smoothingFactor * rawLevel + (1 - this.smoothingFactor) * this.smoothedVolume;
-                    this.port.postMessage({ volumeLevel: this.smoothedVolume });
+                    this.port.postMessage({
+                        volumeLevel: this.smoothedVolume,
+                        clipping: this.peak >= this.clipLevel,
+                    });
+                    this.peak = 0;
 
                     // Silence detection
                     if (rms > this.silenceThreshold) {
                         // Sound detected - mark that speech has started and reset silence counter
                         this.hasSpokenOnce = true;
+                        this.chunkHasSpeech = true;
                         this.silenceDuration = 0;
+                        this.silenceSignalled = false;
                     } else if (this.hasSpokenOnce) {
                         // In silence after speech - accumulate silence duration
                         this.silenceDuration += this.volumeSampleCount;
 
                         // Check if we've exceeded the silence timeout
-                        if (this.silenceDuration >= this.silenceTimeout) {
-                            // Signal auto-stop due to silence
+                        if (this.silenceTimeout > 0 && !this.silenceSignalled
+                            && this.silenceDuration >= this.silenceTimeout) {
+                            // Signal auto-stop due to silence (once, so the gate stays closed)
                             this.port.postMessage({ silenceDetected: true });
-                            this.silenceDuration = 0; // Reset to avoid repeated signals
+                            this.silenceSignalled = true;
                         }
                     }
 
@@ -0,0 +1,240 @@
+//! Budget Settings
+//!
+//! Admin form for the soft and hard budget caps. An empty field means no cap.
+
+use gloo_net::http::Request;
+use shared::{BudgetCap, BudgetLimits};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::utils;
+
+/// Text of the six inputs, in the order soft/hard for each cap
+type CapFields = [String; 6];
+
+/// Rows of the form: label, unit, and index of the soft field
+const ROWS: [(&str, &str, usize); 3] = [
+    ("Per session", "USD", 0),
+    ("Per user per day", "USD", 2),
+    ("Tokens per session", "tokens", 4),
+];
+
+fn format_field(value: Option<f64>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn to_fields(limits: &BudgetLimits) -> CapFields {
+    [
+        format_field(limits.session_cost_usd.soft),
+        format_field(limits.session_cost_usd.hard),
+        format_field(limits.daily_cost_usd.soft),
+        format_field(limits.daily_cost_usd.hard),
+        format_field(limits.session_tokens.soft),
+        format_field(limits.session_tokens.hard),
+    ]
+}
+
+/// Empty means no cap; anything else must be a non-negative number
+fn parse_field(text: &str) -> Result<Option<f64>, String> {
+    let text = text.trim().trim_start_matches('$');
+    if text.is_empty() {
+        return Ok(None);
+    }
+    match text.replace(',', "").parse::<f64>() {
+        Ok(v) if v.is_finite() && v >= 0.0 => Ok(Some(v)),
+        _ => Err(format!("\"{}\" is not a valid limit", text)),
+    }
+}
+
+fn parse_cap(soft: &str, hard: &str, label: &str) -> Result<BudgetCap, String> {
+    let cap = BudgetCap {
+        soft: parse_field(soft)?,
+        hard: parse_field(hard)?,
+    };
+    if let (Some(soft), Some(hard)) = (cap.soft, cap.hard) {
+        if soft > hard {
+            return Err(format!("{}: soft limit is above the hard limit", label));
+        }
+    }
+    Ok(cap)
+}
+
+fn to_limits(fields: &CapFields) -> Result<BudgetLimits, String> {
+    Ok(BudgetLimits {
+        session_cost_usd: parse_cap(&fields[0], &fields[1], ROWS[0].0)?,
+        daily_cost_usd: parse_cap(&fields[2], &fields[3], ROWS[1].0)?,
+        session_tokens: parse_cap(&fields[4], &fields[5], ROWS[2].0)?,
+    })
+}
+
+#[function_component(BudgetSettings)]
+pub fn budget_settings() -> Html {
+    let fields = use_state(CapFields::default);
+    let status = use_state(|| None::<Result<String, String>>);
+    let saving = use_state(|| false);
+
+    {
+        let fields = fields.clone();
+        let status = status.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                let api_endpoint = utils::api_url("/api/admin/budget");
+                match Request::get(&api_endpoint).send().await {
+                    Ok(response) if response.ok() => {
+                        if let Ok(limits) = response.json::<BudgetLimits>().await {
+                            fields.set(to_fields(&limits));
+                        }
+                    }
+                    Ok(response) => status.set(Some(Err(format!(
+                        "Failed to load budget limits: {}",
+                        response.status()
+                    )))),
+                    Err(e) => {
+                        status.set(Some(Err(format!("Failed to load budget limits: {:?}", e))))
+                    }
+                }
+            });
+            || ()
+        });
+    }
+
+    let on_input = |index: usize| {
+        let fields = fields.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut updated = (*fields).clone();
+            updated[index] = input.value();
+            fields.set(updated);
+        })
+    };
+
+    let on_submit = {
+        let fields = fields.clone();
+        let status = status.clone();
+        let saving = saving.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let limits = match to_limits(&fields) {
+                Ok(limits) => limits,
+                Err(message) => {
+                    status.set(Some(Err(message)));
+                    return;
+                }
+            };
+            let fields = fields.clone();
+            let status = status.clone();
+            let saving = saving.clone();
+            saving.set(true);
+            spawn_local(async move {
+                let api_endpoint = utils::api_url("/api/admin/budget");
+                let result = match Request::put(&api_endpoint).json(&limits) {
+                    Ok(request) => request.send().await,
+                    Err(e) => {
+                        status.set(Some(Err(format!("Failed to encode limits: {:?}", e))));
+                        saving.set(false);
+                        return;
+                    }
+                };
+                match result {
+                    Ok(response) if response.ok() => {
+                        if let Ok(saved) = response.json::<BudgetLimits>().await {
+                            fields.set(to_fields(&saved));
+                        }
+                        status.set(Some(Ok("Budget limits saved".to_string())));
+                    }
+                    Ok(response) => status.set(Some(Err(format!(
+                        "Failed to save budget limits: {}",
+                        response.status()
+                    )))),
+                    Err(e) => {
+                        status.set(Some(Err(format!("Failed to save budget limits: {:?}", e))))
+                    }
+                }
+                saving.set(false);
+            });
+        })
+    };
+
+    html! {
+        <form class="budget-settings" onsubmit={on_submit}>
+            <p class="budget-settings-description">
+                { "A soft limit shows a warning in the session. At a hard limit, the backend stops forwarding input and tells the proxy to stop any wiggum loop. Leave a field empty for no limit." }
+            </p>
+            <table class="admin-table budget-table">
+                <thead>
+                    <tr>
+                        <th>{ "Budget" }</th>
+                        <th>{ "Soft limit" }</th>
+                        <th>{ "Hard limit" }</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    { for ROWS.iter().map(|(label, unit, soft)| html! {
+                        <tr>
+                            <td>{ *label }</td>
+                            { for [*soft, soft + 1].into_iter().map(|index| html! {
+                                <td>
+                                    <input
+                                        type="text"
+                                        inputmode="decimal"
+                                        class="budget-input"
+                                        placeholder={format!("No limit ({})", unit)}
+                                        value={fields[index].clone()}
+                                        oninput={on_input(index)}
+                                    />
+                                </td>
+                            }) }
+                        </tr>
+                    }) }
+                </tbody>
+            </table>
+            <div class="budget-settings-actions">
+                <button type="submit" class="tab-btn active" disabled={*saving}>
+                    { if *saving { "Saving..." } else { "Save limits" } }
+                </button>
+                {
+                    match &*status {
+                        Some(Ok(message)) => html! { <span class="budget-saved">{ message }</span> },
+                        Some(Err(message)) => html! { <span class="budget-error">{ message }</span> },
+                        None => html! {},
+                    }
+                }
+            </div>
+        </form>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_field() {
+        assert_eq!(parse_field(""), Ok(None));
+        assert_eq!(parse_field(" $12.50 "), Ok(Some(12.5)));
+        assert_eq!(parse_field("1,000,000"), Ok(Some(1_000_000.0)));
+        assert!(parse_field("-1").is_err());
+        assert!(parse_field("lots").is_err());
+    }
+
+    #[test]
+    fn test_fields_roundtrip() {
+        let limits = BudgetLimits {
+            session_cost_usd: BudgetCap {
+                soft: Some(1.5),
+                hard: Some(5.0),
+            },
+            session_tokens: BudgetCap {
+                soft: None,
+                hard: Some(2_000_000.0),
+            },
+            ..Default::default()
+        };
+        assert_eq!(to_limits(&to_fields(&limits)), Ok(limits));
+
+        let mut fields = to_fields(&limits);
+        fields[0] = "10".to_string();
+        assert!(to_limits(&fields).unwrap_err().contains("Per session"));
+    }
+}
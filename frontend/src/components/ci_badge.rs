@@ -0,0 +1,58 @@
+//! CI Badge Component
+//!
+//! Shows how CI went on a session's branch, linking to the failed run (or
+//! the first run) on GitHub.
+
+use shared::{CiState, CiStatus};
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct CiBadgeProps {
+    pub status: CiStatus,
+    /// Just the icon, for session pills
+    #[prop_or(false)]
+    pub compact: bool,
+}
+
+fn icon(state: CiState) -> &'static str {
+    match state {
+        CiState::Pending => "●",
+        CiState::Success => "✓",
+        CiState::Failure => "✗",
+    }
+}
+
+#[function_component(CiBadge)]
+pub fn ci_badge(props: &CiBadgeProps) -> Html {
+    let status = &props.status;
+    let commit = status.head_sha.get(..7).unwrap_or(&status.head_sha);
+    let title = status
+        .runs
+        .iter()
+        .map(|run| format!("{} {}", icon(run.state), run.name))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let class = classes!(
+        "ci-badge",
+        status.state.as_str(),
+        props.compact.then_some("compact")
+    );
+
+    if props.compact {
+        return html! {
+            <span {class} title={format!("{} on {}\n{}", status.summary(), commit, title)}>
+                { icon(status.state) }
+            </span>
+        };
+    }
+
+    let label = format!("{} {} · {}", icon(status.state), status.summary(), commit);
+    match status.link() {
+        Some(url) => html! {
+            <a {class} {title} href={url.to_string()} target="_blank" rel="noopener noreferrer">
+                { label }
+            </a>
+        },
+        None => html! { <span {class} {title}>{ label }</span> },
+    }
+}
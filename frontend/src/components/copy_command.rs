@@ -1,6 +1,7 @@
 //! Copy Command Component
 //!
-//! A styled code block with a copy-to-clipboard button.
+//! A styled code block with a copy-to-clipboard button, and the button on its
+//! own for use alongside other content.
 
 use gloo::timers::callback::Timeout;
 use wasm_bindgen::JsCast;
@@ -17,16 +18,22 @@ pub struct CopyCommandProps {
     pub label: Option<String>,
 }
 
-#[function_component(CopyCommand)]
-pub fn copy_command(props: &CopyCommandProps) -> Html {
+#[derive(Properties, PartialEq, Clone)]
+pub struct CopyButtonProps {
+    /// The text to copy
+    pub text: String,
+}
+
+#[function_component(CopyButton)]
+pub fn copy_button(props: &CopyButtonProps) -> Html {
     let copied = use_state(|| false);
 
     let on_copy = {
-        let command = props.command.clone();
+        let text = props.text.clone();
         let copied = copied.clone();
 
         Callback::from(move |_: MouseEvent| {
-            let command = command.clone();
+            let text = text.clone();
             let copied = copied.clone();
 
             spawn_local(async move {
@@ -38,7 +45,7 @@ pub fn copy_command(props: &CopyCommandProps) -> Html {
                         .and_then(|v| v.dyn_into::<web_sys::Clipboard>().ok());
 
                     if let Some(clipboard) = clipboard {
-                        let promise = clipboard.write_text(&command);
+                        let promise = clipboard.write_text(&text);
                         let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
 
                         // Show "Copied!" feedback
@@ -64,6 +71,26 @@ pub fn copy_command(props: &CopyCommandProps) -> Html {
 
     let button_text = if *copied { "Copied!" } else { "" };
 
+    html! {
+        <button
+            class={button_class}
+            onclick={on_copy}
+            title="Copy to clipboard"
+        >
+            <span class="copy-icon">
+                // Clipboard SVG icon
+                <svg xmlns="http://www.w3.org/2000/svg" width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2" stroke-linecap="round" stroke-linejoin="round">
+                    <rect x="9" y="9" width="13" height="13" rx="2" ry="2"></rect>
+                    <path d="M5 15H4a2 2 0 0 1-2-2V4a2 2 0 0 1 2-2h9a2 2 0 0 1 2 2v1"></path>
+                </svg>
+            </span>
+            <span class="copy-text">{ button_text }</span>
+        </button>
+    }
+}
+
+#[function_component(CopyCommand)]
+pub fn copy_command(props: &CopyCommandProps) -> Html {
     html! {
         <div class="copy-command-container">
             if let Some(label) = &props.label {
@@ -71,20 +98,7 @@ pub fn copy_command(props: &CopyCommandProps) -> Html {
             }
             <div class="copy-command-block">
                 <pre class="copy-command-text">{ &props.command }</pre>
-                <button
-                    class={button_class}
-                    onclick={on_copy}
-                    title="Copy to clipboard"
-                >
-                    <span class="copy-icon">
-                        // Clipboard SVG icon
-                        <svg xmlns="http://www.w3.org/2000/svg" width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2" stroke-linecap="round" stroke-linejoin="round">
-                            <rect x="9" y="9" width="13" height="13" rx="2" ry="2"></rect>
-                            <path d="M5 15H4a2 2 0 0 1-2-2V4a2 2 0 0 1 2-2h9a2 2 0 0 1 2 2v1"></path>
-                        </svg>
-                    </span>
-                    <span class="copy-text">{ button_text }</span>
-                </button>
+                <CopyButton text={props.command.clone()} />
             </div>
         </div>
     }
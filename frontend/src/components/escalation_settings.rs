@@ -0,0 +1,266 @@
+//! Escalation Settings
+//!
+//! Per-session form for what happens when a permission request waits too
+//! long: who to notify and when, and which answer to apply after that. An
+//! empty delay skips the step. Only the session owner can change it.
+
+use gloo_net::http::Request;
+use shared::{EscalationPolicy, RuleDecision};
+use uuid::Uuid;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+use crate::utils;
+
+/// Text of the form's inputs
+#[derive(Clone, Default, PartialEq)]
+struct PolicyFields {
+    notify_after: String,
+    notify_email: String,
+    default_after: String,
+    allow_by_default: bool,
+}
+
+fn to_fields(policy: &EscalationPolicy) -> PolicyFields {
+    let minutes = |m: Option<u32>| m.map(|m| m.to_string()).unwrap_or_default();
+    PolicyFields {
+        notify_after: minutes(policy.notify_after_minutes),
+        notify_email: policy.notify_email.clone().unwrap_or_default(),
+        default_after: minutes(policy.default_after_minutes),
+        allow_by_default: policy.default_action == RuleDecision::Allow,
+    }
+}
+
+/// Empty means the step is off; anything else must be whole minutes
+fn parse_minutes(text: &str) -> Result<Option<u32>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    text.parse::<u32>()
+        .map(Some)
+        .map_err(|_| format!("\"{}\" is not a number of minutes", text))
+}
+
+fn to_policy(fields: &PolicyFields) -> Result<EscalationPolicy, String> {
+    let notify_after_minutes = parse_minutes(&fields.notify_after)?;
+    let email = fields.notify_email.trim();
+    let policy = EscalationPolicy {
+        notify_after_minutes,
+        notify_email: (notify_after_minutes.is_some() && !email.is_empty())
+            .then(|| email.to_string()),
+        default_after_minutes: parse_minutes(&fields.default_after)?,
+        default_action: if fields.allow_by_default {
+            RuleDecision::Allow
+        } else {
+            RuleDecision::Deny
+        },
+    };
+    policy.validate()?;
+    Ok(policy)
+}
+
+#[derive(Properties, PartialEq)]
+pub struct EscalationSettingsProps {
+    pub session_id: Uuid,
+    /// Whether the current user owns the session
+    pub editable: bool,
+}
+
+#[function_component(EscalationSettings)]
+pub fn escalation_settings(props: &EscalationSettingsProps) -> Html {
+    let fields = use_state(PolicyFields::default);
+    let status = use_state(|| None::<Result<String, String>>);
+    let saving = use_state(|| false);
+    let api_endpoint = utils::api_url(&format!("/api/sessions/{}/escalation", props.session_id));
+
+    {
+        let fields = fields.clone();
+        let status = status.clone();
+        let api_endpoint = api_endpoint.clone();
+        use_effect_with(props.session_id, move |_| {
+            spawn_local(async move {
+                match Request::get(&api_endpoint).send().await {
+                    Ok(response) if response.ok() => {
+                        if let Ok(policy) = response.json::<EscalationPolicy>().await {
+                            fields.set(to_fields(&policy));
+                        }
+                    }
+                    Ok(response) => status.set(Some(Err(format!(
+                        "Failed to load escalation: {}",
+                        response.status()
+                    )))),
+                    Err(e) => status.set(Some(Err(format!("Failed to load escalation: {:?}", e)))),
+                }
+            });
+            || ()
+        });
+    }
+
+    let on_text = |update: fn(&mut PolicyFields, String)| {
+        let fields = fields.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut updated = (*fields).clone();
+            update(&mut updated, input.value());
+            fields.set(updated);
+        })
+    };
+
+    let on_action_change = {
+        let fields = fields.clone();
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            let mut updated = (*fields).clone();
+            updated.allow_by_default = select.value() == "allow";
+            fields.set(updated);
+        })
+    };
+
+    let on_submit = {
+        let fields = fields.clone();
+        let status = status.clone();
+        let saving = saving.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let policy = match to_policy(&fields) {
+                Ok(policy) => policy,
+                Err(message) => {
+                    status.set(Some(Err(message)));
+                    return;
+                }
+            };
+            let fields = fields.clone();
+            let status = status.clone();
+            let saving = saving.clone();
+            let api_endpoint = api_endpoint.clone();
+            saving.set(true);
+            spawn_local(async move {
+                let result = match Request::put(&api_endpoint).json(&policy) {
+                    Ok(request) => request.send().await,
+                    Err(e) => {
+                        status.set(Some(Err(format!("Failed to encode escalation: {:?}", e))));
+                        saving.set(false);
+                        return;
+                    }
+                };
+                match result {
+                    Ok(response) if response.ok() => {
+                        if let Ok(saved) = response.json::<EscalationPolicy>().await {
+                            fields.set(to_fields(&saved));
+                        }
+                        status.set(Some(Ok("Escalation saved".to_string())));
+                    }
+                    Ok(response) if response.status() == 400 => status.set(Some(Err(
+                        "The contact must be a member of this session who can answer requests"
+                            .to_string(),
+                    ))),
+                    Ok(response) => status.set(Some(Err(format!(
+                        "Failed to save escalation: {}",
+                        response.status()
+                    )))),
+                    Err(e) => status.set(Some(Err(format!("Failed to save escalation: {:?}", e)))),
+                }
+                saving.set(false);
+            });
+        })
+    };
+
+    let disabled = !props.editable;
+    html! {
+        <form class="escalation-settings" onsubmit={on_submit}>
+            <h3>{ "When nobody answers" }</h3>
+            <div class="escalation-row">
+                <span>{ "After" }</span>
+                <input
+                    type="text"
+                    inputmode="numeric"
+                    class="escalation-minutes"
+                    placeholder="—"
+                    value={fields.notify_after.clone()}
+                    oninput={on_text(|f, v| f.notify_after = v)}
+                    {disabled}
+                />
+                <span>{ "minutes, notify" }</span>
+                <input
+                    type="email"
+                    class="escalation-email"
+                    placeholder="member@example.com"
+                    value={fields.notify_email.clone()}
+                    oninput={on_text(|f, v| f.notify_email = v)}
+                    {disabled}
+                />
+            </div>
+            <div class="escalation-row">
+                <span>{ "After" }</span>
+                <input
+                    type="text"
+                    inputmode="numeric"
+                    class="escalation-minutes"
+                    placeholder="—"
+                    value={fields.default_after.clone()}
+                    oninput={on_text(|f, v| f.default_after = v)}
+                    {disabled}
+                />
+                <span>{ "minutes," }</span>
+                <select onchange={on_action_change} {disabled}>
+                    <option value="deny" selected={!fields.allow_by_default}>{ "deny" }</option>
+                    <option value="allow" selected={fields.allow_by_default}>{ "allow" }</option>
+                </select>
+                <span>{ "the request" }</span>
+            </div>
+            if props.editable {
+                <div class="escalation-actions">
+                    <button type="submit" class="tab-btn active" disabled={*saving}>
+                        { if *saving { "Saving..." } else { "Save" } }
+                    </button>
+                    {
+                        match &*status {
+                            Some(Ok(message)) => html! { <span class="budget-saved">{ message }</span> },
+                            Some(Err(message)) => html! { <span class="budget-error">{ message }</span> },
+                            None => html! {},
+                        }
+                    }
+                </div>
+            } else {
+                <p class="escalation-note">{ "Only the session owner can change this." }</p>
+            }
+        </form>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields_roundtrip() {
+        let policy = EscalationPolicy {
+            notify_after_minutes: Some(15),
+            notify_email: Some("oncall@example.com".to_string()),
+            default_after_minutes: Some(60),
+            default_action: RuleDecision::Allow,
+        };
+        assert_eq!(to_policy(&to_fields(&policy)), Ok(policy));
+        assert_eq!(
+            to_policy(&PolicyFields::default()),
+            Ok(EscalationPolicy::default())
+        );
+    }
+
+    #[test]
+    fn test_invalid_fields() {
+        let fields = PolicyFields {
+            notify_after: "soon".to_string(),
+            ..Default::default()
+        };
+        assert!(to_policy(&fields).is_err());
+
+        let no_contact = PolicyFields {
+            notify_after: "10".to_string(),
+            ..Default::default()
+        };
+        assert!(to_policy(&no_contact).is_err());
+    }
+}
@@ -0,0 +1,51 @@
+//! Fork Dialog Component
+//!
+//! Shows the `claude-portal fork` command that branches a new session from a
+//! message. The fork runs on the machine and in the directory of the original
+//! session, so the dialog only hands over the command to run there.
+
+use shared::fork_command;
+use uuid::Uuid;
+use yew::prelude::*;
+
+use super::CopyCommand;
+
+#[derive(Properties, PartialEq)]
+pub struct ForkDialogProps {
+    pub session_id: Uuid,
+    pub working_directory: AttrValue,
+    /// The last message the fork replays
+    pub message_id: Uuid,
+    pub on_close: Callback<()>,
+}
+
+#[function_component(ForkDialog)]
+pub fn fork_dialog(props: &ForkDialogProps) -> Html {
+    let command = fork_command(&props.working_directory, props.session_id, props.message_id);
+
+    let on_close = props.on_close.clone();
+    let on_overlay_click = {
+        let on_close = on_close.clone();
+        Callback::from(move |_: MouseEvent| on_close.emit(()))
+    };
+
+    html! {
+        <div class="share-dialog-overlay" onclick={on_overlay_click}>
+            <div class="share-dialog fork-dialog" onclick={|e: MouseEvent| e.stop_propagation()}>
+                <div class="share-dialog-header">
+                    <h2>{ "Fork from Here" }</h2>
+                    <button class="share-dialog-close" onclick={move |_| on_close.emit(())}>
+                        { "×" }
+                    </button>
+                </div>
+                <p class="fork-dialog-note">
+                    { "Starts a new session in the same directory that picks up the conversation up to this message. This session is left as it is." }
+                </p>
+                <CopyCommand
+                    label={"Run on the machine this session is on:".to_string()}
+                    {command}
+                />
+            </div>
+        </div>
+    }
+}
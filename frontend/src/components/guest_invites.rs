@@ -0,0 +1,252 @@
+//! Guest Invites
+//!
+//! Share-dialog section for inviting someone without an account to this
+//! session for a while: name them, choose whether they only watch or may
+//! also answer permission requests, and copy the link. Lists the invites
+//! still in effect, each of which can be revoked.
+
+use gloo_net::http::Request;
+use shared::guests::{describe_minutes, GUEST_DURATIONS_MINUTES};
+use shared::{CreateGuestInviteRequest, CreatedGuestInvite, GuestAccess, GuestInviteInfo};
+use uuid::Uuid;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+use super::copy_command::CopyButton;
+use crate::utils;
+
+/// "14:05" in local time
+fn format_time(ts: &str) -> String {
+    let date = js_sys::Date::new(&ts.into());
+    if date.get_time().is_nan() {
+        return ts.to_string();
+    }
+    format!("{:02}:{:02}", date.get_hours(), date.get_minutes())
+}
+
+fn parse_access(value: &str) -> GuestAccess {
+    if value == "approver" {
+        GuestAccess::Approver
+    } else {
+        GuestAccess::Viewer
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct GuestInvitesProps {
+    pub session_id: Uuid,
+    /// Called after an invite is revoked, since that removes a member
+    pub on_change: Callback<()>,
+}
+
+#[function_component(GuestInvites)]
+pub fn guest_invites(props: &GuestInvitesProps) -> Html {
+    let invites = use_state(Vec::<GuestInviteInfo>::new);
+    let name = use_state(String::new);
+    let access = use_state(|| GuestAccess::Approver);
+    let minutes = use_state(|| GUEST_DURATIONS_MINUTES[1]);
+    let created_url = use_state(|| None::<String>);
+    let error = use_state(|| None::<String>);
+    let api_endpoint = utils::api_url(&format!("/api/sessions/{}/guests", props.session_id));
+
+    let load = {
+        let invites = invites.clone();
+        let error = error.clone();
+        let api_endpoint = api_endpoint.clone();
+        move || {
+            let invites = invites.clone();
+            let error = error.clone();
+            let api_endpoint = api_endpoint.clone();
+            spawn_local(async move {
+                match Request::get(&api_endpoint).send().await {
+                    Ok(response) if response.ok() => {
+                        if let Ok(loaded) = response.json::<Vec<GuestInviteInfo>>().await {
+                            invites.set(loaded);
+                        }
+                    }
+                    Ok(response) => error.set(Some(format!(
+                        "Failed to load guest invites: {}",
+                        response.status()
+                    ))),
+                    Err(e) => error.set(Some(format!("Failed to load guest invites: {:?}", e))),
+                }
+            });
+        }
+    };
+
+    {
+        let load = load.clone();
+        use_effect_with(props.session_id, move |_| {
+            load();
+            || ()
+        });
+    }
+
+    let on_name = {
+        let name = name.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            name.set(input.value());
+        })
+    };
+
+    let on_access = {
+        let access = access.clone();
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            access.set(parse_access(&select.value()));
+        })
+    };
+
+    let on_minutes = {
+        let minutes = minutes.clone();
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            if let Ok(value) = select.value().parse() {
+                minutes.set(value);
+            }
+        })
+    };
+
+    let on_submit = {
+        let name = name.clone();
+        let access = access.clone();
+        let minutes = minutes.clone();
+        let created_url = created_url.clone();
+        let error = error.clone();
+        let api_endpoint = api_endpoint.clone();
+        let load = load.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let request = CreateGuestInviteRequest {
+                name: name.trim().to_string(),
+                access: *access,
+                minutes: *minutes,
+            };
+            if let Err(message) = request.validate() {
+                error.set(Some(message));
+                return;
+            }
+            let name = name.clone();
+            let created_url = created_url.clone();
+            let error = error.clone();
+            let api_endpoint = api_endpoint.clone();
+            let load = load.clone();
+            spawn_local(async move {
+                let result = match Request::post(&api_endpoint).json(&request) {
+                    Ok(request) => request.send().await,
+                    Err(e) => {
+                        error.set(Some(format!("Failed to encode guest invite: {:?}", e)));
+                        return;
+                    }
+                };
+                match result {
+                    Ok(response) if response.ok() => {
+                        if let Ok(created) = response.json::<CreatedGuestInvite>().await {
+                            created_url.set(Some(created.url));
+                            name.set(String::new());
+                            error.set(None);
+                            load();
+                        }
+                    }
+                    Ok(response) => error.set(Some(format!(
+                        "Failed to create guest link: {}",
+                        response.status()
+                    ))),
+                    Err(e) => error.set(Some(format!("Failed to create guest link: {:?}", e))),
+                }
+            });
+        })
+    };
+
+    let on_revoke = |invite_id: Uuid| {
+        let error = error.clone();
+        let api_endpoint = api_endpoint.clone();
+        let load = load.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |_: MouseEvent| {
+            let error = error.clone();
+            let url = format!("{}/{}", api_endpoint, invite_id);
+            let load = load.clone();
+            let on_change = on_change.clone();
+            spawn_local(async move {
+                match Request::delete(&url).send().await {
+                    Ok(response) if response.ok() => {
+                        load();
+                        on_change.emit(());
+                    }
+                    Ok(response) => error.set(Some(format!(
+                        "Failed to revoke guest link: {}",
+                        response.status()
+                    ))),
+                    Err(e) => error.set(Some(format!("Failed to revoke guest link: {:?}", e))),
+                }
+            });
+        })
+    };
+
+    html! {
+        <div class="guest-invites">
+            <h3>{ "Guests" }</h3>
+            <p class="guest-invites-description">
+                { "Invite someone without an account with a link that expires. Their decisions are recorded under their name." }
+            </p>
+            <form class="guest-invite-form" onsubmit={on_submit}>
+                <input
+                    type="text"
+                    placeholder="Guest's name"
+                    value={(*name).clone()}
+                    oninput={on_name}
+                />
+                <select onchange={on_access}>
+                    { for [GuestAccess::Approver, GuestAccess::Viewer].iter().map(|option| html! {
+                        <option value={option.role()} selected={*option == *access}>
+                            { option.label() }
+                        </option>
+                    }) }
+                </select>
+                <select onchange={on_minutes}>
+                    { for GUEST_DURATIONS_MINUTES.iter().map(|option| html! {
+                        <option value={option.to_string()} selected={*option == *minutes}>
+                            { format!("for {}", describe_minutes(*option)) }
+                        </option>
+                    }) }
+                </select>
+                <button type="submit">{ "Create link" }</button>
+            </form>
+            if let Some(message) = &*error {
+                <div class="budget-error">{ message }</div>
+            }
+            if let Some(url) = &*created_url {
+                <div class="guest-invite-link">
+                    <code>{ url }</code>
+                    <CopyButton text={url.clone()} />
+                </div>
+                <p class="guest-invites-description">
+                    { "Copy the link now; it isn't shown again. Anyone with it can sign in as the guest until it expires." }
+                </p>
+            }
+            if !invites.is_empty() {
+                <ul class="guest-invite-list">
+                    { for invites.iter().map(|invite| html! {
+                        <li>
+                            <span class="member-name">{ &invite.name }</span>
+                            <span class="guest-invite-detail">
+                                { format!(
+                                    "{}, until {}{}",
+                                    invite.access.label(),
+                                    format_time(&invite.expires_at),
+                                    if invite.joined { "" } else { " (not opened yet)" }
+                                ) }
+                            </span>
+                            <button class="member-remove" onclick={on_revoke(invite.id)} title="Revoke guest link">
+                                { "×" }
+                            </button>
+                        </li>
+                    }) }
+                </ul>
+            }
+        </div>
+    }
+}
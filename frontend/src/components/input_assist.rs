@@ -0,0 +1,114 @@
+//! Input Assistance Settings
+//!
+//! Browser spellcheck and prompt lint hints for the session input box,
+//! saved in this browser's localStorage and picked up by sessions opened
+//! afterwards.
+
+use serde::{Deserialize, Serialize};
+use yew::prelude::*;
+
+/// Storage key for input assistance settings in localStorage
+const INPUT_ASSIST_STORAGE_KEY: &str = "claude-portal-input-assist";
+
+/// Help offered while typing a prompt, kept per browser
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputAssist {
+    /// Let the browser underline misspellings. Off by default, since
+    /// prompts are full of identifiers and paths.
+    pub spellcheck: bool,
+    /// Show hints such as an unclosed code block under the input
+    pub lint_hints: bool,
+}
+
+impl Default for InputAssist {
+    fn default() -> Self {
+        Self {
+            spellcheck: false,
+            lint_hints: true,
+        }
+    }
+}
+
+impl InputAssist {
+    /// Load input assistance settings from localStorage
+    pub fn load() -> Self {
+        web_sys::window()
+            .and_then(|w| w.local_storage().ok().flatten())
+            .and_then(|storage| storage.get_item(INPUT_ASSIST_STORAGE_KEY).ok().flatten())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    /// Save input assistance settings to localStorage
+    pub fn save(&self) {
+        if let Some(storage) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) {
+            if let Ok(json) = serde_json::to_string(self) {
+                let _ = storage.set_item(INPUT_ASSIST_STORAGE_KEY, &json);
+            }
+        }
+    }
+}
+
+#[function_component(InputAssistForm)]
+pub fn input_assist_form() -> Html {
+    let settings = use_state(InputAssist::load);
+
+    let update = {
+        let settings = settings.clone();
+        move |new: InputAssist| {
+            new.save();
+            settings.set(new);
+        }
+    };
+
+    let on_spellcheck = {
+        let settings = settings.clone();
+        let update = update.clone();
+        Callback::from(move |e: Event| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            update(InputAssist {
+                spellcheck: input.checked(),
+                ..*settings
+            });
+        })
+    };
+
+    let on_lint_hints = {
+        let settings = settings.clone();
+        Callback::from(move |e: Event| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            update(InputAssist {
+                lint_hints: input.checked(),
+                ..*settings
+            });
+        })
+    };
+
+    html! {
+        <section class="voice-settings-section">
+            <div class="section-header">
+                <h2>{ "Input Assistance" }</h2>
+                <p class="section-description">
+                    { "Help while typing a prompt on this device. Hints never stop a message from being sent." }
+                </p>
+            </div>
+            <label class="voice-setting">
+                <input
+                    type="checkbox"
+                    checked={settings.spellcheck}
+                    onchange={on_spellcheck}
+                />
+                { "Spellcheck: let the browser underline misspelled words" }
+            </label>
+            <label class="voice-setting">
+                <input
+                    type="checkbox"
+                    checked={settings.lint_hints}
+                    onchange={on_lint_hints}
+                />
+                { "Prompt hints: point out unclosed code blocks, stray backticks, and mentions of attachments that aren't attached" }
+            </label>
+        </section>
+    }
+}
@@ -0,0 +1,161 @@
+//! Kill Switch Settings
+//!
+//! Admin control for pausing every running session during an incident.
+//! Engaging it tells every connected proxy to hold input and deny
+//! permission requests, and shows a banner on every page until an admin
+//! releases it.
+
+use gloo_net::http::Request;
+use shared::{EngageKillSwitchRequest, KillSwitch};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlTextAreaElement;
+use yew::prelude::*;
+
+use super::maintenance_banner::{format_local, KillSwitchContext};
+use crate::utils;
+
+#[function_component(KillSwitchSettings)]
+pub fn kill_switch_settings() -> Html {
+    let current = use_context::<KillSwitchContext>();
+    let reason = use_state(String::new);
+    let status = use_state(|| None::<Result<String, String>>);
+    let saving = use_state(|| false);
+
+    let on_reason = {
+        let reason = reason.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlTextAreaElement = e.target_unchecked_into();
+            reason.set(input.value());
+        })
+    };
+
+    let on_submit = {
+        let reason = reason.clone();
+        let status = status.clone();
+        let saving = saving.clone();
+        let current = current.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let request = EngageKillSwitchRequest {
+                reason: reason.trim().to_string(),
+            };
+            if let Err(message) = request.validate() {
+                status.set(Some(Err(message)));
+                return;
+            }
+            if current.as_ref().is_some_and(|c| c.is_none())
+                && !gloo::dialogs::confirm(
+                    "Pause every running session? Input is held and permission requests are denied until you resume them.",
+                )
+            {
+                return;
+            }
+            let reason = reason.clone();
+            let status = status.clone();
+            let saving = saving.clone();
+            let current = current.clone();
+            saving.set(true);
+            spawn_local(async move {
+                let api_endpoint = utils::api_url("/api/admin/kill-switch");
+                let result = match Request::put(&api_endpoint).json(&request) {
+                    Ok(request) => request.send().await,
+                    Err(e) => {
+                        status.set(Some(Err(format!("Failed to encode request: {:?}", e))));
+                        saving.set(false);
+                        return;
+                    }
+                };
+                match result {
+                    Ok(response) if response.ok() => {
+                        if let (Some(current), Ok(engaged)) =
+                            (current, response.json::<KillSwitch>().await)
+                        {
+                            current.set(Some(engaged));
+                        }
+                        reason.set(String::new());
+                        status.set(Some(Ok("All sessions paused".to_string())));
+                    }
+                    Ok(response) => status.set(Some(Err(format!(
+                        "Failed to pause sessions: {}",
+                        response.status()
+                    )))),
+                    Err(e) => status.set(Some(Err(format!("Failed to pause sessions: {:?}", e)))),
+                }
+                saving.set(false);
+            });
+        })
+    };
+
+    let on_release = {
+        let status = status.clone();
+        let saving = saving.clone();
+        let current = current.clone();
+        Callback::from(move |_: MouseEvent| {
+            let status = status.clone();
+            let saving = saving.clone();
+            let current = current.clone();
+            saving.set(true);
+            spawn_local(async move {
+                let api_endpoint = utils::api_url("/api/admin/kill-switch");
+                match Request::delete(&api_endpoint).send().await {
+                    Ok(response) if response.ok() => {
+                        if let Some(current) = current {
+                            current.set(None);
+                        }
+                        status.set(Some(Ok("Sessions resumed".to_string())));
+                    }
+                    Ok(response) => status.set(Some(Err(format!(
+                        "Failed to resume sessions: {}",
+                        response.status()
+                    )))),
+                    Err(e) => status.set(Some(Err(format!("Failed to resume sessions: {:?}", e)))),
+                }
+                saving.set(false);
+            });
+        })
+    };
+
+    let engaged = current.as_ref().and_then(|c| (**c).clone());
+    html! {
+        <form class="kill-switch-settings" onsubmit={on_submit}>
+            <h3>{ "Kill switch" }</h3>
+            <p class="budget-settings-description">
+                { "Pauses every running session at once. Proxies hold input back from Claude and deny new permission requests, and every page shows why. Held input is sent once sessions resume." }
+            </p>
+            if let Some(engaged) = &engaged {
+                <p class="kill-switch-status">
+                    { format!(
+                        "Engaged by {} at {}: {}",
+                        engaged.engaged_by,
+                        format_local(engaged.engaged_at),
+                        engaged.reason
+                    ) }
+                </p>
+            }
+            <textarea
+                class="maintenance-message"
+                rows="2"
+                placeholder="Investigating unexpected deletes on build hosts"
+                value={(*reason).clone()}
+                oninput={on_reason}
+            />
+            <div class="escalation-actions">
+                <button type="submit" class="tab-btn kill-switch-engage" disabled={*saving}>
+                    { if engaged.is_some() { "Update reason" } else { "Pause all sessions" } }
+                </button>
+                if engaged.is_some() {
+                    <button type="button" class="tab-btn active" onclick={on_release} disabled={*saving}>
+                        { "Resume sessions" }
+                    </button>
+                }
+                {
+                    match &*status {
+                        Some(Ok(message)) => html! { <span class="budget-saved">{ message }</span> },
+                        Some(Err(message)) => html! { <span class="budget-error">{ message }</span> },
+                        None => html! {},
+                    }
+                }
+            </div>
+        </form>
+    }
+}
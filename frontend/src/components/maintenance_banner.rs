@@ -0,0 +1,158 @@
+//! Maintenance Banner
+//!
+//! Site-wide strip showing the notice an admin has posted, and above it a
+//! strip that can't be hidden while the kill switch is engaged. The
+//! provider polls `/api/maintenance` and `/api/kill-switch` so every page
+//! sees them; pages with a client socket also push changes into the
+//! contexts as soon as they arrive.
+
+use chrono::{DateTime, Utc};
+use gloo_net::http::Request;
+use shared::{KillSwitch, MaintenanceNotice};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::utils;
+
+/// How often the provider re-checks the banners
+const POLL_INTERVAL_MS: u32 = 60_000;
+
+/// Context holding the banner currently up
+pub type MaintenanceContext = UseStateHandle<Option<MaintenanceNotice>>;
+
+/// Context holding the kill switch, if engaged
+pub type KillSwitchContext = UseStateHandle<Option<KillSwitch>>;
+
+/// "Sat, Jan 24, 02:00" in local time
+pub fn format_local(at: DateTime<Utc>) -> String {
+    let date = js_sys::Date::new(&(at.timestamp_millis() as f64).into());
+    let options = js_sys::Object::new();
+    for (key, value) in [
+        ("weekday", "short"),
+        ("month", "short"),
+        ("day", "numeric"),
+        ("hour", "2-digit"),
+        ("minute", "2-digit"),
+    ] {
+        let _ = js_sys::Reflect::set(&options, &key.into(), &value.into());
+    }
+    date.to_locale_string("default", &options).into()
+}
+
+fn window_text(notice: &MaintenanceNotice) -> Option<String> {
+    match (notice.starts_at, notice.ends_at) {
+        (Some(starts), Some(ends)) => Some(format!(
+            "Downtime {} – {}",
+            format_local(starts),
+            format_local(ends)
+        )),
+        (Some(starts), None) => Some(format!("Downtime from {}", format_local(starts))),
+        (None, Some(ends)) => Some(format!("Expected back by {}", format_local(ends))),
+        (None, None) => None,
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct MaintenanceProviderProps {
+    pub children: Html,
+}
+
+#[function_component(MaintenanceProvider)]
+pub fn maintenance_provider(props: &MaintenanceProviderProps) -> Html {
+    let notice = use_state(|| None::<MaintenanceNotice>);
+    // The notice the user closed; a different notice shows again
+    let dismissed = use_state(|| None::<MaintenanceNotice>);
+    let kill_switch = use_state(|| None::<KillSwitch>);
+
+    {
+        let notice = notice.clone();
+        let kill_switch = kill_switch.clone();
+        use_effect_with((), move |_| {
+            let fetch = move || {
+                let notice = notice.clone();
+                let kill_switch = kill_switch.clone();
+                spawn_local(async move {
+                    let api_endpoint = utils::api_url("/api/kill-switch");
+                    match Request::get(&api_endpoint).send().await {
+                        Ok(response) if response.ok() => {
+                            if let Ok(current) = response.json::<Option<KillSwitch>>().await {
+                                if *kill_switch != current {
+                                    kill_switch.set(current);
+                                }
+                            }
+                        }
+                        Ok(response) => {
+                            tracing::warn!("Failed to load kill switch: {}", response.status())
+                        }
+                        Err(e) => tracing::warn!("Failed to load kill switch: {:?}", e),
+                    }
+
+                    let api_endpoint = utils::api_url("/api/maintenance");
+                    match Request::get(&api_endpoint).send().await {
+                        Ok(response) if response.ok() => {
+                            if let Ok(current) = response.json::<Option<MaintenanceNotice>>().await
+                            {
+                                if *notice != current {
+                                    notice.set(current);
+                                }
+                            }
+                        }
+                        Ok(response) => {
+                            tracing::warn!(
+                                "Failed to load maintenance banner: {}",
+                                response.status()
+                            )
+                        }
+                        Err(e) => tracing::warn!("Failed to load maintenance banner: {:?}", e),
+                    }
+                });
+            };
+            fetch();
+            let interval = gloo::timers::callback::Interval::new(POLL_INTERVAL_MS, fetch);
+            move || drop(interval)
+        });
+    }
+
+    let now = DateTime::<Utc>::from_timestamp_millis(js_sys::Date::now() as i64);
+    let visible = notice
+        .as_ref()
+        .filter(|n| now.is_none_or(|now| !n.is_over(now)))
+        .filter(|n| dismissed.as_ref() != Some(*n));
+
+    let on_dismiss = {
+        let notice = notice.clone();
+        let dismissed = dismissed.clone();
+        Callback::from(move |_| dismissed.set((*notice).clone()))
+    };
+
+    html! {
+        <ContextProvider<MaintenanceContext> context={notice.clone()}>
+        <ContextProvider<KillSwitchContext> context={kill_switch.clone()}>
+            if let Some(engaged) = &*kill_switch {
+                <div class="kill-switch-banner" role="alert">
+                    <span class="maintenance-icon">{ "⏸" }</span>
+                    <span class="maintenance-text">
+                        { format!("All sessions are paused: {}", engaged.reason) }
+                    </span>
+                    <span class="maintenance-window">
+                        { format!("by {} since {}", engaged.engaged_by, format_local(engaged.engaged_at)) }
+                    </span>
+                </div>
+            }
+            if let Some(current) = visible {
+                <div class="maintenance-banner" role="status">
+                    <span class="maintenance-icon">{ "🔧" }</span>
+                    <span class="maintenance-text">{ &current.message }</span>
+                    if let Some(window) = window_text(current) {
+                        <span class="maintenance-window">{ window }</span>
+                    }
+                    <button class="maintenance-dismiss" onclick={on_dismiss} title="Hide">
+                        { "×" }
+                    </button>
+                </div>
+            }
+            { props.children.clone() }
+        </ContextProvider<KillSwitchContext>>
+        </ContextProvider<MaintenanceContext>>
+    }
+}
@@ -0,0 +1,241 @@
+//! Maintenance Settings
+//!
+//! Admin form for the site-wide maintenance banner. Posting pushes it to
+//! every connected web client and proxy right away; the optional window is
+//! entered in the admin's local time.
+
+use chrono::{DateTime, Utc};
+use gloo_net::http::Request;
+use shared::MaintenanceNotice;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
+use yew::prelude::*;
+
+use super::maintenance_banner::MaintenanceContext;
+use crate::utils;
+
+/// Text of the form's inputs
+#[derive(Clone, Default, PartialEq)]
+struct NoticeFields {
+    message: String,
+    starts_at: String,
+    ends_at: String,
+}
+
+/// Value for a `datetime-local` input, e.g. "2026-01-24T02:00"
+fn to_local_input(at: DateTime<Utc>) -> String {
+    let date = js_sys::Date::new(&(at.timestamp_millis() as f64).into());
+    format!(
+        "{}-{:02}-{:02}T{:02}:{:02}",
+        date.get_full_year(),
+        date.get_month() + 1,
+        date.get_date(),
+        date.get_hours(),
+        date.get_minutes()
+    )
+}
+
+/// Parse a `datetime-local` value; empty means no time was given
+fn from_local_input(text: &str) -> Result<Option<DateTime<Utc>>, String> {
+    if text.trim().is_empty() {
+        return Ok(None);
+    }
+    // Without an offset the browser reads the value as local time
+    let millis = js_sys::Date::new(&text.into()).get_time();
+    if millis.is_nan() {
+        return Err(format!("\"{}\" is not a date and time", text));
+    }
+    DateTime::from_timestamp_millis(millis as i64)
+        .map(Some)
+        .ok_or_else(|| format!("\"{}\" is out of range", text))
+}
+
+fn to_fields(notice: &MaintenanceNotice) -> NoticeFields {
+    NoticeFields {
+        message: notice.message.clone(),
+        starts_at: notice.starts_at.map(to_local_input).unwrap_or_default(),
+        ends_at: notice.ends_at.map(to_local_input).unwrap_or_default(),
+    }
+}
+
+fn to_notice(fields: &NoticeFields) -> Result<MaintenanceNotice, String> {
+    let notice = MaintenanceNotice {
+        message: fields.message.trim().to_string(),
+        starts_at: from_local_input(&fields.starts_at)?,
+        ends_at: from_local_input(&fields.ends_at)?,
+    };
+    notice.validate()?;
+    Ok(notice)
+}
+
+#[function_component(MaintenanceSettings)]
+pub fn maintenance_settings() -> Html {
+    let current = use_context::<MaintenanceContext>();
+    let fields = use_state(NoticeFields::default);
+    let status = use_state(|| None::<Result<String, String>>);
+    let saving = use_state(|| false);
+
+    // Start from the banner that is up, once the provider has loaded it
+    {
+        let fields = fields.clone();
+        let notice = current.as_ref().and_then(|c| (**c).clone());
+        use_effect_with(notice, move |notice| {
+            if let Some(notice) = notice {
+                fields.set(to_fields(notice));
+            }
+            || ()
+        });
+    }
+
+    let on_message = {
+        let fields = fields.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlTextAreaElement = e.target_unchecked_into();
+            let mut updated = (*fields).clone();
+            updated.message = input.value();
+            fields.set(updated);
+        })
+    };
+
+    let on_time = |update: fn(&mut NoticeFields, String)| {
+        let fields = fields.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut updated = (*fields).clone();
+            update(&mut updated, input.value());
+            fields.set(updated);
+        })
+    };
+
+    let on_submit = {
+        let fields = fields.clone();
+        let status = status.clone();
+        let saving = saving.clone();
+        let current = current.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let notice = match to_notice(&fields) {
+                Ok(notice) => notice,
+                Err(message) => {
+                    status.set(Some(Err(message)));
+                    return;
+                }
+            };
+            let status = status.clone();
+            let saving = saving.clone();
+            let current = current.clone();
+            saving.set(true);
+            spawn_local(async move {
+                let api_endpoint = utils::api_url("/api/admin/maintenance");
+                let result = match Request::put(&api_endpoint).json(&notice) {
+                    Ok(request) => request.send().await,
+                    Err(e) => {
+                        status.set(Some(Err(format!("Failed to encode banner: {:?}", e))));
+                        saving.set(false);
+                        return;
+                    }
+                };
+                match result {
+                    Ok(response) if response.ok() => {
+                        if let (Some(current), Ok(posted)) =
+                            (current, response.json::<MaintenanceNotice>().await)
+                        {
+                            current.set(Some(posted));
+                        }
+                        status.set(Some(Ok("Banner posted to all clients".to_string())));
+                    }
+                    Ok(response) => status.set(Some(Err(format!(
+                        "Failed to post banner: {}",
+                        response.status()
+                    )))),
+                    Err(e) => status.set(Some(Err(format!("Failed to post banner: {:?}", e)))),
+                }
+                saving.set(false);
+            });
+        })
+    };
+
+    let on_clear = {
+        let fields = fields.clone();
+        let status = status.clone();
+        let saving = saving.clone();
+        let current = current.clone();
+        Callback::from(move |_: MouseEvent| {
+            let fields = fields.clone();
+            let status = status.clone();
+            let saving = saving.clone();
+            let current = current.clone();
+            saving.set(true);
+            spawn_local(async move {
+                let api_endpoint = utils::api_url("/api/admin/maintenance");
+                match Request::delete(&api_endpoint).send().await {
+                    Ok(response) if response.ok() => {
+                        if let Some(current) = current {
+                            current.set(None);
+                        }
+                        fields.set(NoticeFields::default());
+                        status.set(Some(Ok("Banner taken down".to_string())));
+                    }
+                    Ok(response) => status.set(Some(Err(format!(
+                        "Failed to take banner down: {}",
+                        response.status()
+                    )))),
+                    Err(e) => status.set(Some(Err(format!("Failed to take banner down: {:?}", e)))),
+                }
+                saving.set(false);
+            });
+        })
+    };
+
+    let is_up = current.as_ref().is_some_and(|c| c.is_some());
+    html! {
+        <form class="maintenance-settings" onsubmit={on_submit}>
+            <h3>{ "Maintenance banner" }</h3>
+            <p class="budget-settings-description">
+                { "Shown at the top of every page and printed by every connected proxy. Use it to warn users before a restart." }
+            </p>
+            <textarea
+                class="maintenance-message"
+                rows="2"
+                placeholder="Upgrading the server; sessions will reconnect automatically"
+                value={fields.message.clone()}
+                oninput={on_message}
+            />
+            <div class="maintenance-window-fields">
+                <label>
+                    { "Downtime from" }
+                    <input
+                        type="datetime-local"
+                        value={fields.starts_at.clone()}
+                        oninput={on_time(|f, v| f.starts_at = v)}
+                    />
+                </label>
+                <label>
+                    { "until" }
+                    <input
+                        type="datetime-local"
+                        value={fields.ends_at.clone()}
+                        oninput={on_time(|f, v| f.ends_at = v)}
+                    />
+                </label>
+            </div>
+            <div class="escalation-actions">
+                <button type="submit" class="tab-btn active" disabled={*saving}>
+                    { if is_up { "Update banner" } else { "Post banner" } }
+                </button>
+                if is_up {
+                    <button type="button" class="tab-btn" onclick={on_clear} disabled={*saving}>
+                        { "Take down" }
+                    </button>
+                }
+                {
+                    match &*status {
+                        Some(Ok(message)) => html! { <span class="budget-saved">{ message }</span> },
+                        Some(Err(message)) => html! { <span class="budget-error">{ message }</span> },
+                        None => html! {},
+                    }
+                }
+            </div>
+        </form>
+    }
+}
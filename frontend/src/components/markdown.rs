@@ -2,11 +2,17 @@
 //!
 //! Parses markdown text and renders it as Yew Html using pulldown-cmark.
 //! Supports: headings, bold, italic, strikethrough, links, code blocks,
-//! inline code, blockquotes, lists, and tables.
+//! inline code, blockquotes, lists, and tables. Code blocks are
+//! highlighted; untagged ones get a language guessed from their contents or
+//! from a file path mentioned just before them.
 
-use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use super::syntax::{detect_lang, highlight_lines, lang_for_path, lang_for_tag, LangSpec};
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, Options, Parser, Tag, TagEnd};
 use yew::prelude::*;
 
+/// Info string suffix marking a code block's language as detected
+const DETECTED: &str = "(detected)";
+
 /// Render markdown text as HTML
 pub fn render_markdown(text: &str) -> Html {
     let mut options = Options::empty();
@@ -14,11 +20,47 @@ pub fn render_markdown(text: &str) -> Html {
     options.insert(Options::ENABLE_STRIKETHROUGH);
 
     let parser = Parser::new_ext(text, options);
-    let events: Vec<Event> = parser.collect();
+    let mut events: Vec<Event> = parser.collect();
+    tag_untagged_code_blocks(&mut events);
 
     render_events(&events)
 }
 
+/// The language of the last file path in `text` that names one, e.g.
+/// "Updated `src/main.rs`:"
+fn path_hint(text: &str) -> Option<&'static LangSpec> {
+    text.split_whitespace()
+        .rev()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
+        .find_map(lang_for_path)
+}
+
+/// Give fenced code blocks without a language tag a detected one. The code
+/// itself decides if it can; otherwise a file path mentioned since the
+/// previous block does.
+fn tag_untagged_code_blocks(events: &mut [Event]) {
+    let mut hint = None;
+    for i in 0..events.len() {
+        match &events[i] {
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(spec) = path_hint(text) {
+                    hint = Some(spec);
+                }
+            }
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) if info.trim().is_empty() => {
+                let code = extract_text(&events[i + 1..]);
+                if let Some(spec) = detect_lang(&code).or(hint) {
+                    let info = CowStr::from(format!("{} {}", spec.tag, DETECTED));
+                    events[i] = Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info)));
+                }
+                hint = None;
+            }
+            Event::Start(Tag::CodeBlock(_)) => hint = None,
+            _ => {}
+        }
+    }
+}
+
 /// Convert pulldown-cmark events to Yew Html
 fn render_events(events: &[Event]) -> Html {
     let mut html_parts: Vec<Html> = Vec::new();
@@ -174,20 +216,35 @@ fn render_heading(level: pulldown_cmark::HeadingLevel, inner: Html) -> Html {
     }
 }
 
-/// Render a code block with optional language class
+/// Render a code block, highlighted and labeled when its language is known
 fn render_code_block(kind: &CodeBlockKind, inner_events: &[Event]) -> Html {
     let code_text = extract_text(inner_events);
-    let lang_class = match kind {
-        CodeBlockKind::Fenced(lang) if !lang.is_empty() => Some(format!(
-            "language-{}",
-            lang.split_whitespace().next().unwrap_or("")
-        )),
-        _ => None,
+    let info = match kind {
+        CodeBlockKind::Fenced(info) => info.trim(),
+        CodeBlockKind::Indented => "",
     };
+    let (tag, rest) = info.split_once(' ').unwrap_or((info, ""));
+    let detected = rest.trim() == DETECTED;
+    let spec = lang_for_tag(tag);
+    let lang_class = (!tag.is_empty()).then(|| format!("language-{}", tag));
+
+    let label = match spec {
+        Some(spec) if detected => html! {
+            <span class="md-code-lang detected" title="Language detected automatically">
+                { spec.name }
+            </span>
+        },
+        _ if !tag.is_empty() => html! { <span class="md-code-lang">{ tag }</span> },
+        _ => html! {},
+    };
+    let lines = highlight_lines(code_text.trim_end_matches('\n').split('\n'), spec);
 
     html! {
         <pre class="md-code-block">
-            <code class={classes!("md-code", lang_class)}>{ code_text }</code>
+            { label }
+            <code class={classes!("md-code", lang_class)}>
+                { for lines.into_iter().map(|line| html! { <>{ line }{ "\n" }</> }) }
+            </code>
         </pre>
     }
 }
@@ -531,6 +588,33 @@ mod tests {
         assert_eq!(extract_text(&events), "Hello World");
     }
 
+    #[test]
+    fn test_untagged_code_blocks_are_detected() {
+        let tag_of = |text: &str| {
+            let mut events: Vec<Event> = Parser::new(text).collect();
+            tag_untagged_code_blocks(&mut events);
+            events.into_iter().find_map(|event| match event {
+                Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) => Some(info.to_string()),
+                _ => None,
+            })
+        };
+
+        assert_eq!(
+            tag_of("```\nfn main() {\n    let mut x = 1;\n}\n```"),
+            Some("rust (detected)".to_string())
+        );
+        assert_eq!(
+            tag_of("In `config.yaml`:\n\n```\nname: demo\n```"),
+            Some("toml (detected)".to_string())
+        );
+        assert_eq!(
+            tag_of("See notes.py\n\n```\nx\n```"),
+            Some("python (detected)".to_string())
+        );
+        assert_eq!(tag_of("```\nhello there\n```"), Some(String::new()));
+        assert_eq!(tag_of("```ts\nlet x\n```"), Some("ts".to_string()));
+    }
+
     #[test]
     fn test_find_next_url_simple() {
         let result = find_next_url("Check https://example.com for info");
@@ -0,0 +1,144 @@
+//! Message Reactions Component
+//!
+//! Small emoji badges under a message, plus a picker for adding new reactions.
+
+use gloo_net::http::Request;
+use shared::{MessageReactionsInfo, ReactionSummary, ToggleReactionRequest, REACTION_EMOJIS};
+use uuid::Uuid;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::utils;
+
+#[derive(Properties, PartialEq)]
+pub struct MessageReactionsProps {
+    pub session_id: Uuid,
+    /// The stored message these reactions belong to
+    pub message_id: Uuid,
+    pub reactions: Vec<ReactionSummary>,
+    /// Called with the updated reactions after a toggle succeeds
+    pub on_change: Callback<(Uuid, Vec<ReactionSummary>)>,
+    /// Shows a "report a problem" button alongside the reaction picker
+    #[prop_or_default]
+    pub on_report: Option<Callback<()>>,
+    /// Shows a "fork from here" button alongside the reaction picker
+    #[prop_or_default]
+    pub on_fork: Option<Callback<()>>,
+}
+
+#[function_component(MessageReactions)]
+pub fn message_reactions(props: &MessageReactionsProps) -> Html {
+    let picker_open = use_state(|| false);
+
+    let toggle = {
+        let session_id = props.session_id;
+        let message_id = props.message_id;
+        let on_change = props.on_change.clone();
+        let picker_open = picker_open.clone();
+
+        Callback::from(move |emoji: String| {
+            picker_open.set(false);
+            let on_change = on_change.clone();
+            spawn_local(async move {
+                let url = utils::api_url(&format!(
+                    "/api/sessions/{}/messages/{}/reactions",
+                    session_id, message_id
+                ));
+                let request = match Request::post(&url).json(&ToggleReactionRequest { emoji }) {
+                    Ok(request) => request,
+                    Err(e) => {
+                        tracing::error!("Failed to build reaction request: {:?}", e);
+                        return;
+                    }
+                };
+                match request.send().await {
+                    Ok(response) if response.ok() => {
+                        if let Ok(info) = response.json::<MessageReactionsInfo>().await {
+                            on_change.emit((info.message_id, info.reactions));
+                        }
+                    }
+                    Ok(response) => {
+                        tracing::error!("Failed to toggle reaction: {}", response.status());
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to toggle reaction: {:?}", e);
+                    }
+                }
+            });
+        })
+    };
+
+    let on_open_picker = {
+        let picker_open = picker_open.clone();
+        Callback::from(move |_: MouseEvent| picker_open.set(!*picker_open))
+    };
+
+    html! {
+        <div class="message-reactions">
+            {
+                props.reactions.iter().map(|reaction| {
+                    let emoji = reaction.emoji.clone();
+                    let toggle = toggle.clone();
+                    html! {
+                        <button
+                            type="button"
+                            class="reaction-badge"
+                            title={reaction.users.join(", ")}
+                            onclick={Callback::from(move |_: MouseEvent| toggle.emit(emoji.clone()))}
+                        >
+                            <span class="reaction-emoji">{ &reaction.emoji }</span>
+                            <span class="reaction-count">{ reaction.users.len() }</span>
+                        </button>
+                    }
+                }).collect::<Html>()
+            }
+            <button
+                type="button"
+                class="reaction-add"
+                title="Add reaction"
+                onclick={on_open_picker}
+            >
+                { "+" }
+            </button>
+            if let Some(on_report) = props.on_report.clone() {
+                <button
+                    type="button"
+                    class="reaction-add"
+                    title="Report a problem with this message"
+                    onclick={Callback::from(move |_: MouseEvent| on_report.emit(()))}
+                >
+                    { "⚑" }
+                </button>
+            }
+            if let Some(on_fork) = props.on_fork.clone() {
+                <button
+                    type="button"
+                    class="reaction-add"
+                    title="Fork a new session from here"
+                    onclick={Callback::from(move |_: MouseEvent| on_fork.emit(()))}
+                >
+                    { "⑂" }
+                </button>
+            }
+            if *picker_open {
+                <div class="reaction-picker">
+                    {
+                        REACTION_EMOJIS.iter().map(|emoji| {
+                            let toggle = toggle.clone();
+                            let value = emoji.to_string();
+                            html! {
+                                <button
+                                    type="button"
+                                    class="reaction-picker-option"
+                                    onclick={Callback::from(move |_: MouseEvent| toggle.emit(value.clone()))}
+                                >
+                                    { *emoji }
+                                </button>
+                            }
+                        }).collect::<Html>()
+                    }
+                </div>
+            }
+        </div>
+    }
+}
@@ -1,8 +1,10 @@
+use super::copy_command::CopyButton;
 use super::markdown::render_markdown;
+use super::syntax::{highlight_lines, lang_for_path};
 use gloo_net::http::Request;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use shared::ToolResultContent;
+use shared::{CompactBoundary, ErrorCode, OperatorAction, RenderLimits, ToolResultContent};
 use uuid::Uuid;
 use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
@@ -82,6 +84,9 @@ pub enum ClaudeMessage {
     User(UserMessage),
     #[serde(rename = "error")]
     Error(ErrorMessage),
+    /// Something a person did in the web UI, stored by the backend
+    #[serde(rename = "operator")]
+    Operator(OperatorMessage),
     #[serde(other)]
     Unknown,
 }
@@ -99,6 +104,13 @@ pub struct UserMessageContent {
     pub content: Option<Vec<ContentBlock>>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperatorMessage {
+    pub user_name: String,
+    #[serde(flatten)]
+    pub action: OperatorAction,
+}
+
 /// Inner error details from API errors
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ErrorDetails {
@@ -115,6 +127,9 @@ pub struct ErrorMessage {
     pub error: Option<ErrorDetails>,
     /// Request ID for API errors
     pub request_id: Option<String>,
+    /// Set on errors from the portal itself rather than the API
+    #[serde(default)]
+    pub code: Option<ErrorCode>,
 }
 
 impl ErrorMessage {
@@ -139,10 +154,60 @@ impl ErrorMessage {
 
     /// Get the error type for display
     pub fn error_type(&self) -> Option<&str> {
-        self.error.as_ref().and_then(|e| e.error_type.as_deref())
+        self.error
+            .as_ref()
+            .and_then(|e| e.error_type.as_deref())
+            .or_else(|| {
+                self.code
+                    .and_then(|code| error_code_text(code).map(|(title, _)| title))
+            })
+    }
+
+    /// What the user can do about a portal error, if anything
+    pub fn hint(&self) -> Option<&'static str> {
+        self.code
+            .and_then(|code| error_code_text(code).map(|(_, hint)| hint))
     }
 }
 
+/// Title and suggested action for each portal error code
+fn error_code_text(code: ErrorCode) -> Option<(&'static str, &'static str)> {
+    let text = match code {
+        ErrorCode::AuthFailed => ("Signed out", "Sign in again, then reload this page."),
+        ErrorCode::AccessDenied => (
+            "Access denied",
+            "Ask the session owner to share it with you.",
+        ),
+        ErrorCode::SessionNotFound => (
+            "Session not found",
+            "It may have been deleted. Pick another session.",
+        ),
+        ErrorCode::ProxyOffline => (
+            "Proxy offline",
+            "Input is queued and sent when the proxy reconnects.",
+        ),
+        ErrorCode::NotDriver => ("Not driving", "Request control from the current driver."),
+        ErrorCode::RateLimited => ("Slow down", "Wait a moment and try again."),
+        ErrorCode::PayloadTooLarge => ("Too large", "Send a smaller message or file."),
+        ErrorCode::BudgetExceeded => (
+            "Budget reached",
+            "An admin needs to raise the limit before more input is sent.",
+        ),
+        ErrorCode::QuotaExceeded => ("Session limit", "Stop another session and try again."),
+        ErrorCode::KillSwitch => (
+            "Sessions paused",
+            "An admin has paused all sessions. Send input again once they resume them.",
+        ),
+        ErrorCode::IncompatibleProtocol => ("Outdated page", "Reload the page."),
+        ErrorCode::Internal => (
+            "Server error",
+            "Try again; report it if it keeps happening.",
+        ),
+        ErrorCode::Other => return None,
+    };
+    Some(text)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct SystemMessage {
     pub subtype: Option<String>,
@@ -237,15 +302,28 @@ pub struct MessageRendererProps {
 
 #[function_component(MessageRenderer)]
 pub fn message_renderer(props: &MessageRendererProps) -> Html {
+    let limits = use_context::<RenderLimits>().unwrap_or_default();
+    let value: Value = serde_json::from_str(&props.json).unwrap_or_default();
+
+    // Compaction shows as a divider and the summary Claude continues from,
+    // not as a system event and a prompt
+    if let Some(boundary) = CompactBoundary::of(&value) {
+        return render_compact_boundary(&boundary);
+    }
+    if let Some(summary) = shared::compaction::summary_text(&value) {
+        return render_compact_summary(&summary);
+    }
+
     // Try to parse as a known message type
-    let parsed: Result<ClaudeMessage, _> = serde_json::from_str(&props.json);
+    let parsed = ClaudeMessage::deserialize(&value);
 
     match parsed {
         Ok(ClaudeMessage::System(msg)) => render_system_message(&msg),
-        Ok(ClaudeMessage::Assistant(msg)) => render_assistant_message(&msg),
+        Ok(ClaudeMessage::Assistant(msg)) => render_assistant_message(&msg, &limits),
         Ok(ClaudeMessage::Result(msg)) => render_result_message(&msg),
-        Ok(ClaudeMessage::User(msg)) => render_user_message(&msg),
+        Ok(ClaudeMessage::User(msg)) => render_user_message(&msg, &limits),
         Ok(ClaudeMessage::Error(msg)) => render_error_message(&msg),
+        Ok(ClaudeMessage::Operator(msg)) => render_operator_message(&msg),
         Ok(ClaudeMessage::Unknown) | Err(_) => {
             html! { <RawMessageRenderer json={props.json.clone()} session_id={props.session_id} /> }
         }
@@ -262,16 +340,17 @@ pub struct MessageGroupRendererProps {
 
 #[function_component(MessageGroupRenderer)]
 pub fn message_group_renderer(props: &MessageGroupRendererProps) -> Html {
+    let limits = use_context::<RenderLimits>().unwrap_or_default();
     match &props.group {
         MessageGroup::Single(json) => {
             html! { <MessageRenderer json={json.clone()} session_id={props.session_id} /> }
         }
-        MessageGroup::AssistantGroup(messages) => render_assistant_group(messages),
+        MessageGroup::AssistantGroup(messages) => render_assistant_group(messages, &limits),
     }
 }
 
 /// Render a group of consecutive assistant messages (and tool results) in a single frame
-fn render_assistant_group(messages: &[String]) -> Html {
+fn render_assistant_group(messages: &[String], limits: &RenderLimits) -> Html {
     // Parse all messages to extract content and sum tokens
     let mut all_blocks: Vec<ContentBlock> = Vec::new();
     let mut total_output_tokens: u64 = 0;
@@ -352,13 +431,13 @@ fn render_assistant_group(messages: &[String]) -> Html {
                 }
             </div>
             <div class="message-body">
-                { render_content_blocks(&all_blocks) }
+                { render_content_blocks(&all_blocks, limits) }
             </div>
         </div>
     }
 }
 
-fn render_user_message(msg: &UserMessage) -> Html {
+fn render_user_message(msg: &UserMessage, limits: &RenderLimits) -> Html {
     // Check if this is a simple text message or a structured message
     if let Some(text) = &msg.content {
         // Simple user input (legacy format)
@@ -395,7 +474,7 @@ fn render_user_message(msg: &UserMessage) -> Html {
             html! {
                 <div class="claude-message user-message tool-result-message">
                     <div class="message-body">
-                        { render_content_blocks(&blocks) }
+                        { render_content_blocks(&blocks, limits) }
                     </div>
                 </div>
             }
@@ -420,6 +499,28 @@ fn render_user_message(msg: &UserMessage) -> Html {
     }
 }
 
+fn render_operator_message(msg: &OperatorMessage) -> Html {
+    let (icon, detail) = match &msg.action {
+        OperatorAction::Input { .. } => ("👤", None),
+        OperatorAction::Interrupt => ("⏹", None),
+        OperatorAction::PermissionDecision { allowed: true, .. } => ("✓", None),
+        OperatorAction::PermissionDecision {
+            allowed: false,
+            reason,
+            ..
+        } => ("✗", reason.clone()),
+    };
+    html! {
+        <div class="operator-event">
+            <span class="operator-event-icon">{ icon }</span>
+            <span class="operator-event-text">{ msg.action.describe(&msg.user_name) }</span>
+            if let Some(detail) = detail {
+                <span class="operator-event-detail">{ format!("— {}", detail) }</span>
+            }
+        </div>
+    }
+}
+
 fn render_error_message(msg: &ErrorMessage) -> Html {
     // Check for special error types
     if msg.is_overload() {
@@ -443,6 +544,9 @@ fn render_error_message(msg: &ErrorMessage) -> Html {
             </div>
             <div class="message-body">
                 <div class="error-text">{ message }</div>
+                if let Some(hint) = msg.hint() {
+                    <div class="error-hint">{ hint }</div>
+                }
             </div>
         </div>
     }
@@ -584,7 +688,51 @@ fn render_compaction_message(msg: &SystemMessage) -> Html {
     }
 }
 
-fn render_assistant_message(msg: &AssistantMessage) -> Html {
+/// Render the divider where Claude Code compacted the context
+fn render_compact_boundary(boundary: &CompactBoundary) -> Html {
+    let trigger = if boundary.is_manual() {
+        "/compact"
+    } else {
+        "context full"
+    };
+    html! {
+        <div class="compaction-boundary" role="separator">
+            <span class="compaction-boundary-label">
+                { "📦 Context compacted — summary below" }
+            </span>
+            <span class="compaction-stat">{ trigger }</span>
+            if let Some(tokens) = boundary.pre_tokens {
+                <span class="compaction-stat" title="Context size before compacting">
+                    { format!("{} tokens before", format_token_count(tokens)) }
+                </span>
+            }
+        </div>
+    }
+}
+
+/// Render the summary Claude Code continues from after compacting,
+/// collapsed since it restates the conversation above
+fn render_compact_summary(summary: &str) -> Html {
+    html! {
+        <div class="claude-message compaction-message">
+            <details class="compaction-summary">
+                <summary class="summary-label">{ "Summary of the conversation so far" }</summary>
+                <div class="summary-text">{ render_markdown(summary) }</div>
+            </details>
+        </div>
+    }
+}
+
+/// Token count with a K suffix past a thousand
+fn format_token_count(tokens: u64) -> String {
+    if tokens >= 1000 {
+        format!("{:.1}K", tokens as f64 / 1000.0)
+    } else {
+        tokens.to_string()
+    }
+}
+
+fn render_assistant_message(msg: &AssistantMessage, limits: &RenderLimits) -> Html {
     let blocks = msg
         .message
         .as_ref()
@@ -636,13 +784,13 @@ fn render_assistant_message(msg: &AssistantMessage) -> Html {
                 }
             </div>
             <div class="message-body">
-                { render_content_blocks(&blocks) }
+                { render_content_blocks(&blocks, limits) }
             </div>
         </div>
     }
 }
 
-fn render_content_blocks(blocks: &[ContentBlock]) -> Html {
+fn render_content_blocks(blocks: &[ContentBlock], limits: &RenderLimits) -> Html {
     html! {
         <>
             {
@@ -652,7 +800,7 @@ fn render_content_blocks(blocks: &[ContentBlock]) -> Html {
                             html! { <div class="assistant-text">{ render_markdown(text) }</div> }
                         }
                         ContentBlock::ToolUse { id: _, name, input } => {
-                            render_tool_use(name, input)
+                            render_tool_use(name, input, limits)
                         }
                         ContentBlock::ToolResult { tool_use_id: _, content, is_error } => {
                             let class = if *is_error { "tool-result error" } else { "tool-result" };
@@ -669,16 +817,30 @@ fn render_content_blocks(blocks: &[ContentBlock]) -> Html {
                                 }
                                 None => String::new(),
                             };
+                            let total_lines = text.lines().count();
                             // Truncate long results (using safe UTF-8 boundary)
-                            let display = if text.len() > 500 {
-                                format!("{}...", truncate_str(&text, 500))
+                            let display = if text.len() > limits.result_preview_chars {
+                                format!("{}...", truncate_str(&text, limits.result_preview_chars))
                             } else {
                                 text
                             };
+                            // Collapsed on phones, where long output buries the conversation,
+                            // and past the deployment's line threshold; errors stay open
+                            let long = limits.result_collapse_lines > 0
+                                && total_lines > limits.result_collapse_lines;
+                            let open = *is_error || !(crate::mobile::is_compact() || long);
+                            let lines = display.lines().count();
+                            let summary = format!(
+                                "{} · {} line{}",
+                                if *is_error { "Error" } else { "Output" },
+                                lines,
+                                if lines == 1 { "" } else { "s" }
+                            );
                             html! {
-                                <div class={class}>
+                                <details class={class} {open}>
+                                    <summary class="tool-result-summary">{ summary }</summary>
                                     <pre class="tool-result-content">{ display }</pre>
-                                </div>
+                                </details>
                             }
                         }
                         ContentBlock::Thinking { thinking } => {
@@ -699,10 +861,10 @@ fn render_content_blocks(blocks: &[ContentBlock]) -> Html {
 
 /// Render a tool use block with special handling for various tools
 /// Registry pattern - add new tool renderers here
-fn render_tool_use(name: &str, input: &Value) -> Html {
+fn render_tool_use(name: &str, input: &Value, limits: &RenderLimits) -> Html {
     match name {
         "Edit" => render_edit_tool_diff(input),
-        "Write" => render_write_tool(input),
+        "Write" => render_write_tool(input, limits),
         "TodoWrite" => render_todowrite_tool(input),
         "AskUserQuestion" => render_askuserquestion_tool(input),
         "ExitPlanMode" => render_exitplanmode_tool(input),
@@ -1200,18 +1362,67 @@ fn render_edit_tool_diff(input: &Value) -> Html {
     }
 }
 
-/// Render the Write tool with file content preview
-fn render_write_tool(input: &Value) -> Html {
+/// The change an Edit or Write permission request would make, as a diff.
+/// A Write shows its whole content as added, since the prior file isn't known.
+pub fn render_permission_diff(tool_name: &str, input: &Value) -> Option<Html> {
+    let field = |name: &str| input.get(name).and_then(|v| v.as_str());
+    let (old_string, new_string) = match tool_name {
+        "Edit" => (field("old_string")?, field("new_string")?),
+        "Write" => ("", field("content")?),
+        _ => return None,
+    };
+    let (added, removed) = diff_stats(old_string, new_string);
+    let replace_all = input
+        .get("replace_all")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    Some(html! {
+        <div class="permission-diff">
+            <div class="permission-diff-stats">
+                <span class="diff-stat-added">{ format!("+{}", added) }</span>
+                <span class="diff-stat-removed">{ format!("-{}", removed) }</span>
+                if replace_all {
+                    <span class="edit-replace-all">{ "(replace all)" }</span>
+                }
+            </div>
+            <div class="diff-container">
+                { render_diff_lines(old_string, new_string) }
+            </div>
+        </div>
+    })
+}
+
+/// Count of (added, removed) lines between two strings
+fn diff_stats(old_string: &str, new_string: &str) -> (usize, usize) {
+    let old_lines: Vec<&str> = old_string.lines().collect();
+    let new_lines: Vec<&str> = new_string.lines().collect();
+    compute_line_diff(&old_lines, &new_lines)
+        .iter()
+        .fold((0, 0), |(added, removed), line| match line {
+            DiffLine::Added(_) => (added + 1, removed),
+            DiffLine::Removed(_) => (added, removed + 1),
+            DiffLine::Context(_) => (added, removed),
+        })
+}
+
+/// Render the Write tool: small files in full, with a copy button, larger
+/// ones as a preview
+fn render_write_tool(input: &Value, limits: &RenderLimits) -> Html {
     let file_path = input
         .get("file_path")
         .and_then(|v| v.as_str())
         .unwrap_or("unknown file");
     let content = input.get("content").and_then(|v| v.as_str()).unwrap_or("");
 
-    // Show a preview of the content (first N lines)
-    let preview_lines: Vec<&str> = content.lines().take(20).collect();
     let total_lines = content.lines().count();
-    let truncated = total_lines > 20;
+    let inline = content.len() <= limits.inline_file_max_bytes;
+    let shown_lines = if inline {
+        total_lines
+    } else {
+        limits.file_preview_lines.min(total_lines)
+    };
+    let lines = highlight_lines(content.lines().take(shown_lines), lang_for_path(file_path));
 
     html! {
         <div class="tool-use write-tool">
@@ -1220,24 +1431,28 @@ fn render_write_tool(input: &Value) -> Html {
                 <span class="tool-name">{ "Write" }</span>
                 <span class="write-file-path">{ file_path }</span>
                 <span class="write-size">{ format!("({} lines, {} bytes)", total_lines, content.len()) }</span>
+                if inline && !content.is_empty() {
+                    <span class="tool-header-spacer"></span>
+                    <CopyButton text={content.to_string()} />
+                }
             </div>
             <div class="write-preview">
-                <pre class="write-content">
+                <pre class={classes!("write-content", inline.then_some("write-full"))}>
                     {
-                        preview_lines.iter().enumerate().map(|(i, line)| {
+                        lines.into_iter().enumerate().map(|(i, line)| {
                             html! {
                                 <div class="write-line">
                                     <span class="line-number">{ format!("{:>4}", i + 1) }</span>
-                                    <span class="line-content">{ *line }</span>
+                                    <span class="line-content">{ line }</span>
                                 </div>
                             }
                         }).collect::<Html>()
                     }
                     {
-                        if truncated {
+                        if shown_lines < total_lines {
                             html! {
                                 <div class="write-truncated">
-                                    { format!("... {} more lines", total_lines - 20) }
+                                    { format!("... {} more lines", total_lines - shown_lines) }
                                 </div>
                             }
                         } else {
@@ -1744,7 +1959,7 @@ fn log_raw_message(session_id: Option<Uuid>, json: &str, reason: &str) {
 
         if let Ok(future) = result {
             if let Err(e) = future.await {
-                log::warn!("Failed to log raw message: {:?}", e);
+                tracing::warn!("Failed to log raw message: {:?}", e);
             }
         }
     });
@@ -1837,8 +2052,44 @@ fn format_duration(ms: u64) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_diff_stats() {
+        assert_eq!(diff_stats("a\nb\nc", "a\nB\nc\nd"), (2, 1));
+        assert_eq!(diff_stats("", "one\ntwo"), (2, 0));
+        assert_eq!(diff_stats("same", "same"), (0, 0));
+    }
+
+    #[test]
+    fn test_operator_message_parses() {
+        let json = r#"{"type":"operator","user_id":"00000000-0000-0000-0000-000000000000","user_name":"Alice","action":"permission_decision","request_id":"r1","tool_name":"Bash","allowed":false,"reason":"not now"}"#;
+        match serde_json::from_str::<ClaudeMessage>(json).unwrap() {
+            ClaudeMessage::Operator(msg) => {
+                assert_eq!(msg.action.describe(&msg.user_name), "Alice denied Bash")
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
     // Error message tests
 
+    #[test]
+    fn test_error_message_from_portal_code() {
+        let msg: ErrorMessage = serde_json::from_value(serde_json::json!({
+            "type": "error",
+            "code": "not_driver",
+            "message": "Alice is driving this session."
+        }))
+        .unwrap();
+        assert_eq!(msg.display_message(), "Alice is driving this session.");
+        assert_eq!(msg.error_type(), Some("Not driving"));
+        assert!(msg.hint().is_some());
+
+        let legacy: ErrorMessage =
+            serde_json::from_value(serde_json::json!({"message": "Access denied"})).unwrap();
+        assert_eq!(legacy.error_type(), None);
+        assert_eq!(legacy.hint(), None);
+    }
+
     #[test]
     fn test_error_message_overload_detection() {
         let msg = ErrorMessage {
@@ -1848,6 +2099,7 @@ mod tests {
                 message: Some("Overloaded".to_string()),
             }),
             request_id: Some("req_123".to_string()),
+            code: None,
         };
         assert!(msg.is_overload());
         assert_eq!(msg.display_message(), "Overloaded");
@@ -1860,6 +2112,7 @@ mod tests {
             message: Some("Something went wrong".to_string()),
             error: None,
             request_id: None,
+            code: None,
         };
         assert!(!msg.is_overload());
         assert_eq!(msg.display_message(), "Something went wrong");
@@ -1875,6 +2128,7 @@ mod tests {
                 message: Some("Invalid API key".to_string()),
             }),
             request_id: Some("req_456".to_string()),
+            code: None,
         };
         assert!(!msg.is_overload());
         assert_eq!(msg.display_message(), "Invalid API key");
@@ -1,12 +1,62 @@
+mod budget_settings;
+mod ci_badge;
 mod copy_command;
+mod escalation_settings;
+mod fork_dialog;
+mod guest_invites;
+mod input_assist;
+mod kill_switch_settings;
+mod maintenance_banner;
+mod maintenance_settings;
 mod markdown;
+mod message_reactions;
 mod message_renderer;
+mod notification_settings;
+mod onboarding_checklist;
+mod outcome_picker;
+mod permission_history;
+mod presence_avatars;
 mod proxy_token_setup;
+mod push_settings;
+mod redaction_settings;
+mod related_sessions;
+mod report_dialog;
+mod require_auth;
+mod runtime_config_settings;
 mod share_dialog;
+mod syntax;
+mod turn_feedback;
+mod undo_dialog;
 mod voice_input;
+mod voice_language_picker;
+mod voice_settings_form;
 
+pub use budget_settings::BudgetSettings;
+pub use ci_badge::CiBadge;
 pub use copy_command::CopyCommand;
-pub use message_renderer::{group_messages, MessageGroupRenderer};
+pub use fork_dialog::ForkDialog;
+pub use input_assist::{InputAssist, InputAssistForm};
+pub use kill_switch_settings::KillSwitchSettings;
+pub use maintenance_banner::{KillSwitchContext, MaintenanceContext, MaintenanceProvider};
+pub use maintenance_settings::MaintenanceSettings;
+pub use message_reactions::MessageReactions;
+pub use message_renderer::{
+    group_messages, render_permission_diff, MessageGroup, MessageGroupRenderer,
+};
+pub use notification_settings::NotificationSettingsForm;
+pub use onboarding_checklist::OnboardingChecklist;
+pub use outcome_picker::OutcomePicker;
+pub use permission_history::PermissionHistoryDialog;
+pub use presence_avatars::PresenceAvatars;
 pub use proxy_token_setup::ProxyTokenSetup;
+pub use push_settings::PushSettings;
+pub use related_sessions::RelatedSessions;
+pub use report_dialog::ReportDialog;
+pub use require_auth::RequireAuth;
+pub use runtime_config_settings::RuntimeConfigSettings;
 pub use share_dialog::ShareDialog;
-pub use voice_input::VoiceInput;
+pub use turn_feedback::TurnFeedback;
+pub use undo_dialog::UndoDialog;
+pub use voice_input::{VoiceInput, VoiceSettings};
+pub use voice_language_picker::VoiceLanguagePicker;
+pub use voice_settings_form::VoiceSettingsForm;
@@ -0,0 +1,245 @@
+//! Notification Settings
+//!
+//! Grid of which channels each notification event reaches the user on,
+//! limited to the channels the server delivers on, plus the Telegram chat
+//! the bot should message. Saving replaces the whole set of choices.
+
+use gloo_net::http::Request;
+use shared::{
+    NotificationChannel, NotificationEvent, NotificationPreferences, NotificationSettings,
+};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::utils;
+
+/// `preferences` with `channel` turned on or off for `event`; the event's
+/// other channels keep their current (possibly default) state
+fn toggled(
+    preferences: &NotificationPreferences,
+    event: NotificationEvent,
+    channel: NotificationChannel,
+    on: bool,
+) -> NotificationPreferences {
+    let mut updated = preferences.clone();
+    let mut channels = preferences.channels(event);
+    if on {
+        channels.insert(channel);
+    } else {
+        channels.remove(&channel);
+    }
+    updated.routes.insert(event, channels);
+    updated
+}
+
+#[function_component(NotificationSettingsForm)]
+pub fn notification_settings_form() -> Html {
+    let settings = use_state(|| None::<NotificationSettings>);
+    let status = use_state(|| None::<Result<String, String>>);
+    let saving = use_state(|| false);
+    let api_endpoint = utils::api_url("/api/notifications");
+
+    {
+        let settings = settings.clone();
+        let status = status.clone();
+        let api_endpoint = api_endpoint.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                match Request::get(&api_endpoint).send().await {
+                    Ok(response) if response.ok() => {
+                        if let Ok(loaded) = response.json::<NotificationSettings>().await {
+                            settings.set(Some(loaded));
+                        }
+                    }
+                    Ok(response) => status.set(Some(Err(format!(
+                        "Failed to load notification settings: {}",
+                        response.status()
+                    )))),
+                    Err(e) => status.set(Some(Err(format!(
+                        "Failed to load notification settings: {:?}",
+                        e
+                    )))),
+                }
+            });
+            || ()
+        });
+    }
+
+    let Some(current) = (*settings).clone() else {
+        return html! {};
+    };
+    if current.available.is_empty() {
+        return html! {};
+    }
+
+    let on_toggle = |event: NotificationEvent, channel: NotificationChannel| {
+        let settings = settings.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            if let Some(current) = &*settings {
+                settings.set(Some(NotificationSettings {
+                    preferences: toggled(&current.preferences, event, channel, input.checked()),
+                    available: current.available.clone(),
+                }));
+            }
+        })
+    };
+
+    let on_chat_id = {
+        let settings = settings.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            if let Some(current) = &*settings {
+                let mut updated = current.clone();
+                updated.preferences.telegram_chat_id = Some(input.value());
+                settings.set(Some(updated));
+            }
+        })
+    };
+
+    let on_submit = {
+        let settings = settings.clone();
+        let status = status.clone();
+        let saving = saving.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let Some(current) = (*settings).clone() else {
+                return;
+            };
+            let mut preferences = current.preferences;
+            preferences.telegram_chat_id = preferences
+                .telegram_chat_id
+                .map(|id| id.trim().to_string())
+                .filter(|id| !id.is_empty());
+            if let Err(message) = preferences.validate() {
+                status.set(Some(Err(message)));
+                return;
+            }
+            let settings = settings.clone();
+            let status = status.clone();
+            let saving = saving.clone();
+            let api_endpoint = api_endpoint.clone();
+            saving.set(true);
+            spawn_local(async move {
+                let result = match Request::put(&api_endpoint).json(&preferences) {
+                    Ok(request) => request.send().await,
+                    Err(e) => {
+                        status.set(Some(Err(format!(
+                            "Failed to encode notification settings: {:?}",
+                            e
+                        ))));
+                        saving.set(false);
+                        return;
+                    }
+                };
+                match result {
+                    Ok(response) if response.ok() => {
+                        if let Ok(saved) = response.json::<NotificationSettings>().await {
+                            settings.set(Some(saved));
+                        }
+                        status.set(Some(Ok("Notifications saved".to_string())));
+                    }
+                    Ok(response) => status.set(Some(Err(format!(
+                        "Failed to save notification settings: {}",
+                        response.status()
+                    )))),
+                    Err(e) => status.set(Some(Err(format!(
+                        "Failed to save notification settings: {:?}",
+                        e
+                    )))),
+                }
+                saving.set(false);
+            });
+        })
+    };
+
+    let preferences = &current.preferences;
+    html! {
+        <section class="notification-settings-section">
+            <div class="section-header">
+                <h2>{ "Notification Channels" }</h2>
+                <p class="section-description">
+                    { "Choose where each event reaches you. Slack posts to the team's shared channel." }
+                </p>
+            </div>
+            <form onsubmit={on_submit}>
+                <table class="notification-grid">
+                    <thead>
+                        <tr>
+                            <th></th>
+                            { for current.available.iter().map(|channel| html! {
+                                <th>{ channel.label() }</th>
+                            }) }
+                        </tr>
+                    </thead>
+                    <tbody>
+                        { for NotificationEvent::ALL.iter().map(|&event| html! {
+                            <tr>
+                                <td>{ event.label() }</td>
+                                { for current.available.iter().map(|&channel| html! {
+                                    <td>
+                                        <input
+                                            type="checkbox"
+                                            checked={preferences.routes_to(event, channel)}
+                                            onchange={on_toggle(event, channel)}
+                                        />
+                                    </td>
+                                }) }
+                            </tr>
+                        }) }
+                    </tbody>
+                </table>
+                if current.available.contains(&NotificationChannel::Telegram) {
+                    <label class="notification-chat-id">
+                        <span>{ "Telegram chat ID" }</span>
+                        <input
+                            type="text"
+                            placeholder="123456789"
+                            value={preferences.telegram_chat_id.clone().unwrap_or_default()}
+                            oninput={on_chat_id}
+                        />
+                    </label>
+                }
+                <div class="notification-actions">
+                    <button type="submit" class="create-button" disabled={*saving}>
+                        { if *saving { "Saving..." } else { "Save" } }
+                    </button>
+                    {
+                        match &*status {
+                            Some(Ok(message)) => html! { <span class="budget-saved">{ message }</span> },
+                            Some(Err(message)) => html! { <span class="budget-error">{ message }</span> },
+                            None => html! {},
+                        }
+                    }
+                </div>
+            </form>
+        </section>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggled_keeps_other_defaults() {
+        use NotificationChannel::*;
+        use NotificationEvent::*;
+
+        let preferences = toggled(
+            &NotificationPreferences::default(),
+            PermissionRequested,
+            Telegram,
+            false,
+        );
+        assert_eq!(preferences.channels(PermissionRequested), [Push].into());
+        assert!(preferences.routes_to(TurnFinished, Slack));
+
+        let preferences = toggled(&preferences, PermissionRequested, Slack, true);
+        assert_eq!(
+            preferences.channels(PermissionRequested),
+            [Push, Slack].into()
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Onboarding Checklist Component
+//!
+//! First-use setup steps for the signed-in user, ticked off from
+//! `GET /api/onboarding`: create a proxy token, connect a proxy, get a first
+//! reply from Claude, and (optionally) turn on voice input. Each unfinished
+//! step links to where it's done.
+
+use gloo_net::http::Request;
+use shared::OnboardingStatus;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::utils;
+use crate::Route;
+
+const VOICE_DOCS_URL: &str =
+    "https://github.com/meawoppl/claude-code-portal/blob/main/docs/DEPLOYING.md#environment-variables";
+
+#[derive(Properties, PartialEq)]
+pub struct OnboardingChecklistProps {
+    /// Open the setup command dialog
+    pub on_setup: Callback<()>,
+    #[prop_or_default]
+    pub is_admin: bool,
+}
+
+struct Step {
+    done: bool,
+    optional: bool,
+    title: &'static str,
+    detail: &'static str,
+    action: Html,
+}
+
+#[function_component(OnboardingChecklist)]
+pub fn onboarding_checklist(props: &OnboardingChecklistProps) -> Html {
+    let status = use_state(|| None::<OnboardingStatus>);
+    let navigator = use_navigator();
+
+    {
+        let status = status.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                match Request::get(&utils::api_url("/api/onboarding"))
+                    .send()
+                    .await
+                {
+                    Ok(response) if response.ok() => {
+                        if let Ok(data) = response.json::<OnboardingStatus>().await {
+                            status.set(Some(data));
+                        }
+                    }
+                    Ok(response) => {
+                        tracing::warn!("Failed to load onboarding status: {}", response.status());
+                    }
+                    Err(e) => tracing::warn!("Failed to load onboarding status: {:?}", e),
+                }
+            });
+            || ()
+        });
+    }
+
+    let Some(status) = (*status).clone() else {
+        return html! {};
+    };
+
+    let setup_button = |label: &'static str| {
+        let on_setup = props.on_setup.clone();
+        html! {
+            <button type="button" onclick={Callback::from(move |_: MouseEvent| on_setup.emit(()))}>
+                { label }
+            </button>
+        }
+    };
+
+    let (voice_detail, voice_action) = if !status.voice_available {
+        (
+            "The server needs Google Speech-to-Text credentials first.",
+            html! {
+                <a href={VOICE_DOCS_URL} target="_blank" rel="noopener noreferrer">
+                    { "Setup guide" }
+                </a>
+            },
+        )
+    } else if props.is_admin {
+        let on_admin = Callback::from(move |_: MouseEvent| {
+            if let Some(navigator) = &navigator {
+                navigator.push(&Route::Admin);
+            }
+        });
+        (
+            "Turn on voice for your account in the admin user list.",
+            html! { <button type="button" onclick={on_admin}>{ "Open Admin" }</button> },
+        )
+    } else {
+        ("Ask an admin to turn on voice for your account.", html! {})
+    };
+
+    let steps = [
+        Step {
+            done: status.token_created,
+            optional: false,
+            title: "Create a proxy token",
+            detail: "Run the setup command and approve the sign-in, or create a token in Settings.",
+            action: setup_button("Show setup command"),
+        },
+        Step {
+            done: status.proxy_connected,
+            optional: false,
+            title: "Connect a proxy",
+            detail: "Run claude-portal in a project on your dev machine.",
+            action: setup_button("Show setup command"),
+        },
+        Step {
+            done: status.session_streamed,
+            optional: false,
+            title: "Get a first reply from Claude",
+            detail: "Open the session and send it a prompt.",
+            action: html! {},
+        },
+        Step {
+            done: status.voice_configured(),
+            optional: true,
+            title: "Set up voice input",
+            detail: voice_detail,
+            action: voice_action,
+        },
+    ];
+
+    html! {
+        <div class="onboarding-checklist">
+            <h2>{ "Get Set Up" }</h2>
+            <ol class="onboarding-checklist-steps">
+                { for steps.into_iter().map(|step| html! {
+                    <li class={classes!("onboarding-checklist-step", step.done.then_some("done"))}>
+                        <span class="onboarding-check">{ if step.done { "✓" } else { "✗" } }</span>
+                        <div class="onboarding-check-content">
+                            <strong>{ step.title }</strong>
+                            if step.optional {
+                                <span class="onboarding-optional">{ "optional" }</span>
+                            }
+                            if !step.done {
+                                <p>{ step.detail }</p>
+                            }
+                        </div>
+                        if !step.done {
+                            <div class="onboarding-check-action">{ step.action }</div>
+                        }
+                    </li>
+                }) }
+            </ol>
+        </div>
+    }
+}
@@ -0,0 +1,69 @@
+//! Outcome Picker Component
+//!
+//! Dropdown for labeling how a finished session turned out.
+
+use gloo_net::http::Request;
+use shared::{SessionOutcome, SetSessionOutcomeRequest};
+use uuid::Uuid;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlSelectElement;
+use yew::prelude::*;
+
+use crate::utils;
+
+#[derive(Properties, PartialEq)]
+pub struct OutcomePickerProps {
+    pub session_id: Uuid,
+    pub outcome: Option<SessionOutcome>,
+    /// Called with the saved outcome once the API accepts it
+    pub on_change: Callback<Option<SessionOutcome>>,
+    #[prop_or(false)]
+    pub disabled: bool,
+}
+
+#[function_component(OutcomePicker)]
+pub fn outcome_picker(props: &OutcomePickerProps) -> Html {
+    let on_select = {
+        let session_id = props.session_id;
+        let on_change = props.on_change.clone();
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            let outcome = SessionOutcome::parse(&select.value());
+            let on_change = on_change.clone();
+            spawn_local(async move {
+                let url = utils::api_url(&format!("/api/sessions/{}/outcome", session_id));
+                let request = match Request::put(&url).json(&SetSessionOutcomeRequest { outcome }) {
+                    Ok(request) => request,
+                    Err(e) => {
+                        tracing::error!("Failed to build outcome request: {:?}", e);
+                        return;
+                    }
+                };
+                match request.send().await {
+                    Ok(response) if response.ok() => on_change.emit(outcome),
+                    Ok(response) => tracing::error!("Failed to set outcome: {}", response.status()),
+                    Err(e) => tracing::error!("Failed to set outcome: {:?}", e),
+                }
+            });
+        })
+    };
+
+    let current = props.outcome.map(|o| o.as_str()).unwrap_or("");
+
+    html! {
+        <select
+            class={classes!("outcome-picker", props.outcome.map(|o| o.as_str()))}
+            onchange={on_select}
+            disabled={props.disabled}
+        >
+            <option value="" selected={current.is_empty()}>{ "Unlabeled" }</option>
+            {
+                SessionOutcome::ALL.iter().map(|outcome| html! {
+                    <option value={outcome.as_str()} selected={current == outcome.as_str()}>
+                        { outcome.label() }
+                    </option>
+                }).collect::<Html>()
+            }
+        </select>
+    }
+}
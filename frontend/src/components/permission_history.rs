@@ -0,0 +1,183 @@
+//! Permission History Dialog
+//!
+//! Lists every permission request a session made, newest first, with who
+//! answered it, when, and how: from the web UI or by a rule the proxy
+//! remembered. Useful for reviewing what was approved while nobody watched.
+//! The session's escalation settings are shown above the list.
+
+use gloo_net::http::Request;
+use shared::permission_rules::rule_target;
+use shared::{DecisionChannel, PermissionDecisionInfo};
+use uuid::Uuid;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use super::escalation_settings::EscalationSettings;
+use crate::utils;
+
+#[derive(Properties, PartialEq)]
+pub struct PermissionHistoryDialogProps {
+    pub session_id: Uuid,
+    /// Whether the current user owns the session and may edit escalation
+    #[prop_or_default]
+    pub is_owner: bool,
+    pub on_close: Callback<()>,
+}
+
+/// "2026-01-23 14:05" in local time
+fn format_timestamp(ts: &str) -> String {
+    let date = js_sys::Date::new(&ts.into());
+    if date.get_time().is_nan() {
+        return ts.to_string();
+    }
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}",
+        date.get_full_year(),
+        date.get_month() + 1,
+        date.get_date(),
+        date.get_hours(),
+        date.get_minutes()
+    )
+}
+
+/// The command or path a request was for, or its compact input
+fn request_summary(entry: &PermissionDecisionInfo) -> String {
+    rule_target(&entry.tool_name, &entry.input)
+        .map(str::to_string)
+        .unwrap_or_else(|| entry.input.to_string())
+}
+
+fn render_decision(entry: &PermissionDecisionInfo) -> Html {
+    let (class, label) = match entry.allowed {
+        Some(true) => ("allowed", "Allowed"),
+        Some(false) => ("denied", "Denied"),
+        None => ("waiting", "Waiting"),
+    };
+    let by = match (entry.channel, &entry.decided_by) {
+        (Some(DecisionChannel::Rule), _) => Some("by rule".to_string()),
+        (Some(DecisionChannel::Timeout), _) => Some("by escalation default".to_string()),
+        (Some(DecisionChannel::Scope), _) => Some("by token scope".to_string()),
+        (Some(DecisionChannel::Terminal), _) => Some("in the proxy's terminal".to_string()),
+        (Some(DecisionChannel::KillSwitch), _) => Some("by the kill switch".to_string()),
+        (Some(DecisionChannel::Web), Some(name)) => Some(format!("by {} (web)", name)),
+        (Some(DecisionChannel::Web), None) => Some("from the web".to_string()),
+        (None, _) => None,
+    };
+    html! {
+        <>
+        <div class="permission-history-decision">
+            <span class={classes!("permission-history-badge", class)}>{ label }</span>
+            if let Some(by) = by {
+                <span class="permission-history-by">{ by }</span>
+            }
+            if let Some(ref at) = entry.decided_at {
+                <span class="permission-history-time">{ format_timestamp(at) }</span>
+            }
+            if let Some(ref detail) = entry.detail {
+                <span class="permission-history-detail" title={detail.clone()}>{ detail }</span>
+            }
+        </div>
+        if let Some(ref at) = entry.escalated_at {
+            <div class="permission-history-escalation">
+                { format!(
+                    "Escalated to {} at {}",
+                    entry.escalated_to.as_deref().unwrap_or("a contact"),
+                    format_timestamp(at)
+                ) }
+            </div>
+        }
+        </>
+    }
+}
+
+#[function_component(PermissionHistoryDialog)]
+pub fn permission_history_dialog(props: &PermissionHistoryDialogProps) -> Html {
+    let entries = use_state(|| None::<Result<Vec<PermissionDecisionInfo>, String>>);
+
+    {
+        let entries = entries.clone();
+        use_effect_with(props.session_id, move |session_id| {
+            let url = utils::api_url(&format!("/api/sessions/{}/permissions", session_id));
+            spawn_local(async move {
+                let result = match Request::get(&url).send().await {
+                    Ok(response) if response.ok() => response
+                        .json::<Vec<PermissionDecisionInfo>>()
+                        .await
+                        .map_err(|e| e.to_string()),
+                    Ok(response) => Err(format!("Server returned {}", response.status())),
+                    Err(e) => Err(e.to_string()),
+                };
+                entries.set(Some(result));
+            });
+            || ()
+        });
+    }
+
+    let on_close = props.on_close.clone();
+    let on_overlay_click = {
+        let on_close = on_close.clone();
+        Callback::from(move |_: MouseEvent| on_close.emit(()))
+    };
+
+    let body = match &*entries {
+        None => html! { <div class="permission-history-empty">{ "Loading…" }</div> },
+        Some(Err(e)) => html! {
+            <div class="permission-history-empty">{ format!("Failed to load history: {}", e) }</div>
+        },
+        Some(Ok(list)) if list.is_empty() => html! {
+            <div class="permission-history-empty">{ "No permission requests yet" }</div>
+        },
+        Some(Ok(list)) => {
+            let count = |allowed| list.iter().filter(|e| e.allowed == allowed).count();
+            html! {
+                <>
+                    <div class="permission-history-summary">
+                        { format!(
+                            "{} allowed · {} denied · {} waiting",
+                            count(Some(true)),
+                            count(Some(false)),
+                            count(None)
+                        ) }
+                    </div>
+                    <ul class="permission-history-list">
+                        { for list.iter().map(|entry| {
+                            let summary = request_summary(entry);
+                            html! {
+                                <li key={entry.request_id.clone()}>
+                                    <div class="permission-history-request">
+                                        <span class="permission-history-time">
+                                            { format_timestamp(&entry.requested_at) }
+                                        </span>
+                                        <span class="tool-name">{ &entry.tool_name }</span>
+                                        <code title={summary.clone()}>{ summary }</code>
+                                    </div>
+                                    { render_decision(entry) }
+                                </li>
+                            }
+                        }) }
+                    </ul>
+                </>
+            }
+        }
+    };
+
+    html! {
+        <div class="share-dialog-overlay" onclick={on_overlay_click}>
+            <div
+                class="share-dialog permission-history-dialog"
+                onclick={|e: MouseEvent| e.stop_propagation()}
+            >
+                <div class="share-dialog-header">
+                    <h2>{ "Permission History" }</h2>
+                    <button class="share-dialog-close" onclick={move |_| on_close.emit(())}>
+                        { "×" }
+                    </button>
+                </div>
+                <div class="permission-history-body">
+                    <EscalationSettings session_id={props.session_id} editable={props.is_owner} />
+                    { body }
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,46 @@
+//! Presence Avatars Component
+//!
+//! Shows who else is connected to a session, highlighting whoever sent the last input.
+
+use shared::PresenceViewer;
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct PresenceAvatarsProps {
+    pub viewers: Vec<PresenceViewer>,
+}
+
+#[function_component(PresenceAvatars)]
+pub fn presence_avatars(props: &PresenceAvatarsProps) -> Html {
+    // Alone in the session - nothing worth showing
+    if props.viewers.len() < 2 {
+        return html! {};
+    }
+
+    html! {
+        <div class="presence-avatars" title={format!("{} viewers", props.viewers.len())}>
+            {
+                props.viewers.iter().map(|viewer| {
+                    let title = if viewer.driving {
+                        format!("{} (driving)", viewer.name)
+                    } else {
+                        format!("{} (watching)", viewer.name)
+                    };
+                    html! {
+                        <span
+                            key={viewer.user_id.to_string()}
+                            class={classes!("presence-avatar", viewer.driving.then_some("driving"))}
+                            {title}
+                        >
+                            if let Some(url) = &viewer.avatar_url {
+                                <img src={url.clone()} alt={viewer.initials()} referrerpolicy="no-referrer" />
+                            } else {
+                                { viewer.initials() }
+                            }
+                        </span>
+                    }
+                }).collect::<Html>()
+            }
+        </div>
+    }
+}
@@ -0,0 +1,277 @@
+//! Push Notification Settings
+//!
+//! Turns browser notifications on or off for this device. Turning them on
+//! registers `/sw.js`, asks for notification permission, subscribes with the
+//! backend's VAPID key, and sends the subscription to the backend; each
+//! device is subscribed separately.
+
+use gloo_net::http::Request;
+use shared::{AppConfig, PushSubscriptionInfo, UnsubscribePushRequest};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{
+    Notification, NotificationPermission, PushSubscription, PushSubscriptionOptionsInit,
+    ServiceWorkerRegistration,
+};
+use yew::prelude::*;
+
+use crate::offline::SERVICE_WORKER_URL;
+use crate::utils;
+
+#[derive(Clone, PartialEq)]
+enum PushState {
+    Loading,
+    /// The browser has no service workers or Push API
+    Unsupported,
+    /// The backend has no VAPID keys
+    NotConfigured,
+    /// The user blocked notifications for the portal
+    Blocked,
+    Off,
+    On,
+}
+
+fn browser_supports_push() -> bool {
+    let Some(window) = web_sys::window() else {
+        return false;
+    };
+    js_sys::Reflect::has(&window.navigator(), &JsValue::from_str("serviceWorker")).unwrap_or(false)
+        && js_sys::Reflect::has(&window, &JsValue::from_str("PushManager")).unwrap_or(false)
+        && js_sys::Reflect::has(&window, &JsValue::from_str("Notification")).unwrap_or(false)
+}
+
+/// Base64url as the backend reports the VAPID key, to the bytes
+/// `PushManager.subscribe` expects
+fn decode_base64url(text: &str) -> Option<js_sys::Uint8Array> {
+    let mut base64 = text.replace('-', "+").replace('_', "/");
+    while !base64.len().is_multiple_of(4) {
+        base64.push('=');
+    }
+    let binary = web_sys::window()?.atob(&base64).ok()?;
+    let bytes: Vec<u8> = binary.chars().map(|c| c as u8).collect();
+    Some(js_sys::Uint8Array::from(bytes.as_slice()))
+}
+
+fn error_text(e: JsValue) -> String {
+    e.as_string()
+        .or_else(|| {
+            e.dyn_ref::<js_sys::Error>()
+                .map(|e| String::from(e.message()))
+        })
+        .unwrap_or_else(|| format!("{:?}", e))
+}
+
+async fn registration() -> Result<ServiceWorkerRegistration, String> {
+    let window = web_sys::window().ok_or("No window")?;
+    let registration = JsFuture::from(
+        window
+            .navigator()
+            .service_worker()
+            .register(SERVICE_WORKER_URL),
+    )
+    .await
+    .map_err(error_text)?;
+    registration.dyn_into().map_err(error_text)
+}
+
+async fn current_subscription(
+    registration: &ServiceWorkerRegistration,
+) -> Result<Option<PushSubscription>, String> {
+    let push_manager = registration.push_manager().map_err(error_text)?;
+    let subscription = JsFuture::from(push_manager.get_subscription().map_err(error_text)?)
+        .await
+        .map_err(error_text)?;
+    if subscription.is_null() || subscription.is_undefined() {
+        return Ok(None);
+    }
+    subscription.dyn_into().map(Some).map_err(error_text)
+}
+
+/// Subscribe this browser and register the subscription with the backend
+async fn enable(public_key: &str) -> Result<PushState, String> {
+    let permission = JsFuture::from(Notification::request_permission().map_err(error_text)?)
+        .await
+        .map_err(error_text)?;
+    if permission.as_string().as_deref() != Some("granted") {
+        return Ok(PushState::Blocked);
+    }
+
+    let registration = registration().await?;
+    let subscription = match current_subscription(&registration).await? {
+        Some(subscription) => subscription,
+        None => {
+            let key = decode_base64url(public_key).ok_or("Invalid push key from server")?;
+            let options = PushSubscriptionOptionsInit::new();
+            options.set_user_visible_only(true);
+            options.set_application_server_key(&key);
+            let push_manager = registration.push_manager().map_err(error_text)?;
+            JsFuture::from(
+                push_manager
+                    .subscribe_with_options(&options)
+                    .map_err(error_text)?,
+            )
+            .await
+            .map_err(error_text)?
+            .dyn_into()
+            .map_err(error_text)?
+        }
+    };
+
+    let json = js_sys::JSON::stringify(&subscription)
+        .map_err(error_text)?
+        .as_string()
+        .unwrap_or_default();
+    let info: PushSubscriptionInfo =
+        serde_json::from_str(&json).map_err(|e| format!("Unexpected subscription: {}", e))?;
+    let response = Request::post(&utils::api_url("/api/push/subscriptions"))
+        .json(&info)
+        .map_err(|e| format!("{:?}", e))?
+        .send()
+        .await
+        .map_err(|e| format!("{:?}", e))?;
+    if !response.ok() {
+        return Err(format!("Server returned {}", response.status()));
+    }
+    Ok(PushState::On)
+}
+
+/// Remove this browser's subscription from the backend and the browser
+async fn disable() -> Result<PushState, String> {
+    let registration = registration().await?;
+    if let Some(subscription) = current_subscription(&registration).await? {
+        let request = UnsubscribePushRequest {
+            endpoint: subscription.endpoint(),
+        };
+        let response = Request::delete(&utils::api_url("/api/push/subscriptions"))
+            .json(&request)
+            .map_err(|e| format!("{:?}", e))?
+            .send()
+            .await
+            .map_err(|e| format!("{:?}", e))?;
+        if !response.ok() {
+            return Err(format!("Server returned {}", response.status()));
+        }
+        JsFuture::from(subscription.unsubscribe().map_err(error_text)?)
+            .await
+            .map_err(error_text)?;
+    }
+    Ok(PushState::Off)
+}
+
+/// Where things stand for this browser, without prompting the user
+async fn load_state(public_key: Option<&str>) -> Result<PushState, String> {
+    if !browser_supports_push() {
+        return Ok(PushState::Unsupported);
+    }
+    if public_key.is_none() {
+        return Ok(PushState::NotConfigured);
+    }
+    if Notification::permission() == NotificationPermission::Denied {
+        return Ok(PushState::Blocked);
+    }
+    let registration = registration().await?;
+    Ok(match current_subscription(&registration).await? {
+        Some(_) if Notification::permission() == NotificationPermission::Granted => PushState::On,
+        _ => PushState::Off,
+    })
+}
+
+#[function_component(PushSettings)]
+pub fn push_settings() -> Html {
+    let state = use_state(|| PushState::Loading);
+    let public_key = use_state(|| None::<String>);
+    let busy = use_state(|| false);
+    let error = use_state(|| None::<String>);
+
+    {
+        let state = state.clone();
+        let public_key = public_key.clone();
+        let error = error.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                let key = match Request::get(&utils::api_url("/api/config")).send().await {
+                    Ok(response) if response.ok() => response
+                        .json::<AppConfig>()
+                        .await
+                        .ok()
+                        .and_then(|config| config.push_public_key),
+                    _ => None,
+                };
+                match load_state(key.as_deref()).await {
+                    Ok(loaded) => state.set(loaded),
+                    Err(e) => {
+                        state.set(PushState::Off);
+                        error.set(Some(e));
+                    }
+                }
+                public_key.set(key);
+            });
+            || ()
+        });
+    }
+
+    let on_toggle = {
+        let state = state.clone();
+        let public_key = public_key.clone();
+        let busy = busy.clone();
+        let error = error.clone();
+        Callback::from(move |_: MouseEvent| {
+            let turning_on = *state == PushState::Off;
+            let public_key = (*public_key).clone().unwrap_or_default();
+            let state = state.clone();
+            let busy = busy.clone();
+            let error = error.clone();
+            busy.set(true);
+            error.set(None);
+            spawn_local(async move {
+                let result = if turning_on {
+                    enable(&public_key).await
+                } else {
+                    disable().await
+                };
+                match result {
+                    Ok(updated) => state.set(updated),
+                    Err(e) => error.set(Some(e)),
+                }
+                busy.set(false);
+            });
+        })
+    };
+
+    let status = match *state {
+        PushState::Loading => "Checking this browser...",
+        PushState::Unsupported => "This browser doesn't support push notifications.",
+        PushState::NotConfigured => "Push notifications aren't set up on this server.",
+        PushState::Blocked => {
+            "Notifications are blocked for this site. Allow them in your browser's site settings, then reload."
+        }
+        PushState::Off => "Off for this device.",
+        PushState::On => "On for this device.",
+    };
+
+    html! {
+        <section class="push-section">
+            <div class="section-header">
+                <h2>{ "Notifications" }</h2>
+                <p class="section-description">
+                    { "Get browser notifications on this device, even with the portal closed. Choose which events they're sent for under Notification Channels." }
+                </p>
+                if matches!(*state, PushState::Off | PushState::On) {
+                    <button class="create-button" onclick={on_toggle} disabled={*busy}>
+                        {
+                            match (*busy, &*state) {
+                                (true, _) => "Working...",
+                                (false, PushState::On) => "Turn Off",
+                                _ => "Turn On",
+                            }
+                        }
+                    </button>
+                }
+            </div>
+            <p class="push-status">{ status }</p>
+            if let Some(error) = &*error {
+                <p class="push-error">{ error }</p>
+            }
+        </section>
+    }
+}
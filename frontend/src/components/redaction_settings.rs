@@ -0,0 +1,223 @@
+//! Redaction Settings
+//!
+//! Per-session form for what viewers don't get to see: every tool's output,
+//! or just that of the checked tools. Shown in the share dialog, which only
+//! the session owner opens; editors always see everything.
+
+use gloo_net::http::Request;
+use shared::redaction::SUGGESTED_TOOLS;
+use shared::RedactionPolicy;
+use uuid::Uuid;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::utils;
+
+/// Tools named in the "Other tools" field, which aren't among the checkboxes
+fn other_tools(policy: &RedactionPolicy) -> String {
+    policy
+        .tools
+        .iter()
+        .filter(|tool| !SUGGESTED_TOOLS.contains(&tool.as_str()))
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The policy with the checkbox tools as given and `others` parsed from the
+/// comma-separated field
+fn with_tools(policy: &RedactionPolicy, checked: &[String], others: &str) -> RedactionPolicy {
+    let mut tools: Vec<String> = checked.to_vec();
+    tools.extend(others.split(',').map(|tool| tool.trim().to_string()));
+    RedactionPolicy {
+        whole_session: policy.whole_session,
+        tools,
+    }
+    .normalized()
+}
+
+#[derive(Properties, PartialEq)]
+pub struct RedactionSettingsProps {
+    pub session_id: Uuid,
+}
+
+#[function_component(RedactionSettings)]
+pub fn redaction_settings(props: &RedactionSettingsProps) -> Html {
+    let policy = use_state(RedactionPolicy::default);
+    let others = use_state(String::new);
+    let status = use_state(|| None::<Result<String, String>>);
+    let saving = use_state(|| false);
+    let api_endpoint = utils::api_url(&format!("/api/sessions/{}/redaction", props.session_id));
+
+    {
+        let policy = policy.clone();
+        let others = others.clone();
+        let status = status.clone();
+        let api_endpoint = api_endpoint.clone();
+        use_effect_with(props.session_id, move |_| {
+            spawn_local(async move {
+                match Request::get(&api_endpoint).send().await {
+                    Ok(response) if response.ok() => {
+                        if let Ok(loaded) = response.json::<RedactionPolicy>().await {
+                            others.set(other_tools(&loaded));
+                            policy.set(loaded);
+                        }
+                    }
+                    Ok(response) => status.set(Some(Err(format!(
+                        "Failed to load redaction: {}",
+                        response.status()
+                    )))),
+                    Err(e) => status.set(Some(Err(format!("Failed to load redaction: {:?}", e)))),
+                }
+            });
+            || ()
+        });
+    }
+
+    let checked: Vec<String> = policy
+        .tools
+        .iter()
+        .filter(|tool| SUGGESTED_TOOLS.contains(&tool.as_str()))
+        .cloned()
+        .collect();
+
+    let on_whole_session = {
+        let policy = policy.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            policy.set(RedactionPolicy {
+                whole_session: input.checked(),
+                ..(*policy).clone()
+            });
+        })
+    };
+
+    let on_tool = |tool: &'static str| {
+        let policy = policy.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut updated = (*policy).clone();
+            updated.tools.retain(|t| t != tool);
+            if input.checked() {
+                updated.tools.push(tool.to_string());
+            }
+            policy.set(updated);
+        })
+    };
+
+    let on_others = {
+        let others = others.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            others.set(input.value());
+        })
+    };
+
+    let on_submit = {
+        let policy = policy.clone();
+        let others = others.clone();
+        let status = status.clone();
+        let saving = saving.clone();
+        let checked = checked.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let updated = with_tools(&policy, &checked, &others);
+            let policy = policy.clone();
+            let others = others.clone();
+            let status = status.clone();
+            let saving = saving.clone();
+            let api_endpoint = api_endpoint.clone();
+            saving.set(true);
+            spawn_local(async move {
+                let result = match Request::put(&api_endpoint).json(&updated) {
+                    Ok(request) => request.send().await,
+                    Err(e) => {
+                        status.set(Some(Err(format!("Failed to encode redaction: {:?}", e))));
+                        saving.set(false);
+                        return;
+                    }
+                };
+                match result {
+                    Ok(response) if response.ok() => {
+                        if let Ok(saved) = response.json::<RedactionPolicy>().await {
+                            others.set(other_tools(&saved));
+                            policy.set(saved);
+                        }
+                        status.set(Some(Ok("Redaction saved".to_string())));
+                    }
+                    Ok(response) => status.set(Some(Err(format!(
+                        "Failed to save redaction: {}",
+                        response.status()
+                    )))),
+                    Err(e) => status.set(Some(Err(format!("Failed to save redaction: {:?}", e)))),
+                }
+                saving.set(false);
+            });
+        })
+    };
+
+    html! {
+        <form class="redaction-settings" onsubmit={on_submit}>
+            <h3>{ "Hidden from viewers" }</h3>
+            <label class="redaction-option">
+                <input
+                    type="checkbox"
+                    checked={policy.whole_session}
+                    onchange={on_whole_session}
+                />
+                { "All tool output and file contents" }
+            </label>
+            <div class="redaction-tools">
+                { for SUGGESTED_TOOLS.iter().map(|tool| html! {
+                    <label class="redaction-option">
+                        <input
+                            type="checkbox"
+                            checked={policy.whole_session || checked.iter().any(|t| t == tool)}
+                            disabled={policy.whole_session}
+                            onchange={on_tool(tool)}
+                        />
+                        { *tool }
+                    </label>
+                }) }
+            </div>
+            <input
+                type="text"
+                class="redaction-others"
+                placeholder="Other tools, e.g. WebFetch, mcp__db__query"
+                value={(*others).clone()}
+                oninput={on_others}
+                disabled={policy.whole_session}
+            />
+            <div class="escalation-actions">
+                <button type="submit" class="tab-btn active" disabled={*saving}>
+                    { if *saving { "Saving..." } else { "Save" } }
+                </button>
+                {
+                    match &*status {
+                        Some(Ok(message)) => html! { <span class="budget-saved">{ message }</span> },
+                        Some(Err(message)) => html! { <span class="budget-error">{ message }</span> },
+                        None => html! {},
+                    }
+                }
+            </div>
+        </form>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_other_tools_roundtrip() {
+        let policy = RedactionPolicy {
+            whole_session: false,
+            tools: vec!["Bash".into(), "WebFetch".into(), "mcp__db__query".into()],
+        };
+        let others = other_tools(&policy);
+        assert_eq!(others, "WebFetch, mcp__db__query");
+        assert_eq!(with_tools(&policy, &["Bash".to_string()], &others), policy);
+        assert_eq!(with_tools(&policy, &[], " , "), RedactionPolicy::default());
+    }
+}
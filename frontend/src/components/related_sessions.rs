@@ -0,0 +1,139 @@
+//! Related Sessions Component
+//!
+//! Suggests past sessions that started on a similar task, from
+//! `GET /api/sessions/{id}/related`. Before a session's first prompt is sent
+//! the suggestions follow the draft instead. Renders nothing when there are
+//! none, including when the server has no embedding provider.
+
+use gloo::timers::callback::Timeout;
+use gloo_net::http::Request;
+use shared::RelatedSession;
+use uuid::Uuid;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::utils;
+use crate::Route;
+
+/// Wait this long after the last keystroke before comparing a draft
+const DRAFT_DEBOUNCE_MS: u32 = 600;
+
+/// Drafts shorter than this don't say enough to compare
+const MIN_DRAFT_CHARS: usize = 16;
+
+#[derive(Properties, PartialEq)]
+pub struct RelatedSessionsProps {
+    pub session_id: Uuid,
+    /// The first prompt being typed, while the session has none yet
+    #[prop_or_default]
+    pub draft: Option<AttrValue>,
+}
+
+#[function_component(RelatedSessions)]
+pub fn related_sessions(props: &RelatedSessionsProps) -> Html {
+    let related = use_state(Vec::<RelatedSession>::new);
+    let dismissed = use_state(|| false);
+    let navigator = use_navigator();
+
+    let draft = props
+        .draft
+        .as_ref()
+        .map(|d| d.trim().to_string())
+        .filter(|d| d.chars().count() >= MIN_DRAFT_CHARS);
+
+    {
+        let related = related.clone();
+        use_effect_with((props.session_id, draft), move |(session_id, draft)| {
+            let mut path = format!("/api/sessions/{}/related", session_id);
+            if let Some(draft) = draft {
+                path.push_str(&format!("?q={}", js_sys::encode_uri_component(draft)));
+            }
+            let fetch = move || {
+                spawn_local(async move {
+                    match Request::get(&utils::api_url(&path)).send().await {
+                        Ok(response) if response.ok() => {
+                            if let Ok(sessions) = response.json::<Vec<RelatedSession>>().await {
+                                related.set(sessions);
+                            }
+                        }
+                        Ok(response) => {
+                            tracing::warn!("Failed to load related sessions: {}", response.status())
+                        }
+                        Err(e) => tracing::warn!("Failed to load related sessions: {:?}", e),
+                    }
+                });
+            };
+            // Dropping the timeout cancels it, so only the last keystroke fetches
+            let timeout = if draft.is_some() {
+                Some(Timeout::new(DRAFT_DEBOUNCE_MS, fetch))
+            } else {
+                fetch();
+                None
+            };
+            move || drop(timeout)
+        });
+    }
+
+    if related.is_empty() || *dismissed {
+        return html! {};
+    }
+
+    let on_dismiss = {
+        let dismissed = dismissed.clone();
+        Callback::from(move |_: MouseEvent| dismissed.set(true))
+    };
+
+    html! {
+        <div class="related-sessions">
+            <div class="related-sessions-header">
+                <span>{ "Similar past sessions" }</span>
+                <button
+                    type="button"
+                    class="related-sessions-dismiss"
+                    title="Hide suggestions"
+                    onclick={on_dismiss}
+                >
+                    { "×" }
+                </button>
+            </div>
+            {
+                related.iter().map(|session| {
+                    let onclick = {
+                        let navigator = navigator.clone();
+                        let session_id = session.session_id;
+                        Callback::from(move |_: MouseEvent| {
+                            if let Some(navigator) = &navigator {
+                                let _ = navigator.push_with_query(
+                                    &Route::Dashboard,
+                                    &[("session", session_id.to_string())],
+                                );
+                            }
+                        })
+                    };
+                    html! {
+                        <button
+                            key={session.session_id.to_string()}
+                            type="button"
+                            class="related-session"
+                            title={session.working_directory.clone()}
+                            {onclick}
+                        >
+                            <span class="related-session-name">
+                                { utils::extract_folder(&session.working_directory) }
+                                { " · " }
+                                { &session.session_name }
+                            </span>
+                            if let Some(outcome) = session.outcome {
+                                <span class={classes!("related-session-outcome", outcome.as_str())}>
+                                    { outcome.label() }
+                                </span>
+                            }
+                            <span class="related-session-prompt">{ &session.first_prompt }</span>
+                        </button>
+                    }
+                }).collect::<Html>()
+            }
+        </div>
+    }
+}
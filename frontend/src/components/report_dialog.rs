@@ -0,0 +1,298 @@
+//! Report Problem Dialog
+//!
+//! Collects a short description and opens a pre-filled issue on the configured
+//! tracker. The offending message's raw JSON is only attached when the user
+//! ticks the box, and is redacted before it is shown or sent.
+
+use shared::{redact_secrets, IncidentInfo};
+use uuid::Uuid;
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
+use yew::prelude::*;
+
+use crate::utils;
+
+/// Longest message JSON included in a report, in bytes. Issue trackers
+/// reject very long pre-fill URLs.
+const MAX_MESSAGE_JSON_LEN: usize = 4000;
+
+#[derive(Properties, PartialEq)]
+pub struct ReportDialogProps {
+    /// New-issue URL from the app config
+    pub issue_url: AttrValue,
+    #[prop_or_default]
+    pub session_id: Option<Uuid>,
+    /// Most recent diagnostic bundle for the session, if any
+    #[prop_or_default]
+    pub incident: Option<IncidentInfo>,
+    /// Raw JSON of the message being reported
+    #[prop_or_default]
+    pub message_json: Option<String>,
+    pub on_close: Callback<()>,
+}
+
+/// Everything that goes into the issue body
+struct ReportDetails<'a> {
+    description: &'a str,
+    frontend_version: &'a str,
+    user_agent: Option<&'a str>,
+    session_id: Option<Uuid>,
+    incident: Option<&'a IncidentInfo>,
+    bundle_url: Option<&'a str>,
+    message_json: Option<&'a str>,
+}
+
+/// Redact and shorten message JSON for inclusion in a report
+fn prepare_message_json(json: &str) -> String {
+    // Pretty-print when possible so the issue is readable
+    let pretty = serde_json::from_str::<serde_json::Value>(json)
+        .ok()
+        .and_then(|v| serde_json::to_string_pretty(&v).ok())
+        .unwrap_or_else(|| json.to_string());
+    let redacted = redact_secrets(&pretty);
+    if redacted.len() <= MAX_MESSAGE_JSON_LEN {
+        return redacted;
+    }
+    let mut end = MAX_MESSAGE_JSON_LEN;
+    while !redacted.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}\n… (truncated)", &redacted[..end])
+}
+
+/// Markdown body for the issue
+fn build_issue_body(details: &ReportDetails) -> String {
+    let mut body = String::new();
+    body.push_str("### What happened\n\n");
+    let description = details.description.trim();
+    body.push_str(if description.is_empty() {
+        "_No description provided._"
+    } else {
+        description
+    });
+    body.push_str("\n\n### Environment\n\n");
+    body.push_str(&format!(
+        "- Frontend version: {}\n",
+        details.frontend_version
+    ));
+    if let Some(agent) = details.user_agent {
+        body.push_str(&format!("- Browser: {}\n", agent));
+    }
+    if let Some(session_id) = details.session_id {
+        body.push_str(&format!("- Session: `{}`\n", session_id));
+    }
+    if let Some(incident) = details.incident {
+        body.push_str(&format!(
+            "- Diagnostic bundle: `{}` ({}, {})\n",
+            incident.id, incident.reason, incident.created_at
+        ));
+        if let Some(url) = details.bundle_url {
+            body.push_str(&format!(
+                "  - Download (requires session access): {}\n",
+                url
+            ));
+        }
+    }
+    if let Some(json) = details.message_json {
+        body.push_str("\n### Message JSON\n\n```json\n");
+        body.push_str(json);
+        body.push_str("\n```\n");
+    }
+    body
+}
+
+/// Append pre-fill parameters to the configured issue URL
+fn issue_url_with_params(base: &str, title: &str, body: &str) -> String {
+    let separator = if base.contains('?') { '&' } else { '?' };
+    format!(
+        "{}{}title={}&body={}",
+        base,
+        separator,
+        js_sys::encode_uri_component(title),
+        js_sys::encode_uri_component(body)
+    )
+}
+
+#[function_component(ReportDialog)]
+pub fn report_dialog(props: &ReportDialogProps) -> Html {
+    let title = use_state(String::new);
+    let description = use_state(String::new);
+    let include_message = use_state(|| false);
+
+    let prepared_json = props.message_json.as_deref().map(prepare_message_json);
+
+    let on_title_input = {
+        let title = title.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            title.set(input.value());
+        })
+    };
+
+    let on_description_input = {
+        let description = description.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlTextAreaElement = e.target_unchecked_into();
+            description.set(input.value());
+        })
+    };
+
+    let on_toggle_include = {
+        let include_message = include_message.clone();
+        Callback::from(move |_: Event| include_message.set(!*include_message))
+    };
+
+    let on_submit = {
+        let title = title.clone();
+        let description = description.clone();
+        let include_message = include_message.clone();
+        let prepared_json = prepared_json.clone();
+        let issue_url = props.issue_url.clone();
+        let session_id = props.session_id;
+        let incident = props.incident.clone();
+        let on_close = props.on_close.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let window = web_sys::window();
+            let user_agent = window
+                .as_ref()
+                .and_then(|w| w.navigator().user_agent().ok());
+            let bundle_url = match (session_id, incident.as_ref()) {
+                (Some(session_id), Some(incident)) => {
+                    let path = utils::api_url(&format!(
+                        "/api/sessions/{}/incidents/{}/bundle",
+                        session_id, incident.id
+                    ));
+                    // Make the link absolute so it works from the issue tracker
+                    let origin = window
+                        .as_ref()
+                        .and_then(|w| w.location().origin().ok())
+                        .unwrap_or_default();
+                    Some(if path.starts_with('/') {
+                        format!("{}{}", origin, path)
+                    } else {
+                        path
+                    })
+                }
+                _ => None,
+            };
+            let body = build_issue_body(&ReportDetails {
+                description: &description,
+                frontend_version: env!("CARGO_PKG_VERSION"),
+                user_agent: user_agent.as_deref(),
+                session_id,
+                incident: incident.as_ref(),
+                bundle_url: bundle_url.as_deref(),
+                message_json: prepared_json.as_deref().filter(|_| *include_message),
+            });
+            let issue_title = match title.trim() {
+                "" => "Problem report",
+                t => t,
+            };
+            let url = issue_url_with_params(&issue_url, issue_title, &body);
+            if let Some(window) = window {
+                let _ = window.open_with_url_and_target(&url, "_blank");
+            }
+            on_close.emit(());
+        })
+    };
+
+    let on_close = props.on_close.clone();
+    let on_overlay_click = {
+        let on_close = on_close.clone();
+        Callback::from(move |_: MouseEvent| on_close.emit(()))
+    };
+
+    html! {
+        <div class="share-dialog-overlay" onclick={on_overlay_click}>
+            <div class="share-dialog report-dialog" onclick={|e: MouseEvent| e.stop_propagation()}>
+                <div class="share-dialog-header">
+                    <h2>{ "Report a Problem" }</h2>
+                    <button class="share-dialog-close" onclick={move |_| on_close.emit(())}>
+                        { "×" }
+                    </button>
+                </div>
+
+                <form class="report-dialog-form" onsubmit={on_submit}>
+                    <input
+                        type="text"
+                        placeholder="Short summary"
+                        value={(*title).clone()}
+                        oninput={on_title_input}
+                        maxlength="200"
+                    />
+                    <textarea
+                        placeholder="What happened? What did you expect?"
+                        value={(*description).clone()}
+                        oninput={on_description_input}
+                        rows="5"
+                    />
+                    if let Some(ref incident) = props.incident {
+                        <div class="report-dialog-note">
+                            { format!("The diagnostic bundle reference ({}) will be included.", incident.reason) }
+                        </div>
+                    }
+                    if let Some(ref json) = prepared_json {
+                        <label class="report-dialog-include">
+                            <input
+                                type="checkbox"
+                                checked={*include_message}
+                                onchange={on_toggle_include}
+                            />
+                            { "Include this message's raw JSON (secrets are redacted, but review it first)" }
+                        </label>
+                        if *include_message {
+                            <pre class="report-dialog-preview">{ json }</pre>
+                        }
+                    }
+                    <div class="report-dialog-note">
+                        { "This opens a pre-filled issue in a new tab. Nothing is sent until you submit it there." }
+                    </div>
+                    <div class="modal-actions">
+                        <button type="submit" class="report-dialog-submit">{ "Open Issue" }</button>
+                    </div>
+                </form>
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prepare_message_json_redacts_and_truncates() {
+        let json = r#"{"type":"user","text":"token sk-ant-REDACTED"}"#;
+        let prepared = prepare_message_json(json);
+        assert!(!prepared.contains("sk-ant-api03"));
+        assert!(prepared.contains("\"type\": \"user\""));
+
+        let long = format!(r#"{{"text":"{}"}}"#, "é".repeat(MAX_MESSAGE_JSON_LEN));
+        let prepared = prepare_message_json(&long);
+        assert!(prepared.ends_with("(truncated)"));
+    }
+
+    #[test]
+    fn test_build_issue_body() {
+        let incident = IncidentInfo {
+            id: Uuid::nil(),
+            session_id: Uuid::nil(),
+            reason: "Claude process exited with code 101".to_string(),
+            created_at: "2026-01-21T00:00:00Z".to_string(),
+        };
+        let body = build_issue_body(&ReportDetails {
+            description: "  ",
+            frontend_version: "1.2.3",
+            user_agent: None,
+            session_id: Some(Uuid::nil()),
+            incident: Some(&incident),
+            bundle_url: None,
+            message_json: Some("{}"),
+        });
+        assert!(body.contains("_No description provided._"));
+        assert!(body.contains("- Frontend version: 1.2.3"));
+        assert!(body.contains("exited with code 101"));
+        assert!(body.contains("```json\n{}\n```"));
+        assert!(!body.contains("Browser"));
+    }
+}
@@ -0,0 +1,59 @@
+//! Route guard
+//!
+//! Wraps pages that need a signed-in user. The guard asks `/api/auth/me`
+//! before rendering them and sends anyone without a session back to the
+//! splash page to sign in. The API enforces sessions on its own; this keeps
+//! signed-out visitors from landing on a page that can only show errors.
+
+use gloo_net::http::Request;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::{utils, Route};
+
+#[derive(Clone, Copy, PartialEq)]
+enum AuthState {
+    Checking,
+    SignedIn,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct RequireAuthProps {
+    pub children: Html,
+}
+
+#[function_component(RequireAuth)]
+pub fn require_auth(props: &RequireAuthProps) -> Html {
+    let state = use_state(|| AuthState::Checking);
+    let navigator = use_navigator();
+
+    {
+        let state = state.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                let api_endpoint = utils::api_url("/api/auth/me");
+                match Request::get(&api_endpoint).send().await {
+                    // No session, or the user behind it is gone
+                    Ok(response) if response.status() == 401 || response.status() == 404 => {
+                        if let Some(navigator) = navigator {
+                            navigator.replace(&Route::Home);
+                        }
+                    }
+                    Ok(_) => state.set(AuthState::SignedIn),
+                    // Let the page show its own error rather than a blank screen
+                    Err(e) => {
+                        tracing::warn!("Failed to check session: {:?}", e);
+                        state.set(AuthState::SignedIn);
+                    }
+                }
+            });
+            || ()
+        });
+    }
+
+    match *state {
+        AuthState::Checking => html! {},
+        AuthState::SignedIn => props.children.clone(),
+    }
+}
@@ -0,0 +1,431 @@
+//! Runtime Config Settings
+//!
+//! Admin form for settings that apply without a server restart. Each field
+//! either follows the server's environment default or overrides it.
+
+use gloo_net::http::Request;
+use shared::{RuntimeConfig, RuntimeConfigInfo, RuntimeConfigOverrides};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::utils;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    AppTitle,
+    IssueReportUrl,
+    AllowedEmailDomain,
+    AllowedEmails,
+    RetentionCount,
+    RetentionDays,
+    MaxSessionsPerUser,
+    RequestsPerMinute,
+    MessagesPerMinute,
+    ResultPreviewChars,
+    ResultCollapseLines,
+    InlineFileMaxBytes,
+    FilePreviewLines,
+}
+
+const FIELDS: [(Field, &str, &str); 13] = [
+    (
+        Field::AppTitle,
+        "App title",
+        "Shown in the top bar and browser tab",
+    ),
+    (
+        Field::IssueReportUrl,
+        "Issue report URL",
+        "Where \"Report a problem\" opens a pre-filled issue",
+    ),
+    (
+        Field::AllowedEmailDomain,
+        "Allowed email domain",
+        "Leave empty to allow any domain",
+    ),
+    (
+        Field::AllowedEmails,
+        "Allowed emails",
+        "Comma-separated; leave empty for no per-email allowlist",
+    ),
+    (
+        Field::RetentionCount,
+        "Messages kept per session",
+        "Older messages are deleted",
+    ),
+    (
+        Field::RetentionDays,
+        "Message retention (days)",
+        "0 keeps messages regardless of age",
+    ),
+    (
+        Field::MaxSessionsPerUser,
+        "Running sessions per user",
+        "New proxies are turned away at this many; 0 for no limit",
+    ),
+    (
+        Field::RequestsPerMinute,
+        "API requests per minute",
+        "Per proxy token or signed-in user; 0 for no limit",
+    ),
+    (
+        Field::MessagesPerMinute,
+        "Socket messages per minute",
+        "Per proxy token or signed-in user; 0 for no limit",
+    ),
+    (
+        Field::ResultPreviewChars,
+        "Tool output preview (characters)",
+        "Longer tool output is cut off in the transcript",
+    ),
+    (
+        Field::ResultCollapseLines,
+        "Collapse tool output over (lines)",
+        "0 collapses only on phones",
+    ),
+    (
+        Field::InlineFileMaxBytes,
+        "Inline file size (bytes)",
+        "Written files up to this size are shown in full",
+    ),
+    (
+        Field::FilePreviewLines,
+        "File preview (lines)",
+        "Lines shown of larger written files",
+    ),
+];
+
+/// Text of a field in `config`
+fn field_text(config: &RuntimeConfig, field: Field) -> String {
+    match field {
+        Field::AppTitle => config.app_title.clone(),
+        Field::IssueReportUrl => config.issue_report_url.clone(),
+        Field::AllowedEmailDomain => config.allowed_email_domain.clone().unwrap_or_default(),
+        Field::AllowedEmails => config
+            .allowed_emails
+            .as_ref()
+            .map(|emails| emails.join(", "))
+            .unwrap_or_default(),
+        Field::RetentionCount => config.message_retention_count.to_string(),
+        Field::RetentionDays => config.message_retention_days.to_string(),
+        Field::MaxSessionsPerUser => config.max_sessions_per_user.to_string(),
+        Field::RequestsPerMinute => config.rate_limit_requests_per_minute.to_string(),
+        Field::MessagesPerMinute => config.rate_limit_messages_per_minute.to_string(),
+        Field::ResultPreviewChars => config.render_limits.result_preview_chars.to_string(),
+        Field::ResultCollapseLines => config.render_limits.result_collapse_lines.to_string(),
+        Field::InlineFileMaxBytes => config.render_limits.inline_file_max_bytes.to_string(),
+        Field::FilePreviewLines => config.render_limits.file_preview_lines.to_string(),
+    }
+}
+
+fn is_overridden(overrides: &RuntimeConfigOverrides, field: Field) -> bool {
+    match field {
+        Field::AppTitle => overrides.app_title.is_some(),
+        Field::IssueReportUrl => overrides.issue_report_url.is_some(),
+        Field::AllowedEmailDomain => overrides.allowed_email_domain.is_some(),
+        Field::AllowedEmails => overrides.allowed_emails.is_some(),
+        Field::RetentionCount => overrides.message_retention_count.is_some(),
+        Field::RetentionDays => overrides.message_retention_days.is_some(),
+        Field::MaxSessionsPerUser => overrides.max_sessions_per_user.is_some(),
+        Field::RequestsPerMinute => overrides.rate_limit_requests_per_minute.is_some(),
+        Field::MessagesPerMinute => overrides.rate_limit_messages_per_minute.is_some(),
+        Field::ResultPreviewChars => overrides.result_preview_chars.is_some(),
+        Field::ResultCollapseLines => overrides.result_collapse_lines.is_some(),
+        Field::InlineFileMaxBytes => overrides.inline_file_max_bytes.is_some(),
+        Field::FilePreviewLines => overrides.file_preview_lines.is_some(),
+    }
+}
+
+/// Form state: whether each field is overridden, and its text
+type FormState = Vec<(bool, String)>;
+
+fn form_state(info: &RuntimeConfigInfo) -> FormState {
+    FIELDS
+        .iter()
+        .map(|(field, _, _)| {
+            (
+                is_overridden(&info.overrides, *field),
+                field_text(&info.effective, *field),
+            )
+        })
+        .collect()
+}
+
+fn to_overrides(state: &FormState) -> Result<RuntimeConfigOverrides, String> {
+    let mut overrides = RuntimeConfigOverrides::default();
+    for ((field, label, _), (enabled, text)) in FIELDS.iter().zip(state) {
+        if !enabled {
+            continue;
+        }
+        let text = text.trim();
+        let number_error = || format!("{} must be a whole number", label);
+        match field {
+            Field::AppTitle => overrides.app_title = Some(text.to_string()),
+            Field::IssueReportUrl => overrides.issue_report_url = Some(text.to_string()),
+            Field::AllowedEmailDomain => overrides.allowed_email_domain = Some(text.to_string()),
+            Field::AllowedEmails => {
+                overrides.allowed_emails = Some(
+                    text.split(',')
+                        .map(|e| e.trim().to_string())
+                        .filter(|e| !e.is_empty())
+                        .collect(),
+                )
+            }
+            Field::RetentionCount => {
+                overrides.message_retention_count = Some(text.parse().map_err(|_| number_error())?)
+            }
+            Field::RetentionDays => {
+                overrides.message_retention_days = Some(text.parse().map_err(|_| number_error())?)
+            }
+            Field::MaxSessionsPerUser => {
+                overrides.max_sessions_per_user = Some(text.parse().map_err(|_| number_error())?)
+            }
+            Field::RequestsPerMinute => {
+                overrides.rate_limit_requests_per_minute =
+                    Some(text.parse().map_err(|_| number_error())?)
+            }
+            Field::MessagesPerMinute => {
+                overrides.rate_limit_messages_per_minute =
+                    Some(text.parse().map_err(|_| number_error())?)
+            }
+            Field::ResultPreviewChars => {
+                overrides.result_preview_chars = Some(text.parse().map_err(|_| number_error())?)
+            }
+            Field::ResultCollapseLines => {
+                overrides.result_collapse_lines = Some(text.parse().map_err(|_| number_error())?)
+            }
+            Field::InlineFileMaxBytes => {
+                overrides.inline_file_max_bytes = Some(text.parse().map_err(|_| number_error())?)
+            }
+            Field::FilePreviewLines => {
+                overrides.file_preview_lines = Some(text.parse().map_err(|_| number_error())?)
+            }
+        }
+    }
+    overrides.validate()?;
+    Ok(overrides)
+}
+
+#[function_component(RuntimeConfigSettings)]
+pub fn runtime_config_settings() -> Html {
+    let info = use_state(|| None::<RuntimeConfigInfo>);
+    let form = use_state(FormState::new);
+    let status = use_state(|| None::<Result<String, String>>);
+    let saving = use_state(|| false);
+
+    {
+        let info = info.clone();
+        let form = form.clone();
+        let status = status.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                let api_endpoint = utils::api_url("/api/admin/config");
+                match Request::get(&api_endpoint).send().await {
+                    Ok(response) if response.ok() => {
+                        if let Ok(data) = response.json::<RuntimeConfigInfo>().await {
+                            form.set(form_state(&data));
+                            info.set(Some(data));
+                        }
+                    }
+                    Ok(response) => status.set(Some(Err(format!(
+                        "Failed to load settings: {}",
+                        response.status()
+                    )))),
+                    Err(e) => status.set(Some(Err(format!("Failed to load settings: {:?}", e)))),
+                }
+            });
+            || ()
+        });
+    }
+
+    let Some(ref loaded) = *info else {
+        return html! {
+            <div class="runtime-config">
+                if let Some(Err(ref message)) = *status {
+                    <span class="budget-error">{ message }</span>
+                } else {
+                    <p>{ "Loading settings..." }</p>
+                }
+            </div>
+        };
+    };
+
+    let on_toggle = |index: usize| {
+        let form = form.clone();
+        let default = field_text(&loaded.defaults, FIELDS[index].0);
+        Callback::from(move |_: Event| {
+            let mut updated = (*form).clone();
+            let (enabled, text) = &mut updated[index];
+            *enabled = !*enabled;
+            if !*enabled {
+                *text = default.clone();
+            }
+            form.set(updated);
+        })
+    };
+
+    let on_input = |index: usize| {
+        let form = form.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut updated = (*form).clone();
+            updated[index].1 = input.value();
+            form.set(updated);
+        })
+    };
+
+    let on_submit = {
+        let info = info.clone();
+        let form = form.clone();
+        let status = status.clone();
+        let saving = saving.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let overrides = match to_overrides(&form) {
+                Ok(overrides) => overrides,
+                Err(message) => {
+                    status.set(Some(Err(message)));
+                    return;
+                }
+            };
+            let info = info.clone();
+            let form = form.clone();
+            let status = status.clone();
+            let saving = saving.clone();
+            saving.set(true);
+            spawn_local(async move {
+                let api_endpoint = utils::api_url("/api/admin/config");
+                let result = match Request::put(&api_endpoint).json(&overrides) {
+                    Ok(request) => request.send().await,
+                    Err(e) => {
+                        status.set(Some(Err(format!("Failed to encode settings: {:?}", e))));
+                        saving.set(false);
+                        return;
+                    }
+                };
+                match result {
+                    Ok(response) if response.ok() => {
+                        if let Ok(data) = response.json::<RuntimeConfigInfo>().await {
+                            form.set(form_state(&data));
+                            info.set(Some(data));
+                        }
+                        status.set(Some(Ok("Saved and applied".to_string())));
+                    }
+                    Ok(response) => status.set(Some(Err(format!(
+                        "Failed to save settings: {}",
+                        response.status()
+                    )))),
+                    Err(e) => status.set(Some(Err(format!("Failed to save settings: {:?}", e)))),
+                }
+                saving.set(false);
+            });
+        })
+    };
+
+    html! {
+        <form class="runtime-config" onsubmit={on_submit}>
+            <p class="budget-settings-description">
+                { "These settings take effect immediately, without a restart. Unchecked fields use the server's environment." }
+            </p>
+            <table class="admin-table">
+                <thead>
+                    <tr>
+                        <th>{ "Setting" }</th>
+                        <th>{ "Override" }</th>
+                        <th>{ "Value" }</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    { for FIELDS.iter().enumerate().map(|(index, (_, label, hint))| {
+                        let (enabled, ref text) = form[index];
+                        html! {
+                            <tr>
+                                <td>
+                                    <div>{ *label }</div>
+                                    <div class="runtime-config-hint">{ *hint }</div>
+                                </td>
+                                <td>
+                                    <input
+                                        type="checkbox"
+                                        checked={enabled}
+                                        onchange={on_toggle(index)}
+                                    />
+                                </td>
+                                <td>
+                                    <input
+                                        type="text"
+                                        class="budget-input runtime-config-input"
+                                        value={text.clone()}
+                                        disabled={!enabled}
+                                        oninput={on_input(index)}
+                                    />
+                                </td>
+                            </tr>
+                        }
+                    }) }
+                </tbody>
+            </table>
+            <div class="budget-settings-actions">
+                <button type="submit" class="tab-btn active" disabled={*saving}>
+                    { if *saving { "Saving..." } else { "Save settings" } }
+                </button>
+                {
+                    match &*status {
+                        Some(Ok(message)) => html! { <span class="budget-saved">{ message }</span> },
+                        Some(Err(message)) => html! { <span class="budget-error">{ message }</span> },
+                        None => html! {},
+                    }
+                }
+            </div>
+        </form>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info() -> RuntimeConfigInfo {
+        let defaults = RuntimeConfig {
+            app_title: "Claude Code Sessions".to_string(),
+            issue_report_url: shared::DEFAULT_ISSUE_REPORT_URL.to_string(),
+            allowed_email_domain: Some("example.com".to_string()),
+            allowed_emails: None,
+            message_retention_count: 100,
+            message_retention_days: 30,
+            max_sessions_per_user: 0,
+            rate_limit_requests_per_minute: 0,
+            rate_limit_messages_per_minute: 0,
+            render_limits: shared::RenderLimits::default(),
+        };
+        let overrides = RuntimeConfigOverrides {
+            allowed_emails: Some(vec![
+                "a@example.com".to_string(),
+                "b@example.com".to_string(),
+            ]),
+            ..Default::default()
+        };
+        RuntimeConfigInfo {
+            effective: defaults.with_overrides(&overrides),
+            defaults,
+            overrides,
+        }
+    }
+
+    #[test]
+    fn test_form_roundtrip() {
+        let info = info();
+        let state = form_state(&info);
+        assert_eq!(state[3], (true, "a@example.com, b@example.com".to_string()));
+        assert!(!state[0].0);
+        assert_eq!(to_overrides(&state), Ok(info.overrides));
+    }
+
+    #[test]
+    fn test_to_overrides_rejects_bad_numbers() {
+        let mut state = form_state(&info());
+        state[5] = (true, "a week".to_string());
+        assert!(to_overrides(&state).unwrap_err().contains("whole number"));
+    }
+}
@@ -4,6 +4,8 @@ use wasm_bindgen_futures::spawn_local;
 use web_sys::HtmlInputElement;
 use yew::prelude::*;
 
+use super::guest_invites::GuestInvites;
+use super::redaction_settings::RedactionSettings;
 use crate::utils;
 
 /// Member info returned from API
@@ -13,6 +15,9 @@ pub struct MemberInfo {
     pub email: String,
     pub name: Option<String>,
     pub role: String,
+    /// When a guest's access ends
+    #[serde(default)]
+    pub expires_at: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, serde::Deserialize)]
@@ -78,13 +83,13 @@ impl Component for ShareDialog {
                             }
                         }
                         Ok(response) => {
-                            log::error!("Failed to load members: {}", response.status());
+                            tracing::error!("Failed to load members: {}", response.status());
                             link.send_message(ShareDialogMsg::SetError(
                                 "Failed to load members".to_string(),
                             ));
                         }
                         Err(e) => {
-                            log::error!("Failed to load members: {:?}", e);
+                            tracing::error!("Failed to load members: {:?}", e);
                             link.send_message(ShareDialogMsg::SetError(
                                 "Failed to load members".to_string(),
                             ));
@@ -139,13 +144,13 @@ impl Component for ShareDialog {
                             ));
                         }
                         Ok(response) => {
-                            log::error!("Failed to add member: {}", response.status());
+                            tracing::error!("Failed to add member: {}", response.status());
                             link.send_message(ShareDialogMsg::SetError(
                                 "Failed to add member".to_string(),
                             ));
                         }
                         Err(e) => {
-                            log::error!("Failed to add member: {:?}", e);
+                            tracing::error!("Failed to add member: {:?}", e);
                             link.send_message(ShareDialogMsg::SetError(
                                 "Failed to add member".to_string(),
                             ));
@@ -173,13 +178,13 @@ impl Component for ShareDialog {
                             link.send_message(ShareDialogMsg::MemberRemoved(user_id));
                         }
                         Ok(response) => {
-                            log::error!("Failed to remove member: {}", response.status());
+                            tracing::error!("Failed to remove member: {}", response.status());
                             link.send_message(ShareDialogMsg::SetError(
                                 "Failed to remove member".to_string(),
                             ));
                         }
                         Err(e) => {
-                            log::error!("Failed to remove member: {:?}", e);
+                            tracing::error!("Failed to remove member: {:?}", e);
                             link.send_message(ShareDialogMsg::SetError(
                                 "Failed to remove member".to_string(),
                             ));
@@ -214,13 +219,13 @@ impl Component for ShareDialog {
                             link.send_message(ShareDialogMsg::RoleChanged(user_id, role));
                         }
                         Ok(response) => {
-                            log::error!("Failed to change role: {}", response.status());
+                            tracing::error!("Failed to change role: {}", response.status());
                             link.send_message(ShareDialogMsg::SetError(
                                 "Failed to change role".to_string(),
                             ));
                         }
                         Err(e) => {
-                            log::error!("Failed to change role: {:?}", e);
+                            tracing::error!("Failed to change role: {:?}", e);
                             link.send_message(ShareDialogMsg::SetError(
                                 "Failed to change role".to_string(),
                             ));
@@ -326,6 +331,17 @@ impl Component for ShareDialog {
                             }
                         }
                     </div>
+
+                    <div class="share-dialog-guests">
+                        <GuestInvites
+                            session_id={ctx.props().session_id}
+                            on_change={ctx.link().callback(|_| ShareDialogMsg::LoadMembers)}
+                        />
+                    </div>
+
+                    <div class="share-dialog-redaction">
+                        <RedactionSettings session_id={ctx.props().session_id} />
+                    </div>
                 </div>
             </div>
         }
@@ -335,12 +351,14 @@ impl Component for ShareDialog {
 impl ShareDialog {
     fn view_member(&self, ctx: &Context<Self>, member: &MemberInfo) -> Html {
         let is_owner = member.role == "owner";
+        let is_guest = member.expires_at.is_some();
         let user_id = member.user_id;
-        let display_name = member
-            .name
-            .as_ref()
-            .map(|n| format!("{} ({})", n, member.email))
-            .unwrap_or_else(|| member.email.clone());
+        let display_name = match &member.name {
+            // Guests' made-up addresses mean nothing to anyone
+            Some(name) if is_guest => name.clone(),
+            Some(name) => format!("{} ({})", name, member.email),
+            None => member.email.clone(),
+        };
 
         let on_remove = ctx
             .link()
@@ -360,6 +378,14 @@ impl ShareDialog {
                 {
                     if is_owner {
                         html! { <span class="member-role owner">{ "Owner" }</span> }
+                    } else if is_guest {
+                        // Guests keep the access their link gave; revoke it to end it early
+                        let access = if member.role == shared::APPROVER_ROLE {
+                            "Approve only"
+                        } else {
+                            "View only"
+                        };
+                        html! { <span class="member-role">{ access }</span> }
                     } else {
                         html! {
                             <>
@@ -0,0 +1,689 @@
+//! Lightweight syntax highlighting for file contents.
+//!
+//! A line tokenizer that picks out keywords, strings, comments and numbers
+//! for common languages, chosen by file extension, a code block's language
+//! tag, or a guess from the code itself. It is deliberately approximate:
+//! good enough to make a created file or a snippet easy to scan.
+
+use yew::prelude::*;
+
+/// What to highlight for one language
+pub struct LangSpec {
+    /// Shown on code blocks whose language was detected
+    pub name: &'static str,
+    /// A code block tag that [`lang_for_tag`] maps back to this language
+    pub tag: &'static str,
+    keywords: &'static [&'static str],
+    line_comment: Option<&'static str>,
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+}
+
+static RUST: LangSpec = LangSpec {
+    name: "Rust",
+    tag: "rust",
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+        "true", "type", "unsafe", "use", "where", "while",
+    ],
+    line_comment: Some("//"),
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"'],
+};
+
+static JAVASCRIPT: LangSpec = LangSpec {
+    name: "JavaScript",
+    tag: "javascript",
+    keywords: &[
+        "async",
+        "await",
+        "break",
+        "case",
+        "catch",
+        "class",
+        "const",
+        "continue",
+        "default",
+        "delete",
+        "do",
+        "else",
+        "export",
+        "extends",
+        "false",
+        "finally",
+        "for",
+        "from",
+        "function",
+        "if",
+        "import",
+        "in",
+        "instanceof",
+        "interface",
+        "let",
+        "new",
+        "null",
+        "return",
+        "static",
+        "super",
+        "switch",
+        "this",
+        "throw",
+        "true",
+        "try",
+        "type",
+        "typeof",
+        "undefined",
+        "var",
+        "void",
+        "while",
+        "yield",
+    ],
+    line_comment: Some("//"),
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '\'', '`'],
+};
+
+static PYTHON: LangSpec = LangSpec {
+    name: "Python",
+    tag: "python",
+    keywords: &[
+        "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+        "elif", "else", "except", "False", "finally", "for", "from", "global", "if", "import",
+        "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return", "True",
+        "try", "while", "with", "yield",
+    ],
+    line_comment: Some("#"),
+    block_comment: None,
+    quotes: &['"', '\''],
+};
+
+static GO: LangSpec = LangSpec {
+    name: "Go",
+    tag: "go",
+    keywords: &[
+        "break",
+        "case",
+        "chan",
+        "const",
+        "continue",
+        "default",
+        "defer",
+        "else",
+        "false",
+        "fallthrough",
+        "for",
+        "func",
+        "go",
+        "goto",
+        "if",
+        "import",
+        "interface",
+        "map",
+        "nil",
+        "package",
+        "range",
+        "return",
+        "select",
+        "struct",
+        "switch",
+        "true",
+        "type",
+        "var",
+    ],
+    line_comment: Some("//"),
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '`'],
+};
+
+static C_FAMILY: LangSpec = LangSpec {
+    name: "C-like",
+    tag: "c",
+    keywords: &[
+        "auto",
+        "bool",
+        "break",
+        "case",
+        "char",
+        "class",
+        "const",
+        "continue",
+        "default",
+        "do",
+        "double",
+        "else",
+        "enum",
+        "extends",
+        "extern",
+        "false",
+        "final",
+        "float",
+        "for",
+        "if",
+        "implements",
+        "import",
+        "int",
+        "long",
+        "namespace",
+        "new",
+        "null",
+        "nullptr",
+        "package",
+        "private",
+        "protected",
+        "public",
+        "return",
+        "short",
+        "static",
+        "struct",
+        "switch",
+        "this",
+        "throw",
+        "true",
+        "try",
+        "typedef",
+        "union",
+        "unsigned",
+        "void",
+        "while",
+    ],
+    line_comment: Some("//"),
+    block_comment: Some(("/*", "*/")),
+    quotes: &['"', '\''],
+};
+
+static SHELL: LangSpec = LangSpec {
+    name: "Shell",
+    tag: "sh",
+    keywords: &[
+        "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+        "in", "local", "return", "then", "while",
+    ],
+    line_comment: Some("#"),
+    block_comment: None,
+    quotes: &['"', '\''],
+};
+
+static CONFIG: LangSpec = LangSpec {
+    name: "Config",
+    tag: "toml",
+    keywords: &["true", "false", "null"],
+    line_comment: Some("#"),
+    block_comment: None,
+    quotes: &['"', '\''],
+};
+
+static JSON: LangSpec = LangSpec {
+    name: "JSON",
+    tag: "json",
+    keywords: &["true", "false", "null"],
+    line_comment: None,
+    block_comment: None,
+    quotes: &['"'],
+};
+
+/// Pick a language from a file path, if it is one we know
+pub fn lang_for_path(path: &str) -> Option<&'static LangSpec> {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    if matches!(name, "Dockerfile" | "Makefile") {
+        return Some(&SHELL);
+    }
+    let ext = name.rsplit_once('.')?.1.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "rs" => &RUST,
+        "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" => &JAVASCRIPT,
+        "py" => &PYTHON,
+        "go" => &GO,
+        "c" | "h" | "cc" | "cpp" | "hpp" | "java" | "kt" | "cs" | "swift" => &C_FAMILY,
+        "sh" | "bash" | "zsh" => &SHELL,
+        "toml" | "yaml" | "yml" | "ini" | "conf" => &CONFIG,
+        "json" => &JSON,
+        _ => return None,
+    })
+}
+
+/// Pick a language from a code block's tag, e.g. "rust" or "ts"
+pub fn lang_for_tag(tag: &str) -> Option<&'static LangSpec> {
+    let tag = tag.to_ascii_lowercase();
+    match tag.as_str() {
+        "rust" => Some(&RUST),
+        "javascript" | "typescript" => Some(&JAVASCRIPT),
+        "python" | "python3" => Some(&PYTHON),
+        "golang" => Some(&GO),
+        "c++" | "csharp" | "kotlin" => Some(&C_FAMILY),
+        "shell" | "console" | "shellsession" => Some(&SHELL),
+        "jsonc" | "json5" => Some(&JSON),
+        _ => lang_for_path(&format!("x.{}", tag)),
+    }
+}
+
+/// Snippets that suggest a language, and how strongly
+const SIGNALS: &[(&LangSpec, &[(&str, u32)])] = &[
+    (
+        &RUST,
+        &[
+            ("fn ", 2),
+            ("let mut ", 3),
+            ("impl ", 2),
+            ("pub fn ", 3),
+            ("use std::", 3),
+            ("#[derive(", 3),
+            ("&self", 2),
+            ("println!(", 3),
+            ("Option<", 1),
+            ("::new(", 1),
+            ("-> ", 1),
+        ],
+    ),
+    (
+        &PYTHON,
+        &[
+            ("def ", 2),
+            ("elif ", 3),
+            ("self.", 1),
+            ("__init__", 3),
+            ("print(", 1),
+            ("import ", 1),
+            ("None", 1),
+            ("    return ", 1),
+        ],
+    ),
+    (
+        &JAVASCRIPT,
+        &[
+            ("const ", 1),
+            ("function ", 2),
+            ("=> ", 2),
+            ("console.log(", 3),
+            ("require(", 3),
+            ("export ", 2),
+            ("===", 3),
+            ("interface ", 1),
+        ],
+    ),
+    (
+        &GO,
+        &[
+            ("func ", 3),
+            ("package ", 2),
+            (" := ", 2),
+            ("fmt.", 3),
+            ("err != nil", 3),
+        ],
+    ),
+    (
+        &C_FAMILY,
+        &[
+            ("#include", 3),
+            ("int main(", 3),
+            ("std::", 2),
+            ("printf(", 2),
+            ("public class ", 3),
+            ("System.out.", 3),
+            ("void ", 1),
+        ],
+    ),
+];
+
+/// First words of lines that make a snippet shell commands
+const SHELL_COMMANDS: &[&str] = &[
+    "$",
+    "cd",
+    "ls",
+    "git",
+    "cargo",
+    "npm",
+    "npx",
+    "yarn",
+    "pnpm",
+    "pip",
+    "python",
+    "make",
+    "docker",
+    "kubectl",
+    "curl",
+    "echo",
+    "export",
+    "sudo",
+    "mkdir",
+    "rm",
+    "cp",
+    "mv",
+    "cat",
+    "grep",
+    "brew",
+    "apt",
+    "go",
+    "trunk",
+    "claude-portal",
+];
+
+/// Guess a snippet's language from its contents
+pub fn detect_lang(code: &str) -> Option<&'static LangSpec> {
+    let trimmed = code.trim();
+    if let Some(shebang) = trimmed.lines().next().and_then(|l| l.strip_prefix("#!")) {
+        return if shebang.contains("python") {
+            Some(&PYTHON)
+        } else if shebang.contains("node") {
+            Some(&JAVASCRIPT)
+        } else {
+            Some(&SHELL)
+        };
+    }
+    if (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(trimmed).is_ok()
+    {
+        return Some(&JSON);
+    }
+
+    let lines: Vec<&str> = trimmed.lines().filter(|l| !l.trim().is_empty()).collect();
+    if lines.is_empty() {
+        return None;
+    }
+    let commands = lines
+        .iter()
+        .filter(|line| {
+            let first = line.split_whitespace().next().unwrap_or_default();
+            SHELL_COMMANDS.contains(&first)
+        })
+        .count();
+    if commands * 2 > lines.len() {
+        return Some(&SHELL);
+    }
+
+    let best = SIGNALS
+        .iter()
+        .map(|(spec, signals)| {
+            let score: u32 = signals
+                .iter()
+                .filter(|(snippet, _)| code.contains(snippet))
+                .map(|(_, weight)| weight)
+                .sum();
+            (*spec, score)
+        })
+        .max_by_key(|(_, score)| *score)
+        .filter(|(_, score)| *score >= 3);
+    if let Some((spec, _)) = best {
+        return Some(spec);
+    }
+
+    // `[section]` headers or mostly `key = value` / `key: value` lines
+    let settings = lines
+        .iter()
+        .filter(|line| {
+            let line = line.trim();
+            (line.starts_with('[') && line.ends_with(']'))
+                || line
+                    .split_once([':', '='])
+                    .is_some_and(|(key, _)| !key.trim().is_empty() && !key.trim().contains(' '))
+        })
+        .count();
+    (settings * 4 >= lines.len() * 3).then_some(&CONFIG)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Plain,
+    Keyword,
+    String,
+    Comment,
+    Number,
+}
+
+impl TokenKind {
+    fn class(self) -> &'static str {
+        match self {
+            TokenKind::Plain => "",
+            TokenKind::Keyword => "tok-keyword",
+            TokenKind::String => "tok-string",
+            TokenKind::Comment => "tok-comment",
+            TokenKind::Number => "tok-number",
+        }
+    }
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Byte length of the prefix of `text` whose chars satisfy `keep`
+fn span_while(text: &str, keep: impl Fn(char) -> bool) -> usize {
+    text.find(|c| !keep(c)).unwrap_or(text.len())
+}
+
+/// Length of a quoted string starting at `text[0]`, through the closing
+/// quote or the end of the line
+fn string_len(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            return i + c.len_utf8();
+        }
+    }
+    text.len()
+}
+
+/// Split one line into tokens. `in_block_comment` carries an unterminated
+/// block comment over to the next line.
+pub fn tokenize_line<'a>(
+    line: &'a str,
+    spec: &LangSpec,
+    in_block_comment: &mut bool,
+) -> Vec<(TokenKind, &'a str)> {
+    // (kind, end offset); runs of the same kind are merged
+    let mut spans: Vec<(TokenKind, usize)> = Vec::new();
+    let mut push = |kind: TokenKind, end: usize| match spans.last_mut() {
+        Some((last_kind, last_end)) if *last_kind == kind => *last_end = end,
+        _ => spans.push((kind, end)),
+    };
+
+    let mut pos = 0;
+    while pos < line.len() {
+        let rest = &line[pos..];
+
+        if *in_block_comment {
+            let (_, end) = spec.block_comment.unwrap_or(("", ""));
+            let len = match rest.find(end) {
+                Some(i) => {
+                    *in_block_comment = false;
+                    i + end.len()
+                }
+                None => rest.len(),
+            };
+            pos += len;
+            push(TokenKind::Comment, pos);
+            continue;
+        }
+
+        if spec
+            .line_comment
+            .is_some_and(|start| rest.starts_with(start))
+        {
+            push(TokenKind::Comment, line.len());
+            break;
+        }
+        if let Some((start, _)) = spec.block_comment {
+            if rest.starts_with(start) {
+                *in_block_comment = true;
+                pos += start.len();
+                push(TokenKind::Comment, pos);
+                continue;
+            }
+        }
+
+        let c = rest.chars().next().unwrap_or_default();
+        let after_ident = line[..pos].chars().next_back().is_some_and(is_ident);
+        let (kind, len) = if spec.quotes.contains(&c) {
+            (TokenKind::String, string_len(rest, c))
+        } else if c.is_ascii_digit() && !after_ident {
+            (
+                TokenKind::Number,
+                span_while(rest, |c| c.is_ascii_alphanumeric() || c == '.' || c == '_'),
+            )
+        } else if is_ident(c) {
+            let len = span_while(rest, is_ident);
+            if !after_ident && spec.keywords.contains(&&rest[..len]) {
+                (TokenKind::Keyword, len)
+            } else {
+                (TokenKind::Plain, len)
+            }
+        } else {
+            (TokenKind::Plain, c.len_utf8())
+        };
+        pos += len;
+        push(kind, pos);
+    }
+
+    let mut start = 0;
+    spans
+        .into_iter()
+        .map(|(kind, end)| {
+            let token = (kind, &line[start..end]);
+            start = end;
+            token
+        })
+        .collect()
+}
+
+/// Highlighted HTML for each line
+pub fn highlight_lines<'a>(
+    lines: impl IntoIterator<Item = &'a str>,
+    spec: Option<&LangSpec>,
+) -> Vec<Html> {
+    let Some(spec) = spec else {
+        return lines.into_iter().map(|line| html! { { line } }).collect();
+    };
+    let mut in_block_comment = false;
+    lines
+        .into_iter()
+        .map(|line| {
+            tokenize_line(line, spec, &mut in_block_comment)
+                .into_iter()
+                .map(|(kind, text)| match kind {
+                    TokenKind::Plain => html! { { text } },
+                    _ => html! { <span class={kind.class()}>{ text }</span> },
+                })
+                .collect::<Html>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(line: &str, spec: &LangSpec) -> Vec<(TokenKind, String)> {
+        tokenize_line(line, spec, &mut false)
+            .into_iter()
+            .map(|(kind, text)| (kind, text.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_lang_for_path() {
+        assert!(std::ptr::eq(lang_for_path("src/main.rs").unwrap(), &RUST));
+        assert!(std::ptr::eq(
+            lang_for_path("app/App.TSX").unwrap(),
+            &JAVASCRIPT
+        ));
+        assert!(std::ptr::eq(
+            lang_for_path("/w/Dockerfile").unwrap(),
+            &SHELL
+        ));
+        assert!(lang_for_path("notes.txt").is_none());
+        assert!(lang_for_path("LICENSE").is_none());
+    }
+
+    fn detected(code: &str) -> Option<&'static str> {
+        detect_lang(code).map(|spec| spec.name)
+    }
+
+    #[test]
+    fn test_lang_for_tag() {
+        assert!(std::ptr::eq(lang_for_tag("Rust").unwrap(), &RUST));
+        assert!(std::ptr::eq(lang_for_tag("ts").unwrap(), &JAVASCRIPT));
+        assert!(std::ptr::eq(lang_for_tag("console").unwrap(), &SHELL));
+        assert!(lang_for_tag("text").is_none());
+        for spec in [
+            &RUST,
+            &JAVASCRIPT,
+            &PYTHON,
+            &GO,
+            &C_FAMILY,
+            &SHELL,
+            &CONFIG,
+            &JSON,
+        ] {
+            assert!(std::ptr::eq(lang_for_tag(spec.tag).unwrap(), spec));
+        }
+    }
+
+    #[test]
+    fn test_detect_lang() {
+        assert_eq!(
+            detected("fn main() {\n    let mut x = 1;\n    println!(\"{}\", x);\n}"),
+            Some("Rust")
+        );
+        assert_eq!(
+            detected("def greet(name):\n    if not name:\n        return None\n    elif name:\n        print(name)"),
+            Some("Python")
+        );
+        assert_eq!(
+            detected("if err != nil {\n    return fmt.Errorf(\"x\")\n}"),
+            Some("Go")
+        );
+        assert_eq!(
+            detected("const x = items.map(i => i.id);\nconsole.log(x);"),
+            Some("JavaScript")
+        );
+        assert_eq!(detected("cd proxy\ncargo test\ngit status"), Some("Shell"));
+        assert_eq!(detected("#!/usr/bin/env python3\nx = 1"), Some("Python"));
+        assert_eq!(detected("{\"a\": [1, 2]}"), Some("JSON"));
+        assert_eq!(
+            detected("[reconnect]\ninitial_secs = 1\nmax_secs = 60"),
+            Some("Config")
+        );
+        assert_eq!(detected("The quick brown fox.\nJumps over it."), None);
+        assert_eq!(detected(""), None);
+    }
+
+    #[test]
+    fn test_tokenize_rust_line() {
+        use TokenKind::*;
+        assert_eq!(
+            kinds(r#"let fn_name = "a\"b"; // 42"#, &RUST),
+            vec![
+                (Keyword, "let".to_string()),
+                (Plain, " fn_name = ".to_string()),
+                (String, r#""a\"b""#.to_string()),
+                (Plain, "; ".to_string()),
+                (Comment, "// 42".to_string()),
+            ]
+        );
+        assert_eq!(
+            kinds("x1 + 0x1F", &RUST),
+            vec![(Plain, "x1 + ".to_string()), (Number, "0x1F".to_string()),]
+        );
+    }
+
+    #[test]
+    fn test_block_comment_spans_lines() {
+        use TokenKind::*;
+        let mut in_block = false;
+        let first = tokenize_line("int x; /* start", &C_FAMILY, &mut in_block);
+        assert_eq!(first.last(), Some(&(Comment, "/* start")));
+        assert!(in_block);
+
+        let second = tokenize_line("end */ return", &C_FAMILY, &mut in_block);
+        assert_eq!(
+            second,
+            vec![(Comment, "end */"), (Plain, " "), (Keyword, "return")]
+        );
+        assert!(!in_block);
+    }
+}
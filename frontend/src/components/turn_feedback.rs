@@ -0,0 +1,160 @@
+//! Turn Feedback Component
+//!
+//! Thumbs-up/down and an optional comment on a completed turn (result message).
+
+use gloo_net::http::Request;
+use shared::{FeedbackRating, TurnFeedbackInfo, TurnFeedbackRequest};
+use uuid::Uuid;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::utils;
+
+#[derive(Properties, PartialEq)]
+pub struct TurnFeedbackProps {
+    pub session_id: Uuid,
+    /// The result message being rated
+    pub message_id: Uuid,
+    /// The current user's existing feedback, if any
+    pub feedback: Option<TurnFeedbackInfo>,
+    /// Called with the new feedback (None when removed)
+    pub on_change: Callback<(Uuid, Option<TurnFeedbackInfo>)>,
+}
+
+fn submit(
+    session_id: Uuid,
+    message_id: Uuid,
+    request: Option<TurnFeedbackRequest>,
+    on_change: Callback<(Uuid, Option<TurnFeedbackInfo>)>,
+) {
+    spawn_local(async move {
+        let url = utils::api_url(&format!(
+            "/api/sessions/{}/messages/{}/feedback",
+            session_id, message_id
+        ));
+        let result = match request {
+            Some(body) => match Request::put(&url).json(&body) {
+                Ok(req) => req.send().await,
+                Err(e) => {
+                    tracing::error!("Failed to build feedback request: {:?}", e);
+                    return;
+                }
+            },
+            None => Request::delete(&url).send().await,
+        };
+        match result {
+            Ok(response) if response.ok() => {
+                let info = response.json::<TurnFeedbackInfo>().await.ok();
+                on_change.emit((message_id, info));
+            }
+            Ok(response) => tracing::error!("Failed to save feedback: {}", response.status()),
+            Err(e) => tracing::error!("Failed to save feedback: {:?}", e),
+        }
+    });
+}
+
+#[function_component(TurnFeedback)]
+pub fn turn_feedback(props: &TurnFeedbackProps) -> Html {
+    let comment_open = use_state(|| false);
+    let comment_ref = use_node_ref();
+
+    let current_rating = props.feedback.as_ref().map(|f| f.rating);
+    let current_comment = props.feedback.as_ref().and_then(|f| f.comment.clone());
+
+    let on_rate = {
+        let session_id = props.session_id;
+        let message_id = props.message_id;
+        let on_change = props.on_change.clone();
+        let current_comment = current_comment.clone();
+        let comment_open = comment_open.clone();
+        Callback::from(move |rating: FeedbackRating| {
+            // Clicking the active rating again clears the feedback
+            let request = (current_rating != Some(rating)).then(|| TurnFeedbackRequest {
+                rating,
+                comment: current_comment.clone(),
+            });
+            if request.is_none() {
+                comment_open.set(false);
+            }
+            submit(session_id, message_id, request, on_change.clone());
+        })
+    };
+
+    let on_toggle_comment = {
+        let comment_open = comment_open.clone();
+        Callback::from(move |_: MouseEvent| comment_open.set(!*comment_open))
+    };
+
+    let on_save_comment = {
+        let session_id = props.session_id;
+        let message_id = props.message_id;
+        let on_change = props.on_change.clone();
+        let comment_ref = comment_ref.clone();
+        let comment_open = comment_open.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let Some(rating) = current_rating else {
+                return;
+            };
+            let comment = comment_ref
+                .cast::<HtmlInputElement>()
+                .map(|input| input.value())
+                .unwrap_or_default();
+            comment_open.set(false);
+            submit(
+                session_id,
+                message_id,
+                Some(TurnFeedbackRequest {
+                    rating,
+                    comment: Some(comment),
+                }),
+                on_change.clone(),
+            );
+        })
+    };
+
+    let rate_button = |rating: FeedbackRating, label: &'static str, title: &'static str| {
+        let on_rate = on_rate.clone();
+        html! {
+            <button
+                type="button"
+                class={classes!("feedback-button", (current_rating == Some(rating)).then_some("selected"))}
+                {title}
+                onclick={Callback::from(move |_: MouseEvent| on_rate.emit(rating))}
+            >
+                { label }
+            </button>
+        }
+    };
+
+    html! {
+        <div class={classes!("turn-feedback", current_rating.is_some().then_some("rated"))}>
+            { rate_button(FeedbackRating::Up, "👍", "This turn went well") }
+            { rate_button(FeedbackRating::Down, "👎", "This turn went badly") }
+            if current_rating.is_some() {
+                <button
+                    type="button"
+                    class="feedback-comment-toggle"
+                    title={current_comment.clone().unwrap_or_else(|| "Add a comment".to_string())}
+                    onclick={on_toggle_comment}
+                >
+                    { if current_comment.is_some() { "Edit comment" } else { "Add comment" } }
+                </button>
+            }
+            if *comment_open {
+                <form class="feedback-comment-form" onsubmit={on_save_comment}>
+                    <input
+                        ref={comment_ref}
+                        type="text"
+                        class="feedback-comment-input"
+                        placeholder="What worked or didn't?"
+                        value={current_comment.unwrap_or_default()}
+                        maxlength="2000"
+                    />
+                    <button type="submit" class="feedback-comment-save">{ "Save" }</button>
+                </form>
+            }
+        </div>
+    }
+}
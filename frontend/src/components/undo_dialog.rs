@@ -0,0 +1,70 @@
+//! Undo Turn Dialog Component
+//!
+//! Confirms undoing Claude's last turn, listing the files it changed. Undo is
+//! an instruction to Claude rather than a restore, so the dialog says what it
+//! will be asked to revert.
+
+use shared::LastTurn;
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct UndoDialogProps {
+    pub turn: LastTurn,
+    pub on_confirm: Callback<()>,
+    pub on_close: Callback<()>,
+}
+
+#[function_component(UndoDialog)]
+pub fn undo_dialog(props: &UndoDialogProps) -> Html {
+    let turn = &props.turn;
+    let on_close = props.on_close.clone();
+    let on_overlay_click = {
+        let on_close = on_close.clone();
+        Callback::from(move |_: MouseEvent| on_close.emit(()))
+    };
+    let on_cancel = {
+        let on_close = on_close.clone();
+        Callback::from(move |_: MouseEvent| on_close.emit(()))
+    };
+    let on_confirm = {
+        let on_confirm = props.on_confirm.clone();
+        Callback::from(move |_: MouseEvent| on_confirm.emit(()))
+    };
+
+    html! {
+        <div class="share-dialog-overlay" onclick={on_overlay_click}>
+            <div class="share-dialog undo-dialog" onclick={|e: MouseEvent| e.stop_propagation()}>
+                <div class="share-dialog-header">
+                    <h2>{ "Undo Last Turn" }</h2>
+                    <button class="share-dialog-close" onclick={move |_| on_close.emit(())}>
+                        { "×" }
+                    </button>
+                </div>
+                <div class="undo-dialog-body">
+                    if turn.in_progress {
+                        <p>{ "Claude is still working. Its turn will be stopped first." }</p>
+                    }
+                    if !turn.files.is_empty() {
+                        <p>{ "Claude will be asked to restore these files:" }</p>
+                        <ul class="undo-dialog-files">
+                            { turn.files.iter().map(|file| html! { <li>{ file }</li> }).collect::<Html>() }
+                        </ul>
+                    }
+                    if turn.ran_commands {
+                        <p class="undo-dialog-note">
+                            { "It also ran shell commands. Claude will try to reverse their effects, but check anything they changed." }
+                        </p>
+                    }
+                    <div class="undo-dialog-actions">
+                        <button type="button" class="control-button secondary" onclick={on_cancel}>
+                            { "Cancel" }
+                        </button>
+                        <button type="button" class="control-button" onclick={on_confirm}>
+                            { "Undo" }
+                        </button>
+                    </div>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -3,10 +3,13 @@
 //! Provides voice-to-text input using the Web Audio API and AudioWorklet.
 //! Audio is captured from the microphone, converted to PCM16 at 16kHz,
 //! and sent via a dedicated WebSocket to the backend for speech-to-text processing.
+//! Stretches of silence are held back rather than streamed, and recording can
+//! stop by itself after a configurable silence or be push-to-talk.
 
 use futures_util::{SinkExt, StreamExt};
 use gloo::utils::window;
 use gloo_net::websocket::{futures::WebSocket, Message};
+use serde::{Deserialize, Serialize};
 use shared::ProxyMessage;
 use std::cell::RefCell;
 use std::rc::Rc;
@@ -41,11 +44,84 @@ fn is_audio_worklet_supported() -> bool {
     false
 }
 
+/// How long the clipping warning stays up after the last clipped sample
+const CLIP_HOLD_MS: f64 = 1500.0;
+
+/// Recording this long without any sound suggests a muted or wrong microphone
+const NO_SIGNAL_MS: f64 = 3000.0;
+
+/// Volume level below which the microphone counts as silent
+const SIGNAL_LEVEL: f32 = 0.02;
+
+/// Storage key for voice settings in localStorage
+pub const VOICE_SETTINGS_STORAGE_KEY: &str = "claude-portal-voice-settings";
+
+/// Choices offered for the silence auto-stop, in seconds (0 = never)
+pub const SILENCE_TIMEOUT_CHOICES: &[u32] = &[0, 1, 2, 3, 5, 10];
+
+/// How recording starts and stops, kept per browser
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VoiceSettings {
+    /// Record only while the mic button (or Space) is held down
+    pub push_to_talk: bool,
+    /// Stop recording after this many seconds of silence following speech,
+    /// or never if 0. Not used with push-to-talk.
+    pub silence_timeout_secs: u32,
+}
+
+impl Default for VoiceSettings {
+    fn default() -> Self {
+        Self {
+            push_to_talk: false,
+            silence_timeout_secs: 2,
+        }
+    }
+}
+
+impl VoiceSettings {
+    /// Load voice settings from localStorage
+    pub fn load() -> Self {
+        web_sys::window()
+            .and_then(|w| w.local_storage().ok().flatten())
+            .and_then(|storage| storage.get_item(VOICE_SETTINGS_STORAGE_KEY).ok().flatten())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    /// Save voice settings to localStorage
+    pub fn save(&self) {
+        if let Some(storage) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) {
+            if let Ok(json) = serde_json::to_string(self) {
+                let _ = storage.set_item(VOICE_SETTINGS_STORAGE_KEY, &json);
+            }
+        }
+    }
+
+    /// Seconds of silence before auto-stop as the audio processor takes it
+    fn processor_silence_timeout(&self) -> u32 {
+        if self.push_to_talk {
+            0
+        } else {
+            self.silence_timeout_secs
+        }
+    }
+}
+
 /// Props for the VoiceInput component
 #[derive(Properties, PartialEq)]
 pub struct VoiceInputProps {
     /// Session ID to associate voice input with
     pub session_id: Uuid,
+    /// Language to recognize, or "auto"
+    #[prop_or(AttrValue::Static(shared::voice::DEFAULT_LANGUAGE))]
+    pub language_code: AttrValue,
+    /// Push-to-talk and silence auto-stop settings
+    #[prop_or_default]
+    pub settings: VoiceSettings,
+    /// In push-to-talk mode, whether the talk key is held; recording follows it
+    #[prop_or(false)]
+    pub held: bool,
     /// Callback when recording state changes
     pub on_recording_change: Callback<bool>,
     /// Callback when final transcription is received
@@ -68,8 +144,9 @@ pub enum VoiceInputMsg {
     StartRecording,
     StopRecording,
     RecordingStarted(VoiceSession),
-    WebSocketMessage(ProxyMessage),
-    VolumeLevel(f32),
+    WebSocketMessage(Box<ProxyMessage>),
+    /// Smoothed volume (0-1), and whether the input clipped since the last one
+    VolumeLevel(f32, bool),
     SilenceDetected,
     Error(String),
 }
@@ -86,7 +163,13 @@ impl Drop for VoiceRecordingState {
     fn drop(&mut self) {
         // Stop the worklet
         if let Ok(port) = self.worklet_node.port() {
-            let _ = port.post_message(&JsValue::from_str(r#"{"command":"stop"}"#));
+            let command = js_sys::Object::new();
+            let _ = js_sys::Reflect::set(
+                &command,
+                &JsValue::from_str("command"),
+                &JsValue::from_str("stop"),
+            );
+            let _ = port.post_message(&command);
         }
 
         // Disconnect nodes
@@ -111,9 +194,19 @@ pub struct VoiceSession {
 /// Voice input component with microphone button
 pub struct VoiceInput {
     is_recording: bool,
+    /// Waiting on the microphone and voice WebSocket
+    starting: bool,
+    /// Released (push-to-talk) before recording had started
+    stop_when_started: bool,
     voice_session: Option<VoiceSession>,
     browser_supported: bool,
     volume_level: f32,
+    /// Loudest volume level since recording started
+    loudest_level: f32,
+    /// When recording started, in ms since the epoch
+    recording_since: f64,
+    /// When the input last clipped, in ms since the epoch
+    clipped_at: Option<f64>,
 }
 
 impl Component for VoiceInput {
@@ -123,16 +216,21 @@ impl Component for VoiceInput {
     fn create(_ctx: &Context<Self>) -> Self {
         Self {
             is_recording: false,
+            starting: false,
+            stop_when_started: false,
             voice_session: None,
             browser_supported: is_audio_worklet_supported(),
             volume_level: 0.0,
+            loudest_level: 0.0,
+            recording_since: 0.0,
+            clipped_at: None,
         }
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             VoiceInputMsg::StartRecording => {
-                if self.is_recording {
+                if self.is_recording || self.starting {
                     return false;
                 }
 
@@ -144,10 +242,21 @@ impl Component for VoiceInput {
 
                 let link = ctx.link().clone();
                 let session_id = ctx.props().session_id;
+                let language_code = ctx.props().language_code.to_string();
+                let silence_timeout_secs = ctx.props().settings.processor_silence_timeout();
                 let on_error = ctx.props().on_error.clone();
+                self.starting = true;
+                self.stop_when_started = false;
 
                 wasm_bindgen_futures::spawn_local(async move {
-                    match start_voice_session(session_id, link.clone()).await {
+                    match start_voice_session(
+                        session_id,
+                        language_code,
+                        silence_timeout_secs,
+                        link.clone(),
+                    )
+                    .await
+                    {
                         Ok(session) => {
                             link.send_message(VoiceInputMsg::RecordingStarted(session));
                         }
@@ -161,6 +270,10 @@ impl Component for VoiceInput {
                 false
             }
             VoiceInputMsg::StopRecording => {
+                if self.starting {
+                    self.stop_when_started = true;
+                    return false;
+                }
                 if !self.is_recording {
                     return false;
                 }
@@ -178,13 +291,22 @@ impl Component for VoiceInput {
                 true
             }
             VoiceInputMsg::RecordingStarted(session) => {
+                self.starting = false;
+                if self.stop_when_started {
+                    // Let go before the microphone was ready
+                    session.audio_sender.borrow_mut().take();
+                    return false;
+                }
                 self.voice_session = Some(session);
                 self.is_recording = true;
+                self.loudest_level = 0.0;
+                self.recording_since = js_sys::Date::now();
+                self.clipped_at = None;
                 ctx.props().on_recording_change.emit(true);
                 true
             }
             VoiceInputMsg::WebSocketMessage(proxy_msg) => {
-                match proxy_msg {
+                match *proxy_msg {
                     ProxyMessage::Transcription {
                         transcript,
                         is_final,
@@ -201,25 +323,33 @@ impl Component for VoiceInput {
                     }
                     ProxyMessage::VoiceEnded { .. } => {
                         // Speech recognition detected end of speech - auto-stop recording
-                        log::info!("Voice session ended by server (end of speech detected)");
+                        tracing::info!("Voice session ended by server (end of speech detected)");
                         ctx.link().send_message(VoiceInputMsg::StopRecording);
                     }
                     _ => {}
                 }
                 false
             }
-            VoiceInputMsg::VolumeLevel(level) => {
+            VoiceInputMsg::VolumeLevel(level, clipping) => {
+                let now = js_sys::Date::now();
                 self.volume_level = level;
+                self.loudest_level = self.loudest_level.max(level);
+                if clipping {
+                    self.clipped_at = Some(now);
+                } else if self.clipped_at.is_some_and(|at| now - at > CLIP_HOLD_MS) {
+                    self.clipped_at = None;
+                }
                 true
             }
             VoiceInputMsg::SilenceDetected => {
                 // Client-side silence detection triggered - auto-stop recording
-                log::info!("Silence detected, auto-stopping voice recording");
+                tracing::info!("Silence detected, auto-stopping voice recording");
                 ctx.link().send_message(VoiceInputMsg::StopRecording);
                 false
             }
             VoiceInputMsg::Error(msg) => {
-                log::error!("Voice input error: {}", msg);
+                tracing::error!("Voice input error: {}", msg);
+                self.starting = false;
                 self.voice_session = None;
                 self.is_recording = false;
                 self.volume_level = 0.0;
@@ -229,39 +359,83 @@ impl Component for VoiceInput {
         }
     }
 
+    fn changed(&mut self, ctx: &Context<Self>, old_props: &Self::Properties) -> bool {
+        let props = ctx.props();
+        if props.settings.push_to_talk && props.held != old_props.held {
+            if props.held && !props.disabled {
+                ctx.link().send_message(VoiceInputMsg::StartRecording);
+            } else if !props.held {
+                ctx.link().send_message(VoiceInputMsg::StopRecording);
+            }
+        }
+        true
+    }
+
     fn view(&self, ctx: &Context<Self>) -> Html {
-        let onclick = if self.is_recording {
-            ctx.link().callback(|_| VoiceInputMsg::StopRecording)
+        let push_to_talk = ctx.props().settings.push_to_talk;
+        let link = ctx.link();
+        let (onclick, onpointerdown, onpointerup) = if push_to_talk {
+            let press = link.callback(|e: PointerEvent| {
+                e.prevent_default();
+                VoiceInputMsg::StartRecording
+            });
+            let release = link.callback(|_: PointerEvent| VoiceInputMsg::StopRecording);
+            (None, Some(press), Some(release))
+        } else if self.is_recording {
+            (
+                Some(link.callback(|_| VoiceInputMsg::StopRecording)),
+                None,
+                None,
+            )
         } else {
-            ctx.link().callback(|_| VoiceInputMsg::StartRecording)
+            (
+                Some(link.callback(|_| VoiceInputMsg::StartRecording)),
+                None,
+                None,
+            )
         };
 
+        let now = js_sys::Date::now();
+        let clipping = self.is_recording && self.clipped_at.is_some();
+        let no_signal = self.is_recording
+            && self.loudest_level < SIGNAL_LEVEL
+            && now - self.recording_since > NO_SIGNAL_MS;
+
         let disabled = ctx.props().disabled || !self.browser_supported;
         let button_class = classes!(
             "voice-button",
+            push_to_talk.then_some("push-to-talk"),
             self.is_recording.then_some("recording"),
+            clipping.then_some("clipping"),
+            no_signal.then_some("no-signal"),
             disabled.then_some("disabled"),
             (!self.browser_supported).then_some("unsupported"),
         );
 
         let title = if !self.browser_supported {
             "Voice input not supported in this browser"
+        } else if clipping {
+            "Too loud: your microphone is clipping. Move back or lower its input level."
+        } else if no_signal {
+            "No sound from the microphone. Check it's unmuted and the right input is selected."
+        } else if push_to_talk {
+            "Hold to talk (or hold Space in an empty message box)"
         } else if self.is_recording {
             "Stop recording (Ctrl+M)"
         } else {
             "Start voice input (Ctrl+M)"
         };
 
-        // Calculate volume level bar style when recording
-        // Uses a green gradient fill from bottom based on volume level
+        // Live level ring around the button while recording: its width
+        // follows the volume, green normally and red while clipping
         let volume_style = if self.is_recording {
-            // Volume level as percentage (0-100%)
-            let fill_percent = (self.volume_level * 100.0).min(100.0);
-            // Green gradient from bottom - matches success/code highlight color
-            format!(
-                "background: linear-gradient(to top, rgba(158, 206, 106, 0.6) {}%, rgba(247, 118, 142, 0.15) {}%)",
-                fill_percent, fill_percent
-            )
+            let width = 1.0 + (self.volume_level * 6.0).min(6.0);
+            let color = if clipping {
+                "rgba(247, 118, 142, 0.8)"
+            } else {
+                "rgba(158, 206, 106, 0.6)"
+            };
+            format!("box-shadow: 0 0 0 {:.1}px {}", width, color)
         } else {
             String::new()
         };
@@ -274,11 +448,18 @@ impl Component for VoiceInput {
                 ref={button_ref}
                 class={button_class}
                 onclick={onclick}
+                onpointerdown={onpointerdown}
+                onpointerup={onpointerup.clone()}
+                onpointerleave={onpointerup.clone()}
+                onpointercancel={onpointerup}
                 disabled={disabled}
                 title={title}
                 type="button"
                 style={volume_style}
             >
+                if clipping {
+                    <span class="voice-clip-badge">{ "!" }</span>
+                }
                 if self.is_recording {
                     <span class="voice-icon recording-icon">{ "\u{1F534}" }</span> // Red circle
                 } else if !self.browser_supported {
@@ -310,6 +491,8 @@ fn build_voice_ws_url(session_id: Uuid) -> String {
 /// Start a voice recording session with WebSocket connection
 async fn start_voice_session(
     session_id: Uuid,
+    language_code: String,
+    silence_timeout_secs: u32,
     link: yew::html::Scope<VoiceInput>,
 ) -> Result<VoiceSession, String> {
     // Connect to voice WebSocket
@@ -321,7 +504,7 @@ async fn start_voice_session(
     // Send StartVoice message
     let start_msg = ProxyMessage::StartVoice {
         session_id,
-        language_code: "en-US".to_string(),
+        language_code,
     };
     let start_json =
         serde_json::to_string(&start_msg).map_err(|_| "Failed to serialize StartVoice message")?;
@@ -341,11 +524,12 @@ async fn start_voice_session(
             match msg {
                 Ok(Message::Text(text)) => {
                     if let Ok(proxy_msg) = serde_json::from_str::<ProxyMessage>(&text) {
-                        link_for_ws.send_message(VoiceInputMsg::WebSocketMessage(proxy_msg));
+                        link_for_ws
+                            .send_message(VoiceInputMsg::WebSocketMessage(Box::new(proxy_msg)));
                     }
                 }
                 Err(e) => {
-                    log::error!("Voice WebSocket error: {:?}", e);
+                    tracing::error!("Voice WebSocket error: {:?}", e);
                     link_for_ws.send_message(VoiceInputMsg::Error(
                         "WebSocket connection lost".to_string(),
                     ));
@@ -372,7 +556,8 @@ async fn start_voice_session(
     });
 
     // Start audio recording
-    let recording_state = start_recording(audio_sender.clone(), link.clone()).await?;
+    let recording_state =
+        start_recording(audio_sender.clone(), silence_timeout_secs, link.clone()).await?;
 
     Ok(VoiceSession {
         _recording_state: recording_state,
@@ -380,9 +565,11 @@ async fn start_voice_session(
     })
 }
 
-/// Start recording audio from the microphone
+/// Start recording audio from the microphone, auto-stopping after
+/// `silence_timeout_secs` of silence following speech (never if 0)
 async fn start_recording(
     audio_sender: AudioSender,
+    silence_timeout_secs: u32,
     link: yew::html::Scope<VoiceInput>,
 ) -> Result<VoiceRecordingState, String> {
     // Get user media (microphone)
@@ -424,6 +611,13 @@ async fn start_recording(
 
     // Create worklet node
     let worklet_options = AudioWorkletNodeOptions::new();
+    let processor_options = js_sys::Object::new();
+    let _ = js_sys::Reflect::set(
+        &processor_options,
+        &JsValue::from_str("silenceTimeoutSecs"),
+        &JsValue::from(silence_timeout_secs),
+    );
+    worklet_options.set_processor_options(Some(&processor_options));
     let worklet_node =
         AudioWorkletNode::new_with_options(&audio_context, "pcm-processor", &worklet_options)
             .map_err(|_| "Failed to create worklet node")?;
@@ -443,10 +637,12 @@ async fn start_recording(
                     }
                 }
             }
-            // Check for volume level
+            // Check for volume level, and whether the input clipped
             if let Ok(volume_val) = js_sys::Reflect::get(&data, &JsValue::from_str("volumeLevel")) {
                 if let Some(volume) = volume_val.as_f64() {
-                    link.send_message(VoiceInputMsg::VolumeLevel(volume as f32));
+                    let clipping = js_sys::Reflect::get(&data, &JsValue::from_str("clipping"))
+                        .is_ok_and(|v| v.is_truthy());
+                    link.send_message(VoiceInputMsg::VolumeLevel(volume as f32, clipping));
                 }
             }
             // Check for silence detection signal
@@ -0,0 +1,82 @@
+//! Voice Language Picker Component
+//!
+//! Dropdown next to the mic button for the language voice input is
+//! recognized in. The choice is saved to the user's account.
+
+use gloo_net::http::Request;
+use shared::voice::{AUTO_LANGUAGE, VOICE_LANGUAGES};
+use shared::SetVoiceLanguageRequest;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlSelectElement;
+use yew::prelude::*;
+
+use crate::utils;
+
+#[derive(Properties, PartialEq)]
+pub struct VoiceLanguagePickerProps {
+    pub language: AttrValue,
+    /// Whether the server's speech provider can detect the language
+    #[prop_or(false)]
+    pub auto_available: bool,
+    /// Called with the saved language once the API accepts it
+    pub on_change: Callback<String>,
+    #[prop_or(false)]
+    pub disabled: bool,
+}
+
+#[function_component(VoiceLanguagePicker)]
+pub fn voice_language_picker(props: &VoiceLanguagePickerProps) -> Html {
+    let on_select = {
+        let on_change = props.on_change.clone();
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            let language_code = select.value();
+            let on_change = on_change.clone();
+            spawn_local(async move {
+                let url = utils::api_url("/api/auth/me/voice-language");
+                let body = SetVoiceLanguageRequest {
+                    language_code: language_code.clone(),
+                };
+                let request = match Request::put(&url).json(&body) {
+                    Ok(request) => request,
+                    Err(e) => {
+                        tracing::error!("Failed to build voice language request: {:?}", e);
+                        return;
+                    }
+                };
+                match request.send().await {
+                    Ok(response) if response.ok() => on_change.emit(language_code),
+                    Ok(response) => {
+                        tracing::error!("Failed to set voice language: {}", response.status())
+                    }
+                    Err(e) => tracing::error!("Failed to set voice language: {:?}", e),
+                }
+            });
+        })
+    };
+
+    let current = props.language.as_str();
+
+    html! {
+        <select
+            class="voice-language-picker"
+            title="Voice input language"
+            onchange={on_select}
+            disabled={props.disabled}
+        >
+            // Keep showing "auto" if it was chosen while the server could detect it
+            if props.auto_available || current == AUTO_LANGUAGE {
+                <option value={AUTO_LANGUAGE} selected={current == AUTO_LANGUAGE}>
+                    { "Auto-detect" }
+                </option>
+            }
+            {
+                VOICE_LANGUAGES.iter().map(|(code, name)| html! {
+                    <option value={*code} selected={current == *code}>
+                        { *name }
+                    </option>
+                }).collect::<Html>()
+            }
+        </select>
+    }
+}
@@ -0,0 +1,85 @@
+//! Voice Input Settings
+//!
+//! Push-to-talk and the silence auto-stop, saved in this browser's
+//! localStorage and picked up by sessions opened afterwards.
+
+use yew::prelude::*;
+
+use super::voice_input::{VoiceSettings, SILENCE_TIMEOUT_CHOICES};
+
+#[function_component(VoiceSettingsForm)]
+pub fn voice_settings_form() -> Html {
+    let settings = use_state(VoiceSettings::load);
+
+    let update = {
+        let settings = settings.clone();
+        move |new: VoiceSettings| {
+            new.save();
+            settings.set(new);
+        }
+    };
+
+    let on_push_to_talk = {
+        let settings = settings.clone();
+        let update = update.clone();
+        Callback::from(move |e: Event| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            update(VoiceSettings {
+                push_to_talk: input.checked(),
+                ..*settings
+            });
+        })
+    };
+
+    let on_timeout = {
+        let settings = settings.clone();
+        Callback::from(move |e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            if let Ok(secs) = select.value().parse() {
+                update(VoiceSettings {
+                    silence_timeout_secs: secs,
+                    ..*settings
+                });
+            }
+        })
+    };
+
+    html! {
+        <section class="voice-settings-section">
+            <div class="section-header">
+                <h2>{ "Voice Input" }</h2>
+                <p class="section-description">
+                    { "How the microphone button records on this device. Silence isn't sent for transcription either way." }
+                </p>
+            </div>
+            <label class="voice-setting">
+                <input
+                    type="checkbox"
+                    checked={settings.push_to_talk}
+                    onchange={on_push_to_talk}
+                />
+                { "Push-to-talk: record only while the mic button, or Space in an empty message box, is held" }
+            </label>
+            <label class="voice-setting">
+                { "Stop recording after " }
+                <select onchange={on_timeout} disabled={settings.push_to_talk}>
+                    { for SILENCE_TIMEOUT_CHOICES.iter().map(|&secs| html! {
+                        <option
+                            value={secs.to_string()}
+                            selected={secs == settings.silence_timeout_secs}
+                        >
+                            {
+                                match secs {
+                                    0 => "never".to_string(),
+                                    1 => "1 second".to_string(),
+                                    _ => format!("{} seconds", secs),
+                                }
+                            }
+                        </option>
+                    }) }
+                </select>
+                { " of silence" }
+            </label>
+        </section>
+    }
+}
@@ -1,14 +1,39 @@
 //! Hook for managing the client WebSocket connection with spend updates.
 
+use crate::components::{KillSwitchContext, MaintenanceContext};
 use crate::utils;
 use futures_util::StreamExt;
 use gloo_net::websocket::{futures::WebSocket, Message};
-use shared::{ProxyMessage, SessionCost};
+use shared::{ProxyMessage, SearchHit, SessionCost};
 use std::collections::HashMap;
 use uuid::Uuid;
 use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 
+/// A permission request in another session that was escalated to this user
+#[derive(Clone, PartialEq)]
+pub struct EscalationAlert {
+    pub session_id: Uuid,
+    pub session_name: String,
+    pub request_id: String,
+    pub tool_name: String,
+    pub waiting_minutes: i64,
+}
+
+/// New matches for one of the user's alerting saved searches
+#[derive(Clone, PartialEq)]
+pub struct SearchAlert {
+    pub saved_search_id: Uuid,
+    pub name: String,
+    /// Newest first
+    pub hits: Vec<SearchHit>,
+    /// More matched than `hits` holds
+    pub more: bool,
+}
+
+/// Hits kept per alert when several arrive before it's dismissed
+const SEARCH_ALERT_HITS: usize = 5;
+
 /// Return value from the use_client_websocket hook.
 pub struct UseClientWebSocket {
     /// Total user spend across all sessions
@@ -17,6 +42,80 @@ pub struct UseClientWebSocket {
     pub session_costs: HashMap<Uuid, f64>,
     /// Server shutdown reason (if server is shutting down)
     pub shutdown_reason: Option<String>,
+    /// Escalated permission requests not yet dismissed, oldest first
+    pub escalations: Vec<EscalationAlert>,
+    /// Dismiss an escalation alert by request ID
+    pub dismiss_escalation: Callback<String>,
+    /// Saved-search alerts not yet dismissed, one per saved search
+    pub search_alerts: Vec<SearchAlert>,
+    /// Dismiss a saved-search alert by saved search ID
+    pub dismiss_search_alert: Callback<Uuid>,
+}
+
+/// Escalation alerts, updated from the socket task and the banner
+#[derive(Default, PartialEq)]
+struct EscalationList {
+    alerts: Vec<EscalationAlert>,
+}
+
+enum EscalationAction {
+    Add(EscalationAlert),
+    Dismiss(String),
+}
+
+impl Reducible for EscalationList {
+    type Action = EscalationAction;
+
+    fn reduce(self: std::rc::Rc<Self>, action: Self::Action) -> std::rc::Rc<Self> {
+        let mut alerts = self.alerts.clone();
+        match action {
+            EscalationAction::Add(alert) => {
+                alerts.retain(|a| a.request_id != alert.request_id);
+                alerts.push(alert);
+            }
+            EscalationAction::Dismiss(request_id) => alerts.retain(|a| a.request_id != request_id),
+        }
+        std::rc::Rc::new(Self { alerts })
+    }
+}
+
+/// Saved-search alerts, updated from the socket task and the banner
+#[derive(Default, PartialEq)]
+struct SearchAlertList {
+    alerts: Vec<SearchAlert>,
+}
+
+enum SearchAlertAction {
+    Add(SearchAlert),
+    Dismiss(Uuid),
+}
+
+impl Reducible for SearchAlertList {
+    type Action = SearchAlertAction;
+
+    fn reduce(self: std::rc::Rc<Self>, action: Self::Action) -> std::rc::Rc<Self> {
+        let mut alerts = self.alerts.clone();
+        match action {
+            SearchAlertAction::Add(mut alert) => {
+                // Fold a repeat alert into the one still showing
+                if let Some(pos) = alerts
+                    .iter()
+                    .position(|a| a.saved_search_id == alert.saved_search_id)
+                {
+                    let previous = alerts.remove(pos);
+                    alert.more |= previous.more;
+                    alert.hits.extend(previous.hits);
+                }
+                if alert.hits.len() > SEARCH_ALERT_HITS {
+                    alert.hits.truncate(SEARCH_ALERT_HITS);
+                    alert.more = true;
+                }
+                alerts.push(alert);
+            }
+            SearchAlertAction::Dismiss(id) => alerts.retain(|a| a.saved_search_id != id),
+        }
+        std::rc::Rc::new(Self { alerts })
+    }
 }
 
 /// Calculate exponential backoff delay for reconnection attempts.
@@ -30,7 +129,8 @@ fn calculate_backoff(attempt: u32) -> u32 {
 
 /// Hook for managing the client WebSocket connection.
 ///
-/// Connects to /ws/client and receives spend updates and server shutdown notifications.
+/// Connects to /ws/client and receives spend updates, escalated permission
+/// requests, saved-search alerts, and server shutdown notifications.
 /// Automatically reconnects with exponential backoff on disconnection.
 ///
 /// # Returns
@@ -50,13 +150,21 @@ pub fn use_client_websocket() -> UseClientWebSocket {
     let total_spend = use_state(|| 0.0f64);
     let session_costs = use_state(HashMap::<Uuid, f64>::new);
     let shutdown_reason = use_state(|| None::<String>);
+    let escalations = use_reducer(EscalationList::default);
+    let search_alerts = use_reducer(SearchAlertList::default);
+    let maintenance = use_context::<MaintenanceContext>();
+    let kill_switch = use_context::<KillSwitchContext>();
 
     {
         let total_spend = total_spend.clone();
         let session_costs = session_costs.clone();
         let shutdown_reason = shutdown_reason.clone();
+        let escalations = escalations.dispatcher();
+        let search_alerts = search_alerts.dispatcher();
 
         use_effect_with((), move |_| {
+            let maintenance = maintenance.clone();
+            let kill_switch = kill_switch.clone();
             let total_spend = total_spend.clone();
             let session_costs = session_costs.clone();
             let shutdown_reason = shutdown_reason.clone();
@@ -99,19 +207,61 @@ pub fn use_client_websocket() -> UseClientWebSocket {
                                                     reason,
                                                     reconnect_delay_ms,
                                                 } => {
-                                                    log::info!(
+                                                    tracing::info!(
                                                         "Server shutdown: {} (reconnect in {}ms)",
                                                         reason,
                                                         reconnect_delay_ms
                                                     );
                                                     shutdown_reason.set(Some(reason));
                                                 }
+                                                ProxyMessage::MaintenanceBanner { notice } => {
+                                                    if let Some(maintenance) = &maintenance {
+                                                        maintenance.set(notice);
+                                                    }
+                                                }
+                                                ProxyMessage::KillSwitch { state } => {
+                                                    if let Some(kill_switch) = &kill_switch {
+                                                        kill_switch.set(state);
+                                                    }
+                                                }
+                                                ProxyMessage::PermissionEscalated {
+                                                    session_id,
+                                                    session_name,
+                                                    request_id,
+                                                    tool_name,
+                                                    waiting_minutes,
+                                                } => {
+                                                    escalations.dispatch(EscalationAction::Add(
+                                                        EscalationAlert {
+                                                            session_id,
+                                                            session_name,
+                                                            request_id,
+                                                            tool_name,
+                                                            waiting_minutes,
+                                                        },
+                                                    ));
+                                                }
+                                                ProxyMessage::SearchAlert {
+                                                    saved_search_id,
+                                                    name,
+                                                    hits,
+                                                    more,
+                                                } => {
+                                                    search_alerts.dispatch(SearchAlertAction::Add(
+                                                        SearchAlert {
+                                                            saved_search_id,
+                                                            name,
+                                                            hits,
+                                                            more,
+                                                        },
+                                                    ));
+                                                }
                                                 _ => {}
                                             }
                                         }
                                     }
                                     Err(e) => {
-                                        log::error!("Client WebSocket error: {:?}", e);
+                                        tracing::error!("Client WebSocket error: {:?}", e);
                                         break;
                                     }
                                     _ => {}
@@ -119,18 +269,18 @@ pub fn use_client_websocket() -> UseClientWebSocket {
                             }
                         }
                         Err(e) => {
-                            log::error!("Failed to connect client WebSocket: {:?}", e);
+                            tracing::error!("Failed to connect client WebSocket: {:?}", e);
                         }
                     }
 
                     // Reconnection with exponential backoff
                     if attempt >= MAX_ATTEMPTS {
-                        log::error!("Client WebSocket: max reconnection attempts reached");
+                        tracing::error!("Client WebSocket: max reconnection attempts reached");
                         break;
                     }
                     let delay_ms = calculate_backoff(attempt);
                     attempt += 1;
-                    log::info!(
+                    tracing::info!(
                         "Client WebSocket reconnecting in {}ms (attempt {})",
                         delay_ms,
                         attempt
@@ -146,5 +296,17 @@ pub fn use_client_websocket() -> UseClientWebSocket {
         total_spend: *total_spend,
         session_costs: (*session_costs).clone(),
         shutdown_reason: (*shutdown_reason).clone(),
+        escalations: escalations.alerts.clone(),
+        dismiss_escalation: {
+            let escalations = escalations.dispatcher();
+            Callback::from(move |request_id| {
+                escalations.dispatch(EscalationAction::Dismiss(request_id))
+            })
+        },
+        search_alerts: search_alerts.alerts.clone(),
+        dismiss_search_alert: {
+            let search_alerts = search_alerts.dispatcher();
+            Callback::from(move |id| search_alerts.dispatch(SearchAlertAction::Dismiss(id)))
+        },
     }
 }
@@ -1,8 +1,8 @@
 //! Hook for managing session list with automatic polling.
 
-use crate::utils;
+use crate::{offline, utils};
 use gloo_net::http::Request;
-use shared::SessionInfo;
+use shared::{SessionInfo, SessionQuota};
 use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 
@@ -10,8 +10,12 @@ use yew::prelude::*;
 pub struct UseSessions {
     /// Current list of sessions
     pub sessions: Vec<SessionInfo>,
+    /// Running sessions against the per-user limit
+    pub quota: SessionQuota,
     /// Whether sessions are currently being loaded (initial load only)
     pub loading: bool,
+    /// Whether the last fetch failed, so `sessions` may be stale or cached
+    pub offline: bool,
     /// Manually trigger a refresh
     pub refresh: Callback<()>,
     /// Update the session list directly (for local modifications)
@@ -21,7 +25,8 @@ pub struct UseSessions {
 /// Hook for fetching and polling session list.
 ///
 /// Automatically fetches sessions on mount and polls every 5 seconds.
-/// Handles 401 responses by redirecting to logout.
+/// Handles 401 responses by redirecting to logout. The list is cached for
+/// offline use, and shown from the cache when the first fetch fails.
 ///
 /// # Returns
 /// * `UseSessions` - The current sessions, loading state, and control callbacks
@@ -38,17 +43,26 @@ pub struct UseSessions {
 #[hook]
 pub fn use_sessions() -> UseSessions {
     let sessions = use_state(Vec::<SessionInfo>::new);
+    let quota = use_state(SessionQuota::default);
     let loading = use_state(|| true);
+    let offline = use_state(|| false);
     let refresh_trigger = use_state(|| 0u32);
+    // Last list written to the offline cache; `None` until a fetch succeeds
+    let cached = use_mut_ref(|| None::<String>);
 
     // Fetch sessions callback
     let fetch_sessions = {
         let sessions = sessions.clone();
+        let quota = quota.clone();
         let loading = loading.clone();
+        let offline = offline.clone();
 
         Callback::from(move |set_loading: bool| {
             let sessions = sessions.clone();
+            let quota = quota.clone();
             let loading = loading.clone();
+            let offline = offline.clone();
+            let cached = cached.clone();
 
             spawn_local(async move {
                 let api_endpoint = utils::api_url("/api/sessions");
@@ -56,6 +70,7 @@ pub fn use_sessions() -> UseSessions {
                     Ok(response) => {
                         if response.status() == 401 {
                             // Session invalid - redirect to logout
+                            offline::clear();
                             if let Some(window) = web_sys::window() {
                                 let _ = window.location().set_href("/api/auth/logout");
                             }
@@ -66,13 +81,35 @@ pub fn use_sessions() -> UseSessions {
                                 if let Ok(parsed) =
                                     serde_json::from_value::<Vec<SessionInfo>>(session_list.clone())
                                 {
+                                    let json = session_list.to_string();
+                                    if cached.borrow().as_deref() != Some(json.as_str()) {
+                                        offline::save_sessions(json.clone());
+                                        *cached.borrow_mut() = Some(json);
+                                    }
                                     sessions.set(parsed);
                                 }
                             }
+                            if let Some(parsed) = data.get("quota").and_then(|q| {
+                                serde_json::from_value::<SessionQuota>(q.clone()).ok()
+                            }) {
+                                if *quota != parsed {
+                                    quota.set(parsed);
+                                }
+                            }
                         }
+                        offline.set(false);
                     }
                     Err(e) => {
-                        log::error!("Failed to fetch sessions: {:?}", e);
+                        tracing::error!("Failed to fetch sessions: {:?}", e);
+                        if cached.borrow().is_none() {
+                            let restored = offline::load_sessions().await.and_then(|json| {
+                                serde_json::from_str::<Vec<SessionInfo>>(&json).ok()
+                            });
+                            if let Some(restored) = restored {
+                                sessions.set(restored);
+                            }
+                        }
+                        offline.set(true);
                     }
                 }
                 if set_loading {
@@ -132,7 +169,9 @@ pub fn use_sessions() -> UseSessions {
 
     UseSessions {
         sessions: (*sessions).clone(),
+        quota: *quota,
         loading: *loading,
+        offline: *offline,
         refresh,
         set_sessions,
     }
@@ -1,14 +1,18 @@
 mod components;
 mod hooks;
+mod mobile;
+mod offline;
 mod pages;
 pub mod utils;
 
 /// Application version from Cargo.toml (set at compile time)
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+use components::{MaintenanceProvider, RequireAuth};
 use pages::{
-    access_denied::AccessDeniedPage, admin::AdminPage, banned::BannedPage,
-    dashboard::DashboardPage, settings::SettingsPage, splash::SplashPage,
+    access_denied::AccessDeniedPage, activity::ActivityPage, admin::AdminPage, banned::BannedPage,
+    dashboard::DashboardPage, search::SearchPage, settings::SettingsPage, splash::SplashPage,
+    usage::UsagePage,
 };
 use yew::prelude::*;
 use yew_router::prelude::*;
@@ -21,6 +25,12 @@ pub enum Route {
     Dashboard,
     #[at("/settings")]
     Settings,
+    #[at("/usage")]
+    Usage,
+    #[at("/search")]
+    Search,
+    #[at("/activity")]
+    Activity,
     #[at("/admin")]
     Admin,
     #[at("/banned")]
@@ -32,9 +42,12 @@ pub enum Route {
 fn switch(routes: Route) -> Html {
     match routes {
         Route::Home => html! { <SplashPage /> },
-        Route::Dashboard => html! { <DashboardPage /> },
-        Route::Settings => html! { <SettingsPage /> },
-        Route::Admin => html! { <AdminPage /> },
+        Route::Dashboard => html! { <RequireAuth><DashboardPage /></RequireAuth> },
+        Route::Settings => html! { <RequireAuth><SettingsPage /></RequireAuth> },
+        Route::Usage => html! { <RequireAuth><UsagePage /></RequireAuth> },
+        Route::Search => html! { <RequireAuth><SearchPage /></RequireAuth> },
+        Route::Activity => html! { <RequireAuth><ActivityPage /></RequireAuth> },
+        Route::Admin => html! { <RequireAuth><AdminPage /></RequireAuth> },
         Route::Banned => html! { <BannedPage /> },
         Route::AccessDenied => html! { <AccessDeniedPage /> },
     }
@@ -44,13 +57,22 @@ fn switch(routes: Route) -> Html {
 fn app() -> Html {
     html! {
         <BrowserRouter>
-            <Switch<Route> render={switch} />
+            <MaintenanceProvider>
+                <Switch<Route> render={switch} />
+            </MaintenanceProvider>
         </BrowserRouter>
     }
 }
 
 #[wasm_bindgen::prelude::wasm_bindgen(start)]
 pub fn run_app() {
-    wasm_logger::init(wasm_logger::Config::default());
+    tracing_wasm::set_as_global_default_with_config(
+        tracing_wasm::WASMLayerConfigBuilder::new()
+            .set_max_level(tracing::Level::INFO)
+            .set_report_logs_in_timings(false)
+            .build(),
+    );
+    offline::register_service_worker();
+    mobile::track_visual_viewport();
     yew::Renderer::<App>::new().render();
 }
@@ -0,0 +1,71 @@
+//! Phone-sized layouts
+//!
+//! On-screen keyboards shrink the visual viewport without resizing the page
+//! on iOS, which would hide the input bar behind the keyboard. The visible
+//! height is mirrored into the `--app-height` CSS variable, which the session
+//! page sizes itself to.
+
+use wasm_bindgen::{closure::Closure, JsCast};
+
+/// Widest screen laid out for phones; matches the CSS breakpoint
+const COMPACT_QUERY: &str = "(max-width: 768px)";
+
+/// A horizontal swipe must travel this far (px)...
+const SWIPE_MIN_DX: i32 = 50;
+/// ...and drift at most this far vertically, so scrolling isn't a swipe
+const SWIPE_MAX_DY: i32 = 30;
+
+/// Keep `--app-height` equal to the visible viewport height
+pub fn track_visual_viewport() {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let Some(viewport) = window.visual_viewport() else {
+        return;
+    };
+
+    let update = {
+        let viewport = viewport.clone();
+        move || {
+            let root = web_sys::window()
+                .and_then(|w| w.document())
+                .and_then(|d| d.document_element())
+                .and_then(|e| e.dyn_into::<web_sys::HtmlElement>().ok());
+            if let Some(root) = root {
+                let _ = root
+                    .style()
+                    .set_property("--app-height", &format!("{}px", viewport.height()));
+            }
+        }
+    };
+    update();
+
+    let on_resize = Closure::<dyn Fn()>::new(update);
+    viewport.set_onresize(Some(on_resize.as_ref().unchecked_ref()));
+    // Lives as long as the page
+    on_resize.forget();
+}
+
+/// Whether the phone layout is in use
+pub fn is_compact() -> bool {
+    web_sys::window()
+        .and_then(|w| w.match_media(COMPACT_QUERY).ok().flatten())
+        .is_some_and(|query| query.matches())
+}
+
+/// Where a touch started, from a `touchstart`
+pub fn touch_origin(e: &web_sys::TouchEvent) -> Option<(i32, i32)> {
+    e.touches()
+        .get(0)
+        .map(|touch| (touch.client_x(), touch.client_y()))
+}
+
+/// Whether a `touchend` finishes a horizontal swipe begun at `origin`
+pub fn is_swipe(origin: (i32, i32), e: &web_sys::TouchEvent) -> bool {
+    let Some(touch) = e.changed_touches().get(0) else {
+        return false;
+    };
+    let dx = touch.client_x() - origin.0;
+    let dy = touch.client_y() - origin.1;
+    dx.abs() >= SWIPE_MIN_DX && dy.abs() <= SWIPE_MAX_DY
+}
@@ -0,0 +1,214 @@
+//! Offline support
+//!
+//! Registers the service worker, which caches the app shell, and keeps the
+//! session list and recently viewed transcripts in IndexedDB so they can be
+//! read without a connection. Everything here is best effort: when IndexedDB
+//! is unavailable (private browsing, old browsers) nothing is cached and the
+//! pages behave as before.
+
+use js_sys::{Object, Promise, Reflect};
+use uuid::Uuid;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{IdbDatabase, IdbObjectStoreParameters, IdbRequest, IdbTransactionMode};
+
+/// Caches the app shell and shows push notifications
+pub const SERVICE_WORKER_URL: &str = "/sw.js";
+
+const DB_NAME: &str = "claude-portal-offline";
+const DB_VERSION: u32 = 1;
+
+/// Transcripts by session ID, indexed by when they were cached
+const TRANSCRIPTS: &str = "transcripts";
+const CACHED_AT_INDEX: &str = "cached_at";
+
+/// The session list, under a single key
+const SESSIONS: &str = "sessions";
+const SESSIONS_KEY: &str = "list";
+
+/// Transcripts kept; the least recently cached are dropped beyond this
+const MAX_TRANSCRIPTS: u32 = 20;
+
+/// Register the service worker, so the app can be installed and opened offline
+pub fn register_service_worker() {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    if !Reflect::has(&window.navigator(), &JsValue::from_str("serviceWorker")).unwrap_or(false) {
+        return;
+    }
+    let promise = window
+        .navigator()
+        .service_worker()
+        .register(SERVICE_WORKER_URL);
+    spawn_local(async move {
+        if let Err(e) = JsFuture::from(promise).await {
+            tracing::warn!("Failed to register service worker: {:?}", e);
+        }
+    });
+}
+
+/// Resolve with a request's result once it succeeds
+async fn finished(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        let success = request.clone();
+        let on_success = Closure::once_into_js(move || {
+            let _ = resolve.call1(&JsValue::NULL, &success.result().unwrap_or_default());
+        });
+        let failure = request.clone();
+        let on_error = Closure::once_into_js(move || {
+            let error = failure.error().ok().flatten().map(JsValue::from);
+            let _ = reject.call1(&JsValue::NULL, &error.unwrap_or_default());
+        });
+        request.set_onsuccess(Some(on_success.unchecked_ref()));
+        request.set_onerror(Some(on_error.unchecked_ref()));
+    });
+    JsFuture::from(promise).await
+}
+
+async fn open() -> Result<IdbDatabase, JsValue> {
+    let factory = web_sys::window()
+        .ok_or("No window")?
+        .indexed_db()?
+        .ok_or("IndexedDB unavailable")?;
+    let request = factory.open_with_u32(DB_NAME, DB_VERSION)?;
+
+    let upgrading = request.clone();
+    let on_upgrade = Closure::once_into_js(move || {
+        let Ok(db) = upgrading
+            .result()
+            .and_then(|db| db.dyn_into::<IdbDatabase>())
+        else {
+            return;
+        };
+        let params = IdbObjectStoreParameters::new();
+        params.set_key_path(&JsValue::from_str("session_id"));
+        if let Ok(store) = db.create_object_store_with_optional_parameters(TRANSCRIPTS, &params) {
+            let _ = store.create_index_with_str(CACHED_AT_INDEX, CACHED_AT_INDEX);
+        }
+        let _ = db.create_object_store(SESSIONS);
+    });
+    request.set_onupgradeneeded(Some(on_upgrade.unchecked_ref()));
+
+    finished(&request).await?.dyn_into()
+}
+
+/// `{ cached_at, json }`, as stored
+fn record(json: &str) -> Result<Object, JsValue> {
+    let record = Object::new();
+    Reflect::set(
+        &record,
+        &CACHED_AT_INDEX.into(),
+        &js_sys::Date::now().into(),
+    )?;
+    Reflect::set(&record, &"json".into(), &json.into())?;
+    Ok(record)
+}
+
+/// The JSON and cache time of a stored record
+fn unpack(record: JsValue) -> Option<(String, f64)> {
+    if record.is_undefined() || record.is_null() {
+        return None;
+    }
+    let json = Reflect::get(&record, &"json".into()).ok()?.as_string()?;
+    let cached_at = Reflect::get(&record, &CACHED_AT_INDEX.into())
+        .ok()?
+        .as_f64()?;
+    Some((json, cached_at))
+}
+
+async fn put(store_name: &str, key: Option<&str>, value: JsValue) -> Result<(), JsValue> {
+    let db = open().await?;
+    let store = db
+        .transaction_with_str_and_mode(store_name, IdbTransactionMode::Readwrite)?
+        .object_store(store_name)?;
+    let request = match key {
+        Some(key) => store.put_with_key(&value, &key.into())?,
+        None => store.put(&value)?,
+    };
+    finished(&request).await?;
+    Ok(())
+}
+
+async fn get(store_name: &str, key: &str) -> Result<JsValue, JsValue> {
+    let db = open().await?;
+    let store = db
+        .transaction_with_str(store_name)?
+        .object_store(store_name)?;
+    finished(&store.get(&key.into())?).await
+}
+
+/// Drop the least recently cached transcripts beyond [`MAX_TRANSCRIPTS`]
+async fn prune_transcripts() -> Result<(), JsValue> {
+    let db = open().await?;
+    let store = db
+        .transaction_with_str_and_mode(TRANSCRIPTS, IdbTransactionMode::Readwrite)?
+        .object_store(TRANSCRIPTS)?;
+    let count = finished(&store.count()?).await?.as_f64().unwrap_or(0.0) as u32;
+    if count <= MAX_TRANSCRIPTS {
+        return Ok(());
+    }
+    let oldest = store
+        .index(CACHED_AT_INDEX)?
+        .get_all_keys_with_key_and_limit(&JsValue::UNDEFINED, count - MAX_TRANSCRIPTS)?;
+    let oldest: js_sys::Array = finished(&oldest).await?.dyn_into()?;
+    for key in oldest.iter() {
+        store.delete(&key)?;
+    }
+    Ok(())
+}
+
+/// Cache a session's transcript, serialized by the session view
+pub fn save_transcript(session_id: Uuid, json: String) {
+    spawn_local(async move {
+        let result = async {
+            let record = record(&json)?;
+            Reflect::set(
+                &record,
+                &"session_id".into(),
+                &session_id.to_string().into(),
+            )?;
+            put(TRANSCRIPTS, None, record.into()).await?;
+            prune_transcripts().await
+        }
+        .await;
+        if let Err(e) = result {
+            tracing::debug!("Failed to cache transcript: {:?}", e);
+        }
+    });
+}
+
+/// A cached transcript and when it was cached (ms since the epoch)
+pub async fn load_transcript(session_id: Uuid) -> Option<(String, f64)> {
+    get(TRANSCRIPTS, &session_id.to_string())
+        .await
+        .ok()
+        .and_then(unpack)
+}
+
+/// Cache the session list, as JSON
+pub fn save_sessions(json: String) {
+    spawn_local(async move {
+        let result = async { put(SESSIONS, Some(SESSIONS_KEY), record(&json)?.into()).await }.await;
+        if let Err(e) = result {
+            tracing::debug!("Failed to cache session list: {:?}", e);
+        }
+    });
+}
+
+/// The cached session list, as JSON
+pub async fn load_sessions() -> Option<String> {
+    get(SESSIONS, SESSIONS_KEY)
+        .await
+        .ok()
+        .and_then(unpack)
+        .map(|(json, _)| json)
+}
+
+/// Forget everything cached, when signing out
+pub fn clear() {
+    let factory = web_sys::window().and_then(|window| window.indexed_db().ok().flatten());
+    if let Some(factory) = factory {
+        let _ = factory.delete_database(DB_NAME);
+    }
+}
@@ -1,7 +1,36 @@
+use gloo::utils::window;
 use yew::prelude::*;
 
 #[function_component(AccessDeniedPage)]
 pub fn access_denied_page() -> Html {
+    // Guest links that have run out land here with reason=guest
+    let guest_link = window()
+        .location()
+        .search()
+        .ok()
+        .and_then(|search| {
+            let params = web_sys::UrlSearchParams::new_with_str(&search).ok()?;
+            params.get("reason")
+        })
+        .is_some_and(|reason| reason == "guest");
+
+    if guest_link {
+        return html! {
+            <div class="banned-container">
+                <div class="banned-content">
+                    <div class="banned-icon">{ "⌛" }</div>
+                    <h1>{ "Guest Link Ended" }</h1>
+                    <p class="banned-message">
+                        { "This guest link has expired or been revoked." }
+                    </p>
+                    <p class="banned-contact">
+                        { "Ask the person who shared it for a new link if you still need access." }
+                    </p>
+                </div>
+            </div>
+        };
+    }
+
     html! {
         <div class="banned-container">
             <div class="banned-content">
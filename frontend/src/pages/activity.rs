@@ -0,0 +1,287 @@
+//! Workspace activity feed page
+//!
+//! Lists notable events from `GET /api/activity`, newest first, across every
+//! session the user can see: sessions starting and ending, permission
+//! requests and escalations, errors, and budget breaches. Kind chips and a
+//! session picker narrow the list; "Load older" pages back. Picking an event
+//! opens its session on the dashboard.
+
+use crate::utils;
+use crate::Route;
+use gloo_net::http::Request;
+use shared::{ActivityEvent, EventKind};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+/// Events fetched per page
+const PAGE_SIZE: usize = 100;
+
+/// "2026-01-23 14:05" in local time
+fn format_timestamp(ts: &str) -> String {
+    let date = js_sys::Date::new(&ts.into());
+    if date.get_time().is_nan() {
+        return ts.to_string();
+    }
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}",
+        date.get_full_year(),
+        date.get_month() + 1,
+        date.get_date(),
+        date.get_hours(),
+        date.get_minutes()
+    )
+}
+
+/// What to list
+#[derive(Clone, PartialEq, Default)]
+struct Filters {
+    /// Kinds to show; all when empty
+    kinds: Vec<EventKind>,
+    session_id: Option<Uuid>,
+}
+
+fn activity_url(filters: &Filters, before: Option<&str>) -> String {
+    let mut path = format!("/api/activity?limit={}", PAGE_SIZE);
+    if !filters.kinds.is_empty() {
+        let kinds: Vec<&str> = filters.kinds.iter().map(EventKind::name).collect();
+        path.push_str(&format!("&kinds={}", kinds.join(",")));
+    }
+    if let Some(session_id) = filters.session_id {
+        path.push_str(&format!("&session_id={}", session_id));
+    }
+    if let Some(before) = before {
+        path.push_str(&format!("&before={}", js_sys::encode_uri_component(before)));
+    }
+    utils::api_url(&path)
+}
+
+/// Class for an event's badge, so errors and spends stand out
+fn kind_class(kind: EventKind) -> &'static str {
+    match kind {
+        EventKind::Error | EventKind::BudgetExceeded => "activity-kind bad",
+        EventKind::PermissionRequested
+        | EventKind::PermissionEscalated
+        | EventKind::GuestInvited
+        | EventKind::GuestJoined => "activity-kind warn",
+        EventKind::SessionStarted | EventKind::SessionEnded => "activity-kind",
+    }
+}
+
+#[function_component(ActivityPage)]
+pub fn activity_page() -> Html {
+    let navigator = use_navigator().unwrap();
+    let filters = use_state(Filters::default);
+    let events = use_state(Vec::<ActivityEvent>::new);
+    // Whether the last page came back full, so there may be older events
+    let more = use_state(|| false);
+    let loading = use_state(|| true);
+    let error = use_state(|| None::<String>);
+    // Sessions seen in the feed, for the picker; kept across filter changes
+    let known_sessions = use_state(BTreeMap::<Uuid, String>::new);
+
+    // Fetch a page; `before` continues after the events already shown
+    let fetch = {
+        let events = events.clone();
+        let more = more.clone();
+        let loading = loading.clone();
+        let error = error.clone();
+        let known_sessions = known_sessions.clone();
+        move |filters: Filters, before: Option<String>| {
+            let events = events.clone();
+            let more = more.clone();
+            let loading = loading.clone();
+            let error = error.clone();
+            let known_sessions = known_sessions.clone();
+            loading.set(true);
+            spawn_local(async move {
+                let url = activity_url(&filters, before.as_deref());
+                match Request::get(&url).send().await {
+                    Ok(response) if response.status() == 401 => {
+                        if let Some(window) = web_sys::window() {
+                            let _ = window.location().set_href("/api/auth/logout");
+                        }
+                    }
+                    Ok(response) if response.ok() => {
+                        match response.json::<Vec<ActivityEvent>>().await {
+                            Ok(page) => {
+                                more.set(page.len() == PAGE_SIZE);
+                                let mut sessions = (*known_sessions).clone();
+                                for event in &page {
+                                    sessions
+                                        .entry(event.session_id)
+                                        .or_insert_with(|| event.session_name.clone());
+                                }
+                                known_sessions.set(sessions);
+                                let mut list = if before.is_some() {
+                                    (*events).clone()
+                                } else {
+                                    Vec::new()
+                                };
+                                list.extend(page);
+                                events.set(list);
+                                error.set(None);
+                            }
+                            Err(e) => error.set(Some(format!("Failed to parse activity: {:?}", e))),
+                        }
+                    }
+                    Ok(response) => error.set(Some(format!(
+                        "Failed to load activity: {}",
+                        response.status()
+                    ))),
+                    Err(e) => error.set(Some(format!("Failed to load activity: {:?}", e))),
+                }
+                loading.set(false);
+            });
+        }
+    };
+
+    {
+        let fetch = fetch.clone();
+        use_effect_with((*filters).clone(), move |filters| {
+            fetch(filters.clone(), None);
+            || ()
+        });
+    }
+
+    let go_back = {
+        let navigator = navigator.clone();
+        Callback::from(move |_| navigator.push(&Route::Dashboard))
+    };
+
+    let toggle_kind = |kind: EventKind| {
+        let filters = filters.clone();
+        Callback::from(move |_| {
+            let mut next = (*filters).clone();
+            if let Some(pos) = next.kinds.iter().position(|k| *k == kind) {
+                next.kinds.remove(pos);
+            } else {
+                next.kinds.push(kind);
+            }
+            filters.set(next);
+        })
+    };
+    let show_all_kinds = {
+        let filters = filters.clone();
+        Callback::from(move |_| {
+            filters.set(Filters {
+                kinds: Vec::new(),
+                ..(*filters).clone()
+            })
+        })
+    };
+
+    let on_session = {
+        let filters = filters.clone();
+        Callback::from(move |e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            filters.set(Filters {
+                session_id: select.value().parse().ok(),
+                ..(*filters).clone()
+            });
+        })
+    };
+
+    let load_older = {
+        let filters = filters.clone();
+        let events = events.clone();
+        Callback::from(move |_| {
+            let before = events.last().map(|event| event.created_at.clone());
+            fetch((*filters).clone(), before);
+        })
+    };
+
+    let open_event = |event: &ActivityEvent| {
+        let navigator = navigator.clone();
+        let session_id = event.session_id;
+        Callback::from(move |_| {
+            let _ = navigator
+                .push_with_query(&Route::Dashboard, &[("session", session_id.to_string())]);
+        })
+    };
+
+    let kind_chips = EventKind::ALL.iter().map(|kind| {
+        let active = filters.kinds.contains(kind);
+        html! {
+            <button
+                class={classes!("tab-button", active.then_some("active"))}
+                onclick={toggle_kind(*kind)}
+            >
+                { kind.label() }
+            </button>
+        }
+    });
+
+    let list = if let Some(err) = &*error {
+        html! { <div class="usage-error">{ err }</div> }
+    } else if events.is_empty() && *loading {
+        html! { <div class="loading">{ "Loading activity..." }</div> }
+    } else if events.is_empty() {
+        html! { <div class="usage-empty">{ "Nothing has happened that matches these filters" }</div> }
+    } else {
+        html! {
+            <ul class="activity-list">
+                { for events.iter().map(|event| html! {
+                    <li key={event.id.to_string()}>
+                        <button class="activity-row" onclick={open_event(event)}>
+                            <span class="activity-time">{ format_timestamp(&event.created_at) }</span>
+                            <span class={kind_class(event.kind)}>{ event.kind.label() }</span>
+                            <span class="activity-session">
+                                { utils::extract_folder(&event.session_name) }
+                            </span>
+                            <span class="activity-summary">{ event.summary() }</span>
+                            if let Some(email) = &event.owner_email {
+                                <span class="activity-owner">{ email }</span>
+                            }
+                        </button>
+                    </li>
+                }) }
+            </ul>
+        }
+    };
+
+    html! {
+        <div class="settings-container">
+            <header class="settings-header">
+                <button class="header-button" onclick={go_back}>
+                    { "< Back" }
+                </button>
+                <h1>{ "Activity" }</h1>
+                <span class="usage-loading-indicator">
+                    { if *loading && !events.is_empty() { "Refreshing..." } else { "" } }
+                </span>
+            </header>
+
+            <nav class="settings-tabs activity-filters">
+                <button
+                    class={classes!("tab-button", filters.kinds.is_empty().then_some("active"))}
+                    onclick={show_all_kinds}
+                >
+                    { "All" }
+                </button>
+                { for kind_chips }
+                <select class="activity-session-filter" onchange={on_session}>
+                    <option value="" selected={filters.session_id.is_none()}>
+                        { "All sessions" }
+                    </option>
+                    { for known_sessions.iter().map(|(id, name)| html! {
+                        <option value={id.to_string()} selected={filters.session_id == Some(*id)}>
+                            { utils::extract_folder(name) }
+                        </option>
+                    }) }
+                </select>
+            </nav>
+
+            <main class="settings-content usage-content">
+                { list }
+                if *more && error.is_none() {
+                    <button class="header-button activity-more" onclick={load_older} disabled={*loading}>
+                        { "Load older" }
+                    </button>
+                }
+            </main>
+        </div>
+    }
+}
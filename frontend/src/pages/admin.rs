@@ -1,12 +1,16 @@
 //! Admin dashboard page
 //!
-//! Restricted to users with is_admin=true. Provides system overview,
+//! Restricted to users with the admin role. Provides system overview,
 //! user management, and session management capabilities.
 
+use crate::components::{
+    BudgetSettings, KillSwitchSettings, MaintenanceSettings, RuntimeConfigSettings,
+};
 use crate::utils;
 use crate::Route;
 use gloo_net::http::Request;
 use serde::Deserialize;
+use shared::UserRole;
 use uuid::Uuid;
 use wasm_bindgen_futures::spawn_local;
 use web_sys::MouseEvent;
@@ -19,6 +23,8 @@ enum AdminTab {
     Overview,
     Users,
     Sessions,
+    Budgets,
+    Settings,
     RawMessages,
 }
 
@@ -42,6 +48,57 @@ struct AdminStats {
     total_cache_creation_tokens: i64,
     #[allow(dead_code)]
     total_cache_read_tokens: i64,
+    #[serde(default)]
+    outcome_trend: Vec<OutcomeWeek>,
+    #[serde(default)]
+    proxy_compression: CompressionTotals,
+    #[serde(default)]
+    web_compression: CompressionTotals,
+    #[serde(default)]
+    relay_drops: RelayDropTotals,
+}
+
+/// Messages the backend shed because a peer wasn't keeping up
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+struct RelayDropTotals {
+    to_proxies: u64,
+    to_web_clients: u64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+struct CompressionTotals {
+    #[allow(dead_code)]
+    messages: u64,
+    raw_bytes: u64,
+    compressed_bytes: u64,
+}
+
+impl CompressionTotals {
+    fn saved_bytes(&self) -> u64 {
+        self.raw_bytes.saturating_sub(self.compressed_bytes)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+struct OutcomeWeek {
+    week_start: String,
+    succeeded: i64,
+    partially: i64,
+    failed: i64,
+    abandoned: i64,
+    unlabeled: i64,
+}
+
+impl OutcomeWeek {
+    fn labeled(&self) -> i64 {
+        self.succeeded + self.partially + self.failed + self.abandoned
+    }
+
+    /// Share of labeled sessions that succeeded, if any were labeled
+    fn success_rate(&self) -> Option<f64> {
+        let labeled = self.labeled();
+        (labeled > 0).then(|| self.succeeded as f64 / labeled as f64 * 100.0)
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, PartialEq)]
@@ -51,7 +108,7 @@ struct AdminUserInfo {
     name: Option<String>,
     #[allow(dead_code)]
     avatar_url: Option<String>,
-    is_admin: bool,
+    role: UserRole,
     disabled: bool,
     voice_enabled: bool,
     created_at: String,
@@ -79,6 +136,8 @@ struct AdminSessionInfo {
     created_at: String,
     last_activity: String,
     is_connected: bool,
+    #[serde(default)]
+    outcome: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -118,6 +177,17 @@ fn format_tokens(count: i64) -> String {
     }
 }
 
+/// Format a byte count with KB/MB suffix for readability
+fn format_bytes(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
 /// Format a timestamp for display
 fn format_timestamp(ts: &str) -> String {
     let date = js_sys::Date::new(&ts.into());
@@ -173,7 +243,7 @@ fn stat_card(props: &StatCardProps) -> Html {
 #[derive(Properties, PartialEq)]
 struct UserRowProps {
     user: AdminUserInfo,
-    on_toggle_admin: Callback<Uuid>,
+    on_change_role: Callback<(Uuid, UserRole)>,
     on_toggle_disabled: Callback<Uuid>,
     on_toggle_voice: Callback<Uuid>,
     current_user_id: Uuid,
@@ -184,10 +254,15 @@ fn user_row(props: &UserRowProps) -> Html {
     let user = &props.user;
     let is_self = user.id == props.current_user_id;
 
-    let on_toggle_admin = {
-        let callback = props.on_toggle_admin.clone();
+    let on_change_role = {
+        let callback = props.on_change_role.clone();
         let user_id = user.id;
-        Callback::from(move |_: MouseEvent| callback.emit(user_id))
+        Callback::from(move |e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            if let Some(role) = UserRole::parse(&select.value()) {
+                callback.emit((user_id, role));
+            }
+        })
     };
 
     let on_toggle_disabled = {
@@ -204,18 +279,18 @@ fn user_row(props: &UserRowProps) -> Html {
 
     let status_class = if user.disabled {
         "user-status disabled"
-    } else if user.is_admin {
-        "user-status admin"
     } else {
-        "user-status active"
+        match user.role {
+            UserRole::Admin => "user-status admin",
+            UserRole::Member => "user-status active",
+            UserRole::Viewer => "user-status viewer",
+        }
     };
 
     let status_text = if user.disabled {
         "Disabled"
-    } else if user.is_admin {
-        "Admin"
     } else {
-        "User"
+        user.role.label()
     };
 
     html! {
@@ -230,14 +305,18 @@ fn user_row(props: &UserRowProps) -> Html {
             <td class="numeric">{ format!("${:.2}", user.total_spend_usd) }</td>
             <td class="timestamp">{ format_timestamp(&user.created_at) }</td>
             <td class="actions">
-                <button
-                    class={classes!("admin-toggle", if user.is_admin { Some("active") } else { None })}
-                    onclick={on_toggle_admin}
+                <select
+                    class="role-select"
+                    onchange={on_change_role}
                     disabled={is_self}
-                    title={if is_self { "Cannot change your own admin status" } else if user.is_admin { "Remove admin" } else { "Make admin" }}
+                    title={if is_self { "Cannot change your own role" } else { "Account role" }}
                 >
-                    { if user.is_admin { "Remove Admin" } else { "Make Admin" } }
-                </button>
+                    { for UserRole::ALL.iter().map(|role| html! {
+                        <option value={role.as_str()} selected={*role == user.role}>
+                            { role.label() }
+                        </option>
+                    }) }
+                </select>
                 <button
                     class={classes!("ban-toggle", if user.disabled { Some("active") } else { None })}
                     onclick={on_toggle_disabled}
@@ -258,6 +337,60 @@ fn user_row(props: &UserRowProps) -> Html {
     }
 }
 
+// ============================================================================
+// Outcome Trend
+// ============================================================================
+
+/// Weekly session outcomes with the success rate of labeled sessions
+fn render_outcome_trend(weeks: &[OutcomeWeek]) -> Html {
+    if weeks.is_empty() {
+        return html! {};
+    }
+
+    html! {
+        <div class="outcome-trend">
+            <h3>{ "Session Outcomes" }</h3>
+            <table class="admin-table">
+                <thead>
+                    <tr>
+                        <th>{ "Week of" }</th>
+                        <th>{ "Succeeded" }</th>
+                        <th>{ "Partially" }</th>
+                        <th>{ "Failed" }</th>
+                        <th>{ "Abandoned" }</th>
+                        <th>{ "Unlabeled" }</th>
+                        <th>{ "Success Rate" }</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    {
+                        weeks.iter().rev().map(|week| {
+                            let rate = week.success_rate();
+                            html! {
+                                <tr key={week.week_start.clone()}>
+                                    <td class="timestamp">{ &week.week_start }</td>
+                                    <td class="numeric">{ week.succeeded }</td>
+                                    <td class="numeric">{ week.partially }</td>
+                                    <td class="numeric">{ week.failed }</td>
+                                    <td class="numeric">{ week.abandoned }</td>
+                                    <td class="numeric">{ week.unlabeled }</td>
+                                    <td class="numeric success-rate">
+                                        <span
+                                            class="success-rate-bar"
+                                            style={format!("width: {:.0}%", rate.unwrap_or(0.0))}
+                                        />
+                                        { rate.map(|r| format!("{:.0}%", r)).unwrap_or_else(|| "-".to_string()) }
+                                    </td>
+                                </tr>
+                            }
+                        }).collect::<Html>()
+                    }
+                </tbody>
+            </table>
+        </div>
+    }
+}
+
 // ============================================================================
 // Session Row Component
 // ============================================================================
@@ -302,6 +435,7 @@ fn session_row(props: &SessionRowProps) -> Html {
             <td class="session-project">{ project_name }</td>
             <td class="session-branch">{ session.git_branch.as_deref().unwrap_or("-") }</td>
             <td class={status_class}>{ status_text }</td>
+            <td class="session-outcome">{ session.outcome.as_deref().unwrap_or("-") }</td>
             <td class="numeric">{ format!("${:.2}", session.total_cost_usd) }</td>
             <td class="timestamp">{ format_timestamp(&session.last_activity) }</td>
             <td class="actions">
@@ -597,29 +731,28 @@ pub fn admin_page() -> Html {
         });
     }
 
-    // Toggle admin handler
-    let on_toggle_admin = {
+    // Change role handler
+    let on_change_role = {
         let users = users.clone();
         let confirm_action = confirm_action.clone();
-        Callback::from(move |user_id: Uuid| {
+        Callback::from(move |(user_id, new_role): (Uuid, UserRole)| {
             let users_inner = users.clone();
             let confirm_inner = confirm_action.clone();
 
-            let target_user = users_inner.iter().find(|u| u.id == user_id).cloned();
-            let is_currently_admin = target_user.as_ref().map(|u| u.is_admin).unwrap_or(false);
-            let action_text = if is_currently_admin {
-                "Remove admin privileges from this user?"
-            } else {
-                "Grant admin privileges to this user?"
+            let action_text = match new_role {
+                UserRole::Admin => "Grant admin privileges to this user?".to_string(),
+                UserRole::Member => "Make this user a member?".to_string(),
+                UserRole::Viewer => {
+                    "Make this user a viewer? Their proxy tokens will be revoked.".to_string()
+                }
             };
 
             let action = Callback::from(move |_: MouseEvent| {
                 let users = users_inner.clone();
                 let confirm = confirm_inner.clone();
-                let new_admin_status = !is_currently_admin;
                 spawn_local(async move {
                     let api_endpoint = utils::api_url(&format!("/api/admin/users/{}", user_id));
-                    let body = serde_json::json!({ "is_admin": new_admin_status });
+                    let body = serde_json::json!({ "role": new_role });
                     match Request::patch(&api_endpoint)
                         .header("Content-Type", "application/json")
                         .body(body.to_string())
@@ -631,20 +764,20 @@ pub fn admin_page() -> Html {
                             if response.status() == 204 {
                                 let mut updated = (*users).clone();
                                 if let Some(user) = updated.iter_mut().find(|u| u.id == user_id) {
-                                    user.is_admin = new_admin_status;
+                                    user.role = new_role;
                                 }
                                 users.set(updated);
                             }
                         }
                         Err(e) => {
-                            log::error!("Failed to update user: {:?}", e);
+                            tracing::error!("Failed to update user: {:?}", e);
                         }
                     }
                     confirm.set(None);
                 });
             });
 
-            confirm_action.set(Some((action_text.to_string(), action)));
+            confirm_action.set(Some((action_text, action)));
         })
     };
 
@@ -690,7 +823,7 @@ pub fn admin_page() -> Html {
                                 }
                             }
                             Err(e) => {
-                                log::error!("Failed to update user: {:?}", e);
+                                tracing::error!("Failed to update user: {:?}", e);
                             }
                         }
                         confirm.set(None);
@@ -739,7 +872,7 @@ pub fn admin_page() -> Html {
                             }
                         }
                         Err(e) => {
-                            log::error!("Failed to ban user: {:?}", e);
+                            tracing::error!("Failed to ban user: {:?}", e);
                         }
                     }
                     ban_dialog.set(None);
@@ -808,7 +941,7 @@ pub fn admin_page() -> Html {
                             }
                         }
                         Err(e) => {
-                            log::error!("Failed to update user: {:?}", e);
+                            tracing::error!("Failed to update user: {:?}", e);
                         }
                     }
                     confirm.set(None);
@@ -849,7 +982,7 @@ pub fn admin_page() -> Html {
                             }
                         }
                         Err(e) => {
-                            log::error!("Failed to delete session: {:?}", e);
+                            tracing::error!("Failed to delete session: {:?}", e);
                         }
                     }
                     confirm.set(None);
@@ -889,7 +1022,7 @@ pub fn admin_page() -> Html {
                             }
                         }
                         Err(e) => {
-                            log::error!("Failed to delete raw message: {:?}", e);
+                            tracing::error!("Failed to delete raw message: {:?}", e);
                         }
                     }
                     confirm.set(None);
@@ -930,6 +1063,14 @@ pub fn admin_page() -> Html {
         let active_tab = active_tab.clone();
         Callback::from(move |_| active_tab.set(AdminTab::Sessions))
     };
+    let on_budgets_tab = {
+        let active_tab = active_tab.clone();
+        Callback::from(move |_| active_tab.set(AdminTab::Budgets))
+    };
+    let on_settings_tab = {
+        let active_tab = active_tab.clone();
+        Callback::from(move |_| active_tab.set(AdminTab::Settings))
+    };
     let on_raw_messages_tab = {
         let active_tab = active_tab.clone();
         Callback::from(move |_| active_tab.set(AdminTab::RawMessages))
@@ -998,6 +1139,18 @@ pub fn admin_page() -> Html {
                                 >
                                     { format!("Sessions ({})", sessions.len()) }
                                 </button>
+                                <button
+                                    class={classes!("tab-btn", if *active_tab == AdminTab::Budgets { Some("active") } else { None })}
+                                    onclick={on_budgets_tab}
+                                >
+                                    { "Budgets" }
+                                </button>
+                                <button
+                                    class={classes!("tab-btn", if *active_tab == AdminTab::Settings { Some("active") } else { None })}
+                                    onclick={on_settings_tab}
+                                >
+                                    { "Settings" }
+                                </button>
                                 <button
                                     class={classes!("tab-btn", if *active_tab == AdminTab::RawMessages { Some("active") } else { None })}
                                     onclick={on_raw_messages_tab}
@@ -1042,7 +1195,26 @@ pub fn admin_page() -> Html {
                                                                 label="Output Tokens"
                                                                 value={format_tokens(s.total_output_tokens)}
                                                             />
+                                                            <StatCard
+                                                                label="Compression Saved"
+                                                                value={format_bytes(s.proxy_compression.saved_bytes() + s.web_compression.saved_bytes())}
+                                                                subvalue={Some(format!(
+                                                                    "{} from proxies, {} to browsers",
+                                                                    format_bytes(s.proxy_compression.saved_bytes()),
+                                                                    format_bytes(s.web_compression.saved_bytes())
+                                                                ))}
+                                                            />
+                                                            <StatCard
+                                                                label="Dropped Messages"
+                                                                value={format!("{}", s.relay_drops.to_proxies + s.relay_drops.to_web_clients)}
+                                                                subvalue={Some(format!(
+                                                                    "{} to proxies, {} to browsers",
+                                                                    s.relay_drops.to_proxies,
+                                                                    s.relay_drops.to_web_clients
+                                                                ))}
+                                                            />
                                                         </div>
+                                                        { render_outcome_trend(&s.outcome_trend) }
                                                     </div>
                                                 }
                                             } else {
@@ -1071,7 +1243,7 @@ pub fn admin_page() -> Html {
                                                                         <UserRow
                                                                             key={user.id.to_string()}
                                                                             user={user.clone()}
-                                                                            on_toggle_admin={on_toggle_admin.clone()}
+                                                                            on_change_role={on_change_role.clone()}
                                                                             on_toggle_disabled={on_toggle_disabled.clone()}
                                                                             on_toggle_voice={on_toggle_voice.clone()}
                                                                             current_user_id={current_user_id.unwrap_or_default()}
@@ -1095,6 +1267,7 @@ pub fn admin_page() -> Html {
                                                                 <th>{ "Project" }</th>
                                                                 <th>{ "Branch" }</th>
                                                                 <th>{ "Status" }</th>
+                                                                <th>{ "Outcome" }</th>
                                                                 <th>{ "Cost" }</th>
                                                                 <th>{ "Last Activity" }</th>
                                                                 <th>{ "Actions" }</th>
@@ -1117,6 +1290,18 @@ pub fn admin_page() -> Html {
                                                 </div>
                                             }
                                         }
+                                        AdminTab::Budgets => {
+                                            html! { <BudgetSettings /> }
+                                        }
+                                        AdminTab::Settings => {
+                                            html! {
+                                                <>
+                                                    <KillSwitchSettings />
+                                                    <MaintenanceSettings />
+                                                    <RuntimeConfigSettings />
+                                                </>
+                                            }
+                                        }
                                         AdminTab::RawMessages => {
                                             html! {
                                                 <div class="admin-raw-messages">
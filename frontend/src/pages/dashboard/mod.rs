@@ -5,11 +5,13 @@
 //! - `SessionRail`: Horizontal carousel of session pills
 //! - `SessionView`: Terminal view for a single session
 //! - `PermissionDialog`: Permission prompt and AskUserQuestion dialogs
+//! - `DashboardStore`: UI state shared by the components above
 
 mod page;
 mod permission_dialog;
 mod session_rail;
 mod session_view;
+mod store;
 mod types;
 
 pub use page::DashboardPage;
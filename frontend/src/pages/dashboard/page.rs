@@ -2,22 +2,30 @@
 
 use super::session_rail::SessionRail;
 use super::session_view::SessionView;
+use super::store::{DashboardAction, DashboardState, DashboardStore};
 use super::types::{
     load_inactive_hidden, load_paused_sessions, save_inactive_hidden, save_paused_sessions,
 };
-use crate::components::ProxyTokenSetup;
+use crate::components::{
+    OnboardingChecklist, PermissionHistoryDialog, ProxyTokenSetup, ReportDialog,
+};
 use crate::hooks::{use_client_websocket, use_keyboard_nav, use_sessions, KeyboardNavConfig};
 use crate::utils;
 use crate::Route;
 use gloo_net::http::Request;
-use shared::{AppConfig, SessionInfo};
-use std::collections::HashSet;
+use shared::{AppConfig, RenderLimits, SessionInfo};
 use uuid::Uuid;
 use wasm_bindgen_futures::spawn_local;
 use web_sys::MouseEvent;
 use yew::prelude::*;
 use yew_router::prelude::*;
 
+/// `/dashboard?session=<id>` opens that session, e.g. from a search result
+#[derive(serde::Deserialize)]
+struct DashboardQuery {
+    session: Option<Uuid>,
+}
+
 // =============================================================================
 // Dashboard Page - Main Orchestrating Component
 // =============================================================================
@@ -25,36 +33,55 @@ use yew_router::prelude::*;
 #[function_component(DashboardPage)]
 pub fn dashboard_page() -> Html {
     let navigator = use_navigator().unwrap();
+    let linked_session = use_location()
+        .and_then(|location| location.query::<DashboardQuery>().ok())
+        .and_then(|query| query.session);
 
     // Use the sessions hook for fetching and polling
     let sessions_hook = use_sessions();
     let sessions = sessions_hook.sessions.clone();
     let loading = sessions_hook.loading;
+    let quota = sessions_hook.quota;
+    let offline = sessions_hook.offline;
 
     // Use the client websocket hook for spend updates
     let ws_hook = use_client_websocket();
     let total_user_spend = ws_hook.total_spend;
     let session_costs = ws_hook.session_costs.clone();
     let server_shutdown_reason = ws_hook.shutdown_reason.clone();
-
-    // UI state
-    let show_new_session = use_state(|| false);
-    let focused_index = use_state(|| 0usize);
-    let awaiting_sessions = use_state(HashSet::<Uuid>::new);
-    let paused_sessions = use_state(load_paused_sessions);
-    let inactive_hidden = use_state(load_inactive_hidden);
-    let connected_sessions = use_state(HashSet::<Uuid>::new);
-    let pending_leave = use_state(|| None::<Uuid>);
+    let escalations = ws_hook.escalations.clone();
+    let dismiss_escalation = ws_hook.dismiss_escalation.clone();
+    let search_alerts = ws_hook.search_alerts.clone();
+    let dismiss_search_alert = ws_hook.dismiss_search_alert.clone();
+
+    // UI state shared with the rail and session views
+    let store: DashboardStore =
+        use_reducer(|| DashboardState::new(load_paused_sessions(), load_inactive_hidden()));
     let is_admin = use_state(|| false);
+    let current_user_id = use_state(|| None::<Uuid>);
     let voice_enabled = use_state(|| false);
+    let voice_language = use_state(|| AttrValue::Static(shared::voice::DEFAULT_LANGUAGE));
+    let voice_auto_language = use_state(|| false);
+    let render_limits = use_state(RenderLimits::default);
     let app_title = use_state(|| "Claude Code Sessions".to_string());
-    let activated_sessions = use_state(HashSet::<Uuid>::new);
-    let initial_focus_set = use_state(|| false);
+    let issue_report_url = use_state(|| AttrValue::Static(shared::DEFAULT_ISSUE_REPORT_URL));
+
+    // Keep the user's pause choices across reloads
+    use_effect_with(store.paused.clone(), |paused| {
+        save_paused_sessions(paused);
+        || ()
+    });
+    use_effect_with(store.inactive_hidden, |hidden| {
+        save_inactive_hidden(*hidden);
+        || ()
+    });
 
-    // Fetch current user info (to check admin status and voice_enabled)
+    // Fetch current user info (admin status, voice_enabled, and our ID for session control)
     {
         let is_admin = is_admin.clone();
+        let current_user_id = current_user_id.clone();
         let voice_enabled = voice_enabled.clone();
+        let voice_language = voice_language.clone();
         use_effect_with((), move |_| {
             spawn_local(async move {
                 let api_endpoint = utils::api_url("/api/auth/me");
@@ -63,9 +90,20 @@ pub fn dashboard_page() -> Html {
                         if let Some(admin) = data.get("is_admin").and_then(|v| v.as_bool()) {
                             is_admin.set(admin);
                         }
+                        if let Some(id) = data
+                            .get("id")
+                            .and_then(|v| v.as_str())
+                            .and_then(|v| v.parse::<Uuid>().ok())
+                        {
+                            current_user_id.set(Some(id));
+                        }
                         if let Some(voice) = data.get("voice_enabled").and_then(|v| v.as_bool()) {
                             voice_enabled.set(voice);
                         }
+                        if let Some(language) = data.get("voice_language").and_then(|v| v.as_str())
+                        {
+                            voice_language.set(AttrValue::from(language.to_string()));
+                        }
                     }
                 }
             });
@@ -76,12 +114,18 @@ pub fn dashboard_page() -> Html {
     // Fetch app configuration (title, etc.)
     {
         let app_title = app_title.clone();
+        let issue_report_url = issue_report_url.clone();
+        let voice_auto_language = voice_auto_language.clone();
+        let render_limits = render_limits.clone();
         use_effect_with((), move |_| {
             spawn_local(async move {
                 let api_endpoint = utils::api_url("/api/config");
                 if let Ok(response) = Request::get(&api_endpoint).send().await {
                     if let Ok(config) = response.json::<AppConfig>().await {
                         app_title.set(config.app_title);
+                        issue_report_url.set(config.issue_report_url.into());
+                        voice_auto_language.set(config.voice_auto_language);
+                        render_limits.set(config.render_limits);
                     }
                 }
             });
@@ -115,32 +159,24 @@ pub fn dashboard_page() -> Html {
         sorted
     };
 
-    // Set initial focus to first non-paused session (once sessions are loaded)
+    // Set initial focus to the linked session, else the first non-paused
+    // one (once sessions are loaded)
     {
         let active_sessions = active_sessions.clone();
-        let paused_sessions = paused_sessions.clone();
-        let focused_index = focused_index.clone();
-        let initial_focus_set = initial_focus_set.clone();
-        let activated_sessions = activated_sessions.clone();
+        let store = store.clone();
 
         use_effect_with(
             (active_sessions.len(), loading),
             move |(session_count, is_loading)| {
-                if !*initial_focus_set && !*is_loading && *session_count > 0 {
-                    let first_non_paused_idx = active_sessions
-                        .iter()
-                        .position(|s| !paused_sessions.contains(&s.id))
+                if !store.initial_focus_set && !*is_loading && *session_count > 0 {
+                    let index = linked_session
+                        .and_then(|id| active_sessions.iter().position(|s| s.id == id))
+                        .or_else(|| active_sessions.iter().position(|s| !store.is_paused(&s.id)))
                         .unwrap_or(0);
-
-                    focused_index.set(first_non_paused_idx);
-
-                    if let Some(session) = active_sessions.get(first_non_paused_idx) {
-                        let mut activated = (*activated_sessions).clone();
-                        activated.insert(session.id);
-                        activated_sessions.set(activated);
-                    }
-
-                    initial_focus_set.set(true);
+                    store.dispatch(DashboardAction::InitialFocus {
+                        index,
+                        session_id: active_sessions.get(index).map(|s| s.id),
+                    });
                 }
                 || ()
             },
@@ -149,38 +185,28 @@ pub fn dashboard_page() -> Html {
 
     // Session selection callback
     let on_select_session = {
-        let focused_index = focused_index.clone();
-        let activated_sessions = activated_sessions.clone();
+        let store = store.clone();
         let active_sessions = active_sessions.clone();
         Callback::from(move |index: usize| {
-            focused_index.set(index);
-            if let Some(session) = active_sessions.get(index) {
-                let mut activated = (*activated_sessions).clone();
-                activated.insert(session.id);
-                activated_sessions.set(activated);
-            }
-        })
-    };
-
-    // Activation callback for keyboard nav
-    let on_activate = {
-        let activated_sessions = activated_sessions.clone();
-        Callback::from(move |session_id: Uuid| {
-            let mut activated = (*activated_sessions).clone();
-            activated.insert(session_id);
-            activated_sessions.set(activated);
+            store.dispatch(DashboardAction::Focus {
+                index,
+                session_id: active_sessions.get(index).map(|s| s.id),
+            });
         })
     };
 
     // Use the keyboard navigation hook
     let keyboard_nav = use_keyboard_nav(KeyboardNavConfig {
         sessions: active_sessions.clone(),
-        focused_index: *focused_index,
-        paused_sessions: (*paused_sessions).clone(),
-        connected_sessions: (*connected_sessions).clone(),
-        inactive_hidden: *inactive_hidden,
+        focused_index: store.focused_index,
+        paused_sessions: store.paused.clone(),
+        connected_sessions: store.connected.clone(),
+        inactive_hidden: store.inactive_hidden,
         on_select: on_select_session.clone(),
-        on_activate,
+        on_activate: {
+            let store = store.clone();
+            Callback::from(move |session_id| store.dispatch(DashboardAction::Activate(session_id)))
+        },
     });
 
     // Navigation callbacks
@@ -194,34 +220,41 @@ pub fn dashboard_page() -> Html {
         Callback::from(move |_| navigator.push(&Route::Settings))
     };
 
+    let go_to_usage = {
+        let navigator = navigator.clone();
+        Callback::from(move |_| navigator.push(&Route::Usage))
+    };
+
+    let go_to_search = {
+        let navigator = navigator.clone();
+        Callback::from(move |_| navigator.push(&Route::Search))
+    };
+
+    let go_to_activity = {
+        let navigator = navigator.clone();
+        Callback::from(move |_| navigator.push(&Route::Activity))
+    };
+
     let do_logout = Callback::from(move |_| {
+        crate::offline::clear();
         if let Some(window) = web_sys::window() {
             let _ = window.location().set_href("/api/auth/logout");
         }
     });
 
     // Leave session callbacks
-    let on_leave = {
-        let pending_leave = pending_leave.clone();
-        Callback::from(move |session_id: Uuid| {
-            pending_leave.set(Some(session_id));
-        })
-    };
-
     let on_cancel_leave = {
-        let pending_leave = pending_leave.clone();
-        Callback::from(move |_| {
-            pending_leave.set(None);
-        })
+        let store = store.clone();
+        Callback::from(move |_| store.dispatch(DashboardAction::SetPendingLeave(None)))
     };
 
     let on_confirm_leave = {
-        let pending_leave = pending_leave.clone();
+        let store = store.clone();
         let refresh = sessions_hook.refresh.clone();
         Callback::from(move |_| {
-            if let Some(session_id) = *pending_leave {
+            if let Some(session_id) = store.pending_leave {
                 let refresh = refresh.clone();
-                let pending_leave = pending_leave.clone();
+                let store = store.clone();
                 spawn_local(async move {
                     let me_endpoint = utils::api_url("/api/auth/me");
                     let user_id = match Request::get(&me_endpoint).send().await {
@@ -247,95 +280,27 @@ pub fn dashboard_page() -> Html {
                                 refresh.emit(());
                             }
                             Ok(response) => {
-                                log::error!(
+                                tracing::error!(
                                     "Failed to leave session: status {}",
                                     response.status()
                                 );
                             }
                             Err(e) => {
-                                log::error!("Failed to leave session: {:?}", e);
+                                tracing::error!("Failed to leave session: {:?}", e);
                             }
                         }
                     } else {
-                        log::error!("Failed to get current user ID for leave");
+                        tracing::error!("Failed to get current user ID for leave");
                     }
-                    pending_leave.set(None);
+                    store.dispatch(DashboardAction::SetPendingLeave(None));
                 });
             }
         })
     };
 
     let toggle_new_session = {
-        let show_new_session = show_new_session.clone();
-        Callback::from(move |_| {
-            show_new_session.set(!*show_new_session);
-        })
-    };
-
-    // Session state callbacks
-    let on_awaiting_change = {
-        let awaiting_sessions = awaiting_sessions.clone();
-        Callback::from(move |(session_id, is_awaiting): (Uuid, bool)| {
-            let mut set = (*awaiting_sessions).clone();
-            if is_awaiting {
-                set.insert(session_id);
-            } else {
-                set.remove(&session_id);
-            }
-            awaiting_sessions.set(set);
-        })
-    };
-
-    let on_cost_change = {
-        Callback::from(move |(_session_id, _cost): (Uuid, f64)| {
-            // Costs now come from the websocket hook, so this is a no-op
-            // but we keep it for API compatibility with SessionView
-        })
-    };
-
-    let on_connected_change = {
-        let connected_sessions = connected_sessions.clone();
-        Callback::from(move |(session_id, connected): (Uuid, bool)| {
-            let mut set = (*connected_sessions).clone();
-            if connected {
-                set.insert(session_id);
-            } else {
-                set.remove(&session_id);
-            }
-            connected_sessions.set(set);
-        })
-    };
-
-    let on_toggle_pause = {
-        let paused_sessions = paused_sessions.clone();
-        Callback::from(move |session_id: Uuid| {
-            let mut set = (*paused_sessions).clone();
-            if set.contains(&session_id) {
-                set.remove(&session_id);
-            } else {
-                set.insert(session_id);
-            }
-            save_paused_sessions(&set);
-            paused_sessions.set(set);
-        })
-    };
-
-    let on_toggle_inactive_hidden = {
-        let inactive_hidden = inactive_hidden.clone();
-        Callback::from(move |_: MouseEvent| {
-            let new_val = !*inactive_hidden;
-            save_inactive_hidden(new_val);
-            inactive_hidden.set(new_val);
-        })
-    };
-
-    let on_message_sent = {
-        let awaiting_sessions = awaiting_sessions.clone();
-        Callback::from(move |current_session_id: Uuid| {
-            let mut set = (*awaiting_sessions).clone();
-            set.remove(&current_session_id);
-            awaiting_sessions.set(set);
-        })
+        let store = store.clone();
+        Callback::from(move |_| store.dispatch(DashboardAction::ToggleNewSession))
     };
 
     let on_branch_change = {
@@ -350,20 +315,14 @@ pub fn dashboard_page() -> Html {
         })
     };
 
+    let on_voice_language_change = {
+        let voice_language = voice_language.clone();
+        Callback::from(move |language: String| voice_language.set(AttrValue::from(language)))
+    };
+
     // Computed values
-    let waiting_count = awaiting_sessions
-        .iter()
-        .filter(|id| !paused_sessions.contains(id))
-        .count();
-
-    let disconnected_count = active_sessions
-        .iter()
-        .filter(|s| {
-            activated_sessions.contains(&s.id)
-                && !paused_sessions.contains(&s.id)
-                && !connected_sessions.contains(&s.id)
-        })
-        .count();
+    let waiting_count = store.waiting_count();
+    let disconnected_count = store.disconnected_count(&active_sessions);
 
     // Update browser tab title
     {
@@ -387,241 +346,384 @@ pub fn dashboard_page() -> Html {
     }
 
     html! {
-        <div class="focus-flow-container" onkeydown={keyboard_nav.on_keydown.clone()} tabindex="0">
-            // Server shutdown warning banner
-            {
-                if let Some(reason) = server_shutdown_reason.as_ref() {
+        <ContextProvider<DashboardStore> context={store.clone()}>
+            <div class="focus-flow-container" onkeydown={keyboard_nav.on_keydown.clone()} tabindex="0">
+                // Server shutdown warning banner
+                {
+                    if let Some(reason) = server_shutdown_reason.as_ref() {
+                        html! {
+                            <div class="server-shutdown-banner">
+                                <span class="shutdown-icon">{ "⚠" }</span>
+                                <span class="shutdown-text">{ format!("Server shutting down: {} — reconnecting...", reason) }</span>
+                            </div>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+
+                // Permission requests in other sessions escalated to this user
+                { for escalations.iter().map(|alert| {
+                    let on_open = {
+                        let on_select = on_select_session.clone();
+                        let dismiss = dismiss_escalation.clone();
+                        let index = active_sessions.iter().position(|s| s.id == alert.session_id);
+                        let request_id = alert.request_id.clone();
+                        Callback::from(move |_: MouseEvent| {
+                            if let Some(index) = index {
+                                on_select.emit(index);
+                            }
+                            dismiss.emit(request_id.clone());
+                        })
+                    };
+                    let name = sessions
+                        .iter()
+                        .find(|s| s.id == alert.session_id)
+                        .map(|s| utils::extract_folder(&s.working_directory))
+                        .unwrap_or(&alert.session_name);
+                    let on_dismiss = {
+                        let dismiss = dismiss_escalation.clone();
+                        let request_id = alert.request_id.clone();
+                        Callback::from(move |_: MouseEvent| dismiss.emit(request_id.clone()))
+                    };
                     html! {
-                        <div class="server-shutdown-banner">
-                            <span class="shutdown-icon">{ "⚠" }</span>
-                            <span class="shutdown-text">{ format!("Server shutting down: {} — reconnecting...", reason) }</span>
+                        <div class="escalation-banner" key={alert.request_id.clone()}>
+                            <span class="escalation-icon">{ "⏰" }</span>
+                            <span class="escalation-text">
+                                { format!(
+                                    "{} in {} has been waiting for approval for {} minutes",
+                                    alert.tool_name,
+                                    name,
+                                    alert.waiting_minutes
+                                ) }
+                            </span>
+                            <button type="button" onclick={on_open}>{ "Open" }</button>
+                            <button type="button" class="escalation-dismiss" onclick={on_dismiss}>{ "×" }</button>
                         </div>
                     }
-                } else {
-                    html! {}
-                }
-            }
+                }) }
+
+                // New matches for the user's alerting saved searches
+                { for search_alerts.iter().map(|alert| {
+                    let latest = alert.hits.first();
+                    let on_open = {
+                        let on_select = on_select_session.clone();
+                        let dismiss = dismiss_search_alert.clone();
+                        let index = latest
+                            .and_then(|hit| active_sessions.iter().position(|s| s.id == hit.session_id));
+                        let id = alert.saved_search_id;
+                        Callback::from(move |_: MouseEvent| {
+                            if let Some(index) = index {
+                                on_select.emit(index);
+                            }
+                            dismiss.emit(id);
+                        })
+                    };
+                    let on_dismiss = {
+                        let dismiss = dismiss_search_alert.clone();
+                        let id = alert.saved_search_id;
+                        Callback::from(move |_: MouseEvent| dismiss.emit(id))
+                    };
+                    let count = format!(
+                        "{}{}",
+                        alert.hits.len(),
+                        if alert.more { "+" } else { "" }
+                    );
+                    let plural = if alert.hits.len() == 1 && !alert.more { "" } else { "s" };
+                    let place = latest
+                        .map(|hit| format!(", latest in {}", utils::extract_folder(&hit.session_name)))
+                        .unwrap_or_default();
+                    html! {
+                        <div class="search-alert-banner" key={alert.saved_search_id.to_string()}>
+                            <span class="search-alert-icon">{ "🔔" }</span>
+                            <span
+                                class="search-alert-text"
+                                title={latest.map(|hit| hit.snippet.clone()).unwrap_or_default()}
+                            >
+                                { format!(
+                                    "\"{}\" matched {} new message{}{}",
+                                    alert.name, count, plural, place
+                                ) }
+                            </span>
+                            <button type="button" onclick={on_open}>{ "Open" }</button>
+                            <button type="button" class="search-alert-dismiss" onclick={on_dismiss}>{ "×" }</button>
+                        </div>
+                    }
+                }) }
 
-            // Header
-            <header class="focus-flow-header">
-                <h1>{ (*app_title).clone() }</h1>
-                <div class="header-actions">
-                    {
-                        if total_user_spend > 0.0 {
-                            html! {
-                                <span class="total-spend-badge" title="Total spend across all sessions">
-                                    { format!("${:.2}", total_user_spend) }
-                                </span>
+                // Header
+                <header class="focus-flow-header">
+                    <h1>{ (*app_title).clone() }</h1>
+                    <div class="header-actions">
+                        {
+                            if total_user_spend > 0.0 {
+                                html! {
+                                    <span class="total-spend-badge" title="Total spend across all sessions">
+                                        { format!("${:.2}", total_user_spend) }
+                                    </span>
+                                }
+                            } else {
+                                html! {}
                             }
-                        } else {
-                            html! {}
                         }
-                    }
-                    {
-                        if waiting_count > 0 {
-                            html! {
-                                <span class="waiting-badge">
-                                    { format!("{} waiting", waiting_count) }
-                                </span>
+                        if let Some(limit) = quota.limit {
+                            <span
+                                class={classes!("quota-badge", quota.is_full().then_some("full"))}
+                                title="Sessions running out of the most you can run at once"
+                            >
+                                { format!("{}/{} running", quota.running, limit) }
+                            </span>
+                        }
+                        {
+                            if waiting_count > 0 {
+                                html! {
+                                    <span class="waiting-badge">
+                                        { format!("{} waiting", waiting_count) }
+                                    </span>
+                                }
+                            } else {
+                                html! {}
                             }
-                        } else {
-                            html! {}
                         }
-                    }
-                    <button
-                        class={classes!("new-session-button", if *show_new_session { "active" } else { "" })}
-                        onclick={toggle_new_session.clone()}
-                        title={if *show_new_session { "Close" } else { "Connect a new Claude proxy session" }}
-                    >
-                        { if *show_new_session { "Close" } else { "+ New Session" } }
-                    </button>
-                    {
-                        if *is_admin {
-                            html! {
-                                <button class="header-button" onclick={go_to_admin.clone()}>
-                                    { "Admin" }
-                                </button>
+                        <button
+                            class={classes!("new-session-button", if store.show_new_session { "active" } else { "" })}
+                            onclick={toggle_new_session.clone()}
+                            title={if store.show_new_session { "Close" } else { "Connect a new Claude proxy session" }}
+                        >
+                            { if store.show_new_session { "Close" } else { "+ New Session" } }
+                        </button>
+                        {
+                            if *is_admin {
+                                html! {
+                                    <button class="header-button" onclick={go_to_admin.clone()}>
+                                        { "Admin" }
+                                    </button>
+                                }
+                            } else {
+                                html! {}
                             }
-                        } else {
-                            html! {}
                         }
-                    }
-                    <button class="header-button" onclick={go_to_settings.clone()}>
-                        { "Settings" }
-                    </button>
-                    <button class="header-button logout" onclick={do_logout.clone()}>
-                        { "Logout" }
-                    </button>
-                </div>
-            </header>
-
-            // New session modal
-            if *show_new_session {
-                <div class="modal-overlay" onclick={toggle_new_session.clone()}>
-                    <div class="modal-content" onclick={Callback::from(|e: MouseEvent| e.stop_propagation())}>
-                        <ProxyTokenSetup />
+                        <button class="header-button" onclick={go_to_search.clone()}>
+                            { "Search" }
+                        </button>
+                        <button class="header-button" onclick={go_to_activity.clone()}>
+                            { "Activity" }
+                        </button>
+                        <button class="header-button" onclick={go_to_usage.clone()}>
+                            { "Usage" }
+                        </button>
+                        <button class="header-button" onclick={go_to_settings.clone()}>
+                            { "Settings" }
+                        </button>
+                        <button class="header-button logout" onclick={do_logout.clone()}>
+                            { "Logout" }
+                        </button>
                     </div>
-                </div>
-            }
-
-            // Reconnection banner
-            if disconnected_count > 0 && !loading {
-                <div class="reconnection-banner">
-                    <span class="reconnection-spinner">{ "↻" }</span>
-                    <span class="reconnection-text">
-                        { if disconnected_count == 1 {
-                            "Reconnecting...".to_string()
-                        } else {
-                            format!("{} sessions reconnecting...", disconnected_count)
-                        }}
-                    </span>
-                </div>
-            }
+                </header>
 
-            if loading {
-                <div class="loading">
-                    <div class="spinner"></div>
-                    <p>{ "Loading sessions..." }</p>
-                </div>
-            } else if active_sessions.is_empty() {
-                <div class="onboarding-container">
-                    <div class="onboarding-content">
-                        <h2>{ "No Sessions Connected" }</h2>
-                        <div class="onboarding-steps">
-                            <div class="onboarding-step">
-                                <span class="step-number">{ "1" }</span>
-                                <div class="step-content">
-                                    <p>{ "Click " }<strong>{ "+ New Session" }</strong>{ " above to get a setup command" }</p>
-                                </div>
-                            </div>
-                            <div class="onboarding-step">
-                                <span class="step-number">{ "2" }</span>
-                                <div class="step-content">
-                                    <p>{ "Run that command on your dev machine to connect Claude Code" }</p>
-                                </div>
-                            </div>
+                // New session modal
+                if store.show_new_session {
+                    <div class="modal-overlay" onclick={toggle_new_session.clone()}>
+                        <div class="modal-content" onclick={Callback::from(|e: MouseEvent| e.stop_propagation())}>
+                            <ProxyTokenSetup />
                         </div>
                     </div>
-                </div>
-            } else {
-                <>
-                    // Session Rail
-                    <SessionRail
-                        sessions={active_sessions.clone()}
-                        focused_index={*focused_index}
-                        awaiting_sessions={(*awaiting_sessions).clone()}
-                        paused_sessions={(*paused_sessions).clone()}
-                        inactive_hidden={*inactive_hidden}
-                        session_costs={session_costs.clone()}
-                        connected_sessions={(*connected_sessions).clone()}
-                        nav_mode={keyboard_nav.nav_mode}
-                        on_select={on_select_session.clone()}
-                        on_leave={on_leave.clone()}
-                        on_toggle_pause={on_toggle_pause.clone()}
-                        on_toggle_inactive_hidden={on_toggle_inactive_hidden.clone()}
-                    />
+                }
 
-                    // Session views
-                    <div class={classes!("session-views-container", if keyboard_nav.nav_mode { Some("nav-mode") } else { None })}>
-                        {
-                            active_sessions.iter().enumerate().map(|(index, session)| {
-                                let is_focused = index == *focused_index;
-                                let is_activated = activated_sessions.contains(&session.id);
-                                if is_activated {
-                                    html! {
-                                        <div
-                                            key={session.id.to_string()}
-                                            class={classes!("session-view-wrapper", if is_focused { "focused" } else { "hidden" })}
-                                        >
-                                            <SessionView
-                                                session={session.clone()}
-                                                focused={is_focused}
-                                                on_awaiting_change={on_awaiting_change.clone()}
-                                                on_cost_change={on_cost_change.clone()}
-                                                on_connected_change={on_connected_change.clone()}
-                                                on_message_sent={on_message_sent.clone()}
-                                                on_branch_change={on_branch_change.clone()}
-                                                voice_enabled={*voice_enabled}
-                                            />
-                                        </div>
-                                    }
-                                } else {
-                                    html! {
-                                        <div
-                                            key={session.id.to_string()}
-                                            class="session-view-wrapper hidden"
-                                        />
-                                    }
-                                }
-                            }).collect::<Html>()
-                        }
+                // Offline banner
+                if offline && !loading {
+                    <div class="offline-banner">
+                        { "Offline — showing the last known sessions" }
+                    </div>
+                }
+
+                // Reconnection banner
+                if disconnected_count > 0 && !loading {
+                    <div class="reconnection-banner">
+                        <span class="reconnection-spinner">{ "↻" }</span>
+                        <span class="reconnection-text">
+                            { if disconnected_count == 1 {
+                                "Reconnecting...".to_string()
+                            } else {
+                                format!("{} sessions reconnecting...", disconnected_count)
+                            }}
+                        </span>
                     </div>
+                }
 
-                    // Keyboard hints
-                    <div class={classes!("keyboard-hints", if keyboard_nav.nav_mode { Some("nav-mode") } else { None })}>
-                        <div class="hints-content">
-                            {
-                                if keyboard_nav.nav_mode {
-                                    html! {
-                                        <>
-                                            <span class="mode-indicator">{ "NAV" }</span>
-                                            <span>{ "↑↓ or jk = navigate" }</span>
-                                            <span>{ "1-9 = select" }</span>
-                                            <span>{ "w = next waiting" }</span>
-                                            <span>{ "Enter/Esc = edit mode" }</span>
-                                        </>
-                                    }
-                                } else {
-                                    html! {
-                                        <>
-                                            <span>{ "Esc = nav mode" }</span>
-                                            <span>{ "Shift+Tab = next (skip paused)" }</span>
-                                            if *voice_enabled {
-                                                <span>{ "Ctrl+M = voice" }</span>
+                if loading {
+                    <div class="loading">
+                        <div class="spinner"></div>
+                        <p>{ "Loading sessions..." }</p>
+                    </div>
+                } else if active_sessions.is_empty() {
+                    <div class="onboarding-container">
+                        <div class="onboarding-content">
+                            <h2>{ "No Sessions Connected" }</h2>
+                            <OnboardingChecklist
+                                is_admin={*is_admin}
+                                on_setup={{
+                                    let store = store.clone();
+                                    Callback::from(move |_| store.dispatch(DashboardAction::ShowNewSession(true)))
+                                }}
+                            />
+                        </div>
+                    </div>
+                } else {
+                    <>
+                        // Session Rail
+                        <SessionRail
+                            sessions={active_sessions.clone()}
+                            session_costs={session_costs.clone()}
+                            nav_mode={keyboard_nav.nav_mode}
+                            on_select={on_select_session.clone()}
+                        />
+
+                        // Session views
+                        <ContextProvider<RenderLimits> context={*render_limits}>
+                            <div class={classes!("session-views-container", if keyboard_nav.nav_mode { Some("nav-mode") } else { None })}>
+                                {
+                                    active_sessions.iter().enumerate().map(|(index, session)| {
+                                        let is_focused = index == store.focused_index;
+                                        let is_activated = store.activated.contains(&session.id);
+                                        if is_activated {
+                                            html! {
+                                                <div
+                                                    key={session.id.to_string()}
+                                                    class={classes!("session-view-wrapper", if is_focused { "focused" } else { "hidden" })}
+                                                >
+                                                    <SessionView
+                                                        session={session.clone()}
+                                                        focused={is_focused}
+                                                        on_branch_change={on_branch_change.clone()}
+                                                        voice_enabled={*voice_enabled}
+                                                        voice_language={(*voice_language).clone()}
+                                                        voice_auto_language={*voice_auto_language}
+                                                        on_voice_language_change={on_voice_language_change.clone()}
+                                                        current_user_id={*current_user_id}
+                                                        is_admin={*is_admin}
+                                                        issue_report_url={(*issue_report_url).clone()}
+                                                    />
+                                                </div>
+                                            }
+                                        } else {
+                                            html! {
+                                                <div
+                                                    key={session.id.to_string()}
+                                                    class="session-view-wrapper hidden"
+                                                />
                                             }
-                                            <span>{ "Enter = send" }</span>
-                                        </>
+                                        }
+                                    }).collect::<Html>()
+                                }
+                            </div>
+                        </ContextProvider<RenderLimits>>
+
+                        // Keyboard hints
+                        <div class={classes!("keyboard-hints", if keyboard_nav.nav_mode { Some("nav-mode") } else { None })}>
+                            <div class="hints-content">
+                                {
+                                    if keyboard_nav.nav_mode {
+                                        html! {
+                                            <>
+                                                <span class="mode-indicator">{ "NAV" }</span>
+                                                <span>{ "↑↓ or jk = navigate" }</span>
+                                                <span>{ "1-9 = select" }</span>
+                                                <span>{ "w = next waiting" }</span>
+                                                <span>{ "Enter/Esc = edit mode" }</span>
+                                            </>
+                                        }
+                                    } else {
+                                        html! {
+                                            <>
+                                                <span>{ "Esc = nav mode" }</span>
+                                                <span>{ "Shift+Tab = next (skip paused)" }</span>
+                                                if *voice_enabled {
+                                                    <span>{ "Ctrl+M = voice" }</span>
+                                                }
+                                                <span>{ "Ctrl+F = find" }</span>
+                                                <span>{ "Enter = send" }</span>
+                                            </>
+                                        }
                                     }
                                 }
+                            </div>
+                            if let Some(session) = store.focused_session(&active_sessions) {
+                                <button
+                                    type="button"
+                                    class="bug-report-link"
+                                    onclick={{
+                                        let store = store.clone();
+                                        let session_id = session.id;
+                                        Callback::from(move |_: MouseEvent| {
+                                            store.dispatch(DashboardAction::ShowPermissionHistory(Some(session_id)))
+                                        })
+                                    }}
+                                >
+                                    { "🛡 Permission History" }
+                                </button>
                             }
+                            <button
+                                type="button"
+                                class="bug-report-link"
+                                onclick={{
+                                    let store = store.clone();
+                                    Callback::from(move |_: MouseEvent| store.dispatch(DashboardAction::ShowReport(true)))
+                                }}
+                            >
+                                { "🐛 Report a Bug" }
+                            </button>
                         </div>
-                        <a
-                            href="https://github.com/meawoppl/claude-code-portal/issues/new"
-                            target="_blank"
-                            rel="noopener noreferrer"
-                            class="bug-report-link"
-                        >
-                            { "🐛 Report a Bug" }
-                        </a>
-                    </div>
-                </>
-            }
+                    </>
+                }
 
-            // Leave confirmation modal
-            {
-                if let Some(session_id) = *pending_leave {
-                    let session_name = sessions.iter()
-                        .find(|s| s.id == session_id)
-                        .map(|s| utils::extract_folder(&s.working_directory))
-                        .unwrap_or("this session");
+                if store.show_report {
+                    <ReportDialog
+                        issue_url={(*issue_report_url).clone()}
+                        on_close={{
+                            let store = store.clone();
+                            Callback::from(move |_| store.dispatch(DashboardAction::ShowReport(false)))
+                        }}
+                    />
+                }
 
-                    html! {
-                        <div class="modal-overlay" onclick={on_cancel_leave.clone()}>
-                            <div class="modal-content delete-confirm" onclick={Callback::from(|e: MouseEvent| e.stop_propagation())}>
-                                <h2>{ "Leave Session?" }</h2>
-                                <p>{ format!("Are you sure you want to leave \"{}\"?", session_name) }</p>
-                                <p class="modal-warning">{ "You will need to be re-invited to access this session again." }</p>
-                                <div class="modal-actions">
-                                    <button class="modal-cancel" onclick={on_cancel_leave.clone()}>{ "Cancel" }</button>
-                                    <button class="modal-confirm" onclick={on_confirm_leave.clone()}>{ "Leave" }</button>
+                if let Some(session_id) = store.permission_history {
+                    <PermissionHistoryDialog
+                        {session_id}
+                        is_owner={sessions.iter().any(|s| s.id == session_id && s.my_role == "owner")}
+                        on_close={{
+                            let store = store.clone();
+                            Callback::from(move |_| store.dispatch(DashboardAction::ShowPermissionHistory(None)))
+                        }}
+                    />
+                }
+
+                // Leave confirmation modal
+                {
+                    if let Some(session_id) = store.pending_leave {
+                        let session_name = sessions.iter()
+                            .find(|s| s.id == session_id)
+                            .map(|s| utils::extract_folder(&s.working_directory))
+                            .unwrap_or("this session");
+
+                        html! {
+                            <div class="modal-overlay" onclick={on_cancel_leave.clone()}>
+                                <div class="modal-content delete-confirm" onclick={Callback::from(|e: MouseEvent| e.stop_propagation())}>
+                                    <h2>{ "Leave Session?" }</h2>
+                                    <p>{ format!("Are you sure you want to leave \"{}\"?", session_name) }</p>
+                                    <p class="modal-warning">{ "You will need to be re-invited to access this session again." }</p>
+                                    <div class="modal-actions">
+                                        <button class="modal-cancel" onclick={on_cancel_leave.clone()}>{ "Cancel" }</button>
+                                        <button class="modal-confirm" onclick={on_confirm_leave.clone()}>{ "Leave" }</button>
+                                    </div>
                                 </div>
                             </div>
-                        </div>
+                        }
+                    } else {
+                        html! {}
                     }
-                } else {
-                    html! {}
                 }
-            }
-        </div>
+            </div>
+        </ContextProvider<DashboardStore>>
     }
 }
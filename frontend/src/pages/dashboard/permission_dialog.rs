@@ -1,12 +1,16 @@
 //! Permission dialog components for tool authorization and user questions
 
+use shared::{PermissionRule, RuleDecision, RuleScope};
 use std::collections::{HashMap, HashSet};
-use web_sys::KeyboardEvent;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlInputElement, HtmlSelectElement, KeyboardEvent};
 use yew::prelude::*;
 
+use crate::components::render_permission_diff;
+
 use super::types::{
-    format_permission_input, parse_ask_user_question, AskUserQuestionInput, PendingPermission,
-    QuestionAnswers,
+    format_permission_input, parse_ask_user_question, permission_choices, AskUserQuestionInput,
+    PendingPermission, PermissionChoice, QuestionAnswers,
 };
 
 /// Props for the PermissionDialog component
@@ -24,6 +28,21 @@ pub struct PermissionDialogProps {
     /// Key is question index, value is the selected answer
     #[prop_or_default]
     pub question_answers: QuestionAnswers,
+    /// Requests in this one's group, including it (see `PermissionGroup`)
+    #[prop_or(1)]
+    pub group_size: usize,
+    /// Description of the group, e.g. "Read under src/"
+    #[prop_or_default]
+    pub group_label: Option<String>,
+    /// Other requests waiting behind this one
+    #[prop_or_default]
+    pub queued: usize,
+    /// Scope of the "always allow/deny this" rule, if ticked
+    #[prop_or_default]
+    pub remember: Option<RuleScope>,
+    /// Callback when the remember checkbox or its scope changes
+    #[prop_or_default]
+    pub on_remember_change: Callback<Option<RuleScope>>,
     /// Reference to the dialog for focus management
     pub dialog_ref: NodeRef,
     /// Callback when user navigates up
@@ -64,6 +83,7 @@ pub fn permission_dialog(props: &PermissionDialogProps) -> Html {
 fn render_standard_permission(props: &PermissionDialogProps) -> Html {
     let perm = &props.permission;
     let input_preview = format_permission_input(&perm.tool_name, &perm.input);
+    let diff = render_permission_diff(&perm.tool_name, &perm.input);
     let has_suggestions = !perm.permission_suggestions.is_empty();
 
     let on_select_up = props.on_select_up.clone();
@@ -87,15 +107,23 @@ fn render_standard_permission(props: &PermissionDialogProps) -> Html {
     });
 
     // Build options list
-    let options: Vec<(&str, &str)> = if has_suggestions {
-        vec![
-            ("allow", "Allow"),
-            ("remember", "Allow & Remember"),
-            ("deny", "Deny"),
-        ]
-    } else {
-        vec![("allow", "Allow"), ("deny", "Deny")]
-    };
+    let group_label = props.group_label.as_deref().unwrap_or("similar requests");
+    let options: Vec<(&str, String)> = permission_choices(has_suggestions, props.group_size)
+        .into_iter()
+        .map(|choice| match choice {
+            PermissionChoice::Allow => ("allow", "Allow".to_string()),
+            PermissionChoice::AllowAndRemember => ("remember", "Allow & Remember".to_string()),
+            PermissionChoice::AllowGroup => (
+                "allow",
+                format!("Allow all {} ({})", props.group_size, group_label),
+            ),
+            PermissionChoice::AllowGroupAndRule => (
+                "remember",
+                format!("Allow all & always allow {} this session", group_label),
+            ),
+            PermissionChoice::Deny => ("deny", "Deny".to_string()),
+        })
+        .collect();
 
     html! {
         <div
@@ -107,6 +135,9 @@ fn render_standard_permission(props: &PermissionDialogProps) -> Html {
             <div class="permission-header">
                 <span class="permission-icon">{ "⚠️" }</span>
                 <span class="permission-title">{ "Permission Required" }</span>
+                if props.queued > 0 {
+                    <span class="permission-queued">{ format!("+{} waiting", props.queued) }</span>
+                }
             </div>
             <div class="permission-body">
                 <div class="permission-tool">
@@ -116,7 +147,9 @@ fn render_standard_permission(props: &PermissionDialogProps) -> Html {
                 <div class="permission-input">
                     <pre>{ input_preview }</pre>
                 </div>
+                { diff.unwrap_or_default() }
             </div>
+            { render_remember_rule(props) }
             <div class="permission-options">
                 {
                     options.iter().enumerate().map(|(i, (class, label))| {
@@ -134,7 +167,7 @@ fn render_standard_permission(props: &PermissionDialogProps) -> Html {
                         html! {
                             <div class={item_class} {onclick}>
                                 <span class="option-cursor">{ cursor }</span>
-                                <span class="option-label">{ *label }</span>
+                                <span class="option-label">{ label }</span>
                             </div>
                         }
                     }).collect::<Html>()
@@ -147,6 +180,58 @@ fn render_standard_permission(props: &PermissionDialogProps) -> Html {
     }
 }
 
+/// The "always allow/deny this" checkbox and how long to remember it for
+fn render_remember_rule(props: &PermissionDialogProps) -> Html {
+    let perm = &props.permission;
+    let rule = PermissionRule::for_request(&perm.tool_name, &perm.input, RuleDecision::Allow);
+    let scope = props.remember.unwrap_or(RuleScope::Session);
+
+    let on_change = props.on_remember_change.clone();
+    let on_toggle = Callback::from(move |e: Event| {
+        let checked = e
+            .target()
+            .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+            .is_some_and(|input| input.checked());
+        on_change.emit(checked.then_some(scope));
+    });
+    let on_change = props.on_remember_change.clone();
+    let on_scope = Callback::from(move |e: Event| {
+        let persistent = e
+            .target()
+            .and_then(|t| t.dyn_into::<HtmlSelectElement>().ok())
+            .is_some_and(|select| select.value() == "persistent");
+        on_change.emit(Some(if persistent {
+            RuleScope::Persistent
+        } else {
+            RuleScope::Session
+        }));
+    });
+    // Keep Space and Enter on these controls from answering the request
+    let stop_keys = Callback::from(|e: KeyboardEvent| e.stop_propagation());
+
+    html! {
+        <div class="permission-remember" onkeydown={stop_keys}>
+            <label>
+                <input
+                    type="checkbox"
+                    checked={props.remember.is_some()}
+                    onchange={on_toggle}
+                />
+                { " Always allow/deny " }
+                <code>{ rule.describe() }</code>
+            </label>
+            <select onchange={on_scope} disabled={props.remember.is_none()}>
+                <option value="session" selected={scope == RuleScope::Session}>
+                    { "for this session" }
+                </option>
+                <option value="persistent" selected={scope == RuleScope::Persistent}>
+                    { "in this directory" }
+                </option>
+            </select>
+        </div>
+    }
+}
+
 /// Render the AskUserQuestion specialized UI - supports multiple questions
 fn render_ask_user_question(props: &PermissionDialogProps, parsed: &AskUserQuestionInput) -> Html {
     let total_questions = parsed.questions.len();
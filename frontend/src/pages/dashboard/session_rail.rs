@@ -1,38 +1,37 @@
 //! SessionRail component - Horizontal carousel of session pills
 
+use super::store::{use_dashboard_store, DashboardAction};
+use crate::components::CiBadge;
 use crate::utils;
-use shared::SessionInfo;
-use std::collections::{HashMap, HashSet};
+use shared::{SessionHealth, SessionInfo};
+use std::collections::HashMap;
 use uuid::Uuid;
 use web_sys::{Element, HtmlElement, WheelEvent};
 use yew::prelude::*;
 
 /// Props for the SessionRail component
+///
+/// Focus, waiting, connection and pause state come from the dashboard store.
 #[derive(Properties, PartialEq)]
 pub struct SessionRailProps {
     pub sessions: Vec<SessionInfo>,
-    pub focused_index: usize,
-    pub awaiting_sessions: HashSet<Uuid>,
-    pub paused_sessions: HashSet<Uuid>,
-    pub inactive_hidden: bool,
     pub session_costs: HashMap<Uuid, f64>,
-    pub connected_sessions: HashSet<Uuid>,
     pub nav_mode: bool,
     pub on_select: Callback<usize>,
-    pub on_leave: Callback<Uuid>,
-    pub on_toggle_pause: Callback<Uuid>,
-    pub on_toggle_inactive_hidden: Callback<MouseEvent>,
 }
 
 /// SessionRail - Horizontal carousel of session pills
 #[function_component(SessionRail)]
 pub fn session_rail(props: &SessionRailProps) -> Html {
+    let store = use_dashboard_store();
     let rail_ref = use_node_ref();
+    // Session whose health details are open
+    let health_open = use_state(|| None::<Uuid>);
 
     // Scroll focused session into view
     {
         let rail_ref = rail_ref.clone();
-        let focused_index = props.focused_index;
+        let focused_index = store.focused_index;
         use_effect_with(focused_index, move |_| {
             if let Some(rail) = rail_ref.cast::<Element>() {
                 if let Some(child) = rail.children().item(focused_index as u32) {
@@ -63,10 +62,10 @@ pub fn session_rail(props: &SessionRailProps) -> Html {
                        session: &SessionInfo,
                        display_number: Option<usize>|
      -> Html {
-        let is_focused = index == props.focused_index;
-        let is_awaiting = props.awaiting_sessions.contains(&session.id);
-        let is_paused = props.paused_sessions.contains(&session.id);
-        let is_connected = props.connected_sessions.contains(&session.id);
+        let is_focused = index == store.focused_index;
+        let is_awaiting = store.awaiting.contains(&session.id);
+        let is_paused = store.is_paused(&session.id);
+        let is_connected = store.connected.contains(&session.id);
         let cost = props.session_costs.get(&session.id).copied().unwrap_or(0.0);
 
         let on_click = {
@@ -75,20 +74,30 @@ pub fn session_rail(props: &SessionRailProps) -> Html {
         };
 
         let on_pause = {
-            let on_toggle_pause = props.on_toggle_pause.clone();
+            let store = store.clone();
             let session_id = session.id;
             Callback::from(move |e: MouseEvent| {
                 e.stop_propagation();
-                on_toggle_pause.emit(session_id);
+                store.dispatch(DashboardAction::TogglePaused(session_id));
             })
         };
 
         let on_leave = {
-            let on_leave = props.on_leave.clone();
+            let store = store.clone();
             let session_id = session.id;
             Callback::from(move |e: MouseEvent| {
                 e.stop_propagation();
-                on_leave.emit(session_id);
+                store.dispatch(DashboardAction::SetPendingLeave(Some(session_id)));
+            })
+        };
+
+        let on_health = {
+            let health_open = health_open.clone();
+            let session_id = session.id;
+            Callback::from(move |e: MouseEvent| {
+                e.stop_propagation();
+                let open = (*health_open != Some(session_id)).then_some(session_id);
+                health_open.set(open);
             })
         };
 
@@ -107,7 +116,17 @@ pub fn session_rail(props: &SessionRailProps) -> Html {
             },
         );
 
-        let hostname = utils::extract_hostname(&session.session_name);
+        let host_info = session.host_info.as_ref();
+        let hostname = host_info
+            .and_then(|host| host.hostname.clone())
+            .unwrap_or_else(|| utils::extract_hostname(&session.session_name).to_string());
+        let dirty = host_info.and_then(|host| host.git_dirty) == Some(true);
+        let pill_title = match host_info.map(|host| host.summary()) {
+            Some(summary) if !summary.is_empty() => {
+                format!("{}\n{}", session.session_name, summary)
+            }
+            _ => session.session_name.clone(),
+        };
         let folder = utils::extract_folder(&session.working_directory);
 
         let connection_class = if is_connected {
@@ -137,17 +156,34 @@ pub fn session_rail(props: &SessionRailProps) -> Html {
                 <span class={connection_class}>
                     { if is_connected { "●" } else { "○" } }
                 </span>
-                <span class="pill-name" title={session.session_name.clone()}>
+                if let Some(health) = &session.health {
+                    <span
+                        class={classes!("pill-health", health.level.as_str())}
+                        onclick={on_health}
+                        title={format!("Health {}/100", health.score)}
+                    />
+                }
+                <span class="pill-name" title={pill_title}>
                     <span class="pill-folder">{ folder }</span>
                     <span class="pill-hostname">{ hostname }</span>
                     {
                         if let Some(ref branch) = session.git_branch {
-                            html! { <span class="pill-branch">{ branch }</span> }
+                            html! {
+                                <span class="pill-branch">
+                                    { branch }
+                                    if dirty {
+                                        <span class="pill-dirty" title="Uncommitted changes">{ "*" }</span>
+                                    }
+                                </span>
+                            }
                         } else {
                             html! {}
                         }
                     }
                 </span>
+                if let Some(ci) = &session.ci {
+                    <CiBadge status={ci.clone()} compact=true />
+                }
                 {
                     if cost > 0.0 {
                         html! { <span class="pill-cost">{ format!("${:.2}", cost) }</span> }
@@ -196,7 +232,7 @@ pub fn session_rail(props: &SessionRailProps) -> Html {
     // Disconnected sessions remain visible - only explicitly paused sessions go in the hidden section
     let (visible_indices, paused_indices): (Vec<_>, Vec<_>) =
         props.sessions.iter().enumerate().partition(|(_, session)| {
-            let is_paused = props.paused_sessions.contains(&session.id);
+            let is_paused = store.is_paused(&session.id);
             !is_paused
         });
 
@@ -207,7 +243,21 @@ pub fn session_rail(props: &SessionRailProps) -> Html {
     // When paused section is shown, all sessions get numbers in display order
     let visible_count = visible_indices.len();
 
-    html! {
+    let health_details = (*health_open).and_then(|id| {
+        let session = props.sessions.iter().find(|s| s.id == id)?;
+        Some((session, session.health.as_ref()?))
+    });
+    let close_health = {
+        let health_open = health_open.clone();
+        Callback::from(move |_| health_open.set(None))
+    };
+
+    let on_toggle_inactive_hidden = {
+        let store = store.clone();
+        Callback::from(move |_: MouseEvent| store.dispatch(DashboardAction::ToggleInactiveHidden))
+    };
+
+    let rail = html! {
         <div class="session-rail" ref={rail_ref} onwheel={on_wheel}>
             // Visible sessions (not paused) - always get numbers starting from 0
             { visible_indices.iter().enumerate().map(|(display_idx, (index, session))| {
@@ -219,13 +269,13 @@ pub fn session_rail(props: &SessionRailProps) -> Html {
                 if paused_count > 0 {
                     let toggle_class = classes!(
                         "session-rail-divider",
-                        if props.inactive_hidden { Some("collapsed") } else { None }
+                        if store.inactive_hidden { Some("collapsed") } else { None }
                     );
                     html! {
-                        <div class={toggle_class} onclick={props.on_toggle_inactive_hidden.clone()}>
+                        <div class={toggle_class} onclick={on_toggle_inactive_hidden}>
                             <span class="divider-line"></span>
-                            <button class="divider-toggle" title={if props.inactive_hidden { "Show paused sessions" } else { "Hide paused sessions" }}>
-                                { if props.inactive_hidden {
+                            <button class="divider-toggle" title={if store.inactive_hidden { "Show paused sessions" } else { "Hide paused sessions" }}>
+                                { if store.inactive_hidden {
                                     format!("▶ {}", paused_count)
                                 } else {
                                     "◀".to_string()
@@ -241,7 +291,7 @@ pub fn session_rail(props: &SessionRailProps) -> Html {
             // Paused sessions (hidden when collapsed)
             // When shown, continue numbering from where visible sessions left off
             {
-                if !props.inactive_hidden {
+                if !store.inactive_hidden {
                     paused_indices.iter().enumerate().map(|(display_idx, (index, session))| {
                         render_pill(*index, session, Some(visible_count + display_idx))
                     }).collect::<Html>()
@@ -250,5 +300,40 @@ pub fn session_rail(props: &SessionRailProps) -> Html {
                 }
             }
         </div>
+    };
+
+    html! {
+        <div class="session-rail-wrapper">
+            { rail }
+            if let Some((session, health)) = health_details {
+                { render_health_popover(session, health, close_health) }
+            }
+        </div>
+    }
+}
+
+/// What lowered a session's health score
+fn render_health_popover(
+    session: &SessionInfo,
+    health: &SessionHealth,
+    on_close: Callback<MouseEvent>,
+) -> Html {
+    html! {
+        <div class={classes!("health-popover", health.level.as_str())}>
+            <div class="health-popover-header">
+                <span class="health-popover-title">
+                    { utils::extract_folder(&session.working_directory) }
+                </span>
+                <span class="health-score">{ format!("{}/100", health.score) }</span>
+                <button class="health-popover-close" onclick={on_close} title="Close">{ "×" }</button>
+            </div>
+            if health.warnings.is_empty() {
+                <p class="health-ok">{ "Nothing needs attention." }</p>
+            } else {
+                <ul class="health-warnings">
+                    { for health.warnings.iter().map(|warning| html! { <li>{ warning }</li> }) }
+                </ul>
+            }
+        </div>
     }
 }
@@ -1,48 +1,141 @@
 //! SessionView component - Main terminal view for a single session
 
-use crate::components::{group_messages, MessageGroupRenderer, VoiceInput};
-use crate::utils;
+use crate::components::{
+    group_messages, CiBadge, ForkDialog, InputAssist, MessageGroup, MessageGroupRenderer,
+    MessageReactions, OutcomePicker, PresenceAvatars, RelatedSessions, ReportDialog, TurnFeedback,
+    UndoDialog, VoiceInput, VoiceLanguagePicker, VoiceSettings,
+};
+use crate::{mobile, offline, utils};
 use gloo::timers::callback::Timeout;
 use gloo_net::http::Request;
-use shared::{ProxyMessage, SendMode, SessionInfo};
-use std::cell::RefCell;
+use serde::{Deserialize, Serialize};
+use shared::artifacts::write_tool_uses;
+use shared::paste::is_large_paste;
+use shared::prompt_lint::lint_prompt;
+use shared::prompt_size;
+use shared::{
+    last_turn, AnnotationInfo, ArtifactInfo, AttachedInput, BudgetLevel, BudgetStatus,
+    IncidentInfo, InputAttachment, LastTurn, MessageReactionsInfo, PermissionRule, PresenceViewer,
+    ProxyMessage, ReactionSummary, RememberedRule, RuleDecision, RuleScope, SendMode, SessionInfo,
+    SessionLifecycle, SessionOutcome, SessionStatus, SnapshotSummary, TraceContext,
+    TurnFeedbackInfo, TurnInfo,
+};
+use std::cell::{Cell, RefCell};
 use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use uuid::Uuid;
 use wasm_bindgen::closure::Closure;
-use wasm_bindgen::JsCast;
+use wasm_bindgen::{JsCast, JsValue};
 use wasm_bindgen_futures::spawn_local;
-use web_sys::{Element, HtmlTextAreaElement, KeyboardEvent};
+use web_sys::{Element, HtmlInputElement, HtmlTextAreaElement, KeyboardEvent, TouchEvent};
 use yew::prelude::*;
 
 use super::history::CommandHistory;
+use super::search::{self, SessionSearch};
 use super::types::{PendingPermission, QuestionAnswers, WsSender, MAX_MESSAGES_PER_SESSION};
 use super::websocket::{connect_websocket, send_message, WsEvent};
 use crate::pages::dashboard::permission_dialog::PermissionDialog;
+use crate::pages::dashboard::store::{DashboardAction, DashboardStore};
 use crate::pages::dashboard::types::{
-    calculate_backoff, parse_ask_user_question, MessagesResponse,
+    calculate_backoff, parse_ask_user_question, permission_choices, MessagesResponse,
+    PermissionChoice, PermissionGroup,
 };
 
+/// A transcript as kept in the offline cache
+#[derive(Serialize, Deserialize)]
+struct CachedTranscript {
+    messages: Vec<String>,
+    ids: Vec<Option<Uuid>>,
+    last_seq: Option<u64>,
+}
+
 /// Props for the SessionView component
+///
+/// Whether the session awaits the user or is connected is reported to the
+/// dashboard store, when there is one.
 #[derive(Properties, PartialEq)]
 pub struct SessionViewProps {
     pub session: SessionInfo,
     pub focused: bool,
-    pub on_awaiting_change: Callback<(Uuid, bool)>,
-    pub on_cost_change: Callback<(Uuid, f64)>,
-    pub on_connected_change: Callback<(Uuid, bool)>,
-    pub on_message_sent: Callback<Uuid>,
     pub on_branch_change: Callback<(Uuid, Option<String>)>,
     #[prop_or(false)]
     pub voice_enabled: bool,
+    /// The user's voice input language, or "auto"
+    #[prop_or(AttrValue::Static(shared::voice::DEFAULT_LANGUAGE))]
+    pub voice_language: AttrValue,
+    /// Whether the speech provider can detect the language
+    #[prop_or(false)]
+    pub voice_auto_language: bool,
+    /// Called with the language once the user's choice is saved
+    #[prop_or_default]
+    pub on_voice_language_change: Callback<String>,
+    /// The logged-in user, used to tell whether we hold input control
+    #[prop_or_default]
+    pub current_user_id: Option<Uuid>,
+    #[prop_or(false)]
+    pub is_admin: bool,
+    /// Destination for "Report a problem"
+    #[prop_or(AttrValue::Static(shared::DEFAULT_ISSUE_REPORT_URL))]
+    pub issue_report_url: AttrValue,
 }
 
 /// Messages for the SessionView component
 pub enum SessionViewMsg {
     SendInput,
+    /// Text longer than `LARGE_PASTE_LINES` was pasted into the input
+    LargePaste(String),
+    /// Move the large paste out of the input into an attachment
+    AttachPaste,
+    /// Leave the large paste in the input
+    KeepPasteInline,
+    /// Drop an attachment before sending
+    RemoveAttachment(usize),
     UpdateInput(String),
-    LoadHistory(Vec<String>, Option<String>),
-    ReceivedOutput(String),
+    LoadHistory(Vec<String>, Vec<Option<Uuid>>, Option<u64>),
+    /// History came from the offline cache, saved at this time (ms since
+    /// the epoch)
+    ShowingCached(f64),
+    ReceivedOutput(String, Option<Uuid>, Option<u64>),
+    /// Reactions for the whole session, loaded alongside history
+    ReactionsLoaded(Vec<MessageReactionsInfo>),
+    /// Reactions on a single message changed
+    ReactionsChanged(Uuid, Vec<ReactionSummary>),
+    /// Show or hide a message's metadata (phones, on swipe)
+    ToggleMeta(Uuid),
+    /// Annotations external tools attached to the session's messages
+    AnnotationsLoaded(Vec<AnnotationInfo>),
+    /// The current user's turn feedback for the session
+    FeedbackLoaded(Vec<TurnFeedbackInfo>),
+    /// Feedback on a result message was saved (None when removed)
+    FeedbackChanged(Uuid, Option<TurnFeedbackInfo>),
+    /// The session's recorded turns
+    TurnsLoaded(Vec<TurnInfo>),
+    /// Ask the current driver for input control
+    RequestControl,
+    /// Pass control to another viewer, or release it
+    HandOffControl(Option<Uuid>),
+    /// Admin override: take control from the current driver
+    TakeControl,
+    DismissControlRequest(Uuid),
+    /// The session's outcome label was saved
+    OutcomeChanged(Option<SessionOutcome>),
+    DismissOutcomePrompt,
+    /// Diagnostic bundles recorded for the session, newest first
+    IncidentsLoaded(Vec<IncidentInfo>),
+    /// The snapshot the proxy last mirrored to the backend
+    SnapshotLoaded(SnapshotSummary),
+    BudgetLoaded(BudgetStatus),
+    /// Files written by Write tool uses so far
+    ArtifactsLoaded(Vec<ArtifactInfo>),
+    /// Open the report dialog, optionally for a message's raw JSON
+    OpenReport(Option<String>),
+    CloseReport,
+    /// Open the fork dialog for a stored message, or close it
+    SetForkAt(Option<Uuid>),
+    /// Ask to confirm undoing Claude's last turn, or close the confirmation
+    SetUndo(Option<LastTurn>),
+    /// Interrupt Claude if it's mid-turn, then ask it to revert the turn
+    ConfirmUndo,
     WebSocketConnected(WsSender),
     WebSocketError(String),
     AttemptReconnect,
@@ -51,6 +144,10 @@ pub enum SessionViewMsg {
     PermissionRequest(PendingPermission),
     ApprovePermission,
     ApprovePermissionAndRemember,
+    /// Approve the current request and the queued ones in its group,
+    /// optionally adding a session rule for the group
+    ApprovePermissionGroup(bool),
+    SetRememberRule(Option<RuleScope>),
     DenyPermission,
     PermissionSelectUp,
     PermissionSelectDown,
@@ -64,6 +161,8 @@ pub enum SessionViewMsg {
     VoiceInterimTranscription(String),
     VoiceError(String),
     ToggleVoice,
+    /// Space pressed or released for push-to-talk
+    PushToTalk(bool),
     SetQuestionAnswer(usize, String),
     ToggleQuestionOption(usize, usize),
     SubmitAllAnswers(QuestionAnswers),
@@ -77,14 +176,65 @@ pub enum SessionViewMsg {
     SetSendMode(SendMode),
     /// Send with wiggum mode specifically
     SendWiggum,
+    /// Open (or refocus) the in-session search box
+    OpenSearch,
+    CloseSearch,
+    SearchInput(String),
+    SearchNext,
+    SearchPrevious,
 }
 
 /// SessionView - Main terminal view for a single session
 pub struct SessionView {
     messages: Vec<String>,
+    /// Stored message IDs, parallel to `messages` (None for local-only messages)
+    message_ids: Vec<Option<Uuid>>,
+    reactions: HashMap<Uuid, Vec<ReactionSummary>>,
+    annotations: Vec<AnnotationInfo>,
+    /// Messages whose metadata was revealed by a swipe, by anchor message
+    meta_revealed: HashSet<Uuid>,
+    /// Where the touch in progress on the message list began
+    touch_origin: Rc<Cell<Option<(i32, i32)>>>,
+    feedback: HashMap<Uuid, TurnFeedbackInfo>,
+    /// Recorded turns, by the result message that ended each
+    turns: HashMap<Uuid, TurnInfo>,
+    viewers: Vec<PresenceViewer>,
+    /// Pending requests for control from other viewers (user ID, display name)
+    control_requests: Vec<(Uuid, String)>,
+    outcome: Option<SessionOutcome>,
+    outcome_prompt_dismissed: bool,
+    incidents: Vec<IncidentInfo>,
+    snapshot: Option<SnapshotSummary>,
+    budget: BudgetStatus,
+    /// Set when the server speaks a different protocol than this page
+    outdated: Option<String>,
+    artifacts: Vec<ArtifactInfo>,
+    /// When the transcript shown was cached, while it's not live
+    cached_at: Option<f64>,
+    /// Set once history shows the session has no prompt yet
+    awaiting_first_prompt: bool,
+    /// What's typed before the first prompt, then the first prompt itself;
+    /// related sessions are suggested from it
+    first_prompt_draft: Option<String>,
+    /// Set while the report dialog is open (with the reported message, if any)
+    report: Option<Option<String>>,
+    /// Message the fork dialog is open for
+    fork_at: Option<Uuid>,
+    /// Set while the undo confirmation is open
+    undo: Option<LastTurn>,
     input_value: String,
+    /// Context in use as of the newest assistant message, in tokens
+    context_used: Option<u64>,
+    /// A large paste still in the input, offered as an attachment
+    large_paste: Option<String>,
+    /// Files to send with the next input
+    attachments: Vec<InputAttachment>,
     ws_connected: bool,
     ws_sender: Option<WsSender>,
+    /// Last measured round trip to the server, in milliseconds
+    latency_ms: Option<u32>,
+    /// Lifecycle state the session's proxy last reported, if it's connected
+    lifecycle: Option<SessionLifecycle>,
     messages_ref: NodeRef,
     input_ref: NodeRef,
     permission_ref: NodeRef,
@@ -95,6 +245,10 @@ pub struct SessionView {
     total_cost: f64,
     cost_flash: bool,
     pending_permission: Option<PendingPermission>,
+    /// Requests that arrived while another was being shown, oldest first
+    queued_permissions: Vec<PendingPermission>,
+    /// Scope of the rule to remember with the answer to the current request
+    remember_rule: Option<RuleScope>,
     permission_selected: usize,
     reconnect_attempt: u32,
     #[allow(dead_code)]
@@ -102,12 +256,26 @@ pub struct SessionView {
     command_history: CommandHistory,
     is_recording: bool,
     interim_transcription: Option<String>,
-    last_message_timestamp: Option<String>,
+    /// Sequence of the newest message shown, for resuming after a reconnect
+    last_seq: Option<u64>,
     voice_button_ref: NodeRef,
+    /// Push-to-talk and silence auto-stop, as set on the settings page
+    voice_settings: VoiceSettings,
+    /// Spellcheck and prompt hints, as set on the settings page
+    input_assist: InputAssist,
+    /// Space is held for push-to-talk
+    voice_held: bool,
     multi_select_options: HashMap<usize, HashSet<usize>>,
     question_answers: QuestionAnswers,
     send_mode: SendMode,
     send_mode_dropdown_open: bool,
+    /// Set while the Ctrl+F search box is open
+    search: Option<SessionSearch>,
+    search_ref: NodeRef,
+    /// Set when the selected match changed, so the next render scrolls to it
+    search_moved: bool,
+    /// Set when the search box was opened, so the next render focuses it
+    focus_search: bool,
 }
 
 impl Component for SessionView {
@@ -117,15 +285,17 @@ impl Component for SessionView {
     fn create(ctx: &Context<Self>) -> Self {
         let link = ctx.link().clone();
         let session_id = ctx.props().session.id;
-        let on_awaiting_change = ctx.props().on_awaiting_change.clone();
+        let store = dashboard_store(ctx);
 
         // Fetch existing messages via REST, then connect WebSocket
         spawn_local(async move {
-            let mut last_message_time: Option<String> = None;
+            let mut last_seq = None;
             let api_endpoint = utils::api_url(&format!("/api/sessions/{}/messages", session_id));
+            let mut loaded = false;
 
             if let Ok(response) = Request::get(&api_endpoint).send().await {
                 if let Ok(data) = response.json::<MessagesResponse>().await {
+                    loaded = true;
                     let is_awaiting = data.messages.last().is_some_and(|msg| {
                         serde_json::from_str::<serde_json::Value>(&msg.content)
                             .ok()
@@ -136,16 +306,92 @@ impl Component for SessionView {
                             })
                             .unwrap_or(false)
                     });
-                    on_awaiting_change.emit((session_id, is_awaiting));
+                    if let Some(store) = &store {
+                        store.dispatch(DashboardAction::SetAwaiting(session_id, is_awaiting));
+                    }
 
-                    last_message_time = data.messages.last().map(|m| m.created_at.clone());
+                    last_seq = data.messages.iter().filter_map(|m| m.seq).max();
 
-                    let messages: Vec<String> =
-                        data.messages.into_iter().map(|m| m.content).collect();
-                    link.send_message(SessionViewMsg::LoadHistory(
-                        messages,
-                        last_message_time.clone(),
-                    ));
+                    let (ids, messages): (Vec<Option<Uuid>>, Vec<String>) = data
+                        .messages
+                        .into_iter()
+                        .map(|m| (Some(m.id), m.content))
+                        .unzip();
+                    link.send_message(SessionViewMsg::LoadHistory(messages, ids, last_seq));
+                }
+            }
+
+            // Offline or the server is unreachable: show what was cached
+            if !loaded {
+                if let Some((json, cached_at)) = offline::load_transcript(session_id).await {
+                    if let Ok(cached) = serde_json::from_str::<CachedTranscript>(&json) {
+                        last_seq = cached.last_seq;
+                        link.send_message(SessionViewMsg::ShowingCached(cached_at));
+                        link.send_message(SessionViewMsg::LoadHistory(
+                            cached.messages,
+                            cached.ids,
+                            cached.last_seq,
+                        ));
+                    }
+                }
+            }
+
+            let reactions_endpoint =
+                utils::api_url(&format!("/api/sessions/{}/reactions", session_id));
+            if let Ok(response) = Request::get(&reactions_endpoint).send().await {
+                if let Ok(reactions) = response.json::<Vec<MessageReactionsInfo>>().await {
+                    link.send_message(SessionViewMsg::ReactionsLoaded(reactions));
+                }
+            }
+
+            let annotations_endpoint =
+                utils::api_url(&format!("/api/sessions/{}/annotations", session_id));
+            if let Ok(response) = Request::get(&annotations_endpoint).send().await {
+                if let Ok(annotations) = response.json::<Vec<AnnotationInfo>>().await {
+                    link.send_message(SessionViewMsg::AnnotationsLoaded(annotations));
+                }
+            }
+
+            let feedback_endpoint =
+                utils::api_url(&format!("/api/sessions/{}/feedback", session_id));
+            if let Ok(response) = Request::get(&feedback_endpoint).send().await {
+                if let Ok(feedback) = response.json::<Vec<TurnFeedbackInfo>>().await {
+                    link.send_message(SessionViewMsg::FeedbackLoaded(feedback));
+                }
+            }
+
+            if let Some(turns) = load_turns(session_id).await {
+                link.send_message(SessionViewMsg::TurnsLoaded(turns));
+            }
+
+            let incidents_endpoint =
+                utils::api_url(&format!("/api/sessions/{}/incidents", session_id));
+            if let Ok(response) = Request::get(&incidents_endpoint).send().await {
+                if let Ok(incidents) = response.json::<Vec<IncidentInfo>>().await {
+                    link.send_message(SessionViewMsg::IncidentsLoaded(incidents));
+                }
+            }
+
+            let snapshot_endpoint =
+                utils::api_url(&format!("/api/sessions/{}/snapshot", session_id));
+            if let Ok(response) = Request::get(&snapshot_endpoint).send().await {
+                if let Ok(snapshot) = response.json::<SnapshotSummary>().await {
+                    link.send_message(SessionViewMsg::SnapshotLoaded(snapshot));
+                }
+            }
+
+            let budget_endpoint = utils::api_url(&format!("/api/sessions/{}/budget", session_id));
+            if let Ok(response) = Request::get(&budget_endpoint).send().await {
+                if let Ok(budget) = response.json::<BudgetStatus>().await {
+                    link.send_message(SessionViewMsg::BudgetLoaded(budget));
+                }
+            }
+
+            let artifacts_endpoint =
+                utils::api_url(&format!("/api/sessions/{}/artifacts", session_id));
+            if let Ok(response) = Request::get(&artifacts_endpoint).send().await {
+                if let Ok(artifacts) = response.json::<Vec<ArtifactInfo>>().await {
+                    link.send_message(SessionViewMsg::ArtifactsLoaded(artifacts));
                 }
             }
 
@@ -154,13 +400,40 @@ impl Component for SessionView {
             let on_event = Callback::from(move |event: WsEvent| {
                 ws_link.send_message(SessionViewMsg::WsEvent(event));
             });
-            connect_websocket(session_id, last_message_time, false, on_event);
+            connect_websocket(session_id, last_seq, false, on_event);
         });
 
         Self {
             messages: vec![],
+            message_ids: vec![],
+            reactions: HashMap::new(),
+            annotations: vec![],
+            meta_revealed: HashSet::new(),
+            touch_origin: Rc::new(Cell::new(None)),
+            feedback: HashMap::new(),
+            turns: HashMap::new(),
+            viewers: vec![],
+            control_requests: vec![],
+            outcome: ctx.props().session.outcome,
+            outcome_prompt_dismissed: false,
+            incidents: vec![],
+            snapshot: None,
+            budget: BudgetStatus::default(),
+            outdated: None,
+            artifacts: vec![],
+            cached_at: None,
+            awaiting_first_prompt: false,
+            first_prompt_draft: None,
+            report: None,
+            fork_at: None,
+            undo: None,
             input_value: String::new(),
+            context_used: None,
+            large_paste: None,
+            attachments: Vec::new(),
             ws_connected: false,
+            latency_ms: None,
+            lifecycle: None,
             ws_sender: None,
             messages_ref: NodeRef::default(),
             input_ref: NodeRef::default(),
@@ -171,22 +444,35 @@ impl Component for SessionView {
             total_cost: 0.0,
             cost_flash: false,
             pending_permission: None,
+            queued_permissions: Vec::new(),
+            remember_rule: None,
             permission_selected: 0,
             reconnect_attempt: 0,
             reconnect_timer: None,
             command_history: CommandHistory::for_session(ctx.props().session.id),
             is_recording: false,
             interim_transcription: None,
-            last_message_timestamp: None,
+            last_seq: None,
             voice_button_ref: NodeRef::default(),
+            voice_settings: VoiceSettings::load(),
+            input_assist: InputAssist::load(),
+            voice_held: false,
             multi_select_options: HashMap::new(),
             question_answers: HashMap::new(),
             send_mode: SendMode::Normal,
             send_mode_dropdown_open: false,
+            search: None,
+            search_ref: NodeRef::default(),
+            search_moved: false,
+            focus_search: false,
         }
     }
 
-    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+    fn changed(&mut self, ctx: &Context<Self>, old_props: &Self::Properties) -> bool {
+        if ctx.props().session.outcome != old_props.session.outcome {
+            self.outcome = ctx.props().session.outcome;
+        }
+
         let now_focused = ctx.props().focused;
         let became_focused = now_focused && !self.was_focused;
         self.was_focused = now_focused;
@@ -213,6 +499,13 @@ impl Component for SessionView {
             }
         }
 
+        if std::mem::take(&mut self.focus_search) {
+            if let Some(input) = self.search_ref.cast::<HtmlInputElement>() {
+                let _ = input.focus();
+                input.select();
+            }
+        }
+
         if let Some(element) = self.messages_ref.cast::<Element>() {
             if first_render {
                 let should_autoscroll = self.should_autoscroll.clone();
@@ -232,9 +525,19 @@ impl Component for SessionView {
                 self.scroll_listener = Some(closure);
             }
 
-            if *self.should_autoscroll.borrow() {
+            if *self.should_autoscroll.borrow() && self.search.is_none() {
                 element.set_scroll_top(element.scroll_height());
             }
+
+            if let Some(search) = &self.search {
+                search::highlight(
+                    &element,
+                    &search.query,
+                    self.current_match_in_slot(),
+                    self.search_moved,
+                );
+                self.search_moved = false;
+            }
         }
     }
 
@@ -242,35 +545,139 @@ impl Component for SessionView {
         match msg {
             SessionViewMsg::WsEvent(event) => self.handle_ws_event(ctx, event),
             SessionViewMsg::UpdateInput(value) => {
+                if self.awaiting_first_prompt {
+                    self.first_prompt_draft = Some(value.clone());
+                }
                 self.input_value = value;
                 true
             }
             SessionViewMsg::SendInput => self.handle_send_input(ctx),
-            SessionViewMsg::LoadHistory(mut messages, last_timestamp) => {
+            SessionViewMsg::LargePaste(text) => {
+                self.large_paste = Some(text);
+                true
+            }
+            SessionViewMsg::AttachPaste => {
+                let Some(text) = self.large_paste.take() else {
+                    return false;
+                };
+                // Leave the input alone if the paste was edited since
+                if let Some(start) = self.input_value.find(&text) {
+                    self.input_value
+                        .replace_range(start..start + text.len(), "");
+                    self.attachments.push(InputAttachment {
+                        name: "paste.txt".to_string(),
+                        content: text,
+                    });
+                }
+                true
+            }
+            SessionViewMsg::KeepPasteInline => {
+                self.large_paste = None;
+                true
+            }
+            SessionViewMsg::RemoveAttachment(index) => {
+                if index < self.attachments.len() {
+                    self.attachments.remove(index);
+                }
+                true
+            }
+            SessionViewMsg::LoadHistory(mut messages, mut ids, last_seq) => {
                 if messages.len() > MAX_MESSAGES_PER_SESSION {
                     let excess = messages.len() - MAX_MESSAGES_PER_SESSION;
                     messages.drain(0..excess);
+                    ids.drain(0..excess);
                 }
+                self.awaiting_first_prompt = !messages.iter().any(|m| {
+                    serde_json::from_str::<serde_json::Value>(m)
+                        .is_ok_and(|v| v.get("type").and_then(|t| t.as_str()) == Some("user"))
+                });
+                self.context_used = messages.iter().rev().find_map(|m| {
+                    serde_json::from_str::<serde_json::Value>(m)
+                        .ok()
+                        .and_then(|v| prompt_size::context_used(&v))
+                });
                 self.messages = messages;
-                self.last_message_timestamp = last_timestamp;
+                self.message_ids = ids;
+                self.last_seq = last_seq;
+                if let Some(search) = &mut self.search {
+                    search.reindex(&self.messages);
+                }
+                self.cache_transcript(ctx);
                 ctx.link().send_message(SessionViewMsg::CheckAwaiting);
                 true
             }
-            SessionViewMsg::ReceivedOutput(output) => self.handle_received_output(ctx, output),
+            SessionViewMsg::ShowingCached(cached_at) => {
+                self.cached_at = Some(cached_at);
+                true
+            }
+            SessionViewMsg::ReceivedOutput(output, message_id, seq) => {
+                self.handle_received_output(ctx, output, message_id, seq)
+            }
+            SessionViewMsg::ReactionsLoaded(reactions) => {
+                self.reactions = reactions
+                    .into_iter()
+                    .map(|info| (info.message_id, info.reactions))
+                    .collect();
+                true
+            }
+            SessionViewMsg::ReactionsChanged(message_id, reactions) => {
+                if reactions.is_empty() {
+                    self.reactions.remove(&message_id);
+                } else {
+                    self.reactions.insert(message_id, reactions);
+                }
+                true
+            }
+            SessionViewMsg::ToggleMeta(message_id) => {
+                if !self.meta_revealed.remove(&message_id) {
+                    self.meta_revealed.insert(message_id);
+                }
+                true
+            }
+            SessionViewMsg::AnnotationsLoaded(annotations) => {
+                self.annotations = annotations;
+                true
+            }
+            SessionViewMsg::FeedbackLoaded(feedback) => {
+                self.feedback = feedback
+                    .into_iter()
+                    .filter_map(|info| info.message_id.map(|id| (id, info)))
+                    .collect();
+                true
+            }
+            SessionViewMsg::FeedbackChanged(message_id, feedback) => {
+                match feedback {
+                    Some(info) => self.feedback.insert(message_id, info),
+                    None => self.feedback.remove(&message_id),
+                };
+                true
+            }
+            SessionViewMsg::TurnsLoaded(turns) => {
+                self.turns = turns
+                    .into_iter()
+                    .filter_map(|turn| turn.end_message_id.map(|id| (id, turn)))
+                    .collect();
+                true
+            }
             SessionViewMsg::ClearCostFlash => {
                 self.cost_flash = false;
                 true
             }
             SessionViewMsg::PermissionRequest(perm) => {
-                self.pending_permission = Some(perm);
-                self.permission_selected = 0;
-                self.question_answers.clear();
-                self.multi_select_options.clear();
-                let session_id = ctx.props().session.id;
-                ctx.props().on_awaiting_change.emit((session_id, true));
-                if let Some(el) = self.permission_ref.cast::<web_sys::HtmlElement>() {
-                    let _ = el.focus();
+                // Replayed on reconnect; don't show a request twice
+                let known = self
+                    .pending_permission
+                    .iter()
+                    .chain(&self.queued_permissions)
+                    .any(|p| p.request_id == perm.request_id);
+                if known {
+                    return false;
                 }
+                if self.pending_permission.is_some() {
+                    self.queued_permissions.push(perm);
+                    return true;
+                }
+                self.show_permission(ctx, perm);
                 true
             }
             SessionViewMsg::PermissionSelectUp => self.handle_permission_select(-1),
@@ -285,14 +692,22 @@ impl Component for SessionView {
             SessionViewMsg::ApprovePermissionAndRemember => {
                 self.handle_approve_permission(ctx, true)
             }
+            SessionViewMsg::ApprovePermissionGroup(add_rule) => {
+                self.handle_approve_permission_group(ctx, add_rule)
+            }
             SessionViewMsg::DenyPermission => self.handle_deny_permission(ctx),
+            SessionViewMsg::SetRememberRule(scope) => {
+                self.remember_rule = scope;
+                true
+            }
             SessionViewMsg::WebSocketConnected(sender) => {
                 self.ws_connected = true;
+                self.cached_at = None;
                 self.ws_sender = Some(sender);
                 self.reconnect_attempt = 0;
                 self.reconnect_timer = None;
                 let session_id = ctx.props().session.id;
-                ctx.props().on_connected_change.emit((session_id, true));
+                dispatch(ctx, DashboardAction::SetConnected(session_id, true));
                 true
             }
             SessionViewMsg::WebSocketError(err) => self.handle_ws_error(ctx, err),
@@ -301,6 +716,16 @@ impl Component for SessionView {
                 false
             }
             SessionViewMsg::CheckAwaiting => {
+                let session_id = ctx.props().session.id;
+                if let Some(lifecycle) = self.lifecycle {
+                    dispatch(
+                        ctx,
+                        DashboardAction::SetAwaiting(session_id, lifecycle.awaits_user()),
+                    );
+                    return false;
+                }
+                // Proxies that don't report their state: guess from the
+                // last message
                 let is_result_awaiting = self.messages.last().is_some_and(|msg| {
                     serde_json::from_str::<serde_json::Value>(msg)
                         .ok()
@@ -312,10 +737,7 @@ impl Component for SessionView {
                         .unwrap_or(false)
                 });
                 let is_awaiting = is_result_awaiting || self.pending_permission.is_some();
-                let session_id = ctx.props().session.id;
-                ctx.props()
-                    .on_awaiting_change
-                    .emit((session_id, is_awaiting));
+                dispatch(ctx, DashboardAction::SetAwaiting(session_id, is_awaiting));
                 false
             }
             SessionViewMsg::BranchChanged(branch) => {
@@ -343,19 +765,26 @@ impl Component for SessionView {
                 self.is_recording = recording;
                 if !recording {
                     self.interim_transcription = None;
+                    // Hand over to the keyboard to correct what was heard
+                    if let Some(input) = self.input_ref.cast::<HtmlTextAreaElement>() {
+                        let _ = input.focus();
+                        let end = input.value().len() as u32;
+                        let _ = input.set_selection_range(end, end);
+                    }
                 }
                 true
             }
             SessionViewMsg::VoiceTranscription(text) => {
+                // A finished segment becomes ordinary input, sent when the
+                // user is happy with it
                 self.interim_transcription = None;
                 if !text.is_empty() {
-                    if self.input_value.is_empty() {
-                        self.input_value = text;
-                    } else {
+                    if !self.input_value.is_empty()
+                        && !self.input_value.ends_with(char::is_whitespace)
+                    {
                         self.input_value.push(' ');
-                        self.input_value.push_str(&text);
                     }
-                    ctx.link().send_message(SessionViewMsg::SendInput);
+                    self.input_value.push_str(&text);
                 }
                 true
             }
@@ -364,12 +793,21 @@ impl Component for SessionView {
                 true
             }
             SessionViewMsg::VoiceError(err) => {
-                log::error!("Voice error: {}", err);
+                tracing::error!("Voice error: {}", err);
                 self.is_recording = false;
                 self.interim_transcription = None;
                 true
             }
+            SessionViewMsg::PushToTalk(held) => {
+                let changed = self.voice_held != held;
+                self.voice_held = held;
+                changed
+            }
             SessionViewMsg::ToggleVoice => {
+                if self.voice_settings.push_to_talk {
+                    // Clicks don't toggle a push-to-talk button; Space is held instead
+                    return false;
+                }
                 if let Some(button) = self.voice_button_ref.cast::<web_sys::HtmlElement>() {
                     button.click();
                 }
@@ -412,7 +850,110 @@ impl Component for SessionView {
                 self.send_mode_dropdown_open = false;
                 self.handle_send_input(ctx)
             }
+            SessionViewMsg::RequestControl => {
+                self.send_control_message(ProxyMessage::RequestControl);
+                false
+            }
+            SessionViewMsg::HandOffControl(to_user_id) => {
+                self.control_requests.clear();
+                self.send_control_message(ProxyMessage::HandOffControl { to_user_id });
+                true
+            }
+            SessionViewMsg::TakeControl => {
+                self.send_control_message(ProxyMessage::TakeControl);
+                false
+            }
+            SessionViewMsg::DismissControlRequest(user_id) => {
+                self.control_requests.retain(|(id, _)| *id != user_id);
+                true
+            }
+            SessionViewMsg::OutcomeChanged(outcome) => {
+                self.outcome = outcome;
+                true
+            }
+            SessionViewMsg::DismissOutcomePrompt => {
+                self.outcome_prompt_dismissed = true;
+                true
+            }
+            SessionViewMsg::IncidentsLoaded(incidents) => {
+                self.incidents = incidents;
+                true
+            }
+            SessionViewMsg::SnapshotLoaded(snapshot) => {
+                self.snapshot = Some(snapshot);
+                true
+            }
+            SessionViewMsg::BudgetLoaded(budget) => {
+                self.budget = budget;
+                true
+            }
+            SessionViewMsg::ArtifactsLoaded(artifacts) => {
+                self.artifacts = artifacts;
+                true
+            }
+            SessionViewMsg::OpenReport(message_json) => {
+                self.report = Some(message_json);
+                true
+            }
+            SessionViewMsg::CloseReport => {
+                self.report = None;
+                true
+            }
+            SessionViewMsg::SetForkAt(message_id) => {
+                self.fork_at = message_id;
+                true
+            }
+            SessionViewMsg::SetUndo(turn) => {
+                self.undo = turn;
+                true
+            }
+            SessionViewMsg::ConfirmUndo => self.handle_undo(ctx),
+            SessionViewMsg::OpenSearch => {
+                if self.search.is_none() {
+                    let mut search = SessionSearch::default();
+                    search.reindex(&self.messages);
+                    self.search = Some(search);
+                }
+                self.focus_search = true;
+                true
+            }
+            SessionViewMsg::CloseSearch => {
+                self.search = None;
+                search::clear_highlights();
+                if let Some(input) = self.input_ref.cast::<HtmlTextAreaElement>() {
+                    let _ = input.focus();
+                }
+                true
+            }
+            SessionViewMsg::SearchInput(query) => {
+                if let Some(search) = &mut self.search {
+                    search.set_query(query);
+                    self.search_moved = true;
+                }
+                true
+            }
+            SessionViewMsg::SearchNext => {
+                if let Some(search) = &mut self.search {
+                    search.next();
+                    self.search_moved = true;
+                }
+                true
+            }
+            SessionViewMsg::SearchPrevious => {
+                if let Some(search) = &mut self.search {
+                    search.previous();
+                    self.search_moved = true;
+                }
+                true
+            }
+        }
+    }
+
+    fn destroy(&mut self, ctx: &Context<Self>) {
+        if self.search.is_some() {
+            search::clear_highlights();
         }
+        self.cache_transcript(ctx);
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
@@ -428,11 +969,32 @@ impl Component for SessionView {
             SessionViewMsg::UpdateInput(input.value())
         });
 
-        let handle_keydown = link.callback(|e: KeyboardEvent| {
+        // The paste goes in as usual; a long one is then offered as a file
+        let handle_paste = link.batch_callback(|e: Event| {
+            let text = e
+                .dyn_ref::<web_sys::ClipboardEvent>()
+                .and_then(|e| e.clipboard_data())
+                .and_then(|data| data.get_data("text").ok())?;
+            // Text areas store line breaks as \n
+            let text = text.replace("\r\n", "\n");
+            is_large_paste(&text).then_some(SessionViewMsg::LargePaste(text))
+        });
+
+        // Holding Space in an empty message box talks, in push-to-talk mode
+        let push_to_talk = ctx.props().voice_enabled
+            && self.voice_settings.push_to_talk
+            && self.input_value.is_empty();
+        let voice_held = self.voice_held;
+
+        let handle_keydown = link.callback(move |e: KeyboardEvent| {
             if e.ctrl_key() && e.key().to_lowercase() == "m" {
                 e.prevent_default();
                 return SessionViewMsg::ToggleVoice;
             }
+            if e.key() == " " && (push_to_talk || voice_held) {
+                e.prevent_default();
+                return SessionViewMsg::PushToTalk(true);
+            }
 
             match e.key().as_str() {
                 "Enter" if !e.shift_key() => {
@@ -456,20 +1018,91 @@ impl Component for SessionView {
             }
         });
 
+        let handle_keyup = link.batch_callback(move |e: KeyboardEvent| {
+            (voice_held && e.key() == " ").then_some(SessionViewMsg::PushToTalk(false))
+        });
+
         let close_dropdown = link.callback(|_| SessionViewMsg::CloseSendModeDropdown);
 
+        let open_search = link.batch_callback(|e: KeyboardEvent| {
+            if (e.ctrl_key() || e.meta_key()) && e.key().to_lowercase() == "f" {
+                e.prevent_default();
+                Some(SessionViewMsg::OpenSearch)
+            } else {
+                None
+            }
+        });
+
         html! {
-            <div class="session-view" onclick={close_dropdown}>
+            <div class="session-view" onclick={close_dropdown} onkeydown={open_search}>
+                <PresenceAvatars viewers={self.viewers.clone()} />
+                { self.render_search_bar(ctx) }
                 <div class="session-view-messages" ref={self.messages_ref.clone()}>
-                    {
-                        group_messages(&self.messages).into_iter().map(|group| {
-                            html! { <MessageGroupRenderer group={group} session_id={Some(ctx.props().session.id)} /> }
-                        }).collect::<Html>()
-                    }
+                    { self.render_host_header(ctx) }
+                    { self.render_messages(ctx) }
                 </div>
 
                 { self.render_permission_dialog(ctx) }
 
+                { self.render_budget_banner() }
+
+                { self.render_outdated_banner() }
+
+                { self.render_offline_banner() }
+
+                { self.render_incident_banner(ctx) }
+
+                { self.render_snapshot_banner(ctx) }
+
+                { self.render_artifacts_bar(ctx) }
+
+                <RelatedSessions
+                    session_id={ctx.props().session.id}
+                    draft={self.first_prompt_draft.clone().map(AttrValue::from)}
+                />
+
+                if let Some(ref message_json) = self.report {
+                    <ReportDialog
+                        issue_url={ctx.props().issue_report_url.clone()}
+                        session_id={Some(ctx.props().session.id)}
+                        incident={self.incidents.first().cloned()}
+                        message_json={message_json.clone()}
+                        on_close={link.callback(|_| SessionViewMsg::CloseReport)}
+                    />
+                }
+
+                if let Some(message_id) = self.fork_at {
+                    <ForkDialog
+                        session_id={ctx.props().session.id}
+                        working_directory={ctx.props().session.working_directory.clone()}
+                        {message_id}
+                        on_close={link.callback(|_| SessionViewMsg::SetForkAt(None))}
+                    />
+                }
+
+                if let Some(ref turn) = self.undo {
+                    <UndoDialog
+                        turn={turn.clone()}
+                        on_confirm={link.callback(|_| SessionViewMsg::ConfirmUndo)}
+                        on_close={link.callback(|_| SessionViewMsg::SetUndo(None))}
+                    />
+                }
+
+                { self.render_control_bar(ctx) }
+
+                { self.render_outcome_prompt(ctx) }
+
+                { self.render_attachments(ctx) }
+
+                if Self::is_read_only(ctx) {
+                    <div class="session-view-readonly">
+                        { "You have view-only access to this session" }
+                    </div>
+                } else if Self::is_approve_only(ctx) {
+                    <div class="session-view-readonly">
+                        { "You can answer permission requests in this session, but not send messages" }
+                    </div>
+                } else {
                 <form class="session-view-input" onsubmit={handle_submit}>
                     <span class="input-prompt">{ ">" }</span>
                     { self.render_interim_transcription() }
@@ -480,15 +1113,25 @@ impl Component for SessionView {
                             self.interim_transcription.is_some().then_some("has-interim")
                         )}
                         placeholder="Type your message... (Shift+Enter for new line)"
+                        spellcheck={if self.input_assist.spellcheck { "true" } else { "false" }}
                         value={self.input_value.clone()}
                         oninput={handle_input}
+                        onpaste={handle_paste}
                         onkeydown={handle_keydown}
+                        onkeyup={handle_keyup}
+                        onblur={voice_held.then(|| link.callback(|_: FocusEvent| SessionViewMsg::PushToTalk(false)))}
                         disabled={!self.ws_connected}
                         rows="1"
                     />
+                    { self.render_lifecycle() }
+                    { self.render_latency() }
+                    { self.render_undo_button(ctx) }
                     { self.render_voice_input(ctx) }
                     { self.render_send_button(ctx) }
+                    { self.render_prompt_hints() }
+                    { self.render_prompt_size() }
                 </form>
+                }
             </div>
         }
     }
@@ -507,9 +1150,9 @@ impl SessionView {
                 ctx.link().send_message(SessionViewMsg::WebSocketError(err));
                 false
             }
-            WsEvent::Output(content) => {
+            WsEvent::Output(content, message_id, seq) => {
                 ctx.link()
-                    .send_message(SessionViewMsg::ReceivedOutput(content));
+                    .send_message(SessionViewMsg::ReceivedOutput(content, message_id, seq));
                 ctx.link().send_message(SessionViewMsg::CheckAwaiting);
                 false
             }
@@ -518,55 +1161,184 @@ impl SessionView {
                     .send_message(SessionViewMsg::PermissionRequest(perm));
                 false
             }
+            WsEvent::PermissionResolved(request_id) => {
+                self.queued_permissions
+                    .retain(|p| p.request_id != request_id);
+                let showing = self
+                    .pending_permission
+                    .as_ref()
+                    .is_some_and(|p| p.request_id == request_id);
+                if showing {
+                    self.pending_permission = None;
+                    self.permission_answered(ctx);
+                }
+                true
+            }
             WsEvent::BranchChanged(branch) => {
                 ctx.link()
                     .send_message(SessionViewMsg::BranchChanged(branch));
                 false
             }
+            WsEvent::Reactions(message_id, reactions) => {
+                ctx.link()
+                    .send_message(SessionViewMsg::ReactionsChanged(message_id, reactions));
+                false
+            }
+            WsEvent::Presence(viewers) => {
+                // Drop requests from viewers who left or already got control
+                self.control_requests
+                    .retain(|(id, _)| viewers.iter().any(|v| v.user_id == *id && !v.driving));
+                self.viewers = viewers;
+                true
+            }
+            WsEvent::ControlRequested(user_id, name) => {
+                if !self.is_driver(ctx) || Some(user_id) == ctx.props().current_user_id {
+                    return false;
+                }
+                if !self.control_requests.iter().any(|(id, _)| *id == user_id) {
+                    self.control_requests.push((user_id, name));
+                }
+                true
+            }
+            WsEvent::Incident(incident) => {
+                self.incidents.insert(0, incident);
+                true
+            }
+            WsEvent::Annotation(annotation) => {
+                self.annotations.push(annotation);
+                true
+            }
+            WsEvent::Budget(budget) => {
+                self.budget = budget;
+                true
+            }
+            WsEvent::Lifecycle(lifecycle) => {
+                self.lifecycle = Some(lifecycle);
+                ctx.link().send_message(SessionViewMsg::CheckAwaiting);
+                true
+            }
+            WsEvent::Latency(rtt_ms) => {
+                self.latency_ms = Some(rtt_ms);
+                true
+            }
+            WsEvent::Outdated(reason) => {
+                self.outdated = Some(reason);
+                true
+            }
         }
     }
 
     fn handle_send_input(&mut self, ctx: &Context<Self>) -> bool {
         let input = self.input_value.trim().to_string();
-        if input.is_empty() {
+        if (input.is_empty() && self.attachments.is_empty()) || self.other_driver(ctx).is_some() {
             return false;
         }
 
-        self.command_history.push(input.clone());
+        if !input.is_empty() {
+            self.command_history.push(input.clone());
+        }
         self.input_value.clear();
+        self.large_paste = None;
+        let attachments = std::mem::take(&mut self.attachments);
+        if self.awaiting_first_prompt {
+            self.awaiting_first_prompt = false;
+            self.first_prompt_draft = Some(input.clone());
+        }
 
         let session_id = ctx.props().session.id;
-        ctx.props().on_message_sent.emit(session_id);
+        dispatch(ctx, DashboardAction::SetAwaiting(session_id, false));
 
         // Capture current send mode and reset to normal after sending
         let send_mode = self.send_mode;
         self.send_mode = SendMode::Normal;
 
         if let Some(ref sender) = self.ws_sender {
+            let content = if attachments.is_empty() {
+                serde_json::Value::String(input)
+            } else {
+                AttachedInput {
+                    text: input,
+                    attachments,
+                }
+                .to_content()
+            };
             let msg = ProxyMessage::ClaudeInput {
-                content: serde_json::Value::String(input),
+                content,
                 send_mode: if send_mode == SendMode::Normal {
                     None
                 } else {
                     Some(send_mode)
                 },
+                // Start a trace the backend and proxy continue
+                traceparent: Some(TraceContext::new_root().to_traceparent()),
             };
             send_message(sender, msg);
         }
         true
     }
 
-    fn handle_received_output(&mut self, ctx: &Context<Self>, output: String) -> bool {
+    /// Stop Claude's turn if it's still going, then send the instruction to
+    /// revert it. Re-reads the turn in case it finished while confirming.
+    fn handle_undo(&mut self, ctx: &Context<Self>) -> bool {
+        self.undo = None;
+        let Some(turn) = last_turn(&self.messages).filter(LastTurn::changed_anything) else {
+            return true;
+        };
+        let Some(ref sender) = self.ws_sender else {
+            return true;
+        };
+        if turn.in_progress {
+            send_message(sender, ProxyMessage::Interrupt);
+        }
+        send_message(
+            sender,
+            ProxyMessage::ClaudeInput {
+                content: serde_json::Value::String(turn.undo_prompt()),
+                send_mode: None,
+                traceparent: Some(TraceContext::new_root().to_traceparent()),
+            },
+        );
+        dispatch(
+            ctx,
+            DashboardAction::SetAwaiting(ctx.props().session.id, false),
+        );
+        true
+    }
+
+    fn handle_received_output(
+        &mut self,
+        ctx: &Context<Self>,
+        output: String,
+        message_id: Option<Uuid>,
+        seq: Option<u64>,
+    ) -> bool {
+        // A message replayed on reconnect that was already shown
+        if let (Some(seq), Some(last_seq)) = (seq, self.last_seq) {
+            if seq <= last_seq {
+                return false;
+            }
+        }
+        if seq.is_some() {
+            self.last_seq = seq;
+        }
+        // Annotations can be posted ahead of the messages they cover
+        if let (Some(seq), Some(id)) = (seq, message_id) {
+            let seq = seq as i64;
+            for annotation in &mut self.annotations {
+                if (annotation.start_seq..=annotation.end_seq).contains(&seq) {
+                    annotation.message_ids.push(id);
+                }
+            }
+        }
+
         if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(&output) {
+            self.record_artifacts(&parsed);
             if parsed.get("type").and_then(|t| t.as_str()) == Some("result") {
                 if let Some(cost) = parsed.get("total_cost_usd").and_then(|c| c.as_f64()) {
                     if cost != self.total_cost {
                         self.total_cost = cost;
                         self.cost_flash = true;
 
-                        let session_id = ctx.props().session.id;
-                        ctx.props().on_cost_change.emit((session_id, cost));
-
                         let link = ctx.link().clone();
                         spawn_local(async move {
                             gloo::timers::future::TimeoutFuture::new(600).await;
@@ -574,20 +1346,59 @@ impl SessionView {
                         });
                     }
                 }
+                // The backend records the turn before relaying its result
+                let session_id = ctx.props().session.id;
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    if let Some(turns) = load_turns(session_id).await {
+                        link.send_message(SessionViewMsg::TurnsLoaded(turns));
+                    }
+                });
+                // Turns end with a result; keep the cache current as they do
+                self.push_message(output, message_id);
+                self.cache_transcript(ctx);
+                return true;
             }
         }
-        self.messages.push(output);
-        if self.messages.len() > MAX_MESSAGES_PER_SESSION {
-            let excess = self.messages.len() - MAX_MESSAGES_PER_SESSION;
-            self.messages.drain(0..excess);
+        self.push_message(output, message_id);
+        true
+    }
+
+    /// Save the transcript for reading offline; a cached one being shown is
+    /// left as is
+    fn cache_transcript(&self, ctx: &Context<Self>) {
+        if self.cached_at.is_some() || self.messages.is_empty() {
+            return;
+        }
+        let transcript = CachedTranscript {
+            messages: self.messages.clone(),
+            ids: self.message_ids.clone(),
+            last_seq: self.last_seq,
+        };
+        if let Ok(json) = serde_json::to_string(&transcript) {
+            offline::save_transcript(ctx.props().session.id, json);
         }
-        self.last_message_timestamp = Some(
-            js_sys::Date::new_0()
+    }
+
+    /// Track files created by Write tool uses in a new output
+    fn record_artifacts(&mut self, output: &serde_json::Value) {
+        for (path, size_bytes) in write_tool_uses(output) {
+            let written_at = js_sys::Date::new_0()
                 .to_iso_string()
                 .as_string()
-                .unwrap_or_default(),
-        );
-        true
+                .unwrap_or_default();
+            match self.artifacts.iter_mut().find(|a| a.path == path) {
+                Some(existing) => {
+                    existing.size_bytes = size_bytes;
+                    existing.written_at = written_at;
+                }
+                None => self.artifacts.push(ArtifactInfo {
+                    path,
+                    size_bytes,
+                    written_at,
+                }),
+            }
+        }
     }
 
     fn handle_permission_select(&mut self, delta: i32) -> bool {
@@ -602,10 +1413,8 @@ impl SessionView {
                 } else {
                     0
                 }
-            } else if !perm.permission_suggestions.is_empty() {
-                2
             } else {
-                1
+                self.permission_choices().len().saturating_sub(1)
             };
 
             if delta < 0 {
@@ -632,13 +1441,22 @@ impl SessionView {
                     ));
                 }
             } else {
-                let has_suggestions = !perm.permission_suggestions.is_empty();
-                let msg = match (self.permission_selected, has_suggestions) {
-                    (0, _) => SessionViewMsg::ApprovePermission,
-                    (1, true) => SessionViewMsg::ApprovePermissionAndRemember,
-                    (1, false) => SessionViewMsg::DenyPermission,
-                    (2, true) => SessionViewMsg::DenyPermission,
-                    _ => SessionViewMsg::ApprovePermission,
+                let choice = self
+                    .permission_choices()
+                    .get(self.permission_selected)
+                    .copied();
+                let msg = match choice {
+                    Some(PermissionChoice::AllowAndRemember) => {
+                        SessionViewMsg::ApprovePermissionAndRemember
+                    }
+                    Some(PermissionChoice::AllowGroup) => {
+                        SessionViewMsg::ApprovePermissionGroup(false)
+                    }
+                    Some(PermissionChoice::AllowGroupAndRule) => {
+                        SessionViewMsg::ApprovePermissionGroup(true)
+                    }
+                    Some(PermissionChoice::Deny) => SessionViewMsg::DenyPermission,
+                    Some(PermissionChoice::Allow) | None => SessionViewMsg::ApprovePermission,
                 };
                 ctx.link().send_message(msg);
             }
@@ -646,26 +1464,115 @@ impl SessionView {
         false
     }
 
-    fn handle_approve_permission(&mut self, ctx: &Context<Self>, remember: bool) -> bool {
-        if let Some(perm) = self.pending_permission.take() {
-            if let Some(ref sender) = self.ws_sender {
-                let msg = ProxyMessage::PermissionResponse {
-                    request_id: perm.request_id,
-                    allow: true,
-                    input: Some(perm.input),
-                    permissions: if remember {
-                        perm.permission_suggestions
-                    } else {
-                        vec![]
-                    },
+    fn show_permission(&mut self, ctx: &Context<Self>, perm: PendingPermission) {
+        self.pending_permission = Some(perm);
+        self.permission_selected = 0;
+        self.remember_rule = None;
+        self.question_answers.clear();
+        self.multi_select_options.clear();
+        let session_id = ctx.props().session.id;
+        dispatch(ctx, DashboardAction::SetAwaiting(session_id, true));
+        if let Some(el) = self.permission_ref.cast::<web_sys::HtmlElement>() {
+            let _ = el.focus();
+        }
+    }
+
+    /// Move on to the next queued request, or back to the input
+    fn permission_answered(&mut self, ctx: &Context<Self>) {
+        if !self.queued_permissions.is_empty() {
+            let next = self.queued_permissions.remove(0);
+            self.show_permission(ctx, next);
+            return;
+        }
+        ctx.link().send_message(SessionViewMsg::CheckAwaiting);
+        if let Some(input) = self.input_ref.cast::<HtmlTextAreaElement>() {
+            let _ = input.focus();
+        }
+    }
+
+    /// The current request's group and how many requests, including it, are in it
+    fn permission_group(&self) -> Option<(PermissionGroup, usize)> {
+        let group = PermissionGroup::of(self.pending_permission.as_ref()?)?;
+        let queued = self
+            .queued_permissions
+            .iter()
+            .filter(|p| PermissionGroup::of(p).as_ref() == Some(&group))
+            .count();
+        Some((group, queued + 1))
+    }
+
+    fn permission_choices(&self) -> Vec<PermissionChoice> {
+        let has_suggestions = self
+            .pending_permission
+            .as_ref()
+            .is_some_and(|p| !p.permission_suggestions.is_empty());
+        let group_size = self.permission_group().map_or(1, |(_, size)| size);
+        permission_choices(has_suggestions, group_size)
+    }
+
+    /// The rule to send with this answer, if "always allow/deny" is ticked
+    fn remembered_rule(
+        &self,
+        perm: &PendingPermission,
+        decision: RuleDecision,
+    ) -> Option<RememberedRule> {
+        self.remember_rule.map(|scope| RememberedRule {
+            rule: PermissionRule::for_request(&perm.tool_name, &perm.input, decision),
+            scope,
+        })
+    }
+
+    fn handle_approve_permission_group(&mut self, ctx: &Context<Self>, add_rule: bool) -> bool {
+        let Some((group, _)) = self.permission_group() else {
+            return self.handle_approve_permission(ctx, false);
+        };
+        let Some(perm) = self.pending_permission.take() else {
+            return false;
+        };
+        let (members, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut self.queued_permissions)
+            .into_iter()
+            .partition(|p| PermissionGroup::of(p).as_ref() == Some(&group));
+        self.queued_permissions = rest;
+
+        if let Some(ref sender) = self.ws_sender {
+            // The rules only need granting once
+            let mut rule = add_rule.then(|| group.rule());
+            let mut remember = self.remembered_rule(&perm, RuleDecision::Allow);
+            for p in std::iter::once(perm).chain(members) {
+                let msg = ProxyMessage::PermissionResponse {
+                    request_id: p.request_id,
+                    allow: true,
+                    input: Some(p.input),
+                    permissions: rule.take().into_iter().collect(),
                     reason: None,
+                    remember: remember.take(),
                 };
                 send_message(sender, msg);
             }
-            ctx.link().send_message(SessionViewMsg::CheckAwaiting);
-            if let Some(input) = self.input_ref.cast::<HtmlTextAreaElement>() {
-                let _ = input.focus();
+        }
+        self.permission_answered(ctx);
+        true
+    }
+
+    fn handle_approve_permission(&mut self, ctx: &Context<Self>, remember: bool) -> bool {
+        if let Some(perm) = self.pending_permission.take() {
+            if let Some(ref sender) = self.ws_sender {
+                let remember_rule = self.remembered_rule(&perm, RuleDecision::Allow);
+                let msg = ProxyMessage::PermissionResponse {
+                    request_id: perm.request_id,
+                    allow: true,
+                    input: Some(perm.input),
+                    permissions: if remember {
+                        perm.permission_suggestions
+                    } else {
+                        vec![]
+                    },
+                    reason: None,
+                    remember: remember_rule,
+                };
+                send_message(sender, msg);
             }
+            self.permission_answered(ctx);
         }
         true
     }
@@ -673,19 +1580,18 @@ impl SessionView {
     fn handle_deny_permission(&mut self, ctx: &Context<Self>) -> bool {
         if let Some(perm) = self.pending_permission.take() {
             if let Some(ref sender) = self.ws_sender {
+                let remember = self.remembered_rule(&perm, RuleDecision::Deny);
                 let msg = ProxyMessage::PermissionResponse {
                     request_id: perm.request_id,
                     allow: false,
                     input: None,
                     permissions: vec![],
                     reason: Some("User denied".to_string()),
+                    remember,
                 };
                 send_message(sender, msg);
             }
-            ctx.link().send_message(SessionViewMsg::CheckAwaiting);
-            if let Some(input) = self.input_ref.cast::<HtmlTextAreaElement>() {
-                let _ = input.focus();
-            }
+            self.permission_answered(ctx);
         }
         true
     }
@@ -693,14 +1599,16 @@ impl SessionView {
     fn handle_ws_error(&mut self, ctx: &Context<Self>, err: String) -> bool {
         self.ws_connected = false;
         self.ws_sender = None;
+        self.latency_ms = None;
+        self.lifecycle = None;
         let session_id = ctx.props().session.id;
-        ctx.props().on_connected_change.emit((session_id, false));
+        dispatch(ctx, DashboardAction::SetConnected(session_id, false));
 
         const MAX_ATTEMPTS: u32 = 10;
         if self.reconnect_attempt < MAX_ATTEMPTS {
             self.reconnect_attempt += 1;
             let delay_ms = calculate_backoff(self.reconnect_attempt - 1);
-            log::info!(
+            tracing::info!(
                 "WebSocket disconnected, reconnecting in {}ms (attempt {})",
                 delay_ms,
                 self.reconnect_attempt
@@ -715,20 +1623,280 @@ impl SessionView {
                 "type": "error",
                 "message": format!("Connection lost: {}", err)
             });
-            self.messages.push(error_msg.to_string());
+            self.push_message(error_msg.to_string(), None);
         }
         true
     }
 
+    /// Append a message, keeping the ID list in step and trimming to the limit
+    fn push_message(&mut self, content: String, message_id: Option<Uuid>) {
+        if let Some(used) = serde_json::from_str::<serde_json::Value>(&content)
+            .ok()
+            .and_then(|v| prompt_size::context_used(&v))
+        {
+            self.context_used = Some(used);
+        }
+        self.messages.push(content);
+        self.message_ids.push(message_id);
+        if self.messages.len() > MAX_MESSAGES_PER_SESSION {
+            let excess = self.messages.len() - MAX_MESSAGES_PER_SESSION;
+            self.messages.drain(0..excess);
+            self.message_ids.drain(0..excess);
+        }
+        if let Some(search) = &mut self.search {
+            search.reindex(&self.messages);
+        }
+    }
+
+    /// Position of the selected match among the matches in its message group
+    fn current_match_in_slot(&self) -> usize {
+        let Some(search) = &self.search else {
+            return 0;
+        };
+        let Some(current) = search.current_match() else {
+            return 0;
+        };
+        let mut offset = 0;
+        for group in group_messages(&self.messages) {
+            let len = match &group {
+                MessageGroup::Single(_) => 1,
+                MessageGroup::AssistantGroup(messages) => messages.len(),
+            };
+            if (offset..offset + len).contains(&current.message) {
+                return search
+                    .matches
+                    .iter()
+                    .take(search.current)
+                    .filter(|m| m.message >= offset)
+                    .count();
+            }
+            offset += len;
+        }
+        0
+    }
+
+    fn render_search_bar(&self, ctx: &Context<Self>) -> Html {
+        let Some(search) = &self.search else {
+            return html! {};
+        };
+        let link = ctx.link();
+        let oninput = link.callback(|e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            SessionViewMsg::SearchInput(input.value())
+        });
+        let onkeydown = link.batch_callback(|e: KeyboardEvent| {
+            // Keep Escape and Enter away from the session and page handlers
+            e.stop_propagation();
+            match e.key().as_str() {
+                "Enter" if e.shift_key() => Some(SessionViewMsg::SearchPrevious),
+                "Enter" => Some(SessionViewMsg::SearchNext),
+                "Escape" => Some(SessionViewMsg::CloseSearch),
+                "f" | "F" if e.ctrl_key() || e.meta_key() => {
+                    e.prevent_default();
+                    Some(SessionViewMsg::OpenSearch)
+                }
+                _ => None,
+            }
+        });
+        let no_matches = search.matches.is_empty();
+
+        html! {
+            <div class="session-search">
+                <input
+                    ref={self.search_ref.clone()}
+                    class="session-search-input"
+                    type="search"
+                    placeholder="Find in session"
+                    value={search.query.clone()}
+                    {oninput}
+                    {onkeydown}
+                />
+                <span class={classes!(
+                    "session-search-counter",
+                    (no_matches && !search.query.is_empty()).then_some("empty")
+                )}>
+                    { search.counter() }
+                </span>
+                <button
+                    class="session-search-nav"
+                    title="Previous match (Shift+Enter)"
+                    disabled={no_matches}
+                    onclick={link.callback(|_| SessionViewMsg::SearchPrevious)}
+                >
+                    { "↑" }
+                </button>
+                <button
+                    class="session-search-nav"
+                    title="Next match (Enter)"
+                    disabled={no_matches}
+                    onclick={link.callback(|_| SessionViewMsg::SearchNext)}
+                >
+                    { "↓" }
+                </button>
+                <button
+                    class="session-search-close"
+                    title="Close (Esc)"
+                    onclick={link.callback(|_| SessionViewMsg::CloseSearch)}
+                >
+                    { "×" }
+                </button>
+            </div>
+        }
+    }
+
+    /// Render message groups, each followed by reactions on its last stored message.
+    ///
+    /// Result messages also get turn feedback controls.
+    fn render_messages(&self, ctx: &Context<Self>) -> Html {
+        let session_id = ctx.props().session.id;
+        let on_reactions_change = ctx.link().callback(|(message_id, reactions)| {
+            SessionViewMsg::ReactionsChanged(message_id, reactions)
+        });
+        let on_feedback_change = ctx.link().callback(|(message_id, feedback)| {
+            SessionViewMsg::FeedbackChanged(message_id, feedback)
+        });
+        // Viewers can't run a proxy, so they can't fork either
+        let can_fork = Self::can_edit(ctx);
+        // CI for the branch shows on the latest result
+        let ci = ctx.props().session.ci.clone();
+        let last_result = self.messages.iter().rposition(|m| is_result_message(m));
+        let mut offset = 0;
+
+        group_messages(&self.messages)
+            .into_iter()
+            .map(|group| {
+                let (len, is_result, raw_json) = match &group {
+                    MessageGroup::Single(json) => (1, is_result_message(json), json.clone()),
+                    MessageGroup::AssistantGroup(messages) => {
+                        (messages.len(), false, format!("[{}]", messages.join(",")))
+                    }
+                };
+                let on_report = ctx
+                    .link()
+                    .callback(move |_| SessionViewMsg::OpenReport(Some(raw_json.clone())));
+                let anchor = self
+                    .message_ids
+                    .get(offset..offset + len)
+                    .and_then(|ids| ids.iter().rev().find_map(|id| *id));
+                let slot = offset..offset + len;
+                let search_hit = self
+                    .search
+                    .as_ref()
+                    .is_some_and(|s| slot.clone().any(|i| s.is_hit(i)));
+                let search_current = self
+                    .search
+                    .as_ref()
+                    .and_then(|s| s.current_match())
+                    .is_some_and(|m| slot.contains(&m.message));
+                let ci_badge = ci
+                    .as_ref()
+                    .filter(|_| is_result && last_result.is_some_and(|i| slot.contains(&i)));
+                let annotations = self
+                    .message_ids
+                    .get(slot.clone())
+                    .map(|ids| self.render_annotations(ids))
+                    .unwrap_or_default();
+                offset += len;
+
+                // Swiping a message sideways shows its metadata on phones
+                let ontouchstart = {
+                    let origin = self.touch_origin.clone();
+                    Callback::from(move |e: TouchEvent| origin.set(mobile::touch_origin(&e)))
+                };
+                let ontouchend = anchor.map(|message_id| {
+                    let origin = self.touch_origin.clone();
+                    ctx.link().batch_callback(move |e: TouchEvent| {
+                        origin
+                            .take()
+                            .filter(|o| mobile::is_swipe(*o, &e))
+                            .map(|_| SessionViewMsg::ToggleMeta(message_id))
+                    })
+                });
+                let meta_revealed = anchor.is_some_and(|id| self.meta_revealed.contains(&id));
+
+                html! {
+                    <div
+                        class={classes!(
+                            "message-slot",
+                            search_hit.then_some("search-hit"),
+                            search_current.then_some("search-current"),
+                            meta_revealed.then_some("meta-revealed")
+                        )}
+                        {ontouchstart}
+                        {ontouchend}
+                    >
+                        <MessageGroupRenderer group={group} session_id={Some(session_id)} />
+                        { annotations }
+                        if let Some(status) = ci_badge {
+                            <CiBadge status={status.clone()} />
+                        }
+                        if let Some(message_id) = anchor {
+                            <MessageReactions
+                                on_fork={can_fork.then(|| ctx.link().callback(move |_| SessionViewMsg::SetForkAt(Some(message_id))))}
+                                {session_id}
+                                {message_id}
+                                reactions={self.reactions.get(&message_id).cloned().unwrap_or_default()}
+                                on_change={on_reactions_change.clone()}
+                                on_report={on_report}
+                            />
+                            if let Some(turn) = self.turns.get(&message_id).filter(|_| is_result) {
+                                { render_turn_summary(turn) }
+                            }
+                            if is_result {
+                                <TurnFeedback
+                                    {session_id}
+                                    {message_id}
+                                    feedback={self.feedback.get(&message_id).cloned()}
+                                    on_change={on_feedback_change.clone()}
+                                />
+                            }
+                        }
+                    </div>
+                }
+            })
+            .collect::<Html>()
+    }
+
+    /// Badges for annotations covering any of a group's messages
+    fn render_annotations(&self, ids: &[Option<Uuid>]) -> Html {
+        let covering: Vec<&AnnotationInfo> = self
+            .annotations
+            .iter()
+            .filter(|a| ids.iter().flatten().any(|id| a.message_ids.contains(id)))
+            .collect();
+        if covering.is_empty() {
+            return html! {};
+        }
+
+        html! {
+            <div class="message-annotations">
+                { for covering.into_iter().map(|annotation| {
+                    let class = classes!("annotation-badge", annotation.level.as_str());
+                    let title = format!("{} · seq {}–{}", annotation.source, annotation.start_seq, annotation.end_seq);
+                    match &annotation.url {
+                        Some(url) => html! {
+                            <a {class} {title} href={url.clone()} target="_blank" rel="noopener noreferrer">
+                                { &annotation.label }
+                            </a>
+                        },
+                        None => html! {
+                            <span {class} {title}>{ &annotation.label }</span>
+                        },
+                    }
+                }) }
+            </div>
+        }
+    }
+
     fn attempt_reconnect(&self, ctx: &Context<Self>) {
         let link = ctx.link().clone();
         let session_id = ctx.props().session.id;
-        let replay_after = self.last_message_timestamp.clone();
+        let last_seq = self.last_seq;
 
         let on_event = Callback::from(move |event: WsEvent| {
             link.send_message(SessionViewMsg::WsEvent(event));
         });
-        connect_websocket(session_id, replay_after, true, on_event);
+        connect_websocket(session_id, last_seq, true, on_event);
     }
 
     fn handle_submit_answers(&mut self, ctx: &Context<Self>, answers: QuestionAnswers) -> bool {
@@ -755,20 +1923,380 @@ impl SessionView {
                     input: Some(answers_json),
                     permissions: vec![],
                     reason: None,
+                    remember: None,
                 };
                 send_message(sender, msg);
             }
             self.multi_select_options.clear();
             self.question_answers.clear();
-            ctx.link().send_message(SessionViewMsg::CheckAwaiting);
-            if let Some(input) = self.input_ref.cast::<HtmlTextAreaElement>() {
-                let _ = input.focus();
-            }
+            self.permission_answered(ctx);
         }
         true
     }
 
+    /// Viewers watch the session but can't send input or answer permissions
+    fn is_read_only(ctx: &Context<Self>) -> bool {
+        ctx.props().session.my_role == "viewer"
+    }
+
+    /// Approvers (guests) answer permission requests but can't send input
+    fn is_approve_only(ctx: &Context<Self>) -> bool {
+        ctx.props().session.my_role == shared::APPROVER_ROLE
+    }
+
+    /// Whether the user can send input and change the session
+    fn can_edit(ctx: &Context<Self>) -> bool {
+        !Self::is_read_only(ctx) && !Self::is_approve_only(ctx)
+    }
+
+    /// The viewer holding input control, if it isn't the current user
+    fn other_driver(&self, ctx: &Context<Self>) -> Option<&PresenceViewer> {
+        let me = ctx.props().current_user_id;
+        self.viewers
+            .iter()
+            .find(|v| v.driving && Some(v.user_id) != me)
+    }
+
+    fn is_driver(&self, ctx: &Context<Self>) -> bool {
+        let me = ctx.props().current_user_id;
+        self.viewers
+            .iter()
+            .any(|v| v.driving && Some(v.user_id) == me)
+    }
+
+    fn send_control_message(&self, msg: ProxyMessage) {
+        if let Some(ref sender) = self.ws_sender {
+            send_message(sender, msg);
+        }
+    }
+
+    /// Warn when a soft budget cap is reached, and explain why input is refused at a hard cap
+    fn render_budget_banner(&self) -> Html {
+        let (class, note) = match self.budget.level {
+            BudgetLevel::Ok => return html! {},
+            BudgetLevel::Soft => ("budget-banner soft", "Input is still being sent."),
+            BudgetLevel::Hard => (
+                "budget-banner hard",
+                "New input is blocked until an admin raises the limit.",
+            ),
+        };
+
+        html! {
+            <div class={class}>
+                <span class="budget-summary">{ format!("💸 {}.", self.budget.summary()) }</span>
+                <span class="budget-note">{ note }</span>
+            </div>
+        }
+    }
+
+    /// Ask for a reload when the server was upgraded under this page
+    fn render_outdated_banner(&self) -> Html {
+        let Some(reason) = &self.outdated else {
+            return html! {};
+        };
+        let reload = Callback::from(|_: MouseEvent| {
+            if let Some(window) = web_sys::window() {
+                let _ = window.location().reload();
+            }
+        });
+
+        html! {
+            <div class="budget-banner soft">
+                <span class="budget-summary">{ reason }</span>
+                <button class="tab-btn active" onclick={reload}>{ "Reload" }</button>
+            </div>
+        }
+    }
+
+    fn render_offline_banner(&self) -> Html {
+        let Some(cached_at) = self.cached_at else {
+            return html! {};
+        };
+        let when: String = js_sys::Date::new(&cached_at.into())
+            .to_locale_string("default", &JsValue::UNDEFINED)
+            .into();
+
+        html! {
+            <div class="offline-banner">
+                { format!("Offline — showing the transcript cached {}", when) }
+            </div>
+        }
+    }
+
+    /// Link to the diagnostic bundle for the most recent incident
+    fn render_incident_banner(&self, ctx: &Context<Self>) -> Html {
+        let Some(latest) = self.incidents.first() else {
+            return html! {};
+        };
+        let bundle_url = utils::api_url(&format!(
+            "/api/sessions/{}/incidents/{}/bundle",
+            ctx.props().session.id,
+            latest.id
+        ));
+        let earlier = self.incidents.len() - 1;
+
+        html! {
+            <div class="incident-banner">
+                <span class="incident-reason" title={latest.reason.clone()}>
+                    { format!("⚠ {}", latest.reason) }
+                </span>
+                if earlier > 0 {
+                    <span class="incident-count">
+                        { format!("+{} earlier", earlier) }
+                    </span>
+                }
+                <a class="incident-download" href={bundle_url} download="">
+                    { "Download diagnostics" }
+                </a>
+                <button
+                    type="button"
+                    class="control-button secondary"
+                    onclick={ctx.link().callback(|_| SessionViewMsg::OpenReport(None))}
+                >
+                    { "Report a problem" }
+                </button>
+            </div>
+        }
+    }
+
+    /// What the proxy left pending, from its last mirrored snapshot, while
+    /// it's disconnected
+    fn render_snapshot_banner(&self, ctx: &Context<Self>) -> Html {
+        let session = &ctx.props().session;
+        let Some(snapshot) = &self.snapshot else {
+            return html! {};
+        };
+        if session.status == SessionStatus::Active
+            || (snapshot.pending_outputs == 0 && snapshot.pending_permission.is_none())
+        {
+            return html! {};
+        }
+        let when: String = js_sys::Date::new(&snapshot.uploaded_at.as_str().into())
+            .to_locale_string("default", &JsValue::UNDEFINED)
+            .into();
+        let mut pending = Vec::new();
+        if snapshot.pending_outputs > 0 {
+            pending.push(format!(
+                "{} undelivered {}",
+                snapshot.pending_outputs,
+                if snapshot.pending_outputs == 1 {
+                    "output"
+                } else {
+                    "outputs"
+                }
+            ));
+        }
+        if let Some(tool) = &snapshot.pending_permission {
+            pending.push(format!("a {} permission request", tool));
+        }
+        let download_url =
+            utils::api_url(&format!("/api/sessions/{}/snapshot/download", session.id));
+
+        html! {
+            <div class="snapshot-banner">
+                <span class="snapshot-summary">
+                    { format!("Proxy left {} (snapshot from {})", pending.join(" and "), when) }
+                </span>
+                <a class="snapshot-download" href={download_url} download="">
+                    { "Download snapshot" }
+                </a>
+            </div>
+        }
+    }
+
+    /// Files written this session, with a zip download while the proxy is connected
+    fn render_artifacts_bar(&self, ctx: &Context<Self>) -> Html {
+        if self.artifacts.is_empty() {
+            return html! {};
+        }
+        let session = &ctx.props().session;
+        let count = self.artifacts.len();
+        let file_list = self
+            .artifacts
+            .iter()
+            .map(|a| a.path.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let download_url =
+            utils::api_url(&format!("/api/sessions/{}/artifacts/download", session.id));
+
+        html! {
+            <div class="artifacts-bar">
+                <span class="artifacts-count" title={file_list}>
+                    { format!("📦 {} {} written", count, if count == 1 { "file" } else { "files" }) }
+                </span>
+                if session.status == SessionStatus::Active {
+                    <a class="artifacts-download" href={download_url} download="">
+                        { "Download artifacts (zip)" }
+                    </a>
+                } else {
+                    <span class="artifacts-offline">
+                        { "Download needs the proxy to be connected" }
+                    </span>
+                }
+            </div>
+        }
+    }
+
+    /// The offer to send a large paste as a file, and the files waiting to
+    /// go with the next input
+    fn render_attachments(&self, ctx: &Context<Self>) -> Html {
+        if self.large_paste.is_none() && self.attachments.is_empty() {
+            return html! {};
+        }
+        let link = ctx.link();
+        html! {
+            <div class="paste-attachments">
+                if let Some(ref text) = self.large_paste {
+                    <div class="paste-offer">
+                        <span>
+                            { format!("Pasted {} lines. Send them as a file to keep the transcript short?", text.lines().count()) }
+                        </span>
+                        <button type="button" class="control-button" onclick={link.callback(|_| SessionViewMsg::AttachPaste)}>
+                            { "Send as file" }
+                        </button>
+                        <button type="button" class="control-button secondary" onclick={link.callback(|_| SessionViewMsg::KeepPasteInline)}>
+                            { "Keep inline" }
+                        </button>
+                    </div>
+                }
+                { for self.attachments.iter().enumerate().map(|(index, attachment)| html! {
+                    <span class="paste-attachment">
+                        { format!("📎 {} · {} lines", attachment.name, attachment.line_count()) }
+                        <button
+                            type="button"
+                            title="Remove"
+                            onclick={link.callback(move |_| SessionViewMsg::RemoveAttachment(index))}
+                        >
+                            { "✕" }
+                        </button>
+                    </span>
+                }) }
+            </div>
+        }
+    }
+
+    /// Ask how an ended session went, until it's labeled or dismissed
+    fn render_outcome_prompt(&self, ctx: &Context<Self>) -> Html {
+        let session = &ctx.props().session;
+        if session.status == SessionStatus::Active
+            || self.outcome.is_some()
+            || self.outcome_prompt_dismissed
+            || !Self::can_edit(ctx)
+        {
+            return html! {};
+        }
+        let link = ctx.link();
+
+        html! {
+            <div class="outcome-prompt">
+                <span>{ "This session has ended. How did it go?" }</span>
+                <OutcomePicker
+                    session_id={session.id}
+                    outcome={self.outcome}
+                    on_change={link.callback(SessionViewMsg::OutcomeChanged)}
+                />
+                <button
+                    type="button"
+                    class="control-button secondary"
+                    onclick={link.callback(|_| SessionViewMsg::DismissOutcomePrompt)}
+                >
+                    { "Not now" }
+                </button>
+            </div>
+        }
+    }
+
+    /// Who holds input control, shown only when several people are connected
+    fn render_control_bar(&self, ctx: &Context<Self>) -> Html {
+        if self.viewers.len() < 2 || Self::is_read_only(ctx) {
+            return html! {};
+        }
+        let link = ctx.link();
+
+        if let Some(driver) = self.other_driver(ctx) {
+            return html! {
+                <div class="control-bar">
+                    <span class="control-status">
+                        { format!("{} is driving", driver.name) }
+                    </span>
+                    <button
+                        type="button"
+                        class="control-button"
+                        onclick={link.callback(|_| SessionViewMsg::RequestControl)}
+                    >
+                        { "Request control" }
+                    </button>
+                    if ctx.props().is_admin {
+                        <button
+                            type="button"
+                            class="control-button override"
+                            onclick={link.callback(|_| SessionViewMsg::TakeControl)}
+                        >
+                            { "Take over" }
+                        </button>
+                    }
+                </div>
+            };
+        }
+
+        if !self.is_driver(ctx) {
+            return html! {
+                <div class="control-bar">
+                    <span class="control-status">{ "Nobody is driving" }</span>
+                    <button
+                        type="button"
+                        class="control-button"
+                        onclick={link.callback(|_| SessionViewMsg::RequestControl)}
+                    >
+                        { "Take control" }
+                    </button>
+                </div>
+            };
+        }
+
+        html! {
+            <div class="control-bar driving">
+                <span class="control-status">{ "You are driving" }</span>
+                {
+                    self.control_requests.iter().map(|(user_id, name)| {
+                        let user_id = *user_id;
+                        html! {
+                            <span class="control-request" key={user_id.to_string()}>
+                                { format!("{} requests control", name) }
+                                <button
+                                    type="button"
+                                    class="control-button"
+                                    onclick={link.callback(move |_| SessionViewMsg::HandOffControl(Some(user_id)))}
+                                >
+                                    { "Hand off" }
+                                </button>
+                                <button
+                                    type="button"
+                                    class="control-button secondary"
+                                    onclick={link.callback(move |_| SessionViewMsg::DismissControlRequest(user_id))}
+                                >
+                                    { "Dismiss" }
+                                </button>
+                            </span>
+                        }
+                    }).collect::<Html>()
+                }
+                <button
+                    type="button"
+                    class="control-button secondary"
+                    onclick={link.callback(|_| SessionViewMsg::HandOffControl(None))}
+                >
+                    { "Release" }
+                </button>
+            </div>
+        }
+    }
+
     fn render_permission_dialog(&self, ctx: &Context<Self>) -> Html {
+        if Self::is_read_only(ctx) {
+            return html! {};
+        }
         if let Some(ref perm) = self.pending_permission {
             let link = ctx.link();
             let on_select_up = link.callback(|_| SessionViewMsg::PermissionSelectUp);
@@ -780,11 +2308,22 @@ impl SessionView {
                 link.callback(|(q_idx, answer)| SessionViewMsg::SetQuestionAnswer(q_idx, answer));
             let on_toggle_option = link
                 .callback(|(q_idx, opt_idx)| SessionViewMsg::ToggleQuestionOption(q_idx, opt_idx));
+            let on_remember_change = link.callback(SessionViewMsg::SetRememberRule);
+            let working_directory = &ctx.props().session.working_directory;
+            let (group_label, group_size) = match self.permission_group() {
+                Some((group, size)) => (Some(group.label(working_directory)), size),
+                None => (None, 1),
+            };
 
             html! {
                 <PermissionDialog
                     permission={perm.clone()}
                     selected={self.permission_selected}
+                    {group_size}
+                    {group_label}
+                    queued={self.queued_permissions.len()}
+                    remember={self.remember_rule}
+                    {on_remember_change}
                     multi_select_options={self.multi_select_options.clone()}
                     question_answers={self.question_answers.clone()}
                     dialog_ref={self.permission_ref.clone()}
@@ -802,18 +2341,148 @@ impl SessionView {
         }
     }
 
+    /// Where the session runs, above its transcript
+    fn render_host_header(&self, ctx: &Context<Self>) -> Html {
+        let session = &ctx.props().session;
+        let Some(host) = &session.host_info else {
+            return html! {};
+        };
+        let dirty = host.git_dirty == Some(true);
+        html! {
+            <div class="session-host-header">
+                <span class="host-summary">{ host.summary() }</span>
+                if let Some(branch) = &session.git_branch {
+                    <span class="host-branch">
+                        { branch }
+                        if dirty {
+                            <span class="host-dirty" title="Uncommitted changes">{ " *" }</span>
+                        }
+                    </span>
+                }
+                if let Some(remote) = &host.git_remote {
+                    <span class="host-remote">{ remote }</span>
+                }
+            </div>
+        }
+    }
+
+    /// Round trip to the server, flagged when slow
+    fn render_latency(&self) -> Html {
+        const SLOW_MS: u32 = 1000;
+        let Some(rtt_ms) = self.latency_ms else {
+            return html! {};
+        };
+        html! {
+            <span
+                class={classes!("latency-indicator", (rtt_ms > SLOW_MS).then_some("slow"))}
+                title="Round trip to the server"
+            >
+                { format!("{}ms", rtt_ms) }
+            </span>
+        }
+    }
+
+    /// Lint hints for the draft, if the user wants them
+    fn render_prompt_hints(&self) -> Html {
+        if !self.input_assist.lint_hints || self.input_value.trim().is_empty() {
+            return html! {};
+        }
+        let hints = lint_prompt(&self.input_value, !self.attachments.is_empty());
+        if hints.is_empty() {
+            return html! {};
+        }
+        html! {
+            <span class="prompt-hints">
+                { for hints.iter().map(|hint| html! {
+                    <span class="prompt-hint">{ hint.message() }</span>
+                }) }
+            </span>
+        }
+    }
+
+    /// Approximate size of the draft, in red when it would take a large
+    /// share of the context Claude has left
+    fn render_prompt_size(&self) -> Html {
+        if self.input_value.trim().is_empty() {
+            return html! {};
+        }
+        let tokens = prompt_size::estimate_tokens(&self.input_value);
+        let remaining = prompt_size::context_remaining(self.context_used);
+        let title = format!(
+            "Approximate prompt size; about {} tokens of context left",
+            format_tokens(remaining as i64)
+        );
+        html! {
+            <span
+                class={classes!(
+                    "prompt-size",
+                    prompt_size::is_large_prompt(tokens, remaining).then_some("large")
+                )}
+                {title}
+            >
+                { format!("~{} tokens", format_tokens(tokens as i64)) }
+            </span>
+        }
+    }
+
+    fn render_lifecycle(&self) -> Html {
+        let Some(lifecycle) = self.lifecycle else {
+            return html! {};
+        };
+        html! {
+            <span
+                class={classes!("lifecycle-indicator", lifecycle.as_str())}
+                title="What Claude is doing"
+            >
+                { lifecycle.label() }
+            </span>
+        }
+    }
+
+    /// The input with the segment still being recognized after it in grey,
+    /// drawn over the textarea while recording
     fn render_interim_transcription(&self) -> Html {
-        if let Some(ref interim) = self.interim_transcription {
-            let preview = if self.input_value.is_empty() {
-                interim.clone()
+        let Some(ref interim) = self.interim_transcription else {
+            return html! {};
+        };
+        let separator =
+            if self.input_value.is_empty() || self.input_value.ends_with(char::is_whitespace) {
+                ""
             } else {
-                format!("{} {}", self.input_value, interim)
+                " "
             };
-            html! {
-                <div class="interim-transcription">{ preview }</div>
-            }
-        } else {
-            html! {}
+        html! {
+            <div class="interim-transcription">
+                <span class="interim-line">
+                    <span class="interim-committed">{ &self.input_value }</span>
+                    { separator }
+                    <span class="interim-pending">{ interim }</span>
+                </span>
+            </div>
+        }
+    }
+
+    /// Offered once Claude's last turn has changed something
+    fn render_undo_button(&self, ctx: &Context<Self>) -> Html {
+        if !self.ws_connected || self.other_driver(ctx).is_some() {
+            return html! {};
+        }
+        let Some(turn) = last_turn(&self.messages).filter(LastTurn::changed_anything) else {
+            return html! {};
+        };
+        let onclick = ctx
+            .link()
+            .callback(move |_| SessionViewMsg::SetUndo(Some(turn.clone())));
+
+        html! {
+            <button
+                type="button"
+                class="undo-turn-button"
+                title="Undo last turn"
+                {onclick}
+            >
+                { "↶" }
+            </button>
         }
     }
 
@@ -826,17 +2495,29 @@ impl SessionView {
             let on_interim_transcription = link.callback(SessionViewMsg::VoiceInterimTranscription);
             let on_error = link.callback(SessionViewMsg::VoiceError);
             let button_ref = self.voice_button_ref.clone();
+            let props = ctx.props();
 
             html! {
+                <>
+                <VoiceLanguagePicker
+                    language={props.voice_language.clone()}
+                    auto_available={props.voice_auto_language}
+                    on_change={props.on_voice_language_change.clone()}
+                    disabled={self.is_recording}
+                />
                 <VoiceInput
                     {session_id}
+                    language_code={props.voice_language.clone()}
                     {on_recording_change}
                     {on_transcription}
                     on_interim_transcription={Some(on_interim_transcription)}
                     {on_error}
+                    settings={self.voice_settings}
+                    held={self.voice_held}
                     disabled={!self.ws_connected}
                     button_ref={Some(button_ref)}
                 />
+                </>
             }
         } else {
             html! {}
@@ -906,3 +2587,74 @@ impl SessionView {
         }
     }
 }
+
+/// The dashboard store, unless the view is shown outside the dashboard
+fn dashboard_store(ctx: &Context<SessionView>) -> Option<DashboardStore> {
+    ctx.link()
+        .context::<DashboardStore>(Callback::noop())
+        .map(|(store, _)| store)
+}
+
+/// Report a change in the session's state to the dashboard store
+fn dispatch(ctx: &Context<SessionView>, action: DashboardAction) {
+    if let Some(store) = dashboard_store(ctx) {
+        store.dispatch(action);
+    }
+}
+
+/// Whether a raw message is a result message (the end of a turn)
+/// Fetch the session's recorded turns
+async fn load_turns(session_id: Uuid) -> Option<Vec<TurnInfo>> {
+    let endpoint = utils::api_url(&format!("/api/sessions/{}/turns", session_id));
+    let response = Request::get(&endpoint).send().await.ok()?;
+    response.json::<Vec<TurnInfo>>().await.ok()
+}
+
+/// One line under a result: which turn it ended, its tokens and its tools
+fn render_turn_summary(turn: &TurnInfo) -> Html {
+    let record = &turn.record;
+    let mut parts = vec![
+        format!("Turn {}", record.turn_index + 1),
+        format!(
+            "{} in / {} out",
+            format_tokens(record.input_tokens),
+            format_tokens(record.output_tokens)
+        ),
+    ];
+    if !record.tools.is_empty() {
+        parts.push(record.tools.join(", "));
+    }
+    if record.compacted {
+        parts.push("context compacted".to_string());
+    }
+    html! {
+        <div
+            class={classes!("turn-summary", record.error.is_some().then_some("turn-error"))}
+            title={record.error.clone()}
+        >
+            { parts.join(" · ") }
+        </div>
+    }
+}
+
+/// Format token count with K/M suffix for readability
+fn format_tokens(count: i64) -> String {
+    if count >= 1_000_000 {
+        format!("{:.1}M", count as f64 / 1_000_000.0)
+    } else if count >= 1_000 {
+        format!("{:.1}K", count as f64 / 1_000.0)
+    } else {
+        count.to_string()
+    }
+}
+
+fn is_result_message(json: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(json)
+        .ok()
+        .and_then(|v| {
+            v.get("type")
+                .and_then(|t| t.as_str())
+                .map(|t| t == "result")
+        })
+        .unwrap_or(false)
+}
@@ -5,9 +5,11 @@
 //! - `types.rs` - Types specific to SessionView (re-exports from parent)
 //! - `websocket.rs` - WebSocket connection management
 //! - `history.rs` - Command history management
+//! - `search.rs` - In-session search (Ctrl+F)
 
 mod component;
 mod history;
+mod search;
 mod types;
 mod websocket;
 
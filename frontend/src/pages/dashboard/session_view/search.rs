@@ -0,0 +1,374 @@
+//! In-session search (Ctrl+F)
+//!
+//! Searches the messages already loaded in the view, without a round trip:
+//! message and result text, the user's prompts, errors, and every string in
+//! a tool call's input. Matching is case-insensitive. Matches are counted per
+//! occurrence so the counter and next/previous agree with what gets
+//! highlighted.
+
+use serde_json::Value;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+use web_sys::{Element, Node};
+
+/// Highlight names registered with the CSS Custom Highlight API
+const HIGHLIGHT_ALL: &str = "session-search";
+const HIGHLIGHT_CURRENT: &str = "session-search-current";
+
+/// One occurrence of the query
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchMatch {
+    /// Index into the view's messages
+    pub message: usize,
+    /// Which occurrence within that message, from 0
+    pub occurrence: usize,
+}
+
+/// Search box state while it is open
+#[derive(Default)]
+pub struct SessionSearch {
+    pub query: String,
+    /// Lowercased searchable text of each message, parallel to the view's
+    pub index: Vec<String>,
+    pub matches: Vec<SearchMatch>,
+    /// Position in `matches` of the selected match
+    pub current: usize,
+}
+
+impl SessionSearch {
+    /// Re-index after the messages changed, keeping the selection in place
+    /// where possible
+    pub fn reindex(&mut self, messages: &[String]) {
+        self.index = messages.iter().map(|m| searchable_text(m)).collect();
+        self.rematch();
+    }
+
+    pub fn set_query(&mut self, query: String) {
+        self.query = query;
+        self.current = 0;
+        self.rematch();
+    }
+
+    fn rematch(&mut self) {
+        self.matches = find_matches(&self.index, &self.query);
+        if self.current >= self.matches.len() {
+            self.current = self.matches.len().saturating_sub(1);
+        }
+    }
+
+    pub fn next(&mut self) {
+        if !self.matches.is_empty() {
+            self.current = (self.current + 1) % self.matches.len();
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if !self.matches.is_empty() {
+            self.current = (self.current + self.matches.len() - 1) % self.matches.len();
+        }
+    }
+
+    pub fn current_match(&self) -> Option<SearchMatch> {
+        self.matches.get(self.current).copied()
+    }
+
+    /// "3 of 12", or "No matches"
+    pub fn counter(&self) -> String {
+        if self.query.is_empty() {
+            String::new()
+        } else if self.matches.is_empty() {
+            "No matches".to_string()
+        } else {
+            format!("{} of {}", self.current + 1, self.matches.len())
+        }
+    }
+
+    /// Whether message `index` contains any match
+    pub fn is_hit(&self, index: usize) -> bool {
+        self.matches.iter().any(|m| m.message == index)
+    }
+}
+
+fn push_strings(value: &Value, out: &mut String) {
+    match value {
+        Value::String(s) => {
+            out.push_str(s);
+            out.push('\n');
+        }
+        Value::Array(items) => items.iter().for_each(|v| push_strings(v, out)),
+        Value::Object(fields) => fields.values().for_each(|v| push_strings(v, out)),
+        _ => {}
+    }
+}
+
+/// Lowercased text a message contributes to the search
+pub fn searchable_text(json: &str) -> String {
+    let Ok(value) = serde_json::from_str::<Value>(json) else {
+        return String::new();
+    };
+    let mut out = String::new();
+    match value.get("type").and_then(Value::as_str) {
+        Some("assistant") | Some("user") => {
+            if let Some(text) = value.get("content").and_then(Value::as_str) {
+                out.push_str(text);
+                out.push('\n');
+            }
+            let blocks = value
+                .pointer("/message/content")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten();
+            for block in blocks {
+                match block.get("type").and_then(Value::as_str) {
+                    Some("text") => push_strings(&block["text"], &mut out),
+                    Some("tool_use") => {
+                        push_strings(&block["name"], &mut out);
+                        push_strings(&block["input"], &mut out);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Some("result") => push_strings(&value["result"], &mut out),
+        Some("error") => {
+            push_strings(&value["message"], &mut out);
+            push_strings(&value["error"], &mut out);
+        }
+        _ => {}
+    }
+    out.to_lowercase()
+}
+
+/// Every occurrence of `query` (case-insensitive) in an index built by
+/// [`searchable_text`], in message order
+pub fn find_matches(index: &[String], query: &str) -> Vec<SearchMatch> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    index
+        .iter()
+        .enumerate()
+        .flat_map(|(message, text)| {
+            text.match_indices(&query)
+                .enumerate()
+                .map(move |(occurrence, _)| SearchMatch {
+                    message,
+                    occurrence,
+                })
+        })
+        .collect()
+}
+
+/// Highlight occurrences of `query` in the rendered slots marked as hits.
+/// `nth` is the selected match's position within the `.search-current`
+/// slot, which is scrolled into view when `scroll` is set. Uses the CSS
+/// Custom Highlight API so the rendered DOM is left untouched; where the
+/// browser lacks it, the slot outlines from the stylesheet are all that
+/// shows.
+pub fn highlight(container: &Element, query: &str, nth: usize, scroll: bool) {
+    let current_slot = container.query_selector(".search-current").ok().flatten();
+    if scroll {
+        if let Some(slot) = &current_slot {
+            slot.scroll_into_view_with_bool(false);
+        }
+    }
+
+    let Some(registry) = highlight_registry() else {
+        return;
+    };
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        clear_highlights();
+        return;
+    }
+
+    let all = js_sys::Array::new();
+    let selected = js_sys::Array::new();
+    if let Ok(slots) = container.query_selector_all(".search-hit") {
+        for i in 0..slots.length() {
+            let Some(slot) = slots.get(i).and_then(|n| n.dyn_into::<Element>().ok()) else {
+                continue;
+            };
+            let is_current = current_slot
+                .as_ref()
+                .is_some_and(|c| c.is_same_node(Some(&slot)));
+            let ranges = text_ranges(&slot, &query);
+            if is_current {
+                // The rendered text can differ from the index (collapsed
+                // tool input, markdown), so fall back to the first range
+                if let Some(range) = ranges.get(nth).or(ranges.first()) {
+                    selected.push(range);
+                    let element = range
+                        .dyn_ref::<web_sys::Range>()
+                        .and_then(|r| r.start_container().ok())
+                        .and_then(|n| n.parent_element());
+                    if let (true, Some(element)) = (scroll, element) {
+                        element.scroll_into_view_with_bool(false);
+                    }
+                }
+            }
+            ranges.iter().for_each(|r| {
+                all.push(r);
+            });
+        }
+    }
+
+    set_highlight(&registry, HIGHLIGHT_ALL, &all);
+    set_highlight(&registry, HIGHLIGHT_CURRENT, &selected);
+}
+
+/// Remove search highlights, e.g. when the search box closes
+pub fn clear_highlights() {
+    if let Some(registry) = highlight_registry() {
+        for name in [HIGHLIGHT_ALL, HIGHLIGHT_CURRENT] {
+            let _ = call_method(&registry, "delete", &js_sys::Array::of1(&name.into()));
+        }
+    }
+}
+
+/// `CSS.highlights`, where supported
+fn highlight_registry() -> Option<JsValue> {
+    let css = js_sys::Reflect::get(&js_sys::global(), &"CSS".into()).ok()?;
+    let registry = js_sys::Reflect::get(&css, &"highlights".into()).ok()?;
+    (!registry.is_undefined()).then_some(registry)
+}
+
+fn call_method(target: &JsValue, name: &str, args: &js_sys::Array) -> Option<JsValue> {
+    let method = js_sys::Reflect::get(target, &name.into())
+        .ok()?
+        .dyn_into::<js_sys::Function>()
+        .ok()?;
+    method.apply(target, args).ok()
+}
+
+fn set_highlight(registry: &JsValue, name: &str, ranges: &js_sys::Array) {
+    let Some(constructor) = js_sys::Reflect::get(&js_sys::global(), &"Highlight".into())
+        .ok()
+        .and_then(|c| c.dyn_into::<js_sys::Function>().ok())
+    else {
+        return;
+    };
+    if let Ok(highlight) = js_sys::Reflect::construct(&constructor, ranges) {
+        let _ = call_method(
+            registry,
+            "set",
+            &js_sys::Array::of2(&name.into(), &highlight),
+        );
+    }
+}
+
+/// Ranges covering each occurrence of `query` within one text node of `root`
+fn text_ranges(root: &Element, query: &str) -> Vec<JsValue> {
+    let Some(document) = root.owner_document() else {
+        return Vec::new();
+    };
+    // NodeFilter.SHOW_TEXT
+    let Ok(walker) = document.create_tree_walker_with_what_to_show(root, 0x4) else {
+        return Vec::new();
+    };
+    let mut ranges = Vec::new();
+    while let Ok(Some(node)) = walker.next_node() {
+        let text = node.text_content().unwrap_or_default();
+        for start in utf16_match_indices(&text, query) {
+            if let Some(range) = range_for(&document, &node, start, query) {
+                ranges.push(range.into());
+            }
+        }
+    }
+    ranges
+}
+
+fn range_for(
+    document: &web_sys::Document,
+    node: &Node,
+    start: u32,
+    query: &str,
+) -> Option<web_sys::Range> {
+    let range = document.create_range().ok()?;
+    let len = query.encode_utf16().count() as u32;
+    range.set_start(node, start).ok()?;
+    range.set_end(node, start + len).ok()?;
+    Some(range)
+}
+
+/// Case-insensitive match offsets in UTF-16 code units, as DOM ranges count
+fn utf16_match_indices(text: &str, query: &str) -> Vec<u32> {
+    let lower = text.to_lowercase();
+    // Lowercasing can change lengths for a few scripts; skip those nodes
+    // rather than highlight the wrong characters
+    if lower.len() != text.len() {
+        return Vec::new();
+    }
+    lower
+        .match_indices(query)
+        .map(|(byte, _)| lower[..byte].encode_utf16().count() as u32)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_indexes_text_and_tool_input() {
+        let text = searchable_text(
+            r#"{"type":"assistant","message":{"content":[
+                {"type":"text","text":"Running the Tests"},
+                {"type":"tool_use","id":"t1","name":"Bash","input":{"command":"cargo test --workspace"}}
+            ]}}"#,
+        );
+        assert!(text.contains("running the tests"));
+        assert!(text.contains("bash"));
+        assert!(text.contains("cargo test --workspace"));
+    }
+
+    #[test]
+    fn test_skips_tool_results() {
+        let text = searchable_text(
+            r#"{"type":"user","message":{"content":[
+                {"type":"tool_result","tool_use_id":"t1","content":"secret output"}
+            ]}}"#,
+        );
+        assert!(text.is_empty());
+    }
+
+    #[test]
+    fn test_counts_each_occurrence() {
+        let index = vec![
+            "cargo test\ncargo build".to_string(),
+            "nothing here".to_string(),
+            "more cargo".to_string(),
+        ];
+        let matches = find_matches(&index, "CARGO");
+        assert_eq!(matches.len(), 3);
+        assert_eq!(
+            matches[1],
+            SearchMatch {
+                message: 0,
+                occurrence: 1
+            }
+        );
+        assert_eq!(matches[2].message, 2);
+        assert!(find_matches(&index, "  ").is_empty());
+    }
+
+    #[test]
+    fn test_navigation_wraps() {
+        let mut search = SessionSearch::default();
+        search.reindex(&[
+            r#"{"type":"user","content":"fix the bug"}"#.to_string(),
+            r#"{"type":"result","result":"Fixed the bug"}"#.to_string(),
+        ]);
+        search.set_query("bug".to_string());
+        assert_eq!(search.counter(), "1 of 2");
+        search.next();
+        assert_eq!(search.current_match().unwrap().message, 1);
+        search.next();
+        assert_eq!(search.counter(), "1 of 2");
+        search.previous();
+        assert_eq!(search.counter(), "2 of 2");
+        search.set_query("nope".to_string());
+        assert_eq!(search.counter(), "No matches");
+    }
+}
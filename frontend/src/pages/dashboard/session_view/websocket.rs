@@ -1,31 +1,58 @@
 //! WebSocket connection management for SessionView
 
 use crate::utils;
+use futures_util::future::{self, Either};
 use futures_util::{SinkExt, StreamExt};
+use gloo::timers::future::TimeoutFuture;
 use gloo_net::websocket::{futures::WebSocket, Message};
-use shared::ProxyMessage;
+use shared::compression::decompress_content;
+use shared::{
+    AnnotationInfo, BudgetStatus, ContentEncoding, IncidentInfo, PresenceViewer, ProxyMessage,
+    ReactionSummary, SessionLifecycle, TraceContext, HEARTBEAT_INTERVAL_MS, HEARTBEAT_TIMEOUT_MS,
+    PROTOCOL_VERSION,
+};
 use uuid::Uuid;
 use wasm_bindgen_futures::spawn_local;
 use yew::Callback;
 
 use super::types::{PendingPermission, WsSender};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 
 /// Messages that can be sent from WebSocket handlers
 pub enum WsEvent {
     Connected(WsSender),
     Error(String),
-    Output(String),
+    /// Claude output, with the stored message ID and sequence when the backend provides them
+    Output(String, Option<Uuid>, Option<u64>),
     Permission(PendingPermission),
+    /// A request was answered elsewhere (e.g. by the escalation default)
+    PermissionResolved(String),
     BranchChanged(Option<String>),
+    Reactions(Uuid, Vec<ReactionSummary>),
+    Presence(Vec<PresenceViewer>),
+    /// Another viewer asked the driver for control (user ID, display name)
+    ControlRequested(Uuid, String),
+    /// A diagnostic bundle was generated for this session
+    Incident(IncidentInfo),
+    /// An external tool annotated messages in this session
+    Annotation(AnnotationInfo),
+    /// The session reached, or came back under, a budget cap
+    Budget(BudgetStatus),
+    /// The session's Claude process changed state
+    Lifecycle(SessionLifecycle),
+    /// Round-trip time to the server, in milliseconds
+    Latency(u32),
+    /// This page and the server speak different protocol versions
+    Outdated(String),
 }
 
-/// Connect to WebSocket and start receiving messages.
+/// Connect to WebSocket and start receiving messages. `last_seq` is the
+/// sequence of the last message already shown; only later ones are replayed.
 /// Returns immediately, spawns async task to handle connection.
 pub fn connect_websocket(
     session_id: Uuid,
-    replay_after: Option<String>,
+    last_seq: Option<u64>,
     resuming: bool,
     on_event: Callback<WsEvent>,
 ) {
@@ -42,8 +69,13 @@ pub fn connect_websocket(
                     working_directory: String::new(),
                     resuming,
                     git_branch: None,
-                    replay_after,
+                    git_repo: None,
+                    host_info: None,
+                    replay_after: None,
+                    last_seq,
                     client_version: None,
+                    accept_encodings: vec![ContentEncoding::Deflate],
+                    protocol_version: Some(PROTOCOL_VERSION),
                 };
 
                 if let Ok(json) = serde_json::to_string(&register_msg) {
@@ -54,37 +86,98 @@ pub fn connect_websocket(
                 }
 
                 let sender = Rc::new(RefCell::new(Some(sender)));
-                on_event.emit(WsEvent::Connected(sender));
+                on_event.emit(WsEvent::Connected(sender.clone()));
 
-                while let Some(msg) = receiver.next().await {
-                    match msg {
-                        Ok(Message::Text(text)) => {
-                            if let Ok(proxy_msg) = serde_json::from_str::<ProxyMessage>(&text) {
-                                handle_proxy_message(proxy_msg, &on_event);
+                let last_pong = Cell::new(None);
+                let receive = async {
+                    while let Some(msg) = receiver.next().await {
+                        match msg {
+                            Ok(Message::Text(text)) => {
+                                match serde_json::from_str::<ProxyMessage>(&text) {
+                                    Ok(ProxyMessage::Pong { sent_at }) => {
+                                        let now = js_sys::Date::now();
+                                        last_pong.set(Some(now));
+                                        let rtt = (now as u64).saturating_sub(sent_at);
+                                        on_event.emit(WsEvent::Latency(rtt as u32));
+                                    }
+                                    Ok(proxy_msg) => handle_proxy_message(proxy_msg, &on_event),
+                                    Err(_) => {}
+                                }
                             }
+                            Err(e) => {
+                                tracing::error!("WebSocket error: {:?}", e);
+                                on_event.emit(WsEvent::Error(format!("{:?}", e)));
+                                break;
+                            }
+                            _ => {}
                         }
-                        Err(e) => {
-                            log::error!("WebSocket error: {:?}", e);
-                            on_event.emit(WsEvent::Error(format!("{:?}", e)));
-                            break;
-                        }
-                        _ => {}
                     }
+                };
+
+                let heartbeat = run_heartbeat(&sender, &last_pong);
+                let ended = future::select(Box::pin(receive), Box::pin(heartbeat)).await;
+                if let Either::Right(_) = ended {
+                    tracing::warn!("WebSocket heartbeat timed out");
+                    on_event.emit(WsEvent::Error("Heartbeat timed out".to_string()));
                 }
             }
             Err(e) => {
-                log::error!("Failed to connect WebSocket: {:?}", e);
+                tracing::error!("Failed to connect WebSocket: {:?}", e);
                 on_event.emit(WsEvent::Error(format!("{:?}", e)));
             }
         }
     });
 }
 
+/// Ping the server until it stops answering. Returns only on timeout; the
+/// caller drops it when the connection ends for any other reason.
+async fn run_heartbeat(sender: &WsSender, last_pong: &Cell<Option<f64>>) {
+    loop {
+        let now = js_sys::Date::now();
+        if last_pong
+            .get()
+            .is_some_and(|at| now - at > HEARTBEAT_TIMEOUT_MS as f64)
+        {
+            return;
+        }
+        send_message(
+            sender,
+            ProxyMessage::Ping {
+                sent_at: now as u64,
+            },
+        );
+        TimeoutFuture::new(HEARTBEAT_INTERVAL_MS as u32).await;
+    }
+}
+
 /// Handle incoming ProxyMessage and emit appropriate events
 fn handle_proxy_message(msg: ProxyMessage, on_event: &Callback<WsEvent>) {
     match msg {
-        ProxyMessage::ClaudeOutput { content } => {
-            on_event.emit(WsEvent::Output(content.to_string()));
+        ProxyMessage::ClaudeOutput {
+            content,
+            message_id,
+            message_seq,
+            traceparent,
+            content_encoding,
+        } => {
+            if let Some(ctx) = traceparent
+                .as_deref()
+                .and_then(TraceContext::from_traceparent)
+            {
+                tracing::debug!("output trace_id={}", ctx.trace_id_hex());
+            }
+            let content = match decompress_content(content, content_encoding) {
+                Ok(content) => content,
+                Err(e) => {
+                    tracing::error!("Failed to decompress output: {}", e);
+                    return;
+                }
+            };
+            on_event.emit(WsEvent::Output(
+                content.to_string(),
+                message_id,
+                message_seq,
+            ));
         }
         ProxyMessage::PermissionRequest {
             request_id,
@@ -99,12 +192,40 @@ fn handle_proxy_message(msg: ProxyMessage, on_event: &Callback<WsEvent>) {
                 permission_suggestions,
             }));
         }
-        ProxyMessage::Error { message } => {
+        ProxyMessage::PermissionResolved { request_id, .. } => {
+            on_event.emit(WsEvent::PermissionResolved(request_id));
+        }
+        ProxyMessage::StateChanged { state } => {
+            on_event.emit(WsEvent::Lifecycle(state));
+        }
+        ProxyMessage::Hello {
+            protocol_version,
+            server_version,
+            error,
+            ..
+        } => {
+            if let Some(error) = error {
+                on_event.emit(WsEvent::Outdated(error));
+            } else if protocol_version != PROTOCOL_VERSION {
+                tracing::info!(
+                    "Server {} speaks protocol {}, page speaks {}",
+                    server_version,
+                    protocol_version,
+                    PROTOCOL_VERSION
+                );
+                on_event.emit(WsEvent::Outdated(format!(
+                    "The server was updated to {} since this page loaded.",
+                    server_version
+                )));
+            }
+        }
+        ProxyMessage::Error { code, message } => {
             let error_json = serde_json::json!({
                 "type": "error",
+                "code": code,
                 "message": message
             });
-            on_event.emit(WsEvent::Output(error_json.to_string()));
+            on_event.emit(WsEvent::Output(error_json.to_string(), None, None));
         }
         ProxyMessage::SessionUpdate {
             session_id: _,
@@ -112,6 +233,35 @@ fn handle_proxy_message(msg: ProxyMessage, on_event: &Callback<WsEvent>) {
         } => {
             on_event.emit(WsEvent::BranchChanged(git_branch));
         }
+        ProxyMessage::MessageReactions {
+            session_id: _,
+            message_id,
+            reactions,
+        } => {
+            on_event.emit(WsEvent::Reactions(message_id, reactions));
+        }
+        ProxyMessage::Presence {
+            session_id: _,
+            viewers,
+        } => {
+            on_event.emit(WsEvent::Presence(viewers));
+        }
+        ProxyMessage::ControlRequested {
+            session_id: _,
+            user_id,
+            name,
+        } => {
+            on_event.emit(WsEvent::ControlRequested(user_id, name));
+        }
+        ProxyMessage::IncidentCreated { incident } => {
+            on_event.emit(WsEvent::Incident(incident));
+        }
+        ProxyMessage::MessageAnnotation { annotation, .. } => {
+            on_event.emit(WsEvent::Annotation(annotation));
+        }
+        ProxyMessage::BudgetStatus { status, .. } => {
+            on_event.emit(WsEvent::Budget(status));
+        }
         _ => {}
     }
 }
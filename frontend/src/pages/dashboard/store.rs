@@ -0,0 +1,224 @@
+//! Dashboard store
+//!
+//! UI state shared across the dashboard: which session is focused, which are
+//! awaiting input, connected, or paused, and which dialogs are open.
+//! `DashboardPage` provides it as context, so components read it with
+//! selectors and change it with typed actions instead of taking a callback
+//! per field through props.
+
+use shared::SessionInfo;
+use std::collections::HashSet;
+use std::rc::Rc;
+use uuid::Uuid;
+use yew::prelude::*;
+
+/// Handle to the store, as provided by `DashboardPage`
+pub type DashboardStore = UseReducerHandle<DashboardState>;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DashboardState {
+    /// Index of the focused session in the rail
+    pub focused_index: usize,
+    /// Whether the first focus after loading has been chosen
+    pub initial_focus_set: bool,
+    /// Sessions that have been opened, and so have a live view
+    pub activated: HashSet<Uuid>,
+    /// Sessions waiting on the user
+    pub awaiting: HashSet<Uuid>,
+    /// Sessions whose view has a live socket
+    pub connected: HashSet<Uuid>,
+    /// Sessions the user has paused (kept in localStorage)
+    pub paused: HashSet<Uuid>,
+    /// Whether inactive sessions are hidden from the rail (kept in localStorage)
+    pub inactive_hidden: bool,
+    pub show_new_session: bool,
+    pub show_report: bool,
+    /// Session whose permission history is open
+    pub permission_history: Option<Uuid>,
+    /// Session the user asked to leave, awaiting confirmation
+    pub pending_leave: Option<Uuid>,
+}
+
+pub enum DashboardAction {
+    /// Focus the session at this rail index, opening it
+    Focus {
+        index: usize,
+        session_id: Option<Uuid>,
+    },
+    /// Focus the first session to show once sessions load; later ones are ignored
+    InitialFocus {
+        index: usize,
+        session_id: Option<Uuid>,
+    },
+    /// Open a session's view without focusing it
+    Activate(Uuid),
+    SetAwaiting(Uuid, bool),
+    SetConnected(Uuid, bool),
+    TogglePaused(Uuid),
+    ToggleInactiveHidden,
+    ShowNewSession(bool),
+    ToggleNewSession,
+    ShowReport(bool),
+    ShowPermissionHistory(Option<Uuid>),
+    /// Ask to leave a session (Some) or drop the request (None)
+    SetPendingLeave(Option<Uuid>),
+}
+
+impl DashboardState {
+    pub fn new(paused: HashSet<Uuid>, inactive_hidden: bool) -> Self {
+        Self {
+            paused,
+            inactive_hidden,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_paused(&self, session_id: &Uuid) -> bool {
+        self.paused.contains(session_id)
+    }
+
+    /// Sessions waiting on the user, not counting paused ones
+    pub fn waiting_count(&self) -> usize {
+        self.awaiting
+            .iter()
+            .filter(|id| !self.paused.contains(id))
+            .count()
+    }
+
+    /// Opened, unpaused sessions whose socket is down
+    pub fn disconnected_count(&self, sessions: &[SessionInfo]) -> usize {
+        sessions
+            .iter()
+            .filter(|s| {
+                self.activated.contains(&s.id)
+                    && !self.paused.contains(&s.id)
+                    && !self.connected.contains(&s.id)
+            })
+            .count()
+    }
+
+    pub fn focused_session<'a>(&self, sessions: &'a [SessionInfo]) -> Option<&'a SessionInfo> {
+        sessions.get(self.focused_index)
+    }
+}
+
+fn set_member(set: &mut HashSet<Uuid>, id: Uuid, member: bool) {
+    if member {
+        set.insert(id);
+    } else {
+        set.remove(&id);
+    }
+}
+
+impl Reducible for DashboardState {
+    type Action = DashboardAction;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        let mut next = (*self).clone();
+        match action {
+            DashboardAction::Focus { index, session_id } => {
+                next.focused_index = index;
+                next.activated.extend(session_id);
+            }
+            DashboardAction::InitialFocus { index, session_id } => {
+                if !next.initial_focus_set {
+                    next.focused_index = index;
+                    next.activated.extend(session_id);
+                    next.initial_focus_set = true;
+                }
+            }
+            DashboardAction::Activate(id) => {
+                next.activated.insert(id);
+            }
+            DashboardAction::SetAwaiting(id, awaiting) => {
+                set_member(&mut next.awaiting, id, awaiting)
+            }
+            DashboardAction::SetConnected(id, connected) => {
+                set_member(&mut next.connected, id, connected)
+            }
+            DashboardAction::TogglePaused(id) => {
+                let paused = !next.paused.contains(&id);
+                set_member(&mut next.paused, id, paused);
+            }
+            DashboardAction::ToggleInactiveHidden => next.inactive_hidden = !next.inactive_hidden,
+            DashboardAction::ShowNewSession(show) => next.show_new_session = show,
+            DashboardAction::ToggleNewSession => next.show_new_session = !next.show_new_session,
+            DashboardAction::ShowReport(show) => next.show_report = show,
+            DashboardAction::ShowPermissionHistory(id) => next.permission_history = id,
+            DashboardAction::SetPendingLeave(id) => next.pending_leave = id,
+        }
+        // Sessions report their state often; skip re-rendering when nothing changed
+        if next == *self {
+            self
+        } else {
+            Rc::new(next)
+        }
+    }
+}
+
+/// The dashboard store provided by `DashboardPage`
+#[hook]
+pub fn use_dashboard_store() -> DashboardStore {
+    use_context::<DashboardStore>().expect("DashboardStore is provided by DashboardPage")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reduce(state: DashboardState, action: DashboardAction) -> Rc<DashboardState> {
+        Rc::new(state).reduce(action)
+    }
+
+    #[test]
+    fn test_initial_focus_only_once() {
+        let a = Uuid::new_v4();
+        let b = Uuid::new_v4();
+        let state = reduce(
+            DashboardState::default(),
+            DashboardAction::InitialFocus {
+                index: 2,
+                session_id: Some(a),
+            },
+        );
+        assert_eq!(state.focused_index, 2);
+        assert!(state.activated.contains(&a));
+
+        let state = state.reduce(DashboardAction::InitialFocus {
+            index: 0,
+            session_id: Some(b),
+        });
+        assert_eq!(state.focused_index, 2);
+        assert!(!state.activated.contains(&b));
+    }
+
+    #[test]
+    fn test_waiting_count_skips_paused() {
+        let a = Uuid::new_v4();
+        let b = Uuid::new_v4();
+        let state = reduce(
+            DashboardState::default(),
+            DashboardAction::SetAwaiting(a, true),
+        )
+        .reduce(DashboardAction::SetAwaiting(b, true))
+        .reduce(DashboardAction::TogglePaused(b));
+        assert_eq!(state.waiting_count(), 1);
+
+        let state = state
+            .reduce(DashboardAction::TogglePaused(b))
+            .reduce(DashboardAction::SetAwaiting(a, false));
+        assert_eq!(state.waiting_count(), 1);
+        assert!(!state.is_paused(&b));
+    }
+
+    #[test]
+    fn test_unchanged_state_is_reused() {
+        let a = Uuid::new_v4();
+        let state = reduce(
+            DashboardState::default(),
+            DashboardAction::SetConnected(a, true),
+        );
+        let again = state.clone().reduce(DashboardAction::SetConnected(a, true));
+        assert!(Rc::ptr_eq(&state, &again));
+    }
+}
@@ -34,11 +34,13 @@ pub type WsSender = Rc<
 /// Message data from the API
 #[derive(Clone, PartialEq, Deserialize)]
 pub struct MessageData {
+    pub id: Uuid,
     #[allow(dead_code)]
     pub role: String,
     pub content: String,
-    /// ISO 8601 timestamp when message was created
-    pub created_at: String,
+    /// Position in the session's history, sent back when reconnecting
+    #[serde(default)]
+    pub seq: Option<u64>,
 }
 
 /// Response from messages API endpoint
@@ -151,3 +153,150 @@ pub fn format_permission_input(tool_name: &str, input: &serde_json::Value) -> St
         _ => serde_json::to_string_pretty(input).unwrap_or_else(|_| format!("{:?}", input)),
     }
 }
+
+/// Tool and directory shared by related permission requests, so they can be
+/// approved together
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PermissionGroup {
+    pub tool_name: String,
+    pub directory: String,
+}
+
+impl PermissionGroup {
+    /// Group for a file tool request: the file's directory, or the searched
+    /// directory for Glob and Grep
+    pub fn of(perm: &PendingPermission) -> Option<Self> {
+        let field = |name: &str| perm.input.get(name).and_then(|v| v.as_str());
+        let directory = match perm.tool_name.as_str() {
+            "Read" | "Edit" | "Write" | "NotebookEdit" => {
+                let path = field("file_path").or_else(|| field("notebook_path"))?;
+                path.rsplit_once('/').map(|(dir, _)| dir)?
+            }
+            "Glob" | "Grep" => field("path")?,
+            _ => return None,
+        };
+        let directory = directory.trim_end_matches('/');
+        (!directory.is_empty()).then(|| Self {
+            tool_name: perm.tool_name.clone(),
+            directory: directory.to_string(),
+        })
+    }
+
+    /// Session rule allowing the tool anywhere under the directory. Absolute
+    /// paths take a leading `//` in Claude's rule syntax.
+    pub fn rule(&self) -> shared::PermissionSuggestion {
+        let prefix = if self.directory.starts_with('/') {
+            "/"
+        } else {
+            ""
+        };
+        shared::PermissionSuggestion {
+            suggestion_type: "addRules".to_string(),
+            destination: "session".to_string(),
+            mode: None,
+            behavior: Some("allow".to_string()),
+            rules: Some(vec![serde_json::json!({
+                "toolName": self.tool_name,
+                "ruleContent": format!("{}{}/**", prefix, self.directory),
+            })]),
+        }
+    }
+
+    /// e.g. "Read under src/", relative to the working directory when inside it
+    pub fn label(&self, working_directory: &str) -> String {
+        let relative = self
+            .directory
+            .strip_prefix(working_directory.trim_end_matches('/'))
+            .and_then(|rest| rest.strip_prefix('/'))
+            .unwrap_or(&self.directory);
+        format!("{} under {}/", self.tool_name, relative)
+    }
+}
+
+/// An answer offered in the standard permission dialog
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PermissionChoice {
+    Allow,
+    AllowAndRemember,
+    /// Allow this request and the queued ones in its group
+    AllowGroup,
+    /// Allow the group and add a session rule for its tool and directory
+    AllowGroupAndRule,
+    Deny,
+}
+
+/// Choices in display order. Group choices appear when other queued
+/// requests share this one's group.
+pub fn permission_choices(has_suggestions: bool, group_size: usize) -> Vec<PermissionChoice> {
+    let mut choices = vec![PermissionChoice::Allow];
+    if has_suggestions {
+        choices.push(PermissionChoice::AllowAndRemember);
+    }
+    if group_size > 1 {
+        choices.push(PermissionChoice::AllowGroup);
+        choices.push(PermissionChoice::AllowGroupAndRule);
+    }
+    choices.push(PermissionChoice::Deny);
+    choices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn perm(tool_name: &str, input: serde_json::Value) -> PendingPermission {
+        PendingPermission {
+            request_id: "r".to_string(),
+            tool_name: tool_name.to_string(),
+            input,
+            permission_suggestions: vec![],
+        }
+    }
+
+    #[test]
+    fn test_permission_group() {
+        let a = PermissionGroup::of(&perm("Read", json!({"file_path": "/w/src/a.rs"})));
+        let b = PermissionGroup::of(&perm("Read", json!({"file_path": "/w/src/b.rs"})));
+        assert_eq!(a, b);
+        assert_eq!(a.as_ref().unwrap().directory, "/w/src");
+
+        let grep = PermissionGroup::of(&perm("Grep", json!({"pattern": "x", "path": "/w/src/"})));
+        assert_eq!(grep.unwrap().directory, "/w/src");
+
+        assert_eq!(
+            PermissionGroup::of(&perm("Read", json!({"file_path": "a.rs"}))),
+            None
+        );
+        assert_eq!(
+            PermissionGroup::of(&perm("Bash", json!({"command": "ls"}))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_permission_group_rule_and_label() {
+        let group = PermissionGroup {
+            tool_name: "Read".to_string(),
+            directory: "/w/src".to_string(),
+        };
+        let rule = group.rule();
+        assert_eq!(rule.destination, "session");
+        assert_eq!(
+            rule.rules.unwrap()[0],
+            json!({"toolName": "Read", "ruleContent": "//w/src/**"})
+        );
+        assert_eq!(group.label("/w"), "Read under src/");
+        assert_eq!(group.label("/other"), "Read under /w/src/");
+    }
+
+    #[test]
+    fn test_permission_choices() {
+        use PermissionChoice::*;
+        assert_eq!(permission_choices(false, 1), vec![Allow, Deny]);
+        assert_eq!(
+            permission_choices(true, 3),
+            vec![Allow, AllowAndRemember, AllowGroup, AllowGroupAndRule, Deny]
+        );
+    }
+}
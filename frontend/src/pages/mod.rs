@@ -1,6 +1,9 @@
 pub mod access_denied;
+pub mod activity;
 pub mod admin;
 pub mod banned;
 pub mod dashboard;
+pub mod search;
 pub mod settings;
 pub mod splash;
+pub mod usage;
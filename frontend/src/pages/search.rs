@@ -0,0 +1,604 @@
+//! Transcript search page
+//!
+//! Searches every session the user belongs to via `GET /api/search`. The
+//! query language is parsed by the backend; the "?" popover documents it
+//! and inserts examples into the query. Picking a result opens its session
+//! on the dashboard.
+//!
+//! When the server has an embedding provider, a "Semantic" toggle ranks
+//! prompts and replies by meaning instead of matching words.
+//!
+//! Queries can be saved (`/api/saved-searches`) to re-run later; a saved
+//! search with alerting on is checked against new messages by the backend,
+//! which raises a banner on the dashboard when it matches.
+
+use crate::utils;
+use crate::Route;
+use gloo_net::http::Request;
+use shared::{
+    AppConfig, SaveSearchRequest, SavedSearch, SearchHit, SearchResponse, UpdateSavedSearchRequest,
+};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+/// Filters shown in the syntax popover: (example, description)
+const SYNTAX_HELP: &[(&str, &str)] = &[
+    (
+        "session:api",
+        "Sessions whose name contains \"api\", or an id prefix",
+    ),
+    ("tool:Bash", "Assistant turns that called a tool"),
+    (
+        "model:opus",
+        "Messages from a model whose name contains \"opus\"",
+    ),
+    (
+        "after:2026-01-01",
+        "On or after a date (UTC); also 7d or 12h ago",
+    ),
+    (
+        "before:7d",
+        "Before a date or time, e.g. 2026-01-31T18:00:00",
+    ),
+    ("is:error", "Failed results, errors, and failed tool calls"),
+    (
+        "\"two words\"",
+        "Quote text or filter values containing spaces",
+    ),
+];
+
+/// "2026-01-23 14:05" in local time
+fn format_timestamp(ts: &str) -> String {
+    let date = js_sys::Date::new(&ts.into());
+    if date.get_time().is_nan() {
+        return ts.to_string();
+    }
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}",
+        date.get_full_year(),
+        date.get_month() + 1,
+        date.get_date(),
+        date.get_hours(),
+        date.get_minutes()
+    )
+}
+
+/// A search to run: the query and how to match its text
+struct SearchRun {
+    query: String,
+    regex: bool,
+    semantic: bool,
+}
+
+fn search_url(run: &SearchRun) -> String {
+    let mut path = format!(
+        "/api/search?q={}",
+        js_sys::encode_uri_component(run.query.trim())
+    );
+    if run.semantic {
+        path.push_str("&mode=semantic");
+    } else if run.regex {
+        path.push_str("&regex=true");
+    }
+    utils::api_url(&path)
+}
+
+/// Fetch the user's saved searches into `saved`
+fn load_saved(saved: UseStateHandle<Vec<SavedSearch>>) {
+    spawn_local(async move {
+        let url = utils::api_url("/api/saved-searches");
+        match Request::get(&url).send().await {
+            Ok(response) if response.ok() => {
+                if let Ok(list) = response.json::<Vec<SavedSearch>>().await {
+                    saved.set(list);
+                }
+            }
+            Ok(response) => {
+                tracing::warn!("Failed to load saved searches: {}", response.status())
+            }
+            Err(e) => tracing::warn!("Failed to load saved searches: {:?}", e),
+        }
+    });
+}
+
+#[function_component(SearchPage)]
+pub fn search_page() -> Html {
+    let navigator = use_navigator().unwrap();
+    let query = use_state(String::new);
+    let regex = use_state(|| false);
+    let semantic = use_state(|| false);
+    // Whether the server can do semantic search, from /api/config
+    let semantic_available = use_state(|| false);
+    let help_open = use_state(|| false);
+    let results = use_state(|| None::<SearchResponse>);
+    let loading = use_state(|| false);
+    let error = use_state(|| None::<String>);
+    let input_ref = use_node_ref();
+    let saved = use_state(Vec::<SavedSearch>::new);
+    // Name typed into the save form, while it's open
+    let save_name = use_state(|| None::<String>);
+    let save_alert = use_state(|| false);
+
+    {
+        let input_ref = input_ref.clone();
+        let saved = saved.clone();
+        let semantic_available = semantic_available.clone();
+        use_effect_with((), move |_| {
+            if let Some(input) = input_ref.cast::<HtmlInputElement>() {
+                let _ = input.focus();
+            }
+            load_saved(saved);
+            spawn_local(async move {
+                if let Ok(response) = Request::get(&utils::api_url("/api/config")).send().await {
+                    if let Ok(config) = response.json::<AppConfig>().await {
+                        semantic_available.set(config.semantic_search);
+                    }
+                }
+            });
+            || ()
+        });
+    }
+
+    let run_search = {
+        let results = results.clone();
+        let loading = loading.clone();
+        let error = error.clone();
+        Callback::from(move |run: SearchRun| {
+            if run.query.trim().is_empty() {
+                return;
+            }
+            let url = search_url(&run);
+            let results = results.clone();
+            let loading = loading.clone();
+            let error = error.clone();
+            loading.set(true);
+            spawn_local(async move {
+                match Request::get(&url).send().await {
+                    Ok(response) if response.status() == 401 => {
+                        if let Some(window) = web_sys::window() {
+                            let _ = window.location().set_href("/api/auth/logout");
+                        }
+                    }
+                    Ok(response) if response.ok() => {
+                        match response.json::<SearchResponse>().await {
+                            Ok(data) => {
+                                results.set(Some(data));
+                                error.set(None);
+                            }
+                            Err(e) => error.set(Some(format!("Failed to parse results: {:?}", e))),
+                        }
+                    }
+                    // Query errors come back as 400 with a readable message
+                    Ok(response) if response.status() == 400 => {
+                        error.set(Some(response.text().await.unwrap_or_default()));
+                    }
+                    Ok(response) => {
+                        error.set(Some(format!("Search failed: {}", response.status())))
+                    }
+                    Err(e) => error.set(Some(format!("Search failed: {:?}", e))),
+                }
+                loading.set(false);
+            });
+        })
+    };
+
+    let on_submit = {
+        let query = query.clone();
+        let regex = regex.clone();
+        let semantic = semantic.clone();
+        let run_search = run_search.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            run_search.emit(SearchRun {
+                query: (*query).clone(),
+                regex: *regex,
+                semantic: *semantic,
+            });
+        })
+    };
+
+    let open_save_form = {
+        let save_name = save_name.clone();
+        let save_alert = save_alert.clone();
+        Callback::from(move |_| {
+            save_name.set(Some(String::new()));
+            save_alert.set(false);
+        })
+    };
+
+    let on_save = {
+        let query = query.clone();
+        let regex = regex.clone();
+        let save_name = save_name.clone();
+        let save_alert = save_alert.clone();
+        let saved = saved.clone();
+        let error = error.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            let Some(name) = (*save_name).clone() else {
+                return;
+            };
+            let body = SaveSearchRequest {
+                name,
+                query: (*query).clone(),
+                regex: *regex,
+                alert: *save_alert,
+            };
+            let save_name = save_name.clone();
+            let saved = saved.clone();
+            let error = error.clone();
+            spawn_local(async move {
+                let url = utils::api_url("/api/saved-searches");
+                let Ok(request) = Request::post(&url).json(&body) else {
+                    return;
+                };
+                match request.send().await {
+                    Ok(response) if response.ok() => {
+                        if let Ok(search) = response.json::<SavedSearch>().await {
+                            let mut list = (*saved).clone();
+                            list.push(search);
+                            saved.set(list);
+                            save_name.set(None);
+                            error.set(None);
+                        }
+                    }
+                    Ok(response) if response.status() == 400 => {
+                        error.set(Some(response.text().await.unwrap_or_default()));
+                    }
+                    Ok(response) => {
+                        error.set(Some(format!("Saving failed: {}", response.status())))
+                    }
+                    Err(e) => error.set(Some(format!("Saving failed: {:?}", e))),
+                }
+            });
+        })
+    };
+
+    let on_save_name = {
+        let save_name = save_name.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            save_name.set(Some(input.value()));
+        })
+    };
+
+    let toggle_save_alert = {
+        let save_alert = save_alert.clone();
+        Callback::from(move |_| save_alert.set(!*save_alert))
+    };
+
+    let cancel_save = {
+        let save_name = save_name.clone();
+        Callback::from(move |_| save_name.set(None))
+    };
+
+    let run_saved = |search: &SavedSearch| {
+        let query = query.clone();
+        let regex = regex.clone();
+        let semantic = semantic.clone();
+        let run_search = run_search.clone();
+        let text = search.query.clone();
+        let is_regex = search.regex;
+        Callback::from(move |_| {
+            query.set(text.clone());
+            regex.set(is_regex);
+            semantic.set(false);
+            run_search.emit(SearchRun {
+                query: text.clone(),
+                regex: is_regex,
+                semantic: false,
+            });
+        })
+    };
+
+    let toggle_alert = |search: &SavedSearch| {
+        let saved = saved.clone();
+        let id = search.id;
+        let alert = !search.alert;
+        Callback::from(move |_| {
+            let saved = saved.clone();
+            spawn_local(async move {
+                let url = utils::api_url(&format!("/api/saved-searches/{}", id));
+                let body = UpdateSavedSearchRequest {
+                    alert: Some(alert),
+                    ..Default::default()
+                };
+                let Ok(request) = Request::patch(&url).json(&body) else {
+                    return;
+                };
+                match request.send().await {
+                    Ok(response) if response.ok() => {
+                        if let Ok(updated) = response.json::<SavedSearch>().await {
+                            let list = saved
+                                .iter()
+                                .map(|s| {
+                                    if s.id == id {
+                                        updated.clone()
+                                    } else {
+                                        s.clone()
+                                    }
+                                })
+                                .collect();
+                            saved.set(list);
+                        }
+                    }
+                    Ok(response) => {
+                        tracing::warn!("Failed to update saved search: {}", response.status())
+                    }
+                    Err(e) => tracing::warn!("Failed to update saved search: {:?}", e),
+                }
+            });
+        })
+    };
+
+    let delete_saved = |search: &SavedSearch| {
+        let saved = saved.clone();
+        let id = search.id;
+        Callback::from(move |_| {
+            let saved = saved.clone();
+            spawn_local(async move {
+                let url = utils::api_url(&format!("/api/saved-searches/{}", id));
+                match Request::delete(&url).send().await {
+                    Ok(response) if response.ok() => {
+                        saved.set(saved.iter().filter(|s| s.id != id).cloned().collect());
+                    }
+                    Ok(response) => {
+                        tracing::warn!("Failed to delete saved search: {}", response.status())
+                    }
+                    Err(e) => tracing::warn!("Failed to delete saved search: {:?}", e),
+                }
+            });
+        })
+    };
+
+    let on_input = {
+        let query = query.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            query.set(input.value());
+        })
+    };
+
+    let toggle_regex = {
+        let regex = regex.clone();
+        Callback::from(move |_| regex.set(!*regex))
+    };
+
+    let toggle_semantic = {
+        let semantic = semantic.clone();
+        Callback::from(move |_| semantic.set(!*semantic))
+    };
+
+    let toggle_help = {
+        let help_open = help_open.clone();
+        Callback::from(move |_| help_open.set(!*help_open))
+    };
+
+    let insert_example = |example: &'static str| {
+        let query = query.clone();
+        let input_ref = input_ref.clone();
+        Callback::from(move |_| {
+            let current = query.trim_end();
+            query.set(if current.is_empty() {
+                format!("{} ", example)
+            } else {
+                format!("{} {} ", current, example)
+            });
+            if let Some(input) = input_ref.cast::<HtmlInputElement>() {
+                let _ = input.focus();
+            }
+        })
+    };
+
+    let go_back = {
+        let navigator = navigator.clone();
+        Callback::from(move |_| navigator.push(&Route::Dashboard))
+    };
+
+    let open_hit = |hit: &SearchHit| {
+        let navigator = navigator.clone();
+        let session_id = hit.session_id;
+        Callback::from(move |_| {
+            let _ = navigator
+                .push_with_query(&Route::Dashboard, &[("session", session_id.to_string())]);
+        })
+    };
+
+    let help = if *help_open {
+        html! {
+            <div class="search-help-popover">
+                <p>
+                    { "Words must all appear in a message, in any order. \
+                       Add filters to narrow the search; repeat one to match any of its values." }
+                </p>
+                <ul>
+                    { for SYNTAX_HELP.iter().map(|(example, description)| html! {
+                        <li>
+                            <button type="button" class="search-help-example" onclick={insert_example(example)}>
+                                <code>{ *example }</code>
+                            </button>
+                            <span>{ *description }</span>
+                        </li>
+                    }) }
+                </ul>
+                <p>
+                    { "With Regex on, the text (filters aside) is one case-insensitive regular expression." }
+                </p>
+            </div>
+        }
+    } else {
+        html! {}
+    };
+
+    let save_form = match &*save_name {
+        Some(name) => html! {
+            <form class="saved-search-form" onsubmit={on_save}>
+                <input
+                    type="text"
+                    class="saved-search-name"
+                    placeholder="Name, e.g. Touches production.env"
+                    value={name.clone()}
+                    oninput={on_save_name}
+                />
+                <label class="search-regex-toggle" title="Check new messages in the background and show a banner when they match">
+                    <input type="checkbox" checked={*save_alert} onchange={toggle_save_alert} />
+                    { "Alert on new matches" }
+                </label>
+                <button type="submit" class="search-submit" disabled={name.trim().is_empty()}>
+                    { "Save" }
+                </button>
+                <button type="button" class="saved-search-cancel" onclick={cancel_save}>
+                    { "Cancel" }
+                </button>
+            </form>
+        },
+        None => html! {},
+    };
+
+    let saved_list = if saved.is_empty() {
+        html! {}
+    } else {
+        html! {
+            <ul class="saved-searches">
+                { for saved.iter().map(|search| html! {
+                    <li class="saved-search" key={search.id.to_string()}>
+                        <button
+                            type="button"
+                            class="saved-search-run"
+                            onclick={run_saved(search)}
+                            title={search.query.clone()}
+                        >
+                            { &search.name }
+                        </button>
+                        if let Some(at) = &search.last_match_at {
+                            <span class="saved-search-last-match">
+                                { format!("matched {}", format_timestamp(at)) }
+                            </span>
+                        }
+                        <label class="saved-search-alert" title="Show a banner when new messages match">
+                            <input type="checkbox" checked={search.alert} onchange={toggle_alert(search)} />
+                            { "Alert" }
+                        </label>
+                        <button
+                            type="button"
+                            class="saved-search-delete"
+                            onclick={delete_saved(search)}
+                            title="Delete saved search"
+                        >
+                            { "×" }
+                        </button>
+                    </li>
+                }) }
+            </ul>
+        }
+    };
+
+    let content = match (&*results, &*error) {
+        (_, Some(err)) => html! { <div class="search-error">{ err }</div> },
+        (None, None) => html! {
+            <div class="search-empty">{ "Search prompts, replies, tool calls, and errors across your sessions" }</div>
+        },
+        (Some(response), None) if response.hits.is_empty() => {
+            html! { <div class="search-empty">{ "No matches" }</div> }
+        }
+        (Some(response), None) => html! {
+            <>
+                <ul class="search-results">
+                    { for response.hits.iter().map(|hit| html! {
+                        <li>
+                            <button class="search-result" onclick={open_hit(hit)}>
+                                <div class="search-result-meta">
+                                    <span class="search-result-session">
+                                        { utils::extract_folder(&hit.session_name) }
+                                    </span>
+                                    <span class={classes!("search-result-role", format!("role-{}", hit.role))}>
+                                        { &hit.role }
+                                    </span>
+                                    if let Some(score) = hit.score {
+                                        <span class="search-result-score" title="Similarity to the query">
+                                            { format!("{:.0}%", score * 100.0) }
+                                        </span>
+                                    }
+                                    <span class="search-result-time">{ format_timestamp(&hit.created_at) }</span>
+                                </div>
+                                <div class="search-result-snippet">{ &hit.snippet }</div>
+                            </button>
+                        </li>
+                    }) }
+                </ul>
+                if response.truncated {
+                    <div class="search-truncated">
+                        { "Showing the most recent matches. Add a session: or date filter to find older ones." }
+                    </div>
+                }
+            </>
+        },
+    };
+
+    html! {
+        <div class="settings-container">
+            <header class="settings-header">
+                <button class="header-button" onclick={go_back}>
+                    { "< Back" }
+                </button>
+                <h1>{ "Search" }</h1>
+            </header>
+
+            <main class="settings-content search-content">
+                <form class="search-form" onsubmit={on_submit}>
+                    <input
+                        ref={input_ref}
+                        type="search"
+                        class="search-query"
+                        placeholder="e.g. migration tool:Bash after:7d"
+                        value={(*query).clone()}
+                        oninput={on_input}
+                    />
+                    <label class="search-regex-toggle" title="Treat the text as a regular expression">
+                        <input
+                            type="checkbox"
+                            checked={*regex && !*semantic}
+                            disabled={*semantic}
+                            onchange={toggle_regex}
+                        />
+                        { "Regex" }
+                    </label>
+                    if *semantic_available {
+                        <label class="search-regex-toggle" title="Find prompts and replies with a similar meaning, not just the same words">
+                            <input type="checkbox" checked={*semantic} onchange={toggle_semantic} />
+                            { "Semantic" }
+                        </label>
+                    }
+                    <button type="submit" class="search-submit" disabled={*loading}>
+                        { if *loading { "Searching..." } else { "Search" } }
+                    </button>
+                    <button
+                        type="button"
+                        class="saved-search-open"
+                        onclick={open_save_form}
+                        disabled={query.trim().is_empty() || save_name.is_some() || *semantic}
+                        title={if *semantic { "Only text searches can be saved" } else { "Save this query" }}
+                    >
+                        { "Save" }
+                    </button>
+                    <div class="search-help">
+                        <button
+                            type="button"
+                            class={classes!("search-help-button", help_open.then_some("active"))}
+                            onclick={toggle_help}
+                            title="Search syntax"
+                        >
+                            { "?" }
+                        </button>
+                        { help }
+                    </div>
+                </form>
+                { save_form }
+                { saved_list }
+                { content }
+            </main>
+        </div>
+    }
+}
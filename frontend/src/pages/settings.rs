@@ -1,10 +1,13 @@
-use crate::components::ShareDialog;
+use crate::components::{
+    InputAssistForm, NotificationSettingsForm, OutcomePicker, PushSettings, ShareDialog,
+    VoiceSettingsForm,
+};
 use crate::utils;
 use crate::Route;
 use gloo_net::http::Request;
 use shared::{
-    CreateProxyTokenRequest, CreateProxyTokenResponse, ProxyTokenInfo, ProxyTokenListResponse,
-    SessionInfo,
+    CreateProxyTokenRequest, CreateProxyTokenResponse, ProxyTokenDetail, ProxyTokenInfo,
+    ProxyTokenListResponse, ProxyTokenScopes, SessionInfo, SessionOutcome, ToolCategory,
 };
 use uuid::Uuid;
 use wasm_bindgen_futures::spawn_local;
@@ -18,6 +21,32 @@ enum SettingsTab {
     Tokens,
 }
 
+/// "read-only, /work/client, 2 sessions, tools: read, edit", or `None` for
+/// an unrestricted token
+fn scope_summary(scopes: &ProxyTokenScopes) -> Option<String> {
+    let mut parts = Vec::new();
+    if scopes.read_only {
+        parts.push("read-only".to_string());
+    }
+    parts.extend(scopes.directories.iter().cloned());
+    if let Some(max) = scopes.max_sessions {
+        parts.push(format!(
+            "{} session{}",
+            max,
+            if max == 1 { "" } else { "s" }
+        ));
+    }
+    if let Some(categories) = &scopes.tool_categories {
+        let names: Vec<&str> = categories.iter().map(|c| c.as_str()).collect();
+        parts.push(if names.is_empty() {
+            "no tools".to_string()
+        } else {
+            format!("tools: {}", names.join(", "))
+        });
+    }
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
+
 /// Calculate days until expiration from ISO date string
 fn days_until_expiration(expires_at: &str) -> Option<i64> {
     // Parse ISO date and compare with current time
@@ -98,23 +127,96 @@ fn token_row(props: &TokenRowProps) -> Html {
         on_revoke.emit(token_id);
     });
 
+    // Sessions opened with the token, loaded when first expanded
+    let expanded = use_state(|| false);
+    let detail = use_state(|| None::<ProxyTokenDetail>);
+    let on_inspect_click = {
+        let expanded = expanded.clone();
+        let detail = detail.clone();
+        Callback::from(move |_| {
+            let expanding = !*expanded;
+            expanded.set(expanding);
+            if !expanding {
+                return;
+            }
+            let detail = detail.clone();
+            spawn_local(async move {
+                let api_endpoint = utils::api_url(&format!("/api/proxy-tokens/{}", token_id));
+                match Request::get(&api_endpoint).send().await {
+                    Ok(response) if response.ok() => {
+                        if let Ok(data) = response.json::<ProxyTokenDetail>().await {
+                            detail.set(Some(data));
+                        }
+                    }
+                    Ok(response) => {
+                        tracing::error!("Failed to inspect token: {}", response.status());
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to inspect token: {:?}", e);
+                    }
+                }
+            });
+        })
+    };
+
     html! {
-        <tr class={if token.revoked || is_expired { "token-row disabled" } else { "token-row" }}>
-            <td class="token-name">{ &token.name }</td>
-            <td class="token-created">{ format_timestamp(&token.created_at) }</td>
-            <td class="token-last-used">
-                { token.last_used_at.as_ref().map(|t| format_timestamp(t)).unwrap_or_else(|| "Never".to_string()) }
-            </td>
-            <td class="token-expires">{ format_timestamp(&token.expires_at) }</td>
-            <td class={status_class}>{ status_text }</td>
-            <td class="token-actions">
-                if !token.revoked && !is_expired {
-                    <button class="revoke-button" onclick={on_revoke_click}>
-                        { "Revoke" }
+        <>
+            <tr class={if token.revoked || is_expired { "token-row disabled" } else { "token-row" }}>
+                <td class="token-name">
+                    { &token.name }
+                    if let Some(summary) = scope_summary(&token.scopes) {
+                        <div class="token-scopes-summary" title="Restrictions on this token">
+                            { summary }
+                        </div>
+                    }
+                </td>
+                <td class="token-created">{ format_timestamp(&token.created_at) }</td>
+                <td class="token-last-used">
+                    { token.last_used_at.as_ref().map(|t| format_timestamp(t)).unwrap_or_else(|| "Never".to_string()) }
+                </td>
+                <td class="token-host">{ token.last_used_host.clone().unwrap_or_else(|| "-".to_string()) }</td>
+                <td class="token-expires">{ format_timestamp(&token.expires_at) }</td>
+                <td class={status_class}>{ status_text }</td>
+                <td class="token-actions">
+                    <button class="share-button" onclick={on_inspect_click}>
+                        { if *expanded { "Hide" } else { "Sessions" } }
                     </button>
-                }
-            </td>
-        </tr>
+                    if !token.revoked && !is_expired {
+                        <button class="revoke-button" onclick={on_revoke_click}>
+                            { "Revoke" }
+                        </button>
+                    }
+                </td>
+            </tr>
+            if *expanded {
+                <tr class="token-detail-row">
+                    <td colspan="7">
+                        { match &*detail {
+                            None => html! { <p class="token-detail-empty">{ "Loading..." }</p> },
+                            Some(detail) if detail.sessions.is_empty() => html! {
+                                <p class="token-detail-empty">{ "No sessions opened with this token yet." }</p>
+                            },
+                            Some(detail) => html! {
+                                <ul class="token-sessions">
+                                    { for detail.sessions.iter().map(|session| html! {
+                                        <li key={session.id.to_string()}>
+                                            <span class={classes!("session-status", session.status.clone())}>
+                                                { &session.status }
+                                            </span>
+                                            <span class="session-name">{ &session.session_name }</span>
+                                            <span class="session-directory">{ &session.working_directory }</span>
+                                            <span class="token-session-activity">
+                                                { format_timestamp(&session.last_activity) }
+                                            </span>
+                                        </li>
+                                    }) }
+                                </ul>
+                            },
+                        } }
+                    </td>
+                </tr>
+            }
+        </>
     }
 }
 
@@ -124,6 +226,7 @@ struct SessionRowProps {
     session: SessionInfo,
     on_delete: Callback<Uuid>,
     on_share: Callback<Uuid>,
+    on_outcome_change: Callback<(Uuid, Option<SessionOutcome>)>,
 }
 
 #[function_component(SessionRow)]
@@ -154,6 +257,11 @@ fn session_row(props: &SessionRowProps) -> Html {
     // Only owners can share
     let is_owner = session.my_role == "owner";
 
+    let on_outcome_change = {
+        let callback = props.on_outcome_change.clone();
+        Callback::from(move |outcome| callback.emit((session_id, outcome)))
+    };
+
     // Format session ID as short form (first 8 chars)
     let short_id = &session.id.to_string()[..8];
 
@@ -171,6 +279,14 @@ fn session_row(props: &SessionRowProps) -> Html {
             <td class="session-activity">{ format_timestamp(&session.last_activity) }</td>
             <td class="session-created">{ format_timestamp(&session.created_at) }</td>
             <td class={status_class}>{ session.status.as_str() }</td>
+            <td class="session-outcome">
+                <OutcomePicker
+                    {session_id}
+                    outcome={session.outcome}
+                    on_change={on_outcome_change}
+                    disabled={session.my_role == "viewer"}
+                />
+            </td>
             <td class="session-actions">
                 if is_owner {
                     <button class="share-button" onclick={on_share_click} title="Share session">
@@ -190,6 +306,35 @@ fn session_row(props: &SessionRowProps) -> Html {
 struct NewTokenForm {
     name: String,
     expires_in_days: u32,
+    /// Allowed working directories, one per line
+    directories: String,
+    read_only: bool,
+    /// Blank for no limit
+    max_sessions: String,
+    /// Unchecked tool categories
+    blocked_tools: Vec<ToolCategory>,
+}
+
+impl NewTokenForm {
+    fn scopes(&self) -> ProxyTokenScopes {
+        ProxyTokenScopes {
+            directories: self
+                .directories
+                .lines()
+                .map(str::trim)
+                .filter(|dir| !dir.is_empty())
+                .map(str::to_string)
+                .collect(),
+            read_only: self.read_only,
+            max_sessions: self.max_sessions.trim().parse().ok().filter(|n| *n > 0),
+            tool_categories: (!self.blocked_tools.is_empty()).then(|| {
+                ToolCategory::ALL
+                    .into_iter()
+                    .filter(|c| !self.blocked_tools.contains(c))
+                    .collect()
+            }),
+        }
+    }
 }
 
 #[function_component(SettingsPage)]
@@ -236,7 +381,7 @@ pub fn settings_page() -> Html {
                         }
                     }
                     Err(e) => {
-                        log::error!("Failed to fetch tokens: {:?}", e);
+                        tracing::error!("Failed to fetch tokens: {:?}", e);
                     }
                 }
                 tokens_loading.set(false);
@@ -274,7 +419,7 @@ pub fn settings_page() -> Html {
                         }
                     }
                     Err(e) => {
-                        log::error!("Failed to fetch sessions: {:?}", e);
+                        tracing::error!("Failed to fetch sessions: {:?}", e);
                     }
                 }
                 sessions_loading.set(false);
@@ -320,7 +465,7 @@ pub fn settings_page() -> Html {
                             }
                         }
                         Err(e) => {
-                            log::error!("Failed to revoke token: {:?}", e);
+                            tracing::error!("Failed to revoke token: {:?}", e);
                         }
                     }
                     confirm_action_inner.set(None);
@@ -335,6 +480,25 @@ pub fn settings_page() -> Html {
     };
 
     // Delete session handler
+    let on_outcome_change = {
+        let sessions = sessions.clone();
+        Callback::from(
+            move |(session_id, outcome): (Uuid, Option<SessionOutcome>)| {
+                let updated: Vec<SessionInfo> = (*sessions)
+                    .iter()
+                    .cloned()
+                    .map(|mut s| {
+                        if s.id == session_id {
+                            s.outcome = outcome;
+                        }
+                        s
+                    })
+                    .collect();
+                sessions.set(updated);
+            },
+        )
+    };
+
     let on_delete_session = {
         let sessions = sessions.clone();
         let confirm_action = confirm_action.clone();
@@ -362,7 +526,7 @@ pub fn settings_page() -> Html {
                             }
                         }
                         Err(e) => {
-                            log::error!("Failed to delete session: {:?}", e);
+                            tracing::error!("Failed to delete session: {:?}", e);
                         }
                     }
                     confirm_action_inner.set(None);
@@ -417,6 +581,7 @@ pub fn settings_page() -> Html {
                     } else {
                         30
                     },
+                    scopes: form_data.scopes(),
                 };
 
                 match Request::post(&api_endpoint)
@@ -433,7 +598,7 @@ pub fn settings_page() -> Html {
                         }
                     }
                     Err(e) => {
-                        log::error!("Failed to create token: {:?}", e);
+                        tracing::error!("Failed to create token: {:?}", e);
                     }
                 }
             });
@@ -461,6 +626,48 @@ pub fn settings_page() -> Html {
         })
     };
 
+    let on_directories_input = {
+        let new_token_form = new_token_form.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: web_sys::HtmlTextAreaElement = e.target_unchecked_into();
+            let mut form = (*new_token_form).clone();
+            form.directories = input.value();
+            new_token_form.set(form);
+        })
+    };
+
+    let on_read_only_change = {
+        let new_token_form = new_token_form.clone();
+        Callback::from(move |_| {
+            let mut form = (*new_token_form).clone();
+            form.read_only = !form.read_only;
+            new_token_form.set(form);
+        })
+    };
+
+    let on_max_sessions_input = {
+        let new_token_form = new_token_form.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            let mut form = (*new_token_form).clone();
+            form.max_sessions = input.value();
+            new_token_form.set(form);
+        })
+    };
+
+    let on_tool_toggle = |category: ToolCategory| {
+        let new_token_form = new_token_form.clone();
+        Callback::from(move |_| {
+            let mut form = (*new_token_form).clone();
+            if let Some(i) = form.blocked_tools.iter().position(|c| *c == category) {
+                form.blocked_tools.remove(i);
+            } else {
+                form.blocked_tools.push(category);
+            }
+            new_token_form.set(form);
+        })
+    };
+
     // Tab click handlers
     let on_tokens_tab = {
         let active_tab = active_tab.clone();
@@ -522,6 +729,7 @@ pub fn settings_page() -> Html {
                 </button>
                 <h1>{ "Settings" }</h1>
                 <button class="header-button logout" onclick={Callback::from(|_| {
+                    crate::offline::clear();
                     if let Some(window) = web_sys::window() {
                         let _ = window.location().set_href("/api/auth/logout");
                     }
@@ -556,7 +764,7 @@ pub fn settings_page() -> Html {
                         <div class="section-header">
                             <h2>{ "Proxy Credentials" }</h2>
                             <p class="section-description">
-                                { "Manage authentication tokens for your Claude Code proxy connections." }
+                                { "Every machine with proxy access, where each token was last used from, and the sessions it opened. Revoking a token disconnects its proxies." }
                             </p>
                             <button class="create-button" onclick={toggle_create_form.clone()}>
                                 { if *show_create_form { "Cancel" } else { "+ Create Token" } }
@@ -608,6 +816,53 @@ pub fn settings_page() -> Html {
                                                 oninput={on_days_input}
                                             />
                                         </div>
+                                        <details class="token-scopes">
+                                            <summary>{ "Restrictions (optional)" }</summary>
+                                            <div class="form-group">
+                                                <label for="token-directories">
+                                                    { "Allowed directories, one per line (subdirectories included; blank allows any)" }
+                                                </label>
+                                                <textarea
+                                                    id="token-directories"
+                                                    rows="2"
+                                                    placeholder="/home/me/client-project"
+                                                    value={new_token_form.directories.clone()}
+                                                    oninput={on_directories_input}
+                                                />
+                                            </div>
+                                            <div class="form-group">
+                                                <label for="token-max-sessions">{ "Max concurrent sessions" }</label>
+                                                <input
+                                                    type="number"
+                                                    id="token-max-sessions"
+                                                    min="1"
+                                                    placeholder="No limit"
+                                                    value={new_token_form.max_sessions.clone()}
+                                                    oninput={on_max_sessions_input}
+                                                />
+                                            </div>
+                                            <label class="token-scope-check">
+                                                <input
+                                                    type="checkbox"
+                                                    checked={new_token_form.read_only}
+                                                    onchange={on_read_only_change}
+                                                />
+                                                { "Read-only: can watch sessions (claude-portal tail) but not run or answer them" }
+                                            </label>
+                                            <fieldset class="token-scope-tools">
+                                                <legend>{ "Tools it may get permission for" }</legend>
+                                                { for ToolCategory::ALL.into_iter().map(|category| html! {
+                                                    <label class="token-scope-check">
+                                                        <input
+                                                            type="checkbox"
+                                                            checked={!new_token_form.blocked_tools.contains(&category)}
+                                                            onchange={on_tool_toggle(category)}
+                                                        />
+                                                        { category.as_str() }
+                                                    </label>
+                                                }) }
+                                            </fieldset>
+                                        </details>
                                         <button type="submit" class="submit-button">
                                             { "Create Token" }
                                         </button>
@@ -634,6 +889,7 @@ pub fn settings_page() -> Html {
                                             <th>{ "Name" }</th>
                                             <th>{ "Created" }</th>
                                             <th>{ "Last Used" }</th>
+                                            <th>{ "Host" }</th>
                                             <th>{ "Expires" }</th>
                                             <th>{ "Status" }</th>
                                             <th>{ "Actions" }</th>
@@ -658,12 +914,24 @@ pub fn settings_page() -> Html {
 
                 // Session Management Tab
                 if *active_tab == SettingsTab::Sessions {
+                    <PushSettings />
+                    <NotificationSettingsForm />
+                    <VoiceSettingsForm />
+                    <InputAssistForm />
                     <section class="sessions-section">
                         <div class="section-header">
                             <h2>{ "Session History" }</h2>
                             <p class="section-description">
                                 { "View and manage your Claude Code sessions across all machines." }
                             </p>
+                            <a
+                                class="create-button"
+                                href={utils::api_url("/api/feedback/export")}
+                                download="turn-feedback.jsonl"
+                                title="Download turn feedback as JSON lines"
+                            >
+                                { "Export Feedback" }
+                            </a>
                         </div>
 
                         if *sessions_loading {
@@ -688,6 +956,7 @@ pub fn settings_page() -> Html {
                                             <th>{ "Last Activity" }</th>
                                             <th>{ "Created" }</th>
                                             <th>{ "Status" }</th>
+                                            <th>{ "Outcome" }</th>
                                             <th>{ "Actions" }</th>
                                         </tr>
                                     </thead>
@@ -699,6 +968,7 @@ pub fn settings_page() -> Html {
                                                     session={session.clone()}
                                                     on_delete={on_delete_session.clone()}
                                                     on_share={on_share_session.clone()}
+                                                    on_outcome_change={on_outcome_change.clone()}
                                                 />
                                             }
                                         }) }
@@ -1,18 +1,83 @@
 use crate::{utils, VERSION};
-use gloo::console;
+use gloo::utils::window;
+use gloo_net::http::Request;
+use shared::AuthProviderInfo;
+use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 
+/// Where a sign-in button goes. When the CLI device flow sent the user here
+/// to pick a provider, the code rides along so approval follows sign-in.
+fn login_path(provider: &str, device_user_code: Option<&str>) -> String {
+    match device_user_code {
+        Some(code) => format!(
+            "/api/auth/device-login?device_user_code={}&provider={}",
+            code, provider
+        ),
+        None => format!("/api/auth/{}", provider),
+    }
+}
+
+fn provider_icon(provider: &str) -> &'static str {
+    match provider {
+        "google" => "G",
+        "github" => "🐙",
+        _ => "🔑",
+    }
+}
+
 #[function_component(SplashPage)]
 pub fn splash_page() -> Html {
-    let handle_login = Callback::from(|_| {
-        console::log!("Redirecting to Google OAuth...");
-        // Redirect to backend OAuth endpoint
-        let window = web_sys::window().expect("no global `window` exists");
-        let location = window.location();
-        let auth_url = utils::api_url("/api/auth/google");
-        let _ = location.set_href(&auth_url);
+    // None until /api/auth/providers answers; empty in dev mode
+    let providers = use_state(|| None::<Vec<AuthProviderInfo>>);
+
+    {
+        let providers = providers.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                let api_endpoint = utils::api_url("/api/auth/providers");
+                match Request::get(&api_endpoint).send().await {
+                    Ok(response) if response.ok() => {
+                        if let Ok(list) = response.json::<Vec<AuthProviderInfo>>().await {
+                            providers.set(Some(list));
+                        }
+                    }
+                    Ok(response) => {
+                        tracing::warn!("Failed to load sign-in options: {}", response.status())
+                    }
+                    Err(e) => tracing::warn!("Failed to load sign-in options: {:?}", e),
+                }
+            });
+            || ()
+        });
+    }
+
+    let device_user_code = window().location().search().ok().and_then(|search| {
+        let params = web_sys::UrlSearchParams::new_with_str(&search).ok()?;
+        params.get("device_user_code")
     });
 
+    let login_buttons = match providers.as_ref() {
+        None => html! {},
+        // Dev mode signs in as the test user
+        Some(list) if list.is_empty() => html! {
+            <a class="login-button" href={utils::api_url("/api/auth/dev-login")}>
+                { "Sign in" }
+            </a>
+        },
+        Some(list) => list
+            .iter()
+            .map(|provider| {
+                let href = utils::api_url(&login_path(&provider.id, device_user_code.as_deref()));
+                html! {
+                    <a class="login-button" {href}>
+                        <span class="provider-icon">{ provider_icon(&provider.id) }</span>
+                        { format!(" Sign in with {}", provider.name) }
+                    </a>
+                }
+            })
+            .collect::<Html>(),
+    };
+
     html! {
         <div class="splash-container">
             <div class="splash-content">
@@ -108,14 +173,16 @@ pub fn splash_page() -> Html {
                     </div>
                     <div class="feature">
                         <h3>{ "🔒 Secure" }</h3>
-                        <p>{ "Google OAuth authentication and encrypted connections" }</p>
+                        <p>{ "Single sign-on with Google, GitHub, or your identity provider, over encrypted connections" }</p>
                     </div>
                 </div>
 
-                <button class="login-button" onclick={handle_login}>
-                    <span class="google-icon">{ "G" }</span>
-                    { " Sign in with Google" }
-                </button>
+                if device_user_code.is_some() {
+                    <p class="login-hint">{ "Sign in to approve your CLI login" }</p>
+                }
+                <div class="login-buttons">
+                    { login_buttons }
+                </div>
 
                 <div class="splash-footer">
                     <span class="version">{ format!("v{}", VERSION) }</span>
@@ -0,0 +1,352 @@
+//! Usage analytics page
+//!
+//! Charts the current user's spend and token usage from `GET /api/usage`.
+//! Charts are plain SVG so large ranges (90+ days) stay cheap to render.
+
+use crate::utils;
+use crate::Route;
+use gloo_net::http::Request;
+use shared::{DailyUsage, UsageRange, UsageReport, UsageTotals};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+/// Rows shown in the ranked session/tool/model lists
+const MAX_RANKED_ROWS: usize = 15;
+
+/// Height of the daily charts in SVG units
+const CHART_HEIGHT: f64 = 120.0;
+
+/// Horizontal space per day in SVG units
+const DAY_WIDTH: f64 = 10.0;
+
+// ============================================================================
+// Helper Functions
+// ============================================================================
+
+/// Format token count with K/M suffix for readability
+fn format_tokens(count: i64) -> String {
+    if count >= 1_000_000 {
+        format!("{:.1}M", count as f64 / 1_000_000.0)
+    } else if count >= 1_000 {
+        format!("{:.1}K", count as f64 / 1_000.0)
+    } else {
+        count.to_string()
+    }
+}
+
+fn format_cost(cost: f64) -> String {
+    if cost > 0.0 && cost < 0.01 {
+        "<$0.01".to_string()
+    } else {
+        format!("${:.2}", cost)
+    }
+}
+
+/// All input tokens, including cache reads and writes
+fn tokens_in(totals: &UsageTotals) -> i64 {
+    totals.input_tokens + totals.cache_creation_tokens + totals.cache_read_tokens
+}
+
+// ============================================================================
+// Charts
+// ============================================================================
+
+/// One series in a daily bar chart
+struct Series {
+    label: &'static str,
+    class: &'static str,
+    value: fn(&UsageTotals) -> f64,
+    format: fn(f64) -> String,
+}
+
+/// Bars per day, one per series, scaled to the largest value
+fn daily_chart(days: &[DailyUsage], series: &[Series]) -> Html {
+    if days.is_empty() {
+        return html! { <div class="usage-empty">{ "No usage in this range" }</div> };
+    }
+
+    let max = days
+        .iter()
+        .flat_map(|d| series.iter().map(|s| (s.value)(&d.totals)))
+        .fold(0.0_f64, f64::max);
+    let width = days.len() as f64 * DAY_WIDTH;
+    let bar_width = (DAY_WIDTH - 2.0) / series.len() as f64;
+
+    let bars = days.iter().enumerate().flat_map(|(i, day)| {
+        series.iter().enumerate().map(move |(j, s)| {
+            let value = (s.value)(&day.totals);
+            let height = if max > 0.0 {
+                value / max * CHART_HEIGHT
+            } else {
+                0.0
+            };
+            let x = i as f64 * DAY_WIDTH + 1.0 + j as f64 * bar_width;
+            html! {
+                <rect
+                    class={s.class}
+                    x={x.to_string()}
+                    y={(CHART_HEIGHT - height).to_string()}
+                    width={bar_width.to_string()}
+                    height={height.to_string()}
+                >
+                    <title>{ format!("{} — {}: {}", day.day, s.label, (s.format)(value)) }</title>
+                </rect>
+            }
+        })
+    });
+
+    let first = &days[0].day;
+    let last = &days[days.len() - 1].day;
+    let format_max = series
+        .first()
+        .map(|s| s.format)
+        .unwrap_or(|v| v.to_string());
+
+    html! {
+        <div class="usage-chart">
+            <div class="usage-chart-scale">{ format!("max {}", format_max(max)) }</div>
+            <svg
+                viewBox={format!("0 0 {} {}", width, CHART_HEIGHT)}
+                preserveAspectRatio="none"
+                class="usage-chart-svg"
+            >
+                { for bars }
+            </svg>
+            <div class="usage-chart-axis">
+                <span>{ first }</span>
+                <span>{ last }</span>
+            </div>
+            if series.len() > 1 {
+                <div class="usage-chart-legend">
+                    { for series.iter().map(|s| html! {
+                        <span class="legend-item">
+                            <span class={classes!("legend-swatch", s.class)} />
+                            { s.label }
+                        </span>
+                    }) }
+                </div>
+            }
+        </div>
+    }
+}
+
+/// Horizontal bars for a ranked list of (label, value, display) rows
+fn ranked_bars(rows: Vec<(String, f64, String)>, empty: &str) -> Html {
+    if rows.is_empty() {
+        return html! { <div class="usage-empty">{ empty.to_string() }</div> };
+    }
+    let max = rows.iter().map(|(_, v, _)| *v).fold(0.0_f64, f64::max);
+
+    html! {
+        <ul class="usage-ranked">
+            { for rows.into_iter().take(MAX_RANKED_ROWS).map(|(label, value, display)| {
+                let pct = if max > 0.0 { value / max * 100.0 } else { 0.0 };
+                html! {
+                    <li class="usage-ranked-row" title={label.clone()}>
+                        <span class="usage-ranked-label">{ label }</span>
+                        <span class="usage-ranked-track">
+                            <span class="usage-ranked-bar" style={format!("width: {:.1}%", pct)} />
+                        </span>
+                        <span class="usage-ranked-value">{ display }</span>
+                    </li>
+                }
+            }) }
+        </ul>
+    }
+}
+
+// ============================================================================
+// Page
+// ============================================================================
+
+#[function_component(UsagePage)]
+pub fn usage_page() -> Html {
+    let navigator = use_navigator().unwrap();
+    let range = use_state(UsageRange::default);
+    let report = use_state(|| None::<UsageReport>);
+    let loading = use_state(|| true);
+    let error = use_state(|| None::<String>);
+
+    {
+        let report = report.clone();
+        let loading = loading.clone();
+        let error = error.clone();
+        use_effect_with(*range, move |range| {
+            let range = *range;
+            loading.set(true);
+            spawn_local(async move {
+                let api_endpoint = utils::api_url(&format!("/api/usage?range={}", range.as_str()));
+                match Request::get(&api_endpoint).send().await {
+                    Ok(response) if response.status() == 401 => {
+                        if let Some(window) = web_sys::window() {
+                            let _ = window.location().set_href("/api/auth/logout");
+                        }
+                    }
+                    Ok(response) if response.ok() => match response.json::<UsageReport>().await {
+                        Ok(data) => {
+                            report.set(Some(data));
+                            error.set(None);
+                        }
+                        Err(e) => error.set(Some(format!("Failed to parse usage: {:?}", e))),
+                    },
+                    Ok(response) => {
+                        error.set(Some(format!("Failed to load usage: {}", response.status())))
+                    }
+                    Err(e) => error.set(Some(format!("Failed to load usage: {:?}", e))),
+                }
+                loading.set(false);
+            });
+            || ()
+        });
+    }
+
+    let go_back = Callback::from(move |_| navigator.push(&Route::Dashboard));
+
+    let range_buttons = UsageRange::ALL.iter().map(|r| {
+        let range_state = range.clone();
+        let r = *r;
+        html! {
+            <button
+                class={classes!("tab-button", (*range == r).then_some("active"))}
+                onclick={Callback::from(move |_| range_state.set(r))}
+            >
+                { r.label() }
+            </button>
+        }
+    });
+
+    let content = match (&*report, &*error) {
+        (_, Some(err)) => html! { <div class="usage-error">{ err }</div> },
+        (None, None) => html! { <div class="loading">{ "Loading usage..." }</div> },
+        (Some(report), None) => render_report(report),
+    };
+
+    html! {
+        <div class="settings-container">
+            <header class="settings-header">
+                <button class="header-button" onclick={go_back}>
+                    { "< Back" }
+                </button>
+                <h1>{ "Usage" }</h1>
+                <span class="usage-loading-indicator">
+                    { if *loading && report.is_some() { "Refreshing..." } else { "" } }
+                </span>
+            </header>
+
+            <nav class="settings-tabs">
+                { for range_buttons }
+            </nav>
+
+            <main class="settings-content usage-content">
+                { content }
+            </main>
+        </div>
+    }
+}
+
+fn render_report(report: &UsageReport) -> Html {
+    let totals = &report.totals;
+
+    let spend = [Series {
+        label: "Spend",
+        class: "series-spend",
+        value: |t| t.cost_usd,
+        format: format_cost,
+    }];
+    let tokens = [
+        Series {
+            label: "Tokens in",
+            class: "series-in",
+            value: |t| tokens_in(t) as f64,
+            format: |v| format_tokens(v as i64),
+        },
+        Series {
+            label: "Tokens out",
+            class: "series-out",
+            value: |t| t.output_tokens as f64,
+            format: |v| format_tokens(v as i64),
+        },
+    ];
+
+    let sessions = report
+        .by_session
+        .iter()
+        .map(|s| {
+            let name = utils::extract_folder(&s.session_name).to_string();
+            let label = if s.session_id.is_none() {
+                format!("{} (deleted)", name)
+            } else {
+                name
+            };
+            (label, s.totals.cost_usd, format_cost(s.totals.cost_usd))
+        })
+        .collect();
+    let models = report
+        .by_model
+        .iter()
+        .map(|m| {
+            (
+                m.model.clone(),
+                m.totals.cost_usd,
+                format_cost(m.totals.cost_usd),
+            )
+        })
+        .collect();
+    let tools = report
+        .by_tool
+        .iter()
+        .map(|t| (t.tool.clone(), t.uses as f64, t.uses.to_string()))
+        .collect();
+
+    html! {
+        <>
+            <div class="stats-grid usage-summary">
+                <div class="admin-stat-card spend-card">
+                    <div class="stat-value">{ format_cost(totals.cost_usd) }</div>
+                    <div class="stat-label">{ "Spend" }</div>
+                </div>
+                <div class="admin-stat-card">
+                    <div class="stat-value">{ totals.turns }</div>
+                    <div class="stat-label">{ "Turns" }</div>
+                </div>
+                <div class="admin-stat-card">
+                    <div class="stat-value">{ format_tokens(tokens_in(totals)) }</div>
+                    <div class="stat-label">{ "Tokens In" }</div>
+                    <div class="stat-subvalue">
+                        { format!("{} from cache", format_tokens(totals.cache_read_tokens)) }
+                    </div>
+                </div>
+                <div class="admin-stat-card">
+                    <div class="stat-value">{ format_tokens(totals.output_tokens) }</div>
+                    <div class="stat-label">{ "Tokens Out" }</div>
+                </div>
+            </div>
+
+            <section class="usage-section">
+                <h2>{ "Spend over time" }</h2>
+                { daily_chart(&report.by_day, &spend) }
+            </section>
+
+            <section class="usage-section">
+                <h2>{ "Tokens per day" }</h2>
+                { daily_chart(&report.by_day, &tokens) }
+            </section>
+
+            <div class="usage-columns">
+                <section class="usage-section">
+                    <h2>{ "Cost per session" }</h2>
+                    { ranked_bars(sessions, "No sessions in this range") }
+                </section>
+                <section class="usage-section">
+                    <h2>{ "Most-used tools" }</h2>
+                    { ranked_bars(tools, "No tool calls in this range") }
+                </section>
+                <section class="usage-section">
+                    <h2>{ "Cost by model" }</h2>
+                    { ranked_bars(models, "No model usage in this range") }
+                </section>
+            </div>
+        </>
+    }
+}
@@ -0,0 +1,133 @@
+/**
+ * Service Worker
+ *
+ * Keeps the app shell available offline, so the installed app opens without
+ * a connection and shows transcripts cached in IndexedDB. API and WebSocket
+ * requests always go to the network.
+ *
+ * Also shows the notifications the backend pushes when a session needs
+ * permission or finishes a long turn, and opens the session when one is
+ * clicked.
+ *
+ * Push payload (JSON): { title, body, url, tag }
+ * - tag replaces an earlier notification for the same session and event
+ */
+const SHELL_CACHE = 'cc-portal-shell-v1';
+
+// Assets are content-hashed, so each deploy adds new entries; keep the newest
+const MAX_SHELL_ENTRIES = 60;
+
+self.addEventListener('install', (event) => {
+    event.waitUntil(
+        caches
+            .open(SHELL_CACHE)
+            .then((cache) => cache.addAll(['/', '/manifest.webmanifest']))
+            .then(() => self.skipWaiting())
+    );
+});
+
+self.addEventListener('activate', (event) => {
+    event.waitUntil(
+        caches
+            .keys()
+            .then((names) =>
+                Promise.all(
+                    names
+                        .filter((name) => name.startsWith('cc-portal-') && name !== SHELL_CACHE)
+                        .map((name) => caches.delete(name))
+                )
+            )
+            .then(() => self.clients.claim())
+    );
+});
+
+function trimShellCache(cache) {
+    return cache.keys().then((keys) =>
+        Promise.all(keys.slice(0, Math.max(0, keys.length - MAX_SHELL_ENTRIES)).map((key) => cache.delete(key)))
+    );
+}
+
+self.addEventListener('fetch', (event) => {
+    const request = event.request;
+    const url = new URL(request.url);
+    if (
+        request.method !== 'GET' ||
+        url.origin !== self.location.origin ||
+        url.pathname.startsWith('/api/') ||
+        url.pathname.startsWith('/ws/')
+    ) {
+        return;
+    }
+
+    // Pages: the network when reachable, else the cached shell. Every route
+    // serves the same index.html, so it's cached once under '/'.
+    if (request.mode === 'navigate') {
+        event.respondWith(
+            fetch(request)
+                .then((response) => {
+                    if (response.ok) {
+                        const copy = response.clone();
+                        caches.open(SHELL_CACHE).then((cache) => cache.put('/', copy));
+                    }
+                    return response;
+                })
+                .catch(() => caches.match('/').then((cached) => cached || Response.error()))
+        );
+        return;
+    }
+
+    // Scripts, styles, and images: cached copy first, refreshed in the background
+    event.respondWith(
+        caches.open(SHELL_CACHE).then((cache) =>
+            cache.match(request).then((cached) => {
+                const network = fetch(request)
+                    .then((response) => {
+                        if (response.ok) {
+                            cache.put(request, response.clone()).then(() => trimShellCache(cache));
+                        }
+                        return response;
+                    })
+                    .catch(() => cached || Response.error());
+                return cached || network;
+            })
+        )
+    );
+});
+
+self.addEventListener('push', (event) => {
+    let data = {};
+    try {
+        data = event.data ? event.data.json() : {};
+    } catch (e) {
+        data = { body: event.data ? event.data.text() : '' };
+    }
+
+    event.waitUntil(
+        self.registration.showNotification(data.title || 'Claude Code Portal', {
+            body: data.body || '',
+            tag: data.tag,
+            renotify: Boolean(data.tag),
+            data: { url: data.url || '/dashboard' },
+        })
+    );
+});
+
+self.addEventListener('notificationclick', (event) => {
+    event.notification.close();
+    const url = event.notification.data && event.notification.data.url;
+
+    event.waitUntil(
+        self.clients.matchAll({ type: 'window', includeUncontrolled: true }).then((clients) => {
+            // Reuse an open portal tab rather than opening another
+            // Pages opened before this worker was installed can't be navigated
+            const client = clients.find((c) => 'focus' in c);
+            if (client) {
+                return client
+                    .navigate(url)
+                    .then((c) => (c || client).focus())
+                    .catch(() => self.clients.openWindow(url));
+            }
+            return self.clients.openWindow(url);
+        })
+    );
+});
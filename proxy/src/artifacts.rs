@@ -0,0 +1,140 @@
+//! Zip archives of files Claude wrote during a session.
+//!
+//! The backend asks for paths taken from Write tool uses. Only regular files
+//! inside the session's working directory are included, up to
+//! [`MAX_ARTIFACT_ARCHIVE_BYTES`] in total; anything left out is listed in a
+//! `SKIPPED.txt` entry so the download explains itself.
+
+use anyhow::Result;
+use shared::MAX_ARTIFACT_ARCHIVE_BYTES;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// Files to archive, as (name in archive, path on disk), and skipped paths
+/// with the reason
+type Selection = (Vec<(String, PathBuf)>, Vec<(String, &'static str)>);
+
+fn select_files(workdir: &Path, paths: &[String], max_bytes: u64) -> Result<Selection> {
+    let workdir = workdir.canonicalize()?;
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+    let mut seen = HashSet::new();
+    let mut total = 0u64;
+
+    for requested in paths {
+        let Ok(path) = workdir.join(requested).canonicalize() else {
+            skipped.push((requested.clone(), "not found"));
+            continue;
+        };
+        if !seen.insert(path.clone()) {
+            continue;
+        }
+        let Ok(name) = path.strip_prefix(&workdir) else {
+            skipped.push((requested.clone(), "outside the working directory"));
+            continue;
+        };
+        let metadata = fs::metadata(&path)?;
+        if !metadata.is_file() {
+            skipped.push((requested.clone(), "not a regular file"));
+            continue;
+        }
+        if total + metadata.len() > max_bytes {
+            skipped.push((requested.clone(), "archive size limit reached"));
+            continue;
+        }
+        total += metadata.len();
+        let name = name
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        files.push((name, path));
+    }
+
+    Ok((files, skipped))
+}
+
+/// Build a zip of the requested files that exist inside `workdir`
+pub fn build_archive(workdir: &Path, paths: &[String]) -> Result<Vec<u8>> {
+    let (files, skipped) = select_files(workdir, paths, MAX_ARTIFACT_ARCHIVE_BYTES)?;
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    for (name, path) in &files {
+        zip.start_file(name.as_str(), options)?;
+        zip.write_all(&fs::read(path)?)?;
+    }
+    if !skipped.is_empty() {
+        zip.start_file("SKIPPED.txt", options)?;
+        for (path, reason) in &skipped {
+            writeln!(zip, "{}: {}", path, reason)?;
+        }
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn entry(archive: &mut zip::ZipArchive<Cursor<Vec<u8>>>, name: &str) -> String {
+        let mut text = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    }
+
+    #[test]
+    fn test_build_archive_restricts_to_workdir() {
+        let root = tempfile::tempdir().unwrap();
+        let workdir = root.path().join("work");
+        fs::create_dir_all(workdir.join("src")).unwrap();
+        fs::write(workdir.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(root.path().join("secret.txt"), "hunter2").unwrap();
+
+        let paths = vec![
+            workdir.join("src/main.rs").to_string_lossy().to_string(),
+            "src/main.rs".to_string(),
+            "../secret.txt".to_string(),
+            "missing.txt".to_string(),
+            "src".to_string(),
+        ];
+        let bytes = build_archive(&workdir, &paths).unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+
+        assert_eq!(archive.len(), 2);
+        assert_eq!(entry(&mut archive, "src/main.rs"), "fn main() {}");
+        assert_eq!(
+            entry(&mut archive, "SKIPPED.txt"),
+            "../secret.txt: outside the working directory\n\
+             missing.txt: not found\n\
+             src: not a regular file\n"
+        );
+    }
+
+    #[test]
+    fn test_select_files_size_cap() {
+        let workdir = tempfile::tempdir().unwrap();
+        fs::write(workdir.path().join("a.txt"), "12345").unwrap();
+        fs::write(workdir.path().join("b.txt"), "123456").unwrap();
+        fs::write(workdir.path().join("c.txt"), "1").unwrap();
+
+        let paths = ["a.txt", "b.txt", "c.txt"].map(String::from);
+        let (files, skipped) = select_files(workdir.path(), &paths, 8).unwrap();
+
+        let names: Vec<_> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["a.txt", "c.txt"]);
+        assert_eq!(
+            skipped,
+            vec![("b.txt".to_string(), "archive size limit reached")]
+        );
+    }
+}
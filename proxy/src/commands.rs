@@ -1,13 +1,27 @@
 //! Subcommand handlers for logout and init.
 
 use anyhow::Result;
+use tracing::warn;
 
 use crate::config::{ProxyConfig, SessionAuth};
+use crate::settings;
 use crate::ui;
 use crate::util;
 
-/// Handle the --logout command
-pub fn handle_logout(config: &mut ProxyConfig, cwd: &str) -> Result<()> {
+/// Handle the --logout command, for `profile` if given, else this directory
+pub fn handle_logout(config: &mut ProxyConfig, cwd: &str, profile: Option<&str>) -> Result<()> {
+    if let Some(name) = profile {
+        match config.profiles.get_mut(name) {
+            Some(p) if p.auth_token.is_some() => {
+                p.auth_token = None;
+                let email = p.user_email.take().unwrap_or_default();
+                config.atomic_save()?;
+                ui::print_logout_success(&email);
+            }
+            _ => ui::print_no_cached_auth(),
+        }
+        return Ok(());
+    }
     if let Some(removed) = config.remove_session_auth(cwd) {
         config.atomic_save()?;
         ui::print_logout_success(&removed.user_email.unwrap_or_default());
@@ -17,12 +31,14 @@ pub fn handle_logout(config: &mut ProxyConfig, cwd: &str) -> Result<()> {
     Ok(())
 }
 
-/// Handle the --init command
+/// Handle the --init command. With a profile, the login is saved to it
+/// rather than to this directory.
 pub fn handle_init(
     config: &mut ProxyConfig,
     cwd: &str,
     init_value: &str,
     backend_url_override: Option<&str>,
+    profile: Option<&str>,
 ) -> Result<()> {
     let (parsed_backend_url, token, session_prefix) = util::parse_init_value(init_value)?;
 
@@ -39,6 +55,17 @@ pub fn handle_init(
 
     ui::print_init_start(user_email.as_deref().unwrap_or("unknown user"));
 
+    if let Some(name) = profile {
+        config.set_profile_backend(name, &backend_url);
+        config.set_profile_auth(name, token, user_email.clone());
+        config.atomic_save()?;
+        ui::print_init_complete(
+            &user_email.unwrap_or_else(|| format!("profile {}", name)),
+            &backend_url,
+        );
+        return Ok(());
+    }
+
     // Save to config
     config.set_session_auth(
         cwd.to_string(),
@@ -70,5 +97,12 @@ pub fn handle_init(
         &backend_url,
     );
 
+    // A settings file to grow into; an existing one is left alone
+    match settings::write_template(&backend_url) {
+        Ok(Some(path)) => ui::print_settings_written(&path),
+        Ok(None) => {}
+        Err(e) => warn!("Couldn't write settings file: {}", e),
+    }
+
     Ok(())
 }
@@ -1,6 +1,6 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::{self, File, OpenOptions};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
@@ -18,6 +18,22 @@ pub struct ProxyConfig {
 
     #[serde(default)]
     pub preferences: Preferences,
+
+    /// Named backends chosen with --profile, each with its own login
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// A backend selected with --profile, and the login for it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub backend_url: String,
+    #[serde(default)]
+    pub auth_token: Option<String>,
+    #[serde(default)]
+    pub user_email: Option<String>,
+    #[serde(default)]
+    pub last_used: Option<String>,
 }
 
 /// Tracks the Claude Code session for a specific directory
@@ -242,6 +258,46 @@ impl ProxyConfig {
             .and_then(|auth| auth.backend_url.as_deref())
     }
 
+    // =========================================================================
+    // Profile Methods
+    // =========================================================================
+
+    pub fn get_profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.get(name)
+    }
+
+    /// Add a profile or point an existing one at another backend. Moving it
+    /// drops its login, which belonged to the old backend.
+    pub fn set_profile_backend(&mut self, name: &str, backend_url: &str) {
+        let profile = self
+            .profiles
+            .entry(name.to_string())
+            .or_insert_with(|| Profile {
+                backend_url: backend_url.to_string(),
+                auth_token: None,
+                user_email: None,
+                last_used: None,
+            });
+        if profile.backend_url != backend_url {
+            profile.backend_url = backend_url.to_string();
+            profile.auth_token = None;
+            profile.user_email = None;
+        }
+    }
+
+    /// Save the login for a profile, which must exist
+    pub fn set_profile_auth(&mut self, name: &str, token: String, user_email: Option<String>) {
+        if let Some(profile) = self.profiles.get_mut(name) {
+            profile.auth_token = Some(token);
+            profile.user_email = user_email;
+            profile.last_used = Some(chrono::Utc::now().to_rfc3339());
+        }
+    }
+
+    pub fn remove_profile(&mut self, name: &str) -> Option<Profile> {
+        self.profiles.remove(name)
+    }
+
     // =========================================================================
     // Directory Session Methods
     // =========================================================================
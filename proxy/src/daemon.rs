@@ -0,0 +1,189 @@
+//! `claude-portal daemon` and `claude-portal attach`.
+//!
+//! The daemon hosts sessions for any number of directories in one process,
+//! all sharing one backend connection through [`Mux`](crate::mux::Mux).
+//! `attach NAME`, run in a project directory, asks the daemon over a Unix
+//! socket to start that directory's session under NAME (resuming it if
+//! there is one) and returns once the daemon has taken it on. Attached
+//! directories are saved, so a daemon restarted by `service` picks their
+//! sessions up again.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// What `attach` asks the daemon for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachRequest {
+    pub working_directory: String,
+    pub session_name: String,
+    /// Start over instead of resuming the directory's session
+    #[serde(default)]
+    pub new_session: bool,
+}
+
+/// The daemon's answer to an [`AttachRequest`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachReply {
+    pub session_id: Option<Uuid>,
+    #[serde(default)]
+    pub resumed: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AttachReply {
+    pub fn failed(error: impl std::fmt::Display) -> Self {
+        Self {
+            session_id: None,
+            resumed: false,
+            error: Some(error.to_string()),
+        }
+    }
+}
+
+/// The daemon's control socket, next to `config.json`
+pub fn socket_path() -> Result<PathBuf> {
+    let dirs = directories::ProjectDirs::from("com", "anthropic", "claude-code-portal")
+        .context("Failed to determine config directory")?;
+    Ok(dirs.config_dir().join("daemon.sock"))
+}
+
+/// Directories attached to the daemon and their session names
+fn attached_path() -> Result<PathBuf> {
+    Ok(socket_path()?.with_file_name("daemon-attached.json"))
+}
+
+/// Attach requests to replay when the daemon starts, one per directory that
+/// was attached when it last stopped
+pub fn load_attached() -> Vec<AttachRequest> {
+    let Ok(path) = attached_path() else {
+        return Vec::new();
+    };
+    let Ok(contents) = std::fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+    let names: BTreeMap<String, String> = match serde_json::from_str(&contents) {
+        Ok(names) => names,
+        Err(e) => {
+            tracing::warn!("Ignoring unreadable {}: {}", path.display(), e);
+            return Vec::new();
+        }
+    };
+    names
+        .into_iter()
+        .map(|(working_directory, session_name)| AttachRequest {
+            working_directory,
+            session_name,
+            new_session: false,
+        })
+        .collect()
+}
+
+/// Save the attached directories, by directory, with their session names
+pub fn save_attached(names: &BTreeMap<String, String>) -> Result<()> {
+    let path = attached_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).context("Failed to create config directory")?;
+    }
+    let temp_path = path.with_extension("tmp");
+    std::fs::write(&temp_path, serde_json::to_string_pretty(names)?)
+        .context("Failed to write attached directories")?;
+    std::fs::rename(&temp_path, &path).context("Failed to save attached directories")?;
+    Ok(())
+}
+
+/// Answer attach requests with `handle` until the process exits. Each
+/// connection carries one request line and gets one reply line.
+#[cfg(unix)]
+pub async fn listen<F>(handle: F) -> Result<()>
+where
+    F: Fn(AttachRequest) -> AttachReply,
+{
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{UnixListener, UnixStream};
+
+    let path = socket_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).context("Failed to create config directory")?;
+    }
+    // A socket left by a daemon that died; a live one answers
+    if path.exists() {
+        if UnixStream::connect(&path).await.is_ok() {
+            anyhow::bail!("A daemon is already running ({})", path.display());
+        }
+        std::fs::remove_file(&path).context("Failed to remove stale daemon socket")?;
+    }
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("Failed to listen on {}", path.display()))?;
+    tracing::info!("Daemon listening on {}", path.display());
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let (read, mut write) = stream.into_split();
+        let mut line = String::new();
+        if BufReader::new(read).read_line(&mut line).await.is_err() {
+            continue;
+        }
+        let reply = match serde_json::from_str::<AttachRequest>(&line) {
+            Ok(request) => handle(request),
+            Err(e) => AttachReply::failed(format!("Bad request: {}", e)),
+        };
+        let mut json = serde_json::to_string(&reply)?;
+        json.push('\n');
+        let _ = write.write_all(json.as_bytes()).await;
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn listen<F>(_handle: F) -> Result<()>
+where
+    F: Fn(AttachRequest) -> AttachReply,
+{
+    anyhow::bail!("The daemon needs Unix domain sockets, which this platform lacks")
+}
+
+/// Send `request` to the running daemon
+#[cfg(unix)]
+pub async fn attach(request: &AttachRequest) -> Result<AttachReply> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixStream;
+
+    let path = socket_path()?;
+    let stream = UnixStream::connect(&path)
+        .await
+        .context("No daemon running. Start one with: claude-portal daemon")?;
+    let (read, mut write) = stream.into_split();
+    let mut json = serde_json::to_string(request)?;
+    json.push('\n');
+    write.write_all(json.as_bytes()).await?;
+
+    let mut line = String::new();
+    BufReader::new(read).read_line(&mut line).await?;
+    serde_json::from_str(&line).context("Unexpected reply from the daemon")
+}
+
+#[cfg(not(unix))]
+pub async fn attach(_request: &AttachRequest) -> Result<AttachReply> {
+    anyhow::bail!("The daemon needs Unix domain sockets, which this platform lacks")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reply_wire_format() {
+        let reply: AttachReply = serde_json::from_str(r#"{"session_id":null}"#).unwrap();
+        assert!(!reply.resumed);
+        assert_eq!(reply.error, None);
+
+        let json = serde_json::to_string(&AttachReply::failed("busy")).unwrap();
+        assert_eq!(
+            json,
+            r#"{"session_id":null,"resumed":false,"error":"busy"}"#
+        );
+    }
+}
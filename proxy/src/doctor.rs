@@ -0,0 +1,441 @@
+//! `claude-portal doctor`: check what a session needs, without starting one.
+//!
+//! Finds the Claude binary, reaches the backend over HTTP and WebSocket with
+//! this directory's login, measures the round trip and the clock offset from
+//! the backend's `Date` header, and makes sure the working directory can be
+//! written. Each problem is printed with what to do about it, and the
+//! command fails if any check did.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use claude_session_lib::{discovery, SessionConfig};
+use colored::Colorize;
+use futures_util::{SinkExt, StreamExt};
+use shared::{ProxyMessage, LARGE_CLOCK_SKEW_MS};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{self, client::IntoClientRequest, http::header, Message},
+};
+
+use crate::heartbeat;
+
+/// Longest any one network check waits
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How the proxy would authenticate
+pub enum Login {
+    /// `--dev`: the backend must be in dev mode too
+    Dev,
+    Token(String),
+    Missing,
+}
+
+/// What the checks run against, as a session started here would use it
+pub struct Target {
+    /// The backend URL, or why there isn't one
+    pub backend_url: Result<String>,
+    pub login: Login,
+    pub working_directory: PathBuf,
+    pub claude_path: Option<PathBuf>,
+    pub claude_version: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Pass(String),
+    /// Works, but may cause trouble: what's wrong and what to do
+    Warn(String, String),
+    /// Won't work: what's wrong and what to do
+    Fail(String, String),
+    /// Not checked because an earlier check failed
+    Skipped(String),
+}
+
+/// Run every check, print the results, and fail if any check did
+pub async fn run(target: Target) -> Result<()> {
+    println!();
+    println!("  {}", "Checking this machine's setup...".bright_white());
+    println!();
+
+    let mut failed = 0;
+    let mut report = |name: &str, outcome: Outcome| {
+        failed += matches!(outcome, Outcome::Fail(..)) as usize;
+        print_outcome(name, &outcome);
+    };
+
+    report("Claude", check_claude(&target).await);
+    report("Directory", check_writable(&target.working_directory));
+
+    match &target.backend_url {
+        Ok(backend_url) => {
+            let (reachable, skew) = check_backend(backend_url).await;
+            let reached = !matches!(reachable, Outcome::Fail(..));
+            report("Backend", reachable);
+            report("Clock", skew);
+            if reached {
+                let (login, websocket) = check_websocket(backend_url, &target.login).await;
+                report("Login", login);
+                report("WebSocket", websocket);
+            } else {
+                report("Login", Outcome::Skipped("backend unreachable".to_string()));
+                report(
+                    "WebSocket",
+                    Outcome::Skipped("backend unreachable".to_string()),
+                );
+            }
+        }
+        Err(e) => {
+            report(
+                "Backend",
+                Outcome::Fail(
+                    e.to_string(),
+                    "Run claude-portal --init <URL> from the portal's \"Add Session\" dialog"
+                        .to_string(),
+                ),
+            );
+        }
+    }
+
+    println!();
+    if failed > 0 {
+        bail!(
+            "{} check{} failed",
+            failed,
+            if failed == 1 { "" } else { "s" }
+        );
+    }
+    println!("  {}", "Everything looks good.".bright_green());
+    Ok(())
+}
+
+fn print_outcome(name: &str, outcome: &Outcome) {
+    let (mark, detail, fix) = match outcome {
+        Outcome::Pass(detail) => ("✓".bright_green(), detail, None),
+        Outcome::Warn(detail, fix) => ("!".bright_yellow(), detail, Some(fix)),
+        Outcome::Fail(detail, fix) => ("✗".bright_red(), detail, Some(fix)),
+        Outcome::Skipped(detail) => ("-".dimmed(), detail, None),
+    };
+    println!("  {} {:<10} {}", mark, name, detail);
+    if let Some(fix) = fix {
+        println!("    {} {}", "→".bright_blue(), fix);
+    }
+}
+
+async fn check_claude(target: &Target) -> Outcome {
+    let config = SessionConfig {
+        working_directory: target.working_directory.clone(),
+        claude_path: target.claude_path.clone(),
+        claude_version: target.claude_version.clone(),
+        ..Default::default()
+    };
+    let pinned = discovery::pinned_version(&config);
+    let path = match discovery::find_claude(&config).await {
+        Ok(path) => path,
+        Err(e) => {
+            let fix = match pinned {
+                Some(pin) => format!(
+                    "Install Claude {} or change the pin (--claude-version or .claude-version)",
+                    pin
+                ),
+                None => "Install Claude (npm install -g @anthropic-ai/claude-code) or pass --claude-path"
+                    .to_string(),
+            };
+            return Outcome::Fail(e.to_string(), fix);
+        }
+    };
+    match discovery::binary_version(&path).await {
+        Some(version) => Outcome::Pass(format!("{} at {}", version, path.display())),
+        None => Outcome::Fail(
+            format!("{} doesn't run", path.display()),
+            "Reinstall Claude, or point --claude-path at a working binary".to_string(),
+        ),
+    }
+}
+
+fn check_writable(dir: &Path) -> Outcome {
+    let probe = dir.join(format!(".claude-portal-doctor-{}", std::process::id()));
+    match std::fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = std::fs::remove_file(&probe);
+            Outcome::Pass(format!("{} is writable", dir.display()))
+        }
+        Err(e) => Outcome::Fail(
+            format!("Can't write to {}: {}", dir.display(), e),
+            "Run from a directory you own, or fix its permissions; Claude edits files here"
+                .to_string(),
+        ),
+    }
+}
+
+fn http_base(backend_url: &str) -> String {
+    backend_url
+        .replace("ws://", "http://")
+        .replace("wss://", "https://")
+}
+
+/// Reach `/api/health`, and work out the clock offset from its `Date`
+async fn check_backend(backend_url: &str) -> (Outcome, Outcome) {
+    let url = format!("{}/api/health", http_base(backend_url));
+    let client = reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        .build()
+        .unwrap_or_default();
+    let sent_ms = heartbeat::now_ms();
+    let started = Instant::now();
+    let response = match client.get(&url).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            return (
+                Outcome::Fail(
+                    format!("Can't reach {}: {}", url, e),
+                    "Check the backend URL (--backend-url) and that this machine can reach it"
+                        .to_string(),
+                ),
+                Outcome::Skipped("backend unreachable".to_string()),
+            );
+        }
+    };
+    let elapsed = started.elapsed();
+    let received_ms = heartbeat::now_ms();
+
+    let reachable = if response.status().is_success() {
+        Outcome::Pass(format!(
+            "{} answered in {} ms",
+            http_base(backend_url),
+            elapsed.as_millis()
+        ))
+    } else {
+        Outcome::Fail(
+            format!("{} answered HTTP {}", url, response.status()),
+            "Check that the URL is the portal itself, not a proxy or login page in front of it"
+                .to_string(),
+        )
+    };
+    let skew = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|date| date.to_str().ok())
+        .and_then(|date| clock_offset_ms(date, sent_ms, received_ms))
+        .map_or_else(
+            || Outcome::Skipped("the backend didn't send the time".to_string()),
+            skew_outcome,
+        );
+    (reachable, skew)
+}
+
+/// The backend's clock minus this machine's, from an HTTP `Date` header
+/// and when the request went out and its answer came back
+fn clock_offset_ms(date: &str, sent_ms: u64, received_ms: u64) -> Option<i64> {
+    let backend_ms = chrono::DateTime::parse_from_rfc2822(date)
+        .ok()?
+        .timestamp_millis();
+    // `Date` is truncated to the second; assume it was stamped mid-second
+    let midpoint = (sent_ms + received_ms) as i64 / 2;
+    Some(backend_ms + 500 - midpoint)
+}
+
+fn skew_outcome(offset_ms: i64) -> Outcome {
+    let described = format!(
+        "{:.1}s {} the backend",
+        offset_ms.unsigned_abs() as f64 / 1000.0,
+        if offset_ms > 0 { "behind" } else { "ahead of" }
+    );
+    if offset_ms.abs() > LARGE_CLOCK_SKEW_MS {
+        Outcome::Warn(
+            format!("This machine's clock is {}", described),
+            "Turn on network time sync (timedatectl set-ntp true, or Date & Time settings)"
+                .to_string(),
+        )
+    } else if offset_ms.abs() < 1_500 {
+        Outcome::Pass("In sync with the backend".to_string())
+    } else {
+        Outcome::Pass(format!("Within tolerance ({})", described))
+    }
+}
+
+/// Open the WebSocket a session would, with this directory's login, and
+/// time a heartbeat over it
+async fn check_websocket(backend_url: &str, login: &Login) -> (Outcome, Outcome) {
+    let token = match login {
+        Login::Dev => None,
+        Login::Token(token) => Some(token.as_str()),
+        Login::Missing => {
+            return (
+                Outcome::Fail(
+                    "Not logged in for this directory".to_string(),
+                    "Run claude-portal here to log in, or use --profile".to_string(),
+                ),
+                Outcome::Skipped("no login".to_string()),
+            );
+        }
+    };
+
+    let mut request = match format!("{}/ws/client", backend_url).into_client_request() {
+        Ok(request) => request,
+        Err(e) => {
+            return (
+                Outcome::Skipped("no WebSocket".to_string()),
+                Outcome::Fail(
+                    format!("Invalid backend URL: {}", e),
+                    "The backend URL must start with ws:// or wss://".to_string(),
+                ),
+            );
+        }
+    };
+    if let Some(token) = token {
+        if let Ok(value) = format!("Bearer {}", token).parse() {
+            request.headers_mut().insert(header::AUTHORIZATION, value);
+        }
+    }
+
+    let stream = match tokio::time::timeout(TIMEOUT, connect_async(request)).await {
+        Ok(Ok((stream, _))) => stream,
+        Ok(Err(tungstenite::Error::Http(response)))
+            if response.status() == tungstenite::http::StatusCode::UNAUTHORIZED =>
+        {
+            let fix = match login {
+                Login::Dev => "Drop --dev; the backend isn't in dev mode",
+                _ => "Log in again with claude-portal --reauth",
+            };
+            return (
+                Outcome::Fail(
+                    "The backend rejected this login (expired or revoked)".to_string(),
+                    fix.to_string(),
+                ),
+                Outcome::Skipped("login rejected".to_string()),
+            );
+        }
+        Ok(Err(e)) => {
+            return (
+                Outcome::Skipped("no WebSocket".to_string()),
+                Outcome::Fail(
+                    format!("Can't open a WebSocket: {}", e),
+                    "HTTP works but WebSockets don't: a proxy or firewall may be blocking the upgrade"
+                        .to_string(),
+                ),
+            );
+        }
+        Err(_) => {
+            return (
+                Outcome::Skipped("no WebSocket".to_string()),
+                Outcome::Fail(
+                    format!("Opening a WebSocket took over {}s", TIMEOUT.as_secs()),
+                    "A proxy or firewall may be holding WebSocket upgrades".to_string(),
+                ),
+            );
+        }
+    };
+    let login = match login {
+        Login::Dev => Outcome::Pass("Dev mode accepted".to_string()),
+        _ => Outcome::Pass("Accepted by the backend".to_string()),
+    };
+
+    let (mut write, mut read) = stream.split();
+    let sent_at = heartbeat::now_ms();
+    let started = Instant::now();
+    let ping = serde_json::to_string(&ProxyMessage::Ping { sent_at }).unwrap_or_default();
+    if let Err(e) = write.send(Message::Text(ping)).await {
+        return (
+            login,
+            Outcome::Fail(
+                format!("The WebSocket closed: {}", e),
+                "A proxy may be closing idle WebSockets; check its timeouts".to_string(),
+            ),
+        );
+    }
+    let pong = tokio::time::timeout(TIMEOUT, async {
+        while let Some(Ok(message)) = read.next().await {
+            let Message::Text(text) = message else {
+                continue;
+            };
+            if let Ok(ProxyMessage::Pong { sent_at: echoed }) = serde_json::from_str(&text) {
+                if echoed == sent_at {
+                    return true;
+                }
+            }
+        }
+        false
+    })
+    .await;
+    let _ = write.send(Message::Close(None)).await;
+
+    let websocket = match pong {
+        Ok(true) => latency_outcome(started.elapsed()),
+        _ => Outcome::Fail(
+            "Connected, but no heartbeat came back".to_string(),
+            "A proxy may be buffering WebSocket traffic; sessions will keep reconnecting"
+                .to_string(),
+        ),
+    };
+    (login, websocket)
+}
+
+fn latency_outcome(rtt: Duration) -> Outcome {
+    let detail = format!("Round trip {} ms", rtt.as_millis());
+    if rtt > heartbeat::SLOW_RTT {
+        Outcome::Warn(
+            detail,
+            "The connection is slow; output will lag in the web UI".to_string(),
+        )
+    } else {
+        Outcome::Pass(detail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_offset() {
+        // 2024-01-01T00:00:00Z
+        let date = "Mon, 01 Jan 2024 00:00:00 GMT";
+        let backend_ms = 1_704_067_200_000;
+        assert_eq!(
+            clock_offset_ms(date, backend_ms + 400, backend_ms + 600),
+            Some(0)
+        );
+        assert_eq!(
+            clock_offset_ms(date, backend_ms - 59_600, backend_ms - 59_400),
+            Some(60_000)
+        );
+        assert_eq!(clock_offset_ms("yesterday", 0, 0), None);
+    }
+
+    #[test]
+    fn test_skew_outcome() {
+        assert!(matches!(skew_outcome(200), Outcome::Pass(_)));
+        assert_eq!(
+            skew_outcome(-5_000),
+            Outcome::Pass("Within tolerance (5.0s ahead of the backend)".to_string())
+        );
+        assert!(matches!(
+            skew_outcome(LARGE_CLOCK_SKEW_MS + 1),
+            Outcome::Warn(..)
+        ));
+    }
+
+    #[test]
+    fn test_latency_outcome() {
+        assert!(matches!(
+            latency_outcome(Duration::from_millis(40)),
+            Outcome::Pass(_)
+        ));
+        assert!(matches!(
+            latency_outcome(heartbeat::SLOW_RTT * 2),
+            Outcome::Warn(..)
+        ));
+    }
+
+    #[test]
+    fn test_check_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(matches!(check_writable(dir.path()), Outcome::Pass(_)));
+        assert!(matches!(
+            check_writable(&dir.path().join("missing")),
+            Outcome::Fail(..)
+        ));
+    }
+}
@@ -0,0 +1,42 @@
+//! `claude-portal fork`: start a session from a point in another's history.
+//!
+//! The backend rebuilds the source session's conversation up to the chosen
+//! message; it becomes the new session's first prompt.
+
+use anyhow::{bail, Context, Result};
+use shared::SessionFork;
+use uuid::Uuid;
+
+/// Fetch the starting point for a fork of `session_id` after message `at`
+pub async fn fetch_fork(
+    backend_url: &str,
+    auth_token: Option<&str>,
+    session_id: Uuid,
+    at: Uuid,
+) -> Result<SessionFork> {
+    let http_base = backend_url
+        .replace("ws://", "http://")
+        .replace("wss://", "https://");
+    let url = format!("{}/api/sessions/{}/fork?at={}", http_base, session_id, at);
+
+    let mut request = reqwest::Client::new().get(&url);
+    if let Some(token) = auth_token {
+        request = request.bearer_auth(token);
+    }
+    let response = request
+        .send()
+        .await
+        .context("Failed to reach the backend")?;
+
+    match response.status().as_u16() {
+        200 => response
+            .json::<SessionFork>()
+            .await
+            .context("Invalid fork response from backend"),
+        401 => bail!("Not authorized. Run with --reauth to log in again."),
+        403 => bail!("This directory's login can't access that session."),
+        404 => bail!("Session or message not found."),
+        422 => bail!("There's no conversation to fork before that message."),
+        status => bail!("Failed to fork session: HTTP {}", status),
+    }
+}
@@ -0,0 +1,100 @@
+//! Application-level heartbeat with the backend.
+//!
+//! The proxy pings every [`HEARTBEAT_INTERVAL_MS`] and the backend echoes the
+//! timestamp back in a `Pong`. That gives a round-trip time, and notices a
+//! half-open connection long before TCP would.
+
+use shared::{HEARTBEAT_INTERVAL_MS, HEARTBEAT_TIMEOUT_MS};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Round trips slower than this are reported in the terminal
+pub const SLOW_RTT: Duration = Duration::from_secs(1);
+
+pub const INTERVAL: Duration = Duration::from_millis(HEARTBEAT_INTERVAL_MS);
+const TIMEOUT: Duration = Duration::from_millis(HEARTBEAT_TIMEOUT_MS);
+
+/// Milliseconds since the Unix epoch, for `Ping::sent_at`
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Change in connection speed worth telling the user about
+#[derive(Debug, PartialEq)]
+pub enum LatencyChange {
+    Slow(Duration),
+    Recovered(Duration),
+}
+
+/// Heartbeat state for one connection
+#[derive(Default)]
+pub struct Heartbeat {
+    last_pong: Option<Instant>,
+    slow: bool,
+}
+
+impl Heartbeat {
+    /// Record a pong for a ping sent at `sent_at` (ms since epoch). Returns
+    /// the round-trip time and whether the connection became slow or fast.
+    pub fn record_pong(
+        &mut self,
+        sent_at: u64,
+        now_ms: u64,
+        now: Instant,
+    ) -> (Duration, Option<LatencyChange>) {
+        self.last_pong = Some(now);
+        let rtt = Duration::from_millis(now_ms.saturating_sub(sent_at));
+        let change = match (self.slow, rtt > SLOW_RTT) {
+            (false, true) => Some(LatencyChange::Slow(rtt)),
+            (true, false) => Some(LatencyChange::Recovered(rtt)),
+            _ => None,
+        };
+        self.slow = rtt > SLOW_RTT;
+        (rtt, change)
+    }
+
+    /// Whether the backend has stopped answering. A backend that never
+    /// answered may not support pings, so it is given the benefit of the doubt.
+    pub fn timed_out(&self, now: Instant) -> bool {
+        self.last_pong
+            .is_some_and(|last| now.duration_since(last) > TIMEOUT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_times_out_only_after_a_pong() {
+        let start = Instant::now();
+        let mut heartbeat = Heartbeat::default();
+        assert!(!heartbeat.timed_out(start + TIMEOUT * 2));
+
+        heartbeat.record_pong(1_000, 1_020, start);
+        assert!(!heartbeat.timed_out(start + TIMEOUT));
+        assert!(heartbeat.timed_out(start + TIMEOUT + Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn test_reports_latency_changes_once() {
+        let now = Instant::now();
+        let mut heartbeat = Heartbeat::default();
+
+        let (rtt, change) = heartbeat.record_pong(1_000, 1_040, now);
+        assert_eq!(rtt, Duration::from_millis(40));
+        assert_eq!(change, None);
+
+        let slow = Duration::from_millis(1_500);
+        assert_eq!(
+            heartbeat.record_pong(2_000, 3_500, now).1,
+            Some(LatencyChange::Slow(slow))
+        );
+        assert_eq!(heartbeat.record_pong(3_000, 4_500, now).1, None);
+        assert_eq!(
+            heartbeat.record_pong(4_000, 4_050, now).1,
+            Some(LatencyChange::Recovered(Duration::from_millis(50)))
+        );
+    }
+}
@@ -0,0 +1,87 @@
+//! In-memory tail of recent log output, included in incident reports.
+//!
+//! The tracing subscriber writes through [`TeeWriter`], which forwards to stdout
+//! as before and keeps the last [`MAX_LOG_LINES`] lines in memory. While the
+//! `--tui` status screen is up, only the in-memory tail is kept.
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::{Mutex, OnceLock};
+
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Number of log lines kept for incident reports
+const MAX_LOG_LINES: usize = 200;
+
+fn tail() -> &'static Mutex<VecDeque<String>> {
+    static TAIL: OnceLock<Mutex<VecDeque<String>>> = OnceLock::new();
+    TAIL.get_or_init(|| Mutex::new(VecDeque::with_capacity(MAX_LOG_LINES)))
+}
+
+/// Drop ANSI color sequences so stored lines read cleanly in a bundle
+fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // CSI sequences end with a byte in the range '@'..='~'
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) && c != '[' {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn push_line(line: &str) {
+    if let Ok(mut tail) = tail().lock() {
+        if tail.len() >= MAX_LOG_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(strip_ansi(line));
+    }
+}
+
+/// Most recent log lines, oldest first, with secrets redacted
+pub fn recent_lines() -> Vec<String> {
+    tail()
+        .lock()
+        .map(|tail| tail.iter().map(|l| shared::redact_secrets(l)).collect())
+        .unwrap_or_default()
+}
+
+/// `MakeWriter` that tees log output to stdout and the in-memory tail
+#[derive(Clone, Copy, Default)]
+pub struct TeeWriter;
+
+impl<'a> MakeWriter<'a> for TeeWriter {
+    type Writer = TeeWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        TeeWriter
+    }
+}
+
+impl Write for TeeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // The fmt layer writes each event in one call, so lines don't interleave
+        for line in String::from_utf8_lossy(buf).lines() {
+            if !line.is_empty() {
+                push_line(line);
+            }
+        }
+        // The status screen owns the terminal while it's up
+        if crate::ui::is_quiet() {
+            return Ok(buf.len());
+        }
+        io::stdout().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
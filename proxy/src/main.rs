@@ -1,20 +1,44 @@
+mod artifacts;
 mod auth;
 mod commands;
 mod config;
+mod daemon;
+mod doctor;
+mod fork;
+mod heartbeat;
+mod log_buffer;
+mod mux;
 mod output_buffer;
+mod paste;
+mod permission_rules;
+mod profiles;
+mod resume;
+mod service;
 mod session;
+mod settings;
+mod snapshot;
+mod status;
+mod tail;
+mod terminal_prompt;
+mod tui;
 mod ui;
 mod update;
 mod util;
 
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use claude_session_lib::{Session as ClaudeSession, SessionConfig};
 use config::{ProxyConfig, SessionAuth};
+use output_buffer::BufferOptions;
 use session::ProxySessionConfig;
-use tracing::{info, warn};
+use settings::FileSettings;
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
 #[derive(Parser, Debug)]
@@ -29,7 +53,17 @@ QUICK START:\n  \
 3. Start coding: claude-portal [claude args]\n\n\
 CONFIG:\n  \
 Configuration is stored in ~/.config/claude-code-portal/config.json and includes\n  \
-the backend URL and authentication tokens per working directory."
+the backend URL and authentication tokens per working directory. Defaults for\n  \
+the backend URL, token, session names, claude binary, log level, and reconnects\n  \
+can be set in ~/.config/claude-proxy/config.toml; flags override them.\n\n\
+DAEMON:\n  \
+claude-portal daemon hosts sessions for many directories in one process over a\n  \
+single backend connection; run claude-portal attach <name> in each directory.\n  \
+claude-portal service install keeps it running as a systemd or launchd user\n  \
+service, for machines whose sessions should survive a reboot.\n\n\
+PROFILES:\n  \
+--profile NAME connects to the profile's backend with its own login instead of\n  \
+the directory's. Manage them with: claude-portal profiles list|add|remove"
 )]
 #[command(after_help = "EXAMPLES:\n  \
   # First-time setup with token from web UI\n  \
@@ -40,11 +74,32 @@ the backend URL and authentication tokens per working directory."
   claude-portal --session-name \"feature-xyz\"\n\n  \
   # Force a fresh session (don't resume previous)\n  \
   claude-portal --new-session\n\n  \
+  # Continue one of Claude's earlier conversations in this directory\n  \
+  claude-portal --resume\n\n  \
   # Pass arguments through to claude CLI\n  \
   claude-portal --model sonnet -- \"explain this code\"\n\n  \
   # Re-authenticate if token expired\n  \
-  claude-portal --reauth")]
+  claude-portal --reauth\n\n  \
+  # Connect to a second backend with its own login\n  \
+  claude-portal profiles add homelab https://portal.home.lan\n  \
+  claude-portal --profile homelab\n\n  \
+  # Watch a session's output from another machine\n  \
+  claude-portal tail 123e4567-e89b-12d3-a456-426614174000\n\n  \
+  # Branch a new session from a message in another (the web UI shows this)\n  \
+  claude-portal fork <session-id> --at <message-id>\n\n  \
+  # Host several projects' sessions in one background process\n  \
+  claude-portal daemon &\n  \
+  cd ~/src/api && claude-portal attach api\n\n  \
+  # Keep the daemon running across reboots, with this directory's login\n  \
+  claude-portal service install\n\n  \
+  # Watch sessions on this machine without the web UI\n  \
+  claude-portal --tui\n\n  \
+  # Compare two saved session snapshots when debugging a restore\n  \
+  claude-portal snapshot diff before.json after.json")]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Initialize proxy with a setup token from the web interface.
     ///
     /// The token URL is displayed in the web UI when you click "Add Session".
@@ -80,6 +135,20 @@ struct Args {
     #[arg(long)]
     new_session: bool,
 
+    /// Continue a conversation from Claude's local history.
+    ///
+    /// Takes the Claude session ID of a conversation started in this
+    /// directory; without one, lists the recent ones to choose from. The
+    /// conversation becomes this directory's session, so later runs resume
+    /// it too.
+    #[arg(
+        long,
+        value_name = "CLAUDE_SESSION_ID",
+        num_args = 0..=1,
+        conflicts_with = "new_session"
+    )]
+    resume: Option<Option<Uuid>>,
+
     /// Force re-authentication with the backend server.
     ///
     /// Use this if your cached auth token has expired or you need
@@ -122,6 +191,66 @@ struct Args {
     #[arg(long)]
     update: bool,
 
+    /// Unacknowledged outputs to keep in memory for replay (default: 1000).
+    ///
+    /// Output waits here while the backend is unreachable and is replayed
+    /// on reconnect; past this many, the oldest is spooled to disk, or
+    /// dropped with --no-buffer-spill.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    buffer_size: Option<u32>,
+
+    /// Keep unacknowledged output in memory only.
+    ///
+    /// By default it is also written to disk, so output produced while the
+    /// backend was unreachable survives a proxy restart, and output past
+    /// --buffer-size is spooled rather than dropped.
+    #[arg(long)]
+    no_buffer_spill: bool,
+
+    /// Claude binary to run instead of searching for one.
+    ///
+    /// By default claude is looked for on PATH, then where npm, Homebrew,
+    /// Volta, bun, and the native installer put it.
+    #[arg(long, value_name = "PATH")]
+    claude_path: Option<PathBuf>,
+
+    /// Only run this Claude version, e.g. 2.1.17 or 2.1.
+    ///
+    /// Defaults to the version in a .claude-version file in this directory
+    /// or one above it, if there is one.
+    #[arg(long, value_name = "VERSION")]
+    claude_version: Option<String>,
+
+    /// Log filter, e.g. debug or info,claude_portal=debug.
+    ///
+    /// RUST_LOG wins over this if set. Defaults to log_level in the
+    /// settings file, then info.
+    #[arg(long, value_name = "FILTER")]
+    log_level: Option<String>,
+
+    /// Connect with a named profile's backend and login.
+    ///
+    /// Defaults to profile in the settings file. Without a profile, the
+    /// backend and login saved for this directory are used.
+    #[arg(long, value_name = "NAME", global = true)]
+    profile: Option<String>,
+
+    /// Show a live status screen instead of the scrolling log.
+    ///
+    /// Lists sessions with their connection and state, permission prompts
+    /// waiting on the web UI, recent messages, and throughput. Press q or
+    /// Ctrl+C to stop.
+    #[arg(long, global = true)]
+    tui: bool,
+
+    /// Seconds a permission request waits on the web UI before the
+    /// terminal asks about it (default: 60, 0 to never ask).
+    ///
+    /// A y or n typed in reply answers the request as if from the web UI.
+    /// Never asked under --tui or when stdin isn't a terminal.
+    #[arg(long, value_name = "SECS")]
+    terminal_prompt_after: Option<u64>,
+
     /// Arguments to pass through to the claude CLI.
     ///
     /// Everything after -- or unrecognized flags are forwarded to claude.
@@ -129,13 +258,104 @@ struct Args {
     claude_args: Vec<String>,
 }
 
-fn default_session_name() -> String {
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print a session's output as it happens.
+    ///
+    /// Follows any session you're a member of, using this directory's saved
+    /// login. Read-only: nothing is sent to the session.
+    Tail {
+        /// Session to follow; defaults to this directory's session
+        session_id: Option<Uuid>,
+
+        /// Print the stored transcript before following new output
+        #[arg(long)]
+        history: bool,
+    },
+    /// Start a new session from a point in another session's history.
+    ///
+    /// Replays the source session's conversation up to and including the
+    /// given message as the new session's first prompt. Run it in the source
+    /// session's directory; the web UI's "Fork from here" shows the command.
+    Fork {
+        /// Session to fork
+        session_id: Uuid,
+
+        /// Message to fork after
+        #[arg(long, value_name = "MESSAGE_ID")]
+        at: Uuid,
+    },
+    /// Inspect or compare saved session snapshots.
+    ///
+    /// For debugging restores without reading the JSON by hand.
+    Snapshot {
+        #[command(subcommand)]
+        command: snapshot::SnapshotCommand,
+    },
+    /// Host sessions for many directories over one backend connection.
+    ///
+    /// Runs until killed. Sessions are added with `attach`, run in each
+    /// directory, and use the login for the directory the daemon was started
+    /// in, or for --profile.
+    Daemon,
+    /// Start this directory's session in the running daemon.
+    ///
+    /// Resumes the directory's previous session if there is one, renamed to
+    /// NAME. Returns once the daemon has taken it on.
+    Attach {
+        /// Session name shown in the web interface
+        name: String,
+
+        /// Start a fresh session instead of resuming this directory's
+        #[arg(long)]
+        new_session: bool,
+    },
+    /// List, add, or remove named profiles for other backends.
+    Profiles {
+        #[command(subcommand)]
+        command: profiles::ProfilesCommand,
+    },
+    /// Run the daemon as a systemd (Linux) or launchd (macOS) user service.
+    ///
+    /// `install` sets it up in this directory with this directory's login,
+    /// or --profile's, so sessions stay reachable after a reboot.
+    Service {
+        #[command(subcommand)]
+        command: service::ServiceCommand,
+    },
+    /// Check that this machine can run a session, and say how to fix what
+    /// can't.
+    ///
+    /// Looks for the Claude binary, reaches the backend over HTTP and
+    /// WebSocket with this directory's login, and checks clock skew and
+    /// write access to this directory. Never logs in or starts a session.
+    Doctor,
+}
+
+/// Name for a new session, from the settings file's pattern if it has one
+fn default_session_name(settings: &FileSettings, cwd: &str) -> String {
     let hostname = hostname::get()
         .ok()
         .and_then(|h| h.into_string().ok())
         .unwrap_or_else(|| "unknown".to_string());
-    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
-    format!("{}-{}", hostname, timestamp)
+    let dir = std::path::Path::new(cwd)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let branch = get_git_branch(cwd);
+    let pattern = settings
+        .session_name
+        .as_deref()
+        .unwrap_or(settings::DEFAULT_SESSION_NAME);
+    settings::expand_session_name(
+        pattern,
+        &settings::NameContext {
+            hostname: &hostname,
+            dir: &dir,
+            branch: branch.as_deref(),
+            now: chrono::Local::now(),
+        },
+    )
 }
 
 /// Get the current git branch name, if in a git repository
@@ -224,15 +444,45 @@ async fn handle_force_update() -> Result<()> {
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    dotenvy::dotenv().ok();
+
+    let mut args = Args::parse();
+    let file_settings = FileSettings::load()?;
+    args.profile = args.profile.or_else(|| file_settings.profile.clone());
+    let settings = match &args.profile {
+        Some(name) => file_settings.with_profile(name),
+        None => file_settings.clone(),
+    };
+    args.claude_path = args.claude_path.or_else(|| settings.claude_path.clone());
+
+    let log_level = args
+        .log_level
+        .clone()
+        .or_else(|| settings.log_level.clone())
+        .unwrap_or_else(|| "info".to_string());
     tracing_subscriber::fmt()
         .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| log_level.into()),
         )
+        .with_writer(log_buffer::TeeWriter)
         .init();
 
-    dotenvy::dotenv().ok();
-
-    let args = Args::parse();
+    // Works on local files only; no config, login, or update check needed
+    if let Some(Command::Snapshot { command }) = args.command {
+        return snapshot::run(command);
+    }
+    if let Some(Command::Profiles { command }) = args.command {
+        return profiles::run(command, &file_settings);
+    }
+    // Only talks to the local daemon, which has its own login
+    if let Some(Command::Attach { name, new_session }) = args.command {
+        return run_attach(name, new_session).await;
+    }
+    // Drives the service manager; the daemon it starts does the rest
+    if let Some(Command::Service { command }) = args.command {
+        return service::run(command, args.profile.as_deref(), &settings);
+    }
 
     // Check for and apply pending updates (Windows only)
     // This handles the case where an update was downloaded but couldn't be
@@ -251,7 +501,11 @@ async fn main() -> Result<()> {
     }
 
     // Check for updates before anything else (unless --no-update or --init/--logout)
-    if !args.no_update && args.init.is_none() && !args.logout {
+    if !args.no_update
+        && args.init.is_none()
+        && !args.logout
+        && !matches!(args.command, Some(Command::Doctor))
+    {
         match update::check_for_update_github(false).await {
             Ok(update::UpdateResult::UpToDate) => {
                 // Continue normally
@@ -279,25 +533,101 @@ async fn main() -> Result<()> {
 
     let mut config = ProxyConfig::load().context("Failed to load config file")?;
 
+    // --init is how a profile can first be created
+    if let Some(name) = &args.profile {
+        if !profiles::valid_name(name) {
+            anyhow::bail!("Invalid profile name '{}'", name);
+        }
+        if args.init.is_none() && !profiles::exists(name, &config, &settings) {
+            anyhow::bail!(
+                "No profile named '{}'. Add it with: claude-portal profiles add {} <url>",
+                name,
+                name
+            );
+        }
+    }
+
+    if let Some(Command::Doctor) = args.command {
+        let login = if args.dev {
+            doctor::Login::Dev
+        } else if let Some(token) = args.auth_token.clone().or(settings.auth_token()?) {
+            doctor::Login::Token(token)
+        } else {
+            saved_auth(&args, &config, &cwd).map_or(doctor::Login::Missing, |(token, _)| {
+                doctor::Login::Token(token.to_string())
+            })
+        };
+        return doctor::run(doctor::Target {
+            backend_url: resolve_backend_url(&args, &config, &settings, &cwd),
+            login,
+            working_directory: PathBuf::from(&cwd),
+            claude_path: args.claude_path.clone(),
+            claude_version: args.claude_version.clone(),
+        })
+        .await;
+    }
+
     // Handle subcommands that exit early
     if args.logout {
-        return commands::handle_logout(&mut config, &cwd);
+        return commands::handle_logout(&mut config, &cwd, args.profile.as_deref());
     }
 
     if let Some(ref init_value) = args.init {
-        return commands::handle_init(&mut config, &cwd, init_value, args.backend_url.as_deref());
+        return commands::handle_init(
+            &mut config,
+            &cwd,
+            init_value,
+            args.backend_url.as_deref(),
+            args.profile.as_deref(),
+        );
+    }
+
+    if let Some(Command::Tail {
+        session_id,
+        history,
+    }) = args.command
+    {
+        let session_id = session_id
+            .or_else(|| config.get_directory_session(&cwd).map(|s| s.session_id))
+            .ok_or_else(|| {
+                anyhow::anyhow!("No session for this directory. Pass a session ID to follow.")
+            })?;
+        let backend_url = resolve_backend_url(&args, &config, &settings, &cwd)?;
+        let auth_token = if args.dev {
+            None
+        } else if let Some(token) = args.auth_token.clone().or(settings.auth_token()?) {
+            Some(token)
+        } else {
+            saved_auth(&args, &config, &cwd).map(|(token, _)| token.to_string())
+        };
+        return tail::run_tail(&backend_url, auth_token.as_deref(), session_id, history).await;
+    }
+
+    if let Some(Command::Fork { session_id, at }) = args.command {
+        return run_fork(&args, &settings, &mut config, &cwd, session_id, at).await;
+    }
+
+    if let Some(Command::Daemon) = args.command {
+        let backend_url = resolve_backend_url(&args, &config, &settings, &cwd)?;
+        let auth_token =
+            resolve_auth_token(&args, &settings, &mut config, &cwd, &backend_url).await?;
+        return run_daemon(&args, &settings, backend_url, auth_token).await;
     }
 
     // Resolve session (new or resume)
-    let (session_id, session_name, resuming) = resolve_session(&args, &cwd)?;
+    let (session_id, session_name, resuming) = match args.resume {
+        Some(conversation) => {
+            resume_conversation(conversation, args.session_name.as_deref(), &settings, &cwd)?
+        }
+        None => resolve_session(
+            args.new_session,
+            args.session_name.as_deref(),
+            &settings,
+            &cwd,
+        )?,
+    };
 
-    // Resolve backend URL: CLI arg > per-directory config > global default
-    let backend_url = args.backend_url.clone()
-        .or_else(|| config.get_backend_url(&cwd).map(|s| s.to_string()))
-        .or_else(|| config.preferences.default_backend_url.clone())
-        .ok_or_else(|| anyhow::anyhow!(
-            "No backend URL configured. Run with --init <URL> first, or specify --backend-url explicitly."
-        ))?;
+    let backend_url = resolve_backend_url(&args, &config, &settings, &cwd)?;
 
     // Print startup info
     ui::print_startup_banner();
@@ -309,7 +639,7 @@ async fn main() -> Result<()> {
     );
 
     // Resolve auth token
-    let auth_token = resolve_auth_token(&args, &mut config, &cwd, &backend_url).await?;
+    let auth_token = resolve_auth_token(&args, &settings, &mut config, &cwd, &backend_url).await?;
 
     // Detect git branch
     let git_branch = get_git_branch(&cwd);
@@ -327,35 +657,218 @@ async fn main() -> Result<()> {
         resume: resuming,
         git_branch,
         claude_args: args.claude_args.clone(),
+        initial_prompt: None,
+        buffer: buffer_options(&args),
+        claude_path: args.claude_path.clone(),
+        claude_version: args.claude_version.clone(),
+        reconnect: settings.reconnect,
+        mux: None,
+        terminal_prompt_after: terminal_prompt_after(&args),
     };
 
     // Start Claude and run session
+    let _screen = start_screen(&args, &session_config.backend_url)?;
     run_proxy_session(session_config).await
 }
 
-/// Resolve which session to use (new or resume existing)
-fn resolve_session(args: &Args, cwd: &str) -> Result<(Uuid, String, bool)> {
+/// Start a new session in this directory that picks up `source_session_id`'s
+/// conversation after message `at`
+async fn run_fork(
+    args: &Args,
+    settings: &FileSettings,
+    config: &mut ProxyConfig,
+    cwd: &str,
+    source_session_id: Uuid,
+    at: Uuid,
+) -> Result<()> {
+    let backend_url = resolve_backend_url(args, config, settings, cwd)?;
+    let auth_token = resolve_auth_token(args, settings, config, cwd, &backend_url).await?;
+    let fork = fork::fetch_fork(&backend_url, auth_token.as_deref(), source_session_id, at).await?;
+    if fork.working_directory != cwd {
+        anyhow::bail!(
+            "That session ran in {}. Run the fork from there.",
+            fork.working_directory
+        );
+    }
+
+    // The fork becomes this directory's session, as with --new-session
+    let session_id = Uuid::new_v4();
+    let session_name = args
+        .session_name
+        .clone()
+        .unwrap_or_else(|| fork.session_name());
+    let (mut proxy_config, lock) =
+        ProxyConfig::load_locked().context("Failed to load config with lock")?;
+    proxy_config.set_directory_session(
+        cwd.to_string(),
+        ProxyConfig::create_directory_session(session_id, session_name.clone()),
+    );
+    proxy_config.save_with_lock(&lock)?;
+    info!(
+        "Forking session {} after message {} as {}",
+        source_session_id, at, session_id
+    );
+
+    ui::print_startup_banner();
+    ui::print_session_info(&session_name, &session_id.to_string(), &backend_url, false);
+    ui::print_forking(&fork.source_session_name);
+
+    let session_config = ProxySessionConfig {
+        backend_url,
+        session_id,
+        session_name,
+        auth_token,
+        working_directory: cwd.to_string(),
+        resume: false,
+        git_branch: get_git_branch(cwd),
+        claude_args: args.claude_args.clone(),
+        initial_prompt: Some(fork.prompt),
+        buffer: buffer_options(args),
+        claude_path: args.claude_path.clone(),
+        claude_version: args.claude_version.clone(),
+        reconnect: settings.reconnect,
+        mux: None,
+        terminal_prompt_after: terminal_prompt_after(args),
+    };
+    let _screen = start_screen(args, &session_config.backend_url)?;
+    run_proxy_session(session_config).await
+}
+
+/// The status screen, if --tui was given
+fn start_screen(args: &Args, backend_url: &str) -> Result<Option<tui::Screen>> {
+    args.tui.then(|| tui::start(backend_url)).transpose()
+}
+
+/// When to ask about unanswered permission requests in the terminal, from
+/// --terminal-prompt-after; never under --tui, which owns the keyboard
+fn terminal_prompt_after(args: &Args) -> Option<Duration> {
+    if args.tui || !std::io::stdin().is_terminal() {
+        return None;
+    }
+    match args
+        .terminal_prompt_after
+        .unwrap_or(terminal_prompt::DEFAULT_AFTER_SECS)
+    {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+/// Output buffer settings from --buffer-size and --no-buffer-spill
+fn buffer_options(args: &Args) -> BufferOptions {
+    BufferOptions {
+        max_messages: args
+            .buffer_size
+            .map_or(output_buffer::DEFAULT_MAX_MESSAGES, |n| n as usize),
+        persist: !args.no_buffer_spill,
+    }
+}
+
+/// Resolve backend URL: CLI arg > per-directory config > settings file >
+/// global default. With a profile: CLI arg > the profile's settings > the
+/// profile's saved URL.
+fn resolve_backend_url(
+    args: &Args,
+    config: &ProxyConfig,
+    settings: &FileSettings,
+    cwd: &str,
+) -> Result<String> {
+    if let Some(name) = &args.profile {
+        return args
+            .backend_url
+            .clone()
+            .or_else(|| settings.backend_url.clone())
+            .or_else(|| config.get_profile(name).map(|p| p.backend_url.clone()))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Profile '{}' has no backend URL. Run: claude-portal profiles add {} <url>",
+                    name,
+                    name
+                )
+            });
+    }
+    args.backend_url
+        .clone()
+        .or_else(|| config.get_backend_url(cwd).map(|s| s.to_string()))
+        .or_else(|| settings.backend_url.clone())
+        .or_else(|| config.preferences.default_backend_url.clone())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "No backend URL configured. Run with --init <URL> first, or specify --backend-url explicitly."
+            )
+        })
+}
+
+/// Make Claude conversation `conversation`, or one picked from this
+/// directory's recent conversations, this directory's session and resume it
+fn resume_conversation(
+    conversation: Option<Uuid>,
+    session_name: Option<&str>,
+    settings: &FileSettings,
+    cwd: &str,
+) -> Result<(Uuid, String, bool)> {
+    let session_id = match conversation {
+        Some(id) => id,
+        None => resume::pick(std::path::Path::new(cwd))?,
+    };
+    // claude --resume only finds conversations started in its directory
+    if !claude_session_lib::history::conversation_exists(std::path::Path::new(cwd), session_id) {
+        anyhow::bail!(
+            "Claude has no conversation {} from this directory. Run claude-portal --resume to list them.",
+            session_id
+        );
+    }
+
+    let (mut config, lock) =
+        ProxyConfig::load_locked().context("Failed to load config with lock")?;
+    let session_name = session_name
+        .map(str::to_string)
+        .or_else(|| {
+            config
+                .get_directory_session(cwd)
+                .filter(|s| s.session_id == session_id)
+                .map(|s| s.session_name.clone())
+        })
+        .unwrap_or_else(|| default_session_name(settings, cwd));
+    config.set_directory_session(
+        cwd.to_string(),
+        ProxyConfig::create_directory_session(session_id, session_name.clone()),
+    );
+    config.save_with_lock(&lock)?;
+
+    info!("Resuming Claude conversation {}", session_id);
+    ui::print_resuming_conversation(&session_id.to_string());
+    Ok((session_id, session_name, true))
+}
+
+/// Resolve which session to use (new or resume existing). `session_name`
+/// names a new session or renames a resumed one.
+fn resolve_session(
+    new_session: bool,
+    session_name: Option<&str>,
+    settings: &FileSettings,
+    cwd: &str,
+) -> Result<(Uuid, String, bool)> {
     let (mut config, lock) =
         ProxyConfig::load_locked().context("Failed to load config with lock")?;
 
     let existing_session = config.get_directory_session(cwd).cloned();
 
     // Force new session or no existing session to resume
-    if args.new_session || existing_session.is_none() {
+    if new_session || existing_session.is_none() {
         let had_existing = existing_session.is_some();
 
         // Start a new session
         let session_id = Uuid::new_v4();
-        let session_name = args
-            .session_name
-            .clone()
-            .unwrap_or_else(default_session_name);
+        let session_name = session_name
+            .map(str::to_string)
+            .unwrap_or_else(|| default_session_name(settings, cwd));
 
         let dir_session = ProxyConfig::create_directory_session(session_id, session_name.clone());
         config.set_directory_session(cwd.to_string(), dir_session);
         config.save_with_lock(&lock)?;
 
-        if args.new_session && had_existing {
+        if new_session && had_existing {
             warn!(
                 "Starting new session (--new-session flag) - previous session will not be resumed"
             );
@@ -372,9 +885,8 @@ fn resolve_session(args: &Args, cwd: &str) -> Result<(Uuid, String, bool)> {
         Ok((session_id, session_name, false))
     } else if let Some(existing) = existing_session {
         // Resume existing session
-        let session_name = args
-            .session_name
-            .clone()
+        let session_name = session_name
+            .map(str::to_string)
             .unwrap_or_else(|| existing.session_name.clone());
 
         config.touch_directory_session(cwd);
@@ -396,6 +908,7 @@ fn resolve_session(args: &Args, cwd: &str) -> Result<(Uuid, String, bool)> {
 /// Resolve the authentication token
 async fn resolve_auth_token(
     args: &Args,
+    settings: &FileSettings,
     config: &mut ProxyConfig,
     cwd: &str,
     backend_url: &str,
@@ -409,10 +922,14 @@ async fn resolve_auth_token(
         return Ok(Some(token.clone()));
     }
 
+    if let Some(token) = settings.auth_token()? {
+        return Ok(Some(token));
+    }
+
     if !args.reauth {
-        if let Some(session_auth) = config.get_session_auth(cwd) {
-            ui::print_user(session_auth.user_email.as_deref().unwrap_or("unknown user"));
-            return Ok(Some(session_auth.auth_token.clone()));
+        if let Some((token, user_email)) = saved_auth(args, config, cwd) {
+            ui::print_user(user_email.unwrap_or("unknown user"));
+            return Ok(Some(token.to_string()));
         }
     }
 
@@ -420,6 +937,13 @@ async fn resolve_auth_token(
     info!("Authenticating via device flow");
     let (token, user_id, user_email) = auth::device_flow_login(backend_url, Some(cwd)).await?;
 
+    if let Some(name) = &args.profile {
+        config.set_profile_backend(name, backend_url);
+        config.set_profile_auth(name, token.clone(), Some(user_email));
+        config.atomic_save()?;
+        return Ok(Some(token));
+    }
+
     config.set_session_auth(
         cwd.to_string(),
         SessionAuth {
@@ -436,17 +960,171 @@ async fn resolve_auth_token(
     Ok(Some(token))
 }
 
+/// The saved login to use, as token and email: the profile's if there is
+/// one, otherwise this directory's
+fn saved_auth<'a>(
+    args: &Args,
+    config: &'a ProxyConfig,
+    cwd: &str,
+) -> Option<(&'a str, Option<&'a str>)> {
+    match &args.profile {
+        Some(name) => config.get_profile(name).and_then(|profile| {
+            let token = profile.auth_token.as_deref()?;
+            Some((token, profile.user_email.as_deref()))
+        }),
+        None => config
+            .get_session_auth(cwd)
+            .map(|auth| (auth.auth_token.as_str(), auth.user_email.as_deref())),
+    }
+}
+
+/// Host sessions for the directories attached to this daemon, all over one
+/// backend connection, until the process is killed
+async fn run_daemon(
+    args: &Args,
+    settings: &FileSettings,
+    backend_url: String,
+    auth_token: Option<String>,
+) -> Result<()> {
+    let mux = mux::Mux::default();
+    tokio::spawn({
+        let mux = mux.clone();
+        let backend_url = backend_url.clone();
+        let reconnect = settings.reconnect;
+        async move { mux.run(backend_url, reconnect).await }
+    });
+    ui::print_daemon_started(&backend_url, &daemon::socket_path()?);
+    let _screen = start_screen(args, &backend_url)?;
+
+    // Directories with a session running, with their names, so one isn't
+    // started twice and a restarted daemon can start them again
+    let attached: Arc<Mutex<BTreeMap<String, String>>> = Arc::default();
+    let attach = |request: daemon::AttachRequest| {
+        let cwd = request.working_directory;
+        {
+            let mut attached = attached.lock().unwrap();
+            if attached.contains_key(&cwd) {
+                return daemon::AttachReply::failed(format!("{} is already attached", cwd));
+            }
+            attached.insert(cwd.clone(), request.session_name.clone());
+        }
+        let (session_id, session_name, resuming) = match resolve_session(
+            request.new_session,
+            Some(&request.session_name),
+            settings,
+            &cwd,
+        ) {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                attached.lock().unwrap().remove(&cwd);
+                return daemon::AttachReply::failed(e);
+            }
+        };
+        info!("Attaching {} in {} as {}", session_name, cwd, session_id);
+        save_attached(&attached);
+
+        let session_config = ProxySessionConfig {
+            backend_url: backend_url.clone(),
+            session_id,
+            session_name,
+            auth_token: auth_token.clone(),
+            working_directory: cwd.clone(),
+            resume: resuming,
+            git_branch: get_git_branch(&cwd),
+            claude_args: args.claude_args.clone(),
+            initial_prompt: None,
+            buffer: buffer_options(args),
+            claude_path: args.claude_path.clone(),
+            claude_version: args.claude_version.clone(),
+            reconnect: settings.reconnect,
+            mux: Some(mux.clone()),
+            // Daemon sessions have no terminal of their own
+            terminal_prompt_after: None,
+        };
+        let attached = attached.clone();
+        tokio::spawn(async move {
+            if let Err(e) = run_proxy_session(session_config).await {
+                error!("Session in {} ended with an error: {}", cwd, e);
+            }
+            info!("Session in {} ended", cwd);
+            attached.lock().unwrap().remove(&cwd);
+            save_attached(&attached);
+        });
+
+        daemon::AttachReply {
+            session_id: Some(session_id),
+            resumed: resuming,
+            error: None,
+        }
+    };
+
+    // Pick up where a previous run left off, e.g. after a reboot
+    for request in daemon::load_attached() {
+        let cwd = request.working_directory.clone();
+        if let Some(error) = attach(request).error {
+            warn!("Couldn't reattach {}: {}", cwd, error);
+        }
+    }
+    daemon::listen(&attach).await
+}
+
+/// Save the daemon's attached directories, logging rather than failing
+fn save_attached(attached: &Mutex<BTreeMap<String, String>>) {
+    if let Err(e) = daemon::save_attached(&attached.lock().unwrap()) {
+        warn!("{}", e);
+    }
+}
+
+/// Ask the running daemon to take on this directory's session
+async fn run_attach(name: String, new_session: bool) -> Result<()> {
+    let cwd = std::env::current_dir()
+        .context("Failed to get current directory")?
+        .to_string_lossy()
+        .to_string();
+    let reply = daemon::attach(&daemon::AttachRequest {
+        working_directory: cwd,
+        session_name: name.clone(),
+        new_session,
+    })
+    .await?;
+    match (reply.error, reply.session_id) {
+        (Some(error), _) => anyhow::bail!(error),
+        (None, Some(session_id)) => {
+            ui::print_attached(&name, &session_id.to_string(), reply.resumed);
+            Ok(())
+        }
+        (None, None) => anyhow::bail!("The daemon didn't start a session"),
+    }
+}
+
 /// Start Claude and run the proxy session
 async fn run_proxy_session(mut config: ProxySessionConfig) -> Result<()> {
     loop {
         ui::print_status("Starting Claude CLI...");
 
-        let mut claude_session = create_claude_session(&config).await?;
+        status::session_started(
+            config.session_id,
+            &config.session_name,
+            &config.working_directory,
+        );
+        let mut claude_session = match create_claude_session(&config).await {
+            Ok(claude_session) => claude_session,
+            Err(e) => {
+                status::session_ended(config.session_id);
+                return Err(e);
+            }
+        };
 
         ui::print_started();
 
         // Create input channel (shared across reconnections)
-        let (input_tx, mut input_rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+        let (input_tx, mut input_rx) =
+            tokio::sync::mpsc::unbounded_channel::<session::ClaudeCommand>();
+
+        // A fork's replayed conversation is Claude's first input
+        if let Some(prompt) = config.initial_prompt.take() {
+            let _ = input_tx.send(session::ClaudeCommand::Input(prompt));
+        }
 
         // Run the connection loop
         let result =
@@ -454,6 +1132,7 @@ async fn run_proxy_session(mut config: ProxySessionConfig) -> Result<()> {
                 .await;
 
         let _ = claude_session.stop().await;
+        status::session_ended(config.session_id);
 
         match result {
             Ok(session::LoopResult::NormalExit) => {
@@ -505,8 +1184,10 @@ async fn create_claude_session(config: &ProxySessionConfig) -> Result<ClaudeSess
         working_directory: PathBuf::from(&config.working_directory),
         session_name: config.session_name.clone(),
         resume: config.resume,
-        claude_path: None,
+        claude_path: config.claude_path.clone(),
+        claude_version: config.claude_version.clone(),
         extra_args: config.claude_args.clone(),
+        buffer_size: Some(config.buffer.max_messages),
     };
 
     if config.resume {
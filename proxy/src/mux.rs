@@ -0,0 +1,249 @@
+//! One backend connection shared by a daemon's sessions.
+//!
+//! [`Mux::run`] keeps a WebSocket open to `/ws/daemon`, reconnecting with
+//! backoff. Each session gets a connection from [`Mux::open`] that behaves
+//! like a socket of its own: frames for the session arrive on its read half,
+//! and what it writes goes out framed with its session ID. When the shared
+//! socket drops, every session's connection ends and each reconnects through
+//! its usual loop.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures_util::{sink, stream, SinkExt, StreamExt};
+use shared::DaemonFrame;
+use tokio::sync::mpsc;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::session::{Backoff, WebSocketConnection};
+use crate::settings::ReconnectSettings;
+
+/// Handle to the shared connection; clones share it
+#[derive(Clone, Default)]
+pub struct Mux {
+    link: Arc<Mutex<Link>>,
+}
+
+#[derive(Default)]
+struct Link {
+    /// Frames to the backend, while connected
+    out: Option<mpsc::UnboundedSender<DaemonFrame>>,
+    routes: HashMap<Uuid, Route>,
+    next_generation: u64,
+}
+
+/// Where frames for a session go
+struct Route {
+    /// Tells this connection apart from earlier ones for the same session
+    generation: u64,
+    inbound: mpsc::UnboundedSender<Message>,
+}
+
+/// Owned by a session's write half; closing the session's connection when
+/// it's dropped
+struct Closer {
+    link: Arc<Mutex<Link>>,
+    out: mpsc::UnboundedSender<DaemonFrame>,
+    session_id: Uuid,
+    generation: u64,
+}
+
+impl Drop for Closer {
+    fn drop(&mut self) {
+        let mut link = self.link.lock().unwrap();
+        let current = link
+            .routes
+            .get(&self.session_id)
+            .is_some_and(|route| route.generation == self.generation);
+        if current {
+            link.routes.remove(&self.session_id);
+            let _ = self.out.send(DaemonFrame::Closed {
+                session_id: self.session_id,
+            });
+        }
+    }
+}
+
+impl Mux {
+    /// A connection for `session_id` over the shared socket, replacing any
+    /// earlier one. Fails while the socket is down, as connecting would.
+    pub fn open(&self, session_id: Uuid) -> Result<WebSocketConnection, Duration> {
+        let mut link = self.link.lock().unwrap();
+        let Some(out) = link.out.clone() else {
+            return Err(Duration::ZERO);
+        };
+        link.next_generation += 1;
+        let generation = link.next_generation;
+        let (inbound, inbound_rx) = mpsc::unbounded_channel();
+        link.routes.insert(
+            session_id,
+            Route {
+                generation,
+                inbound,
+            },
+        );
+
+        let read = stream::unfold(inbound_rx, |mut rx| async move {
+            rx.recv().await.map(|msg| (Ok(msg), rx))
+        });
+        let closer = Closer {
+            link: self.link.clone(),
+            out,
+            session_id,
+            generation,
+        };
+        let write = sink::unfold(closer, move |closer, msg: Message| async move {
+            if let Message::Text(text) = msg {
+                if let Some(frame) = DaemonFrame::from_text(session_id, &text) {
+                    closer
+                        .out
+                        .send(frame)
+                        .map_err(|_| tungstenite::Error::ConnectionClosed)?;
+                }
+            }
+            Ok(closer)
+        });
+        Ok(WebSocketConnection::from_parts(
+            Box::pin(write),
+            Box::pin(read),
+        ))
+    }
+
+    /// Keep the shared socket connected; runs until the process exits
+    pub async fn run(&self, backend_url: String, reconnect: ReconnectSettings) {
+        let ws_url = format!("{}/ws/daemon", backend_url);
+        let mut backoff = Backoff::from_settings(&reconnect);
+        loop {
+            let started = Instant::now();
+            match connect_async(&ws_url).await {
+                Ok((stream, _)) => {
+                    info!("Daemon connected to {}", backend_url);
+                    let (mut write, mut read) = stream.split();
+                    let (out_tx, mut out_rx) = mpsc::unbounded_channel::<DaemonFrame>();
+                    self.link.lock().unwrap().out = Some(out_tx);
+
+                    let writer = async {
+                        while let Some(frame) = out_rx.recv().await {
+                            let Ok(json) = serde_json::to_string(&frame) else {
+                                continue;
+                            };
+                            if write.send(Message::Text(json)).await.is_err() {
+                                break;
+                            }
+                        }
+                    };
+                    let reader = async {
+                        while let Some(Ok(msg)) = read.next().await {
+                            match msg {
+                                Message::Text(text) => self.route(&text),
+                                Message::Close(_) => break,
+                                _ => {}
+                            }
+                        }
+                    };
+                    tokio::select! {
+                        _ = writer => {}
+                        _ = reader => {}
+                    }
+
+                    // Ends every session's read half, so each reconnects
+                    let mut link = self.link.lock().unwrap();
+                    link.out = None;
+                    link.routes.clear();
+                    warn!("Daemon connection lost");
+                }
+                Err(e) => error!("Daemon failed to connect to backend: {}", e),
+            }
+            backoff.reset_if_stable(started.elapsed());
+            tokio::time::sleep(backoff.sleep_duration()).await;
+            backoff.advance();
+        }
+    }
+
+    /// Deliver a frame from the backend to its session
+    fn route(&self, text: &str) {
+        let Ok(frame) = serde_json::from_str::<DaemonFrame>(text) else {
+            warn!("Ignoring malformed daemon frame");
+            return;
+        };
+        let mut link = self.link.lock().unwrap();
+        match frame {
+            DaemonFrame::Message {
+                session_id,
+                message,
+            } => {
+                if let Some(route) = link.routes.get(&session_id) {
+                    let _ = route.inbound.send(Message::Text(message.to_string()));
+                }
+            }
+            // The backend ended it; the session sees its socket close
+            DaemonFrame::Closed { session_id } => {
+                link.routes.remove(&session_id);
+            }
+        }
+    }
+
+    /// Connect without a socket, returning the frames sessions write
+    #[cfg(test)]
+    fn connect_for_test(&self) -> mpsc::UnboundedReceiver<DaemonFrame> {
+        let (out_tx, out_rx) = mpsc::unbounded_channel();
+        self.link.lock().unwrap().out = Some(out_tx);
+        out_rx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::ProxyMessage;
+
+    #[tokio::test]
+    async fn test_sessions_share_one_connection() {
+        let mux = Mux::default();
+        let a = Uuid::new_v4();
+        assert!(mux.open(a).is_err(), "no connection yet");
+
+        let mut out = mux.connect_for_test();
+        let mut conn = mux.open(a).unwrap();
+
+        conn.send(&ProxyMessage::Heartbeat).await.unwrap();
+        let frame = out.recv().await.unwrap();
+        assert_eq!(frame.session_id(), a);
+
+        // Frames for other sessions aren't delivered to this one
+        let heartbeat = serde_json::to_string(&ProxyMessage::Heartbeat).unwrap();
+        let other = DaemonFrame::from_text(Uuid::new_v4(), &heartbeat).unwrap();
+        mux.route(&serde_json::to_string(&other).unwrap());
+        let mine = DaemonFrame::from_text(a, &heartbeat).unwrap();
+        mux.route(&serde_json::to_string(&mine).unwrap());
+        match conn.recv().await {
+            Some(Ok(Message::Text(text))) => assert_eq!(text, heartbeat),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // Dropping the connection tells the backend
+        drop(conn);
+        assert_eq!(
+            out.recv().await,
+            Some(DaemonFrame::Closed { session_id: a })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backend_close_ends_session_stream() {
+        let mux = Mux::default();
+        let mut out = mux.connect_for_test();
+        let a = Uuid::new_v4();
+        let mut conn = mux.open(a).unwrap();
+
+        mux.route(&serde_json::to_string(&DaemonFrame::Closed { session_id: a }).unwrap());
+        assert!(conn.recv().await.is_none());
+        // Already closed on the backend, so no Closed frame goes back
+        drop(conn);
+        assert!(out.try_recv().is_err());
+    }
+}
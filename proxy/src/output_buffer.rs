@@ -3,17 +3,44 @@
 //! This module provides a persistent buffer for Claude outputs that ensures
 //! no messages are lost during WebSocket disconnects. Messages are held until
 //! the backend acknowledges receipt.
+//!
+//! Up to `max_messages` are kept in memory. Past that, while spilling is on,
+//! the oldest are appended to a spool file next to the buffer file instead
+//! of being dropped, so a long outage loses nothing; on reconnect the spool
+//! is replayed first, then the in-memory messages, in sequence order.
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
-use std::fs;
-use std::path::PathBuf;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
 use tracing::{debug, warn};
 use uuid::Uuid;
 
-/// Maximum number of pending messages to keep in memory before spilling to disk
-const MAX_MEMORY_MESSAGES: usize = 1000;
+/// Default maximum number of pending messages to keep
+pub const DEFAULT_MAX_MESSAGES: usize = 1000;
+
+/// How much unacknowledged output a session keeps, and whether it survives
+/// a proxy restart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferOptions {
+    /// Pending messages kept in memory; past this the oldest are spooled to
+    /// disk, or dropped when not persisting
+    pub max_messages: usize,
+    /// Spill pending messages to disk so a restarted proxy replays them and
+    /// a long outage doesn't drop any
+    pub persist: bool,
+}
+
+impl Default for BufferOptions {
+    fn default() -> Self {
+        Self {
+            max_messages: DEFAULT_MAX_MESSAGES,
+            persist: true,
+        }
+    }
+}
 
 /// A sequenced output message waiting for acknowledgment
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +49,14 @@ pub struct PendingOutput {
     pub seq: u64,
     /// The actual content
     pub content: serde_json::Value,
+    /// Trace context assigned when the output was read, reused on replay so a
+    /// delayed delivery shows up in the original trace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traceparent: Option<String>,
+    /// When the output was read, on this machine's clock in milliseconds
+    /// since the Unix epoch. The backend corrects it for clock skew.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub produced_at: Option<u64>,
 }
 
 /// Buffer state that can be persisted to disk
@@ -37,6 +72,15 @@ struct BufferState {
     pending: VecDeque<PendingOutput>,
 }
 
+/// Sequence numbers of the unacknowledged messages in the spool file. They
+/// are contiguous, since the oldest in-memory message is always the one
+/// spilled next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SpoolRange {
+    first: u64,
+    last: u64,
+}
+
 /// Pending output buffer with persistence and acknowledgment tracking
 pub struct PendingOutputBuffer {
     /// Session ID (kept for logging/debugging)
@@ -48,15 +92,18 @@ pub struct PendingOutputBuffer {
     state: BufferState,
     /// Whether we have unsaved changes
     dirty: bool,
+    options: BufferOptions,
+    /// Messages spilled to the spool file, all older than `state.pending`
+    spool: Option<SpoolRange>,
 }
 
 impl PendingOutputBuffer {
     /// Create or load a buffer for the given session
-    pub fn new(session_id: Uuid) -> Result<Self> {
+    pub fn new(session_id: Uuid, options: BufferOptions) -> Result<Self> {
         let persist_path = Self::buffer_path(session_id)?;
 
         // Try to load existing state
-        let state = if persist_path.exists() {
+        let state = if options.persist && persist_path.exists() {
             match fs::read_to_string(&persist_path) {
                 Ok(contents) => match serde_json::from_str::<BufferState>(&contents) {
                     Ok(mut state) => {
@@ -105,12 +152,122 @@ impl PendingOutputBuffer {
             }
         };
 
-        Ok(Self {
+        let mut buffer = Self {
             session_id,
             persist_path,
             state,
             dirty: false,
-        })
+            options,
+            spool: None,
+        };
+        if options.persist {
+            buffer.load_spool();
+        }
+        // A smaller limit than the one the file was written with
+        buffer.trim();
+        Ok(buffer)
+    }
+
+    /// Path of the append-only file holding spilled messages
+    fn spool_path(&self) -> PathBuf {
+        self.persist_path.with_extension("spool")
+    }
+
+    /// Pick up messages spilled before a restart. The buffer file may have
+    /// been saved before the last spill, so in-memory messages that reached
+    /// the spool are dropped from memory.
+    fn load_spool(&mut self) {
+        let path = self.spool_path();
+        let Some(range) = scan_spool(&path) else {
+            return;
+        };
+        let first = range.first.max(self.state.last_ack_seq + 1);
+        if first > range.last {
+            self.remove_spool();
+            return;
+        }
+        self.state.pending.retain(|msg| msg.seq > range.last);
+        self.spool = Some(SpoolRange {
+            first,
+            last: range.last,
+        });
+        debug!(
+            "Loaded spool: seq {}..={} from {}",
+            first,
+            range.last,
+            path.display()
+        );
+    }
+
+    /// Append messages to the spool file. A failed write is cut back off so
+    /// later appends start on a fresh line.
+    fn spill(&mut self, outputs: &[PendingOutput]) -> Result<()> {
+        let (Some(oldest), Some(newest)) = (outputs.first(), outputs.last()) else {
+            return Ok(());
+        };
+        let mut lines = String::new();
+        for output in outputs {
+            lines.push_str(&serde_json::to_string(output).context("Failed to serialize output")?);
+            lines.push('\n');
+        }
+
+        let path = self.spool_path();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .context("Failed to open spool file")?;
+        let len = file.metadata().context("Failed to stat spool file")?.len();
+        if let Err(e) = file.write_all(lines.as_bytes()) {
+            let _ = file.set_len(len);
+            return Err(e).context("Failed to append to spool file");
+        }
+
+        self.spool = Some(SpoolRange {
+            first: self.spool.map_or(oldest.seq, |spool| spool.first),
+            last: newest.seq,
+        });
+        debug!(
+            "Spooled {} messages to disk, through seq={}",
+            outputs.len(),
+            newest.seq
+        );
+        Ok(())
+    }
+
+    fn remove_spool(&mut self) {
+        self.spool = None;
+        let path = self.spool_path();
+        if let Err(e) = fs::remove_file(&path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!("Failed to remove spool file {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// The spooled messages still unacknowledged from `from_seq` on, read
+    /// from disk as they are iterated. The file isn't opened when all of
+    /// them are older.
+    fn spooled_from(&self, from_seq: u64) -> impl Iterator<Item = PendingOutput> {
+        let range = self
+            .spool
+            .filter(|r| from_seq <= r.last)
+            .map(|r| SpoolRange {
+                first: r.first.max(from_seq),
+                last: r.last,
+            });
+        let file = range.and_then(|_| match File::open(self.spool_path()) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                warn!("Failed to open spool file, its messages are lost: {}", e);
+                None
+            }
+        });
+        file.into_iter()
+            .flat_map(|file| BufReader::new(file).lines())
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str::<PendingOutput>(&line).ok())
+            .filter(move |msg| range.is_some_and(|r| (r.first..=r.last).contains(&msg.seq)))
     }
 
     /// Get the path for a session's buffer file
@@ -128,26 +285,24 @@ impl PendingOutputBuffer {
     }
 
     /// Add a new output to the buffer, returning the assigned sequence number
-    pub fn push(&mut self, content: serde_json::Value) -> u64 {
+    pub fn push(
+        &mut self,
+        content: serde_json::Value,
+        traceparent: Option<String>,
+        produced_at: u64,
+    ) -> u64 {
         let seq = self.state.next_seq;
         self.state.next_seq += 1;
 
-        self.state.pending.push_back(PendingOutput { seq, content });
+        self.state.pending.push_back(PendingOutput {
+            seq,
+            content,
+            traceparent,
+            produced_at: Some(produced_at),
+        });
 
         self.dirty = true;
-
-        // Trim if too many messages in memory (keep the most recent ones)
-        if self.state.pending.len() > MAX_MEMORY_MESSAGES {
-            // Keep the last MAX_MEMORY_MESSAGES
-            while self.state.pending.len() > MAX_MEMORY_MESSAGES {
-                if let Some(removed) = self.state.pending.pop_front() {
-                    warn!(
-                        "Buffer overflow, dropping oldest message seq={}",
-                        removed.seq
-                    );
-                }
-            }
-        }
+        self.trim();
 
         debug!(
             "Buffered output seq={}, pending={}",
@@ -157,6 +312,34 @@ impl PendingOutputBuffer {
         seq
     }
 
+    /// Move the oldest messages past `max_messages` to the spool, or drop
+    /// them if they can't be spilled
+    fn trim(&mut self) {
+        let excess = self
+            .state
+            .pending
+            .len()
+            .saturating_sub(self.options.max_messages);
+        if excess == 0 {
+            return;
+        }
+        let oldest: Vec<PendingOutput> = self.state.pending.drain(..excess).collect();
+        self.dirty = true;
+
+        if self.options.persist {
+            match self.spill(&oldest) {
+                Ok(()) => return,
+                Err(e) => warn!("Failed to spool output to disk: {:#}", e),
+            }
+        }
+        for removed in oldest {
+            warn!(
+                "Buffer overflow, dropping oldest message seq={}",
+                removed.seq
+            );
+        }
+    }
+
     /// Acknowledge receipt of all messages up to and including the given sequence
     pub fn acknowledge(&mut self, ack_seq: u64) {
         if ack_seq <= self.state.last_ack_seq {
@@ -174,6 +357,14 @@ impl PendingOutputBuffer {
         self.state.last_ack_seq = ack_seq;
         self.dirty = true;
 
+        if let Some(spool) = self.spool.as_mut() {
+            if ack_seq >= spool.last {
+                self.remove_spool();
+            } else {
+                spool.first = spool.first.max(ack_seq + 1);
+            }
+        }
+
         debug!(
             "Acknowledged up to seq={}, removed {} messages, {} remaining",
             ack_seq,
@@ -182,18 +373,52 @@ impl PendingOutputBuffer {
         );
     }
 
-    /// Get all pending (unacknowledged) messages for replay
+    /// Get the pending (unacknowledged) messages held in memory
     pub fn get_pending(&self) -> impl Iterator<Item = &PendingOutput> {
         self.state.pending.iter()
     }
 
-    /// Get the number of pending messages
+    /// Pending messages from `seq` on, in sequence order: the spool, read
+    /// from disk as it goes, then those in memory. Messages before
+    /// [`Self::dropped_before`] are gone, so a client that has seen less
+    /// than that needs more than this.
+    pub fn since(&self, seq: u64) -> impl Iterator<Item = PendingOutput> + '_ {
+        self.spooled_from(seq).chain(
+            self.state
+                .pending
+                .iter()
+                .filter(move |msg| msg.seq >= seq)
+                .cloned(),
+        )
+    }
+
+    /// Pending messages with sequence numbers from `from_seq` through
+    /// `to_seq`, in order, spooled ones included
+    #[allow(dead_code)]
+    pub fn range(&self, from_seq: u64, to_seq: u64) -> impl Iterator<Item = PendingOutput> + '_ {
+        self.since(from_seq)
+            .take_while(move |msg| msg.seq <= to_seq)
+    }
+
+    /// The oldest sequence number still held. Everything before it was
+    /// acknowledged, or dropped when the buffer overflowed without a spool.
+    /// Equal to `next_seq` when nothing is pending.
+    pub fn dropped_before(&self) -> u64 {
+        self.spool
+            .map(|spool| spool.first)
+            .or_else(|| self.state.pending.front().map(|msg| msg.seq))
+            .unwrap_or(self.state.next_seq)
+    }
+
+    /// Get the number of pending messages, spooled ones included
     pub fn pending_count(&self) -> usize {
-        self.state.pending.len()
+        let spooled = self
+            .spool
+            .map_or(0, |spool| (spool.last - spool.first + 1) as usize);
+        spooled + self.state.pending.len()
     }
 
     /// Get the last acknowledged sequence number
-    #[allow(dead_code)]
     pub fn last_ack_seq(&self) -> u64 {
         self.state.last_ack_seq
     }
@@ -206,7 +431,7 @@ impl PendingOutputBuffer {
 
     /// Persist the buffer state to disk
     pub fn persist(&mut self) -> Result<()> {
-        if !self.dirty {
+        if !self.dirty || !self.options.persist {
             return Ok(());
         }
 
@@ -235,6 +460,7 @@ impl PendingOutputBuffer {
         self.state.pending.clear();
         self.state.last_ack_seq = self.state.next_seq.saturating_sub(1);
         self.dirty = false;
+        self.remove_spool();
 
         if self.persist_path.exists() {
             fs::remove_file(&self.persist_path).context("Failed to remove buffer file")?;
@@ -245,6 +471,25 @@ impl PendingOutputBuffer {
     }
 }
 
+/// The range of sequence numbers in a spool file, skipping lines that don't
+/// parse (such as one cut short by a crash)
+fn scan_spool(path: &Path) -> Option<SpoolRange> {
+    let file = File::open(path).ok()?;
+    let mut range: Option<SpoolRange> = None;
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+        match serde_json::from_str::<PendingOutput>(&line) {
+            Ok(msg) => {
+                range = Some(SpoolRange {
+                    first: range.map_or(msg.seq, |r| r.first),
+                    last: msg.seq,
+                })
+            }
+            Err(e) => warn!("Skipping unreadable spool line: {}", e),
+        }
+    }
+    range
+}
+
 impl Drop for PendingOutputBuffer {
     fn drop(&mut self) {
         // Best-effort persist on drop
@@ -271,12 +516,14 @@ mod tests {
                 ..Default::default()
             },
             dirty: false,
+            options: BufferOptions::default(),
+            spool: None,
         };
 
         // Push some messages
-        let seq1 = buffer.push(serde_json::json!({"type": "test", "n": 1}));
-        let seq2 = buffer.push(serde_json::json!({"type": "test", "n": 2}));
-        let seq3 = buffer.push(serde_json::json!({"type": "test", "n": 3}));
+        let seq1 = buffer.push(serde_json::json!({"type": "test", "n": 1}), None, 0);
+        let seq2 = buffer.push(serde_json::json!({"type": "test", "n": 2}), None, 0);
+        let seq3 = buffer.push(serde_json::json!({"type": "test", "n": 3}), None, 0);
 
         assert_eq!(seq1, 0);
         assert_eq!(seq2, 1);
@@ -305,12 +552,14 @@ mod tests {
                 ..Default::default()
             },
             dirty: false,
+            options: BufferOptions::default(),
+            spool: None,
         };
 
         // Push 3 messages: seq 0, 1, 2
-        buffer.push(serde_json::json!({"n": 1}));
-        buffer.push(serde_json::json!({"n": 2}));
-        buffer.push(serde_json::json!({"n": 3}));
+        buffer.push(serde_json::json!({"n": 1}), None, 0);
+        buffer.push(serde_json::json!({"n": 2}), None, 0);
+        buffer.push(serde_json::json!({"n": 3}), None, 0);
         assert_eq!(buffer.pending_count(), 3);
 
         // Acknowledge up to seq 1 (removes seq 0 and 1, keeps seq 2)
@@ -341,18 +590,194 @@ mod tests {
                 ..Default::default()
             },
             dirty: false,
+            options: BufferOptions {
+                persist: false,
+                ..Default::default()
+            },
+            spool: None,
         };
 
-        // Push more than MAX_MEMORY_MESSAGES
-        for i in 0..MAX_MEMORY_MESSAGES + 100 {
-            buffer.push(serde_json::json!({"n": i}));
+        // Push more than DEFAULT_MAX_MESSAGES
+        for i in 0..DEFAULT_MAX_MESSAGES + 100 {
+            buffer.push(serde_json::json!({"n": i}), None, 0);
         }
 
-        // Should be capped at MAX_MEMORY_MESSAGES
-        assert_eq!(buffer.pending_count(), MAX_MEMORY_MESSAGES);
+        // Should be capped at DEFAULT_MAX_MESSAGES
+        assert_eq!(buffer.pending_count(), DEFAULT_MAX_MESSAGES);
 
         // The oldest messages should have been dropped
         let first = buffer.get_pending().next().unwrap();
         assert_eq!(first.seq, 100); // First 100 were dropped
     }
+
+    #[test]
+    fn test_custom_options() {
+        let session_id = Uuid::new_v4();
+        let persist_path = std::env::temp_dir().join(format!("{}.json", session_id));
+        let mut buffer = PendingOutputBuffer {
+            session_id,
+            persist_path: persist_path.clone(),
+            state: BufferState {
+                session_id,
+                ..Default::default()
+            },
+            dirty: false,
+            options: BufferOptions {
+                max_messages: 2,
+                persist: false,
+            },
+            spool: None,
+        };
+
+        for i in 0..5 {
+            buffer.push(serde_json::json!({"n": i}), None, 0);
+        }
+        let seqs: Vec<u64> = buffer.get_pending().map(|m| m.seq).collect();
+        assert_eq!(seqs, vec![3, 4]);
+
+        // Nothing is written to disk
+        buffer.persist().unwrap();
+        drop(buffer);
+        assert!(!persist_path.exists());
+    }
+
+    #[test]
+    fn test_overflow_spools_to_disk() {
+        let session_id = Uuid::new_v4();
+        let persist_path = std::env::temp_dir().join(format!("{}.json", session_id));
+        let options = BufferOptions {
+            max_messages: 3,
+            persist: true,
+        };
+        let mut buffer = PendingOutputBuffer {
+            session_id,
+            persist_path: persist_path.clone(),
+            state: BufferState {
+                session_id,
+                ..Default::default()
+            },
+            dirty: false,
+            options,
+            spool: None,
+        };
+
+        for i in 0..10 {
+            buffer.push(serde_json::json!({"n": i}), None, 0);
+        }
+        assert_eq!(buffer.pending_count(), 10);
+        assert_eq!(buffer.get_pending().count(), 3);
+        let seqs: Vec<u64> = buffer.since(0).map(|m| m.seq).collect();
+        assert_eq!(seqs, (0..10).collect::<Vec<_>>());
+
+        // A partial ack skips the acknowledged part of the spool
+        buffer.acknowledge(4);
+        let seqs: Vec<u64> = buffer.since(0).map(|m| m.seq).collect();
+        assert_eq!(seqs, (5..10).collect::<Vec<_>>());
+
+        // The proxy dies after another spill, before saving the buffer file
+        buffer.persist().unwrap();
+        buffer.push(serde_json::json!({"n": 10}), None, 0);
+        buffer.dirty = false;
+        drop(buffer);
+
+        // On restart each saved message is replayed once, in order
+        let state = serde_json::from_str(&fs::read_to_string(&persist_path).unwrap()).unwrap();
+        let mut buffer = PendingOutputBuffer {
+            session_id,
+            persist_path: persist_path.clone(),
+            state,
+            dirty: false,
+            options,
+            spool: None,
+        };
+        buffer.load_spool();
+        let seqs: Vec<u64> = buffer.since(0).map(|m| m.seq).collect();
+        assert_eq!(seqs, vec![5, 6, 7, 8, 9]);
+
+        // Acknowledging past the spool removes it
+        let spool_path = buffer.spool_path();
+        assert!(spool_path.exists());
+        buffer.acknowledge(8);
+        assert!(!spool_path.exists());
+        assert_eq!(buffer.pending_count(), 1);
+
+        buffer.clear().unwrap();
+        let _ = fs::remove_file(&persist_path);
+    }
+
+    #[test]
+    fn test_since_and_range_span_the_spool() {
+        let session_id = Uuid::new_v4();
+        let persist_path = std::env::temp_dir().join(format!("{}.json", session_id));
+        let mut buffer = PendingOutputBuffer {
+            session_id,
+            persist_path: persist_path.clone(),
+            state: BufferState {
+                session_id,
+                ..Default::default()
+            },
+            dirty: false,
+            options: BufferOptions {
+                max_messages: 3,
+                persist: true,
+            },
+            spool: None,
+        };
+        assert_eq!(buffer.dropped_before(), 0);
+
+        // 0..=6 spooled, 7..=9 in memory
+        for i in 0..10 {
+            buffer.push(serde_json::json!({"n": i}), None, 0);
+        }
+        let seqs = |outputs: Vec<PendingOutput>| outputs.iter().map(|m| m.seq).collect::<Vec<_>>();
+        assert_eq!(seqs(buffer.since(5).collect()), vec![5, 6, 7, 8, 9]);
+        assert_eq!(seqs(buffer.since(8).collect()), vec![8, 9]);
+        assert!(buffer.since(20).next().is_none());
+        assert_eq!(seqs(buffer.range(2, 4).collect()), vec![2, 3, 4]);
+        assert_eq!(seqs(buffer.range(6, 7).collect()), vec![6, 7]);
+        assert!(buffer.range(20, 30).next().is_none());
+        assert_eq!(buffer.dropped_before(), 0);
+
+        // Acknowledged messages are gone, even if asked for
+        buffer.acknowledge(3);
+        assert_eq!(buffer.dropped_before(), 4);
+        assert_eq!(seqs(buffer.since(0).collect()), (4..10).collect::<Vec<_>>());
+        assert_eq!(seqs(buffer.since(2).collect()), (4..10).collect::<Vec<_>>());
+        assert_eq!(seqs(buffer.range(2, 5).collect()), vec![4, 5]);
+
+        buffer.acknowledge(9);
+        assert_eq!(buffer.dropped_before(), 10);
+        assert!(buffer.since(0).next().is_none());
+
+        buffer.clear().unwrap();
+        let _ = fs::remove_file(&persist_path);
+    }
+
+    #[test]
+    fn test_dropped_before_after_overflow() {
+        let session_id = Uuid::new_v4();
+        let mut buffer = PendingOutputBuffer {
+            session_id,
+            persist_path: PathBuf::from("/tmp/test_buffer4.json"),
+            state: BufferState {
+                session_id,
+                ..Default::default()
+            },
+            dirty: false,
+            options: BufferOptions {
+                max_messages: 2,
+                persist: false,
+            },
+            spool: None,
+        };
+
+        for i in 0..5 {
+            buffer.push(serde_json::json!({"n": i}), None, 0);
+        }
+        // Nothing was acknowledged, but 0..=2 were dropped
+        assert_eq!(buffer.last_ack_seq(), 0);
+        assert_eq!(buffer.dropped_before(), 3);
+        let seqs: Vec<u64> = buffer.since(1).map(|m| m.seq).collect();
+        assert_eq!(seqs, vec![3, 4]);
+    }
 }
@@ -0,0 +1,140 @@
+//! Attachments sent with a prompt.
+//!
+//! Large pastes arrive as an [`AttachedInput`]. Each attachment is written
+//! under [`PASTE_DIR`] in the working directory, never over an existing file,
+//! and Claude gets the typed text with a reference to each saved file. If a
+//! file can't be written its content goes inline instead, so nothing the
+//! user sent is lost.
+
+use anyhow::Result;
+use shared::paste::{safe_file_name, PASTE_DIR};
+use shared::AttachedInput;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use tracing::{info, warn};
+
+/// Keeps the pastes out of `git status`
+const GITIGNORE: &str = "*\n";
+
+/// Write `content` to a new file named after `name` in `dir`, adding `-1`,
+/// `-2`, ... before the extension if the name is taken. Returns the name used.
+fn write_new(dir: &Path, name: &str, content: &str) -> Result<String> {
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+        _ => (name, String::new()),
+    };
+    for n in 0.. {
+        let candidate = if n == 0 {
+            name.to_string()
+        } else {
+            format!("{}-{}{}", stem, n, ext)
+        };
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(dir.join(&candidate))
+        {
+            Ok(mut file) => {
+                file.write_all(content.as_bytes())?;
+                return Ok(candidate);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    unreachable!()
+}
+
+/// Save `input`'s attachments, returning the path of each relative to
+/// `workdir`
+fn save(workdir: &Path, input: &AttachedInput) -> Result<Vec<String>> {
+    let dir = workdir.join(PASTE_DIR);
+    fs::create_dir_all(&dir)?;
+    let gitignore = workdir.join(".claude-portal").join(".gitignore");
+    if !gitignore.exists() {
+        fs::write(&gitignore, GITIGNORE)?;
+    }
+
+    input
+        .attachments
+        .iter()
+        .map(|attachment| {
+            let name = write_new(&dir, &safe_file_name(&attachment.name), &attachment.content)?;
+            Ok(format!("{}/{}", PASTE_DIR, name))
+        })
+        .collect()
+}
+
+/// The prompt to send Claude for `input`
+pub fn prompt(workdir: &Path, input: &AttachedInput) -> String {
+    match save(workdir, input) {
+        Ok(paths) => {
+            info!("Saved {} pasted attachment(s)", paths.len());
+            input.prompt(&paths)
+        }
+        Err(e) => {
+            warn!("Couldn't save pasted attachments, sending inline: {}", e);
+            let mut text = input.text.clone();
+            for attachment in &input.attachments {
+                text.push_str("\n\n");
+                text.push_str(&attachment.content);
+            }
+            text
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::InputAttachment;
+
+    fn input(names: &[&str]) -> AttachedInput {
+        AttachedInput {
+            text: "look at this".to_string(),
+            attachments: names
+                .iter()
+                .map(|name| InputAttachment {
+                    name: name.to_string(),
+                    content: "one\ntwo\n".to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_attachments_are_saved_and_referenced() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = prompt(dir.path(), &input(&["paste.txt", "paste.txt", "../escape"]));
+
+        assert_eq!(
+            text,
+            "look at this\n\n\
+             [Pasted text (2 lines) saved to .claude-portal/pastes/paste.txt]\n\
+             [Pasted text (2 lines) saved to .claude-portal/pastes/paste-1.txt]\n\
+             [Pasted text (2 lines) saved to .claude-portal/pastes/escape]"
+        );
+        let pastes = dir.path().join(PASTE_DIR);
+        assert_eq!(
+            fs::read_to_string(pastes.join("paste-1.txt")).unwrap(),
+            "one\ntwo\n"
+        );
+        assert!(pastes.join("escape").exists());
+        assert_eq!(
+            fs::read_to_string(dir.path().join(".claude-portal/.gitignore")).unwrap(),
+            GITIGNORE
+        );
+    }
+
+    #[test]
+    fn test_unwritable_directory_sends_inline() {
+        let dir = tempfile::tempdir().unwrap();
+        // A file where the directory should be
+        fs::write(dir.path().join(".claude-portal"), "").unwrap();
+        assert_eq!(
+            prompt(dir.path(), &input(&["a.txt"])),
+            "look at this\n\none\ntwo\n"
+        );
+    }
+}
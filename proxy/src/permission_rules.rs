@@ -0,0 +1,184 @@
+//! Permission rules remembered from the web UI.
+//!
+//! Session rules live only as long as the proxy process. Persistent rules are
+//! stored per working directory in `permission-rules.json` next to the config
+//! file, so they apply again the next time a session starts in that directory.
+
+use anyhow::{Context, Result};
+use shared::permission_rules::evaluate_rules;
+use shared::{PermissionRule, RememberedRule, RuleScope};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Persistent rules keyed by working directory
+type RulesFile = HashMap<String, Vec<PermissionRule>>;
+
+pub struct PermissionRules {
+    /// Where persistent rules are saved; `None` keeps them in memory only
+    path: Option<PathBuf>,
+    working_directory: String,
+    session: Vec<PermissionRule>,
+    persistent: Vec<PermissionRule>,
+}
+
+impl PermissionRules {
+    fn rules_path() -> Result<PathBuf> {
+        let config_dir = directories::ProjectDirs::from("com", "anthropic", "claude-code-portal")
+            .context("Failed to determine config directory")?
+            .config_dir()
+            .to_path_buf();
+
+        Ok(config_dir.join("permission-rules.json"))
+    }
+
+    /// Load the persistent rules for `working_directory`. Problems with the
+    /// rules file are logged and the session starts with no rules.
+    pub fn load(working_directory: &str) -> Self {
+        match Self::rules_path() {
+            Ok(path) => Self::load_from(path, working_directory),
+            Err(e) => {
+                tracing::warn!("Permission rules will not be saved: {}", e);
+                Self {
+                    path: None,
+                    working_directory: working_directory.to_string(),
+                    session: Vec::new(),
+                    persistent: Vec::new(),
+                }
+            }
+        }
+    }
+
+    fn load_from(path: PathBuf, working_directory: &str) -> Self {
+        let persistent = match read_rules_file(&path) {
+            Ok(mut file) => file.remove(working_directory).unwrap_or_default(),
+            Err(e) => {
+                tracing::warn!("Ignoring permission rules file {}: {}", path.display(), e);
+                Vec::new()
+            }
+        };
+        Self {
+            path: Some(path),
+            working_directory: working_directory.to_string(),
+            session: Vec::new(),
+            persistent,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.session.len() + self.persistent.len()
+    }
+
+    /// The rule deciding this request, if any. Deny rules take precedence.
+    pub fn check(&self, tool_name: &str, input: &serde_json::Value) -> Option<&PermissionRule> {
+        evaluate_rules(
+            self.session.iter().chain(self.persistent.iter()),
+            tool_name,
+            input,
+        )
+    }
+
+    /// Add a rule, saving it to the rules file if it is persistent. The rule
+    /// stays in effect for this session even if saving fails.
+    pub fn add(&mut self, remembered: RememberedRule) -> Result<()> {
+        let rules = match remembered.scope {
+            RuleScope::Session => &mut self.session,
+            RuleScope::Persistent => &mut self.persistent,
+        };
+        // A newer decision for the same target replaces the older one
+        rules.retain(|r| {
+            r.tool_name != remembered.rule.tool_name || r.pattern != remembered.rule.pattern
+        });
+        rules.push(remembered.rule);
+
+        if remembered.scope == RuleScope::Persistent {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create config directory")?;
+        }
+
+        // Other directories' rules may have changed since we loaded
+        let mut file = read_rules_file(path).unwrap_or_default();
+        file.insert(self.working_directory.clone(), self.persistent.clone());
+
+        let temp_path = path.with_extension("tmp");
+        let contents =
+            serde_json::to_string_pretty(&file).context("Failed to serialize permission rules")?;
+        fs::write(&temp_path, contents).context("Failed to write temp permission rules file")?;
+        fs::rename(&temp_path, path).context("Failed to rename permission rules file")?;
+        Ok(())
+    }
+}
+
+fn read_rules_file(path: &PathBuf) -> Result<RulesFile> {
+    if !path.exists() {
+        return Ok(RulesFile::new());
+    }
+    let contents = fs::read_to_string(path).context("Failed to read permission rules file")?;
+    serde_json::from_str(&contents).context("Failed to parse permission rules file")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use shared::RuleDecision;
+
+    fn remembered(command: &str, decision: RuleDecision, scope: RuleScope) -> RememberedRule {
+        RememberedRule {
+            rule: PermissionRule::for_request("Bash", &json!({ "command": command }), decision),
+            scope,
+        }
+    }
+
+    #[test]
+    fn test_persistent_rules_survive_reload_per_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("permission-rules.json");
+
+        let mut rules = PermissionRules::load_from(path.clone(), "/work/a");
+        rules
+            .add(remembered("ls", RuleDecision::Allow, RuleScope::Session))
+            .unwrap();
+        rules
+            .add(remembered(
+                "make",
+                RuleDecision::Allow,
+                RuleScope::Persistent,
+            ))
+            .unwrap();
+        assert_eq!(rules.len(), 2);
+
+        let reloaded = PermissionRules::load_from(path.clone(), "/work/a");
+        assert_eq!(reloaded.len(), 1);
+        assert!(reloaded
+            .check("Bash", &json!({"command": "make"}))
+            .is_some());
+        assert!(reloaded.check("Bash", &json!({"command": "ls"})).is_none());
+
+        let other = PermissionRules::load_from(path, "/work/b");
+        assert_eq!(other.len(), 0);
+    }
+
+    #[test]
+    fn test_newer_decision_replaces_older() {
+        let mut rules = PermissionRules::load_from(PathBuf::from("/nonexistent/rules.json"), "/w");
+        rules
+            .add(remembered("make", RuleDecision::Allow, RuleScope::Session))
+            .unwrap();
+        rules
+            .add(remembered("make", RuleDecision::Deny, RuleScope::Session))
+            .unwrap();
+        assert_eq!(rules.len(), 1);
+        let rule = rules.check("Bash", &json!({"command": "make"})).unwrap();
+        assert_eq!(rule.decision, RuleDecision::Deny);
+    }
+}
@@ -0,0 +1,219 @@
+//! Named profiles, for connecting to more than one backend.
+//!
+//! `--profile NAME` swaps the per-directory login for the profile's own:
+//! its backend URL and token are saved under `profiles` in `config.json`,
+//! and a `[profiles.NAME]` section in the settings file can set its
+//! defaults. `claude-portal profiles` lists, adds, and removes them.
+
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use colored::Colorize;
+
+use crate::config::ProxyConfig;
+use crate::settings::FileSettings;
+
+#[derive(Subcommand, Debug)]
+pub enum ProfilesCommand {
+    /// Show every profile, its backend, and whether it's logged in
+    List,
+    /// Add a profile, or point an existing one at another backend
+    Add {
+        /// Letters, digits, `-` and `_`
+        name: String,
+        /// Backend the profile connects to
+        backend_url: String,
+    },
+    /// Forget a profile's backend and login
+    Remove { name: String },
+}
+
+/// Whether `name` can be used as a profile name
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+}
+
+/// Whether `name` is a profile in either `config.json` or the settings file
+pub fn exists(name: &str, config: &ProxyConfig, settings: &FileSettings) -> bool {
+    config.get_profile(name).is_some() || settings.profiles.contains_key(name)
+}
+
+/// A line of `profiles list`
+#[derive(Debug, PartialEq)]
+struct ProfileRow {
+    name: String,
+    backend_url: Option<String>,
+    /// Who it's logged in as; `Some("")` when the token has no email
+    user: Option<String>,
+    is_default: bool,
+}
+
+/// Every profile from both files, by name. The settings file's backend URL
+/// wins, as it does when connecting.
+fn rows(config: &ProxyConfig, settings: &FileSettings) -> Vec<ProfileRow> {
+    let mut names: Vec<&String> = config
+        .profiles
+        .keys()
+        .chain(settings.profiles.keys())
+        .collect();
+    names.sort();
+    names.dedup();
+
+    names
+        .into_iter()
+        .map(|name| {
+            let saved = config.get_profile(name);
+            ProfileRow {
+                name: name.clone(),
+                backend_url: settings
+                    .profiles
+                    .get(name)
+                    .and_then(|p| p.backend_url.clone())
+                    .or_else(|| saved.map(|p| p.backend_url.clone())),
+                user: saved
+                    .filter(|p| p.auth_token.is_some())
+                    .map(|p| p.user_email.clone().unwrap_or_default()),
+                is_default: settings.profile.as_deref() == Some(name.as_str()),
+            }
+        })
+        .collect()
+}
+
+pub fn run(command: ProfilesCommand, settings: &FileSettings) -> Result<()> {
+    match command {
+        ProfilesCommand::List => {
+            let config = ProxyConfig::load().context("Failed to load config file")?;
+            let rows = rows(&config, settings);
+            if rows.is_empty() {
+                println!("No profiles. Add one with: claude-portal profiles add <name> <url>");
+                return Ok(());
+            }
+            for row in rows {
+                let marker = if row.is_default { "*" } else { " " };
+                let login = match row.user.as_deref() {
+                    Some("") => "logged in".green().to_string(),
+                    Some(email) => email.green().to_string(),
+                    None => "not logged in".dimmed().to_string(),
+                };
+                println!(
+                    "{} {:<16} {:<40} {}",
+                    marker.bright_green(),
+                    row.name.bright_cyan(),
+                    row.backend_url.as_deref().unwrap_or("(no backend URL)"),
+                    login
+                );
+            }
+        }
+        ProfilesCommand::Add { name, backend_url } => {
+            if !valid_name(&name) {
+                anyhow::bail!(
+                    "Invalid profile name '{}': use letters, digits, '-' and '_'",
+                    name
+                );
+            }
+            let (mut config, lock) =
+                ProxyConfig::load_locked().context("Failed to load config with lock")?;
+            config.set_profile_backend(&name, &backend_url);
+            config.save_with_lock(&lock)?;
+            println!(
+                "{} Profile {} uses {}",
+                "✓".bright_green(),
+                name.bright_cyan(),
+                backend_url
+            );
+            println!(
+                "Log in by starting a session: {}",
+                format!("claude-portal --profile {}", name).bright_cyan()
+            );
+        }
+        ProfilesCommand::Remove { name } => {
+            let (mut config, lock) =
+                ProxyConfig::load_locked().context("Failed to load config with lock")?;
+            let removed = config.remove_profile(&name).is_some();
+            if removed {
+                config.save_with_lock(&lock)?;
+                println!("{} Removed profile {}", "✓".bright_green(), name);
+            }
+            if settings.profiles.contains_key(&name) {
+                println!(
+                    "Its [profiles.{}] section in {} is left for you to edit",
+                    name,
+                    FileSettings::path()?.display()
+                );
+            } else if !removed {
+                anyhow::bail!("No profile named '{}'", name);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_name() {
+        assert!(valid_name("work"));
+        assert!(valid_name("home-lab_2"));
+        assert!(!valid_name(""));
+        assert!(!valid_name("a b"));
+        assert!(!valid_name("../x"));
+    }
+
+    #[test]
+    fn test_rows_merge_both_files() {
+        let mut config = ProxyConfig::default();
+        config.set_profile_backend("work", "https://work.example.com");
+        config.set_profile_auth("work", "token".to_string(), Some("me@work".to_string()));
+        config.set_profile_backend("homelab", "https://old.home.lan");
+
+        let settings: FileSettings = toml::from_str(
+            r#"
+            profile = "homelab"
+            [profiles.homelab]
+            backend_url = "https://portal.home.lan"
+            [profiles.ci]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            rows(&config, &settings),
+            vec![
+                ProfileRow {
+                    name: "ci".to_string(),
+                    backend_url: None,
+                    user: None,
+                    is_default: false,
+                },
+                ProfileRow {
+                    name: "homelab".to_string(),
+                    backend_url: Some("https://portal.home.lan".to_string()),
+                    user: None,
+                    is_default: true,
+                },
+                ProfileRow {
+                    name: "work".to_string(),
+                    backend_url: Some("https://work.example.com".to_string()),
+                    user: Some("me@work".to_string()),
+                    is_default: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_moving_a_profile_drops_its_login() {
+        let mut config = ProxyConfig::default();
+        config.set_profile_backend("work", "https://work.example.com");
+        config.set_profile_auth("work", "token".to_string(), None);
+        config.set_profile_backend("work", "https://work.example.com");
+        assert!(config.get_profile("work").unwrap().auth_token.is_some());
+
+        config.set_profile_backend("work", "https://new.example.com");
+        assert_eq!(config.get_profile("work").unwrap().auth_token, None);
+    }
+}
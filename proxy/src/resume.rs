@@ -0,0 +1,117 @@
+//! `--resume` without an ID: choose a conversation to continue from the
+//! ones Claude has stored for this directory.
+
+use anyhow::{Context, Result};
+use claude_session_lib::history::{self, StoredConversation};
+use colored::Colorize;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+
+/// Conversations offered
+const PICKER_LIMIT: usize = 10;
+
+/// Longest title shown, in characters
+const TITLE_WIDTH: usize = 60;
+
+/// How long ago `modified` was, e.g. "5m ago"
+fn age(modified: SystemTime, now: SystemTime) -> String {
+    let secs = now
+        .duration_since(modified)
+        .unwrap_or(Duration::ZERO)
+        .as_secs();
+    match secs {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86_399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86_400),
+    }
+}
+
+/// A picker line's title, cut to fit
+fn short_title(conversation: &StoredConversation) -> String {
+    let Some(title) = &conversation.title else {
+        return "(no prompt)".to_string();
+    };
+    if title.chars().count() <= TITLE_WIDTH {
+        return title.clone();
+    }
+    let cut: String = title.chars().take(TITLE_WIDTH - 1).collect();
+    format!("{}…", cut)
+}
+
+/// List this directory's recent conversations and ask which to resume
+pub fn pick(cwd: &Path) -> Result<Uuid> {
+    let conversations = history::recent_conversations(cwd, PICKER_LIMIT);
+    if conversations.is_empty() {
+        anyhow::bail!("Claude has no conversations from this directory to resume");
+    }
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!("Pass the conversation to resume: --resume <CLAUDE_SESSION_ID>");
+    }
+
+    let now = SystemTime::now();
+    println!("Recent conversations in this directory:");
+    for (i, conversation) in conversations.iter().enumerate() {
+        println!(
+            "  {:>2}) {:<9} {}  {}",
+            (i + 1).to_string().bright_cyan(),
+            age(conversation.modified, now).dimmed(),
+            conversation.session_id.to_string()[..8].dimmed(),
+            short_title(conversation)
+        );
+    }
+
+    loop {
+        print!("Resume which? [1-{}, q to cancel] ", conversations.len());
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        if std::io::stdin()
+            .lock()
+            .read_line(&mut answer)
+            .context("Failed to read choice")?
+            == 0
+        {
+            anyhow::bail!("Cancelled");
+        }
+        let answer = answer.trim();
+        if answer.eq_ignore_ascii_case("q") {
+            anyhow::bail!("Cancelled");
+        }
+        match answer.parse::<usize>() {
+            Ok(n) if (1..=conversations.len()).contains(&n) => {
+                return Ok(conversations[n - 1].session_id)
+            }
+            _ => println!("Enter a number from the list."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_age_and_title() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let ago = |secs| age(now - Duration::from_secs(secs), now);
+        assert_eq!(ago(5), "just now");
+        assert_eq!(ago(300), "5m ago");
+        assert_eq!(ago(7200), "2h ago");
+        assert_eq!(ago(3 * 86_400), "3d ago");
+        // A clock that moved backwards
+        assert_eq!(age(now + Duration::from_secs(60), now), "just now");
+
+        let mut conversation = StoredConversation {
+            session_id: Uuid::nil(),
+            modified: now,
+            title: Some("x".repeat(80)),
+        };
+        let title = short_title(&conversation);
+        assert_eq!(title.chars().count(), TITLE_WIDTH);
+        assert!(title.ends_with('…'));
+        conversation.title = None;
+        assert_eq!(short_title(&conversation), "(no prompt)");
+    }
+}
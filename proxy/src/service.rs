@@ -0,0 +1,427 @@
+//! `claude-portal service`: keep the daemon running as a user service.
+//!
+//! `install` writes a systemd user unit on Linux, or a launchd agent on
+//! macOS, that runs `claude-portal daemon` in the directory it was run
+//! from, and starts it. The service manager restarts the daemon whenever
+//! it exits, which is also how a self-update takes effect, and starts it
+//! again after a reboot.
+
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::ProxyConfig;
+use crate::settings::FileSettings;
+
+/// Unit name on systemd
+const UNIT: &str = "claude-portal.service";
+
+/// Agent label on launchd
+const LABEL: &str = "com.anthropic.claude-code-portal";
+
+#[derive(Subcommand, Debug)]
+pub enum ServiceCommand {
+    /// Install the service for this directory's login and start it
+    Install,
+    /// Start the installed service
+    Start,
+    /// Stop the service until the next login or `service start`
+    Stop,
+    /// Show whether the service is running
+    Status,
+    /// Stop the service and remove it
+    Uninstall,
+}
+
+/// What the service runs
+#[derive(Debug)]
+struct ServiceSpec {
+    exe: PathBuf,
+    working_directory: PathBuf,
+    profile: Option<String>,
+    /// The installing shell's PATH, so the daemon finds claude the same way
+    path_env: Option<String>,
+}
+
+impl ServiceSpec {
+    fn args(&self) -> Vec<String> {
+        let mut args = vec!["daemon".to_string()];
+        if let Some(profile) = &self.profile {
+            args.push("--profile".to_string());
+            args.push(profile.clone());
+        }
+        args
+    }
+}
+
+/// The service managers `service` knows how to drive
+#[derive(Debug, Clone, Copy)]
+enum Manager {
+    Systemd,
+    Launchd,
+}
+
+impl Manager {
+    fn current() -> Result<Self> {
+        if cfg!(target_os = "macos") {
+            Ok(Self::Launchd)
+        } else if cfg!(target_os = "linux") {
+            Ok(Self::Systemd)
+        } else {
+            anyhow::bail!("Services are supported on Linux (systemd) and macOS (launchd) only")
+        }
+    }
+
+    /// Where the unit or agent file goes
+    fn file_path(self) -> Result<PathBuf> {
+        let dirs = directories::BaseDirs::new().context("Failed to determine home directory")?;
+        Ok(match self {
+            Self::Systemd => dirs.config_dir().join("systemd/user").join(UNIT),
+            Self::Launchd => dirs
+                .home_dir()
+                .join("Library/LaunchAgents")
+                .join(format!("{}.plist", LABEL)),
+        })
+    }
+}
+
+pub fn run(command: ServiceCommand, profile: Option<&str>, settings: &FileSettings) -> Result<()> {
+    let manager = Manager::current()?;
+    match command {
+        ServiceCommand::Install => install(manager, profile, settings),
+        ServiceCommand::Start => match manager {
+            Manager::Systemd => systemctl(&["start", UNIT]),
+            Manager::Launchd => launchctl(&["bootstrap", &gui_domain(), &path_str(manager)?]),
+        },
+        ServiceCommand::Stop => match manager {
+            Manager::Systemd => systemctl(&["stop", UNIT]),
+            Manager::Launchd => launchctl(&["bootout", &agent_target()]),
+        },
+        ServiceCommand::Status => {
+            // Both exit non-zero for a stopped service, which isn't an error here
+            match manager {
+                Manager::Systemd => Command::new("systemctl")
+                    .args(["--user", "status", UNIT])
+                    .status(),
+                Manager::Launchd => Command::new("launchctl")
+                    .args(["print", &agent_target()])
+                    .status(),
+            }
+            .context("Failed to query the service manager")?;
+            Ok(())
+        }
+        ServiceCommand::Uninstall => uninstall(manager),
+    }
+}
+
+fn install(manager: Manager, profile: Option<&str>, settings: &FileSettings) -> Result<()> {
+    let cwd = std::env::current_dir().context("Failed to get current directory")?;
+    let config = ProxyConfig::load().context("Failed to load config file")?;
+    let logged_in = match profile {
+        Some(name) => config
+            .get_profile(name)
+            .is_some_and(|p| p.auth_token.is_some()),
+        None => config.get_session_auth(&cwd.to_string_lossy()).is_some(),
+    };
+    // The service can't answer a login prompt
+    if !logged_in && settings.auth_token()?.is_none() {
+        anyhow::bail!(
+            "Not logged in here. Run claude-portal --init <token-url> in this directory first."
+        );
+    }
+
+    let spec = ServiceSpec {
+        exe: std::env::current_exe().context("Failed to locate the claude-portal binary")?,
+        working_directory: cwd,
+        profile: profile.map(str::to_string),
+        path_env: std::env::var("PATH").ok(),
+    };
+    let path = manager.file_path()?;
+    let contents = match manager {
+        Manager::Systemd => systemd_unit(&spec),
+        Manager::Launchd => {
+            let dirs =
+                directories::BaseDirs::new().context("Failed to determine home directory")?;
+            launchd_plist(
+                &spec,
+                &dirs.home_dir().join("Library/Logs/claude-portal.log"),
+            )
+        }
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::write(&path, contents)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    match manager {
+        Manager::Systemd => {
+            systemctl(&["daemon-reload"])?;
+            systemctl(&["enable", UNIT])?;
+            // Restart rather than start, so a reinstall picks up the new unit
+            systemctl(&["restart", UNIT])?;
+        }
+        Manager::Launchd => {
+            // Unload a previous install; there may be none
+            let _ = Command::new("launchctl")
+                .args(["bootout", &agent_target()])
+                .output();
+            launchctl(&["bootstrap", &gui_domain(), &path.to_string_lossy()])?;
+        }
+    }
+
+    println!(
+        "{} Installed and started the daemon service ({})",
+        "✓".bright_green(),
+        path.display()
+    );
+    println!(
+        "Add sessions by running {} in each project directory.",
+        "claude-portal attach <name>".bright_cyan()
+    );
+    match manager {
+        Manager::Systemd => {
+            println!(
+                "Logs: {}",
+                "journalctl --user -u claude-portal".bright_cyan()
+            );
+            if !lingering() {
+                println!(
+                    "To start it at boot rather than at login, run: {}",
+                    "loginctl enable-linger".bright_cyan()
+                );
+            }
+        }
+        Manager::Launchd => println!("Logs: ~/Library/Logs/claude-portal.log"),
+    }
+    Ok(())
+}
+
+fn uninstall(manager: Manager) -> Result<()> {
+    let path = manager.file_path()?;
+    if !path.exists() {
+        anyhow::bail!("The service isn't installed ({} not found)", path.display());
+    }
+    // Stopping fails if it isn't running, which is fine
+    let _ = match manager {
+        Manager::Systemd => Command::new("systemctl")
+            .args(["--user", "disable", "--now", UNIT])
+            .output(),
+        Manager::Launchd => Command::new("launchctl")
+            .args(["bootout", &agent_target()])
+            .output(),
+    };
+    std::fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    if let Manager::Systemd = manager {
+        systemctl(&["daemon-reload"])?;
+    }
+    println!("{} Removed the daemon service", "✓".bright_green());
+    Ok(())
+}
+
+fn path_str(manager: Manager) -> Result<String> {
+    Ok(manager.file_path()?.to_string_lossy().into_owned())
+}
+
+fn systemctl(args: &[&str]) -> Result<()> {
+    let mut full = vec!["--user"];
+    full.extend_from_slice(args);
+    run_tool("systemctl", &full)
+}
+
+fn launchctl(args: &[&str]) -> Result<()> {
+    run_tool("launchctl", args)
+}
+
+/// Run a service manager command, failing with its output if it fails
+fn run_tool(program: &str, args: &[&str]) -> Result<()> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// launchd's domain for this user's agents
+fn gui_domain() -> String {
+    #[cfg(unix)]
+    let uid = unsafe { libc::getuid() };
+    #[cfg(not(unix))]
+    let uid = 0;
+    format!("gui/{}", uid)
+}
+
+fn agent_target() -> String {
+    format!("{}/{}", gui_domain(), LABEL)
+}
+
+/// Whether systemd keeps this user's services running while they're logged out
+fn lingering() -> bool {
+    std::env::var("USER")
+        .map(|user| Path::new("/var/lib/systemd/linger").join(user).exists())
+        .unwrap_or(false)
+}
+
+/// Quote a word for a unit file's command line, escaping `%` specifiers
+fn systemd_quote(word: &str) -> String {
+    let word = word.replace('%', "%%");
+    if !word.is_empty()
+        && !word
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | ';' | '$'))
+    {
+        return word;
+    }
+    format!(
+        "\"{}\"",
+        word.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('$', "$$")
+    )
+}
+
+fn systemd_unit(spec: &ServiceSpec) -> String {
+    let command: Vec<String> = std::iter::once(spec.exe.to_string_lossy().into_owned())
+        .chain(spec.args())
+        .map(|word| systemd_quote(&word))
+        .collect();
+    let mut unit = format!(
+        "[Unit]\n\
+         Description=Claude Code portal daemon\n\
+         Wants=network-online.target\n\
+         After=network-online.target\n\
+         \n\
+         [Service]\n\
+         WorkingDirectory={}\n\
+         ExecStart={}\n",
+        spec.working_directory.to_string_lossy().replace('%', "%%"),
+        command.join(" ")
+    );
+    if let Some(path) = &spec.path_env {
+        unit.push_str(&format!(
+            "Environment={}\n",
+            systemd_quote(&format!("PATH={}", path))
+        ));
+    }
+    unit.push_str(
+        "Restart=always\n\
+         RestartSec=5\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+    );
+    unit
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn launchd_plist(spec: &ServiceSpec, log_path: &Path) -> String {
+    let string = |text: &str| format!("<string>{}</string>", xml_escape(text));
+    let arguments: Vec<String> = std::iter::once(spec.exe.to_string_lossy().into_owned())
+        .chain(spec.args())
+        .map(|word| format!("\t\t{}\n", string(&word)))
+        .collect();
+    let environment = spec
+        .path_env
+        .as_deref()
+        .map(|path| {
+            format!(
+                "\t<key>EnvironmentVariables</key>\n\
+                 \t<dict>\n\
+                 \t\t<key>PATH</key>\n\
+                 \t\t{}\n\
+                 \t</dict>\n",
+                string(path)
+            )
+        })
+        .unwrap_or_default();
+    let log = string(&log_path.to_string_lossy());
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n\
+         <dict>\n\
+         \t<key>Label</key>\n\
+         \t{}\n\
+         \t<key>ProgramArguments</key>\n\
+         \t<array>\n\
+         {}\
+         \t</array>\n\
+         \t<key>WorkingDirectory</key>\n\
+         \t{}\n\
+         {}\
+         \t<key>RunAtLoad</key>\n\
+         \t<true/>\n\
+         \t<key>KeepAlive</key>\n\
+         \t<true/>\n\
+         \t<key>StandardOutPath</key>\n\
+         \t{}\n\
+         \t<key>StandardErrorPath</key>\n\
+         \t{}\n\
+         </dict>\n\
+         </plist>\n",
+        string(LABEL),
+        arguments.concat(),
+        string(&spec.working_directory.to_string_lossy()),
+        environment,
+        log,
+        log
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> ServiceSpec {
+        ServiceSpec {
+            exe: PathBuf::from("/home/me/bin/claude-portal"),
+            working_directory: PathBuf::from("/home/me/My Projects"),
+            profile: Some("work".to_string()),
+            path_env: Some("/usr/bin:/home/me/.local/bin".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_systemd_unit() {
+        let unit = systemd_unit(&spec());
+        assert!(unit.contains("WorkingDirectory=/home/me/My Projects\n"));
+        assert!(unit.contains("ExecStart=/home/me/bin/claude-portal daemon --profile work\n"));
+        assert!(unit.contains("Environment=PATH=/usr/bin:/home/me/.local/bin\n"));
+        assert!(unit.contains("WantedBy=default.target"));
+
+        assert_eq!(systemd_quote("plain"), "plain");
+        assert_eq!(systemd_quote("/opt/my tools/x"), "\"/opt/my tools/x\"");
+        assert_eq!(systemd_quote("50%"), "50%%");
+        assert_eq!(systemd_quote("a\"b"), "\"a\\\"b\"");
+    }
+
+    #[test]
+    fn test_launchd_plist() {
+        let mut spec = spec();
+        spec.working_directory = PathBuf::from("/Users/me/R&D");
+        spec.profile = None;
+        let plist = launchd_plist(&spec, Path::new("/Users/me/Library/Logs/claude-portal.log"));
+        assert!(plist.contains(
+            "\t\t<string>/home/me/bin/claude-portal</string>\n\t\t<string>daemon</string>\n\t</array>"
+        ));
+        assert!(plist.contains("<string>/Users/me/R&amp;D</string>"));
+        assert!(plist.contains("<key>PATH</key>"));
+        assert!(plist.contains(&format!("<string>{}</string>", LABEL)));
+    }
+}
@@ -0,0 +1,367 @@
+//! Settings from `~/.config/claude-proxy/config.toml`.
+//!
+//! The file holds the options people would otherwise pass on every run.
+//! Command-line flags win over it. It's read only, unlike `config.json`,
+//! which the proxy rewrites to remember logins and sessions; `--init` just
+//! writes a commented starting point if there's no file yet.
+//!
+//! ```toml
+//! backend_url = "https://portal.example.com"
+//! auth_token_env = "CLAUDE_PORTAL_TOKEN"
+//! session_name = "{dir}-{branch}-{date}"
+//! claude_path = "/opt/claude/bin/claude"
+//! log_level = "info"
+//!
+//! [reconnect]
+//! initial_secs = 1
+//! max_secs = 30
+//!
+//! [profiles.homelab]
+//! backend_url = "https://portal.home.lan"
+//! auth_token_file = "~/.secrets/homelab-token"
+//! ```
+//!
+//! A `[profiles.NAME]` section holds the settings for `--profile NAME`,
+//! which override the top-level ones; `profile = "NAME"` picks one when no
+//! `--profile` is given. A profile's backend URL and token reference come
+//! only from its own section, so one backend's token is never sent to
+//! another.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Session name used when neither `--session-name` nor the file sets one
+pub const DEFAULT_SESSION_NAME: &str = "{hostname}-{date}-{time}";
+
+/// How long to wait between reconnect attempts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReconnectSettings {
+    /// First wait after a disconnect
+    pub initial_secs: u64,
+    /// Longest wait; each failed attempt doubles it up to this
+    pub max_secs: u64,
+    /// A connection that lasted this long starts the next wait over
+    pub stable_secs: u64,
+}
+
+impl Default for ReconnectSettings {
+    fn default() -> Self {
+        Self {
+            initial_secs: 1,
+            max_secs: 30,
+            stable_secs: 30,
+        }
+    }
+}
+
+/// The contents of `config.toml`. Every field is optional.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileSettings {
+    pub backend_url: Option<String>,
+    /// Environment variable holding the auth token
+    pub auth_token_env: Option<String>,
+    /// File holding the auth token; `~/` is the home directory
+    pub auth_token_file: Option<String>,
+    /// Pattern for new session names; see [`expand_session_name`]
+    pub session_name: Option<String>,
+    pub claude_path: Option<PathBuf>,
+    /// Log filter, e.g. "debug" or "info,claude_portal=debug"
+    pub log_level: Option<String>,
+    pub reconnect: ReconnectSettings,
+    /// Profile used when `--profile` isn't given
+    pub profile: Option<String>,
+    pub profiles: BTreeMap<String, ProfileSettings>,
+}
+
+/// A `[profiles.NAME]` section. Unset fields other than the backend URL and
+/// token reference fall back to the top level.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileSettings {
+    pub backend_url: Option<String>,
+    pub auth_token_env: Option<String>,
+    pub auth_token_file: Option<String>,
+    pub session_name: Option<String>,
+    pub claude_path: Option<PathBuf>,
+    pub log_level: Option<String>,
+    /// Replaces the top-level `[reconnect]` table as a whole
+    pub reconnect: Option<ReconnectSettings>,
+}
+
+impl FileSettings {
+    pub fn path() -> Result<PathBuf> {
+        let dirs = directories::BaseDirs::new().context("Failed to determine home directory")?;
+        Ok(dirs.config_dir().join("claude-proxy").join("config.toml"))
+    }
+
+    /// Read the settings file, or defaults if there isn't one
+    pub fn load() -> Result<Self> {
+        Self::load_from(&Self::path()?)
+    }
+
+    fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// These settings with profile `name`'s section laid over them
+    pub fn with_profile(&self, name: &str) -> Self {
+        let profile = self.profiles.get(name).cloned().unwrap_or_default();
+        Self {
+            backend_url: profile.backend_url,
+            auth_token_env: profile.auth_token_env,
+            auth_token_file: profile.auth_token_file,
+            session_name: profile.session_name.or(self.session_name.clone()),
+            claude_path: profile.claude_path.or(self.claude_path.clone()),
+            log_level: profile.log_level.or(self.log_level.clone()),
+            reconnect: profile.reconnect.unwrap_or(self.reconnect),
+            profile: Some(name.to_string()),
+            profiles: self.profiles.clone(),
+        }
+    }
+
+    /// The auth token the file points at, if it points at one. A reference
+    /// that doesn't resolve is an error rather than a silent fallback to
+    /// logging in again.
+    pub fn auth_token(&self) -> Result<Option<String>> {
+        if let Some(var) = &self.auth_token_env {
+            let token = std::env::var(var)
+                .with_context(|| format!("auth_token_env: ${} is not set", var))?;
+            return Ok(Some(token.trim().to_string()));
+        }
+        if let Some(file) = &self.auth_token_file {
+            let path = expand_home(file);
+            let token = fs::read_to_string(&path)
+                .with_context(|| format!("auth_token_file: failed to read {}", path.display()))?;
+            return Ok(Some(token.trim().to_string()));
+        }
+        Ok(None)
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), directories::BaseDirs::new()) {
+        (Some(rest), Some(dirs)) => dirs.home_dir().join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Values a session name pattern can use
+pub struct NameContext<'a> {
+    pub hostname: &'a str,
+    /// Last component of the working directory
+    pub dir: &'a str,
+    pub branch: Option<&'a str>,
+    pub now: chrono::DateTime<chrono::Local>,
+}
+
+/// Fill in `{hostname}`, `{dir}`, `{branch}`, `{date}` (YYYYMMDD) and
+/// `{time}` (HHMMSS) in a session name pattern. `{branch}` is "nobranch"
+/// outside a git repository; anything else in braces is left as written.
+pub fn expand_session_name(pattern: &str, ctx: &NameContext) -> String {
+    pattern
+        .replace("{hostname}", ctx.hostname)
+        .replace("{dir}", ctx.dir)
+        .replace("{branch}", ctx.branch.unwrap_or("nobranch"))
+        .replace("{date}", &ctx.now.format("%Y%m%d").to_string())
+        .replace("{time}", &ctx.now.format("%H%M%S").to_string())
+}
+
+/// Starting point written by `--init`
+fn template(backend_url: &str) -> String {
+    format!(
+        "# claude-portal settings. Command-line flags override these.\n\
+         \n\
+         backend_url = \"{backend_url}\"\n\
+         \n\
+         # Read the auth token from an environment variable or a file\n\
+         # instead of the login saved by --init\n\
+         # auth_token_env = \"CLAUDE_PORTAL_TOKEN\"\n\
+         # auth_token_file = \"~/.secrets/claude-portal-token\"\n\
+         \n\
+         # Name for new sessions: {{hostname}}, {{dir}}, {{branch}}, {{date}}, {{time}}\n\
+         # session_name = \"{DEFAULT_SESSION_NAME}\"\n\
+         \n\
+         # claude_path = \"/usr/local/bin/claude\"\n\
+         \n\
+         # log_level = \"info\"\n\
+         \n\
+         # [reconnect]\n\
+         # initial_secs = 1\n\
+         # max_secs = 30\n\
+         # stable_secs = 30\n\
+         \n\
+         # Settings for --profile homelab; unset ones come from above\n\
+         # [profiles.homelab]\n\
+         # backend_url = \"https://portal.home.lan\"\n\
+         # auth_token_env = \"HOMELAB_PORTAL_TOKEN\"\n"
+    )
+}
+
+/// Write a commented settings file for `backend_url` unless one exists,
+/// returning its path if written
+pub fn write_template(backend_url: &str) -> Result<Option<PathBuf>> {
+    let path = FileSettings::path()?;
+    write_template_to(&path, backend_url).map(|written| written.then_some(path))
+}
+
+fn write_template_to(path: &Path, backend_url: &str) -> Result<bool> {
+    if path.exists() {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("Failed to create settings directory")?;
+    }
+    fs::write(path, template(backend_url)).context("Failed to write settings file")?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_settings() {
+        let settings: FileSettings = toml::from_str(
+            r#"
+            backend_url = "https://portal.example.com"
+            auth_token_env = "PORTAL_TOKEN"
+            session_name = "{dir}-{branch}"
+            claude_path = "/opt/claude"
+            log_level = "debug"
+
+            [reconnect]
+            max_secs = 120
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            settings.backend_url.as_deref(),
+            Some("https://portal.example.com")
+        );
+        assert_eq!(settings.claude_path, Some(PathBuf::from("/opt/claude")));
+        assert_eq!(settings.reconnect.max_secs, 120);
+        assert_eq!(settings.reconnect.initial_secs, 1);
+
+        assert_eq!(
+            toml::from_str::<FileSettings>("").unwrap(),
+            FileSettings::default()
+        );
+        // Misspelled keys are reported, not ignored
+        assert!(toml::from_str::<FileSettings>("backend-url = \"x\"").is_err());
+    }
+
+    #[test]
+    fn test_profile_overrides_top_level() {
+        let settings: FileSettings = toml::from_str(
+            r#"
+            backend_url = "https://work.example.com"
+            auth_token_env = "WORK_TOKEN"
+            session_name = "{dir}"
+            profile = "work"
+
+            [profiles.work]
+
+            [profiles.homelab]
+            backend_url = "https://portal.home.lan"
+            auth_token_file = "~/.secrets/homelab"
+            reconnect = { max_secs = 5 }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(settings.profile.as_deref(), Some("work"));
+
+        // The top-level backend and token aren't a profile's
+        let work = settings.with_profile("work");
+        assert_eq!(work.backend_url, None);
+        assert_eq!(work.auth_token_env, None);
+        assert_eq!(work.session_name.as_deref(), Some("{dir}"));
+        assert_eq!(
+            settings.with_profile("unlisted"),
+            work.with_profile("unlisted")
+        );
+
+        let homelab = settings.with_profile("homelab");
+        assert_eq!(
+            homelab.backend_url.as_deref(),
+            Some("https://portal.home.lan")
+        );
+        assert_eq!(
+            homelab.auth_token_file.as_deref(),
+            Some("~/.secrets/homelab")
+        );
+        assert_eq!(homelab.session_name.as_deref(), Some("{dir}"));
+        assert_eq!(homelab.reconnect.max_secs, 5);
+        assert_eq!(homelab.profile.as_deref(), Some("homelab"));
+
+        assert!(toml::from_str::<FileSettings>("[profiles.x]\nbackend = \"y\"").is_err());
+    }
+
+    #[test]
+    fn test_expand_session_name() {
+        let ctx = NameContext {
+            hostname: "laptop",
+            dir: "cc-proxy",
+            branch: Some("main"),
+            now: chrono::Local.with_ymd_and_hms(2026, 3, 4, 5, 6, 7).unwrap(),
+        };
+        assert_eq!(
+            expand_session_name(DEFAULT_SESSION_NAME, &ctx),
+            "laptop-20260304-050607"
+        );
+        assert_eq!(
+            expand_session_name("{dir}/{branch} {other}", &ctx),
+            "cc-proxy/main {other}"
+        );
+        let ctx = NameContext {
+            branch: None,
+            ..ctx
+        };
+        assert_eq!(expand_session_name("{branch}", &ctx), "nobranch");
+    }
+
+    #[test]
+    fn test_token_references() {
+        let dir = tempfile::tempdir().unwrap();
+        let token_file = dir.path().join("token");
+        fs::write(&token_file, "abc123\n").unwrap();
+        let settings = FileSettings {
+            auth_token_file: Some(token_file.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        assert_eq!(settings.auth_token().unwrap().as_deref(), Some("abc123"));
+
+        let settings = FileSettings {
+            auth_token_env: Some("CLAUDE_PROXY_TEST_UNSET_TOKEN".to_string()),
+            ..Default::default()
+        };
+        assert!(settings.auth_token().is_err());
+        assert_eq!(FileSettings::default().auth_token().unwrap(), None);
+    }
+
+    #[test]
+    fn test_template_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("claude-proxy").join("config.toml");
+        assert!(write_template_to(&path, "https://portal.example.com").unwrap());
+        // An existing file is left alone
+        assert!(!write_template_to(&path, "https://other.example.com").unwrap());
+
+        let settings = FileSettings::load_from(&path).unwrap();
+        assert_eq!(
+            settings.backend_url.as_deref(),
+            Some("https://portal.example.com")
+        );
+        assert_eq!(settings.reconnect, ReconnectSettings::default());
+    }
+}
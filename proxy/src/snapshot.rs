@@ -0,0 +1,558 @@
+//! Session snapshots: saving them locally, mirroring them to the backend,
+//! and the `claude-portal snapshot` command for reading saved ones.
+//!
+//! [`LocalSnapshots`] saves the session's snapshot under the config
+//! directory on an interval and whenever the session's events call for it,
+//! sealed with the keys in `CLAUDE_SNAPSHOT_KEYS` if set. While connected,
+//! the proxy also uploads its latest snapshot every [`UPLOAD_INTERVAL`] so
+//! the session's pending state survives losing this host, unless keys are
+//! set: the backend keeps snapshots as plain JSON, so sealed sessions aren't
+//! mirrored. For debugging restores, `inspect` prints what a snapshot would
+//! restore and `diff` shows what changed between two of them; the web UI
+//! offers the mirrored snapshot as a download.
+
+use crate::output_buffer::PendingOutput;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use clap::Subcommand;
+use claude_session_lib::{
+    BufferedOutput, DirectorySnapshotStore, Session as ClaudeSession, SessionEvent,
+    SessionSnapshot, SnapshotKeys, SnapshotScheduler, SnapshotTrigger,
+};
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// How often the proxy mirrors its snapshot to the backend
+pub const UPLOAD_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The session's snapshot with the proxy's unacknowledged outputs as its
+/// pending outputs, since those are what the backend would lose, and
+/// secrets redacted from Claude's arguments
+pub fn for_backend(mut snapshot: SessionSnapshot, pending: &[PendingOutput]) -> SessionSnapshot {
+    snapshot.pending_outputs = pending
+        .iter()
+        .map(|output| BufferedOutput {
+            seq: output.seq,
+            content: output.content.clone(),
+            timestamp: output
+                .produced_at
+                .and_then(|ms| DateTime::from_timestamp_millis(ms as i64))
+                .unwrap_or_else(Utc::now),
+        })
+        .collect();
+    snapshot.config.extra_args = snapshot
+        .config
+        .extra_args
+        .iter()
+        .map(|arg| shared::redact_secrets(arg))
+        .collect();
+    snapshot
+}
+
+/// Uploads snapshots to `PUT /api/sessions/:id/snapshot`, skipping ones
+/// that only differ from the last upload in their timestamp
+#[derive(Clone)]
+pub struct SnapshotUploader {
+    url: String,
+    auth_token: Option<String>,
+    client: reqwest::Client,
+    /// Fingerprint of the last snapshot the backend accepted
+    uploaded: Arc<Mutex<Option<u64>>>,
+}
+
+impl SnapshotUploader {
+    /// The uploader, or `None` when snapshot keys are configured, since the
+    /// backend would store the snapshot unsealed
+    pub fn new(backend_url: &str, auth_token: Option<String>, session_id: Uuid) -> Option<Self> {
+        match SnapshotKeys::from_env() {
+            Ok(None) => {}
+            Ok(Some(_)) => {
+                info!("Snapshot keys are set, so snapshots aren't mirrored to the backend");
+                return None;
+            }
+            Err(e) => {
+                warn!("Not mirroring snapshots to the backend: {}", e);
+                return None;
+            }
+        }
+        let http_base = backend_url
+            .replace("ws://", "http://")
+            .replace("wss://", "https://");
+        Some(Self {
+            url: format!("{}/api/sessions/{}/snapshot", http_base, session_id),
+            auth_token,
+            client: reqwest::Client::new(),
+            uploaded: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Upload in the background unless the backend already has it
+    pub fn upload(&self, snapshot: SessionSnapshot) {
+        let Some(fingerprint) = fingerprint(&snapshot) else {
+            return;
+        };
+        if *self.uploaded.lock().unwrap() == Some(fingerprint) {
+            return;
+        }
+        let uploader = self.clone();
+        tokio::spawn(async move {
+            let mut request = uploader.client.put(&uploader.url).json(&snapshot);
+            if let Some(token) = &uploader.auth_token {
+                request = request.bearer_auth(token);
+            }
+            match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    debug!("Mirrored session snapshot to the backend");
+                    *uploader.uploaded.lock().unwrap() = Some(fingerprint);
+                }
+                // Older backends don't have the endpoint
+                Ok(response) if response.status() == reqwest::StatusCode::NOT_FOUND => {
+                    debug!("Backend doesn't store session snapshots");
+                }
+                Ok(response) => warn!("Snapshot upload failed: HTTP {}", response.status()),
+                Err(e) => warn!("Snapshot upload failed: {}", e),
+            }
+        });
+    }
+}
+
+/// Where the proxy saves its own snapshots, sealed if keys are configured
+fn local_store() -> Result<DirectorySnapshotStore> {
+    let dir = directories::ProjectDirs::from("com", "anthropic", "claude-code-portal")
+        .context("Failed to determine config directory")?
+        .config_dir()
+        .join("snapshots");
+    let store = DirectorySnapshotStore::new(dir);
+    Ok(match SnapshotKeys::from_env()? {
+        Some(keys) => store.with_keys(keys),
+        None => store,
+    })
+}
+
+/// What woke [`LocalSnapshots`]
+pub enum SnapshotWake {
+    /// A save is due regardless of events
+    Due(SnapshotTrigger),
+    /// The session did something that may call for a save
+    Event(SessionEvent),
+}
+
+/// Saves the session's snapshot to [`local_store`], driven by a
+/// [`SnapshotScheduler`] and the session's event stream. Does nothing if
+/// the store can't be set up.
+pub struct LocalSnapshots {
+    scheduler: Option<SnapshotScheduler>,
+    events: broadcast::Receiver<SessionEvent>,
+}
+
+impl LocalSnapshots {
+    pub fn new(session: &ClaudeSession) -> Self {
+        let scheduler = match local_store() {
+            Ok(store) => Some(SnapshotScheduler::new(
+                Arc::new(store),
+                SnapshotScheduler::DEFAULT_INTERVAL,
+            )),
+            Err(e) => {
+                warn!("Not saving session snapshots locally: {:#}", e);
+                None
+            }
+        };
+        Self {
+            scheduler,
+            events: session.subscribe_events(),
+        }
+    }
+
+    /// Wait for a periodic save or the session's next event. Never returns
+    /// if snapshots are off.
+    pub async fn wake(&mut self) -> SnapshotWake {
+        let Some(scheduler) = self.scheduler.as_mut() else {
+            return std::future::pending().await;
+        };
+        tokio::select! {
+            trigger = scheduler.tick() => SnapshotWake::Due(trigger),
+            event = self.events.recv() => match event {
+                Ok(event) => SnapshotWake::Event(event),
+                // A missed event may have called for a save
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    SnapshotWake::Due(SnapshotTrigger::Interval)
+                }
+                Err(broadcast::error::RecvError::Closed) => std::future::pending().await,
+            },
+        }
+    }
+
+    /// Save if `wake` calls for it
+    pub async fn handle(&mut self, wake: SnapshotWake, session: &ClaudeSession) {
+        let Some(scheduler) = self.scheduler.as_mut() else {
+            return;
+        };
+        let saved = match wake {
+            SnapshotWake::Due(trigger) => scheduler.save(session, trigger).await,
+            SnapshotWake::Event(event) => scheduler.observe(&event, session).await.map(|_| ()),
+        };
+        if let Err(e) = saved {
+            warn!("Failed to save session snapshot: {}", e);
+        }
+    }
+
+    /// Handle the events the session has sent but nobody has waited for,
+    /// like its exit, which ends the loops that would otherwise see it
+    pub async fn catch_up(&mut self, session: &ClaudeSession) {
+        loop {
+            let wake = match self.events.try_recv() {
+                Ok(event) => SnapshotWake::Event(event),
+                Err(broadcast::error::TryRecvError::Lagged(_)) => {
+                    SnapshotWake::Due(SnapshotTrigger::Interval)
+                }
+                Err(_) => return,
+            };
+            self.handle(wake, session).await;
+        }
+    }
+}
+
+/// Hash of everything in a snapshot but when it was taken
+fn fingerprint(snapshot: &SessionSnapshot) -> Option<u64> {
+    let mut snapshot = snapshot.clone();
+    snapshot.last_activity = DateTime::UNIX_EPOCH;
+    let bytes = serde_json::to_vec(&snapshot).ok()?;
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SnapshotCommand {
+    /// Print a snapshot's config, permission state, and pending outputs
+    Inspect {
+        /// Snapshot JSON file
+        file: PathBuf,
+
+        /// List each pending output, not just how many there are
+        #[arg(long)]
+        outputs: bool,
+    },
+    /// Show how two snapshots differ
+    Diff {
+        /// Older snapshot
+        a: PathBuf,
+        /// Newer snapshot
+        b: PathBuf,
+    },
+}
+
+pub fn run(command: SnapshotCommand) -> Result<()> {
+    match command {
+        SnapshotCommand::Inspect { file, outputs } => {
+            let snapshot = load(&file)?;
+            for (label, value) in describe(&snapshot) {
+                println!("{:>20} {}", label.dimmed(), value);
+            }
+            if outputs {
+                println!();
+                for output in &snapshot.pending_outputs {
+                    println!(
+                        "{:>8} {} {}",
+                        output.seq.to_string().bright_cyan(),
+                        output.timestamp.to_rfc3339().dimmed(),
+                        output_kind(output)
+                    );
+                }
+            }
+        }
+        SnapshotCommand::Diff { a, b } => {
+            let diff = SnapshotDiff::new(&load(&a)?, &load(&b)?);
+            if diff.is_empty() {
+                println!("Snapshots are the same");
+                return Ok(());
+            }
+            for (label, old, new) in &diff.fields {
+                println!("{:>20} {} → {}", label.dimmed(), old.red(), new.green());
+            }
+            let seqs = [
+                ("Outputs removed", &diff.removed),
+                ("Outputs added", &diff.added),
+                ("Outputs changed", &diff.changed),
+            ];
+            for (label, seqs) in seqs {
+                if !seqs.is_empty() {
+                    println!("{:>20} {}", label.dimmed(), seq_ranges(seqs));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Read a snapshot file, decrypting it with the keys in
+/// `CLAUDE_SNAPSHOT_KEYS` if it's encrypted
+fn load(path: &Path) -> Result<SessionSnapshot> {
+    let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    let keys = SnapshotKeys::from_env()?;
+    SessionSnapshot::from_bytes_with(&bytes, keys.as_ref())
+        .with_context(|| format!("{} is not a readable session snapshot", path.display()))
+}
+
+/// Labeled summary of a snapshot, in display order
+fn describe(snapshot: &SessionSnapshot) -> Vec<(&'static str, String)> {
+    let config = &snapshot.config;
+    let none = || "none".to_string();
+    let outputs = &snapshot.pending_outputs;
+    let mut fields = vec![
+        ("ID", snapshot.id.to_string()),
+        ("Name", config.session_name.clone()),
+        ("Directory", config.working_directory.display().to_string()),
+        ("Resume", config.resume.to_string()),
+        (
+            "Claude path",
+            config
+                .claude_path
+                .as_ref()
+                .map_or_else(none, |p| p.display().to_string()),
+        ),
+        (
+            "Claude version",
+            config.claude_version.clone().unwrap_or_else(none),
+        ),
+        ("Extra args", config.extra_args.join(" ")),
+        (
+            "Buffer size",
+            config.buffer_size.map_or_else(none, |n| n.to_string()),
+        ),
+        ("Last activity", snapshot.last_activity.to_rfc3339()),
+        ("Was running", snapshot.was_running.to_string()),
+        (
+            "Pending outputs",
+            match (outputs.first(), outputs.last()) {
+                (Some(first), Some(last)) => {
+                    format!("{} (seq {}..={})", outputs.len(), first.seq, last.seq)
+                }
+                _ => "0".to_string(),
+            },
+        ),
+    ];
+    match &snapshot.pending_permission {
+        Some(permission) => {
+            fields.push(("Permission", permission.tool_name.clone()));
+            fields.push(("Permission request", permission.request_id.clone()));
+            fields.push(("Permission asked", permission.requested_at.to_rfc3339()));
+        }
+        None => fields.push(("Permission", none())),
+    }
+    fields
+}
+
+/// The `type` of an output's message, for listing
+fn output_kind(output: &BufferedOutput) -> String {
+    output
+        .content
+        .get("type")
+        .and_then(|t| t.as_str())
+        .unwrap_or("?")
+        .to_string()
+}
+
+/// What changed from one snapshot to another
+#[derive(Debug, Default, PartialEq)]
+struct SnapshotDiff {
+    /// Summary fields that differ: label, old, new
+    fields: Vec<(&'static str, String, String)>,
+    /// Pending outputs only in the first
+    removed: Vec<u64>,
+    /// Pending outputs only in the second
+    added: Vec<u64>,
+    /// Pending outputs in both with different content
+    changed: Vec<u64>,
+}
+
+impl SnapshotDiff {
+    fn new(a: &SessionSnapshot, b: &SessionSnapshot) -> Self {
+        let old = describe(a);
+        let new = describe(b);
+        let value = |fields: &[(&str, String)], label| {
+            fields
+                .iter()
+                .find(|(l, _)| *l == label)
+                .map_or_else(|| "-".to_string(), |(_, v)| v.clone())
+        };
+        // Permission fields are only there while one is pending, so take
+        // labels from both
+        let mut labels: Vec<&'static str> = new.iter().map(|(label, _)| *label).collect();
+        for (label, _) in &old {
+            if !labels.contains(label) {
+                labels.push(label);
+            }
+        }
+        let fields = labels
+            .into_iter()
+            .filter_map(|label| {
+                let (old, new) = (value(&old, label), value(&new, label));
+                (old != new).then_some((label, old, new))
+            })
+            .collect();
+
+        let outputs_a: BTreeMap<_, _> = a.pending_outputs.iter().map(|o| (o.seq, o)).collect();
+        let outputs_b: BTreeMap<_, _> = b.pending_outputs.iter().map(|o| (o.seq, o)).collect();
+        Self {
+            fields,
+            removed: outputs_a
+                .keys()
+                .filter(|seq| !outputs_b.contains_key(seq))
+                .copied()
+                .collect(),
+            added: outputs_b
+                .keys()
+                .filter(|seq| !outputs_a.contains_key(seq))
+                .copied()
+                .collect(),
+            changed: outputs_a
+                .iter()
+                .filter(|(seq, o)| outputs_b.get(seq).is_some_and(|b| b.content != o.content))
+                .map(|(seq, _)| *seq)
+                .collect(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Sorted seqs as ranges, e.g. "0..=3, 7"
+fn seq_ranges(seqs: &[u64]) -> String {
+    let mut ranges: Vec<(u64, u64)> = Vec::new();
+    for &seq in seqs {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == seq => *end = seq,
+            _ => ranges.push((seq, seq)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}..={}", start, end)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claude_session_lib::{PendingPermission, SessionConfig};
+    use uuid::Uuid;
+
+    fn output(seq: u64, text: &str) -> BufferedOutput {
+        BufferedOutput {
+            seq,
+            content: serde_json::json!({"type": "assistant", "text": text}),
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn snapshot() -> SessionSnapshot {
+        let config = SessionConfig {
+            session_id: Uuid::new_v4(),
+            session_name: "overnight".to_string(),
+            ..Default::default()
+        };
+        let mut snapshot = SessionSnapshot::new(
+            config.session_id,
+            config,
+            vec![output(0, "a"), output(1, "b"), output(2, "c")],
+            None,
+            true,
+        );
+        snapshot.pending_permission = Some(PendingPermission {
+            request_id: "req-1".to_string(),
+            tool_name: "Bash".to_string(),
+            input: serde_json::json!({"command": "ls"}),
+            requested_at: Utc::now(),
+        });
+        snapshot
+    }
+
+    #[test]
+    fn test_describe() {
+        let fields: BTreeMap<_, _> = describe(&snapshot()).into_iter().collect();
+        assert_eq!(fields["Pending outputs"], "3 (seq 0..=2)");
+        assert_eq!(fields["Permission"], "Bash");
+    }
+
+    #[test]
+    fn test_diff() {
+        let a = snapshot();
+        assert!(SnapshotDiff::new(&a, &a).is_empty());
+
+        let mut b = a.clone();
+        b.was_running = false;
+        b.pending_permission = None;
+        b.pending_outputs = vec![output(1, "b"), output(2, "changed"), output(3, "d")];
+
+        let diff = SnapshotDiff::new(&a, &b);
+        let labels: Vec<_> = diff.fields.iter().map(|(label, _, _)| *label).collect();
+        assert_eq!(
+            labels,
+            vec![
+                "Was running",
+                "Pending outputs",
+                "Permission",
+                "Permission request",
+                "Permission asked"
+            ]
+        );
+        assert_eq!(diff.removed, vec![0]);
+        assert_eq!(diff.added, vec![3]);
+        assert_eq!(diff.changed, vec![2]);
+    }
+
+    #[test]
+    fn test_for_backend() {
+        let mut snapshot = snapshot();
+        snapshot.config.extra_args = vec!["ANTHROPIC_API_KEY=sk-ant-abcdefghijklmnop".to_string()];
+        let pending = vec![PendingOutput {
+            seq: 7,
+            content: serde_json::json!({"type": "result"}),
+            traceparent: None,
+            produced_at: Some(1_700_000_000_000),
+        }];
+
+        let mirrored = for_backend(snapshot, &pending);
+        assert_eq!(mirrored.pending_outputs.len(), 1);
+        assert_eq!(mirrored.pending_outputs[0].seq, 7);
+        assert_eq!(
+            mirrored.pending_outputs[0].timestamp.timestamp_millis(),
+            1_700_000_000_000
+        );
+        assert!(!mirrored.config.extra_args[0].contains("sk-ant-"));
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_timestamp() {
+        let a = snapshot();
+        let mut b = a.clone();
+        b.last_activity = Utc::now() + chrono::Duration::seconds(60);
+        assert_eq!(fingerprint(&a), fingerprint(&b));
+
+        b.was_running = false;
+        assert_ne!(fingerprint(&a), fingerprint(&b));
+    }
+
+    #[test]
+    fn test_seq_ranges() {
+        assert_eq!(seq_ranges(&[0, 1, 2, 3, 7, 9, 10]), "0..=3, 7, 9..=10");
+        assert_eq!(seq_ranges(&[]), "");
+    }
+}
@@ -0,0 +1,282 @@
+//! Live state of this process's sessions, for the `--tui` status screen.
+//!
+//! Sessions report into one process-wide [`Status`] as they connect, change
+//! state, and pass messages; the status screen reads a copy on each redraw.
+//! Recording is cheap, so it happens whether or not the screen is up.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+use claude_session_lib::SessionState as ClaudeState;
+use uuid::Uuid;
+
+/// Number of recent messages kept across all sessions
+const MAX_RECENT: usize = 100;
+
+/// Whether a session can reach the backend
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Connection {
+    Connecting,
+    Connected,
+    /// Lost; the next attempt is at `retry_at`
+    Disconnected {
+        retry_at: Instant,
+    },
+}
+
+/// A permission prompt waiting for an answer from the web UI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingPermission {
+    pub request_id: String,
+    pub tool_name: String,
+}
+
+/// What's known about one session
+#[derive(Debug, Clone)]
+pub struct SessionStatus {
+    pub name: String,
+    pub working_directory: String,
+    pub connection: Connection,
+    /// Claude's state, e.g. "in_turn"
+    pub state: &'static str,
+    pub pending_permissions: Vec<PendingPermission>,
+}
+
+/// Which way a message went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// From the web UI to Claude
+    Input,
+    /// From Claude to the web UI
+    Output,
+}
+
+/// One line of the recent messages list
+#[derive(Debug, Clone)]
+pub struct RecentMessage {
+    pub at: chrono::DateTime<chrono::Local>,
+    pub session_id: Uuid,
+    pub direction: Direction,
+    pub summary: String,
+}
+
+/// Messages and bytes over the backend connections, since start
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Traffic {
+    pub messages_sent: u64,
+    pub bytes_sent: u64,
+    pub messages_received: u64,
+    pub bytes_received: u64,
+}
+
+impl Traffic {
+    /// Per-second rates between an earlier reading and this one, as
+    /// (messages sent, bytes sent, messages received, bytes received)
+    pub fn rates_since(&self, earlier: &Traffic, secs: f64) -> (f64, f64, f64, f64) {
+        if secs <= 0.0 {
+            return (0.0, 0.0, 0.0, 0.0);
+        }
+        let per_sec = |now: u64, then: u64| now.saturating_sub(then) as f64 / secs;
+        (
+            per_sec(self.messages_sent, earlier.messages_sent),
+            per_sec(self.bytes_sent, earlier.bytes_sent),
+            per_sec(self.messages_received, earlier.messages_received),
+            per_sec(self.bytes_received, earlier.bytes_received),
+        )
+    }
+}
+
+/// Everything the status screen shows
+#[derive(Debug, Clone, Default)]
+pub struct Status {
+    pub sessions: BTreeMap<Uuid, SessionStatus>,
+    /// Oldest first
+    pub recent: VecDeque<RecentMessage>,
+    pub traffic: Traffic,
+}
+
+impl Status {
+    fn session_started(&mut self, session_id: Uuid, name: &str, working_directory: &str) {
+        self.sessions.insert(
+            session_id,
+            SessionStatus {
+                name: name.to_string(),
+                working_directory: working_directory.to_string(),
+                connection: Connection::Connecting,
+                state: ClaudeState::Created.name(),
+                pending_permissions: Vec::new(),
+            },
+        );
+    }
+
+    fn set_state(&mut self, session_id: Uuid, state: &ClaudeState) {
+        if let Some(session) = self.sessions.get_mut(&session_id) {
+            session.state = state.name();
+            // No prompt outlives the turn it was raised in
+            if !matches!(
+                state,
+                ClaudeState::InTurn | ClaudeState::AwaitingPermission { .. }
+            ) {
+                session.pending_permissions.clear();
+            }
+        }
+    }
+
+    fn permission_requested(&mut self, session_id: Uuid, request_id: &str, tool_name: &str) {
+        if let Some(session) = self.sessions.get_mut(&session_id) {
+            session.pending_permissions.push(PendingPermission {
+                request_id: request_id.to_string(),
+                tool_name: tool_name.to_string(),
+            });
+        }
+    }
+
+    fn permission_answered(&mut self, session_id: Uuid, request_id: &str) {
+        if let Some(session) = self.sessions.get_mut(&session_id) {
+            session
+                .pending_permissions
+                .retain(|pending| pending.request_id != request_id);
+        }
+    }
+
+    fn record(&mut self, session_id: Uuid, direction: Direction, summary: String) {
+        if self.recent.len() >= MAX_RECENT {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(RecentMessage {
+            at: chrono::Local::now(),
+            session_id,
+            direction,
+            summary,
+        });
+    }
+}
+
+fn status() -> &'static Mutex<Status> {
+    static STATUS: OnceLock<Mutex<Status>> = OnceLock::new();
+    STATUS.get_or_init(Mutex::default)
+}
+
+fn update(f: impl FnOnce(&mut Status)) {
+    if let Ok(mut status) = status().lock() {
+        f(&mut status);
+    }
+}
+
+/// A copy of the current status
+pub fn snapshot() -> Status {
+    status()
+        .lock()
+        .map(|status| status.clone())
+        .unwrap_or_default()
+}
+
+/// A session started (or restarted under a new ID)
+pub fn session_started(session_id: Uuid, name: &str, working_directory: &str) {
+    update(|status| status.session_started(session_id, name, working_directory));
+}
+
+/// A session's Claude process is gone
+pub fn session_ended(session_id: Uuid) {
+    update(|status| {
+        status.sessions.remove(&session_id);
+    });
+}
+
+pub fn set_connection(session_id: Uuid, connection: Connection) {
+    update(|status| {
+        if let Some(session) = status.sessions.get_mut(&session_id) {
+            session.connection = connection;
+        }
+    });
+}
+
+pub fn set_state(session_id: Uuid, state: &ClaudeState) {
+    update(|status| status.set_state(session_id, state));
+}
+
+/// Claude asked for a permission that no remembered rule answers
+pub fn permission_requested(session_id: Uuid, request_id: &str, tool_name: &str) {
+    update(|status| status.permission_requested(session_id, request_id, tool_name));
+}
+
+/// The web UI answered a permission prompt
+pub fn permission_answered(session_id: Uuid, request_id: &str) {
+    update(|status| status.permission_answered(session_id, request_id));
+}
+
+/// Add a line to the recent messages
+pub fn record(session_id: Uuid, direction: Direction, summary: String) {
+    update(|status| status.record(session_id, direction, summary));
+}
+
+/// A message of `bytes` went to the backend
+pub fn sent(bytes: usize) {
+    update(|status| {
+        status.traffic.messages_sent += 1;
+        status.traffic.bytes_sent += bytes as u64;
+    });
+}
+
+/// A message of `bytes` came from the backend
+pub fn received(bytes: usize) {
+    update(|status| {
+        status.traffic.messages_received += 1;
+        status.traffic.bytes_received += bytes as u64;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_permissions_clear_with_the_turn() {
+        let mut status = Status::default();
+        let id = Uuid::new_v4();
+        status.session_started(id, "api", "/src/api");
+        status.set_state(id, &ClaudeState::InTurn);
+        status.permission_requested(id, "r1", "Bash");
+        status.permission_requested(id, "r2", "Edit");
+        status.permission_answered(id, "r2");
+
+        // A state change that arrives late from the same turn keeps it
+        status.set_state(id, &ClaudeState::InTurn);
+        assert_eq!(status.sessions[&id].pending_permissions.len(), 1);
+
+        // An interrupted turn leaves nothing to answer
+        status.set_state(id, &ClaudeState::Ready);
+        assert!(status.sessions[&id].pending_permissions.is_empty());
+        assert_eq!(status.sessions[&id].state, "ready");
+    }
+
+    #[test]
+    fn test_recent_messages_are_capped() {
+        let mut status = Status::default();
+        let id = Uuid::new_v4();
+        for i in 0..MAX_RECENT + 5 {
+            status.record(id, Direction::Output, i.to_string());
+        }
+        assert_eq!(status.recent.len(), MAX_RECENT);
+        assert_eq!(status.recent.front().unwrap().summary, "5");
+    }
+
+    #[test]
+    fn test_traffic_rates() {
+        let earlier = Traffic {
+            messages_sent: 10,
+            bytes_sent: 1000,
+            messages_received: 4,
+            bytes_received: 400,
+        };
+        let now = Traffic {
+            messages_sent: 20,
+            bytes_sent: 3000,
+            messages_received: 4,
+            bytes_received: 400,
+        };
+        assert_eq!(now.rates_since(&earlier, 2.0), (5.0, 1000.0, 0.0, 0.0));
+        assert_eq!(now.rates_since(&earlier, 0.0), (0.0, 0.0, 0.0, 0.0));
+    }
+}
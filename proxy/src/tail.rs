@@ -0,0 +1,347 @@
+//! `claude-portal tail`: follow a session from the terminal.
+//!
+//! Connects to the backend as a web client would (authenticated with the
+//! proxy token instead of a browser cookie), then prints the session's output
+//! with ANSI colors until the connection closes or Ctrl+C. It only watches;
+//! nothing is sent to the session.
+
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::Value;
+use shared::{protocol, CompactBoundary, ErrorCode, ProxyMessage};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{client::IntoClientRequest, http::header, Message},
+};
+use uuid::Uuid;
+
+use crate::ui;
+
+/// Tool results are cut to this many lines
+const MAX_RESULT_LINES: usize = 3;
+
+/// Longest tool argument shown next to the tool name
+const MAX_SUMMARY_CHARS: usize = 80;
+
+/// Input fields that best describe a tool call, in order of preference
+const SUMMARY_FIELDS: &[&str] = &[
+    "command",
+    "file_path",
+    "pattern",
+    "path",
+    "url",
+    "query",
+    "description",
+];
+
+/// Watch `session_id` until the backend closes the connection
+pub async fn run_tail(
+    backend_url: &str,
+    auth_token: Option<&str>,
+    session_id: Uuid,
+    history: bool,
+) -> Result<()> {
+    let mut request = format!("{}/ws/client", backend_url)
+        .into_client_request()
+        .context("Invalid backend URL")?;
+    if let Some(token) = auth_token {
+        request.headers_mut().insert(
+            header::AUTHORIZATION,
+            format!("Bearer {}", token)
+                .parse()
+                .context("Invalid auth token")?,
+        );
+    }
+
+    let (stream, _) = connect_async(request)
+        .await
+        .context("Failed to connect to backend")?;
+    let (mut write, mut read) = stream.split();
+
+    // Without --history, only output stored from now on is replayed
+    let replay_after = (!history).then(|| {
+        chrono::Utc::now()
+            .naive_utc()
+            .format("%Y-%m-%dT%H:%M:%S%.f")
+            .to_string()
+    });
+    let register = ProxyMessage::Register {
+        session_id,
+        session_name: session_id.to_string(),
+        auth_token: None,
+        working_directory: String::new(),
+        resuming: true,
+        git_branch: None,
+        git_repo: None,
+        host_info: None,
+        replay_after,
+        last_seq: None,
+        client_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+        accept_encodings: Vec::new(),
+        protocol_version: Some(protocol::PROTOCOL_VERSION),
+    };
+    write
+        .send(Message::Text(serde_json::to_string(&register)?))
+        .await
+        .context("Failed to subscribe to session")?;
+
+    ui::print_tail_started(&session_id.to_string());
+
+    while let Some(msg) = read.next().await {
+        let text = match msg.context("Connection to backend lost")? {
+            Message::Text(text) => text,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        let Ok(msg) = serde_json::from_str::<ProxyMessage>(&text) else {
+            continue;
+        };
+        match msg {
+            ProxyMessage::ClaudeOutput { content, .. } => {
+                for line in render_output(&content) {
+                    println!("{}", line);
+                }
+            }
+            ProxyMessage::PermissionRequest {
+                tool_name, input, ..
+            } => {
+                println!(
+                    "{} {}",
+                    "? permission requested:".bright_magenta().bold(),
+                    tool_call(&tool_name, &input)
+                );
+            }
+            ProxyMessage::PermissionResolved { .. } => {
+                println!("{}", "  permission answered".dimmed());
+            }
+            ProxyMessage::Hello {
+                error: Some(error), ..
+            } => {
+                return Err(anyhow!(error));
+            }
+            ProxyMessage::Error { code, message } => match code {
+                ErrorCode::AccessDenied | ErrorCode::SessionNotFound => {
+                    return Err(anyhow!(message));
+                }
+                _ => println!("{} {}", "error:".bright_red().bold(), message),
+            },
+            ProxyMessage::MaintenanceBanner {
+                notice: Some(notice),
+            } => {
+                ui::print_maintenance_notice(&notice);
+            }
+            ProxyMessage::KillSwitch { state: Some(state) } => {
+                ui::print_kill_switch_engaged(&state);
+            }
+            _ => {}
+        }
+    }
+
+    ui::print_tail_ended();
+    Ok(())
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    let first_line = text.lines().next().unwrap_or("");
+    if first_line.chars().count() > max_chars || text.contains('\n') {
+        let cut: String = first_line.chars().take(max_chars).collect();
+        format!("{}…", cut)
+    } else {
+        first_line.to_string()
+    }
+}
+
+/// `Bash(cargo test)`, with the most telling argument of the call
+fn tool_call(name: &str, input: &Value) -> String {
+    let summary = SUMMARY_FIELDS
+        .iter()
+        .find_map(|field| input.get(*field).and_then(Value::as_str));
+    match summary {
+        Some(summary) => format!(
+            "{}({})",
+            name.bright_cyan(),
+            truncate(summary, MAX_SUMMARY_CHARS)
+        ),
+        None => name.bright_cyan().to_string(),
+    }
+}
+
+/// Text of a tool result, which is either a string or a list of text blocks
+fn result_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .filter_map(|b| b.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn render_tool_result(block: &Value) -> Vec<String> {
+    let text = result_text(block.get("content"));
+    let is_error = block
+        .get("is_error")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    let lines: Vec<&str> = text.lines().collect();
+    let mut out: Vec<String> = lines
+        .iter()
+        .take(MAX_RESULT_LINES)
+        .map(|line| {
+            let line = format!("  ⎿ {}", line);
+            if is_error {
+                line.red().to_string()
+            } else {
+                line.dimmed().to_string()
+            }
+        })
+        .collect();
+    if lines.len() > MAX_RESULT_LINES {
+        out.push(
+            format!("    … +{} lines", lines.len() - MAX_RESULT_LINES)
+                .dimmed()
+                .to_string(),
+        );
+    }
+    out
+}
+
+/// Terminal lines for one Claude output message
+fn render_output(content: &Value) -> Vec<String> {
+    let blocks = || {
+        content
+            .pointer("/message/content")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+    };
+
+    if CompactBoundary::of(content).is_some() {
+        return vec!["── context compacted ──".dimmed().to_string()];
+    }
+    if shared::compaction::summary_text(content).is_some() {
+        return vec!["(Claude continues from a summary of the conversation)"
+            .dimmed()
+            .italic()
+            .to_string()];
+    }
+
+    match content.get("type").and_then(Value::as_str) {
+        Some("assistant") => blocks()
+            .flat_map(|block| match block.get("type").and_then(Value::as_str) {
+                Some("text") => block
+                    .get("text")
+                    .and_then(Value::as_str)
+                    .map(|text| vec![text.to_string()])
+                    .unwrap_or_default(),
+                Some("tool_use") => vec![format!(
+                    "{} {}",
+                    "●".bright_cyan(),
+                    tool_call(
+                        block.get("name").and_then(Value::as_str).unwrap_or("tool"),
+                        block.get("input").unwrap_or(&Value::Null)
+                    )
+                )],
+                Some("thinking") => vec!["✻ thinking…".dimmed().italic().to_string()],
+                _ => Vec::new(),
+            })
+            .collect(),
+        Some("user") => {
+            if let Some(text) = content.pointer("/message/content").and_then(Value::as_str) {
+                return vec![format!("{} {}", ">".bright_green().bold(), text.bold())];
+            }
+            blocks()
+                .flat_map(|block| match block.get("type").and_then(Value::as_str) {
+                    Some("tool_result") => render_tool_result(block),
+                    Some("text") => block
+                        .get("text")
+                        .and_then(Value::as_str)
+                        .map(|text| vec![format!("{} {}", ">".bright_green().bold(), text.bold())])
+                        .unwrap_or_default(),
+                    _ => Vec::new(),
+                })
+                .collect()
+        }
+        Some("system") if content.get("subtype").and_then(Value::as_str) == Some("init") => {
+            let model = content
+                .get("model")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown model");
+            vec![format!("── session started ({}) ──", model)
+                .dimmed()
+                .to_string()]
+        }
+        Some("result") => {
+            let cost = content
+                .get("total_cost_usd")
+                .and_then(Value::as_f64)
+                .unwrap_or(0.0);
+            let seconds = content
+                .get("duration_ms")
+                .and_then(Value::as_f64)
+                .unwrap_or(0.0)
+                / 1000.0;
+            let failed = content
+                .get("is_error")
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+            let summary = format!("── turn done in {:.1}s, ${:.4} ──", seconds, cost);
+            vec![if failed {
+                summary.yellow().to_string()
+            } else {
+                summary.dimmed().to_string()
+            }]
+        }
+        Some("error") => vec![format!(
+            "{} {}",
+            "error:".bright_red().bold(),
+            content.get("message").and_then(Value::as_str).unwrap_or("")
+        )],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_assistant_text_and_tool_use() {
+        colored::control::set_override(false);
+        let lines = render_output(&json!({
+            "type": "assistant",
+            "message": {"content": [
+                {"type": "text", "text": "Running the tests."},
+                {"type": "tool_use", "name": "Bash", "input": {"command": "cargo test\ncargo fmt"}}
+            ]}
+        }));
+        assert_eq!(lines, vec!["Running the tests.", "● Bash(cargo test…)"]);
+    }
+
+    #[test]
+    fn test_tool_result_is_cut() {
+        colored::control::set_override(false);
+        let lines = render_output(&json!({
+            "type": "user",
+            "message": {"content": [
+                {"type": "tool_result", "content": "a\nb\nc\nd\ne"}
+            ]}
+        }));
+        assert_eq!(lines, vec!["  ⎿ a", "  ⎿ b", "  ⎿ c", "    … +2 lines"]);
+    }
+
+    #[test]
+    fn test_result_summary() {
+        colored::control::set_override(false);
+        let lines = render_output(&json!({
+            "type": "result",
+            "duration_ms": 2500,
+            "total_cost_usd": 0.0123
+        }));
+        assert_eq!(lines, vec!["── turn done in 2.5s, $0.0123 ──"]);
+    }
+}
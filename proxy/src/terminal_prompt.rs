@@ -0,0 +1,218 @@
+//! Answering permission requests in the proxy's terminal.
+//!
+//! Permission requests normally wait for someone in the web UI. When none
+//! answers within `--terminal-prompt-after` seconds, the proxy asks in its
+//! own terminal, where the user may well be sitting, and a `y` or `n` there
+//! answers the request like a web response would. Requests are asked about
+//! one at a time, oldest first; one answered from the web meanwhile is
+//! dropped from the queue. Off when stdin isn't a terminal, under `--tui`,
+//! and for daemon sessions.
+
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::time::Duration;
+
+use shared::permission_rules::rule_target;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use crate::ui;
+
+/// Seconds a request waits on the web UI before the terminal asks, unless
+/// configured
+pub const DEFAULT_AFTER_SECS: u64 = 60;
+
+/// Longest tool target shown in the prompt
+const MAX_TARGET_CHARS: usize = 120;
+
+/// A request waiting for its turn to be asked about
+struct Waiting {
+    request_id: String,
+    tool_name: String,
+    input: serde_json::Value,
+    due: Instant,
+}
+
+/// A decision typed at the terminal
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerminalAnswer {
+    pub request_id: String,
+    pub allow: bool,
+    pub input: serde_json::Value,
+}
+
+/// Requests waiting on the web UI, and the one being asked about
+pub struct TerminalPrompts {
+    after: Option<Duration>,
+    waiting: VecDeque<Waiting>,
+    asking: Option<Waiting>,
+    lines: Option<mpsc::UnboundedReceiver<String>>,
+}
+
+impl TerminalPrompts {
+    /// Ask after `after` has passed without a web answer; `None` never asks
+    pub fn new(after: Option<Duration>) -> Self {
+        Self {
+            after,
+            waiting: VecDeque::new(),
+            asking: None,
+            lines: after.map(|_| read_lines()),
+        }
+    }
+
+    /// Start the clock on a request sent to the web UI
+    pub fn track(&mut self, request_id: &str, tool_name: &str, input: &serde_json::Value) {
+        let Some(after) = self.after else {
+            return;
+        };
+        self.waiting.push_back(Waiting {
+            request_id: request_id.to_string(),
+            tool_name: tool_name.to_string(),
+            input: input.clone(),
+            due: Instant::now() + after,
+        });
+    }
+
+    /// Stop waiting on a request that was answered some other way
+    pub fn resolve(&mut self, request_id: &str) {
+        self.waiting.retain(|w| w.request_id != request_id);
+        if self
+            .asking
+            .as_ref()
+            .is_some_and(|a| a.request_id == request_id)
+        {
+            self.asking = None;
+            ui::print_terminal_prompt_answered_elsewhere();
+        }
+    }
+
+    /// The next decision typed at the terminal. Waits for the oldest request
+    /// to come due, asks about it, and reads answers until one is `y` or
+    /// `n`. Pending forever while nothing is waiting. Cancel-safe: the
+    /// question asked stays asked.
+    pub async fn next_answer(&mut self) -> TerminalAnswer {
+        loop {
+            if self.asking.is_none() {
+                let Some(due) = self.waiting.front().map(|w| w.due) else {
+                    return std::future::pending().await;
+                };
+                tokio::time::sleep_until(due).await;
+                let Some(request) = self.waiting.pop_front() else {
+                    continue;
+                };
+                ui::print_terminal_prompt(&request.tool_name, target(&request).as_deref());
+                self.asking = Some(request);
+            }
+
+            let Some(lines) = self.lines.as_mut() else {
+                return std::future::pending().await;
+            };
+            let Some(line) = lines.recv().await else {
+                // stdin closed; leave the request to the web UI
+                self.lines = None;
+                self.asking = None;
+                continue;
+            };
+            match parse_answer(&line) {
+                Some(allow) => {
+                    let request = self.asking.take().expect("asking");
+                    return TerminalAnswer {
+                        request_id: request.request_id,
+                        allow,
+                        input: request.input,
+                    };
+                }
+                None => ui::print_terminal_prompt_hint(),
+            }
+        }
+    }
+}
+
+/// What the request is for, e.g. the command for Bash
+fn target(request: &Waiting) -> Option<String> {
+    let target = rule_target(&request.tool_name, &request.input)?;
+    let first_line = target.lines().next().unwrap_or_default();
+    Some(match first_line.char_indices().nth(MAX_TARGET_CHARS) {
+        Some((end, _)) => format!("{}…", &first_line[..end]),
+        None if first_line.len() < target.len() => format!("{}…", first_line),
+        None => first_line.to_string(),
+    })
+}
+
+fn parse_answer(line: &str) -> Option<bool> {
+    match line.trim().to_ascii_lowercase().as_str() {
+        "y" | "yes" => Some(true),
+        "n" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// Lines typed at the terminal, read on a thread of their own since stdin
+/// can't be read without blocking
+fn read_lines() -> mpsc::UnboundedReceiver<String> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn waiting(tool_name: &str, input: serde_json::Value) -> Waiting {
+        Waiting {
+            request_id: "r1".to_string(),
+            tool_name: tool_name.to_string(),
+            input,
+            due: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn test_parse_answer() {
+        assert_eq!(parse_answer("y"), Some(true));
+        assert_eq!(parse_answer(" YES\n"), Some(true));
+        assert_eq!(parse_answer("n"), Some(false));
+        assert_eq!(parse_answer(""), None);
+        assert_eq!(parse_answer("maybe"), None);
+    }
+
+    #[test]
+    fn test_target() {
+        let bash = waiting("Bash", json!({"command": "cargo test\ncargo build"}));
+        assert_eq!(target(&bash).as_deref(), Some("cargo test…"));
+        let edit = waiting("Edit", json!({"file_path": "src/main.rs"}));
+        assert_eq!(target(&edit).as_deref(), Some("src/main.rs"));
+        assert_eq!(target(&waiting("Task", json!({}))), None);
+    }
+
+    #[tokio::test]
+    async fn test_resolved_requests_are_not_asked() {
+        let mut prompts = TerminalPrompts {
+            after: Some(Duration::ZERO),
+            waiting: VecDeque::new(),
+            asking: None,
+            lines: None,
+        };
+        prompts.track("r1", "Bash", &json!({"command": "ls"}));
+        prompts.track("r2", "Bash", &json!({"command": "pwd"}));
+        prompts.resolve("r1");
+        assert_eq!(prompts.waiting.len(), 1);
+
+        // Nothing to read answers from, so the question stays open
+        let asked = tokio::time::timeout(Duration::from_millis(50), prompts.next_answer()).await;
+        assert!(asked.is_err());
+        assert_eq!(
+            prompts.asking.as_ref().map(|a| a.request_id.as_str()),
+            Some("r2")
+        );
+    }
+}
@@ -0,0 +1,295 @@
+//! `--tui`: a live status screen in place of the scrolling log.
+//!
+//! Shows each session's connection and state, permission prompts waiting
+//! on the web UI, recent messages, and throughput, from [`status`]. While
+//! it's up, the usual terminal messages and log lines are held back (logs
+//! still reach incident reports). `q` or Ctrl+C stops the proxy, as Ctrl+C
+//! would without the screen.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::status::{self, Connection, Direction, Status, Traffic};
+use crate::ui;
+
+/// How often the screen is redrawn, and keys checked for
+const TICK: Duration = Duration::from_millis(250);
+
+/// How long throughput is averaged over
+const RATE_WINDOW: Duration = Duration::from_secs(2);
+
+/// The status screen, up until dropped
+pub struct Screen {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Take over the terminal and show the status screen
+pub fn start(backend_url: &str) -> Result<Screen> {
+    let terminal = ratatui::try_init().context("Failed to start the status screen")?;
+    ui::set_quiet(true);
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread = std::thread::spawn({
+        let stop = stop.clone();
+        let backend_url = backend_url.to_string();
+        move || run(terminal, &backend_url, &stop)
+    });
+    Ok(Screen {
+        stop,
+        thread: Some(thread),
+    })
+}
+
+fn run(mut terminal: DefaultTerminal, backend_url: &str, stop: &AtomicBool) {
+    let mut rates = Rates::new(Instant::now());
+    while !stop.load(Ordering::Relaxed) {
+        let status = status::snapshot();
+        let now = Instant::now();
+        rates.update(now, status.traffic);
+        if let Err(e) = terminal.draw(|frame| draw(frame, backend_url, &status, &rates, now)) {
+            tracing::warn!("Status screen failed to draw: {}", e);
+            break;
+        }
+
+        if event::poll(TICK).unwrap_or(false) {
+            if let Ok(Event::Key(key)) = event::read() {
+                let ctrl_c =
+                    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if key.kind == KeyEventKind::Press && (key.code == KeyCode::Char('q') || ctrl_c) {
+                    ratatui::restore();
+                    ui::set_quiet(false);
+                    std::process::exit(130);
+                }
+            }
+        }
+    }
+    ratatui::restore();
+    ui::set_quiet(false);
+}
+
+/// Per-second throughput, averaged over about [`RATE_WINDOW`]
+struct Rates {
+    since: (Instant, Traffic),
+    /// (messages sent, bytes sent, messages received, bytes received)
+    current: (f64, f64, f64, f64),
+}
+
+impl Rates {
+    fn new(now: Instant) -> Self {
+        Self {
+            since: (now, Traffic::default()),
+            current: (0.0, 0.0, 0.0, 0.0),
+        }
+    }
+
+    fn update(&mut self, now: Instant, traffic: Traffic) {
+        let elapsed = now.duration_since(self.since.0);
+        if elapsed >= RATE_WINDOW {
+            self.current = traffic.rates_since(&self.since.1, elapsed.as_secs_f64());
+            self.since = (now, traffic);
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, backend_url: &str, status: &Status, rates: &Rates, now: Instant) {
+    let pending: usize = status
+        .sessions
+        .values()
+        .map(|session| session.pending_permissions.len())
+        .sum();
+    let [header, sessions, permissions, recent, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(status.sessions.len().max(1) as u16 + 3),
+        Constraint::Length(pending.max(1) as u16 + 2),
+        Constraint::Min(3),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    frame.render_widget(
+        Line::from(vec![
+            Span::styled(" Claude Code Portal ", Style::new().bold().fg(Color::Blue)),
+            Span::raw(backend_url).dim(),
+        ]),
+        header,
+    );
+
+    let rows = status.sessions.values().map(|session| {
+        let (label, color) = connection_label(&session.connection, now);
+        Row::new(vec![
+            Span::raw(session.name.clone()),
+            Span::raw(session.working_directory.clone()).dim(),
+            Span::styled(label, Style::new().fg(color)),
+            Span::raw(session.state.replace('_', " ")),
+        ])
+    });
+    frame.render_widget(
+        Table::new(
+            rows,
+            [
+                Constraint::Percentage(25),
+                Constraint::Percentage(40),
+                Constraint::Percentage(15),
+                Constraint::Percentage(20),
+            ],
+        )
+        .header(
+            Row::new(["Session", "Directory", "Backend", "State"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().title(" Sessions ")),
+        sessions,
+    );
+
+    let prompts: Vec<ListItem> = status
+        .sessions
+        .values()
+        .flat_map(|session| {
+            session.pending_permissions.iter().map(|permission| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(permission.tool_name.clone(), Style::new().fg(Color::Yellow)),
+                    Span::raw(format!(" in {}", session.name)).dim(),
+                ]))
+            })
+        })
+        .collect();
+    let prompts = if prompts.is_empty() {
+        vec![ListItem::new(Line::from("None").dim())]
+    } else {
+        prompts
+    };
+    frame.render_widget(
+        List::new(prompts).block(Block::bordered().title(" Waiting for permission ")),
+        permissions,
+    );
+
+    // Newest at the bottom, showing as many as fit
+    let room = recent.height.saturating_sub(2) as usize;
+    let messages: Vec<ListItem> = status
+        .recent
+        .iter()
+        .skip(status.recent.len().saturating_sub(room))
+        .map(|message| {
+            let name = status
+                .sessions
+                .get(&message.session_id)
+                .map_or("ended", |session| session.name.as_str());
+            let arrow = match message.direction {
+                Direction::Input => Span::styled("→ ", Style::new().fg(Color::Cyan)),
+                Direction::Output => Span::styled("← ", Style::new().fg(Color::Green)),
+            };
+            ListItem::new(Line::from(vec![
+                Span::raw(message.at.format("%H:%M:%S ").to_string()).dim(),
+                Span::raw(format!("{} ", name)).dim(),
+                arrow,
+                Span::raw(message.summary.clone()),
+            ]))
+        })
+        .collect();
+    frame.render_widget(
+        List::new(messages).block(Block::bordered().title(" Recent messages ")),
+        recent,
+    );
+
+    let (msgs_out, bytes_out, msgs_in, bytes_in) = rates.current;
+    frame.render_widget(
+        Paragraph::new(Line::from(vec![
+            Span::raw(format!(
+                " ↑ {:.1} msg/s {}/s  ↓ {:.1} msg/s {}/s",
+                msgs_out,
+                format_bytes(bytes_out),
+                msgs_in,
+                format_bytes(bytes_in)
+            )),
+            Span::raw("   q to stop").dim(),
+        ])),
+        footer,
+    );
+}
+
+/// What to show for a connection, and in which color
+fn connection_label(connection: &Connection, now: Instant) -> (String, Color) {
+    match connection {
+        Connection::Connecting => ("connecting".to_string(), Color::Yellow),
+        Connection::Connected => ("connected".to_string(), Color::Green),
+        Connection::Disconnected { retry_at } => {
+            let secs = retry_at.saturating_duration_since(now).as_secs();
+            (format!("retry in {}s", secs), Color::Red)
+        }
+    }
+}
+
+/// A byte count for humans, e.g. "1.5 KB"
+fn format_bytes(bytes: f64) -> String {
+    if bytes < 1024.0 {
+        format!("{:.0} B", bytes)
+    } else if bytes < 1024.0 * 1024.0 {
+        format!("{:.1} KB", bytes / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes / (1024.0 * 1024.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0.0), "0 B");
+        assert_eq!(format_bytes(1536.0), "1.5 KB");
+        assert_eq!(format_bytes(3.0 * 1024.0 * 1024.0), "3.0 MB");
+    }
+
+    #[test]
+    fn test_rates_average_over_window() {
+        let start = Instant::now();
+        let mut rates = Rates::new(start);
+        let traffic = Traffic {
+            messages_sent: 4,
+            bytes_sent: 4096,
+            ..Traffic::default()
+        };
+
+        // Too soon to say
+        rates.update(start + Duration::from_millis(500), traffic);
+        assert_eq!(rates.current, (0.0, 0.0, 0.0, 0.0));
+
+        rates.update(start + RATE_WINDOW, traffic);
+        assert_eq!(rates.current, (2.0, 2048.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_connection_label() {
+        let now = Instant::now();
+        let retry_at = now + Duration::from_secs(4);
+        assert_eq!(
+            connection_label(&Connection::Disconnected { retry_at }, now).0,
+            "retry in 4s"
+        );
+        assert_eq!(
+            connection_label(&Connection::Connected, now),
+            ("connected".to_string(), Color::Green)
+        );
+    }
+}
@@ -2,31 +2,62 @@
 
 use colored::Colorize;
 use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set while the `--tui` status screen owns the terminal, which these
+/// messages would otherwise draw over
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// `println!`, unless the status screen is up
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if !is_quiet() {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// `print!`, unless the status screen is up
+macro_rules! say_inline {
+    ($($arg:tt)*) => {
+        if !is_quiet() {
+            print!($($arg)*);
+        }
+    };
+}
 
 /// Print the startup banner
 pub fn print_startup_banner() {
-    println!();
-    println!(
+    say!();
+    say!(
         "{}",
         "╭──────────────────────────────────────╮".bright_blue()
     );
-    println!(
+    say!(
         "{}",
         "│      Claude Code Portal Starting     │".bright_blue()
     );
-    println!(
+    say!(
         "{}",
         "╰──────────────────────────────────────╯".bright_blue()
     );
-    println!();
+    say!();
 }
 
 /// Print session information
 pub fn print_session_info(session_name: &str, session_id: &str, backend_url: &str, resuming: bool) {
-    println!("  {} {}", "Session:".dimmed(), session_name.bright_white());
-    println!("  {} {}", "ID:".dimmed(), session_id[..8].bright_cyan());
-    println!("  {} {}", "Backend:".dimmed(), backend_url.bright_white());
-    println!(
+    say!("  {} {}", "Session:".dimmed(), session_name.bright_white());
+    say!("  {} {}", "ID:".dimmed(), session_id[..8].bright_cyan());
+    say!("  {} {}", "Backend:".dimmed(), backend_url.bright_white());
+    say!(
         "  {} {}",
         "Mode:".dimmed(),
         if resuming {
@@ -35,49 +66,49 @@ pub fn print_session_info(session_name: &str, session_id: &str, backend_url: &st
             "new".bright_green()
         }
     );
-    println!();
+    say!();
 }
 
 /// Print the "proxy ready" banner
 pub fn print_ready_banner() {
-    println!();
-    println!(
+    say!();
+    say!(
         "{}",
         "╭──────────────────────────────────────╮".bright_green()
     );
-    println!(
+    say!(
         "{}",
         "│         ✓ Proxy Ready                │".bright_green()
     );
-    println!(
+    say!(
         "{}",
         "╰──────────────────────────────────────╯".bright_green()
     );
-    println!();
-    println!("  Session is now visible in the web interface.");
-    println!("  Press {} to stop.", "Ctrl+C".bright_yellow());
-    println!();
+    say!();
+    say!("  Session is now visible in the web interface.");
+    say!("  Press {} to stop.", "Ctrl+C".bright_yellow());
+    say!();
 }
 
 /// Print dev mode status
 pub fn print_dev_mode() {
-    println!(
+    say!(
         "  {} {}",
         "Mode:".dimmed(),
         "development (no auth)".bright_yellow()
     );
-    println!();
+    say!();
 }
 
 /// Print authenticated user
 pub fn print_user(email: &str) {
-    println!("  {} {}", "User:".dimmed(), email.bright_white());
-    println!();
+    say!("  {} {}", "User:".dimmed(), email.bright_white());
+    say!();
 }
 
 /// Print "new session" status message
 pub fn print_new_session_forced() {
-    println!(
+    say!(
         "  {} {} Starting new session (--new-session flag)",
         "⚠".bright_yellow(),
         "WARNING:".bright_yellow()
@@ -86,15 +117,24 @@ pub fn print_new_session_forced() {
 
 /// Print "no previous session" message
 pub fn print_no_previous_session() {
-    println!(
+    say!(
         "  {} No previous session found, starting fresh",
         "→".bright_blue()
     );
 }
 
+/// Print "forking session" message
+pub fn print_forking(source_session_name: &str) {
+    say!(
+        "  {} Forking {}, replaying its conversation",
+        "→".bright_blue(),
+        source_session_name.bright_white()
+    );
+}
+
 /// Print "resuming session" message
 pub fn print_resuming_session(session_id: &str, created_at: &str) {
-    println!(
+    say!(
         "  {} Resuming session {} from {}",
         "→".bright_green(),
         session_id[..8].bright_cyan(),
@@ -102,62 +142,272 @@ pub fn print_resuming_session(session_id: &str, created_at: &str) {
     );
 }
 
+/// Print that an earlier Claude conversation is being continued
+pub fn print_resuming_conversation(session_id: &str) {
+    say!(
+        "  {} Resuming Claude conversation {}",
+        "→".bright_green(),
+        session_id[..8].bright_cyan()
+    );
+}
+
 /// Print a status line with spinner prefix
 pub fn print_status(message: &str) {
-    print!("  {} {} ", "→".bright_blue(), message);
+    say_inline!("  {} {} ", "→".bright_blue(), message);
     let _ = std::io::stdout().flush();
 }
 
 /// Print "connected" result
 pub fn print_connected() {
-    println!("{}", "connected".bright_green());
+    say!("{}", "connected".bright_green());
 }
 
 /// Print "registered" result
 pub fn print_registered() {
-    println!("{}", "registered".bright_green());
+    say!("{}", "registered".bright_green());
 }
 
 /// Print "started" result
 pub fn print_started() {
-    println!("{}", "started".bright_green());
+    say!("{}", "started".bright_green());
 }
 
 /// Print "failed" result
 pub fn print_failed() {
-    println!("{}", "failed".bright_red());
+    say!("{}", "failed".bright_red());
 }
 
 /// Print registration failure with error message
 pub fn print_registration_failed(error: &str) {
-    println!("{}", "failed".bright_red());
-    println!(
+    say!("{}", "failed".bright_red());
+    say!(
         "  {} Registration error: {}",
         "✗".bright_red(),
         error.bright_red()
     );
 }
 
+/// Print that the backend speaks a protocol this build can't use
+pub fn print_protocol_mismatch(error: &str) {
+    say!("{}", "failed".bright_red());
+    say!("  {} {}", "✗".bright_red(), error.bright_red());
+    say!(
+        "  {} Run: {} to get the latest proxy",
+        "→".bright_blue(),
+        "claude-portal --update".bright_cyan()
+    );
+}
+
 /// Print hint to re-authenticate
 pub fn print_reauth_hint() {
-    println!(
+    say!(
         "  {} Run: {} to re-authenticate",
         "→".bright_blue(),
         "claude-portal --reauth".bright_cyan()
     );
 }
 
+/// Print that registration will be retried once a session slot frees up
+pub fn print_quota_hint() {
+    say!(
+        "  {} Stop another session and this one will connect on the next retry",
+        "→".bright_blue()
+    );
+}
+
 /// Print connection restored message
 pub fn print_connection_restored() {
-    println!("  {} Connection restored", "✓".bright_green());
-    println!();
+    say!("  {} Connection restored", "✓".bright_green());
+    say!();
+}
+
+/// Print the header for `claude-portal tail`
+pub fn print_tail_started(session_id: &str) {
+    say!(
+        "  {} Following session {} (Ctrl+C to stop)",
+        "→".bright_blue(),
+        session_id.bright_cyan()
+    );
+    say!();
+}
+
+/// Print that the backend closed a `claude-portal tail` connection
+pub fn print_tail_ended() {
+    say!();
+    say!("  {} Connection closed", "✗".bright_red());
+}
+
+/// Print a warning that round trips to the backend have become slow
+pub fn print_slow_connection(rtt_ms: u128) {
+    say!(
+        "  {} Slow connection to backend: {}ms round trip",
+        "⚠".bright_yellow(),
+        rtt_ms.to_string().bright_cyan()
+    );
+}
+
+/// Print that round trips to the backend are fast again
+pub fn print_connection_recovered(rtt_ms: u128) {
+    say!(
+        "  {} Connection back to normal: {}ms round trip",
+        "✓".bright_green(),
+        rtt_ms
+    );
+}
+
+/// Print that the backend stopped answering heartbeats
+pub fn print_heartbeat_timeout() {
+    say!();
+    say!(
+        "  {} Backend stopped responding to heartbeats",
+        "⚠".bright_yellow()
+    );
+}
+
+/// Print a permission rule remembered from the web UI
+pub fn print_permission_rule_added(rule: &str, allow: bool, persistent: bool) {
+    say!(
+        "  {} Will {} {} {}",
+        "✓".bright_green(),
+        if allow { "allow" } else { "deny" },
+        rule.bright_cyan(),
+        if persistent {
+            "in this directory from now on"
+        } else {
+            "for the rest of this session"
+        }
+    );
+}
+
+/// Print a permission request answered by a remembered rule
+pub fn print_permission_rule_applied(rule: &str, allow: bool) {
+    let verb = if allow {
+        "Allowed".bright_green()
+    } else {
+        "Denied".bright_red()
+    };
+    say!("  {} {} by remembered rule", verb, rule.bright_cyan());
+}
+
+/// Ask at the terminal about a permission request nobody answered on the web
+pub fn print_terminal_prompt(tool_name: &str, target: Option<&str>) {
+    say!();
+    match target {
+        Some(target) => say!(
+            "  {} Claude wants to use {}: {}",
+            "?".bright_yellow(),
+            tool_name.bright_cyan(),
+            target
+        ),
+        None => say!(
+            "  {} Claude wants to use {}",
+            "?".bright_yellow(),
+            tool_name.bright_cyan()
+        ),
+    }
+    say_inline!("  Nobody has answered in the web UI. Allow? [y/n] ");
+    let _ = std::io::stdout().flush();
+}
+
+/// Print how to answer the terminal prompt, after something else was typed
+pub fn print_terminal_prompt_hint() {
+    say_inline!("  Type y to allow or n to deny: ");
+    let _ = std::io::stdout().flush();
+}
+
+/// Print that the request being asked about was answered on the web
+pub fn print_terminal_prompt_answered_elsewhere() {
+    say!();
+    say!("  {} Answered in the web UI", "→".bright_blue());
+}
+
+/// Print a permission request answered at the terminal
+pub fn print_terminal_answer(allow: bool) {
+    let verb = if allow {
+        "Allowed".bright_green()
+    } else {
+        "Denied".bright_red()
+    };
+    say!("  {} from the terminal", verb);
+}
+
+/// Print a maintenance banner posted by the portal's operators
+pub fn print_maintenance_notice(notice: &shared::MaintenanceNotice) {
+    let local = |t: chrono::DateTime<chrono::Utc>| {
+        t.with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string()
+    };
+    say!();
+    say!("  {} {}", "Maintenance:".bright_yellow(), notice.message);
+    match (notice.starts_at, notice.ends_at) {
+        (Some(starts), Some(ends)) => say!(
+            "  {} Downtime {} to {}",
+            "→".bright_blue(),
+            local(starts).bright_cyan(),
+            local(ends).bright_cyan()
+        ),
+        (Some(starts), None) => say!(
+            "  {} Downtime from {}",
+            "→".bright_blue(),
+            local(starts).bright_cyan()
+        ),
+        (None, Some(ends)) => say!(
+            "  {} Expected back by {}",
+            "→".bright_blue(),
+            local(ends).bright_cyan()
+        ),
+        (None, None) => {}
+    }
+}
+
+/// Print that the maintenance banner was taken down
+pub fn print_maintenance_cleared() {
+    say!("  {} Maintenance notice cleared", "✓".bright_green());
+}
+
+/// Print that an admin engaged the kill switch
+pub fn print_kill_switch_engaged(kill_switch: &shared::KillSwitch) {
+    say!();
+    say!(
+        "  {} {}",
+        "All sessions paused:".bright_red().bold(),
+        kill_switch.reason
+    );
+    say!(
+        "  {} Input is held and permission requests are denied until {} resumes them",
+        "→".bright_blue(),
+        kill_switch.engaged_by.bright_cyan()
+    );
+}
+
+/// Print that a permission request was denied because sessions are paused
+pub fn print_kill_switch_denied(tool_name: &str) {
+    say!(
+        "  {} {} while sessions are paused",
+        "Denied".bright_red(),
+        tool_name.bright_cyan()
+    );
+}
+
+/// Print that the kill switch was released
+pub fn print_kill_switch_released(held_inputs: usize) {
+    say!(
+        "  {} Sessions resumed{}",
+        "✓".bright_green(),
+        match held_inputs {
+            0 => String::new(),
+            1 => ", sending 1 held input".to_string(),
+            n => format!(", sending {} held inputs", n),
+        }
+    );
 }
 
 /// Print disconnection message with backoff
 #[allow(dead_code)]
 pub fn print_disconnected(backoff_secs: u64) {
-    println!();
-    println!(
+    say!();
+    say!(
         "  {} WebSocket disconnected. Reconnecting in {}s...",
         "⚠".bright_yellow(),
         backoff_secs
@@ -166,20 +416,20 @@ pub fn print_disconnected(backoff_secs: u64) {
 
 /// Print disconnection message with backoff and pending message count
 pub fn print_disconnected_with_pending(backoff_secs: u64, pending_count: usize) {
-    println!();
+    say!();
     if pending_count > 0 {
-        println!(
+        say!(
             "  {} WebSocket disconnected. {} pending messages buffered.",
             "⚠".bright_yellow(),
             pending_count.to_string().bright_cyan()
         );
-        println!(
+        say!(
             "  {} Reconnecting in {}s...",
             "→".bright_blue(),
             backoff_secs
         );
     } else {
-        println!(
+        say!(
             "  {} WebSocket disconnected. Reconnecting in {}s...",
             "⚠".bright_yellow(),
             backoff_secs
@@ -189,17 +439,17 @@ pub fn print_disconnected_with_pending(backoff_secs: u64, pending_count: usize)
 
 /// Print logout success
 pub fn print_logout_success(email: &str) {
-    println!("{} Logged out from {}", "✓".bright_green(), email);
+    say!("{} Logged out from {}", "✓".bright_green(), email);
 }
 
 /// Print no cached auth message
 pub fn print_no_cached_auth() {
-    println!("No cached authentication found for this directory");
+    say!("No cached authentication found for this directory");
 }
 
 /// Print init success
 pub fn print_init_start(email: &str) {
-    println!(
+    say!(
         "{} Initializing proxy with token for {}",
         "→".bright_blue(),
         email
@@ -208,58 +458,92 @@ pub fn print_init_start(email: &str) {
 
 /// Print init complete
 pub fn print_init_complete(email: &str, backend_url: &str) {
-    println!("{} Configuration saved for {}", "✓".bright_green(), email);
-    println!("  Backend: {}", backend_url);
-    println!();
-    println!(
+    say!("{} Configuration saved for {}", "✓".bright_green(), email);
+    say!("  Backend: {}", backend_url);
+    say!();
+    say!(
         "You can now run {} without arguments.",
         "claude-portal".bright_cyan()
     );
 }
 
+/// Print where --init wrote a settings file to edit
+pub fn print_settings_written(path: &std::path::Path) {
+    say!(
+        "Defaults for every directory can go in {}",
+        path.display().to_string().bright_cyan()
+    );
+}
+
+/// Print that the daemon is up and how to add sessions to it
+pub fn print_daemon_started(backend_url: &str, socket: &std::path::Path) {
+    say!(
+        "{} Daemon running for {}",
+        "✓".bright_green(),
+        backend_url.bright_cyan()
+    );
+    say!("  Control socket: {}", socket.display());
+    say!(
+        "Add a session by running {} in its directory.",
+        "claude-portal attach <name>".bright_cyan()
+    );
+}
+
+/// Print that the daemon took on this directory's session
+pub fn print_attached(name: &str, session_id: &str, resumed: bool) {
+    let verb = if resumed { "Resumed" } else { "Started" };
+    say!(
+        "{} {} {} in the daemon ({})",
+        "✓".bright_green(),
+        verb,
+        name.bright_cyan(),
+        session_id.dimmed()
+    );
+}
+
 /// Print session not found message (when resuming a session that doesn't exist locally)
 pub fn print_session_not_found(session_id: &str) {
-    println!();
-    println!(
+    say!();
+    say!(
         "  {} Previous session {} not found locally",
         "⚠".bright_yellow(),
         session_id[..8].bright_cyan()
     );
-    println!(
+    say!(
         "  {} Starting a fresh session instead...",
         "→".bright_blue()
     );
-    println!();
+    say!();
 }
 
 /// Print update complete message
 pub fn print_update_complete() {
-    println!();
-    println!(
+    say!();
+    say!(
         "{}",
         "╭──────────────────────────────────────╮".bright_green()
     );
-    println!(
+    say!(
         "{}",
         "│         ✓ Update Installed           │".bright_green()
     );
-    println!(
+    say!(
         "{}",
         "╰──────────────────────────────────────╯".bright_green()
     );
-    println!();
-    println!(
+    say!();
+    say!(
         "  A new version of {} has been installed.",
         "claude-portal".bright_cyan()
     );
-    println!("  Please run the command again to use the updated version.");
-    println!();
+    say!("  Please run the command again to use the updated version.");
+    say!();
 }
 
 /// Print checking for updates message
 pub fn print_checking_for_updates() {
-    println!();
-    println!(
+    say!();
+    say!(
         "  {} Checking for updates from GitHub...",
         "→".bright_blue()
     );
@@ -267,58 +551,58 @@ pub fn print_checking_for_updates() {
 
 /// Print up to date message
 pub fn print_up_to_date() {
-    println!(
+    say!(
         "  {} {} is up to date.",
         "✓".bright_green(),
         "claude-portal".bright_cyan()
     );
-    println!();
+    say!();
 }
 
 /// Print update available message
 pub fn print_update_available(version: &str, download_url: &str) {
-    println!();
-    println!(
+    say!();
+    say!(
         "{}",
         "╭──────────────────────────────────────╮".bright_yellow()
     );
-    println!(
+    say!(
         "{}",
         "│         Update Available             │".bright_yellow()
     );
-    println!(
+    say!(
         "{}",
         "╰──────────────────────────────────────╯".bright_yellow()
     );
-    println!();
-    println!("  {} {}", "Version:".dimmed(), version.bright_white());
-    println!();
-    println!("  To update, run:");
-    println!(
+    say!();
+    say!("  {} {}", "Version:".dimmed(), version.bright_white());
+    say!();
+    say!("  To update, run:");
+    say!(
         "    {} {}",
         "$".dimmed(),
         "claude-portal --update".bright_cyan()
     );
-    println!();
-    println!("  Or download manually from:");
-    println!("    {}", download_url.bright_blue());
-    println!();
+    say!();
+    say!("  Or download manually from:");
+    say!("    {}", download_url.bright_blue());
+    say!();
 }
 
 /// Print update check failed message
 pub fn print_update_check_failed(error: &str) {
-    println!(
+    say!(
         "  {} Failed to check for updates: {}",
         "✗".bright_red(),
         error
     );
-    println!();
+    say!();
 }
 
 /// Print updating from GitHub message
 pub fn print_updating_from_github() {
-    println!();
-    println!(
+    say!();
+    say!(
         "  {} Downloading latest version from GitHub...",
         "→".bright_blue()
     );
@@ -326,15 +610,15 @@ pub fn print_updating_from_github() {
 
 /// Print update failed message
 pub fn print_update_failed(error: &str) {
-    println!();
-    println!("  {} Update failed: {}", "✗".bright_red(), error);
-    println!();
+    say!();
+    say!("  {} Update failed: {}", "✗".bright_red(), error);
+    say!();
 }
 
 /// Print pending update applied message (Windows)
 pub fn print_pending_update_applied() {
-    println!();
-    println!(
+    say!();
+    say!(
         "  {} Pending update applied successfully.",
         "✓".bright_green()
     );
@@ -0,0 +1,224 @@
+//! Workspace activity feed
+//!
+//! The backend publishes notable session events: sessions starting and
+//! ending, permission requests and their escalation, errors, and budget
+//! breaches. Each goes to the configured webhooks and is stored, and the
+//! stored events are listed newest first at `/api/activity` for a review of
+//! everything that happened across the sessions the caller can see.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// What happened to a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    SessionStarted,
+    SessionEnded,
+    PermissionRequested,
+    /// A permission request nobody answered was passed on to another
+    /// member, or answered with the session's default
+    PermissionEscalated,
+    Error,
+    BudgetExceeded,
+    /// The owner sent someone a guest link
+    GuestInvited,
+    /// A guest opened their link
+    GuestJoined,
+}
+
+impl EventKind {
+    pub const ALL: [EventKind; 8] = [
+        EventKind::SessionStarted,
+        EventKind::SessionEnded,
+        EventKind::PermissionRequested,
+        EventKind::PermissionEscalated,
+        EventKind::Error,
+        EventKind::BudgetExceeded,
+        EventKind::GuestInvited,
+        EventKind::GuestJoined,
+    ];
+
+    /// Name on the wire, e.g. "session_started"
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::SessionStarted => "session_started",
+            EventKind::SessionEnded => "session_ended",
+            EventKind::PermissionRequested => "permission_requested",
+            EventKind::PermissionEscalated => "permission_escalated",
+            EventKind::Error => "error",
+            EventKind::BudgetExceeded => "budget_exceeded",
+            EventKind::GuestInvited => "guest_invited",
+            EventKind::GuestJoined => "guest_joined",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    /// For filters in the UI
+    pub fn label(&self) -> &'static str {
+        match self {
+            EventKind::SessionStarted => "Session started",
+            EventKind::SessionEnded => "Session ended",
+            EventKind::PermissionRequested => "Permission requested",
+            EventKind::PermissionEscalated => "Permission escalated",
+            EventKind::Error => "Error",
+            EventKind::BudgetExceeded => "Budget exceeded",
+            EventKind::GuestInvited => "Guest invited",
+            EventKind::GuestJoined => "Guest joined",
+        }
+    }
+}
+
+/// A stored event, as listed by `/api/activity`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ActivityEvent {
+    pub id: Uuid,
+    pub kind: EventKind,
+    pub session_id: Uuid,
+    pub session_name: String,
+    /// Email of the session's owner
+    pub owner_email: Option<String>,
+    /// The event's details, as sent to webhooks
+    pub data: serde_json::Value,
+    pub created_at: String,
+}
+
+impl ActivityEvent {
+    /// One line describing the event, from its details
+    pub fn summary(&self) -> String {
+        let text = |key: &str| self.data.get(key).and_then(|v| v.as_str());
+        match self.kind {
+            EventKind::SessionStarted => {
+                let verb = if self.data.get("resuming").and_then(|v| v.as_bool()) == Some(true) {
+                    "Resumed"
+                } else {
+                    "Started"
+                };
+                match (text("working_directory"), text("git_branch")) {
+                    (Some(dir), Some(branch)) => format!("{} in {} on {}", verb, dir, branch),
+                    (Some(dir), None) => format!("{} in {}", verb, dir),
+                    _ => verb.to_string(),
+                }
+            }
+            EventKind::SessionEnded => "Proxy disconnected".to_string(),
+            EventKind::PermissionRequested => {
+                format!("Asked to use {}", text("tool_name").unwrap_or("a tool"))
+            }
+            EventKind::PermissionEscalated => {
+                let tool = text("tool_name").unwrap_or("a tool");
+                let minutes = self
+                    .data
+                    .get("waiting_minutes")
+                    .and_then(|v| v.as_i64())
+                    .unwrap_or(0);
+                match (text("notified"), text("default")) {
+                    (Some(email), _) => {
+                        format!("{} waited {} minutes; notified {}", tool, minutes, email)
+                    }
+                    (None, Some(default)) => format!(
+                        "{} waited {} minutes; applied the default ({})",
+                        tool, minutes, default
+                    ),
+                    _ => format!("{} waited {} minutes", tool, minutes),
+                }
+            }
+            EventKind::Error => {
+                let message = text("message").unwrap_or("Unknown error");
+                match text("source") {
+                    Some("proxy") => format!("Proxy reported: {}", message),
+                    _ => message.to_string(),
+                }
+            }
+            EventKind::BudgetExceeded => text("summary")
+                .unwrap_or("Went over a budget limit")
+                .to_string(),
+            EventKind::GuestInvited => {
+                let guest = text("guest").unwrap_or("a guest");
+                let access = match text("access") {
+                    Some("approver") => "to approve",
+                    _ => "to watch",
+                };
+                match (text("invited_by"), text("duration")) {
+                    (Some(by), Some(duration)) => {
+                        format!("{} invited {} {} for {}", by, guest, access, duration)
+                    }
+                    _ => format!("Invited {} {}", guest, access),
+                }
+            }
+            EventKind::GuestJoined => {
+                format!("{} joined as a guest", text("guest").unwrap_or("A guest"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(kind: EventKind, data: serde_json::Value) -> ActivityEvent {
+        ActivityEvent {
+            id: Uuid::nil(),
+            kind,
+            session_id: Uuid::nil(),
+            session_name: "api".to_string(),
+            owner_email: None,
+            data,
+            created_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_kind_names_round_trip() {
+        for kind in EventKind::ALL {
+            assert_eq!(EventKind::parse(kind.name()), Some(kind));
+            assert_eq!(
+                serde_json::to_value(kind).unwrap(),
+                json!(kind.name()),
+                "serde and name() agree"
+            );
+        }
+        assert_eq!(EventKind::parse("nope"), None);
+    }
+
+    #[test]
+    fn test_summaries() {
+        let started = event(
+            EventKind::SessionStarted,
+            json!({ "working_directory": "/src/api", "git_branch": "main", "resuming": true }),
+        );
+        assert_eq!(started.summary(), "Resumed in /src/api on main");
+
+        let escalated = event(
+            EventKind::PermissionEscalated,
+            json!({ "tool_name": "Bash", "waiting_minutes": 15, "default": "deny" }),
+        );
+        assert_eq!(
+            escalated.summary(),
+            "Bash waited 15 minutes; applied the default (deny)"
+        );
+
+        let error = event(
+            EventKind::Error,
+            json!({ "source": "proxy", "message": "Claude exited" }),
+        );
+        assert_eq!(error.summary(), "Proxy reported: Claude exited");
+        assert_eq!(
+            event(EventKind::BudgetExceeded, json!({})).summary(),
+            "Went over a budget limit"
+        );
+
+        let invited = event(
+            EventKind::GuestInvited,
+            json!({ "guest": "Sam", "access": "approver", "invited_by": "ana@example.com", "duration": "1 hour" }),
+        );
+        assert_eq!(
+            invited.summary(),
+            "ana@example.com invited Sam to approve for 1 hour"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Annotations attached to a session's messages by external tools
+//!
+//! CI systems, scanners and the like post an annotation against a range of
+//! message `seq`s ("this change failed CI"); the web UI shows it as a badge
+//! on those messages.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Longest accepted `source`
+pub const MAX_ANNOTATION_SOURCE_LEN: usize = 64;
+
+/// Longest accepted `label`
+pub const MAX_ANNOTATION_LABEL_LEN: usize = 200;
+
+/// Longest accepted `url`
+pub const MAX_ANNOTATION_URL_LEN: usize = 2048;
+
+/// Largest accepted `data`, serialized
+pub const MAX_ANNOTATION_DATA_BYTES: usize = 16 * 1024;
+
+/// How an annotation is shown
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnotationLevel {
+    #[default]
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl AnnotationLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Success => "success",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "info" => Some(Self::Info),
+            "success" => Some(Self::Success),
+            "warning" => Some(Self::Warning),
+            "error" => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+
+/// Body of `POST /api/sessions/:id/annotations`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateAnnotationRequest {
+    /// What posted the annotation, e.g. "github-actions"
+    pub source: String,
+    /// First message `seq` the annotation applies to
+    pub start_seq: i64,
+    /// Last message `seq` it applies to; just `start_seq` if omitted
+    #[serde(default)]
+    pub end_seq: Option<i64>,
+    #[serde(default)]
+    pub level: AnnotationLevel,
+    /// Short text shown on the badge
+    pub label: String,
+    /// Where the badge links to, e.g. the failed CI run
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Anything else the tool wants to keep with the annotation
+    #[serde(default)]
+    pub data: Option<serde_json::Value>,
+}
+
+impl CreateAnnotationRequest {
+    /// Check the request, returning why it's rejected
+    pub fn validate(&self) -> Result<(), &'static str> {
+        let source = self.source.trim();
+        if source.is_empty() || source.len() > MAX_ANNOTATION_SOURCE_LEN {
+            return Err("source must be 1 to 64 characters");
+        }
+        let label = self.label.trim();
+        if label.is_empty() || label.len() > MAX_ANNOTATION_LABEL_LEN {
+            return Err("label must be 1 to 200 characters");
+        }
+        if self.start_seq < 0 || self.end_seq() < self.start_seq {
+            return Err("seq range is invalid");
+        }
+        if let Some(url) = &self.url {
+            if !(url.starts_with("https://") || url.starts_with("http://"))
+                || url.len() > MAX_ANNOTATION_URL_LEN
+            {
+                return Err("url must be an http(s) URL");
+            }
+        }
+        if let Some(data) = &self.data {
+            if data.to_string().len() > MAX_ANNOTATION_DATA_BYTES {
+                return Err("data is too large");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn end_seq(&self) -> i64 {
+        self.end_seq.unwrap_or(self.start_seq)
+    }
+}
+
+/// An annotation as returned by the API and broadcast to viewers
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AnnotationInfo {
+    pub id: Uuid,
+    pub source: String,
+    pub level: AnnotationLevel,
+    pub label: String,
+    pub url: Option<String>,
+    pub data: Option<serde_json::Value>,
+    pub start_seq: i64,
+    pub end_seq: i64,
+    /// Stored messages in the range when the annotation was loaded, for
+    /// placing its badge
+    pub message_ids: Vec<Uuid>,
+    pub created_at: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request() -> CreateAnnotationRequest {
+        serde_json::from_value(json!({
+            "source": "github-actions",
+            "start_seq": 10,
+            "label": "CI failed",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_defaults() {
+        let req = request();
+        assert_eq!(req.level, AnnotationLevel::Info);
+        assert_eq!(req.end_seq(), 10);
+        assert!(req.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut req = request();
+        req.end_seq = Some(9);
+        assert!(req.validate().is_err());
+
+        let mut req = request();
+        req.label = "  ".to_string();
+        assert!(req.validate().is_err());
+
+        let mut req = request();
+        req.url = Some("javascript:alert(1)".to_string());
+        assert!(req.validate().is_err());
+        req.url = Some("https://ci.example.com/runs/1".to_string());
+        assert!(req.validate().is_ok());
+    }
+
+    #[test]
+    fn test_level_round_trip() {
+        for level in [
+            AnnotationLevel::Info,
+            AnnotationLevel::Success,
+            AnnotationLevel::Warning,
+            AnnotationLevel::Error,
+        ] {
+            assert_eq!(AnnotationLevel::parse(level.as_str()), Some(level));
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Files written by Claude during a session
+//!
+//! The backend finds them in stored Write tool uses; the proxy packages them
+//! into a zip on request, since only it can read the working directory.
+
+use serde::{Deserialize, Serialize};
+
+/// Largest archive the proxy will build, before compression
+pub const MAX_ARTIFACT_ARCHIVE_BYTES: u64 = 50 * 1024 * 1024;
+
+/// A file created or overwritten by a Write tool use
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ArtifactInfo {
+    /// Path as given to the Write tool
+    pub path: String,
+    /// Size of the content written, in bytes
+    pub size_bytes: u64,
+    /// When the last write was recorded
+    pub written_at: String,
+}
+
+/// Paths and content sizes of Write tool uses in one Claude output
+pub fn write_tool_uses(content: &serde_json::Value) -> Vec<(String, u64)> {
+    if content.get("type").and_then(|t| t.as_str()) != Some("assistant") {
+        return Vec::new();
+    }
+    let Some(blocks) = content
+        .get("message")
+        .and_then(|m| m.get("content"))
+        .and_then(|c| c.as_array())
+    else {
+        return Vec::new();
+    };
+
+    blocks
+        .iter()
+        .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("tool_use"))
+        .filter(|b| b.get("name").and_then(|n| n.as_str()) == Some("Write"))
+        .filter_map(|b| {
+            let input = b.get("input")?;
+            let path = input.get("file_path")?.as_str()?;
+            let size = input
+                .get("content")
+                .and_then(|c| c.as_str())
+                .map_or(0, |c| c.len() as u64);
+            Some((path.to_string(), size))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_write_tool_uses() {
+        let content = json!({
+            "type": "assistant",
+            "message": {
+                "content": [
+                    {"type": "text", "text": "Creating files"},
+                    {"type": "tool_use", "id": "1", "name": "Write",
+                     "input": {"file_path": "/work/src/main.rs", "content": "fn main() {}"}},
+                    {"type": "tool_use", "id": "2", "name": "Edit",
+                     "input": {"file_path": "/work/README.md"}},
+                    {"type": "tool_use", "id": "3", "name": "Write",
+                     "input": {"file_path": "/work/empty.txt"}}
+                ]
+            }
+        });
+        assert_eq!(
+            write_tool_uses(&content),
+            vec![
+                ("/work/src/main.rs".to_string(), 12),
+                ("/work/empty.txt".to_string(), 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_tool_uses_ignores_other_outputs() {
+        assert!(write_tool_uses(&json!({"type": "user", "message": {"content": []}})).is_empty());
+        assert!(write_tool_uses(&json!({"type": "assistant"})).is_empty());
+    }
+}
@@ -0,0 +1,271 @@
+//! Budget limits and their evaluation
+//!
+//! Admins set soft and hard caps on session spend, daily spend per user, and
+//! session token usage. Crossing a soft cap only warns; crossing a hard cap
+//! makes the backend stop forwarding input to the session.
+
+use serde::{Deserialize, Serialize};
+
+/// Soft and hard thresholds for one budget dimension. `None` means no cap.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct BudgetCap {
+    #[serde(default)]
+    pub soft: Option<f64>,
+    #[serde(default)]
+    pub hard: Option<f64>,
+}
+
+impl BudgetCap {
+    /// Which threshold `used` has reached, and its value
+    fn check(&self, used: f64) -> Option<(BudgetLevel, f64)> {
+        if let Some(hard) = self.hard.filter(|hard| used >= *hard) {
+            return Some((BudgetLevel::Hard, hard));
+        }
+        self.soft
+            .filter(|soft| used >= *soft)
+            .map(|soft| (BudgetLevel::Soft, soft))
+    }
+}
+
+/// Budget caps configured by an admin
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct BudgetLimits {
+    /// Spend per session, in USD
+    #[serde(default)]
+    pub session_cost_usd: BudgetCap,
+    /// Spend per user per UTC day, in USD
+    #[serde(default)]
+    pub daily_cost_usd: BudgetCap,
+    /// Tokens per session, counting input, output, and cache tokens
+    #[serde(default)]
+    pub session_tokens: BudgetCap,
+}
+
+/// Usage that budget caps are checked against
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BudgetUsage {
+    pub session_cost_usd: f64,
+    pub daily_cost_usd: f64,
+    pub session_tokens: i64,
+}
+
+impl BudgetLimits {
+    pub fn evaluate(&self, usage: &BudgetUsage) -> BudgetStatus {
+        let checks = [
+            (
+                BudgetKind::SessionCost,
+                &self.session_cost_usd,
+                usage.session_cost_usd,
+            ),
+            (
+                BudgetKind::DailyCost,
+                &self.daily_cost_usd,
+                usage.daily_cost_usd,
+            ),
+            (
+                BudgetKind::SessionTokens,
+                &self.session_tokens,
+                usage.session_tokens as f64,
+            ),
+        ];
+        let breaches: Vec<BudgetBreach> = checks
+            .into_iter()
+            .filter_map(|(kind, cap, used)| {
+                cap.check(used).map(|(level, limit)| BudgetBreach {
+                    kind,
+                    level,
+                    limit,
+                    used,
+                })
+            })
+            .collect();
+        let level = breaches
+            .iter()
+            .map(|b| b.level)
+            .max()
+            .unwrap_or(BudgetLevel::Ok);
+        BudgetStatus { level, breaches }
+    }
+
+    /// How much of its first cap (soft, else hard) the most used dimension
+    /// has spent, as a fraction; `None` when nothing is capped
+    pub fn proximity(&self, usage: &BudgetUsage) -> Option<f64> {
+        [
+            (self.session_cost_usd, usage.session_cost_usd),
+            (self.daily_cost_usd, usage.daily_cost_usd),
+            (self.session_tokens, usage.session_tokens as f64),
+        ]
+        .into_iter()
+        .filter_map(|(cap, used)| {
+            let limit = cap.soft.or(cap.hard).filter(|limit| *limit > 0.0)?;
+            Some(used / limit)
+        })
+        .reduce(f64::max)
+    }
+}
+
+/// How close a session is to its budget, from least to most severe
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetLevel {
+    #[default]
+    Ok,
+    /// A soft cap was reached; input is still forwarded
+    Soft,
+    /// A hard cap was reached; input is refused
+    Hard,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetKind {
+    SessionCost,
+    DailyCost,
+    SessionTokens,
+}
+
+/// A cap that has been reached
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BudgetBreach {
+    pub kind: BudgetKind,
+    pub level: BudgetLevel,
+    pub limit: f64,
+    pub used: f64,
+}
+
+impl BudgetBreach {
+    /// e.g. "Session spend $5.20 has reached the $5.00 hard limit"
+    pub fn describe(&self) -> String {
+        let (what, used, limit) = match self.kind {
+            BudgetKind::SessionCost => (
+                "Session spend",
+                format!("${:.2}", self.used),
+                format!("${:.2}", self.limit),
+            ),
+            BudgetKind::DailyCost => (
+                "Today's spend",
+                format!("${:.2}", self.used),
+                format!("${:.2}", self.limit),
+            ),
+            BudgetKind::SessionTokens => (
+                "Session tokens",
+                format!("{:.0}", self.used),
+                format!("{:.0}", self.limit),
+            ),
+        };
+        let level = match self.level {
+            BudgetLevel::Hard => "hard",
+            _ => "soft",
+        };
+        format!(
+            "{} {} has reached the {} {} limit",
+            what, used, limit, level
+        )
+    }
+}
+
+/// Budget state of a session
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BudgetStatus {
+    pub level: BudgetLevel,
+    #[serde(default)]
+    pub breaches: Vec<BudgetBreach>,
+}
+
+impl BudgetStatus {
+    pub fn is_blocked(&self) -> bool {
+        self.level == BudgetLevel::Hard
+    }
+
+    /// One line per breach, most severe first
+    pub fn summary(&self) -> String {
+        let mut breaches: Vec<&BudgetBreach> = self.breaches.iter().collect();
+        breaches.sort_by_key(|b| std::cmp::Reverse(b.level));
+        breaches
+            .iter()
+            .map(|b| b.describe())
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> BudgetLimits {
+        BudgetLimits {
+            session_cost_usd: BudgetCap {
+                soft: Some(1.0),
+                hard: Some(5.0),
+            },
+            daily_cost_usd: BudgetCap {
+                soft: None,
+                hard: Some(20.0),
+            },
+            session_tokens: BudgetCap {
+                soft: Some(100_000.0),
+                hard: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_under_every_cap_is_ok() {
+        let status = limits().evaluate(&BudgetUsage {
+            session_cost_usd: 0.5,
+            daily_cost_usd: 10.0,
+            session_tokens: 50_000,
+        });
+        assert_eq!(status.level, BudgetLevel::Ok);
+        assert!(status.breaches.is_empty());
+        assert!(BudgetLimits::default()
+            .evaluate(&BudgetUsage {
+                session_cost_usd: 1e9,
+                daily_cost_usd: 1e9,
+                session_tokens: i64::MAX,
+            })
+            .breaches
+            .is_empty());
+    }
+
+    #[test]
+    fn test_most_severe_breach_wins() {
+        let status = limits().evaluate(&BudgetUsage {
+            session_cost_usd: 2.0,
+            daily_cost_usd: 25.0,
+            session_tokens: 10,
+        });
+        assert_eq!(status.level, BudgetLevel::Hard);
+        assert!(status.is_blocked());
+        assert_eq!(status.breaches.len(), 2);
+        assert_eq!(
+            status.summary(),
+            "Today's spend $25.00 has reached the $20.00 hard limit; \
+             Session spend $2.00 has reached the $1.00 soft limit"
+        );
+    }
+
+    #[test]
+    fn test_hard_cap_reports_only_hard_breach() {
+        let status = limits().evaluate(&BudgetUsage {
+            session_cost_usd: 5.0,
+            ..Default::default()
+        });
+        assert_eq!(status.breaches.len(), 1);
+        assert_eq!(status.breaches[0].level, BudgetLevel::Hard);
+        assert_eq!(status.breaches[0].limit, 5.0);
+    }
+
+    #[test]
+    fn test_proximity() {
+        let usage = BudgetUsage {
+            session_cost_usd: 0.8,
+            daily_cost_usd: 2.0,
+            session_tokens: 10_000,
+        };
+        // $0.80 of the $1.00 soft cap is the closest
+        assert_eq!(limits().proximity(&usage), Some(0.8));
+        assert_eq!(BudgetLimits::default().proximity(&usage), None);
+    }
+}
@@ -0,0 +1,163 @@
+//! CI results for the branch a session is working on
+//!
+//! Proxies report the GitHub repository of the working directory's `origin`
+//! remote; GitHub webhooks then tell the backend how runs on each branch
+//! went, and sessions on that repository and branch show the result.
+
+use serde::{Deserialize, Serialize};
+
+/// Outcome of a CI run, or of all runs on a commit together
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CiState {
+    Pending,
+    Success,
+    Failure,
+}
+
+impl CiState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Success => "success",
+            Self::Failure => "failure",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "pending" => Some(Self::Pending),
+            "success" => Some(Self::Success),
+            "failure" => Some(Self::Failure),
+            _ => None,
+        }
+    }
+
+    /// State of a GitHub check run or workflow run from its `status` and
+    /// `conclusion`. Skipped and neutral runs count as passing.
+    pub fn from_github(status: &str, conclusion: Option<&str>) -> Self {
+        if status != "completed" {
+            return Self::Pending;
+        }
+        match conclusion {
+            Some("success" | "neutral" | "skipped") => Self::Success,
+            _ => Self::Failure,
+        }
+    }
+
+    /// All runs together: failed if any failed, else pending if any is
+    /// still running
+    pub fn combine(states: impl IntoIterator<Item = Self>) -> Option<Self> {
+        states.into_iter().fold(None, |combined, state| {
+            Some(match (combined, state) {
+                (Some(Self::Failure), _) | (_, Self::Failure) => Self::Failure,
+                (Some(Self::Pending), _) | (_, Self::Pending) => Self::Pending,
+                _ => Self::Success,
+            })
+        })
+    }
+}
+
+/// One workflow or check on the latest commit
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CiRun {
+    pub name: String,
+    pub state: CiState,
+    pub url: Option<String>,
+}
+
+/// CI for the latest commit on a session's branch
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CiStatus {
+    /// All runs together
+    pub state: CiState,
+    pub head_sha: String,
+    pub runs: Vec<CiRun>,
+    /// When the last run was reported
+    pub updated_at: String,
+}
+
+impl CiStatus {
+    /// Short text for badges, e.g. "CI failed (1/3)"
+    pub fn summary(&self) -> String {
+        match self.state {
+            CiState::Pending => "CI running".to_string(),
+            CiState::Success => "CI passed".to_string(),
+            CiState::Failure => {
+                let failed = self
+                    .runs
+                    .iter()
+                    .filter(|r| r.state == CiState::Failure)
+                    .count();
+                format!("CI failed ({}/{})", failed, self.runs.len())
+            }
+        }
+    }
+
+    /// Where a badge should link: the first failed run, else the first run
+    pub fn link(&self) -> Option<&str> {
+        self.runs
+            .iter()
+            .find(|r| r.state == CiState::Failure)
+            .or(self.runs.first())
+            .and_then(|r| r.url.as_deref())
+    }
+}
+
+/// `owner/repo` of a GitHub remote URL, for SSH and HTTPS remotes
+pub fn github_repo(remote: &str) -> Option<String> {
+    let remote = remote.trim();
+    let path = remote
+        .strip_prefix("git@github.com:")
+        .or_else(|| remote.strip_prefix("ssh://git@github.com/"))
+        .or_else(|| remote.strip_prefix("https://github.com/"))
+        .or_else(|| remote.strip_prefix("http://github.com/"))?;
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let (owner, repo) = path.split_once('/')?;
+    if owner.is_empty() || repo.is_empty() || repo.contains('/') {
+        return None;
+    }
+    Some(format!("{}/{}", owner, repo))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_github_repo() {
+        for remote in [
+            "git@github.com:meawoppl/cc-proxy.git",
+            "https://github.com/meawoppl/cc-proxy",
+            "https://github.com/meawoppl/cc-proxy.git\n",
+            "ssh://git@github.com/meawoppl/cc-proxy.git",
+        ] {
+            assert_eq!(github_repo(remote).as_deref(), Some("meawoppl/cc-proxy"));
+        }
+        assert_eq!(github_repo("git@gitlab.com:a/b.git"), None);
+        assert_eq!(github_repo("https://github.com/a"), None);
+    }
+
+    #[test]
+    fn test_from_github() {
+        assert_eq!(CiState::from_github("in_progress", None), CiState::Pending);
+        assert_eq!(
+            CiState::from_github("completed", Some("skipped")),
+            CiState::Success
+        );
+        assert_eq!(
+            CiState::from_github("completed", Some("timed_out")),
+            CiState::Failure
+        );
+    }
+
+    #[test]
+    fn test_combine() {
+        use CiState::*;
+        assert_eq!(CiState::combine([]), None);
+        assert_eq!(CiState::combine([Success, Success]), Some(Success));
+        assert_eq!(CiState::combine([Success, Pending]), Some(Pending));
+        assert_eq!(CiState::combine([Pending, Failure, Success]), Some(Failure));
+    }
+}
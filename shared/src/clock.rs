@@ -0,0 +1,106 @@
+//! Clock skew between a proxy and the backend
+//!
+//! Proxies stamp outputs with their own clock, which may be minutes or hours
+//! off. Each `Ping` carries the proxy's clock and arrives no earlier than it
+//! was sent, so `sent_at - received_at` is the proxy's offset minus the
+//! one-way delay. The largest recent sample is the one with the least delay,
+//! and is taken as the offset.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Offsets beyond this are flagged in diagnostics
+pub const LARGE_CLOCK_SKEW_MS: i64 = 30_000;
+
+/// Pings kept for the estimate, about a minute at the heartbeat interval
+const SAMPLES: usize = 12;
+
+/// Running estimate of how far a proxy's clock is ahead of the backend's
+#[derive(Debug, Clone, Default)]
+pub struct ClockSkew {
+    /// `sent_at - received_at` of recent pings, in milliseconds
+    samples: VecDeque<i64>,
+}
+
+impl ClockSkew {
+    /// Record a ping sent at `sent_at` on the proxy's clock and received at
+    /// `received_at` on the backend's (both ms since the Unix epoch)
+    pub fn record(&mut self, sent_at: u64, received_at: u64) {
+        if self.samples.len() == SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sent_at as i64 - received_at as i64);
+    }
+
+    /// How far the proxy's clock is ahead (negative: behind), `None` before
+    /// the first ping
+    pub fn offset_ms(&self) -> Option<i64> {
+        self.samples.iter().copied().max()
+    }
+
+    /// Backend time of something the proxy stamped at `proxy_time`, given
+    /// that the backend received it at `received_at`; never later than that.
+    /// `None` before the first ping, when the proxy's clock can't be trusted.
+    pub fn normalize(&self, proxy_time: u64, received_at: u64) -> Option<u64> {
+        let offset = self.offset_ms()?;
+        let normalized = (proxy_time as i64).saturating_sub(offset).max(0) as u64;
+        Some(normalized.min(received_at))
+    }
+
+    /// What diagnostics report about this estimate
+    pub fn report(&self) -> ClockSkewReport {
+        let offset_ms = self.offset_ms();
+        ClockSkewReport {
+            offset_ms,
+            large: offset_ms.is_some_and(|o| o.abs() > LARGE_CLOCK_SKEW_MS),
+            samples: self.samples.len(),
+        }
+    }
+}
+
+/// Clock skew as included in a session's diagnostic bundle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClockSkewReport {
+    /// How far the proxy's clock is ahead of the backend's, if known
+    pub offset_ms: Option<i64>,
+    /// Whether the offset exceeds [`LARGE_CLOCK_SKEW_MS`]; proxy log
+    /// timestamps in the bundle are then off by about `offset_ms`
+    pub large: bool,
+    /// Pings the estimate is based on
+    pub samples: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_uses_least_delayed_ping() {
+        let mut skew = ClockSkew::default();
+        assert_eq!(skew.offset_ms(), None);
+
+        // Proxy is 60s ahead; pings take 20ms, then 500ms
+        skew.record(1_060_000, 1_000_020);
+        skew.record(1_065_000, 1_005_500);
+        assert_eq!(skew.offset_ms(), Some(59_980));
+        assert!(skew.report().large);
+
+        // Old samples age out
+        for i in 0..SAMPLES as u64 {
+            skew.record(2_000_000 + i * 5_000, 2_000_010 + i * 5_000);
+        }
+        assert_eq!(skew.offset_ms(), Some(-10));
+        assert!(!skew.report().large);
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut skew = ClockSkew::default();
+        assert_eq!(skew.normalize(900, 1_000), None);
+
+        // Proxy is an hour behind; nothing lands after it was received
+        skew.record(1_000_000, 4_600_000);
+        assert_eq!(skew.normalize(1_000_000, 4_700_000), Some(4_600_000));
+        assert_eq!(skew.normalize(1_200_000, 4_700_000), Some(4_700_000));
+    }
+}
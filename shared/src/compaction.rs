@@ -0,0 +1,130 @@
+//! Context compaction boundaries
+//!
+//! When Claude Code compacts a conversation it emits a `compact_boundary`
+//! system message, then feeds the summary it wrote back in as a user
+//! message. Neither was typed by anyone, so the transcript shows them as a
+//! divider and a summary, and turn records note that context was compacted
+//! rather than counting the summary as a prompt.
+
+use serde_json::Value;
+
+/// How a summary Claude Code writes for itself begins, for summaries that
+/// aren't flagged with `isCompactSummary`
+const SUMMARY_PREFIX: &str = "This session is being continued from a previous conversation";
+
+/// A `compact_boundary` system message
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompactBoundary {
+    /// `auto` when the context filled up, `manual` for `/compact`
+    pub trigger: Option<String>,
+    /// Context size before compacting, in tokens
+    pub pre_tokens: Option<u64>,
+}
+
+impl CompactBoundary {
+    /// The boundary `message` marks, if it is one
+    pub fn of(message: &Value) -> Option<Self> {
+        if message.get("type").and_then(Value::as_str) != Some("system")
+            || message.get("subtype").and_then(Value::as_str) != Some("compact_boundary")
+        {
+            return None;
+        }
+        let metadata = message.get("compact_metadata");
+        Some(Self {
+            trigger: metadata
+                .and_then(|m| m.get("trigger"))
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            pre_tokens: metadata
+                .and_then(|m| m.get("pre_tokens"))
+                .and_then(Value::as_u64),
+        })
+    }
+
+    /// Whether `/compact` asked for it, rather than the context filling up
+    pub fn is_manual(&self) -> bool {
+        self.trigger.as_deref() == Some("manual")
+    }
+}
+
+/// Text of a user message's content, whether a string or text blocks
+fn user_text(message: &Value) -> Option<String> {
+    if let Some(text) = message.get("content").and_then(Value::as_str) {
+        return Some(text.to_string());
+    }
+    match message.pointer("/message/content")? {
+        Value::String(text) => Some(text.clone()),
+        Value::Array(blocks) => {
+            let texts: Vec<&str> = blocks
+                .iter()
+                .filter(|b| b.get("type").and_then(Value::as_str) == Some("text"))
+                .filter_map(|b| b.get("text").and_then(Value::as_str))
+                .collect();
+            (!texts.is_empty()).then(|| texts.join("\n"))
+        }
+        _ => None,
+    }
+}
+
+/// The summary `message` carries, if it's the one Claude Code feeds back in
+/// after compacting
+pub fn summary_text(message: &Value) -> Option<String> {
+    if message.get("type").and_then(Value::as_str) != Some("user") {
+        return None;
+    }
+    let text = user_text(message)?;
+    let flagged = message.get("isCompactSummary").and_then(Value::as_bool) == Some(true);
+    (flagged || text.starts_with(SUMMARY_PREFIX)).then_some(text)
+}
+
+/// Whether `message` is part of a compaction: the boundary or the summary
+pub fn is_compaction(message: &Value) -> bool {
+    CompactBoundary::of(message).is_some() || summary_text(message).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_boundary() {
+        let boundary = json!({
+            "type": "system",
+            "subtype": "compact_boundary",
+            "session_id": "879c1a88-3756-4092-aa95-0020c4ed9692",
+            "compact_metadata": {"trigger": "auto", "pre_tokens": 155285},
+        });
+        let parsed = CompactBoundary::of(&boundary).unwrap();
+        assert_eq!(parsed.trigger.as_deref(), Some("auto"));
+        assert_eq!(parsed.pre_tokens, Some(155285));
+        assert!(!parsed.is_manual());
+
+        let bare = json!({"type": "system", "subtype": "compact_boundary"});
+        assert_eq!(CompactBoundary::of(&bare), Some(CompactBoundary::default()));
+        assert_eq!(
+            CompactBoundary::of(&json!({"type": "system", "subtype": "init"})),
+            None
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let flagged = json!({
+            "type": "user",
+            "isCompactSummary": true,
+            "message": {"role": "user", "content": [{"type": "text", "text": "Summary: ..."}]},
+        });
+        assert_eq!(summary_text(&flagged).as_deref(), Some("Summary: ..."));
+
+        let unflagged = json!({
+            "type": "user",
+            "message": {"content": format!("{} that ran out of context.", SUMMARY_PREFIX)},
+        });
+        assert!(is_compaction(&unflagged));
+
+        let prompt = json!({"type": "user", "message": {"content": "fix the build"}});
+        assert_eq!(summary_text(&prompt), None);
+        assert!(!is_compaction(&prompt));
+    }
+}
@@ -0,0 +1,141 @@
+//! Compression of large output payloads
+//!
+//! Read and Bash results can run to hundreds of KB of JSON. When both ends
+//! of a connection agree on an encoding at registration, `ClaudeOutput` and
+//! `SequencedOutput` content above [`COMPRESSION_THRESHOLD`] is sent as a
+//! base64 string of the deflated JSON, with `content_encoding` set so the
+//! receiver knows to expand it. Pure Rust, so it also runs in the browser.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+/// Content smaller than this (as serialized JSON) is always sent as-is
+pub const COMPRESSION_THRESHOLD: usize = 8 * 1024;
+
+/// Largest payload accepted when expanding, to bound memory use
+pub const MAX_DECOMPRESSED_BYTES: usize = 64 * 1024 * 1024;
+
+/// Deflate level: favours speed, output is still well under half for JSON
+const DEFLATE_LEVEL: u8 = 6;
+
+/// Encodings a client can offer in `Register`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentEncoding {
+    /// Raw deflate (RFC 1951), base64 encoded
+    Deflate,
+}
+
+/// The encoding to use with a peer that offered `offered`, if any
+pub fn negotiate_encoding(offered: &[ContentEncoding]) -> Option<ContentEncoding> {
+    offered
+        .iter()
+        .copied()
+        .find(|e| *e == ContentEncoding::Deflate)
+}
+
+/// Compressed content and its size before and after
+#[derive(Debug, Clone)]
+pub struct Compressed {
+    /// Base64 string to send in place of the original content
+    pub content: serde_json::Value,
+    /// Length of the content serialized as JSON
+    pub raw_len: usize,
+    /// Length of the base64 string sent instead
+    pub encoded_len: usize,
+}
+
+/// Compress `content` if it is large enough to be worth it. Returns `None`
+/// when it should be sent as-is.
+pub fn compress_content(content: &serde_json::Value) -> Option<Compressed> {
+    let raw = serde_json::to_vec(content).ok()?;
+    if raw.len() < COMPRESSION_THRESHOLD {
+        return None;
+    }
+    let deflated = miniz_oxide::deflate::compress_to_vec(&raw, DEFLATE_LEVEL);
+    let encoded = BASE64.encode(deflated);
+    if encoded.len() >= raw.len() {
+        return None;
+    }
+    Some(Compressed {
+        raw_len: raw.len(),
+        encoded_len: encoded.len(),
+        content: serde_json::Value::String(encoded),
+    })
+}
+
+/// Undo [`compress_content`]. Content without an encoding is returned unchanged.
+pub fn decompress_content(
+    content: serde_json::Value,
+    encoding: Option<ContentEncoding>,
+) -> Result<serde_json::Value, String> {
+    expand_content(content, encoding).map(|(content, _)| content)
+}
+
+/// Like [`decompress_content`], also returning the length of the expanded
+/// JSON, for receivers that keep compression metrics. The length is `None`
+/// for content that was not compressed.
+pub fn expand_content(
+    content: serde_json::Value,
+    encoding: Option<ContentEncoding>,
+) -> Result<(serde_json::Value, Option<usize>), String> {
+    let Some(ContentEncoding::Deflate) = encoding else {
+        return Ok((content, None));
+    };
+    let encoded = content
+        .as_str()
+        .ok_or("compressed content is not a string")?;
+    let deflated = BASE64
+        .decode(encoded)
+        .map_err(|e| format!("invalid base64: {}", e))?;
+    let raw = miniz_oxide::inflate::decompress_to_vec_with_limit(&deflated, MAX_DECOMPRESSED_BYTES)
+        .map_err(|e| format!("invalid deflate stream: {}", e))?;
+    let value =
+        serde_json::from_slice(&raw).map_err(|e| format!("invalid JSON after inflate: {}", e))?;
+    Ok((value, Some(raw.len())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_large_content_round_trips() {
+        let output = "line of tool output\n".repeat(2000);
+        let content = json!({"type": "user", "content": output});
+
+        let compressed = compress_content(&content).expect("large content is compressed");
+        assert!(compressed.encoded_len < compressed.raw_len / 4);
+        assert!(compressed.content.is_string());
+
+        let (restored, raw_len) =
+            expand_content(compressed.content, Some(ContentEncoding::Deflate)).unwrap();
+        assert_eq!(restored, content);
+        assert_eq!(raw_len, Some(compressed.raw_len));
+    }
+
+    #[test]
+    fn test_small_content_is_left_alone() {
+        let content = json!({"type": "assistant", "text": "hi"});
+        assert!(compress_content(&content).is_none());
+        assert_eq!(decompress_content(content.clone(), None).unwrap(), content);
+    }
+
+    #[test]
+    fn test_invalid_payload_is_an_error() {
+        let bad = json!("not base64 at all!");
+        assert!(decompress_content(bad, Some(ContentEncoding::Deflate)).is_err());
+        assert!(decompress_content(json!({}), Some(ContentEncoding::Deflate)).is_err());
+    }
+
+    #[test]
+    fn test_negotiation() {
+        assert_eq!(
+            negotiate_encoding(&[ContentEncoding::Deflate]),
+            Some(ContentEncoding::Deflate)
+        );
+        assert_eq!(negotiate_encoding(&[]), None);
+    }
+}
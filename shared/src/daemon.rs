@@ -0,0 +1,78 @@
+//! Several sessions over one connection
+//!
+//! A proxy daemon hosts many sessions but keeps a single WebSocket to the
+//! backend, at `/ws/daemon`. Each frame on it carries one session's
+//! [`ProxyMessage`](crate::ProxyMessage) along with the session it belongs
+//! to. Per session, the messages are exactly what a lone proxy would send
+//! over `/ws/session`, starting with `Register`, so both ends run the usual
+//! session handling on each and only route frames.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A frame on a multiplexed connection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "frame", rename_all = "snake_case")]
+pub enum DaemonFrame {
+    /// A message for or from one session. Kept as JSON so routing doesn't
+    /// depend on knowing every message type.
+    Message {
+        session_id: Uuid,
+        message: serde_json::Value,
+    },
+    /// The sender is done with the session's connection; the receiver
+    /// drops its end, as if a lone socket had closed
+    Closed { session_id: Uuid },
+}
+
+impl DaemonFrame {
+    pub fn session_id(&self) -> Uuid {
+        match self {
+            DaemonFrame::Message { session_id, .. } | DaemonFrame::Closed { session_id } => {
+                *session_id
+            }
+        }
+    }
+
+    /// Wrap a session's message, given as the JSON text a lone socket would
+    /// carry. `None` if it isn't JSON.
+    pub fn from_text(session_id: Uuid, text: &str) -> Option<Self> {
+        let message = serde_json::from_str(text).ok()?;
+        Some(DaemonFrame::Message {
+            session_id,
+            message,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProxyMessage;
+
+    #[test]
+    fn test_frames_round_trip() {
+        let session_id = Uuid::new_v4();
+        let text = serde_json::to_string(&ProxyMessage::Heartbeat).unwrap();
+        let frame = DaemonFrame::from_text(session_id, &text).unwrap();
+        assert_eq!(frame.session_id(), session_id);
+
+        let wire = serde_json::to_string(&frame).unwrap();
+        assert!(wire.contains(r#""frame":"message""#));
+        let DaemonFrame::Message { message, .. } = serde_json::from_str(&wire).unwrap() else {
+            panic!("not a message frame");
+        };
+        assert!(matches!(
+            serde_json::from_value(message).unwrap(),
+            ProxyMessage::Heartbeat
+        ));
+
+        let closed: DaemonFrame = serde_json::from_str(&format!(
+            r#"{{"frame":"closed","session_id":"{}"}}"#,
+            session_id
+        ))
+        .unwrap();
+        assert_eq!(closed, DaemonFrame::Closed { session_id });
+        assert_eq!(DaemonFrame::from_text(session_id, "not json"), None);
+    }
+}
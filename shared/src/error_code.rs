@@ -0,0 +1,86 @@
+//! Machine-readable error codes
+//!
+//! `ProxyMessage::Error` and a failed `RegisterAck` carry an [`ErrorCode`]
+//! next to their free-text detail. Clients choose what to show (and whether
+//! to retry) from the code, and treat the detail as supporting text only.
+//! Peers that predate codes send none, which reads as [`ErrorCode::Other`].
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The token or cookie was missing, invalid, or expired
+    AuthFailed,
+    /// The user isn't a member of the session, or lacks the role for the action
+    AccessDenied,
+    /// The session doesn't exist (or no longer does)
+    SessionNotFound,
+    /// The session's proxy isn't connected
+    ProxyOffline,
+    /// Another member is driving the session
+    NotDriver,
+    /// Too many requests; try again shortly
+    RateLimited,
+    /// The message or upload is bigger than the server accepts
+    PayloadTooLarge,
+    /// A budget hard cap was reached
+    BudgetExceeded,
+    /// The user already has as many sessions running as allowed
+    QuotaExceeded,
+    /// An admin has paused all sessions with the kill switch
+    KillSwitch,
+    /// Client and server protocol versions can't work together
+    IncompatibleProtocol,
+    /// Something failed on the server
+    Internal,
+    /// Unrecognized, or sent by a peer without error codes
+    #[default]
+    #[serde(other)]
+    Other,
+}
+
+impl ErrorCode {
+    /// Whether the same request may succeed if simply retried later
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ErrorCode::ProxyOffline
+                | ErrorCode::RateLimited
+                | ErrorCode::QuotaExceeded
+                | ErrorCode::Internal
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wire_names() {
+        assert_eq!(
+            serde_json::to_string(&ErrorCode::PayloadTooLarge).unwrap(),
+            r#""payload_too_large""#
+        );
+        assert_eq!(
+            serde_json::from_str::<ErrorCode>(r#""auth_failed""#).unwrap(),
+            ErrorCode::AuthFailed
+        );
+    }
+
+    #[test]
+    fn test_unknown_code_is_other() {
+        assert_eq!(
+            serde_json::from_str::<ErrorCode>(r#""added_in_a_later_version""#).unwrap(),
+            ErrorCode::Other
+        );
+    }
+
+    #[test]
+    fn test_retryable() {
+        assert!(ErrorCode::RateLimited.is_retryable());
+        assert!(!ErrorCode::AuthFailed.is_retryable());
+        assert!(!ErrorCode::IncompatibleProtocol.is_retryable());
+    }
+}
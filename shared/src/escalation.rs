@@ -0,0 +1,138 @@
+//! Escalation of unanswered permission requests
+//!
+//! A session owner can ask for a second member to be notified when a
+//! permission request has waited too long, and for a default answer to be
+//! applied after a longer wait, so sessions left running overnight neither
+//! stall forever nor run unattended without a trace.
+
+use crate::RuleDecision;
+use serde::{Deserialize, Serialize};
+
+/// Per-session escalation settings. A step without a delay is skipped.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EscalationPolicy {
+    /// Minutes a request waits before `notify_email` is notified
+    #[serde(default)]
+    pub notify_after_minutes: Option<u32>,
+    /// Session member to notify; must be able to answer requests
+    #[serde(default)]
+    pub notify_email: Option<String>,
+    /// Minutes a request waits before `default_action` is applied
+    #[serde(default)]
+    pub default_after_minutes: Option<u32>,
+    /// Answer applied when nobody responds in time
+    pub default_action: RuleDecision,
+}
+
+impl Default for EscalationPolicy {
+    fn default() -> Self {
+        Self {
+            notify_after_minutes: None,
+            notify_email: None,
+            default_after_minutes: None,
+            default_action: RuleDecision::Deny,
+        }
+    }
+}
+
+/// What to do about a request that has waited a while
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscalationStep {
+    /// Tell the secondary contact
+    Notify,
+    /// Answer the request with the policy's default
+    ApplyDefault(RuleDecision),
+}
+
+impl EscalationPolicy {
+    /// Problems that would make the policy unusable, if any
+    pub fn validate(&self) -> Result<(), String> {
+        if self.notify_after_minutes == Some(0) || self.default_after_minutes == Some(0) {
+            return Err("Delays must be at least one minute".to_string());
+        }
+        let has_email = self
+            .notify_email
+            .as_deref()
+            .is_some_and(|e| !e.trim().is_empty());
+        if self.notify_after_minutes.is_some() && !has_email {
+            return Err("Choose who to notify".to_string());
+        }
+        if let (Some(notify), Some(default)) =
+            (self.notify_after_minutes, self.default_after_minutes)
+        {
+            if notify >= default {
+                return Err("The default action must come after the notification".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// The step due for a request that has waited `waited_minutes`, given
+    /// whether the contact was already notified
+    pub fn due_step(&self, waited_minutes: i64, notified: bool) -> Option<EscalationStep> {
+        let reached = |after: Option<u32>| after.is_some_and(|m| waited_minutes >= i64::from(m));
+        if reached(self.default_after_minutes) {
+            return Some(EscalationStep::ApplyDefault(self.default_action));
+        }
+        if !notified && self.notify_email.is_some() && reached(self.notify_after_minutes) {
+            return Some(EscalationStep::Notify);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> EscalationPolicy {
+        EscalationPolicy {
+            notify_after_minutes: Some(15),
+            notify_email: Some("oncall@example.com".to_string()),
+            default_after_minutes: Some(60),
+            default_action: RuleDecision::Deny,
+        }
+    }
+
+    #[test]
+    fn test_steps_follow_the_clock() {
+        let policy = policy();
+        assert_eq!(policy.due_step(5, false), None);
+        assert_eq!(policy.due_step(15, false), Some(EscalationStep::Notify));
+        assert_eq!(policy.due_step(30, true), None);
+        assert_eq!(
+            policy.due_step(60, true),
+            Some(EscalationStep::ApplyDefault(RuleDecision::Deny))
+        );
+        // A request found late goes straight to the default
+        assert_eq!(
+            policy.due_step(90, false),
+            Some(EscalationStep::ApplyDefault(RuleDecision::Deny))
+        );
+        assert_eq!(EscalationPolicy::default().due_step(1000, false), None);
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(policy().validate().is_ok());
+        assert!(EscalationPolicy::default().validate().is_ok());
+
+        let no_contact = EscalationPolicy {
+            notify_email: None,
+            ..policy()
+        };
+        assert!(no_contact.validate().is_err());
+
+        let out_of_order = EscalationPolicy {
+            default_after_minutes: Some(10),
+            ..policy()
+        };
+        assert!(out_of_order.validate().is_err());
+
+        let zero = EscalationPolicy {
+            notify_after_minutes: Some(0),
+            ..policy()
+        };
+        assert!(zero.validate().is_err());
+    }
+}
@@ -0,0 +1,74 @@
+//! Session forking
+//!
+//! A fork is a new session in the same directory whose first prompt replays
+//! another session's conversation up to a chosen message, so an alternative
+//! approach can be explored without disturbing the original thread.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Where a fork starts, from `GET /api/sessions/:id/fork?at=<message id>`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionFork {
+    pub source_session_id: Uuid,
+    pub source_session_name: String,
+    /// The source session's directory, where the fork runs too
+    pub working_directory: String,
+    /// The last message replayed into the fork
+    pub at_message_id: Uuid,
+    /// First prompt for the fork: the conversation up to `at_message_id`
+    pub prompt: String,
+}
+
+impl SessionFork {
+    /// Default name for the forked session
+    pub fn session_name(&self) -> String {
+        format!("{} (fork)", self.source_session_name)
+    }
+}
+
+/// The shell command that starts a fork of `session_id` after `message_id`
+pub fn fork_command(working_directory: &str, session_id: Uuid, message_id: Uuid) -> String {
+    format!(
+        "cd {} && claude-portal fork {} --at {}",
+        shell_quote(working_directory),
+        session_id,
+        message_id
+    )
+}
+
+/// Quote `value` for a POSIX shell unless it's plainly safe as is
+fn shell_quote(value: &str) -> String {
+    let safe = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/._-~+:@,".contains(c));
+    if safe {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fork_command() {
+        let session_id = Uuid::nil();
+        let message_id = Uuid::max();
+        assert_eq!(
+            fork_command("/home/me/repo", session_id, message_id),
+            format!(
+                "cd /home/me/repo && claude-portal fork {} --at {}",
+                session_id, message_id
+            )
+        );
+        assert!(fork_command("/home/me/my repo", session_id, message_id)
+            .starts_with("cd '/home/me/my repo' && "));
+        assert!(
+            fork_command("/tmp/it's", session_id, message_id).starts_with(r"cd '/tmp/it'\''s' && ")
+        );
+    }
+}
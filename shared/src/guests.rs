@@ -0,0 +1,141 @@
+//! Time-boxed guest access
+//!
+//! A session owner can invite someone without an account to one session
+//! for a while, through a login link. The guest can watch, or watch and
+//! answer permission requests, and loses access when the time is up.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Session role of a guest who may answer permission requests but not send
+/// input
+pub const APPROVER_ROLE: &str = "approver";
+
+/// Longest a guest invite can last
+pub const MAX_GUEST_MINUTES: u32 = 24 * 60;
+
+/// Durations offered when inviting a guest
+pub const GUEST_DURATIONS_MINUTES: [u32; 4] = [15, 60, 240, MAX_GUEST_MINUTES];
+
+/// Longest guest name
+pub const MAX_GUEST_NAME_CHARS: usize = 64;
+
+/// What a guest may do in the session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuestAccess {
+    /// Watch only
+    Viewer,
+    /// Watch and answer permission requests
+    Approver,
+}
+
+impl GuestAccess {
+    /// The session role the guest is given
+    pub fn role(&self) -> &'static str {
+        match self {
+            GuestAccess::Viewer => "viewer",
+            GuestAccess::Approver => APPROVER_ROLE,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GuestAccess::Viewer => "View only",
+            GuestAccess::Approver => "Approve only",
+        }
+    }
+}
+
+/// Body of `POST /api/sessions/:id/guests`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CreateGuestInviteRequest {
+    /// Who the guest is, shown to other members and in the audit log
+    pub name: String,
+    pub access: GuestAccess,
+    /// How long the link and the access it gives last
+    pub minutes: u32,
+}
+
+impl CreateGuestInviteRequest {
+    /// Problems that would make the invite unusable, if any
+    pub fn validate(&self) -> Result<(), String> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("Give the guest a name".to_string());
+        }
+        if name.chars().count() > MAX_GUEST_NAME_CHARS {
+            return Err(format!(
+                "Guest names are at most {} characters",
+                MAX_GUEST_NAME_CHARS
+            ));
+        }
+        if self.minutes == 0 || self.minutes > MAX_GUEST_MINUTES {
+            return Err("Guest access lasts between a minute and a day".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// A guest invite, as listed in the share dialog
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GuestInviteInfo {
+    pub id: Uuid,
+    pub name: String,
+    pub access: GuestAccess,
+    /// Who sent the invite
+    pub invited_by: String,
+    /// RFC 3339
+    pub expires_at: String,
+    /// Whether the link has been opened
+    pub joined: bool,
+}
+
+/// Response to creating an invite; the link is shown only this once
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CreatedGuestInvite {
+    pub invite: GuestInviteInfo,
+    pub url: String,
+}
+
+/// "15 minutes", "4 hours", "1 day"
+pub fn describe_minutes(minutes: u32) -> String {
+    let (count, unit) = if minutes.is_multiple_of(24 * 60) {
+        (minutes / (24 * 60), "day")
+    } else if minutes.is_multiple_of(60) {
+        (minutes / 60, "hour")
+    } else {
+        (minutes, "minute")
+    };
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(name: &str, minutes: u32) -> CreateGuestInviteRequest {
+        CreateGuestInviteRequest {
+            name: name.to_string(),
+            access: GuestAccess::Approver,
+            minutes,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(request("Sam", 60).validate().is_ok());
+        assert!(request("  ", 60).validate().is_err());
+        assert!(request(&"x".repeat(65), 60).validate().is_err());
+        assert!(request("Sam", 0).validate().is_err());
+        assert!(request("Sam", MAX_GUEST_MINUTES + 1).validate().is_err());
+    }
+
+    #[test]
+    fn test_describe_minutes() {
+        assert_eq!(describe_minutes(15), "15 minutes");
+        assert_eq!(describe_minutes(60), "1 hour");
+        assert_eq!(describe_minutes(240), "4 hours");
+        assert_eq!(describe_minutes(MAX_GUEST_MINUTES), "1 day");
+    }
+}
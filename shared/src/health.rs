@@ -0,0 +1,199 @@
+//! Session health
+//!
+//! The backend scores each running session from signals it already stores:
+//! how many recent turns failed, whether a turn has gone quiet, how full
+//! Claude's context is, and how close the session is to a budget cap. The
+//! dashboard shows the result as a colored dot, so the session most likely
+//! to need someone stands out.
+
+use crate::BudgetLevel;
+use serde::{Deserialize, Serialize};
+
+/// Tokens Claude's context holds before it has to compact
+pub const CONTEXT_WINDOW_TOKENS: u64 = 200_000;
+
+/// A running turn with no output for this long is considered stalled
+pub const STALL_SECS: u64 = 10 * 60;
+
+/// Turns the error rate is measured over
+pub const RECENT_TURNS: usize = 10;
+
+/// What a session's health is worked out from
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HealthSignals {
+    /// Turns among the last `RECENT_TURNS`
+    pub recent_turns: u32,
+    /// Of those, turns that ended in an error
+    pub recent_errors: u32,
+    /// Time since the last output of a turn that hasn't finished
+    pub quiet_secs: Option<u64>,
+    /// Context size as of Claude's last reply
+    pub context_tokens: Option<u64>,
+    /// Fraction of the nearest budget cap spent
+    pub budget_proximity: Option<f64>,
+    pub budget_level: BudgetLevel,
+}
+
+/// How urgently a session needs attention, from least to most
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthLevel {
+    #[default]
+    Good,
+    Warning,
+    Critical,
+}
+
+impl HealthLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Good => "good",
+            Self::Warning => "warning",
+            Self::Critical => "critical",
+        }
+    }
+}
+
+/// A session's health score and what lowered it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionHealth {
+    /// 100 for a session with nothing wrong, down to 0
+    pub score: u8,
+    pub level: HealthLevel,
+    /// One line per problem, worst first
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+impl Default for SessionHealth {
+    fn default() -> Self {
+        Self {
+            score: 100,
+            level: HealthLevel::Good,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl HealthSignals {
+    /// Score the signals: each problem costs points, and its warning is
+    /// listed by how many
+    pub fn evaluate(&self) -> SessionHealth {
+        let mut problems: Vec<(u32, String)> = Vec::new();
+
+        if self.recent_turns > 0 && self.recent_errors > 0 {
+            let rate = self.recent_errors as f64 / self.recent_turns as f64;
+            problems.push((
+                (rate * 50.0).round() as u32,
+                format!(
+                    "{} of the last {} turns ended in an error",
+                    self.recent_errors, self.recent_turns
+                ),
+            ));
+        }
+
+        if let Some(quiet) = self.quiet_secs.filter(|secs| *secs >= STALL_SECS) {
+            problems.push((
+                35,
+                format!("No output for {}m while a turn is running", quiet / 60),
+            ));
+        }
+
+        if let Some(tokens) = self.context_tokens {
+            let used = tokens as f64 / CONTEXT_WINDOW_TOKENS as f64;
+            if used >= 0.75 {
+                problems.push((
+                    (10.0 + (used.min(1.0) - 0.75) * 80.0).round() as u32,
+                    format!(
+                        "Context is {:.0}% full; Claude will compact soon",
+                        used.min(1.0) * 100.0
+                    ),
+                ));
+            }
+        }
+
+        match self.budget_level {
+            BudgetLevel::Hard => problems.push((50, "A hard budget cap was reached".into())),
+            BudgetLevel::Soft => problems.push((20, "A soft budget cap was reached".into())),
+            BudgetLevel::Ok => {
+                if let Some(near) = self.budget_proximity.filter(|p| *p >= 0.8) {
+                    problems.push((10, format!("{:.0}% of a budget cap spent", near * 100.0)));
+                }
+            }
+        }
+
+        problems.sort_by_key(|(cost, _)| std::cmp::Reverse(*cost));
+        let lost: u32 = problems.iter().map(|(cost, _)| cost).sum();
+        let score = 100u32.saturating_sub(lost) as u8;
+        let level = match score {
+            80.. => HealthLevel::Good,
+            50..=79 => HealthLevel::Warning,
+            _ => HealthLevel::Critical,
+        };
+        SessionHealth {
+            score,
+            level,
+            warnings: problems.into_iter().map(|(_, warning)| warning).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_healthy_session() {
+        let health = HealthSignals {
+            recent_turns: 10,
+            context_tokens: Some(40_000),
+            budget_proximity: Some(0.2),
+            ..Default::default()
+        }
+        .evaluate();
+        assert_eq!(health, SessionHealth::default());
+    }
+
+    #[test]
+    fn test_problems_add_up() {
+        let health = HealthSignals {
+            recent_turns: 10,
+            recent_errors: 2,
+            quiet_secs: Some(15 * 60),
+            ..Default::default()
+        }
+        .evaluate();
+        // 10 for the errors, 35 for the stall
+        assert_eq!(health.score, 55);
+        assert_eq!(health.level, HealthLevel::Warning);
+        assert_eq!(
+            health.warnings,
+            [
+                "No output for 15m while a turn is running",
+                "2 of the last 10 turns ended in an error"
+            ]
+        );
+
+        let health = HealthSignals {
+            recent_turns: 4,
+            recent_errors: 4,
+            context_tokens: Some(CONTEXT_WINDOW_TOKENS),
+            budget_level: BudgetLevel::Hard,
+            ..Default::default()
+        }
+        .evaluate();
+        assert_eq!(health.score, 0);
+        assert_eq!(health.level, HealthLevel::Critical);
+        assert_eq!(health.warnings.len(), 3);
+    }
+
+    #[test]
+    fn test_short_quiet_spell_is_not_a_stall() {
+        let health = HealthSignals {
+            quiet_secs: Some(STALL_SECS - 1),
+            ..Default::default()
+        }
+        .evaluate();
+        assert_eq!(health.level, HealthLevel::Good);
+    }
+}
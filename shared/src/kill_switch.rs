@@ -0,0 +1,82 @@
+//! Workspace-wide kill switch
+//!
+//! For incident response: an admin can pause every running session at once.
+//! While the switch is engaged, proxies hold input back from Claude and deny
+//! new permission requests, and every page shows a banner saying why. The
+//! held input goes through once the switch is released.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Longest reason accepted, in characters
+pub const MAX_KILL_SWITCH_REASON_LEN: usize = 500;
+
+/// Body of `PUT /api/admin/kill-switch`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EngageKillSwitchRequest {
+    /// Shown in the banner and given to Claude with each denial
+    pub reason: String,
+}
+
+impl EngageKillSwitchRequest {
+    /// Problems that would make the request unusable, if any
+    pub fn validate(&self) -> Result<(), String> {
+        let reason = self.reason.trim();
+        if reason.is_empty() {
+            return Err("Say why sessions are being paused".to_string());
+        }
+        if reason.chars().count() > MAX_KILL_SWITCH_REASON_LEN {
+            return Err(format!(
+                "The reason must be at most {} characters",
+                MAX_KILL_SWITCH_REASON_LEN
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// An engaged kill switch
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KillSwitch {
+    pub reason: String,
+    /// Email of the admin who engaged it
+    pub engaged_by: String,
+    pub engaged_at: DateTime<Utc>,
+}
+
+impl KillSwitch {
+    /// The reason given to Claude for a permission request denied meanwhile
+    pub fn deny_reason(&self) -> String {
+        format!("All sessions are paused by an admin: {}", self.reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let request = |reason: &str| EngageKillSwitchRequest {
+            reason: reason.to_string(),
+        };
+        assert!(request("Runaway deletes on build hosts").validate().is_ok());
+        assert!(request("  ").validate().is_err());
+        assert!(request(&"x".repeat(MAX_KILL_SWITCH_REASON_LEN + 1))
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_deny_reason() {
+        let switch = KillSwitch {
+            reason: "Investigating leaked credentials".to_string(),
+            engaged_by: "oncall@example.com".to_string(),
+            engaged_at: Utc::now(),
+        };
+        assert_eq!(
+            switch.deny_reason(),
+            "All sessions are paused by an admin: Investigating leaked credentials"
+        );
+    }
+}
@@ -9,6 +9,153 @@ pub use proxy_tokens::*;
 pub mod api;
 pub use api::{ApiClientConfig, ApiError, CcProxyApi};
 
+// Incident bundle types and secret redaction
+pub mod incident;
+pub use incident::{redact_secrets, IncidentConfigSummary, IncidentInfo};
+
+// Budget caps and their evaluation
+pub mod budget;
+pub use budget::{
+    BudgetBreach, BudgetCap, BudgetKind, BudgetLevel, BudgetLimits, BudgetStatus, BudgetUsage,
+};
+
+// Admin-editable settings applied without a restart
+pub mod runtime_config;
+pub use runtime_config::{RuntimeConfig, RuntimeConfigInfo, RuntimeConfigOverrides};
+
+// Error codes carried by Error and RegisterAck
+pub mod error_code;
+pub use error_code::ErrorCode;
+
+// Limit on sessions a user has running at once
+pub mod quota;
+pub use quota::SessionQuota;
+
+// Files written during a session, exportable as a zip
+pub mod artifacts;
+pub use artifacts::{ArtifactInfo, MAX_ARTIFACT_ARCHIVE_BYTES};
+
+// W3C trace context carried on relayed messages
+pub mod trace;
+pub use trace::TraceContext;
+
+// Allow/deny rules remembered from the permission dialog
+pub mod permission_rules;
+pub use permission_rules::{PermissionRule, RememberedRule, RuleDecision, RuleScope};
+
+// Compression of large output payloads
+pub mod compression;
+pub use compression::ContentEncoding;
+
+// Notifying someone about, then defaulting, unanswered permission requests
+pub mod escalation;
+pub use escalation::{EscalationPolicy, EscalationStep};
+
+// Hiding sensitive tool output from session viewers
+pub mod redaction;
+pub use redaction::{RedactionPolicy, Redactor};
+
+// Site-wide maintenance banner set by admins
+pub mod maintenance;
+pub use maintenance::MaintenanceNotice;
+
+// Admin switch that pauses every running session during an incident
+pub mod kill_switch;
+pub use kill_switch::{EngageKillSwitchRequest, KillSwitch};
+
+// Protocol version negotiation between proxy, backend, and web clients
+pub mod protocol;
+pub use protocol::{MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+
+// Transcript search across sessions
+pub mod search;
+pub use search::{
+    RelatedSession, SaveSearchRequest, SavedSearch, SearchHit, SearchResponse,
+    UpdateSavedSearchRequest,
+};
+
+// Branching a new session from a point in another's history
+pub mod fork;
+pub use fork::{fork_command, SessionFork};
+
+// Reverting Claude's last turn
+pub mod undo;
+pub use undo::{last_turn, LastTurn};
+
+// Operator actions stored alongside Claude's output
+pub mod operator;
+pub use operator::{OperatorAction, OperatorEvent, OPERATOR_MESSAGE_TYPE};
+
+// Browser push notification subscriptions
+pub mod push;
+pub use push::{PushSubscriptionInfo, PushSubscriptionKeys, UnsubscribePushRequest};
+
+// Estimating how far a proxy's clock is off
+pub mod clock;
+pub use clock::{ClockSkew, ClockSkewReport, LARGE_CLOCK_SKEW_MS};
+
+// Badges external tools attach to ranges of messages
+pub mod annotations;
+pub use annotations::{AnnotationInfo, AnnotationLevel, CreateAnnotationRequest};
+
+// CI results for the branch a session is on
+pub mod ci;
+pub use ci::{CiRun, CiState, CiStatus};
+
+// Session snapshots mirrored from the proxy
+pub mod snapshot;
+pub use snapshot::{SnapshotSummary, MAX_SNAPSHOT_BYTES};
+
+// Context compaction boundaries and summaries
+pub mod compaction;
+pub use compaction::CompactBoundary;
+
+// Turns worked out from a session's messages
+pub mod turns;
+pub use turns::{TurnAnalyzer, TurnInfo, TurnRecord};
+
+// Stripping terminal control sequences from tool output
+pub mod sanitize;
+
+// Large pastes sent to the proxy as files
+pub mod paste;
+pub use paste::{AttachedInput, InputAttachment};
+
+// Approximate token counts for the input draft
+pub mod prompt_size;
+
+// Lint hints for the input draft
+pub mod prompt_lint;
+pub use prompt_lint::PromptHint;
+
+// Many sessions over one proxy daemon connection
+pub mod daemon;
+pub use daemon::DaemonFrame;
+
+// Languages for voice input
+pub mod voice;
+pub use voice::{SetVoiceLanguageRequest, VOICE_LANGUAGES};
+
+// Notable session events, stored for the workspace activity feed
+pub mod activity;
+pub use activity::{ActivityEvent, EventKind};
+
+// Health scores that flag sessions needing attention
+pub mod health;
+pub use health::{HealthLevel, HealthSignals, SessionHealth};
+
+// Which channels each notification reaches a user on
+pub mod notifications;
+pub use notifications::{
+    NotificationChannel, NotificationEvent, NotificationPreferences, NotificationSettings,
+};
+
+// Time-boxed access to one session for people without an account
+pub mod guests;
+pub use guests::{
+    CreateGuestInviteRequest, CreatedGuestInvite, GuestAccess, GuestInviteInfo, APPROVER_ROLE,
+};
+
 // Re-export claude-codes types for frontend message parsing
 pub use claude_codes::io::{
     ContentBlock, ImageBlock, ImageSource, PermissionSuggestion, TextBlock, ThinkingBlock,
@@ -38,17 +185,47 @@ pub enum ProxyMessage {
         /// Current git branch (if in a git repo)
         #[serde(default)]
         git_branch: Option<String>,
+        /// GitHub repository (`owner/repo`) of the `origin` remote, if any
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        git_repo: Option<String>,
+        /// Machine, checkout state and Claude version the proxy runs with
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        host_info: Option<HostInfo>,
         /// Only replay messages created after this timestamp (ISO 8601 format)
         /// If None, replay all history. Used by web clients to avoid duplicate messages.
         #[serde(default)]
         replay_after: Option<String>,
+        /// `message_seq` of the last message a reconnecting web client rendered.
+        /// Only later messages are replayed; takes precedence over `replay_after`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        last_seq: Option<u64>,
         /// Client version (e.g., "1.0.0") - helps track client versions in use
         #[serde(default)]
         client_version: Option<String>,
+        /// Content encodings the client can read and write for large outputs
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        accept_encodings: Vec<ContentEncoding>,
+        /// Wire protocol version; absent from clients that predate negotiation
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        protocol_version: Option<u32>,
     },
 
     /// Output from Claude Code to be displayed
-    ClaudeOutput { content: serde_json::Value },
+    ClaudeOutput {
+        content: serde_json::Value,
+        /// ID of the stored message (backend -> web clients), used to attach reactions
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        message_id: Option<Uuid>,
+        /// Sequence of the stored message (backend -> web clients), used to resume
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        message_seq: Option<u64>,
+        /// W3C traceparent of the span that relayed this output
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        traceparent: Option<String>,
+        /// Set when `content` is compressed; see [`compression`]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content_encoding: Option<ContentEncoding>,
+    },
 
     /// Input to Claude Code from user
     ClaudeInput {
@@ -56,17 +233,45 @@ pub enum ProxyMessage {
         /// Optional send mode (normal, wiggum)
         #[serde(default, skip_serializing_if = "Option::is_none")]
         send_mode: Option<SendMode>,
+        /// W3C traceparent of the client span that sent this input
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        traceparent: Option<String>,
     },
 
+    /// Stop the turn Claude is working on (web client -> backend -> proxy).
+    /// Only relayed to a connected proxy; queued, it could stop a later turn.
+    Interrupt,
+
     /// Heartbeat to keep connection alive
     Heartbeat,
 
+    /// Liveness probe, sent every [`HEARTBEAT_INTERVAL_MS`]. The receiver
+    /// answers with a `Pong` echoing `sent_at`.
+    Ping {
+        /// Sender's clock, in milliseconds since the Unix epoch
+        sent_at: u64,
+    },
+
+    /// Reply to a `Ping`; the sender computes round-trip time from `sent_at`
+    Pong { sent_at: u64 },
+
     /// Error message
-    Error { message: String },
+    Error {
+        /// What went wrong, for clients to act on
+        #[serde(default)]
+        code: ErrorCode,
+        /// Human-readable detail
+        #[serde(default)]
+        message: String,
+    },
 
     /// Session status update
     SessionStatus { status: SessionStatus },
 
+    /// The session's Claude process moved to a new lifecycle state
+    /// (proxy -> backend -> web clients; sent to a web client on join too)
+    StateChanged { state: SessionLifecycle },
+
     /// Permission request from Claude (tool wants to execute)
     PermissionRequest {
         /// Unique ID for this permission request (to correlate responses)
@@ -95,6 +300,25 @@ pub enum ProxyMessage {
         /// Optional reason for denial
         #[serde(skip_serializing_if = "Option::is_none")]
         reason: Option<String>,
+        /// Rule for the proxy to apply to later matching requests
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        remember: Option<RememberedRule>,
+    },
+
+    /// Protocol handshake (backend -> proxy/web client)
+    /// Answers a `Register` that carried a protocol version, before `RegisterAck`
+    Hello {
+        /// Protocol version the backend speaks
+        protocol_version: u32,
+        /// Oldest client protocol the backend accepts
+        min_protocol_version: u32,
+        /// Backend build version (e.g. "1.4.0")
+        server_version: String,
+        /// Version both sides will use; the lower of the two
+        negotiated_version: u32,
+        /// Why the client was rejected, if it was
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
     },
 
     /// Backend acknowledgment of session registration
@@ -106,6 +330,12 @@ pub enum ProxyMessage {
         /// Error message if registration failed
         #[serde(skip_serializing_if = "Option::is_none")]
         error: Option<String>,
+        /// Why registration failed, if it did
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error_code: Option<ErrorCode>,
+        /// Encoding agreed from the client's `accept_encodings`, if any
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content_encoding: Option<ContentEncoding>,
     },
 
     /// Update session metadata (e.g., git branch changed)
@@ -132,6 +362,16 @@ pub enum ProxyMessage {
         seq: u64,
         /// The actual output content
         content: serde_json::Value,
+        /// W3C traceparent assigned by the proxy when the output was read
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        traceparent: Option<String>,
+        /// Set when `content` is compressed; see [`compression`]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content_encoding: Option<ContentEncoding>,
+        /// When the proxy read the output, on its own clock in milliseconds
+        /// since the Unix epoch; see [`clock`]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        produced_at: Option<u64>,
     },
 
     /// Acknowledge receipt of output messages (backend -> proxy)
@@ -152,6 +392,9 @@ pub enum ProxyMessage {
         seq: i64,
         /// The actual input content
         content: serde_json::Value,
+        /// W3C traceparent of the backend span that forwarded this input
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        traceparent: Option<String>,
     },
 
     /// Acknowledge receipt of input messages (proxy -> backend)
@@ -170,7 +413,8 @@ pub enum ProxyMessage {
     StartVoice {
         /// The session to associate voice input with
         session_id: Uuid,
-        /// Language code for speech recognition (default: "en-US")
+        /// Language code for speech recognition (default: "en-US"), or
+        /// "auto" to let the provider detect it
         #[serde(default = "default_language_code")]
         language_code: String,
     },
@@ -208,6 +452,149 @@ pub enum ProxyMessage {
         session_id: Uuid,
     },
 
+    /// Reactions on a stored message changed (backend -> web clients)
+    MessageReactions {
+        /// The session the message belongs to
+        session_id: Uuid,
+        /// The message that was reacted to
+        message_id: Uuid,
+        /// Current reactions on the message, one entry per emoji
+        reactions: Vec<ReactionSummary>,
+    },
+
+    /// An external tool annotated messages in a session (backend -> web clients)
+    MessageAnnotation {
+        session_id: Uuid,
+        annotation: AnnotationInfo,
+    },
+
+    /// Who is currently viewing a session (backend -> web clients)
+    /// Sent whenever a viewer joins, leaves, or control changes hands
+    Presence {
+        session_id: Uuid,
+        /// Connected viewers, one entry per user
+        viewers: Vec<PresenceViewer>,
+    },
+
+    /// Ask to become the session's driver (web client -> backend)
+    /// Granted immediately when nobody is driving, otherwise forwarded to the driver
+    RequestControl,
+
+    /// Pass control to another connected viewer, or release it when `to_user_id` is None
+    /// (web client -> backend, driver only)
+    HandOffControl {
+        #[serde(default)]
+        to_user_id: Option<Uuid>,
+    },
+
+    /// Take control regardless of the current driver (web client -> backend, admins only)
+    TakeControl,
+
+    /// A viewer asked the current driver for control (backend -> web clients)
+    ControlRequested {
+        session_id: Uuid,
+        user_id: Uuid,
+        name: String,
+    },
+
+    /// A permission request the proxy answered itself from a remembered rule
+    /// (proxy -> backend), recorded in the session's permission history
+    PermissionRuleApplied {
+        request_id: String,
+        tool_name: String,
+        input: serde_json::Value,
+        allow: bool,
+        /// The rule that decided it, e.g. "Bash(cargo test)"
+        rule: String,
+    },
+
+    /// A permission request answered at the proxy's terminal after nobody
+    /// answered on the web (proxy -> backend), recorded and dismissed like
+    /// a web answer
+    PermissionAnsweredInTerminal { request_id: String, allow: bool },
+
+    /// A permission request the proxy denied because the kill switch is
+    /// engaged (proxy -> backend), recorded in the session's permission
+    /// history
+    PermissionDeniedByKillSwitch {
+        request_id: String,
+        tool_name: String,
+        input: serde_json::Value,
+        /// Whether the request had already been sent to the web UI, which
+        /// then still shows it
+        forwarded: bool,
+    },
+
+    /// A permission request in a session you can answer has waited past the
+    /// session's escalation delay (backend -> the notified user's clients)
+    PermissionEscalated {
+        session_id: Uuid,
+        session_name: String,
+        request_id: String,
+        tool_name: String,
+        waiting_minutes: i64,
+    },
+
+    /// A permission request was answered without a web client, e.g. by the
+    /// escalation default (backend -> web clients), so it can be dismissed
+    PermissionResolved { request_id: String, allow: bool },
+
+    /// New messages matched one of the user's alerting saved searches
+    /// (backend -> the owner's clients)
+    SearchAlert {
+        saved_search_id: Uuid,
+        name: String,
+        /// Newest first, capped at a few
+        hits: Vec<SearchHit>,
+        /// More matched than are listed
+        more: bool,
+    },
+
+    /// Diagnostics for a session that ended in an error or crashed (proxy -> backend)
+    /// The backend adds a sanitized transcript tail and stores the bundle
+    IncidentReport {
+        reason: String,
+        proxy_version: String,
+        /// OS and architecture, e.g. "linux-x86_64"
+        platform: String,
+        config_summary: IncidentConfigSummary,
+        /// Most recent proxy log lines, already redacted
+        #[serde(default)]
+        log_tail: Vec<String>,
+    },
+
+    /// A diagnostic bundle was recorded for a session (backend -> web clients)
+    IncidentCreated { incident: IncidentInfo },
+
+    /// Budget state of a session changed, or input was refused because a hard
+    /// cap was reached (backend -> web clients and proxy)
+    BudgetStatus {
+        session_id: Uuid,
+        status: BudgetStatus,
+    },
+
+    /// Ask the proxy to zip files from its working directory (backend -> proxy)
+    ArtifactsRequest {
+        /// Correlates the chunks of the reply
+        request_id: Uuid,
+        /// Paths as given to the Write tool
+        paths: Vec<String>,
+    },
+
+    /// Part of the zip archive answering an `ArtifactsRequest` (proxy -> backend)
+    ArtifactsChunk {
+        request_id: Uuid,
+        /// Base64-encoded archive bytes
+        #[serde(default)]
+        data: String,
+        /// Set on the last chunk
+        #[serde(default)]
+        done: bool,
+        /// Set instead of data if the archive couldn't be built
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+
     /// Server is shutting down (backend -> all clients)
     /// Sent to all connected WebSocket clients before graceful shutdown
     ServerShutdown {
@@ -216,10 +603,25 @@ pub enum ProxyMessage {
         /// Suggested delay before reconnecting (milliseconds)
         reconnect_delay_ms: u64,
     },
+
+    /// Maintenance banner changed (backend -> all clients)
+    /// Also sent on connect while a banner is up; `None` clears it
+    MaintenanceBanner {
+        #[serde(default)]
+        notice: Option<MaintenanceNotice>,
+    },
+
+    /// Kill switch engaged or released (backend -> all clients)
+    /// Also sent when a proxy registers, so one that was away learns of a
+    /// release; `None` means released
+    KillSwitch {
+        #[serde(default)]
+        state: Option<KillSwitch>,
+    },
 }
 
 fn default_language_code() -> String {
-    "en-US".to_string()
+    voice::DEFAULT_LANGUAGE.to_string()
 }
 
 /// Cost information for a single session
@@ -229,6 +631,84 @@ pub struct SessionCost {
     pub total_cost_usd: f64,
 }
 
+// ============================================================================
+// Message Reactions
+// ============================================================================
+
+/// How often each side of a WebSocket pings the other
+pub const HEARTBEAT_INTERVAL_MS: u64 = 5_000;
+
+/// A connection that has answered pings before is considered dead after
+/// this long without hearing anything. Peers that never answered a ping
+/// (older versions) are not timed out.
+pub const HEARTBEAT_TIMEOUT_MS: u64 = 15_000;
+
+/// Emoji that may be used as message reactions
+pub const REACTION_EMOJIS: &[&str] = &["👍", "👎", "⚠️", "🎉", "👀", "❓"];
+
+/// Aggregated reactions for a single emoji on a message
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReactionSummary {
+    pub emoji: String,
+    /// Display names of the users who reacted, in reaction order
+    pub users: Vec<String>,
+}
+
+/// All reactions on a single message
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MessageReactionsInfo {
+    pub message_id: Uuid,
+    pub reactions: Vec<ReactionSummary>,
+}
+
+/// Request to toggle the current user's reaction on a message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToggleReactionRequest {
+    pub emoji: String,
+}
+
+// ============================================================================
+// Turn Feedback
+// ============================================================================
+
+/// Thumbs-up/down rating left on a completed turn
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedbackRating {
+    Up,
+    Down,
+}
+
+impl FeedbackRating {
+    pub fn as_str(&self) -> &str {
+        match self {
+            FeedbackRating::Up => "up",
+            FeedbackRating::Down => "down",
+        }
+    }
+}
+
+/// Request to rate a result message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnFeedbackRequest {
+    pub rating: FeedbackRating,
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+/// Feedback the current user left on a turn
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TurnFeedbackInfo {
+    /// The result message that was rated (None once the message is pruned)
+    pub message_id: Option<Uuid>,
+    pub session_id: Uuid,
+    pub rating: FeedbackRating,
+    pub comment: Option<String>,
+    /// The user prompt that started the turn, captured when the feedback was left
+    pub prompt: Option<String>,
+    pub created_at: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum SessionStatus {
@@ -247,6 +727,103 @@ impl SessionStatus {
     }
 }
 
+/// Where a session's Claude process is in its lifecycle, as its proxy
+/// last reported
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionLifecycle {
+    Created,
+    Spawning,
+    Ready,
+    InTurn,
+    AwaitingPermission,
+    Paused,
+    Exited,
+}
+
+impl SessionLifecycle {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionLifecycle::Created => "created",
+            SessionLifecycle::Spawning => "spawning",
+            SessionLifecycle::Ready => "ready",
+            SessionLifecycle::InTurn => "in_turn",
+            SessionLifecycle::AwaitingPermission => "awaiting_permission",
+            SessionLifecycle::Paused => "paused",
+            SessionLifecycle::Exited => "exited",
+        }
+    }
+
+    /// Short description for status indicators
+    pub fn label(&self) -> &'static str {
+        match self {
+            SessionLifecycle::Created | SessionLifecycle::Spawning => "Starting",
+            SessionLifecycle::Ready => "Idle",
+            SessionLifecycle::InTurn => "Working",
+            SessionLifecycle::AwaitingPermission => "Needs permission",
+            SessionLifecycle::Paused => "Paused",
+            SessionLifecycle::Exited => "Exited",
+        }
+    }
+
+    /// Whether the session is waiting on a person: its turn is done, or a
+    /// tool needs permission
+    pub fn awaits_user(&self) -> bool {
+        matches!(
+            self,
+            SessionLifecycle::Ready | SessionLifecycle::AwaitingPermission
+        )
+    }
+}
+
+/// How a finished session turned out, labeled by its owner or an editor
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionOutcome {
+    Succeeded,
+    Partially,
+    Failed,
+    Abandoned,
+}
+
+impl SessionOutcome {
+    pub const ALL: [SessionOutcome; 4] = [
+        SessionOutcome::Succeeded,
+        SessionOutcome::Partially,
+        SessionOutcome::Failed,
+        SessionOutcome::Abandoned,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionOutcome::Succeeded => "succeeded",
+            SessionOutcome::Partially => "partially",
+            SessionOutcome::Failed => "failed",
+            SessionOutcome::Abandoned => "abandoned",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SessionOutcome::Succeeded => "Succeeded",
+            SessionOutcome::Partially => "Partially succeeded",
+            SessionOutcome::Failed => "Failed",
+            SessionOutcome::Abandoned => "Abandoned",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|o| o.as_str() == s)
+    }
+}
+
+/// Request to set (or clear) a session's outcome
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetSessionOutcomeRequest {
+    #[serde(default)]
+    pub outcome: Option<SessionOutcome>,
+}
+
 /// Send mode for user input
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
 #[serde(rename_all = "lowercase")]
@@ -274,8 +851,67 @@ pub struct SessionInfo {
     pub updated_at: Option<String>,
     #[serde(default)]
     pub git_branch: Option<String>,
+    /// GitHub repository (`owner/repo`) the session is working in
+    #[serde(default)]
+    pub git_repo: Option<String>,
+    /// CI on the session's branch, when GitHub reports it
+    #[serde(default)]
+    pub ci: Option<CiStatus>,
+    /// Where the proxy runs, as of its last registration
+    #[serde(default)]
+    pub host_info: Option<HostInfo>,
+    /// How likely the session is to need attention; running sessions only
+    #[serde(default)]
+    pub health: Option<SessionHealth>,
     /// The current user's role in this session (owner, editor, viewer)
     pub my_role: String,
+    #[serde(default)]
+    pub outcome: Option<SessionOutcome>,
+}
+
+/// The machine and checkout a proxy runs on, reported when it registers
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    /// Operating system, as Rust names it ("linux", "macos", "windows")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os: Option<String>,
+    /// URL of the `origin` remote, without credentials
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_remote: Option<String>,
+    /// Whether the working tree has uncommitted changes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_dirty: Option<bool>,
+    /// Version `claude --version` reports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claude_version: Option<String>,
+}
+
+impl HostInfo {
+    /// The OS as people write it
+    pub fn os_name(&self) -> Option<&str> {
+        self.os.as_deref().map(|os| match os {
+            "macos" => "macOS",
+            "linux" => "Linux",
+            "windows" => "Windows",
+            "freebsd" => "FreeBSD",
+            other => other,
+        })
+    }
+
+    /// One line for headers and tooltips, e.g. "devbox · Linux · claude 2.1.17"
+    pub fn summary(&self) -> String {
+        let claude = self
+            .claude_version
+            .as_ref()
+            .map(|v| format!("claude {}", v));
+        [self.hostname.as_deref(), self.os_name(), claude.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" · ")
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -297,6 +933,242 @@ pub struct MessageInfo {
     pub created_at: String,
 }
 
+// ============================================================================
+// Permission History
+// ============================================================================
+
+/// How a permission request was answered
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DecisionChannel {
+    /// A user in the web UI
+    Web,
+    /// The proxy, from a remembered rule
+    Rule,
+    /// The session's escalation default, after nobody answered in time
+    Timeout,
+    /// Denied because the proxy's token isn't scoped for the tool
+    Scope,
+    /// At the proxy's terminal
+    Terminal,
+    /// Denied by the proxy while the kill switch was engaged
+    #[serde(rename = "kill_switch")]
+    KillSwitch,
+}
+
+impl DecisionChannel {
+    pub fn as_str(&self) -> &str {
+        match self {
+            DecisionChannel::Web => "web",
+            DecisionChannel::Rule => "rule",
+            DecisionChannel::Timeout => "timeout",
+            DecisionChannel::Scope => "scope",
+            DecisionChannel::Terminal => "terminal",
+            DecisionChannel::KillSwitch => "kill_switch",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "web" => Some(DecisionChannel::Web),
+            "rule" => Some(DecisionChannel::Rule),
+            "timeout" => Some(DecisionChannel::Timeout),
+            "scope" => Some(DecisionChannel::Scope),
+            "terminal" => Some(DecisionChannel::Terminal),
+            "kill_switch" => Some(DecisionChannel::KillSwitch),
+            _ => None,
+        }
+    }
+}
+
+/// One permission request and, once answered, its decision
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PermissionDecisionInfo {
+    pub request_id: String,
+    pub tool_name: String,
+    pub input: serde_json::Value,
+    pub requested_at: String,
+    /// `None` while the request is still waiting
+    pub allowed: Option<bool>,
+    pub channel: Option<DecisionChannel>,
+    /// Display name of the user who decided, for web decisions
+    pub decided_by: Option<String>,
+    /// The deny reason, or the rule that decided it
+    pub detail: Option<String>,
+    pub decided_at: Option<String>,
+    /// When the session's escalation contact was notified, if it was
+    #[serde(default)]
+    pub escalated_at: Option<String>,
+    /// Who was notified
+    #[serde(default)]
+    pub escalated_to: Option<String>,
+}
+
+// ============================================================================
+// Presence
+// ============================================================================
+
+/// A user currently connected to a session from the web UI
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PresenceViewer {
+    pub user_id: Uuid,
+    /// Display name (falls back to email)
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+    /// Whether this user currently holds input control of the session
+    #[serde(default)]
+    pub driving: bool,
+}
+
+impl PresenceViewer {
+    /// Up to two initials for avatar placeholders
+    pub fn initials(&self) -> String {
+        let initials: String = self
+            .name
+            .split(|c: char| c.is_whitespace() || c == '.' || c == '@')
+            .filter_map(|part| part.chars().next())
+            .take(2)
+            .collect();
+        initials.to_uppercase()
+    }
+}
+
+// ============================================================================
+// Usage Reporting
+// ============================================================================
+
+/// Time window for usage reports
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum UsageRange {
+    #[serde(rename = "7d")]
+    Week,
+    #[default]
+    #[serde(rename = "30d")]
+    Month,
+    #[serde(rename = "90d")]
+    Quarter,
+    #[serde(rename = "all")]
+    All,
+}
+
+impl UsageRange {
+    pub const ALL: [UsageRange; 4] = [
+        UsageRange::Week,
+        UsageRange::Month,
+        UsageRange::Quarter,
+        UsageRange::All,
+    ];
+
+    /// Query-string form (`7d`, `30d`, `90d`, `all`)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UsageRange::Week => "7d",
+            UsageRange::Month => "30d",
+            UsageRange::Quarter => "90d",
+            UsageRange::All => "all",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            UsageRange::Week => "Last 7 days",
+            UsageRange::Month => "Last 30 days",
+            UsageRange::Quarter => "Last 90 days",
+            UsageRange::All => "All time",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|r| r.as_str() == s)
+    }
+
+    /// Number of days covered, including today (None for all time)
+    pub fn days(&self) -> Option<i64> {
+        match self {
+            UsageRange::Week => Some(7),
+            UsageRange::Month => Some(30),
+            UsageRange::Quarter => Some(90),
+            UsageRange::All => None,
+        }
+    }
+}
+
+/// Cost and token totals for a slice of usage
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct UsageTotals {
+    pub cost_usd: f64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cache_creation_tokens: i64,
+    pub cache_read_tokens: i64,
+    /// Completed turns (result messages)
+    pub turns: i64,
+}
+
+impl UsageTotals {
+    pub fn add(&mut self, other: &UsageTotals) {
+        self.cost_usd += other.cost_usd;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.turns += other.turns;
+    }
+}
+
+/// Usage for a single calendar day (UTC)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DailyUsage {
+    /// `YYYY-MM-DD`
+    pub day: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+/// Usage attributed to a single model
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ModelUsage {
+    pub model: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+/// Usage for a single session
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionUsage {
+    /// None once the session has been deleted; its usage still counts
+    pub session_id: Option<Uuid>,
+    pub session_name: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+/// How often a tool was called
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ToolUsage {
+    pub tool: String,
+    pub uses: i64,
+}
+
+/// Response for `GET /api/usage`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UsageReport {
+    pub range: UsageRange,
+    /// First day included (`YYYY-MM-DD`), None for all time
+    pub since: Option<String>,
+    pub totals: UsageTotals,
+    /// Every day in the range, oldest first (days without usage are zero)
+    pub by_day: Vec<DailyUsage>,
+    /// Most expensive first
+    pub by_model: Vec<ModelUsage>,
+    /// Most expensive first
+    pub by_session: Vec<SessionUsage>,
+    /// Most used first
+    #[serde(default)]
+    pub by_tool: Vec<ToolUsage>,
+}
+
 // ============================================================================
 // Device Flow Types (shared between backend and proxy)
 // ============================================================================
@@ -329,10 +1201,135 @@ pub enum DevicePollResponse {
 // App Configuration (served to frontend)
 // ============================================================================
 
+/// First-use setup progress returned by /api/onboarding
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OnboardingStatus {
+    /// The user has created a proxy token, directly or through `--init`
+    pub token_created: bool,
+    /// A proxy has registered a session for the user
+    pub proxy_connected: bool,
+    /// Claude has replied in one of the user's sessions
+    pub session_streamed: bool,
+    /// The server has speech recognition credentials
+    pub voice_available: bool,
+    /// An admin has enabled voice input for the user
+    pub voice_enabled: bool,
+}
+
+impl OnboardingStatus {
+    pub fn voice_configured(&self) -> bool {
+        self.voice_available && self.voice_enabled
+    }
+
+    /// Whether the required steps are done; voice is optional
+    pub fn setup_done(&self) -> bool {
+        self.token_created && self.proxy_connected && self.session_streamed
+    }
+}
+
 /// Application configuration returned by /api/config endpoint
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     /// Custom title for the app (displayed in top bar)
     /// Defaults to "Claude Code Sessions" if not configured
     pub app_title: String,
+    /// Where "Report a problem" sends users; `title` and `body` are appended
+    /// as query parameters, which GitHub's new-issue page pre-fills
+    #[serde(default = "default_issue_report_url")]
+    pub issue_report_url: String,
+    /// Whether `GET /api/search?mode=semantic` is available
+    #[serde(default)]
+    pub semantic_search: bool,
+    /// VAPID public key browsers subscribe with, base64url encoded; `None`
+    /// when push notifications aren't configured
+    #[serde(default)]
+    pub push_public_key: Option<String>,
+    /// Whether the speech provider can detect the spoken language, so voice
+    /// input may offer "auto"
+    #[serde(default)]
+    pub voice_auto_language: bool,
+    /// How much of long tool output the transcript shows
+    #[serde(default)]
+    pub render_limits: RenderLimits,
+}
+
+/// How much of long tool output the transcript shows before cutting it off
+/// or collapsing it. Set per deployment, so operators can tune verbosity
+/// without rebuilding the frontend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenderLimits {
+    /// Characters of a tool result shown before it's cut off
+    pub result_preview_chars: usize,
+    /// Tool results longer than this many lines start collapsed (0 = only
+    /// on phones)
+    pub result_collapse_lines: usize,
+    /// Written files up to this size are shown in full (bytes)
+    pub inline_file_max_bytes: usize,
+    /// Lines shown of a written file too large to inline
+    pub file_preview_lines: usize,
+}
+
+impl Default for RenderLimits {
+    fn default() -> Self {
+        Self {
+            result_preview_chars: 500,
+            result_collapse_lines: 0,
+            inline_file_max_bytes: 16 * 1024,
+            file_preview_lines: 20,
+        }
+    }
+}
+
+/// Default destination for problem reports
+pub const DEFAULT_ISSUE_REPORT_URL: &str =
+    "https://github.com/meawoppl/claude-code-portal/issues/new";
+
+fn default_issue_report_url() -> String {
+    DEFAULT_ISSUE_REPORT_URL.to_string()
+}
+
+/// What an account may do across the portal. Members see the sessions they
+/// own or were shared; admins see every session and manage users; viewers
+/// can watch sessions shared with them but can't run sessions or send input.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UserRole {
+    Admin,
+    #[default]
+    Member,
+    Viewer,
+}
+
+impl UserRole {
+    pub const ALL: [UserRole; 3] = [UserRole::Admin, UserRole::Member, UserRole::Viewer];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserRole::Admin => "admin",
+            UserRole::Member => "member",
+            UserRole::Viewer => "viewer",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            UserRole::Admin => "Admin",
+            UserRole::Member => "Member",
+            UserRole::Viewer => "Viewer",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|r| r.as_str() == s)
+    }
+}
+
+/// A sign-in option returned by `GET /api/auth/providers`; the button links
+/// to `/api/auth/{id}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthProviderInfo {
+    /// `google`, `github`, or `oidc`
+    pub id: String,
+    /// Button label, e.g. "GitHub" or the configured `OIDC_NAME`
+    pub name: String,
 }
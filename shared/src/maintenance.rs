@@ -0,0 +1,104 @@
+//! Operator maintenance notices
+//!
+//! An admin can post a banner that every web client and proxy shows, with an
+//! optional downtime window, so users hear about a restart before their
+//! sessions drop instead of after.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Longest banner message accepted, in characters
+pub const MAX_MAINTENANCE_MESSAGE_LEN: usize = 500;
+
+/// Banner text plus an optional scheduled downtime window
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MaintenanceNotice {
+    pub message: String,
+    /// When downtime is expected to begin
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub starts_at: Option<DateTime<Utc>>,
+    /// When service is expected to be back; the notice lapses after this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+impl MaintenanceNotice {
+    /// Problems that would make the notice unusable, if any
+    pub fn validate(&self) -> Result<(), String> {
+        let message = self.message.trim();
+        if message.is_empty() {
+            return Err("The banner needs a message".to_string());
+        }
+        if message.chars().count() > MAX_MAINTENANCE_MESSAGE_LEN {
+            return Err(format!(
+                "The message must be at most {} characters",
+                MAX_MAINTENANCE_MESSAGE_LEN
+            ));
+        }
+        if let (Some(starts), Some(ends)) = (self.starts_at, self.ends_at) {
+            if ends <= starts {
+                return Err("The window must end after it starts".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the downtime window has passed at `now`
+    pub fn is_over(&self, now: DateTime<Utc>) -> bool {
+        self.ends_at.is_some_and(|ends| now >= ends)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 24, hour, 0, 0).unwrap()
+    }
+
+    fn notice() -> MaintenanceNotice {
+        MaintenanceNotice {
+            message: "Upgrading the database".to_string(),
+            starts_at: Some(at(2)),
+            ends_at: Some(at(3)),
+        }
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(notice().validate().is_ok());
+
+        let blank = MaintenanceNotice {
+            message: "  ".to_string(),
+            ..notice()
+        };
+        assert!(blank.validate().is_err());
+
+        let backwards = MaintenanceNotice {
+            starts_at: Some(at(4)),
+            ..notice()
+        };
+        assert!(backwards.validate().is_err());
+
+        let too_long = MaintenanceNotice {
+            message: "x".repeat(MAX_MAINTENANCE_MESSAGE_LEN + 1),
+            ..notice()
+        };
+        assert!(too_long.validate().is_err());
+    }
+
+    #[test]
+    fn test_lapses_after_window() {
+        let notice = notice();
+        assert!(!notice.is_over(at(1)));
+        assert!(notice.is_over(at(3)));
+
+        let open_ended = MaintenanceNotice {
+            ends_at: None,
+            ..notice
+        };
+        assert!(!open_ended.is_over(at(23)));
+    }
+}
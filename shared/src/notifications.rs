@@ -0,0 +1,186 @@
+//! Notification routing
+//!
+//! Events worth telling someone about go out over whichever channels the
+//! server has configured. Each user chooses which channels each event
+//! reaches them on; events they haven't chosen for follow the defaults.
+//! Slack posts to one shared channel, so an event is posted there when any
+//! recipient routes it to Slack. Prompt events go to push and chat by
+//! default; the daily digest goes to email.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A way of reaching people outside the portal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationChannel {
+    /// Browser notifications on subscribed devices
+    Push,
+    /// The server's Slack channel
+    Slack,
+    /// Direct messages from the server's Telegram bot
+    Telegram,
+    /// Mail to the address the user signed in with
+    Email,
+}
+
+impl NotificationChannel {
+    pub const ALL: [Self; 4] = [Self::Push, Self::Slack, Self::Telegram, Self::Email];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Push => "Browser",
+            Self::Slack => "Slack",
+            Self::Telegram => "Telegram",
+            Self::Email => "Email",
+        }
+    }
+}
+
+/// Something that happened in a session that someone may want to hear about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    /// Claude asked for permission to use a tool
+    PermissionRequested,
+    /// A permission request has gone unanswered for a while
+    PermissionWaiting,
+    /// A session's escalation policy named this user for a waiting request
+    PermissionEscalated,
+    /// Claude finished a turn
+    TurnFinished,
+    /// A summary of a session's turns over the previous UTC day
+    DailyDigest,
+}
+
+impl NotificationEvent {
+    pub const ALL: [Self; 5] = [
+        Self::PermissionRequested,
+        Self::PermissionWaiting,
+        Self::PermissionEscalated,
+        Self::TurnFinished,
+        Self::DailyDigest,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::PermissionRequested => "Permission requested",
+            Self::PermissionWaiting => "Permission still waiting",
+            Self::PermissionEscalated => "Permission escalated to you",
+            Self::TurnFinished => "Turn finished",
+            Self::DailyDigest => "Daily digest",
+        }
+    }
+
+    /// Channels the event goes to for users who haven't chosen
+    pub fn default_channels(&self) -> &'static [NotificationChannel] {
+        use NotificationChannel::*;
+        match self {
+            Self::PermissionRequested => &[Push, Telegram],
+            Self::PermissionWaiting => &[Slack],
+            Self::PermissionEscalated => &[Push, Telegram],
+            Self::TurnFinished => &[Push, Slack, Telegram],
+            Self::DailyDigest => &[Email],
+        }
+    }
+}
+
+/// A user's notification choices
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationPreferences {
+    /// Channels per event; events missing here use their defaults
+    #[serde(default)]
+    pub routes: BTreeMap<NotificationEvent, BTreeSet<NotificationChannel>>,
+    /// Telegram chat the bot messages, from the user's `/start` with it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub telegram_chat_id: Option<String>,
+}
+
+impl NotificationPreferences {
+    /// Channels `event` reaches this user on
+    pub fn channels(&self, event: NotificationEvent) -> BTreeSet<NotificationChannel> {
+        match self.routes.get(&event) {
+            Some(channels) => channels.clone(),
+            None => event.default_channels().iter().copied().collect(),
+        }
+    }
+
+    /// Whether `event` reaches this user on `channel`
+    pub fn routes_to(&self, event: NotificationEvent, channel: NotificationChannel) -> bool {
+        self.channels(event).contains(&channel)
+    }
+
+    /// Problems that would make the preferences unusable, if any
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(chat_id) = &self.telegram_chat_id {
+            // Private chats are numeric; channels and groups may be @names
+            let chat_id = chat_id.trim();
+            let numeric = chat_id
+                .strip_prefix('-')
+                .unwrap_or(chat_id)
+                .chars()
+                .all(|c| c.is_ascii_digit());
+            if chat_id.is_empty() || !(numeric || chat_id.starts_with('@')) {
+                return Err("A Telegram chat ID is a number or an @name".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `GET /api/notifications`: the user's choices and the channels this
+/// server can deliver on
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NotificationSettings {
+    pub preferences: NotificationPreferences,
+    #[serde(default)]
+    pub available: Vec<NotificationChannel>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_and_overrides() {
+        use NotificationChannel::*;
+        use NotificationEvent::*;
+
+        let mut preferences = NotificationPreferences::default();
+        assert!(preferences.routes_to(PermissionRequested, Push));
+        assert!(!preferences.routes_to(PermissionRequested, Slack));
+
+        preferences
+            .routes
+            .insert(PermissionRequested, [Push, Slack].into());
+        preferences.routes.insert(TurnFinished, BTreeSet::new());
+        assert!(preferences.routes_to(PermissionRequested, Slack));
+        assert!(preferences.channels(TurnFinished).is_empty());
+        assert!(preferences.routes_to(PermissionWaiting, Slack));
+        assert_eq!(preferences.channels(DailyDigest), [Email].into());
+    }
+
+    #[test]
+    fn test_wire_format() {
+        let json = r#"{"routes":{"turn_finished":["slack"]},"telegram_chat_id":"12345"}"#;
+        let preferences: NotificationPreferences = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            preferences.channels(NotificationEvent::TurnFinished),
+            [NotificationChannel::Slack].into()
+        );
+        assert_eq!(serde_json::to_string(&preferences).unwrap(), json);
+        assert!(preferences.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_chat_id() {
+        let with_chat = |id: &str| NotificationPreferences {
+            telegram_chat_id: Some(id.to_string()),
+            ..Default::default()
+        };
+        assert!(with_chat("-100123").validate().is_ok());
+        assert!(with_chat("@team_alerts").validate().is_ok());
+        assert!(with_chat("").validate().is_err());
+        assert!(with_chat("me").validate().is_err());
+    }
+}
@@ -0,0 +1,118 @@
+//! Operator actions recorded in session transcripts
+//!
+//! Claude's output only shows its half of a session. What people do in the
+//! web UI (sending prompts, interrupting, answering permission requests) is
+//! stored in the same message stream as `{"type": "operator", ...}` entries,
+//! so history and replays show who did what, and when, between Claude's turns.
+
+use crate::SendMode;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// The `type` of stored operator messages
+pub const OPERATOR_MESSAGE_TYPE: &str = "operator";
+
+/// What the operator did
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum OperatorAction {
+    /// Sent a prompt; Claude echoes the text when it reads it
+    Input {
+        content: String,
+        #[serde(default)]
+        send_mode: SendMode,
+    },
+    /// Stopped Claude's turn
+    Interrupt,
+    /// Answered a permission request
+    PermissionDecision {
+        request_id: String,
+        #[serde(default)]
+        tool_name: Option<String>,
+        allowed: bool,
+        #[serde(default)]
+        reason: Option<String>,
+    },
+}
+
+/// One operator action as stored in the transcript
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OperatorEvent {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub user_id: Uuid,
+    pub user_name: String,
+    #[serde(flatten)]
+    pub action: OperatorAction,
+}
+
+impl OperatorEvent {
+    pub fn new(user_id: Uuid, user_name: String, action: OperatorAction) -> Self {
+        Self {
+            kind: OPERATOR_MESSAGE_TYPE.to_string(),
+            user_id,
+            user_name,
+            action,
+        }
+    }
+
+    /// "Alice interrupted Claude", for history views
+    pub fn describe(&self) -> String {
+        self.action.describe(&self.user_name)
+    }
+}
+
+impl OperatorAction {
+    /// What `user_name` did, as a sentence fragment
+    pub fn describe(&self, user_name: &str) -> String {
+        match self {
+            OperatorAction::Input {
+                send_mode: SendMode::Wiggum,
+                ..
+            } => format!("{} started a Wiggum loop", user_name),
+            OperatorAction::Input { .. } => format!("{} sent a prompt", user_name),
+            OperatorAction::Interrupt => format!("{} interrupted Claude", user_name),
+            OperatorAction::PermissionDecision {
+                tool_name, allowed, ..
+            } => format!(
+                "{} {} {}",
+                user_name,
+                if *allowed { "allowed" } else { "denied" },
+                tool_name.as_deref().unwrap_or("a tool request")
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operator_event_format() {
+        let event = OperatorEvent::new(
+            Uuid::nil(),
+            "Alice".to_string(),
+            OperatorAction::PermissionDecision {
+                request_id: "req-1".to_string(),
+                tool_name: Some("Bash".to_string()),
+                allowed: false,
+                reason: None,
+            },
+        );
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "operator");
+        assert_eq!(json["action"], "permission_decision");
+        assert_eq!(json["tool_name"], "Bash");
+        assert_eq!(event.describe(), "Alice denied Bash");
+
+        let parsed: OperatorEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, event);
+
+        let interrupt: OperatorEvent = serde_json::from_str(
+            r#"{"type":"operator","action":"interrupt","user_id":"00000000-0000-0000-0000-000000000000","user_name":"Bob"}"#,
+        )
+        .unwrap();
+        assert_eq!(interrupt.describe(), "Bob interrupted Claude");
+    }
+}
@@ -0,0 +1,190 @@
+//! Large pastes sent as files
+//!
+//! Pasting a few hundred lines into the prompt bloats both the transcript and
+//! Claude's context. The web client offers to send such a paste as an
+//! attachment instead: the input's content becomes an [`AttachedInput`], the
+//! proxy writes each attachment under [`PASTE_DIR`] in the working directory,
+//! and Claude gets the typed text plus a one-line reference to each file.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Pastes longer than this many lines are offered as an attachment
+pub const LARGE_PASTE_LINES: usize = 200;
+
+/// Where the proxy writes attachments, relative to the working directory
+pub const PASTE_DIR: &str = ".claude-portal/pastes";
+
+/// Longest attachment name kept, in characters
+const MAX_NAME_CHARS: usize = 64;
+
+/// A file sent along with a prompt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputAttachment {
+    /// Suggested file name; see [`safe_file_name`]
+    pub name: String,
+    pub content: String,
+}
+
+impl InputAttachment {
+    pub fn line_count(&self) -> usize {
+        self.content.lines().count()
+    }
+}
+
+/// Input content carrying attachments, sent in place of a plain string
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttachedInput {
+    pub text: String,
+    pub attachments: Vec<InputAttachment>,
+}
+
+impl AttachedInput {
+    /// Read input content, if it carries attachments
+    pub fn from_content(content: &Value) -> Option<Self> {
+        content.get("attachments")?;
+        serde_json::from_value(content.clone()).ok()
+    }
+
+    pub fn to_content(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+
+    /// The prompt Claude gets once the attachments are saved at `paths`
+    pub fn prompt(&self, paths: &[String]) -> String {
+        let references: Vec<String> = self
+            .attachments
+            .iter()
+            .zip(paths)
+            .map(|(attachment, path)| reference(path, attachment.line_count()))
+            .collect();
+        if self.text.trim().is_empty() {
+            references.join("\n")
+        } else {
+            format!("{}\n\n{}", self.text, references.join("\n"))
+        }
+    }
+
+    /// The typed text, with a note of how many files came with it
+    pub fn summary(&self) -> String {
+        match self.attachments.len() {
+            0 => self.text.clone(),
+            1 => format!("{} [+1 attached file]", self.text),
+            n => format!("{} [+{} attached files]", self.text, n),
+        }
+    }
+}
+
+/// The line standing in for an attachment in the prompt
+pub fn reference(path: &str, lines: usize) -> String {
+    let unit = if lines == 1 { "line" } else { "lines" };
+    format!("[Pasted text ({} {}) saved to {}]", lines, unit, path)
+}
+
+/// Input content as text: a plain string as is, attached input as its
+/// summary, anything else as JSON
+pub fn input_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        other => match AttachedInput::from_content(other) {
+            Some(input) => input.summary(),
+            None => other.to_string(),
+        },
+    }
+}
+
+/// Whether pasted text is long enough to offer sending as a file
+pub fn is_large_paste(text: &str) -> bool {
+    text.lines().count() > LARGE_PASTE_LINES
+}
+
+/// A file name that stays inside [`PASTE_DIR`]: no separators or leading
+/// dots, only ASCII letters, digits, `-`, `_` and `.`, and never empty
+pub fn safe_file_name(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let cleaned: String = base
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(MAX_NAME_CHARS)
+        .collect();
+    let cleaned = cleaned.trim_start_matches('.');
+    if cleaned.is_empty() {
+        "paste.txt".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn paste(lines: usize) -> String {
+        (0..lines).map(|i| format!("line {}\n", i)).collect()
+    }
+
+    #[test]
+    fn test_large_paste() {
+        assert!(!is_large_paste(&paste(LARGE_PASTE_LINES)));
+        assert!(is_large_paste(&paste(LARGE_PASTE_LINES + 1)));
+    }
+
+    #[test]
+    fn test_content_round_trip() {
+        let input = AttachedInput {
+            text: "what's wrong here?".to_string(),
+            attachments: vec![InputAttachment {
+                name: "paste.log".to_string(),
+                content: paste(3),
+            }],
+        };
+        let content = input.to_content();
+        assert_eq!(AttachedInput::from_content(&content), Some(input.clone()));
+        assert_eq!(AttachedInput::from_content(&json!("plain")), None);
+        assert_eq!(AttachedInput::from_content(&json!({"text": "x"})), None);
+
+        assert_eq!(
+            input.prompt(&[".claude-portal/pastes/paste.log".to_string()]),
+            "what's wrong here?\n\n[Pasted text (3 lines) saved to .claude-portal/pastes/paste.log]"
+        );
+        assert_eq!(
+            input_text(&content),
+            "what's wrong here? [+1 attached file]"
+        );
+        assert_eq!(input_text(&json!("hi")), "hi");
+    }
+
+    #[test]
+    fn test_prompt_without_text() {
+        let input = AttachedInput {
+            text: " ".to_string(),
+            attachments: vec![InputAttachment {
+                name: "a.txt".to_string(),
+                content: "x".to_string(),
+            }],
+        };
+        assert_eq!(
+            input.prompt(&["p/a.txt".to_string()]),
+            "[Pasted text (1 line) saved to p/a.txt]"
+        );
+    }
+
+    #[test]
+    fn test_safe_file_name() {
+        assert_eq!(safe_file_name("paste-1.txt"), "paste-1.txt");
+        assert_eq!(safe_file_name("../../etc/passwd"), "passwd");
+        assert_eq!(safe_file_name("..\\..\\boot.ini"), "boot.ini");
+        assert_eq!(safe_file_name(".bashrc"), "bashrc");
+        assert_eq!(safe_file_name("my notes (1).md"), "my_notes__1_.md");
+        assert_eq!(safe_file_name(".."), "paste.txt");
+        assert_eq!(safe_file_name(""), "paste.txt");
+        assert_eq!(safe_file_name(&"a".repeat(200)).len(), 64);
+    }
+}
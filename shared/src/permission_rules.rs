@@ -0,0 +1,177 @@
+//! Permission rules remembered from the approval dialog
+//!
+//! Ticking "always allow/deny this" on a permission request sends a rule
+//! with the response. The proxy then answers matching requests itself,
+//! either for the rest of the session or, for persistent rules, in every
+//! later session in the same working directory.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleDecision {
+    Allow,
+    Deny,
+}
+
+impl RuleDecision {
+    pub fn as_str(&self) -> &str {
+        match self {
+            RuleDecision::Allow => "allow",
+            RuleDecision::Deny => "deny",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "allow" => Some(RuleDecision::Allow),
+            "deny" => Some(RuleDecision::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// How long a remembered rule lasts
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleScope {
+    /// Until the proxy exits
+    Session,
+    /// Saved to the proxy's rules file for this working directory
+    Persistent,
+}
+
+/// The part of a tool input a rule matches against: the command for Bash,
+/// the path for file tools. Other tools are matched by name alone.
+pub fn rule_target<'a>(tool_name: &str, input: &'a serde_json::Value) -> Option<&'a str> {
+    let field = match tool_name {
+        "Bash" => "command",
+        "Read" | "Edit" | "Write" => "file_path",
+        "NotebookEdit" => "notebook_path",
+        "Glob" | "Grep" => "path",
+        "WebFetch" => "url",
+        _ => return None,
+    };
+    input.get(field).and_then(|v| v.as_str())
+}
+
+/// Allow or deny a tool, optionally only for a matching target
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PermissionRule {
+    pub tool_name: String,
+    /// Exact target, or a prefix when it ends in `*`. `None` matches any
+    /// use of the tool.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    pub decision: RuleDecision,
+}
+
+impl PermissionRule {
+    /// A rule matching exactly this request
+    pub fn for_request(tool_name: &str, input: &serde_json::Value, decision: RuleDecision) -> Self {
+        Self {
+            tool_name: tool_name.to_string(),
+            pattern: rule_target(tool_name, input).map(str::to_string),
+            decision,
+        }
+    }
+
+    pub fn matches(&self, tool_name: &str, input: &serde_json::Value) -> bool {
+        if self.tool_name != tool_name {
+            return false;
+        }
+        let Some(pattern) = &self.pattern else {
+            return true;
+        };
+        let Some(target) = rule_target(tool_name, input) else {
+            return false;
+        };
+        match pattern.strip_suffix('*') {
+            Some(prefix) => target.starts_with(prefix),
+            None => target == pattern,
+        }
+    }
+
+    /// e.g. "Bash(cargo test)"
+    pub fn describe(&self) -> String {
+        match &self.pattern {
+            Some(pattern) => format!("{}({})", self.tool_name, pattern),
+            None => self.tool_name.clone(),
+        }
+    }
+}
+
+/// A rule to remember, sent along with a permission response
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RememberedRule {
+    pub rule: PermissionRule,
+    pub scope: RuleScope,
+}
+
+/// The decision of the first matching rule, with deny rules checked first
+pub fn evaluate_rules<'a>(
+    rules: impl IntoIterator<Item = &'a PermissionRule> + Clone,
+    tool_name: &str,
+    input: &serde_json::Value,
+) -> Option<&'a PermissionRule> {
+    let matching = |decision| {
+        rules
+            .clone()
+            .into_iter()
+            .find(|r| r.decision == decision && r.matches(tool_name, input))
+    };
+    matching(RuleDecision::Deny).or_else(|| matching(RuleDecision::Allow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_rule_matching() {
+        let exact = PermissionRule::for_request(
+            "Bash",
+            &json!({"command": "cargo test"}),
+            RuleDecision::Allow,
+        );
+        assert_eq!(exact.describe(), "Bash(cargo test)");
+        assert!(exact.matches("Bash", &json!({"command": "cargo test"})));
+        assert!(!exact.matches("Bash", &json!({"command": "cargo test --all"})));
+        assert!(!exact.matches("Read", &json!({"file_path": "cargo test"})));
+
+        let prefix = PermissionRule {
+            tool_name: "Read".to_string(),
+            pattern: Some("/w/src/*".to_string()),
+            decision: RuleDecision::Allow,
+        };
+        assert!(prefix.matches("Read", &json!({"file_path": "/w/src/main.rs"})));
+        assert!(!prefix.matches("Read", &json!({"file_path": "/w/README.md"})));
+
+        let any = PermissionRule::for_request("Task", &json!({}), RuleDecision::Allow);
+        assert_eq!(any.pattern, None);
+        assert!(any.matches("Task", &json!({"prompt": "x"})));
+    }
+
+    #[test]
+    fn test_deny_rules_win() {
+        let rules = vec![
+            PermissionRule {
+                tool_name: "Bash".to_string(),
+                pattern: None,
+                decision: RuleDecision::Allow,
+            },
+            PermissionRule::for_request(
+                "Bash",
+                &json!({"command": "rm -rf /"}),
+                RuleDecision::Deny,
+            ),
+        ];
+        let decision = |command: &str| {
+            evaluate_rules(&rules, "Bash", &json!({ "command": command })).map(|r| r.decision)
+        };
+        assert_eq!(decision("ls"), Some(RuleDecision::Allow));
+        assert_eq!(decision("rm -rf /"), Some(RuleDecision::Deny));
+        assert_eq!(evaluate_rules(&rules, "Read", &json!({})), None);
+    }
+}
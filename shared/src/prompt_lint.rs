@@ -0,0 +1,126 @@
+//! Prompt lint hints
+//!
+//! Cheap checks the web client runs on the draft in the input box, for
+//! mistakes that would otherwise cost a turn: a code block left open, a
+//! stray backtick, or a prompt that talks about an attachment nobody
+//! attached. They're hints shown under the input; nothing is blocked.
+
+/// Something that looks wrong with a draft
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptHint {
+    /// An odd number of ``` fences
+    UnclosedCodeBlock,
+    /// An odd number of single backticks outside code blocks
+    UnmatchedBacktick,
+    /// The text mentions an attachment but none is attached
+    MissingAttachment,
+}
+
+impl PromptHint {
+    pub fn message(self) -> &'static str {
+        match self {
+            PromptHint::UnclosedCodeBlock => "Code block isn't closed with ```",
+            PromptHint::UnmatchedBacktick => "Unmatched backtick",
+            PromptHint::MissingAttachment => {
+                "Did you mean to attach the file you referenced? Nothing is attached"
+            }
+        }
+    }
+}
+
+/// Words that suggest the writer thinks something is attached
+const ATTACHMENT_PHRASES: &[&str] = &["attached", "attachment", "see the file below"];
+
+/// Hints for `text`, in the order they should be shown
+pub fn lint_prompt(text: &str, has_attachments: bool) -> Vec<PromptHint> {
+    let mut hints = Vec::new();
+
+    // Fences toggle code blocks; inline backticks only count outside them
+    let mut in_block = false;
+    let mut inline_ticks = 0;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_block = !in_block;
+            continue;
+        }
+        if !in_block {
+            // Runs like `` `a` `` pair up with a run of the same length
+            inline_ticks += backtick_runs(line);
+        }
+    }
+    if in_block {
+        hints.push(PromptHint::UnclosedCodeBlock);
+    } else if inline_ticks % 2 == 1 {
+        hints.push(PromptHint::UnmatchedBacktick);
+    }
+
+    if !has_attachments {
+        let lower = text.to_lowercase();
+        if ATTACHMENT_PHRASES
+            .iter()
+            .any(|phrase| contains_word(&lower, phrase))
+        {
+            hints.push(PromptHint::MissingAttachment);
+        }
+    }
+    hints
+}
+
+/// Number of separate runs of backticks in `line`
+fn backtick_runs(line: &str) -> usize {
+    let mut runs = 0;
+    let mut previous = None;
+    for c in line.chars() {
+        if c == '`' && previous != Some('`') {
+            runs += 1;
+        }
+        previous = Some(c);
+    }
+    runs
+}
+
+/// Whether `phrase` appears in `text` as whole words, so "unattached"
+/// doesn't count
+fn contains_word(text: &str, phrase: &str) -> bool {
+    text.match_indices(phrase).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + phrase.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_prompts() {
+        assert!(lint_prompt("fix the `parse` function", false).is_empty());
+        assert!(lint_prompt("run ``cargo `test` ``", false).is_empty());
+        assert!(lint_prompt("```rust\nlet a = `b;\n```\ndone", false).is_empty());
+        assert!(lint_prompt("", false).is_empty());
+    }
+
+    #[test]
+    fn test_backticks() {
+        assert_eq!(
+            lint_prompt("look at `main.rs and fix it", false),
+            vec![PromptHint::UnmatchedBacktick]
+        );
+        // An open fence wins over any inline count
+        assert_eq!(
+            lint_prompt("here:\n```\nfn main() {}", false),
+            vec![PromptHint::UnclosedCodeBlock]
+        );
+    }
+
+    #[test]
+    fn test_missing_attachment() {
+        assert_eq!(
+            lint_prompt("The attached log shows the crash", false),
+            vec![PromptHint::MissingAttachment]
+        );
+        assert!(lint_prompt("The attached log shows the crash", true).is_empty());
+        assert!(lint_prompt("an unattached volume", false).is_empty());
+    }
+}
@@ -0,0 +1,105 @@
+//! Approximate prompt sizes
+//!
+//! The web client shows roughly how many tokens the draft in the input box
+//! will cost, and warns when that's a large share of the context Claude has
+//! left. There's no tokenizer in the browser, so [`estimate_tokens`] counts
+//! characters instead; it's meant to tell 2K from 20K, not to be exact.
+
+use serde_json::Value;
+
+/// Context window assumed for a session
+pub const CONTEXT_WINDOW_TOKENS: u64 = 200_000;
+
+/// A draft using more than this share of the remaining context, in
+/// percent, is flagged as large
+pub const LARGE_PROMPT_PERCENT: u64 = 10;
+
+/// ASCII characters per token, about right for English prose and code
+const ASCII_CHARS_PER_TOKEN: u64 = 4;
+
+/// Approximate token count of `text`. Other scripts tokenize far less
+/// densely than ASCII, so each non-ASCII character counts as a token.
+pub fn estimate_tokens(text: &str) -> u64 {
+    let mut ascii: u64 = 0;
+    let mut other: u64 = 0;
+    for c in text.chars() {
+        if c.is_ascii() {
+            ascii += 1;
+        } else {
+            other += 1;
+        }
+    }
+    ascii.div_ceil(ASCII_CHARS_PER_TOKEN) + other
+}
+
+/// Tokens of context in use as of an assistant message: everything it was
+/// given, cached or not. `None` for other messages.
+pub fn context_used(message: &Value) -> Option<u64> {
+    if message.get("type")?.as_str()? != "assistant" {
+        return None;
+    }
+    let usage = message.pointer("/message/usage")?;
+    let field = |name: &str| usage.get(name).and_then(Value::as_u64).unwrap_or(0);
+    Some(
+        field("input_tokens")
+            + field("cache_read_input_tokens")
+            + field("cache_creation_input_tokens"),
+    )
+}
+
+/// Context left after `used` tokens
+pub fn context_remaining(used: Option<u64>) -> u64 {
+    CONTEXT_WINDOW_TOKENS.saturating_sub(used.unwrap_or(0))
+}
+
+/// Whether a draft of `tokens` is large next to the context remaining
+pub fn is_large_prompt(tokens: u64, remaining: u64) -> bool {
+    tokens * 100 > remaining * LARGE_PROMPT_PERCENT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abc"), 1);
+        assert_eq!(estimate_tokens("fix the failing test"), 5);
+        assert_eq!(estimate_tokens("日本語"), 3);
+        assert_eq!(estimate_tokens(&"x".repeat(4_000)), 1_000);
+    }
+
+    #[test]
+    fn test_context_used() {
+        let assistant = json!({
+            "type": "assistant",
+            "message": {"usage": {
+                "input_tokens": 10,
+                "cache_read_input_tokens": 50_000,
+                "cache_creation_input_tokens": 2_000,
+                "output_tokens": 300,
+            }},
+        });
+        assert_eq!(context_used(&assistant), Some(52_010));
+        assert_eq!(
+            context_used(&json!({"type": "assistant", "message": {}})),
+            None
+        );
+        assert_eq!(context_used(&json!({"type": "result", "usage": {}})), None);
+    }
+
+    #[test]
+    fn test_large_prompt() {
+        assert_eq!(context_remaining(None), CONTEXT_WINDOW_TOKENS);
+        assert_eq!(context_remaining(Some(250_000)), 0);
+
+        let remaining = context_remaining(Some(100_000));
+        assert!(!is_large_prompt(10_000, remaining));
+        assert!(is_large_prompt(10_001, remaining));
+        // Anything is large once the context is full
+        assert!(is_large_prompt(1, 0));
+        assert!(!is_large_prompt(0, 0));
+    }
+}
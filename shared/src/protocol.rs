@@ -0,0 +1,80 @@
+//! WebSocket protocol versioning
+//!
+//! Proxies and web clients announce [`PROTOCOL_VERSION`] in `Register`, and
+//! the backend answers with `Hello` before `RegisterAck`. Clients built
+//! before negotiation existed send no version and are treated as version 0.
+//! Bump the version when a message changes in a way older peers can't
+//! ignore, and raise [`MIN_PROTOCOL_VERSION`] once the server stops
+//! supporting an older version.
+
+/// Protocol version spoken by this build
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Oldest peer version this build still works with. 0 means clients from
+/// before version negotiation are still served.
+pub const MIN_PROTOCOL_VERSION: u32 = 0;
+
+/// How a peer's protocol version relates to ours
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compatibility {
+    /// Same version
+    Current,
+    /// Different but supported; both sides speak the lower of the two
+    Degraded { negotiated: u32 },
+    /// Older than we support
+    TooOld,
+}
+
+/// Compare a peer's announced version (`None` if it predates negotiation)
+/// with ours
+// The minimum is 0 until clients without negotiation are dropped
+#[allow(clippy::absurd_extreme_comparisons)]
+pub fn check_peer(peer: Option<u32>) -> Compatibility {
+    let peer = peer.unwrap_or(0);
+    if peer < MIN_PROTOCOL_VERSION {
+        Compatibility::TooOld
+    } else if peer == PROTOCOL_VERSION {
+        Compatibility::Current
+    } else {
+        Compatibility::Degraded {
+            negotiated: peer.min(PROTOCOL_VERSION),
+        }
+    }
+}
+
+/// Error sent to a client whose protocol the backend no longer supports
+pub fn too_old_message(peer: Option<u32>) -> String {
+    format!(
+        "This client speaks protocol version {}, but the server requires {} or newer. \
+         Update with `claude-portal --update` (or reload the page) and try again.",
+        peer.unwrap_or(0),
+        MIN_PROTOCOL_VERSION
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_version_is_current() {
+        assert_eq!(check_peer(Some(PROTOCOL_VERSION)), Compatibility::Current);
+    }
+
+    #[test]
+    fn test_newer_peer_negotiates_down() {
+        assert_eq!(
+            check_peer(Some(PROTOCOL_VERSION + 1)),
+            Compatibility::Degraded {
+                negotiated: PROTOCOL_VERSION
+            }
+        );
+    }
+
+    #[test]
+    fn test_legacy_peer_is_version_zero() {
+        // Still served for now; becomes TooOld once the minimum is raised
+        assert_eq!(check_peer(None), Compatibility::Degraded { negotiated: 0 });
+        assert!(too_old_message(None).contains("version 0"));
+    }
+}
@@ -56,6 +56,113 @@ pub struct CreateProxyTokenRequest {
     /// Token lifetime in days (default: 30)
     #[serde(default = "default_expires_in_days")]
     pub expires_in_days: u32,
+    /// What the token may do; unrestricted by default
+    #[serde(default)]
+    pub scopes: ProxyTokenScopes,
+}
+
+/// Kinds of tool a scoped token can be limited to, by tool name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolCategory {
+    /// Read, Glob, Grep, LS
+    Read,
+    /// Edit, MultiEdit, Write, NotebookEdit
+    Edit,
+    /// Bash and background shells
+    Execute,
+    /// WebFetch, WebSearch
+    Web,
+    /// MCP server tools (`mcp__*`)
+    Mcp,
+    /// Anything else, e.g. Task
+    Other,
+}
+
+impl ToolCategory {
+    pub const ALL: [ToolCategory; 6] = [
+        ToolCategory::Read,
+        ToolCategory::Edit,
+        ToolCategory::Execute,
+        ToolCategory::Web,
+        ToolCategory::Mcp,
+        ToolCategory::Other,
+    ];
+
+    pub fn of(tool_name: &str) -> Self {
+        match tool_name {
+            "Read" | "Glob" | "Grep" | "LS" | "NotebookRead" => ToolCategory::Read,
+            "Edit" | "MultiEdit" | "Write" | "NotebookEdit" => ToolCategory::Edit,
+            "Bash" | "BashOutput" | "KillShell" | "KillBash" => ToolCategory::Execute,
+            "WebFetch" | "WebSearch" => ToolCategory::Web,
+            name if name.starts_with("mcp__") => ToolCategory::Mcp,
+            _ => ToolCategory::Other,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ToolCategory::Read => "read",
+            ToolCategory::Edit => "edit",
+            ToolCategory::Execute => "execute",
+            ToolCategory::Web => "web",
+            ToolCategory::Mcp => "mcp",
+            ToolCategory::Other => "other",
+        }
+    }
+}
+
+/// Restrictions on a proxy token, e.g. one shared with a contractor. The
+/// default restricts nothing, which is what tokens issued before scopes
+/// existed get.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProxyTokenScopes {
+    /// Working directories sessions may run in, each including everything
+    /// below it; empty allows any
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub directories: Vec<String>,
+    /// Only watch sessions (`claude-portal tail`): no starting sessions,
+    /// sending input, or answering permission requests
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub read_only: bool,
+    /// Sessions the token may have running at once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_sessions: Option<u32>,
+    /// Tool categories permission requests may be answered for; other
+    /// requests are denied without asking. `None` allows all.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_categories: Option<Vec<ToolCategory>>,
+}
+
+impl ProxyTokenScopes {
+    pub fn is_unrestricted(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether `path` is one of the allowed directories or inside one.
+    /// Paths with `..` components never match a restriction.
+    pub fn allows_directory(&self, path: &str) -> bool {
+        if self.directories.is_empty() {
+            return true;
+        }
+        if path.split('/').any(|part| part == "..") {
+            return false;
+        }
+        let path = path.trim_end_matches('/');
+        self.directories.iter().any(|dir| {
+            let dir = dir.trim_end_matches('/');
+            match path.strip_prefix(dir) {
+                Some(rest) => rest.is_empty() || rest.starts_with('/') || dir.is_empty(),
+                None => false,
+            }
+        })
+    }
+
+    pub fn allows_tool(&self, tool_name: &str) -> bool {
+        self.tool_categories
+            .as_ref()
+            .is_none_or(|allowed| allowed.contains(&ToolCategory::of(tool_name)))
+    }
 }
 
 fn default_expires_in_days() -> u32 {
@@ -84,6 +191,28 @@ pub struct ProxyTokenInfo {
     pub last_used_at: Option<String>,
     pub expires_at: String,
     pub revoked: bool,
+    /// Address the token was last used from
+    #[serde(default)]
+    pub last_used_host: Option<String>,
+    #[serde(default)]
+    pub scopes: ProxyTokenScopes,
+}
+
+/// A session opened with a proxy token
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProxyTokenSession {
+    pub id: Uuid,
+    pub session_name: String,
+    pub working_directory: String,
+    pub status: String,
+    pub last_activity: String,
+}
+
+/// One token with the sessions it opened, most recent first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProxyTokenDetail {
+    pub token: ProxyTokenInfo,
+    pub sessions: Vec<ProxyTokenSession>,
 }
 
 /// List of proxy tokens
@@ -195,4 +324,43 @@ mod tests {
         assert_eq!(config.token, decoded.token);
         assert_eq!(config.session_name_prefix, decoded.session_name_prefix);
     }
+
+    #[test]
+    fn test_scope_directories() {
+        let scopes = ProxyTokenScopes {
+            directories: vec!["/work/client/".to_string()],
+            ..Default::default()
+        };
+        assert!(scopes.allows_directory("/work/client"));
+        assert!(scopes.allows_directory("/work/client/api/"));
+        assert!(!scopes.allows_directory("/work/client-other"));
+        assert!(!scopes.allows_directory("/work/client/../secrets"));
+        assert!(!scopes.allows_directory("/home/me"));
+        assert!(ProxyTokenScopes::default().allows_directory("/anywhere"));
+    }
+
+    #[test]
+    fn test_scope_tools() {
+        let scopes = ProxyTokenScopes {
+            tool_categories: Some(vec![ToolCategory::Read, ToolCategory::Edit]),
+            ..Default::default()
+        };
+        assert!(scopes.allows_tool("Grep"));
+        assert!(scopes.allows_tool("MultiEdit"));
+        assert!(!scopes.allows_tool("Bash"));
+        assert!(!scopes.allows_tool("mcp__github__create_issue"));
+        assert!(ProxyTokenScopes::default().allows_tool("Bash"));
+    }
+
+    #[test]
+    fn test_unscoped_request_parses() {
+        let req: CreateProxyTokenRequest = serde_json::from_str(r#"{"name":"laptop"}"#).unwrap();
+        assert!(req.scopes.is_unrestricted());
+        assert_eq!(req.expires_in_days, 30);
+        // Unrestricted scopes serialize to nothing
+        assert_eq!(
+            serde_json::to_string(&ProxyTokenScopes::default()).unwrap(),
+            "{}"
+        );
+    }
 }
@@ -0,0 +1,41 @@
+//! Web Push subscriptions
+//!
+//! The browser's `PushSubscription.toJSON()` has the same shape as
+//! [`PushSubscriptionInfo`], so the frontend posts it to the backend as is.
+
+use serde::{Deserialize, Serialize};
+
+/// Where to deliver pushes for one browser, and the keys to encrypt them to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PushSubscriptionInfo {
+    pub endpoint: String,
+    pub keys: PushSubscriptionKeys,
+}
+
+/// Base64url-encoded keys from the browser
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PushSubscriptionKeys {
+    /// The browser's P-256 public key
+    pub p256dh: String,
+    /// 16-byte authentication secret
+    pub auth: String,
+}
+
+/// Body of `DELETE /api/push/subscriptions`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnsubscribePushRequest {
+    pub endpoint: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_browser_subscription() {
+        let json = r#"{"endpoint":"https://fcm.googleapis.com/fcm/send/abc","expirationTime":null,"keys":{"p256dh":"BCVx","auth":"BTBZ"}}"#;
+        let info: PushSubscriptionInfo = serde_json::from_str(json).unwrap();
+        assert_eq!(info.endpoint, "https://fcm.googleapis.com/fcm/send/abc");
+        assert_eq!(info.keys.auth, "BTBZ");
+    }
+}
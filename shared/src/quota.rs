@@ -0,0 +1,65 @@
+//! Concurrent session quota
+//!
+//! Admins can cap how many sessions each user has running at once, so one
+//! person can't spin up enough proxies to drain a shared API budget. The
+//! backend checks the quota when a proxy registers and reports usage with
+//! the session list.
+
+use serde::{Deserialize, Serialize};
+
+/// A user's running sessions against their limit
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct SessionQuota {
+    /// Sessions the user owns whose proxy is connected
+    pub running: u32,
+    /// Most sessions allowed at once; `None` means no limit
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+impl SessionQuota {
+    /// `max_per_user` is the configured limit, where 0 means none
+    pub fn new(running: u32, max_per_user: u32) -> Self {
+        Self {
+            running,
+            limit: (max_per_user > 0).then_some(max_per_user),
+        }
+    }
+
+    /// Whether starting another session would go over the limit
+    pub fn is_full(&self) -> bool {
+        self.limit.is_some_and(|limit| self.running >= limit)
+    }
+
+    /// Error shown to a proxy turned away because the quota is full
+    pub fn exceeded_message(&self) -> String {
+        format!(
+            "Session limit reached: {} of {} sessions are already running. \
+             Stop one of them, or ask an admin to raise the limit.",
+            self.running,
+            self.limit.unwrap_or(self.running)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zero_means_unlimited() {
+        let quota = SessionQuota::new(50, 0);
+        assert_eq!(quota.limit, None);
+        assert!(!quota.is_full());
+    }
+
+    #[test]
+    fn test_full_at_limit() {
+        assert!(!SessionQuota::new(2, 3).is_full());
+        assert!(SessionQuota::new(3, 3).is_full());
+        assert!(SessionQuota::new(4, 3).is_full());
+        assert!(SessionQuota::new(3, 3)
+            .exceeded_message()
+            .contains("3 of 3"));
+    }
+}
@@ -0,0 +1,286 @@
+//! Redacting transcripts for viewers
+//!
+//! A session owner can mark the whole session, or particular tools, as
+//! sensitive. Members with the viewer role then see a placeholder in place of
+//! those tools' results (command output, file contents read) and of the file
+//! contents written through their input, in the transcript and in permission
+//! requests alike. The rest of a tool call's input, such as a Bash command or
+//! a file path, is still shown. Owners and editors see everything.
+//!
+//! The backend redacts wherever a viewer gets either: live output and
+//! permission requests, history, the SSE stream, search, and the permission
+//! history. What it can't redact, raw snapshots, forks, artifact downloads
+//! and incident bundles, it refuses to viewers instead.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// What a viewer sees in place of redacted content
+pub const REDACTED: &str = "[redacted]";
+
+/// Tools offered as sensitive in the session settings
+pub const SUGGESTED_TOOLS: [&str; 5] = ["Bash", "Read", "Write", "Edit", "Grep"];
+
+/// Tool input fields that carry file contents, by tool
+const CONTENT_FIELDS: &[(&str, &[&str])] = &[
+    ("Write", &["content"]),
+    ("Edit", &["old_string", "new_string"]),
+    ("MultiEdit", &["edits"]),
+    ("NotebookEdit", &["new_source"]),
+];
+
+/// Per-session redaction settings. Nothing is redacted by default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RedactionPolicy {
+    /// Redact every tool's results
+    #[serde(default)]
+    pub whole_session: bool,
+    /// Tools whose results are redacted, e.g. "Bash"
+    #[serde(default)]
+    pub tools: Vec<String>,
+}
+
+impl RedactionPolicy {
+    /// Whether the policy redacts anything
+    pub fn is_empty(&self) -> bool {
+        !self.whole_session && self.tools.is_empty()
+    }
+
+    /// Whether a tool's results are redacted
+    pub fn covers(&self, tool_name: &str) -> bool {
+        self.whole_session || self.tools.iter().any(|tool| tool == tool_name)
+    }
+
+    /// The same policy with tool names trimmed, blanks dropped, and each
+    /// named once
+    pub fn normalized(mut self) -> Self {
+        let mut tools: Vec<String> = Vec::new();
+        for tool in self.tools.iter().map(|tool| tool.trim()) {
+            if !tool.is_empty() && !tools.iter().any(|t| t == tool) {
+                tools.push(tool.to_string());
+            }
+        }
+        self.tools = tools;
+        self
+    }
+}
+
+/// Redacts a session's messages for a viewer. Fed messages in order, it
+/// remembers which tool each result answers; a result whose tool call it
+/// never saw is redacted, since it can't tell.
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    policy: RedactionPolicy,
+    /// Tool name by tool use id
+    tools: HashMap<String, String>,
+}
+
+impl Redactor {
+    pub fn new(policy: RedactionPolicy) -> Self {
+        Self {
+            policy,
+            tools: HashMap::new(),
+        }
+    }
+
+    /// Redact one message's content in place, returning whether it changed
+    pub fn apply(&mut self, content: &mut Value) -> bool {
+        match content.get("type").and_then(Value::as_str) {
+            Some("assistant") => self.redact_tool_uses(content),
+            Some("user") => self.redact_tool_results(content),
+            _ => false,
+        }
+    }
+
+    fn redact_tool_uses(&mut self, content: &mut Value) -> bool {
+        let Some(blocks) = content_blocks(content) else {
+            return false;
+        };
+        let mut changed = false;
+        for block in blocks {
+            if block.get("type").and_then(Value::as_str) != Some("tool_use") {
+                continue;
+            }
+            let name = block
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            if let Some(id) = block.get("id").and_then(Value::as_str) {
+                self.tools.insert(id.to_string(), name.clone());
+            }
+            if let Some(input) = block.get_mut("input") {
+                changed |= self.redact_input(&name, input);
+            }
+        }
+        changed
+    }
+
+    /// Redact a tool call's input in place, as sent with a permission
+    /// request or kept in the permission history, returning whether it
+    /// changed. Only the file contents a covered tool writes are hidden.
+    pub fn redact_input(&self, tool_name: &str, input: &mut Value) -> bool {
+        if !self.policy.covers(tool_name) {
+            return false;
+        }
+        let fields = CONTENT_FIELDS
+            .iter()
+            .find(|(tool, _)| *tool == tool_name)
+            .map_or(&[][..], |(_, fields)| *fields);
+        let Some(input) = input.as_object_mut() else {
+            return false;
+        };
+        let mut changed = false;
+        for field in fields {
+            if let Some(value) = input.get_mut(*field) {
+                *value = Value::String(REDACTED.to_string());
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    fn redact_tool_results(&mut self, content: &mut Value) -> bool {
+        let mut changed = false;
+        if let Some(blocks) = content_blocks(content) {
+            for block in blocks {
+                if block.get("type").and_then(Value::as_str) != Some("tool_result") {
+                    continue;
+                }
+                let covered = block
+                    .get("tool_use_id")
+                    .and_then(Value::as_str)
+                    .and_then(|id| self.tools.get(id))
+                    .is_none_or(|name| self.policy.covers(name));
+                if covered {
+                    block["content"] = Value::String(REDACTED.to_string());
+                    changed = true;
+                }
+            }
+        }
+        // Claude repeats the result in a structured form alongside the message
+        if changed {
+            if let Some(result) = content.get_mut("tool_use_result") {
+                *result = Value::String(REDACTED.to_string());
+            }
+        }
+        changed
+    }
+}
+
+/// A message's content blocks, if it has a list of them
+fn content_blocks(content: &mut Value) -> Option<&mut Vec<Value>> {
+    content
+        .get_mut("message")?
+        .get_mut("content")?
+        .as_array_mut()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn tool_use(id: &str, name: &str, input: Value) -> Value {
+        json!({
+            "type": "assistant",
+            "message": {"content": [
+                {"type": "text", "text": "Let me look"},
+                {"type": "tool_use", "id": id, "name": name, "input": input}
+            ]}
+        })
+    }
+
+    fn tool_result(id: &str, output: &str) -> Value {
+        json!({
+            "type": "user",
+            "message": {"content": [
+                {"type": "tool_result", "tool_use_id": id, "content": output}
+            ]},
+            "tool_use_result": {"stdout": output}
+        })
+    }
+
+    #[test]
+    fn test_only_listed_tools_are_redacted() {
+        let mut redactor = Redactor::new(RedactionPolicy {
+            whole_session: false,
+            tools: vec!["Bash".to_string()],
+        });
+
+        let mut bash = tool_use("t1", "Bash", json!({"command": "cat .env"}));
+        assert!(!redactor.apply(&mut bash), "the command itself is shown");
+        let mut output = tool_result("t1", "API_KEY=hunter2");
+        assert!(redactor.apply(&mut output));
+        assert_eq!(output["message"]["content"][0]["content"], REDACTED);
+        assert_eq!(output["tool_use_result"], REDACTED);
+
+        let mut grep = tool_use("t2", "Grep", json!({"pattern": "fn main"}));
+        redactor.apply(&mut grep);
+        let mut found = tool_result("t2", "src/main.rs");
+        assert!(!redactor.apply(&mut found));
+        assert_eq!(found["message"]["content"][0]["content"], "src/main.rs");
+    }
+
+    #[test]
+    fn test_whole_session_redacts_results_and_written_files() {
+        let mut redactor = Redactor::new(RedactionPolicy {
+            whole_session: true,
+            tools: Vec::new(),
+        });
+
+        let mut write = tool_use(
+            "t1",
+            "Write",
+            json!({"file_path": "/src/secret.rs", "content": "const KEY: &str = \"k\";"}),
+        );
+        assert!(redactor.apply(&mut write));
+        let input = &write["message"]["content"][1]["input"];
+        assert_eq!(input["file_path"], "/src/secret.rs");
+        assert_eq!(input["content"], REDACTED);
+        assert_eq!(write["message"]["content"][0]["text"], "Let me look");
+
+        let mut text = json!({"type": "result", "result": "Done"});
+        assert!(!redactor.apply(&mut text));
+    }
+
+    #[test]
+    fn test_redact_input() {
+        let redactor = Redactor::new(RedactionPolicy {
+            whole_session: false,
+            tools: vec!["Edit".to_string()],
+        });
+
+        let mut edit = json!({"file_path": "/etc/app.toml", "old_string": "a", "new_string": "b"});
+        assert!(redactor.redact_input("Edit", &mut edit));
+        assert_eq!(edit["file_path"], "/etc/app.toml");
+        assert_eq!(edit["old_string"], REDACTED);
+        assert_eq!(edit["new_string"], REDACTED);
+
+        let mut write = json!({"file_path": "/a", "content": "secret"});
+        assert!(!redactor.redact_input("Write", &mut write));
+        assert_eq!(write["content"], "secret");
+    }
+
+    #[test]
+    fn test_unmatched_results_are_redacted() {
+        let mut redactor = Redactor::new(RedactionPolicy {
+            whole_session: false,
+            tools: vec!["Read".to_string()],
+        });
+        let mut orphan = tool_result("unseen", "contents");
+        assert!(redactor.apply(&mut orphan));
+    }
+
+    #[test]
+    fn test_normalized() {
+        let policy = RedactionPolicy {
+            whole_session: false,
+            tools: vec![" Bash".into(), "".into(), "Bash".into(), "Read".into()],
+        }
+        .normalized();
+        assert_eq!(policy.tools, vec!["Bash", "Read"]);
+        assert!(RedactionPolicy::default().is_empty());
+    }
+}
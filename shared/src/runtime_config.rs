@@ -0,0 +1,256 @@
+//! Runtime configuration
+//!
+//! Settings that can change while the server is running, as opposed to ports,
+//! database URLs, and secrets, which are read once at startup. The server's
+//! environment supplies the defaults and admins override individual fields.
+
+use crate::RenderLimits;
+use serde::{Deserialize, Serialize};
+
+/// Effective runtime settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RuntimeConfig {
+    /// Title shown in the top bar and browser tab
+    pub app_title: String,
+    /// Where "Report a problem" opens a pre-filled issue
+    pub issue_report_url: String,
+    /// Emails in this domain may sign in (alongside `allowed_emails`)
+    pub allowed_email_domain: Option<String>,
+    /// Specific emails that may sign in, lowercase
+    pub allowed_emails: Option<Vec<String>>,
+    /// Messages kept per session
+    pub message_retention_count: i64,
+    /// Days messages are kept (0 = no age limit)
+    pub message_retention_days: u32,
+    /// Sessions each user may have running at once (0 = no limit)
+    #[serde(default)]
+    pub max_sessions_per_user: u32,
+    /// API requests each proxy token or signed-in user may make per minute
+    /// (0 = no limit)
+    #[serde(default)]
+    pub rate_limit_requests_per_minute: u32,
+    /// WebSocket messages each proxy token or signed-in user may send per
+    /// minute (0 = no limit)
+    #[serde(default)]
+    pub rate_limit_messages_per_minute: u32,
+    /// How much of long tool output the transcript shows
+    #[serde(default)]
+    pub render_limits: RenderLimits,
+}
+
+/// Admin overrides on top of the environment defaults.
+///
+/// Absent fields use the default. An empty domain or email list overrides a
+/// default restriction with none.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RuntimeConfigOverrides {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issue_report_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_email_domain: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_emails: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_retention_count: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_retention_days: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_sessions_per_user: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit_requests_per_minute: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit_messages_per_minute: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result_preview_chars: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result_collapse_lines: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inline_file_max_bytes: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_preview_lines: Option<usize>,
+}
+
+impl RuntimeConfigOverrides {
+    /// Problems that would make the overrides unusable, if any
+    pub fn validate(&self) -> Result<(), String> {
+        if self
+            .app_title
+            .as_deref()
+            .is_some_and(|t| t.trim().is_empty())
+        {
+            return Err("App title can't be empty".to_string());
+        }
+        if let Some(ref url) = self.issue_report_url {
+            if !(url.starts_with("https://") || url.starts_with("http://")) {
+                return Err("Issue report URL must start with http:// or https://".to_string());
+            }
+        }
+        if self.message_retention_count.is_some_and(|c| c < 1) {
+            return Err("Retention count must be at least 1".to_string());
+        }
+        if self.result_preview_chars.is_some_and(|c| c < 1)
+            || self.file_preview_lines.is_some_and(|l| l < 1)
+        {
+            return Err("Preview lengths must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl RuntimeConfig {
+    pub fn with_overrides(&self, overrides: &RuntimeConfigOverrides) -> RuntimeConfig {
+        let allowed_email_domain = match overrides.allowed_email_domain.as_deref() {
+            Some(domain) => Some(domain.trim().to_lowercase()).filter(|d| !d.is_empty()),
+            None => self.allowed_email_domain.clone(),
+        };
+        let allowed_emails = match overrides.allowed_emails.as_ref() {
+            Some(emails) => Some(
+                emails
+                    .iter()
+                    .map(|e| e.trim().to_lowercase())
+                    .filter(|e| !e.is_empty())
+                    .collect::<Vec<_>>(),
+            )
+            .filter(|emails| !emails.is_empty()),
+            None => self.allowed_emails.clone(),
+        };
+        RuntimeConfig {
+            app_title: overrides
+                .app_title
+                .clone()
+                .unwrap_or_else(|| self.app_title.clone()),
+            issue_report_url: overrides
+                .issue_report_url
+                .clone()
+                .unwrap_or_else(|| self.issue_report_url.clone()),
+            allowed_email_domain,
+            allowed_emails,
+            message_retention_count: overrides
+                .message_retention_count
+                .unwrap_or(self.message_retention_count),
+            message_retention_days: overrides
+                .message_retention_days
+                .unwrap_or(self.message_retention_days),
+            max_sessions_per_user: overrides
+                .max_sessions_per_user
+                .unwrap_or(self.max_sessions_per_user),
+            rate_limit_requests_per_minute: overrides
+                .rate_limit_requests_per_minute
+                .unwrap_or(self.rate_limit_requests_per_minute),
+            rate_limit_messages_per_minute: overrides
+                .rate_limit_messages_per_minute
+                .unwrap_or(self.rate_limit_messages_per_minute),
+            render_limits: RenderLimits {
+                result_preview_chars: overrides
+                    .result_preview_chars
+                    .unwrap_or(self.render_limits.result_preview_chars),
+                result_collapse_lines: overrides
+                    .result_collapse_lines
+                    .unwrap_or(self.render_limits.result_collapse_lines),
+                inline_file_max_bytes: overrides
+                    .inline_file_max_bytes
+                    .unwrap_or(self.render_limits.inline_file_max_bytes),
+                file_preview_lines: overrides
+                    .file_preview_lines
+                    .unwrap_or(self.render_limits.file_preview_lines),
+            },
+        }
+    }
+}
+
+/// Response of `GET /api/admin/config`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RuntimeConfigInfo {
+    /// Values from the server's environment
+    pub defaults: RuntimeConfig,
+    pub overrides: RuntimeConfigOverrides,
+    /// What the server is using now
+    pub effective: RuntimeConfig,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> RuntimeConfig {
+        RuntimeConfig {
+            app_title: "Claude Code Sessions".to_string(),
+            issue_report_url: "https://example.com/issues/new".to_string(),
+            allowed_email_domain: Some("example.com".to_string()),
+            allowed_emails: None,
+            message_retention_count: 100,
+            message_retention_days: 30,
+            max_sessions_per_user: 0,
+            rate_limit_requests_per_minute: 0,
+            rate_limit_messages_per_minute: 0,
+            render_limits: RenderLimits::default(),
+        }
+    }
+
+    #[test]
+    fn test_no_overrides_keeps_defaults() {
+        assert_eq!(
+            defaults().with_overrides(&RuntimeConfigOverrides::default()),
+            defaults()
+        );
+    }
+
+    #[test]
+    fn test_overrides_replace_fields() {
+        let effective = defaults().with_overrides(&RuntimeConfigOverrides {
+            app_title: Some("Team Portal".to_string()),
+            allowed_email_domain: Some(" ".to_string()),
+            allowed_emails: Some(vec![" Alice@Example.com ".to_string(), "".to_string()]),
+            message_retention_days: Some(0),
+            file_preview_lines: Some(50),
+            ..Default::default()
+        });
+        assert_eq!(effective.app_title, "Team Portal");
+        assert_eq!(effective.allowed_email_domain, None);
+        assert_eq!(
+            effective.allowed_emails,
+            Some(vec!["alice@example.com".to_string()])
+        );
+        assert_eq!(effective.message_retention_days, 0);
+        assert_eq!(effective.message_retention_count, 100);
+        assert_eq!(effective.render_limits.file_preview_lines, 50);
+        assert_eq!(effective.render_limits.result_preview_chars, 500);
+    }
+
+    #[test]
+    fn test_overrides_serialize_only_set_fields() {
+        let overrides = RuntimeConfigOverrides {
+            message_retention_count: Some(50),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_string(&overrides).unwrap(),
+            r#"{"message_retention_count":50}"#
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(RuntimeConfigOverrides::default().validate().is_ok());
+        assert!(RuntimeConfigOverrides {
+            issue_report_url: Some("javascript:alert(1)".to_string()),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(RuntimeConfigOverrides {
+            message_retention_count: Some(0),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(RuntimeConfigOverrides {
+            result_preview_chars: Some(0),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,221 @@
+//! Terminal control sequence sanitizer
+//!
+//! Tool output can carry escape sequences meant for a terminal: cursor
+//! movement, screen clears, window titles, clipboard writes (OSC 52) and
+//! hyperlinks (OSC 8). None of them mean anything in the web UI, and some can
+//! mislead it, so they're stripped before output is stored or relayed. SGR
+//! color sequences (`ESC [ ... m`) are kept; tabs and line breaks are kept.
+//! Every other control character is dropped.
+
+use serde_json::Value;
+use std::borrow::Cow;
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+
+/// C1 control introducers, the 8-bit forms of `ESC [`, `ESC ]`, and so on
+const C1_CSI: char = '\u{9b}';
+const C1_OSC: char = '\u{9d}';
+const C1_ST: char = '\u{9c}';
+
+/// Whether `c` is a control character that needs handling
+fn is_control(c: char) -> bool {
+    (c.is_control() && !matches!(c, '\n' | '\t' | '\r')) || c == '\u{7f}'
+}
+
+/// A CSI sequence's parameter bytes and final byte, if it's color (SGR)
+fn is_sgr(params: &str, final_byte: char) -> bool {
+    final_byte == 'm'
+        && params
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == ';' || c == ':')
+}
+
+/// Read a CSI sequence after its introducer, returning it if it's color
+fn read_csi(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Option<String> {
+    let mut params = String::new();
+    while let Some(&c) = chars.peek() {
+        match c {
+            // Parameter and intermediate bytes
+            '\x20'..='\x3f' => {
+                params.push(c);
+                chars.next();
+            }
+            '\x40'..='\x7e' => {
+                chars.next();
+                return is_sgr(&params, c).then(|| format!("{ESC}[{params}m"));
+            }
+            // Anything else cuts the sequence short
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// Skip a string sequence (OSC, DCS, SOS, PM, APC) up to and including its
+/// terminator: BEL (for OSC), ST, or `ESC \`. Unterminated ones run to the end.
+fn skip_string(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) {
+    while let Some(c) = chars.next() {
+        match c {
+            BEL | C1_ST => return,
+            ESC => {
+                if chars.peek() == Some(&'\\') {
+                    chars.next();
+                }
+                return;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Strip every control sequence from `text` except SGR colors
+pub fn sanitize_terminal(text: &str) -> Cow<'_, str> {
+    if !text.chars().any(is_control) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if !is_control(c) {
+            out.push(c);
+            continue;
+        }
+        match c {
+            ESC => match chars.next() {
+                Some('[') => out.extend(read_csi(&mut chars)),
+                Some(']' | 'P' | 'X' | '^' | '_') => skip_string(&mut chars),
+                // Character set designations and the like: ESC, intermediates, final
+                Some('\x20'..='\x2f') => {
+                    while chars.next_if(|c| ('\x20'..='\x2f').contains(c)).is_some() {}
+                    chars.next();
+                }
+                // Two-character sequences, e.g. ESC 7 (save cursor) or ESC c (reset)
+                _ => {}
+            },
+            C1_CSI => out.extend(read_csi(&mut chars)),
+            C1_OSC | '\u{90}' | '\u{98}' | '\u{9e}' | '\u{9f}' => skip_string(&mut chars),
+            _ => {}
+        }
+    }
+    // Colors alone leave the text as it was
+    if out == text {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(out)
+    }
+}
+
+/// Sanitize every string in `value` in place, returning whether any changed
+pub fn sanitize_value(value: &mut Value) -> bool {
+    match value {
+        Value::String(s) => match sanitize_terminal(s) {
+            Cow::Borrowed(_) => false,
+            Cow::Owned(clean) => {
+                *s = clean;
+                true
+            }
+        },
+        // Every item is visited, even after one has changed
+        Value::Array(items) => {
+            let mut changed = false;
+            for item in items {
+                changed |= sanitize_value(item);
+            }
+            changed
+        }
+        Value::Object(map) => {
+            let mut changed = false;
+            for item in map.values_mut() {
+                changed |= sanitize_value(item);
+            }
+            changed
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_plain_text_is_untouched() {
+        let text = "line one\n\tline two\r\nünïcode ✓";
+        assert!(matches!(sanitize_terminal(text), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_colors_are_kept() {
+        let text = "\x1b[1;31merror\x1b[0m and \x1b[38;2;255;0;0mtruecolor\x1b[m";
+        assert!(matches!(sanitize_terminal(text), Cow::Borrowed(_)));
+        assert_eq!(sanitize_terminal("\x1b[38:5:208mx"), "\x1b[38:5:208mx");
+    }
+
+    #[test]
+    fn test_cursor_movement_is_stripped() {
+        assert_eq!(
+            sanitize_terminal("a\x1b[2J\x1b[H\x1b[10;20Hb\x1b[?25l\x1b[3Ac\x1b[K"),
+            "abc"
+        );
+        // Private-mode or intermediate bytes make it not a color
+        assert_eq!(sanitize_terminal("x\x1b[?1049hy\x1b[1 m"), "xy");
+        assert_eq!(sanitize_terminal("\x1b7saved\x1b8\x1bc\x1b(B"), "saved");
+    }
+
+    #[test]
+    fn test_osc_sequences_are_stripped() {
+        // Window title, clipboard write, and a hyperlink, each terminator kind
+        assert_eq!(sanitize_terminal("\x1b]0;pwned\x07ok"), "ok");
+        assert_eq!(
+            sanitize_terminal("\x1b]52;c;ZWNobyBoaQ==\x1b\\after"),
+            "after"
+        );
+        assert_eq!(
+            sanitize_terminal("\x1b]8;;javascript:alert(1)\x1b\\click\x1b]8;;\x1b\\"),
+            "click"
+        );
+        // Unterminated ones swallow the rest rather than leaking their payload
+        assert_eq!(sanitize_terminal("before\x1b]2;title never ends"), "before");
+        assert_eq!(sanitize_terminal("\x1bPq#0;2;0;0;0\x1b\\img"), "img");
+    }
+
+    #[test]
+    fn test_c1_controls() {
+        assert_eq!(sanitize_terminal("a\u{9b}2Jb\u{9b}31mc"), "ab\x1b[31mc");
+        assert_eq!(sanitize_terminal("\u{9d}0;title\u{9c}ok"), "ok");
+        assert_eq!(sanitize_terminal("x\u{85}y"), "xy");
+    }
+
+    #[test]
+    fn test_other_controls_are_dropped() {
+        assert_eq!(
+            sanitize_terminal("ding\x07 back\x08\x08 del\x7f nul\0"),
+            "ding back del nul"
+        );
+        // A lone or truncated escape goes too
+        assert_eq!(sanitize_terminal("end\x1b"), "end");
+        assert_eq!(sanitize_terminal("cut\x1b[12"), "cut");
+        assert_eq!(sanitize_terminal("cut\x1b[1\nnext"), "cut\nnext");
+    }
+
+    #[test]
+    fn test_sanitize_value() {
+        let mut value = json!({
+            "type": "user",
+            "message": {"content": [{
+                "type": "tool_result",
+                "content": "\x1b]0;x\x07\x1b[32mok\x1b[0m\x1b[2K",
+            }]},
+            "count": 3,
+        });
+        assert!(sanitize_value(&mut value));
+        assert_eq!(
+            value.pointer("/message/content/0/content").unwrap(),
+            "\x1b[32mok\x1b[0m"
+        );
+        assert!(!sanitize_value(&mut value));
+    }
+}
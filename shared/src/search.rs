@@ -0,0 +1,85 @@
+//! Transcript search results
+//!
+//! `GET /api/search?q=...` searches the stored messages of every session the
+//! caller is a member of. The query language (free text plus `session:`,
+//! `tool:`, `model:`, `before:`, `after:`, and `is:error` filters) is parsed
+//! by the backend. With `mode=semantic` the free text is compared by meaning
+//! against prompts and assistant turns, when the server has an embedding
+//! provider. The same embeddings suggest related past sessions.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// One stored message that matched
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub message_id: Uuid,
+    pub session_id: Uuid,
+    pub session_name: String,
+    /// Message type: assistant, user, result, error, ...
+    pub role: String,
+    pub created_at: String,
+    /// Text around the first match; the start of the message in semantic
+    /// mode
+    pub snippet: String,
+    /// Cosine similarity to the query, in semantic mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResponse {
+    /// Newest first
+    pub hits: Vec<SearchHit>,
+    /// Set when the search stopped early, either at the result limit or at
+    /// the number of messages it will scan; narrowing the query (a session
+    /// or date filter) finds older matches
+    pub truncated: bool,
+}
+
+/// A past session whose first prompt resembles another session's, from
+/// `GET /api/sessions/{id}/related`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelatedSession {
+    pub session_id: Uuid,
+    pub session_name: String,
+    pub working_directory: String,
+    /// Start of the session's first prompt
+    pub first_prompt: String,
+    pub last_activity: String,
+    pub outcome: Option<crate::SessionOutcome>,
+    /// Cosine similarity of the two first prompts
+    pub score: f32,
+}
+
+/// A query the user saved from the search page (`/api/saved-searches`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub id: Uuid,
+    pub name: String,
+    pub query: String,
+    pub regex: bool,
+    /// Evaluated in the background against new messages; matches are sent
+    /// to the owner's open clients as `ProxyMessage::SearchAlert`
+    pub alert: bool,
+    /// When the alert last found something, if ever
+    pub last_match_at: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveSearchRequest {
+    pub name: String,
+    pub query: String,
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub alert: bool,
+}
+
+/// PATCH body; unset fields are left alone
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateSavedSearchRequest {
+    pub name: Option<String>,
+    pub alert: Option<bool>,
+}
@@ -0,0 +1,71 @@
+//! Session snapshots the proxy mirrors to the backend
+//!
+//! The proxy periodically uploads its latest `SessionSnapshot` (from
+//! claude-session-lib) so a session's pending outputs and permission request
+//! survive losing the proxy host. The backend stores the snapshot as opaque
+//! JSON; this is the summary the web UI shows of it.
+
+use serde::{Deserialize, Serialize};
+
+/// Largest snapshot upload the backend accepts, in bytes
+pub const MAX_SNAPSHOT_BYTES: usize = 16 * 1024 * 1024;
+
+/// What a stored snapshot would restore, as listed by the API
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SnapshotSummary {
+    /// When the backend received it
+    pub uploaded_at: String,
+    /// The proxy's last activity when it took the snapshot
+    pub last_activity: Option<String>,
+    /// Outputs the backend hadn't acknowledged
+    pub pending_outputs: usize,
+    /// Tool of the permission request waiting for an answer, if any
+    pub pending_permission: Option<String>,
+    /// Whether Claude was running
+    pub was_running: bool,
+}
+
+impl SnapshotSummary {
+    /// Summarize a snapshot's JSON; fields it doesn't have are left empty
+    pub fn from_json(snapshot: &serde_json::Value, uploaded_at: String) -> Self {
+        Self {
+            uploaded_at,
+            last_activity: snapshot["last_activity"].as_str().map(str::to_string),
+            pending_outputs: snapshot["pending_outputs"]
+                .as_array()
+                .map_or(0, |outputs| outputs.len()),
+            pending_permission: snapshot["pending_permission"]["tool_name"]
+                .as_str()
+                .map(str::to_string),
+            was_running: snapshot["was_running"].as_bool().unwrap_or(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_json() {
+        let snapshot = serde_json::json!({
+            "id": "00000000-0000-0000-0000-000000000000",
+            "pending_outputs": [{"seq": 4}, {"seq": 5}],
+            "pending_permission": {"request_id": "req-1", "tool_name": "Bash"},
+            "last_activity": "2026-02-04T09:00:00Z",
+            "was_running": true
+        });
+        let summary = SnapshotSummary::from_json(&snapshot, "now".to_string());
+        assert_eq!(summary.pending_outputs, 2);
+        assert_eq!(summary.pending_permission.as_deref(), Some("Bash"));
+        assert_eq!(
+            summary.last_activity.as_deref(),
+            Some("2026-02-04T09:00:00Z")
+        );
+        assert!(summary.was_running);
+
+        let empty = SnapshotSummary::from_json(&serde_json::json!({}), "now".to_string());
+        assert_eq!(empty.pending_outputs, 0);
+        assert_eq!(empty.pending_permission, None);
+    }
+}
@@ -0,0 +1,136 @@
+//! W3C trace context
+//!
+//! Relayed messages carry a `traceparent` string so one user input or one
+//! Claude output can be followed from the proxy through the backend to the
+//! browser. Only the proxy and frontend generate ids here; the backend hands
+//! them to OpenTelemetry.
+
+use uuid::Uuid;
+
+/// Trace and parent span ids, as in a `traceparent` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+    pub sampled: bool,
+}
+
+fn random_span_id() -> [u8; 8] {
+    let mut span_id = [0u8; 8];
+    span_id.copy_from_slice(&Uuid::new_v4().as_bytes()[..8]);
+    span_id
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex<const N: usize>(text: &str) -> Option<[u8; N]> {
+    if text.len() != N * 2 || !text.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+        return None;
+    }
+    let mut bytes = [0u8; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+impl TraceContext {
+    /// Start a new trace
+    pub fn new_root() -> Self {
+        Self {
+            trace_id: *Uuid::new_v4().as_bytes(),
+            span_id: random_span_id(),
+            sampled: true,
+        }
+    }
+
+    /// A new span in the same trace, parented to this one
+    pub fn child(&self) -> Self {
+        Self {
+            span_id: random_span_id(),
+            ..*self
+        }
+    }
+
+    pub fn trace_id_hex(&self) -> String {
+        to_hex(&self.trace_id)
+    }
+
+    /// Format as `00-<trace id>-<span id>-<flags>`
+    pub fn to_traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-{:02x}",
+            to_hex(&self.trace_id),
+            to_hex(&self.span_id),
+            self.sampled as u8
+        )
+    }
+
+    /// Parse a version 00 `traceparent`. All-zero ids are invalid.
+    pub fn from_traceparent(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let (version, trace_id, span_id, flags) =
+            (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        if version != "00" || parts.next().is_some() {
+            return None;
+        }
+        let trace_id = from_hex::<16>(trace_id)?;
+        let span_id = from_hex::<8>(span_id)?;
+        let flags = from_hex::<1>(flags)?[0];
+        if trace_id == [0; 16] || span_id == [0; 8] {
+            return None;
+        }
+        Some(Self {
+            trace_id,
+            span_id,
+            sampled: flags & 1 == 1,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traceparent_roundtrip() {
+        let root = TraceContext::new_root();
+        let parsed = TraceContext::from_traceparent(&root.to_traceparent()).unwrap();
+        assert_eq!(parsed, root);
+
+        let child = root.child();
+        assert_eq!(child.trace_id, root.trace_id);
+        assert_ne!(child.span_id, root.span_id);
+    }
+
+    #[test]
+    fn test_parse_known_value() {
+        let ctx = TraceContext::from_traceparent(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        )
+        .unwrap();
+        assert_eq!(ctx.trace_id_hex(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(
+            ctx.span_id,
+            [0x00, 0xf0, 0x67, 0xaa, 0x0b, 0xa9, 0x02, 0xb7]
+        );
+        assert!(ctx.sampled);
+    }
+
+    #[test]
+    fn test_rejects_invalid() {
+        for value in [
+            "",
+            "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+        ] {
+            assert_eq!(TraceContext::from_traceparent(value), None, "{}", value);
+        }
+    }
+}
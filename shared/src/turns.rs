@@ -0,0 +1,338 @@
+//! Per-turn records
+//!
+//! A turn runs from a prompt to the result message that ends it.
+//! [`TurnAnalyzer`] follows a session's messages as they're stored and
+//! yields a [`TurnRecord`] at each result, so turns are worked out once and
+//! persisted rather than reparsed from the transcript for every query.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::compaction::is_compaction;
+use crate::undo::is_prompt;
+
+/// One finished turn
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TurnRecord {
+    /// Position in the session, from 0
+    pub turn_index: i32,
+    /// Sequence of the message that started it, usually the prompt
+    pub start_seq: i64,
+    /// Sequence of the result message that ended it
+    pub end_seq: i64,
+    pub duration_ms: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost_usd: f64,
+    /// Tools called, each once, in the order first called
+    pub tools: Vec<String>,
+    /// What went wrong, if the turn ended in an error
+    pub error: Option<String>,
+    /// Whether Claude Code compacted the context during it, so what came
+    /// before is only a summary from here on
+    #[serde(default)]
+    pub compacted: bool,
+}
+
+/// A stored turn, as returned by `GET /api/sessions/:id/turns`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TurnInfo {
+    #[serde(flatten)]
+    pub record: TurnRecord,
+    /// The result message that ended the turn
+    pub end_message_id: Option<Uuid>,
+    pub ended_at: String,
+}
+
+/// Running totals a result message reports for its Claude process
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Totals {
+    cost_usd: f64,
+    input_tokens: i64,
+    output_tokens: i64,
+}
+
+impl Totals {
+    fn of(result: &Value) -> Self {
+        let tokens = |key: &str| {
+            result
+                .get("usage")
+                .and_then(|u| u.get(key))
+                .and_then(Value::as_i64)
+                .unwrap_or(0)
+        };
+        Self {
+            cost_usd: result
+                .get("total_cost_usd")
+                .and_then(Value::as_f64)
+                .unwrap_or(0.0),
+            input_tokens: tokens("input_tokens"),
+            output_tokens: tokens("output_tokens"),
+        }
+    }
+
+    /// Usage since `previous`; totals that went backwards mean the process
+    /// restarted and counted from zero, so all of them are new
+    fn since(self, previous: Option<Totals>) -> Totals {
+        let Some(prev) = previous else {
+            return self;
+        };
+        if self.cost_usd < prev.cost_usd
+            || self.input_tokens < prev.input_tokens
+            || self.output_tokens < prev.output_tokens
+        {
+            return self;
+        }
+        Totals {
+            cost_usd: self.cost_usd - prev.cost_usd,
+            input_tokens: self.input_tokens - prev.input_tokens,
+            output_tokens: self.output_tokens - prev.output_tokens,
+        }
+    }
+}
+
+/// The turn in progress
+#[derive(Debug, Clone)]
+struct OpenTurn {
+    start_seq: i64,
+    started_at_ms: i64,
+    tools: Vec<String>,
+    compacted: bool,
+}
+
+/// Splits a session's messages into turns as they arrive
+#[derive(Debug, Clone, Default)]
+pub struct TurnAnalyzer {
+    next_index: i32,
+    open: Option<OpenTurn>,
+    last_totals: Option<Totals>,
+    /// A compaction seen between turns, credited to the next one
+    compacted: bool,
+}
+
+impl TurnAnalyzer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Carry on after turn `turn_index`, which ended with `last_result`
+    pub fn after(turn_index: i32, last_result: Option<&Value>) -> Self {
+        Self {
+            next_index: turn_index + 1,
+            open: None,
+            last_totals: last_result.map(Totals::of),
+            compacted: false,
+        }
+    }
+
+    /// Follow the message stored at `seq` (received at `at_ms`, ms since the
+    /// epoch), returning the turn it finishes, if it's a result
+    pub fn push(&mut self, seq: i64, at_ms: i64, message: &Value) -> Option<TurnRecord> {
+        let kind = message.get("type").and_then(Value::as_str);
+        if self.open.is_none() {
+            // System messages come before a turn; anything else is part of one
+            let starts = kind != Some("system") && kind != Some("result") || is_prompt(message);
+            if starts {
+                self.open = Some(OpenTurn {
+                    start_seq: seq,
+                    started_at_ms: at_ms,
+                    tools: Vec::new(),
+                    compacted: std::mem::take(&mut self.compacted),
+                });
+            }
+        }
+        if is_compaction(message) {
+            match &mut self.open {
+                Some(open) => open.compacted = true,
+                None => self.compacted = true,
+            }
+        }
+
+        match kind {
+            Some("assistant") => {
+                if let Some(open) = &mut self.open {
+                    let names = message
+                        .pointer("/message/content")
+                        .and_then(Value::as_array)
+                        .into_iter()
+                        .flatten()
+                        .filter(|b| b.get("type").and_then(Value::as_str) == Some("tool_use"))
+                        .filter_map(|b| b.get("name").and_then(Value::as_str));
+                    for name in names {
+                        if !open.tools.iter().any(|t| t == name) {
+                            open.tools.push(name.to_string());
+                        }
+                    }
+                }
+                None
+            }
+            Some("result") => Some(self.finish(seq, at_ms, message)),
+            _ => None,
+        }
+    }
+
+    fn finish(&mut self, seq: i64, at_ms: i64, result: &Value) -> TurnRecord {
+        let open = self.open.take().unwrap_or(OpenTurn {
+            start_seq: seq,
+            started_at_ms: at_ms,
+            tools: Vec::new(),
+            compacted: std::mem::take(&mut self.compacted),
+        });
+        let totals = Totals::of(result);
+        let used = totals.since(self.last_totals);
+        self.last_totals = Some(totals);
+
+        let is_error = result.get("is_error").and_then(Value::as_bool) == Some(true);
+        let subtype = result.get("subtype").and_then(Value::as_str);
+        let error = if is_error || subtype.is_some_and(|s| s != "success") {
+            Some(
+                result
+                    .get("result")
+                    .and_then(Value::as_str)
+                    .filter(|r| !r.is_empty())
+                    .or(subtype)
+                    .unwrap_or("error")
+                    .to_string(),
+            )
+        } else {
+            None
+        };
+
+        let record = TurnRecord {
+            turn_index: self.next_index,
+            start_seq: open.start_seq,
+            end_seq: seq,
+            duration_ms: result
+                .get("duration_ms")
+                .and_then(Value::as_i64)
+                .unwrap_or((at_ms - open.started_at_ms).max(0)),
+            input_tokens: used.input_tokens,
+            output_tokens: used.output_tokens,
+            cost_usd: used.cost_usd,
+            tools: open.tools,
+            error,
+            compacted: open.compacted,
+        };
+        self.next_index += 1;
+        record
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn prompt(text: &str) -> Value {
+        json!({"type": "user", "message": {"content": text}})
+    }
+
+    fn tools(names: &[&str]) -> Value {
+        let blocks: Vec<Value> = names
+            .iter()
+            .map(|n| json!({"type": "tool_use", "name": n, "input": {}}))
+            .collect();
+        json!({"type": "assistant", "message": {"content": blocks}})
+    }
+
+    fn result(cost: f64, input: i64, output: i64) -> Value {
+        json!({
+            "type": "result",
+            "subtype": "success",
+            "is_error": false,
+            "duration_ms": 1200,
+            "total_cost_usd": cost,
+            "usage": {"input_tokens": input, "output_tokens": output},
+        })
+    }
+
+    #[test]
+    fn test_turns_from_messages() {
+        let mut analyzer = TurnAnalyzer::new();
+        assert_eq!(analyzer.push(1, 0, &json!({"type": "system"})), None);
+        assert_eq!(analyzer.push(2, 0, &prompt("fix it")), None);
+        assert_eq!(analyzer.push(3, 100, &tools(&["Read", "Edit"])), None);
+        let tool_result =
+            json!({"type": "user", "message": {"content": [{"type": "tool_result"}]}});
+        assert_eq!(analyzer.push(4, 200, &tool_result), None);
+        assert_eq!(analyzer.push(5, 300, &tools(&["Edit", "Bash"])), None);
+
+        let first = analyzer.push(6, 1500, &result(0.10, 100, 50)).unwrap();
+        assert_eq!(first.turn_index, 0);
+        assert_eq!((first.start_seq, first.end_seq), (2, 6));
+        assert_eq!(first.duration_ms, 1200);
+        assert_eq!(first.tools, vec!["Read", "Edit", "Bash"]);
+        assert_eq!(first.error, None);
+
+        analyzer.push(7, 2000, &prompt("and the tests"));
+        let second = analyzer.push(8, 3000, &result(0.25, 160, 90)).unwrap();
+        assert_eq!(second.turn_index, 1);
+        assert_eq!(second.start_seq, 7);
+        assert!((second.cost_usd - 0.15).abs() < 1e-9);
+        assert_eq!((second.input_tokens, second.output_tokens), (60, 40));
+        assert!(second.tools.is_empty());
+    }
+
+    #[test]
+    fn test_restart_and_errors() {
+        let mut analyzer = TurnAnalyzer::after(4, Some(&result(1.0, 1000, 500)));
+        analyzer.push(20, 0, &prompt("again"));
+        let error = json!({
+            "type": "result",
+            "subtype": "error_max_turns",
+            "is_error": true,
+            "total_cost_usd": 0.05,
+            "usage": {"input_tokens": 10, "output_tokens": 5},
+        });
+        let turn = analyzer.push(21, 700, &error).unwrap();
+        assert_eq!(turn.turn_index, 5);
+        // Totals went backwards: the process restarted
+        assert!((turn.cost_usd - 0.05).abs() < 1e-9);
+        assert_eq!(turn.duration_ms, 700);
+        assert_eq!(turn.error.as_deref(), Some("error_max_turns"));
+
+        // A result with nothing before it is a turn of its own
+        let lone = analyzer.push(22, 800, &result(0.06, 11, 6)).unwrap();
+        assert_eq!((lone.start_seq, lone.end_seq), (22, 22));
+    }
+
+    #[test]
+    fn test_compaction() {
+        let mut analyzer = TurnAnalyzer::new();
+        analyzer.push(1, 0, &prompt("keep going"));
+        let boundary = json!({
+            "type": "system",
+            "subtype": "compact_boundary",
+            "compact_metadata": {"trigger": "auto", "pre_tokens": 155000},
+        });
+        analyzer.push(2, 10, &boundary);
+        let summary = json!({
+            "type": "user",
+            "isCompactSummary": true,
+            "message": {"content": "Summary of the conversation so far"},
+        });
+        // The summary carries on the turn rather than starting one
+        assert_eq!(analyzer.push(3, 20, &summary), None);
+        let turn = analyzer.push(4, 30, &result(0.1, 10, 5)).unwrap();
+        assert_eq!(turn.start_seq, 1);
+        assert!(turn.compacted);
+
+        // A boundary between turns counts toward the next
+        analyzer.push(5, 40, &boundary);
+        analyzer.push(6, 50, &prompt("next"));
+        assert!(
+            analyzer
+                .push(7, 60, &result(0.2, 20, 10))
+                .unwrap()
+                .compacted
+        );
+        analyzer.push(8, 70, &prompt("and then"));
+        assert!(
+            !analyzer
+                .push(9, 80, &result(0.3, 30, 15))
+                .unwrap()
+                .compacted
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! Undoing Claude's last turn
+//!
+//! Sessions keep no file checkpoints to restore from, so undo asks Claude to
+//! revert what its last turn changed. [`last_turn`] works out what that was
+//! from the transcript, for the confirmation and for the instruction itself.
+
+use serde_json::Value;
+
+/// Tools that change files, with the input field naming the file
+const EDIT_TOOLS: &[(&str, &str)] = &[
+    ("Write", "file_path"),
+    ("Edit", "file_path"),
+    ("MultiEdit", "file_path"),
+    ("NotebookEdit", "notebook_path"),
+];
+
+/// What Claude's most recent turn changed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LastTurn {
+    /// Files its edit tools touched, in the order first touched
+    pub files: Vec<String>,
+    /// Whether it ran shell commands, which may have changed other files
+    pub ran_commands: bool,
+    /// Whether Claude is still working on it
+    pub in_progress: bool,
+}
+
+impl LastTurn {
+    /// Whether there's anything to undo
+    pub fn changed_anything(&self) -> bool {
+        !self.files.is_empty() || self.ran_commands
+    }
+
+    /// The instruction that asks Claude to revert the turn
+    pub fn undo_prompt(&self) -> String {
+        let mut prompt = String::from("Undo the changes you made in your last turn");
+        if self.files.is_empty() {
+            prompt.push_str(".\n\n");
+        } else {
+            prompt.push_str(", restoring these files to how they were before it:\n");
+            for file in &self.files {
+                prompt.push_str(&format!("- {}\n", file));
+            }
+            prompt.push('\n');
+        }
+        if self.ran_commands {
+            prompt.push_str(
+                "Also reverse any changes made by the shell commands you ran in that turn. ",
+            );
+        }
+        prompt
+            .push_str("Don't make any other changes, and list what you reverted when you're done.");
+        prompt
+    }
+}
+
+/// Whether a message is a prompt typed by a user, as opposed to the tool
+/// results Claude's turns feed back to it or a compaction summary
+pub(crate) fn is_prompt(message: &Value) -> bool {
+    if message.get("type").and_then(Value::as_str) != Some("user")
+        || crate::compaction::summary_text(message).is_some()
+    {
+        return false;
+    }
+    if message.get("content").and_then(Value::as_str).is_some() {
+        return true;
+    }
+    match message.pointer("/message/content") {
+        Some(Value::String(_)) => true,
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .any(|block| block.get("type").and_then(Value::as_str) == Some("text")),
+        _ => false,
+    }
+}
+
+/// What the turn after the last prompt in `messages` (raw JSON, oldest
+/// first) changed, or `None` if there's been no prompt
+pub fn last_turn<S: AsRef<str>>(messages: &[S]) -> Option<LastTurn> {
+    let mut turn = LastTurn {
+        in_progress: true,
+        ..LastTurn::default()
+    };
+    let mut files_newest_first = Vec::new();
+    for raw in messages.iter().rev() {
+        let Ok(message) = serde_json::from_str::<Value>(raw.as_ref()) else {
+            continue;
+        };
+        if is_prompt(&message) {
+            for file in files_newest_first.into_iter().rev() {
+                if !turn.files.contains(&file) {
+                    turn.files.push(file);
+                }
+            }
+            return Some(turn);
+        }
+        match message.get("type").and_then(Value::as_str) {
+            Some("result") => turn.in_progress = false,
+            Some("assistant") => {
+                let blocks = message
+                    .pointer("/message/content")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten();
+                // Reversed so that, flipped back, files come in turn order
+                for block in blocks.rev() {
+                    if block.get("type").and_then(Value::as_str) != Some("tool_use") {
+                        continue;
+                    }
+                    let name = block.get("name").and_then(Value::as_str);
+                    if name == Some("Bash") {
+                        turn.ran_commands = true;
+                    }
+                    let path = EDIT_TOOLS
+                        .iter()
+                        .find(|(tool, _)| Some(*tool) == name)
+                        .and_then(|(_, field)| block.pointer(&format!("/input/{}", field)))
+                        .and_then(Value::as_str);
+                    if let Some(path) = path {
+                        files_newest_first.push(path.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn tool_use(name: &str, input: Value) -> String {
+        json!({
+            "type": "assistant",
+            "message": {"content": [{"type": "tool_use", "name": name, "input": input}]}
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_last_turn() {
+        let messages = vec![
+            json!({"type": "user", "message": {"content": "first"}}).to_string(),
+            tool_use("Write", json!({"file_path": "old.rs"})),
+            json!({"type": "result"}).to_string(),
+            json!({"type": "user", "message": {"content": [{"type": "text", "text": "second"}]}})
+                .to_string(),
+            tool_use("Edit", json!({"file_path": "b.rs"})),
+            json!({"type": "user", "message": {"content": [{"type": "tool_result"}]}}).to_string(),
+            tool_use("Write", json!({"file_path": "a.rs"})),
+            tool_use("Edit", json!({"file_path": "b.rs"})),
+            tool_use("Read", json!({"file_path": "c.rs"})),
+        ];
+
+        let turn = last_turn(&messages).unwrap();
+        assert_eq!(turn.files, vec!["b.rs", "a.rs"]);
+        assert!(!turn.ran_commands);
+        assert!(turn.in_progress);
+
+        let mut finished = messages.clone();
+        finished.push(tool_use("Bash", json!({"command": "rm -rf target"})));
+        finished.push(json!({"type": "result"}).to_string());
+        let turn = last_turn(&finished).unwrap();
+        assert!(turn.ran_commands);
+        assert!(!turn.in_progress);
+
+        assert_eq!(last_turn(&messages[1..3]), None);
+    }
+
+    #[test]
+    fn test_undo_prompt() {
+        let turn = LastTurn {
+            files: vec!["src/lib.rs".to_string()],
+            ran_commands: true,
+            in_progress: false,
+        };
+        let prompt = turn.undo_prompt();
+        assert!(prompt.contains("\n- src/lib.rs\n"));
+        assert!(prompt.contains("shell commands"));
+
+        let prompt = LastTurn::default().undo_prompt();
+        assert!(prompt.starts_with("Undo the changes you made in your last turn.\n\n"));
+        assert!(!prompt.contains("shell commands"));
+    }
+}
@@ -0,0 +1,58 @@
+//! Voice input languages
+//!
+//! Each user picks the language their speech is recognized in; the choice is
+//! sent with `ProxyMessage::StartVoice`. Providers that can identify the
+//! language themselves also accept [`AUTO_LANGUAGE`].
+
+use serde::{Deserialize, Serialize};
+
+/// Language used until a user picks one
+pub const DEFAULT_LANGUAGE: &str = "en-US";
+
+/// Ask the speech provider to detect the language
+pub const AUTO_LANGUAGE: &str = "auto";
+
+/// Languages offered for voice input: BCP-47 code and display name
+pub const VOICE_LANGUAGES: &[(&str, &str)] = &[
+    ("en-US", "English (US)"),
+    ("en-GB", "English (UK)"),
+    ("es-ES", "Español"),
+    ("fr-FR", "Français"),
+    ("de-DE", "Deutsch"),
+    ("it-IT", "Italiano"),
+    ("pt-BR", "Português (Brasil)"),
+    ("nl-NL", "Nederlands"),
+    ("pl-PL", "Polski"),
+    ("ru-RU", "Русский"),
+    ("uk-UA", "Українська"),
+    ("tr-TR", "Türkçe"),
+    ("hi-IN", "हिन्दी"),
+    ("ja-JP", "日本語"),
+    ("ko-KR", "한국어"),
+    ("zh-CN", "中文 (简体)"),
+];
+
+/// Whether `code` is a language a user may choose
+pub fn is_voice_language(code: &str) -> bool {
+    code == AUTO_LANGUAGE || VOICE_LANGUAGES.iter().any(|(c, _)| *c == code)
+}
+
+/// Body of `PUT /api/auth/me/voice-language`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SetVoiceLanguageRequest {
+    pub language_code: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_voice_language() {
+        assert!(is_voice_language(DEFAULT_LANGUAGE));
+        assert!(is_voice_language("auto"));
+        assert!(is_voice_language("ja-JP"));
+        assert!(!is_voice_language("en"));
+        assert!(!is_voice_language("'; DROP TABLE users"));
+    }
+}